@@ -1,5 +1,6 @@
 use std::{
     fs::read_to_string,
+    future::pending,
     sync::{Arc, Mutex, RwLock},
 };
 
@@ -8,36 +9,57 @@ use defguard_common::{
     VERSION,
     config::{Command, DefGuardConfig, SERVER_CONFIG},
     db::{
-        init_db,
+        init_db, init_replica_pool,
         models::{Settings, settings::initialize_current_settings},
     },
 };
 use defguard_core::{
-    auth::failed_login::FailedLoginMap,
-    db::{AppEvent, GatewayEvent, User},
+    auth::{
+        admin_access::init_admin_access, break_glass::init_break_glass_admin,
+        failed_login::FailedLoginMap,
+    },
+    db::{
+        AppEvent, GatewayEvent, User,
+        replica::{ReadReplica, run_replica_health_check},
+    },
     enterprise::{
         activity_log_stream::activity_log_stream_manager::run_activity_log_stream_manager,
         license::{License, run_periodic_license_check, set_cached_license},
         limits::update_counts,
     },
     events::{ApiEvent, BidiStreamEvent, GrpcEvent, InternalEvent},
+    gateway_alerts::run_periodic_gateway_disconnect_alerts,
     gateway_config,
     grpc::{
         WorkerState,
-        gateway::{client_state::ClientMap, map::GatewayMap},
+        gateway::{
+            broadcast::run_gateway_event_broadcast, client_state::ClientMap, map::GatewayMap,
+        },
         run_grpc_bidi_stream, run_grpc_server,
     },
-    init_dev_env, init_vpn_location, run_web_server,
+    init_dev_env, init_vpn_location,
+    notification_digest::run_periodic_notification_digest,
+    run_web_server,
+    telemetry::otlp_layer,
     utility_thread::run_utility_thread,
     version::IncompatibleComponents,
     wireguard_peer_disconnect::run_periodic_peer_disconnect,
     wireguard_stats_purge::run_periodic_stats_purge,
+    wireguard_stats_rollup::run_periodic_stats_rollup,
 };
 use defguard_event_logger::{message::EventLoggerMessage, run_event_logger};
 use defguard_event_router::{RouterReceiverSet, run_event_router};
 use defguard_mail::{Mail, run_mail_handler};
 use secrecy::ExposeSecret;
-use tokio::sync::{broadcast, mpsc::unbounded_channel};
+use tokio::{
+    signal::{
+        ctrl_c,
+        unix::{SignalKind, signal},
+    },
+    sync::{broadcast, mpsc::unbounded_channel},
+    time::{sleep, timeout},
+};
+use tokio_util::sync::CancellationToken;
 
 #[macro_use]
 extern crate tracing;
@@ -52,10 +74,13 @@ async fn main() -> Result<(), anyhow::Error> {
         .set(config.clone())
         .expect("Failed to initialize server config.");
 
-    // initialize tracing with version formatter
-    defguard_version::tracing::init(
+    // initialize tracing with version formatter and optional span export
+    let (otlp_layer, tracer_provider) = otlp_layer(&config)?.unzip();
+    defguard_version::tracing::init_with_layer(
         defguard_version::Version::parse(VERSION)?,
         &config.log_level,
+        config.log_format.parse()?,
+        otlp_layer,
     )?;
 
     info!("Starting ... version v{VERSION}");
@@ -69,6 +94,18 @@ async fn main() -> Result<(), anyhow::Error> {
         config.database_password.expose_secret(),
     )
     .await;
+    let replica = config.database_replica_host.as_ref().map(|host| {
+        ReadReplica::new(init_replica_pool(
+            host,
+            config.database_replica_port.unwrap_or(config.database_port),
+            &config.database_name,
+            &config.database_user,
+            config.database_password.expose_secret(),
+        ))
+    });
+    if let Some(replica) = &replica {
+        tokio::spawn(run_replica_health_check(replica.clone()));
+    }
 
     // handle optional subcommands
     if let Some(command) = &config.cmd {
@@ -108,7 +145,16 @@ async fn main() -> Result<(), anyhow::Error> {
 
     // setup communication channels for services
     let (webhook_tx, webhook_rx) = unbounded_channel::<AppEvent>();
-    let (wireguard_tx, _wireguard_rx) = broadcast::channel::<GatewayEvent>(256);
+    let (wireguard_tx, _wireguard_rx) =
+        broadcast::channel::<GatewayEvent>(config.gateway_event_buffer);
+    // when events are shared with other core instances, gateways connected to this one subscribe
+    // to a separate channel, which receives events of all instances
+    let share_gateway_events = config.gateway_event_backend == "postgres";
+    let gateway_events_tx = if share_gateway_events {
+        broadcast::channel::<GatewayEvent>(config.gateway_event_buffer).0
+    } else {
+        wireguard_tx.clone()
+    };
     let (mail_tx, mail_rx) = unbounded_channel::<Mail>();
     let (event_logger_tx, event_logger_rx) = unbounded_channel::<EventLoggerMessage>();
 
@@ -121,6 +167,12 @@ async fn main() -> Result<(), anyhow::Error> {
     // initialize admin user
     User::init_admin_user(&pool, config.default_admin_password.expose_secret()).await?;
 
+    // provision or disable the emergency admin account
+    init_break_glass_admin(&pool, &config).await?;
+
+    // restrict networks and countries admin endpoints can be accessed from
+    init_admin_access(&config)?;
+
     // initialize default settings
     Settings::init_defaults(&pool).await?;
     // initialize global settings struct
@@ -156,76 +208,250 @@ async fn main() -> Result<(), anyhow::Error> {
         }
     }
 
+    // Shutdown is coordinated in stages, so that nothing queued on the way is lost: servers stop
+    // accepting requests and finish in-flight ones, then queued events are routed and stored in
+    // the activity log, and finally queued emails are sent.
+    let shutdown = CancellationToken::new();
+    let router_shutdown = CancellationToken::new();
+    let mail_shutdown = CancellationToken::new();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown_signal().await;
+            info!("Shutdown signal received, stopping services");
+            shutdown.cancel();
+        }
+    });
+
+    // services processing queued messages are stopped separately
+    let mut event_router = tokio::spawn(run_event_router(
+        RouterReceiverSet::new(
+            api_event_rx,
+            grpc_event_rx,
+            bidi_event_rx,
+            internal_event_rx,
+        ),
+        event_logger_tx,
+        wireguard_tx.clone(),
+        mail_tx.clone(),
+        activity_log_stream_reload_notify.clone(),
+        router_shutdown.clone().cancelled_owned(),
+    ));
+    let mut event_logger = tokio::spawn(run_event_logger(
+        pool.clone(),
+        event_logger_rx,
+        activity_log_messages_tx,
+    ));
+    let mut activity_log_stream_manager = tokio::spawn(run_activity_log_stream_manager(
+        pool.clone(),
+        activity_log_stream_reload_notify,
+        activity_log_messages_rx,
+        shutdown.clone().cancelled_owned(),
+    ));
+    let mut mail_handler = tokio::spawn(run_mail_handler(
+        mail_rx,
+        pool.clone(),
+        mail_shutdown.clone().cancelled_owned(),
+    ));
+
+    let servers = async {
+        tokio::try_join!(
+            run_grpc_server(
+                Arc::clone(&worker_state),
+                pool.clone(),
+                Arc::clone(&gateway_state),
+                Arc::clone(&client_state),
+                wireguard_tx.clone(),
+                gateway_events_tx.clone(),
+                mail_tx.clone(),
+                grpc_cert,
+                grpc_key,
+                failed_logins.clone(),
+                grpc_event_tx,
+                webhook_tx.clone(),
+                Arc::clone(&incompatible_components),
+                shutdown.clone().cancelled_owned(),
+            ),
+            run_web_server(
+                worker_state,
+                Arc::clone(&gateway_state),
+                webhook_tx.clone(),
+                webhook_rx,
+                wireguard_tx.clone(),
+                mail_tx.clone(),
+                pool.clone(),
+                replica.clone(),
+                failed_logins,
+                api_event_tx,
+                Arc::clone(&incompatible_components),
+                shutdown.clone().cancelled_owned(),
+            ),
+        )
+    };
+    let shutdown_timeout = config.shutdown_timeout;
+
     // run services
-    tokio::select! {
+    let stopping = tokio::select! {
+        res = servers => match res {
+            Ok(_) if shutdown.is_cancelled() => {
+                info!("Web and gRPC servers stopped");
+                true
+            }
+            res => {
+                error!("Web or gRPC server returned early: {res:?}");
+                false
+            }
+        },
+        () = async {
+            shutdown.cancelled().await;
+            sleep(shutdown_timeout.into()).await;
+        } => {
+            // long-lived gRPC streams of gateways and clients don't finish by themselves
+            warn!("Requests haven't finished in {shutdown_timeout}, closing remaining connections");
+            true
+        }
         res = run_grpc_bidi_stream(
             pool.clone(),
             wireguard_tx.clone(),
             mail_tx.clone(),
             bidi_event_tx,
+            Arc::clone(&client_state),
             Arc::clone(&incompatible_components),
-        ), if config.proxy_url.is_some() => error!("Proxy gRPC stream returned early: {res:?}"),
-        res = run_grpc_server(
-            Arc::clone(&worker_state),
-            pool.clone(),
-            Arc::clone(&gateway_state),
-            client_state,
-            wireguard_tx.clone(),
-            mail_tx.clone(),
-            grpc_cert,
-            grpc_key,
-            failed_logins.clone(),
-            grpc_event_tx,
-            Arc::clone(&incompatible_components),
-        ) => error!("gRPC server returned early: {res:?}"),
-        res = run_web_server(
-            worker_state,
-            gateway_state,
-            webhook_tx,
-            webhook_rx,
-            wireguard_tx.clone(),
-            mail_tx.clone(),
+        ), if config.proxy_url.is_some() => {
+            error!("Proxy gRPC stream returned early: {res:?}");
+            false
+        }
+        res = run_gateway_event_broadcast(
             pool.clone(),
-            failed_logins,
-            api_event_tx,
-            incompatible_components,
-        ) => error!("Web server returned early: {res:?}"),
-        res = run_mail_handler(mail_rx) => error!("Mail handler returned early: {res:?}"),
+            wireguard_tx.subscribe(),
+            gateway_events_tx.clone()
+        ), if share_gateway_events => {
+            error!("Gateway event broadcast task returned early: {res:?}");
+            false
+        }
+        res = &mut mail_handler => {
+            error!("Mail handler returned early: {res:?}");
+            false
+        }
         res = run_periodic_peer_disconnect(
             pool.clone(),
             wireguard_tx.clone(),
             internal_event_tx.clone()
-        ) => error!("Periodic peer disconnect task returned early: {res:?}"),
+        ) => {
+            error!("Periodic peer disconnect task returned early: {res:?}");
+            false
+        }
         res = run_periodic_stats_purge(
             pool.clone(),
             config.stats_purge_frequency.into(),
             config.stats_purge_threshold.into()
-        ), if !config.disable_stats_purge =>
-            error!("Periodic stats purge task returned early: {res:?}"),
-        res = run_periodic_license_check(&pool) =>
-            error!("Periodic license check task returned early: {res:?}"),
-        res = run_utility_thread(&pool, wireguard_tx.clone()) =>
-            error!("Utility thread returned early: {res:?}"),
-        res = run_event_router(
-            RouterReceiverSet::new(
-                api_event_rx,
-                grpc_event_rx,
-                bidi_event_rx,
-                internal_event_rx
-            ),
-            event_logger_tx,
-            wireguard_tx,
-            mail_tx,
-            activity_log_stream_reload_notify.clone()
-        ) => error!("Event router returned early: {res:?}"),
-        res = run_event_logger(pool.clone(), event_logger_rx, activity_log_messages_tx.clone()) =>
-            error!("Activity log event logger returned early: {res:?}"),
-        res = run_activity_log_stream_manager(
+        ), if !config.disable_stats_purge => {
+            error!("Periodic stats purge task returned early: {res:?}");
+            false
+        }
+        res = run_periodic_stats_rollup(pool.clone()) => {
+            error!("Periodic stats rollup task returned early: {res:?}");
+            false
+        }
+        res = run_periodic_notification_digest(pool.clone(), mail_tx.clone()) => {
+            error!("Periodic notification digest task returned early: {res:?}");
+            false
+        }
+        res = run_periodic_gateway_disconnect_alerts(
             pool.clone(),
-            activity_log_stream_reload_notify.clone(),
-            activity_log_messages_rx
-        ) => error!("Activity log stream manager returned early: {res:?}"),
+            Arc::clone(&gateway_state),
+            webhook_tx.clone(),
+            mail_tx.clone()
+        ) => {
+            error!("Periodic gateway disconnect alerts task returned early: {res:?}");
+            false
+        }
+        res = run_periodic_license_check(&pool) => {
+            error!("Periodic license check task returned early: {res:?}");
+            false
+        }
+        res = run_utility_thread(
+            &pool,
+            wireguard_tx.clone(),
+            mail_tx.clone(),
+            internal_event_tx
+        ) => {
+            error!("Utility thread returned early: {res:?}");
+            false
+        }
+        res = &mut event_router => {
+            error!("Event router returned early: {res:?}");
+            false
+        }
+        res = &mut event_logger => {
+            error!("Activity log event logger returned early: {res:?}");
+            false
+        }
+        res = &mut activity_log_stream_manager => {
+            error!("Activity log stream manager returned early: {res:?}");
+            false
+        }
+    };
+
+    if stopping {
+        let drain = async {
+            router_shutdown.cancel();
+            match event_router.await {
+                Ok(Ok(())) => info!("Event router stopped"),
+                res => error!("Event router failed while routing queued events: {res:?}"),
+            }
+            // event logger stops once the event router is gone and queued events are stored
+            match event_logger.await {
+                Ok(Ok(())) => info!("Activity log event logger stopped"),
+                res => error!("Activity log event logger failed while storing events: {res:?}"),
+            }
+            match activity_log_stream_manager.await {
+                Ok(Ok(())) => info!("Activity log stream manager stopped"),
+                res => error!("Activity log stream manager failed while stopping: {res:?}"),
+            }
+            mail_shutdown.cancel();
+            if let Err(err) = mail_handler.await {
+                error!("Mail handler failed while sending queued emails: {err}");
+            }
+        };
+        if timeout(shutdown_timeout.into(), drain).await.is_err() {
+            warn!(
+                "Queued events and emails haven't been processed in {shutdown_timeout}, \
+                remaining ones will be lost"
+            );
+        }
+    }
+    pool.close().await;
+
+    if let Some(Err(err)) = tracer_provider.map(|provider| provider.shutdown()) {
+        error!("Failed to flush exported spans: {err}");
     }
 
     Ok(())
 }
+
+/// Completes when the process is asked to terminate, either with SIGTERM (e.g. on redeploys) or
+/// Ctrl+C.
+async fn shutdown_signal() {
+    let mut sigterm = match signal(SignalKind::terminate()) {
+        Ok(sigterm) => Some(sigterm),
+        Err(err) => {
+            error!("Failed to listen for SIGTERM: {err}");
+            None
+        }
+    };
+    tokio::select! {
+        res = ctrl_c() => {
+            if let Err(err) = res {
+                error!("Failed to listen for Ctrl+C: {err}");
+                pending::<()>().await;
+            }
+        }
+        Some(()) = async {
+            match sigterm.as_mut() {
+                Some(sigterm) => sigterm.recv().await,
+                None => pending().await,
+            }
+        } => {}
+    }
+}
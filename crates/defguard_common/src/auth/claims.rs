@@ -37,6 +37,9 @@ pub struct Claims {
     pub exp: u64,
     // not before
     pub nbf: u64,
+    // JWT ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
 }
 
 impl Claims {
@@ -60,6 +63,7 @@ impl Claims {
             client_id,
             exp,
             nbf,
+            jti: None,
         }
     }
 
@@ -1,4 +1,4 @@
-use std::{net::IpAddr, sync::OnceLock};
+use std::{net::IpAddr, path::PathBuf, sync::OnceLock};
 
 use clap::{Args, Parser, Subcommand};
 use humantime::Duration;
@@ -31,10 +31,31 @@ pub struct DefGuardConfig {
     #[arg(long, env = "DEFGUARD_LOG_LEVEL", default_value = "info")]
     pub log_level: String,
 
+    /// Log output format: `text` for human-readable lines, `json` for one JSON object per line.
+    #[arg(
+        long,
+        env = "DEFGUARD_LOG_FORMAT",
+        default_value = "text",
+        value_parser = ["text", "json"]
+    )]
+    pub log_format: String,
+
     // TODO: restore file logging, seems to have vanished during the switch to tracing
     #[arg(long, env = "DEFGUARD_LOG_FILE")]
     pub log_file: Option<String>,
 
+    /// OTLP gRPC endpoint, e.g. `http://localhost:4317`, to export tracing spans to.
+    /// Spans are not exported if not set.
+    #[arg(long, env = "DEFGUARD_OTLP_ENDPOINT", value_parser = Url::parse)]
+    pub otlp_endpoint: Option<Url>,
+
+    #[arg(
+        long,
+        env = "DEFGUARD_OTLP_SERVICE_NAME",
+        default_value = "defguard-core"
+    )]
+    pub otlp_service_name: String,
+
     #[arg(long, env = "DEFGUARD_AUTH_COOKIE_TIMEOUT", default_value = "7d")]
     #[serde(skip_serializing)]
     pub auth_cookie_timeout: Duration,
@@ -59,6 +80,15 @@ pub struct DefGuardConfig {
     #[serde(skip_serializing)]
     pub database_password: SecretString,
 
+    // read-only replica used for heavy queries, like activity log and statistics; it's accessed
+    // with the same database name and credentials as the primary
+    #[arg(long, env = "DEFGUARD_DB_REPLICA_HOST")]
+    pub database_replica_host: Option<String>,
+
+    // defaults to `DEFGUARD_DB_PORT`
+    #[arg(long, env = "DEFGUARD_DB_REPLICA_PORT")]
+    pub database_replica_port: Option<u16>,
+
     #[arg(long, env = "DEFGUARD_HTTP_PORT", default_value_t = 8000)]
     pub http_port: u16,
 
@@ -79,6 +109,24 @@ pub struct DefGuardConfig {
     #[serde(skip_serializing)]
     pub default_admin_password: SecretString,
 
+    /// Password of the emergency local admin account. The account is provisioned at startup
+    /// if set and disabled at startup if not.
+    #[arg(long, env = "DEFGUARD_BREAK_GLASS_PASSWORD")]
+    #[serde(skip_serializing)]
+    pub break_glass_password: Option<SecretString>,
+
+    #[arg(
+        long,
+        env = "DEFGUARD_BREAK_GLASS_USERNAME",
+        default_value = "breakglass"
+    )]
+    pub break_glass_username: String,
+
+    /// How long the emergency admin account stays enabled after startup.
+    #[arg(long, env = "DEFGUARD_BREAK_GLASS_VALIDITY", default_value = "4h")]
+    #[serde(skip_serializing)]
+    pub break_glass_validity: Duration,
+
     #[arg(long, env = "DEFGUARD_OPENID_KEY", value_parser = Self::parse_openid_key)]
     #[serde(skip_serializing)]
     pub openid_signing_key: Option<RsaPrivateKey>,
@@ -114,6 +162,11 @@ pub struct DefGuardConfig {
     #[serde(skip_serializing)]
     pub mfa_code_timeout: Duration,
 
+    /// Minimum time between email MFA code resends for a single user.
+    #[arg(long, env = "DEFGUARD_MFA_CODE_RESEND_INTERVAL", default_value = "30s")]
+    #[serde(skip_serializing)]
+    pub mfa_code_resend_interval: Duration,
+
     #[arg(long, env = "DEFGUARD_SESSION_TIMEOUT", default_value = "7d")]
     #[serde(skip_serializing)]
     pub session_timeout: Duration,
@@ -126,6 +179,14 @@ pub struct DefGuardConfig {
     #[serde(skip_serializing)]
     pub password_reset_token_timeout: Duration,
 
+    #[arg(
+        long,
+        env = "DEFGUARD_EMAIL_VERIFICATION_TOKEN_TIMEOUT",
+        default_value = "24h"
+    )]
+    #[serde(skip_serializing)]
+    pub email_verification_token_timeout: Duration,
+
     #[arg(
         long,
         env = "DEFGUARD_ENROLLMENT_SESSION_TIMEOUT",
@@ -177,6 +238,98 @@ pub struct DefGuardConfig {
 
     #[arg(long, env = "DEFGUARD_GRPC_BIND_ADDRESS")]
     pub grpc_bind_address: Option<IpAddr>,
+
+    // maximum request body size (in bytes) accepted by regular API endpoints
+    #[arg(long, env = "DEFGUARD_HTTP_BODY_LIMIT", default_value_t = 2 * 1024 * 1024)]
+    pub http_body_limit: usize,
+
+    // maximum request body size (in bytes) accepted by import and upload endpoints
+    #[arg(
+        long,
+        env = "DEFGUARD_HTTP_IMPORT_BODY_LIMIT",
+        default_value_t = 32 * 1024 * 1024
+    )]
+    pub http_import_body_limit: usize,
+
+    // number of gateway events buffered for each connected gateway before it's considered lagging
+    #[arg(long, env = "DEFGUARD_GATEWAY_EVENT_BUFFER", default_value_t = 1024)]
+    pub gateway_event_buffer: usize,
+
+    // how gateway events reach gateways: `local` passes them only to gateways connected to this
+    // instance, `postgres` shares them with other core instances using the same database
+    #[arg(
+        long,
+        env = "DEFGUARD_GATEWAY_EVENT_BACKEND",
+        default_value = "local",
+        value_parser = ["local", "postgres"]
+    )]
+    pub gateway_event_backend: String,
+
+    // maximum number of custom activity log events a single user can submit per minute
+    #[arg(long, env = "DEFGUARD_CUSTOM_EVENT_RATE_LIMIT", default_value_t = 60)]
+    pub custom_event_rate_limit: u32,
+
+    // number of requests to sensitive endpoints (login, MFA verification, password reset,
+    // enrollment start) a single client can make per minute, 0 disables rate limiting
+    #[arg(long, env = "DEFGUARD_RATE_LIMIT_REQUESTS", default_value_t = 20)]
+    pub rate_limit_requests: u32,
+
+    // number of requests to sensitive endpoints a client can make in a burst
+    #[arg(long, env = "DEFGUARD_RATE_LIMIT_BURST", default_value_t = 10)]
+    pub rate_limit_burst: u32,
+
+    // number of consecutive rejected requests after which the violation is recorded
+    // in the activity log
+    #[arg(
+        long,
+        env = "DEFGUARD_RATE_LIMIT_VIOLATION_THRESHOLD",
+        default_value_t = 5
+    )]
+    pub rate_limit_violation_threshold: u32,
+
+    // maximum number of LDAP connections used concurrently by bulk operations
+    #[arg(long, env = "DEFGUARD_LDAP_CONCURRENCY", default_value_t = 8)]
+    pub ldap_concurrency: usize,
+
+    // networks admin endpoints can be accessed from, access is not restricted if empty
+    #[arg(long, env = "DEFGUARD_ADMIN_ALLOWED_NETWORKS", value_delimiter = ',')]
+    pub admin_allowed_networks: Vec<IpNetwork>,
+
+    // ISO codes of countries admin endpoints can be accessed from, requires GeoIP database
+    #[arg(long, env = "DEFGUARD_ADMIN_ALLOWED_COUNTRIES", value_delimiter = ',')]
+    pub admin_allowed_countries: Vec<String>,
+
+    // path to MaxMind GeoIP2 or GeoLite2 country database
+    #[arg(long, env = "DEFGUARD_GEOIP_DB")]
+    pub geoip_db: Option<PathBuf>,
+
+    // source of client addresses used for login lockouts, rate limits and admin access
+    // restrictions, headers set by a reverse proxy (e.g. `RightmostXForwardedFor`) should only be
+    // used behind one; behind a reverse proxy, set this to its header: with `ConnectInfo`,
+    // forwarded requests from private or loopback addresses are not locked out or rate limited
+    // by address, as the proxy address is shared by all clients
+    #[arg(
+        long,
+        env = "DEFGUARD_CLIENT_IP_SOURCE",
+        default_value = "ConnectInfo",
+        value_parser = [
+            "ConnectInfo",
+            "RightmostForwarded",
+            "RightmostXForwardedFor",
+            "XRealIp",
+            "TrueClientIp",
+            "CfConnectingIp",
+            "FlyClientIp",
+            "CloudFrontViewerAddress",
+        ]
+    )]
+    pub client_ip_source: String,
+
+    // time to wait on shutdown for in-flight requests to finish and, after that, for queued
+    // events and emails to be processed
+    #[arg(long, env = "DEFGUARD_SHUTDOWN_TIMEOUT", default_value = "30s")]
+    #[serde(skip_serializing)]
+    pub shutdown_timeout: Duration,
 }
 
 #[derive(Clone, Debug, Subcommand)]
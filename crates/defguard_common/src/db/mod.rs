@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 use sqlx::{
     PgPool,
@@ -16,6 +18,9 @@ pub type Id = i64;
 // reference: https://docs.rs/sqlx/latest/sqlx/attr.test.html#automatic-migrations-requires-migrate-feature
 pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("../../migrations");
 
+// queries fail fast when the replica goes down, instead of waiting for the next health check
+const REPLICA_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Initializes and migrates postgres database. Returns DB pool object.
 pub async fn init_db(host: &str, port: u16, name: &str, user: &str, password: &str) -> PgPool {
     info!("Initializing DB pool");
@@ -35,6 +40,22 @@ pub async fn init_db(host: &str, port: u16, name: &str, user: &str, password: &s
     pool
 }
 
+/// Creates pool of a read-only replica. Connections are established lazily, so an unreachable
+/// replica doesn't prevent startup. Migrations are not run, they're replicated from the primary.
+#[must_use]
+pub fn init_replica_pool(host: &str, port: u16, name: &str, user: &str, password: &str) -> PgPool {
+    info!("Initializing DB replica pool");
+    let opts = PgConnectOptions::new()
+        .host(host)
+        .port(port)
+        .username(user)
+        .password(password)
+        .database(name);
+    PgPoolOptions::new()
+        .acquire_timeout(REPLICA_ACQUIRE_TIMEOUT)
+        .connect_lazy_with(opts)
+}
+
 // Helper function to instantiate pool manually as a workaround for issues with `sqlx::test` macro
 // reference: https://github.com/launchbadge/sqlx/issues/2567#issuecomment-2009849261
 pub async fn setup_pool(options: PgConnectOptions) -> PgPool {
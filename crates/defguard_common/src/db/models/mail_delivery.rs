@@ -0,0 +1,172 @@
+use std::fmt;
+
+use chrono::{NaiveDateTime, Utc};
+use model_derive::Model;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, Type, query_as};
+
+use crate::db::{Id, NoId};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize, Type)]
+#[sqlx(type_name = "mail_delivery_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum MailDeliveryStatus {
+    /// Waiting to be sent, also between redelivery attempts.
+    Queued,
+    Sent,
+    /// Won't be delivered unless resent by an administrator.
+    Failed,
+}
+
+impl fmt::Display for MailDeliveryStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Queued => write!(f, "queued"),
+            Self::Sent => write!(f, "sent"),
+            Self::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// Record of an outgoing email, kept as a proof of delivery.
+///
+/// Message content is only kept until the message is sent, so that failed messages can be resent.
+#[derive(Clone, Debug, Deserialize, Model, Serialize)]
+#[table(mail_delivery)]
+pub struct MailDelivery<I = NoId> {
+    pub id: I,
+    pub recipient: String,
+    pub subject: String,
+    #[serde(skip_serializing)]
+    pub content: Option<String>,
+    #[serde(skip_serializing)]
+    pub plain_content: Option<String>,
+    /// Attachments aren't stored, so such messages can't be resent.
+    pub has_attachments: bool,
+    #[model(enum)]
+    pub status: MailDeliveryStatus,
+    pub attempts: i32,
+    /// Last response from the SMTP server or reason of the failure.
+    pub smtp_response: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub sent_at: Option<NaiveDateTime>,
+    pub failed_at: Option<NaiveDateTime>,
+    pub resent_at: Option<NaiveDateTime>,
+}
+
+impl MailDelivery {
+    #[must_use]
+    pub fn new(
+        recipient: String,
+        subject: String,
+        content: String,
+        plain_content: Option<String>,
+        has_attachments: bool,
+    ) -> Self {
+        Self {
+            id: NoId,
+            recipient,
+            subject,
+            content: Some(content),
+            plain_content,
+            has_attachments,
+            status: MailDeliveryStatus::Queued,
+            attempts: 0,
+            smtp_response: None,
+            created_at: Utc::now().naive_utc(),
+            sent_at: None,
+            failed_at: None,
+            resent_at: None,
+        }
+    }
+}
+
+impl<I> MailDelivery<I> {
+    /// Marks message as delivered to the SMTP server and drops its content.
+    pub fn register_sent(&mut self, response: String) {
+        self.attempts += 1;
+        self.status = MailDeliveryStatus::Sent;
+        self.smtp_response = Some(response);
+        self.sent_at = Some(Utc::now().naive_utc());
+        self.content = None;
+        self.plain_content = None;
+    }
+
+    /// Records a failed delivery attempt. Unless `permanent`, the message stays queued
+    /// for redelivery.
+    pub fn register_failure(&mut self, error: String, permanent: bool) {
+        self.attempts += 1;
+        self.smtp_response = Some(error);
+        if permanent {
+            self.give_up();
+        }
+    }
+
+    /// Marks message as failed without another delivery attempt.
+    pub fn give_up(&mut self) {
+        self.status = MailDeliveryStatus::Failed;
+        self.failed_at = Some(Utc::now().naive_utc());
+    }
+
+    /// Whether the message can be queued for delivery again.
+    #[must_use]
+    pub fn can_resend(&self) -> bool {
+        self.status == MailDeliveryStatus::Failed && self.content.is_some() && !self.has_attachments
+    }
+}
+
+impl MailDelivery<Id> {
+    /// Fetches most recent deliveries, optionally only those with given status.
+    pub async fn list<'e, E>(
+        executor: E,
+        status: Option<MailDeliveryStatus>,
+        limit: i64,
+    ) -> Result<Vec<Self>, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, recipient, subject, content, plain_content, has_attachments, \
+            status \"status: _\", attempts, smtp_response, created_at, sent_at, failed_at, \
+            resent_at FROM mail_delivery \
+            WHERE $1::mail_delivery_status IS NULL OR status = $1 ORDER BY id DESC LIMIT $2",
+            status as Option<MailDeliveryStatus>,
+            limit
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_delivery_status() {
+        let mut delivery = MailDelivery::new(
+            "hpotter@hogwart.edu.uk".into(),
+            "Enrollment".into(),
+            "<p>Welcome</p>".into(),
+            None,
+            false,
+        );
+        assert!(!delivery.can_resend());
+
+        delivery.register_failure("421 Service not available".into(), false);
+        assert_eq!(delivery.status, MailDeliveryStatus::Queued);
+        assert_eq!(delivery.attempts, 1);
+
+        delivery.register_failure("550 Mailbox unavailable".into(), true);
+        assert_eq!(delivery.status, MailDeliveryStatus::Failed);
+        assert!(delivery.failed_at.is_some());
+        assert!(delivery.can_resend());
+
+        delivery.register_sent("250 OK".into());
+        assert_eq!(delivery.status, MailDeliveryStatus::Sent);
+        assert_eq!(delivery.attempts, 3);
+        assert!(delivery.content.is_none());
+        assert!(!delivery.can_resend());
+    }
+}
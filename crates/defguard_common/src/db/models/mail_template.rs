@@ -0,0 +1,101 @@
+use std::fmt;
+
+use model_derive::Model;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, Type, query_as};
+
+use crate::db::{Id, NoId};
+
+/// Built-in email templates which can be overridden by administrators.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize, Type)]
+#[sqlx(type_name = "mail_template_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum MailTemplateKind {
+    EnrollmentStart,
+    NewDeviceLogin,
+    EmailMfaCode,
+}
+
+impl MailTemplateKind {
+    pub const ALL: [Self; 3] = [
+        Self::EnrollmentStart,
+        Self::NewDeviceLogin,
+        Self::EmailMfaCode,
+    ];
+
+    /// Names of variables available to the template, in addition to `date_now`,
+    /// `current_year` and `application_version` which every mail gets.
+    #[must_use]
+    pub fn variables(self) -> &'static [&'static str] {
+        match self {
+            Self::EnrollmentStart => &[
+                "first_name",
+                "last_name",
+                "username",
+                "defguard_url",
+                "admin_first_name",
+                "admin_last_name",
+                "admin_email",
+                "admin_phone",
+                "enrollment_url",
+                "link_url",
+                "token",
+            ],
+            Self::NewDeviceLogin => &["ip_address", "device_type"],
+            Self::EmailMfaCode => &["name", "code", "timeout"],
+        }
+    }
+}
+
+impl fmt::Display for MailTemplateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EnrollmentStart => write!(f, "enrollment start"),
+            Self::NewDeviceLogin => write!(f, "new device login"),
+            Self::EmailMfaCode => write!(f, "email MFA code"),
+        }
+    }
+}
+
+/// Custom subject and body used instead of the built-in template. Both are Tera templates
+/// rendered with the same variables as the template they replace.
+#[derive(Clone, Debug, Deserialize, Model, Serialize)]
+#[table(mail_template_override)]
+pub struct MailTemplateOverride<I = NoId> {
+    pub id: I,
+    #[model(enum)]
+    pub kind: MailTemplateKind,
+    pub subject: Option<String>,
+    pub body: Option<String>,
+}
+
+impl MailTemplateOverride {
+    #[must_use]
+    pub fn new(kind: MailTemplateKind, subject: Option<String>, body: Option<String>) -> Self {
+        Self {
+            id: NoId,
+            kind,
+            subject,
+            body,
+        }
+    }
+}
+
+impl MailTemplateOverride<Id> {
+    pub async fn find_by_kind<'e, E>(
+        executor: E,
+        kind: MailTemplateKind,
+    ) -> Result<Option<Self>, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, kind \"kind: _\", subject, body \
+            FROM mail_template_override WHERE kind = $1",
+            kind as MailTemplateKind
+        )
+        .fetch_optional(executor)
+        .await
+    }
+}
@@ -3,6 +3,8 @@ pub mod authentication_key;
 pub mod biometric_auth;
 pub mod device_login;
 pub mod error;
+pub mod mail_delivery;
+pub mod mail_template;
 pub mod settings;
 pub mod user;
 
@@ -11,5 +13,7 @@ pub use authentication_key::{AuthenticationKey, AuthenticationKeyType};
 pub use biometric_auth::{BiometricAuth, BiometricChallenge};
 pub use device_login::DeviceLoginEvent;
 pub use error::ModelError;
+pub use mail_delivery::{MailDelivery, MailDeliveryStatus};
+pub use mail_template::{MailTemplateKind, MailTemplateOverride};
 pub use settings::{Settings, SettingsEssentials};
 pub use user::MFAMethod;
@@ -1,5 +1,7 @@
 use std::{collections::HashMap, fmt};
 
+use chrono_tz::Tz;
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
 use sqlx::{PgExecutor, PgPool, Type, query, query_as};
 use struct_patch::Patch;
@@ -11,6 +13,11 @@ use crate::{global_value, secret::SecretStringWrapper};
 
 global_value!(SETTINGS, Option<Settings>, None, set_settings, get_settings);
 
+/// Maximal length of user passwords.
+pub const MAX_PASSWORD_LENGTH: i32 = 128;
+/// Maximal number of previous passwords of a user remembered to prevent their reuse.
+pub const MAX_PASSWORD_HISTORY_SIZE: i32 = 24;
+
 /// Initializes global `SETTINGS` struct at program startup
 pub async fn initialize_current_settings(pool: &PgPool) -> Result<(), sqlx::Error> {
     debug!("Initializing global settings struct");
@@ -40,6 +47,24 @@ pub async fn update_current_settings<'e, E: sqlx::PgExecutor<'e>>(
 pub enum SettingsValidationError {
     #[error("Cannot enable gateway disconnect notifications. SMTP is not configured")]
     CannotEnableGatewayNotifications,
+    #[error("Invalid password hashing parameters")]
+    InvalidPasswordHashParams,
+    #[error("Unknown timezone {0}")]
+    InvalidTimezone(String),
+    #[error("Activity log retention period must be a positive number of days")]
+    InvalidActivityLogRetention,
+    #[error("Invalid LDAP attribute name {0}")]
+    InvalidLdapAttribute(String),
+    #[error("Invalid LDAP group pattern {0}")]
+    InvalidLdapGroupPattern(String),
+    #[error("Invalid TOTP parameters")]
+    InvalidTotpParams,
+    #[error("Invalid password policy")]
+    InvalidPasswordPolicy,
+    #[error("Invalid login lockout parameters")]
+    InvalidLoginLockout,
+    #[error("CAPTCHA site key and secret key are required")]
+    InvalidCaptcha,
 }
 
 #[derive(Clone, Deserialize, Serialize, PartialEq, Eq, Type, Debug, Default)]
@@ -63,6 +88,26 @@ pub enum OpenidUsernameHandling {
     PruneEmailDomain,
 }
 
+/// Hash algorithm used to generate TOTP codes.
+#[derive(Clone, Debug, Copy, Eq, PartialEq, Deserialize, Serialize, Default, Type)]
+#[sqlx(type_name = "totp_algorithm", rename_all = "lowercase")]
+pub enum TotpAlgorithm {
+    #[default]
+    Sha1,
+    Sha256,
+}
+
+/// Service verifying CAPTCHA challenges on public enrollment and password reset pages.
+#[derive(Clone, Debug, Copy, Eq, PartialEq, Deserialize, Serialize, Default, Type)]
+#[sqlx(type_name = "captcha_provider", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CaptchaProvider {
+    #[default]
+    None,
+    HCaptcha,
+    Turnstile,
+}
+
 #[derive(Clone, Debug, Copy, Eq, PartialEq, Deserialize, Serialize, Default, Type)]
 #[sqlx(type_name = "ldap_sync_status", rename_all = "lowercase")]
 pub enum LdapSyncStatus {
@@ -99,6 +144,11 @@ pub struct Settings {
     pub smtp_user: Option<String>,
     pub smtp_password: Option<SecretStringWrapper>,
     pub smtp_sender: Option<String>,
+    // SMS gateway
+    pub sms_gateway_url: Option<String>,
+    pub sms_gateway_user: Option<String>,
+    pub sms_gateway_password: Option<SecretStringWrapper>,
+    pub sms_sender: Option<String>,
     // Enrollment
     pub enrollment_vpn_step_optional: bool,
     pub enrollment_welcome_message: Option<String>,
@@ -135,14 +185,64 @@ pub struct Settings {
     // The attribute which is used to map LDAP usernames to Defguard usernames
     pub ldap_user_rdn_attr: Option<String>,
     pub ldap_sync_groups: Vec<String>,
-    // Whether to create a new account when users try to log in with external OpenID
-    pub openid_create_account: bool,
+    // Attributes holding user profile data, for directories with non-standard schemas
+    pub ldap_email_attr: Option<String>,
+    pub ldap_first_name_attr: Option<String>,
+    pub ldap_last_name_attr: Option<String>,
+    pub ldap_phone_attr: Option<String>,
+    // Patterns limiting which groups are synced, empty list means all groups
+    pub ldap_group_filter: Vec<String>,
+    // Patterns of groups which are never synced
+    pub ldap_group_exclude: Vec<String>,
     pub openid_username_handling: OpenidUsernameHandling,
     pub license: Option<String>,
     // Gateway disconnect notifications
     pub gateway_disconnect_notifications_enabled: bool,
     pub gateway_disconnect_notifications_inactivity_threshold: i32,
     pub gateway_disconnect_notifications_reconnect_notification_enabled: bool,
+    // Argon2id parameters used for new password hashes
+    // memory cost in KiB
+    pub password_hash_memory_cost: i32,
+    // number of iterations
+    pub password_hash_time_cost: i32,
+    // degree of parallelism
+    pub password_hash_parallelism: i32,
+    // IANA name of the timezone used for scheduling and displaying timestamps
+    pub timezone: String,
+    // number of days activity log events are kept for, `None` keeps them forever
+    pub activity_log_retention_days: Option<i32>,
+    // move expired activity log events to the archive table instead of deleting them
+    pub activity_log_retention_archive: bool,
+    // TOTP parameters, changing the algorithm or digits requires users to enroll TOTP again
+    pub totp_algorithm: TotpAlgorithm,
+    pub totp_digits: i32,
+    // number of time steps before and after the current one accepted to tolerate clock drift
+    pub totp_skew: i32,
+    // Password policy
+    pub password_min_length: i32,
+    pub password_require_uppercase: bool,
+    pub password_require_lowercase: bool,
+    pub password_require_digit: bool,
+    pub password_require_special: bool,
+    // minimal zxcvbn strength score (0-4), 0 doesn't check the strength
+    pub password_min_score: i32,
+    // passwords which can't be used, compared case-insensitively
+    pub password_banned: Vec<String>,
+    // number of most recent passwords of a user which can't be reused, 0 allows reuse
+    pub password_history_size: i32,
+    // Login lockout
+    // consecutive failed logins of a user before the account is locked, 0 disables the lockout
+    pub login_lockout_threshold: i32,
+    // consecutive failed logins from an IP address before it is locked, 0 disables the lockout
+    pub login_lockout_ip_threshold: i32,
+    // lockout duration in minutes
+    pub login_lockout_duration: i32,
+    // let locked users with MFA enabled log in, as they still have to complete MFA
+    pub login_lockout_mfa_fallback: bool,
+    // CAPTCHA on enrollment start and password reset request pages served by the proxy
+    pub captcha_provider: CaptchaProvider,
+    pub captcha_site_key: Option<String>,
+    pub captcha_secret_key: Option<SecretStringWrapper>,
 }
 
 // Implement manually to avoid exposing the license key.
@@ -163,6 +263,10 @@ impl fmt::Debug for Settings {
             .field("smtp_user", &self.smtp_user)
             .field("smtp_password", &self.smtp_password)
             .field("smtp_sender", &self.smtp_sender)
+            .field("sms_gateway_url", &self.sms_gateway_url)
+            .field("sms_gateway_user", &self.sms_gateway_user)
+            .field("sms_gateway_password", &self.sms_gateway_password)
+            .field("sms_sender", &self.sms_sender)
             .field(
                 "enrollment_vpn_step_optional",
                 &self.enrollment_vpn_step_optional,
@@ -206,7 +310,12 @@ impl fmt::Debug for Settings {
             )
             .field("ldap_user_rdn_attr", &self.ldap_user_rdn_attr)
             .field("ldap_sync_groups", &self.ldap_sync_groups)
-            .field("openid_create_account", &self.openid_create_account)
+            .field("ldap_email_attr", &self.ldap_email_attr)
+            .field("ldap_first_name_attr", &self.ldap_first_name_attr)
+            .field("ldap_last_name_attr", &self.ldap_last_name_attr)
+            .field("ldap_phone_attr", &self.ldap_phone_attr)
+            .field("ldap_group_filter", &self.ldap_group_filter)
+            .field("ldap_group_exclude", &self.ldap_group_exclude)
             .field("openid_username_handling", &self.openid_username_handling)
             .field(
                 "gateway_disconnect_notifications_enabled",
@@ -220,6 +329,48 @@ impl fmt::Debug for Settings {
                 "gateway_disconnect_notifications_reconnect_notification_enabled",
                 &self.gateway_disconnect_notifications_reconnect_notification_enabled,
             )
+            .field("password_hash_memory_cost", &self.password_hash_memory_cost)
+            .field("password_hash_time_cost", &self.password_hash_time_cost)
+            .field("password_hash_parallelism", &self.password_hash_parallelism)
+            .field("timezone", &self.timezone)
+            .field(
+                "activity_log_retention_days",
+                &self.activity_log_retention_days,
+            )
+            .field(
+                "activity_log_retention_archive",
+                &self.activity_log_retention_archive,
+            )
+            .field("totp_algorithm", &self.totp_algorithm)
+            .field("totp_digits", &self.totp_digits)
+            .field("totp_skew", &self.totp_skew)
+            .field("password_min_length", &self.password_min_length)
+            .field(
+                "password_require_uppercase",
+                &self.password_require_uppercase,
+            )
+            .field(
+                "password_require_lowercase",
+                &self.password_require_lowercase,
+            )
+            .field("password_require_digit", &self.password_require_digit)
+            .field("password_require_special", &self.password_require_special)
+            .field("password_min_score", &self.password_min_score)
+            .field("password_banned", &self.password_banned.len())
+            .field("password_history_size", &self.password_history_size)
+            .field("login_lockout_threshold", &self.login_lockout_threshold)
+            .field(
+                "login_lockout_ip_threshold",
+                &self.login_lockout_ip_threshold,
+            )
+            .field("login_lockout_duration", &self.login_lockout_duration)
+            .field(
+                "login_lockout_mfa_fallback",
+                &self.login_lockout_mfa_fallback,
+            )
+            .field("captcha_provider", &self.captcha_provider)
+            .field("captcha_site_key", &self.captcha_site_key)
+            .field("captcha_secret_key", &self.captcha_secret_key)
             .finish_non_exhaustive()
     }
 }
@@ -241,15 +392,27 @@ impl Settings {
             ldap_bind_password \"ldap_bind_password?: SecretStringWrapper\", \
             ldap_group_search_base, ldap_user_search_base, ldap_user_obj_class, \
             ldap_group_obj_class, ldap_username_attr, ldap_groupname_attr, \
-            ldap_group_member_attr, ldap_member_attr, openid_create_account, \
+            ldap_group_member_attr, ldap_member_attr, \
             license, gateway_disconnect_notifications_enabled, ldap_use_starttls, \
             ldap_tls_verify_cert, gateway_disconnect_notifications_inactivity_threshold, \
             gateway_disconnect_notifications_reconnect_notification_enabled, \
             ldap_sync_status \"ldap_sync_status: LdapSyncStatus\", \
             ldap_enabled, ldap_sync_enabled, ldap_is_authoritative, \
             ldap_sync_interval, ldap_user_auxiliary_obj_classes, ldap_uses_ad, \
-            ldap_user_rdn_attr, ldap_sync_groups, \
-            openid_username_handling \"openid_username_handling: OpenidUsernameHandling\" \
+            ldap_user_rdn_attr, ldap_sync_groups, ldap_email_attr, ldap_first_name_attr, \
+            ldap_last_name_attr, ldap_phone_attr, ldap_group_filter, ldap_group_exclude, \
+            openid_username_handling \"openid_username_handling: OpenidUsernameHandling\", \
+            password_hash_memory_cost, password_hash_time_cost, password_hash_parallelism, \
+            timezone, activity_log_retention_days, activity_log_retention_archive, \
+            totp_algorithm \"totp_algorithm: TotpAlgorithm\", totp_digits, totp_skew, \
+            sms_gateway_url, sms_gateway_user, \
+            sms_gateway_password \"sms_gateway_password?: SecretStringWrapper\", sms_sender, \
+            password_min_length, password_require_uppercase, password_require_lowercase, \
+            password_require_digit, password_require_special, password_min_score, \
+            password_banned, password_history_size, login_lockout_threshold, \
+            login_lockout_ip_threshold, login_lockout_duration, login_lockout_mfa_fallback, \
+            captcha_provider \"captcha_provider: CaptchaProvider\", captcha_site_key, \
+            captcha_secret_key \"captcha_secret_key?: SecretStringWrapper\" \
             FROM \"settings\" WHERE id = 1",
         )
         .fetch_optional(executor)
@@ -269,6 +432,106 @@ impl Settings {
             warn!("Cannot enable gateway disconnect notifications. SMTP is not configured.");
             return Err(SettingsValidationError::CannotEnableGatewayNotifications);
         }
+        // Argon2 requires at least 8 KiB of memory per lane.
+        if self.password_hash_time_cost < 1
+            || self.password_hash_parallelism < 1
+            || i64::from(self.password_hash_memory_cost)
+                < 8 * i64::from(self.password_hash_parallelism)
+        {
+            warn!("Invalid password hashing parameters: {self:?}");
+            return Err(SettingsValidationError::InvalidPasswordHashParams);
+        }
+        if self.timezone.parse::<Tz>().is_err() {
+            warn!("Unknown timezone: {}", self.timezone);
+            return Err(SettingsValidationError::InvalidTimezone(
+                self.timezone.clone(),
+            ));
+        }
+        if self
+            .activity_log_retention_days
+            .is_some_and(|days| days < 1)
+        {
+            warn!(
+                "Invalid activity log retention period: {:?}",
+                self.activity_log_retention_days
+            );
+            return Err(SettingsValidationError::InvalidActivityLogRetention);
+        }
+        if !(6..=8).contains(&self.totp_digits) || !(0..=10).contains(&self.totp_skew) {
+            warn!(
+                "Invalid TOTP parameters: {} digits, skew of {} steps",
+                self.totp_digits, self.totp_skew
+            );
+            return Err(SettingsValidationError::InvalidTotpParams);
+        }
+        if !(1..=MAX_PASSWORD_LENGTH).contains(&self.password_min_length)
+            || !(0..=4).contains(&self.password_min_score)
+            || !(0..=MAX_PASSWORD_HISTORY_SIZE).contains(&self.password_history_size)
+        {
+            warn!(
+                "Invalid password policy: minimal length {}, minimal score {}, history of {} \
+                passwords",
+                self.password_min_length, self.password_min_score, self.password_history_size
+            );
+            return Err(SettingsValidationError::InvalidPasswordPolicy);
+        }
+        if self.login_lockout_threshold < 0
+            || self.login_lockout_ip_threshold < 0
+            || self.login_lockout_duration < 1
+        {
+            warn!(
+                "Invalid login lockout parameters: user threshold {}, IP threshold {}, duration {} \
+                minutes",
+                self.login_lockout_threshold,
+                self.login_lockout_ip_threshold,
+                self.login_lockout_duration
+            );
+            return Err(SettingsValidationError::InvalidLoginLockout);
+        }
+        if self.captcha_provider != CaptchaProvider::None
+            && (self.captcha_site_key.as_deref().is_none_or(str::is_empty)
+                || self
+                    .captcha_secret_key
+                    .as_ref()
+                    .is_none_or(|key| key.expose_secret().is_empty()))
+        {
+            warn!(
+                "Cannot enable {:?} CAPTCHA without a site key and a secret key",
+                self.captcha_provider
+            );
+            return Err(SettingsValidationError::InvalidCaptcha);
+        }
+        for attr in [
+            &self.ldap_username_attr,
+            &self.ldap_groupname_attr,
+            &self.ldap_group_member_attr,
+            &self.ldap_member_attr,
+            &self.ldap_user_rdn_attr,
+            &self.ldap_email_attr,
+            &self.ldap_first_name_attr,
+            &self.ldap_last_name_attr,
+            &self.ldap_phone_attr,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if !attr.is_empty() && !is_valid_ldap_attribute(attr) {
+                warn!("Invalid LDAP attribute name: {attr}");
+                return Err(SettingsValidationError::InvalidLdapAttribute(attr.clone()));
+            }
+        }
+        for pattern in self
+            .ldap_group_filter
+            .iter()
+            .chain(&self.ldap_group_exclude)
+        {
+            if let Err(err) = ldap_group_pattern(pattern) {
+                warn!("Invalid LDAP group pattern {pattern}: {err}");
+                return Err(SettingsValidationError::InvalidLdapGroupPattern(
+                    pattern.clone(),
+                ));
+            }
+        }
 
         Ok(())
     }
@@ -312,21 +575,54 @@ impl Settings {
             ldap_member_attr = $31, \
             ldap_use_starttls = $32, \
             ldap_tls_verify_cert = $33, \
-            openid_create_account = $34, \
-            license = $35, \
-            gateway_disconnect_notifications_enabled = $36, \
-            gateway_disconnect_notifications_inactivity_threshold = $37, \
-            gateway_disconnect_notifications_reconnect_notification_enabled = $38, \
-            ldap_sync_status = $39, \
-            ldap_enabled = $40, \
-            ldap_sync_enabled = $41, \
-            ldap_is_authoritative = $42, \
-            ldap_sync_interval = $43, \
-            ldap_user_auxiliary_obj_classes = $44, \
-            ldap_uses_ad = $45, \
-            ldap_user_rdn_attr = $46, \
-            ldap_sync_groups = $47, \
-            openid_username_handling = $48 \
+            license = $34, \
+            gateway_disconnect_notifications_enabled = $35, \
+            gateway_disconnect_notifications_inactivity_threshold = $36, \
+            gateway_disconnect_notifications_reconnect_notification_enabled = $37, \
+            ldap_sync_status = $38, \
+            ldap_enabled = $39, \
+            ldap_sync_enabled = $40, \
+            ldap_is_authoritative = $41, \
+            ldap_sync_interval = $42, \
+            ldap_user_auxiliary_obj_classes = $43, \
+            ldap_uses_ad = $44, \
+            ldap_user_rdn_attr = $45, \
+            ldap_sync_groups = $46, \
+            openid_username_handling = $47, \
+            password_hash_memory_cost = $48, \
+            password_hash_time_cost = $49, \
+            password_hash_parallelism = $50, \
+            timezone = $51, \
+            activity_log_retention_days = $52, \
+            activity_log_retention_archive = $53, \
+            ldap_email_attr = $54, \
+            ldap_first_name_attr = $55, \
+            ldap_last_name_attr = $56, \
+            ldap_phone_attr = $57, \
+            ldap_group_filter = $58, \
+            ldap_group_exclude = $59, \
+            totp_algorithm = $60, \
+            totp_digits = $61, \
+            totp_skew = $62, \
+            sms_gateway_url = $63, \
+            sms_gateway_user = $64, \
+            sms_gateway_password = $65, \
+            sms_sender = $66, \
+            password_min_length = $67, \
+            password_require_uppercase = $68, \
+            password_require_lowercase = $69, \
+            password_require_digit = $70, \
+            password_require_special = $71, \
+            password_min_score = $72, \
+            password_banned = $73, \
+            password_history_size = $74, \
+            login_lockout_threshold = $75, \
+            login_lockout_ip_threshold = $76, \
+            login_lockout_duration = $77, \
+            login_lockout_mfa_fallback = $78, \
+            captcha_provider = $79, \
+            captcha_site_key = $80, \
+            captcha_secret_key = $81 \
             WHERE id = 1",
             self.openid_enabled,
             self.wireguard_enabled,
@@ -361,7 +657,6 @@ impl Settings {
             self.ldap_member_attr,
             self.ldap_use_starttls,
             self.ldap_tls_verify_cert,
-            self.openid_create_account,
             self.license,
             self.gateway_disconnect_notifications_enabled,
             self.gateway_disconnect_notifications_inactivity_threshold,
@@ -376,6 +671,40 @@ impl Settings {
             self.ldap_user_rdn_attr,
             &self.ldap_sync_groups as &Vec<String>,
             &self.openid_username_handling as &OpenidUsernameHandling,
+            self.password_hash_memory_cost,
+            self.password_hash_time_cost,
+            self.password_hash_parallelism,
+            self.timezone,
+            self.activity_log_retention_days,
+            self.activity_log_retention_archive,
+            self.ldap_email_attr,
+            self.ldap_first_name_attr,
+            self.ldap_last_name_attr,
+            self.ldap_phone_attr,
+            &self.ldap_group_filter as &Vec<String>,
+            &self.ldap_group_exclude as &Vec<String>,
+            &self.totp_algorithm as &TotpAlgorithm,
+            self.totp_digits,
+            self.totp_skew,
+            self.sms_gateway_url,
+            self.sms_gateway_user,
+            &self.sms_gateway_password as &Option<SecretStringWrapper>,
+            self.sms_sender,
+            self.password_min_length,
+            self.password_require_uppercase,
+            self.password_require_lowercase,
+            self.password_require_digit,
+            self.password_require_special,
+            self.password_min_score,
+            &self.password_banned as &Vec<String>,
+            self.password_history_size,
+            self.login_lockout_threshold,
+            self.login_lockout_ip_threshold,
+            self.login_lockout_duration,
+            self.login_lockout_mfa_fallback,
+            &self.captcha_provider as &CaptchaProvider,
+            self.captcha_site_key,
+            &self.captcha_secret_key as &Option<SecretStringWrapper>,
         )
         .execute(executor)
         .await?;
@@ -383,6 +712,12 @@ impl Settings {
         Ok(())
     }
 
+    /// Deployment timezone. Falls back to UTC if the configured name is unknown.
+    #[must_use]
+    pub fn deployment_timezone(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
+
     #[must_use]
     pub fn get_current_settings() -> Self {
         // fetch global settings
@@ -428,6 +763,19 @@ impl Settings {
             && self.smtp_sender != Some(String::new())
     }
 
+    /// Check if all required SMS gateway options are configured.
+    /// User & password can be empty for gateways without authentication.
+    #[must_use]
+    pub fn sms_configured(&self) -> bool {
+        self.sms_gateway_url
+            .as_deref()
+            .is_some_and(|url| !url.is_empty())
+            && self
+                .sms_sender
+                .as_deref()
+                .is_some_and(|sender| !sender.is_empty())
+    }
+
     #[must_use]
     pub fn ldap_using_username_as_rdn(&self) -> bool {
         self.ldap_user_rdn_attr
@@ -477,6 +825,51 @@ impl From<Settings> for SettingsEssentials {
     }
 }
 
+/// Checks if the name is a valid LDAP attribute description: either a descriptor, starting with
+/// a letter and followed by letters, digits or hyphens, or a numeric OID (RFC 4512, section 1.4).
+fn is_valid_ldap_attribute(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '-')
+        }
+        Some(first) if first.is_ascii_digit() => name
+            .split('.')
+            .all(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit())),
+        _ => false,
+    }
+}
+
+/// Compiles LDAP group name pattern into a case-insensitive regular expression.
+///
+/// Patterns enclosed in slashes (e.g. `/^vpn-\d+$/`) are regular expressions, other patterns are
+/// globs matching the whole group name, where `*` matches any sequence of characters and `?`
+/// matches a single character.
+pub fn ldap_group_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    let expression = match pattern
+        .strip_prefix('/')
+        .and_then(|rest| rest.strip_suffix('/'))
+    {
+        Some(expression) => expression.to_string(),
+        None => {
+            let mut expression = String::from("^");
+            for character in pattern.chars() {
+                match character {
+                    '*' => expression.push_str(".*"),
+                    '?' => expression.push('.'),
+                    _ => expression.push_str(&regex::escape(&character.to_string())),
+                }
+            }
+            expression.push('$');
+            expression
+        }
+    };
+
+    RegexBuilder::new(&expression)
+        .case_insensitive(true)
+        .build()
+}
+
 pub mod defaults {
     pub static WELCOME_MESSAGE: &str = "Dear {{ first_name }} {{ last_name }},
 
@@ -522,6 +915,20 @@ mod test {
 
     use super::*;
 
+    /// Settings which pass validation, validated fields use their database defaults.
+    fn valid_settings() -> Settings {
+        Settings {
+            password_hash_memory_cost: 19456,
+            password_hash_time_cost: 2,
+            password_hash_parallelism: 1,
+            timezone: "UTC".into(),
+            totp_digits: 6,
+            password_min_length: 8,
+            login_lockout_duration: 15,
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_smtp_config() {
         let mut settings = Settings::default();
@@ -546,6 +953,129 @@ mod test {
         assert!(settings.smtp_configured());
     }
 
+    #[test]
+    fn test_timezone_validation() {
+        let mut settings = Settings {
+            timezone: "Europe/Warsaw".into(),
+            ..valid_settings()
+        };
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.deployment_timezone(), Tz::Europe__Warsaw);
+
+        settings.timezone = "Mars/Olympus_Mons".into();
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsValidationError::InvalidTimezone(_))
+        ));
+        assert_eq!(settings.deployment_timezone(), Tz::UTC);
+    }
+
+    #[test]
+    fn test_activity_log_retention_validation() {
+        let mut settings = valid_settings();
+        assert!(settings.validate().is_ok());
+
+        settings.activity_log_retention_days = Some(90);
+        assert!(settings.validate().is_ok());
+
+        settings.activity_log_retention_days = Some(0);
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsValidationError::InvalidActivityLogRetention)
+        ));
+    }
+
+    #[test]
+    fn test_totp_validation() {
+        let mut settings = Settings {
+            totp_algorithm: TotpAlgorithm::Sha256,
+            totp_digits: 8,
+            totp_skew: 1,
+            ..valid_settings()
+        };
+        assert!(settings.validate().is_ok());
+
+        settings.totp_digits = 10;
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsValidationError::InvalidTotpParams)
+        ));
+
+        settings.totp_digits = 6;
+        settings.totp_skew = -1;
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsValidationError::InvalidTotpParams)
+        ));
+    }
+
+    #[test]
+    fn test_captcha_validation() {
+        let mut settings = Settings {
+            captcha_provider: CaptchaProvider::HCaptcha,
+            ..valid_settings()
+        };
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsValidationError::InvalidCaptcha)
+        ));
+
+        settings.captcha_site_key = Some("site-key".into());
+        settings.captcha_secret_key = Some(SecretStringWrapper::from_str("").unwrap());
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsValidationError::InvalidCaptcha)
+        ));
+
+        settings.captcha_secret_key = Some(SecretStringWrapper::from_str("secret").unwrap());
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn test_ldap_attribute_validation() {
+        let mut settings = Settings {
+            ldap_email_attr: Some("userPrincipalName".into()),
+            ldap_phone_attr: Some("2.5.4.20".into()),
+            ldap_user_rdn_attr: Some(String::new()),
+            ..valid_settings()
+        };
+        assert!(settings.validate().is_ok());
+
+        for invalid in ["given name", "sn)(uid=*", "-mail", "2.5..4"] {
+            settings.ldap_first_name_attr = Some(invalid.into());
+            assert!(matches!(
+                settings.validate(),
+                Err(SettingsValidationError::InvalidLdapAttribute(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_ldap_group_pattern() {
+        let glob = ldap_group_pattern("vpn-*.admins?").unwrap();
+        assert!(glob.is_match("vpn-office.admins1"));
+        assert!(glob.is_match("VPN-.ADMINSx"));
+        assert!(!glob.is_match("vpn-office-admins1"));
+        assert!(!glob.is_match("old-vpn-office.admins1"));
+
+        let regex = ldap_group_pattern(r"/^team-\d+$/").unwrap();
+        assert!(regex.is_match("Team-42"));
+        assert!(!regex.is_match("team-x"));
+
+        let mut settings = Settings {
+            ldap_group_filter: vec!["vpn-*".into()],
+            ldap_group_exclude: vec!["/^vpn-(test|dev)/".into()],
+            ..valid_settings()
+        };
+        assert!(settings.validate().is_ok());
+
+        settings.ldap_group_exclude.push("/vpn-(/".into());
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsValidationError::InvalidLdapGroupPattern(_))
+        ));
+    }
+
     #[test]
     fn dg25_32_test_dont_expose_license_key() {
         let key = "0000000000000000";
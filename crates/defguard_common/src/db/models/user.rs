@@ -11,6 +11,7 @@ pub enum MFAMethod {
     OneTimePassword,
     Webauthn,
     Email,
+    Sms,
 }
 
 // Web MFA methods
@@ -24,6 +25,7 @@ impl fmt::Display for MFAMethod {
                 MFAMethod::OneTimePassword => "TOTP",
                 MFAMethod::Webauthn => "WebAuthn",
                 MFAMethod::Email => "Email",
+                MFAMethod::Sms => "SMS",
             }
         )
     }
@@ -0,0 +1,584 @@
+//! Saturation statistics of internal message queues and other runtime metrics.
+//!
+//! Queue consumers record the number of messages still waiting after each receive along with
+//! the time it took to process them, so that backpressure can be observed before it results in
+//! lost emails or stale gateway configuration. Request latencies and failure counters are
+//! recorded where they happen and exported in Prometheus text exposition format.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::db::Id;
+
+/// Activity log events waiting to be stored by the event logger.
+pub static EVENT_LOGGER_QUEUE: QueueStats = QueueStats::new();
+/// Emails waiting to be sent by the mail handler.
+pub static MAIL_QUEUE: QueueStats = QueueStats::new();
+/// Events broadcast to connected gateways, each gateway consumes its own copy.
+pub static GATEWAY_EVENT_QUEUES: GatewayQueueStats = GatewayQueueStats::new();
+
+/// Failed MFA attempts, labeled by source (web or desktop client) and method.
+pub static MFA_FAILURES: LabeledCounters = LabeledCounters::new();
+/// Processing time of HTTP requests, labeled by method and matched route.
+pub static HTTP_REQUESTS: RequestLatencies = RequestLatencies::new();
+/// Processing time of gRPC requests, labeled by gRPC method path.
+pub static GRPC_REQUESTS: RequestLatencies = RequestLatencies::new();
+
+/// Mail handler, reporting SMTP errors.
+pub static MAIL_HANDLER: ComponentState = ComponentState::new();
+/// LDAP synchronization, reporting sync errors.
+pub static LDAP_SYNC: ComponentState = ComponentState::new();
+/// Utility thread running periodic jobs, sending a heartbeat on every iteration.
+pub static UTILITY_THREAD: ComponentState = ComponentState::new();
+
+// Upper bounds of request latency histogram buckets in seconds
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug)]
+pub struct QueueStats {
+    depth: AtomicU64,
+    max_depth: AtomicU64,
+    processed: AtomicU64,
+    total_latency_us: AtomicU64,
+    max_latency_us: AtomicU64,
+    dropped: AtomicU64,
+    last_processed: AtomicU64,
+}
+
+impl QueueStats {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            depth: AtomicU64::new(0),
+            max_depth: AtomicU64::new(0),
+            processed: AtomicU64::new(0),
+            total_latency_us: AtomicU64::new(0),
+            max_latency_us: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            last_processed: AtomicU64::new(0),
+        }
+    }
+
+    /// Records the number of messages waiting in the queue.
+    pub fn record_depth(&self, depth: usize) {
+        let depth = depth as u64;
+        self.depth.store(depth, Ordering::Relaxed);
+        self.max_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    /// Records `count` messages processed in `latency`.
+    pub fn record_processed(&self, count: usize, latency: Duration) {
+        let latency_us = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        self.processed.fetch_add(count as u64, Ordering::Relaxed);
+        self.total_latency_us
+            .fetch_add(latency_us, Ordering::Relaxed);
+        self.max_latency_us.fetch_max(latency_us, Ordering::Relaxed);
+        self.last_processed.store(unix_now(), Ordering::Relaxed);
+    }
+
+    /// Records messages which were skipped because the consumer couldn't keep up.
+    pub fn record_dropped(&self, count: u64) {
+        self.dropped.fetch_add(count, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> QueueSnapshot {
+        let processed = self.processed.load(Ordering::Relaxed);
+        let total_latency_us = self.total_latency_us.load(Ordering::Relaxed);
+        let avg_latency_ms = if processed == 0 {
+            0.0
+        } else {
+            total_latency_us as f64 / processed as f64 / 1000.0
+        };
+        let last_processed = self.last_processed.load(Ordering::Relaxed);
+
+        QueueSnapshot {
+            depth: self.depth.load(Ordering::Relaxed),
+            max_depth: self.max_depth.load(Ordering::Relaxed),
+            processed,
+            dropped: self.dropped.load(Ordering::Relaxed),
+            avg_latency_ms,
+            max_latency_ms: self.max_latency_us.load(Ordering::Relaxed) as f64 / 1000.0,
+            last_processed: (last_processed != 0).then_some(last_processed),
+        }
+    }
+}
+
+impl Default for QueueStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Point-in-time view of [`QueueStats`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct QueueSnapshot {
+    pub depth: u64,
+    pub max_depth: u64,
+    pub processed: u64,
+    pub dropped: u64,
+    pub avg_latency_ms: f64,
+    pub max_latency_ms: f64,
+    /// Unix timestamp of the last processed message.
+    pub last_processed: Option<u64>,
+}
+
+impl QueueSnapshot {
+    /// Combines snapshots of queues fed with the same messages, like receivers of a broadcast
+    /// channel. Depth and latencies are those of the slowest consumer.
+    #[must_use]
+    pub fn aggregate<'a>(snapshots: impl IntoIterator<Item = &'a Self>) -> Self {
+        let mut aggregate = Self {
+            depth: 0,
+            max_depth: 0,
+            processed: 0,
+            dropped: 0,
+            avg_latency_ms: 0.0,
+            max_latency_ms: 0.0,
+            last_processed: None,
+        };
+        let mut total_latency_ms = 0.0;
+        for snapshot in snapshots {
+            aggregate.depth = aggregate.depth.max(snapshot.depth);
+            aggregate.max_depth = aggregate.max_depth.max(snapshot.max_depth);
+            aggregate.processed += snapshot.processed;
+            aggregate.dropped += snapshot.dropped;
+            aggregate.max_latency_ms = aggregate.max_latency_ms.max(snapshot.max_latency_ms);
+            aggregate.last_processed = aggregate.last_processed.max(snapshot.last_processed);
+            total_latency_ms += snapshot.avg_latency_ms * snapshot.processed as f64;
+        }
+        if aggregate.processed > 0 {
+            aggregate.avg_latency_ms = total_latency_ms / aggregate.processed as f64;
+        }
+        aggregate
+    }
+}
+
+/// Queue statistics of gateway event streams, kept separately for every gateway since each one
+/// receives all events on its own broadcast receiver.
+#[derive(Debug, Default)]
+pub struct GatewayQueueStats(Mutex<BTreeMap<(Id, String), Arc<QueueStats>>>);
+
+impl GatewayQueueStats {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(Mutex::new(BTreeMap::new()))
+    }
+
+    /// Returns statistics of a gateway identified by location ID and hostname. Statistics are
+    /// kept across reconnections of the same gateway.
+    pub fn gateway(&self, location_id: Id, hostname: &str) -> Arc<QueueStats> {
+        let mut queues = self.0.lock().expect("Failed to acquire metrics lock");
+        Arc::clone(
+            queues
+                .entry((location_id, hostname.to_string()))
+                .or_default(),
+        )
+    }
+
+    /// Snapshots of all gateways, keyed by location ID and hostname.
+    #[must_use]
+    pub fn snapshots(&self) -> Vec<((Id, String), QueueSnapshot)> {
+        let queues = self.0.lock().expect("Failed to acquire metrics lock");
+        queues
+            .iter()
+            .map(|(key, stats)| (key.clone(), stats.snapshot()))
+            .collect()
+    }
+}
+
+/// Renders queue snapshots in Prometheus text exposition format.
+#[must_use]
+pub fn render_metrics(queues: &[(&str, &QueueSnapshot)]) -> String {
+    let queues = queues
+        .iter()
+        .map(|(queue, snapshot)| (labels(&[("queue", queue)]), *snapshot))
+        .collect::<Vec<_>>();
+    render_labeled_metrics(&queues)
+}
+
+/// Renders queue snapshots distinguished by rendered label sets (see [`labels`]) in Prometheus
+/// text exposition format.
+#[must_use]
+pub fn render_labeled_metrics(queues: &[(String, &QueueSnapshot)]) -> String {
+    let mut metrics = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: fn(&QueueSnapshot) -> f64| {
+        let _ = writeln!(metrics, "# HELP defguard_queue_{name} {help}");
+        let _ = writeln!(metrics, "# TYPE defguard_queue_{name} {kind}");
+        for (labels, snapshot) in queues {
+            let _ = writeln!(
+                metrics,
+                "defguard_queue_{name}{{{labels}}} {}",
+                value(snapshot)
+            );
+        }
+    };
+    metric(
+        "depth",
+        "gauge",
+        "Number of messages waiting in the queue.",
+        |s| s.depth as f64,
+    );
+    metric(
+        "max_depth",
+        "gauge",
+        "Highest number of messages observed waiting in the queue.",
+        |s| s.max_depth as f64,
+    );
+    metric(
+        "processed_total",
+        "counter",
+        "Number of processed messages.",
+        |s| s.processed as f64,
+    );
+    metric(
+        "dropped_total",
+        "counter",
+        "Number of messages skipped because the consumer couldn't keep up.",
+        |s| s.dropped as f64,
+    );
+    metric(
+        "latency_avg_ms",
+        "gauge",
+        "Average message processing time in milliseconds.",
+        |s| s.avg_latency_ms,
+    );
+    metric(
+        "latency_max_ms",
+        "gauge",
+        "Longest message processing time in milliseconds.",
+        |s| s.max_latency_ms,
+    );
+
+    metrics
+}
+
+/// Renders a Prometheus label set, e.g. `method="GET",route="/api/v1/user"`.
+#[must_use]
+pub fn labels(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{name}=\"{value}\"")
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Appends a metric family in Prometheus text exposition format. Samples are pairs of rendered
+/// label sets (see [`labels`]) and values.
+pub fn write_metric(
+    metrics: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    samples: impl IntoIterator<Item = (String, f64)>,
+) {
+    let _ = writeln!(metrics, "# HELP {name} {help}");
+    let _ = writeln!(metrics, "# TYPE {name} {kind}");
+    for (labels, value) in samples {
+        if labels.is_empty() {
+            let _ = writeln!(metrics, "{name} {value}");
+        } else {
+            let _ = writeln!(metrics, "{name}{{{labels}}} {value}");
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Liveness of a long-running component along with the last error it reported.
+#[derive(Debug)]
+pub struct ComponentState {
+    last_heartbeat: AtomicU64,
+    last_error: Mutex<Option<(u64, String)>>,
+}
+
+impl ComponentState {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            last_heartbeat: AtomicU64::new(0),
+            last_error: Mutex::new(None),
+        }
+    }
+
+    /// Records that the component is alive.
+    pub fn heartbeat(&self) {
+        self.last_heartbeat.store(unix_now(), Ordering::Relaxed);
+    }
+
+    pub fn record_error<E: std::fmt::Display>(&self, error: E) {
+        let mut last_error = self
+            .last_error
+            .lock()
+            .expect("Failed to acquire component state lock");
+        *last_error = Some((unix_now(), error.to_string()));
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> ComponentSnapshot {
+        let last_heartbeat = self.last_heartbeat.load(Ordering::Relaxed);
+        let last_error = self
+            .last_error
+            .lock()
+            .expect("Failed to acquire component state lock")
+            .clone();
+        let (last_error_at, last_error) = last_error.unzip();
+
+        ComponentSnapshot {
+            last_heartbeat: (last_heartbeat != 0).then_some(last_heartbeat),
+            last_error,
+            last_error_at,
+        }
+    }
+}
+
+impl Default for ComponentState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Point-in-time view of [`ComponentState`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ComponentSnapshot {
+    /// Unix timestamp of the last heartbeat.
+    pub last_heartbeat: Option<u64>,
+    pub last_error: Option<String>,
+    /// Unix timestamp of the last error.
+    pub last_error_at: Option<u64>,
+}
+
+/// Counters distinguished by label values.
+#[derive(Debug, Default)]
+pub struct LabeledCounters(Mutex<BTreeMap<String, u64>>);
+
+impl LabeledCounters {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(Mutex::new(BTreeMap::new()))
+    }
+
+    pub fn increment(&self, labels: &[(&str, &str)]) {
+        let mut counters = self.0.lock().expect("Failed to acquire metrics lock");
+        *counters.entry(self::labels(labels)).or_default() += 1;
+    }
+
+    /// Appends counters in Prometheus text exposition format.
+    pub fn render(&self, metrics: &mut String, name: &str, help: &str) {
+        let counters = self.0.lock().expect("Failed to acquire metrics lock");
+        write_metric(
+            metrics,
+            name,
+            "counter",
+            help,
+            counters
+                .iter()
+                .map(|(labels, count)| (labels.clone(), *count as f64)),
+        );
+    }
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    // cumulative counts of observations not greater than bucket bounds
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+/// Request latency histograms distinguished by label values.
+#[derive(Debug, Default)]
+pub struct RequestLatencies(Mutex<BTreeMap<String, Histogram>>);
+
+impl RequestLatencies {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(Mutex::new(BTreeMap::new()))
+    }
+
+    /// Records a request handled in `latency`.
+    pub fn observe(&self, labels: &[(&str, &str)], latency: Duration) {
+        let seconds = latency.as_secs_f64();
+        let mut histograms = self.0.lock().expect("Failed to acquire metrics lock");
+        let histogram = histograms.entry(self::labels(labels)).or_default();
+        for (bucket, bound) in histogram.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        histogram.count += 1;
+        histogram.sum += seconds;
+    }
+
+    /// Appends histograms in Prometheus text exposition format.
+    pub fn render(&self, metrics: &mut String, name: &str, help: &str) {
+        let histograms = self.0.lock().expect("Failed to acquire metrics lock");
+        let _ = writeln!(metrics, "# HELP {name} {help}");
+        let _ = writeln!(metrics, "# TYPE {name} histogram");
+        for (labels, histogram) in histograms.iter() {
+            let separator = if labels.is_empty() { "" } else { "," };
+            for (count, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS) {
+                let _ = writeln!(
+                    metrics,
+                    "{name}_bucket{{{labels}{separator}le=\"{bound}\"}} {count}"
+                );
+            }
+            let _ = writeln!(
+                metrics,
+                "{name}_bucket{{{labels}{separator}le=\"+Inf\"}} {}",
+                histogram.count
+            );
+            let _ = writeln!(metrics, "{name}_sum{{{labels}}} {}", histogram.sum);
+            let _ = writeln!(metrics, "{name}_count{{{labels}}} {}", histogram.count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_stats() {
+        let stats = QueueStats::new();
+        assert_eq!(stats.snapshot().last_processed, None);
+
+        stats.record_depth(5);
+        stats.record_depth(2);
+        stats.record_processed(1, Duration::from_millis(10));
+        stats.record_processed(1, Duration::from_millis(30));
+        stats.record_dropped(3);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.depth, 2);
+        assert_eq!(snapshot.max_depth, 5);
+        assert_eq!(snapshot.processed, 2);
+        assert_eq!(snapshot.dropped, 3);
+        assert_eq!(snapshot.avg_latency_ms, 20.0);
+        assert_eq!(snapshot.max_latency_ms, 30.0);
+        assert!(snapshot.last_processed.is_some());
+
+        let metrics = render_metrics(&[("mail", &snapshot)]);
+        assert!(metrics.contains("# TYPE defguard_queue_depth gauge\n"));
+        assert!(metrics.contains("defguard_queue_depth{queue=\"mail\"} 2\n"));
+        assert!(metrics.contains("defguard_queue_dropped_total{queue=\"mail\"} 3\n"));
+    }
+
+    #[test]
+    fn test_gateway_queue_stats() {
+        let queues = GatewayQueueStats::new();
+        let first = queues.gateway(1, "gateway-1");
+        let second = queues.gateway(1, "gateway-2");
+        first.record_depth(4);
+        first.record_processed(1, Duration::from_millis(10));
+        first.record_dropped(2);
+        second.record_depth(1);
+        second.record_processed(3, Duration::from_millis(30));
+        // reconnected gateway keeps its statistics
+        queues.gateway(1, "gateway-1").record_depth(3);
+
+        let snapshots = queues.snapshots();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].0, (1, "gateway-1".to_string()));
+        assert_eq!(snapshots[0].1.depth, 3);
+        assert_eq!(snapshots[0].1.max_depth, 4);
+        assert_eq!(snapshots[1].1.depth, 1);
+
+        let aggregate = QueueSnapshot::aggregate(snapshots.iter().map(|(_, snapshot)| snapshot));
+        assert_eq!(aggregate.depth, 3);
+        assert_eq!(aggregate.max_depth, 4);
+        assert_eq!(aggregate.processed, 4);
+        assert_eq!(aggregate.dropped, 2);
+        assert_eq!(aggregate.avg_latency_ms, 10.0);
+        assert_eq!(aggregate.max_latency_ms, 30.0);
+    }
+
+    #[test]
+    fn test_component_state() {
+        let state = ComponentState::new();
+        assert_eq!(
+            state.snapshot(),
+            ComponentSnapshot {
+                last_heartbeat: None,
+                last_error: None,
+                last_error_at: None,
+            }
+        );
+
+        state.heartbeat();
+        state.record_error("connection refused");
+        state.record_error("timed out");
+        let snapshot = state.snapshot();
+        assert!(snapshot.last_heartbeat.is_some());
+        assert_eq!(snapshot.last_error.as_deref(), Some("timed out"));
+        assert!(snapshot.last_error_at.is_some());
+    }
+
+    #[test]
+    fn test_labeled_counters() {
+        let counters = LabeledCounters::new();
+        counters.increment(&[("source", "web"), ("method", "totp")]);
+        counters.increment(&[("source", "web"), ("method", "totp")]);
+        counters.increment(&[("source", "desktop_client"), ("method", "\"email\"")]);
+
+        let mut metrics = String::new();
+        counters.render(
+            &mut metrics,
+            "defguard_mfa_failures_total",
+            "Failed MFA attempts.",
+        );
+        assert!(metrics.contains("# TYPE defguard_mfa_failures_total counter\n"));
+        assert!(
+            metrics.contains("defguard_mfa_failures_total{source=\"web\",method=\"totp\"} 2\n")
+        );
+        assert!(metrics.contains(
+            "defguard_mfa_failures_total{source=\"desktop_client\",method=\"\\\"email\\\"\"} 1\n"
+        ));
+    }
+
+    #[test]
+    fn test_request_latencies() {
+        let latencies = RequestLatencies::new();
+        latencies.observe(&[("route", "/api/v1/user")], Duration::from_millis(20));
+        latencies.observe(&[("route", "/api/v1/user")], Duration::from_millis(300));
+
+        let mut metrics = String::new();
+        latencies.render(
+            &mut metrics,
+            "defguard_http_request_duration_seconds",
+            "Latency.",
+        );
+        for line in [
+            "# TYPE defguard_http_request_duration_seconds histogram\n",
+            "defguard_http_request_duration_seconds_bucket{route=\"/api/v1/user\",le=\"0.01\"} 0\n",
+            "defguard_http_request_duration_seconds_bucket{route=\"/api/v1/user\",le=\"0.025\"} 1\n",
+            "defguard_http_request_duration_seconds_bucket{route=\"/api/v1/user\",le=\"0.5\"} 2\n",
+            "defguard_http_request_duration_seconds_bucket{route=\"/api/v1/user\",le=\"+Inf\"} 2\n",
+            "defguard_http_request_duration_seconds_count{route=\"/api/v1/user\"} 2\n",
+        ] {
+            assert!(metrics.contains(line), "missing {line}");
+        }
+    }
+}
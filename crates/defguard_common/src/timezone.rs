@@ -0,0 +1,32 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+
+/// Converts a naive UTC timestamp, as stored in the database, to local time in given timezone.
+/// Serializes to ISO 8601 with the UTC offset.
+#[must_use]
+pub fn localize(timestamp: NaiveDateTime, timezone: Tz) -> DateTime<FixedOffset> {
+    timezone.from_utc_datetime(&timestamp).fixed_offset()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn test_localize() {
+        let timestamp = NaiveDate::from_ymd_opt(2025, 7, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        assert_eq!(
+            localize(timestamp, Tz::Europe__Warsaw).to_rfc3339(),
+            "2025-07-01T14:00:00+02:00"
+        );
+        assert_eq!(
+            localize(timestamp, Tz::UTC).to_rfc3339(),
+            "2025-07-01T12:00:00+00:00"
+        );
+    }
+}
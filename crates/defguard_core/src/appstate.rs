@@ -2,9 +2,9 @@ use std::sync::{Arc, Mutex, RwLock};
 
 use axum::extract::FromRef;
 use axum_extra::extract::cookie::Key;
-use defguard_common::config::server_config;
+use defguard_common::{config::server_config, diagnostics::MFA_FAILURES};
 use defguard_mail::Mail;
-use reqwest::Client;
+use reqwest::{Client, header::CONTENT_TYPE};
 use secrecy::ExposeSecret;
 use serde_json::json;
 use sqlx::PgPool;
@@ -18,30 +18,47 @@ use tokio::{
 use webauthn_rs::prelude::*;
 
 use crate::{
-    auth::failed_login::FailedLoginMap,
-    db::{AppEvent, GatewayEvent, WebHook},
+    auth::{
+        failed_login::FailedLoginMap,
+        rate_limit::{RateLimiter, run_rate_limiter_sweep},
+    },
+    db::{AppEvent, GatewayEvent, WebHook, replica::ReadReplica},
     error::WebError,
-    events::ApiEvent,
+    events::{ApiEvent, ApiEventType},
     grpc::gateway::{send_multiple_wireguard_events, send_wireguard_event},
+    handlers::activity_log::CustomEventRateLimiter,
     version::IncompatibleComponents,
 };
 
 const X_DEFGUARD_EVENT: &str = "x-defguard-event";
+const X_DEFGUARD_SIGNATURE: &str = "x-defguard-signature";
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
+    pub(crate) replica: Option<ReadReplica>,
     tx: UnboundedSender<AppEvent>,
     pub wireguard_tx: Sender<GatewayEvent>,
     pub mail_tx: UnboundedSender<Mail>,
     pub webauthn: Arc<Webauthn>,
     pub failed_logins: Arc<Mutex<FailedLoginMap>>,
+    pub(crate) rate_limiter: Arc<Mutex<RateLimiter>>,
     key: Key,
     pub event_tx: UnboundedSender<ApiEvent>,
     pub incompatible_components: Arc<RwLock<IncompatibleComponents>>,
+    pub(crate) custom_event_limits: Arc<Mutex<CustomEventRateLimiter>>,
 }
 
 impl AppState {
+    /// Pool for heavy read-only queries: the read replica if it's configured and reachable,
+    /// the primary database otherwise.
+    pub(crate) fn read_pool(&self) -> &PgPool {
+        self.replica
+            .as_ref()
+            .and_then(ReadReplica::pool)
+            .unwrap_or(&self.pool)
+    }
+
     pub(crate) fn trigger_action(&self, event: AppEvent) {
         let event_name = event.name().to_owned();
         match self.tx.send(event) {
@@ -65,16 +82,21 @@ impl AppState {
                         (json!({ "username": username }), "user_deleted")
                     }
                     AppEvent::HWKeyProvision(data) => (json!(data), "user_keys"),
+                    AppEvent::GatewayDisconnected(data) => (json!(data), "gateway_disconnected"),
+                    AppEvent::ClientConnected(data) => (json!(data), "client_connected"),
+                    AppEvent::ClientDisconnected(data) => (json!(data), "client_disconnected"),
                 };
+                let body = payload.to_string();
                 for webhook in webhooks {
-                    match reqwest_client
+                    let mut request = reqwest_client
                         .post(&webhook.url)
                         .bearer_auth(&webhook.token)
                         .header(X_DEFGUARD_EVENT, event)
-                        .json(&payload)
-                        .send()
-                        .await
-                    {
+                        .header(CONTENT_TYPE, "application/json");
+                    if let Some(signature) = webhook.signature(body.as_bytes()) {
+                        request = request.header(X_DEFGUARD_SIGNATURE, signature);
+                    }
+                    match request.body(body.clone()).send().await {
                         Ok(res) => {
                             info!("Trigger sent to {}, status {}", webhook.url, res.status());
                         }
@@ -103,12 +125,16 @@ impl AppState {
     ///
     /// This method is fallible since events are used for communication between services
     pub fn emit_event(&self, event: ApiEvent) -> Result<(), WebError> {
+        if let ApiEventType::UserMfaLoginFailed { mfa_method, .. } = &*event.event {
+            MFA_FAILURES.increment(&[("source", "web"), ("method", &mfa_method.to_string())]);
+        }
         Ok(self.event_tx.send(event)?)
     }
 
     /// Create application state
     pub fn new(
         pool: PgPool,
+        replica: Option<ReadReplica>,
         tx: UnboundedSender<AppEvent>,
         rx: UnboundedReceiver<AppEvent>,
         wireguard_tx: Sender<GatewayEvent>,
@@ -118,38 +144,46 @@ impl AppState {
         incompatible_components: Arc<RwLock<IncompatibleComponents>>,
     ) -> Self {
         spawn(Self::handle_triggers(pool.clone(), rx));
+        let rate_limiter = Arc::default();
+        spawn(run_rate_limiter_sweep(Arc::downgrade(&rate_limiter)));
 
         let config = server_config();
-        let webauthn_builder = WebauthnBuilder::new(
-            config
-                .webauthn_rp_id
-                .as_ref()
-                .expect("Webauth RP ID configuration is required"),
-            &config.url,
-        )
-        .expect("Invalid WebAuthn configuration");
-        let webauthn = Arc::new(
-            webauthn_builder
-                .build()
-                .expect("Invalid WebAuthn configuration"),
-        );
+        let webauthn = Arc::new(build_webauthn());
 
         let key = Key::from(config.secret_key.expose_secret().as_bytes());
 
         Self {
             pool,
+            replica,
             tx,
             wireguard_tx,
             mail_tx,
             webauthn,
             failed_logins,
+            rate_limiter,
             key,
             event_tx,
             incompatible_components,
+            custom_event_limits: Arc::default(),
         }
     }
 }
 
+/// Builds WebAuthn relying party from server configuration.
+pub(crate) fn build_webauthn() -> Webauthn {
+    let config = server_config();
+    WebauthnBuilder::new(
+        config
+            .webauthn_rp_id
+            .as_ref()
+            .expect("Webauth RP ID configuration is required"),
+        &config.url,
+    )
+    .expect("Invalid WebAuthn configuration")
+    .build()
+    .expect("Invalid WebAuthn configuration")
+}
+
 impl FromRef<AppState> for Key {
     fn from_ref(state: &AppState) -> Self {
         state.key.clone()
@@ -0,0 +1,243 @@
+//! Restriction of client addresses allowed to access admin endpoints.
+
+use std::{net::IpAddr, sync::OnceLock};
+
+use axum::{
+    extract::{FromRef, FromRequestParts, OriginalUri},
+    http::{header::USER_AGENT, request::Parts},
+};
+use axum_client_ip::SecureClientIp;
+use defguard_common::config::DefGuardConfig;
+use ipnetwork::IpNetwork;
+use maxminddb::{Reader, geoip2};
+use thiserror::Error;
+
+use super::SessionInfo;
+use crate::{
+    appstate::AppState,
+    error::WebError,
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
+};
+
+static ADMIN_ACCESS_POLICY: OnceLock<AdminAccessPolicy> = OnceLock::new();
+
+#[derive(Debug, Error, PartialEq)]
+pub enum AdminAccessError {
+    #[error("address {0} is not in allowed networks")]
+    NetworkNotAllowed(IpAddr),
+    #[error("address {0} is located in country {1}, which is not allowed")]
+    CountryNotAllowed(IpAddr, String),
+    #[error("country of address {0} is unknown")]
+    UnknownCountry(IpAddr),
+}
+
+/// Networks and countries admin endpoints can be accessed from.
+pub struct AdminAccessPolicy {
+    networks: Vec<IpNetwork>,
+    countries: Vec<String>,
+    geoip: Option<Reader<Vec<u8>>>,
+}
+
+// Addresses which can't be located, exempt from the country check.
+pub(super) fn is_local(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local(),
+        IpAddr::V6(ip) => ip.is_loopback() || ip.is_unique_local() || ip.is_unicast_link_local(),
+    }
+}
+
+impl AdminAccessPolicy {
+    pub fn from_config(config: &DefGuardConfig) -> Result<Self, anyhow::Error> {
+        let countries: Vec<_> = config
+            .admin_allowed_countries
+            .iter()
+            .map(|country| country.trim().to_uppercase())
+            .collect();
+        let geoip = match &config.geoip_db {
+            Some(path) => Some(Reader::open_readfile(path)?),
+            None if !countries.is_empty() => {
+                return Err(anyhow::anyhow!(
+                    "Admin access can't be restricted to countries without a GeoIP database, \
+                    set DEFGUARD_GEOIP_DB"
+                ));
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            networks: config.admin_allowed_networks.clone(),
+            countries,
+            geoip,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.networks.is_empty() && self.countries.is_empty()
+    }
+
+    fn country(&self, ip: IpAddr) -> Option<String> {
+        let country: geoip2::Country = self.geoip.as_ref()?.lookup(ip).ok()?;
+        country.country?.iso_code.map(str::to_string)
+    }
+
+    /// Check if admin endpoints can be accessed from the address.
+    ///
+    /// The address has to belong to one of allowed networks and be located in one of allowed
+    /// countries, if any are configured. Local addresses are exempt from the country check.
+    pub(crate) fn check(&self, ip: IpAddr) -> Result<(), AdminAccessError> {
+        let ip = ip.to_canonical();
+        if !self.networks.is_empty() && !self.networks.iter().any(|network| network.contains(ip)) {
+            return Err(AdminAccessError::NetworkNotAllowed(ip));
+        }
+        if self.countries.is_empty() || is_local(ip) {
+            return Ok(());
+        }
+        match self.country(ip) {
+            Some(country) if self.countries.contains(&country) => Ok(()),
+            Some(country) => Err(AdminAccessError::CountryNotAllowed(ip, country)),
+            None => Err(AdminAccessError::UnknownCountry(ip)),
+        }
+    }
+}
+
+/// Set up admin access restrictions from the configuration. Access is not restricted if neither
+/// allowed networks nor countries are configured.
+pub fn init_admin_access(config: &DefGuardConfig) -> Result<(), anyhow::Error> {
+    let policy = AdminAccessPolicy::from_config(config)?;
+    if policy.is_empty() {
+        return Ok(());
+    }
+    info!(
+        "Restricting admin access to networks {:?} and countries {:?}",
+        policy.networks, policy.countries
+    );
+    if ADMIN_ACCESS_POLICY.set(policy).is_err() {
+        warn!("Admin access restrictions have already been initialized");
+    }
+    Ok(())
+}
+
+/// Deny access to admin endpoints from addresses not allowed by the admin access policy.
+///
+/// The client address is taken from the configured source, so that it can't be spoofed with
+/// headers not set by a trusted reverse proxy. Denied requests are recorded in the activity log.
+pub(crate) async fn check_admin_access<S>(
+    parts: &mut Parts,
+    state: &S,
+    session_info: &SessionInfo,
+) -> Result<(), WebError>
+where
+    S: Send + Sync,
+    AppState: FromRef<S>,
+{
+    let Some(policy) = ADMIN_ACCESS_POLICY.get() else {
+        return Ok(());
+    };
+    let SecureClientIp(ip) = SecureClientIp::from_request_parts(parts, state)
+        .await
+        .map_err(|_| WebError::BadRequest("Missing client IP".to_string()))?;
+    let Err(err) = policy.check(ip) else {
+        return Ok(());
+    };
+
+    let path = parts
+        .extensions
+        .get::<OriginalUri>()
+        .map_or(&parts.uri, |uri| &uri.0)
+        .path()
+        .to_string();
+    warn!(
+        "Denied user {} access to {} {path}: {err}",
+        session_info.user.username, parts.method
+    );
+    let user_agent = parts
+        .headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let appstate = AppState::from_ref(state);
+    appstate.emit_event(ApiEvent {
+        context: ApiRequestContext::new(
+            session_info.user.id,
+            session_info.user.username.clone(),
+            ip,
+            user_agent.to_string(),
+        ),
+        event: Box::new(ApiEventType::AdminAccessDenied {
+            method: parts.method.to_string(),
+            path,
+            reason: err.to_string(),
+        }),
+    })?;
+
+    Err(WebError::Forbidden("access denied".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    #[test]
+    fn test_allowed_networks() {
+        let policy = AdminAccessPolicy {
+            networks: vec![
+                "10.0.0.0/8".parse().unwrap(),
+                "2001:db8::/32".parse().unwrap(),
+            ],
+            countries: Vec::new(),
+            geoip: None,
+        };
+        assert!(policy.check(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3))).is_ok());
+        assert!(policy.check("2001:db8::1".parse().unwrap()).is_ok());
+        // IPv4-mapped IPv6 address
+        assert!(
+            policy
+                .check(IpAddr::V6(Ipv4Addr::new(10, 1, 2, 3).to_ipv6_mapped()))
+                .is_ok()
+        );
+        let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
+        assert_eq!(
+            policy.check(ip),
+            Err(AdminAccessError::NetworkNotAllowed(ip))
+        );
+        let ip = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        assert_eq!(
+            policy.check(ip),
+            Err(AdminAccessError::NetworkNotAllowed(ip))
+        );
+    }
+
+    #[test]
+    fn test_allowed_countries() {
+        let policy = AdminAccessPolicy {
+            networks: Vec::new(),
+            countries: vec!["PL".into()],
+            geoip: None,
+        };
+        // local addresses can't be located
+        assert!(policy.check(IpAddr::V4(Ipv4Addr::LOCALHOST)).is_ok());
+        assert!(policy.check(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3))).is_ok());
+        assert!(policy.check("fd00::1".parse().unwrap()).is_ok());
+        let ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1));
+        assert_eq!(policy.check(ip), Err(AdminAccessError::UnknownCountry(ip)));
+
+        // both restrictions apply
+        let policy = AdminAccessPolicy {
+            networks: vec!["192.168.0.0/16".parse().unwrap()],
+            countries: vec!["PL".into()],
+            geoip: None,
+        };
+        assert!(
+            policy
+                .check(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)))
+                .is_ok()
+        );
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3));
+        assert_eq!(
+            policy.check(ip),
+            Err(AdminAccessError::NetworkNotAllowed(ip))
+        );
+    }
+}
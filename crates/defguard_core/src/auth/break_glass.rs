@@ -0,0 +1,151 @@
+//! Emergency local admin account, used when the identity provider is unavailable.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr},
+    sync::{LazyLock, Mutex},
+};
+
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use defguard_common::config::DefGuardConfig;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+use super::failed_login::FailedLoginError;
+use crate::db::{Group, User, models::group::Permission};
+
+// Time window in seconds
+const BREAK_GLASS_LOGIN_WINDOW: i64 = 15 * 60;
+// Failed login attempts allowed from a single client IP within the window
+const BREAK_GLASS_FAILED_LOGINS_PER_IP: usize = 5;
+// Failed login attempts allowed from all clients within the window
+const BREAK_GLASS_FAILED_LOGINS: usize = 20;
+
+static BREAK_GLASS_FAILED_ATTEMPTS: LazyLock<Mutex<HashMap<IpAddr, Vec<DateTime<Local>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Check if another break-glass login attempt from the client can proceed. Only failed attempts,
+/// recorded with [`record_failed_break_glass_login`], count towards the limits.
+/// Clients without an address of their own, see [`super::client_ip::ClientIp::limited`], are only
+/// limited in total.
+pub(crate) fn check_break_glass_login(ip: Option<IpAddr>) -> Result<(), FailedLoginError> {
+    let mut attempts = BREAK_GLASS_FAILED_ATTEMPTS
+        .lock()
+        .expect("Failed to get a lock on break-glass login attempts.");
+    let now = Local::now();
+    attempts.retain(|_, times| {
+        times.retain(|time| now - *time < TimeDelta::seconds(BREAK_GLASS_LOGIN_WINDOW));
+        !times.is_empty()
+    });
+    if let Some(ip) = ip.map(|ip| ip.to_canonical()) {
+        if attempts.get(&ip).map_or(0, Vec::len) >= BREAK_GLASS_FAILED_LOGINS_PER_IP {
+            warn!("Too many failed break-glass login attempts from {ip}, preventing login");
+            return Err(FailedLoginError);
+        }
+    }
+    if attempts.values().map(Vec::len).sum::<usize>() >= BREAK_GLASS_FAILED_LOGINS {
+        warn!("Too many failed break-glass login attempts, preventing login");
+        return Err(FailedLoginError);
+    }
+    Ok(())
+}
+
+/// Record a failed break-glass login attempt from the client. Attempts of clients without an
+/// address of their own are recorded under the unspecified address, to count towards the total.
+pub(crate) fn record_failed_break_glass_login(ip: Option<IpAddr>) {
+    BREAK_GLASS_FAILED_ATTEMPTS
+        .lock()
+        .expect("Failed to get a lock on break-glass login attempts.")
+        .entry(ip.map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |ip| ip.to_canonical()))
+        .or_default()
+        .push(Local::now());
+}
+
+/// Provision the break-glass admin account if its password is configured, or disable existing
+/// break-glass accounts otherwise.
+///
+/// The account is (re-)enabled for the configured validity period on every startup and then
+/// disabled by the scheduled deactivation task.
+pub async fn init_break_glass_admin(
+    pool: &PgPool,
+    config: &DefGuardConfig,
+) -> Result<(), anyhow::Error> {
+    let now = Utc::now().naive_utc();
+    let mut transaction = pool.begin().await?;
+    let Some(password) = &config.break_glass_password else {
+        let count = User::deactivate_break_glass_at(&mut *transaction, now).await?;
+        transaction.commit().await?;
+        if count > 0 {
+            info!("Break-glass password is not configured, disabling {count} break-glass accounts");
+        }
+        return Ok(());
+    };
+
+    let username = &config.break_glass_username;
+    let admin_group = Group::find_by_permission(&mut *transaction, Permission::IsAdmin)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(anyhow::anyhow!(
+            "No admin group found, can't provision the break-glass admin account"
+        ))?;
+    let user = if let Some(mut user) = User::find_by_username(&mut *transaction, username).await? {
+        if !user.is_break_glass(&mut *transaction).await? {
+            return Err(anyhow::anyhow!(
+                "User {username} already exists and is not a break-glass account. \
+                Choose a different break-glass username."
+            ));
+        }
+        user.set_password(password.expose_secret());
+        user.is_active = true;
+        user.save(&mut *transaction).await?;
+        user
+    } else {
+        let user = User::new(
+            username.clone(),
+            Some(password.expose_secret()),
+            "Administrator".into(),
+            "Break-glass".into(),
+            format!("{username}@defguard"),
+            None,
+        )
+        .save(&mut *transaction)
+        .await?;
+        user.set_break_glass(&mut *transaction, true).await?;
+        user
+    };
+    user.add_to_group(&mut *transaction, &admin_group).await?;
+
+    let validity = TimeDelta::from_std(*config.break_glass_validity)?;
+    let deactivate_at = now + validity;
+    user.set_deactivate_at(&mut *transaction, Some(deactivate_at))
+        .await?;
+    transaction.commit().await?;
+    warn!("Break-glass admin account {username} is enabled until {deactivate_at} UTC");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_break_glass_login_limits() {
+        let ip = Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+        let other_ip = Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)));
+        for _ in 0..BREAK_GLASS_FAILED_LOGINS_PER_IP {
+            assert!(check_break_glass_login(ip).is_ok());
+            record_failed_break_glass_login(ip);
+        }
+        // other clients aren't affected by failures of a single one
+        assert!(check_break_glass_login(ip).is_err());
+        assert!(check_break_glass_login(other_ip).is_ok());
+
+        // until failures from all clients reach the global limit
+        for i in BREAK_GLASS_FAILED_LOGINS_PER_IP..BREAK_GLASS_FAILED_LOGINS {
+            record_failed_break_glass_login(Some(IpAddr::V4(Ipv4Addr::new(198, 51, 100, i as u8))));
+        }
+        assert!(check_break_glass_login(other_ip).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+//! Client addresses used by per-address login lockouts and rate limits.
+
+use std::net::IpAddr;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{Extensions, HeaderMap, request::Parts},
+};
+use axum_client_ip::{SecureClientIp, SecureClientIpSource};
+
+use super::admin_access::is_local;
+use crate::error::WebError;
+
+// Headers reverse proxies add to forwarded requests.
+const FORWARDING_HEADERS: [&str; 3] = ["forwarded", "x-forwarded-for", "x-real-ip"];
+
+/// Client address taken from the configured source.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ClientIp {
+    pub ip: IpAddr,
+    /// Set for addresses of reverse proxies, which all clients behind them share.
+    pub shared: bool,
+}
+
+impl ClientIp {
+    /// With the default `ConnectInfo` source, forwarded requests coming from private or loopback
+    /// addresses have the address of a reverse proxy, which `client_ip_source` hasn't been
+    /// configured for. Such addresses are marked as shared.
+    pub(crate) fn from_request(
+        source: &SecureClientIpSource,
+        headers: &HeaderMap,
+        extensions: &Extensions,
+    ) -> Option<Self> {
+        let SecureClientIp(ip) = SecureClientIp::from(source, headers, extensions).ok()?;
+        let shared = matches!(source, SecureClientIpSource::ConnectInfo)
+            && is_local(ip.to_canonical())
+            && FORWARDING_HEADERS
+                .iter()
+                .any(|name| headers.contains_key(*name));
+        if shared {
+            debug!(
+                "Request from {ip} has been forwarded by a reverse proxy, but the client IP source \
+                isn't configured, so it isn't limited by address"
+            );
+        }
+        Some(Self { ip, shared })
+    }
+
+    /// Address the client can be limited by, `None` if it's shared with other clients.
+    pub(crate) fn limited(&self) -> Option<IpAddr> {
+        (!self.shared).then_some(self.ip)
+    }
+}
+
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+{
+    type Rejection = WebError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<SecureClientIpSource>()
+            .and_then(|source| Self::from_request(source, &parts.headers, &parts.extensions))
+            .ok_or_else(|| {
+                error!("Failed to get client IP");
+                WebError::ClientIpError
+            })
+    }
+}
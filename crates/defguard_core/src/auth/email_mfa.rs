@@ -0,0 +1,42 @@
+//! Throttling of email MFA code resends.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use chrono::{DateTime, Local, TimeDelta};
+use defguard_common::{config::server_config, db::Id};
+
+use super::failed_login::FailedLoginError;
+
+// When the last email MFA code was sent to a given user
+static EMAIL_MFA_CODES_SENT: LazyLock<Mutex<HashMap<Id, DateTime<Local>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn resend_interval() -> TimeDelta {
+    TimeDelta::from_std(*server_config().mfa_code_resend_interval).unwrap_or(TimeDelta::MAX)
+}
+
+/// Record that an email MFA code has just been sent to the user.
+pub(crate) fn register_email_mfa_code_sent(user_id: Id) {
+    EMAIL_MFA_CODES_SENT
+        .lock()
+        .expect("Failed to get a lock on sent email MFA codes.")
+        .insert(user_id, Local::now());
+}
+
+/// Check if another email MFA code can be sent to the user.
+pub(crate) fn check_email_mfa_resend(user_id: Id) -> Result<(), FailedLoginError> {
+    let mut sent = EMAIL_MFA_CODES_SENT
+        .lock()
+        .expect("Failed to get a lock on sent email MFA codes.");
+    let now = Local::now();
+    let interval = resend_interval();
+    sent.retain(|_, sent_at| now - *sent_at < interval);
+    if sent.contains_key(&user_id) {
+        warn!("Email MFA code for user {user_id} was requested again too soon, not sending");
+        return Err(FailedLoginError);
+    }
+    Ok(())
+}
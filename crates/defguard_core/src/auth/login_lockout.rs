@@ -0,0 +1,206 @@
+//! Lockout of user accounts and client addresses after consecutive failed web UI logins.
+//!
+//! Failed logins are counted in the database, so that lockouts apply to all Defguard instances.
+//! Functions below also apply the fixed per-username throttling of `FailedLoginMap`, so that
+//! login handlers don't have to deal with both.
+//!
+//! Addresses of reverse proxies, shared by all clients behind them, are never locked out.
+
+use std::net::IpAddr;
+
+use chrono::NaiveDateTime;
+use defguard_common::db::{Id, models::Settings};
+
+use super::{
+    client_ip::ClientIp,
+    failed_login::{FailedLoginError, check_failed_logins, log_failed_login_attempt},
+};
+use crate::{
+    appstate::AppState,
+    db::{User, models::login_lockout::LoginLockout},
+    error::WebError,
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
+};
+
+/// Lockout parameters configured in settings.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LockoutPolicy {
+    user_threshold: i32,
+    ip_threshold: i32,
+    // minutes
+    duration: i32,
+    mfa_fallback: bool,
+}
+
+impl From<&Settings> for LockoutPolicy {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            user_threshold: settings.login_lockout_threshold,
+            ip_threshold: settings.login_lockout_ip_threshold,
+            duration: settings.login_lockout_duration,
+            mfa_fallback: settings.login_lockout_mfa_fallback,
+        }
+    }
+}
+
+// Counters are keyed by the canonical form, so that IPv4-mapped IPv6 addresses share them.
+fn ip_key(ip: IpAddr) -> String {
+    ip.to_canonical().to_string()
+}
+
+/// Reject web UI logins of the username or email, if throttled, and from a locked out client
+/// address.
+pub(crate) async fn check_login_allowed(
+    appstate: &AppState,
+    username_or_email: &str,
+    client_ip: ClientIp,
+) -> Result<(), WebError> {
+    check_failed_logins(&appstate.failed_logins, username_or_email)?;
+    if let Some(ip) = client_ip.limited() {
+        if let Some(locked_until) =
+            LoginLockout::ip_locked_until(&appstate.pool, &ip_key(ip)).await?
+        {
+            info!("Preventing login from address {ip}, which is locked out until {locked_until}");
+            return Err(FailedLoginError.into());
+        }
+    }
+    Ok(())
+}
+
+/// Reject web UI logins of a locked out user.
+///
+/// If enabled in settings, locked out users with MFA enabled can still verify their password.
+/// Returns `true` in such case, and the caller has to make sure the login can't be completed
+/// without MFA. The lockout is lifted once MFA is completed.
+pub(crate) async fn check_user_lockout(
+    appstate: &AppState,
+    user: &User<Id>,
+) -> Result<bool, WebError> {
+    let Some(locked_until) = LoginLockout::user_locked_until(&appstate.pool, user.id).await? else {
+        return Ok(false);
+    };
+    let policy = LockoutPolicy::from(&Settings::get_current_settings());
+    if policy.mfa_fallback && user.mfa_enabled {
+        info!(
+            "User {} is locked out until {locked_until}, requiring MFA to log in",
+            user.username
+        );
+        return Ok(true);
+    }
+    info!(
+        "Preventing user {} from logging in, the account is locked out until {locked_until}",
+        user.username
+    );
+    Err(FailedLoginError.into())
+}
+
+/// Reset failed login counters of the user and client address once the login is complete,
+/// i.e. after MFA if the user has it enabled.
+pub(crate) async fn reset_failed_logins(
+    appstate: &AppState,
+    user_id: Id,
+    ip: IpAddr,
+) -> Result<(), WebError> {
+    LoginLockout::delete_for_user(&appstate.pool, user_id).await?;
+    LoginLockout::delete_for_ip(&appstate.pool, &ip_key(ip)).await?;
+    Ok(())
+}
+
+// Lock once failures reach the threshold. Each lockout is applied, and reported, only once.
+async fn lock_after_threshold(
+    appstate: &AppState,
+    attempts: &LoginLockout,
+    threshold: i32,
+    duration: i32,
+) -> Result<Option<(u32, NaiveDateTime)>, WebError> {
+    if attempts.failed_attempts < threshold || attempts.locked_until.is_some() {
+        return Ok(None);
+    }
+    let locked_until = attempts.lock(&appstate.pool, duration).await?;
+    let failed_attempts = u32::try_from(attempts.failed_attempts).unwrap_or_default();
+    Ok(locked_until.map(|locked_until| (failed_attempts, locked_until)))
+}
+
+/// Record a failed web UI login and lock the user and client address if they exceeded
+/// the thresholds configured in settings. New lockouts are recorded in the activity log.
+pub(crate) async fn record_failed_login(
+    appstate: &AppState,
+    username_or_email: &str,
+    user: Option<&User<Id>>,
+    client_ip: ClientIp,
+    user_agent: &str,
+) -> Result<(), WebError> {
+    let ip = client_ip.ip;
+    log_failed_login_attempt(&appstate.failed_logins, username_or_email);
+    let policy = LockoutPolicy::from(&Settings::get_current_settings());
+    LoginLockout::delete_expired(&appstate.pool, policy.duration).await?;
+
+    let user_lockout = match user {
+        Some(user) if policy.user_threshold > 0 => {
+            let attempts =
+                LoginLockout::record_user_failure(&appstate.pool, user.id, policy.duration).await?;
+            lock_after_threshold(appstate, &attempts, policy.user_threshold, policy.duration)
+                .await?
+        }
+        _ => None,
+    };
+    let ip_lockout = match client_ip.limited() {
+        Some(ip) if policy.ip_threshold > 0 => {
+            let attempts =
+                LoginLockout::record_ip_failure(&appstate.pool, &ip_key(ip), policy.duration)
+                    .await?;
+            lock_after_threshold(appstate, &attempts, policy.ip_threshold, policy.duration).await?
+        }
+        _ => None,
+    };
+
+    if let Some((failed_attempts, locked_until)) = ip_lockout {
+        warn!(
+            "Locking out address {ip} until {locked_until} after {failed_attempts} consecutive \
+            failed logins"
+        );
+    }
+    // activity log events are attributed to users, so lockouts caused by logins to unknown
+    // accounts are only logged
+    let Some(user) = user else {
+        return Ok(());
+    };
+    let context =
+        ApiRequestContext::new(user.id, user.username.clone(), ip, user_agent.to_string());
+    if let Some((failed_attempts, locked_until)) = user_lockout {
+        warn!(
+            "Locking out user {} until {locked_until} after {failed_attempts} consecutive failed \
+            logins",
+            user.username
+        );
+        appstate.emit_event(ApiEvent {
+            context: context.clone(),
+            event: Box::new(ApiEventType::UserLockedOut {
+                failed_attempts,
+                locked_until,
+            }),
+        })?;
+    }
+    if let Some((failed_attempts, locked_until)) = ip_lockout {
+        appstate.emit_event(ApiEvent {
+            context,
+            event: Box::new(ApiEventType::LoginIpLockedOut {
+                ip_address: ip_key(ip),
+                failed_attempts,
+                locked_until,
+            }),
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Lift the lockout of the user. Returns `false` if the user wasn't locked out.
+pub(crate) async fn unlock_user(appstate: &AppState, user_id: Id) -> Result<bool, WebError> {
+    Ok(LoginLockout::delete_for_user(&appstate.pool, user_id).await?)
+}
+
+/// Lift the lockout of the client address. Returns `false` if the address wasn't locked out.
+pub(crate) async fn unlock_ip(appstate: &AppState, ip: IpAddr) -> Result<bool, WebError> {
+    Ok(LoginLockout::delete_for_ip(&appstate.pool, &ip_key(ip)).await?)
+}
@@ -1,31 +1,62 @@
+pub mod admin_access;
+pub mod break_glass;
+pub(crate) mod client_ip;
+pub mod email_mfa;
 pub mod failed_login;
+pub mod login_lockout;
+pub mod password_policy;
+pub(crate) mod rate_limit;
+
+use std::collections::HashMap;
 
 use axum::{
-    extract::{FromRef, FromRequestParts, OptionalFromRequestParts},
-    http::request::Parts,
+    extract::{FromRef, FromRequestParts, OptionalFromRequestParts, OriginalUri, Path},
+    http::{Method, header::USER_AGENT, request::Parts},
 };
-use axum_client_ip::InsecureClientIp;
+use axum_client_ip::SecureClientIp;
 use axum_extra::{
     TypedHeader,
-    extract::cookie::CookieJar,
+    extract::{
+        PrivateCookieJar,
+        cookie::{Cookie, CookieJar},
+    },
     headers::{Authorization, authorization::Bearer},
 };
+use chrono::{TimeDelta, Utc};
 use defguard_common::db::Id;
+use sqlx::{PgExecutor, PgPool};
+use tracing::Span;
 
 use crate::{
     appstate::AppState,
     db::{
         Group, OAuth2Token, Session, SessionState, User,
-        models::{group::Permission, oauth2client::OAuth2Client},
+        models::{
+            group::Permission,
+            impersonation::{Impersonation, ImpersonationInfo, ImpersonationMode},
+            oauth2client::OAuth2Client,
+        },
+    },
+    enterprise::{
+        db::models::{
+            api_tokens::{ApiToken, ApiTokenScope},
+            client_credentials::ClientCredentialsToken,
+        },
+        is_business_license_active,
     },
-    enterprise::{db::models::api_tokens::ApiToken, is_business_license_active},
     error::WebError,
-    handlers::SESSION_COOKIE_NAME,
+    handlers::{
+        SESSION_COOKIE_NAME,
+        impersonation::{IMPERSONATION_COOKIE_NAME, end_impersonation},
+    },
 };
 
 pub const TOTP_CODE_VALIDITY_PERIOD: u64 = 30;
 pub const EMAIL_CODE_DIGITS: u32 = 6;
+pub const SMS_CODE_DIGITS: u32 = 6;
 pub const TOTP_CODE_DIGITS: u32 = 6;
+// Minimal time in seconds between updates of the session's last use time
+const SESSION_LAST_SEEN_INTERVAL: i64 = 60;
 
 impl<S> FromRequestParts<S> for Session
 where
@@ -49,38 +80,88 @@ where
             if let Some(header) = maybe_auth_header {
                 let token_string = header.token();
                 debug!("Trying to authorize request using API token: {token_string}");
-                return match ApiToken::try_find_by_auth_token(&appstate.pool, token_string).await {
-                    Ok(Some(api_token)) => {
-                        // create a dummy session and don't store it in the DB
-                        // since each request needs to be authorized anyway
-                        let ip_address = InsecureClientIp::from_request_parts(parts, state)
-                            .await
-                            .map_err(|err| {
-                            error!("Failed to get client IP: {err:?}");
-                            WebError::ClientIpError
-                        })?;
-                        Ok(Session::new(
-                            api_token.user_id,
-                            SessionState::ApiTokenVerified,
-                            ip_address.0.to_string(),
-                            None,
-                        ))
+                // nested routers strip the path prefix, so check the original URI
+                let path = parts
+                    .extensions
+                    .get::<OriginalUri>()
+                    .map_or(&parts.uri, |uri| &uri.0)
+                    .path();
+                let (user_id, scopes) = if let Some(api_token) =
+                    ApiToken::try_find_by_auth_token(&appstate.pool, token_string).await?
+                {
+                    if !api_token.allows_request(&parts.method, path) {
+                        warn!(
+                            "API token {} scoped to location {:?} with scopes {:?} used for \
+                            {} {path}",
+                            api_token.name, api_token.location_id, api_token.scopes, parts.method
+                        );
+                        return Err(WebError::Forbidden(
+                            "API token is not allowed to access this resource".into(),
+                        ));
                     }
-                    Ok(None) => Err(WebError::Authorization("Invalid API token".into())),
-                    Err(err) => Err(err.into()),
+                    (api_token.user_id, api_token.scopes)
+                } else if let Some(token) =
+                    ClientCredentialsToken::find_active(&appstate.pool, token_string).await?
+                {
+                    // access tokens issued with the `client_credentials` grant are always scoped
+                    if !token
+                        .scopes
+                        .iter()
+                        .any(|scope| scope.allows(&parts.method, path))
+                    {
+                        warn!(
+                            "Access token of OpenID client {} with scopes {:?} used for {} {path}",
+                            token.oauth2client_id, token.scopes, parts.method
+                        );
+                        return Err(WebError::Forbidden(
+                            "Access token is not allowed to access this resource".into(),
+                        ));
+                    }
+                    (token.user_id, token.scopes)
+                } else {
+                    return Err(WebError::Authorization("Invalid API token".into()));
                 };
+                // create a dummy session and don't store it in the DB
+                // since each request needs to be authorized anyway
+                let ip_address = SecureClientIp::from_request_parts(parts, state)
+                    .await
+                    .map_err(|err| {
+                        error!("Failed to get client IP: {err:?}");
+                        WebError::ClientIpError
+                    })?;
+                // remember token scopes for role extractors
+                parts.extensions.insert(scopes);
+                return Ok(Session::new(
+                    user_id,
+                    SessionState::ApiTokenVerified,
+                    ip_address.0.to_string(),
+                    None,
+                ));
             }
         }
 
         let Ok(cookies) = CookieJar::from_request_parts(parts, state).await;
+        let Ok(private_cookies) = PrivateCookieJar::from_request_parts(parts, &appstate).await;
+        if let Some(session) =
+            impersonated_session(parts, state, &appstate, &cookies, &private_cookies).await?
+        {
+            return Ok(session);
+        }
+        // sessions assumed by impersonating admins are never loaded here
         if let Some(session_cookie) = cookies.get(SESSION_COOKIE_NAME) {
             return {
                 match Session::find_by_id(&appstate.pool, session_cookie.value()).await {
-                    Ok(Some(session)) => {
+                    Ok(Some(mut session)) => {
                         if session.expired() {
                             let _result = session.delete(&appstate.pool).await;
                             Err(WebError::Authorization("Session expired".into()))
                         } else {
+                            // avoid writing to the database on every request
+                            if Utc::now().naive_utc() - session.last_seen
+                                > TimeDelta::seconds(SESSION_LAST_SEEN_INTERVAL)
+                            {
+                                session.touch(&appstate.pool).await?;
+                            }
                             Ok(session)
                         }
                     }
@@ -94,14 +175,74 @@ where
     }
 }
 
+/// Session of the user impersonated by the admin making the request, if there is one.
+///
+/// Impersonation only works together with the admin session it was started from.
+async fn impersonated_session<S>(
+    parts: &mut Parts,
+    state: &S,
+    appstate: &AppState,
+    cookies: &CookieJar,
+    private_cookies: &PrivateCookieJar,
+) -> Result<Option<Session>, WebError>
+where
+    S: Send + Sync,
+{
+    let Some(cookie) = private_cookies.get(IMPERSONATION_COOKIE_NAME) else {
+        return Ok(None);
+    };
+    let Some(impersonation) =
+        Impersonation::find_by_session_id(&appstate.pool, cookie.value()).await?
+    else {
+        // already stopped, or the admin session has ended
+        return Ok(None);
+    };
+    // left over from another session, e.g. after logging in again
+    if cookies.get(SESSION_COOKIE_NAME).map(Cookie::value)
+        != Some(impersonation.admin_session_id.as_str())
+    {
+        return Ok(None);
+    }
+    if impersonation.expired() {
+        let ip_address = SecureClientIp::from_request_parts(parts, state)
+            .await
+            .map_err(|err| {
+                error!("Failed to get client IP: {err:?}");
+                WebError::ClientIpError
+            })?;
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        end_impersonation(appstate, impersonation, ip_address.0, user_agent, true).await?;
+        return Err(WebError::Authorization("Impersonation has expired".into()));
+    }
+    if impersonation.mode == ImpersonationMode::ReadOnly && !parts.method.is_safe() {
+        return Err(WebError::Forbidden(
+            "Read-only impersonation doesn't allow modifications".into(),
+        ));
+    }
+    let session = Session::find_impersonated(&appstate.pool, &impersonation.session_id).await?;
+    // remember impersonation details for the session info extractor
+    parts.extensions.insert(impersonation);
+    Ok(session)
+}
+
 // Extension of base user session that contains user data fetched from database.
 // This represents a session for a user who completed the login process (including MFA, if enabled).
 #[derive(Clone)]
 pub struct SessionInfo {
     pub session: Session,
     pub user: User<Id>,
+    /// Never set for scoped API tokens, which only grant access to routes covered by their scopes.
     pub is_admin: bool,
+    /// Set if an admin is impersonating the user.
+    pub impersonation: Option<ImpersonationInfo>,
     groups: Vec<Group<Id>>,
+    /// Permission scopes of the API token used to authorize the request.
+    /// Empty for regular sessions and unrestricted tokens.
+    token_scopes: Vec<ApiTokenScope>,
 }
 
 impl SessionInfo {
@@ -111,10 +252,55 @@ impl SessionInfo {
             session,
             user,
             is_admin,
+            impersonation: None,
             groups: Vec::new(),
+            token_scopes: Vec::new(),
         }
     }
 
+    /// Admins can manage other users' objects, as can scoped API tokens on routes covered
+    /// by their scopes.
+    #[must_use]
+    pub(crate) fn can_manage_others(&self) -> bool {
+        self.is_admin || self.is_scoped_token()
+    }
+
+    /// Checks if the request was authorized with a scoped API token.
+    #[must_use]
+    pub(crate) fn is_scoped_token(&self) -> bool {
+        !self.token_scopes.is_empty()
+    }
+
+    /// Scoped API tokens can't be used to modify admins.
+    pub(crate) async fn check_token_target_user<'e, E>(
+        &self,
+        executor: E,
+        user: &User<Id>,
+    ) -> Result<(), WebError>
+    where
+        E: PgExecutor<'e>,
+    {
+        if self.is_scoped_token() {
+            check_not_admin_user(executor, user).await?;
+        }
+        Ok(())
+    }
+
+    /// Scoped API tokens can't be used to modify groups granting admin permissions.
+    pub(crate) async fn check_token_target_group<'e, E>(
+        &self,
+        executor: E,
+        group: &Group<Id>,
+    ) -> Result<(), WebError>
+    where
+        E: PgExecutor<'e>,
+    {
+        if self.is_scoped_token() {
+            check_not_admin_group(executor, group).await?;
+        }
+        Ok(())
+    }
+
     fn contains_any_group(&self, group_names: &[&str]) -> bool {
         self.groups
             .iter()
@@ -135,6 +321,14 @@ where
         let user = User::find_by_id(&appstate.pool, session.user_id).await?;
 
         if let Some(user) = user {
+            // service accounts have no MFA, API tokens are their only way in
+            if session.state != SessionState::ApiTokenVerified
+                && user.is_service_account(&appstate.pool).await?
+            {
+                return Err(WebError::Forbidden(
+                    "Service accounts can only authenticate with API tokens".into(),
+                ));
+            }
             if user.mfa_enabled
                 && (session.state != SessionState::MultiFactorVerified
                     && session.state != SessionState::ApiTokenVerified)
@@ -144,22 +338,65 @@ where
             let Ok(groups) = user.member_of(&appstate.pool).await else {
                 return Err(WebError::DbError("cannot fetch groups".into()));
             };
-            let is_admin = user.is_admin(&appstate.pool).await?;
-
-            // non-admin users are not allowed to use token auth
-            if !is_admin && session.state == SessionState::ApiTokenVerified {
-                return Err(WebError::Forbidden(
-                    "Token authentication is not allowed for normal users".into(),
-                ));
+            let token_scopes = if session.state == SessionState::ApiTokenVerified {
+                parts
+                    .extensions
+                    .get::<Vec<ApiTokenScope>>()
+                    .cloned()
+                    .unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            // scoped API tokens only have access to routes covered by their scopes,
+            // which have already been checked when authorizing the request
+            let is_admin = token_scopes.is_empty() && user.is_admin(&appstate.pool).await?;
+
+            if token_scopes.is_empty() {
+                // non-admin users are not allowed to use unscoped token auth
+                if !is_admin && session.state == SessionState::ApiTokenVerified {
+                    return Err(WebError::Forbidden(
+                        "Token authentication is not allowed for normal users".into(),
+                    ));
+                }
+            } else {
+                let path = parts
+                    .extensions
+                    .get::<OriginalUri>()
+                    .map_or(&parts.uri, |uri| &uri.0)
+                    .path();
+                check_scoped_token_target(&appstate.pool, &parts.method, path).await?;
             }
 
+            let impersonation = match parts.extensions.get::<Impersonation>() {
+                Some(impersonation) => {
+                    let admin = User::find_by_id(&appstate.pool, impersonation.admin_id)
+                        .await?
+                        .ok_or_else(|| WebError::Authorization("Admin not found".into()))?;
+                    Some(ImpersonationInfo {
+                        admin: admin.username,
+                        mode: impersonation.mode,
+                        expires: impersonation.expires,
+                    })
+                }
+                None => None,
+            };
+
+            // identify the user in logs of the rest of the request
+            Span::current().record("user", &user.username);
+
             // Store session info into request extensions so future extractors can use it
             let session_info = SessionInfo {
                 session,
                 user,
                 is_admin,
+                impersonation,
                 groups,
+                token_scopes,
             };
+            // applies to all sessions with admin privileges, not only to admin-only routes
+            if session_info.can_manage_others() {
+                admin_access::check_admin_access(parts, state, &session_info).await?;
+            }
             parts.extensions.insert(session_info.clone());
             Ok(session_info)
         } else {
@@ -168,6 +405,64 @@ where
     }
 }
 
+/// Scoped API tokens can't be used to modify admins or groups granting admin permissions,
+/// so that they can't be used to escalate privileges.
+async fn check_scoped_token_target(
+    pool: &PgPool,
+    method: &Method,
+    path: &str,
+) -> Result<(), WebError> {
+    if method.is_safe() {
+        return Ok(());
+    }
+    let Some(route) = path.strip_prefix("/api/v1/") else {
+        return Ok(());
+    };
+    let segments: Vec<&str> = route.trim_end_matches('/').split('/').collect();
+    let username = match segments.as_slice() {
+        ["user", username, ..] | ["group", _, "user", username] => Some(*username),
+        _ => None,
+    };
+    if let Some(username) = username {
+        if let Some(user) = User::find_by_username(pool, username).await? {
+            check_not_admin_user(pool, &user).await?;
+        }
+    }
+    if let ["group" | "group-quota", name, ..] = segments.as_slice() {
+        if let Some(group) = Group::find_by_name(pool, name).await? {
+            check_not_admin_group(pool, &group).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn check_not_admin_user<'e, E>(executor: E, user: &User<Id>) -> Result<(), WebError>
+where
+    E: PgExecutor<'e>,
+{
+    if user.is_admin(executor).await? {
+        warn!("Scoped API token used to modify admin {}", user.username);
+        return Err(WebError::Forbidden(
+            "API token is not allowed to modify admin users".into(),
+        ));
+    }
+    Ok(())
+}
+
+async fn check_not_admin_group<'e, E>(executor: E, group: &Group<Id>) -> Result<(), WebError>
+where
+    E: PgExecutor<'e>,
+{
+    if group.grants_admin(executor).await? {
+        warn!("Scoped API token used to modify admin group {}", group.name);
+        return Err(WebError::Forbidden(
+            "API token is not allowed to modify admin groups".into(),
+        ));
+    }
+    Ok(())
+}
+
 #[macro_export]
 macro_rules! role {
     ($name:ident, $($permission:path)*) => {
@@ -188,6 +483,10 @@ macro_rules! role {
                 if !session_info.user.is_active {
                     return Err(WebError::Forbidden("user is disabled".into()));
                 }
+                // permission scopes of API tokens replace group permissions
+                if !session_info.token_scopes.is_empty() {
+                    return Ok(Self {});
+                }
                 let appstate = AppState::from_ref(state);
                 $(
                 let groups_with_permission = Group::find_by_permission(
@@ -207,6 +506,52 @@ macro_rules! role {
 
 role!(AdminRole, Permission::IsAdmin);
 
+/// Allows admins, as well as owners of the group named by the `name` path parameter,
+/// to manage the group's membership.
+///
+/// Groups granting admin permissions can only be managed by admins.
+pub struct GroupOwner;
+
+impl<S> FromRequestParts<S> for GroupOwner
+where
+    S: Send + Sync,
+    AppState: FromRef<S>,
+{
+    type Rejection = WebError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let session_info = SessionInfo::from_request_parts(parts, state).await?;
+        if !session_info.user.is_active {
+            return Err(WebError::Forbidden("user is disabled".into()));
+        }
+        // scoped API tokens can't be used for admin groups, see `check_scoped_token_target`
+        if session_info.can_manage_others() {
+            return Ok(Self);
+        }
+        let Path(params) = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| WebError::Forbidden("access denied".into()))?;
+        let Some(name) = params.get("name") else {
+            return Err(WebError::Forbidden("access denied".into()));
+        };
+        let appstate = AppState::from_ref(state);
+        // non-admins can't tell missing groups apart from groups they don't own
+        let Some(group) = Group::find_by_name(&appstate.pool, name).await? else {
+            return Err(WebError::Forbidden("access denied".into()));
+        };
+        if group.is_owner(&appstate.pool, session_info.user.id).await?
+            && !group.grants_admin(&appstate.pool).await?
+        {
+            debug!(
+                "User {} managing group {} as its owner",
+                session_info.user.username, group.name
+            );
+            return Ok(Self);
+        }
+        Err(WebError::Forbidden("access denied".into()))
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct UserClaims {
     pub email: Option<String>,
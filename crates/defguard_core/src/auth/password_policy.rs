@@ -0,0 +1,244 @@
+//! Requirements for user passwords, configured in settings.
+
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHash, PasswordVerifier},
+};
+use defguard_common::db::{
+    Id,
+    models::{
+        Settings,
+        settings::{MAX_PASSWORD_HISTORY_SIZE, MAX_PASSWORD_LENGTH},
+    },
+};
+use sqlx::PgExecutor;
+use thiserror::Error;
+use zxcvbn::zxcvbn;
+
+use crate::{db::User, error::WebError};
+
+/// Password policy, as presented to users setting a new password.
+#[derive(Clone, Debug, Serialize)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    pub require_special: bool,
+    // minimal zxcvbn strength score (0-4)
+    pub min_score: u8,
+    // number of most recent passwords which can't be reused
+    pub history_size: usize,
+    // lowercase passwords which can't be used
+    #[serde(skip)]
+    banned: Vec<String>,
+}
+
+/// Requirement of the password policy which a password doesn't meet.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasswordPolicyViolation {
+    TooShort,
+    TooLong,
+    NoUppercase,
+    NoLowercase,
+    NoDigit,
+    NoSpecial,
+    TooWeak,
+    Banned,
+    Reused,
+}
+
+#[derive(Debug, Error)]
+#[error("Password doesn't meet the password policy: {violations:?}")]
+pub struct PasswordPolicyError {
+    pub policy: PasswordPolicy,
+    pub violations: Vec<PasswordPolicyViolation>,
+}
+
+impl From<&Settings> for PasswordPolicy {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            min_length: usize::try_from(settings.password_min_length).unwrap_or_default(),
+            max_length: MAX_PASSWORD_LENGTH as usize,
+            require_uppercase: settings.password_require_uppercase,
+            require_lowercase: settings.password_require_lowercase,
+            require_digit: settings.password_require_digit,
+            require_special: settings.password_require_special,
+            min_score: u8::try_from(settings.password_min_score).unwrap_or_default(),
+            history_size: usize::try_from(
+                settings
+                    .password_history_size
+                    .min(MAX_PASSWORD_HISTORY_SIZE),
+            )
+            .unwrap_or_default(),
+            banned: settings
+                .password_banned
+                .iter()
+                .map(|password| password.to_lowercase())
+                .collect(),
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Requirements the password doesn't meet, apart from reuse of previous passwords.
+    ///
+    /// `user_inputs` are personal details of the user, which make passwords containing them weaker.
+    #[must_use]
+    pub fn violations(&self, password: &str, user_inputs: &[&str]) -> Vec<PasswordPolicyViolation> {
+        let mut violations = Vec::new();
+        let length = password.chars().count();
+        if length < self.min_length {
+            violations.push(PasswordPolicyViolation::TooShort);
+        }
+        if length > self.max_length {
+            violations.push(PasswordPolicyViolation::TooLong);
+        }
+        if self.require_uppercase && !password.chars().any(|c| c.is_ascii_uppercase()) {
+            violations.push(PasswordPolicyViolation::NoUppercase);
+        }
+        if self.require_lowercase && !password.chars().any(|c| c.is_ascii_lowercase()) {
+            violations.push(PasswordPolicyViolation::NoLowercase);
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            violations.push(PasswordPolicyViolation::NoDigit);
+        }
+        if self.require_special && !password.chars().any(|c| c.is_ascii_punctuation()) {
+            violations.push(PasswordPolicyViolation::NoSpecial);
+        }
+        if self.banned.contains(&password.to_lowercase()) {
+            violations.push(PasswordPolicyViolation::Banned);
+        }
+        // zxcvbn is slow for long passwords, which are strong enough anyway
+        if self.min_score > 0
+            && length <= self.max_length
+            && zxcvbn(password, user_inputs).map_or(0, |entropy| entropy.score()) < self.min_score
+        {
+            violations.push(PasswordPolicyViolation::TooWeak);
+        }
+
+        violations
+    }
+}
+
+// Check if the password matches any of the hashes.
+fn matches_any(password: &str, hashes: &[String]) -> bool {
+    hashes.iter().any(|hash| {
+        PasswordHash::new(hash).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+    })
+}
+
+/// Check a new password against the password policy configured in settings.
+///
+/// `user` is the existing user setting the password, if any. Their personal details lower the
+/// strength score of passwords containing them and their recent passwords can't be reused.
+pub(crate) async fn check_password_policy<'e, E>(
+    executor: E,
+    password: &str,
+    user: Option<&User<Id>>,
+) -> Result<(), WebError>
+where
+    E: PgExecutor<'e>,
+{
+    let policy = PasswordPolicy::from(&Settings::get_current_settings());
+    let user_inputs: Vec<&str> = user.map_or_else(Vec::new, |user| {
+        vec![
+            user.username.as_str(),
+            user.first_name.as_str(),
+            user.last_name.as_str(),
+            user.email.as_str(),
+        ]
+    });
+    let mut violations = policy.violations(password, &user_inputs);
+
+    if let Some(user) = user {
+        if policy.history_size > 0 {
+            // the current password is also remembered in the history, unless it was set before
+            // the history was introduced
+            let mut hashes = user
+                .password_history(executor, policy.history_size as i64)
+                .await?;
+            hashes.extend(user.password_hash.clone());
+            if matches_any(password, &hashes) {
+                violations.push(PasswordPolicyViolation::Reused);
+            }
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(PasswordPolicyError { policy, violations }.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 8,
+            max_length: 128,
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digit: true,
+            require_special: true,
+            min_score: 0,
+            history_size: 0,
+            banned: vec!["password1!".into()],
+        }
+    }
+
+    #[test]
+    fn test_password_policy_violations() {
+        let policy = policy();
+        assert!(policy.violations("strongPass1234$!", &[]).is_empty());
+        assert_eq!(
+            policy.violations("1H$", &[]),
+            [
+                PasswordPolicyViolation::TooShort,
+                PasswordPolicyViolation::NoLowercase
+            ]
+        );
+        assert_eq!(
+            policy.violations("notSostrong!", &[]),
+            [PasswordPolicyViolation::NoDigit]
+        );
+        assert_eq!(
+            policy.violations("noSoStrong1234", &[]),
+            [PasswordPolicyViolation::NoSpecial]
+        );
+        assert_eq!(
+            policy.violations(&"aA1!".repeat(40), &[]),
+            [PasswordPolicyViolation::TooLong]
+        );
+        assert_eq!(
+            policy.violations("PassWord1!", &[]),
+            [PasswordPolicyViolation::Banned]
+        );
+    }
+
+    #[test]
+    fn test_password_policy_strength() {
+        let policy = PasswordPolicy {
+            min_score: 3,
+            ..policy()
+        };
+        assert_eq!(
+            policy.violations("Qwerty123!", &[]),
+            [PasswordPolicyViolation::TooWeak]
+        );
+        assert_eq!(
+            policy.violations("Hpotter1234!", &["hpotter"]),
+            [PasswordPolicyViolation::TooWeak]
+        );
+        assert!(policy.violations("c0rrect-H0rse-battery", &[]).is_empty());
+    }
+}
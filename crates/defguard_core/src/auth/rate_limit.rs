@@ -0,0 +1,669 @@
+//! Token bucket rate limiting of sensitive endpoints, like login and MFA verification.
+//!
+//! Every client gets a bucket per endpoint, keyed by its address, by its session and, for login
+//! and MFA, by the user it logs in as, so that neither rotating addresses nor sessions allow
+//! bypassing the limit. Addresses of reverse proxies, shared by all clients behind them, don't
+//! get buckets.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{IpAddr, Ipv4Addr},
+    sync::{Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    Json,
+    body::{Body, to_bytes},
+    extract::{Request, State},
+    http::{
+        StatusCode,
+        header::{RETRY_AFTER, USER_AGENT},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_client_ip::SecureClientIpSource;
+use axum_extra::extract::cookie::CookieJar;
+use defguard_common::{
+    config::{DefGuardConfig, server_config},
+    db::Id,
+};
+use serde_json::json;
+use sqlx::Error as SqlxError;
+use tokio::time::interval;
+
+use super::client_ip::ClientIp;
+use crate::{
+    appstate::AppState,
+    db::{Session, User},
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
+    handlers::SESSION_COOKIE_NAME,
+};
+
+/// Endpoints limited independently of each other.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum RateLimitedEndpoint {
+    Login,
+    Mfa,
+    PasswordReset,
+    Enrollment,
+}
+
+impl fmt::Display for RateLimitedEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Login => "login",
+            Self::Mfa => "mfa",
+            Self::PasswordReset => "password_reset",
+            Self::Enrollment => "enrollment",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Client a request is attributed to.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum RateLimitKey {
+    Ip(IpAddr),
+    /// Session ID, or enrollment or password reset token for requests coming through the proxy.
+    Session(String),
+    /// Lowercase username or e-mail address the client logs in, or requests password reset, as.
+    Username(String),
+}
+
+/// Rate limiting parameters configured with command line arguments.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RateLimitPolicy {
+    requests_per_minute: u32,
+    burst: u32,
+    pub violation_threshold: u32,
+}
+
+impl From<&DefGuardConfig> for RateLimitPolicy {
+    fn from(config: &DefGuardConfig) -> Self {
+        Self {
+            requests_per_minute: config.rate_limit_requests,
+            burst: config.rate_limit_burst,
+            violation_threshold: config.rate_limit_violation_threshold,
+        }
+    }
+}
+
+impl RateLimitPolicy {
+    fn capacity(&self) -> f64 {
+        f64::from(self.burst.max(1))
+    }
+
+    // tokens added per second
+    fn refill_rate(&self) -> f64 {
+        f64::from(self.requests_per_minute) / 60.0
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+    /// Requests rejected since the last accepted one.
+    rejected: u32,
+}
+
+impl TokenBucket {
+    fn new(policy: &RateLimitPolicy, now: Instant) -> Self {
+        Self {
+            tokens: policy.capacity(),
+            updated: now,
+            rejected: 0,
+        }
+    }
+
+    fn refill(&mut self, policy: &RateLimitPolicy, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * policy.refill_rate()).min(policy.capacity());
+        self.updated = now;
+    }
+
+    // Time until a token is available.
+    fn wait_time(&self, policy: &RateLimitPolicy) -> Duration {
+        Duration::from_secs_f64(((1.0 - self.tokens) / policy.refill_rate()).max(0.0))
+    }
+}
+
+/// Request rejected by the rate limiter.
+#[derive(Debug, PartialEq)]
+pub(crate) struct RateLimited {
+    pub retry_after: Duration,
+    /// Consecutive rejected requests of the client, including this one.
+    pub rejected_requests: u32,
+}
+
+impl RateLimited {
+    /// Value of the `Retry-After` header, in whole seconds.
+    pub(crate) fn retry_after_secs(&self) -> u64 {
+        self.retry_after.as_secs_f64().ceil() as u64
+    }
+}
+
+// How often buckets which have refilled are removed.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+// Login requests are small, larger bodies aren't buffered to find the username.
+const LOGIN_BODY_LIMIT: usize = 16 * 1024;
+
+/// Token buckets of clients of rate limited endpoints.
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: HashMap<(RateLimitedEndpoint, RateLimitKey), TokenBucket>,
+}
+
+impl RateLimiter {
+    /// Take a token from buckets of all the keys the request is attributed to.
+    /// The request is rejected, without taking any tokens, if one of the buckets is empty.
+    pub(crate) fn check(
+        &mut self,
+        policy: &RateLimitPolicy,
+        endpoint: RateLimitedEndpoint,
+        keys: &[RateLimitKey],
+    ) -> Result<(), RateLimited> {
+        self.check_at(policy, endpoint, keys, Instant::now())
+    }
+
+    fn check_at(
+        &mut self,
+        policy: &RateLimitPolicy,
+        endpoint: RateLimitedEndpoint,
+        keys: &[RateLimitKey],
+        now: Instant,
+    ) -> Result<(), RateLimited> {
+        if policy.requests_per_minute == 0 {
+            return Ok(());
+        }
+
+        let mut limited: Option<RateLimited> = None;
+        for key in keys {
+            let bucket = self
+                .buckets
+                .entry((endpoint, key.clone()))
+                .or_insert_with(|| TokenBucket::new(policy, now));
+            bucket.refill(policy, now);
+            if bucket.tokens < 1.0 {
+                bucket.rejected += 1;
+                let retry_after = bucket.wait_time(policy);
+                let rejected_requests = bucket.rejected;
+                limited = Some(match limited {
+                    Some(limited) => RateLimited {
+                        retry_after: limited.retry_after.max(retry_after),
+                        rejected_requests: limited.rejected_requests.max(rejected_requests),
+                    },
+                    None => RateLimited {
+                        retry_after,
+                        rejected_requests,
+                    },
+                });
+            }
+        }
+        if let Some(limited) = limited {
+            return Err(limited);
+        }
+
+        for key in keys {
+            if let Some(bucket) = self.buckets.get_mut(&(endpoint, key.clone())) {
+                bucket.tokens -= 1.0;
+                bucket.rejected = 0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove buckets which have refilled, as they're equivalent to new ones.
+    pub(crate) fn sweep(&mut self, policy: &RateLimitPolicy) {
+        self.sweep_at(policy, Instant::now());
+    }
+
+    fn sweep_at(&mut self, policy: &RateLimitPolicy, now: Instant) {
+        self.buckets.retain(|_, bucket| {
+            bucket.refill(policy, now);
+            bucket.tokens < policy.capacity()
+        });
+    }
+}
+
+/// Periodically remove refilled buckets of the rate limiter, until it's dropped.
+pub(crate) async fn run_rate_limiter_sweep(limiter: Weak<Mutex<RateLimiter>>) {
+    let mut sweep = interval(SWEEP_INTERVAL);
+    loop {
+        sweep.tick().await;
+        let Some(limiter) = limiter.upgrade() else {
+            break;
+        };
+        let policy = RateLimitPolicy::from(server_config());
+        limiter
+            .lock()
+            .expect("Failed to get a lock on rate limiter.")
+            .sweep(&policy);
+    }
+}
+
+fn too_many_requests(limited: &RateLimited) -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(RETRY_AFTER, limited.retry_after_secs().to_string())],
+        Json(json!({ "msg": "Too many requests" })),
+    )
+        .into_response()
+}
+
+// User the request is attributed to: the user logging in, or the user of the session.
+async fn find_user(
+    appstate: &AppState,
+    username: Option<&str>,
+    session_id: Option<&str>,
+) -> Result<Option<User<Id>>, SqlxError> {
+    if let Some(username) = username {
+        let mut conn = appstate.pool.acquire().await?;
+        return User::find_by_username_or_email(&mut conn, username).await;
+    }
+    match session_id {
+        Some(session_id) => match Session::find_by_id(&appstate.pool, session_id).await? {
+            Some(session) => User::find_by_id(&appstate.pool, session.user_id).await,
+            None => Ok(None),
+        },
+        None => Ok(None),
+    }
+}
+
+// Username or e-mail address from the body of a login request. The request is rebuilt from the
+// buffered body, or `Err` is returned if the body is too large to buffer.
+async fn login_username(request: Request) -> Result<(Request, Option<String>), StatusCode> {
+    let (parts, body) = request.into_parts();
+    let Ok(bytes) = to_bytes(body, LOGIN_BODY_LIMIT).await else {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    };
+    let username = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|value| value.get("username")?.as_str().map(str::to_lowercase));
+    Ok((Request::from_parts(parts, Body::from(bytes)), username))
+}
+
+// Address of the client taken from the configured source.
+fn request_client_ip(request: &Request) -> Option<ClientIp> {
+    request
+        .extensions()
+        .get::<SecureClientIpSource>()
+        .and_then(|source| ClientIp::from_request(source, request.headers(), request.extensions()))
+}
+
+/// Record repeated violations of the rate limit. Activity log events are attributed to users,
+/// so violations of unknown clients are only logged.
+async fn report_violation(
+    appstate: &AppState,
+    endpoint: RateLimitedEndpoint,
+    ip: Option<IpAddr>,
+    user: Option<User<Id>>,
+    user_agent: String,
+    rejected_requests: u32,
+) {
+    let ip_address = ip.map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+    warn!(
+        "Client {ip_address} exceeded the {endpoint} rate limit, {rejected_requests} consecutive \
+        requests rejected"
+    );
+    let Some(user) = user else {
+        return;
+    };
+    let context = ApiRequestContext::new(
+        user.id,
+        user.username,
+        ip.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        user_agent,
+    );
+    if let Err(err) = appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::RateLimitExceeded {
+            endpoint: endpoint.to_string(),
+            ip_address,
+            rejected_requests,
+        }),
+    }) {
+        error!("Failed to record {endpoint} rate limit violation: {err}");
+    }
+}
+
+/// Reject requests exceeding the rate limit of the endpoint with `429 Too Many Requests`
+/// and a `Retry-After` header.
+pub(crate) async fn rate_limit(
+    State((appstate, endpoint)): State<(AppState, RateLimitedEndpoint)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let policy = RateLimitPolicy::from(server_config());
+    let client_ip = request_client_ip(&request);
+    let ip = client_ip.map(|client_ip| client_ip.ip.to_canonical());
+    let session_id = CookieJar::from_headers(request.headers())
+        .get(SESSION_COOKIE_NAME)
+        .map(|cookie| cookie.value().to_string());
+    let (request, username, user) = match endpoint {
+        RateLimitedEndpoint::Login => match login_username(request).await {
+            Ok((request, username)) => (request, username, None),
+            Err(status) => return status.into_response(),
+        },
+        // MFA is limited per user of the session, which was created by verifying the password
+        RateLimitedEndpoint::Mfa => {
+            let user = find_user(&appstate, None, session_id.as_deref())
+                .await
+                .unwrap_or_else(|err| {
+                    error!("Failed to find the user of {endpoint} request: {err}");
+                    None
+                });
+            let username = user.as_ref().map(|user| user.username.to_lowercase());
+            (request, username, user)
+        }
+        RateLimitedEndpoint::PasswordReset | RateLimitedEndpoint::Enrollment => {
+            (request, None, None)
+        }
+    };
+    let mut keys = Vec::with_capacity(3);
+    keys.extend(
+        client_ip
+            .and_then(|client_ip| client_ip.limited())
+            .map(|ip| RateLimitKey::Ip(ip.to_canonical())),
+    );
+    keys.extend(session_id.clone().map(RateLimitKey::Session));
+    keys.extend(username.clone().map(RateLimitKey::Username));
+
+    let result = appstate
+        .rate_limiter
+        .lock()
+        .expect("Failed to get a lock on rate limiter.")
+        .check(&policy, endpoint, &keys);
+    let Err(limited) = result else {
+        return next.run(request).await;
+    };
+    debug!(
+        "Rejecting {endpoint} request exceeding the rate limit, retry after {}s",
+        limited.retry_after_secs()
+    );
+    if limited.rejected_requests == policy.violation_threshold {
+        let user_agent = request
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let user = match user {
+            Some(user) => Some(user),
+            None => find_user(&appstate, username.as_deref(), session_id.as_deref())
+                .await
+                .unwrap_or_else(|err| {
+                    error!("Failed to find the user exceeding the {endpoint} rate limit: {err}");
+                    None
+                }),
+        };
+        report_violation(
+            &appstate,
+            endpoint,
+            ip,
+            user,
+            user_agent,
+            limited.rejected_requests,
+        )
+        .await;
+    }
+    too_many_requests(&limited)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use axum::extract::ConnectInfo;
+
+    use super::*;
+
+    const POLICY: RateLimitPolicy = RateLimitPolicy {
+        requests_per_minute: 60,
+        burst: 3,
+        violation_threshold: 2,
+    };
+
+    #[test]
+    fn test_rate_limit_burst_and_refill() {
+        let mut limiter = RateLimiter::default();
+        let keys = [RateLimitKey::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST))];
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(
+                limiter
+                    .check_at(&POLICY, RateLimitedEndpoint::Login, &keys, now)
+                    .is_ok()
+            );
+        }
+        let limited = limiter
+            .check_at(&POLICY, RateLimitedEndpoint::Login, &keys, now)
+            .unwrap_err();
+        assert_eq!(limited.rejected_requests, 1);
+        assert_eq!(limited.retry_after_secs(), 1);
+        let limited = limiter
+            .check_at(&POLICY, RateLimitedEndpoint::Login, &keys, now)
+            .unwrap_err();
+        assert_eq!(limited.rejected_requests, 2);
+
+        // other endpoints have separate buckets
+        assert!(
+            limiter
+                .check_at(&POLICY, RateLimitedEndpoint::Mfa, &keys, now)
+                .is_ok()
+        );
+
+        // one token per second is added
+        let later = now + Duration::from_secs(1);
+        assert!(
+            limiter
+                .check_at(&POLICY, RateLimitedEndpoint::Login, &keys, later)
+                .is_ok()
+        );
+        assert!(
+            limiter
+                .check_at(&POLICY, RateLimitedEndpoint::Login, &keys, later)
+                .is_err()
+        );
+        // rejected requests are counted since the last accepted one
+        let later = later + Duration::from_secs(1);
+        assert!(
+            limiter
+                .check_at(&POLICY, RateLimitedEndpoint::Login, &keys, later)
+                .is_ok()
+        );
+        assert_eq!(
+            limiter
+                .check_at(&POLICY, RateLimitedEndpoint::Login, &keys, later)
+                .unwrap_err()
+                .rejected_requests,
+            1
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_keys() {
+        let mut limiter = RateLimiter::default();
+        let session = RateLimitKey::Session("session".into());
+        let now = Instant::now();
+
+        // the session is limited regardless of the address
+        for i in 0..3 {
+            let ip = RateLimitKey::Ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, i)));
+            assert!(
+                limiter
+                    .check_at(
+                        &POLICY,
+                        RateLimitedEndpoint::Mfa,
+                        &[ip, session.clone()],
+                        now
+                    )
+                    .is_ok()
+            );
+        }
+        let ip = RateLimitKey::Ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3)));
+        assert!(
+            limiter
+                .check_at(
+                    &POLICY,
+                    RateLimitedEndpoint::Mfa,
+                    &[ip.clone(), session],
+                    now
+                )
+                .is_err()
+        );
+        // rejected request didn't take a token from the address bucket
+        for _ in 0..3 {
+            assert!(
+                limiter
+                    .check_at(&POLICY, RateLimitedEndpoint::Mfa, &[ip.clone()], now)
+                    .is_ok()
+            );
+        }
+    }
+
+    #[test]
+    fn test_rate_limit_username() {
+        let mut limiter = RateLimiter::default();
+        let username = RateLimitKey::Username("hpotter".into());
+        let now = Instant::now();
+
+        // logins without a session are limited per user, regardless of the address
+        for i in 0..3 {
+            let ip = RateLimitKey::Ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, i)));
+            assert!(
+                limiter
+                    .check_at(
+                        &POLICY,
+                        RateLimitedEndpoint::Login,
+                        &[ip, username.clone()],
+                        now
+                    )
+                    .is_ok()
+            );
+        }
+        let ip = RateLimitKey::Ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3)));
+        assert!(
+            limiter
+                .check_at(&POLICY, RateLimitedEndpoint::Login, &[ip, username], now)
+                .is_err()
+        );
+    }
+
+    fn forwarded_request(source: SecureClientIpSource, forwarded_for: Option<&str>) -> Request {
+        let mut builder = axum::http::Request::builder()
+            .extension(source)
+            .extension(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 8000))));
+        if let Some(forwarded_for) = forwarded_for {
+            builder = builder.header("x-forwarded-for", forwarded_for);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn test_rate_limit_forwarded_address() {
+        let mut limiter = RateLimiter::default();
+        let now = Instant::now();
+        let ip_key = |request: &Request| {
+            request_client_ip(request)
+                .and_then(|client_ip| client_ip.limited())
+                .map(RateLimitKey::Ip)
+                .unwrap()
+        };
+
+        // clients behind a configured reverse proxy have separate buckets
+        let first = ip_key(&forwarded_request(
+            SecureClientIpSource::RightmostXForwardedFor,
+            Some("203.0.113.1"),
+        ));
+        let second = ip_key(&forwarded_request(
+            SecureClientIpSource::RightmostXForwardedFor,
+            Some("203.0.113.2"),
+        ));
+        assert_eq!(
+            first,
+            RateLimitKey::Ip(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1)))
+        );
+        for _ in 0..3 {
+            assert!(
+                limiter
+                    .check_at(&POLICY, RateLimitedEndpoint::Login, &[first.clone()], now)
+                    .is_ok()
+            );
+        }
+        assert!(
+            limiter
+                .check_at(&POLICY, RateLimitedEndpoint::Login, &[first], now)
+                .is_err()
+        );
+        assert!(
+            limiter
+                .check_at(&POLICY, RateLimitedEndpoint::Login, &[second], now)
+                .is_ok()
+        );
+
+        // address of a reverse proxy which isn't configured is shared by its clients
+        let client_ip = request_client_ip(&forwarded_request(
+            SecureClientIpSource::ConnectInfo,
+            Some("203.0.113.1"),
+        ))
+        .unwrap();
+        assert!(client_ip.shared);
+        assert_eq!(client_ip.limited(), None);
+        let client_ip =
+            request_client_ip(&forwarded_request(SecureClientIpSource::ConnectInfo, None)).unwrap();
+        assert_eq!(
+            client_ip.limited(),
+            Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_sweep() {
+        let mut limiter = RateLimiter::default();
+        let keys = [RateLimitKey::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST))];
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(
+                limiter
+                    .check_at(&POLICY, RateLimitedEndpoint::Login, &keys, now)
+                    .is_ok()
+            );
+        }
+        assert!(
+            limiter
+                .check_at(&POLICY, RateLimitedEndpoint::Mfa, &keys, now)
+                .is_ok()
+        );
+        assert_eq!(limiter.buckets.len(), 2);
+
+        // only the bucket which has refilled is removed
+        limiter.sweep_at(&POLICY, now + Duration::from_secs(1));
+        assert_eq!(limiter.buckets.len(), 1);
+        limiter.sweep_at(&POLICY, now + Duration::from_secs(3));
+        assert!(limiter.buckets.is_empty());
+    }
+
+    #[test]
+    fn test_rate_limit_disabled() {
+        let policy = RateLimitPolicy {
+            requests_per_minute: 0,
+            ..POLICY
+        };
+        let mut limiter = RateLimiter::default();
+        let keys = [RateLimitKey::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST))];
+        for _ in 0..100 {
+            assert!(
+                limiter
+                    .check(&policy, RateLimitedEndpoint::Login, &keys)
+                    .is_ok()
+            );
+        }
+    }
+}
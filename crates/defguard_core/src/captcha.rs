@@ -0,0 +1,109 @@
+//! Server-side verification of CAPTCHA challenges solved on public enrollment and password reset
+//! pages served by the proxy, so bots can't make us send emails.
+
+use std::{net::IpAddr, time::Duration};
+
+use defguard_common::db::models::{Settings, settings::CaptchaProvider};
+use thiserror::Error;
+
+const HCAPTCHA_VERIFY_URL: &str = "https://api.hcaptcha.com/siteverify";
+const TURNSTILE_VERIFY_URL: &str = "https://challenges.cloudflare.com/turnstile/v0/siteverify";
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum CaptchaError {
+    #[error("CAPTCHA is not configured")]
+    NotConfigured,
+    #[error("CAPTCHA response is missing")]
+    Missing,
+    #[error("CAPTCHA response was rejected: {0}")]
+    Rejected(String),
+    #[error("CAPTCHA verification request failed: {0}")]
+    Request(#[from] reqwest::Error),
+}
+
+// Both hCaptcha and Turnstile respond in this format.
+#[derive(Deserialize)]
+struct VerifyResponse {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+fn verify_url(provider: CaptchaProvider) -> Option<&'static str> {
+    match provider {
+        CaptchaProvider::None => None,
+        CaptchaProvider::HCaptcha => Some(HCAPTCHA_VERIFY_URL),
+        CaptchaProvider::Turnstile => Some(TURNSTILE_VERIFY_URL),
+    }
+}
+
+/// Verify the CAPTCHA response `token` sent by the client with the provider configured
+/// in settings. Passes if CAPTCHA is disabled.
+pub async fn verify_captcha(
+    settings: &Settings,
+    token: Option<&str>,
+    remote_ip: Option<IpAddr>,
+) -> Result<(), CaptchaError> {
+    let Some(url) = verify_url(settings.captcha_provider) else {
+        return Ok(());
+    };
+    let Some(secret) = &settings.captcha_secret_key else {
+        return Err(CaptchaError::NotConfigured);
+    };
+    let Some(token) = token.filter(|token| !token.is_empty()) else {
+        return Err(CaptchaError::Missing);
+    };
+
+    let remote_ip = remote_ip.map(|ip| ip.to_string());
+    let mut form = vec![("secret", secret.expose_secret()), ("response", token)];
+    if let Some(remote_ip) = &remote_ip {
+        form.push(("remoteip", remote_ip.as_str()));
+    }
+    let response: VerifyResponse = reqwest::Client::builder()
+        .timeout(VERIFY_TIMEOUT)
+        .build()?
+        .post(url)
+        .form(&form)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if response.success {
+        Ok(())
+    } else {
+        Err(CaptchaError::Rejected(response.error_codes.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use defguard_common::secret::SecretStringWrapper;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_verify_captcha_without_response() {
+        let mut settings = Settings::default();
+        assert!(verify_captcha(&settings, None, None).await.is_ok());
+
+        settings.captcha_provider = CaptchaProvider::Turnstile;
+        assert!(matches!(
+            verify_captcha(&settings, Some("token"), None).await,
+            Err(CaptchaError::NotConfigured)
+        ));
+
+        settings.captcha_secret_key = Some(SecretStringWrapper::from_str("secret").unwrap());
+        assert!(matches!(
+            verify_captcha(&settings, None, None).await,
+            Err(CaptchaError::Missing)
+        ));
+        assert!(matches!(
+            verify_captcha(&settings, Some(""), None).await,
+            Err(CaptchaError::Missing)
+        ));
+    }
+}
@@ -1,4 +1,5 @@
 pub mod models;
+pub mod replica;
 
 pub use models::{
     MFAInfo, UserDetails, UserInfo,
@@ -9,7 +10,7 @@ pub use models::{
     session::{Session, SessionState},
     user::User,
     webauthn::WebAuthn,
-    webhook::{AppEvent, HWKeyUserData, WebHook},
+    webhook::{AppEvent, ClientConnectionData, GatewayDisconnectedData, HWKeyUserData, WebHook},
     wireguard::{GatewayEvent, WireguardNetwork},
     yubikey::YubiKey,
 };
@@ -1,21 +1,32 @@
+use std::net::IpAddr;
+
 use chrono::NaiveDateTime;
 use defguard_common::db::{
     Id,
     models::{
-        AuthenticationKey, AuthenticationKeyType, MFAMethod, Settings,
-        settings::{LdapSyncStatus, OpenidUsernameHandling, SmtpEncryption},
+        AuthenticationKey, AuthenticationKeyType, MFAMethod, MailTemplateKind, Settings,
+        settings::{CaptchaProvider, LdapSyncStatus, OpenidUsernameHandling, SmtpEncryption},
     },
 };
 
 use crate::{
     db::{
         Device, Group, User, WebAuthn, WebHook, WireguardNetwork,
-        models::oauth2client::OAuth2Client,
+        models::{
+            device_profile::DeviceProfile,
+            gateway_token::GatewayToken,
+            group::{GroupQuota, GroupSyncAuthority},
+            impersonation::ImpersonationMode,
+            oauth2client::OAuth2Client,
+        },
     },
     enterprise::db::models::{
         activity_log_stream::{ActivityLogStream, ActivityLogStreamType},
-        api_tokens::ApiToken,
+        api_tokens::{ApiToken, ApiTokenScope},
         openid_provider::{DirectorySyncTarget, DirectorySyncUserBehavior, OpenIdProvider},
+        posture_policy::PostureViolation,
+        provisioning_policy::{ProvisioningPolicy, ProvisioningSource},
+        saml_provider::SamlProvider,
         snat::UserSnatBinding,
     },
     events::ClientMFAMethod,
@@ -26,11 +37,53 @@ pub struct LoginFailedMetadata {
     pub message: String,
 }
 
+#[derive(Serialize)]
+pub struct AdminAccessDeniedMetadata {
+    pub method: String,
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Serialize)]
+pub struct UserLockedOutMetadata {
+    pub failed_attempts: u32,
+    pub locked_until: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct LoginIpLockedOutMetadata {
+    pub ip_address: String,
+    pub failed_attempts: u32,
+    pub locked_until: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct LoginIpUnlockedMetadata {
+    pub ip_address: String,
+}
+
+#[derive(Serialize)]
+pub struct RateLimitExceededMetadata {
+    pub endpoint: String,
+    pub ip_address: String,
+    pub rejected_requests: u32,
+}
+
 #[derive(Serialize)]
 pub struct MfaLoginMetadata {
     pub mfa_method: MFAMethod,
 }
 
+#[derive(Serialize)]
+pub struct RecoveryCodeUsedMetadata {
+    pub remaining: usize,
+}
+
+#[derive(Serialize)]
+pub struct RecoveryCodesRegeneratedMetadata {
+    pub count: usize,
+}
+
 #[derive(Serialize)]
 pub struct MfaLoginFailedMetadata {
     pub mfa_method: MFAMethod,
@@ -84,6 +137,13 @@ pub struct DeviceMetadata {
     pub device: Device<Id>,
 }
 
+#[derive(Serialize)]
+pub struct DeviceApprovalMetadata {
+    pub owner: UserNoSecrets,
+    pub device: Device<Id>,
+    pub location: WireguardNetwork<Id>,
+}
+
 #[derive(Serialize)]
 pub struct DeviceModifiedMetadata {
     pub owner: UserNoSecrets,
@@ -104,6 +164,38 @@ pub struct NetworkDeviceModifiedMetadata {
     pub after: Device<Id>,
 }
 
+#[derive(Serialize)]
+pub struct DeviceAddressReservedMetadata {
+    pub device: Device<Id>,
+    pub location: WireguardNetwork<Id>,
+    pub before: Vec<IpAddr>,
+    pub after: Vec<IpAddr>,
+}
+
+#[derive(Serialize)]
+pub struct DeviceAddressReleasedMetadata {
+    pub device: Device<Id>,
+    pub location: WireguardNetwork<Id>,
+    pub addresses: Vec<IpAddr>,
+}
+
+#[derive(Serialize)]
+pub struct DeviceProfileMetadata {
+    pub profile: DeviceProfile<Id>,
+}
+
+#[derive(Serialize)]
+pub struct DeviceProfileModifiedMetadata {
+    pub before: DeviceProfile<Id>,
+    pub after: DeviceProfile<Id>,
+}
+
+#[derive(Serialize)]
+pub struct DeviceProfileAssignedMetadata {
+    pub device: Device<Id>,
+    pub profile: Option<DeviceProfile<Id>>,
+}
+
 #[derive(Serialize)]
 pub struct UserMetadata {
     pub user: UserNoSecrets,
@@ -121,6 +213,23 @@ pub struct UserGroupsModifiedMetadata {
     pub before: Vec<String>,
     pub after: Vec<String>,
 }
+
+#[derive(Serialize)]
+pub struct UserProvisionedMetadata {
+    pub user: UserNoSecrets,
+    pub source: ProvisioningSource,
+    /// Default groups the user joined.
+    pub groups: Vec<String>,
+    pub pending_approval: bool,
+}
+
+#[derive(Serialize)]
+pub struct AccountCreationRefusedMetadata {
+    pub source: ProvisioningSource,
+    /// Email address or LDAP username of the user.
+    pub identity: String,
+}
+
 #[derive(Serialize)]
 pub struct MfaSecurityKeyMetadata {
     pub key: WebAuthnNoSecrets,
@@ -155,6 +264,13 @@ pub struct ActivityLogStreamModifiedMetadata {
     pub after: ActivityLogStreamNoSecrets,
 }
 
+#[derive(Serialize)]
+pub struct ActivityLogPurgedMetadata {
+    pub removed: u64,
+    pub archived: bool,
+    pub threshold: NaiveDateTime,
+}
+
 #[derive(Serialize)]
 pub struct ActivityLogStreamNoSecrets {
     pub id: Id,
@@ -172,27 +288,183 @@ impl From<ActivityLogStream<Id>> for ActivityLogStreamNoSecrets {
     }
 }
 
+/// VPN client metadata always carries top-level location, device and user IDs,
+/// so downstream consumers can correlate events without matching on names.
 #[derive(Serialize)]
 pub struct VpnClientMetadata {
+    pub location_id: Id,
+    pub device_id: Id,
+    pub user_id: Id,
     pub location: WireguardNetwork<Id>,
     pub device: Device<Id>,
 }
 
+impl VpnClientMetadata {
+    #[must_use]
+    pub fn new(location: WireguardNetwork<Id>, device: Device<Id>) -> Self {
+        Self {
+            location_id: location.id,
+            device_id: device.id,
+            user_id: device.user_id,
+            location,
+            device,
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct VpnClientMfaMetadata {
+    pub location_id: Id,
+    pub device_id: Id,
+    pub user_id: Id,
     pub location: WireguardNetwork<Id>,
     pub device: Device<Id>,
     pub method: ClientMFAMethod,
 }
 
+impl VpnClientMfaMetadata {
+    #[must_use]
+    pub fn new(
+        location: WireguardNetwork<Id>,
+        device: Device<Id>,
+        method: ClientMFAMethod,
+    ) -> Self {
+        Self {
+            location_id: location.id,
+            device_id: device.id,
+            user_id: device.user_id,
+            location,
+            device,
+            method,
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct VpnClientMfaFailedMetadata {
+    pub location_id: Id,
+    pub device_id: Id,
+    pub user_id: Id,
     pub location: WireguardNetwork<Id>,
     pub device: Device<Id>,
     pub method: ClientMFAMethod,
     pub message: String,
 }
 
+impl VpnClientMfaFailedMetadata {
+    #[must_use]
+    pub fn new(
+        location: WireguardNetwork<Id>,
+        device: Device<Id>,
+        method: ClientMFAMethod,
+        message: String,
+    ) -> Self {
+        Self {
+            location_id: location.id,
+            device_id: device.id,
+            user_id: device.user_id,
+            location,
+            device,
+            method,
+            message,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MailTemplateMetadata {
+    pub kind: MailTemplateKind,
+}
+
+#[derive(Serialize)]
+pub struct VpnClientMfaLockedOutMetadata {
+    pub location_id: Id,
+    pub device_id: Id,
+    pub user_id: Id,
+    pub location: WireguardNetwork<Id>,
+    pub device: Device<Id>,
+    pub method: ClientMFAMethod,
+    pub attempts: u32,
+    pub locked_until: Option<NaiveDateTime>,
+}
+
+impl VpnClientMfaLockedOutMetadata {
+    #[must_use]
+    pub fn new(
+        location: WireguardNetwork<Id>,
+        device: Device<Id>,
+        method: ClientMFAMethod,
+        attempts: u32,
+        locked_until: Option<NaiveDateTime>,
+    ) -> Self {
+        Self {
+            location_id: location.id,
+            device_id: device.id,
+            user_id: device.user_id,
+            location,
+            device,
+            method,
+            attempts,
+            locked_until,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct VpnClientSessionLimitMetadata {
+    pub location_id: Id,
+    pub device_id: Id,
+    pub user_id: Id,
+    pub location: WireguardNetwork<Id>,
+    pub device: Device<Id>,
+    pub limit: i32,
+}
+
+impl VpnClientSessionLimitMetadata {
+    #[must_use]
+    pub fn new(location: WireguardNetwork<Id>, device: Device<Id>, limit: i32) -> Self {
+        Self {
+            location_id: location.id,
+            device_id: device.id,
+            user_id: device.user_id,
+            location,
+            device,
+            limit,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct VpnClientMfaPostureFailedMetadata {
+    pub location_id: Id,
+    pub device_id: Id,
+    pub user_id: Id,
+    pub location: WireguardNetwork<Id>,
+    pub device: Device<Id>,
+    pub method: ClientMFAMethod,
+    pub violations: Vec<PostureViolation>,
+}
+
+impl VpnClientMfaPostureFailedMetadata {
+    #[must_use]
+    pub fn new(
+        location: WireguardNetwork<Id>,
+        device: Device<Id>,
+        method: ClientMFAMethod,
+        violations: Vec<PostureViolation>,
+    ) -> Self {
+        Self {
+            location_id: location.id,
+            device_id: device.id,
+            user_id: device.user_id,
+            location,
+            device,
+            method,
+            violations,
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct EnrollmentDeviceAddedMetadata {
     pub device: Device<Id>,
@@ -214,6 +486,19 @@ pub struct VpnLocationModifiedMetadata {
     pub after: WireguardNetwork<Id>,
 }
 
+#[derive(Serialize)]
+pub struct VpnLocationMaintenanceMetadata {
+    pub location: WireguardNetwork<Id>,
+    pub message: Option<String>,
+    pub disconnected: usize,
+}
+
+#[derive(Serialize)]
+pub struct GatewayTokenMetadata {
+    pub location: WireguardNetwork<Id>,
+    pub token: GatewayToken<Id>,
+}
+
 #[derive(Serialize)]
 pub struct ApiTokenMetadata {
     pub owner: UserNoSecrets,
@@ -226,6 +511,9 @@ pub struct ApiTokenNoSecrets {
     pub user_id: Id,
     pub created_at: NaiveDateTime,
     pub name: String,
+    pub location_id: Option<Id>,
+    pub group_id: Option<Id>,
+    pub scopes: Vec<ApiTokenScope>,
 }
 
 impl From<ApiToken<Id>> for ApiTokenNoSecrets {
@@ -235,6 +523,9 @@ impl From<ApiToken<Id>> for ApiTokenNoSecrets {
             user_id: value.user_id,
             created_at: value.created_at,
             name: value.name,
+            location_id: value.location_id,
+            group_id: value.group_id,
+            scopes: value.scopes,
         }
     }
 }
@@ -287,6 +578,13 @@ pub struct OpenIdAppStateChangedMetadata {
     pub enabled: bool,
 }
 
+#[derive(Serialize)]
+pub struct ClientCredentialsMetadata {
+    pub app: OAuth2ClientNoSecrets,
+    pub service_account: UserNoSecrets,
+    pub scopes: Vec<ApiTokenScope>,
+}
+
 #[derive(Serialize)]
 pub struct OpenIdProviderMetadata {
     pub provider: OpenIdProviderNoSecrets,
@@ -331,6 +629,34 @@ impl From<OpenIdProvider<Id>> for OpenIdProviderNoSecrets {
     }
 }
 
+/// SAML provider without identity provider metadata XML, which is too long for the activity log.
+#[derive(Serialize)]
+pub struct SamlProviderMetadata {
+    pub id: Id,
+    pub name: String,
+    pub display_name: Option<String>,
+    pub enabled: bool,
+    pub groups_attribute: Option<String>,
+}
+
+impl From<SamlProvider<Id>> for SamlProviderMetadata {
+    fn from(value: SamlProvider<Id>) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            display_name: value.display_name,
+            enabled: value.enabled,
+            groups_attribute: value.groups_attribute,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ProvisioningPolicyModifiedMetadata {
+    pub before: ProvisioningPolicy,
+    pub after: ProvisioningPolicy,
+}
+
 #[derive(Serialize)]
 pub struct SettingsUpdateMetadata {
     pub before: SettingsNoSecrets,
@@ -388,14 +714,43 @@ pub struct SettingsNoSecrets {
     // The attribute which is used to map LDAP usernames to Defguard usernames
     pub ldap_user_rdn_attr: Option<String>,
     pub ldap_sync_groups: Vec<String>,
-    // Whether to create a new account when users try to log in with external OpenID
-    pub openid_create_account: bool,
+    pub ldap_email_attr: Option<String>,
+    pub ldap_first_name_attr: Option<String>,
+    pub ldap_last_name_attr: Option<String>,
+    pub ldap_phone_attr: Option<String>,
+    pub ldap_group_filter: Vec<String>,
+    pub ldap_group_exclude: Vec<String>,
     pub openid_username_handling: OpenidUsernameHandling,
     pub license: Option<String>,
     // Gateway disconnect notifications
     pub gateway_disconnect_notifications_enabled: bool,
     pub gateway_disconnect_notifications_inactivity_threshold: i32,
     pub gateway_disconnect_notifications_reconnect_notification_enabled: bool,
+    // Password hashing
+    pub password_hash_memory_cost: i32,
+    pub password_hash_time_cost: i32,
+    pub password_hash_parallelism: i32,
+    // Password policy
+    pub password_min_length: i32,
+    pub password_require_uppercase: bool,
+    pub password_require_lowercase: bool,
+    pub password_require_digit: bool,
+    pub password_require_special: bool,
+    pub password_min_score: i32,
+    pub password_banned: Vec<String>,
+    pub password_history_size: i32,
+    // Login lockout
+    pub login_lockout_threshold: i32,
+    pub login_lockout_ip_threshold: i32,
+    pub login_lockout_duration: i32,
+    pub login_lockout_mfa_fallback: bool,
+    // CAPTCHA
+    pub captcha_provider: CaptchaProvider,
+    pub captcha_site_key: Option<String>,
+    pub timezone: String,
+    // Activity log retention
+    pub activity_log_retention_days: Option<i32>,
+    pub activity_log_retention_archive: bool,
 }
 
 impl From<Settings> for SettingsNoSecrets {
@@ -440,7 +795,12 @@ impl From<Settings> for SettingsNoSecrets {
             ldap_user_auxiliary_obj_classes: value.ldap_user_auxiliary_obj_classes,
             ldap_user_rdn_attr: value.ldap_user_rdn_attr,
             ldap_sync_groups: value.ldap_sync_groups,
-            openid_create_account: value.openid_create_account,
+            ldap_email_attr: value.ldap_email_attr,
+            ldap_first_name_attr: value.ldap_first_name_attr,
+            ldap_last_name_attr: value.ldap_last_name_attr,
+            ldap_phone_attr: value.ldap_phone_attr,
+            ldap_group_filter: value.ldap_group_filter,
+            ldap_group_exclude: value.ldap_group_exclude,
             openid_username_handling: value.openid_username_handling,
             license: value.license,
             gateway_disconnect_notifications_enabled: value
@@ -449,6 +809,26 @@ impl From<Settings> for SettingsNoSecrets {
                 .gateway_disconnect_notifications_inactivity_threshold,
             gateway_disconnect_notifications_reconnect_notification_enabled: value
                 .gateway_disconnect_notifications_reconnect_notification_enabled,
+            password_hash_memory_cost: value.password_hash_memory_cost,
+            password_hash_time_cost: value.password_hash_time_cost,
+            password_hash_parallelism: value.password_hash_parallelism,
+            password_min_length: value.password_min_length,
+            password_require_uppercase: value.password_require_uppercase,
+            password_require_lowercase: value.password_require_lowercase,
+            password_require_digit: value.password_require_digit,
+            password_require_special: value.password_require_special,
+            password_min_score: value.password_min_score,
+            password_banned: value.password_banned,
+            password_history_size: value.password_history_size,
+            login_lockout_threshold: value.login_lockout_threshold,
+            login_lockout_ip_threshold: value.login_lockout_ip_threshold,
+            login_lockout_duration: value.login_lockout_duration,
+            login_lockout_mfa_fallback: value.login_lockout_mfa_fallback,
+            captcha_provider: value.captcha_provider,
+            captcha_site_key: value.captcha_site_key,
+            timezone: value.timezone,
+            activity_log_retention_days: value.activity_log_retention_days,
+            activity_log_retention_archive: value.activity_log_retention_archive,
         }
     }
 }
@@ -476,6 +856,24 @@ pub struct GroupAssignedMetadata {
     pub user: UserNoSecrets,
 }
 
+#[derive(Serialize)]
+pub struct GroupQuotaModifiedMetadata {
+    pub group: Group<Id>,
+    pub quota: GroupQuota,
+}
+
+#[derive(Serialize)]
+pub struct GroupSyncAuthorityModifiedMetadata {
+    pub group: Group<Id>,
+    pub sync_authority: Option<GroupSyncAuthority>,
+}
+
+#[derive(Serialize)]
+pub struct GroupParentModifiedMetadata {
+    pub group: Group<Id>,
+    pub parent: Option<Group<Id>>,
+}
+
 #[derive(Serialize)]
 pub struct GroupMembersModifiedMetadata {
     pub group: Group<Id>,
@@ -547,6 +945,36 @@ pub struct UserMfaDisabledMetadata {
     pub user: UserNoSecrets,
 }
 
+#[derive(Serialize)]
+pub struct UserSessionRevokedMetadata {
+    pub user: UserNoSecrets,
+    pub ip_address: String,
+    pub device_info: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct UserSessionsRevokedMetadata {
+    pub user: UserNoSecrets,
+    pub count: u64,
+}
+
+#[derive(Serialize)]
+pub struct ImpersonationStartedMetadata {
+    pub admin: UserNoSecrets,
+    pub user: UserNoSecrets,
+    pub mode: ImpersonationMode,
+    pub expires: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct ImpersonationEndedMetadata {
+    pub admin: UserNoSecrets,
+    pub user: UserNoSecrets,
+    pub mode: ImpersonationMode,
+    /// Impersonation hit its time limit instead of being ended by the admin.
+    pub expired: bool,
+}
+
 #[derive(Serialize)]
 pub struct ClientConfigurationTokenMetadata {
     pub user: UserNoSecrets,
@@ -563,3 +991,10 @@ pub struct UserSnatBindingModifiedMetadata {
     pub before: UserSnatBinding<Id>,
     pub after: UserSnatBinding<Id>,
 }
+
+#[derive(Serialize)]
+pub struct CustomEventMetadata {
+    pub namespace: String,
+    pub name: String,
+    pub data: Option<serde_json::Value>,
+}
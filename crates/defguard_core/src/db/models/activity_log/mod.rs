@@ -2,10 +2,13 @@ use chrono::NaiveDateTime;
 use defguard_common::db::{Id, NoId};
 use ipnetwork::IpNetwork;
 use model_derive::Model;
-use sqlx::{FromRow, Type};
+use sqlx::{FromRow, PgPool, Type, query};
 
 pub mod metadata;
 
+// Maximum number of events removed by a single purge statement, keeps transactions short.
+const PURGE_BATCH_SIZE: i64 = 10_000;
+
 #[derive(Clone, Debug, Deserialize, Serialize, Type)]
 #[sqlx(type_name = "activity_log_module", rename_all = "snake_case")]
 #[serde(rename_all = "lowercase")]
@@ -14,6 +17,8 @@ pub enum ActivityLogModule {
     Client,
     Vpn,
     Enrollment,
+    Custom,
+    Ldap,
 }
 
 /// Represents activity log event type as it's stored in the DB
@@ -30,7 +35,15 @@ pub enum EventType {
     UserMfaLogin,
     UserMfaLoginFailed,
     RecoveryCodeUsed,
+    RecoveryCodesRegenerated,
     UserLogout,
+    BreakGlassLogin,
+    AdminAccessDenied,
+    UserLockedOut,
+    LoginIpLockedOut,
+    UserUnlocked,
+    LoginIpUnlocked,
+    RateLimitExceeded,
     // mfa management
     MfaDisabled,
     UserMfaDisabled,
@@ -38,54 +51,90 @@ pub enum EventType {
     MfaTotpEnabled,
     MfaEmailDisabled,
     MfaEmailEnabled,
+    MfaSmsDisabled,
+    MfaSmsEnabled,
     MfaSecurityKeyAdded,
     MfaSecurityKeyRemoved,
     // user management
     UserAdded,
     UserRemoved,
     UserModified,
+    UserDisabled,
     UserGroupsModified,
+    UserProvisioned,
+    AccountCreationRefused,
     PasswordChanged,
     PasswordChangedByAdmin,
     PasswordReset,
+    UserSessionRevoked,
+    UserSessionsRevoked,
+    ImpersonationStarted,
+    ImpersonationEnded,
     // device management
     DeviceAdded,
     DeviceRemoved,
     DeviceModified,
+    DeviceApproved,
+    DeviceRejected,
     NetworkDeviceAdded,
     NetworkDeviceRemoved,
     NetworkDeviceModified,
+    DeviceAddressReserved,
+    DeviceAddressReleased,
+    DeviceProfileAdded,
+    DeviceProfileModified,
+    DeviceProfileRemoved,
+    DeviceProfileAssigned,
     // activity log stream
     ActivityLogStreamCreated,
     ActivityLogStreamModified,
     ActivityLogStreamRemoved,
+    ActivityLogPurged,
     ClientConfigurationTokenAdded,
     // OpenID app management
     OpenIdAppAdded,
     OpenIdAppRemoved,
     OpenIdAppModified,
     OpenIdAppStateChanged,
+    ClientCredentialsSet,
+    ClientCredentialsRemoved,
     // OpenID provider management
     OpenIdProviderRemoved,
     OpenIdProviderModified,
+    // SAML provider management
+    SamlProviderModified,
+    SamlProviderRemoved,
+    // Just-in-time provisioning
+    ProvisioningPolicyModified,
     // VPN location management
     VpnLocationAdded,
     VpnLocationRemoved,
     VpnLocationModified,
+    VpnLocationMaintenanceEnabled,
+    VpnLocationMaintenanceDisabled,
+    GatewayTokenAdded,
+    GatewayTokenRevoked,
     // VPN client events
     VpnClientConnected,
     VpnClientDisconnected,
     VpnClientConnectedMfa,
     VpnClientDisconnectedMfa,
     VpnClientMfaFailed,
+    VpnClientMfaLockedOut,
+    VpnClientMfaPostureFailed,
+    VpnClientMfaTimeout,
+    VpnClientSessionLimitExceeded,
     // Enrollment events
     EnrollmentTokenAdded,
+    EnrollmentTokenRevoked,
     EnrollmentStarted,
     EnrollmentDeviceAdded,
     EnrollmentCompleted,
+    EnrollmentBlocked,
     PasswordResetRequested,
     PasswordResetStarted,
     PasswordResetCompleted,
+    PasswordResetBlocked,
     // API token management,
     ApiTokenAdded,
     ApiTokenRemoved,
@@ -94,6 +143,8 @@ pub enum EventType {
     SettingsUpdated,
     SettingsUpdatedPartial,
     SettingsDefaultBrandingRestored,
+    MailTemplateModified,
+    MailTemplateReset,
     // Groups management
     GroupsBulkAssigned,
     GroupAdded,
@@ -101,7 +152,13 @@ pub enum EventType {
     GroupRemoved,
     GroupMemberAdded,
     GroupMemberRemoved,
+    GroupMembershipExpired,
     GroupMembersModified,
+    GroupQuotaModified,
+    GroupSyncAuthorityModified,
+    GroupParentModified,
+    GroupJoinRequestApproved,
+    GroupJoinRequestRejected,
     // WebHook management
     WebHookAdded,
     WebHookModified,
@@ -115,6 +172,8 @@ pub enum EventType {
     UserSnatBindingAdded,
     UserSnatBindingRemoved,
     UserSnatBindingModified,
+    // Events submitted by external integrations
+    CustomEvent,
 }
 
 #[derive(Model, FromRow, Serialize)]
@@ -133,4 +192,158 @@ pub struct ActivityLogEvent<I = NoId> {
     pub device: String,
     pub description: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    pub request_id: Option<String>,
+    /// Admin who triggered the event while impersonating the user.
+    pub impersonator_id: Option<Id>,
+    pub impersonator: Option<String>,
+}
+
+impl ActivityLogEvent<Id> {
+    /// Delete events older than `threshold`, moving them to the archive table first if `archive`
+    /// is set. Events are removed in batches to avoid locking large parts of the table.
+    /// Returns the number of removed events.
+    pub async fn purge_older_than(
+        pool: &PgPool,
+        threshold: NaiveDateTime,
+        archive: bool,
+    ) -> Result<u64, sqlx::Error> {
+        let mut removed = 0;
+        loop {
+            let result = if archive {
+                query!(
+                    "WITH moved AS (\
+                        DELETE FROM activity_log_event WHERE id IN (\
+                            SELECT id FROM activity_log_event WHERE timestamp < $1 LIMIT $2\
+                        ) RETURNING id, timestamp, user_id, username, location, ip, event, \
+                        module, device, description, metadata, request_id, impersonator_id, \
+                        impersonator\
+                    ) \
+                    INSERT INTO activity_log_event_archive (id, timestamp, user_id, username, \
+                    location, ip, event, module, device, description, metadata, request_id, \
+                    impersonator_id, impersonator) \
+                    SELECT id, timestamp, user_id, username, location, ip, event, module, device, \
+                    description, metadata, request_id, impersonator_id, impersonator FROM moved",
+                    threshold,
+                    PURGE_BATCH_SIZE,
+                )
+                .execute(pool)
+                .await?
+            } else {
+                query!(
+                    "DELETE FROM activity_log_event WHERE id IN (\
+                        SELECT id FROM activity_log_event WHERE timestamp < $1 LIMIT $2\
+                    )",
+                    threshold,
+                    PURGE_BATCH_SIZE,
+                )
+                .execute(pool)
+                .await?
+            };
+            removed += result.rows_affected();
+            if result.rows_affected() < PURGE_BATCH_SIZE as u64 {
+                break;
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use chrono::{TimeDelta, Utc};
+    use defguard_common::db::setup_pool;
+    use sqlx::{
+        postgres::{PgConnectOptions, PgPoolOptions},
+        query, query_scalar,
+    };
+
+    use super::*;
+
+    async fn add_event(pool: &PgPool, timestamp: NaiveDateTime) {
+        ActivityLogEvent {
+            id: NoId,
+            timestamp,
+            user_id: 1,
+            username: "admin".into(),
+            location: None,
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST).into(),
+            event: EventType::UserLogin,
+            module: ActivityLogModule::Defguard,
+            device: String::new(),
+            description: None,
+            metadata: None,
+            request_id: None,
+            impersonator_id: None,
+            impersonator: None,
+        }
+        .save(pool)
+        .await
+        .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn test_purge_older_than(_: PgPoolOptions, options: PgConnectOptions) {
+        let pool = setup_pool(options).await;
+        let now = Utc::now().naive_utc();
+        let threshold = now - TimeDelta::days(30);
+
+        add_event(&pool, now - TimeDelta::days(60)).await;
+        add_event(&pool, now - TimeDelta::days(31)).await;
+        add_event(&pool, now - TimeDelta::days(1)).await;
+
+        let removed = ActivityLogEvent::purge_older_than(&pool, threshold, true)
+            .await
+            .unwrap();
+        assert_eq!(removed, 2);
+        let archived = query_scalar!("SELECT count(*) \"count!\" FROM activity_log_event_archive")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(archived, 2);
+
+        add_event(&pool, now - TimeDelta::days(45)).await;
+        let removed = ActivityLogEvent::purge_older_than(&pool, threshold, false)
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+        let remaining = query_scalar!("SELECT count(*) \"count!\" FROM activity_log_event")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 1);
+        let archived = query_scalar!("SELECT count(*) \"count!\" FROM activity_log_event_archive")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(archived, 2);
+    }
+
+    // The archive table is created with `LIKE`, so columns added to events later
+    // have to be added to the archive by hand.
+    #[sqlx::test]
+    async fn test_archive_columns_match(_: PgPoolOptions, options: PgConnectOptions) {
+        let pool = setup_pool(options).await;
+        let mut columns = Vec::new();
+        for table in ["activity_log_event", "activity_log_event_archive"] {
+            let table_columns = query!(
+                "SELECT column_name::text \"name!\", udt_name::text \"type_name!\", \
+                is_nullable::text \"is_nullable!\" FROM information_schema.columns \
+                WHERE table_schema = current_schema() AND table_name::text = $1 \
+                ORDER BY column_name",
+                table
+            )
+            .fetch_all(&pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|column| (column.name, column.type_name, column.is_nullable))
+            .collect::<Vec<_>>();
+            columns.push(table_columns);
+        }
+        assert!(!columns[0].is_empty());
+        assert_eq!(columns[0], columns[1]);
+    }
 }
@@ -0,0 +1,382 @@
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use defguard_common::db::{Id, models::BiometricChallenge};
+use defguard_proto::proxy::MfaMethod;
+use sqlx::{Error as SqlxError, PgExecutor, query, query_as, query_scalar};
+use webauthn_rs::prelude::PasskeyAuthentication;
+
+/// Desktop client MFA login in progress, keyed by device public key.
+///
+/// Logins are stored in the database, so that a login started through one core instance can be
+/// finished through another.
+#[derive(Clone, Debug)]
+pub struct ClientLoginSession {
+    pub pubkey: String,
+    pub method: MfaMethod,
+    pub location_id: Id,
+    pub device_id: Id,
+    pub user_id: Id,
+    pub openid_auth_completed: bool,
+    pub biometric_challenge: Option<BiometricChallenge>,
+    pub passkey_authentication: Option<PasskeyAuthentication>,
+    pub failed_attempts: u32,
+    pub expires_at: NaiveDateTime,
+}
+
+struct ClientLoginSessionRow {
+    pubkey: String,
+    method: i32,
+    location_id: Id,
+    device_id: Id,
+    user_id: Id,
+    openid_auth_completed: bool,
+    biometric_auth_pub_key: Option<String>,
+    biometric_challenge: Option<String>,
+    passkey_authentication: Option<Vec<u8>>,
+    failed_attempts: i32,
+    expires_at: NaiveDateTime,
+}
+
+impl TryFrom<ClientLoginSessionRow> for ClientLoginSession {
+    type Error = SqlxError;
+
+    fn try_from(row: ClientLoginSessionRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            pubkey: row.pubkey,
+            method: MfaMethod::try_from(row.method).map_err(|err| SqlxError::Decode(err.into()))?,
+            location_id: row.location_id,
+            device_id: row.device_id,
+            user_id: row.user_id,
+            openid_auth_completed: row.openid_auth_completed,
+            biometric_challenge: row.biometric_challenge.map(|challenge| BiometricChallenge {
+                auth_pub_key: row.biometric_auth_pub_key,
+                challenge,
+            }),
+            passkey_authentication: row
+                .passkey_authentication
+                .and_then(|state| serde_cbor::from_slice(&state).ok()),
+            failed_attempts: u32::try_from(row.failed_attempts).unwrap_or_default(),
+            expires_at: row.expires_at,
+        })
+    }
+}
+
+impl ClientLoginSession {
+    #[must_use]
+    pub fn new(
+        pubkey: String,
+        method: MfaMethod,
+        location_id: Id,
+        device_id: Id,
+        user_id: Id,
+        lifetime: TimeDelta,
+    ) -> Self {
+        Self {
+            pubkey,
+            method,
+            location_id,
+            device_id,
+            user_id,
+            openid_auth_completed: false,
+            biometric_challenge: None,
+            passkey_authentication: None,
+            failed_attempts: 0,
+            expires_at: Utc::now().naive_utc() + lifetime,
+        }
+    }
+
+    /// Store the login, replacing the one previously started for the same device.
+    pub async fn save<'e, E>(&self, executor: E) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let passkey_authentication = self
+            .passkey_authentication
+            .as_ref()
+            .and_then(|state| serde_cbor::to_vec(state).ok());
+        query!(
+            "INSERT INTO client_login_session (pubkey, method, location_id, device_id, user_id, \
+            openid_auth_completed, biometric_auth_pub_key, biometric_challenge, \
+            passkey_authentication, failed_attempts, expires_at) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) \
+            ON CONFLICT (pubkey) DO UPDATE SET method = $2, location_id = $3, device_id = $4, \
+            user_id = $5, openid_auth_completed = $6, biometric_auth_pub_key = $7, \
+            biometric_challenge = $8, passkey_authentication = $9, failed_attempts = $10, \
+            expires_at = $11",
+            self.pubkey,
+            self.method as i32,
+            self.location_id,
+            self.device_id,
+            self.user_id,
+            self.openid_auth_completed,
+            self.biometric_challenge
+                .as_ref()
+                .and_then(|challenge| challenge.auth_pub_key.clone()),
+            self.biometric_challenge
+                .as_ref()
+                .map(|challenge| challenge.challenge.clone()),
+            passkey_authentication,
+            self.failed_attempts as i32,
+            self.expires_at
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn find<'e, E>(executor: E, pubkey: &str) -> Result<Option<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            ClientLoginSessionRow,
+            "SELECT pubkey, method, location_id, device_id, user_id, openid_auth_completed, \
+            biometric_auth_pub_key, biometric_challenge, passkey_authentication, \
+            failed_attempts, expires_at FROM client_login_session WHERE pubkey = $1",
+            pubkey
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(Self::try_from)
+        .transpose()
+    }
+
+    pub async fn exists<'e, E>(executor: E, pubkey: &str) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT EXISTS (SELECT 1 FROM client_login_session WHERE pubkey = $1) \"exists!\"",
+            pubkey
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Logins which haven't expired yet, ordered by location and device.
+    pub async fn all_active<'e, E>(executor: E) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            ClientLoginSessionRow,
+            "SELECT pubkey, method, location_id, device_id, user_id, openid_auth_completed, \
+            biometric_auth_pub_key, biometric_challenge, passkey_authentication, \
+            failed_attempts, expires_at FROM client_login_session \
+            WHERE expires_at > NOW() AT TIME ZONE 'UTC' ORDER BY location_id, device_id"
+        )
+        .fetch_all(executor)
+        .await?
+        .into_iter()
+        .map(Self::try_from)
+        .collect()
+    }
+
+    /// Removes the login, returns `false` if it didn't exist.
+    pub async fn delete<'e, E>(executor: E, pubkey: &str) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let result = query!("DELETE FROM client_login_session WHERE pubkey = $1", pubkey)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Removes and returns logins which haven't been finished in time.
+    ///
+    /// Each expired login is returned by one core instance only.
+    pub async fn delete_expired<'e, E>(executor: E) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            ClientLoginSessionRow,
+            "DELETE FROM client_login_session WHERE expires_at <= NOW() AT TIME ZONE 'UTC' \
+            RETURNING pubkey, method, location_id, device_id, user_id, openid_auth_completed, \
+            biometric_auth_pub_key, biometric_challenge, passkey_authentication, \
+            failed_attempts, expires_at"
+        )
+        .fetch_all(executor)
+        .await?
+        .into_iter()
+        .map(Self::try_from)
+        .collect()
+    }
+
+    pub async fn delete_for_user<'e, E>(executor: E, user_id: Id) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "DELETE FROM client_login_session WHERE user_id = $1",
+            user_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_for_location<'e, E>(executor: E, location_id: Id) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "DELETE FROM client_login_session WHERE location_id = $1",
+            location_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes login of the device in the location, returns `false` if there was none.
+    pub async fn delete_for_device<'e, E>(
+        executor: E,
+        location_id: Id,
+        device_id: Id,
+    ) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let result = query!(
+            "DELETE FROM client_login_session WHERE location_id = $1 AND device_id = $2",
+            location_id,
+            device_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Counts a rejected MFA code, returns the number of failed attempts or `None` if the login
+    /// doesn't exist.
+    pub async fn register_failed_attempt<'e, E>(
+        executor: E,
+        pubkey: &str,
+    ) -> Result<Option<u32>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let attempts = query_scalar!(
+            "UPDATE client_login_session SET failed_attempts = failed_attempts + 1 \
+            WHERE pubkey = $1 RETURNING failed_attempts",
+            pubkey
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(attempts.map(|attempts| u32::try_from(attempts).unwrap_or_default()))
+    }
+}
+
+/// Rejected desktop client MFA codes of a single user, counted across all of their logins.
+#[derive(Clone, Debug)]
+pub struct ClientMfaUserAttempts {
+    pub user_id: Id,
+    pub failed_attempts: u32,
+    pub window_start: NaiveDateTime,
+    pub locked_until: Option<NaiveDateTime>,
+}
+
+struct ClientMfaUserAttemptsRow {
+    user_id: Id,
+    failed_attempts: i32,
+    window_start: NaiveDateTime,
+    locked_until: Option<NaiveDateTime>,
+}
+
+impl From<ClientMfaUserAttemptsRow> for ClientMfaUserAttempts {
+    fn from(row: ClientMfaUserAttemptsRow) -> Self {
+        Self {
+            user_id: row.user_id,
+            failed_attempts: u32::try_from(row.failed_attempts).unwrap_or_default(),
+            window_start: row.window_start,
+            locked_until: row.locked_until,
+        }
+    }
+}
+
+impl ClientMfaUserAttempts {
+    #[must_use]
+    pub fn new(user_id: Id, now: NaiveDateTime) -> Self {
+        Self {
+            user_id,
+            failed_attempts: 0,
+            window_start: now,
+            locked_until: None,
+        }
+    }
+
+    /// Counter can be discarded once the lockout is over or the recorded failures are older than
+    /// `lockout`.
+    #[must_use]
+    pub fn is_expired(&self, now: NaiveDateTime, lockout: TimeDelta) -> bool {
+        match self.locked_until {
+            Some(locked_until) => locked_until <= now,
+            None => self.window_start + lockout <= now,
+        }
+    }
+
+    pub async fn find<'e, E>(executor: E, user_id: Id) -> Result<Option<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let attempts = query_as!(
+            ClientMfaUserAttemptsRow,
+            "SELECT user_id, failed_attempts, window_start, locked_until \
+            FROM client_mfa_user_attempts WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(attempts.map(Self::from))
+    }
+
+    pub async fn save<'e, E>(&self, executor: E) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "INSERT INTO client_mfa_user_attempts \
+            (user_id, failed_attempts, window_start, locked_until) VALUES ($1, $2, $3, $4) \
+            ON CONFLICT (user_id) DO UPDATE SET failed_attempts = $2, window_start = $3, \
+            locked_until = $4",
+            self.user_id,
+            self.failed_attempts as i32,
+            self.window_start,
+            self.locked_until
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete<'e, E>(executor: E, user_id: Id) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "DELETE FROM client_mfa_user_attempts WHERE user_id = $1",
+            user_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes counters which are no longer relevant, see [`Self::is_expired`].
+    pub async fn delete_expired<'e, E>(executor: E, lockout: TimeDelta) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let now = Utc::now().naive_utc();
+        query!(
+            "DELETE FROM client_mfa_user_attempts \
+            WHERE locked_until <= $1 OR (locked_until IS NULL AND window_start <= $2)",
+            now,
+            now - lockout
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
@@ -18,13 +18,19 @@ use rand::{
 };
 use sqlx::{
     Error as SqlxError, FromRow, PgConnection, PgExecutor, PgPool, Type,
-    postgres::types::PgInterval, query, query_as,
+    postgres::types::PgInterval, query, query_as, query_scalar,
 };
 use thiserror::Error;
 use utoipa::ToSchema;
 
-use super::wireguard::{
-    LocationMfaMode, NetworkAddressError, WIREGUARD_MAX_HANDSHAKE, WireguardNetwork,
+use super::{
+    device_approval::DeviceApproval,
+    device_key_history::DeviceKeyHistory,
+    device_profile::{DeviceProfile, apply_device_profile},
+    wireguard::{
+        IpAssignmentStrategy, LocationMfaMode, NetworkAddressError, WIREGUARD_MAX_HANDSHAKE,
+        WireguardNetwork, network_ips_from, network_size,
+    },
 };
 use crate::{
     KEY_LENGTH,
@@ -35,6 +41,10 @@ use crate::{
     enterprise::db::models::enterprise_settings::EnterpriseSettings,
 };
 
+// Placeholder for the private key in generated device configs, which is normally
+// known only to the device itself.
+const PRIVATE_KEY_PLACEHOLDER: &str = "YOUR_PRIVATE_KEY";
+
 #[derive(Deserialize, Serialize, ToSchema)]
 pub struct DeviceConfig {
     pub(crate) network_id: Id,
@@ -52,6 +62,15 @@ pub struct DeviceConfig {
     pub(crate) service_location_mode: ServiceLocationMode,
 }
 
+impl DeviceConfig {
+    /// Fills in the private key for devices provisioned with a server-generated keypair.
+    #[must_use]
+    pub(crate) fn with_private_key(mut self, private_key: &str) -> Self {
+        self.config = self.config.replace(PRIVATE_KEY_PLACEHOLDER, private_key);
+        self
+    }
+}
+
 // The type of a device:
 // User: A device of a user, which may be in multiple networks, e.g. a laptop
 // Network: A stand-alone device added by a user permanently bound to one network, e.g. a printer
@@ -191,6 +210,7 @@ impl DeviceInfo {
 pub struct UserDevice {
     #[serde(flatten)]
     pub device: Device<Id>,
+    pub expires_at: Option<NaiveDateTime>,
     pub networks: Vec<UserDeviceNetworkInfo>,
 }
 
@@ -259,9 +279,11 @@ impl UserDevice {
                 }
             })
             .collect();
+        let expires_at = device.expires_at(pool).await?;
 
         Ok(Some(Self {
             device,
+            expires_at,
             networks: networks_info,
         }))
     }
@@ -359,6 +381,59 @@ impl WireguardNetworkDevice {
         Ok(())
     }
 
+    /// Whether the addresses were reserved by an admin and aren't reassigned automatically.
+    pub(crate) async fn ip_reserved<'e, E>(&self, executor: E) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT ip_reserved FROM wireguard_network_device \
+            WHERE device_id = $1 AND wireguard_network_id = $2",
+            self.device_id,
+            self.wireguard_network_id,
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub(crate) async fn set_ip_reserved<'e, E>(
+        &self,
+        executor: E,
+        ip_reserved: bool,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "UPDATE wireguard_network_device SET ip_reserved = $3 \
+            WHERE device_id = $1 AND wireguard_network_id = $2",
+            self.device_id,
+            self.wireguard_network_id,
+            ip_reserved
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// IDs of devices with addresses reserved in the network.
+    pub(crate) async fn reserved_for_network<'e, E>(
+        executor: E,
+        network_id: Id,
+    ) -> Result<Vec<Id>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT device_id FROM wireguard_network_device \
+            WHERE wireguard_network_id = $1 AND ip_reserved",
+            network_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
     pub(crate) async fn delete<'e, E>(&self, executor: E) -> Result<(), SqlxError>
     where
         E: PgExecutor<'e>,
@@ -510,7 +585,8 @@ impl WireguardNetworkDevice {
             "SELECT id, name, address, port, pubkey, prvkey, endpoint, dns, allowed_ips, \
             connected_at, keepalive_interval, peer_disconnect_threshold, \
             acl_enabled, acl_default_allow, location_mfa_mode \"location_mfa_mode: LocationMfaMode\", \
-            service_location_mode \"service_location_mode: ServiceLocationMode\" \
+            service_location_mode \"service_location_mode: ServiceLocationMode\", \
+            ip_assignment_strategy \"ip_assignment_strategy: IpAssignmentStrategy\" \
             FROM wireguard_network WHERE id = $1",
             self.wireguard_network_id
         )
@@ -527,6 +603,8 @@ pub enum DeviceError {
     DatabaseError(#[from] sqlx::Error),
     #[error(transparent)]
     NetworkIpAssignmentError(#[from] NetworkAddressError),
+    #[error("Device profile doesn't allow location {0}")]
+    LocationNotAllowed(String),
     #[error("Unexpected error: {0}")]
     Unexpected(String),
 }
@@ -596,7 +674,7 @@ impl Device<Id> {
 
         format!(
             "[Interface]\n\
-            PrivateKey = YOUR_PRIVATE_KEY\n\
+            PrivateKey = {PRIVATE_KEY_PLACEHOLDER}\n\
             Address = {}\n\
             {dns}\n\
             \n\
@@ -693,6 +771,13 @@ impl Device<Id> {
         location: &WireguardNetwork<Id>,
         enterprise_settings: &EnterpriseSettings,
     ) -> Result<(DeviceNetworkInfo, DeviceConfig), DeviceError> {
+        let profile = DeviceProfile::find_for_device(&mut *transaction, self.id).await?;
+        let location = location
+            .clone()
+            .apply_device_settings(&mut *transaction, self)
+            .await?;
+        let location = &apply_device_profile(profile.as_ref(), location.clone())
+            .ok_or_else(|| DeviceError::LocationNotAllowed(location.name.clone()))?;
         let wireguard_network_device =
             WireguardNetworkDevice::find(&mut *transaction, self.id, location.id)
                 .await?
@@ -730,6 +815,13 @@ impl Device<Id> {
         ip: &[IpAddr],
         enterprise_settings: &EnterpriseSettings,
     ) -> Result<(DeviceNetworkInfo, DeviceConfig), DeviceError> {
+        let profile = DeviceProfile::find_for_device(&mut *transaction, self.id).await?;
+        let location = location
+            .clone()
+            .apply_device_settings(&mut *transaction, self)
+            .await?;
+        let location = &apply_device_profile(profile.as_ref(), location.clone())
+            .ok_or_else(|| DeviceError::LocationNotAllowed(location.name.clone()))?;
         let wireguard_network_device = self
             .assign_network_ips(&mut *transaction, location, ip)
             .await?;
@@ -768,6 +860,7 @@ impl Device<Id> {
         let locations = WireguardNetwork::all(&mut *transaction).await?;
 
         let enterprise_settings = EnterpriseSettings::get(&mut *transaction).await?;
+        let profile = DeviceProfile::find_for_device(&mut *transaction, self.id).await?;
 
         let mut configs = Vec::new();
         let mut network_info = Vec::new();
@@ -798,13 +891,33 @@ impl Device<Id> {
                     self.name,
                     self.user_id
                 );
+                let location = location
+                    .apply_device_settings(&mut *transaction, self)
+                    .await?;
+                // IP is reserved anyway, so the device can use the location
+                // once its profile allows it
+                let location_id = location.id;
+                let Some(location) = apply_device_profile(profile.as_ref(), location) else {
+                    debug!(
+                        "Profile of device {self} doesn't allow location {location_id}, skipping config"
+                    );
+                    continue;
+                };
                 let device_network_info = DeviceNetworkInfo {
                     network_id: location.id,
                     device_wireguard_ips: wireguard_network_device.wireguard_ips.clone(),
                     preshared_key: wireguard_network_device.preshared_key.clone(),
                     is_authorized: wireguard_network_device.is_authorized,
                 };
-                network_info.push(device_network_info);
+                // peer is pushed to gateways once an admin approves the device
+                if DeviceApproval::request(&mut *transaction, self.id, location.id).await? {
+                    info!(
+                        "Device {} (user {}) is waiting for approval in location {location}",
+                        self.name, self.user_id
+                    );
+                } else {
+                    network_info.push(device_network_info);
+                }
 
                 let config =
                     Self::create_config(&location, &wireguard_network_device, &enterprise_settings);
@@ -830,7 +943,8 @@ impl Device<Id> {
     /// Assign the next available IP address in each subnet of the network to this device.
     ///
     /// For every CIDR block in `network.address`, this function:
-    /// 1. Iterates through the block's IPs in order.
+    /// 1. Iterates through the block's IPs in order, starting at the offset chosen by the
+    ///    network's `ip_assignment_strategy` and wrapping around at the end of the block.
     /// 2. Skips any IP that:
     ///    - Fails the `can_assign_ips` validation (out of range, reserved, or already in use by another device), or
     ///    - Appears in the optional `reserved_ips`.
@@ -862,6 +976,9 @@ impl Device<Id> {
         );
         let mut ips = Vec::new();
         let reserved = reserved_ips.unwrap_or_default();
+        let scope = network
+            .device_address_scope(&mut *transaction, self.user_id)
+            .await?;
 
         // Iterate over all network addresses and assign new IP for the device in each of them
         for address in &network.address {
@@ -871,7 +988,7 @@ impl Device<Id> {
             );
             // Don't reassign addresses for networks that didn't change
             if let Some(ip) =
-                current_ips.and_then(|ips| ips.iter().find(|ip| address.contains(**ip)))
+                current_ips.and_then(|ips| ips.iter().find(|ip| scope.allows(address, **ip)))
             {
                 debug!(
                     "Skipping reassignment of already assigned valid IP {ip} for device {} in network {} with addresses {:?}",
@@ -880,12 +997,18 @@ impl Device<Id> {
                 ips.push(*ip);
                 continue;
             }
+            // Search only the address pool of the user's group, if there's one
+            let range = scope.range(address);
+            let start = network
+                .ip_assignment_strategy
+                .start_offset(network_size(&range), &self.wireguard_pubkey);
             let mut picked = None;
-            for ip in address {
-                if network
-                    .can_assign_ips(transaction, &[ip], Some(self.id))
-                    .await
-                    .is_ok()
+            for ip in network_ips_from(range, start) {
+                if scope.allows(address, ip)
+                    && network
+                        .can_assign_ips(transaction, &[ip], Some(self.id))
+                        .await
+                        .is_ok()
                     && !reserved.contains(&ip)
                 {
                     picked = Some(ip);
@@ -968,7 +1091,8 @@ impl Device<Id> {
             "SELECT id, name, address, port, pubkey, prvkey, endpoint, dns, allowed_ips, \
             connected_at,  keepalive_interval, peer_disconnect_threshold, \
             acl_enabled, acl_default_allow, location_mfa_mode \"location_mfa_mode: LocationMfaMode\", \
-            service_location_mode \"service_location_mode: ServiceLocationMode\" \
+            service_location_mode \"service_location_mode: ServiceLocationMode\", \
+            ip_assignment_strategy \"ip_assignment_strategy: IpAssignmentStrategy\" \
             FROM wireguard_network WHERE id IN \
             (SELECT wireguard_network_id FROM wireguard_network_device WHERE device_id = $1 ORDER BY id LIMIT 1)",
             self.id
@@ -1035,6 +1159,97 @@ impl Device<Id> {
             self.user_id
         ).fetch_one(executor).await
     }
+
+    /// Replace the WireGuard public key of the device, remembering the previous one.
+    ///
+    /// Authorization in MFA-protected locations is revoked, since it was bound to the previous
+    /// key. Returns device info with the previous key, which has to be removed from gateways,
+    /// and device info with the new key, which has to be added.
+    pub(crate) async fn rotate_pubkey(
+        &mut self,
+        conn: &mut PgConnection,
+        wireguard_pubkey: String,
+    ) -> Result<(DeviceInfo, DeviceInfo), DeviceError> {
+        let previous = DeviceInfo::from_device(&mut *conn, self.clone())
+            .await
+            .map_err(|err| DeviceError::Unexpected(err.to_string()))?;
+        DeviceKeyHistory::new(self.id, self.wireguard_pubkey.clone())
+            .save(&mut *conn)
+            .await?;
+        self.wireguard_pubkey = wireguard_pubkey;
+        self.save(&mut *conn).await?;
+
+        let network_devices = WireguardNetworkDevice::find_by_device(&mut *conn, self.id)
+            .await?
+            .unwrap_or_default();
+        for mut network_device in network_devices {
+            let Some(location) =
+                WireguardNetwork::find_by_id(&mut *conn, network_device.wireguard_network_id)
+                    .await?
+            else {
+                continue;
+            };
+            if location.mfa_enabled() && network_device.is_authorized {
+                debug!("Revoking authorization of device {self} in location {location}");
+                network_device.is_authorized = false;
+                network_device.preshared_key = None;
+                network_device.authorized_at = None;
+                network_device.update(&mut *conn).await?;
+            }
+        }
+
+        let current = DeviceInfo::from_device(&mut *conn, self.clone())
+            .await
+            .map_err(|err| DeviceError::Unexpected(err.to_string()))?;
+        Ok((previous, current))
+    }
+
+    /// Time at which the device is removed from gateways and marked as not configured.
+    pub(crate) async fn expires_at<'e, E>(
+        &self,
+        executor: E,
+    ) -> Result<Option<NaiveDateTime>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!("SELECT expires_at FROM device WHERE id = $1", self.id)
+            .fetch_one(executor)
+            .await
+    }
+
+    pub(crate) async fn set_expires_at<'e, E>(
+        &self,
+        executor: E,
+        expires_at: Option<NaiveDateTime>,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "UPDATE device SET expires_at = $2 WHERE id = $1",
+            self.id,
+            expires_at
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// IDs of configured devices whose expiry time has passed.
+    pub(crate) async fn find_expired<'e, E>(
+        executor: E,
+        now: NaiveDateTime,
+    ) -> Result<Vec<Id>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT id FROM device WHERE configured AND expires_at <= $1",
+            now
+        )
+        .fetch_all(executor)
+        .await
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,135 @@
+use chrono::{NaiveDateTime, Utc};
+use defguard_common::db::{Id, NoId};
+use model_derive::Model;
+use sqlx::{Error as SqlxError, PgExecutor, Type, query, query_as, query_scalar};
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize, ToSchema, Type)]
+#[sqlx(type_name = "device_approval_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// Admin decision about a device added to a location which requires device approval.
+/// Peers of devices which aren't approved are not pushed to gateways of the location.
+#[derive(Clone, Debug, Model)]
+#[table(device_approval)]
+pub struct DeviceApproval<I = NoId> {
+    pub id: I,
+    pub device_id: Id,
+    pub location_id: Id,
+    #[model(enum)]
+    pub status: DeviceApprovalStatus,
+    pub requested_at: NaiveDateTime,
+    pub decided_by: Option<Id>,
+    pub decided_at: Option<NaiveDateTime>,
+}
+
+/// Pending device approval along with details needed to decide on it.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DeviceApprovalInfo {
+    pub id: Id,
+    pub device_id: Id,
+    pub device_name: String,
+    pub wireguard_pubkey: String,
+    pub username: String,
+    pub location_id: Id,
+    pub location_name: String,
+    pub requested_at: NaiveDateTime,
+}
+
+impl DeviceApprovalInfo {
+    /// Fetches approvals which haven't been decided on yet, oldest first.
+    pub async fn all_pending<'e, E>(executor: E) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT a.id, a.device_id, d.name device_name, d.wireguard_pubkey, u.username, \
+            a.location_id, n.name location_name, a.requested_at FROM device_approval a \
+            JOIN device d ON d.id = a.device_id JOIN \"user\" u ON u.id = d.user_id \
+            JOIN wireguard_network n ON n.id = a.location_id \
+            WHERE a.status = 'pending' ORDER BY a.requested_at",
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
+
+impl DeviceApproval {
+    /// Puts a device added to a location on hold, if the location requires device approval.
+    /// Returns `true` if the device has to wait for a decision.
+    pub async fn request<'e, E>(
+        executor: E,
+        device_id: Id,
+        location_id: Id,
+    ) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let id = query_scalar!(
+            "INSERT INTO device_approval (device_id, location_id, requested_at) \
+            SELECT $1, id, $3 FROM wireguard_network WHERE id = $2 AND device_approval_required \
+            ON CONFLICT (device_id, location_id) DO NOTHING RETURNING id",
+            device_id,
+            location_id,
+            Utc::now().naive_utc()
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(id.is_some())
+    }
+
+    /// Checks if a device can be configured as a peer in a location. Devices added before
+    /// the location started requiring approval are approved implicitly.
+    pub async fn is_approved<'e, E>(
+        executor: E,
+        device_id: Id,
+        location_id: Id,
+    ) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT NOT EXISTS ( \
+                SELECT 1 FROM device_approval \
+                WHERE device_id = $1 AND location_id = $2 AND status <> 'approved' \
+            ) \"approved!\"",
+            device_id,
+            location_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Removes all decisions about devices in a location, e.g. when it stops requiring approval.
+    pub async fn delete_for_location<'e, E>(executor: E, location_id: Id) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "DELETE FROM device_approval WHERE location_id = $1",
+            location_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
+
+impl DeviceApproval<Id> {
+    /// Records decision of an administrator.
+    pub fn decide(&mut self, approved: bool, decided_by: Id) {
+        self.status = if approved {
+            DeviceApprovalStatus::Approved
+        } else {
+            DeviceApprovalStatus::Rejected
+        };
+        self.decided_at = Some(Utc::now().naive_utc());
+        self.decided_by = Some(decided_by);
+    }
+}
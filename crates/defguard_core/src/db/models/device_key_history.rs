@@ -0,0 +1,54 @@
+use chrono::{NaiveDateTime, Utc};
+use defguard_common::db::Id;
+use sqlx::{Error as SqlxError, PgExecutor, query, query_as};
+
+/// Public key used by a device before its WireGuard keypair was rotated.
+#[derive(Clone, Debug, Serialize)]
+pub struct DeviceKeyHistory {
+    pub device_id: Id,
+    pub wireguard_pubkey: String,
+    pub rotated_at: NaiveDateTime,
+}
+
+impl DeviceKeyHistory {
+    /// Key replaced right now.
+    #[must_use]
+    pub fn new(device_id: Id, wireguard_pubkey: String) -> Self {
+        Self {
+            device_id,
+            wireguard_pubkey,
+            rotated_at: Utc::now().naive_utc(),
+        }
+    }
+
+    pub async fn save<'e, E>(&self, executor: E) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "INSERT INTO device_key_history (device_id, wireguard_pubkey, rotated_at) \
+            VALUES ($1, $2, $3)",
+            self.device_id,
+            self.wireguard_pubkey,
+            self.rotated_at
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Previous keys of a device, most recently replaced first.
+    pub async fn all_for_device<'e, E>(executor: E, device_id: Id) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT device_id, wireguard_pubkey, rotated_at FROM device_key_history \
+            WHERE device_id = $1 ORDER BY rotated_at DESC",
+            device_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -0,0 +1,326 @@
+use defguard_common::db::{Id, NoId};
+use sqlx::{PgConnection, PgExecutor, query, query_as, query_scalar};
+use utoipa::ToSchema;
+
+use super::wireguard::WireguardNetwork;
+
+/// Named group of devices (e.g. "laptops", "servers") sharing configuration overrides,
+/// which take precedence over location settings when device configuration is generated.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, ToSchema)]
+pub struct DeviceProfile<I = NoId> {
+    pub id: I,
+    pub name: String,
+    pub description: Option<String>,
+    /// Overrides location DNS. Empty string disables DNS in generated configuration.
+    pub dns: Option<String>,
+    /// Overrides location keepalive interval.
+    pub keepalive_interval: Option<i32>,
+    /// Whether devices with this profile may use only `allowed_locations`. Stays set when all
+    /// allowed locations are removed, so that the profile doesn't silently allow all locations.
+    pub restrict_locations: bool,
+    /// IDs of locations which devices with this profile may use, if restricted.
+    pub allowed_locations: Vec<Id>,
+}
+
+impl DeviceProfile {
+    #[must_use]
+    pub fn new(
+        name: String,
+        description: Option<String>,
+        dns: Option<String>,
+        keepalive_interval: Option<i32>,
+        allowed_locations: Vec<Id>,
+    ) -> Self {
+        Self {
+            id: NoId,
+            name,
+            description,
+            dns,
+            keepalive_interval,
+            restrict_locations: !allowed_locations.is_empty(),
+            allowed_locations,
+        }
+    }
+
+    pub async fn save(self, conn: &mut PgConnection) -> Result<DeviceProfile<Id>, sqlx::Error> {
+        let id = query_scalar!(
+            "INSERT INTO device_profile (name, description, dns, keepalive_interval, \
+            restrict_locations) VALUES ($1, $2, $3, $4, $5) RETURNING id",
+            self.name,
+            self.description,
+            self.dns,
+            self.keepalive_interval,
+            self.restrict_locations,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        let profile = self.with_id(id);
+        profile.save_locations(conn).await?;
+
+        Ok(profile)
+    }
+
+    #[must_use]
+    pub fn with_id(self, id: Id) -> DeviceProfile<Id> {
+        DeviceProfile {
+            id,
+            name: self.name,
+            description: self.description,
+            dns: self.dns,
+            keepalive_interval: self.keepalive_interval,
+            restrict_locations: self.restrict_locations,
+            allowed_locations: self.allowed_locations,
+        }
+    }
+}
+
+impl<I> DeviceProfile<I> {
+    /// Returns `true` if devices with this profile may use given location.
+    #[must_use]
+    pub(crate) fn allows_location(&self, location_id: Id) -> bool {
+        !self.restrict_locations || self.allowed_locations.contains(&location_id)
+    }
+
+    /// Restricts devices with this profile to given locations. Empty list means all locations.
+    pub(crate) fn set_allowed_locations(&mut self, allowed_locations: Vec<Id>) {
+        self.restrict_locations = !allowed_locations.is_empty();
+        self.allowed_locations = allowed_locations;
+    }
+
+    /// Returns location settings with this profile's overrides applied.
+    #[must_use]
+    pub(crate) fn apply(&self, mut location: WireguardNetwork<Id>) -> WireguardNetwork<Id> {
+        if let Some(dns) = &self.dns {
+            location.dns = Some(dns.clone());
+        }
+        if let Some(keepalive_interval) = self.keepalive_interval {
+            location.keepalive_interval = keepalive_interval;
+        }
+        location
+    }
+}
+
+impl DeviceProfile<Id> {
+    pub async fn find_by_id<'e, E>(executor: E, id: Id) -> Result<Option<Self>, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, name, description, dns, keepalive_interval, restrict_locations, \
+            ARRAY(SELECT location_id FROM device_profile_location \
+            WHERE profile_id = p.id ORDER BY location_id) \"allowed_locations!\" \
+            FROM device_profile p WHERE id = $1",
+            id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn all<'e, E>(executor: E) -> Result<Vec<Self>, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, name, description, dns, keepalive_interval, restrict_locations, \
+            ARRAY(SELECT location_id FROM device_profile_location \
+            WHERE profile_id = p.id ORDER BY location_id) \"allowed_locations!\" \
+            FROM device_profile p ORDER BY id"
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    pub(crate) async fn find_by_name<'e, E>(
+        executor: E,
+        name: &str,
+    ) -> Result<Option<Self>, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, name, description, dns, keepalive_interval, restrict_locations, \
+            ARRAY(SELECT location_id FROM device_profile_location \
+            WHERE profile_id = p.id ORDER BY location_id) \"allowed_locations!\" \
+            FROM device_profile p WHERE name = $1",
+            name
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn save(&mut self, conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+        query!(
+            "UPDATE device_profile SET name = $2, description = $3, dns = $4, \
+            keepalive_interval = $5, restrict_locations = $6 WHERE id = $1",
+            self.id,
+            self.name,
+            self.description,
+            self.dns,
+            self.keepalive_interval,
+            self.restrict_locations,
+        )
+        .execute(&mut *conn)
+        .await?;
+        self.save_locations(conn).await
+    }
+
+    /// Replaces stored allowed locations with `allowed_locations`.
+    async fn save_locations(&self, conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+        query!(
+            "DELETE FROM device_profile_location WHERE profile_id = $1",
+            self.id
+        )
+        .execute(&mut *conn)
+        .await?;
+        query!(
+            "INSERT INTO device_profile_location (profile_id, location_id) \
+            SELECT $1, unnest($2::bigint[])",
+            self.id,
+            &self.allowed_locations
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete<'e, E>(self, executor: E) -> Result<(), sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!("DELETE FROM device_profile WHERE id = $1", self.id)
+            .execute(executor)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Fetch profile assigned to a device, if any.
+    pub(crate) async fn find_for_device<'e, E>(
+        executor: E,
+        device_id: Id,
+    ) -> Result<Option<Self>, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT p.id, p.name, p.description, p.dns, p.keepalive_interval, \
+            p.restrict_locations, \
+            ARRAY(SELECT location_id FROM device_profile_location \
+            WHERE profile_id = p.id ORDER BY location_id) \"allowed_locations!\" \
+            FROM device_profile p JOIN device_profile_device dpd ON p.id = dpd.profile_id \
+            WHERE dpd.device_id = $1",
+            device_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// IDs of devices which have this profile assigned.
+    pub(crate) async fn device_ids<'e, E>(&self, executor: E) -> Result<Vec<Id>, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "SELECT device_id FROM device_profile_device WHERE profile_id = $1 \
+            ORDER BY device_id",
+            self.id
+        )
+        .fetch_all(executor)
+        .await
+        .map(|rows| rows.into_iter().map(|row| row.device_id).collect())
+    }
+}
+
+/// Assign a profile to a device, replacing the previous one. `None` removes the assignment.
+pub(crate) async fn assign_device_profile<'e, E>(
+    executor: E,
+    device_id: Id,
+    profile_id: Option<Id>,
+) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    match profile_id {
+        Some(profile_id) => {
+            query!(
+                "INSERT INTO device_profile_device (device_id, profile_id) VALUES ($1, $2) \
+                ON CONFLICT (device_id) DO UPDATE SET profile_id = $2",
+                device_id,
+                profile_id
+            )
+            .execute(executor)
+            .await?;
+        }
+        None => {
+            query!(
+                "DELETE FROM device_profile_device WHERE device_id = $1",
+                device_id
+            )
+            .execute(executor)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies device profile to location settings used in configuration generation.
+/// Returns `None` if the profile doesn't allow given location.
+#[must_use]
+pub(crate) fn apply_device_profile(
+    profile: Option<&DeviceProfile<Id>>,
+    location: WireguardNetwork<Id>,
+) -> Option<WireguardNetwork<Id>> {
+    match profile {
+        Some(profile) if !profile.allows_location(location.id) => None,
+        Some(profile) => Some(profile.apply(location)),
+        None => Some(location),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_apply_device_profile() {
+        let location = WireguardNetwork {
+            id: 1,
+            ..Default::default()
+        };
+        let mut profile = DeviceProfile::new(
+            "servers".into(),
+            None,
+            Some("10.0.0.53".into()),
+            None,
+            Vec::new(),
+        )
+        .with_id(1);
+
+        let applied = apply_device_profile(Some(&profile), location.clone()).unwrap();
+        assert_eq!(applied.dns, Some("10.0.0.53".into()));
+        assert_eq!(applied.keepalive_interval, location.keepalive_interval);
+
+        profile.keepalive_interval = Some(5);
+        let applied = apply_device_profile(Some(&profile), location.clone()).unwrap();
+        assert_eq!(applied.keepalive_interval, 5);
+
+        profile.set_allowed_locations(vec![2]);
+        assert!(apply_device_profile(Some(&profile), location.clone()).is_none());
+
+        // profile stays restricted after its only allowed location is removed
+        profile.allowed_locations.clear();
+        assert!(apply_device_profile(Some(&profile), location.clone()).is_none());
+
+        profile.set_allowed_locations(Vec::new());
+        assert!(apply_device_profile(Some(&profile), location.clone()).is_some());
+
+        let applied = apply_device_profile(None, location.clone()).unwrap();
+        assert_eq!(applied.dns, location.dns);
+    }
+}
@@ -2,7 +2,10 @@ use chrono::{NaiveDateTime, TimeDelta, Utc};
 use defguard_common::{
     VERSION,
     config::server_config,
-    db::{Id, models::Settings},
+    db::{
+        Id,
+        models::{MailTemplateKind, MailTemplateOverride, Settings},
+    },
     random::gen_alphanumeric,
 };
 use defguard_mail::{
@@ -10,16 +13,17 @@ use defguard_mail::{
     templates::{self, TemplateError, safe_tera},
 };
 use reqwest::Url;
-use sqlx::{Error as SqlxError, PgConnection, PgExecutor, PgPool, query, query_as};
+use sqlx::{Error as SqlxError, PgConnection, PgExecutor, PgPool, query, query_as, query_scalar};
 use tera::Context;
 use thiserror::Error;
 use tokio::sync::mpsc::UnboundedSender;
 use tonic::{Code, Status};
 
-use super::User;
+use super::{User, group::Group};
 
 pub static ENROLLMENT_TOKEN_TYPE: &str = "ENROLLMENT";
 pub static PASSWORD_RESET_TOKEN_TYPE: &str = "PASSWORD_RESET";
+pub static EMAIL_VERIFICATION_TOKEN_TYPE: &str = "EMAIL_VERIFICATION";
 
 static ENROLLMENT_START_MAIL_SUBJECT: &str = "Defguard user enrollment";
 static DESKTOP_START_MAIL_SUBJECT: &str = "Defguard desktop client configuration";
@@ -79,6 +83,27 @@ impl From<TokenError> for Status {
     }
 }
 
+/// Enrollment token customization chosen by the admin.
+#[derive(Debug)]
+pub struct EnrollmentTokenOptions {
+    /// Number of enrollment sessions which can be started with the token.
+    pub max_uses: i32,
+    /// Groups the user is added to when enrollment is completed.
+    pub group_ids: Vec<Id>,
+    /// Locations the user gets access to when enrollment is completed.
+    pub location_ids: Vec<Id>,
+}
+
+impl Default for EnrollmentTokenOptions {
+    fn default() -> Self {
+        Self {
+            max_uses: 1,
+            group_ids: Vec::new(),
+            location_ids: Vec::new(),
+        }
+    }
+}
+
 // Representation of a user enrollment session
 #[derive(Clone, Debug)]
 pub struct Token {
@@ -91,6 +116,9 @@ pub struct Token {
     pub used_at: Option<NaiveDateTime>,
     pub token_type: Option<String>,
     pub device_id: Option<Id>,
+    /// Number of enrollment sessions which can be started with the token.
+    pub max_uses: i32,
+    pub use_count: i32,
 }
 
 impl Token {
@@ -113,6 +141,8 @@ impl Token {
             used_at: None,
             token_type,
             device_id: None,
+            max_uses: 1,
+            use_count: 0,
         }
     }
 
@@ -121,8 +151,9 @@ impl Token {
         E: PgExecutor<'e>,
     {
         query!(
-            "INSERT INTO token (id, user_id, admin_id, email, created_at, expires_at, used_at, token_type, device_id) \
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            "INSERT INTO token (id, user_id, admin_id, email, created_at, expires_at, used_at, \
+            token_type, device_id, max_uses, use_count) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
             self.id,
             self.user_id,
             self.admin_id,
@@ -131,7 +162,9 @@ impl Token {
             self.expires_at,
             self.used_at,
             self.token_type,
-            self.device_id
+            self.device_id,
+            self.max_uses,
+            self.use_count
         )
         .execute(executor)
         .await?;
@@ -150,6 +183,12 @@ impl Token {
         self.used_at.is_some()
     }
 
+    // check if another enrollment session can be started with the token
+    #[must_use]
+    pub fn has_remaining_uses(&self) -> bool {
+        self.use_count < self.max_uses
+    }
+
     // check if enrollment session is still valid
     // after using the token user has 10 minutes to complete enrollment
     #[must_use]
@@ -182,18 +221,23 @@ impl Token {
                 debug!("Session already exists yet it is still valid.");
                 Ok(used_at + TimeDelta::seconds(session_timeout_seconds as i64))
             }
-            // session expired
-            Some(_) => {
+            // session expired and the token can't be used again
+            Some(_) if !self.has_remaining_uses() => {
                 debug!("Session has expired.");
                 Err(TokenError::TokenUsed)
             }
-            // session not yet started
-            None => {
+            // session not yet started or a previous session expired
+            _ => {
                 let now = Utc::now().naive_utc();
-                query!("UPDATE token SET used_at = $1 WHERE id = $2", now, self.id)
-                    .execute(transaction)
-                    .await?;
+                query!(
+                    "UPDATE token SET used_at = $1, use_count = use_count + 1 WHERE id = $2",
+                    now,
+                    self.id
+                )
+                .execute(transaction)
+                .await?;
                 self.used_at = Some(now);
+                self.use_count += 1;
 
                 debug!("Generate a new session successfully.");
                 Ok(now + TimeDelta::seconds(session_timeout_seconds as i64))
@@ -204,8 +248,8 @@ impl Token {
     pub async fn find_by_id(pool: &PgPool, id: &str) -> Result<Self, TokenError> {
         if let Some(enrollment) = query_as!(
             Self,
-            "SELECT id, user_id, admin_id, email, created_at, expires_at, used_at, token_type, device_id \
-            FROM token WHERE id = $1",
+            "SELECT id, user_id, admin_id, email, created_at, expires_at, used_at, token_type, \
+            device_id, max_uses, use_count FROM token WHERE id = $1",
             id
         )
         .fetch_optional(pool)
@@ -222,14 +266,150 @@ impl Token {
     pub async fn fetch_all(pool: &PgPool) -> Result<Vec<Self>, TokenError> {
         let tokens = query_as!(
             Self,
-            "SELECT id, user_id, admin_id, email, created_at, expires_at, used_at, token_type, device_id \
-            FROM token",
+            "SELECT id, user_id, admin_id, email, created_at, expires_at, used_at, token_type, \
+            device_id, max_uses, use_count FROM token",
         )
         .fetch_all(pool)
         .await?;
         Ok(tokens)
     }
 
+    /// Fetch enrollment tokens which can still be used to start an enrollment session.
+    pub async fn fetch_active_enrollment_tokens<'e, E>(executor: E) -> Result<Vec<Self>, TokenError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let tokens = query_as!(
+            Self,
+            "SELECT id, user_id, admin_id, email, created_at, expires_at, used_at, token_type, \
+            device_id, max_uses, use_count FROM token \
+            WHERE token_type = $1 AND expires_at > NOW() AND use_count < max_uses \
+            ORDER BY created_at DESC",
+            ENROLLMENT_TOKEN_TYPE
+        )
+        .fetch_all(executor)
+        .await?;
+        Ok(tokens)
+    }
+
+    pub async fn delete<'e, E>(&self, executor: E) -> Result<(), TokenError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!("DELETE FROM token WHERE id = $1", self.id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+
+    /// Store groups and locations assigned to the user when enrollment is completed.
+    pub async fn save_assignments(
+        &self,
+        transaction: &mut PgConnection,
+        group_ids: &[Id],
+        location_ids: &[Id],
+    ) -> Result<(), TokenError> {
+        query!(
+            "INSERT INTO token_group (token_id, group_id) SELECT $1, unnest($2::bigint[])",
+            self.id,
+            group_ids
+        )
+        .execute(&mut *transaction)
+        .await?;
+        query!(
+            "INSERT INTO token_location (token_id, location_id) \
+            SELECT $1, unnest($2::bigint[])",
+            self.id,
+            location_ids
+        )
+        .execute(&mut *transaction)
+        .await?;
+        Ok(())
+    }
+
+    /// Groups the user is added to when enrollment is completed.
+    pub async fn fetch_groups<'e, E>(&self, executor: E) -> Result<Vec<Group<Id>>, TokenError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let groups = query_as!(
+            Group,
+            "SELECT id, name, is_admin FROM \"group\" \
+            JOIN token_group ON \"group\".id = token_group.group_id \
+            WHERE token_group.token_id = $1 ORDER BY name",
+            self.id
+        )
+        .fetch_all(executor)
+        .await?;
+        Ok(groups)
+    }
+
+    /// Locations the user gets access to when enrollment is completed.
+    pub async fn fetch_location_ids<'e, E>(&self, executor: E) -> Result<Vec<Id>, TokenError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let location_ids = query_scalar!(
+            "SELECT location_id FROM token_location WHERE token_id = $1 ORDER BY location_id",
+            self.id
+        )
+        .fetch_all(executor)
+        .await?;
+        Ok(location_ids)
+    }
+
+    /// Add the user to groups assigned to the token. Access to assigned locations is granted
+    /// through groups as well: if the user can't access a location yet, they're added to the
+    /// first (by name) of its allowed non-admin groups.
+    /// Returns groups the user has been added to.
+    pub async fn assign_user(
+        &self,
+        transaction: &mut PgConnection,
+        user: &User<Id>,
+    ) -> Result<Vec<Group<Id>>, TokenError> {
+        let mut groups = self.fetch_groups(&mut *transaction).await?;
+        let member_of = user.effective_group_names(&mut *transaction).await?;
+        for location_id in self.fetch_location_ids(&mut *transaction).await? {
+            let allowed_groups = query_as!(
+                Group,
+                "SELECT id, name, is_admin FROM \"group\" \
+                JOIN wireguard_network_allowed_group wag ON \"group\".id = wag.group_id \
+                WHERE wag.network_id = $1 ORDER BY name",
+                location_id
+            )
+            .fetch_all(&mut *transaction)
+            .await?;
+            // locations without allowed groups are available to all users
+            if allowed_groups.is_empty()
+                || allowed_groups.iter().any(|allowed| {
+                    member_of.contains(&allowed.name)
+                        || groups.iter().any(|group| group.id == allowed.id)
+                })
+            {
+                continue;
+            }
+            match allowed_groups.into_iter().find(|group| !group.is_admin) {
+                Some(group) => groups.push(group),
+                None => warn!(
+                    "Location {location_id} is only available to admins, user {} won't get \
+                    access to it",
+                    user.username
+                ),
+            }
+        }
+
+        for group in &groups {
+            debug!(
+                "Adding user {} to group {} assigned to enrollment token",
+                user.username, group.name
+            );
+            user.add_to_group(&mut *transaction, group).await?;
+        }
+        groups.retain(|group| !member_of.contains(&group.name));
+
+        Ok(groups)
+    }
+
     pub async fn fetch_user<'e, E>(&self, executor: E) -> Result<User<Id>, TokenError>
     where
         E: PgExecutor<'e>,
@@ -308,6 +488,42 @@ impl Token {
         Ok(())
     }
 
+    pub async fn delete_unused_user_email_verification_tokens(
+        transaction: &mut PgConnection,
+        user_id: Id,
+    ) -> Result<(), TokenError> {
+        debug!("Deleting unused email verification tokens for user {user_id}");
+        let result = query!(
+            "DELETE FROM token \
+            WHERE user_id = $1 \
+            AND token_type = 'EMAIL_VERIFICATION' \
+            AND used_at IS NULL",
+            user_id
+        )
+        .execute(transaction)
+        .await?;
+        debug!(
+            "Deleted {} unused email verification tokens for user {user_id}",
+            result.rows_affected()
+        );
+
+        Ok(())
+    }
+
+    /// Marks token as used.
+    pub async fn mark_used<'e, E>(&mut self, executor: E) -> Result<(), TokenError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let now = Utc::now().naive_utc();
+        query!("UPDATE token SET used_at = $1 WHERE id = $2", now, self.id)
+            .execute(executor)
+            .await?;
+        self.used_at = Some(now);
+
+        Ok(())
+    }
+
     /// Prepare context for rendering welcome messages
     /// Available tags include:
     /// - first_name
@@ -399,6 +615,7 @@ impl User<Id> {
         admin: &User<Id>,
         email: Option<String>,
         token_timeout_seconds: u64,
+        options: &EnrollmentTokenOptions,
         enrollment_service_url: Url,
         send_user_notification: bool,
         mail_tx: UnboundedSender<Mail>,
@@ -433,15 +650,19 @@ impl User<Id> {
             .await?;
 
         debug!("Create a new enrollment token for user {}.", self.username);
-        let enrollment = Token::new(
+        let mut enrollment = Token::new(
             self.id,
             Some(admin.id),
             email.clone(),
             token_timeout_seconds,
             Some(ENROLLMENT_TOKEN_TYPE.to_string()),
         );
+        enrollment.max_uses = options.max_uses;
         debug!("Saving a new enrollment token...");
         enrollment.save(&mut *transaction).await?;
+        enrollment
+            .save_assignments(&mut *transaction, &options.group_ids, &options.location_ids)
+            .await?;
         debug!(
             "Saved a new enrollment token with id {} for user {}.",
             enrollment.id, self.username
@@ -461,22 +682,31 @@ impl User<Id> {
                 let base_message_context = enrollment
                     .get_welcome_message_context(&mut *transaction)
                     .await?;
+                let custom = MailTemplateOverride::find_by_kind(
+                    &mut *transaction,
+                    MailTemplateKind::EnrollmentStart,
+                )
+                .await?;
+                let rendered = templates::enrollment_start_mail(
+                    base_message_context,
+                    enrollment_service_url,
+                    &enrollment.id,
+                    ENROLLMENT_START_MAIL_SUBJECT,
+                    custom.as_ref(),
+                )
+                .map_err(|err| {
+                    debug!(
+                        "Cannot send an email to the user {} due to the error {}.",
+                        self.username,
+                        err.to_string()
+                    );
+                    TokenError::NotificationError(err.to_string())
+                })?;
                 let mail = Mail {
                     to: email.clone(),
-                    subject: ENROLLMENT_START_MAIL_SUBJECT.to_string(),
-                    content: templates::enrollment_start_mail(
-                        base_message_context,
-                        enrollment_service_url,
-                        &enrollment.id,
-                    )
-                    .map_err(|err| {
-                        debug!(
-                            "Cannot send an email to the user {} due to the error {}.",
-                            self.username,
-                            err.to_string()
-                        );
-                        TokenError::NotificationError(err.to_string())
-                    })?,
+                    subject: rendered.subject,
+                    content: rendered.content,
+                    plain_content: None,
                     attachments: Vec::new(),
                     result_tx: None,
                 };
@@ -586,6 +816,7 @@ impl User<Id> {
                         );
                         TokenError::NotificationError(err.to_string())
                     })?,
+                    plain_content: None,
                     attachments: Vec::new(),
                     result_tx: None,
                 };
@@ -0,0 +1,80 @@
+use defguard_common::db::Id;
+use sqlx::{Error as SqlxError, PgExecutor, query, query_as};
+use utoipa::ToSchema;
+
+/// Alert fired when gateways of a location stay disconnected longer than `threshold_minutes`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, ToSchema)]
+pub struct GatewayAlertRule {
+    pub location_id: Id,
+    pub threshold_minutes: i32,
+    /// Trigger webhooks subscribed to gateway disconnects.
+    pub notify_webhooks: bool,
+    /// Send email to all admin users.
+    pub notify_admins: bool,
+}
+
+impl GatewayAlertRule {
+    pub async fn all<'e, E>(executor: E) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT location_id, threshold_minutes, notify_webhooks, notify_admins \
+            FROM gateway_alert_rule ORDER BY location_id"
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    pub async fn find_by_location<'e, E>(
+        executor: E,
+        location_id: Id,
+    ) -> Result<Option<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT location_id, threshold_minutes, notify_webhooks, notify_admins \
+            FROM gateway_alert_rule WHERE location_id = $1",
+            location_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Store the rule, replacing the current one for the location.
+    pub async fn save<'e, E>(&self, executor: E) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "INSERT INTO gateway_alert_rule \
+            (location_id, threshold_minutes, notify_webhooks, notify_admins) \
+            VALUES ($1, $2, $3, $4) \
+            ON CONFLICT (location_id) DO UPDATE SET threshold_minutes = $2, \
+            notify_webhooks = $3, notify_admins = $4",
+            self.location_id,
+            self.threshold_minutes,
+            self.notify_webhooks,
+            self.notify_admins
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_for_location<'e, E>(executor: E, location_id: Id) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "DELETE FROM gateway_alert_rule WHERE location_id = $1",
+            location_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt;
+
+use chrono::{NaiveDateTime, Utc};
+use defguard_common::{
+    auth::claims::{Claims, ClaimsType},
+    db::{Id, NoId},
+};
+use jsonwebtoken::errors::Error as JWTError;
+use model_derive::Model;
+use sqlx::{Error as SqlxError, PgExecutor, query, query_as};
+use utoipa::ToSchema;
+
+/// Gateway auth token issued for a location. Each location can have multiple tokens, so that
+/// redundant gateways can be run and tokens rotated without downtime.
+///
+/// Only the token metadata is stored; the token itself is a JWT which identifies this record
+/// and is shown once, when the token is issued.
+#[derive(Clone, Debug, Deserialize, Model, PartialEq, Serialize, ToSchema)]
+#[table(gateway_token)]
+pub struct GatewayToken<I = NoId> {
+    pub id: I,
+    pub location_id: Id,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    /// Last time a gateway fetched configuration using this token.
+    pub last_connected_at: Option<NaiveDateTime>,
+    /// Hostname of the last gateway which used this token.
+    pub last_hostname: Option<String>,
+}
+
+impl<I> fmt::Display for GatewayToken<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl GatewayToken {
+    #[must_use]
+    pub fn new(location_id: Id, name: String) -> Self {
+        Self {
+            id: NoId,
+            location_id,
+            name,
+            created_at: Utc::now().naive_utc(),
+            last_connected_at: None,
+            last_hostname: None,
+        }
+    }
+}
+
+impl GatewayToken<Id> {
+    pub async fn all_for_location<'e, E>(
+        executor: E,
+        location_id: Id,
+    ) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, location_id, name, created_at, last_connected_at, last_hostname \
+            FROM gateway_token WHERE location_id = $1 ORDER BY id",
+            location_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    pub async fn find_for_location<'e, E>(
+        executor: E,
+        location_id: Id,
+        id: Id,
+    ) -> Result<Option<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, location_id, name, created_at, last_connected_at, last_hostname \
+            FROM gateway_token WHERE location_id = $1 AND id = $2",
+            location_id,
+            id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Remember which gateway used the token.
+    pub(crate) async fn record_connection<'e, E>(
+        &mut self,
+        executor: E,
+        hostname: &str,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let now = Utc::now().naive_utc();
+        query!(
+            "UPDATE gateway_token SET last_connected_at = $2, last_hostname = $3 WHERE id = $1",
+            self.id,
+            now,
+            hostname
+        )
+        .execute(executor)
+        .await?;
+        self.last_connected_at = Some(now);
+        self.last_hostname = Some(hostname.into());
+        Ok(())
+    }
+
+    /// Generate JWT used by gateways to authenticate. Unlike tokens generated by
+    /// `WireguardNetwork::generate_gateway_token()`, it carries the token ID, so it stops working
+    /// once the token is revoked.
+    pub fn to_jwt(&self) -> Result<String, JWTError> {
+        let location_id = self.location_id;
+        let mut claims = Claims::new(
+            ClaimsType::Gateway,
+            format!("DEFGUARD-NETWORK-{location_id}"),
+            location_id.to_string(),
+            u32::MAX.into(),
+        );
+        claims.jti = Some(self.id.to_string());
+        claims.to_jwt()
+    }
+}
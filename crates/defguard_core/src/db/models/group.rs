@@ -1,12 +1,82 @@
-use std::fmt;
+use std::{collections::HashMap, fmt};
 
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
 use defguard_common::db::{Id, NoId, models::ModelError};
+use ipnetwork::IpNetwork;
 use model_derive::Model;
-use sqlx::{Error as SqlxError, FromRow, PgConnection, PgExecutor, query, query_as, query_scalar};
+use sqlx::{
+    Error as SqlxError, FromRow, PgConnection, PgExecutor, Type, query, query_as, query_scalar,
+};
+use thiserror::Error;
 use utoipa::ToSchema;
 
 use crate::db::{User, WireguardNetwork};
 
+/// Address sub-pool, routes, DNS and firewall tag of an allowed group of a network.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, ToSchema)]
+pub struct NetworkGroupPool {
+    pub group: String,
+    /// Subnets of network addresses which devices of group members get their addresses from.
+    #[schema(value_type = Vec<String>)]
+    pub address_pool: Vec<IpNetwork>,
+    /// Routed subnets (AllowedIPs) which devices of group members get in their configuration
+    /// instead of network allowed IPs. Empty list keeps network allowed IPs.
+    #[serde(default)]
+    #[schema(value_type = Vec<String>)]
+    pub allowed_ips: Vec<IpNetwork>,
+    /// DNS servers which devices of group members get instead of network DNS.
+    #[serde(default)]
+    pub dns: Option<String>,
+    /// DNS search domains which devices of group members get instead of network search domains.
+    #[serde(default)]
+    pub dns_search_domains: Vec<String>,
+    pub firewall_tag: Option<String>,
+    /// If a user belongs to multiple allowed groups, settings of the group with the highest
+    /// priority are used.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// Weekly time window in which members of an allowed group may connect to a network.
+/// Times are in the timezone of the connecting user, or the deployment timezone if the user didn't
+/// choose one. Windows ending before they start continue past midnight into the next day.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, ToSchema)]
+pub struct NetworkAccessWindow {
+    pub group: String,
+    /// ISO weekday numbers the window starts on, 1 is Monday.
+    pub weekdays: Vec<i32>,
+    #[schema(value_type = String)]
+    pub start_time: NaiveTime,
+    #[schema(value_type = String)]
+    pub end_time: NaiveTime,
+}
+
+impl NetworkAccessWindow {
+    /// Current time in given timezone, see [`User::effective_timezone`].
+    #[must_use]
+    pub fn local_now(timezone: Tz) -> NaiveDateTime {
+        Utc::now().with_timezone(&timezone).naive_local()
+    }
+
+    /// Checks whether the window covers given local time.
+    #[must_use]
+    pub fn contains(&self, time: NaiveDateTime) -> bool {
+        let weekday = time.weekday();
+        let starts_on = |weekday: Weekday| {
+            self.weekdays
+                .contains(&(weekday.number_from_monday() as i32))
+        };
+        let time = time.time();
+        if self.start_time < self.end_time {
+            starts_on(weekday) && time >= self.start_time && time < self.end_time
+        } else {
+            (starts_on(weekday) && time >= self.start_time)
+                || (starts_on(weekday.pred()) && time < self.end_time)
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Permission {
     IsAdmin,
@@ -38,6 +108,195 @@ impl Default for Group {
     }
 }
 
+/// Decides how differences in group membership between Defguard and LDAP are resolved.
+/// Groups without an explicit authority follow the global LDAP authority setting.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize, Type, ToSchema)]
+#[sqlx(type_name = "group_sync_authority", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum GroupSyncAuthority {
+    /// Defguard membership overwrites LDAP.
+    Local,
+    /// LDAP membership overwrites Defguard, local membership changes are rejected.
+    Ldap,
+    /// Members are added on both sides, nothing is removed during sync.
+    Merge,
+}
+
+/// Fetches names of groups with an explicit sync authority.
+pub(crate) async fn group_sync_authorities<'e, E>(
+    executor: E,
+) -> Result<HashMap<String, GroupSyncAuthority>, SqlxError>
+where
+    E: PgExecutor<'e>,
+{
+    let groups = query!(
+        "SELECT name, sync_authority \"sync_authority!: GroupSyncAuthority\" FROM \"group\" \
+        WHERE sync_authority IS NOT NULL"
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(groups
+        .into_iter()
+        .map(|group| (group.name, group.sync_authority))
+        .collect())
+}
+
+/// Group membership removed after reaching its expiry time.
+#[derive(Debug)]
+pub(crate) struct ExpiredMembership {
+    pub group_id: Id,
+    pub user_id: Id,
+    pub expires_at: NaiveDateTime,
+}
+
+/// Removes group memberships which have expired and returns them.
+pub(crate) async fn remove_expired_memberships<'e, E>(
+    executor: E,
+) -> Result<Vec<ExpiredMembership>, SqlxError>
+where
+    E: PgExecutor<'e>,
+{
+    query_as!(
+        ExpiredMembership,
+        "DELETE FROM group_user WHERE expires_at <= NOW() \
+        RETURNING group_id \"group_id!\", user_id \"user_id!\", expires_at \"expires_at!\""
+    )
+    .fetch_all(executor)
+    .await
+}
+
+#[derive(Debug, Error)]
+pub enum GroupQuotaError {
+    #[error("Group {0} has reached its limit of {1} users")]
+    UsersExceeded(String, i32),
+    #[error("Members of group {0} have reached their limit of {1} devices")]
+    DevicesExceeded(String, i32),
+    #[error(transparent)]
+    DbError(#[from] SqlxError),
+}
+
+/// Optional limits for the number of group members and the number of their devices.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize, ToSchema)]
+pub struct GroupQuota {
+    pub max_users: Option<i32>,
+    pub max_devices: Option<i32>,
+    /// Number of devices each member can have connected to VPN at the same time.
+    #[serde(default)]
+    pub max_sessions: Option<i32>,
+}
+
+/// Group quota along with its current utilization.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct GroupQuotaUsage {
+    pub name: String,
+    pub max_users: Option<i32>,
+    pub users: i64,
+    pub max_devices: Option<i32>,
+    /// Total number of user devices owned by group members.
+    pub devices: i64,
+    pub max_sessions: Option<i32>,
+}
+
+impl GroupQuotaUsage {
+    pub(crate) async fn all<'e, E>(executor: E) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT g.name, g.max_users, g.max_devices, \
+            (SELECT COUNT(*) FROM group_user gu WHERE gu.group_id = g.id) \"users!\", \
+            (SELECT COUNT(*) FROM group_user gu JOIN device d ON d.user_id = gu.user_id \
+                WHERE gu.group_id = g.id AND d.device_type = 'user') \"devices!\", \
+            g.max_sessions \
+            FROM \"group\" g ORDER BY g.name"
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Fetches quota utilization of all groups the user belongs to.
+    pub(crate) async fn all_for_user<'e, E>(
+        executor: E,
+        user_id: Id,
+    ) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT g.name, g.max_users, g.max_devices, \
+            (SELECT COUNT(*) FROM group_user gu WHERE gu.group_id = g.id) \"users!\", \
+            (SELECT COUNT(*) FROM group_user gu JOIN device d ON d.user_id = gu.user_id \
+                WHERE gu.group_id = g.id AND d.device_type = 'user') \"devices!\", \
+            g.max_sessions \
+            FROM \"group\" g JOIN group_user m ON m.group_id = g.id \
+            WHERE m.user_id = $1 ORDER BY g.name",
+            user_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Verify that current utilization fits within the quota.
+    fn check(&self) -> Result<(), GroupQuotaError> {
+        if let Some(limit) = self
+            .max_users
+            .filter(|limit| self.users > i64::from(*limit))
+        {
+            return Err(GroupQuotaError::UsersExceeded(self.name.clone(), limit));
+        }
+        if let Some(limit) = self
+            .max_devices
+            .filter(|limit| self.devices > i64::from(*limit))
+        {
+            return Err(GroupQuotaError::DevicesExceeded(self.name.clone(), limit));
+        }
+        Ok(())
+    }
+}
+
+/// Check if a user can add another device without exceeding quotas of any of their groups.
+/// Meant to be called inside a transaction, right before the device is saved. Groups with a device
+/// limit stay locked until the transaction ends, so concurrent additions can't exceed the limit.
+pub(crate) async fn check_device_quota(
+    conn: &mut PgConnection,
+    user_id: Id,
+) -> Result<(), GroupQuotaError> {
+    query!(
+        "SELECT g.id FROM \"group\" g JOIN group_user gu ON gu.group_id = g.id \
+        WHERE gu.user_id = $1 AND g.max_devices IS NOT NULL ORDER BY g.id FOR UPDATE OF g",
+        user_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    for usage in GroupQuotaUsage::all_for_user(&mut *conn, user_id).await? {
+        if let Some(limit) = usage
+            .max_devices
+            .filter(|limit| usage.devices >= i64::from(*limit))
+        {
+            return Err(GroupQuotaError::DevicesExceeded(usage.name, limit));
+        }
+    }
+    Ok(())
+}
+
+/// Number of devices the user can have connected to VPN at the same time, which is the lowest
+/// session limit of their groups, or `None` if none of them has one.
+pub(crate) async fn session_limit<'e, E>(executor: E, user_id: Id) -> Result<Option<i32>, SqlxError>
+where
+    E: PgExecutor<'e>,
+{
+    query_scalar!(
+        "SELECT MIN(g.max_sessions) FROM \"group\" g \
+        JOIN group_user gu ON gu.group_id = g.id WHERE gu.user_id = $1",
+        user_id
+    )
+    .fetch_one(executor)
+    .await
+}
+
 impl Group {
     #[must_use]
     pub fn new<S: Into<String>>(name: S) -> Self {
@@ -140,6 +399,234 @@ impl Group<Id> {
         Ok(result.unwrap_or(false))
     }
 
+    pub(crate) async fn set_quota<'e, E>(
+        &self,
+        executor: E,
+        quota: &GroupQuota,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "UPDATE \"group\" SET max_users = $2, max_devices = $3, max_sessions = $4 \
+            WHERE id = $1",
+            self.id,
+            quota.max_users,
+            quota.max_devices,
+            quota.max_sessions
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn sync_authority<'e, E>(
+        &self,
+        executor: E,
+    ) -> Result<Option<GroupSyncAuthority>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT sync_authority \"sync_authority: GroupSyncAuthority\" FROM \"group\" \
+            WHERE id = $1",
+            self.id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub(crate) async fn set_sync_authority<'e, E>(
+        &self,
+        executor: E,
+        sync_authority: Option<GroupSyncAuthority>,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "UPDATE \"group\" SET sync_authority = $2 WHERE id = $1",
+            self.id,
+            &sync_authority as &Option<GroupSyncAuthority>
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Fetches parent group, which this group inherits location access and permissions from.
+    pub(crate) async fn parent<'e, E>(&self, executor: E) -> Result<Option<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT p.id, p.name, p.is_admin FROM \"group\" g \
+            JOIN \"group\" p ON p.id = g.parent_id WHERE g.id = $1",
+            self.id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub(crate) async fn set_parent<'e, E>(
+        &self,
+        executor: E,
+        parent_id: Option<Id>,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "UPDATE \"group\" SET parent_id = $2 WHERE id = $1",
+            self.id,
+            parent_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Whether users can ask to join this group by themselves.
+    pub(crate) async fn self_service<'e, E>(&self, executor: E) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!("SELECT self_service FROM \"group\" WHERE id = $1", self.id)
+            .fetch_one(executor)
+            .await
+    }
+
+    pub(crate) async fn set_self_service<'e, E>(
+        &self,
+        executor: E,
+        self_service: bool,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "UPDATE \"group\" SET self_service = $2 WHERE id = $1",
+            self.id,
+            self_service
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Fetches users deciding on requests to join this group.
+    pub(crate) async fn owners<'e, E>(&self, executor: E) -> Result<Vec<User<Id>>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            User,
+            "SELECT \"user\".id, username, password_hash, last_name, first_name, email, \
+            phone, mfa_enabled, totp_enabled, totp_secret, email_mfa_enabled, email_mfa_secret, \
+            mfa_method \"mfa_method: _\", recovery_codes, is_active, openid_sub, \
+            from_ldap, ldap_pass_randomized, ldap_rdn, ldap_user_path, enrollment_pending \
+            FROM \"user\" \
+            JOIN group_owner ON \"user\".id = group_owner.user_id \
+            WHERE group_owner.group_id = $1",
+            self.id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Replaces owners of this group.
+    pub(crate) async fn set_owners(
+        &self,
+        transaction: &mut PgConnection,
+        user_ids: &[Id],
+    ) -> Result<(), SqlxError> {
+        query!("DELETE FROM group_owner WHERE group_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await?;
+        query!(
+            "INSERT INTO group_owner (group_id, user_id) SELECT $1, unnest($2::bigint[])",
+            self.id,
+            user_ids
+        )
+        .execute(&mut *transaction)
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn is_owner<'e, E>(&self, executor: E, user_id: Id) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT EXISTS (SELECT 1 FROM group_owner WHERE group_id = $1 AND user_id = $2) \"exists!\"",
+            self.id,
+            user_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Fetches IDs of all groups nested below this group, at any depth.
+    pub(crate) async fn descendant_ids<'e, E>(&self, executor: E) -> Result<Vec<Id>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "WITH RECURSIVE descendants(id) AS ( \
+                SELECT id FROM \"group\" WHERE parent_id = $1 \
+                UNION \
+                SELECT g.id FROM \"group\" g JOIN descendants d ON g.parent_id = d.id \
+            ) SELECT id \"id!\" FROM descendants",
+            self.id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Whether members of this group are admins, either directly or through an ancestor group.
+    pub(crate) async fn grants_admin<'e, E>(&self, executor: E) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "WITH RECURSIVE ancestors(id, parent_id, is_admin) AS ( \
+                SELECT id, parent_id, is_admin FROM \"group\" WHERE id = $1 \
+                UNION \
+                SELECT g.id, g.parent_id, g.is_admin FROM \"group\" g \
+                JOIN ancestors a ON g.id = a.parent_id \
+            ) SELECT EXISTS (SELECT 1 FROM ancestors WHERE is_admin) \"exists!\"",
+            self.id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Verify that current group members and their devices fit within the group quota.
+    /// Meant to be called inside a transaction, after members have been added. The group row stays
+    /// locked until the transaction ends, so that concurrent additions are counted one after
+    /// another.
+    pub(crate) async fn check_quota(&self, conn: &mut PgConnection) -> Result<(), GroupQuotaError> {
+        // utilization is counted in a separate statement, which sees changes committed while
+        // waiting for the lock
+        query!("SELECT id FROM \"group\" WHERE id = $1 FOR UPDATE", self.id)
+            .fetch_one(&mut *conn)
+            .await?;
+        let usage = query_as!(
+            GroupQuotaUsage,
+            "SELECT g.name, g.max_users, g.max_devices, \
+            (SELECT COUNT(*) FROM group_user gu WHERE gu.group_id = g.id) \"users!\", \
+            (SELECT COUNT(*) FROM group_user gu JOIN device d ON d.user_id = gu.user_id \
+                WHERE gu.group_id = g.id AND d.device_type = 'user') \"devices!\", \
+            g.max_sessions \
+            FROM \"group\" g WHERE g.id = $1",
+            self.id
+        )
+        .fetch_one(conn)
+        .await?;
+        usage.check()
+    }
+
     pub(crate) async fn set_permission<'e, E>(
         &self,
         executor: E,
@@ -278,6 +765,155 @@ impl WireguardNetwork<Id> {
         Ok(())
     }
 
+    /// Fetch address pools, routes, DNS and firewall tags configured for allowed groups of a given
+    /// network.
+    pub async fn fetch_group_pools<'e, E>(
+        &self,
+        executor: E,
+    ) -> Result<Vec<NetworkGroupPool>, ModelError>
+    where
+        E: PgExecutor<'e>,
+    {
+        debug!("Fetching allowed group pools for network {self}");
+        let pools = query_as!(
+            NetworkGroupPool,
+            "SELECT g.name \"group\", wag.address_pool, wag.allowed_ips, wag.dns, \
+            wag.dns_search_domains, wag.firewall_tag, wag.priority \
+            FROM wireguard_network_allowed_group wag JOIN \"group\" g ON wag.group_id = g.id \
+            WHERE wag.network_id = $1 \
+            AND (cardinality(wag.address_pool) > 0 OR cardinality(wag.allowed_ips) > 0 \
+                OR wag.dns IS NOT NULL OR cardinality(wag.dns_search_domains) > 0 \
+                OR wag.firewall_tag IS NOT NULL) \
+            ORDER BY wag.priority DESC, g.name",
+            self.id
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(pools)
+    }
+
+    /// Set address pools, routes, DNS and firewall tags of allowed groups. Groups must already be
+    /// allowed in the network, pools of groups not present in `pools` are cleared.
+    pub async fn set_group_pools(
+        &self,
+        transaction: &mut PgConnection,
+        pools: &[NetworkGroupPool],
+    ) -> Result<(), ModelError> {
+        info!("Setting allowed group pools for network {self} to: {pools:?}");
+        query!(
+            "UPDATE wireguard_network_allowed_group \
+            SET address_pool = '{}', allowed_ips = '{}', dns = NULL, dns_search_domains = '{}', \
+            firewall_tag = NULL, priority = 0 WHERE network_id = $1",
+            self.id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        for pool in pools {
+            query!(
+                "UPDATE wireguard_network_allowed_group \
+                SET address_pool = $3, allowed_ips = $4, dns = $5, dns_search_domains = $6, \
+                firewall_tag = $7, priority = $8 \
+                WHERE network_id = $1 AND group_id = (SELECT id FROM \"group\" WHERE name = $2)",
+                self.id,
+                pool.group,
+                &pool.address_pool,
+                &pool.allowed_ips,
+                pool.dns,
+                &pool.dns_search_domains,
+                pool.firewall_tag,
+                pool.priority
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fetch access windows configured for allowed groups of a given network.
+    pub async fn fetch_access_windows<'e, E>(
+        &self,
+        executor: E,
+    ) -> Result<Vec<NetworkAccessWindow>, ModelError>
+    where
+        E: PgExecutor<'e>,
+    {
+        debug!("Fetching access windows for network {self}");
+        let windows = query_as!(
+            NetworkAccessWindow,
+            "SELECT g.name \"group\", w.weekdays, w.start_time, w.end_time \
+            FROM wireguard_network_access_window w JOIN \"group\" g ON w.group_id = g.id \
+            WHERE w.network_id = $1 ORDER BY g.name, w.id",
+            self.id
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(windows)
+    }
+
+    /// Set access windows of allowed groups, replacing the current ones. Groups must already be
+    /// allowed in the network.
+    pub async fn set_access_windows(
+        &self,
+        transaction: &mut PgConnection,
+        windows: &[NetworkAccessWindow],
+    ) -> Result<(), ModelError> {
+        info!("Setting access windows for network {self} to: {windows:?}");
+        query!(
+            "DELETE FROM wireguard_network_access_window WHERE network_id = $1",
+            self.id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        for window in windows {
+            query!(
+                "INSERT INTO wireguard_network_access_window \
+                (network_id, group_id, weekdays, start_time, end_time) \
+                SELECT $1, id, $3, $4, $5 FROM \"group\" WHERE name = $2",
+                self.id,
+                window.group,
+                &window.weekdays,
+                window.start_time,
+                window.end_time
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether the user may connect to the network at given local time. Access is granted
+    /// if any of the user's allowed groups has no access windows or has a window covering `time`.
+    pub async fn is_within_access_window(
+        &self,
+        conn: &mut PgConnection,
+        user: &User<Id>,
+        time: NaiveDateTime,
+    ) -> Result<bool, ModelError> {
+        let windows = self.fetch_access_windows(&mut *conn).await?;
+        if windows.is_empty() {
+            return Ok(true);
+        }
+        let Some(allowed_groups) = self.get_allowed_groups(&mut *conn).await? else {
+            return Ok(true);
+        };
+        let user_groups = user.effective_group_names(&mut *conn).await?;
+
+        Ok(allowed_groups
+            .iter()
+            .filter(|group| user_groups.contains(group))
+            .any(|group| {
+                let mut group_windows = windows
+                    .iter()
+                    .filter(|window| &window.group == group)
+                    .peekable();
+                group_windows.peek().is_none() || group_windows.any(|window| window.contains(time))
+            }))
+    }
+
     /// Remove all allowed groups for a given network
     async fn clear_allowed_groups(&self, transaction: &mut PgConnection) -> Result<(), ModelError> {
         info!("Removing all allowed groups for network {self}");
@@ -297,6 +933,7 @@ impl WireguardNetwork<Id> {
 
 #[cfg(test)]
 mod test {
+    use chrono::{TimeDelta, Utc};
     use defguard_common::db::setup_pool;
     use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 
@@ -350,6 +987,55 @@ mod test {
         assert!(members.is_empty());
     }
 
+    #[sqlx::test]
+    async fn test_expired_group_members(_: PgPoolOptions, options: PgConnectOptions) {
+        let pool = setup_pool(options).await;
+
+        let group = Group::new("contractors").save(&pool).await.unwrap();
+        let user = User::new(
+            "hpotter",
+            Some("pass123"),
+            "Potter",
+            "Harry",
+            "h.potter@hogwart.edu.uk",
+            None,
+        )
+        .save(&pool)
+        .await
+        .unwrap();
+        let other_user = User::new(
+            "rweasley",
+            Some("pass123"),
+            "Weasley",
+            "Ron",
+            "r.weasley@hogwart.edu.uk",
+            None,
+        )
+        .save(&pool)
+        .await
+        .unwrap();
+        let now = Utc::now().naive_utc();
+        user.add_to_group_until(&pool, &group, Some(now + TimeDelta::hours(1)))
+            .await
+            .unwrap();
+        other_user.add_to_group(&pool, &group).await.unwrap();
+
+        assert!(remove_expired_memberships(&pool).await.unwrap().is_empty());
+        assert_eq!(group.member_usernames(&pool).await.unwrap().len(), 2);
+
+        // re-adding the member updates the expiry
+        user.add_to_group_until(&pool, &group, Some(now - TimeDelta::minutes(1)))
+            .await
+            .unwrap();
+        let expired = remove_expired_memberships(&pool).await.unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].group_id, group.id);
+        assert_eq!(expired[0].user_id, user.id);
+
+        let members = group.member_usernames(&pool).await.unwrap();
+        assert_eq!(members, vec![other_user.username]);
+    }
+
     #[sqlx::test]
     async fn test_group_permissions(_: PgPoolOptions, options: PgConnectOptions) {
         let pool = setup_pool(options).await;
@@ -392,4 +1078,206 @@ mod test {
         assert_eq!(groups.len(), 2);
         assert!(groups.iter().any(|g| g.name == "admin2"));
     }
+
+    #[test]
+    fn test_access_window_contains() {
+        // 2025-06-02 is a Monday
+        let at = |day: u32, hour: u32| {
+            chrono::NaiveDate::from_ymd_opt(2025, 6, day)
+                .unwrap()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap()
+        };
+        let business_hours = NetworkAccessWindow {
+            group: "vendors".into(),
+            weekdays: vec![1, 2, 3, 4, 5],
+            start_time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        };
+        assert!(business_hours.contains(at(2, 9)));
+        assert!(business_hours.contains(at(6, 16)));
+        assert!(!business_hours.contains(at(2, 17)));
+        assert!(!business_hours.contains(at(2, 8)));
+        assert!(!business_hours.contains(at(7, 12)));
+
+        // overnight window continues on the next day
+        let night_shift = NetworkAccessWindow {
+            weekdays: vec![5],
+            start_time: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            end_time: NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
+            ..business_hours
+        };
+        assert!(night_shift.contains(at(6, 23)));
+        assert!(night_shift.contains(at(7, 5)));
+        assert!(!night_shift.contains(at(7, 6)));
+        assert!(!night_shift.contains(at(6, 5)));
+        assert!(!night_shift.contains(at(7, 23)));
+    }
+
+    #[sqlx::test]
+    async fn test_is_within_access_window(_: PgPoolOptions, options: PgConnectOptions) {
+        let pool = setup_pool(options).await;
+        let mut network = WireguardNetwork::default();
+        network.try_set_address("10.1.1.1/24").unwrap();
+        let network = network.save(&pool).await.unwrap();
+        let vendors = Group::new("vendors").save(&pool).await.unwrap();
+        let employees = Group::new("employees").save(&pool).await.unwrap();
+        let vendor = User::new("vendor", None, "Tester", "Test", "vendor@test.com", None)
+            .save(&pool)
+            .await
+            .unwrap();
+        vendor.add_to_group(&pool, &vendors).await.unwrap();
+        let employee = User::new(
+            "employee",
+            None,
+            "Tester",
+            "Test",
+            "employee@test.com",
+            None,
+        )
+        .save(&pool)
+        .await
+        .unwrap();
+        employee.add_to_group(&pool, &vendors).await.unwrap();
+        employee.add_to_group(&pool, &employees).await.unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        network
+            .set_allowed_groups(
+                &mut conn,
+                vec![vendors.name.clone(), employees.name.clone()],
+            )
+            .await
+            .unwrap();
+        // 2025-06-02 is a Monday
+        let monday = chrono::NaiveDate::from_ymd_opt(2025, 6, 2)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        let saturday = monday + TimeDelta::days(5);
+        assert!(
+            network
+                .is_within_access_window(&mut conn, &vendor, saturday)
+                .await
+                .unwrap()
+        );
+
+        network
+            .set_access_windows(
+                &mut conn,
+                &[NetworkAccessWindow {
+                    group: vendors.name.clone(),
+                    weekdays: vec![1, 2, 3, 4, 5],
+                    start_time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                    end_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+                }],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            network
+                .fetch_access_windows(&mut *conn)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(
+            network
+                .is_within_access_window(&mut conn, &vendor, monday)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !network
+                .is_within_access_window(&mut conn, &vendor, saturday)
+                .await
+                .unwrap()
+        );
+        // groups without windows are not restricted
+        assert!(
+            network
+                .is_within_access_window(&mut conn, &employee, saturday)
+                .await
+                .unwrap()
+        );
+
+        // windows are removed along with the allowed group
+        network
+            .set_allowed_groups(&mut conn, vec![employees.name.clone()])
+            .await
+            .unwrap();
+        assert!(
+            network
+                .fetch_access_windows(&mut *conn)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_concurrent_quota_check(_: PgPoolOptions, options: PgConnectOptions) {
+        let pool = setup_pool(options).await;
+
+        let group = Group::new("limited").save(&pool).await.unwrap();
+        group
+            .set_quota(
+                &pool,
+                &GroupQuota {
+                    max_users: Some(1),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        let user = User::new(
+            "hpotter",
+            Some("pass123"),
+            "Potter",
+            "Harry",
+            "h.potter@hogwart.edu.uk",
+            None,
+        )
+        .save(&pool)
+        .await
+        .unwrap();
+        let other_user = User::new(
+            "rweasley",
+            Some("pass123"),
+            "Weasley",
+            "Ron",
+            "r.weasley@hogwart.edu.uk",
+            None,
+        )
+        .save(&pool)
+        .await
+        .unwrap();
+
+        let mut transaction = pool.begin().await.unwrap();
+        user.add_to_group(&mut *transaction, &group).await.unwrap();
+        group.check_quota(&mut transaction).await.unwrap();
+
+        // the other addition waits for the first one and counts its member
+        let other = {
+            let pool = pool.clone();
+            let group = group.clone();
+            tokio::spawn(async move {
+                let mut transaction = pool.begin().await.unwrap();
+                other_user
+                    .add_to_group(&mut *transaction, &group)
+                    .await
+                    .unwrap();
+                group.check_quota(&mut transaction).await
+            })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        transaction.commit().await.unwrap();
+
+        assert!(matches!(
+            other.await.unwrap(),
+            Err(GroupQuotaError::UsersExceeded(_, 1))
+        ));
+        assert_eq!(group.member_usernames(&pool).await.unwrap().len(), 1);
+    }
 }
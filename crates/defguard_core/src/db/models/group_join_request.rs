@@ -0,0 +1,145 @@
+use chrono::{NaiveDateTime, Utc};
+use defguard_common::db::{Id, NoId};
+use model_derive::Model;
+use sqlx::{Error as SqlxError, PgExecutor, Type, query_as};
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize, ToSchema, Type)]
+#[sqlx(type_name = "group_join_request_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum GroupJoinRequestStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// Request of a user to become a member of a self-service group.
+#[derive(Clone, Debug, Model)]
+#[table(group_join_request)]
+pub struct GroupJoinRequest<I = NoId> {
+    pub id: I,
+    pub group_id: Id,
+    pub user_id: Id,
+    #[model(enum)]
+    pub status: GroupJoinRequestStatus,
+    pub comment: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub decided_at: Option<NaiveDateTime>,
+    pub decided_by: Option<Id>,
+}
+
+/// Pending join request along with details needed to decide on it.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct GroupJoinRequestInfo {
+    pub id: Id,
+    pub group: String,
+    pub username: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub comment: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Self-service group which a user can ask to join.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct SelfServiceGroup {
+    pub name: String,
+    /// Whether the user is already waiting for a decision.
+    pub pending: bool,
+}
+
+impl SelfServiceGroup {
+    /// Fetches self-service groups which given user isn't a member of.
+    pub async fn all_for_user<'e, E>(executor: E, user_id: Id) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT g.name, EXISTS ( \
+                SELECT 1 FROM group_join_request r \
+                WHERE r.group_id = g.id AND r.user_id = $1 AND r.status = 'pending' \
+            ) \"pending!\" FROM \"group\" g \
+            WHERE g.self_service AND NOT EXISTS ( \
+                SELECT 1 FROM group_user gu WHERE gu.group_id = g.id AND gu.user_id = $1 \
+            ) ORDER BY g.name",
+            user_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
+
+impl GroupJoinRequest {
+    #[must_use]
+    pub fn new(group_id: Id, user_id: Id, comment: Option<String>) -> Self {
+        Self {
+            id: NoId,
+            group_id,
+            user_id,
+            status: GroupJoinRequestStatus::Pending,
+            comment,
+            created_at: Utc::now().naive_utc(),
+            decided_at: None,
+            decided_by: None,
+        }
+    }
+}
+
+impl GroupJoinRequest<Id> {
+    /// Fetches request of a user to join a group which hasn't been decided on yet.
+    pub async fn find_pending<'e, E>(
+        executor: E,
+        group_id: Id,
+        user_id: Id,
+    ) -> Result<Option<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, group_id, user_id, status \"status: _\", comment, created_at, \
+            decided_at, decided_by FROM group_join_request \
+            WHERE group_id = $1 AND user_id = $2 AND status = 'pending'",
+            group_id,
+            user_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Records decision of an administrator or a group owner.
+    pub fn decide(&mut self, approved: bool, decided_by: Id) {
+        self.status = if approved {
+            GroupJoinRequestStatus::Approved
+        } else {
+            GroupJoinRequestStatus::Rejected
+        };
+        self.decided_at = Some(Utc::now().naive_utc());
+        self.decided_by = Some(decided_by);
+    }
+}
+
+impl GroupJoinRequestInfo {
+    /// Fetches pending requests, limited to groups owned by `owner_id` if given.
+    pub async fn all_pending<'e, E>(
+        executor: E,
+        owner_id: Option<Id>,
+    ) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT r.id, g.name \"group\", u.username, u.first_name, u.last_name, r.comment, \
+            r.created_at FROM group_join_request r \
+            JOIN \"group\" g ON g.id = r.group_id JOIN \"user\" u ON u.id = r.user_id \
+            WHERE r.status = 'pending' AND ($1::bigint IS NULL OR EXISTS ( \
+                SELECT 1 FROM group_owner o WHERE o.group_id = r.group_id AND o.user_id = $1 \
+            )) ORDER BY r.created_at",
+            owner_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt;
+
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use defguard_common::db::Id;
+use sqlx::{Error as SqlxError, PgExecutor, Type, query, query_as};
+use utoipa::ToSchema;
+
+/// What an admin impersonating a user is allowed to do.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize, ToSchema, Type)]
+#[sqlx(type_name = "impersonation_mode", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ImpersonationMode {
+    /// Only requests which don't modify anything, i.e. `GET`, are allowed.
+    ReadOnly,
+    Full,
+}
+
+impl fmt::Display for ImpersonationMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReadOnly => write!(f, "read-only"),
+            Self::Full => write!(f, "full access"),
+        }
+    }
+}
+
+/// Session of a user assumed by an admin, used to see Defguard the way the user does.
+///
+/// The impersonated session is tied to the admin session it was started from and has a hard time
+/// limit, after which it can't be used anymore.
+#[derive(Clone, Debug)]
+pub struct Impersonation {
+    /// ID of the impersonated user session.
+    pub session_id: String,
+    pub admin_id: Id,
+    pub admin_session_id: String,
+    pub user_id: Id,
+    pub mode: ImpersonationMode,
+    pub created: NaiveDateTime,
+    pub expires: NaiveDateTime,
+}
+
+/// Impersonation details shown to the admin, so that it's clear whose session is being used.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ImpersonationInfo {
+    /// Username of the impersonating admin.
+    pub admin: String,
+    pub mode: ImpersonationMode,
+    pub expires: NaiveDateTime,
+}
+
+impl Impersonation {
+    #[must_use]
+    pub fn new(
+        session_id: String,
+        admin_id: Id,
+        admin_session_id: String,
+        user_id: Id,
+        mode: ImpersonationMode,
+        lifetime: TimeDelta,
+    ) -> Self {
+        let now = Utc::now().naive_utc();
+        Self {
+            session_id,
+            admin_id,
+            admin_session_id,
+            user_id,
+            mode,
+            created: now,
+            expires: now + lifetime,
+        }
+    }
+
+    #[must_use]
+    pub fn expired(&self) -> bool {
+        self.expires < Utc::now().naive_utc()
+    }
+
+    pub async fn find_by_session_id<'e, E>(
+        executor: E,
+        session_id: &str,
+    ) -> Result<Option<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT session_id, admin_id, admin_session_id, user_id, \
+            mode \"mode: ImpersonationMode\", created, expires \
+            FROM impersonation WHERE session_id = $1",
+            session_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn save<'e, E>(&self, executor: E) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "INSERT INTO impersonation \
+            (session_id, admin_id, admin_session_id, user_id, mode, created, expires) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            self.session_id,
+            self.admin_id,
+            self.admin_session_id,
+            self.user_id,
+            self.mode as ImpersonationMode,
+            self.created,
+            self.expires,
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
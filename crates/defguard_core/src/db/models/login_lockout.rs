@@ -0,0 +1,193 @@
+use chrono::NaiveDateTime;
+use defguard_common::db::Id;
+use sqlx::{Error as SqlxError, PgExecutor, query, query_as, query_scalar};
+
+/// Consecutive failed web UI logins of a user or from a client address.
+///
+/// Counters are stored in the database, so that lockouts apply to all Defguard instances.
+#[derive(Clone, Debug)]
+pub struct LoginLockout {
+    pub id: Id,
+    pub user_id: Option<Id>,
+    /// Canonical form of the client address.
+    pub ip_address: Option<String>,
+    pub failed_attempts: i32,
+    pub last_attempt: NaiveDateTime,
+    pub locked_until: Option<NaiveDateTime>,
+}
+
+impl LoginLockout {
+    /// Count a failed login of the user. Counting starts over once the lockout is over or the last
+    /// failure is older than `duration` minutes.
+    pub async fn record_user_failure<'e, E>(
+        executor: E,
+        user_id: Id,
+        duration: i32,
+    ) -> Result<Self, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "INSERT INTO login_lockout (user_id, failed_attempts, last_attempt) \
+            VALUES ($1, 1, NOW() AT TIME ZONE 'UTC') \
+            ON CONFLICT (user_id) DO UPDATE SET \
+            failed_attempts = CASE WHEN COALESCE(login_lockout.locked_until, \
+            login_lockout.last_attempt + make_interval(mins => $2)) <= NOW() AT TIME ZONE 'UTC' \
+            THEN 1 ELSE login_lockout.failed_attempts + 1 END, \
+            locked_until = CASE WHEN COALESCE(login_lockout.locked_until, \
+            login_lockout.last_attempt + make_interval(mins => $2)) <= NOW() AT TIME ZONE 'UTC' \
+            THEN NULL ELSE login_lockout.locked_until END, \
+            last_attempt = EXCLUDED.last_attempt \
+            RETURNING id, user_id, ip_address, failed_attempts, last_attempt, locked_until",
+            user_id,
+            duration
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Count a failed login from the client address, same as [`Self::record_user_failure`].
+    pub async fn record_ip_failure<'e, E>(
+        executor: E,
+        ip_address: &str,
+        duration: i32,
+    ) -> Result<Self, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "INSERT INTO login_lockout (ip_address, failed_attempts, last_attempt) \
+            VALUES ($1, 1, NOW() AT TIME ZONE 'UTC') \
+            ON CONFLICT (ip_address) DO UPDATE SET \
+            failed_attempts = CASE WHEN COALESCE(login_lockout.locked_until, \
+            login_lockout.last_attempt + make_interval(mins => $2)) <= NOW() AT TIME ZONE 'UTC' \
+            THEN 1 ELSE login_lockout.failed_attempts + 1 END, \
+            locked_until = CASE WHEN COALESCE(login_lockout.locked_until, \
+            login_lockout.last_attempt + make_interval(mins => $2)) <= NOW() AT TIME ZONE 'UTC' \
+            THEN NULL ELSE login_lockout.locked_until END, \
+            last_attempt = EXCLUDED.last_attempt \
+            RETURNING id, user_id, ip_address, failed_attempts, last_attempt, locked_until",
+            ip_address,
+            duration
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Lock for `duration` minutes. Returns the end of the lockout, or `None` if it has already
+    /// been applied, possibly by another instance.
+    pub async fn lock<'e, E>(
+        &self,
+        executor: E,
+        duration: i32,
+    ) -> Result<Option<NaiveDateTime>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "UPDATE login_lockout \
+            SET locked_until = NOW() AT TIME ZONE 'UTC' + make_interval(mins => $2) \
+            WHERE id = $1 AND locked_until IS NULL RETURNING locked_until \"locked_until!\"",
+            self.id,
+            duration
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// End of the active lockout of the user, if any.
+    pub async fn user_locked_until<'e, E>(
+        executor: E,
+        user_id: Id,
+    ) -> Result<Option<NaiveDateTime>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT locked_until \"locked_until!\" FROM login_lockout \
+            WHERE user_id = $1 AND locked_until > NOW() AT TIME ZONE 'UTC'",
+            user_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// End of the active lockout of the client address, if any.
+    pub async fn ip_locked_until<'e, E>(
+        executor: E,
+        ip_address: &str,
+    ) -> Result<Option<NaiveDateTime>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT locked_until \"locked_until!\" FROM login_lockout \
+            WHERE ip_address = $1 AND locked_until > NOW() AT TIME ZONE 'UTC'",
+            ip_address
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Reset failed logins of the user. Returns `true` if the user was locked out.
+    pub async fn delete_for_user<'e, E>(executor: E, user_id: Id) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let locked = query_scalar!(
+            "DELETE FROM login_lockout WHERE user_id = $1 \
+            RETURNING COALESCE(locked_until > NOW() AT TIME ZONE 'UTC', false) \"locked!\"",
+            user_id
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(locked.unwrap_or_default())
+    }
+
+    /// Reset failed logins from the client address. Returns `true` if the address was locked out.
+    pub async fn delete_for_ip<'e, E>(executor: E, ip_address: &str) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let locked = query_scalar!(
+            "DELETE FROM login_lockout WHERE ip_address = $1 \
+            RETURNING COALESCE(locked_until > NOW() AT TIME ZONE 'UTC', false) \"locked!\"",
+            ip_address
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(locked.unwrap_or_default())
+    }
+
+    /// Active lockouts of users and client addresses.
+    pub async fn all_active<'e, E>(executor: E) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, user_id, ip_address, failed_attempts, last_attempt, locked_until \
+            FROM login_lockout WHERE locked_until > NOW() AT TIME ZONE 'UTC' ORDER BY locked_until"
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Remove counters which would start over with the next failure, i.e. lockouts which are over
+    /// and failures older than `duration` minutes.
+    pub async fn delete_expired<'e, E>(executor: E, duration: i32) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "DELETE FROM login_lockout WHERE COALESCE(locked_until, \
+            last_attempt + make_interval(mins => $1)) <= NOW() AT TIME ZONE 'UTC'",
+            duration
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
@@ -1,13 +1,26 @@
 pub mod activity_log;
+pub mod client_login_session;
 pub mod device;
+pub mod device_approval;
+pub mod device_key_history;
+pub mod device_profile;
 pub mod enrollment;
+pub mod gateway_alert;
+pub mod gateway_token;
 pub mod group;
+pub mod group_join_request;
+pub mod impersonation;
+pub mod login_lockout;
+pub mod notification_digest;
 pub mod oauth2authorizedapp;
 pub mod oauth2client;
 pub mod oauth2token;
 pub mod polling_token;
 pub mod session;
+pub mod sms_mfa;
+pub mod trusted_device;
 pub mod user;
+pub mod vpn_session;
 pub mod webauthn;
 pub mod webhook;
 pub mod wireguard;
@@ -16,6 +29,7 @@ pub mod yubikey;
 
 use std::collections::HashSet;
 
+use chrono::NaiveDateTime;
 use defguard_common::db::{
     Id,
     models::{BiometricAuth, MFAMethod},
@@ -23,7 +37,7 @@ use defguard_common::db::{
 use sqlx::{Error as SqlxError, PgConnection, PgPool, query_as};
 use utoipa::ToSchema;
 
-use self::{device::UserDevice, user::User};
+use self::{device::UserDevice, sms_mfa::SmsMfa, trusted_device::TrustedDevice, user::User};
 use super::Group;
 
 #[derive(Deserialize, Serialize)]
@@ -60,6 +74,8 @@ pub struct UserInfo {
     pub mfa_enabled: bool,
     pub totp_enabled: bool,
     pub email_mfa_enabled: bool,
+    #[serde(default)]
+    pub sms_mfa_enabled: bool,
     pub groups: Vec<String>,
     pub mfa_method: MFAMethod,
     pub authorized_apps: Vec<OAuth2AuthorizedAppInfo>,
@@ -67,6 +83,16 @@ pub struct UserInfo {
     pub enrolled: bool,
     pub is_admin: bool,
     pub ldap_pass_requires_change: bool,
+    // IANA timezone name, deployment timezone is used if not set;
+    // left unchanged on update if missing, empty name clears it
+    #[serde(default)]
+    pub timezone: Option<String>,
+    // service accounts authenticate only with API tokens
+    #[serde(default)]
+    pub service_account: bool,
+    // account is disabled automatically at this moment, can be set by admins only
+    #[serde(default)]
+    pub deactivate_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Default)]
@@ -97,6 +123,9 @@ impl UserInfo {
             mfa_enabled: user.mfa_enabled,
             totp_enabled: user.totp_enabled,
             email_mfa_enabled: user.email_mfa_enabled,
+            sms_mfa_enabled: SmsMfa::find_by_user(pool, user.id)
+                .await?
+                .is_some_and(|sms_mfa| sms_mfa.enabled),
             groups,
             mfa_method: user.mfa_method.clone(),
             authorized_apps,
@@ -104,6 +133,9 @@ impl UserInfo {
             enrolled: user.is_enrolled(),
             is_admin: user.is_admin(pool).await?,
             ldap_pass_requires_change: user.ldap_pass_randomized,
+            timezone: user.timezone(pool).await?,
+            service_account: user.is_service_account(pool).await?,
+            deactivate_at: user.deactivate_at(pool).await?,
         })
     }
 
@@ -121,6 +153,7 @@ impl UserInfo {
         } else {
             if !self.is_active {
                 user.logout_all_sessions(&mut *transaction).await?;
+                TrustedDevice::delete_for_user(&mut *transaction, user.id).await?;
             }
             user.is_active = self.is_active;
             user.save(&mut *transaction).await?;
@@ -140,7 +173,7 @@ impl UserInfo {
         let mut group_diff = GroupDiff::default();
 
         // handle groups
-        let mut present_groups = user.member_of(&mut *transaction).await?;
+        let mut present_groups = user.direct_member_of(&mut *transaction).await?;
 
         // add to groups if not already a member
         for groupname in &self.groups {
@@ -224,6 +257,7 @@ pub struct MFAInfo {
     totp_available: bool,
     webauthn_available: bool,
     email_available: bool,
+    sms_available: bool,
 }
 
 impl MFAInfo {
@@ -232,7 +266,8 @@ impl MFAInfo {
             Self,
             "SELECT mfa_method \"mfa_method: _\", totp_enabled totp_available, \
             email_mfa_enabled email_available, \
-            (SELECT count(*) > 0 FROM webauthn WHERE user_id = $1) \"webauthn_available!\" \
+            (SELECT count(*) > 0 FROM webauthn WHERE user_id = $1) \"webauthn_available!\", \
+            (SELECT count(*) > 0 FROM user_sms_mfa WHERE user_id = $1 AND enabled) \"sms_available!\" \
             FROM \"user\" WHERE \"user\".id = $1",
             user.id
         )
@@ -242,7 +277,7 @@ impl MFAInfo {
 
     #[must_use]
     pub fn mfa_available(&self) -> bool {
-        self.webauthn_available || self.totp_available || self.email_available
+        self.webauthn_available || self.totp_available || self.email_available || self.sms_available
     }
 
     #[must_use]
@@ -266,6 +301,9 @@ impl MFAInfo {
         if self.email_available {
             methods.push(MFAMethod::Email);
         }
+        if self.sms_available {
+            methods.push(MFAMethod::Sms);
+        }
         Some(methods)
     }
 }
@@ -0,0 +1,119 @@
+use chrono::NaiveDateTime;
+use defguard_common::db::{Id, NoId};
+use model_derive::Model;
+use sqlx::{Error as SqlxError, PgExecutor, query, query_as, query_scalar};
+use utoipa::ToSchema;
+
+/// Email notification preferences of a user.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize, ToSchema)]
+pub struct NotificationSettings {
+    /// Collect non-critical notifications into a daily digest instead of sending them right away.
+    pub email_digest: bool,
+}
+
+impl NotificationSettings {
+    /// Fetches preferences of given user, defaults are used if the user has never changed them.
+    pub async fn find_by_user<'e, E>(executor: E, user_id: Id) -> Result<Self, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let settings = query_as!(
+            Self,
+            "SELECT email_digest FROM user_notification_settings WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(settings.unwrap_or_default())
+    }
+
+    pub async fn save_for_user<'e, E>(&self, executor: E, user_id: Id) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "INSERT INTO user_notification_settings (user_id, email_digest) VALUES ($1, $2) \
+            ON CONFLICT (user_id) DO UPDATE SET email_digest = $2",
+            user_id,
+            self.email_digest
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Notification held back until the next digest is sent to the user.
+#[derive(Clone, Debug, Model)]
+#[table(notification_digest_entry)]
+pub struct DigestEntry<I = NoId> {
+    pub id: I,
+    pub user_id: Id,
+    pub title: String,
+    pub summary: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl DigestEntry {
+    #[must_use]
+    pub fn new(user_id: Id, title: String, summary: String, created_at: NaiveDateTime) -> Self {
+        Self {
+            id: NoId,
+            user_id,
+            title,
+            summary,
+            created_at,
+        }
+    }
+}
+
+impl DigestEntry<Id> {
+    /// Returns users with notifications older than `created_before`.
+    pub async fn users_due<'e, E>(
+        executor: E,
+        created_before: NaiveDateTime,
+    ) -> Result<Vec<Id>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT user_id FROM notification_digest_entry \
+            GROUP BY user_id HAVING MIN(created_at) < $1",
+            created_before
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Fetches notifications queued for given user, oldest first.
+    pub async fn all_for_user<'e, E>(executor: E, user_id: Id) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, user_id, title, summary, created_at FROM notification_digest_entry \
+            WHERE user_id = $1 ORDER BY created_at, id",
+            user_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Removes notifications which have been included in a digest.
+    pub async fn delete_many<'e, E>(executor: E, ids: &[Id]) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "DELETE FROM notification_digest_entry WHERE id = ANY($1)",
+            ids
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -25,6 +25,7 @@ pub struct Session {
     pub webauthn_challenge: Option<Vec<u8>>,
     pub ip_address: String,
     pub device_info: Option<String>,
+    pub last_seen: NaiveDateTime,
 }
 
 impl From<Session> for SessionContext {
@@ -55,6 +56,7 @@ impl Session {
             webauthn_challenge: None,
             ip_address,
             device_info,
+            last_seen: now.naive_utc(),
         }
     }
 
@@ -63,21 +65,65 @@ impl Session {
         self.expires < Utc::now().naive_utc()
     }
 
+    /// Identifier of the session which can be exposed in the API, unlike the session cookie value.
+    #[must_use]
+    pub fn public_id(&self) -> String {
+        sha256::digest(self.id.as_str())
+    }
+
+    /// Sessions assumed by impersonating admins aren't returned, they can only be loaded with
+    /// [`Session::find_impersonated`].
     pub async fn find_by_id(pool: &PgPool, id: &str) -> Result<Option<Self>, SqlxError> {
         query_as!(
             Self,
             "SELECT id, user_id, state \"state: SessionState\", created, expires, webauthn_challenge, \
-            ip_address, device_info FROM session WHERE id = $1",
+            ip_address, device_info, last_seen FROM session WHERE id = $1 \
+            AND NOT EXISTS (SELECT 1 FROM impersonation WHERE session_id = session.id)",
+            id
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Fetch the user session assumed by an impersonating admin.
+    pub async fn find_impersonated(pool: &PgPool, id: &str) -> Result<Option<Self>, SqlxError> {
+        query_as!(
+            Self,
+            "SELECT id, user_id, state \"state: SessionState\", created, expires, webauthn_challenge, \
+            ip_address, device_info, last_seen FROM session WHERE id = $1 \
+            AND EXISTS (SELECT 1 FROM impersonation WHERE session_id = session.id)",
             id
         )
         .fetch_optional(pool)
         .await
     }
 
+    /// Fetch sessions of the user which haven't expired yet, most recently used first.
+    /// Sessions assumed by impersonating admins aren't included.
+    pub async fn find_active_by_user<'e, E>(
+        executor: E,
+        user_id: Id,
+    ) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, user_id, state \"state: SessionState\", created, expires, webauthn_challenge, \
+            ip_address, device_info, last_seen FROM session \
+            WHERE user_id = $1 AND expires >= now() \
+            AND NOT EXISTS (SELECT 1 FROM impersonation WHERE session_id = session.id) \
+            ORDER BY last_seen DESC",
+            user_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
     pub async fn save(&self, pool: &PgPool) -> Result<(), SqlxError> {
         query!(
-            "INSERT INTO session (id, user_id, state, created, expires, webauthn_challenge, ip_address, device_info) \
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            "INSERT INTO session (id, user_id, state, created, expires, webauthn_challenge, ip_address, device_info, last_seen) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
             self.id,
             self.user_id,
             self.state.clone() as i16,
@@ -86,6 +132,7 @@ impl Session {
             self.webauthn_challenge,
             self.ip_address,
             self.device_info,
+            self.last_seen,
         )
         .execute(pool)
         .await?;
@@ -106,6 +153,24 @@ impl Session {
         Ok(())
     }
 
+    /// Record that the session has just been used.
+    pub async fn touch<'e, E>(&mut self, executor: E) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let now = Utc::now().naive_utc();
+        query!(
+            "UPDATE session SET last_seen = $1 WHERE id = $2",
+            now,
+            self.id
+        )
+        .execute(executor)
+        .await?;
+        self.last_seen = now;
+
+        Ok(())
+    }
+
     #[must_use]
     pub fn get_passkey_registration(&self) -> Option<PasskeyRegistration> {
         self.webauthn_challenge
@@ -196,4 +261,26 @@ impl Session {
 
         Ok(())
     }
+
+    /// Delete all sessions of the user other than the one with the given ID, except for sessions
+    /// assumed by impersonating admins. Returns the number of deleted sessions.
+    pub async fn delete_all_for_user_except<'e, E>(
+        executor: E,
+        user_id: Id,
+        id: &str,
+    ) -> Result<u64, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let result = query!(
+            "DELETE FROM session WHERE user_id = $1 AND id != $2 \
+            AND NOT EXISTS (SELECT 1 FROM impersonation WHERE session_id = session.id)",
+            user_id,
+            id
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
 }
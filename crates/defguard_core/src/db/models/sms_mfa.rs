@@ -0,0 +1,108 @@
+use std::time::SystemTime;
+
+use defguard_common::{config::server_config, db::Id, random::gen_totp_secret};
+use sqlx::{Error as SqlxError, PgExecutor, query, query_as};
+use totp_lite::{Sha1, totp_custom};
+
+use crate::auth::SMS_CODE_DIGITS;
+
+/// Phone number a user receives SMS MFA codes on.
+///
+/// The number is kept apart from the profile phone number and has to be verified with a code
+/// before SMS MFA is enabled.
+pub struct SmsMfa {
+    pub user_id: Id,
+    pub phone: String,
+    secret: Vec<u8>,
+    pub enabled: bool,
+}
+
+impl SmsMfa {
+    /// Start verification of a new phone number, replacing the previous one.
+    pub async fn init<'e, E>(executor: E, user_id: Id, phone: String) -> Result<Self, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let secret = gen_totp_secret();
+        query!(
+            "INSERT INTO user_sms_mfa (user_id, phone, secret, enabled) \
+            VALUES ($1, $2, $3, false) \
+            ON CONFLICT (user_id) DO UPDATE SET phone = $2, secret = $3, enabled = false",
+            user_id,
+            phone,
+            secret
+        )
+        .execute(executor)
+        .await?;
+        Ok(Self {
+            user_id,
+            phone,
+            secret,
+            enabled: false,
+        })
+    }
+
+    pub async fn find_by_user<'e, E>(executor: E, user_id: Id) -> Result<Option<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT user_id, phone, secret, enabled FROM user_sms_mfa WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn enable<'e, E>(&mut self, executor: E) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "UPDATE user_sms_mfa SET enabled = true WHERE user_id = $1",
+            self.user_id
+        )
+        .execute(executor)
+        .await?;
+        self.enabled = true;
+        Ok(())
+    }
+
+    pub async fn delete_for_user<'e, E>(executor: E, user_id: Id) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!("DELETE FROM user_sms_mfa WHERE user_id = $1", user_id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+
+    fn code_at(&self, timestamp: u64) -> String {
+        let timeout = server_config().mfa_code_timeout.as_secs();
+        totp_custom::<Sha1>(timeout, SMS_CODE_DIGITS, &self.secret, timestamp)
+    }
+
+    /// Generate a code to be sent in a text message.
+    ///
+    /// Like email MFA codes, it stays valid for the current and the next time frame.
+    #[must_use]
+    pub fn generate_code(&self) -> Option<String> {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()?;
+        Some(self.code_at(timestamp.as_secs()))
+    }
+
+    /// Check if `code` is valid for the current or the previous time frame.
+    #[must_use]
+    pub fn verify_code(&self, code: &str) -> bool {
+        let Ok(timestamp) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) else {
+            return false;
+        };
+        let timeout = server_config().mfa_code_timeout.as_secs();
+        let now = timestamp.as_secs();
+        code == self.code_at(now) || code == self.code_at(now.saturating_sub(timeout))
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use defguard_common::db::Id;
+use sqlx::{Error as SqlxError, PgExecutor, query, query_scalar};
+
+/// Upper limit of the trust period a desktop client can request after MFA login.
+pub const MAX_DEVICE_TRUST_HOURS: u32 = 24 * 30;
+/// Length of the secret handed to the desktop client when its device becomes trusted.
+pub const DEVICE_TRUST_SECRET_LENGTH: usize = 32;
+
+/// Device which skips desktop client MFA in a location until `trusted_until`.
+/// Trust is bound to the device public key and a secret issued to the client, so it's lost
+/// once the key changes and can't be used by anyone who only knows the public key.
+#[derive(Clone, Debug)]
+pub struct TrustedDevice {
+    pub location_id: Id,
+    pub device_pubkey: String,
+    secret_hash: String,
+    pub trusted_until: NaiveDateTime,
+}
+
+impl TrustedDevice {
+    /// Trust a device for given number of hours, capped at [`MAX_DEVICE_TRUST_HOURS`].
+    /// Only the hash of `secret` is stored.
+    #[must_use]
+    pub fn new(location_id: Id, device_pubkey: String, secret: &str, hours: u32) -> Self {
+        let hours = hours.min(MAX_DEVICE_TRUST_HOURS);
+        Self {
+            location_id,
+            device_pubkey,
+            secret_hash: Self::hash_secret(secret),
+            trusted_until: Utc::now().naive_utc() + TimeDelta::hours(hours.into()),
+        }
+    }
+
+    fn hash_secret(secret: &str) -> String {
+        sha256::digest(secret)
+    }
+
+    /// Store trust record, extending or shortening the current one.
+    pub async fn save<'e, E>(&self, executor: E) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "INSERT INTO client_mfa_trusted_device \
+            (location_id, device_pubkey, secret_hash, trusted_until) VALUES ($1, $2, $3, $4) \
+            ON CONFLICT (location_id, device_pubkey) \
+            DO UPDATE SET secret_hash = $3, trusted_until = $4",
+            self.location_id,
+            self.device_pubkey,
+            self.secret_hash,
+            self.trusted_until
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Checks if a device is currently trusted in a location and `secret` is the one issued
+    /// when the trust was granted.
+    pub async fn is_trusted<'e, E>(
+        executor: E,
+        location_id: Id,
+        device_pubkey: &str,
+        secret: &str,
+    ) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT EXISTS (SELECT 1 FROM client_mfa_trusted_device \
+            WHERE location_id = $1 AND device_pubkey = $2 AND secret_hash = $3 \
+            AND trusted_until > NOW() AT TIME ZONE 'UTC') \"exists!\"",
+            location_id,
+            device_pubkey,
+            Self::hash_secret(secret)
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Removes all trust records of a location, e.g. after the policy has been disabled.
+    pub async fn delete_for_location<'e, E>(executor: E, location_id: Id) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "DELETE FROM client_mfa_trusted_device WHERE location_id = $1",
+            location_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes trust record of a device in a location, e.g. after it lost access to it.
+    pub async fn delete_for_device<'e, E>(
+        executor: E,
+        location_id: Id,
+        device_pubkey: &str,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "DELETE FROM client_mfa_trusted_device WHERE location_id = $1 AND device_pubkey = $2",
+            location_id,
+            device_pubkey
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes trust records of all devices of a user, e.g. after the user has been disabled.
+    pub async fn delete_for_user<'e, E>(executor: E, user_id: Id) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "DELETE FROM client_mfa_trusted_device WHERE device_pubkey IN \
+            (SELECT wireguard_pubkey FROM device WHERE user_id = $1)",
+            user_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
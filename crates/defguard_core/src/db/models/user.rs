@@ -1,16 +1,24 @@
 use std::{collections::HashSet, fmt, time::SystemTime};
 
 use argon2::{
-    Argon2,
+    Algorithm, Argon2, Params, Version,
     password_hash::{
         PasswordHash, PasswordHasher, PasswordVerifier, SaltString, errors::Error as HashError,
         rand_core::OsRng,
     },
 };
 use axum::http::StatusCode;
+use chrono::{NaiveDateTime, Utc};
+use chrono_tz::Tz;
 use defguard_common::{
     config::server_config,
-    db::{Id, NoId, models::MFAMethod},
+    db::{
+        Id, NoId,
+        models::{
+            MFAMethod, Settings,
+            settings::{MAX_PASSWORD_HISTORY_SIZE, TotpAlgorithm, get_settings},
+        },
+    },
     random::{gen_alphanumeric, gen_totp_secret},
 };
 use defguard_mail::templates::UserContext;
@@ -26,12 +34,14 @@ use sqlx::{
     Error as SqlxError, FromRow, PgConnection, PgExecutor, PgPool, query, query_as, query_scalar,
 };
 use tokio::sync::broadcast::Sender;
-use totp_lite::{Sha1, totp_custom};
+use totp_lite::{Sha1, Sha256, totp_custom};
 
 use super::{
     MFAInfo, OAuth2AuthorizedAppInfo, SecurityKey,
     device::{Device, DeviceInfo, DeviceType, UserDevice},
     group::Group,
+    sms_mfa::SmsMfa,
+    trusted_device::TrustedDevice,
     webauthn::WebAuthn,
 };
 use crate::{
@@ -158,11 +168,86 @@ impl<I: std::fmt::Debug> fmt::Debug for User<I> {
     }
 }
 
+/// Argon2id parameters for new password hashes, as configured in settings.
+/// Falls back to library defaults if settings are not initialized or invalid.
+fn password_hash_params() -> Params {
+    let settings = get_settings();
+    let Some(settings) = settings.as_ref() else {
+        return Params::default();
+    };
+    let params = (
+        u32::try_from(settings.password_hash_memory_cost),
+        u32::try_from(settings.password_hash_time_cost),
+        u32::try_from(settings.password_hash_parallelism),
+    );
+    match params {
+        (Ok(m_cost), Ok(t_cost), Ok(p_cost)) => Params::new(m_cost, t_cost, p_cost, None)
+            .unwrap_or_else(|err| {
+                warn!("Invalid password hashing parameters, using defaults: {err}");
+                Params::default()
+            }),
+        _ => {
+            warn!("Invalid password hashing parameters, using defaults");
+            Params::default()
+        }
+    }
+}
+
 fn hash_password(password: &str) -> Result<String, HashError> {
     let salt = SaltString::generate(&mut OsRng);
-    Ok(Argon2::default()
-        .hash_password(password.as_bytes(), &salt)?
-        .to_string())
+    Ok(
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, password_hash_params())
+            .hash_password(password.as_bytes(), &salt)?
+            .to_string(),
+    )
+}
+
+/// Beginning of PHC strings of password hashes created with the currently configured parameters,
+/// e.g. `$argon2id$v=19$m=19456,t=2,p=1$`.
+fn current_password_hash_prefix() -> String {
+    let params = password_hash_params();
+    format!(
+        "${}$v={}$m={},t={},p={}$",
+        Algorithm::Argon2id.ident(),
+        u32::from(Version::V0x13),
+        params.m_cost(),
+        params.t_cost(),
+        params.p_cost()
+    )
+}
+
+/// Checks if a password hash should be replaced, i.e. it was created with a different KDF
+/// or with parameters other than the currently configured ones.
+#[must_use]
+pub(crate) fn is_legacy_password_hash(hash: &str) -> bool {
+    let Ok(parsed_hash) = PasswordHash::new(hash) else {
+        return true;
+    };
+    if parsed_hash.algorithm != Algorithm::Argon2id.ident()
+        || parsed_hash.version != Some(Version::V0x13.into())
+    {
+        return true;
+    }
+    let current = password_hash_params();
+    match Params::try_from(&parsed_hash) {
+        Ok(params) => {
+            params.m_cost() != current.m_cost()
+                || params.t_cost() != current.t_cost()
+                || params.p_cost() != current.p_cost()
+        }
+        Err(_) => true,
+    }
+}
+
+/// Generate TOTP code for the given time using the configured hash algorithm.
+#[must_use]
+fn totp_code(algorithm: TotpAlgorithm, digits: u32, secret: &[u8], time: u64) -> String {
+    match algorithm {
+        TotpAlgorithm::Sha1 => totp_custom::<Sha1>(TOTP_CODE_VALIDITY_PERIOD, digits, secret, time),
+        TotpAlgorithm::Sha256 => {
+            totp_custom::<Sha256>(TOTP_CODE_VALIDITY_PERIOD, digits, secret, time)
+        }
+    }
 }
 
 impl From<User<Id>> for UserContext {
@@ -226,6 +311,7 @@ impl<I> User<I> {
     pub(crate) fn verify_password(&self, password: &str) -> Result<(), HashError> {
         debug!("Checking if password matches for user {}", self.username);
         if let Some(hash) = &self.password_hash {
+            // Parameters are read from the hash itself, so legacy hashes are verified as well.
             let parsed_hash = PasswordHash::new(hash)?;
             Argon2::default().verify_password(password.as_bytes(), &parsed_hash)
         } else {
@@ -272,6 +358,237 @@ impl<I> User<I> {
 }
 
 impl User<Id> {
+    /// Rehashes a verified password if the stored hash is a legacy one.
+    /// See [`is_legacy_password_hash`].
+    pub(crate) async fn upgrade_password_hash<'e, E>(
+        &mut self,
+        executor: E,
+        password: &str,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        if !self
+            .password_hash
+            .as_deref()
+            .is_some_and(is_legacy_password_hash)
+        {
+            return Ok(());
+        }
+        let Ok(password_hash) = hash_password(password) else {
+            error!("Failed to rehash password for user {}", self.username);
+            return Ok(());
+        };
+        query!(
+            "UPDATE \"user\" SET password_hash = $1 WHERE id = $2",
+            password_hash,
+            self.id
+        )
+        .execute(executor)
+        .await?;
+        info!("Upgraded legacy password hash for user {}", self.username);
+        self.password_hash = Some(password_hash);
+
+        Ok(())
+    }
+
+    /// IANA name of the timezone chosen by the user, if any.
+    pub(crate) async fn timezone<'e, E>(&self, executor: E) -> Result<Option<String>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!("SELECT timezone FROM \"user\" WHERE id = $1", self.id)
+            .fetch_one(executor)
+            .await
+    }
+
+    pub(crate) async fn set_timezone<'e, E>(
+        &self,
+        executor: E,
+        timezone: Option<&str>,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "UPDATE \"user\" SET timezone = $2 WHERE id = $1",
+            self.id,
+            timezone
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Moment the account is scheduled to be disabled at, e.g. the last day of employment.
+    pub(crate) async fn deactivate_at<'e, E>(
+        &self,
+        executor: E,
+    ) -> Result<Option<NaiveDateTime>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!("SELECT deactivate_at FROM \"user\" WHERE id = $1", self.id)
+            .fetch_one(executor)
+            .await
+    }
+
+    pub(crate) async fn set_deactivate_at<'e, E>(
+        &self,
+        executor: E,
+        deactivate_at: Option<NaiveDateTime>,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "UPDATE \"user\" SET deactivate_at = $2 WHERE id = $1",
+            self.id,
+            deactivate_at
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Service accounts can't log in interactively and only authenticate with API tokens.
+    pub(crate) async fn is_service_account<'e, E>(&self, executor: E) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT service_account FROM \"user\" WHERE id = $1",
+            self.id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub(crate) async fn set_service_account<'e, E>(
+        &self,
+        executor: E,
+        service_account: bool,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "UPDATE \"user\" SET service_account = $2 WHERE id = $1",
+            self.id,
+            service_account
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn is_break_glass<'e, E>(&self, executor: E) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!("SELECT break_glass FROM \"user\" WHERE id = $1", self.id)
+            .fetch_one(executor)
+            .await
+    }
+
+    pub(crate) async fn set_break_glass<'e, E>(
+        &self,
+        executor: E,
+        break_glass: bool,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "UPDATE \"user\" SET break_glass = $2 WHERE id = $1",
+            self.id,
+            break_glass
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Remember the current password of the user in the password history to prevent its reuse.
+    /// Only the most recent passwords allowed by `MAX_PASSWORD_HISTORY_SIZE` are kept.
+    pub(crate) async fn remember_password(
+        &self,
+        transaction: &mut PgConnection,
+    ) -> Result<(), SqlxError> {
+        let Some(password_hash) = &self.password_hash else {
+            return Ok(());
+        };
+        query!(
+            "INSERT INTO password_history (user_id, password_hash) VALUES ($1, $2)",
+            self.id,
+            password_hash
+        )
+        .execute(&mut *transaction)
+        .await?;
+        query!(
+            "DELETE FROM password_history WHERE user_id = $1 AND id NOT IN ( \
+                SELECT id FROM password_history WHERE user_id = $1 ORDER BY id DESC LIMIT $2)",
+            self.id,
+            i64::from(MAX_PASSWORD_HISTORY_SIZE)
+        )
+        .execute(&mut *transaction)
+        .await?;
+        Ok(())
+    }
+
+    /// Hashes of the most recent passwords of the user, newest first.
+    pub(crate) async fn password_history<'e, E>(
+        &self,
+        executor: E,
+        limit: i64,
+    ) -> Result<Vec<String>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT password_hash FROM password_history WHERE user_id = $1 \
+            ORDER BY id DESC LIMIT $2",
+            self.id,
+            limit
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Timezone used to present timestamps to the user.
+    /// Falls back to the deployment timezone if the user didn't choose one.
+    pub(crate) async fn effective_timezone<'e, E>(&self, executor: E) -> Result<Tz, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let timezone = self
+            .timezone(executor)
+            .await?
+            .and_then(|timezone| timezone.parse().ok())
+            .unwrap_or_else(|| Settings::get_current_settings().deployment_timezone());
+        Ok(timezone)
+    }
+
+    /// Returns the number of all stored password hashes and the number of legacy ones among them.
+    /// Hashes are compared by their PHC string prefix, see [`current_password_hash_prefix`].
+    pub(crate) async fn count_legacy_password_hashes<'e, E>(
+        executor: E,
+    ) -> Result<(i64, i64), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let counts = query!(
+            "SELECT count(*) \"total!\", \
+            count(*) FILTER (WHERE NOT starts_with(password_hash, $1)) \"legacy!\" \
+            FROM \"user\" WHERE password_hash IS NOT NULL",
+            current_password_hash_prefix()
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok((counts.total, counts.legacy))
+    }
+
     /// Generate new TOTP secret, save it, then return it as RFC 4648 base32-encoded string.
     pub async fn new_totp_secret<'e, E>(&mut self, executor: E) -> Result<String, SqlxError>
     where
@@ -336,6 +653,8 @@ impl User<Id> {
 
     /// Check if any of the multi-factor authentication methods is on.
     /// - TOTP is enabled
+    /// - email MFA is enabled
+    /// - SMS MFA is enabled
     /// - a security key for Webauthn
     async fn check_mfa_enabled<'e, E>(&self, executor: E) -> Result<bool, SqlxError>
     where
@@ -348,7 +667,9 @@ impl User<Id> {
 
         query_scalar!(
             "SELECT totp_enabled OR email_mfa_enabled \
-            OR count(webauthn.id) > 0 \"bool!\" FROM \"user\" \
+            OR count(webauthn.id) > 0 \
+            OR (SELECT count(*) > 0 FROM user_sms_mfa WHERE user_id = $1 AND enabled) \"bool!\" \
+            FROM \"user\" \
             LEFT JOIN webauthn ON webauthn.user_id = \"user\".id \
             WHERE \"user\".id = $1 GROUP BY totp_enabled, email_mfa_enabled;",
             self.id
@@ -424,6 +745,7 @@ impl User<Id> {
         self.is_active = false;
         self.save(&mut *conn).await?;
         self.logout_all_sessions(&mut *conn).await?;
+        TrustedDevice::delete_for_user(&mut *conn, self.id).await?;
         self.sync_allowed_devices(conn, wg_tx).await?;
         Ok(())
     }
@@ -544,6 +866,35 @@ impl User<Id> {
         Ok(Some(self.recovery_codes.clone()))
     }
 
+    /// Replace recovery codes with a new set, invalidating the old ones.
+    pub(crate) async fn regenerate_recovery_codes<'e, E>(
+        &mut self,
+        executor: E,
+    ) -> Result<Vec<String>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        self.recovery_codes.clear();
+        let codes = self.get_recovery_codes(executor).await?;
+        Ok(codes.unwrap_or_default())
+    }
+
+    /// Times at which recovery codes were used, most recent first.
+    pub(crate) async fn recovery_code_usage<'e, E>(
+        &self,
+        executor: E,
+    ) -> Result<Vec<NaiveDateTime>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT used_at FROM recovery_code_usage WHERE user_id = $1 ORDER BY used_at DESC",
+            self.id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
     /// Disable MFA; discard recovery codes, TOTP secret, and security keys.
     pub async fn disable_mfa(&mut self, pool: &PgPool) -> Result<(), SqlxError> {
         query!(
@@ -554,6 +905,7 @@ impl User<Id> {
         .execute(pool)
         .await?;
         WebAuthn::delete_all_for_user(pool, self.id).await?;
+        SmsMfa::delete_for_user(pool, self.id).await?;
 
         self.totp_secret = None;
         self.email_mfa_secret = None;
@@ -701,17 +1053,24 @@ impl User<Id> {
     }
 
     /// Check if TOTP `code` is valid.
+    ///
+    /// Algorithm, number of digits and the number of neighbouring time steps accepted
+    /// to tolerate clock drift come from settings.
     #[must_use]
     pub fn verify_totp_code(&self, code: &str) -> bool {
         if let Some(totp_secret) = &self.totp_secret {
             if let Ok(timestamp) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
-                let expected_code = totp_custom::<Sha1>(
-                    TOTP_CODE_VALIDITY_PERIOD,
-                    TOTP_CODE_DIGITS,
-                    totp_secret,
-                    timestamp.as_secs(),
-                );
-                return code == expected_code;
+                let settings = Settings::get_current_settings();
+                let digits = settings.totp_digits.try_into().unwrap_or(TOTP_CODE_DIGITS);
+                let step = TOTP_CODE_VALIDITY_PERIOD as i64;
+                return (-settings.totp_skew..=settings.totp_skew).any(|offset| {
+                    timestamp
+                        .as_secs()
+                        .checked_add_signed(i64::from(offset) * step)
+                        .is_some_and(|time| {
+                            code == totp_code(settings.totp_algorithm, digits, totp_secret, time)
+                        })
+                });
             }
         }
 
@@ -812,13 +1171,22 @@ impl User<Id> {
             // Note: swap_remove() should be faster than remove().
             self.recovery_codes.swap_remove(index);
 
+            let mut transaction = pool.begin().await?;
             query!(
                 "UPDATE \"user\" SET recovery_codes = $2 WHERE id = $1",
                 self.id,
                 &self.recovery_codes
             )
-            .execute(pool)
+            .execute(&mut *transaction)
+            .await?;
+            query!(
+                "INSERT INTO recovery_code_usage (user_id, used_at) VALUES ($1, $2)",
+                self.id,
+                Utc::now().naive_utc()
+            )
+            .execute(&mut *transaction)
             .await?;
+            transaction.commit().await?;
 
             Ok(true)
         } else {
@@ -826,6 +1194,39 @@ impl User<Id> {
         }
     }
 
+    /// Schedule deactivation of all enabled break-glass accounts at `deactivate_at`.
+    pub(crate) async fn deactivate_break_glass_at<'e, E>(
+        executor: E,
+        deactivate_at: NaiveDateTime,
+    ) -> Result<u64, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let result = query!(
+            "UPDATE \"user\" SET deactivate_at = $1 WHERE break_glass AND is_active",
+            deactivate_at
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// IDs of active users whose scheduled deactivation time has passed.
+    pub(crate) async fn find_due_for_deactivation<'e, E>(
+        executor: E,
+        now: NaiveDateTime,
+    ) -> Result<Vec<Id>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT id FROM \"user\" WHERE is_active AND deactivate_at <= $1",
+            now
+        )
+        .fetch_all(executor)
+        .await
+    }
+
     pub async fn find_by_username<'e, E>(
         executor: E,
         username: &str,
@@ -934,7 +1335,46 @@ impl User<Id> {
         .await
     }
 
+    /// Names of groups the user belongs to, including groups inherited from parent groups.
+    pub(crate) async fn effective_group_names<'e, E>(
+        &self,
+        executor: E,
+    ) -> Result<Vec<String>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT \"group\".name FROM \"group\" \
+            JOIN group_user_effective gu ON \"group\".id = gu.group_id \
+            WHERE gu.user_id = $1",
+            self.id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Groups the user belongs to, including groups inherited from parent groups.
     pub(crate) async fn member_of<'e, E>(&self, executor: E) -> Result<Vec<Group<Id>>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Group,
+            "SELECT id, name, is_admin FROM \"group\" \
+            JOIN group_user_effective gu ON \"group\".id = gu.group_id \
+            WHERE gu.user_id = $1",
+            self.id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Groups the user is a direct member of. Used when synchronizing memberships, which can only
+    /// change direct membership.
+    pub(crate) async fn direct_member_of<'e, E>(
+        &self,
+        executor: E,
+    ) -> Result<Vec<Group<Id>>, SqlxError>
     where
         E: PgExecutor<'e>,
     {
@@ -1026,6 +1466,29 @@ impl User<Id> {
         Ok(())
     }
 
+    /// Adds the user to a group until `expires_at`; memberships without expiry are permanent.
+    /// For existing members only the expiry is updated.
+    pub(crate) async fn add_to_group_until<'e, E>(
+        &self,
+        executor: E,
+        group: &Group<Id>,
+        expires_at: Option<NaiveDateTime>,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "INSERT INTO group_user (group_id, user_id, expires_at) VALUES ($1, $2, $3) \
+            ON CONFLICT (group_id, user_id) DO UPDATE SET expires_at = EXCLUDED.expires_at",
+            group.id,
+            self.id,
+            expires_at
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
     pub(crate) async fn remove_from_group<'e, E>(
         &self,
         executor: E,
@@ -1179,7 +1642,7 @@ impl User<Id> {
     where
         E: PgExecutor<'e>,
     {
-        query_scalar!("SELECT EXISTS (SELECT 1 FROM group_user gu LEFT JOIN \"group\" g ON gu.group_id = g.id \
+        query_scalar!("SELECT EXISTS (SELECT 1 FROM group_user_effective gu LEFT JOIN \"group\" g ON gu.group_id = g.id \
         WHERE is_admin = true AND user_id = $1) \"bool!\"", self.id)
             .fetch_one(executor)
             .await
@@ -1198,7 +1661,7 @@ impl User<Id> {
             u.totp_secret, u.email_mfa_secret, u.mfa_method \"mfa_method: _\", u.recovery_codes, u.is_active, u.openid_sub, \
             from_ldap, ldap_pass_randomized, ldap_rdn, ldap_user_path, enrollment_pending \
             FROM \"user\" u \
-            WHERE EXISTS (SELECT 1 FROM group_user gu LEFT JOIN \"group\" g ON gu.group_id = g.id \
+            WHERE EXISTS (SELECT 1 FROM group_user_effective gu LEFT JOIN \"group\" g ON gu.group_id = g.id \
             WHERE is_admin = true AND user_id = u.id) AND u.is_active = true"
         )
         .fetch_all(executor)
@@ -1327,6 +1790,38 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_password_hash_prefix() {
+        let hash = hash_password("pass123").unwrap();
+        assert!(hash.starts_with(&current_password_hash_prefix()));
+        assert!(!is_legacy_password_hash(&hash));
+        assert!(is_legacy_password_hash(
+            "$argon2i$v=19$m=4096,t=3,p=1$c29tZXNhbHQ$iWh06vD8Fy27wf9npn6FXWiCX4K6pW6Ue1Bnzz07Z8A"
+        ));
+    }
+
+    #[test]
+    fn test_totp_code() {
+        // test vectors from RFC 6238
+        assert_eq!(
+            totp_code(TotpAlgorithm::Sha1, 8, b"12345678901234567890", 59),
+            "94287082"
+        );
+        assert_eq!(
+            totp_code(
+                TotpAlgorithm::Sha256,
+                8,
+                b"12345678901234567890123456789012",
+                1_111_111_109
+            ),
+            "68084774"
+        );
+        assert_eq!(
+            totp_code(TotpAlgorithm::Sha1, 6, b"12345678901234567890", 59),
+            "287082"
+        );
+    }
+
     #[sqlx::test]
     async fn test_user(_: PgPoolOptions, options: PgConnectOptions) {
         let pool = setup_pool(options).await;
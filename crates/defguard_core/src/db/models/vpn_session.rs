@@ -0,0 +1,223 @@
+use chrono::NaiveDateTime;
+use defguard_common::db::Id;
+use sqlx::{Error as SqlxError, PgExecutor, PgPool, query, query_as, query_scalar};
+use utoipa::ToSchema;
+
+/// Period during which a device was connected to a location.
+///
+/// Sessions are recorded from VPN client connect and disconnect events. Transferred bytes are
+/// calculated from peer stats collected during the session when it ends.
+#[derive(Clone, Debug)]
+pub struct VpnSession {
+    pub id: Id,
+    pub user_id: Id,
+    pub device_id: Id,
+    pub location_id: Id,
+    pub endpoint: String,
+    pub connected_at: NaiveDateTime,
+    pub disconnected_at: Option<NaiveDateTime>,
+    pub upload: i64,
+    pub download: i64,
+}
+
+/// VPN session with device and location names, as returned by the API.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, ToSchema)]
+pub struct VpnSessionInfo {
+    pub id: Id,
+    pub user_id: Id,
+    pub device_id: Id,
+    pub device_name: String,
+    pub location_id: Id,
+    pub location_name: String,
+    pub endpoint: String,
+    pub connected_at: NaiveDateTime,
+    // `None` while the session is ongoing
+    pub disconnected_at: Option<NaiveDateTime>,
+    pub duration_secs: Option<i64>,
+    // bytes sent to peer
+    pub upload: i64,
+    // bytes received from peer
+    pub download: i64,
+}
+
+impl VpnSession {
+    /// Start a VPN session for the device connected to the location.
+    ///
+    /// A session left open for the same device and location, e.g. when the connection ended while
+    /// Defguard was not running, is closed at the device's latest handshake first.
+    pub async fn start(
+        pool: &PgPool,
+        user_id: Id,
+        device_id: Id,
+        location_id: Id,
+        endpoint: &str,
+        connected_at: NaiveDateTime,
+    ) -> Result<Self, SqlxError> {
+        let mut transaction = pool.begin().await?;
+        query!(
+            "UPDATE vpn_session SET disconnected_at = GREATEST(connected_at, ( \
+                SELECT MAX(latest_handshake) FROM wireguard_peer_stats \
+                WHERE device_id = $2 AND network = $1 \
+                AND collected_at >= vpn_session.connected_at)), \
+            (upload, download) = ( \
+                SELECT COALESCE(MAX(upload) - MIN(upload), 0), \
+                COALESCE(MAX(download) - MIN(download), 0) \
+                FROM wireguard_peer_stats \
+                WHERE device_id = $2 AND network = $1 \
+                AND collected_at >= vpn_session.connected_at) \
+            WHERE location_id = $1 AND device_id = $2 AND disconnected_at IS NULL",
+            location_id,
+            device_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        let session = query_as!(
+            Self,
+            "INSERT INTO vpn_session (user_id, device_id, location_id, endpoint, connected_at) \
+            VALUES ($1, $2, $3, $4, $5) \
+            RETURNING id, user_id, device_id, location_id, endpoint, connected_at, \
+            disconnected_at, upload, download",
+            user_id,
+            device_id,
+            location_id,
+            endpoint,
+            connected_at
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(session)
+    }
+
+    /// Fetch ongoing sessions of the user, oldest first.
+    pub(crate) async fn find_ongoing_by_user<'e, E>(
+        executor: E,
+        user_id: Id,
+    ) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, user_id, device_id, location_id, endpoint, connected_at, disconnected_at, \
+            upload, download FROM vpn_session \
+            WHERE user_id = $1 AND disconnected_at IS NULL ORDER BY connected_at, id",
+            user_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Finish the ongoing VPN session of the device connected to the location.
+    pub async fn finish<'e, E>(
+        executor: E,
+        device_id: Id,
+        location_id: Id,
+        disconnected_at: NaiveDateTime,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        // All stats collected since the session has started belong to it.
+        query!(
+            "UPDATE vpn_session SET disconnected_at = GREATEST(connected_at, $3), \
+            (upload, download) = ( \
+                SELECT COALESCE(MAX(upload) - MIN(upload), 0), \
+                COALESCE(MAX(download) - MIN(download), 0) \
+                FROM wireguard_peer_stats \
+                WHERE device_id = $2 AND network = $1 \
+                AND collected_at >= vpn_session.connected_at) \
+            WHERE location_id = $1 AND device_id = $2 AND disconnected_at IS NULL",
+            location_id,
+            device_id,
+            disconnected_at
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
+
+impl VpnSessionInfo {
+    /// Fetch a page of sessions of the user, most recent first.
+    pub async fn find_by_user<'e, E>(
+        executor: E,
+        user_id: Id,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT s.id, s.user_id, s.device_id, d.name device_name, s.location_id, \
+            n.name location_name, s.endpoint, s.connected_at, s.disconnected_at, \
+            EXTRACT(EPOCH FROM s.disconnected_at - s.connected_at)::bigint duration_secs, \
+            s.upload, s.download \
+            FROM vpn_session s \
+            JOIN device d ON d.id = s.device_id \
+            JOIN wireguard_network n ON n.id = s.location_id \
+            WHERE s.user_id = $1 \
+            ORDER BY s.connected_at DESC, s.id DESC LIMIT $2 OFFSET $3",
+            user_id,
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    pub async fn count_by_user<'e, E>(executor: E, user_id: Id) -> Result<i64, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT count(*) \"count!\" FROM vpn_session WHERE user_id = $1",
+            user_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Fetch a page of sessions of the device, most recent first.
+    pub async fn find_by_device<'e, E>(
+        executor: E,
+        device_id: Id,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT s.id, s.user_id, s.device_id, d.name device_name, s.location_id, \
+            n.name location_name, s.endpoint, s.connected_at, s.disconnected_at, \
+            EXTRACT(EPOCH FROM s.disconnected_at - s.connected_at)::bigint duration_secs, \
+            s.upload, s.download \
+            FROM vpn_session s \
+            JOIN device d ON d.id = s.device_id \
+            JOIN wireguard_network n ON n.id = s.location_id \
+            WHERE s.device_id = $1 \
+            ORDER BY s.connected_at DESC, s.id DESC LIMIT $2 OFFSET $3",
+            device_id,
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    pub async fn count_by_device<'e, E>(executor: E, device_id: Id) -> Result<i64, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT count(*) \"count!\" FROM vpn_session WHERE device_id = $1",
+            device_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+}
@@ -1,7 +1,7 @@
 use defguard_common::db::{Id, NoId, models::ModelError};
 use model_derive::Model;
 use sqlx::{Error as SqlxError, PgExecutor, PgPool, query, query_as, query_scalar};
-use webauthn_rs::prelude::Passkey;
+use webauthn_rs::prelude::{AuthenticationResult, Passkey};
 
 #[derive(Model, Clone, Debug, PartialEq)]
 pub struct WebAuthn<I = NoId> {
@@ -32,6 +32,20 @@ impl<I> WebAuthn<I> {
 
         Ok(passkey)
     }
+
+    /// Applies counters from a successful authentication to the stored [`Passkey`].
+    /// Returns `true` if the passkey has changed and needs to be saved.
+    pub(crate) fn update_credential(
+        &mut self,
+        auth_result: &AuthenticationResult,
+    ) -> Result<bool, ModelError> {
+        let mut passkey = self.passkey()?;
+        if passkey.update_credential(auth_result) != Some(true) {
+            return Ok(false);
+        }
+        self.passkey = serde_cbor::to_vec(&passkey).map_err(|_| ModelError::CannotModify)?;
+        Ok(true)
+    }
 }
 
 impl WebAuthn<Id> {
@@ -1,8 +1,17 @@
-use defguard_common::db::{Id, NoId};
+use std::net::IpAddr;
+
+use chrono::NaiveDateTime;
+use defguard_common::{
+    db::{Id, NoId},
+    hex::hex_decode,
+};
 use model_derive::Model;
-use sqlx::{Error as SqlxError, FromRow, PgPool, query_as};
+use sqlx::{
+    Error as SqlxError, FromRow, PgConnection, PgExecutor, PgPool, query, query_as, query_scalar,
+};
 
 use super::UserInfo;
+use crate::events::GrpcRequestContext;
 
 /// App events which triggers webhook action
 #[derive(Debug)]
@@ -11,6 +20,9 @@ pub enum AppEvent {
     UserModified(UserInfo),
     UserDeleted(String),
     HWKeyProvision(HWKeyUserData),
+    GatewayDisconnected(GatewayDisconnectedData),
+    ClientConnected(ClientConnectionData),
+    ClientDisconnected(ClientConnectionData),
 }
 
 /// User data send on HWKeyProvision AppEvent
@@ -23,6 +35,45 @@ pub struct HWKeyUserData {
     pub serial: String,
 }
 
+/// Gateway data send on GatewayDisconnected AppEvent
+#[derive(Debug, Serialize)]
+pub struct GatewayDisconnectedData {
+    pub location_id: Id,
+    pub location_name: String,
+    pub gateway_name: Option<String>,
+    pub gateway_hostname: String,
+    pub disconnected_at: NaiveDateTime,
+    pub threshold_minutes: i32,
+}
+
+/// VPN client data send on ClientConnected and ClientDisconnected AppEvents
+#[derive(Debug, Serialize)]
+pub struct ClientConnectionData {
+    pub location_id: Id,
+    pub location_name: String,
+    pub user_id: Id,
+    pub username: String,
+    pub device_id: Id,
+    pub device_name: String,
+    pub public_ip: IpAddr,
+    pub timestamp: NaiveDateTime,
+}
+
+impl From<&GrpcRequestContext> for ClientConnectionData {
+    fn from(context: &GrpcRequestContext) -> Self {
+        Self {
+            location_id: context.location.id,
+            location_name: context.location.name.clone(),
+            user_id: context.user_id,
+            username: context.username.clone(),
+            device_id: context.device_id,
+            device_name: context.device_name.clone(),
+            public_ip: context.ip,
+            timestamp: context.timestamp,
+        }
+    }
+}
+
 impl AppEvent {
     // Debug name
     #[must_use]
@@ -32,6 +83,9 @@ impl AppEvent {
             Self::UserModified(_) => "user modified",
             Self::UserDeleted(_) => "user deleted",
             Self::HWKeyProvision(_) => "hwkey provisioned",
+            Self::GatewayDisconnected(_) => "gateway disconnected",
+            Self::ClientConnected(_) => "client connected",
+            Self::ClientDisconnected(_) => "client disconnected",
         }
     }
 
@@ -43,6 +97,18 @@ impl AppEvent {
             Self::UserModified(_) => "on_user_modified",
             Self::UserDeleted(_) => "on_user_deleted",
             Self::HWKeyProvision(_) => "on_hwkey_provision",
+            Self::GatewayDisconnected(_) => "on_gateway_disconnected",
+            Self::ClientConnected(_) => "on_client_connected",
+            Self::ClientDisconnected(_) => "on_client_disconnected",
+        }
+    }
+
+    /// Location the event relates to, used to filter webhooks subscribed to specific locations.
+    #[must_use]
+    pub fn location_id(&self) -> Option<Id> {
+        match self {
+            Self::ClientConnected(data) | Self::ClientDisconnected(data) => Some(data.location_id),
+            _ => None,
         }
     }
 }
@@ -58,18 +124,38 @@ pub struct WebHook<I = NoId> {
     pub on_user_deleted: bool,
     pub on_user_modified: bool,
     pub on_hwkey_provision: bool,
+    #[serde(default)]
+    pub on_gateway_disconnected: bool,
+    #[serde(default)]
+    pub on_client_connected: bool,
+    #[serde(default)]
+    pub on_client_disconnected: bool,
+    /// Key used to sign payloads with HMAC-SHA256, if set.
+    #[serde(default)]
+    pub secret: Option<String>,
 }
 
 impl WebHook<Id> {
     /// Fetch all enabled webhooks.
+    ///
+    /// For events related to a location, webhooks subscribed to specific locations are only
+    /// returned if the location is one of them. Webhooks without any locations receive events
+    /// from all locations.
     pub async fn all_enabled(pool: &PgPool, trigger: &AppEvent) -> Result<Vec<Self>, SqlxError> {
         let column_name = trigger.column_name();
         let query = format!(
             "SELECT id, url, description, token, enabled, on_user_created, \
-            on_user_deleted, on_user_modified, on_hwkey_provision FROM webhook \
-            WHERE enabled AND {column_name}"
+            on_user_deleted, on_user_modified, on_hwkey_provision, on_gateway_disconnected, \
+            on_client_connected, on_client_disconnected, secret \
+            FROM webhook WHERE enabled AND {column_name} AND ($1::bigint IS NULL \
+            OR NOT EXISTS (SELECT 1 FROM webhook_location WHERE webhook_id = webhook.id) \
+            OR EXISTS (SELECT 1 FROM webhook_location \
+            WHERE webhook_id = webhook.id AND location_id = $1))"
         );
-        query_as(&query).fetch_all(pool).await
+        query_as(&query)
+            .bind(trigger.location_id())
+            .fetch_all(pool)
+            .await
     }
 
     /// Find [`WebHook`] by URL.
@@ -77,10 +163,99 @@ impl WebHook<Id> {
         query_as!(
             Self,
             "SELECT id, url, description, token, enabled, on_user_created, \
-            on_user_deleted, on_user_modified, on_hwkey_provision FROM webhook WHERE url = $1",
+            on_user_deleted, on_user_modified, on_hwkey_provision, on_gateway_disconnected, \
+            on_client_connected, on_client_disconnected, secret \
+            FROM webhook WHERE url = $1",
             url
         )
         .fetch_optional(pool)
         .await
     }
+
+    /// IDs of locations this webhook is subscribed to.
+    pub async fn locations<'e, E>(&self, executor: E) -> Result<Vec<Id>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT location_id FROM webhook_location WHERE webhook_id = $1 ORDER BY location_id",
+            self.id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Replace locations this webhook is subscribed to.
+    pub async fn set_locations(
+        &self,
+        transaction: &mut PgConnection,
+        locations: &[Id],
+    ) -> Result<(), SqlxError> {
+        query!(
+            "DELETE FROM webhook_location WHERE webhook_id = $1",
+            self.id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        query!(
+            "INSERT INTO webhook_location (webhook_id, location_id) \
+            SELECT $1, location_id FROM UNNEST($2::bigint[]) location_id",
+            self.id,
+            locations
+        )
+        .execute(&mut *transaction)
+        .await?;
+        Ok(())
+    }
+
+    /// Signature of the payload sent in the `x-defguard-signature` header, if this webhook has a
+    /// secret set.
+    #[must_use]
+    pub fn signature(&self, payload: &[u8]) -> Option<String> {
+        let secret = self.secret.as_ref().filter(|secret| !secret.is_empty())?;
+        Some(format!(
+            "sha256={}",
+            hmac_sha256(secret.as_bytes(), payload)
+        ))
+    }
+}
+
+// SHA-256 block size in bytes
+const SHA256_BLOCK_SIZE: usize = 64;
+
+/// HMAC-SHA256 as defined in RFC 2104, returned as a lowercase hex string.
+fn hmac_sha256(key: &[u8], message: &[u8]) -> String {
+    let hash = |data: &[u8]| hex_decode(sha256::digest(data)).expect("digest is a hex string");
+    let mut block_key = if key.len() > SHA256_BLOCK_SIZE {
+        hash(key)
+    } else {
+        key.to_vec()
+    };
+    block_key.resize(SHA256_BLOCK_SIZE, 0);
+    let mut inner: Vec<u8> = block_key.iter().map(|byte| byte ^ 0x36).collect();
+    inner.extend_from_slice(message);
+    let mut outer: Vec<u8> = block_key.iter().map(|byte| byte ^ 0x5c).collect();
+    outer.extend(hash(&inner));
+    sha256::digest(outer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256() {
+        // test cases 2 and 6 from RFC 4231
+        assert_eq!(
+            hmac_sha256(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
 }
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::{self, Display},
     iter::zip,
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
@@ -16,15 +16,16 @@ use defguard_proto::{
     enterprise::firewall::FirewallConfig,
     gateway::Peer,
     proxy::{
-        LocationMfaMode as ProtoLocationMfaMode, ServiceLocationMode as ProtoServiceLocationMode,
+        LocationMfaMode as ProtoLocationMfaMode, MfaMethod,
+        ServiceLocationMode as ProtoServiceLocationMode,
     },
 };
 use ipnetwork::{IpNetwork, IpNetworkError, NetworkSize};
 use model_derive::Model;
-use rand::rngs::OsRng;
+use rand::{Rng, rngs::OsRng, thread_rng};
 use sqlx::{
     Error as SqlxError, FromRow, PgConnection, PgExecutor, PgPool, Type,
-    postgres::types::PgInterval, query_as, query_scalar,
+    postgres::types::PgInterval, query, query_as, query_scalar,
 };
 use thiserror::Error;
 use tokio::sync::broadcast::Sender;
@@ -36,6 +37,8 @@ use super::{
     device::{
         Device, DeviceError, DeviceInfo, DeviceNetworkInfo, DeviceType, WireguardNetworkDevice,
     },
+    group::{NetworkAccessWindow, NetworkGroupPool},
+    trusted_device::TrustedDevice,
     user::User,
     wireguard_peer_stats::WireguardPeerStats,
 };
@@ -90,6 +93,37 @@ pub enum GatewayEvent {
     DeviceDeleted(DeviceInfo),
     FirewallConfigChanged(Id, FirewallConfig),
     FirewallDisabled(Id),
+    GatewayTokenRevoked(Id, Id),
+}
+
+/// Describes the event by its type and IDs only, so that logging it doesn't leak keys.
+impl Display for GatewayEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NetworkCreated(network_id, _) => {
+                write!(f, "NetworkCreated(network {network_id})")
+            }
+            Self::NetworkModified(network_id, ..) => {
+                write!(f, "NetworkModified(network {network_id})")
+            }
+            Self::NetworkDeleted(network_id, _) => {
+                write!(f, "NetworkDeleted(network {network_id})")
+            }
+            Self::DeviceCreated(info) => write!(f, "DeviceCreated(device {})", info.device.id),
+            Self::DeviceModified(info) => write!(f, "DeviceModified(device {})", info.device.id),
+            Self::DeviceDeleted(info) => write!(f, "DeviceDeleted(device {})", info.device.id),
+            Self::FirewallConfigChanged(network_id, _) => {
+                write!(f, "FirewallConfigChanged(network {network_id})")
+            }
+            Self::FirewallDisabled(network_id) => {
+                write!(f, "FirewallDisabled(network {network_id})")
+            }
+            Self::GatewayTokenRevoked(network_id, token_id) => write!(
+                f,
+                "GatewayTokenRevoked(network {network_id}, token {token_id})"
+            ),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize, ToSchema, Type)]
@@ -133,6 +167,33 @@ impl From<LocationMfaMode> for ProtoLocationMfaMode {
     }
 }
 
+/// Internal MFA method which can be allowed in a location.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize, ToSchema, Type)]
+#[sqlx(type_name = "location_mfa_method", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum LocationMfaMethod {
+    Totp,
+    Email,
+    Webauthn,
+    Biometric,
+    MobileApprove,
+}
+
+impl LocationMfaMethod {
+    /// Maps MFA method selected by the desktop client. External (OpenID) MFA has no counterpart.
+    #[must_use]
+    pub fn from_proto(method: MfaMethod) -> Option<Self> {
+        match method {
+            MfaMethod::Totp => Some(Self::Totp),
+            MfaMethod::Email => Some(Self::Email),
+            MfaMethod::Webauthn => Some(Self::Webauthn),
+            MfaMethod::Biometric => Some(Self::Biometric),
+            MfaMethod::MobileApprove => Some(Self::MobileApprove),
+            MfaMethod::Oidc => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize, ToSchema, Type)]
 #[sqlx(type_name = "service_location_mode", rename_all = "lowercase")]
 #[serde(rename_all = "lowercase")]
@@ -165,6 +226,133 @@ impl From<ServiceLocationMode> for ProtoServiceLocationMode {
     }
 }
 
+/// Policy used to pick an IP address for a new device in a location.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize, ToSchema, Type,
+)]
+#[sqlx(type_name = "ip_assignment_strategy", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum IpAssignmentStrategy {
+    /// First free address, counting from the start of the network.
+    #[default]
+    Sequential,
+    /// Free address closest to a randomly chosen point in the network.
+    Random,
+    /// Free address closest to a point derived from the device public key,
+    /// so the same device gets the same address on re-creation.
+    PubkeyHash,
+}
+
+impl IpAssignmentStrategy {
+    /// Offset within a network of `network_size` addresses from which the search
+    /// for a free address should start.
+    pub(crate) fn start_offset(self, network_size: u128, pubkey: &str) -> u128 {
+        if network_size == 0 {
+            return 0;
+        }
+        match self {
+            Self::Sequential => 0,
+            Self::Random => thread_rng().gen_range(0..network_size),
+            Self::PubkeyHash => {
+                let digest = sha256::digest(pubkey);
+                // 32 hex digits make exactly 128 bits.
+                u128::from_str_radix(&digest[..32], 16).unwrap_or_default() % network_size
+            }
+        }
+    }
+}
+
+/// Number of addresses in `network`, including network and broadcast addresses.
+pub(crate) fn network_size(network: &IpNetwork) -> u128 {
+    let max_prefix = if network.is_ipv4() { 32 } else { 128 };
+    let host_bits = u32::from(max_prefix - network.prefix());
+    1u128.checked_shl(host_bits).unwrap_or(u128::MAX)
+}
+
+/// Iterates over all addresses in `network`, starting at `start` offset and wrapping around
+/// to the beginning of the network.
+pub(crate) fn network_ips_from(network: IpNetwork, start: u128) -> impl Iterator<Item = IpAddr> {
+    let size = network_size(&network);
+    let start = start.min(size.saturating_sub(1));
+    (0..size).map(move |step| {
+        let offset = if step < size - start {
+            start + step
+        } else {
+            step - (size - start)
+        };
+        match network {
+            IpNetwork::V4(net) => {
+                // Offset always fits, IPv4 network has at most 2^32 addresses.
+                let offset = u32::try_from(offset).unwrap_or_default();
+                IpAddr::V4(Ipv4Addr::from(
+                    u32::from(net.network()).wrapping_add(offset),
+                ))
+            }
+            IpNetwork::V6(net) => IpAddr::V6(Ipv6Addr::from(
+                u128::from(net.network()).wrapping_add(offset),
+            )),
+        }
+    })
+}
+
+/// Address at the same offset in the corresponding network of `to` as `ip` is in its network of
+/// `from`. Networks are matched by position, e.g. to carry addresses over to a cloned location.
+pub(crate) fn translate_ip(ip: IpAddr, from: &[IpNetwork], to: &[IpNetwork]) -> Option<IpAddr> {
+    let index = from.iter().position(|network| network.contains(ip))?;
+    let target = to.get(index)?;
+    let offset = match (ip, from[index].network(), target) {
+        (IpAddr::V4(ip), IpAddr::V4(network), IpNetwork::V4(_)) => {
+            u128::from(u32::from(ip) - u32::from(network))
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network), IpNetwork::V6(_)) => {
+            u128::from(ip) - u128::from(network)
+        }
+        _ => return None,
+    };
+    if offset >= network_size(target) {
+        return None;
+    }
+    network_ips_from(*target, offset).next()
+}
+
+/// Addresses which may be assigned to devices of a given user in a network, as limited by
+/// address pools of the network's allowed groups.
+#[derive(Debug, Default)]
+pub(crate) struct DeviceAddressScope {
+    // pool of the user's allowed group with the highest priority
+    pool: Vec<IpNetwork>,
+    // pools of other groups, unavailable to the user
+    excluded: Vec<IpNetwork>,
+}
+
+impl DeviceAddressScope {
+    /// Part of network `address` to search for a free device address in.
+    pub(crate) fn range(&self, address: &IpNetwork) -> IpNetwork {
+        self.pool
+            .iter()
+            .find(|pool| address.contains(pool.network()))
+            .copied()
+            .unwrap_or(*address)
+    }
+
+    /// Checks if `ip` from network `address` may be assigned to the device.
+    pub(crate) fn allows(&self, address: &IpNetwork, ip: IpAddr) -> bool {
+        let range = self.range(address);
+        range.contains(ip)
+            && (range != *address || !self.excluded.iter().any(|pool| pool.contains(ip)))
+    }
+
+    /// Checks if all `ips` may be assigned to the device in a network with given `addresses`.
+    pub(crate) fn allows_all(&self, addresses: &[IpNetwork], ips: &[IpAddr]) -> bool {
+        ips.iter().all(|ip| {
+            addresses
+                .iter()
+                .find(|address| address.contains(*ip))
+                .is_some_and(|address| self.allows(address, *ip))
+        })
+    }
+}
+
 /// Stores configuration required to setup a WireGuard network
 #[derive(Clone, Deserialize, Eq, Hash, Model, PartialEq, Serialize, ToSchema)]
 #[table(wireguard_network)]
@@ -192,6 +380,9 @@ pub struct WireguardNetwork<I = NoId> {
     pub location_mfa_mode: LocationMfaMode,
     #[model(enum)]
     pub service_location_mode: ServiceLocationMode,
+    #[model(enum)]
+    #[serde(default)]
+    pub ip_assignment_strategy: IpAssignmentStrategy,
 }
 
 pub struct WireguardKey {
@@ -230,6 +421,7 @@ impl fmt::Debug for WireguardNetwork<Id> {
             .field("peer_disconnect_threshold", &self.peer_disconnect_threshold)
             .field("location_mfa_mode", &self.location_mfa_mode)
             .field("service_location_mode", &self.service_location_mode)
+            .field("ip_assignment_strategy", &self.ip_assignment_strategy)
             .finish()
     }
 }
@@ -254,6 +446,7 @@ impl Default for WireguardNetwork<Id> {
             acl_enabled: false,
             location_mfa_mode: LocationMfaMode::default(),
             service_location_mode: ServiceLocationMode::default(),
+            ip_assignment_strategy: IpAssignmentStrategy::default(),
         }
     }
 }
@@ -336,6 +529,7 @@ impl WireguardNetwork {
             acl_default_allow,
             location_mfa_mode,
             service_location_mode,
+            ip_assignment_strategy: IpAssignmentStrategy::default(),
         }
     }
 
@@ -367,7 +561,8 @@ impl WireguardNetwork<Id> {
             "SELECT id, name, address, port, pubkey, prvkey, endpoint, dns, allowed_ips, \
             connected_at, keepalive_interval, peer_disconnect_threshold, \
             acl_enabled, acl_default_allow, location_mfa_mode \"location_mfa_mode: LocationMfaMode\", \
-            service_location_mode \"service_location_mode: ServiceLocationMode\" \
+            service_location_mode \"service_location_mode: ServiceLocationMode\", \
+            ip_assignment_strategy \"ip_assignment_strategy: IpAssignmentStrategy\" \
             FROM wireguard_network WHERE name = $1",
             name
         )
@@ -445,6 +640,92 @@ impl WireguardNetwork<Id> {
         }
     }
 
+    /// Get addresses which may be assigned to devices of a given user. Devices of members of
+    /// allowed groups with address pools get addresses from the pool of the group with the
+    /// highest priority, devices of other users get addresses outside of all pools.
+    pub(crate) async fn device_address_scope<'e, E>(
+        &self,
+        executor: E,
+        user_id: Id,
+    ) -> Result<DeviceAddressScope, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let pools = query!(
+            "SELECT wag.address_pool, EXISTS ( \
+                SELECT 1 FROM group_user_effective gu \
+                WHERE gu.group_id = wag.group_id AND gu.user_id = $2 \
+            ) \"member!\" \
+            FROM wireguard_network_allowed_group wag \
+            WHERE wag.network_id = $1 AND cardinality(wag.address_pool) > 0 \
+            ORDER BY wag.priority DESC, wag.group_id",
+            self.id,
+            user_id
+        )
+        .fetch_all(executor)
+        .await?;
+
+        let mut scope = DeviceAddressScope::default();
+        for pool in pools {
+            if pool.member && scope.pool.is_empty() {
+                scope.pool = pool.address_pool;
+            } else {
+                scope.excluded.extend(pool.address_pool);
+            }
+        }
+
+        Ok(scope)
+    }
+
+    /// Returns location settings used to generate configuration of a given device. Routes and DNS
+    /// overrides of the highest priority allowed groups the device owner belongs to replace
+    /// location settings, DNS search domains are appended to DNS servers. Group overrides only
+    /// affect user devices, network devices always use location settings.
+    pub(crate) async fn apply_device_settings<'e, E>(
+        mut self,
+        executor: E,
+        device: &Device<Id>,
+    ) -> Result<Self, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let user_id = (device.device_type == DeviceType::User).then_some(device.user_id);
+        let settings = query!(
+            "SELECT n.dns_search_domains, \
+            (SELECT wag.allowed_ips FROM wireguard_network_allowed_group wag \
+                JOIN group_user_effective gu ON gu.group_id = wag.group_id \
+                WHERE wag.network_id = n.id AND gu.user_id = $2 \
+                AND cardinality(wag.allowed_ips) > 0 \
+                ORDER BY wag.priority DESC, wag.group_id LIMIT 1) \"group_allowed_ips\", \
+            (SELECT wag.dns FROM wireguard_network_allowed_group wag \
+                JOIN group_user_effective gu ON gu.group_id = wag.group_id \
+                WHERE wag.network_id = n.id AND gu.user_id = $2 AND wag.dns IS NOT NULL \
+                ORDER BY wag.priority DESC, wag.group_id LIMIT 1) \"group_dns\", \
+            (SELECT wag.dns_search_domains FROM wireguard_network_allowed_group wag \
+                JOIN group_user_effective gu ON gu.group_id = wag.group_id \
+                WHERE wag.network_id = n.id AND gu.user_id = $2 \
+                AND cardinality(wag.dns_search_domains) > 0 \
+                ORDER BY wag.priority DESC, wag.group_id LIMIT 1) \"group_dns_search_domains\" \
+            FROM wireguard_network n WHERE n.id = $1",
+            self.id,
+            user_id
+        )
+        .fetch_one(executor)
+        .await?;
+        if let Some(allowed_ips) = settings.group_allowed_ips {
+            self.allowed_ips = allowed_ips;
+        }
+        if let Some(dns) = settings.group_dns {
+            self.dns = Some(dns);
+        }
+        let search_domains = settings
+            .group_dns_search_domains
+            .unwrap_or(settings.dns_search_domains);
+        self.dns = join_dns(self.dns, &search_domains);
+
+        Ok(self)
+    }
+
     /// Get a list of all devices belonging to users in allowed groups.
     /// Admin users should always be allowed to access a network.
     /// Note: Doesn't check if the devices are really in the network.
@@ -462,7 +743,7 @@ impl WireguardNetwork<Id> {
                 configured
                 FROM device d \
                 JOIN \"user\" u ON d.user_id = u.id \
-                JOIN group_user gu ON u.id = gu.user_id \
+                JOIN group_user_effective gu ON u.id = gu.user_id \
                 JOIN \"group\" g ON gu.group_id = g.id \
                 WHERE g.\"name\" IN (SELECT * FROM UNNEST($1::text[])) \
                 AND u.is_active = true \
@@ -510,7 +791,7 @@ impl WireguardNetwork<Id> {
                 configured
                 FROM device d \
                 JOIN \"user\" u ON d.user_id = u.id \
-                JOIN group_user gu ON u.id = gu.user_id \
+                JOIN group_user_effective gu ON u.id = gu.user_id \
                 JOIN \"group\" g ON gu.group_id = g.id \
                 WHERE g.\"name\" IN (SELECT * FROM UNNEST($1::text[])) \
                 AND u.is_active = true \
@@ -562,6 +843,65 @@ impl WireguardNetwork<Id> {
         Ok(())
     }
 
+    /// Assign IPs in network for all existing devices, keeping their offsets from the `source`
+    /// network where possible, e.g. `10.1.1.5` becomes `10.2.1.5`. Reservations are copied along
+    /// with the addresses. Devices which can't keep their offsets get the next free addresses.
+    pub(crate) async fn add_all_allowed_devices_from(
+        &self,
+        transaction: &mut PgConnection,
+        source: &WireguardNetwork<Id>,
+    ) -> Result<(), ModelError> {
+        info!("Assigning IPs in network {self} for all existing devices as in network {source}");
+        let mut source_devices: HashMap<Id, WireguardNetworkDevice> =
+            WireguardNetworkDevice::all_for_network(&mut *transaction, source.id)
+                .await?
+                .into_iter()
+                .map(|network_device| (network_device.device_id, network_device))
+                .collect();
+        let reserved_devices =
+            WireguardNetworkDevice::reserved_for_network(&mut *transaction, source.id).await?;
+
+        // keep offsets first, so that automatically assigned addresses don't take them
+        let mut remaining = Vec::new();
+        for device in self.get_allowed_devices(&mut *transaction).await? {
+            let ips = source_devices
+                .remove(&device.id)
+                .and_then(|network_device| {
+                    network_device
+                        .wireguard_ips
+                        .iter()
+                        .map(|ip| translate_ip(*ip, &source.address, &self.address))
+                        .collect::<Option<Vec<_>>>()
+                })
+                .filter(|ips| ips.len() == self.address.len());
+            let Some(ips) = ips else {
+                remaining.push(device);
+                continue;
+            };
+            if self
+                .can_assign_ips(&mut *transaction, &ips, Some(device.id))
+                .await
+                .is_err()
+            {
+                remaining.push(device);
+                continue;
+            }
+            let network_device = WireguardNetworkDevice::new(self.id, device.id, ips);
+            network_device.insert(&mut *transaction).await?;
+            if reserved_devices.contains(&device.id) {
+                network_device
+                    .set_ip_reserved(&mut *transaction, true)
+                    .await?;
+            }
+        }
+        for device in remaining {
+            device
+                .assign_next_network_ip(&mut *transaction, self, None, None)
+                .await?;
+        }
+        Ok(())
+    }
+
     /// Generate network IPs for a device if it's allowed in network
     pub(crate) async fn add_device_to_network(
         &self,
@@ -610,12 +950,38 @@ impl WireguardNetwork<Id> {
         // when necessary; remove processed entry from all devices list initial list should
         // now contain only devices to be added.
         let mut events: Vec<GatewayEvent> = Vec::new();
+        let pools_configured = self
+            .fetch_group_pools(&mut *transaction)
+            .await?
+            .iter()
+            .any(|pool| !pool.address_pool.is_empty());
+        let reserved_devices: HashSet<Id> =
+            WireguardNetworkDevice::reserved_for_network(&mut *transaction, self.id)
+                .await?
+                .into_iter()
+                .collect();
+        let mut address_scopes = HashMap::new();
         for device_network_config in currently_configured_devices {
             // Device is allowed and an IP was already assigned
             if let Some(device) = allowed_devices.remove(&device_network_config.device_id) {
+                // Device address pool may have changed along with user groups or network settings.
+                // Reserved addresses are kept as long as they are within the network.
+                let ip_reserved = reserved_devices.contains(&device.id);
+                let mut in_address_scope = true;
+                if pools_configured && !ip_reserved {
+                    if !address_scopes.contains_key(&device.user_id) {
+                        let scope = self
+                            .device_address_scope(&mut *transaction, device.user_id)
+                            .await?;
+                        address_scopes.insert(device.user_id, scope);
+                    }
+                    in_address_scope = address_scopes[&device.user_id]
+                        .allows_all(&self.address, &device_network_config.wireguard_ips);
+                }
                 // Network address has changed and IP addresses need to be updated
                 if !self.contains_all(&device_network_config.wireguard_ips)
                     || self.address.len() != device_network_config.wireguard_ips.len()
+                    || !in_address_scope
                 {
                     let wireguard_network_device = device
                         .assign_next_network_ip(
@@ -625,6 +991,15 @@ impl WireguardNetwork<Id> {
                             Some(&device_network_config.wireguard_ips),
                         )
                         .await?;
+                    if ip_reserved {
+                        info!(
+                            "Reserved addresses of device {device} no longer match network {self}, \
+                            reassigned them automatically"
+                        );
+                        wireguard_network_device
+                            .set_ip_reserved(&mut *transaction, false)
+                            .await?;
+                    }
                     events.push(GatewayEvent::DeviceModified(DeviceInfo {
                         device,
                         network_info: vec![DeviceNetworkInfo {
@@ -645,6 +1020,12 @@ impl WireguardNetwork<Id> {
                 if let Some(device) =
                     Device::find_by_id(&mut *transaction, device_network_config.device_id).await?
                 {
+                    TrustedDevice::delete_for_device(
+                        &mut *transaction,
+                        self.id,
+                        &device.wireguard_pubkey,
+                    )
+                    .await?;
                     events.push(GatewayEvent::DeviceDeleted(DeviceInfo {
                         device,
                         network_info: vec![DeviceNetworkInfo {
@@ -870,7 +1251,7 @@ impl WireguardNetwork<Id> {
                 // fetch user info
                 None => match User::find_by_id(&mut *transaction, device.user_id).await? {
                     Some(user) => {
-                        let groups = user.member_of_names(&mut *transaction).await?;
+                        let groups = user.effective_group_names(&mut *transaction).await?;
                         user_groups.insert(device.user_id, groups);
                         // FIXME: ugly workaround to get around `groups` being dropped
                         user_groups.get(&device.user_id).unwrap()
@@ -1299,6 +1680,316 @@ impl WireguardNetwork<Id> {
         }
     }
 
+    /// Internal MFA methods accepted in this location. Empty list means all methods are allowed.
+    pub async fn allowed_mfa_methods<'e, E>(
+        &self,
+        executor: E,
+    ) -> Result<Vec<LocationMfaMethod>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT allowed_mfa_methods \"allowed_mfa_methods: Vec<LocationMfaMethod>\" \
+            FROM wireguard_network WHERE id = $1",
+            self.id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub async fn set_allowed_mfa_methods<'e, E>(
+        &self,
+        executor: E,
+        methods: &[LocationMfaMethod],
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        info!("Setting allowed MFA methods for network {self} to: {methods:?}");
+        query!(
+            "UPDATE wireguard_network SET allowed_mfa_methods = $2 WHERE id = $1",
+            self.id,
+            methods as &[LocationMfaMethod]
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Whether desktop clients may skip MFA on devices trusted after a previous login.
+    pub async fn mfa_device_trust_enabled<'e, E>(&self, executor: E) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT mfa_device_trust_enabled FROM wireguard_network WHERE id = $1",
+            self.id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub async fn set_mfa_device_trust_enabled<'e, E>(
+        &self,
+        executor: E,
+        enabled: bool,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        info!("Setting MFA device trust for network {self} to: {enabled}");
+        query!(
+            "UPDATE wireguard_network SET mfa_device_trust_enabled = $2 WHERE id = $1",
+            self.id,
+            enabled
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Whether devices added to this location wait for an admin approval before their peers are
+    /// pushed to gateways.
+    pub async fn device_approval_required<'e, E>(&self, executor: E) -> Result<bool, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT device_approval_required FROM wireguard_network WHERE id = $1",
+            self.id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub async fn set_device_approval_required<'e, E>(
+        &self,
+        executor: E,
+        required: bool,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        info!("Setting device approval requirement for network {self} to: {required}");
+        query!(
+            "UPDATE wireguard_network SET device_approval_required = $2 WHERE id = $1",
+            self.id,
+            required
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// DNS search domains pushed to devices along with DNS servers.
+    pub async fn dns_search_domains<'e, E>(&self, executor: E) -> Result<Vec<String>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT dns_search_domains FROM wireguard_network WHERE id = $1",
+            self.id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub async fn set_dns_search_domains<'e, E>(
+        &self,
+        executor: E,
+        domains: &[String],
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        info!("Setting DNS search domains for network {self} to: {domains:?}");
+        query!(
+            "UPDATE wireguard_network SET dns_search_domains = $2 WHERE id = $1",
+            self.id,
+            domains
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Interval in hours after which preshared keys of location peers are regenerated.
+    /// `None` means preshared keys are only used by MFA logins.
+    pub async fn psk_rotation_interval<'e, E>(&self, executor: E) -> Result<Option<i32>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT psk_rotation_interval FROM wireguard_network WHERE id = $1",
+            self.id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub async fn set_psk_rotation_interval<'e, E>(
+        &self,
+        executor: E,
+        interval: Option<i32>,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        info!("Setting preshared key rotation interval for network {self} to: {interval:?}");
+        query!(
+            "UPDATE wireguard_network SET psk_rotation_interval = $2, psk_rotated_at = NULL \
+            WHERE id = $1",
+            self.id,
+            interval
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Maintenance state of the location, shown to users and admins.
+    pub async fn maintenance<'e, E>(&self, executor: E) -> Result<LocationMaintenance, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            LocationMaintenance,
+            "SELECT maintenance_enabled enabled, maintenance_message message \
+            FROM wireguard_network WHERE id = $1",
+            self.id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub async fn set_maintenance<'e, E>(
+        &self,
+        executor: E,
+        maintenance: &LocationMaintenance,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        info!("Setting maintenance of network {self} to: {maintenance:?}");
+        query!(
+            "UPDATE wireguard_network SET maintenance_enabled = $2, maintenance_message = $3 \
+            WHERE id = $1",
+            self.id,
+            maintenance.enabled,
+            maintenance.message
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Revoke authorization of all devices logged in to the location with MFA. Returns revoked
+    /// authorizations to disconnect the devices from gateways.
+    pub(crate) async fn revoke_mfa_authorizations(
+        &self,
+        transaction: &mut PgConnection,
+    ) -> Result<Vec<DeviceInfo>, SqlxError> {
+        let mut revoked = Vec::new();
+        for mut network_device in
+            WireguardNetworkDevice::all_for_network(&mut *transaction, self.id)
+                .await?
+                .into_iter()
+                .filter(|network_device| network_device.is_authorized)
+        {
+            let Some(device) =
+                Device::find_by_id(&mut *transaction, network_device.device_id).await?
+            else {
+                continue;
+            };
+            info!("Revoking authorization of device {device} in location {self}");
+            network_device.is_authorized = false;
+            network_device.preshared_key = None;
+            network_device.update(&mut *transaction).await?;
+            revoked.push(DeviceInfo {
+                device,
+                network_info: vec![DeviceNetworkInfo {
+                    network_id: self.id,
+                    device_wireguard_ips: network_device.wireguard_ips,
+                    preshared_key: network_device.preshared_key,
+                    is_authorized: network_device.is_authorized,
+                }],
+            });
+        }
+        Ok(revoked)
+    }
+
+    /// Remove preshared keys of all location peers, e.g. after preshared key rotation has been
+    /// disabled for a location without MFA.
+    pub(crate) async fn clear_preshared_keys<'e, E>(&self, executor: E) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "UPDATE wireguard_network_device SET preshared_key = NULL \
+            WHERE wireguard_network_id = $1",
+            self.id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// IDs of locations without MFA whose preshared keys are due for rotation.
+    pub(crate) async fn find_due_for_psk_rotation<'e, E>(
+        executor: E,
+        now: NaiveDateTime,
+    ) -> Result<Vec<Id>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT id FROM wireguard_network \
+            WHERE psk_rotation_interval IS NOT NULL \
+            AND location_mfa_mode = 'disabled'::location_mfa_mode \
+            AND (psk_rotated_at IS NULL \
+                OR psk_rotated_at + make_interval(hours => psk_rotation_interval) <= $1)",
+            now
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Generate new preshared keys for all peers of the location.
+    /// Returns device info with new keys, which has to be sent to gateways.
+    pub(crate) async fn rotate_preshared_keys(
+        &self,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<DeviceInfo>, ModelError> {
+        let mut devices = Vec::new();
+        for mut network_device in
+            WireguardNetworkDevice::all_for_network(&mut *conn, self.id).await?
+        {
+            let Some(device) = Device::find_by_id(&mut *conn, network_device.device_id).await?
+            else {
+                continue;
+            };
+            network_device.preshared_key = Some(Self::genkey().public);
+            network_device.update(&mut *conn).await?;
+            devices.push(DeviceInfo {
+                device,
+                network_info: vec![DeviceNetworkInfo {
+                    network_id: self.id,
+                    device_wireguard_ips: network_device.wireguard_ips,
+                    preshared_key: network_device.preshared_key,
+                    is_authorized: network_device.is_authorized,
+                }],
+            });
+        }
+        query!(
+            "UPDATE wireguard_network SET psk_rotated_at = NOW() AT TIME ZONE 'UTC' WHERE id = $1",
+            self.id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(devices)
+    }
+
     // fetch all locations using external MFA
     pub(crate) async fn all_using_external_mfa<'e, E>(
         executor: E,
@@ -1311,7 +2002,8 @@ impl WireguardNetwork<Id> {
             "SELECT id, name, address, port, pubkey, prvkey, endpoint, dns, allowed_ips, \
             connected_at, keepalive_interval, peer_disconnect_threshold, acl_enabled, \
             acl_default_allow, location_mfa_mode \"location_mfa_mode: LocationMfaMode\", \
-            service_location_mode \"service_location_mode: ServiceLocationMode\" \
+            service_location_mode \"service_location_mode: ServiceLocationMode\", \
+            ip_assignment_strategy \"ip_assignment_strategy: IpAssignmentStrategy\" \
             FROM wireguard_network WHERE location_mfa_mode = 'external'::location_mfa_mode",
         )
         .fetch_all(executor)
@@ -1364,10 +2056,21 @@ impl Default for WireguardNetwork {
             acl_default_allow: false,
             location_mfa_mode: LocationMfaMode::default(),
             service_location_mode: ServiceLocationMode::default(),
+            ip_assignment_strategy: IpAssignmentStrategy::default(),
         }
     }
 }
 
+/// Location maintenance, e.g. before gateway upgrades. New desktop client MFA logins are rejected
+/// while it's enabled.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize, ToSchema)]
+pub struct LocationMaintenance {
+    pub enabled: bool,
+    /// Message shown to users, e.g. expected end of the maintenance.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
 #[derive(Serialize, ToSchema)]
 pub struct WireguardNetworkInfo {
     #[serde(flatten)]
@@ -1375,6 +2078,14 @@ pub struct WireguardNetworkInfo {
     pub connected: bool,
     pub gateways: Vec<GatewayState>,
     pub allowed_groups: Vec<String>,
+    pub group_pools: Vec<NetworkGroupPool>,
+    pub access_windows: Vec<NetworkAccessWindow>,
+    pub allowed_mfa_methods: Vec<LocationMfaMethod>,
+    pub mfa_device_trust_enabled: bool,
+    pub psk_rotation_interval: Option<i32>,
+    pub dns_search_domains: Vec<String>,
+    pub maintenance: LocationMaintenance,
+    pub device_approval_required: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialEq)]
@@ -1498,6 +2209,18 @@ pub(crate) async fn networks_stats(
 
 // If `force_all_traffic` setting is enabled we override the allowed_ips
 // to also enforce this on legacy clients.
+/// DNS entries of WireGuard configuration: servers followed by search domains. Search domains
+/// are only added if DNS servers are configured.
+#[must_use]
+fn join_dns(servers: Option<String>, search_domains: &[String]) -> Option<String> {
+    match servers {
+        Some(servers) if !servers.trim().is_empty() && !search_domains.is_empty() => {
+            Some(format!("{servers}, {}", search_domains.join(", ")))
+        }
+        servers => servers,
+    }
+}
+
 pub fn get_allowed_ips_for_device(
     enterprise_settings: &EnterpriseSettings,
     location: &WireguardNetwork<Id>,
@@ -1526,6 +2249,96 @@ mod test {
     use super::*;
     use crate::db::Group;
 
+    #[test]
+    fn test_join_dns() {
+        let domains = vec!["corp.example.com".to_string(), "example.com".to_string()];
+        assert_eq!(
+            join_dns(Some("10.0.0.53, 10.0.0.54".into()), &domains),
+            Some("10.0.0.53, 10.0.0.54, corp.example.com, example.com".into())
+        );
+        assert_eq!(
+            join_dns(Some("10.0.0.53".into()), &[]),
+            Some("10.0.0.53".into())
+        );
+        // empty DNS disables DNS in generated configuration
+        assert_eq!(join_dns(Some(String::new()), &domains), Some(String::new()));
+        assert_eq!(join_dns(None, &domains), None);
+    }
+
+    #[test]
+    fn test_network_ips_from() {
+        let network = IpNetwork::from_str("10.1.1.0/30").unwrap();
+        assert_eq!(network_size(&network), 4);
+        let ips: Vec<IpAddr> = network_ips_from(network, 2).collect();
+        assert_eq!(
+            ips,
+            ["10.1.1.2", "10.1.1.3", "10.1.1.0", "10.1.1.1"]
+                .map(|ip| IpAddr::from_str(ip).unwrap())
+        );
+
+        let network = IpNetwork::from_str("fd00::/64").unwrap();
+        assert_eq!(network_size(&network), 1 << 64);
+        let ips: Vec<IpAddr> = network_ips_from(network, (1 << 64) - 1).take(2).collect();
+        assert_eq!(
+            ips,
+            ["fd00::ffff:ffff:ffff:ffff", "fd00::"].map(|ip| IpAddr::from_str(ip).unwrap())
+        );
+
+        let pubkey = "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=";
+        assert_eq!(
+            IpAssignmentStrategy::Sequential.start_offset(256, pubkey),
+            0
+        );
+        assert_eq!(
+            IpAssignmentStrategy::PubkeyHash.start_offset(256, pubkey),
+            210
+        );
+        assert!(IpAssignmentStrategy::Random.start_offset(256, pubkey) < 256);
+    }
+
+    #[test]
+    fn test_translate_ip() {
+        let from = [
+            IpNetwork::from_str("10.1.1.1/24").unwrap(),
+            IpNetwork::from_str("fd00::1/64").unwrap(),
+        ];
+        let to = [
+            IpNetwork::from_str("10.2.0.1/16").unwrap(),
+            IpNetwork::from_str("fd01::1/64").unwrap(),
+        ];
+        let translate = |ip: &str| translate_ip(IpAddr::from_str(ip).unwrap(), &from, &to);
+        assert_eq!(
+            translate("10.1.1.5"),
+            Some(IpAddr::from_str("10.2.0.5").unwrap())
+        );
+        assert_eq!(
+            translate("fd00::1:5"),
+            Some(IpAddr::from_str("fd01::1:5").unwrap())
+        );
+        assert_eq!(translate("10.3.1.5"), None);
+
+        // offset doesn't fit in a smaller network
+        let to = [IpNetwork::from_str("10.2.1.1/28").unwrap()];
+        assert_eq!(
+            translate_ip(IpAddr::from_str("10.1.1.5").unwrap(), &from, &to),
+            Some(IpAddr::from_str("10.2.1.5").unwrap())
+        );
+        assert_eq!(
+            translate_ip(IpAddr::from_str("10.1.1.20").unwrap(), &from, &to),
+            None
+        );
+        // networks of different IP versions don't match
+        assert_eq!(
+            translate_ip(IpAddr::from_str("fd00::5").unwrap(), &from, &to[..0]),
+            None
+        );
+        let to = [to[0], IpNetwork::from_str("10.3.0.1/24").unwrap()];
+        assert_eq!(
+            translate_ip(IpAddr::from_str("fd00::5").unwrap(), &from, &to),
+            None
+        );
+    }
+
     #[sqlx::test]
     async fn test_connected_at_reconnection(_: PgPoolOptions, options: PgConnectOptions) {
         let pool = setup_pool(options).await;
@@ -2079,6 +2892,167 @@ mod test {
         transaction.commit().await.unwrap();
     }
 
+    #[sqlx::test]
+    async fn test_sync_allowed_devices_with_group_pools(
+        _: PgPoolOptions,
+        options: PgConnectOptions,
+    ) {
+        let pool = setup_pool(options).await;
+        let mut network = WireguardNetwork::default();
+        network.try_set_address("10.1.1.1/24").unwrap();
+        let network = network.save(&pool).await.unwrap();
+
+        let contractors = Group::new("contractors").save(&pool).await.unwrap();
+        let employees = Group::new("employees").save(&pool).await.unwrap();
+        let others = Group::new("others").save(&pool).await.unwrap();
+
+        let mut devices = Vec::new();
+        for (name, groups) in [
+            ("contractor", vec![&contractors, &employees]),
+            ("employee", vec![&employees]),
+            ("other", vec![&others]),
+        ] {
+            let user = User::new(
+                name,
+                None,
+                "Tester",
+                "Test",
+                format!("{name}@test.com").as_str(),
+                None,
+            )
+            .save(&pool)
+            .await
+            .unwrap();
+            for group in groups {
+                user.add_to_group(&pool, group).await.unwrap();
+            }
+            for i in 0..3 {
+                let device = Device::new(
+                    format!("{name}{i}"),
+                    format!("{name}key{i}"),
+                    user.id,
+                    DeviceType::User,
+                    None,
+                    true,
+                )
+                .save(&pool)
+                .await
+                .unwrap();
+                devices.push((name, device));
+            }
+        }
+
+        let contractors_pool = NetworkGroupPool {
+            group: contractors.name.clone(),
+            address_pool: vec!["10.1.1.128/26".parse().unwrap()],
+            allowed_ips: vec!["10.2.0.0/16".parse().unwrap()],
+            dns: None,
+            dns_search_domains: Vec::new(),
+            firewall_tag: Some("contractor".into()),
+            priority: 10,
+        };
+        let employees_pool = NetworkGroupPool {
+            group: employees.name.clone(),
+            address_pool: vec!["10.1.1.64/26".parse().unwrap()],
+            allowed_ips: Vec::new(),
+            dns: Some("10.1.1.53".into()),
+            dns_search_domains: vec!["corp.example.com".into()],
+            firewall_tag: None,
+            priority: 0,
+        };
+        let mut transaction = pool.begin().await.unwrap();
+        network
+            .set_allowed_groups(
+                &mut transaction,
+                vec![
+                    contractors.name.clone(),
+                    employees.name.clone(),
+                    others.name.clone(),
+                ],
+            )
+            .await
+            .unwrap();
+        network
+            .set_group_pools(
+                &mut transaction,
+                &[employees_pool.clone(), contractors_pool.clone()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            network.fetch_group_pools(&mut *transaction).await.unwrap(),
+            vec![contractors_pool.clone(), employees_pool.clone()]
+        );
+        network
+            .sync_allowed_devices(&mut transaction, None)
+            .await
+            .unwrap();
+
+        let contractors_net: IpNetwork = "10.1.1.128/26".parse().unwrap();
+        let employees_net: IpNetwork = "10.1.1.64/26".parse().unwrap();
+        for (name, device) in &devices {
+            let ip = WireguardNetworkDevice::find(&mut *transaction, device.id, network.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .wireguard_ips[0];
+            match *name {
+                // highest priority group pool is used
+                "contractor" => assert!(contractors_net.contains(ip)),
+                "employee" => assert!(employees_net.contains(ip)),
+                _ => assert!(!contractors_net.contains(ip) && !employees_net.contains(ip)),
+            }
+        }
+
+        // group routes and DNS replace network settings, groups without DNS overrides don't
+        // shadow overrides of lower priority groups
+        for (name, device) in &devices {
+            let location = network
+                .clone()
+                .apply_device_settings(&mut *transaction, device)
+                .await
+                .unwrap();
+            match *name {
+                "contractor" => {
+                    assert_eq!(location.allowed_ips, contractors_pool.allowed_ips);
+                    assert_eq!(location.dns, Some("10.1.1.53, corp.example.com".into()));
+                }
+                "employee" => {
+                    assert_eq!(location.allowed_ips, network.allowed_ips);
+                    assert_eq!(location.dns, Some("10.1.1.53, corp.example.com".into()));
+                }
+                _ => {
+                    assert_eq!(location.allowed_ips, network.allowed_ips);
+                    assert_eq!(location.dns, None);
+                }
+            }
+        }
+
+        // contractors pool removed, devices are moved to the pool of the other group
+        network
+            .set_group_pools(&mut transaction, &[employees_pool])
+            .await
+            .unwrap();
+        let events = network
+            .sync_allowed_devices(&mut transaction, None)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 3);
+        for (name, device) in &devices {
+            let ip = WireguardNetworkDevice::find(&mut *transaction, device.id, network.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .wireguard_ips[0];
+            match *name {
+                "contractor" | "employee" => assert!(employees_net.contains(ip)),
+                _ => assert!(!employees_net.contains(ip)),
+            }
+        }
+
+        transaction.commit().await.unwrap();
+    }
+
     #[sqlx::test]
     async fn test_can_assign_ips(_: PgPoolOptions, options: PgConnectOptions) {
         let pool = setup_pool(options).await;
@@ -1,11 +1,12 @@
 use std::time::Duration;
 
-use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use chrono::{DateTime, DurationRound, NaiveDateTime, TimeDelta, Utc};
 use defguard_common::db::{Id, NoId};
 use humantime::format_duration;
 use ipnetwork::IpNetwork;
 use model_derive::Model;
 use sqlx::{PgExecutor, PgPool, query, query_as, query_scalar};
+use utoipa::ToSchema;
 
 #[derive(Debug, Deserialize, Model, Serialize)]
 #[table(wireguard_peer_stats)]
@@ -25,6 +26,26 @@ pub struct WireguardPeerStats<I = NoId> {
     pub allowed_ips: Option<String>,
 }
 
+/// Resolution of peer stats stored in rollup tables.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsResolution {
+    #[default]
+    Hour,
+    Day,
+}
+
+/// Transfer of all peers (or a single one) aggregated over one period.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, ToSchema)]
+pub struct AggregatedPeerStats {
+    pub period_start: NaiveDateTime,
+    // bytes sent to peers
+    pub upload: i64,
+    // bytes received from peers
+    pub download: i64,
+    pub latest_handshake: Option<NaiveDateTime>,
+}
+
 impl WireguardPeerStats {
     /// Delete stats older than a configured threshold.
     /// This is done to prevent unnecessary table growth.
@@ -43,9 +64,12 @@ impl WireguardPeerStats {
         let threshold = (Utc::now()
             - TimeDelta::from_std(stats_purge_threshold).expect("Failed to parse duration"))
         .naive_utc();
+        // Stats which haven't been rolled up yet are never removed.
         let result = query!(
             "DELETE FROM wireguard_peer_stats \
             WHERE collected_at < $1 \
+            AND collected_at < ( \
+                SELECT COALESCE(MAX(rolled_up_until), '-infinity') FROM wireguard_stats_rollup) \
             AND (device_id, network, collected_at) NOT IN ( \
                 SELECT device_id, network, MAX(collected_at) \
                 FROM wireguard_peer_stats \
@@ -107,6 +131,153 @@ impl WireguardPeerStats {
 
         Ok(())
     }
+
+    /// Aggregates raw stats collected since the previous rollup into hourly and daily tables.
+    /// Only full hours are rolled up, daily totals of the current day grow as hours pass.
+    ///
+    /// Returns the time until which raw stats have been rolled up, if there are any stats.
+    pub async fn rollup_stats(pool: &PgPool) -> Result<Option<NaiveDateTime>, sqlx::Error> {
+        let start = Utc::now();
+        let until = start
+            .duration_trunc(TimeDelta::hours(1))
+            .expect("Failed to truncate timestamp")
+            .naive_utc();
+        let since = match Self::rolled_up_until(pool).await? {
+            Some(since) => since,
+            None => {
+                let Some(first) =
+                    query_scalar!("SELECT MIN(collected_at) FROM wireguard_peer_stats")
+                        .fetch_one(pool)
+                        .await?
+                else {
+                    debug!("No stats to roll up");
+                    return Ok(None);
+                };
+                first
+                    .duration_trunc(TimeDelta::hours(1))
+                    .expect("Failed to truncate timestamp")
+            }
+        };
+        if since >= until {
+            debug!("Stats are already rolled up until {since}");
+            return Ok(Some(since));
+        }
+
+        debug!("Rolling up stats collected between {since} and {until}");
+        let mut transaction = pool.begin().await?;
+        let hourly = query!(
+            "INSERT INTO wireguard_peer_stats_hourly \
+            (device_id, network, period_start, upload, download, latest_handshake) \
+            SELECT device_id, network, date_trunc('hour', collected_at), \
+            CAST(sum(upload) AS bigint), CAST(sum(download) AS bigint), max(latest_handshake) \
+            FROM wireguard_peer_stats_view \
+            WHERE collected_at >= $1 AND collected_at < $2 \
+            GROUP BY 1, 2, 3 \
+            ON CONFLICT (device_id, network, period_start) DO UPDATE \
+            SET upload = EXCLUDED.upload, download = EXCLUDED.download, \
+            latest_handshake = EXCLUDED.latest_handshake",
+            since,
+            until
+        )
+        .execute(&mut *transaction)
+        .await?;
+        // Daily totals are recalculated from hourly ones for all affected days.
+        let daily = query!(
+            "INSERT INTO wireguard_peer_stats_daily \
+            (device_id, network, period_start, upload, download, latest_handshake) \
+            SELECT device_id, network, date_trunc('day', period_start), \
+            CAST(sum(upload) AS bigint), CAST(sum(download) AS bigint), max(latest_handshake) \
+            FROM wireguard_peer_stats_hourly \
+            WHERE period_start >= date_trunc('day', $1::timestamp) AND period_start < $2 \
+            GROUP BY 1, 2, 3 \
+            ON CONFLICT (device_id, network, period_start) DO UPDATE \
+            SET upload = EXCLUDED.upload, download = EXCLUDED.download, \
+            latest_handshake = EXCLUDED.latest_handshake",
+            since,
+            until
+        )
+        .execute(&mut *transaction)
+        .await?;
+        query!(
+            "INSERT INTO wireguard_stats_rollup (started_at, finished_at, rolled_up_until) \
+            VALUES ($1, $2, $3)",
+            start.naive_utc(),
+            Utc::now().naive_utc(),
+            until
+        )
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+
+        info!(
+            "Rolled up stats until {until}, updated {} hourly and {} daily records",
+            hourly.rows_affected(),
+            daily.rows_affected()
+        );
+
+        Ok(Some(until))
+    }
+
+    /// Returns the time until which raw stats have been rolled up.
+    pub async fn rolled_up_until<'e, E>(executor: E) -> Result<Option<NaiveDateTime>, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!("SELECT MAX(rolled_up_until) FROM wireguard_stats_rollup")
+            .fetch_one(executor)
+            .await
+    }
+
+    /// Retrieves rolled up transfer series of a network, optionally limited to a single device,
+    /// for periods starting in range `[from, to)`.
+    pub async fn aggregated_series<'e, E>(
+        executor: E,
+        network_id: Id,
+        device_id: Option<Id>,
+        resolution: StatsResolution,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Result<Vec<AggregatedPeerStats>, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        match resolution {
+            StatsResolution::Hour => {
+                query_as!(
+                    AggregatedPeerStats,
+                    "SELECT period_start, CAST(sum(upload) AS bigint) \"upload!\", \
+                    CAST(sum(download) AS bigint) \"download!\", max(latest_handshake) latest_handshake \
+                    FROM wireguard_peer_stats_hourly \
+                    WHERE network = $1 AND ($2::bigint IS NULL OR device_id = $2) \
+                    AND period_start >= $3 AND period_start < $4 \
+                    GROUP BY period_start ORDER BY period_start",
+                    network_id,
+                    device_id,
+                    from,
+                    to
+                )
+                .fetch_all(executor)
+                .await
+            }
+            StatsResolution::Day => {
+                query_as!(
+                    AggregatedPeerStats,
+                    "SELECT period_start, CAST(sum(upload) AS bigint) \"upload!\", \
+                    CAST(sum(download) AS bigint) \"download!\", max(latest_handshake) latest_handshake \
+                    FROM wireguard_peer_stats_daily \
+                    WHERE network = $1 AND ($2::bigint IS NULL OR device_id = $2) \
+                    AND period_start >= $3 AND period_start < $4 \
+                    GROUP BY period_start ORDER BY period_start",
+                    network_id,
+                    device_id,
+                    from,
+                    to
+                )
+                .fetch_all(executor)
+                .await
+            }
+        }
+    }
 }
 
 impl WireguardPeerStats<Id> {
@@ -0,0 +1,74 @@
+//! Optional read-only database replica, used for heavy queries like activity log and statistics.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use sqlx::{Connection, Error as SqlxError, PgPool};
+use tokio::time::{sleep, timeout};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Read-only replica of the database. It's considered unavailable until the first successful
+/// health check, in the meantime queries are sent to the primary database.
+#[derive(Clone)]
+pub struct ReadReplica {
+    pool: PgPool,
+    available: Arc<AtomicBool>,
+}
+
+impl ReadReplica {
+    #[must_use]
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            available: Arc::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn is_available(&self) -> bool {
+        self.available.load(Ordering::Relaxed)
+    }
+
+    /// Pool of the replica, if it's reachable.
+    #[must_use]
+    pub fn pool(&self) -> Option<&PgPool> {
+        self.is_available().then_some(&self.pool)
+    }
+
+    async fn check(&self) -> Result<(), SqlxError> {
+        match timeout(CHECK_TIMEOUT, self.pool.acquire()).await {
+            Ok(Ok(mut conn)) => conn.ping().await,
+            Ok(Err(err)) => Err(err),
+            Err(_) => Err(SqlxError::PoolTimedOut),
+        }
+    }
+}
+
+/// Periodically checks if the replica is reachable, so that heavy queries fall back to the
+/// primary database shortly after the replica goes down and return once it's back.
+#[instrument(skip_all)]
+pub async fn run_replica_health_check(replica: ReadReplica) {
+    let mut first_check = true;
+    loop {
+        let result = replica.check().await;
+        let available = result.is_ok();
+        let was_available = replica.available.swap(available, Ordering::Relaxed);
+        match result {
+            Ok(()) if !was_available => info!("Database replica is available, using it for reads"),
+            Err(err) if was_available || first_check => {
+                warn!("Database replica is unreachable, reading from the primary database: {err}");
+            }
+            Err(err) => debug!("Database replica is still unreachable: {err}"),
+            Ok(()) => {}
+        }
+        first_check = false;
+        sleep(CHECK_INTERVAL).await;
+    }
+}
@@ -1,4 +1,4 @@
-use std::{sync::Arc, time::Duration};
+use std::{pin::pin, sync::Arc, time::Duration};
 
 use bytes::Bytes;
 use sqlx::PgPool;
@@ -8,7 +8,11 @@ use tracing::debug;
 
 use super::ActivityLogStreamReconfigurationNotification;
 use crate::enterprise::{
-    activity_log_stream::http_stream::{HttpActivityLogStreamConfig, run_http_stream_task},
+    activity_log_stream::{
+        http_stream::{HttpActivityLogStreamConfig, run_http_stream_task},
+        kafka_stream::run_kafka_stream_task,
+        syslog_stream::run_syslog_stream_task,
+    },
     db::models::activity_log_stream::{ActivityLogStream, ActivityLogStreamConfig},
     is_business_license_active,
 };
@@ -21,8 +25,10 @@ pub async fn run_activity_log_stream_manager(
     pool: PgPool,
     notification: ActivityLogStreamReconfigurationNotification,
     activity_log_messages_rx: Receiver<Bytes>,
+    shutdown: impl Future<Output = ()>,
 ) -> anyhow::Result<()> {
     info!("Starting activity log stream manager");
+    let mut shutdown = pin!(shutdown);
 
     let mut enterprise_check_timer = interval(Duration::from_secs(ENTERPRISE_CHECK_PERIOD_SECS));
 
@@ -66,6 +72,22 @@ pub async fn run_activity_log_stream_manager(
                                 cancel_token.clone(),
                             ));
                         }
+                        ActivityLogStreamConfig::Syslog(stream_config) => {
+                            handles.spawn(run_syslog_stream_task(
+                                activity_log_stream.name.clone(),
+                                stream_config,
+                                activity_log_messages_rx.resubscribe(),
+                                cancel_token.clone(),
+                            ));
+                        }
+                        ActivityLogStreamConfig::Kafka(stream_config) => {
+                            handles.spawn(run_kafka_stream_task(
+                                activity_log_stream.name.clone(),
+                                stream_config,
+                                activity_log_messages_rx.resubscribe(),
+                                cancel_token.clone(),
+                            ));
+                        }
                     }
                 } else {
                     error!(
@@ -84,8 +106,16 @@ pub async fn run_activity_log_stream_manager(
         // - stream config update
         // - enterprise features got disabled/enabled
         // - streaming task terminated early
+        // - shutdown
         loop {
             tokio::select! {
+                () = &mut shutdown => {
+                    // Streams stop on their own after sending events published before the event
+                    // logger has stopped.
+                    info!("Stopping activity log stream manager, waiting for streams to send remaining events");
+                    handles.join_all().await;
+                    return Ok(());
+                }
                 () = notification.notified() => {
                     info!(
                         "Activity log stream manager configuration refresh notification received, reloading streaming tasks."
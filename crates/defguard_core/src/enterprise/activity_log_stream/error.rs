@@ -6,6 +6,12 @@ pub enum ActivityLogStreamError {
     ConfigDeserializeError(String, String),
     #[error("Sqlx error: {0}")]
     SqlxError(#[from] sqlx::Error),
+    #[error("Invalid TLS configuration: {0}")]
+    InvalidTlsConfig(String),
+    #[error("Invalid syslog configuration: {0}")]
+    InvalidSyslogConfig(String),
+    #[error("Invalid Kafka configuration: {0}")]
+    InvalidKafkaConfig(String),
     #[error("Parsing http header value failed")]
     HeaderValueParsing(),
 }
@@ -4,7 +4,7 @@ use base64::prelude::{BASE64_STANDARD, Engine};
 use bytes::Bytes;
 use defguard_common::secret::SecretStringWrapper;
 use reqwest::tls;
-use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::{Receiver, error::RecvError};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error};
 
@@ -63,6 +63,10 @@ pub(super) async fn run_http_stream_task(
                             }
                         }
                     },
+                    Err(RecvError::Closed) => {
+                        debug!("Activity log stream ({stream_name}) channel closed, all events have been sent.");
+                        break;
+                    }
                     Err(e) => {
                         error!("Receiving activity log stream message failed ! Reason: {}", e.to_string());
                         break;
@@ -99,11 +103,13 @@ fn build_client(config: &HttpActivityLogStreamConfig) -> Result<reqwest::Client,
     }
 
     let mut client = reqwest::ClientBuilder::new().default_headers(headers);
-    if let Some(cert) = &config.cert {
-        if config.url.contains("https") {
-            match tls::Certificate::from_pem(cert.as_bytes()) {
-                Ok(parsed_cert) => {
-                    client = client.add_root_certificate(parsed_cert);
+    if config.url.contains("https") {
+        if let Some(cert) = &config.cert {
+            match tls::Certificate::from_pem_bundle(cert.as_bytes()) {
+                Ok(parsed_certs) => {
+                    for parsed_cert in parsed_certs {
+                        client = client.add_root_certificate(parsed_cert);
+                    }
                 }
                 Err(e) => {
                     error!(
@@ -114,6 +120,27 @@ fn build_client(config: &HttpActivityLogStreamConfig) -> Result<reqwest::Client,
                 }
             }
         }
+        if let (Some(client_cert), Some(client_key)) = (&config.client_cert, &config.client_key) {
+            match tls::Identity::from_pkcs8_pem(
+                client_cert.as_bytes(),
+                client_key.expose_secret().as_bytes(),
+            ) {
+                Ok(identity) => {
+                    debug!(
+                        "Client certificate added to {} activity log stream",
+                        config.stream_name
+                    );
+                    client = client.identity(identity);
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to add client certificate for {} activity log stream. Reason: {e}",
+                        config.stream_name
+                    );
+                    return Err(e);
+                }
+            }
+        }
     }
     if cfg!(debug_assertions) {
         client = client.danger_accept_invalid_hostnames(true);
@@ -129,6 +156,9 @@ pub(super) struct HttpActivityLogStreamConfig {
     pub password: Option<SecretStringWrapper>,
     // cert to use for tls
     pub cert: Option<String>,
+    // client certificate and key used for mutual TLS
+    pub client_cert: Option<String>,
+    pub client_key: Option<SecretStringWrapper>,
 }
 
 impl HttpActivityLogStreamConfig {
@@ -136,6 +166,8 @@ impl HttpActivityLogStreamConfig {
         Self {
             stream_name,
             cert: value.cert,
+            client_cert: value.client_cert,
+            client_key: value.client_key,
             password: value.password,
             url: value.url,
             username: value.username,
@@ -146,6 +178,8 @@ impl HttpActivityLogStreamConfig {
         Self {
             stream_name,
             cert: value.cert,
+            client_cert: value.client_cert,
+            client_key: value.client_key,
             password: value.password,
             url: value.url,
             username: value.username,
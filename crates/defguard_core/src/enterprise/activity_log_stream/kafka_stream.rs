@@ -0,0 +1,352 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use bytes::Bytes;
+use rskafka::client::{
+    Client, ClientBuilder, Credentials, SaslConfig,
+    partition::{Compression, UnknownTopicHandling},
+    producer::Record,
+};
+use rustls::{
+    ClientConfig, RootCertStore,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+};
+use tokio::sync::broadcast::{Receiver, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error};
+
+use super::{StreamedEvent, parse_events};
+use crate::enterprise::db::models::activity_log_stream::{
+    KafkaActivityLogStream, KafkaPayloadFormat, KafkaSaslMechanism,
+};
+
+/// Avro schema of activity log event records, published for consumers decoding `avro` payloads.
+pub const AVRO_SCHEMA: &str = r#"{
+  "type": "record",
+  "name": "ActivityLogEvent",
+  "namespace": "net.defguard",
+  "fields": [
+    {"name": "timestamp", "type": {"type": "long", "logicalType": "timestamp-micros"}},
+    {"name": "user_id", "type": "long"},
+    {"name": "username", "type": "string"},
+    {"name": "location", "type": ["null", "string"]},
+    {"name": "ip", "type": "string"},
+    {"name": "event", "type": "string"},
+    {"name": "module", "type": "string"},
+    {"name": "device", "type": "string"},
+    {"name": "description", "type": ["null", "string"]},
+    {"name": "metadata", "type": ["null", "string"]}
+  ]
+}"#;
+
+const CONTENT_TYPE_HEADER: &str = "content-type";
+
+/// Spawns an asynchronous task that reads activity log events from the channel and produces them
+/// as records to a Kafka topic.
+///
+/// Records are keyed by user ID, so events of a single user always land in the same partition
+/// and keep their order.
+///
+/// # Parameters
+///
+/// - `stream_name`: Name of this activity log stream, used in logs.
+/// - `config`: Configuration for this Kafka activity log stream.
+/// - `rx`: A `tokio::sync::broadcast::Receiver<Bytes>` from which activity log messages are received.
+/// - `cancel_token`: Shared `CancellationToken` used to signal task shutdown.
+pub(super) async fn run_kafka_stream_task(
+    stream_name: String,
+    config: KafkaActivityLogStream,
+    mut rx: Receiver<Bytes>,
+    cancel_token: Arc<CancellationToken>,
+) {
+    let tls_config = if config.tls {
+        match build_tls_config(&config) {
+            Ok(tls_config) => Some(Arc::new(tls_config)),
+            Err(err) => {
+                error!("Failed to build TLS configuration for stream {stream_name}: {err}");
+                return;
+            }
+        }
+    } else {
+        None
+    };
+    // producer is created lazily and dropped after a failed produce request,
+    // so the next batch of events triggers a reconnect
+    let mut producer: Option<KafkaProducer> = None;
+    loop {
+        tokio::select! {
+            () = cancel_token.cancelled() => {
+                debug!("Activity log stream ({stream_name}) task received cancellation signal.");
+                break;
+            },
+            res = rx.recv() => {
+                match res {
+                    Ok(msg) => {
+                        let records = build_records(config.format, &msg);
+                        if records.is_empty() {
+                            continue;
+                        }
+                        if producer.is_none() {
+                            match KafkaProducer::connect(&config, tls_config.clone()).await {
+                                Ok(new_producer) => producer = Some(new_producer),
+                                Err(err) => {
+                                    error!("Activity log stream {stream_name} failed to connect to Kafka brokers. Reason: {err}");
+                                    continue;
+                                }
+                            }
+                        }
+                        if let Some(kafka_producer) = producer.as_ref() {
+                            if let Err(err) = kafka_producer.produce(records).await {
+                                error!("Activity log stream {stream_name} failed to produce records. Reason: {err}");
+                                producer = None;
+                            }
+                        }
+                    },
+                    Err(RecvError::Closed) => {
+                        debug!("Activity log stream ({stream_name}) channel closed, all events have been sent.");
+                        break;
+                    }
+                    Err(e) => {
+                        error!("Receiving activity log stream message failed ! Reason: {}", e.to_string());
+                        break;
+                    }
+                }
+            },
+        }
+    }
+}
+
+struct KafkaProducer {
+    client: Client,
+    topic: String,
+    partitions: Vec<i32>,
+}
+
+impl KafkaProducer {
+    async fn connect(
+        config: &KafkaActivityLogStream,
+        tls_config: Option<Arc<ClientConfig>>,
+    ) -> Result<Self, rskafka::client::error::Error> {
+        let mut builder = ClientBuilder::new(config.brokers.clone());
+        if let Some(sasl) = &config.sasl {
+            let credentials = Credentials::new(
+                sasl.username.clone(),
+                sasl.password.expose_secret().to_string(),
+            );
+            builder = builder.sasl_config(match sasl.mechanism {
+                KafkaSaslMechanism::Plain => SaslConfig::Plain(credentials),
+                KafkaSaslMechanism::ScramSha256 => SaslConfig::ScramSha256(credentials),
+                KafkaSaslMechanism::ScramSha512 => SaslConfig::ScramSha512(credentials),
+            });
+        }
+        if let Some(tls_config) = tls_config {
+            builder = builder.tls_config(tls_config);
+        }
+        let client = builder.build().await?;
+
+        let partitions = client
+            .list_topics()
+            .await?
+            .into_iter()
+            .find(|topic| topic.name == config.topic)
+            .map(|topic| topic.partitions.into_iter().collect())
+            .unwrap_or_default();
+        debug!(
+            "Connected to Kafka brokers, topic {} has partitions: {partitions:?}",
+            config.topic
+        );
+
+        Ok(Self {
+            client,
+            topic: config.topic.clone(),
+            partitions,
+        })
+    }
+
+    /// Produces records, grouped into a single request per partition.
+    async fn produce(
+        &self,
+        records: Vec<(i64, Record)>,
+    ) -> Result<(), rskafka::client::error::Error> {
+        let mut batches: HashMap<i32, Vec<Record>> = HashMap::new();
+        for (user_id, record) in records {
+            batches
+                .entry(self.partition_for(user_id))
+                .or_default()
+                .push(record);
+        }
+        for (partition, batch) in batches {
+            let partition_client = self
+                .client
+                .partition_client(self.topic.clone(), partition, UnknownTopicHandling::Retry)
+                .await?;
+            partition_client
+                .produce(batch, Compression::NoCompression)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    fn partition_for(&self, user_id: i64) -> i32 {
+        if self.partitions.is_empty() {
+            // topic metadata was not available, broker may create the topic on first write
+            return 0;
+        }
+        let index = user_id.rem_euclid(self.partitions.len() as i64) as usize;
+        self.partitions[index]
+    }
+}
+
+fn build_tls_config(config: &KafkaActivityLogStream) -> Result<ClientConfig, anyhow::Error> {
+    let mut roots = RootCertStore::empty();
+    if let Some(cert) = &config.cert {
+        for cert in CertificateDer::pem_slice_iter(cert.as_bytes()) {
+            roots.add(cert?)?;
+        }
+    } else {
+        roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+    }
+
+    let builder =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots);
+    let tls_config = match (&config.client_cert, &config.client_key) {
+        (Some(client_cert), Some(client_key)) => {
+            let certs = CertificateDer::pem_slice_iter(client_cert.as_bytes())
+                .collect::<Result<Vec<_>, _>>()?;
+            let key = PrivateKeyDer::from_pem_slice(client_key.expose_secret().as_bytes())?;
+            builder.with_client_auth_cert(certs, key)?
+        }
+        _ => builder.with_no_client_auth(),
+    };
+
+    Ok(tls_config)
+}
+
+/// Converts a batch of NDJSON serialized activity log events into Kafka records paired with
+/// user IDs used for partitioning.
+fn build_records(format: KafkaPayloadFormat, batch: &[u8]) -> Vec<(i64, Record)> {
+    let content_type = match format {
+        KafkaPayloadFormat::Json => "application/json",
+        KafkaPayloadFormat::Avro => "avro/binary",
+    };
+    parse_events(batch)
+        .into_iter()
+        .map(|(line, event)| {
+            let value = match format {
+                KafkaPayloadFormat::Json => line.as_bytes().to_vec(),
+                KafkaPayloadFormat::Avro => encode_avro(&event),
+            };
+            let record = Record {
+                key: Some(event.user_id.to_string().into_bytes()),
+                value: Some(value),
+                headers: BTreeMap::from([(
+                    CONTENT_TYPE_HEADER.to_string(),
+                    content_type.as_bytes().to_vec(),
+                )]),
+                timestamp: event.timestamp.and_utc(),
+            };
+            (event.user_id, record)
+        })
+        .collect()
+}
+
+/// Encodes activity log event as Avro binary datum matching [`AVRO_SCHEMA`].
+fn encode_avro(event: &StreamedEvent) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    write_avro_long(&mut buffer, event.timestamp.and_utc().timestamp_micros());
+    write_avro_long(&mut buffer, event.user_id);
+    write_avro_string(&mut buffer, &event.username);
+    write_avro_optional_string(&mut buffer, event.location.as_deref());
+    write_avro_string(&mut buffer, &event.ip.ip().to_string());
+    write_avro_string(&mut buffer, &event.event);
+    write_avro_string(&mut buffer, &event.module);
+    write_avro_string(&mut buffer, &event.device);
+    write_avro_optional_string(&mut buffer, event.description.as_deref());
+    let metadata = event.metadata.as_ref().map(ToString::to_string);
+    write_avro_optional_string(&mut buffer, metadata.as_deref());
+
+    buffer
+}
+
+/// Writes zig-zag encoded variable-length long.
+fn write_avro_long(buffer: &mut Vec<u8>, value: i64) {
+    let mut value = ((value << 1) ^ (value >> 63)) as u64;
+    while value >= 0x80 {
+        buffer.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn write_avro_string(buffer: &mut Vec<u8>, value: &str) {
+    write_avro_long(buffer, value.len() as i64);
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+/// Writes `["null", "string"]` union value.
+fn write_avro_optional_string(buffer: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            write_avro_long(buffer, 1);
+            write_avro_string(buffer, value);
+        }
+        None => write_avro_long(buffer, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BATCH: &str = concat!(
+        r#"{"id":null,"timestamp":"1970-01-01T00:00:01","user_id":1,"username":"admin","location":null,"ip":"10.0.0.1/32","event":"user_login","module":"defguard","device":"Firefox","description":null,"metadata":{"a":1}}"#,
+        "\n",
+    );
+
+    #[test]
+    fn test_avro_long_encoding() {
+        for (value, expected) in [
+            (0, vec![0x00]),
+            (-1, vec![0x01]),
+            (1, vec![0x02]),
+            (-64, vec![0x7f]),
+            (64, vec![0x80, 0x01]),
+            (1_000_000, vec![0x80, 0x89, 0x7a]),
+        ] {
+            let mut buffer = Vec::new();
+            write_avro_long(&mut buffer, value);
+            assert_eq!(buffer, expected, "encoding of {value}");
+        }
+    }
+
+    #[test]
+    fn test_build_records() {
+        let records = build_records(KafkaPayloadFormat::Json, BATCH.as_bytes());
+        assert_eq!(records.len(), 1);
+        let (user_id, record) = &records[0];
+        assert_eq!(*user_id, 1);
+        assert_eq!(record.key.as_deref(), Some(b"1".as_slice()));
+        assert_eq!(record.value.as_deref(), Some(BATCH.trim_end().as_bytes()));
+        assert_eq!(
+            record.headers.get(CONTENT_TYPE_HEADER).map(Vec::as_slice),
+            Some(b"application/json".as_slice())
+        );
+
+        let records = build_records(KafkaPayloadFormat::Avro, BATCH.as_bytes());
+        let mut expected = vec![0x80, 0x89, 0x7a, 0x02];
+        expected.extend_from_slice(b"\x0aadmin");
+        expected.push(0x00);
+        expected.extend_from_slice(b"\x1010.0.0.1");
+        expected.extend_from_slice(b"\x14user_login");
+        expected.extend_from_slice(b"\x10defguard");
+        expected.extend_from_slice(b"\x0eFirefox");
+        expected.push(0x00);
+        expected.extend_from_slice(b"\x02\x0e{\"a\":1}");
+        assert_eq!(records[0].1.value.as_deref(), Some(expected.as_slice()));
+    }
+}
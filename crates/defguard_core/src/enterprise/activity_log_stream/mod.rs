@@ -1,5 +1,44 @@
+use chrono::NaiveDateTime;
+use ipnetwork::IpNetwork;
+
 pub mod activity_log_stream_manager;
 pub mod error;
 pub mod http_stream;
+pub mod kafka_stream;
+pub mod syslog_stream;
 
 pub type ActivityLogStreamReconfigurationNotification = std::sync::Arc<tokio::sync::Notify>;
+
+/// Activity log event as it's serialized by the event logger.
+#[derive(Debug, Deserialize)]
+struct StreamedEvent {
+    timestamp: NaiveDateTime,
+    user_id: i64,
+    username: String,
+    location: Option<String>,
+    ip: IpNetwork,
+    event: String,
+    module: String,
+    device: String,
+    description: Option<String>,
+    metadata: Option<serde_json::Value>,
+}
+
+/// Splits a batch of NDJSON serialized activity log events into lines and parsed events.
+fn parse_events(batch: &[u8]) -> Vec<(&str, StreamedEvent)> {
+    let Ok(batch) = std::str::from_utf8(batch) else {
+        error!("Activity log stream message is not valid UTF-8");
+        return Vec::new();
+    };
+    batch
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str::<StreamedEvent>(line) {
+            Ok(event) => Some((line, event)),
+            Err(err) => {
+                error!("Failed to deserialize streamed activity log event. Reason: {err}");
+                None
+            }
+        })
+        .collect()
+}
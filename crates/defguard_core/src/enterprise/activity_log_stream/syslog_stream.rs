@@ -0,0 +1,339 @@
+use std::{
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use bytes::Bytes;
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    net::{TcpStream, UdpSocket, lookup_host},
+    sync::broadcast::{Receiver, error::RecvError},
+    time::timeout,
+};
+use tokio_native_tls::{
+    TlsConnector, TlsStream,
+    native_tls::{self, Certificate, Identity},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error};
+
+use super::{StreamedEvent, parse_events};
+use crate::enterprise::db::models::activity_log_stream::{SyslogActivityLogStream, SyslogProtocol};
+
+const APP_NAME: &str = "defguard";
+// SD-ID of the structured data element carrying event context;
+// 32473 is the private enterprise number reserved for documentation (RFC 5612)
+const SD_ID: &str = "defguard@32473";
+// RFC 5424 limits MSGID to 32 characters
+const MSGID_MAX_LENGTH: usize = 32;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Spawns an asynchronous task that reads activity log events from the channel and forwards them
+/// to a syslog collector as RFC 5424 messages.
+///
+/// # Parameters
+///
+/// - `stream_name`: Name of this activity log stream, used in logs.
+/// - `config`: Configuration for this syslog activity log stream.
+/// - `rx`: A `tokio::sync::broadcast::Receiver<Bytes>` from which activity log messages are received.
+/// - `cancel_token`: Shared `CancellationToken` used to signal task shutdown.
+pub(super) async fn run_syslog_stream_task(
+    stream_name: String,
+    config: SyslogActivityLogStream,
+    mut rx: Receiver<Bytes>,
+    cancel_token: Arc<CancellationToken>,
+) {
+    let tls_connector = if config.protocol == SyslogProtocol::Tls {
+        match build_tls_connector(&config) {
+            Ok(connector) => Some(connector),
+            Err(err) => {
+                error!("Failed to build TLS connector for stream {stream_name}: {err}");
+                return;
+            }
+        }
+    } else {
+        None
+    };
+    // connection is established lazily and dropped after a failed write,
+    // so the next batch of events triggers a reconnect
+    let mut connection: Option<SyslogConnection> = None;
+    loop {
+        tokio::select! {
+            () = cancel_token.cancelled() => {
+                debug!("Activity log stream ({stream_name}) task received cancellation signal.");
+                break;
+            },
+            res = rx.recv() => {
+                match res {
+                    Ok(msg) => {
+                        let messages = format_messages(&config, &msg);
+                        if messages.is_empty() {
+                            continue;
+                        }
+                        if connection.is_none() {
+                            match SyslogConnection::connect(&config, tls_connector.as_ref()).await {
+                                Ok(new_connection) => connection = Some(new_connection),
+                                Err(err) => {
+                                    error!("Activity log stream {stream_name} failed to connect to {}:{}. Reason: {err}", config.host, config.port);
+                                    continue;
+                                }
+                            }
+                        }
+                        if let Some(conn) = connection.as_mut() {
+                            for message in &messages {
+                                if let Err(err) = conn.send(message).await {
+                                    error!("Activity log stream {stream_name} failed to send messages. Reason: {err}");
+                                    connection = None;
+                                    break;
+                                }
+                            }
+                        }
+                    },
+                    Err(RecvError::Closed) => {
+                        debug!("Activity log stream ({stream_name}) channel closed, all events have been sent.");
+                        break;
+                    }
+                    Err(e) => {
+                        error!("Receiving activity log stream message failed ! Reason: {}", e.to_string());
+                        break;
+                    }
+                }
+            },
+        }
+    }
+}
+
+enum SyslogConnection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl SyslogConnection {
+    async fn connect(
+        config: &SyslogActivityLogStream,
+        tls_connector: Option<&TlsConnector>,
+    ) -> Result<Self, std::io::Error> {
+        let address = (config.host.as_str(), config.port);
+        let connection = match config.protocol {
+            SyslogProtocol::Udp => {
+                let remote = lookup_host(address).await?.next().ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        format!("failed to resolve {}", config.host),
+                    )
+                })?;
+                let local: SocketAddr = if remote.is_ipv4() {
+                    (Ipv4Addr::UNSPECIFIED, 0).into()
+                } else {
+                    (Ipv6Addr::UNSPECIFIED, 0).into()
+                };
+                let socket = UdpSocket::bind(local).await?;
+                socket.connect(remote).await?;
+                Self::Udp(socket)
+            }
+            SyslogProtocol::Tcp => Self::Tcp(Self::connect_tcp(address).await?),
+            SyslogProtocol::Tls => {
+                let connector = tls_connector
+                    .ok_or_else(|| std::io::Error::other("TLS connector is not configured"))?;
+                let stream = Self::connect_tcp(address).await?;
+                let stream = connector
+                    .connect(&config.host, stream)
+                    .await
+                    .map_err(std::io::Error::other)?;
+                Self::Tls(Box::new(stream))
+            }
+        };
+        debug!(
+            "Connected to syslog collector {}:{}",
+            config.host, config.port
+        );
+
+        Ok(connection)
+    }
+
+    async fn connect_tcp(address: (&str, u16)) -> Result<TcpStream, std::io::Error> {
+        timeout(CONNECT_TIMEOUT, TcpStream::connect(address))
+            .await
+            .map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::TimedOut, "connection timed out")
+            })?
+    }
+
+    /// Sends a single message. UDP uses one datagram per message, stream transports use
+    /// octet-counting framing (RFC 6587 and RFC 5425).
+    async fn send(&mut self, message: &str) -> Result<(), std::io::Error> {
+        match self {
+            Self::Udp(socket) => {
+                socket.send(message.as_bytes()).await?;
+                Ok(())
+            }
+            Self::Tcp(stream) => write_framed(stream, message).await,
+            Self::Tls(stream) => write_framed(stream.as_mut(), message).await,
+        }
+    }
+}
+
+async fn write_framed<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    message: &str,
+) -> Result<(), std::io::Error> {
+    let frame = format!("{} {message}", message.len());
+    writer.write_all(frame.as_bytes()).await?;
+    writer.flush().await
+}
+
+fn build_tls_connector(
+    config: &SyslogActivityLogStream,
+) -> Result<TlsConnector, native_tls::Error> {
+    let mut builder = native_tls::TlsConnector::builder();
+    if let Some(cert) = &config.cert {
+        for pem in split_pem_bundle(cert) {
+            builder.add_root_certificate(Certificate::from_pem(pem.as_bytes())?);
+        }
+    }
+    if let (Some(client_cert), Some(client_key)) = (&config.client_cert, &config.client_key) {
+        let identity = Identity::from_pkcs8(
+            client_cert.as_bytes(),
+            client_key.expose_secret().as_bytes(),
+        )?;
+        builder.identity(identity);
+    }
+    if cfg!(debug_assertions) {
+        builder.danger_accept_invalid_hostnames(true);
+    }
+
+    Ok(TlsConnector::from(builder.build()?))
+}
+
+/// Splits a bundle of PEM certificates into separate certificates.
+fn split_pem_bundle(bundle: &str) -> Vec<String> {
+    const END_MARKER: &str = "-----END CERTIFICATE-----";
+    bundle
+        .split_inclusive(END_MARKER)
+        .filter(|pem| pem.contains(END_MARKER))
+        .map(|pem| pem.trim().to_string())
+        .collect()
+}
+
+/// Converts a batch of NDJSON serialized activity log events into RFC 5424 messages.
+fn format_messages(config: &SyslogActivityLogStream, batch: &[u8]) -> Vec<String> {
+    parse_events(batch)
+        .iter()
+        .map(|(_, event)| format_message(config, event))
+        .collect()
+}
+
+/// Formats activity log event as RFC 5424 syslog message.
+///
+/// Event context is sent as a structured data element, event description is used as message body.
+fn format_message(config: &SyslogActivityLogStream, event: &StreamedEvent) -> String {
+    let priority = config.facility as u8 * 8 + config.severity_for(&event.event) as u8;
+    let timestamp = event.timestamp.and_utc().format("%Y-%m-%dT%H:%M:%S%.6fZ");
+    let hostname = config.hostname.as_deref().unwrap_or("-");
+    let msgid: String = event
+        .event
+        .chars()
+        .filter(char::is_ascii_graphic)
+        .take(MSGID_MAX_LENGTH)
+        .collect();
+    let msgid = if msgid.is_empty() { "-" } else { &msgid };
+
+    let mut params = vec![
+        ("user_id", event.user_id.to_string()),
+        ("username", event.username.clone()),
+        ("ip", event.ip.ip().to_string()),
+        ("device", event.device.clone()),
+        ("module", event.module.clone()),
+    ];
+    if let Some(location) = &event.location {
+        params.push(("location", location.clone()));
+    }
+    let structured_data = params
+        .iter()
+        .map(|(name, value)| format!(" {name}=\"{}\"", escape_param_value(value)))
+        .collect::<String>();
+
+    let mut message = format!(
+        "<{priority}>1 {timestamp} {hostname} {APP_NAME} {} {msgid} [{SD_ID}{structured_data}]",
+        std::process::id()
+    );
+    let body = event.description.as_deref().unwrap_or(&event.event);
+    if !body.is_empty() {
+        // UTF-8 encoded MSG is marked with BOM
+        message.push_str(" \u{feff}");
+        message.push_str(body);
+    }
+
+    message
+}
+
+/// Escapes characters which must not appear unescaped in PARAM-VALUE (RFC 5424, section 6.3.3).
+fn escape_param_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        if matches!(character, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_format_syslog_message() {
+        let config: SyslogActivityLogStream = serde_json::from_value(json!({
+            "host": "syslog.example.com",
+            "port": 514,
+            "facility": "auth",
+            "severity_mapping": {"user_login_failed": "warning"},
+            "hostname": "defguard-core",
+        }))
+        .unwrap();
+        let batch = concat!(
+            r#"{"id":null,"timestamp":"2025-01-02T03:04:05.123456","user_id":1,"username":"ad\"min]","location":null,"ip":"10.0.0.1/32","event":"user_login_failed","module":"defguard","device":"Firefox","description":"Failed login","metadata":null}"#,
+            "\n",
+            r#"{"id":null,"timestamp":"2025-01-02T03:04:06","user_id":2,"username":"user","location":"office","ip":"10.0.0.2/32","event":"vpn_client_connected","module":"vpn","device":"laptop","description":null,"metadata":null}"#,
+            "\n",
+        );
+
+        let messages = format_messages(&config, batch.as_bytes());
+        let pid = std::process::id();
+        assert_eq!(
+            messages,
+            vec![
+                format!(
+                    "<36>1 2025-01-02T03:04:05.123456Z defguard-core defguard {pid} user_login_failed \
+                    [defguard@32473 user_id=\"1\" username=\"ad\\\"min\\]\" ip=\"10.0.0.1\" \
+                    device=\"Firefox\" module=\"defguard\"] \u{feff}Failed login"
+                ),
+                format!(
+                    "<38>1 2025-01-02T03:04:06.000000Z defguard-core defguard {pid} vpn_client_connected \
+                    [defguard@32473 user_id=\"2\" username=\"user\" ip=\"10.0.0.2\" \
+                    device=\"laptop\" module=\"vpn\" location=\"office\"] \u{feff}vpn_client_connected"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_pem_bundle() {
+        let bundle = "-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----\n\
+            -----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----\n";
+        assert_eq!(
+            split_pem_bundle(bundle),
+            vec![
+                "-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----",
+                "-----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----",
+            ]
+        );
+    }
+}
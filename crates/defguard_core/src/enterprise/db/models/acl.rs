@@ -20,7 +20,7 @@ use crate::{
     appstate::AppState,
     db::{
         Device, GatewayEvent, Group, User, WireguardNetwork,
-        models::wireguard::{LocationMfaMode, ServiceLocationMode},
+        models::wireguard::{IpAssignmentStrategy, LocationMfaMode, ServiceLocationMode},
     },
     enterprise::{
         firewall::FirewallError,
@@ -929,7 +929,8 @@ impl AclRule<Id> {
                 "SELECT n.id, name, address, port, pubkey, prvkey, endpoint, dns, allowed_ips, \
                 connected_at, keepalive_interval, peer_disconnect_threshold, \
                 acl_enabled, acl_default_allow, location_mfa_mode \"location_mfa_mode: LocationMfaMode\", \
-                service_location_mode \"service_location_mode: ServiceLocationMode\" \
+                service_location_mode \"service_location_mode: ServiceLocationMode\", \
+                ip_assignment_strategy \"ip_assignment_strategy: IpAssignmentStrategy\" \
                 FROM aclrulenetwork r \
                 JOIN wireguard_network n \
                 ON n.id = r.network_id \
@@ -1,13 +1,19 @@
+use std::collections::HashMap;
+
 use defguard_common::{
     db::{Id, NoId},
     secret::SecretStringWrapper,
 };
 use model_derive::Model;
+use reqwest::tls::{Certificate, Identity};
 use serde::Serialize;
 use sqlx::{Error as SqlxError, FromRow, PgExecutor, Type, query_as};
 use strum_macros::{Display, EnumString};
 
-use crate::enterprise::activity_log_stream::error::ActivityLogStreamError;
+use crate::{
+    db::models::activity_log::EventType,
+    enterprise::activity_log_stream::error::ActivityLogStreamError,
+};
 
 #[derive(Debug, Serialize, Deserialize, Type, EnumString, Display, Clone, PartialEq)]
 #[sqlx(type_name = "text", rename_all = "snake_case")]
@@ -17,6 +23,10 @@ pub enum ActivityLogStreamType {
     VectorHttp,
     #[strum(serialize = "logstash_http")]
     LogstashHttp,
+    #[strum(serialize = "syslog")]
+    Syslog,
+    #[strum(serialize = "kafka")]
+    Kafka,
 }
 
 #[derive(Clone, Debug, Serialize, Model, FromRow, PartialEq)]
@@ -33,6 +43,8 @@ pub struct ActivityLogStream<I = NoId> {
 pub enum ActivityLogStreamConfig {
     VectorHttp(VectorHttpActivityLogStream),
     LogstashHttp(LogstashHttpActivityLogStream),
+    Syslog(SyslogActivityLogStream),
+    Kafka(KafkaActivityLogStream),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -40,8 +52,12 @@ pub struct LogstashHttpActivityLogStream {
     pub url: String,
     pub username: Option<String>,
     pub password: Option<SecretStringWrapper>,
-    // cert to use for tls
+    // cert to use for tls, may be a bundle of multiple PEM certificates
     pub cert: Option<String>,
+    // client certificate (PEM) presented to the server for mutual TLS
+    pub client_cert: Option<String>,
+    // PKCS#8 private key (PEM) matching `client_cert`
+    pub client_key: Option<SecretStringWrapper>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -49,8 +65,274 @@ pub struct VectorHttpActivityLogStream {
     pub url: String,
     pub username: Option<String>,
     pub password: Option<SecretStringWrapper>,
-    // cert to use for tls
+    // cert to use for tls, may be a bundle of multiple PEM certificates
+    pub cert: Option<String>,
+    // client certificate (PEM) presented to the server for mutual TLS
+    pub client_cert: Option<String>,
+    // PKCS#8 private key (PEM) matching `client_cert`
+    pub client_key: Option<SecretStringWrapper>,
+}
+
+/// Transport used to deliver messages to a syslog collector.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogProtocol {
+    #[default]
+    Udp,
+    Tcp,
+    Tls,
+}
+
+/// Syslog facility as defined in RFC 5424, section 6.2.1.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogFacility {
+    Kern = 0,
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    Authpriv = 10,
+    Ftp = 11,
+    Ntp = 12,
+    Audit = 13,
+    Alert = 14,
+    Clock = 15,
+    #[default]
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+/// Syslog severity as defined in RFC 5424, section 6.2.1.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogSeverity {
+    Emergency = 0,
+    Alert = 1,
+    Critical = 2,
+    Error = 3,
+    Warning = 4,
+    Notice = 5,
+    #[default]
+    Informational = 6,
+    Debug = 7,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyslogActivityLogStream {
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub protocol: SyslogProtocol,
+    #[serde(default)]
+    pub facility: SyslogFacility,
+    // severity used for event types missing from `severity_mapping`
+    #[serde(default)]
+    pub severity: SyslogSeverity,
+    // severity overrides keyed by activity log event type, e.g. `user_login_failed`
+    #[serde(default)]
+    pub severity_mapping: HashMap<String, SyslogSeverity>,
+    // HOSTNAME field of sent messages, nil value is used if not set
+    pub hostname: Option<String>,
+    // cert to use for tls, may be a bundle of multiple PEM certificates
     pub cert: Option<String>,
+    // client certificate (PEM) presented to the server for mutual TLS
+    pub client_cert: Option<String>,
+    // PKCS#8 private key (PEM) matching `client_cert`
+    pub client_key: Option<SecretStringWrapper>,
+}
+
+impl SyslogActivityLogStream {
+    /// Severity of messages sent for given activity log event type.
+    #[must_use]
+    pub fn severity_for(&self, event_type: &str) -> SyslogSeverity {
+        self.severity_mapping
+            .get(event_type)
+            .copied()
+            .unwrap_or(self.severity)
+    }
+
+    fn validate(&self) -> Result<(), ActivityLogStreamError> {
+        if self.host.trim().is_empty() {
+            return Err(ActivityLogStreamError::InvalidSyslogConfig(
+                "host is required".into(),
+            ));
+        }
+        if self.port == 0 {
+            return Err(ActivityLogStreamError::InvalidSyslogConfig(
+                "port is required".into(),
+            ));
+        }
+        // RFC 5424 limits HOSTNAME to 255 printable ASCII characters
+        if let Some(hostname) = &self.hostname {
+            if hostname.is_empty()
+                || hostname.len() > 255
+                || !hostname.bytes().all(|byte| byte.is_ascii_graphic())
+            {
+                return Err(ActivityLogStreamError::InvalidSyslogConfig(format!(
+                    "invalid hostname: {hostname}"
+                )));
+            }
+        }
+        for event_type in self.severity_mapping.keys() {
+            if serde_json::from_value::<EventType>(serde_json::Value::String(event_type.clone()))
+                .is_err()
+            {
+                return Err(ActivityLogStreamError::InvalidSyslogConfig(format!(
+                    "unknown event type in severity mapping: {event_type}"
+                )));
+            }
+        }
+        if self.protocol == SyslogProtocol::Tls {
+            validate_tls_config(
+                self.cert.as_deref(),
+                self.client_cert.as_deref(),
+                self.client_key.as_ref(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Encoding of Kafka record values.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KafkaPayloadFormat {
+    #[default]
+    Json,
+    Avro,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KafkaSaslMechanism {
+    Plain,
+    ScramSha256,
+    ScramSha512,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KafkaSaslConfig {
+    pub mechanism: KafkaSaslMechanism,
+    pub username: String,
+    pub password: SecretStringWrapper,
+}
+
+// Kafka limits topic names to 249 characters
+const KAFKA_TOPIC_MAX_LENGTH: usize = 249;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KafkaActivityLogStream {
+    // bootstrap brokers in `host:port` format
+    pub brokers: Vec<String>,
+    pub topic: String,
+    #[serde(default)]
+    pub format: KafkaPayloadFormat,
+    pub sasl: Option<KafkaSaslConfig>,
+    #[serde(default)]
+    pub tls: bool,
+    // cert to use for tls, may be a bundle of multiple PEM certificates
+    pub cert: Option<String>,
+    // client certificate (PEM) presented to the broker for mutual TLS
+    pub client_cert: Option<String>,
+    // PKCS#8 private key (PEM) matching `client_cert`
+    pub client_key: Option<SecretStringWrapper>,
+}
+
+impl KafkaActivityLogStream {
+    fn validate(&self) -> Result<(), ActivityLogStreamError> {
+        if self.brokers.is_empty() {
+            return Err(ActivityLogStreamError::InvalidKafkaConfig(
+                "at least one broker is required".into(),
+            ));
+        }
+        for broker in &self.brokers {
+            let valid = broker
+                .rsplit_once(':')
+                .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+            if !valid {
+                return Err(ActivityLogStreamError::InvalidKafkaConfig(format!(
+                    "invalid broker address: {broker}"
+                )));
+            }
+        }
+        if self.topic.is_empty()
+            || self.topic.len() > KAFKA_TOPIC_MAX_LENGTH
+            || self.topic == "."
+            || self.topic == ".."
+            || !self
+                .topic
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        {
+            return Err(ActivityLogStreamError::InvalidKafkaConfig(format!(
+                "invalid topic name: {}",
+                self.topic
+            )));
+        }
+        if let Some(sasl) = &self.sasl {
+            if sasl.username.is_empty() {
+                return Err(ActivityLogStreamError::InvalidKafkaConfig(
+                    "SASL username is required".into(),
+                ));
+            }
+        }
+        if self.tls {
+            validate_tls_config(
+                self.cert.as_deref(),
+                self.client_cert.as_deref(),
+                self.client_key.as_ref(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Make sure TLS material in stream config can be loaded before it's saved.
+fn validate_tls_config(
+    cert: Option<&str>,
+    client_cert: Option<&str>,
+    client_key: Option<&SecretStringWrapper>,
+) -> Result<(), ActivityLogStreamError> {
+    if let Some(cert) = cert {
+        Certificate::from_pem_bundle(cert.as_bytes()).map_err(|err| {
+            ActivityLogStreamError::InvalidTlsConfig(format!("invalid CA certificate: {err}"))
+        })?;
+    }
+    match (client_cert, client_key) {
+        (Some(client_cert), Some(client_key)) => {
+            Identity::from_pkcs8_pem(
+                client_cert.as_bytes(),
+                client_key.expose_secret().as_bytes(),
+            )
+            .map_err(|err| {
+                ActivityLogStreamError::InvalidTlsConfig(format!(
+                    "invalid client certificate or key: {err}"
+                ))
+            })?;
+        }
+        (None, None) => {}
+        _ => {
+            return Err(ActivityLogStreamError::InvalidTlsConfig(
+                "client certificate and key must be provided together".into(),
+            ));
+        }
+    }
+
+    Ok(())
 }
 
 impl ActivityLogStreamConfig {
@@ -61,7 +343,14 @@ impl ActivityLogStreamConfig {
         match stream_type {
             ActivityLogStreamType::VectorHttp => {
                 match serde_json::from_value::<VectorHttpActivityLogStream>(value.clone()) {
-                    Ok(deserialized) => Ok(Self::VectorHttp(deserialized)),
+                    Ok(deserialized) => {
+                        validate_tls_config(
+                            deserialized.cert.as_deref(),
+                            deserialized.client_cert.as_deref(),
+                            deserialized.client_key.as_ref(),
+                        )?;
+                        Ok(Self::VectorHttp(deserialized))
+                    }
                     Err(e) => Err(ActivityLogStreamError::ConfigDeserializeError(
                         stream_type.to_string(),
                         e.to_string(),
@@ -70,7 +359,38 @@ impl ActivityLogStreamConfig {
             }
             ActivityLogStreamType::LogstashHttp => {
                 match serde_json::from_value::<LogstashHttpActivityLogStream>(value.clone()) {
-                    Ok(deserialized) => Ok(Self::LogstashHttp(deserialized)),
+                    Ok(deserialized) => {
+                        validate_tls_config(
+                            deserialized.cert.as_deref(),
+                            deserialized.client_cert.as_deref(),
+                            deserialized.client_key.as_ref(),
+                        )?;
+                        Ok(Self::LogstashHttp(deserialized))
+                    }
+                    Err(e) => Err(ActivityLogStreamError::ConfigDeserializeError(
+                        stream_type.to_string(),
+                        e.to_string(),
+                    )),
+                }
+            }
+            ActivityLogStreamType::Kafka => {
+                match serde_json::from_value::<KafkaActivityLogStream>(value.clone()) {
+                    Ok(deserialized) => {
+                        deserialized.validate()?;
+                        Ok(Self::Kafka(deserialized))
+                    }
+                    Err(e) => Err(ActivityLogStreamError::ConfigDeserializeError(
+                        stream_type.to_string(),
+                        e.to_string(),
+                    )),
+                }
+            }
+            ActivityLogStreamType::Syslog => {
+                match serde_json::from_value::<SyslogActivityLogStream>(value.clone()) {
+                    Ok(deserialized) => {
+                        deserialized.validate()?;
+                        Ok(Self::Syslog(deserialized))
+                    }
                     Err(e) => Err(ActivityLogStreamError::ConfigDeserializeError(
                         stream_type.to_string(),
                         e.to_string(),
@@ -105,3 +425,152 @@ impl ActivityLogStream<Id> {
         Ok(configs)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_http_stream_tls_config_validation() {
+        // plain config without TLS material
+        let config = json!({"url": "https://logs.example.com"});
+        assert!(
+            ActivityLogStreamConfig::from_serde_value(&ActivityLogStreamType::VectorHttp, &config)
+                .is_ok()
+        );
+
+        // client certificate without a key
+        let config = json!({
+            "url": "https://logs.example.com",
+            "client_cert": "-----BEGIN CERTIFICATE-----\n-----END CERTIFICATE-----\n",
+        });
+        assert!(matches!(
+            ActivityLogStreamConfig::from_serde_value(
+                &ActivityLogStreamType::LogstashHttp,
+                &config
+            ),
+            Err(ActivityLogStreamError::InvalidTlsConfig(_))
+        ));
+
+        // malformed CA bundle
+        let config = json!({
+            "url": "https://logs.example.com",
+            "cert": "-----BEGIN CERTIFICATE-----\nnot a certificate\n-----END CERTIFICATE-----\n",
+        });
+        assert!(matches!(
+            ActivityLogStreamConfig::from_serde_value(&ActivityLogStreamType::VectorHttp, &config),
+            Err(ActivityLogStreamError::InvalidTlsConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_syslog_stream_config_validation() {
+        let config = json!({"host": "syslog.example.com", "port": 514});
+        let Ok(ActivityLogStreamConfig::Syslog(stream)) =
+            ActivityLogStreamConfig::from_serde_value(&ActivityLogStreamType::Syslog, &config)
+        else {
+            panic!("valid syslog config rejected");
+        };
+        assert_eq!(stream.protocol, SyslogProtocol::Udp);
+        assert_eq!(stream.facility, SyslogFacility::Local0);
+        assert_eq!(
+            stream.severity_for("user_login"),
+            SyslogSeverity::Informational
+        );
+
+        // severity mapping overrides default severity
+        let config = json!({
+            "host": "syslog.example.com",
+            "port": 6514,
+            "protocol": "tls",
+            "facility": "auth",
+            "severity": "notice",
+            "severity_mapping": {"user_login_failed": "warning"},
+        });
+        let Ok(ActivityLogStreamConfig::Syslog(stream)) =
+            ActivityLogStreamConfig::from_serde_value(&ActivityLogStreamType::Syslog, &config)
+        else {
+            panic!("valid syslog config rejected");
+        };
+        assert_eq!(stream.severity_for("user_login"), SyslogSeverity::Notice);
+        assert_eq!(
+            stream.severity_for("user_login_failed"),
+            SyslogSeverity::Warning
+        );
+
+        // unknown event type
+        let config = json!({
+            "host": "syslog.example.com",
+            "port": 514,
+            "severity_mapping": {"not_an_event": "error"},
+        });
+        assert!(matches!(
+            ActivityLogStreamConfig::from_serde_value(&ActivityLogStreamType::Syslog, &config),
+            Err(ActivityLogStreamError::InvalidSyslogConfig(_))
+        ));
+
+        // missing host
+        let config = json!({"host": " ", "port": 514});
+        assert!(matches!(
+            ActivityLogStreamConfig::from_serde_value(&ActivityLogStreamType::Syslog, &config),
+            Err(ActivityLogStreamError::InvalidSyslogConfig(_))
+        ));
+
+        // unknown protocol
+        let config = json!({"host": "syslog.example.com", "port": 514, "protocol": "http"});
+        assert!(matches!(
+            ActivityLogStreamConfig::from_serde_value(&ActivityLogStreamType::Syslog, &config),
+            Err(ActivityLogStreamError::ConfigDeserializeError(..))
+        ));
+    }
+
+    #[test]
+    fn test_kafka_stream_config_validation() {
+        let config = json!({
+            "brokers": ["kafka-1.example.com:9092", "kafka-2.example.com:9092"],
+            "topic": "defguard.activity-log",
+        });
+        let Ok(ActivityLogStreamConfig::Kafka(stream)) =
+            ActivityLogStreamConfig::from_serde_value(&ActivityLogStreamType::Kafka, &config)
+        else {
+            panic!("valid kafka config rejected");
+        };
+        assert_eq!(stream.format, KafkaPayloadFormat::Json);
+        assert!(!stream.tls);
+
+        let config = json!({
+            "brokers": ["kafka.example.com:9093"],
+            "topic": "activity",
+            "format": "avro",
+            "tls": true,
+            "sasl": {"mechanism": "scram-sha-512", "username": "defguard", "password": "secret"},
+        });
+        assert!(
+            ActivityLogStreamConfig::from_serde_value(&ActivityLogStreamType::Kafka, &config)
+                .is_ok()
+        );
+
+        // broker without port
+        let config = json!({"brokers": ["kafka.example.com"], "topic": "activity"});
+        assert!(matches!(
+            ActivityLogStreamConfig::from_serde_value(&ActivityLogStreamType::Kafka, &config),
+            Err(ActivityLogStreamError::InvalidKafkaConfig(_))
+        ));
+
+        // no brokers
+        let config = json!({"brokers": [], "topic": "activity"});
+        assert!(matches!(
+            ActivityLogStreamConfig::from_serde_value(&ActivityLogStreamType::Kafka, &config),
+            Err(ActivityLogStreamError::InvalidKafkaConfig(_))
+        ));
+
+        // invalid topic name
+        let config = json!({"brokers": ["kafka.example.com:9092"], "topic": "activity log"});
+        assert!(matches!(
+            ActivityLogStreamConfig::from_serde_value(&ActivityLogStreamType::Kafka, &config),
+            Err(ActivityLogStreamError::InvalidKafkaConfig(_))
+        ));
+    }
+}
@@ -1,7 +1,190 @@
+use std::{fmt, str::FromStr};
+
+use axum::http::Method;
 use chrono::NaiveDateTime;
 use defguard_common::db::{Id, NoId};
 use model_derive::Model;
-use sqlx::{Error as SqlxError, PgExecutor, query_as};
+use sqlx::{Error as SqlxError, PgExecutor, Type, query_as};
+
+/// Permission scopes which restrict what an API token can be used for.
+/// Tokens without any scopes have the same access as their (admin) owner.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, Type)]
+#[sqlx(type_name = "api_token_scope", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ApiTokenScope {
+    /// Reading users, groups, locations and devices.
+    ReadOnly,
+    /// Users and groups, excluding API token management.
+    UserManagement,
+    /// Locations and devices.
+    NetworkManagement,
+}
+
+impl ApiTokenScope {
+    /// Checks if this scope covers a request.
+    #[must_use]
+    pub(crate) fn allows(self, method: &Method, path: &str) -> bool {
+        let Some(route) = path.strip_prefix("/api/v1/") else {
+            return false;
+        };
+        let route = route.trim_end_matches('/');
+        let routes: &[(Method, &str)] = match self {
+            Self::ReadOnly => &READ_ONLY_ROUTES,
+            Self::UserManagement => &USER_MANAGEMENT_ROUTES,
+            Self::NetworkManagement => &NETWORK_MANAGEMENT_ROUTES,
+        };
+        routes
+            .iter()
+            .any(|(allowed, pattern)| allowed == method && route_matches(pattern, route))
+    }
+}
+
+impl fmt::Display for ApiTokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReadOnly => write!(f, "read_only"),
+            Self::UserManagement => write!(f, "user_management"),
+            Self::NetworkManagement => write!(f, "network_management"),
+        }
+    }
+}
+
+impl FromStr for ApiTokenScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read_only" => Ok(Self::ReadOnly),
+            "user_management" => Ok(Self::UserManagement),
+            "network_management" => Ok(Self::NetworkManagement),
+            _ => Err(format!("Unknown API token scope: {s}")),
+        }
+    }
+}
+
+/// Checks if a route (relative to `/api/v1`) matches a pattern, in which `*` stands for
+/// a single path segment.
+fn route_matches(pattern: &str, route: &str) -> bool {
+    let mut segments = route.split('/');
+    pattern.split('/').all(|part| {
+        segments
+            .next()
+            .is_some_and(|segment| part == "*" || part == segment)
+    }) && segments.next().is_none()
+}
+
+/// Routes (relative to `/api/v1`) covered by [`ApiTokenScope::ReadOnly`].
+/// Settings, API tokens and routes which issue tokens are deliberately left out.
+static READ_ONLY_ROUTES: [(Method, &str); 24] = [
+    (Method::GET, "me"),
+    (Method::GET, "user"),
+    (Method::GET, "user/*"),
+    (Method::GET, "user/*/vpn-sessions"),
+    (Method::GET, "group"),
+    (Method::GET, "group/*"),
+    (Method::GET, "group-info"),
+    (Method::GET, "group-quota"),
+    (Method::GET, "network"),
+    (Method::GET, "network/*"),
+    (Method::GET, "network/*/gateways"),
+    (Method::GET, "network/*/connectivity"),
+    (Method::GET, "network/*/stats"),
+    (Method::GET, "network/*/stats/users"),
+    (Method::GET, "network/*/stats/aggregated"),
+    (Method::GET, "device"),
+    (Method::GET, "device/*"),
+    (Method::GET, "device/*/key-history"),
+    (Method::GET, "device/*/vpn-sessions"),
+    (Method::GET, "device/user/*"),
+    (Method::GET, "device/network"),
+    (Method::GET, "device/network/*"),
+    (Method::GET, "device_profile"),
+    (Method::GET, "device_profile/*"),
+];
+
+/// Routes (relative to `/api/v1`) covered by [`ApiTokenScope::UserManagement`].
+/// API token management is deliberately left out.
+static USER_MANAGEMENT_ROUTES: [(Method, &str); 25] = [
+    (Method::GET, "user"),
+    (Method::POST, "user"),
+    (Method::POST, "user/available"),
+    (Method::GET, "user/*"),
+    (Method::PUT, "user/*"),
+    (Method::DELETE, "user/*"),
+    (Method::POST, "user/*/start_enrollment"),
+    (Method::PUT, "user/*/password"),
+    (Method::POST, "user/*/reset_password"),
+    (Method::DELETE, "user/*/mfa"),
+    (Method::GET, "user/*/session"),
+    (Method::DELETE, "user/*/session"),
+    (Method::DELETE, "user/*/lockout"),
+    (Method::GET, "group"),
+    (Method::POST, "group"),
+    (Method::GET, "group/*"),
+    (Method::PUT, "group/*"),
+    (Method::DELETE, "group/*"),
+    (Method::POST, "group/*"),
+    (Method::DELETE, "group/*/user/*"),
+    (Method::PUT, "group/*/owners"),
+    (Method::GET, "group-info"),
+    (Method::POST, "groups-assign"),
+    (Method::GET, "group-quota"),
+    (Method::PUT, "group-quota/*"),
+];
+
+/// Routes (relative to `/api/v1`) covered by [`ApiTokenScope::NetworkManagement`].
+/// Gateway and network token issuance is deliberately left out.
+static NETWORK_MANAGEMENT_ROUTES: [(Method, &str); 49] = [
+    (Method::GET, "network"),
+    (Method::POST, "network"),
+    (Method::GET, "network/*"),
+    (Method::PUT, "network/*"),
+    (Method::DELETE, "network/*"),
+    (Method::POST, "network/*/clone"),
+    (Method::PUT, "network/*/maintenance"),
+    (Method::GET, "network/*/gateways"),
+    (Method::DELETE, "network/*/gateways/*"),
+    (Method::GET, "network/*/connectivity"),
+    (Method::POST, "network/*/devices"),
+    (Method::GET, "network/*/stats"),
+    (Method::GET, "network/*/stats/users"),
+    (Method::GET, "network/*/stats/aggregated"),
+    (Method::GET, "network/*/snat"),
+    (Method::POST, "network/*/snat"),
+    (Method::PUT, "network/*/snat/*"),
+    (Method::DELETE, "network/*/snat/*"),
+    (Method::GET, "network/*/posture_policy"),
+    (Method::PUT, "network/*/posture_policy"),
+    (Method::DELETE, "network/*/posture_policy"),
+    (Method::GET, "network/*/device/*/ip"),
+    (Method::PUT, "network/*/device/*/ip"),
+    (Method::DELETE, "network/*/device/*/ip"),
+    (Method::GET, "device"),
+    (Method::GET, "device/*"),
+    (Method::POST, "device/*"),
+    (Method::PUT, "device/*"),
+    (Method::DELETE, "device/*"),
+    (Method::PUT, "device/*/profile"),
+    (Method::PUT, "device/*/expiry"),
+    (Method::GET, "device/*/key-history"),
+    (Method::GET, "device/*/vpn-sessions"),
+    (Method::GET, "device/user/*"),
+    (Method::GET, "device/network"),
+    (Method::POST, "device/network"),
+    (Method::GET, "device/network/*"),
+    (Method::PUT, "device/network/*"),
+    (Method::DELETE, "device/network/*"),
+    (Method::GET, "device/network/ip/*"),
+    (Method::POST, "device/network/ip/*"),
+    (Method::GET, "device_profile"),
+    (Method::POST, "device_profile"),
+    (Method::GET, "device_profile/*"),
+    (Method::PUT, "device_profile/*"),
+    (Method::DELETE, "device_profile/*"),
+    (Method::GET, "device-approval"),
+    (Method::POST, "device-approval/*/approve"),
+    (Method::POST, "device-approval/*/reject"),
+];
 
 #[derive(Clone, Debug, Deserialize, Model, Serialize, PartialEq)]
 #[table(api_token)]
@@ -11,11 +194,30 @@ pub struct ApiToken<I = NoId> {
     pub created_at: NaiveDateTime,
     pub name: String,
     pub token_hash: String,
+    /// Location this token is scoped to. Scoped tokens only grant read-only access
+    /// to the location's details, gateway status and peer statistics.
+    pub location_id: Option<Id>,
+    /// Group this token is bound to. The token stops working once its owner
+    /// is no longer a member of the group.
+    pub group_id: Option<Id>,
+    #[model(enum)]
+    pub scopes: Vec<ApiTokenScope>,
 }
 
+/// Routes (relative to `/api/v1/network/{location_id}`) available to location-scoped tokens.
+const LOCATION_TOKEN_ROUTES: [&str; 4] = ["", "gateways", "stats", "stats/users"];
+
 impl ApiToken {
     #[must_use]
-    pub fn new(user_id: Id, created_at: NaiveDateTime, name: String, token_string: &str) -> Self {
+    pub fn new(
+        user_id: Id,
+        created_at: NaiveDateTime,
+        name: String,
+        token_string: &str,
+        location_id: Option<Id>,
+        group_id: Option<Id>,
+        scopes: Vec<ApiTokenScope>,
+    ) -> Self {
         let token_hash = Self::hash_token(token_string);
         Self {
             id: NoId,
@@ -23,6 +225,9 @@ impl ApiToken {
             created_at,
             name,
             token_hash,
+            location_id,
+            group_id,
+            scopes,
         }
     }
 
@@ -39,8 +244,8 @@ impl ApiToken<Id> {
     {
         query_as!(
             Self,
-            "SELECT id, user_id, created_at, name, token_hash \
-                    FROM api_token WHERE user_id = $1 ORDER BY id",
+            "SELECT id, user_id, created_at, name, token_hash, location_id, group_id, \
+                    scopes \"scopes: _\" FROM api_token WHERE user_id = $1 ORDER BY id",
             user_id
         )
         .fetch_all(executor)
@@ -57,15 +262,38 @@ impl ApiToken<Id> {
         let token_hash = ApiToken::hash_token(auth_token);
         let maybe_token = query_as!(
             Self,
-            "SELECT at.id, user_id, created_at, name, token_hash \
-             FROM api_token at JOIN \"user\" ON \"user\".id = user_id \
-             WHERE token_hash = $1 AND \"user\".is_active = true",
+            "SELECT at.id, at.user_id, created_at, name, token_hash, location_id, at.group_id, \
+             scopes \"scopes: _\" FROM api_token at JOIN \"user\" ON \"user\".id = at.user_id \
+             WHERE token_hash = $1 AND \"user\".is_active = true AND (at.group_id IS NULL \
+             OR EXISTS (SELECT 1 FROM group_user_effective gu \
+             WHERE gu.group_id = at.group_id AND gu.user_id = at.user_id))",
             token_hash
         )
         .fetch_optional(executor)
         .await?;
         Ok(maybe_token)
     }
+
+    /// Checks if this token can be used to authorize a request.
+    /// Tokens without a location scope or permission scopes are not restricted here.
+    #[must_use]
+    pub(crate) fn allows_request(&self, method: &Method, path: &str) -> bool {
+        if !self.scopes.is_empty() && !self.scopes.iter().any(|scope| scope.allows(method, path)) {
+            return false;
+        }
+        let Some(location_id) = self.location_id else {
+            return true;
+        };
+        if method != Method::GET {
+            return false;
+        }
+        let Some(rest) = path.strip_prefix("/api/v1/network/") else {
+            return false;
+        };
+        let rest = rest.trim_end_matches('/');
+        let (id, route) = rest.split_once('/').unwrap_or((rest, ""));
+        id.parse::<Id>().is_ok_and(|id| id == location_id) && LOCATION_TOKEN_ROUTES.contains(&route)
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -73,6 +301,9 @@ pub struct ApiTokenInfo {
     pub id: Id,
     pub name: String,
     pub created_at: NaiveDateTime,
+    pub location_id: Option<Id>,
+    pub group_id: Option<Id>,
+    pub scopes: Vec<ApiTokenScope>,
 }
 
 impl From<ApiToken<Id>> for ApiTokenInfo {
@@ -81,6 +312,92 @@ impl From<ApiToken<Id>> for ApiTokenInfo {
             id: token.id,
             name: token.name,
             created_at: token.created_at,
+            location_id: token.location_id,
+            group_id: token.group_id,
+            scopes: token.scopes,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+
+    use super::*;
+
+    #[test]
+    fn test_location_token_allowed_routes() {
+        let mut token = ApiToken::new(
+            1,
+            Utc::now().naive_utc(),
+            "token".into(),
+            "secret",
+            None,
+            None,
+            Vec::new(),
+        )
+        .with_id(1);
+        assert!(token.allows_request(&Method::DELETE, "/api/v1/user/admin"));
+
+        token.location_id = Some(2);
+        assert!(token.allows_request(&Method::GET, "/api/v1/network/2"));
+        assert!(token.allows_request(&Method::GET, "/api/v1/network/2/gateways"));
+        assert!(token.allows_request(&Method::GET, "/api/v1/network/2/stats"));
+        assert!(token.allows_request(&Method::GET, "/api/v1/network/2/stats/users"));
+
+        assert!(!token.allows_request(&Method::PUT, "/api/v1/network/2"));
+        assert!(!token.allows_request(&Method::GET, "/api/v1/network/3/stats"));
+        assert!(!token.allows_request(&Method::GET, "/api/v1/network/2/token"));
+        assert!(!token.allows_request(&Method::GET, "/api/v1/network"));
+        assert!(!token.allows_request(&Method::GET, "/api/v1/me"));
+    }
+
+    #[test]
+    fn test_token_permission_scopes() {
+        let mut token = ApiToken::new(
+            1,
+            Utc::now().naive_utc(),
+            "token".into(),
+            "secret",
+            None,
+            Some(1),
+            vec![ApiTokenScope::ReadOnly],
+        )
+        .with_id(1);
+        assert!(token.allows_request(&Method::GET, "/api/v1/user"));
+        assert!(token.allows_request(&Method::GET, "/api/v1/network/1"));
+        assert!(!token.allows_request(&Method::POST, "/api/v1/user"));
+        // settings, other users' tokens and token issuance aren't covered
+        assert!(!token.allows_request(&Method::GET, "/api/v1/settings"));
+        assert!(!token.allows_request(&Method::GET, "/api/v1/settings_enterprise"));
+        assert!(!token.allows_request(&Method::GET, "/api/v1/user/admin/api_token"));
+        assert!(!token.allows_request(&Method::GET, "/api/v1/network/1/token"));
+        assert!(!token.allows_request(&Method::GET, "/api/v1/network/1/gateway_token"));
+        assert!(!token.allows_request(&Method::GET, "/api/v1/activity_log"));
+
+        token.scopes = vec![ApiTokenScope::UserManagement];
+        assert!(token.allows_request(&Method::POST, "/api/v1/user"));
+        assert!(token.allows_request(&Method::PUT, "/api/v1/user/hpotter"));
+        assert!(token.allows_request(&Method::DELETE, "/api/v1/group/students/user/hpotter"));
+        assert!(token.allows_request(&Method::GET, "/api/v1/group-info"));
+        assert!(!token.allows_request(&Method::POST, "/api/v1/user/hpotter/api_token"));
+        assert!(!token.allows_request(&Method::GET, "/api/v1/user/hpotter/api_token"));
+        assert!(!token.allows_request(&Method::GET, "/api/v1/network"));
+        assert!(!token.allows_request(&Method::GET, "/api/v1/userinfo"));
+        assert!(!token.allows_request(&Method::PUT, "/api/v1/group/students/sync_authority"));
+        assert!(!token.allows_request(&Method::PUT, "/api/v1/user/hpotter/auth_key"));
+
+        token.scopes = vec![ApiTokenScope::ReadOnly, ApiTokenScope::NetworkManagement];
+        assert!(token.allows_request(&Method::POST, "/api/v1/network"));
+        assert!(token.allows_request(&Method::DELETE, "/api/v1/device/1"));
+        assert!(token.allows_request(&Method::GET, "/api/v1/user"));
+        assert!(!token.allows_request(&Method::DELETE, "/api/v1/user/hpotter"));
+        assert!(!token.allows_request(&Method::POST, "/api/v1/network/1/gateway_token"));
+        assert!(!token.allows_request(&Method::PUT, "/api/v1/settings"));
+
+        // location scope still applies
+        token.location_id = Some(2);
+        assert!(token.allows_request(&Method::GET, "/api/v1/network/2"));
+        assert!(!token.allows_request(&Method::GET, "/api/v1/user"));
+    }
+}
@@ -0,0 +1,156 @@
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use defguard_common::db::{Id, NoId};
+use model_derive::Model;
+use sqlx::{Error as SqlxError, PgExecutor, query, query_as};
+
+use super::api_tokens::ApiTokenScope;
+
+/// Configuration of the `client_credentials` grant for an OpenID client.
+/// Tokens issued to the client act on behalf of a service account, limited to given scopes.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ClientCredentials {
+    pub oauth2client_id: Id,
+    pub user_id: Id,
+    pub scopes: Vec<ApiTokenScope>,
+}
+
+impl ClientCredentials {
+    pub async fn find_by_client<'e, E>(
+        executor: E,
+        oauth2client_id: Id,
+    ) -> Result<Option<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT oauth2client_id, user_id, scopes \"scopes: _\" FROM oauth2client_credentials \
+            WHERE oauth2client_id = $1",
+            oauth2client_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn save<'e, E>(&self, executor: E) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "INSERT INTO oauth2client_credentials (oauth2client_id, user_id, scopes) \
+            VALUES ($1, $2, $3) \
+            ON CONFLICT (oauth2client_id) DO UPDATE SET user_id = $2, scopes = $3",
+            self.oauth2client_id,
+            self.user_id,
+            &self.scopes as &Vec<ApiTokenScope>
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Disables the grant for a client.
+    pub async fn delete_for_client<'e, E>(executor: E, oauth2client_id: Id) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "DELETE FROM oauth2client_credentials WHERE oauth2client_id = $1",
+            oauth2client_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Access token issued with the `client_credentials` grant. Only a hash of the token is stored.
+#[derive(Clone, Debug, Model)]
+#[table(oauth2client_token)]
+pub struct ClientCredentialsToken<I = NoId> {
+    pub id: I,
+    pub oauth2client_id: Id,
+    pub user_id: Id,
+    pub token_hash: String,
+    #[model(enum)]
+    pub scopes: Vec<ApiTokenScope>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+impl ClientCredentialsToken {
+    #[must_use]
+    pub fn new(
+        credentials: &ClientCredentials,
+        token_string: &str,
+        scopes: Vec<ApiTokenScope>,
+        lifetime: TimeDelta,
+    ) -> Self {
+        let created_at = Utc::now().naive_utc();
+        Self {
+            id: NoId,
+            oauth2client_id: credentials.oauth2client_id,
+            user_id: credentials.user_id,
+            token_hash: Self::hash_token(token_string),
+            scopes,
+            created_at,
+            expires_at: created_at + lifetime,
+        }
+    }
+}
+
+impl<I> ClientCredentialsToken<I> {
+    fn hash_token(token_string: &str) -> String {
+        sha256::digest(token_string)
+    }
+}
+
+impl ClientCredentialsToken<Id> {
+    /// Finds a token which hasn't expired, issued to an enabled client on behalf of an active
+    /// user.
+    pub async fn find_active<'e, E>(
+        executor: E,
+        token_string: &str,
+    ) -> Result<Option<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT t.id, t.oauth2client_id, t.user_id, t.token_hash, t.scopes \"scopes: _\", \
+            t.created_at, t.expires_at FROM oauth2client_token t \
+            JOIN oauth2client c ON c.id = t.oauth2client_id \
+            JOIN \"user\" u ON u.id = t.user_id \
+            WHERE t.token_hash = $1 AND t.expires_at > NOW() AT TIME ZONE 'UTC' \
+            AND c.enabled AND u.is_active",
+            Self::hash_token(token_string)
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Removes tokens of a client, e.g. after the grant has been disabled.
+    pub async fn delete_for_client<'e, E>(executor: E, oauth2client_id: Id) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "DELETE FROM oauth2client_token WHERE oauth2client_id = $1",
+            oauth2client_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes tokens which have expired.
+    pub async fn delete_expired<'e, E>(executor: E) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!("DELETE FROM oauth2client_token WHERE expires_at <= NOW() AT TIME ZONE 'UTC'")
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+}
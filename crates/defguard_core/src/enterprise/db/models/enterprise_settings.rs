@@ -1,3 +1,6 @@
+use std::net::IpAddr;
+
+use ipnetwork::IpNetwork;
 use sqlx::{PgExecutor, Type, query, query_as};
 use struct_patch::Patch;
 
@@ -12,6 +15,11 @@ pub struct EnterpriseSettings {
     pub client_traffic_policy: ClientTrafficPolicy,
     /// If true, manual WireGuard setup is disabled
     pub only_client_activation: bool,
+    /// Networks from which enrollment and password reset may be started.
+    /// Empty list means no network restriction.
+    pub self_service_allowed_networks: Vec<IpNetwork>,
+    /// If true, enrollment and password reset may also be started from VPN location addresses.
+    pub self_service_allow_vpn: bool,
 }
 
 // We want to be conscious of what the defaults are here
@@ -22,6 +30,8 @@ impl Default for EnterpriseSettings {
             admin_device_management: false,
             only_client_activation: false,
             client_traffic_policy: ClientTrafficPolicy::default(),
+            self_service_allowed_networks: Vec::new(),
+            self_service_allow_vpn: false,
         }
     }
 }
@@ -40,7 +50,7 @@ impl EnterpriseSettings {
                 Self,
                 "SELECT admin_device_management, \
 				client_traffic_policy \"client_traffic_policy: ClientTrafficPolicy\", \
-				only_client_activation \
+				only_client_activation, self_service_allowed_networks, self_service_allow_vpn \
                 FROM \"enterprisesettings\" WHERE id = 1",
             )
             .fetch_optional(executor)
@@ -59,17 +69,47 @@ impl EnterpriseSettings {
             "UPDATE \"enterprisesettings\" SET \
             admin_device_management = $1, \
 			client_traffic_policy = $2, \
-            only_client_activation = $3 \
+            only_client_activation = $3, \
+            self_service_allowed_networks = $4, \
+            self_service_allow_vpn = $5 \
             WHERE id = 1",
             self.admin_device_management,
             self.client_traffic_policy as ClientTrafficPolicy,
             self.only_client_activation,
+            &self.self_service_allowed_networks as &Vec<IpNetwork>,
+            self.self_service_allow_vpn,
         )
         .execute(executor)
         .await?;
 
         Ok(())
     }
+
+    /// Returns `true` if enrollment and password reset are restricted to some networks.
+    #[must_use]
+    pub(crate) fn self_service_restricted(&self) -> bool {
+        !self.self_service_allowed_networks.is_empty() || self.self_service_allow_vpn
+    }
+
+    /// Checks if enrollment and password reset may be started from given client address.
+    /// `location_addresses` are the addresses of all VPN locations.
+    #[must_use]
+    pub(crate) fn self_service_allows(&self, ip: IpAddr, location_addresses: &[IpNetwork]) -> bool {
+        if !self.self_service_restricted() {
+            return true;
+        }
+        if self
+            .self_service_allowed_networks
+            .iter()
+            .any(|network| network.contains(ip))
+        {
+            return true;
+        }
+        self.self_service_allow_vpn
+            && location_addresses
+                .iter()
+                .any(|network| network.contains(ip))
+    }
 }
 
 /// Describes allowed traffic options for clients connecting to the instance.
@@ -85,3 +125,34 @@ pub enum ClientTrafficPolicy {
     /// Clients are forced to route all traffic through the VPN.
     ForceAllTraffic,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_self_service_allows() {
+        let location_addresses = ["10.1.0.1/24".parse().unwrap()];
+        let office_ip = "192.168.5.10".parse().unwrap();
+        let vpn_ip = "10.1.0.7".parse().unwrap();
+        let public_ip = "203.0.113.5".parse().unwrap();
+
+        let mut settings = EnterpriseSettings::default();
+        assert!(!settings.self_service_restricted());
+        assert!(settings.self_service_allows(public_ip, &location_addresses));
+
+        settings.self_service_allowed_networks = vec!["192.168.0.0/16".parse().unwrap()];
+        assert!(settings.self_service_allows(office_ip, &location_addresses));
+        assert!(!settings.self_service_allows(vpn_ip, &location_addresses));
+        assert!(!settings.self_service_allows(public_ip, &location_addresses));
+
+        settings.self_service_allow_vpn = true;
+        assert!(settings.self_service_allows(office_ip, &location_addresses));
+        assert!(settings.self_service_allows(vpn_ip, &location_addresses));
+        assert!(!settings.self_service_allows(public_ip, &location_addresses));
+
+        settings.self_service_allowed_networks.clear();
+        assert!(!settings.self_service_allows(office_ip, &location_addresses));
+        assert!(settings.self_service_allows(vpn_ip, &location_addresses));
+    }
+}
@@ -0,0 +1,203 @@
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use defguard_common::db::{Id, NoId};
+use model_derive::Model;
+use sqlx::{PgExecutor, Type, query_as, query_scalar};
+use utoipa::ToSchema;
+
+/// Number of attempts after which a queued operation is marked as failed.
+pub const LDAP_OPERATION_MAX_ATTEMPTS: i32 = 10;
+// Times in seconds
+const LDAP_OPERATION_RETRY_BASE_DELAY: i64 = 30;
+const LDAP_OPERATION_RETRY_MAX_DELAY: i64 = 60 * 60;
+
+/// LDAP modification stored in the outbound operation queue.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, Type, ToSchema)]
+#[sqlx(type_name = "ldap_operation_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum LdapOperationKind {
+    AddUserToGroups,
+    RemoveUserFromGroups,
+    UpdateUserState,
+    ModifyGroup,
+    DeleteGroup,
+}
+
+/// LDAP modification which couldn't be applied and is retried in the background.
+#[derive(Clone, Debug, Deserialize, Model, PartialEq, Serialize, ToSchema)]
+#[table(ldap_operation)]
+pub struct LdapOperation<I = NoId> {
+    pub id: I,
+    #[model(enum)]
+    pub kind: LdapOperationKind,
+    pub user_id: Option<Id>,
+    pub group_id: Option<Id>,
+    /// Names of affected groups. For group modifications, the name before the modification.
+    #[model(ref)]
+    pub group_names: Vec<String>,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: NaiveDateTime,
+    /// Set once all attempts are used up, failed operations are no longer retried.
+    pub failed: bool,
+    pub created_at: NaiveDateTime,
+}
+
+/// Delay before the next attempt, doubled with every failed attempt.
+fn retry_delay(attempts: i32) -> TimeDelta {
+    let exponent = u32::try_from(attempts - 1).unwrap_or_default().min(16);
+    TimeDelta::seconds(
+        (LDAP_OPERATION_RETRY_BASE_DELAY << exponent).min(LDAP_OPERATION_RETRY_MAX_DELAY),
+    )
+}
+
+impl LdapOperation {
+    #[must_use]
+    fn new(
+        kind: LdapOperationKind,
+        user_id: Option<Id>,
+        group_id: Option<Id>,
+        group_names: Vec<String>,
+    ) -> Self {
+        let now = Utc::now().naive_utc();
+        Self {
+            id: NoId,
+            kind,
+            user_id,
+            group_id,
+            group_names,
+            attempts: 0,
+            last_error: None,
+            next_attempt_at: now,
+            failed: false,
+            created_at: now,
+        }
+    }
+
+    #[must_use]
+    pub fn add_user_to_groups(user_id: Id, group_names: Vec<String>) -> Self {
+        Self::new(
+            LdapOperationKind::AddUserToGroups,
+            Some(user_id),
+            None,
+            group_names,
+        )
+    }
+
+    #[must_use]
+    pub fn remove_user_from_groups(user_id: Id, group_names: Vec<String>) -> Self {
+        Self::new(
+            LdapOperationKind::RemoveUserFromGroups,
+            Some(user_id),
+            None,
+            group_names,
+        )
+    }
+
+    #[must_use]
+    pub fn update_user_state(user_id: Id) -> Self {
+        Self::new(
+            LdapOperationKind::UpdateUserState,
+            Some(user_id),
+            None,
+            Vec::new(),
+        )
+    }
+
+    #[must_use]
+    pub fn modify_group(old_name: String, group_id: Id) -> Self {
+        Self::new(
+            LdapOperationKind::ModifyGroup,
+            None,
+            Some(group_id),
+            vec![old_name],
+        )
+    }
+
+    #[must_use]
+    pub fn delete_group(name: String) -> Self {
+        Self::new(LdapOperationKind::DeleteGroup, None, None, vec![name])
+    }
+}
+
+impl<I> LdapOperation<I> {
+    /// Records a failed attempt and schedules the next one with exponential backoff.
+    /// Marks the operation as failed once all attempts are used up.
+    pub fn register_failure(&mut self, error: String) {
+        self.attempts += 1;
+        self.last_error = Some(error);
+        if self.attempts >= LDAP_OPERATION_MAX_ATTEMPTS {
+            self.failed = true;
+        } else {
+            self.next_attempt_at = Utc::now().naive_utc() + retry_delay(self.attempts);
+        }
+    }
+}
+
+impl LdapOperation<Id> {
+    /// Fetches operations awaiting a retry in the order they were queued.
+    pub(crate) async fn all_pending<'e, E>(executor: E) -> Result<Vec<Self>, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, kind \"kind: _\", user_id, group_id, group_names, attempts, last_error, \
+            next_attempt_at, failed, created_at FROM ldap_operation WHERE NOT failed ORDER BY id"
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Returns the time of the next attempt of the oldest operation awaiting a retry, if any.
+    pub(crate) async fn first_pending_attempt<'e, E>(
+        executor: E,
+    ) -> Result<Option<NaiveDateTime>, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT next_attempt_at FROM ldap_operation WHERE NOT failed ORDER BY id LIMIT 1"
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Makes a failed operation eligible for automatic retries again.
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+        self.failed = false;
+        self.next_attempt_at = Utc::now().naive_utc();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_register_failure() {
+        let mut operation = LdapOperation::delete_group("staff".into());
+        let queued_at = operation.next_attempt_at;
+
+        operation.register_failure("connection refused".into());
+        assert_eq!(operation.attempts, 1);
+        assert_eq!(operation.last_error.as_deref(), Some("connection refused"));
+        assert!(!operation.failed);
+        assert!(operation.next_attempt_at >= queued_at + TimeDelta::seconds(30));
+
+        for _ in 1..LDAP_OPERATION_MAX_ATTEMPTS {
+            operation.register_failure("connection refused".into());
+        }
+        assert_eq!(operation.attempts, LDAP_OPERATION_MAX_ATTEMPTS);
+        assert!(operation.failed);
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), TimeDelta::seconds(30));
+        assert_eq!(retry_delay(2), TimeDelta::seconds(60));
+        assert_eq!(retry_delay(4), TimeDelta::seconds(240));
+        assert_eq!(retry_delay(8), TimeDelta::hours(1));
+        assert_eq!(retry_delay(100), TimeDelta::hours(1));
+    }
+}
@@ -0,0 +1,59 @@
+use chrono::{NaiveDateTime, Utc};
+use defguard_common::db::{Id, NoId};
+use model_derive::Model;
+use sqlx::{PgConnection, query};
+use utoipa::ToSchema;
+
+use crate::db::models::group::GroupSyncAuthority;
+
+/// Difference in group membership between Defguard and LDAP which was resolved by overwriting
+/// one of the sides during the last LDAP sync.
+#[derive(Clone, Debug, Deserialize, Model, PartialEq, Serialize, ToSchema)]
+#[table(ldap_sync_conflict)]
+pub struct LdapSyncConflict<I = NoId> {
+    pub id: I,
+    pub group_name: String,
+    /// Usernames of members present only in Defguard.
+    #[model(ref)]
+    pub defguard_only: Vec<String>,
+    /// Usernames of members present only in LDAP.
+    #[model(ref)]
+    pub ldap_only: Vec<String>,
+    /// Side whose membership was kept.
+    #[model(enum)]
+    pub resolution: GroupSyncAuthority,
+    pub detected_at: NaiveDateTime,
+}
+
+impl LdapSyncConflict {
+    #[must_use]
+    pub fn new(
+        group_name: String,
+        defguard_only: Vec<String>,
+        ldap_only: Vec<String>,
+        resolution: GroupSyncAuthority,
+    ) -> Self {
+        Self {
+            id: NoId,
+            group_name,
+            defguard_only,
+            ldap_only,
+            resolution,
+            detected_at: Utc::now().naive_utc(),
+        }
+    }
+
+    /// Replaces conflicts stored during the previous sync.
+    pub(crate) async fn replace_all(
+        transaction: &mut PgConnection,
+        conflicts: Vec<Self>,
+    ) -> Result<(), sqlx::Error> {
+        query!("DELETE FROM ldap_sync_conflict")
+            .execute(&mut *transaction)
+            .await?;
+        for conflict in conflicts {
+            conflict.save(&mut *transaction).await?;
+        }
+        Ok(())
+    }
+}
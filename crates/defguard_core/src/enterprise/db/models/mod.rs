@@ -1,6 +1,12 @@
 pub mod acl;
 pub mod activity_log_stream;
 pub mod api_tokens;
+pub mod client_credentials;
 pub mod enterprise_settings;
+pub mod ldap_operation;
+pub mod ldap_sync_conflict;
 pub mod openid_provider;
+pub mod posture_policy;
+pub mod provisioning_policy;
+pub mod saml_provider;
 pub mod snat;
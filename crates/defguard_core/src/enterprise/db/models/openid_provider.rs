@@ -86,6 +86,18 @@ impl From<String> for DirectorySyncTarget {
     }
 }
 
+/// Mapping of an ID token claim, e.g. `groups` or `roles`, onto Defguard groups.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct GroupClaimMapping {
+    /// Name of the claim, nested claims are separated with dots, e.g. `realm_access.roles`.
+    /// `None` disables the mapping.
+    pub claim: Option<String>,
+    /// Create groups which don't exist in Defguard, otherwise such claim values are skipped.
+    pub create_groups: bool,
+    /// Only log membership changes instead of applying them.
+    pub dry_run: bool,
+}
+
 #[derive(Clone, Debug, Deserialize, Model, Serialize, PartialEq)]
 pub struct OpenIdProvider<I = NoId> {
     pub id: I,
@@ -247,4 +259,46 @@ impl OpenIdProvider<Id> {
         .fetch_optional(executor)
         .await
     }
+
+    pub(crate) async fn group_claim_mapping<'e, E>(
+        &self,
+        executor: E,
+    ) -> Result<GroupClaimMapping, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            GroupClaimMapping,
+            "SELECT group_claim claim, group_claim_create_groups create_groups, \
+            group_claim_dry_run dry_run FROM openidprovider WHERE id = $1",
+            self.id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub(crate) async fn set_group_claim_mapping<'e, E>(
+        &self,
+        executor: E,
+        mapping: &GroupClaimMapping,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        info!(
+            "Setting group claim mapping of OpenID provider {} to: {mapping:?}",
+            self.name
+        );
+        query!(
+            "UPDATE openidprovider SET group_claim = $2, group_claim_create_groups = $3, \
+            group_claim_dry_run = $4 WHERE id = $1",
+            self.id,
+            mapping.claim,
+            mapping.create_groups,
+            mapping.dry_run
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
 }
@@ -0,0 +1,214 @@
+use std::fmt;
+
+use defguard_common::db::{Id, NoId};
+use model_derive::Model;
+use sqlx::{PgExecutor, query_as};
+use utoipa::ToSchema;
+
+/// Device state reported by the desktop client when connecting to an MFA location.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PostureReport {
+    pub os_version: Option<String>,
+    pub disk_encrypted: Option<bool>,
+    pub client_version: Option<String>,
+}
+
+/// Reason for rejecting a device which doesn't meet location posture policy.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, ToSchema)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum PostureViolation {
+    DiskNotEncrypted,
+    OsVersionTooOld {
+        required: String,
+        reported: Option<String>,
+    },
+    ClientVersionTooOld {
+        required: String,
+        reported: Option<String>,
+    },
+}
+
+impl fmt::Display for PostureViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DiskNotEncrypted => write!(f, "disk encryption is required"),
+            Self::OsVersionTooOld { required, reported } => write!(
+                f,
+                "OS version {} is older than required {required}",
+                reported.as_deref().unwrap_or("unknown")
+            ),
+            Self::ClientVersionTooOld { required, reported } => write!(
+                f,
+                "client version {} is older than required {required}",
+                reported.as_deref().unwrap_or("unknown")
+            ),
+        }
+    }
+}
+
+/// Splits a version string such as `14.2.1`, `10.0.22631` or `1.6.0-beta` into numeric
+/// components. Non-numeric suffixes of components are ignored.
+fn version_components(version: &str) -> Option<Vec<u64>> {
+    version
+        .trim()
+        .split('.')
+        .map(|part| {
+            let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse().ok()
+        })
+        .collect()
+}
+
+/// Checks if a version string can be used as a minimum version in a posture policy.
+#[must_use]
+pub fn is_valid_version(version: &str) -> bool {
+    version_components(version).is_some()
+}
+
+/// Compares versions component by component, missing components are treated as zeros.
+/// Versions which can't be parsed never satisfy the requirement.
+fn version_at_least(version: Option<&str>, required: &str) -> bool {
+    let (Some(mut version), Some(mut required)) = (
+        version.and_then(version_components),
+        version_components(required),
+    ) else {
+        return false;
+    };
+    let len = version.len().max(required.len());
+    version.resize(len, 0);
+    required.resize(len, 0);
+    version >= required
+}
+
+/// Requirements devices have to meet to complete desktop client MFA for a location.
+#[derive(Clone, Debug, Deserialize, Model, PartialEq, Serialize, ToSchema)]
+#[table(location_posture_policy)]
+pub struct LocationPosturePolicy<I = NoId> {
+    pub id: I,
+    pub location_id: Id,
+    pub require_disk_encryption: bool,
+    pub min_os_version: Option<String>,
+    pub min_client_version: Option<String>,
+}
+
+impl LocationPosturePolicy {
+    #[must_use]
+    pub fn new(
+        location_id: Id,
+        require_disk_encryption: bool,
+        min_os_version: Option<String>,
+        min_client_version: Option<String>,
+    ) -> Self {
+        Self {
+            id: NoId,
+            location_id,
+            require_disk_encryption,
+            min_os_version,
+            min_client_version,
+        }
+    }
+}
+
+impl<I> LocationPosturePolicy<I> {
+    /// Returns all requirements the reported device state doesn't meet. Missing report values
+    /// are treated as not meeting the requirement.
+    #[must_use]
+    pub fn evaluate(&self, report: &PostureReport) -> Vec<PostureViolation> {
+        let mut violations = Vec::new();
+        if self.require_disk_encryption && report.disk_encrypted != Some(true) {
+            violations.push(PostureViolation::DiskNotEncrypted);
+        }
+        if let Some(required) = &self.min_os_version {
+            if !version_at_least(report.os_version.as_deref(), required) {
+                violations.push(PostureViolation::OsVersionTooOld {
+                    required: required.clone(),
+                    reported: report.os_version.clone(),
+                });
+            }
+        }
+        if let Some(required) = &self.min_client_version {
+            if !version_at_least(report.client_version.as_deref(), required) {
+                violations.push(PostureViolation::ClientVersionTooOld {
+                    required: required.clone(),
+                    reported: report.client_version.clone(),
+                });
+            }
+        }
+        violations
+    }
+}
+
+impl LocationPosturePolicy<Id> {
+    pub async fn find_by_location<'e, E>(
+        executor: E,
+        location_id: Id,
+    ) -> Result<Option<Self>, sqlx::Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, location_id, require_disk_encryption, min_os_version, min_client_version \
+            FROM location_posture_policy WHERE location_id = $1",
+            location_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_version_at_least() {
+        assert!(version_at_least(Some("14.2.1"), "14"));
+        assert!(version_at_least(Some("14"), "14.0.0"));
+        assert!(version_at_least(Some("10.0.22631"), "10.0.19045"));
+        assert!(version_at_least(Some("1.6.0-beta"), "1.6"));
+        assert!(!version_at_least(Some("22.04"), "24.04"));
+        assert!(!version_at_least(Some("13.9"), "14"));
+        assert!(!version_at_least(Some("unknown"), "1"));
+        assert!(!version_at_least(None, "1"));
+        assert!(!is_valid_version("latest"));
+    }
+
+    #[test]
+    fn test_evaluate_posture() {
+        let policy = LocationPosturePolicy::new(1, true, Some("14".into()), Some("1.6.0".into()));
+        let report = PostureReport {
+            os_version: Some("14.5".into()),
+            disk_encrypted: Some(true),
+            client_version: Some("1.6.2".into()),
+        };
+        assert!(policy.evaluate(&report).is_empty());
+
+        assert_eq!(
+            policy.evaluate(&PostureReport::default()),
+            vec![
+                PostureViolation::DiskNotEncrypted,
+                PostureViolation::OsVersionTooOld {
+                    required: "14".into(),
+                    reported: None,
+                },
+                PostureViolation::ClientVersionTooOld {
+                    required: "1.6.0".into(),
+                    reported: None,
+                },
+            ]
+        );
+
+        let report = PostureReport {
+            client_version: Some("1.5.9".into()),
+            ..report
+        };
+        assert_eq!(
+            policy.evaluate(&report),
+            vec![PostureViolation::ClientVersionTooOld {
+                required: "1.6.0".into(),
+                reported: Some("1.5.9".into()),
+            }]
+        );
+    }
+}
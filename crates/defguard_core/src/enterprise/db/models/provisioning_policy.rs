@@ -0,0 +1,91 @@
+use std::fmt;
+
+use sqlx::{Error as SqlxError, PgExecutor, Type, query, query_as};
+
+/// External source of users whose accounts are created just in time, on their first login.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, Type)]
+#[sqlx(type_name = "provisioning_source", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ProvisioningSource {
+    OpenId,
+    Ldap,
+    Saml,
+}
+
+impl fmt::Display for ProvisioningSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OpenId => write!(f, "OpenID"),
+            Self::Ldap => write!(f, "LDAP"),
+            Self::Saml => write!(f, "SAML"),
+        }
+    }
+}
+
+/// Decides what happens when a user authenticated by an external source logs in for the first
+/// time and there is no matching Defguard account.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ProvisioningPolicy {
+    pub source: ProvisioningSource,
+    /// If false, the login is refused until an admin creates the account.
+    pub create_account: bool,
+    /// Names of groups new accounts join. Groups which don't exist are skipped.
+    pub default_groups: Vec<String>,
+    /// New accounts are disabled until an admin enables them.
+    pub start_disabled: bool,
+    /// Template of usernames of new accounts, e.g. `{given_initial}{family_name}`.
+    /// Not supported for LDAP, whose accounts always keep their LDAP username.
+    pub username_template: Option<String>,
+}
+
+impl ProvisioningPolicy {
+    /// Policy matching the behaviour from before policies were configurable. SAML accounts
+    /// weren't created unless enabled.
+    #[must_use]
+    pub fn new(source: ProvisioningSource) -> Self {
+        Self {
+            source,
+            create_account: source != ProvisioningSource::Saml,
+            default_groups: Vec::new(),
+            start_disabled: false,
+            username_template: None,
+        }
+    }
+
+    pub async fn get<'e, E>(executor: E, source: ProvisioningSource) -> Result<Self, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        let policy = query_as!(
+            Self,
+            "SELECT source \"source: ProvisioningSource\", create_account, default_groups, \
+            start_disabled, username_template FROM provisioning_policy WHERE source = $1",
+            source as ProvisioningSource
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(policy.unwrap_or_else(|| Self::new(source)))
+    }
+
+    pub(crate) async fn save<'e, E>(&self, executor: E) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "INSERT INTO provisioning_policy \
+            (source, create_account, default_groups, start_disabled, username_template) \
+            VALUES ($1, $2, $3, $4, $5) ON CONFLICT (source) DO UPDATE SET \
+            create_account = $2, default_groups = $3, start_disabled = $4, \
+            username_template = $5",
+            self.source as ProvisioningSource,
+            self.create_account,
+            &self.default_groups,
+            self.start_disabled,
+            self.username_template,
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::{NaiveDateTime, Utc};
+use defguard_common::{
+    db::{Id, NoId},
+    random::gen_alphanumeric,
+};
+use model_derive::Model;
+use sqlx::{Error as SqlxError, PgExecutor, query, query_as, query_scalar};
+
+/// SAML 2.0 identity provider. Defguard acts as the service provider and accepts assertions
+/// signed with certificates from the identity provider metadata.
+#[derive(Clone, Debug, Deserialize, Model, PartialEq, Serialize)]
+#[table(saml_provider)]
+pub struct SamlProvider<I = NoId> {
+    pub id: I,
+    pub name: String,
+    pub display_name: Option<String>,
+    pub enabled: bool,
+    /// Identity provider metadata XML.
+    pub idp_metadata: String,
+    // Names (or friendly names) of assertion attributes mapped onto user fields
+    pub email_attribute: String,
+    /// Extracted from email if not set.
+    pub username_attribute: Option<String>,
+    pub first_name_attribute: String,
+    pub last_name_attribute: String,
+    pub phone_attribute: Option<String>,
+    /// Group membership is reconciled with values of this attribute on every login.
+    pub groups_attribute: Option<String>,
+    /// Create groups which don't exist in Defguard, otherwise such attribute values are skipped.
+    pub create_groups: bool,
+}
+
+impl SamlProvider<Id> {
+    pub(crate) async fn find_by_name<'e, E>(
+        executor: E,
+        name: &str,
+    ) -> Result<Option<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, name, display_name, enabled, idp_metadata, email_attribute, \
+            username_attribute, first_name_attribute, last_name_attribute, phone_attribute, \
+            groups_attribute, create_groups FROM saml_provider WHERE name = $1",
+            name
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// ID of the user linked to the SAML subject name ID.
+    pub(crate) async fn linked_user_id<'e, E>(
+        &self,
+        executor: E,
+        name_id: &str,
+    ) -> Result<Option<Id>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_scalar!(
+            "SELECT user_id FROM saml_user WHERE provider_id = $1 AND name_id = $2",
+            self.id,
+            name_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Links the user to the SAML subject name ID, so the user logs in as this identity.
+    pub(crate) async fn link_user<'e, E>(
+        &self,
+        executor: E,
+        name_id: &str,
+        user_id: Id,
+    ) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "INSERT INTO saml_user (provider_id, name_id, user_id) VALUES ($1, $2, $3)",
+            self.id,
+            name_id,
+            user_id
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// User authenticated at the assertion consumer service, whose login hasn't been completed yet.
+///
+/// Groups from the assertion are applied once the login is complete, i.e. after MFA if the user
+/// has it enabled, so the login is bound to the session until then.
+#[derive(Clone, Debug)]
+pub struct SamlLogin {
+    pub id: String,
+    pub provider_id: Id,
+    pub user_id: Id,
+    /// `None` if group mapping is disabled.
+    pub groups: Option<Vec<String>>,
+    pub created: NaiveDateTime,
+    pub session_id: Option<String>,
+}
+
+impl SamlLogin {
+    #[must_use]
+    pub fn new(provider_id: Id, user_id: Id, groups: Option<Vec<String>>) -> Self {
+        Self {
+            id: gen_alphanumeric(24),
+            provider_id,
+            user_id,
+            groups,
+            created: Utc::now().naive_utc(),
+            session_id: None,
+        }
+    }
+
+    pub(crate) async fn save<'e, E>(&self, executor: E) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "INSERT INTO saml_login (id, provider_id, user_id, groups, created, session_id) \
+            VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (id) DO UPDATE SET session_id = $6",
+            self.id,
+            self.provider_id,
+            self.user_id,
+            self.groups.as_deref(),
+            self.created,
+            self.session_id,
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes and returns the login which hasn't got a session yet.
+    pub(crate) async fn take<'e, E>(executor: E, id: &str) -> Result<Option<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "DELETE FROM saml_login WHERE id = $1 AND session_id IS NULL \
+            RETURNING id, provider_id, user_id, groups, created, session_id",
+            id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Removes and returns the login waiting for the session to complete MFA.
+    pub(crate) async fn take_for_session<'e, E>(
+        executor: E,
+        session_id: &str,
+    ) -> Result<Option<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "DELETE FROM saml_login WHERE session_id = $1 \
+            RETURNING id, provider_id, user_id, groups, created, session_id",
+            session_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Removes logins which weren't completed within `max_age` minutes and have no session.
+    pub(crate) async fn delete_expired<'e, E>(executor: E, max_age: i32) -> Result<(), SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query!(
+            "DELETE FROM saml_login WHERE session_id IS NULL \
+            AND created < NOW() AT TIME ZONE 'UTC' - make_interval(mins => $1)",
+            max_age
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -327,7 +327,7 @@ async fn sync_user_groups<T: DirectorySync>(
 
     let mut transaction = pool.begin().await?;
 
-    let current_groups = user.member_of(&mut *transaction).await?;
+    let current_groups = user.direct_member_of(&mut *transaction).await?;
     let current_group_names: Vec<&str> = current_groups.iter().map(|g| g.name.as_str()).collect();
     let mut add_to_ldap_groups = HashSet::new();
     let mut remove_from_ldap_groups = HashSet::new();
@@ -518,7 +518,7 @@ async fn sync_all_users_groups<T: DirectorySync>(
             continue;
         };
 
-        let current_groups = user.member_of(&mut *transaction).await?;
+        let current_groups = user.direct_member_of(&mut *transaction).await?;
         debug!(
             "User {} is a member of {} groups in Defguard: {:?}",
             user.email,
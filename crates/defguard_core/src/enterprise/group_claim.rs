@@ -0,0 +1,333 @@
+//! Mapping of an ID token claim, e.g. `groups` or `roles`, onto Defguard groups. Membership of
+//! users logging in through the external OpenID provider is reconciled with the claim on every
+//! login, so groups don't have to be maintained in two places.
+
+use std::collections::{HashMap, HashSet};
+
+use defguard_common::db::{Id, models::Settings};
+use serde_json::Value;
+use sqlx::{Error as SqlxError, PgPool};
+use tokio::sync::broadcast::Sender;
+
+use crate::{
+    db::{
+        GatewayEvent, Group, User,
+        models::group::{GroupSyncAuthority, group_sync_authorities},
+    },
+    enterprise::{
+        db::models::openid_provider::{GroupClaimMapping, OpenIdProvider},
+        ldap::utils::{ldap_add_user_to_groups, ldap_remove_user_from_groups},
+    },
+    error::WebError,
+};
+
+/// Group membership changes resulting from a claim.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct GroupClaimChanges {
+    /// Groups which don't exist yet and are created.
+    pub created: Vec<String>,
+    /// Groups the user is added to, including the created ones.
+    pub joined: Vec<String>,
+    /// Groups the user is removed from.
+    pub left: Vec<String>,
+    /// Claim values without a matching group, when creating groups is disabled, and groups
+    /// which membership is managed by LDAP.
+    pub skipped: Vec<String>,
+}
+
+impl GroupClaimChanges {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.joined.is_empty() && self.left.is_empty()
+    }
+}
+
+/// Extracts values of a claim. Nested claims are addressed with dots, e.g. `realm_access.roles`.
+/// The claim may hold either a list of strings or a single string.
+#[must_use]
+pub fn claim_values(claims: &Value, claim: &str) -> Vec<String> {
+    let mut value = claims;
+    for key in claim.split('.') {
+        match value.get(key) {
+            Some(nested) => value = nested,
+            None => return Vec::new(),
+        }
+    }
+    let mut values: Vec<String> = match value {
+        Value::String(value) => vec![value.clone()],
+        Value::Array(values) => values
+            .iter()
+            .filter_map(|value| value.as_str().map(ToString::to_string))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let mut seen = HashSet::new();
+    values.retain(|value| !value.trim().is_empty() && seen.insert(value.clone()));
+    values
+}
+
+/// Sync authorities of groups shared with LDAP, empty if LDAP sync is disabled.
+pub(crate) async fn ldap_group_authorities(
+    pool: &PgPool,
+) -> Result<HashMap<String, GroupSyncAuthority>, SqlxError> {
+    let settings = Settings::get_current_settings();
+    if settings.ldap_enabled && settings.ldap_sync_enabled {
+        group_sync_authorities(pool).await
+    } else {
+        Ok(HashMap::new())
+    }
+}
+
+/// Computes membership changes which make user groups match claim values.
+/// Admin groups are never left because of the claim, so a misconfigured mapping can't lock
+/// administrators out. Membership of groups managed by LDAP isn't changed, and groups merged with
+/// LDAP are never left, since LDAP sync would revert such changes.
+#[must_use]
+pub fn plan_group_changes(
+    values: &[String],
+    member_of: &[Group<Id>],
+    existing: &[Group<Id>],
+    create_groups: bool,
+    authorities: &HashMap<String, GroupSyncAuthority>,
+) -> GroupClaimChanges {
+    let mut changes = GroupClaimChanges::default();
+    let member_of_names: HashSet<&str> = member_of.iter().map(|g| g.name.as_str()).collect();
+    let existing_names: HashSet<&str> = existing.iter().map(|g| g.name.as_str()).collect();
+
+    for value in values {
+        if member_of_names.contains(value.as_str()) {
+            continue;
+        }
+        if authorities.get(value) == Some(&GroupSyncAuthority::Ldap) {
+            changes.skipped.push(value.clone());
+        } else if existing_names.contains(value.as_str()) {
+            changes.joined.push(value.clone());
+        } else if create_groups {
+            changes.created.push(value.clone());
+            changes.joined.push(value.clone());
+        } else {
+            changes.skipped.push(value.clone());
+        }
+    }
+    for group in member_of {
+        if group.is_admin || values.contains(&group.name) {
+            continue;
+        }
+        match authorities.get(&group.name) {
+            Some(GroupSyncAuthority::Ldap | GroupSyncAuthority::Merge) => (),
+            Some(GroupSyncAuthority::Local) | None => changes.left.push(group.name.clone()),
+        }
+    }
+
+    changes
+}
+
+/// Computes membership changes of a user for given ID token claims.
+pub(crate) async fn preview_group_changes(
+    pool: &PgPool,
+    mapping: &GroupClaimMapping,
+    user: &User<Id>,
+    claims: &Value,
+) -> Result<GroupClaimChanges, WebError> {
+    let Some(claim) = &mapping.claim else {
+        return Ok(GroupClaimChanges::default());
+    };
+    let values = claim_values(claims, claim);
+    let member_of = user.direct_member_of(pool).await?;
+    let existing = Group::all(pool).await?;
+    let authorities = ldap_group_authorities(pool).await?;
+    Ok(plan_group_changes(
+        &values,
+        &member_of,
+        &existing,
+        mapping.create_groups,
+        &authorities,
+    ))
+}
+
+/// Reconciles group membership of a user who logged in through the external OpenID provider
+/// with the ID token claim configured in the group claim mapping. Does nothing if the mapping
+/// is disabled.
+pub(crate) async fn sync_user_groups_from_claims(
+    pool: &PgPool,
+    provider: &OpenIdProvider<Id>,
+    user: &User<Id>,
+    claims: &Value,
+    wg_tx: &Sender<GatewayEvent>,
+) -> Result<(), WebError> {
+    let mapping = provider.group_claim_mapping(pool).await?;
+    let Some(claim) = &mapping.claim else {
+        debug!("Group claim mapping is disabled, skipping syncing groups of user {user}");
+        return Ok(());
+    };
+    let changes = preview_group_changes(pool, &mapping, user, claims).await?;
+    if !changes.skipped.is_empty() {
+        debug!(
+            "Groups {:?} from claim {claim} of user {user} don't exist in Defguard, skipping them",
+            changes.skipped
+        );
+    }
+    if changes.is_empty() {
+        debug!("Groups of user {user} already match claim {claim}");
+        return Ok(());
+    }
+    if mapping.dry_run {
+        info!(
+            "Group claim mapping dry run for user {user}: would create groups {:?}, join groups \
+            {:?} and leave groups {:?}",
+            changes.created, changes.joined, changes.left
+        );
+        return Ok(());
+    }
+
+    apply_group_changes(pool, user, &changes, wg_tx).await?;
+    info!(
+        "Synced groups of user {user} with claim {claim}: joined {:?}, left {:?}",
+        changes.joined, changes.left
+    );
+
+    Ok(())
+}
+
+/// Applies planned membership changes of a user, creating missing groups, and updates gateways
+/// and LDAP accordingly.
+pub(crate) async fn apply_group_changes(
+    pool: &PgPool,
+    user: &User<Id>,
+    changes: &GroupClaimChanges,
+    wg_tx: &Sender<GatewayEvent>,
+) -> Result<(), WebError> {
+    let mut transaction = pool.begin().await?;
+    for name in &changes.joined {
+        let group = match Group::find_by_name(&mut *transaction, name).await? {
+            Some(group) => group,
+            None => {
+                info!("Creating group {name} for user {user}");
+                Group::new(name.as_str()).save(&mut *transaction).await?
+            }
+        };
+        user.add_to_group(&mut *transaction, &group).await?;
+    }
+    for name in &changes.left {
+        if let Some(group) = Group::find_by_name(&mut *transaction, name).await? {
+            user.remove_from_group(&mut *transaction, &group).await?;
+        }
+    }
+    user.sync_allowed_devices(&mut transaction, wg_tx).await?;
+    transaction.commit().await?;
+
+    ldap_add_user_to_groups(
+        user,
+        changes.joined.iter().map(String::as_str).collect(),
+        pool,
+    )
+    .await;
+    ldap_remove_user_from_groups(
+        user,
+        changes.left.iter().map(String::as_str).collect(),
+        pool,
+    )
+    .await;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn group(id: Id, name: &str, is_admin: bool) -> Group<Id> {
+        Group {
+            id,
+            name: name.into(),
+            is_admin,
+        }
+    }
+
+    #[test]
+    fn test_claim_values() {
+        let claims = json!({
+            "groups": ["vpn", "devs", "", "devs"],
+            "role": "admin",
+            "realm_access": {"roles": ["ops"]},
+            "amr": [1, 2],
+        });
+        assert_eq!(claim_values(&claims, "groups"), ["vpn", "devs"]);
+        assert_eq!(claim_values(&claims, "role"), ["admin"]);
+        assert_eq!(claim_values(&claims, "realm_access.roles"), ["ops"]);
+        assert!(claim_values(&claims, "amr").is_empty());
+        assert!(claim_values(&claims, "missing").is_empty());
+        assert!(claim_values(&claims, "role.nested").is_empty());
+    }
+
+    #[test]
+    fn test_plan_group_changes() {
+        let existing = [
+            group(1, "admin", true),
+            group(2, "vpn", false),
+            group(3, "devs", false),
+            group(4, "sales", false),
+        ];
+        let member_of = [existing[0].clone(), existing[3].clone()];
+        let values = ["vpn".to_string(), "devs".into(), "ops".into()];
+
+        let authorities = HashMap::new();
+        let changes = plan_group_changes(&values, &member_of, &existing, false, &authorities);
+        assert_eq!(
+            changes,
+            GroupClaimChanges {
+                created: Vec::new(),
+                joined: vec!["vpn".into(), "devs".into()],
+                left: vec!["sales".into()],
+                skipped: vec!["ops".into()],
+            }
+        );
+
+        let changes = plan_group_changes(&values, &member_of, &existing, true, &authorities);
+        assert_eq!(changes.created, ["ops"]);
+        assert_eq!(changes.joined, ["vpn", "devs", "ops"]);
+        assert!(changes.skipped.is_empty());
+
+        let changes = plan_group_changes(
+            &["admin".into()],
+            &member_of,
+            &existing,
+            false,
+            &authorities,
+        );
+        assert_eq!(changes.left, ["sales"]);
+        assert!(changes.joined.is_empty());
+    }
+
+    #[test]
+    fn test_plan_group_changes_ldap_authority() {
+        let existing = [
+            group(1, "vpn", false),
+            group(2, "devs", false),
+            group(3, "sales", false),
+            group(4, "ops", false),
+        ];
+        let member_of = [existing[2].clone(), existing[3].clone()];
+        let values = ["vpn".to_string(), "devs".into()];
+        let authorities = HashMap::from([
+            ("vpn".to_string(), GroupSyncAuthority::Ldap),
+            ("devs".to_string(), GroupSyncAuthority::Merge),
+            ("sales".to_string(), GroupSyncAuthority::Ldap),
+            ("ops".to_string(), GroupSyncAuthority::Merge),
+        ]);
+
+        // groups managed by LDAP are neither joined nor left, merged groups are only joined
+        let changes = plan_group_changes(&values, &member_of, &existing, false, &authorities);
+        assert_eq!(
+            changes,
+            GroupClaimChanges {
+                created: Vec::new(),
+                joined: vec!["devs".into()],
+                left: Vec::new(),
+                skipped: vec!["vpn".into()],
+            }
+        );
+    }
+}
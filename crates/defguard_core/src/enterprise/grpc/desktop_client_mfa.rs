@@ -7,10 +7,12 @@ use crate::{
     enterprise::{
         handlers::openid_login::{extract_state_data, user_from_claims},
         is_business_license_active,
+        provisioning::{account_creation_refused_bidi_event, user_provisioned_bidi_event},
     },
+    error::WebError,
     events::{BidiRequestContext, BidiStreamEvent, BidiStreamEventType, DesktopClientMfaEvent},
     grpc::{
-        client_mfa::{ClientLoginSession, ClientMfaServer},
+        client_mfa::{ClientLogin, ClientMfaServer},
         utils::parse_client_ip_agent,
     },
 };
@@ -42,31 +44,30 @@ impl ClientMfaServer {
         let pubkey = Self::parse_token(&token)?;
 
         // fetch login session
-        let Some(session) = self.sessions.get(&pubkey).cloned() else {
+        let Some(ClientLogin {
+            mut session,
+            location,
+            device,
+            user,
+        }) = self.find_login(&pubkey).await?
+        else {
             debug!("Client login session not found");
             return Err(Status::invalid_argument("login session not found"));
         };
-        let ClientLoginSession {
-            method,
-            device,
-            location,
-            user,
-            openid_auth_completed,
-            biometric_challenge: _,
-        } = session;
+        let method = session.method;
 
-        if openid_auth_completed {
+        if session.openid_auth_completed {
             debug!("Client login session already completed");
             return Err(Status::invalid_argument("login session already completed"));
         }
 
         if method != MfaMethod::Oidc {
             debug!("Invalid MFA method for OIDC authentication: {method:?}");
-            self.sessions.remove(&pubkey);
+            self.remove_login(&pubkey).await;
             return Err(Status::invalid_argument("invalid MFA method"));
         }
 
-        let (ip, _user_agent) = parse_client_ip_agent(&info).map_err(Status::internal)?;
+        let (ip, user_agent) = parse_client_ip_agent(&info).map_err(Status::internal)?;
         let context = BidiRequestContext::new(
             user.id,
             user.username.clone(),
@@ -81,7 +82,7 @@ impl ClientMfaServer {
         }) {
             Ok(url) => url,
             Err(status) => {
-                self.sessions.remove(&pubkey);
+                self.remove_login(&pubkey).await;
                 self.emit_event(BidiStreamEvent {
                     context,
                     event: BidiStreamEventType::DesktopClientMfa(Box::new(
@@ -97,12 +98,28 @@ impl ClientMfaServer {
             }
         };
 
-        match user_from_claims(&self.pool, Nonce::new(request.nonce.clone()), code, url).await {
-            Ok(claims_user) => {
+        match user_from_claims(
+            &self.pool,
+            Nonce::new(request.nonce.clone()),
+            code,
+            url,
+            &self.wireguard_tx,
+        )
+        .await
+        {
+            Ok((claims_user, provisioning)) => {
+                if let Some(provisioning) = provisioning {
+                    self.emit_event(user_provisioned_bidi_event(
+                        &claims_user,
+                        provisioning,
+                        ip,
+                        user_agent,
+                    ))?;
+                }
                 // if thats not our user, prevent login
                 if claims_user.id != user.id {
                     info!("User {claims_user} tried to use OIDC MFA for another user: {user}");
-                    self.sessions.remove(&pubkey);
+                    self.remove_login(&pubkey).await;
                     self.emit_event(BidiStreamEvent {
                         context,
                         event: BidiStreamEventType::DesktopClientMfa(Box::new(
@@ -123,7 +140,10 @@ impl ClientMfaServer {
             }
             Err(err) => {
                 info!("Failed to verify OIDC code: {err}");
-                self.sessions.remove(&pubkey);
+                if let WebError::AccountCreationRefused(refused) = &err {
+                    self.emit_event(account_creation_refused_bidi_event(refused, ip, user_agent))?;
+                }
+                self.remove_login(&pubkey).await;
                 self.emit_event(BidiStreamEvent {
                     context,
                     event: BidiStreamEventType::DesktopClientMfa(Box::new(
@@ -139,17 +159,13 @@ impl ClientMfaServer {
             }
         }
 
-        self.sessions.insert(
-            pubkey.clone(),
-            ClientLoginSession {
-                method,
-                device: device.clone(),
-                location: location.clone(),
-                user: user.clone(),
-                openid_auth_completed: true,
-                biometric_challenge: None,
-            },
-        );
+        session.openid_auth_completed = true;
+        session.biometric_challenge = None;
+        session.passkey_authentication = None;
+        session.save(&self.pool).await.map_err(|err| {
+            error!("Failed to store desktop client login session: {err}");
+            Status::internal("unexpected error")
+        })?;
 
         Ok(())
     }
@@ -1,12 +1,16 @@
 use defguard_common::db::Id;
-use defguard_proto::proxy::{DeviceInfo, InstanceInfoRequest, InstanceInfoResponse};
+use defguard_proto::proxy::{
+    DeviceConfigResponse, DeviceInfo, InstanceInfoRequest, InstanceInfoResponse,
+    RotateDeviceKeyRequest,
+};
 use sqlx::PgPool;
+use tokio::sync::broadcast::Sender;
 use tonic::Status;
 
 use crate::{
-    db::{Device, User, models::polling_token::PollingToken},
+    db::{Device, GatewayEvent, User, WireguardNetwork, models::polling_token::PollingToken},
     enterprise::is_business_license_active,
-    grpc::utils::build_device_config_response,
+    grpc::{gateway::send_wireguard_event, utils::build_device_config_response},
 };
 
 pub struct PollingServer {
@@ -93,4 +97,94 @@ impl PollingServer {
             device_config: Some(device_config),
         })
     }
+
+    /// Replaces the WireGuard public key of the device the polling token belongs to, e.g. during
+    /// annual key rotation. Returns device configuration for the new key. Enterprise only.
+    #[instrument(skip_all)]
+    pub async fn rotate_key(
+        &self,
+        request: RotateDeviceKeyRequest,
+        wireguard_tx: &Sender<GatewayEvent>,
+    ) -> Result<DeviceConfigResponse, Status> {
+        let token = self.validate_session(&request.token).await?;
+        let Some(mut device) = Device::find_by_id(&self.pool, token.device_id)
+            .await
+            .map_err(|err| {
+                error!("Failed to retrieve device id {}: {err}", token.device_id);
+                Status::internal("failed to retrieve device")
+            })?
+        else {
+            error!("Device id {} not found", token.device_id);
+            return Err(Status::internal("device not found"));
+        };
+        debug!("Rotating key of device {device}");
+
+        let Some(user) = User::find_by_device_id(&self.pool, device.id)
+            .await
+            .map_err(|err| {
+                error!("Failed to retrieve user for device id {}: {err}", device.id);
+                Status::internal("failed to retrieve user")
+            })?
+        else {
+            error!("User for device id {} not found", device.id);
+            return Err(Status::internal("user not found"));
+        };
+        if !user.is_active {
+            warn!(
+                "Denying key rotation for inactive user {}({})",
+                user.username, user.id
+            );
+            return Err(Status::permission_denied("user inactive"));
+        }
+
+        Device::validate_pubkey(&request.pubkey).map_err(Status::invalid_argument)?;
+        let existing = Device::find_by_pubkey(&self.pool, &request.pubkey)
+            .await
+            .map_err(|err| {
+                error!(
+                    "Failed to check if pubkey {} is in use: {err}",
+                    request.pubkey
+                );
+                Status::internal("unexpected error")
+            })?;
+        let locations = WireguardNetwork::all(&self.pool).await.map_err(|err| {
+            error!("Failed to fetch all locations: {err}");
+            Status::internal("unexpected error")
+        })?;
+        if existing.is_some()
+            || locations
+                .iter()
+                .any(|location| location.pubkey == request.pubkey)
+        {
+            warn!(
+                "Denying key rotation of device {device}, pubkey {} is already in use",
+                request.pubkey
+            );
+            return Err(Status::invalid_argument("pubkey already in use"));
+        }
+
+        let mut transaction = self.pool.begin().await.map_err(|err| {
+            error!("Failed to start transaction while rotating device key: {err}");
+            Status::internal("unexpected error")
+        })?;
+        let (previous, current) = device
+            .rotate_pubkey(&mut transaction, request.pubkey)
+            .await
+            .map_err(|err| {
+                error!("Failed to rotate key of device {}: {err}", token.device_id);
+                Status::internal("unexpected error")
+            })?;
+        transaction.commit().await.map_err(|err| {
+            error!("Failed to commit transaction while rotating device key: {err}");
+            Status::internal("unexpected error")
+        })?;
+        send_wireguard_event(GatewayEvent::DeviceDeleted(previous), wireguard_tx);
+        send_wireguard_event(GatewayEvent::DeviceCreated(current), wireguard_tx);
+        info!(
+            "User {} rotated key of device {device} from desktop client",
+            user.username
+        );
+
+        build_device_config_response(&self.pool, device, None, None).await
+    }
 }
@@ -4,15 +4,15 @@ use axum::{
     http::StatusCode,
 };
 use chrono::Utc;
-use defguard_common::random::gen_alphanumeric;
+use defguard_common::{db::Id, random::gen_alphanumeric};
 use serde_json::json;
 
 use super::LicenseInfo;
 use crate::{
     appstate::AppState,
     auth::{AdminRole, SessionInfo},
-    db::User,
-    enterprise::db::models::api_tokens::{ApiToken, ApiTokenInfo},
+    db::{Group, User, WireguardNetwork},
+    enterprise::db::models::api_tokens::{ApiToken, ApiTokenInfo, ApiTokenScope},
     error::WebError,
     events::{ApiEvent, ApiEventType, ApiRequestContext},
     handlers::{ApiResponse, ApiResult, user_for_admin_or_self},
@@ -23,6 +23,15 @@ const API_TOKEN_LENGTH: usize = 32;
 #[derive(Deserialize, Serialize, Debug)]
 pub struct AddApiTokenData {
     pub name: String,
+    /// Restricts the token to read-only access to a single location.
+    #[serde(default)]
+    pub location_id: Option<Id>,
+    /// Binds the token to a group the owner is a member of.
+    #[serde(default)]
+    pub group_id: Option<Id>,
+    /// Restricts the token to the given permission scopes.
+    #[serde(default)]
+    pub scopes: Vec<ApiTokenScope>,
 }
 
 pub async fn add_api_token(
@@ -39,19 +48,50 @@ pub async fn add_api_token(
     // authorize request
     let user = user_for_admin_or_self(&appstate.pool, &session, &username).await?;
 
-    // prevent creating tokens for non-admin users
-    if !user.is_admin(&appstate.pool).await? {
+    // prevent creating unrestricted tokens for non-admin users
+    let is_scoped = data.group_id.is_some() && !data.scopes.is_empty();
+    if !is_scoped && !user.is_admin(&appstate.pool).await? {
         error!(
             "User {} attempted to create API token for non-admin user {username}",
             session.user.username
         );
         return Err(WebError::Forbidden(
-            "Cannot create API token for non-admin user".into(),
+            "Cannot create API token for non-admin user without group and scopes".into(),
         ));
     }
 
     // TODO: check if the name is already used
 
+    if let Some(location_id) = data.location_id {
+        let location = WireguardNetwork::find_by_id(&appstate.pool, location_id).await?;
+        if location.is_none() {
+            error!("Cannot create API token for non-existent location {location_id}");
+            return Err(WebError::ObjectNotFound(format!(
+                "Location {location_id} not found"
+            )));
+        }
+    }
+
+    if let Some(group_id) = data.group_id {
+        let Some(group) = Group::find_by_id(&appstate.pool, group_id).await? else {
+            error!("Cannot create API token for non-existent group {group_id}");
+            return Err(WebError::ObjectNotFound(format!(
+                "Group {group_id} not found"
+            )));
+        };
+        let groups = user.effective_group_names(&appstate.pool).await?;
+        if !groups.contains(&group.name) {
+            error!(
+                "Cannot create API token bound to group {} for user {username} who is not a member",
+                group.name
+            );
+            return Err(WebError::BadRequest(format!(
+                "User {username} is not a member of group {}",
+                group.name
+            )));
+        }
+    }
+
     // generate token string
     // all API tokens start with a `dg-` prefix
     let token_string = format!("dg-{}", gen_alphanumeric(API_TOKEN_LENGTH));
@@ -61,6 +101,9 @@ pub async fn add_api_token(
         Utc::now().naive_utc(),
         data.name.clone(),
         &token_string,
+        data.location_id,
+        data.group_id,
+        data.scopes,
     )
     .save(&appstate.pool)
     .await?;
@@ -0,0 +1,158 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use defguard_common::db::Id;
+use serde_json::json;
+
+use super::LicenseInfo;
+use crate::{
+    appstate::AppState,
+    auth::{AdminRole, SessionInfo},
+    db::{User, models::oauth2client::OAuth2Client},
+    enterprise::db::models::{
+        api_tokens::ApiTokenScope,
+        client_credentials::{ClientCredentials, ClientCredentialsToken},
+    },
+    error::WebError,
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
+    handlers::{ApiResponse, ApiResult},
+};
+
+/// Configuration of the `client_credentials` grant as exposed by the API.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ClientCredentialsConfig {
+    /// Service account on behalf of which the client acts.
+    pub service_account: String,
+    /// Permission scopes the client can request.
+    pub scopes: Vec<ApiTokenScope>,
+}
+
+async fn find_client(appstate: &AppState, client_id: &str) -> Result<OAuth2Client<Id>, WebError> {
+    OAuth2Client::find_by_client_id(&appstate.pool, client_id)
+        .await?
+        .ok_or_else(|| WebError::ObjectNotFound(format!("OpenID client {client_id} not found")))
+}
+
+pub async fn get_client_credentials(
+    _license: LicenseInfo,
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+    Path(client_id): Path<String>,
+) -> ApiResult {
+    let client = find_client(&appstate, &client_id).await?;
+    let Some(credentials) = ClientCredentials::find_by_client(&appstate.pool, client.id).await?
+    else {
+        return Err(WebError::ObjectNotFound(format!(
+            "Client credentials grant is not enabled for OpenID client {client_id}"
+        )));
+    };
+    let Some(user) = User::find_by_id(&appstate.pool, credentials.user_id).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "User {} not found",
+            credentials.user_id
+        )));
+    };
+
+    Ok(ApiResponse {
+        json: json!(ClientCredentialsConfig {
+            service_account: user.username,
+            scopes: credentials.scopes,
+        }),
+        status: StatusCode::OK,
+    })
+}
+
+/// Enables the `client_credentials` grant for a client or changes its configuration.
+/// Previously issued access tokens are revoked.
+pub async fn set_client_credentials(
+    _license: LicenseInfo,
+    _admin: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(client_id): Path<String>,
+    Json(data): Json<ClientCredentialsConfig>,
+) -> ApiResult {
+    debug!(
+        "User {} configuring client credentials grant for OpenID client {client_id}",
+        session.user.username
+    );
+    let client = find_client(&appstate, &client_id).await?;
+    if data.scopes.is_empty() {
+        return Err(WebError::BadRequest(
+            "At least one scope is required".into(),
+        ));
+    }
+    let Some(user) = User::find_by_username(&appstate.pool, &data.service_account).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "User {} not found",
+            data.service_account
+        )));
+    };
+    if !user.is_service_account(&appstate.pool).await? {
+        return Err(WebError::BadRequest(format!(
+            "User {} is not a service account",
+            user.username
+        )));
+    }
+
+    let mut transaction = appstate.pool.begin().await?;
+    ClientCredentials {
+        oauth2client_id: client.id,
+        user_id: user.id,
+        scopes: data.scopes.clone(),
+    }
+    .save(&mut *transaction)
+    .await?;
+    ClientCredentialsToken::delete_for_client(&mut *transaction, client.id).await?;
+    transaction.commit().await?;
+    info!(
+        "User {} enabled client credentials grant for OpenID client {} acting as {} with scopes \
+        {:?}",
+        session.user.username, client.name, user.username, data.scopes
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::ClientCredentialsSet {
+            app: client,
+            service_account: user,
+            scopes: data.scopes.clone(),
+        }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!(data),
+        status: StatusCode::OK,
+    })
+}
+
+/// Disables the `client_credentials` grant for a client and revokes tokens issued with it.
+pub async fn delete_client_credentials(
+    _license: LicenseInfo,
+    _admin: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(client_id): Path<String>,
+) -> ApiResult {
+    let client = find_client(&appstate, &client_id).await?;
+    let mut transaction = appstate.pool.begin().await?;
+    ClientCredentials::delete_for_client(&mut *transaction, client.id).await?;
+    ClientCredentialsToken::delete_for_client(&mut *transaction, client.id).await?;
+    transaction.commit().await?;
+    info!(
+        "User {} disabled client credentials grant for OpenID client {}",
+        session.user.username, client.name
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::ClientCredentialsRemoved { app: client }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!({}),
+        status: StatusCode::OK,
+    })
+}
@@ -7,9 +7,13 @@ use crate::{
 pub mod acl;
 pub mod activity_log_stream;
 pub mod api_tokens;
+pub mod client_credentials;
 pub mod enterprise_settings;
 pub mod openid_login;
 pub mod openid_providers;
+pub mod posture_policy;
+pub mod provisioning;
+pub mod saml;
 
 use axum::{
     extract::{FromRef, FromRequestParts},
@@ -85,7 +89,7 @@ where
         let appstate = AppState::from_ref(state);
         let session = SessionInfo::from_request_parts(parts, state).await?;
         let settings = EnterpriseSettings::get(&appstate.pool).await?;
-        if settings.admin_device_management && !session.is_admin {
+        if settings.admin_device_management && !session.can_manage_others() {
             Err(WebError::Forbidden(
                 "Only admin users can manage devices".into(),
             ))
@@ -1,5 +1,7 @@
+use std::net::IpAddr;
+
 use axum::{Json, extract::State, http::StatusCode};
-use axum_client_ip::InsecureClientIp;
+use axum_client_ip::SecureClientIp;
 use axum_extra::{
     TypedHeader,
     extract::{
@@ -8,7 +10,10 @@ use axum_extra::{
     },
     headers::UserAgent,
 };
-use base64::{Engine, prelude::BASE64_STANDARD};
+use base64::{
+    Engine,
+    prelude::{BASE64_STANDARD, BASE64_URL_SAFE_NO_PAD},
+};
 use defguard_common::{
     config::server_config,
     db::{
@@ -22,9 +27,10 @@ use openidconnect::{
     core::{CoreAuthenticationFlow, CoreClient, CoreProviderMetadata, CoreUserInfoClaims},
 };
 use reqwest::Url;
-use serde_json::json;
+use serde_json::{Value, json};
 use sqlx::PgPool;
 use time::Duration;
+use tokio::sync::broadcast::Sender;
 
 const COOKIE_MAX_AGE: Duration = Duration::days(1);
 static CSRF_COOKIE_NAME: &str = "csrf";
@@ -36,11 +42,21 @@ pub(crate) const SELECT_ACCOUNT_SUPPORTED_PROVIDERS: &[&str] = &["Google"];
 use super::LicenseInfo;
 use crate::{
     appstate::AppState,
-    db::User,
+    db::{GatewayEvent, User},
     enterprise::{
-        db::models::openid_provider::OpenIdProvider,
-        directory_sync::sync_user_groups_if_configured, ldap::utils::ldap_update_user_state,
+        db::models::{
+            openid_provider::OpenIdProvider,
+            provisioning_policy::{ProvisioningPolicy, ProvisioningSource},
+        },
+        directory_sync::sync_user_groups_if_configured,
+        group_claim::sync_user_groups_from_claims,
+        ldap::utils::ldap_update_user_state,
         limits::update_counts,
+        provisioning::{
+            AccountCreationRefused, Provisioning, UsernameTemplateValues, check_pending_approval,
+            emit_account_creation_refused, emit_user_provisioned, provision_user,
+            render_username_template,
+        },
     },
     error::WebError,
     handlers::{
@@ -186,13 +202,24 @@ pub(crate) async fn make_oidc_client(
     Ok((client_id, core_client))
 }
 
+/// Decode payload of an ID token, which has already been verified, to access custom claims.
+fn raw_id_token_claims(id_token: &str) -> Option<Value> {
+    let payload = id_token.split('.').nth(1)?;
+    let payload = BASE64_URL_SAFE_NO_PAD.decode(payload).ok()?;
+    serde_json::from_slice(&payload).ok()
+}
+
 /// Get or create `User` from OpenID claims.
+/// Groups of the user are reconciled with the ID token claim if group claim mapping is enabled.
+/// New accounts are created according to the OpenID provisioning policy, in which case details
+/// of the provisioning are returned as well. Such accounts may be disabled pending approval.
 pub(crate) async fn user_from_claims(
     pool: &PgPool,
     nonce: Nonce,
     code: AuthorizationCode,
     callback_url: Url,
-) -> Result<User<Id>, WebError> {
+    wg_tx: &Sender<GatewayEvent>,
+) -> Result<(User<Id>, Option<Provisioning>), WebError> {
     let Some(provider) = OpenIdProvider::get_current(pool).await? else {
         return Err(WebError::ObjectNotFound(
             "OpenID provider not set".to_string(),
@@ -280,6 +307,7 @@ pub(crate) async fn user_from_claims(
     let sub = token_claims.subject().to_string();
 
     // Handle logging in or creating user.
+    let mut provisioning = None;
     let user = match User::find_by_sub(pool, &sub)
         .await
         .map_err(|err| WebError::Authorization(err.to_string()))?
@@ -313,25 +341,13 @@ pub(crate) async fn user_from_claims(
                 user.save(pool).await?;
                 user
             } else {
-                let settings = Settings::get_current_settings();
+                let policy = ProvisioningPolicy::get(pool, ProvisioningSource::OpenId).await?;
                 // Check if the user should be created, if doesn't exist (default: true).
-                if !settings.openid_create_account {
-                    warn!(
-                        "User with email address {} is trying to log in through OpenID Connect \
-                        for the first time, but the account creation is disabled. An enrollment \
-                        should performed.",
-                        email.as_str()
-                    );
-                    return Err(WebError::Authorization(
-                        "User not found and the automatic account creation is disabled. \
-                        Enable it or create the user."
-                            .into(),
-                    ));
-                }
+                AccountCreationRefused::check(&policy, email.as_str())?;
 
                 // Try to get the username from `preferred_username` claim.
                 // If it's not there, extract it from email.
-                let username = if let Some(username) = token_claims.preferred_username() {
+                let preferred_username = if let Some(username) = token_claims.preferred_username() {
                     let username = username.as_str();
                     debug!(
                         "Preferred username {username} found in the claims. Using the username."
@@ -349,23 +365,6 @@ pub(crate) async fn user_from_claims(
                     username
                 };
 
-                let username = prune_username(username, settings.openid_username_handling);
-                // Check if the username is valid just in case, not everything can be handled by the
-                // pruning.
-                check_username(&username)?;
-
-                info!(
-                    "User {username} is logging in through OpenID Connect for the first time and \
-                    there is no account with the same email address ({}). Creating a new account.",
-                    email.as_str()
-                );
-                // Check if user with the same username already exists (usernames are unique).
-                if User::find_by_username(pool, &username).await?.is_some() {
-                    return Err(WebError::Authorization(format!(
-                        "User with username {username} already exists"
-                    )));
-                }
-
                 // Extract all necessary information from the token or call the userinfo endpoint.
                 let given_name = token_claims
                     .given_name()
@@ -382,12 +381,16 @@ pub(crate) async fn user_from_claims(
                     phone,
                 ) = (given_name, family_name, phone)
                 {
-                    debug!("Given name and family name found in the claims for user {username}.");
+                    debug!(
+                        "Given name and family name found in the claims for user with email \
+                        address {email:?}."
+                    );
                     (given_name, family_name, phone)
                 } else {
                     debug!(
-                        "Given name or family name not found in the claims for user {username}, \
-                        trying to get them from the user info endpoint. Current values: \
+                        "Given name or family name not found in the claims for user with email \
+                        address {email:?}, trying to get them from the user info endpoint. \
+                        Current values: \
                         given_name: {given_name:?}, family_name: {family_name:?}, phone: {phone:?}"
                     );
 
@@ -436,28 +439,81 @@ pub(crate) async fn user_from_claims(
 
                     debug!(
                         "Given name and family name successfully retrieved from the user info \
-                        endpoint for user {username}."
+                        endpoint for user with email address {email:?}."
                     );
 
                     (given_name, family_name, phone)
                 };
 
+                let given_name = given_name.to_string();
+                let family_name = family_name.to_string();
+                let username = match &policy.username_template {
+                    Some(template) => {
+                        let username = render_username_template(
+                            template,
+                            &UsernameTemplateValues {
+                                username: preferred_username,
+                                email: email.as_str(),
+                                given_name: &given_name,
+                                family_name: &family_name,
+                            },
+                        );
+                        debug!("Username {username} rendered from template {template}");
+                        username
+                    }
+                    None => preferred_username.to_string(),
+                };
+                let settings = Settings::get_current_settings();
+                let username = prune_username(&username, settings.openid_username_handling);
+                // Check if the username is valid just in case, not everything can be handled by the
+                // pruning.
+                check_username(&username)?;
+
+                info!(
+                    "User {username} is logging in through OpenID Connect for the first time and \
+                    there is no account with the same email address ({}). Creating a new account.",
+                    email.as_str()
+                );
+                // Check if user with the same username already exists (usernames are unique).
+                if User::find_by_username(pool, &username).await?.is_some() {
+                    return Err(WebError::Authorization(format!(
+                        "User with username {username} already exists"
+                    )));
+                }
+
                 let mut user = User::new(
-                    username.to_string(),
+                    username,
                     None,
-                    family_name.to_string(),
-                    given_name.to_string(),
+                    family_name,
+                    given_name,
                     email.to_string(),
                     phone.map(|v| v.to_string()),
                 );
                 user.openid_sub = Some(sub);
-                user.save(pool).await?
+                let (user, provisioned) = provision_user(pool, &policy, user).await?;
+                provisioning = Some(provisioned);
+                user
             }
         }
     };
 
+    match raw_id_token_claims(&id_token.to_string()) {
+        Some(claims) => {
+            if let Err(err) =
+                sync_user_groups_from_claims(pool, &provider, &user, &claims, wg_tx).await
+            {
+                error!(
+                    "Failed to sync groups of user {} with the ID token claim while the user was \
+                    logging in through an external provider: {err}",
+                    user.username
+                );
+            }
+        }
+        None => warn!("Failed to decode ID token claims of user {}", user.username),
+    }
+
     update_counts(pool).await?;
-    Ok(user)
+    Ok((user, provisioning))
 }
 
 pub(crate) async fn get_auth_info(
@@ -539,7 +595,7 @@ pub(crate) async fn auth_callback(
     cookies: CookieJar,
     mut private_cookies: PrivateCookieJar,
     user_agent: TypedHeader<UserAgent>,
-    InsecureClientIp(insecure_ip): InsecureClientIp,
+    SecureClientIp(ip): SecureClientIp,
     State(appstate): State<AppState>,
     Json(payload): Json<AuthenticationResponse>,
 ) -> Result<(CookieJar, PrivateCookieJar, ApiResponse), WebError> {
@@ -567,23 +623,48 @@ pub(crate) async fn auth_callback(
         .remove(Cookie::from(CSRF_COOKIE_NAME));
 
     let config = server_config();
-    let mut user = user_from_claims(
+    let result = user_from_claims(
         &appstate.pool,
         Nonce::new(cookie_nonce),
         payload.code,
         config.callback_url(),
+        &appstate.wireguard_tx,
     )
-    .await?;
+    .await;
+    if let Err(WebError::AccountCreationRefused(refused)) = &result {
+        emit_account_creation_refused(&appstate, refused, ip, user_agent.as_str())?;
+    }
+    let (user, provisioning) = result?;
+    if let Some(provisioning) = provisioning {
+        emit_user_provisioned(&appstate, &user, provisioning, ip, user_agent.as_str())?;
+    }
+    check_pending_approval(&user)?;
 
-    let (session, user_info, mfa_info) = create_session(
-        &appstate.pool,
-        &appstate.mail_tx,
-        insecure_ip,
+    external_login_response(
+        &appstate,
+        cookies,
+        private_cookies,
+        ip,
         user_agent.as_str(),
-        &mut user,
+        user,
     )
-    .await?;
+    .await
+}
 
+/// Creates a session for a user authenticated by an external identity provider. Responds with
+/// MFA info if the user has to complete MFA, otherwise with user info.
+pub(crate) async fn external_login_response(
+    appstate: &AppState,
+    cookies: CookieJar,
+    mut private_cookies: PrivateCookieJar,
+    ip: IpAddr,
+    user_agent: &str,
+    mut user: User<Id>,
+) -> Result<(CookieJar, PrivateCookieJar, ApiResponse), WebError> {
+    let (session, user_info, mfa_info) =
+        create_session(&appstate.pool, &appstate.mail_tx, ip, user_agent, &mut user).await?;
+
+    let config = server_config();
     let max_age = Duration::seconds(config.auth_cookie_timeout.as_secs() as i64);
     let cookie_domain = config
         .cookie_domain
@@ -8,16 +8,22 @@ use defguard_common::db::models::{
     settings::{OpenidUsernameHandling, update_current_settings},
 };
 use rsa::{RsaPrivateKey, pkcs8::DecodePrivateKey};
-use serde_json::json;
+use serde_json::{Value, json};
 
 use super::LicenseInfo;
 use crate::{
     appstate::AppState,
     auth::{AdminRole, SessionInfo},
-    db::{WireguardNetwork, models::wireguard::LocationMfaMode},
+    db::{User, WireguardNetwork, models::wireguard::LocationMfaMode},
     enterprise::{
-        db::models::openid_provider::OpenIdProvider, directory_sync::test_directory_sync_connection,
+        db::models::{
+            openid_provider::{GroupClaimMapping, OpenIdProvider},
+            provisioning_policy::{ProvisioningPolicy, ProvisioningSource},
+        },
+        directory_sync::test_directory_sync_connection,
+        group_claim::preview_group_changes,
     },
+    error::WebError,
     events::{ApiEvent, ApiEventType, ApiRequestContext},
     handlers::{ApiResponse, ApiResult},
 };
@@ -44,6 +50,15 @@ pub struct AddProviderData {
     pub username_handling: OpenidUsernameHandling,
     pub jumpcloud_api_key: Option<String>,
     pub prefetch_users: bool,
+    #[serde(default)]
+    pub group_claim_mapping: GroupClaimMapping,
+}
+
+#[derive(Deserialize)]
+pub struct GroupClaimPreviewData {
+    username: String,
+    /// ID token claims, e.g. copied from a decoded token.
+    claims: Value,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -125,9 +140,13 @@ pub async fn add_openid_provider(
     };
 
     let mut settings = Settings::get_current_settings();
-    settings.openid_create_account = provider_data.create_account;
     settings.openid_username_handling = provider_data.username_handling;
     update_current_settings(&appstate.pool, settings).await?;
+    // account creation is a part of the OpenID provisioning policy
+    let mut provisioning_policy =
+        ProvisioningPolicy::get(&appstate.pool, ProvisioningSource::OpenId).await?;
+    provisioning_policy.create_account = provider_data.create_account;
+    provisioning_policy.save(&appstate.pool).await?;
 
     let group_match = if let Some(group_match) = provider_data.directory_sync_group_match {
         if group_match.is_empty() {
@@ -142,6 +161,12 @@ pub async fn add_openid_provider(
         Vec::new()
     };
 
+    let mut group_claim_mapping = provider_data.group_claim_mapping;
+    group_claim_mapping.claim = group_claim_mapping
+        .claim
+        .map(|claim| claim.trim().to_string())
+        .filter(|claim| !claim.is_empty());
+
     // Currently, we only support one OpenID provider at a time
     let new_provider = OpenIdProvider::new(
         provider_data.name,
@@ -165,6 +190,9 @@ pub async fn add_openid_provider(
     )
     .upsert(&appstate.pool)
     .await?;
+    new_provider
+        .set_group_claim_mapping(&appstate.pool, &group_claim_mapping)
+        .await?;
     info!(
         "User {} added OpenID client {}",
         session.user.username, new_provider.name
@@ -188,16 +216,20 @@ pub async fn get_current_openid_provider(
     State(appstate): State<AppState>,
 ) -> ApiResult {
     let settings = Settings::get_current_settings();
+    // account creation is a part of the OpenID provisioning policy
+    let policy = ProvisioningPolicy::get(&appstate.pool, ProvisioningSource::OpenId).await?;
     match OpenIdProvider::get_current(&appstate.pool).await? {
         Some(mut provider) => {
+            let group_claim_mapping = provider.group_claim_mapping(&appstate.pool).await?;
             // Get rid of it, it should stay on the backend only.
             provider.google_service_account_key = None;
             provider.okta_private_jwk = None;
             Ok(ApiResponse {
                 json: json!({
                     "provider": json!(provider),
-                    "settings": json!({"create_account": settings.openid_create_account,
+                    "settings": json!({"create_account": policy.create_account,
                         "username_handling": settings.openid_username_handling}),
+                    "group_claim_mapping": group_claim_mapping,
                 }),
                 status: StatusCode::OK,
             })
@@ -205,7 +237,7 @@ pub async fn get_current_openid_provider(
         None => Ok(ApiResponse {
             json: json!({
                 "provider": null,
-                "settings": json!({"create_account": settings.openid_create_account,
+                "settings": json!({"create_account": policy.create_account,
                     "username_handling": settings.openid_username_handling}),
             }),
             status: StatusCode::NO_CONTENT,
@@ -213,6 +245,34 @@ pub async fn get_current_openid_provider(
     }
 }
 
+/// Preview group membership changes which the group claim mapping of the current provider would
+/// make for a user logging in with given ID token claims. Nothing is changed.
+pub async fn preview_group_claim_mapping(
+    _license: LicenseInfo,
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+    Json(data): Json<GroupClaimPreviewData>,
+) -> ApiResult {
+    let Some(provider) = OpenIdProvider::get_current(&appstate.pool).await? else {
+        return Err(WebError::ObjectNotFound(
+            "OpenID provider not set".to_string(),
+        ));
+    };
+    let Some(user) = User::find_by_username(&appstate.pool, &data.username).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "User {} not found",
+            data.username
+        )));
+    };
+    let mapping = provider.group_claim_mapping(&appstate.pool).await?;
+    let changes = preview_group_changes(&appstate.pool, &mapping, &user, &data.claims).await?;
+
+    Ok(ApiResponse {
+        json: json!(changes),
+        status: StatusCode::OK,
+    })
+}
+
 pub async fn delete_openid_provider(
     _license: LicenseInfo,
     _admin: AdminRole,
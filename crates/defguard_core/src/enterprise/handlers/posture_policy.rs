@@ -0,0 +1,216 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use defguard_common::db::Id;
+use serde_json::json;
+use utoipa::ToSchema;
+
+use super::LicenseInfo;
+use crate::{
+    appstate::AppState,
+    auth::{AdminRole, SessionInfo},
+    db::WireguardNetwork,
+    enterprise::db::models::posture_policy::{LocationPosturePolicy, is_valid_version},
+    error::WebError,
+    handlers::{ApiResponse, ApiResult},
+};
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct PosturePolicyData {
+    /// Require devices to report an encrypted system disk
+    pub require_disk_encryption: bool,
+    /// Minimum OS version, e.g. `14.2` or `10.0.19045`
+    pub min_os_version: Option<String>,
+    /// Minimum desktop client version, e.g. `1.6.0`
+    pub min_client_version: Option<String>,
+}
+
+impl PosturePolicyData {
+    fn validate(&self) -> Result<(), WebError> {
+        for version in [&self.min_os_version, &self.min_client_version]
+            .into_iter()
+            .flatten()
+        {
+            if !is_valid_version(version) {
+                return Err(WebError::BadRequest(format!(
+                    "{version} is not a valid version"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn find_location(
+    appstate: &AppState,
+    location_id: Id,
+) -> Result<WireguardNetwork<Id>, WebError> {
+    WireguardNetwork::find_by_id(&appstate.pool, location_id)
+        .await?
+        .ok_or_else(|| WebError::ObjectNotFound(format!("Location {location_id} not found")))
+}
+
+/// Get device posture policy of a WireGuard location
+///
+/// # Returns
+/// - `LocationPosturePolicy` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    get,
+    path = "/api/v1/network/{location_id}/posture_policy",
+    params(
+        ("location_id" = Id, Path, description = "WireGuard location ID")
+    ),
+    responses(
+        (status = 200, description = "Device posture policy", body = LocationPosturePolicy),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden - Admin role required"),
+        (status = 404, description = "Not found - location or policy does not exist"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub async fn get_posture_policy(
+    _license: LicenseInfo,
+    _admin_role: AdminRole,
+    Path(location_id): Path<Id>,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    let location = find_location(&appstate, location_id).await?;
+    debug!("Fetching device posture policy for location {location}");
+    let policy = LocationPosturePolicy::find_by_location(&appstate.pool, location.id)
+        .await?
+        .ok_or_else(|| {
+            WebError::ObjectNotFound(format!("Posture policy for location {location} not found"))
+        })?;
+
+    Ok(ApiResponse {
+        json: json!(policy),
+        status: StatusCode::OK,
+    })
+}
+
+/// Set device posture policy of a WireGuard location
+///
+/// Devices which don't meet the policy are denied when completing desktop client MFA.
+///
+/// # Returns
+/// - `LocationPosturePolicy` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    put,
+    path = "/api/v1/network/{location_id}/posture_policy",
+    params(
+        ("location_id" = Id, Path, description = "WireGuard location ID")
+    ),
+    request_body = PosturePolicyData,
+    responses(
+        (status = 200, description = "Device posture policy set", body = LocationPosturePolicy),
+        (status = 400, description = "Bad request - Invalid version"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden - Admin role required"),
+        (status = 404, description = "Not found - location does not exist"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub async fn set_posture_policy(
+    _license: LicenseInfo,
+    _admin_role: AdminRole,
+    session: SessionInfo,
+    Path(location_id): Path<Id>,
+    State(appstate): State<AppState>,
+    Json(data): Json<PosturePolicyData>,
+) -> ApiResult {
+    data.validate()?;
+    let location = find_location(&appstate, location_id).await?;
+    debug!(
+        "User {} setting device posture policy for location {location}: {data:?}",
+        session.user.username
+    );
+
+    let policy = match LocationPosturePolicy::find_by_location(&appstate.pool, location.id).await? {
+        Some(mut policy) => {
+            policy.require_disk_encryption = data.require_disk_encryption;
+            policy.min_os_version = data.min_os_version;
+            policy.min_client_version = data.min_client_version;
+            policy.save(&appstate.pool).await?;
+            policy
+        }
+        None => {
+            LocationPosturePolicy::new(
+                location.id,
+                data.require_disk_encryption,
+                data.min_os_version,
+                data.min_client_version,
+            )
+            .save(&appstate.pool)
+            .await?
+        }
+    };
+    info!(
+        "User {} set device posture policy for location {location}",
+        session.user.username
+    );
+
+    Ok(ApiResponse {
+        json: json!(policy),
+        status: StatusCode::OK,
+    })
+}
+
+/// Remove device posture policy of a WireGuard location
+///
+/// # Returns
+/// - empty JSON
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    delete,
+    path = "/api/v1/network/{location_id}/posture_policy",
+    params(
+        ("location_id" = Id, Path, description = "WireGuard location ID")
+    ),
+    responses(
+        (status = 200, description = "Device posture policy removed"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden - Admin role required"),
+        (status = 404, description = "Not found - location or policy does not exist"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub async fn delete_posture_policy(
+    _license: LicenseInfo,
+    _admin_role: AdminRole,
+    session: SessionInfo,
+    Path(location_id): Path<Id>,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    let location = find_location(&appstate, location_id).await?;
+    let policy = LocationPosturePolicy::find_by_location(&appstate.pool, location.id)
+        .await?
+        .ok_or_else(|| {
+            WebError::ObjectNotFound(format!("Posture policy for location {location} not found"))
+        })?;
+    policy.delete(&appstate.pool).await?;
+    info!(
+        "User {} removed device posture policy for location {location}",
+        session.user.username
+    );
+
+    Ok(ApiResponse::default())
+}
@@ -0,0 +1,116 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde_json::json;
+
+use super::LicenseInfo;
+use crate::{
+    appstate::AppState,
+    auth::{AdminRole, SessionInfo},
+    db::Group,
+    enterprise::{
+        db::models::provisioning_policy::{ProvisioningPolicy, ProvisioningSource},
+        provisioning::validate_username_template,
+    },
+    error::WebError,
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
+    handlers::{ApiResponse, ApiResult},
+};
+
+#[derive(Deserialize, Serialize)]
+pub struct ProvisioningPolicyData {
+    pub create_account: bool,
+    #[serde(default)]
+    pub default_groups: Vec<String>,
+    #[serde(default)]
+    pub start_disabled: bool,
+    pub username_template: Option<String>,
+}
+
+pub(crate) async fn list_provisioning_policies(
+    _license: LicenseInfo,
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    let mut policies = Vec::new();
+    for source in [
+        ProvisioningSource::OpenId,
+        ProvisioningSource::Ldap,
+        ProvisioningSource::Saml,
+    ] {
+        policies.push(ProvisioningPolicy::get(&appstate.pool, source).await?);
+    }
+    Ok(ApiResponse {
+        json: json!(policies),
+        status: StatusCode::OK,
+    })
+}
+
+pub(crate) async fn modify_provisioning_policy(
+    _license: LicenseInfo,
+    _admin: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(source): Path<ProvisioningSource>,
+    Json(data): Json<ProvisioningPolicyData>,
+) -> ApiResult {
+    debug!(
+        "User {} modifying {source} provisioning policy",
+        session.user.username
+    );
+    let username_template = data
+        .username_template
+        .map(|template| template.trim().to_string())
+        .filter(|template| !template.is_empty());
+    if let Some(template) = &username_template {
+        // LDAP entries are looked up by username, so it can't differ from the LDAP one
+        if source == ProvisioningSource::Ldap {
+            return Err(WebError::BadRequest(
+                "Username template is not supported for LDAP, accounts always keep their LDAP \
+                username"
+                    .into(),
+            ));
+        }
+        validate_username_template(template).map_err(WebError::BadRequest)?;
+    }
+    let mut default_groups: Vec<String> = Vec::new();
+    for name in data.default_groups {
+        let name = name.trim();
+        if name.is_empty() || default_groups.iter().any(|group| group == name) {
+            continue;
+        }
+        if Group::find_by_name(&appstate.pool, name).await?.is_none() {
+            return Err(WebError::BadRequest(format!("Group {name} doesn't exist")));
+        }
+        default_groups.push(name.to_string());
+    }
+
+    let before = ProvisioningPolicy::get(&appstate.pool, source).await?;
+    let policy = ProvisioningPolicy {
+        source,
+        create_account: data.create_account,
+        default_groups,
+        start_disabled: data.start_disabled,
+        username_template,
+    };
+    policy.save(&appstate.pool).await?;
+    info!(
+        "User {} modified {source} provisioning policy",
+        session.user.username
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::ProvisioningPolicyModified {
+            before,
+            after: policy.clone(),
+        }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!(policy),
+        status: StatusCode::OK,
+    })
+}
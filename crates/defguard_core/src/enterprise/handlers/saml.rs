@@ -0,0 +1,754 @@
+use axum::{
+    Form, Json,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_client_ip::SecureClientIp;
+use axum_extra::{
+    TypedHeader,
+    extract::{
+        CookieJar, PrivateCookieJar,
+        cookie::{Cookie, SameSite},
+    },
+    headers::UserAgent,
+};
+use chrono::Utc;
+use defguard_common::{
+    config::server_config,
+    db::{Id, NoId, models::Settings},
+};
+use serde_json::json;
+use sqlx::PgPool;
+use time::Duration;
+
+use super::{
+    LicenseInfo,
+    openid_login::{external_login_response, prune_username},
+};
+use crate::{
+    appstate::AppState,
+    auth::{AdminRole, SessionInfo},
+    db::{Group, User},
+    enterprise::{
+        db::models::{
+            provisioning_policy::{ProvisioningPolicy, ProvisioningSource},
+            saml_provider::{SamlLogin, SamlProvider},
+        },
+        group_claim::{apply_group_changes, ldap_group_authorities, plan_group_changes},
+        limits::update_counts,
+        provisioning::{
+            AccountCreationRefused, Provisioning, UsernameTemplateValues, check_pending_approval,
+            emit_account_creation_refused, emit_user_provisioned, provision_user,
+            render_username_template,
+        },
+        saml::{
+            SamlUser, assertion_attributes, authentication_request, parse_idp_metadata,
+            sp_metadata, validate_response,
+        },
+    },
+    error::WebError,
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
+    handlers::{ApiResponse, ApiResult, SESSION_COOKIE_NAME, user::check_username},
+};
+
+// Authentication request, which the response posted to ACS must refer to.
+static SAML_REQUEST_COOKIE_NAME: &str = "saml_request";
+// ID of the login of a user authenticated at ACS, who hasn't got a session yet.
+static SAML_LOGIN_COOKIE_NAME: &str = "saml_login";
+const SAML_REQUEST_MAX_AGE: Duration = Duration::minutes(10);
+const SAML_LOGIN_MAX_AGE: Duration = Duration::minutes(5);
+const SAML_CALLBACK_PATH: &str = "/api/v1/saml/callback";
+// Web UI page which finishes the login by calling the callback endpoint.
+const WEB_CALLBACK_PATH: &str = "/auth/callback/saml";
+
+#[derive(Deserialize, Serialize)]
+pub struct SamlProviderData {
+    pub name: String,
+    pub display_name: Option<String>,
+    pub enabled: bool,
+    pub idp_metadata: String,
+    pub email_attribute: String,
+    pub username_attribute: Option<String>,
+    pub first_name_attribute: String,
+    pub last_name_attribute: String,
+    pub phone_attribute: Option<String>,
+    pub groups_attribute: Option<String>,
+    pub create_groups: bool,
+}
+
+/// Authentication request stored in a cookie until the identity provider posts the response.
+#[derive(Deserialize, Serialize)]
+struct SamlRequest {
+    id: String,
+    /// User linking the SAML identity to their account.
+    link_user_id: Option<Id>,
+}
+
+/// Form posted by the identity provider with the HTTP-POST binding.
+#[derive(Deserialize)]
+pub(crate) struct SamlResponseForm {
+    #[serde(rename = "SAMLResponse")]
+    saml_response: String,
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+impl SamlProviderData {
+    /// Validates the configuration and applies it to a provider.
+    fn apply<I>(self, provider: &mut SamlProvider<I>) -> Result<(), WebError> {
+        parse_idp_metadata(&self.idp_metadata)?;
+        let email_attribute = self.email_attribute.trim();
+        if email_attribute.is_empty() {
+            return Err(WebError::BadRequest("Email attribute is required".into()));
+        }
+        provider.display_name = non_empty(self.display_name);
+        provider.enabled = self.enabled;
+        provider.idp_metadata = self.idp_metadata;
+        provider.email_attribute = email_attribute.to_string();
+        provider.username_attribute = non_empty(self.username_attribute);
+        provider.first_name_attribute = self.first_name_attribute.trim().to_string();
+        provider.last_name_attribute = self.last_name_attribute.trim().to_string();
+        provider.phone_attribute = non_empty(self.phone_attribute);
+        provider.groups_attribute = non_empty(self.groups_attribute);
+        provider.create_groups = self.create_groups;
+        Ok(())
+    }
+}
+
+/// Provider name is a part of URLs registered in the identity provider.
+fn check_provider_name(name: &str) -> Result<(), WebError> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(WebError::BadRequest(format!(
+            "Invalid SAML provider name {name}, only letters, digits, '-' and '_' are allowed"
+        )));
+    }
+    Ok(())
+}
+
+async fn find_provider(pool: &PgPool, name: &str) -> Result<SamlProvider<Id>, WebError> {
+    SamlProvider::find_by_name(pool, name)
+        .await?
+        .ok_or_else(|| WebError::ObjectNotFound(format!("SAML provider {name} not found")))
+}
+
+async fn find_enabled_provider(pool: &PgPool, name: &str) -> Result<SamlProvider<Id>, WebError> {
+    let provider = find_provider(pool, name).await?;
+    if provider.enabled {
+        Ok(provider)
+    } else {
+        Err(WebError::ObjectNotFound(format!(
+            "SAML provider {name} not found"
+        )))
+    }
+}
+
+pub(crate) async fn list_saml_providers(
+    _license: LicenseInfo,
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    let providers = SamlProvider::all(&appstate.pool).await?;
+    Ok(ApiResponse {
+        json: json!(providers),
+        status: StatusCode::OK,
+    })
+}
+
+pub(crate) async fn add_saml_provider(
+    _license: LicenseInfo,
+    _admin: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Json(data): Json<SamlProviderData>,
+) -> ApiResult {
+    debug!(
+        "User {} adding SAML provider {}",
+        session.user.username, data.name
+    );
+    check_provider_name(&data.name)?;
+    if SamlProvider::find_by_name(&appstate.pool, &data.name)
+        .await?
+        .is_some()
+    {
+        return Err(WebError::ObjectAlreadyExists(format!(
+            "SAML provider {} already exists",
+            data.name
+        )));
+    }
+    let mut provider = SamlProvider {
+        id: NoId,
+        name: data.name.clone(),
+        display_name: None,
+        enabled: true,
+        idp_metadata: String::new(),
+        email_attribute: String::new(),
+        username_attribute: None,
+        first_name_attribute: String::new(),
+        last_name_attribute: String::new(),
+        phone_attribute: None,
+        groups_attribute: None,
+        create_groups: false,
+    };
+    data.apply(&mut provider)?;
+    let provider = provider.save(&appstate.pool).await?;
+    info!(
+        "User {} added SAML provider {}",
+        session.user.username, provider.name
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::SamlProviderModified {
+            provider: provider.clone(),
+        }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!(provider),
+        status: StatusCode::CREATED,
+    })
+}
+
+pub(crate) async fn modify_saml_provider(
+    _license: LicenseInfo,
+    _admin: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(name): Path<String>,
+    Json(data): Json<SamlProviderData>,
+) -> ApiResult {
+    debug!(
+        "User {} modifying SAML provider {name}",
+        session.user.username
+    );
+    let mut provider = find_provider(&appstate.pool, &name).await?;
+    data.apply(&mut provider)?;
+    provider.save(&appstate.pool).await?;
+    info!(
+        "User {} modified SAML provider {}",
+        session.user.username, provider.name
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::SamlProviderModified {
+            provider: provider.clone(),
+        }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!(provider),
+        status: StatusCode::OK,
+    })
+}
+
+pub(crate) async fn delete_saml_provider(
+    _license: LicenseInfo,
+    _admin: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult {
+    debug!(
+        "User {} deleting SAML provider {name}",
+        session.user.username
+    );
+    let provider = find_provider(&appstate.pool, &name).await?;
+    provider.clone().delete(&appstate.pool).await?;
+    info!(
+        "User {} deleted SAML provider {}",
+        session.user.username, provider.name
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::SamlProviderRemoved { provider }),
+    })?;
+
+    Ok(ApiResponse::default())
+}
+
+/// Service provider metadata to be imported in the identity provider.
+pub(crate) async fn get_saml_metadata(
+    _license: LicenseInfo,
+    State(appstate): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Response, WebError> {
+    let provider = find_enabled_provider(&appstate.pool, &name).await?;
+    let metadata = sp_metadata(&provider)?;
+    Ok((
+        [(header::CONTENT_TYPE, "application/samlmetadata+xml")],
+        metadata,
+    )
+        .into_response())
+}
+
+// Prepares an authentication request and sets the cookie the response is checked against.
+fn auth_info_response(
+    private_cookies: PrivateCookieJar,
+    provider: SamlProvider<Id>,
+    link_user_id: Option<Id>,
+) -> Result<(PrivateCookieJar, ApiResponse), WebError> {
+    let (url, id) = authentication_request(&provider)?;
+    let request = serde_json::to_string(&SamlRequest { id, link_user_id })
+        .map_err(|err| WebError::Serialization(err.to_string()))?;
+
+    // The response is posted cross-site by the identity provider, so the cookie can't be strict.
+    let config = server_config();
+    let cookie_domain = config
+        .cookie_domain
+        .as_ref()
+        .expect("Cookie domain not found");
+    let request_cookie = Cookie::build((SAML_REQUEST_COOKIE_NAME, request))
+        .domain(cookie_domain)
+        .path(format!("/api/v1/saml/{}/acs", provider.name))
+        .http_only(true)
+        .same_site(SameSite::None)
+        .secure(!config.cookie_insecure)
+        .max_age(SAML_REQUEST_MAX_AGE)
+        .build();
+
+    Ok((
+        private_cookies.add(request_cookie),
+        ApiResponse {
+            json: json!({
+                "url": url,
+                "button_display_name": provider.display_name.unwrap_or(provider.name),
+            }),
+            status: StatusCode::OK,
+        },
+    ))
+}
+
+/// Prepares an authentication request and returns the identity provider URL which the user has
+/// to be redirected to.
+pub(crate) async fn get_saml_auth_info(
+    _license: LicenseInfo,
+    private_cookies: PrivateCookieJar,
+    State(appstate): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<(PrivateCookieJar, ApiResponse), WebError> {
+    let provider = find_enabled_provider(&appstate.pool, &name).await?;
+    auth_info_response(private_cookies, provider, None)
+}
+
+/// Same as [`get_saml_auth_info`], but the identity authenticated by the provider gets linked to
+/// the account of the logged in user. Accounts which already exist are never linked by email
+/// address, since identity providers don't necessarily verify it.
+pub(crate) async fn get_saml_link_info(
+    _license: LicenseInfo,
+    session: SessionInfo,
+    private_cookies: PrivateCookieJar,
+    State(appstate): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<(PrivateCookieJar, ApiResponse), WebError> {
+    if session.impersonation.is_some() {
+        return Err(WebError::Forbidden(
+            "SAML identities can't be linked while impersonating a user".into(),
+        ));
+    }
+    let provider = find_enabled_provider(&appstate.pool, &name).await?;
+    debug!(
+        "User {} is linking their account with SAML provider {name}",
+        session.user.username
+    );
+    auth_info_response(private_cookies, provider, Some(session.user.id))
+}
+
+/// Finds the user linked to the SAML identity, links it to the user who requested it or creates
+/// an account according to the SAML provisioning policy.
+async fn user_from_saml(
+    pool: &PgPool,
+    provider: &SamlProvider<Id>,
+    name_id: &str,
+    saml_user: &SamlUser,
+    link_user_id: Option<Id>,
+) -> Result<(User<Id>, Option<Provisioning>), WebError> {
+    let linked_user_id = provider.linked_user_id(pool, name_id).await?;
+    if let (Some(linked_user_id), Some(link_user_id)) = (linked_user_id, link_user_id) {
+        if linked_user_id != link_user_id {
+            return Err(WebError::Authorization(format!(
+                "This identity of SAML provider {} is already linked to another user",
+                provider.name
+            )));
+        }
+    }
+    if let Some(user_id) = linked_user_id.or(link_user_id) {
+        let user = User::find_by_id(pool, user_id)
+            .await?
+            .ok_or_else(|| WebError::Authorization("User not found".into()))?;
+        if !user.is_active {
+            debug!("User {} tried to log in, but is disabled", user.username);
+            return Err(WebError::Authorization("User is disabled".into()));
+        }
+        if linked_user_id.is_none() {
+            provider.link_user(pool, name_id, user.id).await?;
+            info!(
+                "Linked account of user {} with SAML provider {}",
+                user.username, provider.name
+            );
+        }
+        debug!(
+            "User {} is logging in through SAML provider {}",
+            user.username, provider.name
+        );
+        return Ok((user, None));
+    }
+
+    if User::find_by_email(pool, &saml_user.email).await?.is_some() {
+        warn!(
+            "User with email address {} is logging in through SAML provider {} for the first \
+            time, but an account with the same email address already exists. The account has \
+            to be linked with the provider by its owner.",
+            saml_user.email, provider.name
+        );
+        return Err(WebError::Authorization(
+            "An account with the same email address already exists. Log in to Defguard and link \
+            your account with the identity provider."
+                .into(),
+        ));
+    }
+
+    let policy = ProvisioningPolicy::get(pool, ProvisioningSource::Saml).await?;
+    AccountCreationRefused::check(&policy, &saml_user.email)?;
+
+    let missing = |attribute: &str| {
+        WebError::BadRequest(format!(
+            "Attribute {attribute} not found in SAML assertion. Make sure your identity \
+            provider is configured to release it."
+        ))
+    };
+    let first_name = saml_user
+        .first_name
+        .clone()
+        .ok_or_else(|| missing(&provider.first_name_attribute))?;
+    let last_name = saml_user
+        .last_name
+        .clone()
+        .ok_or_else(|| missing(&provider.last_name_attribute))?;
+    let username = match &saml_user.username {
+        Some(username) => username.as_str(),
+        None => saml_user.email.split('@').next().unwrap_or_default(),
+    };
+    let username = match &policy.username_template {
+        Some(template) => render_username_template(
+            template,
+            &UsernameTemplateValues {
+                username,
+                email: &saml_user.email,
+                given_name: &first_name,
+                family_name: &last_name,
+            },
+        ),
+        None => username.to_string(),
+    };
+    let username = prune_username(
+        &username,
+        Settings::get_current_settings().openid_username_handling,
+    );
+    check_username(&username)?;
+    if User::find_by_username(pool, &username).await?.is_some() {
+        return Err(WebError::Authorization(format!(
+            "User with username {username} already exists"
+        )));
+    }
+
+    info!(
+        "User {username} is logging in through SAML provider {} for the first time. Creating a \
+        new account.",
+        provider.name
+    );
+    let user = User::new(
+        username,
+        None,
+        last_name,
+        first_name,
+        saml_user.email.clone(),
+        saml_user.phone.clone(),
+    );
+    let (user, provisioning) = provision_user(pool, &policy, user).await?;
+    provider.link_user(pool, name_id, user.id).await?;
+    update_counts(pool).await?;
+    Ok((user, Some(provisioning)))
+}
+
+/// Assertion consumer service. Validates the SAML response posted by the identity provider and
+/// redirects to the web UI, which finishes the login.
+pub(crate) async fn saml_acs(
+    _license: LicenseInfo,
+    private_cookies: PrivateCookieJar,
+    user_agent: TypedHeader<UserAgent>,
+    SecureClientIp(ip): SecureClientIp,
+    State(appstate): State<AppState>,
+    Path(name): Path<String>,
+    Form(form): Form<SamlResponseForm>,
+) -> Result<(PrivateCookieJar, Redirect), WebError> {
+    debug!("SAML response received from provider {name}, logging in user...");
+    let provider = find_enabled_provider(&appstate.pool, &name).await?;
+    let request = private_cookies
+        .get(SAML_REQUEST_COOKIE_NAME)
+        .ok_or(WebError::Authorization(
+            "SAML request cookie not found".into(),
+        ))?;
+    let request: SamlRequest = serde_json::from_str(request.value_trimmed())
+        .map_err(|_| WebError::Authorization("Invalid SAML request cookie".into()))?;
+    let private_cookies = private_cookies
+        .remove(Cookie::build(SAML_REQUEST_COOKIE_NAME).path(format!("/api/v1/saml/{name}/acs")));
+
+    let assertion = validate_response(&provider, &form.saml_response, &request.id)?;
+    let name_id = assertion
+        .subject
+        .as_ref()
+        .and_then(|subject| subject.name_id.as_ref())
+        .map(|name_id| name_id.value.as_str())
+        .filter(|name_id| !name_id.is_empty())
+        .ok_or(WebError::Authorization(
+            "Subject name ID not found in SAML assertion".into(),
+        ))?;
+    let saml_user =
+        SamlUser::from_attributes(&provider, Some(name_id), &assertion_attributes(&assertion))?;
+    let result = user_from_saml(
+        &appstate.pool,
+        &provider,
+        name_id,
+        &saml_user,
+        request.link_user_id,
+    )
+    .await;
+    if let Err(WebError::AccountCreationRefused(refused)) = &result {
+        emit_account_creation_refused(&appstate, refused, ip, user_agent.as_str())?;
+    }
+    let (user, provisioning) = result?;
+    if let Some(provisioning) = provisioning {
+        emit_user_provisioned(&appstate, &user, provisioning, ip, user_agent.as_str())?;
+    }
+    check_pending_approval(&user)?;
+
+    // Groups are synced once the login is complete.
+    let max_age = i32::try_from(SAML_LOGIN_MAX_AGE.whole_minutes()).unwrap_or(i32::MAX);
+    SamlLogin::delete_expired(&appstate.pool, max_age).await?;
+    let login = SamlLogin::new(provider.id, user.id, saml_user.groups);
+    login.save(&appstate.pool).await?;
+
+    let config = server_config();
+    let cookie_domain = config
+        .cookie_domain
+        .as_ref()
+        .expect("Cookie domain not found");
+    let login_cookie = Cookie::build((SAML_LOGIN_COOKIE_NAME, login.id))
+        .domain(cookie_domain)
+        .path(SAML_CALLBACK_PATH)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(!config.cookie_insecure)
+        .max_age(SAML_LOGIN_MAX_AGE)
+        .build();
+    info!(
+        "User {} authenticated through SAML provider {name}",
+        user.username
+    );
+
+    Ok((
+        private_cookies.add(login_cookie),
+        Redirect::to(WEB_CALLBACK_PATH),
+    ))
+}
+
+// Reconciles group membership with the groups attribute of the SAML assertion.
+async fn sync_saml_groups(
+    appstate: &AppState,
+    login: &SamlLogin,
+    user: &User<Id>,
+) -> Result<(), WebError> {
+    let Some(groups) = &login.groups else {
+        return Ok(());
+    };
+    let Some(provider) = SamlProvider::find_by_id(&appstate.pool, login.provider_id).await? else {
+        return Ok(());
+    };
+    let member_of = user.direct_member_of(&appstate.pool).await?;
+    let existing = Group::all(&appstate.pool).await?;
+    let authorities = ldap_group_authorities(&appstate.pool).await?;
+    let changes = plan_group_changes(
+        groups,
+        &member_of,
+        &existing,
+        provider.create_groups,
+        &authorities,
+    );
+    if !changes.is_empty() {
+        apply_group_changes(&appstate.pool, user, &changes, &appstate.wireguard_tx).await?;
+        info!(
+            "Synced groups of user {} with SAML provider {}: joined {:?}, left {:?}",
+            user.username, provider.name, changes.joined, changes.left
+        );
+    }
+    Ok(())
+}
+
+/// Applies groups of the SAML login once the session completed MFA.
+pub(crate) async fn complete_saml_login(
+    appstate: &AppState,
+    session_id: &str,
+) -> Result<(), WebError> {
+    let Some(login) = SamlLogin::take_for_session(&appstate.pool, session_id).await? else {
+        return Ok(());
+    };
+    if let Some(user) = User::find_by_id(&appstate.pool, login.user_id).await? {
+        if let Err(err) = sync_saml_groups(appstate, &login, &user).await {
+            error!(
+                "Failed to sync groups of user {} with SAML provider: {err}",
+                user.username
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Creates a session for the user authenticated at the assertion consumer service.
+pub(crate) async fn saml_callback(
+    _license: LicenseInfo,
+    cookies: CookieJar,
+    private_cookies: PrivateCookieJar,
+    user_agent: TypedHeader<UserAgent>,
+    SecureClientIp(ip): SecureClientIp,
+    State(appstate): State<AppState>,
+) -> Result<(CookieJar, PrivateCookieJar, ApiResponse), WebError> {
+    let login_id = private_cookies
+        .get(SAML_LOGIN_COOKIE_NAME)
+        .ok_or(WebError::Authorization(
+            "SAML login cookie not found".into(),
+        ))?
+        .value_trimmed()
+        .to_string();
+    let private_cookies =
+        private_cookies.remove(Cookie::build(SAML_LOGIN_COOKIE_NAME).path(SAML_CALLBACK_PATH));
+
+    let mut login = SamlLogin::take(&appstate.pool, &login_id)
+        .await?
+        .ok_or_else(|| WebError::Authorization("Invalid SAML login cookie".into()))?;
+    if (Utc::now().naive_utc() - login.created).num_seconds() > SAML_LOGIN_MAX_AGE.whole_seconds() {
+        return Err(WebError::Authorization("SAML login has expired".into()));
+    }
+    let user = User::find_by_id(&appstate.pool, login.user_id)
+        .await?
+        .ok_or_else(|| WebError::Authorization("Invalid SAML login cookie".into()))?;
+    if !user.is_active {
+        return Err(WebError::Authorization("User is disabled".into()));
+    }
+
+    let (cookies, private_cookies, response) = external_login_response(
+        &appstate,
+        cookies,
+        private_cookies,
+        ip,
+        user_agent.as_str(),
+        user.clone(),
+    )
+    .await?;
+    if response.status == StatusCode::CREATED {
+        // MFA is required, groups are synced once it's completed
+        if login.groups.is_some() {
+            if let Some(session_cookie) = cookies.get(SESSION_COOKIE_NAME) {
+                login.session_id = Some(session_cookie.value().to_string());
+                login.save(&appstate.pool).await?;
+            }
+        }
+    } else if let Err(err) = sync_saml_groups(&appstate, &login, &user).await {
+        error!(
+            "Failed to sync groups of user {} with SAML provider: {err}",
+            user.username
+        );
+    }
+
+    Ok((cookies, private_cookies, response))
+}
+
+#[cfg(test)]
+mod test {
+    use defguard_common::db::{models::settings::initialize_current_settings, setup_pool};
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn test_user_from_saml_provisioning(_: PgPoolOptions, options: PgConnectOptions) {
+        let pool = setup_pool(options).await;
+        initialize_current_settings(&pool).await.unwrap();
+        let provider = SamlProvider {
+            id: NoId,
+            name: "entra".into(),
+            display_name: None,
+            enabled: true,
+            idp_metadata: String::new(),
+            email_attribute: "email".into(),
+            username_attribute: None,
+            first_name_attribute: "givenName".into(),
+            last_name_attribute: "surname".into(),
+            phone_attribute: None,
+            groups_attribute: None,
+            create_groups: false,
+        }
+        .save(&pool)
+        .await
+        .unwrap();
+        Group::new("contractors").save(&pool).await.unwrap();
+        let saml_user = SamlUser {
+            email: "hgranger@hogwart.edu.uk".into(),
+            username: None,
+            first_name: Some("Hermione".into()),
+            last_name: Some("Granger".into()),
+            phone: None,
+            groups: None,
+        };
+
+        // accounts aren't created by default
+        let result = user_from_saml(&pool, &provider, "hgranger", &saml_user, None).await;
+        assert!(matches!(
+            result,
+            Err(WebError::AccountCreationRefused(ref refused))
+                if refused.source == ProvisioningSource::Saml
+                    && refused.identity == saml_user.email
+        ));
+        assert!(
+            User::find_by_email(&pool, &saml_user.email)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        ProvisioningPolicy {
+            source: ProvisioningSource::Saml,
+            create_account: true,
+            default_groups: vec!["contractors".into(), "deleted".into()],
+            start_disabled: true,
+            username_template: Some("ext-{given_initial}{family_name}".into()),
+        }
+        .save(&pool)
+        .await
+        .unwrap();
+        let (user, provisioning) = user_from_saml(&pool, &provider, "hgranger", &saml_user, None)
+            .await
+            .unwrap();
+        assert_eq!(user.username, "ext-HGranger");
+        assert!(!user.is_active);
+        assert_eq!(user.member_of_names(&pool).await.unwrap(), ["contractors"]);
+        let provisioning = provisioning.unwrap();
+        assert_eq!(provisioning.source, ProvisioningSource::Saml);
+        assert_eq!(provisioning.groups, ["contractors"]);
+        assert!(provisioning.pending_approval);
+
+        // the identity is linked with the new account, which waits for approval
+        let result = user_from_saml(&pool, &provider, "hgranger", &saml_user, None).await;
+        assert!(matches!(result, Err(WebError::Authorization(_))));
+    }
+}
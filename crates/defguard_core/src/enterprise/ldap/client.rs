@@ -14,6 +14,7 @@ use super::error::LdapError;
 use crate::{db::User, enterprise::ldap::model::extract_rdn_value};
 
 impl super::LDAPConnection {
+    #[instrument(name = "ldap_connect", skip_all)]
     pub(crate) async fn create() -> Result<super::LDAPConnection, LdapError> {
         let settings = Settings::get_current_settings();
         let config = super::LDAPConfig::try_from(settings.clone())?;
@@ -34,10 +35,16 @@ impl super::LDAPConnection {
             .await?
             .success()?;
 
-        Ok(Self { config, ldap, url })
+        Ok(Self {
+            config,
+            ldap,
+            url,
+            sync_events: Vec::new(),
+        })
     }
 
     /// Searches LDAP for users.
+    #[instrument(name = "ldap_search_users", skip(self))]
     pub(super) async fn search_users(
         &mut self,
         filter: &str,
@@ -58,6 +65,7 @@ impl super::LDAPConnection {
         Ok(rs.into_iter().map(SearchEntry::construct).collect())
     }
 
+    #[instrument(name = "ldap_get", skip(self))]
     pub(crate) async fn get(&mut self, dn: &str) -> Result<Option<SearchEntry>, LdapError> {
         debug!("Searching for LDAP object with DN {dn}");
         let search_result = self
@@ -133,6 +141,7 @@ impl super::LDAPConnection {
     }
 
     /// Searches LDAP for groups.
+    #[instrument(name = "ldap_search_groups", skip(self))]
     pub(super) async fn search_groups(
         &mut self,
         filter: &str,
@@ -156,6 +165,7 @@ impl super::LDAPConnection {
     }
 
     /// Creates LDAP object with specified distinguished name and attributes.
+    #[instrument(name = "ldap_add", skip(self, attrs))]
     pub(super) async fn add(
         &mut self,
         dn: &str,
@@ -170,6 +180,7 @@ impl super::LDAPConnection {
     }
 
     /// Updates LDAP object with specified distinguished name and attributes.
+    #[instrument(name = "ldap_modify", skip(self, mods))]
     pub(super) async fn modify<S>(
         &mut self,
         old_dn: &str,
@@ -193,6 +204,7 @@ impl super::LDAPConnection {
     }
 
     /// Deletes LDAP object with specified distinguished name.
+    #[instrument(name = "ldap_delete", skip(self))]
     pub(super) async fn delete(&mut self, dn: &str) -> Result<(), LdapError> {
         debug!("Deleting LDAP object {dn}");
         let result = self.ldap.delete(dn).await?;
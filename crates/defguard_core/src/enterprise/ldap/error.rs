@@ -1,6 +1,8 @@
 use sqlx::error::Error as SqlxError;
 use thiserror::Error;
 
+use crate::enterprise::provisioning::AccountCreationRefused;
+
 /// LDAP server responses (especially `LdapResult.text` and `LdapResult.matched`) may contain
 /// null bytes and non-printable control characters that corrupt log output. This function
 /// filters out all control characters except `\n` and `\t`.
@@ -41,6 +43,11 @@ pub enum LdapError {
     ObjectAlreadyExists(String),
     #[error("User {0} does not belong to the defined synchronization groups in {1}")]
     UserNotInLDAPSyncGroups(String, &'static str),
+    #[error(transparent)]
+    AccountCreationRefused(#[from] AccountCreationRefused),
+    /// Failures of a bulk operation, keyed by the entry (e.g. username) they occurred for.
+    #[error("LDAP operation failed for {} entries", .0.len())]
+    EntriesFailed(Vec<(String, LdapError)>),
 }
 
 impl From<ldap3::LdapError> for LdapError {
@@ -1,24 +1,30 @@
-use std::{collections::HashSet, future::Future};
+use std::{
+    collections::{BTreeMap, HashSet},
+    future::Future,
+};
 
 use defguard_common::db::{
     Id,
     models::{
         Settings,
-        settings::{LdapSyncStatus, update_current_settings},
+        settings::{LdapSyncStatus, ldap_group_pattern, update_current_settings},
     },
 };
 #[cfg(not(test))]
 use ldap3::Ldap;
 use ldap3::{Mod, SearchEntry, ldap_escape};
-use model::UserObjectClass;
+use model::{LdapUserAttributes, UserObjectClass};
 use rand::Rng;
+use regex::Regex;
 use sqlx::PgPool;
 use sync::{get_ldap_sync_status, is_ldap_desynced, set_ldap_sync_status};
+use tokio::sync::mpsc::UnboundedSender;
 
 use self::error::{LdapError, sanitize_ldap_string};
 use crate::{
     db::{self, User},
     enterprise::{is_business_license_active, ldap::model::extract_dn_path, limits::update_counts},
+    events::InternalEvent,
 };
 
 #[cfg(not(test))]
@@ -26,6 +32,7 @@ pub mod client;
 pub mod error;
 pub mod hash;
 pub mod model;
+pub mod queue;
 pub mod sync;
 #[cfg(test)]
 pub mod test_client;
@@ -35,7 +42,10 @@ pub mod utils;
 ///
 /// This function may trigger either full and incremental sync based on the current sync status.
 /// Sets LDAP sync status to OutOfSync if any errors occur during the process.
-pub(crate) async fn do_ldap_sync(pool: &PgPool) -> Result<(), LdapError> {
+pub(crate) async fn do_ldap_sync(
+    pool: &PgPool,
+    internal_event_tx: &UnboundedSender<InternalEvent>,
+) -> Result<(), LdapError> {
     debug!("Starting LDAP sync, if enabled");
     let mut settings = Settings::get_current_settings();
 
@@ -80,7 +90,14 @@ pub(crate) async fn do_ldap_sync(pool: &PgPool) -> Result<(), LdapError> {
         }
     };
 
-    if let Err(err) = ldap_connection.sync(pool, is_ldap_desynced()).await {
+    let result = ldap_connection.sync(pool, is_ldap_desynced()).await;
+    // changes committed before a failure are reported as well
+    for event in ldap_connection.take_sync_events() {
+        if let Err(err) = internal_event_tx.send(event) {
+            error!("Failed to send LDAP sync activity log event: {err}");
+        }
+    }
+    if let Err(err) = result {
         set_ldap_sync_status(LdapSyncStatus::OutOfSync, pool).await?;
         return Err(err);
     }
@@ -160,6 +177,11 @@ pub struct LDAPConfig {
     pub ldap_uses_ad: bool,
     pub ldap_user_rdn_attr: Option<String>,
     pub ldap_sync_groups: Vec<String>,
+    pub ldap_user_attributes: LdapUserAttributes,
+    // Compiled `ldap_group_filter` patterns
+    pub ldap_group_filter: Vec<Regex>,
+    // Compiled `ldap_group_exclude` patterns
+    pub ldap_group_exclude: Vec<Regex>,
 }
 
 #[cfg(test)]
@@ -180,6 +202,9 @@ impl Default for LDAPConfig {
             ldap_uses_ad: false,
             ldap_user_rdn_attr: None,
             ldap_sync_groups: Vec::new(),
+            ldap_user_attributes: LdapUserAttributes::default(),
+            ldap_group_filter: Vec::new(),
+            ldap_group_exclude: Vec::new(),
         }
     }
 }
@@ -247,6 +272,21 @@ impl LDAPConfig {
         obj_classes
     }
 
+    /// Checks if the group is in the scope of the sync, as limited by group filter and exclusion
+    /// patterns. Groups outside of the scope are ignored in both Defguard and LDAP.
+    #[must_use]
+    pub(crate) fn group_in_sync_scope(&self, groupname: &str) -> bool {
+        (self.ldap_group_filter.is_empty()
+            || self
+                .ldap_group_filter
+                .iter()
+                .any(|pattern| pattern.is_match(groupname)))
+            && !self
+                .ldap_group_exclude
+                .iter()
+                .any(|pattern| pattern.is_match(groupname))
+    }
+
     /// Checks if the LDAP configuration uses the username as the RDN.
     /// This happens if the user RDN attribute is not set or is empty,
     pub(crate) fn using_username_as_rdn(&self) -> bool {
@@ -284,7 +324,29 @@ impl TryFrom<Settings> for LDAPConfig {
             }
         }
 
+        /// Compiles group name patterns, see [`ldap_group_pattern`].
+        fn compile_patterns(
+            patterns: &[String],
+            setting_name: &str,
+        ) -> Result<Vec<Regex>, LdapError> {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    ldap_group_pattern(pattern).map_err(|err| {
+                        LdapError::MissingSettings(format!(
+                            "Setting {setting_name} contains invalid pattern {pattern}: {err}"
+                        ))
+                    })
+                })
+                .collect()
+        }
+
         Ok(Self {
+            ldap_group_filter: compile_patterns(&settings.ldap_group_filter, "ldap_group_filter")?,
+            ldap_group_exclude: compile_patterns(
+                &settings.ldap_group_exclude,
+                "ldap_group_exclude",
+            )?,
             ldap_member_attr: validate_string_setting(
                 settings.ldap_member_attr,
                 "ldap_member_attr",
@@ -325,15 +387,45 @@ impl TryFrom<Settings> for LDAPConfig {
             ldap_uses_ad: settings.ldap_uses_ad,
             ldap_user_rdn_attr: settings.ldap_user_rdn_attr,
             ldap_sync_groups: settings.ldap_sync_groups,
+            ldap_user_attributes: LdapUserAttributes {
+                email: validate_string_setting(settings.ldap_email_attr, "ldap_email_attr")?,
+                first_name: validate_string_setting(
+                    settings.ldap_first_name_attr,
+                    "ldap_first_name_attr",
+                )?,
+                last_name: validate_string_setting(
+                    settings.ldap_last_name_attr,
+                    "ldap_last_name_attr",
+                )?,
+                phone: validate_string_setting(settings.ldap_phone_attr, "ldap_phone_attr")?,
+            },
         })
     }
 }
 
+/// Configured LDAP attribute and whether it was found in the sample user entry.
+#[derive(Debug, Serialize)]
+pub struct LdapResolvedAttribute {
+    pub name: String,
+    pub resolved: bool,
+}
+
+/// Result of resolving the configured attribute mapping against a user entry from LDAP.
+#[derive(Debug, Serialize)]
+pub struct LdapAttributeResolution {
+    /// DN of the user entry used to resolve attributes, `None` if no user was found.
+    pub sample_dn: Option<String>,
+    /// Resolved attributes by the name of the setting configuring them.
+    pub attributes: BTreeMap<&'static str, LdapResolvedAttribute>,
+}
+
 #[cfg(not(test))]
 pub struct LDAPConnection {
     pub config: LDAPConfig,
     pub ldap: Ldap,
     pub url: String,
+    /// Activity log events for changes applied to Defguard by the sync.
+    sync_events: Vec<InternalEvent>,
 }
 
 #[cfg(test)]
@@ -341,6 +433,7 @@ pub struct LDAPConnection {
     pub config: LDAPConfig,
     pub url: String,
     pub test_client: test_client::TestClient,
+    sync_events: Vec<InternalEvent>,
 }
 
 impl LDAPConnection {
@@ -539,7 +632,12 @@ impl LDAPConnection {
         if let Some(entry) = entries.pop() {
             info!("Performed LDAP user search: {username}");
             self.test_bind_user(&entry.dn, password).await?;
-            User::from_searchentry(&entry, username, Some(password))
+            User::from_searchentry(
+                &entry,
+                username,
+                Some(password),
+                &self.config.ldap_user_attributes,
+            )
         } else {
             Err(LdapError::ObjectNotFound(format!(
                 "User {username} not found",
@@ -564,7 +662,7 @@ impl LDAPConnection {
         }
         if let Some(entry) = entries.pop() {
             info!("Performed LDAP user search by username: {username}");
-            User::from_searchentry(&entry, username, None)
+            User::from_searchentry(&entry, username, None, &self.config.ldap_user_attributes)
         } else {
             Err(LdapError::ObjectNotFound(format!(
                 "User {username} not found",
@@ -572,6 +670,51 @@ impl LDAPConnection {
         }
     }
 
+    /// Checks which of the configured user attributes are present on a user entry from LDAP.
+    ///
+    /// The first entry matching the user object class is used, so optional attributes (e.g. phone
+    /// or group membership) may be reported as unresolved if that user doesn't have them set.
+    pub(crate) async fn resolve_user_attributes(
+        &mut self,
+    ) -> Result<LdapAttributeResolution, LdapError> {
+        let filter = format!("(objectClass={})", self.config.ldap_user_obj_class);
+        let entry = self.search_users(&filter).await?.into_iter().next();
+        let attributes = &self.config.ldap_user_attributes;
+        let mapping = [
+            (
+                "ldap_username_attr",
+                self.config.ldap_username_attr.as_str(),
+            ),
+            ("ldap_user_rdn_attr", self.config.get_rdn_attr()),
+            ("ldap_member_attr", self.config.ldap_member_attr.as_str()),
+            ("ldap_email_attr", attributes.email.as_str()),
+            ("ldap_first_name_attr", attributes.first_name.as_str()),
+            ("ldap_last_name_attr", attributes.last_name.as_str()),
+            ("ldap_phone_attr", attributes.phone.as_str()),
+        ];
+        let resolution = LdapAttributeResolution {
+            sample_dn: entry.as_ref().map(|entry| entry.dn.clone()),
+            attributes: mapping
+                .into_iter()
+                .map(|(setting, name)| {
+                    let resolved = entry
+                        .as_ref()
+                        .is_some_and(|entry| model::get_value(entry, name).is_some());
+                    (
+                        setting,
+                        LdapResolvedAttribute {
+                            name: name.to_string(),
+                            resolved,
+                        },
+                    )
+                })
+                .collect(),
+        };
+        debug!("Resolved LDAP user attributes: {resolution:?}");
+
+        Ok(resolution)
+    }
+
     /// Retrieves user from LDAP by DN (Distinguished Name).
     /// Returns an error if the user doesn't exist at the specified DN.
     pub async fn get_user_by_dn<I>(&mut self, user: &User<I>) -> Result<User, LdapError> {
@@ -580,7 +723,12 @@ impl LDAPConnection {
         match self.get(&dn).await? {
             Some(entry) => {
                 info!("Found LDAP user with DN: {}", dn);
-                User::from_searchentry(&entry, &user.username, None)
+                User::from_searchentry(
+                    &entry,
+                    &user.username,
+                    None,
+                    &self.config.ldap_user_attributes,
+                )
             }
             None => Err(LdapError::ObjectNotFound(sanitize_ldap_string(&format!(
                 "User {dn} not found",
@@ -625,6 +773,7 @@ impl LDAPConnection {
         let user_obj_classes = self.config.get_all_user_obj_classes();
         let username_attr = self.config.ldap_username_attr.clone();
         let rdn_attr = self.config.get_rdn_attr().to_string();
+        let profile_attributes = self.config.profile_attributes().cloned();
         if !self.is_username_available(&user.username).await?
             || self.user_exists_by_dn(&user_dn).await?
         {
@@ -644,6 +793,7 @@ impl LDAPConnection {
                 self.config.ldap_uses_ad,
                 &username_attr,
                 &rdn_attr,
+                profile_attributes.as_ref(),
             ),
         )
         .await?;
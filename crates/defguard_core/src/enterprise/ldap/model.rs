@@ -54,19 +54,70 @@ impl PartialEq<UserObjectClass> for &str {
     }
 }
 
+impl UserObjectClass {
+    /// Checks if the object class is one of the classes known to Defguard.
+    fn is_known(obj_class: &str) -> bool {
+        [
+            Self::SambaSamAccount,
+            Self::InetOrgPerson,
+            Self::SimpleSecurityObject,
+            Self::User,
+        ]
+        .iter()
+        .any(|known| <&str>::from(known).eq_ignore_ascii_case(obj_class))
+    }
+}
+
+/// Names of LDAP attributes holding user profile data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LdapUserAttributes {
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub phone: String,
+}
+
+impl Default for LdapUserAttributes {
+    /// Attributes of the `inetOrgPerson` object class.
+    fn default() -> Self {
+        Self {
+            email: "mail".to_string(),
+            first_name: "givenName".to_string(),
+            last_name: "sn".to_string(),
+            phone: "mobile".to_string(),
+        }
+    }
+}
+
+impl LDAPConfig {
+    /// Returns profile attribute mapping if user entries can hold profile attributes.
+    ///
+    /// This is the case for `inetOrgPerson` and `user` object classes and for custom structural
+    /// object classes, which are expected to define the mapped attributes.
+    #[must_use]
+    pub(crate) fn profile_attributes(&self) -> Option<&LdapUserAttributes> {
+        let obj_classes = self.get_all_user_obj_classes();
+        let supported = obj_classes.contains(&UserObjectClass::InetOrgPerson.into())
+            || obj_classes.contains(&UserObjectClass::User.into())
+            || !UserObjectClass::is_known(&self.ldap_user_obj_class);
+        supported.then_some(&self.ldap_user_attributes)
+    }
+}
+
 impl User {
     pub fn from_searchentry(
         entry: &SearchEntry,
         username: &str,
         password: Option<&str>,
+        attributes: &LdapUserAttributes,
     ) -> Result<Self, LdapError> {
         let mut user = Self::new(
             username.into(),
             password,
-            get_value_or_error(entry, "sn")?,
-            get_value_or_error(entry, "givenName")?,
-            get_value_or_error(entry, "mail")?,
-            get_value(entry, "mobile"),
+            get_value_or_error(entry, &attributes.last_name)?,
+            get_value_or_error(entry, &attributes.first_name)?,
+            get_value_or_error(entry, &attributes.email)?,
+            get_value(entry, &attributes.phone),
         );
         user.from_ldap = true;
         if let Some(rdn) = extract_rdn_value(&entry.dn) {
@@ -110,15 +161,18 @@ impl<I> User<I> {
 
     #[must_use]
     pub fn as_ldap_mod<'a>(&'a self, config: &'a LDAPConfig) -> Vec<Mod<&'a str>> {
-        let obj_classes = config.get_all_user_obj_classes();
         let mut changes = vec![];
-        if obj_classes.contains(&UserObjectClass::InetOrgPerson.into())
-            || obj_classes.contains(&UserObjectClass::User.into())
-        {
+        if let Some(attributes) = config.profile_attributes() {
             changes.extend_from_slice(&[
-                Mod::Replace("sn", hashset![self.last_name.as_str()]),
-                Mod::Replace("givenName", hashset![self.first_name.as_str()]),
-                Mod::Replace("mail", hashset![self.email.as_str()]),
+                Mod::Replace(
+                    attributes.last_name.as_str(),
+                    hashset![self.last_name.as_str()],
+                ),
+                Mod::Replace(
+                    attributes.first_name.as_str(),
+                    hashset![self.first_name.as_str()],
+                ),
+                Mod::Replace(attributes.email.as_str(), hashset![self.email.as_str()]),
             ]);
 
             // Allow renaming the user if the CN is not a part of the RDN
@@ -137,9 +191,12 @@ impl<I> User<I> {
 
             if let Some(phone) = &self.phone {
                 if phone.is_empty() {
-                    changes.push(Mod::Replace("mobile", HashSet::new()));
+                    changes.push(Mod::Replace(attributes.phone.as_str(), HashSet::new()));
                 } else {
-                    changes.push(Mod::Replace("mobile", hashset![phone.as_str()]));
+                    changes.push(Mod::Replace(
+                        attributes.phone.as_str(),
+                        hashset![phone.as_str()],
+                    ));
                 }
             }
         } else {
@@ -195,16 +252,21 @@ impl<I> User<I> {
         uses_ad: bool,
         username_attr: &'a str,
         rdn_attr: &'a str,
+        profile_attributes: Option<&'a LdapUserAttributes>,
     ) -> Vec<(&'a str, HashSet<&'a str>)> {
         let mut attrs = vec![];
         attrs.push((rdn_attr, hashset![self.ldap_rdn_value()]));
-        if object_classes.contains(UserObjectClass::InetOrgPerson.into())
-            || object_classes.contains(UserObjectClass::User.into())
-        {
+        if let Some(attributes) = profile_attributes {
             attrs.extend_from_slice(&[
-                ("sn", hashset![self.last_name.as_str()]),
-                ("givenName", hashset![self.first_name.as_str()]),
-                ("mail", hashset![self.email.as_str()]),
+                (
+                    attributes.last_name.as_str(),
+                    hashset![self.last_name.as_str()],
+                ),
+                (
+                    attributes.first_name.as_str(),
+                    hashset![self.first_name.as_str()],
+                ),
+                (attributes.email.as_str(), hashset![self.email.as_str()]),
             ]);
 
             if !Self::in_attrs(&attrs, "cn") {
@@ -217,7 +279,7 @@ impl<I> User<I> {
 
             if let Some(phone) = &self.phone {
                 if !phone.is_empty() {
-                    attrs.push(("mobile", hashset![phone.as_str()]));
+                    attrs.push((attributes.phone.as_str(), hashset![phone.as_str()]));
                 }
             }
         }
@@ -264,17 +326,28 @@ impl User<Id> {
     /// - he is in a group that is allowed to be synced or no such groups are configured
     /// - he is active (not disabled)
     /// - he is enrolled
+    /// - he isn't a break-glass account, as those are local only
     pub(crate) async fn ldap_sync_allowed<'e, E>(&self, executor: E) -> Result<bool, SqlxError>
     where
         E: PgExecutor<'e>,
     {
         let sync_groups = Settings::get_current_settings().ldap_sync_groups;
-        let my_groups = self.member_of(executor).await?;
-        Ok(
-            (sync_groups.is_empty() || my_groups.iter().any(|g| sync_groups.contains(&g.name)))
-                && self.is_active
-                && self.is_enrolled(),
+        let Some(user) = sqlx::query!(
+            "SELECT break_glass, ARRAY(SELECT name FROM \"group\" \
+            JOIN group_user ON \"group\".id = group_user.group_id \
+            WHERE group_user.user_id = $1) \"groups!\" \
+            FROM \"user\" WHERE id = $1",
+            self.id
         )
+        .fetch_optional(executor)
+        .await?
+        else {
+            return Ok(false);
+        };
+        Ok(!user.break_glass
+            && (sync_groups.is_empty() || user.groups.iter().any(|g| sync_groups.contains(g)))
+            && self.is_active
+            && self.is_enrolled())
     }
 
     pub(super) async fn get_without_ldap_path<'e, E>(executor: E) -> Result<Vec<Self>, SqlxError>
@@ -296,18 +369,17 @@ impl User<Id> {
     }
 }
 
-fn get_value_or_error(entry: &SearchEntry, key: &str) -> Result<String, LdapError> {
-    match entry.attrs.get(key) {
-        Some(values) if !values.is_empty() => Ok(values[0].clone()),
-        _ => Err(LdapError::MissingAttribute(key.to_string())),
-    }
+/// Returns the first value of an attribute. Attribute names are case-insensitive.
+pub(crate) fn get_value(entry: &SearchEntry, key: &str) -> Option<String> {
+    entry
+        .attrs
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .and_then(|(_, values)| values.first().cloned())
 }
 
-fn get_value(entry: &SearchEntry, key: &str) -> Option<String> {
-    match entry.attrs.get(key) {
-        Some(values) if !values.is_empty() => Some(values[0].clone()),
-        _ => None,
-    }
+fn get_value_or_error(entry: &SearchEntry, key: &str) -> Result<String, LdapError> {
+    get_value(entry, key).ok_or_else(|| LdapError::MissingAttribute(key.to_string()))
 }
 
 /// Get first value from distinguished name, for example: cn=<value>,...
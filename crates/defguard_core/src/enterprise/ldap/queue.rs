@@ -0,0 +1,167 @@
+//! Outbound queue of LDAP modifications which couldn't be applied right away, e.g. because the
+//! LDAP server was unreachable. Queued operations are retried in the background with exponential
+//! backoff, operations which keep failing are marked as failed and may be retried by an admin.
+
+use std::collections::HashSet;
+
+use chrono::Utc;
+use defguard_common::db::{Id, models::Settings};
+use sqlx::PgPool;
+
+use super::{
+    LDAPConnection,
+    error::LdapError,
+    utils::{add_user_to_groups, remove_user_from_groups},
+};
+use crate::{
+    db::{Group, User},
+    enterprise::{
+        db::models::ldap_operation::{LdapOperation, LdapOperationKind},
+        is_business_license_active,
+    },
+};
+
+/// Checks whether there are queued operations awaiting a retry. New operations should be queued
+/// as well in such case, so that they are not applied before the older ones.
+pub(super) async fn has_pending_operations(pool: &PgPool) -> Result<bool, LdapError> {
+    Ok(LdapOperation::first_pending_attempt(pool).await?.is_some())
+}
+
+/// Stores operations in the queue, `error` is the reason they couldn't be applied right away.
+pub(super) async fn queue_operations(
+    pool: &PgPool,
+    operations: impl IntoIterator<Item = LdapOperation>,
+    error: Option<&LdapError>,
+) {
+    for mut operation in operations {
+        if let Some(error) = error {
+            operation.register_failure(error.to_string());
+        }
+        let kind = operation.kind;
+        match operation.save(pool).await {
+            Ok(operation) => debug!("Queued LDAP operation {kind:?} with id {}", operation.id),
+            Err(err) => error!("Failed to queue LDAP operation {kind:?}: {err}"),
+        }
+    }
+}
+
+/// Retries queued LDAP operations if LDAP is enabled and enterprise features are available.
+pub(crate) async fn do_ldap_operation_queue(pool: &PgPool) -> Result<(), LdapError> {
+    let settings = Settings::get_current_settings();
+    if !settings.ldap_enabled || !is_business_license_active() {
+        debug!("LDAP is disabled, not processing queued LDAP operations");
+        return Ok(());
+    }
+
+    let Some(next_attempt_at) = LdapOperation::first_pending_attempt(pool).await? else {
+        return Ok(());
+    };
+    if next_attempt_at > Utc::now().naive_utc() {
+        debug!("Next attempt to apply queued LDAP operations is scheduled at {next_attempt_at}");
+        return Ok(());
+    }
+
+    let mut ldap_connection = LDAPConnection::create().await?;
+    let applied = ldap_connection.process_operation_queue(pool).await?;
+    if applied > 0 {
+        info!("Applied {applied} queued LDAP operations");
+    }
+
+    Ok(())
+}
+
+impl LDAPConnection {
+    /// Applies queued operations in the order they were queued. Processing stops at the first
+    /// operation that fails again, so that later operations never overtake earlier ones.
+    ///
+    /// Returns the number of applied operations.
+    pub(crate) async fn process_operation_queue(
+        &mut self,
+        pool: &PgPool,
+    ) -> Result<usize, LdapError> {
+        let mut applied = 0;
+        for mut operation in LdapOperation::all_pending(pool).await? {
+            let id = operation.id;
+            match self.apply_operation(&operation, pool).await {
+                Ok(()) => {
+                    debug!("Applied queued LDAP operation {id}");
+                    operation.delete(pool).await?;
+                    applied += 1;
+                }
+                Err(err) => {
+                    operation.register_failure(err.to_string());
+                    operation.save(pool).await?;
+                    if operation.failed {
+                        error!(
+                            "Queued LDAP operation {id} failed after {} attempts, giving up: {err}",
+                            operation.attempts
+                        );
+                    } else {
+                        warn!(
+                            "Queued LDAP operation {id} failed, next attempt at {}: {err}",
+                            operation.next_attempt_at
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(applied)
+    }
+
+    async fn apply_operation(
+        &mut self,
+        operation: &LdapOperation<Id>,
+        pool: &PgPool,
+    ) -> Result<(), LdapError> {
+        let groups: HashSet<&str> = operation.group_names.iter().map(String::as_str).collect();
+        match operation.kind {
+            LdapOperationKind::AddUserToGroups
+            | LdapOperationKind::RemoveUserFromGroups
+            | LdapOperationKind::UpdateUserState => {
+                let Some(user_id) = operation.user_id else {
+                    return Err(LdapError::MissingAttribute("user_id".into()));
+                };
+                let Some(mut user) = User::find_by_id(pool, user_id).await? else {
+                    return Err(LdapError::ObjectNotFound(format!("User {user_id}")));
+                };
+                match operation.kind {
+                    LdapOperationKind::AddUserToGroups => {
+                        add_user_to_groups(self, &user, groups, pool).await
+                    }
+                    LdapOperationKind::RemoveUserFromGroups => {
+                        remove_user_from_groups(self, &user, groups, pool).await
+                    }
+                    _ => self.update_users_state(vec![&mut user], pool).await,
+                }
+            }
+            LdapOperationKind::ModifyGroup => {
+                let (Some(group_id), Some(groupname)) =
+                    (operation.group_id, operation.group_names.first())
+                else {
+                    return Err(LdapError::MissingAttribute("group_id".into()));
+                };
+                let Some(group) = Group::find_by_id(pool, group_id).await? else {
+                    return Err(LdapError::ObjectNotFound(format!("Group {group_id}")));
+                };
+                if self.group_exists(groupname).await? {
+                    self.modify_group(groupname, &group).await
+                } else {
+                    debug!("Group {groupname} doesn't exist in LDAP, skipping modification");
+                    Ok(())
+                }
+            }
+            LdapOperationKind::DeleteGroup => {
+                for groupname in groups {
+                    if self.group_exists(groupname).await? {
+                        self.delete_group(groupname).await?;
+                    } else {
+                        debug!("Group {groupname} doesn't exist in LDAP, skipping deletion");
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -44,6 +44,14 @@
 //! - The LDAP change pull is performed relatively often
 //! - One object is not changed in both sources between two asynchronous syncs (may cause overwriting of changes), but this sounds like an unlikely scenario
 //!
+//! # Per-group sync authority
+//!
+//! Groups which are managed in both Defguard and LDAP can override the global authority with their own
+//! sync authority. `local` keeps the Defguard membership, `ldap` keeps the LDAP membership and `merge` only adds
+//! missing members on both sides, never removing anyone. When an explicit `local` or `ldap` authority overwrites
+//! membership differences, the affected group is recorded as a conflict, which can be reviewed by administrators.
+//! Conflicts are replaced after each full or incremental sync.
+//!
 //! # Potential improvements and issues
 //!
 //! - Some optimizations could be made using the implementation-specific object modification/creation timestamps in LDAP. Currently everything is compared
@@ -52,8 +60,9 @@
 //! - There is no real pagination and everything is loaded into the memory at once. This may be an issue at some point. 10k LDAP records wasn't a problem in testing.
 //!   We may have bigger issues with other parts of Defguard with that user count though.
 //!
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+use chrono::Utc;
 use defguard_common::db::{
     Id,
     models::{
@@ -62,10 +71,16 @@ use defguard_common::db::{
     },
 };
 use sqlx::{PgConnection, PgPool};
+use utoipa::ToSchema;
 
 use super::{LDAPConfig, error::LdapError};
 use crate::{
-    db::{Group, User},
+    db::{
+        Group, User,
+        models::group::{GroupSyncAuthority, group_sync_authorities},
+    },
+    enterprise::db::models::ldap_sync_conflict::LdapSyncConflict,
+    events::{InternalEvent, LdapSyncChange},
     hashset,
 };
 
@@ -213,13 +228,34 @@ pub(super) struct GroupSyncChanges<'a> {
     pub delete_defguard: HashMap<String, HashSet<User<Id>>>,
     pub add_ldap: HashMap<String, HashSet<User<Id>>>,
     pub delete_ldap: HashMap<String, HashSet<&'a User>>,
+    pub conflicts: Vec<LdapSyncConflict>,
+}
+
+impl From<Authority> for GroupSyncAuthority {
+    fn from(authority: Authority) -> Self {
+        match authority {
+            Authority::Defguard => Self::Local,
+            Authority::LDAP => Self::Ldap,
+        }
+    }
+}
+
+fn sorted_usernames<'u>(users: impl Iterator<Item = &'u String>) -> Vec<String> {
+    let mut usernames = users.cloned().collect::<Vec<_>>();
+    usernames.sort();
+    usernames
 }
 
 /// Computes what groups should be added/deleted and where
+///
+/// Groups with an explicit sync authority are resolved according to it instead of the global
+/// authority. Membership differences which are overwritten in such groups are reported as
+/// conflicts.
 pub(super) fn compute_group_sync_changes<'a>(
     defguard_memberships: HashMap<String, HashSet<User<Id>>>,
     ldap_memberships: HashMap<String, HashSet<&'a User>>,
     authority: Authority,
+    group_authorities: &HashMap<String, GroupSyncAuthority>,
     ldap_config: &LDAPConfig,
 ) -> GroupSyncChanges<'a> {
     debug!("Computing group sync changes (group membership changes), authority: {authority:?}");
@@ -227,11 +263,17 @@ pub(super) fn compute_group_sync_changes<'a>(
     let mut add_defguard = HashMap::new();
     let mut delete_ldap = HashMap::new();
     let mut add_ldap = HashMap::new();
+    let mut conflicts = Vec::new();
 
     for (group, members) in defguard_memberships.clone() {
         debug!("Checking group {} for changes", group);
+        let group_authority = group_authorities.get(&group).copied();
+        let resolution = group_authority.unwrap_or_else(|| authority.into());
         if let Some(ldap_members) = ldap_memberships.get(&group) {
-            debug!("Group {group:?} found in LDAP, checking for membership differences");
+            debug!(
+                "Group {group:?} found in LDAP, checking for membership differences, resolution: \
+                {resolution:?}"
+            );
             let missing_from_defguard = ldap_members
                 .iter()
                 .filter(|u| {
@@ -256,17 +298,35 @@ pub(super) fn compute_group_sync_changes<'a>(
                 "Group {group:?} members missing from Defguard: {missing_from_defguard:?}, missing from LDAP: {missing_from_ldap:?}"
             );
 
+            let overwritten = match resolution {
+                GroupSyncAuthority::Local => !missing_from_defguard.is_empty(),
+                GroupSyncAuthority::Ldap => !missing_from_ldap.is_empty(),
+                GroupSyncAuthority::Merge => false,
+            };
+            if group_authority.is_some() && overwritten {
+                warn!(
+                    "Group {group:?} membership differs between Defguard and LDAP, keeping the \
+                    {resolution:?} membership"
+                );
+                conflicts.push(LdapSyncConflict::new(
+                    group.clone(),
+                    sorted_usernames(missing_from_ldap.iter().map(|u| &u.username)),
+                    sorted_usernames(missing_from_defguard.iter().map(|u| &u.username)),
+                    resolution,
+                ));
+            }
+
             if missing_from_defguard.is_empty() {
                 debug!("Group {group:?} has no members missing from Defguard");
             } else {
-                match authority {
-                    Authority::Defguard => {
+                match resolution {
+                    GroupSyncAuthority::Local => {
                         debug!(
                             "Group {group:?} has members missing from Defguard, marking them for deletion in LDAP: {missing_from_defguard:?}"
                         );
                         delete_ldap.insert(group.clone(), missing_from_defguard);
                     }
-                    Authority::LDAP => {
+                    GroupSyncAuthority::Ldap | GroupSyncAuthority::Merge => {
                         debug!(
                             "Group {group:?} has members missing from Defguard, marking them for addition in Defguard: {missing_from_defguard:?}"
                         );
@@ -278,14 +338,14 @@ pub(super) fn compute_group_sync_changes<'a>(
             if missing_from_ldap.is_empty() {
                 debug!("Group {group:?} has no members missing from LDAP");
             } else {
-                match authority {
-                    Authority::Defguard => {
+                match resolution {
+                    GroupSyncAuthority::Local | GroupSyncAuthority::Merge => {
                         debug!(
                             "Group {group:?} has members missing from LDAP, marking them for addition to LDAP: {missing_from_ldap:?}"
                         );
                         add_ldap.insert(group.clone(), missing_from_ldap);
                     }
-                    Authority::LDAP => {
+                    GroupSyncAuthority::Ldap => {
                         debug!(
                             "Group {group:?} has members missing from LDAP, marking them for deletion in Defguard: {missing_from_ldap:?}"
                         );
@@ -294,17 +354,29 @@ pub(super) fn compute_group_sync_changes<'a>(
                 }
             }
         } else {
-            match authority {
-                Authority::Defguard => {
+            match resolution {
+                GroupSyncAuthority::Local | GroupSyncAuthority::Merge => {
                     debug!(
-                        "Group {group:?} is missing from LDAP, marking it for addition to LDAP along with all members due to Defguard authority"
+                        "Group {group:?} is missing from LDAP, marking it for addition to LDAP along with all members, resolution: {resolution:?}"
                     );
                     add_ldap.insert(group.clone(), members);
                 }
-                Authority::LDAP => {
+                GroupSyncAuthority::Ldap => {
                     debug!(
                         "Group {group:?} is missing from LDAP, marking all its member for deletion from Defguard due to LDAP authority"
                     );
+                    if group_authority.is_some() && !members.is_empty() {
+                        warn!(
+                            "Group {group:?} is missing from LDAP, removing all its members from \
+                            Defguard"
+                        );
+                        conflicts.push(LdapSyncConflict::new(
+                            group.clone(),
+                            sorted_usernames(members.iter().map(|u| &u.username)),
+                            Vec::new(),
+                            resolution,
+                        ));
+                    }
                     delete_defguard.insert(group.clone(), members);
                 }
             }
@@ -313,16 +385,20 @@ pub(super) fn compute_group_sync_changes<'a>(
 
     for (group, members) in ldap_memberships {
         if !defguard_memberships.contains_key(&group) {
-            match authority {
-                Authority::Defguard => {
+            let resolution = group_authorities
+                .get(&group)
+                .copied()
+                .unwrap_or_else(|| authority.into());
+            match resolution {
+                GroupSyncAuthority::Local => {
                     debug!(
                         "Group {group:?} is missing from Defguard, marking all its member for deletion from LDAP due to Defguard authority"
                     );
                     delete_ldap.insert(group, members);
                 }
-                Authority::LDAP => {
+                GroupSyncAuthority::Ldap | GroupSyncAuthority::Merge => {
                     debug!(
-                        "Group {group:?} is missing from Defguard, marking all its member for addition to Defguard due to LDAP authority"
+                        "Group {group:?} is missing from Defguard, marking all its member for addition to Defguard, resolution: {resolution:?}"
                     );
                     add_defguard.insert(group, members);
                 }
@@ -335,6 +411,7 @@ pub(super) fn compute_group_sync_changes<'a>(
         delete_defguard,
         add_ldap,
         delete_ldap,
+        conflicts,
     };
 
     debug!("Completed computing group sync changes");
@@ -343,6 +420,73 @@ pub(super) fn compute_group_sync_changes<'a>(
     sync_changes
 }
 
+/// Users and group memberships which take part in the sync.
+struct SyncState {
+    ldap_users: Vec<User>,
+    defguard_users: Vec<User<Id>>,
+    defguard_memberships: HashMap<String, HashSet<User<Id>>>,
+}
+
+/// Selects the sync authority. Incremental sync always pulls changes from LDAP.
+fn sync_authority(full: bool) -> Authority {
+    if full {
+        let settings = Settings::get_current_settings();
+        let settings_authority = if settings.ldap_is_authoritative {
+            Authority::LDAP
+        } else {
+            Authority::Defguard
+        };
+        debug!("Full LDAP sync requested, using the following authority: {settings_authority:?}");
+        settings_authority
+    } else {
+        debug!("Incremental LDAP sync requested.");
+        Authority::LDAP
+    }
+}
+
+/// Changes an LDAP sync would make, computed without applying them.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LdapSyncPreview {
+    /// Whether a full sync was previewed, incremental sync only pulls changes from LDAP.
+    pub full: bool,
+    pub users: LdapUserSyncPreview,
+    pub memberships: LdapMembershipSyncPreview,
+    /// Group membership differences which would be overwritten due to group sync authority.
+    pub conflicts: Vec<LdapSyncConflict>,
+}
+
+/// Usernames of users which would be created, deleted or modified on each side.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LdapUserSyncPreview {
+    pub add_defguard: Vec<String>,
+    pub delete_defguard: Vec<String>,
+    pub modify_defguard: Vec<String>,
+    pub add_ldap: Vec<String>,
+    pub delete_ldap: Vec<String>,
+    pub modify_ldap: Vec<String>,
+}
+
+/// Usernames of group members which would be added or removed on each side, by group name.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LdapMembershipSyncPreview {
+    pub add_defguard: BTreeMap<String, Vec<String>>,
+    pub delete_defguard: BTreeMap<String, Vec<String>>,
+    pub add_ldap: BTreeMap<String, Vec<String>>,
+    pub delete_ldap: BTreeMap<String, Vec<String>>,
+}
+
+fn membership_preview<'u, M>(
+    memberships: impl Iterator<Item = (&'u String, M)>,
+) -> BTreeMap<String, Vec<String>>
+where
+    M: Iterator<Item = &'u String>,
+{
+    memberships
+        .map(|(group, members)| (group.clone(), sorted_usernames(members)))
+        .filter(|(_, members)| !members.is_empty())
+        .collect()
+}
+
 fn attrs_different(defguard_user: &User<Id>, ldap_user: &User, config: &LDAPConfig) -> bool {
     let mut different = false;
 
@@ -438,6 +582,21 @@ pub fn get_ldap_sync_interval() -> u64 {
 }
 
 impl super::LDAPConnection {
+    /// Queues activity log events for changes committed to the Defguard database.
+    fn record_sync_changes(&mut self, changes: Vec<LdapSyncChange>) {
+        let timestamp = Utc::now().naive_utc();
+        self.sync_events.extend(
+            changes
+                .into_iter()
+                .map(|change| InternalEvent::LdapSync { timestamp, change }),
+        );
+    }
+
+    /// Takes activity log events queued since the last call.
+    pub(crate) fn take_sync_events(&mut self) -> Vec<InternalEvent> {
+        std::mem::take(&mut self.sync_events)
+    }
+
     /// Applies user modifications to users that are present in both LDAP and Defguard
     async fn apply_user_modifications(
         &mut self,
@@ -446,6 +605,7 @@ impl super::LDAPConnection {
         pool: &PgPool,
     ) -> Result<(), LdapError> {
         let mut transaction = pool.begin().await?;
+        let mut sync_changes = Vec::new();
 
         for (ldap_user, defguard_user) in &mut intersecting_users {
             if attrs_different(defguard_user, ldap_user, &self.config) {
@@ -455,8 +615,13 @@ impl super::LDAPConnection {
                 match authority {
                     Authority::LDAP => {
                         debug!("Applying LDAP user attributes to Defguard user");
+                        let before = defguard_user.clone();
                         defguard_user.update_from_ldap_user(ldap_user, &self.config);
                         defguard_user.save(&mut *transaction).await?;
+                        sync_changes.push(LdapSyncChange::UserModified {
+                            before,
+                            after: defguard_user.clone(),
+                        });
                     }
                     Authority::Defguard => {
                         debug!("Applying Defguard user attributes to LDAP user");
@@ -467,6 +632,7 @@ impl super::LDAPConnection {
         }
 
         transaction.commit().await?;
+        self.record_sync_changes(sync_changes);
 
         Ok(())
     }
@@ -491,11 +657,17 @@ impl super::LDAPConnection {
 
         let user_dn = self.config.user_dn_from_user(user);
         let ldap_user = self.get_user_by_dn(user).await?;
-        let defguard_groups = user.member_of_names(pool).await?;
+        let mut defguard_groups = user.member_of_names(pool).await?;
+        defguard_groups.retain(|group_name| self.config.group_in_sync_scope(group_name));
         let mut ldap_groups = Vec::new();
         for group_entry in self.get_user_groups(&user_dn).await? {
             match self.group_entry_to_name(group_entry) {
-                Ok(group_name) => ldap_groups.push(group_name),
+                Ok(group_name) if self.config.group_in_sync_scope(&group_name) => {
+                    ldap_groups.push(group_name);
+                }
+                Ok(group_name) => {
+                    debug!("LDAP group {group_name} is out of the sync scope, skipping");
+                }
                 Err(err) => {
                     warn!(
                         "Failed to convert group entry to name during user synchronization: \
@@ -528,6 +700,7 @@ impl super::LDAPConnection {
             defguard_memberships,
             ldap_memberships,
             authority,
+            &group_sync_authorities(pool).await?,
             &self.config,
         );
         self.apply_user_group_sync_changes(pool, changes).await?;
@@ -605,26 +778,8 @@ impl super::LDAPConnection {
         Ok(())
     }
 
-    /// Synchronizes users and groups between Defguard and LDAP
-    pub(crate) async fn sync(&mut self, pool: &PgPool, full: bool) -> Result<(), LdapError> {
-        let settings = Settings::get_current_settings();
-        let authority = if full {
-            let settings_authority = if settings.ldap_is_authoritative {
-                Authority::LDAP
-            } else {
-                Authority::Defguard
-            };
-            debug!(
-                "Full LDAP sync requested, using the following authority: {settings_authority:?}"
-            );
-            settings_authority
-        } else {
-            debug!("Incremental LDAP sync requested.");
-            Authority::LDAP
-        };
-
-        self.fix_missing_user_path(pool).await?;
-
+    /// Loads users and group memberships which take part in the sync from Defguard and LDAP.
+    async fn load_sync_state(&mut self, pool: &PgPool) -> Result<SyncState, LdapError> {
         let mut sync_groups = Vec::new();
         for groupname in &self.config.ldap_sync_groups {
             if let Some(group) = Group::find_by_name(pool, groupname).await? {
@@ -644,7 +799,7 @@ impl super::LDAPConnection {
             sync_group_members.extend(members);
         }
 
-        let mut all_ldap_users = self.get_all_users().await?;
+        let all_ldap_users = self.get_all_users().await?;
         let mut all_defguard_users = User::all(pool).await?;
 
         // Filter out users that should be ignored from sync
@@ -668,14 +823,14 @@ impl super::LDAPConnection {
         debug!("LDAP users: {:?}", ldap_usernames);
         debug!("Defguard users: {:?}", defguard_usernames);
 
-        let all_ldap_users_groupsync = all_ldap_users.clone();
-        let ldap_memberships = self
-            .get_ldap_group_memberships(&all_ldap_users_groupsync)
-            .await?;
         let mut defguard_memberships = HashMap::new();
         let defguard_groups = Group::all(pool).await?;
 
         for group in defguard_groups {
+            if !self.config.group_in_sync_scope(&group.name) {
+                debug!("Group {} is out of the sync scope, skipping", group.name);
+                continue;
+            }
             let mut members = HashSet::new();
             for member in group.members(pool).await? {
                 if member.ldap_sync_allowed(pool).await? {
@@ -685,6 +840,47 @@ impl super::LDAPConnection {
             defguard_memberships.insert(group.name, members);
         }
 
+        Ok(SyncState {
+            ldap_users: all_ldap_users,
+            defguard_users: all_defguard_users,
+            defguard_memberships,
+        })
+    }
+
+    /// Returns LDAP group memberships, limited to groups in the sync scope.
+    async fn get_scoped_ldap_group_memberships<'a>(
+        &mut self,
+        all_ldap_users: &'a [User],
+    ) -> Result<HashMap<String, HashSet<&'a User>>, LdapError> {
+        let mut memberships = self.get_ldap_group_memberships(all_ldap_users).await?;
+        memberships.retain(|groupname, _| {
+            let in_scope = self.config.group_in_sync_scope(groupname);
+            if !in_scope {
+                debug!("LDAP group {groupname} is out of the sync scope, skipping");
+            }
+            in_scope
+        });
+
+        Ok(memberships)
+    }
+
+    /// Synchronizes users and groups between Defguard and LDAP
+    pub(crate) async fn sync(&mut self, pool: &PgPool, full: bool) -> Result<(), LdapError> {
+        let authority = sync_authority(full);
+
+        self.fix_missing_user_path(pool).await?;
+
+        let SyncState {
+            ldap_users: mut all_ldap_users,
+            defguard_users: mut all_defguard_users,
+            defguard_memberships,
+        } = self.load_sync_state(pool).await?;
+
+        let all_ldap_users_groupsync = all_ldap_users.clone();
+        let ldap_memberships = self
+            .get_scoped_ldap_group_memberships(&all_ldap_users_groupsync)
+            .await?;
+
         let intersecting_users =
             extract_intersecting_users(&mut all_defguard_users, &mut all_ldap_users, &self.config);
 
@@ -698,17 +894,29 @@ impl super::LDAPConnection {
             &self.config,
         );
 
-        let membership_changes = compute_group_sync_changes(
+        let mut membership_changes = compute_group_sync_changes(
             defguard_memberships,
             ldap_memberships,
             authority,
+            &group_sync_authorities(pool).await?,
             &self.config,
         );
+        let conflicts = std::mem::take(&mut membership_changes.conflicts);
 
         self.apply_user_sync_changes(pool, user_changes).await?;
         self.apply_user_group_sync_changes(pool, membership_changes)
             .await?;
 
+        if !conflicts.is_empty() {
+            warn!(
+                "Found {} group membership conflicts during LDAP sync",
+                conflicts.len()
+            );
+        }
+        let mut transaction = pool.begin().await?;
+        LdapSyncConflict::replace_all(&mut transaction, conflicts).await?;
+        transaction.commit().await?;
+
         if full {
             debug!("Full LDAP sync completed");
         } else {
@@ -718,6 +926,103 @@ impl super::LDAPConnection {
         Ok(())
     }
 
+    /// Computes changes which a sync would make, without applying any of them.
+    ///
+    /// Missing LDAP paths of users are not fixed in this mode, so users affected by
+    /// [`Self::fix_missing_user_path`] may be reported as missing from LDAP.
+    pub(crate) async fn sync_preview(
+        &mut self,
+        pool: &PgPool,
+        full: bool,
+    ) -> Result<LdapSyncPreview, LdapError> {
+        let authority = sync_authority(full);
+
+        let SyncState {
+            ldap_users: mut all_ldap_users,
+            defguard_users: mut all_defguard_users,
+            defguard_memberships,
+        } = self.load_sync_state(pool).await?;
+
+        let all_ldap_users_groupsync = all_ldap_users.clone();
+        let ldap_memberships = self
+            .get_scoped_ldap_group_memberships(&all_ldap_users_groupsync)
+            .await?;
+
+        let intersecting_users =
+            extract_intersecting_users(&mut all_defguard_users, &mut all_ldap_users, &self.config);
+        let modified_users = intersecting_users
+            .iter()
+            .filter(|(ldap_user, defguard_user)| {
+                attrs_different(defguard_user, ldap_user, &self.config)
+            })
+            .map(|(_, defguard_user)| &defguard_user.username);
+        let (modify_defguard, modify_ldap) = match authority {
+            Authority::LDAP => (sorted_usernames(modified_users), Vec::new()),
+            Authority::Defguard => (Vec::new(), sorted_usernames(modified_users)),
+        };
+
+        let user_changes = compute_user_sync_changes(
+            &mut all_ldap_users,
+            &mut all_defguard_users,
+            authority,
+            &self.config,
+        );
+        let membership_changes = compute_group_sync_changes(
+            defguard_memberships,
+            ldap_memberships,
+            authority,
+            &group_sync_authorities(pool).await?,
+            &self.config,
+        );
+
+        let preview =
+            LdapSyncPreview {
+                full,
+                users: LdapUserSyncPreview {
+                    add_defguard: sorted_usernames(
+                        user_changes.add_defguard.iter().map(|user| &user.username),
+                    ),
+                    delete_defguard: sorted_usernames(
+                        user_changes
+                            .delete_defguard
+                            .iter()
+                            .map(|user| &user.username),
+                    ),
+                    modify_defguard,
+                    add_ldap: sorted_usernames(
+                        user_changes.add_ldap.iter().map(|user| &user.username),
+                    ),
+                    delete_ldap: sorted_usernames(
+                        user_changes.delete_ldap.iter().map(|user| &user.username),
+                    ),
+                    modify_ldap,
+                },
+                memberships: LdapMembershipSyncPreview {
+                    add_defguard: membership_preview(membership_changes.add_defguard.iter().map(
+                        |(group, members)| (group, members.iter().map(|user| &user.username)),
+                    )),
+                    delete_defguard: membership_preview(
+                        membership_changes
+                            .delete_defguard
+                            .iter()
+                            .map(|(group, members)| {
+                                (group, members.iter().map(|user| &user.username))
+                            }),
+                    ),
+                    add_ldap: membership_preview(membership_changes.add_ldap.iter().map(
+                        |(group, members)| (group, members.iter().map(|user| &user.username)),
+                    )),
+                    delete_ldap: membership_preview(membership_changes.delete_ldap.iter().map(
+                        |(group, members)| (group, members.iter().map(|user| &user.username)),
+                    )),
+                },
+                conflicts: membership_changes.conflicts,
+            };
+        debug!("Computed LDAP sync preview: {preview:?}");
+
+        Ok(preview)
+    }
+
     async fn apply_user_group_sync_changes(
         &mut self,
         pool: &PgPool,
@@ -725,6 +1030,7 @@ impl super::LDAPConnection {
     ) -> Result<(), LdapError> {
         debug!("Applying group memberships sync changes");
         let mut transaction = pool.begin().await?;
+        let mut sync_changes = Vec::new();
         let mut admin_count = User::find_admins(&mut *transaction).await?.len();
         for (groupname, members) in changes.delete_defguard {
             if members.is_empty() {
@@ -748,10 +1054,18 @@ impl super::LDAPConnection {
                         );
                         admin_count -= 1;
                         member.remove_from_group(&mut *transaction, &group).await?;
+                        sync_changes.push(LdapSyncChange::GroupMemberRemoved {
+                            group: group.clone(),
+                            user: member,
+                        });
                     }
                 } else {
                     debug!("Removing user {} from group {}", member.username, groupname);
                     member.remove_from_group(&mut *transaction, &group).await?;
+                    sync_changes.push(LdapSyncChange::GroupMemberRemoved {
+                        group: group.clone(),
+                        user: member,
+                    });
                 }
             }
         }
@@ -767,6 +1081,10 @@ impl super::LDAPConnection {
                     User::find_by_username(&mut *transaction, &member.username).await?
                 {
                     user.add_to_group(&mut *transaction, &group).await?;
+                    sync_changes.push(LdapSyncChange::GroupMemberAdded {
+                        group: group.clone(),
+                        user,
+                    });
                 } else {
                     warn!(
                         "LDAP user {} not found in Defguard, despite completing user sync earlier. \
@@ -779,6 +1097,7 @@ impl super::LDAPConnection {
         }
 
         transaction.commit().await?;
+        self.record_sync_changes(sync_changes);
 
         for (groupname, members) in changes.delete_ldap {
             for member in members {
@@ -801,6 +1120,7 @@ impl super::LDAPConnection {
         mut changes: UserSyncChanges,
     ) -> Result<(), LdapError> {
         let mut transaction = pool.begin().await?;
+        let mut sync_changes = Vec::new();
         let mut admin_count = User::find_admins(&mut *transaction).await?.len();
         for user in changes.delete_defguard {
             if user.is_admin(&mut *transaction).await? {
@@ -812,11 +1132,13 @@ impl super::LDAPConnection {
                 } else {
                     admin_count -= 1;
                     debug!("Deleting admin user {} from Defguard", user.username);
-                    user.delete(&mut *transaction).await?;
+                    user.clone().delete(&mut *transaction).await?;
+                    sync_changes.push(LdapSyncChange::UserRemoved { user });
                 }
             } else {
                 debug!("Deleting user {} from Defguard", user.username);
-                user.delete(&mut *transaction).await?;
+                user.clone().delete(&mut *transaction).await?;
+                sync_changes.push(LdapSyncChange::UserRemoved { user });
             }
         }
 
@@ -845,11 +1167,13 @@ impl super::LDAPConnection {
                     "LDAP user {} does not exist in Defguard yet, adding...",
                     user.username
                 );
-                user.save(&mut *transaction).await?;
+                let user = user.save(&mut *transaction).await?;
+                sync_changes.push(LdapSyncChange::UserAdded { user });
             }
         }
 
         transaction.commit().await?;
+        self.record_sync_changes(sync_changes);
 
         for user in changes.delete_ldap {
             debug!("Deleting user {} from LDAP", user.username);
@@ -879,7 +1203,8 @@ impl super::LDAPConnection {
                     LdapError::ObjectNotFound(format!("No {username_attr} attribute found"))
                 })?;
 
-            match User::from_searchentry(&entry, username, None) {
+            match User::from_searchentry(&entry, username, None, &self.config.ldap_user_attributes)
+            {
                 Ok(user) => all_users.push(user),
                 Err(err) => {
                     warn!(
@@ -253,6 +253,7 @@ impl super::LDAPConnection {
             config: super::LDAPConfig::default(),
             url: String::new(),
             test_client: TestClient::default(),
+            sync_events: Vec::new(),
         })
     }
 
@@ -496,6 +497,7 @@ impl super::LDAPConnection {
                     false,
                     &config.ldap_username_attr,
                     &rdn_attr,
+                    config.profile_attributes(),
                 );
                 users.push(SearchEntry {
                     dn: dn.clone(),
@@ -568,6 +570,7 @@ impl<I> User<I> {
             false,
             &config.ldap_username_attr,
             &rdn_attr,
+            config.profile_attributes(),
         )
         .into_iter()
         .map(|(k, v)| (k.to_string(), v.iter().map(|s| s.to_string()).collect()))
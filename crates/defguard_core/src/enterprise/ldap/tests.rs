@@ -6,15 +6,19 @@ use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 
 use super::*;
 use crate::{
-    db::{Group, User},
-    enterprise::ldap::{
-        model::extract_rdn_value,
-        sync::{
-            Authority, compute_group_sync_changes, compute_user_sync_changes,
-            extract_intersecting_users,
+    db::{Group, User, models::group::GroupSyncAuthority},
+    enterprise::{
+        db::models::ldap_operation::{LDAP_OPERATION_MAX_ATTEMPTS, LdapOperation},
+        ldap::{
+            model::extract_rdn_value,
+            sync::{
+                Authority, compute_group_sync_changes, compute_user_sync_changes,
+                extract_intersecting_users,
+            },
+            test_client::LdapEvent,
         },
-        test_client::LdapEvent,
     },
+    events::{InternalEvent, LdapSyncChange},
 };
 
 const PASSWORD: &str = "test_password";
@@ -870,6 +874,7 @@ fn test_compute_group_sync_changes_empty_maps() {
         defguard_memberships,
         ldap_memberships,
         Authority::LDAP,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -894,6 +899,7 @@ fn test_ldap_authority_add_group_to_defguard() {
         defguard_memberships,
         ldap_memberships,
         Authority::LDAP,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -925,6 +931,7 @@ fn test_ldap_authority_delete_group_from_defguard(_: PgPoolOptions, options: PgC
         defguard_memberships,
         ldap_memberships,
         Authority::LDAP,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -956,6 +963,7 @@ fn test_defguard_authority_add_group_to_ldap(_: PgPoolOptions, options: PgConnec
         defguard_memberships,
         ldap_memberships,
         Authority::Defguard,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -983,6 +991,7 @@ fn test_defguard_authority_delete_group_from_ldap() {
         defguard_memberships,
         ldap_memberships,
         Authority::Defguard,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -1017,6 +1026,7 @@ fn test_matching_groups_no_changes(_: PgPoolOptions, options: PgConnectOptions)
         defguard_memberships.clone(),
         ldap_memberships.clone(),
         Authority::LDAP,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -1037,6 +1047,7 @@ fn test_matching_groups_no_changes(_: PgPoolOptions, options: PgConnectOptions)
         defguard_memberships,
         ldap_memberships,
         Authority::Defguard,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -1078,6 +1089,7 @@ fn test_ldap_authority_add_users_to_group(_: PgPoolOptions, options: PgConnectOp
         defguard_memberships,
         ldap_memberships,
         Authority::LDAP,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -1114,6 +1126,7 @@ fn test_ldap_authority_remove_users_from_group(_: PgPoolOptions, options: PgConn
         defguard_memberships,
         ldap_memberships,
         Authority::LDAP,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -1166,6 +1179,7 @@ fn test_multiple_groups_ldap_authority(_: PgPoolOptions, options: PgConnectOptio
         defguard_memberships,
         ldap_memberships,
         Authority::LDAP,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -1237,6 +1251,7 @@ fn test_multiple_groups_defguard_authority(_: PgPoolOptions, options: PgConnectO
         defguard_memberships,
         ldap_memberships,
         Authority::Defguard,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -1275,6 +1290,7 @@ fn test_empty_groups() {
         defguard_memberships,
         ldap_memberships,
         Authority::LDAP,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -1346,6 +1362,7 @@ fn test_complex_group_memberships(_: PgPoolOptions, options: PgConnectOptions) {
         defguard_memberships.clone(),
         ldap_memberships.clone(),
         Authority::LDAP,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -1380,6 +1397,7 @@ fn test_complex_group_memberships(_: PgPoolOptions, options: PgConnectOptions) {
         defguard_memberships,
         ldap_memberships,
         Authority::Defguard,
+        &HashMap::new(),
         &LDAPConfig::default(),
     );
 
@@ -1410,6 +1428,98 @@ fn test_complex_group_memberships(_: PgPoolOptions, options: PgConnectOptions) {
     assert!(changes_defguard.delete_ldap["group4"].contains(&user3_noid));
 }
 
+#[sqlx::test]
+fn test_merge_group_authority(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let user1 = make_test_user("user1", None, None)
+        .save(&pool)
+        .await
+        .unwrap();
+    let user2 = make_test_user("user2", None, None)
+        .save(&pool)
+        .await
+        .unwrap();
+    let user3 = make_test_user("user3", None, None);
+    let mut defguard_memberships = HashMap::new();
+    defguard_memberships.insert(
+        "test_group".to_string(),
+        HashSet::from_iter(vec![user1, user2.clone()]),
+    );
+    let user1_noid = make_test_user("user1", None, None);
+    let mut ldap_memberships = HashMap::new();
+    ldap_memberships.insert(
+        "test_group".to_string(),
+        HashSet::from_iter(vec![&user1_noid, &user3]),
+    );
+    let group_authorities = HashMap::from([("test_group".to_string(), GroupSyncAuthority::Merge)]);
+
+    let changes = compute_group_sync_changes(
+        defguard_memberships,
+        ldap_memberships,
+        Authority::LDAP,
+        &group_authorities,
+        &LDAPConfig::default(),
+    );
+
+    // Merge never removes members, missing ones are added on both sides
+    assert!(changes.delete_defguard.is_empty());
+    assert!(changes.delete_ldap.is_empty());
+    assert_eq!(changes.add_defguard["test_group"].len(), 1);
+    assert!(changes.add_defguard["test_group"].contains(&user3));
+    assert_eq!(changes.add_ldap["test_group"].len(), 1);
+    assert!(changes.add_ldap["test_group"].contains(&user2));
+    assert!(changes.conflicts.is_empty());
+}
+
+#[sqlx::test]
+fn test_local_group_authority_overrides_ldap(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let user1 = make_test_user("user1", None, None)
+        .save(&pool)
+        .await
+        .unwrap();
+    let user2 = make_test_user("user2", None, None);
+    let mut defguard_memberships = HashMap::new();
+    defguard_memberships.insert(
+        "local_group".to_string(),
+        HashSet::from_iter(vec![user1.clone()]),
+    );
+    defguard_memberships.insert(
+        "ldap_group".to_string(),
+        HashSet::from_iter(vec![user1.clone()]),
+    );
+    let mut ldap_memberships = HashMap::new();
+    ldap_memberships.insert("local_group".to_string(), HashSet::from_iter(vec![&user2]));
+    ldap_memberships.insert("ldap_group".to_string(), HashSet::from_iter(vec![&user2]));
+    let group_authorities = HashMap::from([("local_group".to_string(), GroupSyncAuthority::Local)]);
+
+    let changes = compute_group_sync_changes(
+        defguard_memberships,
+        ldap_memberships,
+        Authority::LDAP,
+        &group_authorities,
+        &LDAPConfig::default(),
+    );
+
+    // local_group keeps the Defguard membership
+    assert!(changes.add_ldap["local_group"].contains(&user1));
+    assert!(changes.delete_ldap["local_group"].contains(&user2));
+    assert!(!changes.add_defguard.contains_key("local_group"));
+    assert!(!changes.delete_defguard.contains_key("local_group"));
+
+    // ldap_group follows the global LDAP authority
+    assert!(changes.add_defguard["ldap_group"].contains(&user2));
+    assert!(changes.delete_defguard["ldap_group"].contains(&user1));
+
+    // only groups with explicit authority are reported
+    assert_eq!(changes.conflicts.len(), 1);
+    let conflict = &changes.conflicts[0];
+    assert_eq!(conflict.group_name, "local_group");
+    assert_eq!(conflict.defguard_only, vec!["user1".to_string()]);
+    assert_eq!(conflict.ldap_only, vec!["user2".to_string()]);
+    assert_eq!(conflict.resolution, GroupSyncAuthority::Local);
+}
+
 #[test]
 fn test_extract_intersecting_users_empty() {
     let mut defguard_users = Vec::<User<Id>>::new();
@@ -2032,6 +2142,70 @@ async fn test_sync_simple_nested_ou_changes(_: PgPoolOptions, options: PgConnect
     assert!(ldap_conn.test_client.get_events().is_empty());
 }
 
+#[sqlx::test]
+async fn test_sync_records_activity_log_events(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let _ = initialize_current_settings(&pool).await;
+    let mut ldap_conn = super::LDAPConnection::create().await.unwrap();
+    let config = ldap_conn.config.clone();
+
+    let group = Group::new("developers").save(&pool).await.unwrap();
+
+    let mut user1 = make_test_user("user1", None, None);
+    user1.ldap_user_path = Some("ou=users,dc=example,dc=com".to_string());
+    user1.ldap_rdn = Some("user1".to_string());
+    user1.from_ldap = true;
+    let user1 = user1.save(&pool).await.unwrap();
+
+    let mut ldap_user1 = user1.clone().as_noid();
+    ldap_user1.first_name = "UpdatedFirst1".to_string();
+    ldap_conn
+        .test_client_mut()
+        .add_test_user(&ldap_user1, &config);
+
+    let mut ldap_only_user = make_test_user("user2", None, None);
+    ldap_only_user.ldap_user_path = Some("ou=users,dc=example,dc=com".to_string());
+    ldap_conn
+        .test_client_mut()
+        .add_test_user(&ldap_only_user, &config);
+    ldap_conn
+        .test_client_mut()
+        .add_test_group(&group.clone().as_noid(), &config);
+    ldap_conn.test_client_mut().add_test_membership(
+        &group.clone().as_noid(),
+        &ldap_only_user,
+        &config,
+    );
+
+    ldap_conn.sync(&pool, false).await.unwrap();
+
+    let events = ldap_conn.take_sync_events();
+    let changes: Vec<_> = events
+        .iter()
+        .map(|event| match event {
+            InternalEvent::LdapSync { change, .. } => change,
+            other => panic!("Unexpected event {other:?}"),
+        })
+        .collect();
+    assert!(changes.iter().any(|change| matches!(
+        change,
+        LdapSyncChange::UserModified { before, after }
+            if before.first_name == "first name" && after.first_name == "UpdatedFirst1"
+    )));
+    assert!(changes.iter().any(|change| matches!(
+        change,
+        LdapSyncChange::UserAdded { user } if user.username == "user2"
+    )));
+    assert!(changes.iter().any(|change| matches!(
+        change,
+        LdapSyncChange::GroupMemberAdded { group, user }
+            if group.name == "developers" && user.username == "user2"
+    )));
+
+    // events are handed over only once
+    assert!(ldap_conn.take_sync_events().is_empty());
+}
+
 #[sqlx::test]
 async fn test_sync_incremental_with_nested_ou_conflicts(
     _: PgPoolOptions,
@@ -2415,6 +2589,141 @@ async fn test_sync_group_membership_with_intersecting_users(
     assert!(ldap_conn.test_client.get_events().is_empty());
 }
 
+#[sqlx::test]
+async fn test_sync_respects_group_scope(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let _ = initialize_current_settings(&pool).await;
+    let mut ldap_conn = super::LDAPConnection::create().await.unwrap();
+    ldap_conn.config.ldap_group_filter = vec![ldap_group_pattern("vpn-*").unwrap()];
+    ldap_conn.config.ldap_group_exclude = vec![ldap_group_pattern("/^vpn-test$/").unwrap()];
+    let config = ldap_conn.config.clone();
+
+    let sales = Group::new("sales").save(&pool).await.unwrap();
+
+    let mut user1 = make_test_user("user1", None, None);
+    user1.ldap_user_path = Some("ou=users,dc=example,dc=com".to_string());
+    user1.ldap_rdn = Some("user1".to_string());
+    user1.from_ldap = true;
+    let user1 = user1.save(&pool).await.unwrap();
+    user1.add_to_group(&pool, &sales).await.unwrap();
+
+    let ldap_user1 = user1.clone().as_noid();
+    ldap_conn
+        .test_client_mut()
+        .add_test_user(&ldap_user1, &config);
+    for name in ["vpn-office", "vpn-test", "marketing"] {
+        ldap_conn
+            .test_client_mut()
+            .add_test_membership(&Group::new(name), &ldap_user1, &config);
+    }
+
+    ldap_conn.sync(&pool, false).await.unwrap();
+
+    let user1_groups = user1.member_of_names(&pool).await.unwrap();
+    assert!(user1_groups.contains(&"vpn-office".to_string()));
+    // Out-of-scope groups are neither pulled from LDAP nor stripped in Defguard.
+    assert!(user1_groups.contains(&"sales".to_string()));
+    assert!(!user1_groups.contains(&"vpn-test".to_string()));
+    assert!(!user1_groups.contains(&"marketing".to_string()));
+    assert!(
+        Group::find_by_name(&pool, "vpn-test")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        Group::find_by_name(&pool, "marketing")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(ldap_conn.test_client.get_events().is_empty());
+}
+
+#[sqlx::test]
+async fn test_sync_preview_does_not_apply_changes(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let _ = initialize_current_settings(&pool).await;
+    let mut ldap_conn = super::LDAPConnection::create().await.unwrap();
+    let config = ldap_conn.config.clone();
+
+    let group1 = Group::new("engineering").save(&pool).await.unwrap();
+    let group2 = Group::new("management").save(&pool).await.unwrap();
+
+    let mut user1 = make_test_user("user1", None, None);
+    user1.ldap_user_path = Some("ou=users,dc=example,dc=com".to_string());
+    user1.ldap_rdn = Some("user1".to_string());
+    user1.from_ldap = true;
+    let user1 = user1.save(&pool).await.unwrap();
+    user1.add_to_group(&pool, &group1).await.unwrap();
+
+    let mut user2 = make_test_user("user2", None, None);
+    user2.ldap_user_path = Some("ou=users,dc=example,dc=com".to_string());
+    user2.ldap_rdn = Some("user2".to_string());
+    user2.from_ldap = true;
+    let user2 = user2.save(&pool).await.unwrap();
+    user2.add_to_group(&pool, &group1).await.unwrap();
+
+    let ldap_user1 = user1.clone().as_noid();
+    ldap_conn
+        .test_client_mut()
+        .add_test_user(&ldap_user1, &config);
+    ldap_conn.test_client_mut().add_test_membership(
+        &group1.clone().as_noid(),
+        &ldap_user1,
+        &config,
+    );
+    ldap_conn.test_client_mut().add_test_membership(
+        &group2.clone().as_noid(),
+        &ldap_user1,
+        &config,
+    );
+
+    let ldap_user2 = user2.clone().as_noid();
+    ldap_conn
+        .test_client_mut()
+        .add_test_user(&ldap_user2, &config);
+
+    let mut ldap_user3 = make_test_user("user3", None, None);
+    ldap_user3.ldap_user_path = Some("ou=users,dc=example,dc=com".to_string());
+    ldap_user3.ldap_rdn = Some("user3".to_string());
+    ldap_conn
+        .test_client_mut()
+        .add_test_user(&ldap_user3, &config);
+
+    let preview = ldap_conn.sync_preview(&pool, false).await.unwrap();
+    assert!(!preview.full);
+    assert_eq!(preview.users.add_defguard, vec!["user3".to_string()]);
+    assert!(preview.users.delete_defguard.is_empty());
+    assert!(preview.users.modify_defguard.is_empty());
+    assert!(preview.users.add_ldap.is_empty());
+    assert!(preview.users.delete_ldap.is_empty());
+    assert_eq!(
+        preview.memberships.add_defguard.get("management"),
+        Some(&vec!["user1".to_string()])
+    );
+    assert_eq!(
+        preview.memberships.delete_defguard.get("engineering"),
+        Some(&vec!["user2".to_string()])
+    );
+    assert!(preview.memberships.add_ldap.is_empty());
+    assert!(preview.memberships.delete_ldap.is_empty());
+    assert!(preview.conflicts.is_empty());
+
+    // nothing has been applied on either side
+    assert!(
+        User::find_by_username(&pool, "user3")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let user1_groups = user1.member_of_names(&pool).await.unwrap();
+    assert_eq!(user1_groups, vec!["engineering".to_string()]);
+    let user2_groups = user2.member_of_names(&pool).await.unwrap();
+    assert_eq!(user2_groups, vec!["engineering".to_string()]);
+    assert!(ldap_conn.test_client.get_events().is_empty());
+}
+
 #[sqlx::test]
 async fn test_get_empty_user_path(_: PgPoolOptions, options: PgConnectOptions) {
     let pool = setup_pool(options).await;
@@ -2466,7 +2775,13 @@ fn test_from_searchentry() {
             bin_attrs: HashMap::new(),
         };
 
-        let user = User::from_searchentry(&entry, "user1", Some("password123")).unwrap();
+        let user = User::from_searchentry(
+            &entry,
+            "user1",
+            Some("password123"),
+            &LdapUserAttributes::default(),
+        )
+        .unwrap();
 
         assert_eq!(user.username, "user1");
         assert_eq!(user.last_name, "lastname1");
@@ -2489,7 +2804,8 @@ fn test_from_searchentry() {
             bin_attrs: HashMap::new(),
         };
 
-        let user = User::from_searchentry(&entry, "user1", None).unwrap();
+        let user =
+            User::from_searchentry(&entry, "user1", None, &LdapUserAttributes::default()).unwrap();
 
         assert_eq!(user.username, "user1");
         assert_eq!(user.last_name, "lastname1");
@@ -2511,7 +2827,7 @@ fn test_from_searchentry() {
             bin_attrs: HashMap::new(),
         };
 
-        let result = User::from_searchentry(&entry, "user1", None);
+        let result = User::from_searchentry(&entry, "user1", None, &LdapUserAttributes::default());
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -2531,7 +2847,7 @@ fn test_from_searchentry() {
             bin_attrs: HashMap::new(),
         };
 
-        let result = User::from_searchentry(&entry, "user1", None);
+        let result = User::from_searchentry(&entry, "user1", None, &LdapUserAttributes::default());
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -2551,7 +2867,7 @@ fn test_from_searchentry() {
             bin_attrs: HashMap::new(),
         };
 
-        let result = User::from_searchentry(&entry, "user1", None);
+        let result = User::from_searchentry(&entry, "user1", None, &LdapUserAttributes::default());
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -2572,7 +2888,7 @@ fn test_from_searchentry() {
             bin_attrs: HashMap::new(),
         };
 
-        let result = User::from_searchentry(&entry, "user1", None);
+        let result = User::from_searchentry(&entry, "user1", None, &LdapUserAttributes::default());
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -2593,7 +2909,7 @@ fn test_from_searchentry() {
             bin_attrs: HashMap::new(),
         };
 
-        let result = User::from_searchentry(&entry, "user1", None);
+        let result = User::from_searchentry(&entry, "user1", None, &LdapUserAttributes::default());
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -2611,7 +2927,7 @@ fn test_from_searchentry() {
             bin_attrs: HashMap::new(),
         };
 
-        let result = User::from_searchentry(&entry, "user1", None);
+        let result = User::from_searchentry(&entry, "user1", None, &LdapUserAttributes::default());
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -2633,7 +2949,8 @@ fn test_from_searchentry() {
         };
 
         // Test with invalid username (contains special characters)
-        let result = User::from_searchentry(&entry, "user@#$%", None);
+        let result =
+            User::from_searchentry(&entry, "user@#$%", None, &LdapUserAttributes::default());
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -2655,7 +2972,13 @@ fn test_from_searchentry() {
             bin_attrs: HashMap::new(),
         };
 
-        let user = User::from_searchentry(&entry, "user1", Some("password123")).unwrap();
+        let user = User::from_searchentry(
+            &entry,
+            "user1",
+            Some("password123"),
+            &LdapUserAttributes::default(),
+        )
+        .unwrap();
 
         assert_eq!(user.username, "user1");
         assert_eq!(user.last_name, "lastname1");
@@ -2683,7 +3006,13 @@ fn test_from_searchentry() {
             bin_attrs: HashMap::new(),
         };
 
-        let user = User::from_searchentry(&entry, "user1", Some("mypassword")).unwrap();
+        let user = User::from_searchentry(
+            &entry,
+            "user1",
+            Some("mypassword"),
+            &LdapUserAttributes::default(),
+        )
+        .unwrap();
 
         assert_eq!(user.username, "user1");
         assert!(user.password_hash.is_some());
@@ -2719,7 +3048,8 @@ fn test_from_searchentry() {
             bin_attrs: HashMap::new(),
         };
 
-        let user = User::from_searchentry(&entry, "user1", None).unwrap();
+        let user =
+            User::from_searchentry(&entry, "user1", None, &LdapUserAttributes::default()).unwrap();
 
         // Should use the first value when multiple values are present
         assert_eq!(user.last_name, "lastname1");
@@ -2742,7 +3072,8 @@ fn test_from_searchentry() {
             bin_attrs: HashMap::new(),
         };
 
-        let user = User::from_searchentry(&entry, "testuser", None).unwrap();
+        let user = User::from_searchentry(&entry, "testuser", None, &LdapUserAttributes::default())
+            .unwrap();
 
         // Verify LDAP-specific fields are properly set
         assert!(user.from_ldap);
@@ -2756,6 +3087,7 @@ fn test_from_searchentry() {
 
 #[test]
 fn test_as_ldap_attrs() {
+    let attributes = LdapUserAttributes::default();
     let user = User::new(
         "testuser".to_string(),
         Some("password123"),
@@ -2773,6 +3105,7 @@ fn test_as_ldap_attrs() {
         false,
         "uid",
         "cn",
+        Some(&attributes),
     );
 
     assert!(attrs.contains(&("cn", hashset!["testuser"])));
@@ -2790,6 +3123,7 @@ fn test_as_ldap_attrs() {
         true,
         "uid",
         "cn",
+        Some(&attributes),
     );
 
     assert!(attrs.contains(&("sAMAccountName", hashset!["testuser"])));
@@ -2805,6 +3139,7 @@ fn test_as_ldap_attrs() {
         false,
         "uid",
         "uid",
+        None,
     );
 
     assert!(attrs.contains(&("userPassword", hashset!["{SSHA}hashedpw"])));
@@ -2819,6 +3154,7 @@ fn test_as_ldap_attrs() {
         false,
         "uid",
         "customRDN",
+        Some(&attributes),
     );
 
     assert!(attrs.contains(&("customRDN", hashset![user.ldap_rdn_value()])));
@@ -2841,6 +3177,7 @@ fn test_as_ldap_attrs() {
         false,
         "uid",
         "cn",
+        Some(&attributes),
     );
 
     assert!(
@@ -2953,6 +3290,105 @@ fn test_as_ldap_mod_with_custom_rdn() {
     assert!(mods.contains(&Mod::Replace("sAMAccountName", hashset!["testuser"])));
 }
 
+#[test]
+fn test_custom_user_attribute_mapping() {
+    let user = User::new(
+        "testuser".to_string(),
+        Some("password123"),
+        "Smith".to_string(),
+        "John".to_string(),
+        "john.smith@example.com".to_string(),
+        Some("5551234".to_string()),
+    );
+
+    let config = LDAPConfig {
+        ldap_user_obj_class: "corpPerson".to_string(),
+        ldap_user_attributes: LdapUserAttributes {
+            email: "corpMail".to_string(),
+            first_name: "corpGivenName".to_string(),
+            last_name: "corpSurname".to_string(),
+            phone: "corpPhone".to_string(),
+        },
+        ..Default::default()
+    };
+
+    let mods = user.as_ldap_mod(&config);
+    assert!(mods.contains(&Mod::Replace("corpSurname", hashset!["Smith"])));
+    assert!(mods.contains(&Mod::Replace("corpGivenName", hashset!["John"])));
+    assert!(mods.contains(&Mod::Replace(
+        "corpMail",
+        hashset!["john.smith@example.com"]
+    )));
+    assert!(mods.contains(&Mod::Replace("corpPhone", hashset!["5551234"])));
+    assert!(!mods.contains(&Mod::Replace("mail", hashset!["john.smith@example.com"])));
+
+    // attribute names returned by the server may differ in case
+    let mut attrs = HashMap::new();
+    attrs.insert("corpsurname".to_string(), vec!["Smith".to_string()]);
+    attrs.insert("CORPGIVENNAME".to_string(), vec!["John".to_string()]);
+    attrs.insert(
+        "corpMail".to_string(),
+        vec!["john.smith@example.com".to_string()],
+    );
+    let entry = SearchEntry {
+        dn: "cn=testuser,dc=example,dc=com".to_string(),
+        attrs,
+        bin_attrs: HashMap::new(),
+    };
+    let ldap_user =
+        User::from_searchentry(&entry, "testuser", None, &config.ldap_user_attributes).unwrap();
+    assert_eq!(ldap_user.last_name, "Smith");
+    assert_eq!(ldap_user.first_name, "John");
+    assert_eq!(ldap_user.email, "john.smith@example.com");
+    assert_eq!(ldap_user.phone, None);
+
+    // known object classes without profile attributes
+    let config = LDAPConfig {
+        ldap_user_obj_class: UserObjectClass::SimpleSecurityObject.into(),
+        ..Default::default()
+    };
+    assert!(config.profile_attributes().is_none());
+    assert!(
+        !user
+            .as_ldap_mod(&config)
+            .iter()
+            .any(|change| matches!(change, Mod::Replace("mail", _)))
+    );
+}
+
+#[tokio::test]
+async fn test_resolve_user_attributes() {
+    let mut ldap_conn = LDAPConnection::create().await.unwrap();
+
+    let resolution = ldap_conn.resolve_user_attributes().await.unwrap();
+    assert!(resolution.sample_dn.is_none());
+    assert!(resolution.attributes.values().all(|attr| !attr.resolved));
+
+    let config = ldap_conn.config.clone();
+    let test_user = make_test_user("testuser", None, None);
+    ldap_conn
+        .test_client_mut()
+        .add_test_user(&test_user, &config);
+
+    let resolution = ldap_conn.resolve_user_attributes().await.unwrap();
+    assert_eq!(
+        resolution.sample_dn,
+        Some(config.user_dn_from_user(&test_user))
+    );
+    for setting in [
+        "ldap_username_attr",
+        "ldap_user_rdn_attr",
+        "ldap_email_attr",
+        "ldap_first_name_attr",
+        "ldap_last_name_attr",
+    ] {
+        assert!(resolution.attributes[setting].resolved, "{setting}");
+    }
+    // user is not a member of any group
+    assert!(!resolution.attributes["ldap_member_attr"].resolved);
+    assert_eq!(resolution.attributes["ldap_email_attr"].name, "mail");
+}
+
 #[test]
 fn test_extract_dn_path_various_cases() {
     assert_eq!(
@@ -3184,3 +3620,59 @@ async fn test_ldap_sync_allowed_all_conditions_false(_: PgPoolOptions, options:
     let result = user.ldap_sync_allowed(&pool).await.unwrap();
     assert!(!result);
 }
+
+#[sqlx::test]
+async fn test_process_operation_queue(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let _ = initialize_current_settings(&pool).await;
+    let mut ldap_conn = super::LDAPConnection::create().await.unwrap();
+    let config = ldap_conn.config.clone();
+
+    let user = make_test_user("user1", None, None)
+        .save(&pool)
+        .await
+        .unwrap();
+    ldap_conn
+        .test_client_mut()
+        .add_test_user(&user.clone().as_noid(), &config);
+    ldap_conn
+        .test_client_mut()
+        .add_test_group(&Group::new("old"), &config);
+
+    LdapOperation::add_user_to_groups(user.id, vec!["staff".into()])
+        .save(&pool)
+        .await
+        .unwrap();
+    LdapOperation::delete_group("old".into())
+        .save(&pool)
+        .await
+        .unwrap();
+    let mut failed = LdapOperation::delete_group("failed".into());
+    for _ in 0..LDAP_OPERATION_MAX_ATTEMPTS {
+        failed.register_failure("connection refused".into());
+    }
+    let failed = failed.save(&pool).await.unwrap();
+
+    let applied = ldap_conn.process_operation_queue(&pool).await.unwrap();
+    assert_eq!(applied, 2);
+
+    // Applied operations are removed, permanently failed ones are kept for the admin to review.
+    let operations = LdapOperation::all(&pool).await.unwrap();
+    assert_eq!(operations.len(), 1);
+    assert_eq!(operations[0].id, failed.id);
+    assert!(operations[0].failed);
+
+    let events = ldap_conn.test_client.get_events();
+    let staff_dn = config.group_dn("staff");
+    assert!(
+        events
+            .iter()
+            .any(|event| matches!(event, LdapEvent::ObjectAdded { dn, .. } if *dn == staff_dn))
+    );
+    assert!(events.contains(&LdapEvent::ObjectDeleted {
+        dn: config.group_dn("old"),
+    }));
+    assert!(!events.contains(&LdapEvent::ObjectDeleted {
+        dn: config.group_dn("failed"),
+    }));
+}
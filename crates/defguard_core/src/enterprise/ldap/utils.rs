@@ -3,23 +3,36 @@
 
 use std::collections::{HashMap, HashSet};
 
-use defguard_common::db::Id;
+use defguard_common::{config::server_config, db::Id};
+use futures_util::future::join_all;
 use sqlx::PgPool;
 
-use super::{LDAPConnection, error::LdapError};
+use super::{
+    LDAPConnection,
+    error::LdapError,
+    queue::{has_pending_operations, queue_operations},
+};
 use crate::{
     db::{Group, User},
-    enterprise::ldap::with_ldap_status,
+    enterprise::{
+        db::models::{
+            ldap_operation::LdapOperation,
+            provisioning_policy::{ProvisioningPolicy, ProvisioningSource},
+        },
+        ldap::with_ldap_status,
+        provisioning::{AccountCreationRefused, Provisioning, provision_user},
+    },
 };
 
 /// Retrieves a user from LDAP if they are in the configured LDAP sync groups.
 ///
-/// Creates a new user in Defguard if they do not exist and marks them as coming from LDAP.
+/// Creates a new user in Defguard if they do not exist, according to the LDAP provisioning policy,
+/// and marks them as coming from LDAP. Details of the provisioning are returned in such case.
 pub(crate) async fn login_through_ldap(
     pool: &PgPool,
     username: &str,
     password: &str,
-) -> Result<User<Id>, LdapError> {
+) -> Result<(User<Id>, Option<Provisioning>), LdapError> {
     debug!("Logging in user {username} through LDAP");
     let mut ldap_connection = LDAPConnection::create().await?;
     let mut ldap_user = ldap_connection
@@ -44,18 +57,59 @@ pub(crate) async fn login_through_ldap(
         );
         defguard_user.from_ldap = true;
         defguard_user.save(pool).await?;
-        defguard_user
+        (defguard_user, None)
     } else {
+        let policy = ProvisioningPolicy::get(pool, ProvisioningSource::Ldap).await?;
+        AccountCreationRefused::check(&policy, &ldap_user.username)?;
         debug!(
             "User {ldap_user} doesn't exist in Defguard, creating them first based on LDAP data"
         );
         ldap_user.from_ldap = true;
-        ldap_user.save(pool).await?
+        let (user, provisioning) = provision_user(pool, &policy, ldap_user).await?;
+        (user, Some(provisioning))
     };
 
     Ok(user)
 }
 
+/// Splits `entries` into at most `server_config().ldap_concurrency` batches of similar size.
+fn split_into_batches<T>(entries: impl IntoIterator<Item = T>) -> Vec<Vec<T>> {
+    split_into_n_batches(entries, server_config().ldap_concurrency)
+}
+
+fn split_into_n_batches<T>(entries: impl IntoIterator<Item = T>, n: usize) -> Vec<Vec<T>> {
+    let entries: Vec<T> = entries.into_iter().collect();
+    let batch_count = n.max(1).min(entries.len());
+    let mut batches: Vec<Vec<T>> = (0..batch_count).map(|_| Vec::new()).collect();
+    for (index, entry) in entries.into_iter().enumerate() {
+        batches[index % batch_count].push(entry);
+    }
+    batches
+}
+
+/// Runs `op` for every batch concurrently and gathers per-entry errors returned by all batches.
+/// Each batch is expected to use its own [`LDAPConnection`], so the number of batches bounds
+/// the number of concurrent LDAP connections.
+async fn run_batches<T, F, Fut>(batches: Vec<Vec<T>>, op: F) -> Result<(), LdapError>
+where
+    F: Fn(Vec<T>) -> Fut,
+    Fut: Future<Output = Vec<(String, LdapError)>>,
+{
+    let errors: Vec<_> = join_all(batches.into_iter().map(op))
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        for (entry, err) in &errors {
+            warn!("LDAP operation failed for {entry}: {err}");
+        }
+        Err(LdapError::EntriesFailed(errors))
+    }
+}
+
 /// Convenience wrapper around [`ldap_update_users_state`] to update a single user.
 pub(crate) async fn ldap_update_user_state(user: &mut User<Id>, pool: &PgPool) {
     let vec = vec![user];
@@ -65,10 +119,39 @@ pub(crate) async fn ldap_update_user_state(user: &mut User<Id>, pool: &PgPool) {
 /// See the [`LDAPConnection::update_users_state`] function for details.
 pub(crate) async fn ldap_update_users_state(users: Vec<&mut User<Id>>, pool: &PgPool) {
     let _ = Box::pin(with_ldap_status(pool, async {
-        debug!("Updating users state in LDAP");
-        let mut ldap_connection = LDAPConnection::create().await?;
-        ldap_connection.update_users_state(users, pool).await?;
-        Ok(())
+        debug!("Updating state of {} users in LDAP", users.len());
+        let operations = |users: &[&mut User<Id>]| {
+            users
+                .iter()
+                .map(|user| LdapOperation::update_user_state(user.id))
+                .collect::<Vec<_>>()
+        };
+        if has_pending_operations(pool).await? {
+            debug!("There are LDAP operations awaiting a retry, queueing the user state update");
+            queue_operations(pool, operations(users.as_slice()), None).await;
+            return Ok(());
+        }
+        run_batches(split_into_batches(users), |batch| async move {
+            let mut errors = Vec::new();
+            let mut ldap_connection = match LDAPConnection::create().await {
+                Ok(connection) => connection,
+                Err(err) => {
+                    queue_operations(pool, operations(batch.as_slice()), Some(&err)).await;
+                    errors.push((format!("batch of {} users", batch.len()), err));
+                    return errors;
+                }
+            };
+            for user in batch {
+                let username = user.username.clone();
+                let operation = LdapOperation::update_user_state(user.id);
+                if let Err(err) = ldap_connection.update_users_state(vec![user], pool).await {
+                    queue_operations(pool, [operation], Some(&err)).await;
+                    errors.push((username, err));
+                }
+            }
+            errors
+        })
+        .await
     }))
     .await;
 }
@@ -170,14 +253,25 @@ pub(crate) async fn ldap_delete_user<I>(user: &User<I>, pool: &PgPool) {
 pub(crate) async fn ldap_delete_users<I>(users: Vec<&User<I>>, pool: &PgPool) {
     let _: Result<(), LdapError> = with_ldap_status(pool, async {
         debug!("Deleting {:?} users from LDAP", users.len());
-        let mut ldap_connection = LDAPConnection::create().await?;
-        for user in users {
-            debug!("Deleting user {user} from LDAP");
-            ldap_connection.delete_user(user).await?;
-            debug!("User {user} deleted from LDAP");
-        }
-
-        Ok(())
+        run_batches(split_into_batches(users), |batch| async move {
+            let mut errors = Vec::new();
+            let mut ldap_connection = match LDAPConnection::create().await {
+                Ok(connection) => connection,
+                Err(err) => {
+                    errors.push((format!("batch of {} users", batch.len()), err));
+                    return errors;
+                }
+            };
+            for user in batch {
+                debug!("Deleting user {user} from LDAP");
+                match ldap_connection.delete_user(user).await {
+                    Ok(()) => debug!("User {user} deleted from LDAP"),
+                    Err(err) => errors.push((user.username.clone(), err)),
+                }
+            }
+            errors
+        })
+        .await
     })
     .await;
 }
@@ -205,31 +299,46 @@ pub(crate) async fn ldap_add_users_to_groups(
     pool: &PgPool,
 ) {
     let _: Result<(), LdapError> = with_ldap_status(pool, async {
-        let mut ldap_connection = LDAPConnection::create().await?;
-        let sync_groups = ldap_connection.config.ldap_sync_groups.clone();
-        let sync_groups_lookup = sync_groups
-            .iter()
-            .map(String::as_str)
-            .collect::<HashSet<_>>();
-
-        for (user, groups) in user_groups {
-            let adding_to_sync_groups = groups
-                .iter()
-                .any(|group| sync_groups_lookup.contains(*group));
-            if !user.ldap_sync_allowed(pool).await? && !adding_to_sync_groups {
-                debug!(
-                    "User {user} is not allowed to be synced to LDAP as he is not in the \
-                    specified sync groups, skipping"
-                );
-                continue;
-            }
-
-            for group in groups {
-                ldap_connection.add_user_to_group(user, group).await?;
-            }
+        debug!("Adding {} users to groups in LDAP", user_groups.len());
+        if has_pending_operations(pool).await? {
+            debug!("There are LDAP operations awaiting a retry, queueing the group additions");
+            queue_operations(
+                pool,
+                membership_operations(&user_groups, LdapOperation::add_user_to_groups),
+                None,
+            )
+            .await;
+            return Ok(());
         }
-
-        Ok(())
+        run_batches(split_into_batches(user_groups), |batch| async move {
+            let mut errors = Vec::new();
+            let mut ldap_connection = match LDAPConnection::create().await {
+                Ok(connection) => connection,
+                Err(err) => {
+                    queue_operations(
+                        pool,
+                        membership_operations(
+                            batch.iter().map(|(user, groups)| (user, groups)),
+                            LdapOperation::add_user_to_groups,
+                        ),
+                        Some(&err),
+                    )
+                    .await;
+                    errors.push((format!("batch of {} users", batch.len()), err));
+                    return errors;
+                }
+            };
+            for (user, groups) in batch {
+                let operation = LdapOperation::add_user_to_groups(user.id, group_names(&groups));
+                if let Err(err) = add_user_to_groups(&mut ldap_connection, user, groups, pool).await
+                {
+                    queue_operations(pool, [operation], Some(&err)).await;
+                    errors.push((user.username.clone(), err));
+                }
+            }
+            errors
+        })
+        .await
     })
     .await;
 }
@@ -240,38 +349,123 @@ pub(crate) async fn ldap_remove_users_from_groups(
     pool: &PgPool,
 ) {
     let _: Result<(), LdapError> = with_ldap_status(pool, async {
-        let mut ldap_connection = LDAPConnection::create().await?;
-        let sync_groups = ldap_connection.config.ldap_sync_groups.clone();
-        let sync_groups_lookup = sync_groups
-            .iter()
-            .map(String::as_str)
-            .collect::<HashSet<_>>();
-
-        for (user, groups) in user_groups {
-            let removing_from_sync_groups = groups
-                .iter()
-                .any(|group| sync_groups_lookup.contains(*group));
-            if !user.ldap_sync_allowed(pool).await? && !removing_from_sync_groups {
-                debug!(
-                    "User {user} is not allowed to be synced to LDAP as he is not in the
-                    specified sync groups, skipping"
-                );
-                continue;
-            }
-            for group in groups {
-                if ldap_connection.group_exists(group).await? {
-                    ldap_connection.remove_user_from_group(user, group).await?;
-                } else {
-                    debug!("Group {group} doesn't exist in LDAP, skipping removal of user {user}");
+        debug!("Removing {} users from groups in LDAP", user_groups.len());
+        if has_pending_operations(pool).await? {
+            debug!("There are LDAP operations awaiting a retry, queueing the group removals");
+            queue_operations(
+                pool,
+                membership_operations(&user_groups, LdapOperation::remove_user_from_groups),
+                None,
+            )
+            .await;
+            return Ok(());
+        }
+        run_batches(split_into_batches(user_groups), |batch| async move {
+            let mut errors = Vec::new();
+            let mut ldap_connection = match LDAPConnection::create().await {
+                Ok(connection) => connection,
+                Err(err) => {
+                    queue_operations(
+                        pool,
+                        membership_operations(
+                            batch.iter().map(|(user, groups)| (user, groups)),
+                            LdapOperation::remove_user_from_groups,
+                        ),
+                        Some(&err),
+                    )
+                    .await;
+                    errors.push((format!("batch of {} users", batch.len()), err));
+                    return errors;
+                }
+            };
+            for (user, groups) in batch {
+                let operation =
+                    LdapOperation::remove_user_from_groups(user.id, group_names(&groups));
+                if let Err(err) =
+                    remove_user_from_groups(&mut ldap_connection, user, groups, pool).await
+                {
+                    queue_operations(pool, [operation], Some(&err)).await;
+                    errors.push((user.username.clone(), err));
                 }
             }
-        }
-
-        Ok(())
+            errors
+        })
+        .await
     })
     .await;
 }
 
+fn group_names(groups: &HashSet<&str>) -> Vec<String> {
+    groups.iter().map(ToString::to_string).collect()
+}
+
+/// Builds queue entries for group membership changes which couldn't be applied right away.
+fn membership_operations<'a>(
+    user_groups: impl IntoIterator<Item = (&'a &'a User<Id>, &'a HashSet<&'a str>)>,
+    operation: fn(Id, Vec<String>) -> LdapOperation,
+) -> Vec<LdapOperation> {
+    user_groups
+        .into_iter()
+        .map(|(user, groups)| operation(user.id, group_names(groups)))
+        .collect()
+}
+
+/// Checks whether any of `groups` is one of the configured LDAP sync groups.
+fn contains_sync_group(ldap_connection: &LDAPConnection, groups: &HashSet<&str>) -> bool {
+    ldap_connection
+        .config
+        .ldap_sync_groups
+        .iter()
+        .any(|group| groups.contains(group.as_str()))
+}
+
+pub(super) async fn add_user_to_groups(
+    ldap_connection: &mut LDAPConnection,
+    user: &User<Id>,
+    groups: HashSet<&str>,
+    pool: &PgPool,
+) -> Result<(), LdapError> {
+    let adding_to_sync_groups = contains_sync_group(ldap_connection, &groups);
+    if !user.ldap_sync_allowed(pool).await? && !adding_to_sync_groups {
+        debug!(
+            "User {user} is not allowed to be synced to LDAP as he is not in the \
+            specified sync groups, skipping"
+        );
+        return Ok(());
+    }
+
+    for group in groups {
+        ldap_connection.add_user_to_group(user, group).await?;
+    }
+
+    Ok(())
+}
+
+pub(super) async fn remove_user_from_groups(
+    ldap_connection: &mut LDAPConnection,
+    user: &User<Id>,
+    groups: HashSet<&str>,
+    pool: &PgPool,
+) -> Result<(), LdapError> {
+    let removing_from_sync_groups = contains_sync_group(ldap_connection, &groups);
+    if !user.ldap_sync_allowed(pool).await? && !removing_from_sync_groups {
+        debug!(
+            "User {user} is not allowed to be synced to LDAP as he is not in the \
+            specified sync groups, skipping"
+        );
+        return Ok(());
+    }
+    for group in groups {
+        if ldap_connection.group_exists(group).await? {
+            ldap_connection.remove_user_from_group(user, group).await?;
+        } else {
+            debug!("Group {group} doesn't exist in LDAP, skipping removal of user {user}");
+        }
+    }
+
+    Ok(())
+}
+
 pub(crate) async fn ldap_change_password(user: &mut User<Id>, password: &str, pool: &PgPool) {
     let _: Result<(), LdapError> = with_ldap_status(pool, async {
         debug!("Changing password for user {user} in LDAP");
@@ -311,8 +505,21 @@ pub(crate) async fn ldap_change_password(user: &mut User<Id>, password: &str, po
 pub(crate) async fn ldap_modify_group(groupname: &str, group: &Group<Id>, pool: &PgPool) {
     let _: Result<(), LdapError> = with_ldap_status(pool, async {
         debug!("Modifying group {groupname} in LDAP");
-        let mut ldap_connection = LDAPConnection::create().await?;
-        ldap_connection.modify_group(groupname, group).await
+        let operation = LdapOperation::modify_group(groupname.to_string(), group.id);
+        if has_pending_operations(pool).await? {
+            debug!("There are LDAP operations awaiting a retry, queueing the group modification");
+            queue_operations(pool, [operation], None).await;
+            return Ok(());
+        }
+        let result = async {
+            let mut ldap_connection = LDAPConnection::create().await?;
+            ldap_connection.modify_group(groupname, group).await
+        }
+        .await;
+        if let Err(err) = &result {
+            queue_operations(pool, [operation], Some(err)).await;
+        }
+        result
     })
     .await;
 }
@@ -320,8 +527,84 @@ pub(crate) async fn ldap_modify_group(groupname: &str, group: &Group<Id>, pool:
 pub(crate) async fn ldap_delete_group(groupname: &str, pool: &PgPool) {
     let _: Result<(), LdapError> = with_ldap_status(pool, async {
         debug!("Deleting group {groupname} from LDAP");
-        let mut ldap_connection = LDAPConnection::create().await?;
-        ldap_connection.delete_group(groupname).await
+        let operation = LdapOperation::delete_group(groupname.to_string());
+        if has_pending_operations(pool).await? {
+            debug!("There are LDAP operations awaiting a retry, queueing the group deletion");
+            queue_operations(pool, [operation], None).await;
+            return Ok(());
+        }
+        let result = async {
+            let mut ldap_connection = LDAPConnection::create().await?;
+            ldap_connection.delete_group(groupname).await
+        }
+        .await;
+        if let Err(err) = &result {
+            queue_operations(pool, [operation], Some(err)).await;
+        }
+        result
     })
     .await;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_split_into_batches() {
+        let batches = split_into_n_batches(1..=7, 3);
+        assert_eq!(batches, vec![vec![1, 4, 7], vec![2, 5], vec![3, 6]]);
+
+        // no empty batches are created
+        assert_eq!(split_into_n_batches(1..=2, 8), vec![vec![1], vec![2]]);
+        assert!(split_into_n_batches(Vec::<u8>::new(), 8).is_empty());
+        // concurrency of 0 is treated as 1
+        assert_eq!(split_into_n_batches(1..=3, 0), vec![vec![1, 2, 3]]);
+    }
+
+    #[tokio::test]
+    async fn test_run_batches() {
+        let batches = split_into_n_batches(1..=6, 2);
+        assert!(run_batches(batches, |_| async { Vec::new() }).await.is_ok());
+        assert!(
+            run_batches(Vec::<Vec<u8>>::new(), |_| async { Vec::new() })
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_batches_partial_failure() {
+        let processed = AtomicUsize::new(0);
+        let batches = split_into_n_batches(1..=6, 3);
+        let result = run_batches(batches, |batch| {
+            let processed = &processed;
+            async move {
+                let mut errors = Vec::new();
+                for entry in batch {
+                    processed.fetch_add(1, Ordering::Relaxed);
+                    if entry % 2 == 0 {
+                        errors.push((
+                            format!("user{entry}"),
+                            LdapError::ObjectNotFound(entry.to_string()),
+                        ));
+                    }
+                }
+                errors
+            }
+        })
+        .await;
+
+        // failures don't stop processing of the remaining entries
+        assert_eq!(processed.load(Ordering::Relaxed), 6);
+        let Err(LdapError::EntriesFailed(mut errors)) = result else {
+            panic!("expected failed entries, got {result:?}");
+        };
+        errors.sort_by(|a, b| a.0.cmp(&b.0));
+        let entries: Vec<_> = errors.iter().map(|(entry, _)| entry.as_str()).collect();
+        assert_eq!(entries, ["user2", "user4", "user6"]);
+        assert!(matches!(errors[0].1, LdapError::ObjectNotFound(ref id) if id == "2"));
+    }
+}
@@ -2,11 +2,14 @@ pub mod activity_log_stream;
 pub mod db;
 pub mod directory_sync;
 pub mod firewall;
+pub mod group_claim;
 pub mod grpc;
 pub mod handlers;
 pub mod ldap;
 pub mod license;
 pub mod limits;
+pub mod provisioning;
+pub mod saml;
 pub mod snat;
 mod utils;
 
@@ -0,0 +1,329 @@
+//! Just-in-time provisioning of accounts for users who log in through an external source
+//! (OpenID provider, LDAP or SAML provider) for the first time, according to the source's
+//! [`ProvisioningPolicy`].
+
+use std::net::IpAddr;
+
+use defguard_common::db::{Id, NoId};
+use sqlx::{Error as SqlxError, PgPool};
+use thiserror::Error;
+
+use crate::{
+    appstate::AppState,
+    db::{Group, User},
+    enterprise::{
+        db::models::provisioning_policy::{ProvisioningPolicy, ProvisioningSource},
+        ldap::utils::ldap_add_user_to_groups,
+    },
+    error::WebError,
+    events::{
+        ApiEvent, ApiEventType, ApiRequestContext, BidiRequestContext, BidiStreamEvent,
+        BidiStreamEventType,
+    },
+};
+
+/// Placeholders which can be used in username templates.
+pub const USERNAME_TEMPLATE_PLACEHOLDERS: &[&str] = &[
+    "username",
+    "email_local",
+    "given_name",
+    "family_name",
+    "given_initial",
+];
+
+/// Values substituted for username template placeholders.
+pub struct UsernameTemplateValues<'a> {
+    /// Username suggested by the source, e.g. the `preferred_username` claim.
+    pub username: &'a str,
+    pub email: &'a str,
+    pub given_name: &'a str,
+    pub family_name: &'a str,
+}
+
+impl UsernameTemplateValues<'_> {
+    fn get(&self, placeholder: &str) -> String {
+        match placeholder {
+            "username" => self.username.to_string(),
+            "email_local" => self.email.split('@').next().unwrap_or_default().to_string(),
+            "given_name" => self.given_name.to_string(),
+            "family_name" => self.family_name.to_string(),
+            "given_initial" => self.given_name.chars().take(1).collect(),
+            _ => String::new(),
+        }
+    }
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+fn parse_template(template: &str) -> Result<Vec<Segment<'_>>, String> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            segments.push(Segment::Text(&rest[..start]));
+        }
+        let Some(end) = rest[start..].find('}') else {
+            return Err(format!(
+                "Unclosed placeholder in username template {template}"
+            ));
+        };
+        let placeholder = &rest[start + 1..start + end];
+        if !USERNAME_TEMPLATE_PLACEHOLDERS.contains(&placeholder) {
+            return Err(format!(
+                "Unknown placeholder {{{placeholder}}} in username template {template}, \
+                supported placeholders: {}",
+                USERNAME_TEMPLATE_PLACEHOLDERS.join(", ")
+            ));
+        }
+        segments.push(Segment::Placeholder(placeholder));
+        rest = &rest[start + end + 1..];
+    }
+    if rest.contains('}') {
+        return Err(format!("Unexpected '}}' in username template {template}"));
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    if !segments
+        .iter()
+        .any(|segment| matches!(segment, Segment::Placeholder(_)))
+    {
+        return Err(format!(
+            "Username template {template} doesn't contain any placeholder, so all users would \
+            get the same username"
+        ));
+    }
+    Ok(segments)
+}
+
+/// Checks that a username template only uses supported placeholders.
+pub fn validate_username_template(template: &str) -> Result<(), String> {
+    parse_template(template).map(|_| ())
+}
+
+/// Renders a username from a template. Characters not allowed in usernames are handled later,
+/// together with usernames which don't come from a template.
+#[must_use]
+pub fn render_username_template(template: &str, values: &UsernameTemplateValues) -> String {
+    match parse_template(template) {
+        Ok(segments) => segments
+            .into_iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.to_string(),
+                Segment::Placeholder(placeholder) => values.get(placeholder),
+            })
+            .collect(),
+        Err(err) => {
+            warn!("Ignoring invalid username template: {err}");
+            values.username.to_string()
+        }
+    }
+}
+
+/// Details of an account created just in time, recorded in the activity log.
+#[derive(Clone, Debug)]
+pub struct Provisioning {
+    pub source: ProvisioningSource,
+    /// Default groups the user joined.
+    pub groups: Vec<String>,
+    /// The account is disabled until an admin enables it.
+    pub pending_approval: bool,
+}
+
+/// Login of a user without an account, refused because the policy doesn't allow creating it.
+#[derive(Debug, Error)]
+#[error(
+    "User not found and the automatic account creation is disabled. Enable it or create the user."
+)]
+pub struct AccountCreationRefused {
+    pub source: ProvisioningSource,
+    /// Email address or LDAP username of the user.
+    pub identity: String,
+}
+
+impl AccountCreationRefused {
+    /// Refuses the login if the policy doesn't allow creating accounts.
+    pub(crate) fn check(policy: &ProvisioningPolicy, identity: &str) -> Result<(), Self> {
+        if policy.create_account {
+            return Ok(());
+        }
+        warn!(
+            "User {identity} is trying to log in through {} for the first time, but the account \
+            creation is disabled",
+            policy.source
+        );
+        Err(Self {
+            source: policy.source,
+            identity: identity.to_string(),
+        })
+    }
+}
+
+/// Creates an account according to the policy: sets its initial state and adds it to the
+/// default groups.
+pub(crate) async fn provision_user(
+    pool: &PgPool,
+    policy: &ProvisioningPolicy,
+    mut user: User<NoId>,
+) -> Result<(User<Id>, Provisioning), SqlxError> {
+    user.is_active = !policy.start_disabled;
+    let mut transaction = pool.begin().await?;
+    let user = user.save(&mut *transaction).await?;
+    let mut groups = Vec::new();
+    for name in &policy.default_groups {
+        if let Some(group) = Group::find_by_name(&mut *transaction, name).await? {
+            user.add_to_group(&mut *transaction, &group).await?;
+            groups.push(name.clone());
+        } else {
+            warn!(
+                "Default group {name} of {} provisioning policy doesn't exist, not adding user \
+                {user} to it",
+                policy.source
+            );
+        }
+    }
+    transaction.commit().await?;
+
+    if !groups.is_empty() {
+        ldap_add_user_to_groups(&user, groups.iter().map(String::as_str).collect(), pool).await;
+    }
+    if policy.start_disabled {
+        info!(
+            "Created account of user {user} logging in through {} for the first time, the \
+            account is disabled until an admin approves it",
+            policy.source
+        );
+    } else {
+        info!(
+            "Created account of user {user} logging in through {} for the first time",
+            policy.source
+        );
+    }
+
+    Ok((
+        user,
+        Provisioning {
+            source: policy.source,
+            groups,
+            pending_approval: policy.start_disabled,
+        },
+    ))
+}
+
+/// Records creation of an account in the activity log.
+pub(crate) fn emit_user_provisioned(
+    appstate: &AppState,
+    user: &User<Id>,
+    provisioning: Provisioning,
+    ip: IpAddr,
+    user_agent: &str,
+) -> Result<(), WebError> {
+    appstate.emit_event(ApiEvent {
+        context: ApiRequestContext::new(user.id, user.username.clone(), ip, user_agent.to_string()),
+        event: Box::new(ApiEventType::UserProvisioned {
+            user: user.clone(),
+            source: provisioning.source,
+            groups: provisioning.groups,
+            pending_approval: provisioning.pending_approval,
+        }),
+    })
+}
+
+/// Records a login refused by the provisioning policy in the activity log. There is no account
+/// to attribute the event to, so it's attributed to the identity from the source.
+pub(crate) fn emit_account_creation_refused(
+    appstate: &AppState,
+    refused: &AccountCreationRefused,
+    ip: IpAddr,
+    user_agent: &str,
+) -> Result<(), WebError> {
+    appstate.emit_event(ApiEvent {
+        context: ApiRequestContext::new(0, refused.identity.clone(), ip, user_agent.to_string()),
+        event: Box::new(ApiEventType::AccountCreationRefused {
+            source: refused.source,
+            identity: refused.identity.clone(),
+        }),
+    })
+}
+
+/// Same as [`emit_user_provisioned`], for logins handled through the proxy.
+pub(crate) fn user_provisioned_bidi_event(
+    user: &User<Id>,
+    provisioning: Provisioning,
+    ip: IpAddr,
+    user_agent: String,
+) -> BidiStreamEvent {
+    BidiStreamEvent {
+        context: BidiRequestContext::new(user.id, user.username.clone(), ip, user_agent),
+        event: BidiStreamEventType::UserProvisioned {
+            user: user.clone(),
+            source: provisioning.source,
+            groups: provisioning.groups,
+            pending_approval: provisioning.pending_approval,
+        },
+    }
+}
+
+/// Same as [`emit_account_creation_refused`], for logins handled through the proxy.
+pub(crate) fn account_creation_refused_bidi_event(
+    refused: &AccountCreationRefused,
+    ip: IpAddr,
+    user_agent: String,
+) -> BidiStreamEvent {
+    BidiStreamEvent {
+        context: BidiRequestContext::new(0, refused.identity.clone(), ip, user_agent),
+        event: BidiStreamEventType::AccountCreationRefused {
+            source: refused.source,
+            identity: refused.identity.clone(),
+        },
+    }
+}
+
+/// Refuses login of a user whose just created account hasn't been approved yet.
+pub(crate) fn check_pending_approval(user: &User<Id>) -> Result<(), WebError> {
+    if user.is_active {
+        Ok(())
+    } else {
+        Err(WebError::Authorization(
+            "Your account has been created and is awaiting approval by an administrator".into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_username_template() {
+        let values = UsernameTemplateValues {
+            username: "harry",
+            email: "hpotter@hogwart.edu.uk",
+            given_name: "Harry",
+            family_name: "Potter",
+        };
+        assert_eq!(
+            render_username_template("{given_initial}{family_name}", &values),
+            "HPotter"
+        );
+        assert_eq!(
+            render_username_template("ext-{email_local}", &values),
+            "ext-hpotter"
+        );
+        assert_eq!(
+            render_username_template("{given_name}.{family_name}.{username}", &values),
+            "Harry.Potter.harry"
+        );
+
+        assert!(validate_username_template("{given_name}_{family_name}").is_ok());
+        assert!(validate_username_template("{nickname}").is_err());
+        assert!(validate_username_template("{given_name").is_err());
+        assert!(validate_username_template("given_name}").is_err());
+        assert!(validate_username_template("admin").is_err());
+        // invalid templates fall back to the username from the source
+        assert_eq!(render_username_template("{nickname}", &values), "harry");
+    }
+}
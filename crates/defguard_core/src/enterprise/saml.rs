@@ -0,0 +1,286 @@
+//! SAML 2.0 service provider. Users log in through a SAML identity provider with the HTTP-Redirect
+//! binding for authentication requests and the HTTP-POST binding for responses sent to the
+//! assertion consumer service (ACS). Assertions must be signed with a certificate from the
+//! identity provider metadata.
+
+use std::collections::HashMap;
+
+use defguard_common::{config::server_config, db::Id};
+use reqwest::Url;
+use samael::{
+    metadata::{EntityDescriptor, HTTP_REDIRECT_BINDING},
+    schema::Assertion,
+    service_provider::{ServiceProvider, ServiceProviderBuilder},
+};
+use thiserror::Error;
+
+use crate::enterprise::db::models::saml_provider::SamlProvider;
+
+#[derive(Debug, Error)]
+pub enum SamlError {
+    #[error("Invalid identity provider metadata: {0}")]
+    InvalidMetadata(String),
+    #[error("Identity provider doesn't support HTTP-Redirect binding for single sign-on")]
+    MissingSsoEndpoint,
+    #[error("Failed to prepare SAML request: {0}")]
+    Request(String),
+    #[error("Invalid SAML response: {0}")]
+    InvalidResponse(String),
+    #[error("Attribute {0} not found in SAML assertion")]
+    MissingAttribute(String),
+}
+
+/// Parses identity provider metadata XML.
+pub(crate) fn parse_idp_metadata(xml: &str) -> Result<EntityDescriptor, SamlError> {
+    xml.parse::<EntityDescriptor>()
+        .map_err(|err| SamlError::InvalidMetadata(err.to_string()))
+}
+
+/// Builds an absolute URL of a SAML endpoint for a given provider.
+fn endpoint_url(provider_name: &str, endpoint: &str) -> Url {
+    let mut url = server_config().url.clone();
+    if let Ok(mut path_segments) = url.path_segments_mut() {
+        path_segments
+            .pop_if_empty()
+            .extend(&["api", "v1", "saml", provider_name, endpoint]);
+    }
+    url
+}
+
+/// Service provider entity ID, which is also the URL of its metadata.
+#[must_use]
+pub fn entity_id(provider_name: &str) -> Url {
+    endpoint_url(provider_name, "metadata")
+}
+
+/// URL of the assertion consumer service, where identity provider posts SAML responses.
+#[must_use]
+pub fn acs_url(provider_name: &str) -> Url {
+    endpoint_url(provider_name, "acs")
+}
+
+pub(crate) fn service_provider(provider: &SamlProvider<Id>) -> Result<ServiceProvider, SamlError> {
+    ServiceProviderBuilder::default()
+        .entity_id(entity_id(&provider.name).to_string())
+        .metadata_url(entity_id(&provider.name).to_string())
+        .acs_url(acs_url(&provider.name).to_string())
+        .idp_metadata(parse_idp_metadata(&provider.idp_metadata)?)
+        .allow_idp_initiated(false)
+        .build()
+        .map_err(|err| SamlError::InvalidMetadata(err.to_string()))
+}
+
+/// Service provider metadata XML to be imported in the identity provider.
+pub(crate) fn sp_metadata(provider: &SamlProvider<Id>) -> Result<String, SamlError> {
+    let metadata = service_provider(provider)?
+        .metadata()
+        .map_err(|err| SamlError::Request(err.to_string()))?;
+    metadata
+        .to_xml()
+        .map_err(|err| SamlError::Request(err.to_string()))
+}
+
+/// Prepares an authentication request. Returns the identity provider URL the user should be
+/// redirected to and ID of the request, which the response must refer to.
+pub(crate) fn authentication_request(
+    provider: &SamlProvider<Id>,
+) -> Result<(Url, String), SamlError> {
+    let sp = service_provider(provider)?;
+    let sso_url = sp
+        .sso_binding_location(HTTP_REDIRECT_BINDING)
+        .ok_or(SamlError::MissingSsoEndpoint)?;
+    let request = sp
+        .make_authentication_request(&sso_url)
+        .map_err(|err| SamlError::Request(err.to_string()))?;
+    let url = request
+        .redirect("")
+        .map_err(|err| SamlError::Request(err.to_string()))?
+        .ok_or_else(|| SamlError::Request("failed to encode authentication request".into()))?;
+    Ok((url, request.id))
+}
+
+/// Validates signature, conditions and destination of a base64 encoded SAML response, which has
+/// to be a response to the authentication request with `request_id`.
+pub(crate) fn validate_response(
+    provider: &SamlProvider<Id>,
+    saml_response: &str,
+    request_id: &str,
+) -> Result<Assertion, SamlError> {
+    service_provider(provider)?
+        .parse_base64_response(saml_response, Some(&[request_id]))
+        .map_err(|err| SamlError::InvalidResponse(err.to_string()))
+}
+
+/// Collects values of assertion attributes, accessible both by name and friendly name.
+#[must_use]
+pub fn assertion_attributes(assertion: &Assertion) -> HashMap<String, Vec<String>> {
+    let mut attributes: HashMap<String, Vec<String>> = HashMap::new();
+    for statement in assertion.attribute_statements.iter().flatten() {
+        for attribute in &statement.attributes {
+            let values: Vec<String> = attribute
+                .values
+                .iter()
+                .filter_map(|value| value.value.clone())
+                .collect();
+            for name in [&attribute.name, &attribute.friendly_name]
+                .into_iter()
+                .flatten()
+            {
+                attributes
+                    .entry(name.clone())
+                    .or_default()
+                    .extend(values.iter().cloned());
+            }
+        }
+    }
+    attributes
+}
+
+/// User details mapped from assertion attributes according to provider configuration.
+#[derive(Debug, PartialEq)]
+pub struct SamlUser {
+    pub email: String,
+    pub username: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub phone: Option<String>,
+    /// `None` if group mapping is disabled.
+    pub groups: Option<Vec<String>>,
+}
+
+impl SamlUser {
+    /// Maps attributes onto user fields. Email falls back to the subject name ID, which
+    /// identity providers commonly set to the email address.
+    pub fn from_attributes<I>(
+        provider: &SamlProvider<I>,
+        name_id: Option<&str>,
+        attributes: &HashMap<String, Vec<String>>,
+    ) -> Result<Self, SamlError> {
+        let first = |name: &str| {
+            attributes
+                .get(name)
+                .and_then(|values| values.iter().find(|value| !value.trim().is_empty()))
+                .map(|value| value.trim().to_string())
+        };
+        let email = first(&provider.email_attribute)
+            .or_else(|| {
+                name_id
+                    .filter(|id| id.contains('@'))
+                    .map(ToString::to_string)
+            })
+            .ok_or_else(|| SamlError::MissingAttribute(provider.email_attribute.clone()))?;
+        let groups = provider.groups_attribute.as_ref().map(|name| {
+            let mut groups: Vec<String> = Vec::new();
+            for value in attributes.get(name).into_iter().flatten() {
+                let value = value.trim();
+                if !value.is_empty() && !groups.iter().any(|group| group == value) {
+                    groups.push(value.to_string());
+                }
+            }
+            groups
+        });
+
+        Ok(Self {
+            email,
+            username: provider.username_attribute.as_deref().and_then(first),
+            first_name: first(&provider.first_name_attribute),
+            last_name: first(&provider.last_name_attribute),
+            phone: provider.phone_attribute.as_deref().and_then(first),
+            groups,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use defguard_common::db::NoId;
+
+    use super::*;
+
+    fn provider() -> SamlProvider {
+        SamlProvider {
+            id: NoId,
+            name: "entra".into(),
+            display_name: None,
+            enabled: true,
+            idp_metadata: String::new(),
+            email_attribute: "email".into(),
+            username_attribute: None,
+            first_name_attribute: "givenName".into(),
+            last_name_attribute: "surname".into(),
+            phone_attribute: None,
+            groups_attribute: Some("groups".into()),
+            create_groups: false,
+        }
+    }
+
+    fn attributes(values: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        values
+            .iter()
+            .map(|(name, values)| {
+                (
+                    (*name).to_string(),
+                    values.iter().map(ToString::to_string).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_saml_user_from_attributes() {
+        let provider = provider();
+        let user = SamlUser::from_attributes(
+            &provider,
+            Some("a1b2c3"),
+            &attributes(&[
+                ("email", &["hpotter@hogwart.edu.uk"]),
+                ("givenName", &[" Harry "]),
+                ("surname", &["", "Potter"]),
+                ("groups", &["gryffindor", "students", "gryffindor", " "]),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            user,
+            SamlUser {
+                email: "hpotter@hogwart.edu.uk".into(),
+                username: None,
+                first_name: Some("Harry".into()),
+                last_name: Some("Potter".into()),
+                phone: None,
+                groups: Some(vec!["gryffindor".into(), "students".into()]),
+            }
+        );
+
+        // email falls back to name ID
+        let user =
+            SamlUser::from_attributes(&provider, Some("hpotter@hogwart.edu.uk"), &attributes(&[]))
+                .unwrap();
+        assert_eq!(user.email, "hpotter@hogwart.edu.uk");
+        assert_eq!(user.groups, Some(Vec::new()));
+
+        assert!(matches!(
+            SamlUser::from_attributes(&provider, Some("a1b2c3"), &attributes(&[])),
+            Err(SamlError::MissingAttribute(_))
+        ));
+    }
+
+    #[test]
+    fn test_saml_user_group_mapping_disabled() {
+        let mut provider = provider();
+        provider.groups_attribute = None;
+        provider.username_attribute = Some("uid".into());
+        let user = SamlUser::from_attributes(
+            &provider,
+            None,
+            &attributes(&[
+                ("email", &["hpotter@hogwart.edu.uk"]),
+                ("uid", &["hpotter"]),
+                ("groups", &["gryffindor"]),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(user.username.as_deref(), Some("hpotter"));
+        assert_eq!(user.groups, None);
+    }
+}
@@ -1,4 +1,4 @@
-use axum::http::StatusCode;
+use axum::{extract::multipart::MultipartError, http::StatusCode};
 use defguard_common::db::models::{ModelError, settings::SettingsValidationError};
 use defguard_mail::templates::TemplateError;
 use sqlx::error::Error as SqlxError;
@@ -7,14 +7,19 @@ use tokio::sync::mpsc::error::SendError;
 use utoipa::ToSchema;
 
 use crate::{
-    auth::failed_login::FailedLoginError,
-    db::models::{device::DeviceError, enrollment::TokenError, wireguard::WireguardNetworkError},
+    auth::{failed_login::FailedLoginError, password_policy::PasswordPolicyError},
+    db::models::{
+        device::DeviceError, enrollment::TokenError, group::GroupQuotaError,
+        wireguard::WireguardNetworkError,
+    },
     enterprise::{
         activity_log_stream::error::ActivityLogStreamError, db::models::acl::AclError,
         firewall::FirewallError, ldap::error::LdapError, license::LicenseError,
+        provisioning::AccountCreationRefused, saml::SamlError,
     },
     events::ApiEvent,
     grpc::gateway::map::GatewayMapError,
+    sms::SmsError,
 };
 
 /// Represents kinds of error that occurred
@@ -28,6 +33,8 @@ pub enum WebError {
     WebauthnRegistration(String),
     #[error("Email MFA error: {0}")]
     EmailMfa(String),
+    #[error("SMS MFA error: {0}")]
+    SmsMfa(String),
     #[error("Incorrect username: {0}")]
     IncorrectUsername(String),
     #[error("Object not found: {0}")]
@@ -82,6 +89,12 @@ pub enum WebError {
     #[error("Activity log stream error: {0}")]
     #[schema(value_type=Object)]
     ActivityLogStreamError(#[from] ActivityLogStreamError),
+    #[error(transparent)]
+    #[schema(value_type=Object)]
+    PasswordPolicy(#[from] PasswordPolicyError),
+    #[error(transparent)]
+    #[schema(value_type=Object)]
+    AccountCreationRefused(#[from] AccountCreationRefused),
 }
 
 impl From<tonic::Status> for WebError {
@@ -96,12 +109,42 @@ impl From<StatusCode> for WebError {
     }
 }
 
+impl From<MultipartError> for WebError {
+    fn from(error: MultipartError) -> Self {
+        error!("Failed to read multipart request: {error}");
+        Self::Http(error.status())
+    }
+}
+
 impl From<LdapError> for WebError {
     fn from(error: LdapError) -> Self {
         Self::Ldap(error.to_string())
     }
 }
 
+impl From<SmsError> for WebError {
+    fn from(error: SmsError) -> Self {
+        Self::SmsMfa(error.to_string())
+    }
+}
+
+impl From<SamlError> for WebError {
+    fn from(error: SamlError) -> Self {
+        match error {
+            SamlError::InvalidMetadata(_) | SamlError::MissingSsoEndpoint => {
+                Self::BadRequest(error.to_string())
+            }
+            SamlError::InvalidResponse(_) | SamlError::MissingAttribute(_) => {
+                Self::Authorization(error.to_string())
+            }
+            SamlError::Request(_) => {
+                error!("{error}");
+                Self::Http(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
 impl From<SqlxError> for WebError {
     fn from(error: SqlxError) -> Self {
         Self::DbError(error.to_string())
@@ -120,11 +163,23 @@ impl From<DeviceError> for WebError {
             DeviceError::PubkeyConflict(..) => Self::PubkeyValidation(error.to_string()),
             DeviceError::DatabaseError(_) => Self::DbError(error.to_string()),
             DeviceError::NetworkIpAssignmentError(_) => Self::ModelError(error.to_string()),
+            DeviceError::LocationNotAllowed(_) => Self::Forbidden(error.to_string()),
             DeviceError::Unexpected(_) => Self::Http(StatusCode::INTERNAL_SERVER_ERROR),
         }
     }
 }
 
+impl From<GroupQuotaError> for WebError {
+    fn from(error: GroupQuotaError) -> Self {
+        match error {
+            GroupQuotaError::UsersExceeded(..) | GroupQuotaError::DevicesExceeded(..) => {
+                Self::BadRequest(error.to_string())
+            }
+            GroupQuotaError::DbError(_) => Self::DbError(error.to_string()),
+        }
+    }
+}
+
 impl From<GatewayMapError> for WebError {
     fn from(error: GatewayMapError) -> Self {
         match error {
@@ -182,9 +237,16 @@ impl From<TokenError> for WebError {
 impl From<SettingsValidationError> for WebError {
     fn from(err: SettingsValidationError) -> Self {
         match err {
-            SettingsValidationError::CannotEnableGatewayNotifications => {
-                Self::BadRequest(err.to_string())
-            }
+            SettingsValidationError::CannotEnableGatewayNotifications
+            | SettingsValidationError::InvalidPasswordHashParams
+            | SettingsValidationError::InvalidTimezone(_)
+            | SettingsValidationError::InvalidActivityLogRetention
+            | SettingsValidationError::InvalidLdapAttribute(_)
+            | SettingsValidationError::InvalidLdapGroupPattern(_)
+            | SettingsValidationError::InvalidTotpParams
+            | SettingsValidationError::InvalidPasswordPolicy
+            | SettingsValidationError::InvalidLoginLockout
+            | SettingsValidationError::InvalidCaptcha => Self::BadRequest(err.to_string()),
         }
     }
 }
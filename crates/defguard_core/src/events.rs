@@ -3,19 +3,31 @@ use std::net::IpAddr;
 use chrono::{NaiveDateTime, Utc};
 use defguard_common::db::{
     Id,
-    models::{AuthenticationKey, MFAMethod, Settings},
+    models::{AuthenticationKey, MFAMethod, MailTemplateKind, Settings},
 };
 use defguard_proto::proxy::MfaMethod;
 
 use crate::{
     db::{
         Device, Group, User, WebAuthn, WebHook, WireguardNetwork,
-        models::oauth2client::OAuth2Client,
+        models::{
+            device_profile::DeviceProfile,
+            gateway_token::GatewayToken,
+            group::{GroupQuota, GroupSyncAuthority},
+            impersonation::ImpersonationMode,
+            oauth2client::OAuth2Client,
+        },
     },
     enterprise::db::models::{
-        activity_log_stream::ActivityLogStream, api_tokens::ApiToken,
-        openid_provider::OpenIdProvider, snat::UserSnatBinding,
-    },
+        activity_log_stream::ActivityLogStream,
+        api_tokens::{ApiToken, ApiTokenScope},
+        openid_provider::OpenIdProvider,
+        posture_policy::PostureViolation,
+        provisioning_policy::{ProvisioningPolicy, ProvisioningSource},
+        saml_provider::SamlProvider,
+        snat::UserSnatBinding,
+    },
+    headers::current_request_id,
 };
 
 /// Shared context that needs to be added to every API event
@@ -30,6 +42,12 @@ pub struct ApiRequestContext {
     pub username: String,
     pub ip: IpAddr,
     pub device: String,
+    /// Identifier of the HTTP request, as sent in the `X-Request-Id` header.
+    pub request_id: Option<String>,
+    /// ID of the admin who made the request while impersonating the user.
+    pub impersonator_id: Option<Id>,
+    /// Username of the admin who made the request while impersonating the user.
+    pub impersonator: Option<String>,
 }
 
 impl ApiRequestContext {
@@ -42,6 +60,9 @@ impl ApiRequestContext {
             username,
             ip,
             device,
+            request_id: current_request_id(),
+            impersonator_id: None,
+            impersonator: None,
         }
     }
 }
@@ -90,6 +111,32 @@ pub enum ApiEventType {
         message: String,
     },
     UserLogout,
+    BreakGlassLogin,
+    AdminAccessDenied {
+        method: String,
+        path: String,
+        reason: String,
+    },
+    UserLockedOut {
+        failed_attempts: u32,
+        locked_until: NaiveDateTime,
+    },
+    LoginIpLockedOut {
+        ip_address: String,
+        failed_attempts: u32,
+        locked_until: NaiveDateTime,
+    },
+    UserUnlocked {
+        user: User<Id>,
+    },
+    LoginIpUnlocked {
+        ip_address: String,
+    },
+    RateLimitExceeded {
+        endpoint: String,
+        ip_address: String,
+        rejected_requests: u32,
+    },
     UserMfaLogin {
         mfa_method: MFAMethod,
     },
@@ -97,7 +144,12 @@ pub enum ApiEventType {
         mfa_method: MFAMethod,
         message: String,
     },
-    RecoveryCodeUsed,
+    RecoveryCodeUsed {
+        remaining: usize,
+    },
+    RecoveryCodesRegenerated {
+        count: usize,
+    },
     PasswordChangedByAdmin {
         user: User<Id>,
     },
@@ -105,6 +157,27 @@ pub enum ApiEventType {
     PasswordReset {
         user: User<Id>,
     },
+    UserSessionRevoked {
+        user: User<Id>,
+        ip_address: String,
+        device_info: Option<String>,
+    },
+    UserSessionsRevoked {
+        user: User<Id>,
+        count: u64,
+    },
+    ImpersonationStarted {
+        admin: User<Id>,
+        user: User<Id>,
+        mode: ImpersonationMode,
+        expires: NaiveDateTime,
+    },
+    ImpersonationEnded {
+        admin: User<Id>,
+        user: User<Id>,
+        mode: ImpersonationMode,
+        expired: bool,
+    },
     MfaDisabled,
     UserMfaDisabled {
         user: User<Id>,
@@ -113,6 +186,8 @@ pub enum ApiEventType {
     MfaTotpEnabled,
     MfaEmailDisabled,
     MfaEmailEnabled,
+    MfaSmsDisabled,
+    MfaSmsEnabled,
     MfaSecurityKeyAdded {
         key: WebAuthn<Id>,
     },
@@ -134,6 +209,18 @@ pub enum ApiEventType {
         before: Vec<String>,
         after: Vec<String>,
     },
+    UserProvisioned {
+        user: User<Id>,
+        source: ProvisioningSource,
+        groups: Vec<String>,
+        pending_approval: bool,
+    },
+    /// Login of a user without an account, refused by the provisioning policy.
+    AccountCreationRefused {
+        source: ProvisioningSource,
+        /// Email address or LDAP username of the user.
+        identity: String,
+    },
     UserDeviceAdded {
         owner: User<Id>,
         device: Device<Id>,
@@ -147,6 +234,16 @@ pub enum ApiEventType {
         before: Device<Id>,
         after: Device<Id>,
     },
+    DeviceApproved {
+        owner: User<Id>,
+        device: Device<Id>,
+        location: WireguardNetwork<Id>,
+    },
+    DeviceRejected {
+        owner: User<Id>,
+        device: Device<Id>,
+        location: WireguardNetwork<Id>,
+    },
     NetworkDeviceAdded {
         device: Device<Id>,
         location: WireguardNetwork<Id>,
@@ -160,6 +257,31 @@ pub enum ApiEventType {
         after: Device<Id>,
         location: WireguardNetwork<Id>,
     },
+    DeviceAddressReserved {
+        device: Device<Id>,
+        location: WireguardNetwork<Id>,
+        before: Vec<IpAddr>,
+        after: Vec<IpAddr>,
+    },
+    DeviceAddressReleased {
+        device: Device<Id>,
+        location: WireguardNetwork<Id>,
+        addresses: Vec<IpAddr>,
+    },
+    DeviceProfileAdded {
+        profile: DeviceProfile<Id>,
+    },
+    DeviceProfileModified {
+        before: DeviceProfile<Id>,
+        after: DeviceProfile<Id>,
+    },
+    DeviceProfileRemoved {
+        profile: DeviceProfile<Id>,
+    },
+    DeviceProfileAssigned {
+        device: Device<Id>,
+        profile: Option<DeviceProfile<Id>>,
+    },
     ActivityLogStreamCreated {
         stream: ActivityLogStream<Id>,
     },
@@ -180,6 +302,22 @@ pub enum ApiEventType {
         before: WireguardNetwork<Id>,
         after: WireguardNetwork<Id>,
     },
+    VpnLocationMaintenanceEnabled {
+        location: WireguardNetwork<Id>,
+        message: Option<String>,
+        disconnected: usize,
+    },
+    VpnLocationMaintenanceDisabled {
+        location: WireguardNetwork<Id>,
+    },
+    GatewayTokenAdded {
+        location: WireguardNetwork<Id>,
+        token: GatewayToken<Id>,
+    },
+    GatewayTokenRevoked {
+        location: WireguardNetwork<Id>,
+        token: GatewayToken<Id>,
+    },
     ApiTokenAdded {
         owner: User<Id>,
         token: ApiToken<Id>,
@@ -208,12 +346,30 @@ pub enum ApiEventType {
         app: OAuth2Client<Id>,
         enabled: bool,
     },
+    ClientCredentialsSet {
+        app: OAuth2Client<Id>,
+        service_account: User<Id>,
+        scopes: Vec<ApiTokenScope>,
+    },
+    ClientCredentialsRemoved {
+        app: OAuth2Client<Id>,
+    },
     OpenIdProviderModified {
         provider: OpenIdProvider<Id>,
     },
     OpenIdProviderRemoved {
         provider: OpenIdProvider<Id>,
     },
+    SamlProviderModified {
+        provider: SamlProvider<Id>,
+    },
+    SamlProviderRemoved {
+        provider: SamlProvider<Id>,
+    },
+    ProvisioningPolicyModified {
+        before: ProvisioningPolicy,
+        after: ProvisioningPolicy,
+    },
     SettingsUpdated {
         before: Settings,
         after: Settings,
@@ -223,6 +379,12 @@ pub enum ApiEventType {
         after: Settings,
     },
     SettingsDefaultBrandingRestored,
+    MailTemplateModified {
+        kind: MailTemplateKind,
+    },
+    MailTemplateReset {
+        kind: MailTemplateKind,
+    },
     GroupsBulkAssigned {
         users: Vec<User<Id>>,
         groups: Vec<Group<Id>>,
@@ -250,6 +412,26 @@ pub enum ApiEventType {
         added: Vec<User<Id>>,
         removed: Vec<User<Id>>,
     },
+    GroupQuotaModified {
+        group: Group<Id>,
+        quota: GroupQuota,
+    },
+    GroupSyncAuthorityModified {
+        group: Group<Id>,
+        sync_authority: Option<GroupSyncAuthority>,
+    },
+    GroupParentModified {
+        group: Group<Id>,
+        parent: Option<Group<Id>>,
+    },
+    GroupJoinRequestApproved {
+        group: Group<Id>,
+        user: User<Id>,
+    },
+    GroupJoinRequestRejected {
+        group: Group<Id>,
+        user: User<Id>,
+    },
     WebHookAdded {
         webhook: WebHook<Id>,
     },
@@ -278,6 +460,9 @@ pub enum ApiEventType {
     EnrollmentTokenAdded {
         user: User<Id>,
     },
+    EnrollmentTokenRevoked {
+        user: User<Id>,
+    },
     ClientConfigurationTokenAdded {
         user: User<Id>,
     },
@@ -297,6 +482,12 @@ pub enum ApiEventType {
         before: UserSnatBinding<Id>,
         after: UserSnatBinding<Id>,
     },
+    CustomEvent {
+        namespace: String,
+        name: String,
+        description: Option<String>,
+        data: Option<serde_json::Value>,
+    },
 }
 
 /// Events from Web API
@@ -325,6 +516,13 @@ pub enum GrpcEvent {
         location: WireguardNetwork<Id>,
         device: Device<Id>,
     },
+    /// Client has been disconnected since its owner connected more devices than allowed.
+    ClientSessionLimitExceeded {
+        context: GrpcRequestContext,
+        location: WireguardNetwork<Id>,
+        device: Device<Id>,
+        limit: i32,
+    },
 }
 
 /// Shared context for every event generated from a user request in the bi-directional gRPC stream.
@@ -368,6 +566,24 @@ pub enum BidiStreamEventType {
     Enrollment(Box<EnrollmentEvent>),
     PasswordReset(Box<PasswordResetEvent>),
     DesktopClientMfa(Box<DesktopClientMfaEvent>),
+    /// Client exceeded the rate limit of enrollment or password reset requests.
+    RateLimitExceeded {
+        endpoint: String,
+        ip_address: String,
+        rejected_requests: u32,
+    },
+    /// Account created for a user logging in through the OpenID provider via proxy.
+    UserProvisioned {
+        user: User<Id>,
+        source: ProvisioningSource,
+        groups: Vec<String>,
+        pending_approval: bool,
+    },
+    /// Login via proxy of a user without an account, refused by the provisioning policy.
+    AccountCreationRefused {
+        source: ProvisioningSource,
+        identity: String,
+    },
 }
 
 #[derive(Debug)]
@@ -375,6 +591,7 @@ pub enum EnrollmentEvent {
     EnrollmentStarted,
     EnrollmentDeviceAdded { device: Device<Id> },
     EnrollmentCompleted,
+    EnrollmentBlocked,
 }
 
 #[derive(Debug)]
@@ -382,6 +599,7 @@ pub enum PasswordResetEvent {
     PasswordResetRequested,
     PasswordResetStarted,
     PasswordResetCompleted,
+    PasswordResetBlocked,
 }
 
 pub type ClientMFAMethod = MfaMethod;
@@ -399,6 +617,30 @@ pub enum DesktopClientMfaEvent {
         method: ClientMFAMethod,
         message: String,
     },
+    LockedOut {
+        device: Device<Id>,
+        location: WireguardNetwork<Id>,
+        method: ClientMFAMethod,
+        attempts: u32,
+        locked_until: Option<NaiveDateTime>,
+    },
+    Timeout {
+        device: Device<Id>,
+        location: WireguardNetwork<Id>,
+        method: ClientMFAMethod,
+    },
+    PostureFailed {
+        device: Device<Id>,
+        location: WireguardNetwork<Id>,
+        method: ClientMFAMethod,
+        violations: Vec<PostureViolation>,
+    },
+    /// Device has been disconnected since its owner logged in on more devices than allowed.
+    SessionLimitExceeded {
+        device: Device<Id>,
+        location: WireguardNetwork<Id>,
+        limit: i32,
+    },
 }
 
 /// Shared context for every internally-triggered event.
@@ -427,6 +669,16 @@ impl InternalEventContext {
     }
 }
 
+/// Changes applied to Defguard objects by LDAP synchronization
+#[derive(Debug)]
+pub enum LdapSyncChange {
+    UserAdded { user: User<Id> },
+    UserRemoved { user: User<Id> },
+    UserModified { before: User<Id>, after: User<Id> },
+    GroupMemberAdded { group: Group<Id>, user: User<Id> },
+    GroupMemberRemoved { group: Group<Id>, user: User<Id> },
+}
+
 /// Events emmited by background threads, not triggered directly by users
 #[derive(Debug)]
 pub enum InternalEvent {
@@ -434,4 +686,30 @@ pub enum InternalEvent {
         context: InternalEventContext,
         location: WireguardNetwork<Id>,
     },
+    GroupMembershipExpired {
+        timestamp: NaiveDateTime,
+        group: Group<Id>,
+        user: User<Id>,
+    },
+    /// User has been disabled at the scheduled deactivation time.
+    UserDisabled {
+        timestamp: NaiveDateTime,
+        user: User<Id>,
+    },
+    /// Device has been removed from gateways at its expiry time.
+    DeviceExpired {
+        timestamp: NaiveDateTime,
+        owner: User<Id>,
+        device: Device<Id>,
+    },
+    ActivityLogPurged {
+        timestamp: NaiveDateTime,
+        removed: u64,
+        archived: bool,
+        threshold: NaiveDateTime,
+    },
+    LdapSync {
+        timestamp: NaiveDateTime,
+        change: LdapSyncChange,
+    },
 }
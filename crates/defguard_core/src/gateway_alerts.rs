@@ -0,0 +1,131 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use defguard_mail::Mail;
+use sqlx::PgPool;
+use tokio::{sync::mpsc::UnboundedSender, time::sleep};
+use uuid::Uuid;
+
+use crate::{
+    db::{AppEvent, GatewayDisconnectedData, models::gateway_alert::GatewayAlertRule},
+    grpc::gateway::{map::GatewayMap, state::GatewayState},
+    handlers::mail::send_gateway_disconnected_email,
+};
+
+// How long to sleep between loop iterations
+const GATEWAY_ALERT_LOOP_SLEEP: Duration = Duration::from_secs(60);
+
+/// Disconnection is identified by gateway and the moment it has disconnected,
+/// so each one is alerted on once.
+type Disconnection = (Uuid, NaiveDateTime);
+
+/// Fires configured notifications for a gateway disconnected for too long.
+async fn alert(
+    pool: &PgPool,
+    webhook_tx: &UnboundedSender<AppEvent>,
+    mail_tx: &UnboundedSender<Mail>,
+    rule: &GatewayAlertRule,
+    gateway: GatewayState,
+    disconnected_at: NaiveDateTime,
+) {
+    info!(
+        "Gateway {} of location {} has been disconnected for over {} minutes",
+        gateway.hostname, gateway.network_name, rule.threshold_minutes
+    );
+    if rule.notify_admins {
+        if let Err(err) = send_gateway_disconnected_email(
+            gateway.name.clone(),
+            gateway.network_name.clone(),
+            &gateway.hostname,
+            mail_tx,
+            pool,
+        )
+        .await
+        {
+            error!(
+                "Failed to send gateway disconnect alert for {}: {err}",
+                gateway.hostname
+            );
+        }
+    }
+    if rule.notify_webhooks {
+        let event = AppEvent::GatewayDisconnected(GatewayDisconnectedData {
+            location_id: gateway.network_id,
+            location_name: gateway.network_name,
+            gateway_name: gateway.name,
+            gateway_hostname: gateway.hostname,
+            disconnected_at,
+            threshold_minutes: rule.threshold_minutes,
+        });
+        if let Err(err) = webhook_tx.send(event) {
+            error!("Failed to trigger gateway disconnect webhooks: {err}");
+        }
+    }
+}
+
+/// Periodically checks gateways against per-location alert rules and notifies when a gateway
+/// stays disconnected longer than the configured threshold.
+#[instrument(skip_all)]
+pub async fn run_periodic_gateway_disconnect_alerts(
+    pool: PgPool,
+    gateway_state: Arc<Mutex<GatewayMap>>,
+    webhook_tx: UnboundedSender<AppEvent>,
+    mail_tx: UnboundedSender<Mail>,
+) -> Result<(), sqlx::Error> {
+    info!("Starting periodic gateway disconnect alerts");
+    let mut alerted: HashSet<Disconnection> = HashSet::new();
+
+    loop {
+        debug!("Checking gateway disconnect alert rules");
+        match GatewayAlertRule::all(&pool).await {
+            Ok(rules) => {
+                let gateways = gateway_state
+                    .lock()
+                    .expect("Failed to acquire lock on gateway state")
+                    .as_flattened();
+                let now = Utc::now().naive_utc();
+                let mut current = HashSet::new();
+                for rule in &rules {
+                    let threshold = TimeDelta::minutes(rule.threshold_minutes.into());
+                    let Some(location_gateways) = gateways.get(&rule.location_id) else {
+                        continue;
+                    };
+                    for gateway in location_gateways {
+                        if gateway.connected {
+                            continue;
+                        }
+                        let Some(disconnected_at) = gateway.disconnected_at else {
+                            continue;
+                        };
+                        let disconnection = (gateway.uid, disconnected_at);
+                        current.insert(disconnection);
+                        if now - disconnected_at < threshold || alerted.contains(&disconnection) {
+                            continue;
+                        }
+                        alert(
+                            &pool,
+                            &webhook_tx,
+                            &mail_tx,
+                            rule,
+                            gateway.clone(),
+                            disconnected_at,
+                        )
+                        .await;
+                        alerted.insert(disconnection);
+                    }
+                }
+                // forget disconnections which have ended
+                alerted.retain(|disconnection| current.contains(disconnection));
+            }
+            Err(err) => error!("Failed to fetch gateway alert rules: {err}"),
+        }
+
+        // wait till next iteration
+        debug!("Sleeping until next iteration");
+        sleep(GATEWAY_ALERT_LOOP_SLEEP).await;
+    }
+}
@@ -53,9 +53,18 @@ impl auth_service_server::AuthService for AuthServer {
         check_failed_logins(&self.failed_logins, &request.username)
             .map_err(|_| Status::resource_exhausted("too many login requests"))?;
 
-        if let Ok(Some(user)) = User::find_by_username(&self.pool, &request.username).await {
+        if let Ok(Some(mut user)) = User::find_by_username(&self.pool, &request.username).await {
             if user.verify_password(&request.password).is_ok() {
                 info!("Authentication successful for user {}", request.username);
+                if let Err(err) = user
+                    .upgrade_password_hash(&self.pool, &request.password)
+                    .await
+                {
+                    error!(
+                        "Failed to upgrade password hash for user {}: {err}",
+                        request.username
+                    );
+                }
                 Ok(Response::new(AuthenticateResponse {
                     token: Self::create_jwt(&request.username).map_err(|_| {
                         log_failed_login_attempt(&self.failed_logins, &request.username);
@@ -1,47 +1,77 @@
-use std::collections::HashMap;
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::Arc,
+};
 
-use chrono::Utc;
+use chrono::{NaiveDateTime, TimeDelta, Utc};
 use defguard_common::{
     auth::claims::{Claims, ClaimsType},
     db::{
         Id,
         models::{BiometricAuth, BiometricChallenge},
     },
+    diagnostics::MFA_FAILURES,
+    random::gen_alphanumeric,
 };
-use defguard_mail::Mail;
+use defguard_mail::{Mail, templates::SessionContext};
 use defguard_proto::proxy::{
     self, ClientMfaFinishRequest, ClientMfaFinishResponse, ClientMfaStartRequest,
     ClientMfaStartResponse, ClientMfaTokenValidationRequest, ClientMfaTokenValidationResponse,
     MfaMethod,
 };
-use sqlx::PgPool;
+use serde_json::json;
+use sqlx::{Error as SqlxError, PgPool};
 use thiserror::Error;
 use tokio::sync::{
     broadcast::Sender,
     mpsc::{UnboundedSender, error::SendError},
 };
 use tonic::{Code, Status};
+use webauthn_rs::prelude::{
+    AuthenticationResult, PasskeyAuthentication, PublicKeyCredential, Webauthn,
+};
 
 use crate::{
+    auth::email_mfa::check_email_mfa_resend,
     db::{
-        Device, GatewayEvent, User, UserInfo, WireguardNetwork,
+        Device, GatewayEvent, User, WebAuthn, WireguardNetwork,
         models::{
+            client_login_session::{ClientLoginSession, ClientMfaUserAttempts},
             device::{DeviceInfo, DeviceNetworkInfo, WireguardNetworkDevice},
-            wireguard::LocationMfaMode,
+            group::NetworkAccessWindow,
+            trusted_device::{DEVICE_TRUST_SECRET_LENGTH, TrustedDevice},
+            wireguard::{LocationMfaMethod, LocationMfaMode},
+        },
+    },
+    enterprise::{
+        db::models::{
+            openid_provider::OpenIdProvider,
+            posture_policy::{LocationPosturePolicy, PostureReport},
         },
+        is_business_license_active,
     },
-    enterprise::{db::models::openid_provider::OpenIdProvider, is_business_license_active},
     events::{BidiRequestContext, BidiStreamEvent, BidiStreamEventType, DesktopClientMfaEvent},
-    grpc::utils::parse_client_ip_agent,
-    handlers::mail::send_email_mfa_code_email,
+    grpc::{
+        client_version::parse_client_version_platform, gateway::send_wireguard_event,
+        session_limit, utils::parse_client_ip_agent,
+    },
+    handlers::mail::{send_client_mfa_locked_out_email, send_email_mfa_code_email},
 };
 
 const CLIENT_SESSION_TIMEOUT: u64 = 60 * 5; // 10 minutes
+const CLIENT_SESSION_LIFETIME: TimeDelta = TimeDelta::seconds(CLIENT_SESSION_TIMEOUT as i64);
+// rejected codes allowed within a single login session before it's terminated
+const MAX_SESSION_FAILED_ATTEMPTS: u32 = 3;
+// rejected codes allowed for a single user before desktop client MFA is blocked for them
+const MAX_USER_FAILED_ATTEMPTS: u32 = 10;
+const USER_LOCKOUT_DURATION: TimeDelta = TimeDelta::minutes(15);
 
 #[derive(Debug, Error)]
 pub enum ClientMfaServerError {
     #[error("gRPC event channel error: {0}")]
     BidiEventChannelError(#[from] SendError<BidiStreamEvent>),
+    #[error("Database error: {0}")]
+    DbError(#[from] SqlxError),
 }
 
 impl From<ClientMfaServerError> for Status {
@@ -50,22 +80,20 @@ impl From<ClientMfaServerError> for Status {
     }
 }
 
-#[derive(Clone)]
-pub(crate) struct ClientLoginSession {
-    pub(crate) method: MfaMethod,
+/// Login session along with the objects it refers to.
+pub(crate) struct ClientLogin {
+    pub(crate) session: ClientLoginSession,
     pub(crate) location: WireguardNetwork<Id>,
     pub(crate) device: Device<Id>,
     pub(crate) user: User<Id>,
-    pub(crate) openid_auth_completed: bool,
-    pub(crate) biometric_challenge: Option<BiometricChallenge>,
 }
 
 pub(crate) struct ClientMfaServer {
     pub(crate) pool: PgPool,
     mail_tx: UnboundedSender<Mail>,
-    wireguard_tx: Sender<GatewayEvent>,
-    pub(crate) sessions: HashMap<String, ClientLoginSession>,
+    pub(crate) wireguard_tx: Sender<GatewayEvent>,
     bidi_event_tx: UnboundedSender<BidiStreamEvent>,
+    webauthn: Arc<Webauthn>,
 }
 
 impl ClientMfaServer {
@@ -75,13 +103,14 @@ impl ClientMfaServer {
         mail_tx: UnboundedSender<Mail>,
         wireguard_tx: Sender<GatewayEvent>,
         bidi_event_tx: UnboundedSender<BidiStreamEvent>,
+        webauthn: Arc<Webauthn>,
     ) -> Self {
         Self {
             pool,
             mail_tx,
             wireguard_tx,
             bidi_event_tx,
-            sessions: HashMap::new(),
+            webauthn,
         }
     }
 
@@ -109,9 +138,225 @@ impl ClientMfaServer {
     }
 
     pub(crate) fn emit_event(&self, event: BidiStreamEvent) -> Result<(), ClientMfaServerError> {
+        if let BidiStreamEventType::DesktopClientMfa(mfa_event) = &event.event {
+            if let DesktopClientMfaEvent::Failed { method, .. } = &**mfa_event {
+                MFA_FAILURES.increment(&[
+                    ("source", "desktop_client"),
+                    ("method", method.as_str_name()),
+                ]);
+            }
+        }
         Ok(self.bidi_event_tx.send(event)?)
     }
 
+    /// Fetches the location, device and user of the login session. Returns `None` if any of
+    /// them has been removed in the meantime.
+    async fn load_login(
+        &self,
+        session: ClientLoginSession,
+    ) -> Result<Option<ClientLogin>, SqlxError> {
+        let Some(location) = WireguardNetwork::find_by_id(&self.pool, session.location_id).await?
+        else {
+            return Ok(None);
+        };
+        let Some(device) = Device::find_by_id(&self.pool, session.device_id).await? else {
+            return Ok(None);
+        };
+        let Some(user) = User::find_by_id(&self.pool, session.user_id).await? else {
+            return Ok(None);
+        };
+        Ok(Some(ClientLogin {
+            session,
+            location,
+            device,
+            user,
+        }))
+    }
+
+    /// Fetches login session of the device identified by `pubkey`.
+    pub(crate) async fn find_login(&self, pubkey: &str) -> Result<Option<ClientLogin>, Status> {
+        let login = match ClientLoginSession::find(&self.pool, pubkey).await {
+            Ok(Some(session)) => self.load_login(session).await,
+            Ok(None) => Ok(None),
+            Err(err) => Err(err),
+        };
+        login.map_err(|err| {
+            error!("Failed to fetch desktop client login session: {err}");
+            Status::internal("unexpected error")
+        })
+    }
+
+    /// Removes login session of the device identified by `pubkey`, if there is one.
+    pub(crate) async fn remove_login(&self, pubkey: &str) {
+        if let Err(err) = ClientLoginSession::delete(&self.pool, pubkey).await {
+            error!("Failed to remove desktop client login session: {err}");
+        }
+    }
+
+    /// Evicts login sessions which outlived `CLIENT_SESSION_TIMEOUT` without being finished.
+    ///
+    /// Emits a timeout event for every removed session and discards stale failed attempt counters.
+    pub(crate) async fn remove_expired_sessions(&self) {
+        let expired = match ClientLoginSession::delete_expired(&self.pool).await {
+            Ok(expired) => expired,
+            Err(err) => {
+                error!("Failed to remove expired desktop client login sessions: {err}");
+                return;
+            }
+        };
+        for session in expired {
+            let login = match self.load_login(session).await {
+                Ok(Some(login)) => login,
+                Ok(None) => continue,
+                Err(err) => {
+                    error!("Failed to fetch expired desktop client login session: {err}");
+                    continue;
+                }
+            };
+            debug!(
+                "Desktop client login session for device {} of user {} expired",
+                login.device.name, login.user.username
+            );
+            let context = BidiRequestContext::new(
+                login.user.id,
+                login.user.username.clone(),
+                IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                format!("{} (ID {})", login.device.name, login.device.id),
+            );
+            if let Err(err) = self.emit_event(BidiStreamEvent {
+                context,
+                event: BidiStreamEventType::DesktopClientMfa(Box::new(
+                    DesktopClientMfaEvent::Timeout {
+                        location: login.location,
+                        device: login.device,
+                        method: login.session.method,
+                    },
+                )),
+            }) {
+                error!("Failed to emit desktop client MFA timeout event: {err}");
+            }
+        }
+
+        if let Err(err) =
+            ClientMfaUserAttempts::delete_expired(&self.pool, USER_LOCKOUT_DURATION).await
+        {
+            error!("Failed to remove stale desktop client MFA attempt counters: {err}");
+        }
+    }
+
+    /// Returns the time until which given user is blocked from desktop client MFA, if any.
+    async fn user_locked_until(&self, user_id: Id) -> Result<Option<NaiveDateTime>, Status> {
+        let now = Utc::now().naive_utc();
+        let attempts = ClientMfaUserAttempts::find(&self.pool, user_id)
+            .await
+            .map_err(|err| {
+                error!("Failed to fetch desktop client MFA attempts of user {user_id}: {err}");
+                Status::internal("unexpected error")
+            })?;
+        Ok(attempts
+            .and_then(|attempts| attempts.locked_until)
+            .filter(|locked_until| *locked_until > now))
+    }
+
+    /// Records a rejected MFA code for the login session identified by `pubkey`.
+    ///
+    /// The session is terminated after `MAX_SESSION_FAILED_ATTEMPTS` and the user is blocked
+    /// for `USER_LOCKOUT_DURATION` after `MAX_USER_FAILED_ATTEMPTS`, in which case they're also
+    /// notified by email. Returns the status which should be sent back to the client.
+    async fn register_failed_attempt(
+        &self,
+        pubkey: &str,
+        user_id: Id,
+        ip: IpAddr,
+    ) -> Result<Status, ClientMfaServerError> {
+        let Some(session_attempts) =
+            ClientLoginSession::register_failed_attempt(&self.pool, pubkey).await?
+        else {
+            return Ok(Status::unauthenticated("unauthorized"));
+        };
+
+        let now = Utc::now().naive_utc();
+        let mut user_attempts = ClientMfaUserAttempts::find(&self.pool, user_id)
+            .await?
+            .filter(|attempts| !attempts.is_expired(now, USER_LOCKOUT_DURATION))
+            .unwrap_or_else(|| ClientMfaUserAttempts::new(user_id, now));
+        user_attempts.failed_attempts += 1;
+        if user_attempts.failed_attempts >= MAX_USER_FAILED_ATTEMPTS {
+            user_attempts.locked_until = Some(now + USER_LOCKOUT_DURATION);
+        }
+        user_attempts.save(&self.pool).await?;
+        let locked_until = user_attempts.locked_until;
+
+        if session_attempts < MAX_SESSION_FAILED_ATTEMPTS && locked_until.is_none() {
+            return Ok(Status::unauthenticated("unauthorized"));
+        }
+
+        let Some(session) = ClientLoginSession::find(&self.pool, pubkey).await? else {
+            return Ok(Status::unauthenticated("unauthorized"));
+        };
+        ClientLoginSession::delete(&self.pool, pubkey).await?;
+        let Some(ClientLogin {
+            session,
+            location,
+            device,
+            user,
+        }) = self.load_login(session).await?
+        else {
+            return Ok(Status::unauthenticated("unauthorized"));
+        };
+        if let Some(locked_until) = locked_until {
+            warn!(
+                "User {} exceeded the limit of failed desktop client MFA attempts, blocking \
+                further logins until {locked_until}",
+                user.username
+            );
+            // drop other pending logins of this user as well
+            ClientLoginSession::delete_for_user(&self.pool, user.id).await?;
+        } else {
+            warn!(
+                "Desktop client login session for device {} terminated after \
+                {session_attempts} failed MFA attempts",
+                device.name
+            );
+        }
+
+        self.emit_event(BidiStreamEvent {
+            context: BidiRequestContext::new(
+                user.id,
+                user.username.clone(),
+                ip,
+                format!("{} (ID {})", device.name, device.id),
+            ),
+            event: BidiStreamEventType::DesktopClientMfa(Box::new(
+                DesktopClientMfaEvent::LockedOut {
+                    location: location.clone(),
+                    device: device.clone(),
+                    method: session.method,
+                    attempts: session_attempts,
+                    locked_until,
+                },
+            )),
+        })?;
+        if let Some(locked_until) = locked_until {
+            let session = SessionContext {
+                ip_address: ip.to_string(),
+                device_info: None,
+            };
+            if let Err(err) = send_client_mfa_locked_out_email(
+                &user,
+                &device.name,
+                &location.name,
+                locked_until,
+                &self.mail_tx,
+                Some(&session),
+            ) {
+                error!("Failed to send client MFA lockout email: {err}");
+            }
+        }
+
+        Ok(Status::resource_exhausted("too many failed MFA attempts"))
+    }
+
     /// Allows proxy to verify if token is valid and active
     #[instrument(skip_all)]
     pub(crate) async fn validate_mfa_token(
@@ -119,16 +364,100 @@ impl ClientMfaServer {
         request: ClientMfaTokenValidationRequest,
     ) -> Result<ClientMfaTokenValidationResponse, Status> {
         let pubkey = Self::parse_token(&request.token)?;
-        let session_active = self.sessions.contains_key(&pubkey);
+        let session_active = ClientLoginSession::exists(&self.pool, &pubkey)
+            .await
+            .map_err(|err| {
+                error!("Failed to fetch desktop client login session: {err}");
+                Status::internal("unexpected error")
+            })?;
         Ok(ClientMfaTokenValidationResponse {
             token_valid: session_active,
         })
     }
 
+    /// Checks device posture reported by the client against posture policy of the location.
+    /// OS and client versions missing from the report are taken from device info.
+    ///
+    /// Devices which don't meet the policy are denied with a JSON list of violations as the
+    /// status message.
+    async fn check_device_posture(
+        &self,
+        location: &WireguardNetwork<Id>,
+        device: &Device<Id>,
+        user: &User<Id>,
+        method: MfaMethod,
+        posture: Option<&proxy::DevicePosture>,
+        info: &Option<proxy::DeviceInfo>,
+    ) -> Result<(), Status> {
+        if !is_business_license_active() {
+            return Ok(());
+        }
+        let policy = LocationPosturePolicy::find_by_location(&self.pool, location.id)
+            .await
+            .map_err(|err| {
+                error!("Failed to fetch posture policy for location {location}: {err}");
+                Status::internal("unexpected error")
+            })?;
+        let Some(policy) = policy else {
+            return Ok(());
+        };
+
+        let (version, platform) = parse_client_version_platform(info.as_ref());
+        let report = PostureReport {
+            os_version: posture
+                .and_then(|posture| posture.os_version.clone())
+                .or_else(|| {
+                    platform
+                        .map(|platform| platform.version)
+                        .filter(|version| !version.is_empty())
+                }),
+            disk_encrypted: posture.and_then(|posture| posture.disk_encrypted),
+            client_version: posture
+                .and_then(|posture| posture.client_version.clone())
+                .or_else(|| version.map(|version| version.to_string())),
+        };
+        let violations = policy.evaluate(&report);
+        if violations.is_empty() {
+            debug!("Device {device} meets posture policy of location {location}");
+            return Ok(());
+        }
+
+        warn!(
+            "Device {device} of user {} doesn't meet posture policy of location {location}: \
+            {violations:?}",
+            user.username
+        );
+        let message = json!({
+            "reason": "posture_check_failed",
+            "violations": violations,
+        })
+        .to_string();
+        let (ip, _user_agent) = parse_client_ip_agent(info).map_err(Status::internal)?;
+        self.emit_event(BidiStreamEvent {
+            context: BidiRequestContext::new(
+                user.id,
+                user.username.clone(),
+                ip,
+                format!("{} (ID {})", device.name, device.id),
+            ),
+            event: BidiStreamEventType::DesktopClientMfa(Box::new(
+                DesktopClientMfaEvent::PostureFailed {
+                    location: location.clone(),
+                    device: device.clone(),
+                    method,
+                    violations,
+                },
+            )),
+        })?;
+
+        Err(Status::permission_denied(message))
+    }
+
     #[instrument(skip_all)]
     pub async fn start_client_mfa_login(
         &mut self,
         request: ClientMfaStartRequest,
+        info: Option<proxy::DeviceInfo>,
     ) -> Result<ClientMfaStartResponse, Status> {
         debug!("Starting desktop client login: {request:?}");
         // fetch location
@@ -145,6 +474,22 @@ impl ClientMfaServer {
             return Err(Status::invalid_argument("MFA not enabled for location"));
         }
 
+        // reject new logins while the location is under maintenance
+        let maintenance = location.maintenance(&self.pool).await.map_err(|err| {
+            error!("Failed to fetch maintenance of location {location}: {err}");
+            Status::internal("unexpected error")
+        })?;
+        if maintenance.enabled {
+            warn!("Rejecting MFA login to location {location} under maintenance");
+            let message = match maintenance.message {
+                Some(message) => {
+                    format!("location {} is under maintenance: {message}", location.name)
+                }
+                None => format!("location {} is under maintenance", location.name),
+            };
+            return Err(Status::unavailable(message));
+        }
+
         // fetch device
         let Ok(Some(device)) = Device::find_by_pubkey(&self.pool, &request.pubkey).await else {
             error!("Failed to find device with pubkey {}", request.pubkey);
@@ -156,13 +501,27 @@ impl ClientMfaServer {
             error!("Failed to find user with ID {}", device.user_id);
             return Err(Status::invalid_argument("user not found"));
         };
-        let user_info = UserInfo::from_user(&self.pool, &user).await.map_err(|_| {
-            error!("Failed to fetch user info for {}", user.username);
-            Status::internal("unexpected error")
-        })?;
+
+        if !user.is_active {
+            warn!(
+                "Disabled user {} tried to start desktop client login",
+                user.username
+            );
+            return Err(Status::permission_denied("user is disabled"));
+        }
+
+        // reject logins of users who exceeded the limit of failed attempts
+        if let Some(locked_until) = self.user_locked_until(user.id).await? {
+            warn!(
+                "User {} tried to start desktop client login while blocked until {locked_until}",
+                user.username
+            );
+            return Err(Status::resource_exhausted("too many failed MFA attempts"));
+        }
 
         // validate user is allowed to connect to a given location
-        Self::validate_location_access(&self.pool, &location, &user_info).await?;
+        Self::validate_location_access(&self.pool, &location, &user).await?;
+        Self::validate_access_window(&self.pool, &location, &user).await?;
 
         user.verify_mfa_state(&self.pool).await.map_err(|err| {
             error!(
@@ -186,6 +545,7 @@ impl ClientMfaServer {
                 LocationMfaMode::Internal,
                 MfaMethod::Totp
                 | MfaMethod::Email
+                | MfaMethod::Webauthn
                 | MfaMethod::Biometric
                 | MfaMethod::MobileApprove,
             ) => {
@@ -207,7 +567,84 @@ impl ClientMfaServer {
             }
         }
 
+        // check if selected method is permitted by location MFA method policy
+        if let Some(method) = LocationMfaMethod::from_proto(selected_method) {
+            let allowed_methods =
+                location
+                    .allowed_mfa_methods(&self.pool)
+                    .await
+                    .map_err(|err| {
+                        error!(
+                            "Failed to fetch allowed MFA methods for location {location}: {err}"
+                        );
+                        Status::internal("unexpected error")
+                    })?;
+            if !allowed_methods.is_empty() && !allowed_methods.contains(&method) {
+                warn!(
+                    "User {} selected MFA method ({selected_method}) which is not allowed in \
+                    location {location}",
+                    user.username
+                );
+                let message = json!({
+                    "reason": "mfa_method_not_allowed",
+                    "allowed_methods": allowed_methods,
+                })
+                .to_string();
+                return Err(Status::permission_denied(message));
+            }
+        }
+
+        // deny devices which don't meet location requirements before sending any codes
+        self.check_device_posture(
+            &location,
+            &device,
+            &user,
+            selected_method,
+            request.posture.as_ref(),
+            &info,
+        )
+        .await?;
+
+        // devices trusted after a previous login are authorized without prompting for a code,
+        // as long as the client presents the secret it received when the trust was granted
+        if self
+            .is_device_trusted(&location, &device, request.trust_secret.as_deref())
+            .await?
+        {
+            let preshared_key = self.authorize_device(&device, &location).await?;
+            info!(
+                "Desktop client login for {} at location {location} skipped MFA for trusted \
+                device {device}",
+                user.username
+            );
+            let (ip, _user_agent) = parse_client_ip_agent(&info).map_err(Status::internal)?;
+            self.emit_event(BidiStreamEvent {
+                context: BidiRequestContext::new(
+                    user.id,
+                    user.username.clone(),
+                    ip,
+                    format!("{} (ID {})", device.name, device.id),
+                ),
+                event: BidiStreamEventType::DesktopClientMfa(Box::new(
+                    DesktopClientMfaEvent::Connected {
+                        location: location.clone(),
+                        device: device.clone(),
+                        method: selected_method,
+                    },
+                )),
+            })?;
+            self.enforce_session_limit(&user, &device, &location)
+                .await?;
+            return Ok(ClientMfaStartResponse {
+                token: Self::generate_token(&request.pubkey)?,
+                challenge: None,
+                preshared_key: Some(preshared_key),
+            });
+        }
+
         let mut selected_mobile_auth: Option<BiometricAuth<Id>> = None;
+        let mut passkey_authentication: Option<PasskeyAuthentication> = None;
+        let mut webauthn_challenge: Option<String> = None;
 
         // check if selected method is configured
         match selected_method {
@@ -242,6 +679,39 @@ impl ClientMfaServer {
                     ));
                 }
             }
+            MfaMethod::Webauthn => {
+                let passkeys = WebAuthn::passkeys_for_user(&self.pool, user.id)
+                    .await
+                    .map_err(|err| {
+                        error!(
+                            "Failed to fetch security keys for user {}: {err}",
+                            user.username
+                        );
+                        Status::internal("unexpected error")
+                    })?;
+                if passkeys.is_empty() {
+                    error!("No security keys registered for user {}", user.username);
+                    return Err(Status::invalid_argument(
+                        "selected MFA method not available",
+                    ));
+                }
+                let (challenge, authentication) = self
+                    .webauthn
+                    .start_passkey_authentication(&passkeys)
+                    .map_err(|err| {
+                        error!(
+                            "Failed to start WebAuthn authentication for user {}: {err}",
+                            user.username
+                        );
+                        Status::internal("unexpected error")
+                    })?;
+                // the client passes the challenge to `navigator.credentials.get()`
+                webauthn_challenge = Some(serde_json::to_string(&challenge).map_err(|err| {
+                    error!("Failed to serialize WebAuthn challenge: {err}");
+                    Status::internal("unexpected error")
+                })?);
+                passkey_authentication = Some(authentication);
+            }
             MfaMethod::Email => {
                 if !user.email_mfa_enabled {
                     error!("Email MFA not enabled for user {}", user.username);
@@ -249,14 +719,37 @@ impl ClientMfaServer {
                         "selected MFA method not available",
                     ));
                 }
+                // Starting the login again for the same device is a code resend request.
+                let resend = ClientLoginSession::find(&self.pool, &request.pubkey)
+                    .await
+                    .map_err(|err| {
+                        error!("Failed to fetch desktop client login session: {err}");
+                        Status::internal("unexpected error")
+                    })?
+                    .is_some_and(|session| session.method == MfaMethod::Email);
+                if resend {
+                    if check_email_mfa_resend(user.id).is_err() {
+                        return Err(Status::resource_exhausted("email code requested too soon"));
+                    }
+                    // rotate the secret, so codes sent before are no longer valid
+                    user.new_email_secret(&self.pool).await.map_err(|err| {
+                        error!(
+                            "Failed to rotate email MFA secret for user {}: {err}",
+                            user.username
+                        );
+                        Status::internal("unexpected error")
+                    })?;
+                }
                 // send email code
-                send_email_mfa_code_email(&user, &self.mail_tx, None).map_err(|err| {
-                    error!(
-                        "Failed to send email MFA code for user {}: {err}",
-                        user.username
-                    );
-                    Status::internal("unexpected error")
-                })?;
+                send_email_mfa_code_email(&self.pool, &user, &self.mail_tx, None)
+                    .await
+                    .map_err(|err| {
+                        error!(
+                            "Failed to send email MFA code for user {}: {err}",
+                            user.username
+                        );
+                        Status::internal("unexpected error")
+                    })?;
             }
             MfaMethod::Oidc => {
                 if !is_business_license_active() {
@@ -311,32 +804,96 @@ impl ClientMfaServer {
 
         let response_challenge = biometric_challenge
             .as_ref()
-            .map(|challenge| challenge.challenge.clone());
+            .map(|challenge| challenge.challenge.clone())
+            .or(webauthn_challenge);
 
         // store login session
-        self.sessions.insert(
+        let mut session = ClientLoginSession::new(
             request.pubkey,
-            ClientLoginSession {
-                method: selected_method,
-                location,
-                device,
-                user,
-                openid_auth_completed: false,
-                biometric_challenge,
-            },
+            selected_method,
+            location.id,
+            device.id,
+            user.id,
+            CLIENT_SESSION_LIFETIME,
         );
+        session.biometric_challenge = biometric_challenge;
+        session.passkey_authentication = passkey_authentication;
+        session.save(&self.pool).await.map_err(|err| {
+            error!("Failed to store desktop client login session: {err}");
+            Status::internal("unexpected error")
+        })?;
 
         Ok(ClientMfaStartResponse {
             token,
             challenge: response_challenge,
+            preshared_key: None,
         })
     }
 
+    /// Checks if the device is trusted in the location with given secret and the location still
+    /// allows it.
+    async fn is_device_trusted(
+        &self,
+        location: &WireguardNetwork<Id>,
+        device: &Device<Id>,
+        secret: Option<&str>,
+    ) -> Result<bool, Status> {
+        let Some(secret) = secret else {
+            return Ok(false);
+        };
+        let enabled = location
+            .mfa_device_trust_enabled(&self.pool)
+            .await
+            .map_err(|err| {
+                error!("Failed to fetch device trust policy of location {location}: {err}");
+                Status::internal("unexpected error")
+            })?;
+        if !enabled {
+            return Ok(false);
+        }
+        TrustedDevice::is_trusted(&self.pool, location.id, &device.wireguard_pubkey, secret)
+            .await
+            .map_err(|err| {
+                error!("Failed to check trust of device {device} in location {location}: {err}");
+                Status::internal("unexpected error")
+            })
+    }
+
+    /// Stores updated counters of the security key used for authentication.
+    async fn update_passkey_credentials(
+        &self,
+        user: &User<Id>,
+        auth_result: &AuthenticationResult,
+    ) -> Result<(), Status> {
+        let webauthns = WebAuthn::all_for_user(&self.pool, user.id)
+            .await
+            .map_err(|err| {
+                error!(
+                    "Failed to fetch security keys for user {}: {err}",
+                    user.username
+                );
+                Status::internal("unexpected error")
+            })?;
+        for mut webauthn in webauthns {
+            let updated = webauthn.update_credential(auth_result).map_err(|err| {
+                error!("Failed to update security key {}: {err}", webauthn.name);
+                Status::internal("unexpected error")
+            })?;
+            if updated {
+                webauthn.save(&self.pool).await.map_err(|err| {
+                    error!("Failed to update security key {}: {err}", webauthn.name);
+                    Status::internal("unexpected error")
+                })?;
+            }
+        }
+        Ok(())
+    }
+
     /// Checks if given user is allowed to access a location
     async fn validate_location_access(
         pool: &PgPool,
         location: &WireguardNetwork<Id>,
-        user_info: &UserInfo,
+        user: &User<Id>,
     ) -> Result<(), Status> {
         // acquire connection
         let mut conn = pool.acquire().await.map_err(|_| {
@@ -354,15 +911,22 @@ impl ClientMfaServer {
             })?;
         // if no groups are specified all users are allowed
         if let Some(groups) = allowed_groups {
-            // check if user belongs to one of allowed groups
+            // check if user belongs to one of allowed groups, including inherited ones
+            let user_groups = user
+                .effective_group_names(&mut *conn)
+                .await
+                .map_err(|err| {
+                    error!("Failed to fetch groups of user {}: {err}", user.username);
+                    Status::internal("unexpected error")
+                })?;
             if !groups
                 .iter()
-                .any(|allowed_group| user_info.groups.contains(allowed_group))
+                .any(|allowed_group| user_groups.contains(allowed_group))
             {
                 error!(
                     "User {} not allowed to connect to location {location} because he doesn't belong to any of the allowed groups.
                     User groups: {:?}, allowed groups: {:?}",
-                    user_info.username, user_info.groups, groups
+                    user.username, user_groups, groups
                 );
                 return Err(Status::unauthenticated("unauthorized"));
             }
@@ -370,6 +934,39 @@ impl ClientMfaServer {
         Ok(())
     }
 
+    /// Validate the current time falls within access windows of the user's allowed groups.
+    async fn validate_access_window(
+        pool: &PgPool,
+        location: &WireguardNetwork<Id>,
+        user: &User<Id>,
+    ) -> Result<(), Status> {
+        let mut conn = pool.acquire().await.map_err(|_| {
+            error!("Failed to acquire DB connection");
+            Status::internal("unexpected error")
+        })?;
+        let timezone = user.effective_timezone(&mut *conn).await.map_err(|err| {
+            error!("Failed to fetch timezone of user {}: {err}", user.username);
+            Status::internal("unexpected error")
+        })?;
+        let now = NetworkAccessWindow::local_now(timezone);
+        let allowed = location
+            .is_within_access_window(&mut conn, user, now)
+            .await
+            .map_err(|err| {
+                error!("Failed to check access windows of location {location}: {err}");
+                Status::internal("unexpected error")
+            })?;
+        if !allowed {
+            warn!(
+                "User {} not allowed to connect to location {location} outside of access windows \
+                of their groups at {now}",
+                user.username
+            );
+            return Err(Status::permission_denied("outside of access window"));
+        }
+        Ok(())
+    }
+
     #[instrument(skip_all)]
     pub async fn finish_client_mfa_login(
         &mut self,
@@ -381,17 +978,22 @@ impl ClientMfaServer {
         let pubkey = Self::parse_token(&request.token)?;
 
         // fetch login session
-        let Some(session) = self.sessions.get(&pubkey) else {
+        let Some(login) = self.find_login(&pubkey).await? else {
             error!("Client login session not found");
             return Err(Status::invalid_argument("login session not found"));
         };
-        let ClientLoginSession {
-            method,
-            device,
+        let ClientLogin {
+            session,
             location,
+            device,
             user,
+        } = &login;
+        let ClientLoginSession {
+            method,
             openid_auth_completed,
             biometric_challenge,
+            passkey_authentication,
+            ..
         } = session;
 
         // Prepare event context
@@ -403,6 +1005,39 @@ impl ClientMfaServer {
             format!("{} (ID {})", device.name, device.id),
         );
 
+        // user may have been disabled since the login was started
+        match User::find_by_id(&self.pool, user.id).await {
+            Ok(Some(current)) if current.is_active => {}
+            Ok(_) => {
+                warn!(
+                    "Disabled user {} tried to finish desktop client login",
+                    user.username
+                );
+                self.remove_login(&pubkey).await;
+                return Err(Status::permission_denied("user is disabled"));
+            }
+            Err(err) => {
+                error!("Failed to fetch user {}: {err}", user.username);
+                return Err(Status::internal("unexpected error"));
+            }
+        }
+
+        // device state may have changed since the login was started
+        if let Err(status) = self
+            .check_device_posture(
+                location,
+                device,
+                user,
+                *method,
+                request.posture.as_ref(),
+                &info,
+            )
+            .await
+        {
+            self.remove_login(&pubkey).await;
+            return Err(status);
+        }
+
         // validate code
         match method {
             MfaMethod::MobileApprove => {
@@ -443,7 +1078,7 @@ impl ClientMfaServer {
                                 },
                             )),
                         })?;
-                        return Err(Status::unauthenticated("unauthorized"));
+                        return Err(self.register_failed_attempt(&pubkey, user.id, ip).await?);
                     }
                 }
             }
@@ -478,7 +1113,60 @@ impl ClientMfaServer {
                                 },
                             )),
                         })?;
-                        return Err(Status::unauthenticated("unauthorized"));
+                        return Err(self.register_failed_attempt(&pubkey, user.id, ip).await?);
+                    }
+                }
+            }
+            MfaMethod::Webauthn => {
+                let authentication = passkey_authentication.as_ref().ok_or_else(|| {
+                    error!("WebAuthn authentication state not found in MFA session");
+                    Status::internal("unexpected error")
+                })?;
+                // signed assertion returned by `navigator.credentials.get()`
+                let Some(credential) = request.code.as_deref().and_then(|code| {
+                    serde_json::from_str::<PublicKeyCredential>(code)
+                        .inspect_err(|err| error!("Failed to parse WebAuthn assertion: {err}"))
+                        .ok()
+                }) else {
+                    self.emit_event(BidiStreamEvent {
+                        context,
+                        event: BidiStreamEventType::DesktopClientMfa(Box::new(
+                            DesktopClientMfaEvent::Failed {
+                                location: location.clone(),
+                                device: device.clone(),
+                                method: *method,
+                                message: "WebAuthn assertion not provided in request".to_string(),
+                            },
+                        )),
+                    })?;
+                    return Err(Status::invalid_argument("WebAuthn assertion not provided"));
+                };
+                match self
+                    .webauthn
+                    .finish_passkey_authentication(&credential, authentication)
+                {
+                    Ok(auth_result) => {
+                        if auth_result.needs_update() {
+                            self.update_passkey_credentials(user, &auth_result).await?;
+                        }
+                    }
+                    Err(err) => {
+                        error!(
+                            "WebAuthn verification for device {} failed: {err}",
+                            device.name
+                        );
+                        self.emit_event(BidiStreamEvent {
+                            context,
+                            event: BidiStreamEventType::DesktopClientMfa(Box::new(
+                                DesktopClientMfaEvent::Failed {
+                                    location: location.clone(),
+                                    device: device.clone(),
+                                    method: *method,
+                                    message: "WebAuthn assertion rejected".to_string(),
+                                },
+                            )),
+                        })?;
+                        return Err(self.register_failed_attempt(&pubkey, user.id, ip).await?);
                     }
                 }
             }
@@ -513,7 +1201,7 @@ impl ClientMfaServer {
                             },
                         )),
                     })?;
-                    return Err(Status::unauthenticated("unauthorized"));
+                    return Err(self.register_failed_attempt(&pubkey, user.id, ip).await?);
                 }
             }
             MfaMethod::Email => {
@@ -547,7 +1235,7 @@ impl ClientMfaServer {
                             },
                         )),
                     })?;
-                    return Err(Status::unauthenticated("unauthorized"));
+                    return Err(self.register_failed_attempt(&pubkey, user.id, ip).await?);
                 }
             }
             MfaMethod::Oidc => {
@@ -580,6 +1268,59 @@ impl ClientMfaServer {
             }
         }
 
+        let preshared_key = self.authorize_device(device, location).await?;
+        info!(
+            "Desktop client login finished for {} at location {} with method {}",
+            user.username,
+            location.name,
+            method.as_str_name()
+        );
+        self.emit_event(BidiStreamEvent {
+            context,
+            event: BidiStreamEventType::DesktopClientMfa(Box::new(
+                DesktopClientMfaEvent::Connected {
+                    location: location.clone(),
+                    device: device.clone(),
+                    method: *method,
+                },
+            )),
+        })?;
+        self.enforce_session_limit(user, device, location).await?;
+
+        // remember the device if requested by the user and allowed by the location
+        let trust_secret = match request.trust_device_hours.filter(|hours| *hours > 0) {
+            Some(hours) => self.trust_device(location, device, user, hours).await,
+            None => None,
+        };
+
+        let response = ClientMfaFinishResponse {
+            preshared_key,
+            token: match method {
+                MfaMethod::MobileApprove => Some(request.token.clone()),
+                _ => None,
+            },
+            trust_secret,
+        };
+
+        // remove login session and reset failed attempts counter
+        if let Err(err) = ClientMfaUserAttempts::delete(&self.pool, user.id).await {
+            error!(
+                "Failed to reset desktop client MFA attempts of user {}: {err}",
+                user.username
+            );
+        }
+        self.remove_login(&pubkey).await;
+
+        Ok(response)
+    }
+
+    /// Authorizes device in a location with a newly generated PSK and notifies gateways.
+    /// Returns the PSK which has to be passed to the client.
+    async fn authorize_device(
+        &self,
+        device: &Device<Id>,
+        location: &WireguardNetwork<Id>,
+    ) -> Result<String, Status> {
         // begin transaction
         let mut transaction = self.pool.begin().await.map_err(|_| {
             error!("Failed to begin transaction");
@@ -611,8 +1352,6 @@ impl ClientMfaServer {
                 Status::internal("unexpected error")
             })?;
 
-        // send gateway event
-        debug!("Sending `peer_create` message to gateway");
         let device_info = DeviceInfo {
             device: device.clone(),
             network_info: vec![DeviceNetworkInfo {
@@ -622,39 +1361,6 @@ impl ClientMfaServer {
                 is_authorized: network_device.is_authorized,
             }],
         };
-        let event = GatewayEvent::DeviceCreated(device_info);
-        self.wireguard_tx.send(event).map_err(|err| {
-            error!("Error sending WireGuard event: {err}");
-            Status::internal("unexpected error")
-        })?;
-
-        info!(
-            "Desktop client login finished for {} at location {} with method {}",
-            user.username,
-            location.name,
-            method.as_str_name()
-        );
-        self.emit_event(BidiStreamEvent {
-            context,
-            event: BidiStreamEventType::DesktopClientMfa(Box::new(
-                DesktopClientMfaEvent::Connected {
-                    location: location.clone(),
-                    device: device.clone(),
-                    method: *method,
-                },
-            )),
-        })?;
-
-        let response = ClientMfaFinishResponse {
-            preshared_key: key.public,
-            token: match method {
-                MfaMethod::MobileApprove => Some(request.token.clone()),
-                _ => None,
-            },
-        };
-
-        // remove login session from map
-        self.sessions.remove(&pubkey);
 
         // commit transaction
         transaction.commit().await.map_err(|_| {
@@ -662,6 +1368,96 @@ impl ClientMfaServer {
             Status::internal("unexpected error")
         })?;
 
-        Ok(response)
+        // send gateway event only once the authorization is stored in the database;
+        // delivery is best effort, gateways which miss it will pick it up on their next
+        // configuration sync, so it doesn't affect the login result
+        debug!("Sending `peer_create` message to gateway");
+        send_wireguard_event(GatewayEvent::DeviceCreated(device_info), &self.wireguard_tx);
+
+        Ok(key.public)
+    }
+
+    /// Disconnects the oldest sessions of the user if authorizing the device exceeded their
+    /// session limit. Failures are only logged, as the login itself has already succeeded.
+    async fn enforce_session_limit(
+        &self,
+        user: &User<Id>,
+        device: &Device<Id>,
+        location: &WireguardNetwork<Id>,
+    ) -> Result<(), ClientMfaServerError> {
+        let terminated = match session_limit::enforce_session_limit(
+            &self.pool,
+            &self.wireguard_tx,
+            user.id,
+            location.id,
+            device.id,
+        )
+        .await
+        {
+            Ok(terminated) => terminated,
+            Err(err) => {
+                error!(
+                    "Failed to enforce VPN session limit of user {}: {err}",
+                    user.username
+                );
+                return Ok(());
+            }
+        };
+        for session in terminated {
+            self.emit_event(BidiStreamEvent {
+                context: BidiRequestContext::new(
+                    user.id,
+                    user.username.clone(),
+                    session.ip,
+                    format!("{} (ID {})", session.device.name, session.device.id),
+                ),
+                event: BidiStreamEventType::DesktopClientMfa(Box::new(
+                    DesktopClientMfaEvent::SessionLimitExceeded {
+                        location: session.location,
+                        device: session.device,
+                        limit: session.limit,
+                    },
+                )),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Stores a trust record letting the device skip MFA in the location for given number of
+    /// hours. Returns the secret the client has to present to skip MFA, or `None` if the device
+    /// hasn't been trusted. Failures are only logged, as the login itself has already succeeded.
+    async fn trust_device(
+        &self,
+        location: &WireguardNetwork<Id>,
+        device: &Device<Id>,
+        user: &User<Id>,
+        hours: u32,
+    ) -> Option<String> {
+        match location.mfa_device_trust_enabled(&self.pool).await {
+            Ok(true) => {}
+            Ok(false) => {
+                debug!(
+                    "Location {location} doesn't allow trusting devices, ignoring trust request \
+                    for device {device}"
+                );
+                return None;
+            }
+            Err(err) => {
+                error!("Failed to fetch device trust policy of location {location}: {err}");
+                return None;
+            }
+        }
+        let secret = gen_alphanumeric(DEVICE_TRUST_SECRET_LENGTH);
+        let trusted_device =
+            TrustedDevice::new(location.id, device.wireguard_pubkey.clone(), &secret, hours);
+        if let Err(err) = trusted_device.save(&self.pool).await {
+            error!("Failed to store trust of device {device} in location {location}: {err}");
+            return None;
+        }
+        info!(
+            "Device {device} of user {} is trusted in location {location} until {}",
+            user.username, trusted_device.trusted_until
+        );
+        Some(secret)
     }
 }
@@ -1,4 +1,7 @@
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    net::{IpAddr, Ipv4Addr},
+};
 
 use defguard_common::{
     csv::AsCsv,
@@ -33,25 +36,27 @@ use crate::{
         models::{
             device::{DeviceConfig, DeviceInfo, DeviceType},
             enrollment::{ENROLLMENT_TOKEN_TYPE, Token, TokenError},
+            group::{GroupQuotaError, check_device_quota},
             polling_token::PollingToken,
             wireguard::{LocationMfaMode, ServiceLocationMode},
         },
     },
     enterprise::{
         db::models::{enterprise_settings::EnterpriseSettings, openid_provider::OpenIdProvider},
-        ldap::utils::ldap_add_user,
+        ldap::utils::{ldap_add_user, ldap_add_user_to_groups},
         limits::update_counts,
     },
     events::{BidiRequestContext, BidiStreamEvent, BidiStreamEventType, EnrollmentEvent},
     grpc::{
         client_version::ClientFeature,
-        utils::{build_device_config_response, new_polling_token, parse_client_ip_agent},
-    },
-    handlers::{
-        mail::{
-            send_email_mfa_activation_email, send_mfa_configured_email, send_new_device_added_email,
+        gateway::send_wireguard_event,
+        utils::{
+            build_device_config_response, check_captcha, check_new_password, new_polling_token,
+            parse_client_ip_agent, self_service_network_allowed,
         },
-        user::check_password_strength,
+    },
+    handlers::mail::{
+        send_email_mfa_activation_email, send_mfa_configured_email, send_new_device_added_email,
     },
     headers::get_device_info,
     is_valid_phone_number, server_config,
@@ -111,9 +116,7 @@ impl EnrollmentServer {
 
     /// Sends given `GatewayEvent` to be handled by gateway GRPC server
     pub fn send_wireguard_event(&self, event: GatewayEvent) {
-        if let Err(err) = self.wireguard_tx.send(event) {
-            error!("Error sending WireGuard event {err}");
-        }
+        send_wireguard_event(event, &self.wireguard_tx);
     }
 
     // Send event to the dedicated bidi stream event channel
@@ -137,6 +140,7 @@ impl EnrollmentServer {
         info: Option<defguard_proto::proxy::DeviceInfo>,
     ) -> Result<EnrollmentStartResponse, Status> {
         debug!("Starting enrollment session, request: {request:?}");
+        check_captcha(&info, request.captcha_token.as_deref()).await?;
         // fetch enrollment token
         debug!("Try to find an enrollment token {}.", request.token);
         let mut enrollment = Token::find_by_id(&self.pool, &request.token).await?;
@@ -162,6 +166,25 @@ impl EnrollmentServer {
                 );
                 return Err(Status::permission_denied("user is disabled"));
             }
+
+            if !self_service_network_allowed(&self.pool, &info).await? {
+                warn!(
+                    "Blocked enrollment start for user {} from a network outside of the allowed ones",
+                    user.username
+                );
+                // client address may be missing in blocked requests
+                let (ip, user_agent) = parse_client_ip_agent(&info)
+                    .unwrap_or_else(|_| (IpAddr::V4(Ipv4Addr::UNSPECIFIED), String::new()));
+                let context = BidiRequestContext::new(user.id, user.username, ip, user_agent);
+                self.emit_event(context, EnrollmentEvent::EnrollmentBlocked)
+                    .map_err(|err| {
+                        error!("Failed to send event. Reason: {err}",);
+                        Status::internal("unexpected error")
+                    })?;
+                return Err(Status::permission_denied(
+                    "enrollment is only allowed from the corporate network or VPN",
+                ));
+            }
             info!(
                 "User {}({:?}) is active, proceeding with enrollment",
                 user.username, user.id
@@ -382,14 +405,6 @@ impl EnrollmentServer {
         }
         debug!("IP address {ip_address}, device info {device_info:?}");
 
-        // check if password is strong enough
-        debug!("Verifying password strength for user activation process.");
-        if let Err(err) = check_password_strength(&request.password) {
-            error!("Password not strong enough: {err}");
-            return Err(Status::invalid_argument("password not strong enough"));
-        }
-        debug!("Password is strong enough to complete the user activation process.");
-
         // fetch related users
         let mut user = enrollment.fetch_user(&self.pool).await?;
         debug!(
@@ -412,6 +427,11 @@ impl EnrollmentServer {
         }
         debug!("User is active.");
 
+        // check if password meets the password policy
+        debug!("Verifying password strength for user activation process.");
+        check_new_password(&self.pool, &request.password, &user).await?;
+        debug!("Password is strong enough to complete the user activation process.");
+
         let mut transaction = self.pool.begin().await.map_err(|err| {
             error!("Failed to begin transaction: {err}");
             Status::internal("unexpected error")
@@ -425,9 +445,58 @@ impl EnrollmentServer {
             error!("Failed to update user {}: {err}", user.username);
             Status::internal("unexpected error")
         })?;
+        user.remember_password(&mut transaction)
+            .await
+            .map_err(|err| {
+                error!(
+                    "Failed to store password history of user {}: {err}",
+                    user.username
+                );
+                Status::internal("unexpected error")
+            })?;
         debug!("Updating user details ended with success.");
         let _ = update_counts(&self.pool).await;
 
+        // assign groups and locations chosen by the admin who created the token
+        let assigned_groups = enrollment.assign_user(&mut transaction, &user).await?;
+        for group in &assigned_groups {
+            group
+                .check_quota(&mut *transaction)
+                .await
+                .map_err(|err| match err {
+                    GroupQuotaError::DbError(err) => {
+                        error!(
+                            "Failed to check quota of group {} for user {}: {err}",
+                            group.name, user.username
+                        );
+                        Status::internal("unexpected error")
+                    }
+                    _ => {
+                        warn!(
+                            "User {} can't be added to group {}: {err}",
+                            user.username, group.name
+                        );
+                        Status::resource_exhausted(err.to_string())
+                    }
+                })?;
+        }
+        if !assigned_groups.is_empty() {
+            info!(
+                "Added user {} to groups assigned to the enrollment token: {:?}",
+                user.username,
+                assigned_groups
+                    .iter()
+                    .map(|group| group.name.as_str())
+                    .collect::<Vec<_>>()
+            );
+            WireguardNetwork::sync_all_networks(&mut transaction, &self.wireguard_tx)
+                .await
+                .map_err(|err| {
+                    error!("Failed to sync networks: {err}");
+                    Status::internal("unexpected error")
+                })?;
+        }
+
         debug!("Retriving settings to send welcome email...");
         let settings = Settings::get_current_settings();
         debug!("Settings successfully retrieved.");
@@ -478,6 +547,13 @@ impl EnrollmentServer {
         })?;
 
         ldap_add_user(&mut user, Some(&request.password), &self.pool).await;
+        if !assigned_groups.is_empty() {
+            let group_names = assigned_groups
+                .iter()
+                .map(|group| group.name.as_str())
+                .collect::<HashSet<_>>();
+            ldap_add_user_to_groups(&user, group_names, &self.pool).await;
+        }
 
         info!("User {} activated", user.username);
 
@@ -687,6 +763,24 @@ impl EnrollmentServer {
                 "Creating new device for user {}({:?}): {}.",
                 user.username, user.id, request.name
             );
+            check_device_quota(&mut *transaction, user.id)
+                .await
+                .map_err(|err| match err {
+                    GroupQuotaError::DbError(err) => {
+                        error!(
+                            "Failed to check device quota for user {}({:?}): {err}",
+                            user.username, user.id
+                        );
+                        Status::internal("unexpected error")
+                    }
+                    _ => {
+                        warn!(
+                            "User {}({:?}) can't add device {}: {err}",
+                            user.username, user.id, request.name
+                        );
+                        Status::resource_exhausted(err.to_string())
+                    }
+                })?;
             let device = Device::new(
                 request.name.clone(),
                 request.pubkey.clone(),
@@ -1138,6 +1232,7 @@ impl Token {
             content: self
                 .get_welcome_email_content(&mut *transaction, ip_address, device_info)
                 .await?,
+            plain_content: None,
             attachments: Vec::new(),
             result_tx: None,
         };
@@ -1174,6 +1269,7 @@ impl Token {
                 ip_address,
                 device_info,
             )?,
+            plain_content: None,
             attachments: Vec::new(),
             result_tx: None,
         };
@@ -0,0 +1,370 @@
+//! Fan-out of gateway events between core instances sharing the same database.
+//!
+//! Events sent on the local channel are passed to gateways connected to this instance and
+//! published in the `gateway_event_broadcast` table. A trigger announces every published event
+//! with `NOTIFY`, so that other instances can fetch it and pass it to their gateways.
+//! Published events are kept for a few minutes only, long enough for instances which briefly
+//! lost the database connection to catch up.
+
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use defguard_common::db::Id;
+use defguard_proto::{enterprise::firewall::FirewallConfig, gateway::Peer};
+use prost::Message;
+use sqlx::{
+    Error as SqlxError, PgExecutor, PgPool, postgres::PgListener, query, query_as, query_scalar,
+};
+use thiserror::Error;
+use tokio::{
+    sync::broadcast::{Receiver, Sender, error::RecvError},
+    time::{interval, sleep},
+};
+use uuid::Uuid;
+
+use super::send_wireguard_event;
+use crate::db::{GatewayEvent, WireguardNetwork, models::device::DeviceInfo};
+
+// channel used by the `notify_gateway_event()` trigger
+const NOTIFY_CHANNEL: &str = "gateway_event";
+const RETENTION: TimeDelta = TimeDelta::minutes(5);
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Error)]
+pub enum GatewayEventBroadcastError {
+    #[error(transparent)]
+    DbError(#[from] SqlxError),
+    #[error("Failed to serialize gateway event: {0}")]
+    SerializationError(#[from] serde_cbor::Error),
+    #[error("Failed to decode gateway event: {0}")]
+    DecodeError(#[from] prost::DecodeError),
+    #[error("Local gateway event channel closed")]
+    ChannelClosed,
+}
+
+/// Network along with its private key, which is skipped by the regular serialization.
+#[derive(Deserialize, Serialize)]
+struct SharedNetwork {
+    network: WireguardNetwork<Id>,
+    prvkey: String,
+}
+
+impl From<&WireguardNetwork<Id>> for SharedNetwork {
+    fn from(network: &WireguardNetwork<Id>) -> Self {
+        Self {
+            network: network.clone(),
+            prvkey: network.prvkey.clone(),
+        }
+    }
+}
+
+impl From<SharedNetwork> for WireguardNetwork<Id> {
+    fn from(shared: SharedNetwork) -> Self {
+        Self {
+            prvkey: shared.prvkey,
+            ..shared.network
+        }
+    }
+}
+
+/// Device along with its preshared keys, which are skipped by the regular serialization.
+#[derive(Deserialize, Serialize)]
+struct SharedDevice {
+    info: DeviceInfo,
+    preshared_keys: Vec<Option<String>>,
+}
+
+impl From<&DeviceInfo> for SharedDevice {
+    fn from(info: &DeviceInfo) -> Self {
+        Self {
+            info: info.clone(),
+            preshared_keys: info
+                .network_info
+                .iter()
+                .map(|network_info| network_info.preshared_key.clone())
+                .collect(),
+        }
+    }
+}
+
+impl From<SharedDevice> for DeviceInfo {
+    fn from(shared: SharedDevice) -> Self {
+        let mut info = shared.info;
+        for (network_info, preshared_key) in info.network_info.iter_mut().zip(shared.preshared_keys)
+        {
+            network_info.preshared_key = preshared_key;
+        }
+        info
+    }
+}
+
+/// Serializable form of [`GatewayEvent`]. Proto messages are stored encoded.
+#[derive(Deserialize, Serialize)]
+enum SharedEvent {
+    NetworkCreated(Id, SharedNetwork),
+    NetworkModified(Id, SharedNetwork, Vec<Vec<u8>>, Option<Vec<u8>>),
+    NetworkDeleted(Id, String),
+    DeviceCreated(SharedDevice),
+    DeviceModified(SharedDevice),
+    DeviceDeleted(SharedDevice),
+    FirewallConfigChanged(Id, Vec<u8>),
+    FirewallDisabled(Id),
+    GatewayTokenRevoked(Id, Id),
+}
+
+impl From<&GatewayEvent> for SharedEvent {
+    fn from(event: &GatewayEvent) -> Self {
+        match event {
+            GatewayEvent::NetworkCreated(id, network) => Self::NetworkCreated(*id, network.into()),
+            GatewayEvent::NetworkModified(id, network, peers, firewall_config) => {
+                Self::NetworkModified(
+                    *id,
+                    network.into(),
+                    peers.iter().map(Message::encode_to_vec).collect(),
+                    firewall_config.as_ref().map(Message::encode_to_vec),
+                )
+            }
+            GatewayEvent::NetworkDeleted(id, name) => Self::NetworkDeleted(*id, name.clone()),
+            GatewayEvent::DeviceCreated(info) => Self::DeviceCreated(info.into()),
+            GatewayEvent::DeviceModified(info) => Self::DeviceModified(info.into()),
+            GatewayEvent::DeviceDeleted(info) => Self::DeviceDeleted(info.into()),
+            GatewayEvent::FirewallConfigChanged(id, firewall_config) => {
+                Self::FirewallConfigChanged(*id, firewall_config.encode_to_vec())
+            }
+            GatewayEvent::FirewallDisabled(id) => Self::FirewallDisabled(*id),
+            GatewayEvent::GatewayTokenRevoked(location_id, token_id) => {
+                Self::GatewayTokenRevoked(*location_id, *token_id)
+            }
+        }
+    }
+}
+
+impl TryFrom<SharedEvent> for GatewayEvent {
+    type Error = prost::DecodeError;
+
+    fn try_from(event: SharedEvent) -> Result<Self, Self::Error> {
+        let event = match event {
+            SharedEvent::NetworkCreated(id, network) => Self::NetworkCreated(id, network.into()),
+            SharedEvent::NetworkModified(id, network, peers, firewall_config) => {
+                Self::NetworkModified(
+                    id,
+                    network.into(),
+                    peers
+                        .iter()
+                        .map(|peer| Peer::decode(peer.as_slice()))
+                        .collect::<Result<_, _>>()?,
+                    firewall_config
+                        .map(|config| FirewallConfig::decode(config.as_slice()))
+                        .transpose()?,
+                )
+            }
+            SharedEvent::NetworkDeleted(id, name) => Self::NetworkDeleted(id, name),
+            SharedEvent::DeviceCreated(device) => Self::DeviceCreated(device.into()),
+            SharedEvent::DeviceModified(device) => Self::DeviceModified(device.into()),
+            SharedEvent::DeviceDeleted(device) => Self::DeviceDeleted(device.into()),
+            SharedEvent::FirewallConfigChanged(id, firewall_config) => {
+                Self::FirewallConfigChanged(id, FirewallConfig::decode(firewall_config.as_slice())?)
+            }
+            SharedEvent::FirewallDisabled(id) => Self::FirewallDisabled(id),
+            SharedEvent::GatewayTokenRevoked(location_id, token_id) => {
+                Self::GatewayTokenRevoked(location_id, token_id)
+            }
+        };
+        Ok(event)
+    }
+}
+
+struct PublishedEvent {
+    id: Id,
+    payload: Vec<u8>,
+}
+
+impl PublishedEvent {
+    fn decode(&self) -> Result<GatewayEvent, GatewayEventBroadcastError> {
+        let event: SharedEvent = serde_cbor::from_slice(&self.payload)?;
+        Ok(event.try_into()?)
+    }
+
+    /// Events published by other instances after the one with given ID.
+    async fn all_after<'e, E>(executor: E, origin: Uuid, after: Id) -> Result<Vec<Self>, SqlxError>
+    where
+        E: PgExecutor<'e>,
+    {
+        query_as!(
+            Self,
+            "SELECT id, payload FROM gateway_event_broadcast \
+            WHERE id > $1 AND origin <> $2 ORDER BY id",
+            after,
+            origin
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
+
+async fn publish(
+    pool: &PgPool,
+    origin: Uuid,
+    event: &GatewayEvent,
+) -> Result<(), GatewayEventBroadcastError> {
+    let payload = serde_cbor::to_vec(&SharedEvent::from(event))?;
+    query!(
+        "INSERT INTO gateway_event_broadcast (origin, payload) VALUES ($1, $2)",
+        origin,
+        payload
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Fetches an event announced by `NOTIFY`; returns `None` for events published by this instance.
+async fn fetch(pool: &PgPool, origin: Uuid, id: Id) -> Result<Option<PublishedEvent>, SqlxError> {
+    query_as!(
+        PublishedEvent,
+        "SELECT id, payload FROM gateway_event_broadcast WHERE id = $1 AND origin <> $2",
+        id,
+        origin
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+async fn remove_old_events(pool: &PgPool) -> Result<(), SqlxError> {
+    let threshold = (Utc::now() - RETENTION).naive_utc();
+    query!(
+        "DELETE FROM gateway_event_broadcast WHERE created_at < $1",
+        threshold
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Passes events from the local channel to gateways connected to this instance and publishes
+/// them for other instances, while passing events published by other instances to local gateways.
+///
+/// `local_rx` has to be subscribed to the channel all other components send gateway events to,
+/// and gateway gRPC server has to subscribe to `gateway_tx`.
+#[instrument(skip_all)]
+pub async fn run_gateway_event_broadcast(
+    pool: PgPool,
+    mut local_rx: Receiver<GatewayEvent>,
+    gateway_tx: Sender<GatewayEvent>,
+) -> Result<(), GatewayEventBroadcastError> {
+    let origin = Uuid::new_v4();
+    let mut listener = PgListener::connect_with(&pool).await?;
+    listener.listen(NOTIFY_CHANNEL).await?;
+    let mut last_id =
+        query_scalar!("SELECT COALESCE(MAX(id), 0) \"id!\" FROM gateway_event_broadcast")
+            .fetch_one(&pool)
+            .await?;
+    let mut cleanup = interval(CLEANUP_INTERVAL);
+    info!("Sharing gateway events with other core instances as {origin}");
+
+    loop {
+        tokio::select! {
+            event = local_rx.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(
+                            "Gateway event broadcast lagged behind by {skipped} events, they \
+                            will be applied on next configuration sync of gateways"
+                        );
+                        continue;
+                    }
+                    Err(RecvError::Closed) => {
+                        return Err(GatewayEventBroadcastError::ChannelClosed);
+                    }
+                };
+                if let Err(err) = publish(&pool, origin, &event).await {
+                    error!("Failed to publish gateway event to other core instances: {err}");
+                }
+                send_wireguard_event(event, &gateway_tx);
+            }
+            notification = listener.try_recv() => {
+                let published = match notification {
+                    Ok(Some(notification)) => {
+                        let Ok(id) = notification.payload().parse::<Id>() else {
+                            warn!("Ignoring invalid gateway event notification: {notification:?}");
+                            continue;
+                        };
+                        last_id = last_id.max(id);
+                        fetch(&pool, origin, id).await.map(|event| event.into_iter().collect())
+                    }
+                    // notifications sent while the connection was down are lost
+                    Ok(None) => {
+                        warn!(
+                            "Lost connection while listening for gateway events of other core \
+                            instances, fetching events published in the meantime"
+                        );
+                        let published = PublishedEvent::all_after(&pool, origin, last_id).await;
+                        if let Ok(events) = &published {
+                            last_id = events.last().map_or(last_id, |event| event.id.max(last_id));
+                        }
+                        published
+                    }
+                    Err(err) => {
+                        error!(
+                            "Failed to listen for gateway events of other core instances: {err}"
+                        );
+                        sleep(RECONNECT_DELAY).await;
+                        continue;
+                    }
+                };
+                let published = match published {
+                    Ok(published) => published,
+                    Err(err) => {
+                        error!("Failed to fetch gateway events of other core instances: {err}");
+                        continue;
+                    }
+                };
+                for event in published {
+                    match event.decode() {
+                        Ok(event) => send_wireguard_event(event, &gateway_tx),
+                        Err(err) => error!("Failed to decode gateway event {}: {err}", event.id),
+                    }
+                }
+            }
+            _ = cleanup.tick() => {
+                if let Err(err) = remove_old_events(&pool).await {
+                    error!("Failed to remove old gateway events: {err}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_event_keeps_secrets() {
+        let network = WireguardNetwork {
+            prvkey: "private".into(),
+            ..Default::default()
+        };
+        let event = GatewayEvent::NetworkCreated(network.id, network);
+        let payload = serde_cbor::to_vec(&SharedEvent::from(&event)).unwrap();
+        let shared: SharedEvent = serde_cbor::from_slice(&payload).unwrap();
+        match GatewayEvent::try_from(shared).unwrap() {
+            GatewayEvent::NetworkCreated(_, network) => assert_eq!(network.prvkey, "private"),
+            event => panic!("Unexpected event: {event:?}"),
+        }
+
+        let firewall_config = FirewallConfig {
+            default_policy: 1,
+            ..Default::default()
+        };
+        let event = GatewayEvent::FirewallConfigChanged(1, firewall_config.clone());
+        let payload = serde_cbor::to_vec(&SharedEvent::from(&event)).unwrap();
+        let shared: SharedEvent = serde_cbor::from_slice(&payload).unwrap();
+        match GatewayEvent::try_from(shared).unwrap() {
+            GatewayEvent::FirewallConfigChanged(1, config) => assert_eq!(config, firewall_config),
+            event => panic!("Unexpected event: {event:?}"),
+        }
+    }
+}
@@ -26,6 +26,14 @@ impl From<ClientMapError> for Status {
     }
 }
 
+/// Latest heartbeat received from a desktop client through the proxy
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ClientHeartbeat {
+    pub received_at: NaiveDateTime,
+    // connection state reported by the client
+    pub connected: bool,
+}
+
 /// Represents current information about a connected VPN client
 #[derive(Debug, Serialize, Clone)]
 pub struct ClientState {
@@ -41,6 +49,8 @@ pub struct ClientState {
     pub total_upload: i64,
     // total bytes received from peer
     pub total_download: i64,
+    // latest heartbeat, only available for clients which send them
+    pub heartbeat: Option<ClientHeartbeat>,
 }
 
 impl ClientState {
@@ -63,6 +73,7 @@ impl ClientState {
             latest_update,
             total_upload,
             total_download,
+            heartbeat: None,
         }
     }
 
@@ -81,6 +92,30 @@ impl ClientState {
         self.total_upload = upload;
         self.total_download = download;
     }
+
+    /// Returns the time at which the client should be considered disconnected
+    /// or `None` if the client is still active.
+    ///
+    /// Clients sending heartbeats are kept connected even if the peer is idle and stops
+    /// handshaking. Once the heartbeats stop (e.g. the client crashed) or the client reports
+    /// it has disconnected, the session ends at the time of the latest heartbeat.
+    /// Other clients are disconnected based on their latest handshake only.
+    fn disconnected_at(
+        &self,
+        now: NaiveDateTime,
+        disconnect_threshold: TimeDelta,
+    ) -> Option<NaiveDateTime> {
+        match self.heartbeat {
+            Some(heartbeat)
+                if !heartbeat.connected || now - heartbeat.received_at > disconnect_threshold =>
+            {
+                Some(heartbeat.received_at)
+            }
+            Some(_) => None,
+            None if now - self.latest_handshake > disconnect_threshold => Some(now),
+            None => None,
+        }
+    }
 }
 
 /// Helper struct used to handle connected VPN clients state
@@ -152,9 +187,38 @@ impl ClientMap {
         Ok(())
     }
 
+    /// Stores a heartbeat received from an already connected VPN client.
+    ///
+    /// Heartbeats only affect the state of existing sessions, new sessions are started
+    /// based on gateway stats.
+    pub fn record_heartbeat(
+        &mut self,
+        location_id: Id,
+        public_key: &str,
+        connected: bool,
+    ) -> Result<(), ClientMapError> {
+        let client_state = self
+            .get_vpn_client(location_id, public_key)
+            .ok_or_else(|| ClientMapError::ClientNotFound {
+                public_key: public_key.to_string(),
+                location_id,
+            })?;
+        debug!(
+            "Received heartbeat from VPN client {public_key} connected to location \
+            {location_id}, reported state: connected={connected}"
+        );
+        client_state.heartbeat = Some(ClientHeartbeat {
+            received_at: Utc::now().naive_utc(),
+            connected,
+        });
+
+        Ok(())
+    }
+
     /// Removes all disconnected clients for a given location.
     ///
     /// A client is considered disconnected if there have not been any stats received for it in more than `peer_disconnect_threshold_secs`.
+    /// For clients sending heartbeats the latest heartbeat is used instead.
     ///
     /// Returns a list of devices.
     pub fn disconnect_inactive_vpn_clients_for_location(
@@ -177,9 +241,9 @@ impl ClientMap {
             // remove clients which have been inactive longer than given location's `peer_disconnect_threshold`
             location_map.retain(|public_key, client_state| {
                 let now = Utc::now().naive_utc();
-                if (now - client_state.latest_handshake) > disconnect_threshold {
-                	debug!("VPN client's {public_key} ({}, ID {}) latest handshake ({}) was more than {peer_disconnect_threshold_secs} seconds ago. Marking VPN client as disconnected", client_state.device.name, client_state.device.id, client_state.latest_handshake);
-                    let disconnect_event_context = GrpcRequestContext::new(
+                if let Some(disconnected_at) = client_state.disconnected_at(now, disconnect_threshold) {
+                	debug!("VPN client {public_key} ({}, ID {}) is no longer active (latest handshake: {}, latest heartbeat: {:?}). Marking VPN client as disconnected at {disconnected_at}", client_state.device.name, client_state.device.id, client_state.latest_handshake, client_state.heartbeat);
+                    let mut disconnect_event_context = GrpcRequestContext::new(
                         client_state.user_id,
                         client_state.username.clone(),
                         client_state.endpoint.ip(),
@@ -187,6 +251,7 @@ impl ClientMap {
                         client_state.device.name.clone(),
                         location.clone()
                     );
+                    disconnect_event_context.timestamp = disconnected_at;
                     disconnected_clients
                         .push((client_state.device.clone(), disconnect_event_context));
 
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 use defguard_common::db::Id;
 use defguard_mail::Mail;
 use defguard_version::tracing::VersionInfo;
@@ -10,7 +10,8 @@ use thiserror::Error;
 use tokio::sync::mpsc::UnboundedSender;
 use uuid::Uuid;
 
-use super::state::GatewayState;
+use super::state::{GatewayConnectionEventKind, GatewayState};
+use crate::db::models::gateway_token::GatewayToken;
 
 /// Helper struct used to handle gateway state. Gateways are grouped by network.
 type GatewayHostname = String;
@@ -52,15 +53,21 @@ impl GatewayMap {
         network_name: &str,
         hostname: String,
         name: Option<String>,
+        token: Option<&GatewayToken<Id>>,
         mail_tx: UnboundedSender<Mail>,
         version: Version,
     ) {
         info!("Adding gateway {hostname} with to gateway map for network {network_id}",);
-        let gateway_state =
+        let mut gateway_state =
             GatewayState::new(network_id, network_name, &hostname, name, mail_tx, version);
+        gateway_state.set_token(token);
 
         if let Some(network_gateway_map) = self.0.get_mut(&network_id) {
-            network_gateway_map.entry(hostname).or_insert(gateway_state);
+            // gateway may reconnect using a different token
+            network_gateway_map
+                .entry(hostname)
+                .and_modify(|state| state.set_token(token))
+                .or_insert(gateway_state);
         } else {
             // no map for a given network exists yet
             let mut network_gateway_map = HashMap::new();
@@ -119,9 +126,11 @@ impl GatewayMap {
                 // check if a gateway is reconnecting to avoid sending notifications on initial
                 // connection
                 let is_reconnecting = state.disconnected_at.is_some();
+                let now = Utc::now().naive_utc();
                 state.connected = true;
                 state.disconnected_at = None;
-                state.connected_at = Some(Utc::now().naive_utc());
+                state.connected_at = Some(now);
+                state.record_connection_event(GatewayConnectionEventKind::Connected, now);
                 state.cancel_pending_disconnect_notification();
                 if is_reconnecting {
                     state.handle_reconnect_notification(pool);
@@ -153,8 +162,10 @@ impl GatewayMap {
         debug!("Disconnecting gateway {hostname} in network {network_id}");
         if let Some(network_gateway_map) = self.0.get_mut(&network_id) {
             if let Some(state) = network_gateway_map.get_mut(&hostname) {
+                let now = Utc::now().naive_utc();
                 state.connected = false;
-                state.disconnected_at = Some(Utc::now().naive_utc());
+                state.disconnected_at = Some(now);
+                state.record_connection_event(GatewayConnectionEventKind::Disconnected, now);
                 state.handle_disconnect_notification(pool);
                 debug!("Gateway {hostname} found in gateway map, current state: {state:?}");
                 info!("Gateway {hostname} disconnected in network {network_id}");
@@ -166,6 +177,28 @@ impl GatewayMap {
         Err(err)
     }
 
+    /// Store peer handshake reported by a gateway if it's more recent than the current one.
+    pub(crate) fn record_handshake(
+        &mut self,
+        network_id: Id,
+        hostname: &str,
+        handshake: NaiveDateTime,
+    ) {
+        let Some(state) = self
+            .0
+            .get_mut(&network_id)
+            .and_then(|network_gateway_map| network_gateway_map.get_mut(hostname))
+        else {
+            return;
+        };
+        if state
+            .last_handshake
+            .is_none_or(|last_handshake| last_handshake < handshake)
+        {
+            state.last_handshake = Some(handshake);
+        }
+    }
+
     /// Return `true` if at least one gateway in a given network is connected.
     #[must_use]
     pub(crate) fn connected(&self, network_id: Id) -> bool {
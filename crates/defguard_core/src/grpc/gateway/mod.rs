@@ -7,7 +7,10 @@ use std::{
 
 use chrono::{DateTime, TimeDelta, Utc};
 use client_state::ClientMap;
-use defguard_common::db::{Id, NoId};
+use defguard_common::{
+    db::{Id, NoId},
+    diagnostics::{GATEWAY_EVENT_QUEUES, QueueStats},
+};
 use defguard_mail::Mail;
 use defguard_proto::{
     enterprise::firewall::FirewallConfig,
@@ -22,37 +25,49 @@ use sqlx::{Error as SqlxError, PgExecutor, PgPool, query};
 use thiserror::Error;
 use tokio::{
     sync::{
-        broadcast::{Receiver as BroadcastReceiver, Sender},
+        broadcast::{Receiver as BroadcastReceiver, Sender, error::RecvError},
         mpsc::{self, Receiver, UnboundedSender, error::SendError},
     },
     task::JoinHandle,
-    time::{Duration, interval},
+    time::{Duration, Instant, interval},
 };
 use tokio_stream::Stream;
 use tonic::{Code, Request, Response, Status, metadata::MetadataMap};
 
 use self::map::GatewayMap;
+use super::session_limit;
 use crate::{
     db::{
-        Device, GatewayEvent, User,
-        models::{wireguard::WireguardNetwork, wireguard_peer_stats::WireguardPeerStats},
+        AppEvent, Device, GatewayEvent, User,
+        models::{
+            device_approval::DeviceApproval, device_profile::DeviceProfile,
+            gateway_token::GatewayToken, vpn_session::VpnSession, wireguard::WireguardNetwork,
+            wireguard_peer_stats::WireguardPeerStats,
+        },
     },
     events::{GrpcEvent, GrpcRequestContext},
 };
 
+pub mod broadcast;
 pub mod client_state;
 pub mod map;
-pub(crate) mod state;
+pub mod state;
 
 const PEER_DISCONNECT_INTERVAL: u64 = 60;
 
 /// Sends given `GatewayEvent` to be handled by gateway GRPC server
 ///
 /// If you want to use it inside the API context, use [`crate::AppState::send_wireguard_event`] instead
+///
+/// Delivery to gateways is best effort: if no gateway is currently connected the event is dropped,
+/// since every gateway fetches full configuration from the database when it (re)connects.
 pub fn send_wireguard_event(event: GatewayEvent, wg_tx: &Sender<GatewayEvent>) {
-    debug!("Sending the following WireGuard event to the gateway: {event:?}");
+    debug!("Sending the following WireGuard event to the gateway: {event}");
     if let Err(err) = wg_tx.send(event) {
-        error!("Error sending WireGuard event {err}");
+        debug!(
+            "No gateway is connected, WireGuard event will be applied on next configuration sync: {}",
+            err.0
+        );
     }
 }
 
@@ -86,8 +101,12 @@ pub struct GatewayServer {
     gateway_state: Arc<Mutex<GatewayMap>>,
     client_state: Arc<Mutex<ClientMap>>,
     wireguard_tx: Sender<GatewayEvent>,
+    // update streams subscribe to this channel, which differs from `wireguard_tx` if events are
+    // shared with other core instances
+    gateway_events_tx: Sender<GatewayEvent>,
     mail_tx: UnboundedSender<Mail>,
     grpc_event_tx: UnboundedSender<GrpcEvent>,
+    webhook_tx: UnboundedSender<AppEvent>,
 }
 
 impl WireguardNetwork<Id> {
@@ -95,6 +114,8 @@ impl WireguardNetwork<Id> {
     ///
     /// Each device is marked as allowed or not allowed in a given network,
     /// which enables enforcing peer disconnect in MFA-protected networks.
+    /// Devices whose profile doesn't allow this location are skipped, as well as devices
+    /// which haven't been approved by an admin.
     ///
     /// If the location is a service location, only returns peers if enterprise features are enabled.
     pub async fn get_peers<'e, E>(&self, executor: E) -> Result<Vec<Peer>, SqlxError>
@@ -117,13 +138,26 @@ impl WireguardNetwork<Id> {
                 ARRAY(
                     SELECT host(ip)
                     FROM unnest(wnd.wireguard_ips) AS ip
-                ) \"allowed_ips!: Vec<String>\" \
+                ) \"allowed_ips!: Vec<String>\", \
+                p.keepalive_interval \"keepalive_interval?\", \
+                n.psk_rotation_interval IS NOT NULL \"psk_rotation_enabled!\" \
             FROM wireguard_network_device wnd \
+            JOIN wireguard_network n ON wnd.wireguard_network_id = n.id \
             JOIN device d ON wnd.device_id = d.id \
             JOIN \"user\" u ON d.user_id = u.id \
+            LEFT JOIN device_profile_device dpd ON d.id = dpd.device_id \
+            LEFT JOIN device_profile p ON dpd.profile_id = p.id \
             WHERE wireguard_network_id = $1 AND (is_authorized = true OR NOT $2) \
             AND d.configured = true \
             AND u.is_active = true \
+            AND (p.id IS NULL OR NOT p.restrict_locations OR EXISTS ( \
+                SELECT 1 FROM device_profile_location dpl \
+                WHERE dpl.profile_id = p.id AND dpl.location_id = $1 \
+            )) \
+            AND NOT EXISTS ( \
+                SELECT 1 FROM device_approval da \
+                WHERE da.device_id = d.id AND da.location_id = $1 AND da.status <> 'approved' \
+            ) \
             ORDER BY d.id ASC",
             self.id,
             self.mfa_enabled()
@@ -140,13 +174,17 @@ impl WireguardNetwork<Id> {
                 allowed_ips: row.allowed_ips,
                 // Don't send preshared key if MFA is not enabled, it can't be used and may
                 // cause issues with clients connecting if they expect no preshared key
-                // e.g. when you disable MFA on a location
-                preshared_key: if self.mfa_enabled() {
+                // e.g. when you disable MFA on a location. Locations without MFA use preshared
+                // keys only when they are rotated periodically.
+                preshared_key: if self.mfa_enabled() || row.psk_rotation_enabled {
                     row.preshared_key
                 } else {
                     None
                 },
-                keepalive_interval: Some(self.keepalive_interval as u32),
+                // device profile may override location keepalive
+                keepalive_interval: Some(
+                    row.keepalive_interval.unwrap_or(self.keepalive_interval) as u32
+                ),
             })
             .collect();
 
@@ -157,6 +195,8 @@ impl WireguardNetwork<Id> {
 /// Utility struct encapsulating commonly extracted metadata fields during gRPC communication.
 struct GatewayMetadata {
     network_id: Id,
+    // `None` for tokens issued before multiple tokens per location were introduced
+    token_id: Option<Id>,
     hostname: String,
     version: Version,
     // info: String,
@@ -170,16 +210,20 @@ impl GatewayServer {
         gateway_state: Arc<Mutex<GatewayMap>>,
         client_state: Arc<Mutex<ClientMap>>,
         wireguard_tx: Sender<GatewayEvent>,
+        gateway_events_tx: Sender<GatewayEvent>,
         mail_tx: UnboundedSender<Mail>,
         grpc_event_tx: UnboundedSender<GrpcEvent>,
+        webhook_tx: UnboundedSender<AppEvent>,
     ) -> Self {
         Self {
             pool,
             gateway_state,
             client_state,
             wireguard_tx,
+            gateway_events_tx,
             mail_tx,
             grpc_event_tx,
+            webhook_tx,
         }
     }
 
@@ -205,6 +249,45 @@ impl GatewayServer {
         None
     }
 
+    // parse gateway token id from request metadata intercepted from JWT token
+    fn get_token_id(metadata: &MetadataMap) -> Result<Option<Id>, Status> {
+        metadata
+            .get("gateway_token_id")
+            .map(|ascii_value| {
+                ascii_value
+                    .to_str()
+                    .ok()
+                    .and_then(|slice| slice.parse::<Id>().ok())
+                    .ok_or_else(|| Status::new(Code::Internal, "Invalid gateway token ID"))
+            })
+            .transpose()
+    }
+
+    /// Check if the gateway token hasn't been revoked.
+    async fn verify_token(
+        &self,
+        network_id: Id,
+        token_id: Option<Id>,
+    ) -> Result<Option<GatewayToken<Id>>, Status> {
+        let Some(token_id) = token_id else {
+            return Ok(None);
+        };
+        match GatewayToken::find_for_location(&self.pool, network_id, token_id).await {
+            Ok(Some(token)) => Ok(Some(token)),
+            Ok(None) => {
+                warn!("Gateway used revoked token {token_id} for network {network_id}");
+                Err(Status::unauthenticated("Gateway token has been revoked"))
+            }
+            Err(err) => {
+                error!("Failed to fetch gateway token {token_id}: {err}");
+                Err(Status::new(
+                    Code::Internal,
+                    "Failed to retrieve gateway token",
+                ))
+            }
+        }
+    }
+
     // extract gateway hostname from request headers
     fn get_gateway_hostname(metadata: &MetadataMap) -> Result<String, Status> {
         match metadata.get("hostname") {
@@ -236,9 +319,87 @@ impl GatewayServer {
     }
 
     fn emit_event(&self, event: GrpcEvent) -> Result<(), GatewayServerError> {
+        self.trigger_client_webhooks(&event);
         Ok(self.grpc_event_tx.send(event)?)
     }
 
+    /// Trigger webhooks subscribed to VPN client connection events.
+    fn trigger_client_webhooks(&self, event: &GrpcEvent) {
+        let app_event = match event {
+            GrpcEvent::ClientConnected { context, .. } => AppEvent::ClientConnected(context.into()),
+            GrpcEvent::ClientDisconnected { context, .. } => {
+                AppEvent::ClientDisconnected(context.into())
+            }
+            _ => return,
+        };
+        if let Err(err) = self.webhook_tx.send(app_event) {
+            error!("Failed to trigger VPN client connection webhooks: {err}");
+        }
+    }
+
+    /// Record the end of VPN session of a disconnected client.
+    ///
+    /// Errors are only logged, so that session history doesn't affect stats processing.
+    async fn finish_vpn_session(
+        &self,
+        device: &Device<Id>,
+        location: &WireguardNetwork<Id>,
+        context: &GrpcRequestContext,
+    ) {
+        if let Err(err) =
+            VpnSession::finish(&self.pool, device.id, location.id, context.timestamp).await
+        {
+            error!(
+                "Failed to finish VPN session of device {} in location {}: {err}",
+                device.name, location.name
+            );
+        }
+    }
+
+    /// Disconnect the oldest sessions of the user if connecting the device exceeded their limit.
+    async fn enforce_session_limit(
+        &self,
+        user: &User<Id>,
+        device: &Device<Id>,
+        location: &WireguardNetwork<Id>,
+    ) -> Result<(), GatewayServerError> {
+        let terminated = match session_limit::enforce_session_limit(
+            &self.pool,
+            &self.wireguard_tx,
+            user.id,
+            location.id,
+            device.id,
+        )
+        .await
+        {
+            Ok(terminated) => terminated,
+            Err(err) => {
+                error!(
+                    "Failed to enforce VPN session limit of user {}: {err}",
+                    user.username
+                );
+                return Ok(());
+            }
+        };
+        for session in terminated {
+            let context = GrpcRequestContext::new(
+                user.id,
+                user.username.clone(),
+                session.ip,
+                session.device.id,
+                session.device.name.clone(),
+                session.location.clone(),
+            );
+            self.emit_event(GrpcEvent::ClientSessionLimitExceeded {
+                context,
+                location: session.location,
+                device: session.device,
+                limit: session.limit,
+            })?;
+        }
+        Ok(())
+    }
+
     /// Helper method to fetch `Device` info from DB by pubkey and return appropriate errors
     async fn fetch_device_from_db(&self, public_key: &str) -> Result<Option<Device<Id>>, Status> {
         let device = Device::find_by_pubkey(&self.pool, public_key)
@@ -311,6 +472,7 @@ impl GatewayServer {
         let (version, _info) = version_info_from_metadata(metadata);
         Ok(GatewayMetadata {
             network_id: Self::get_network_id(metadata)?,
+            token_id: Self::get_token_id(metadata)?,
             hostname: Self::get_gateway_hostname(metadata)?,
             version,
         })
@@ -357,26 +519,35 @@ impl WireguardPeerStats {
 /// Helper struct for handling gateway events
 struct GatewayUpdatesHandler {
     network_id: Id,
+    token_id: Option<Id>,
     network: WireguardNetwork<Id>,
     gateway_hostname: String,
     events_rx: BroadcastReceiver<GatewayEvent>,
+    queue: Arc<QueueStats>,
     tx: mpsc::Sender<Result<Update, Status>>,
+    pool: PgPool,
 }
 
 impl GatewayUpdatesHandler {
     pub fn new(
         network_id: Id,
+        token_id: Option<Id>,
         network: WireguardNetwork<Id>,
         gateway_hostname: String,
         events_rx: BroadcastReceiver<GatewayEvent>,
         tx: mpsc::Sender<Result<Update, Status>>,
+        pool: PgPool,
     ) -> Self {
+        let queue = GATEWAY_EVENT_QUEUES.gateway(network_id, &gateway_hostname);
         Self {
             network_id,
+            token_id,
             network,
             gateway_hostname,
             events_rx,
+            queue,
             tx,
+            pool,
         }
     }
 
@@ -389,8 +560,32 @@ impl GatewayUpdatesHandler {
             "Starting update stream to gateway: {}, network {}",
             self.gateway_hostname, self.network
         );
-        while let Ok(update) = self.events_rx.recv().await {
-            debug!("Received WireGuard update: {update:?}");
+        loop {
+            let update = match self.events_rx.recv().await {
+                Ok(update) => update,
+                Err(RecvError::Lagged(skipped)) => {
+                    self.queue.record_dropped(skipped);
+                    // Some events were dropped because this gateway couldn't keep up.
+                    // Instead of closing the stream, resend full network configuration
+                    // so the gateway ends up in the same state as the database.
+                    warn!(
+                        "Update stream to gateway {}, network {} lagged behind by {skipped} events, resynchronizing network configuration",
+                        self.gateway_hostname, self.network
+                    );
+                    if self.send_full_network_update().await.is_err() {
+                        error!(
+                            "Closing update stream to gateway: {}, network {}",
+                            self.gateway_hostname, self.network
+                        );
+                        break;
+                    }
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            debug!("Received WireGuard update: {update}");
+            self.queue.record_depth(self.events_rx.len());
+            let started = Instant::now();
             let result = match update {
                 GatewayEvent::NetworkCreated(network_id, network) => {
                     if network_id == self.network_id {
@@ -439,6 +634,22 @@ impl GatewayUpdatesHandler {
                                 );
                                 continue;
                             }
+                            if !self.peer_approved(device.device.id).await {
+                                debug!(
+                                    "Created WireGuard device {} is waiting for approval in location {}",
+                                    device.device.name, self.network.name
+                                );
+                                continue;
+                            }
+                            let Some(keepalive_interval) =
+                                self.peer_keepalive_interval(device.device.id).await
+                            else {
+                                debug!(
+                                    "Profile of created WireGuard device {} doesn't allow location {}",
+                                    device.device.name, self.network.name
+                                );
+                                continue;
+                            };
                             self.send_peer_update(
                                 Peer {
                                     pubkey: device.device.wireguard_pubkey,
@@ -448,9 +659,7 @@ impl GatewayUpdatesHandler {
                                         .map(IpAddr::to_string)
                                         .collect(),
                                     preshared_key: network_info.preshared_key.clone(),
-                                    keepalive_interval: Some(
-                                        self.network.keepalive_interval as u32,
-                                    ),
+                                    keepalive_interval: Some(keepalive_interval),
                                 },
                                 0,
                             )
@@ -474,22 +683,35 @@ impl GatewayUpdatesHandler {
                                 );
                                 continue;
                             }
-                            self.send_peer_update(
-                                Peer {
-                                    pubkey: device.device.wireguard_pubkey,
-                                    allowed_ips: network_info
-                                        .device_wireguard_ips
-                                        .iter()
-                                        .map(IpAddr::to_string)
-                                        .collect(),
-                                    preshared_key: network_info.preshared_key.clone(),
-                                    keepalive_interval: Some(
-                                        self.network.keepalive_interval as u32,
-                                    ),
-                                },
-                                1,
-                            )
-                            .await
+                            if !self.peer_approved(device.device.id).await {
+                                debug!(
+                                    "Modified WireGuard device {} is waiting for approval in location {}",
+                                    device.device.name, self.network.name
+                                );
+                                continue;
+                            }
+                            match self.peer_keepalive_interval(device.device.id).await {
+                                Some(keepalive_interval) => {
+                                    self.send_peer_update(
+                                        Peer {
+                                            pubkey: device.device.wireguard_pubkey,
+                                            allowed_ips: network_info
+                                                .device_wireguard_ips
+                                                .iter()
+                                                .map(IpAddr::to_string)
+                                                .collect(),
+                                            preshared_key: network_info.preshared_key.clone(),
+                                            keepalive_interval: Some(keepalive_interval),
+                                        },
+                                        1,
+                                    )
+                                    .await
+                                }
+                                // profile may have been changed, so remove the peer if present
+                                None => {
+                                    self.send_peer_delete(&device.device.wireguard_pubkey).await
+                                }
+                            }
                         }
                         None => Ok(()),
                     }
@@ -519,15 +741,116 @@ impl GatewayUpdatesHandler {
                         Ok(())
                     }
                 }
+                GatewayEvent::GatewayTokenRevoked(location_id, token_id) => {
+                    if location_id == self.network_id && self.token_id == Some(token_id) {
+                        self.send_token_revoked().await
+                    } else {
+                        Ok(())
+                    }
+                }
             };
+            self.queue.record_processed(1, started.elapsed());
             if result.is_err() {
                 error!(
-                    "Closing update steam to gateway: {}, network {}",
+                    "Closing update stream to gateway: {}, network {}",
                     self.gateway_hostname, self.network
                 );
                 break;
             }
         }
+        // events left in the receiver are dropped together with it
+        self.queue.record_depth(0);
+    }
+
+    /// Returns peer keepalive interval for a device, taking its profile into account.
+    /// `None` means the device profile doesn't allow this location.
+    async fn peer_keepalive_interval(&self, device_id: Id) -> Option<u32> {
+        let profile = match DeviceProfile::find_for_device(&self.pool, device_id).await {
+            Ok(profile) => profile,
+            Err(err) => {
+                error!("Failed to fetch profile of device {device_id}: {err}");
+                None
+            }
+        };
+        match profile {
+            Some(profile) if !profile.allows_location(self.network_id) => None,
+            Some(profile) => Some(
+                profile
+                    .keepalive_interval
+                    .unwrap_or(self.network.keepalive_interval) as u32,
+            ),
+            None => Some(self.network.keepalive_interval as u32),
+        }
+    }
+
+    /// Checks if a device isn't waiting for approval, nor rejected, in this location.
+    async fn peer_approved(&self, device_id: Id) -> bool {
+        match DeviceApproval::is_approved(&self.pool, device_id, self.network_id).await {
+            Ok(approved) => approved,
+            Err(err) => {
+                error!("Failed to check approval of device {device_id}: {err}");
+                false
+            }
+        }
+    }
+
+    /// Sends complete network configuration (including all peers and firewall rules)
+    /// loaded from the database.
+    ///
+    /// Used to reconcile gateway state after some events have been missed.
+    async fn send_full_network_update(&mut self) -> Result<(), Status> {
+        let mut conn = self.pool.acquire().await.map_err(|err| {
+            error!("Failed to acquire DB connection: {err}");
+            Status::new(Code::Internal, "Failed to acquire DB connection")
+        })?;
+        let network = WireguardNetwork::find_by_id(&mut *conn, self.network_id)
+            .await
+            .map_err(|err| {
+                error!("Failed to retrieve network {}: {err}", self.network_id);
+                Status::new(Code::Internal, format!("Failed to retrieve network: {err}"))
+            })?
+            .ok_or_else(|| {
+                Status::new(
+                    Code::Internal,
+                    format!("Network with id {} not found", self.network_id),
+                )
+            })?;
+        let peers = network.get_peers(&mut *conn).await.map_err(|err| {
+            error!(
+                "Failed to fetch peers from the database for network {}: {err}",
+                self.network_id
+            );
+            Status::new(
+                Code::Internal,
+                format!(
+                    "Failed to retrieve peers from the database for network: {}",
+                    self.network_id
+                ),
+            )
+        })?;
+        let maybe_firewall_config =
+            network
+                .try_get_firewall_config(&mut conn)
+                .await
+                .map_err(|err| {
+                    error!(
+                        "Failed to generate firewall config for network {}: {err}",
+                        self.network_id
+                    );
+                    Status::new(
+                        Code::Internal,
+                        format!(
+                            "Failed to generate firewall config for network: {}",
+                            self.network_id
+                        ),
+                    )
+                })?;
+
+        let result = self
+            .send_network_update(&network, peers, maybe_firewall_config, 1)
+            .await;
+        self.network = network;
+        result
     }
 
     /// Sends updated network configuration
@@ -672,6 +995,23 @@ impl GatewayUpdatesHandler {
         Ok(())
     }
 
+    /// Notify gateway that its token has been revoked. Always returns an error, so that the updates
+    /// stream gets closed.
+    async fn send_token_revoked(&self) -> Result<(), Status> {
+        warn!(
+            "Token used by gateway {} in network {} has been revoked",
+            self.gateway_hostname, self.network
+        );
+        let status = Status::unauthenticated("Gateway token has been revoked");
+        if let Err(err) = self.tx.send(Err(status.clone())).await {
+            debug!(
+                "Failed to notify gateway {} about revoked token: {err}",
+                self.gateway_hostname
+            );
+        }
+        Err(status)
+    }
+
     /// Send firewall disable command to gateway
     async fn send_firewall_disable(&self) -> Result<(), Status> {
         debug!(
@@ -762,9 +1102,11 @@ impl gateway_service_server::GatewayService for GatewayServer {
     ) -> Result<Response<()>, Status> {
         let GatewayMetadata {
             network_id,
+            token_id,
             hostname,
             ..
         } = Self::extract_metadata(request.metadata())?;
+        self.verify_token(network_id, token_id).await?;
         let mut stream = request.into_inner();
         let mut disconnect_timer = interval(Duration::from_secs(PEER_DISCONNECT_INTERVAL));
         // FIXME: tracing causes looping messages, like `INFO gateway_config:gateway_stats:...`.
@@ -799,6 +1141,7 @@ impl gateway_service_server::GatewayService for GatewayServer {
 
                     // emit client disconnect events
                     for (device, context) in disconnected_clients {
+                        self.finish_vpn_session(&device, &location, &context).await;
                         self.emit_event(GrpcEvent::ClientDisconnected {
                             context,
                             location: location.clone(),
@@ -843,6 +1186,12 @@ impl gateway_service_server::GatewayService for GatewayServer {
             // otherwise a peer was added to the gateway interface
             // but has not connected yet
             if let Some(endpoint) = &stats.endpoint {
+                self.gateway_state.lock().unwrap().record_handshake(
+                    network_id,
+                    &hostname,
+                    stats.latest_handshake,
+                );
+
                 // parse client endpoint IP
                 let socket_addr: SocketAddr = endpoint.clone().parse().map_err(|err| {
                     error!("Failed to parse VPN client endpoint: {err}");
@@ -853,12 +1202,12 @@ impl gateway_service_server::GatewayService for GatewayServer {
                 })?;
 
                 // perform client state operations in a dedicated block to drop mutex guard
-                let disconnected_clients = {
+                let (new_client, disconnected_clients) = {
                     // acquire lock on client state map
                     let mut client_map = self.get_client_state_guard()?;
 
                     // update connected clients map
-                    match client_map.get_vpn_client(network_id, &public_key) {
+                    let new_client = match client_map.get_vpn_client(network_id, &public_key) {
                         Some(client_state) => {
                             // update connected client state
                             client_state.update_client_state(
@@ -868,6 +1217,7 @@ impl gateway_service_server::GatewayService for GatewayServer {
                                 stats.upload,
                                 stats.download,
                             );
+                            None
                         }
                         None => {
                             // don't mark inactive peers as connected
@@ -885,7 +1235,6 @@ impl gateway_service_server::GatewayService for GatewayServer {
                                     &stats,
                                 )?;
 
-                                // emit connection event
                                 let context = GrpcRequestContext::new(
                                     user.id,
                                     user.username.clone(),
@@ -894,21 +1243,48 @@ impl gateway_service_server::GatewayService for GatewayServer {
                                     device.name.clone(),
                                     location.clone(),
                                 );
-                                self.emit_event(GrpcEvent::ClientConnected {
-                                    context,
-                                    location: location.clone(),
-                                    device: device.clone(),
-                                })?;
+                                Some((device, context))
+                            } else {
+                                None
                             }
                         }
-                    }
+                    };
 
                     // disconnect inactive clients
-                    client_map.disconnect_inactive_vpn_clients_for_location(&location)?
+                    let disconnected_clients =
+                        client_map.disconnect_inactive_vpn_clients_for_location(&location)?;
+                    (new_client, disconnected_clients)
                 };
 
+                // emit connection event
+                if let Some((device, context)) = new_client {
+                    if let Err(err) = VpnSession::start(
+                        &self.pool,
+                        user.id,
+                        device.id,
+                        network_id,
+                        endpoint,
+                        stats.collected_at,
+                    )
+                    .await
+                    {
+                        error!(
+                            "Failed to start VPN session of device {} in location {}: {err}",
+                            device.name, location.name
+                        );
+                    }
+                    self.emit_event(GrpcEvent::ClientConnected {
+                        context,
+                        location: location.clone(),
+                        device: device.clone(),
+                    })?;
+                    self.enforce_session_limit(&user, &device, &location)
+                        .await?;
+                }
+
                 // emit client disconnect events
                 for (device, context) in disconnected_clients {
+                    self.finish_vpn_session(&device, &location, &context).await;
                     self.emit_event(GrpcEvent::ClientDisconnected {
                         context,
                         location: location.clone(),
@@ -942,11 +1318,13 @@ impl gateway_service_server::GatewayService for GatewayServer {
         debug!("Sending configuration to gateway client.");
         let GatewayMetadata {
             network_id,
+            token_id,
             hostname,
             version,
             ..
             // info,
         } = Self::extract_metadata(request.metadata())?;
+        let mut token = self.verify_token(network_id, token_id).await?;
         // FIXME: tracing causes looping messages, like `INFO gateway_config:gateway_stats:...`.
         // let span = tracing::info_span!("gateway_config", component = %DefguardComponent::Gateway,
         //     version = version.to_string(), info);
@@ -975,6 +1353,13 @@ impl gateway_service_server::GatewayService for GatewayServer {
 
         debug!("Sending configuration to gateway client, network {network}.");
 
+        // remember which gateway instance uses the token
+        if let Some(token) = &mut token {
+            if let Err(err) = token.record_connection(&mut *conn, &hostname).await {
+                error!("Failed to save connection of gateway token {token}: {err}");
+            }
+        }
+
         // store connected gateway in memory
         {
             let mut state = self.gateway_state.lock().unwrap();
@@ -983,6 +1368,7 @@ impl gateway_service_server::GatewayService for GatewayServer {
                 &network.name,
                 hostname,
                 request.into_inner().name,
+                token.as_ref(),
                 self.mail_tx.clone(),
                 version,
             );
@@ -1024,10 +1410,12 @@ impl gateway_service_server::GatewayService for GatewayServer {
     async fn updates(&self, request: Request<()>) -> Result<Response<Self::UpdatesStream>, Status> {
         let GatewayMetadata {
             network_id,
+            token_id,
             hostname,
             ..
             // info,
         } = Self::extract_metadata(request.metadata())?;
+        self.verify_token(network_id, token_id).await?;
         // FIXME: tracing causes looping messages, like `INFO gateway_config:gateway_stats:...`.
         // let span = tracing::info_span!("gateway_updates", component = %DefguardComponent::Gateway,
         //     version = version.to_string(), info);
@@ -1052,7 +1440,7 @@ impl gateway_service_server::GatewayService for GatewayServer {
         info!("New client connected to updates stream: {hostname}, network {network}",);
 
         let (tx, rx) = mpsc::channel(4);
-        let events_rx = self.wireguard_tx.subscribe();
+        let events_rx = self.gateway_events_tx.subscribe();
         let mut state = self.gateway_state.lock().unwrap();
         state
             .connect_gateway(network_id, &hostname, &self.pool)
@@ -1066,9 +1454,17 @@ impl gateway_service_server::GatewayService for GatewayServer {
 
         // clone here before moving into a closure
         let gateway_hostname = hostname.clone();
+        let pool = self.pool.clone();
         let handle = tokio::spawn(async move {
-            let mut update_handler =
-                GatewayUpdatesHandler::new(network_id, network, gateway_hostname, events_rx, tx);
+            let mut update_handler = GatewayUpdatesHandler::new(
+                network_id,
+                token_id,
+                network,
+                gateway_hostname,
+                events_rx,
+                tx,
+                pool,
+            );
             update_handler.run().await;
         });
 
@@ -13,10 +13,28 @@ use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::{
+    db::models::gateway_token::GatewayToken,
     grpc::MIN_GATEWAY_VERSION,
     handlers::mail::{send_gateway_disconnected_email, send_gateway_reconnected_email},
 };
 
+/// Number of recent connection changes kept for each gateway.
+const GATEWAY_HISTORY_LIMIT: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GatewayConnectionEventKind {
+    Connected,
+    Disconnected,
+}
+
+/// Gateway connection change, kept in memory since core startup.
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct GatewayConnectionEvent {
+    pub kind: GatewayConnectionEventKind,
+    pub timestamp: NaiveDateTime,
+}
+
 #[derive(Clone, Debug, Serialize, ToSchema)]
 pub struct GatewayState {
     pub uid: Uuid,
@@ -25,8 +43,15 @@ pub struct GatewayState {
     pub network_name: String,
     pub name: Option<String>,
     pub hostname: String,
+    /// Token used by the gateway, `None` for tokens which can't be revoked individually.
+    pub token_id: Option<Id>,
+    pub token_name: Option<String>,
     pub connected_at: Option<NaiveDateTime>,
     pub disconnected_at: Option<NaiveDateTime>,
+    /// Most recent peer handshake reported by the gateway.
+    pub last_handshake: Option<NaiveDateTime>,
+    /// Recent connection changes, oldest first.
+    pub history: Vec<GatewayConnectionEvent>,
     #[serde(skip)]
     pub mail_tx: UnboundedSender<Mail>,
     #[serde(skip)]
@@ -52,14 +77,37 @@ impl GatewayState {
             network_name: network_name.into(),
             name,
             hostname: hostname.into(),
+            token_id: None,
+            token_name: None,
             connected_at: None,
             disconnected_at: None,
+            last_handshake: None,
+            history: Vec::new(),
             mail_tx,
             pending_notification_cancel_token: None,
             version,
         }
     }
 
+    pub(super) fn set_token(&mut self, token: Option<&GatewayToken<Id>>) {
+        self.token_id = token.map(|token| token.id);
+        self.token_name = token.map(|token| token.name.clone());
+    }
+
+    /// Appends connection change to history, dropping the oldest entries over the limit.
+    pub(super) fn record_connection_event(
+        &mut self,
+        kind: GatewayConnectionEventKind,
+        timestamp: NaiveDateTime,
+    ) {
+        self.history
+            .push(GatewayConnectionEvent { kind, timestamp });
+        if self.history.len() > GATEWAY_HISTORY_LIMIT {
+            let excess = self.history.len() - GATEWAY_HISTORY_LIMIT;
+            self.history.drain(..excess);
+        }
+    }
+
     /// Checks if gateway disconnect notification should be sent.
     pub(super) fn handle_disconnect_notification(&mut self, pool: &PgPool) {
         debug!("Checking if gateway disconnect notification needs to be sent");
@@ -49,6 +49,16 @@ impl Interceptor for JwtInterceptor {
                             .parse()
                             .map_err(|_| Status::unknown("Network ID parsing error"))?,
                     );
+                    // tokens issued before multiple tokens per location were introduced don't
+                    // have an ID
+                    if let Some(token_id) = claims.jti {
+                        request_metadata.insert(
+                            "gateway_token_id",
+                            token_id
+                                .parse()
+                                .map_err(|_| Status::unknown("Token ID parsing error"))?,
+                        );
+                    }
                 }
 
                 // FIXME: can we push whole Claims object into metadata?
@@ -26,16 +26,18 @@ use tokio::{
         broadcast::Sender,
         mpsc::{self, UnboundedSender},
     },
+    task::spawn,
     time::sleep,
 };
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tonic::{
-    Code, Streaming,
+    Code, Status, Streaming,
     transport::{
         Certificate, ClientTlsConfig, Endpoint, Identity, Server, ServerTlsConfig, server::Router,
     },
 };
-use tower::ServiceBuilder;
+use tower::{Layer, ServiceBuilder};
+use tracing::field;
 
 use self::{
     auth::AuthServer, client_mfa::ClientMfaServer, enrollment::EnrollmentServer,
@@ -44,7 +46,11 @@ use self::{
 };
 pub use crate::version::MIN_GATEWAY_VERSION;
 use crate::{
-    auth::failed_login::FailedLoginMap,
+    appstate::build_webauthn,
+    auth::{
+        failed_login::FailedLoginMap,
+        rate_limit::{RateLimitedEndpoint, RateLimiter, run_rate_limiter_sweep},
+    },
     db::{
         AppEvent, GatewayEvent,
         models::enrollment::{ENROLLMENT_TOKEN_TYPE, Token},
@@ -61,22 +67,31 @@ use crate::{
         },
         is_business_license_active,
         ldap::utils::ldap_update_user_state,
+        provisioning::{account_creation_refused_bidi_event, user_provisioned_bidi_event},
     },
+    error::WebError,
     events::{BidiStreamEvent, GrpcEvent},
-    grpc::gateway::{client_state::ClientMap, map::GatewayMap},
+    grpc::{
+        gateway::{client_state::ClientMap, map::GatewayMap},
+        utils::{ProxySession, check_rate_limit, parse_client_ip_agent, verify_heartbeat_token},
+    },
+    headers::REQUEST_ID_HEADER_NAME,
+    metrics::GrpcMetricsLayer,
     server_config,
+    telemetry::set_remote_parent,
     version::{IncompatibleComponents, IncompatibleProxyData, is_proxy_version_supported},
 };
 
 static VERSION_ZERO: Version = Version::new(0, 0, 0);
 
 mod auth;
-pub(crate) mod client_mfa;
+pub mod client_mfa;
 pub mod client_version;
 pub mod enrollment;
 pub mod gateway;
 mod interceptor;
 pub mod password_reset;
+pub(crate) mod session_limit;
 pub(crate) mod utils;
 pub mod worker;
 
@@ -89,6 +104,7 @@ pub mod proto {
 }
 
 use defguard_proto::{
+    FILE_DESCRIPTOR_SET,
     auth::auth_service_server::AuthServiceServer,
     gateway::gateway_service_server::GatewayServiceServer,
     proxy::{
@@ -115,6 +131,9 @@ struct ProxyMessageLoopContext<'a> {
     password_reset_server: &'a mut PasswordResetServer,
     client_mfa_server: &'a mut ClientMfaServer,
     polling_server: &'a mut PollingServer,
+    rate_limiter: Arc<Mutex<RateLimiter>>,
+    bidi_event_tx: UnboundedSender<BidiStreamEvent>,
+    client_state: Arc<Mutex<ClientMap>>,
     endpoint_uri: &'a Uri,
 }
 
@@ -131,6 +150,8 @@ async fn handle_proxy_message_loop(
             }
             Ok(Some(received)) => {
                 debug!("Received message from proxy; ID={}", received.id);
+                // abandoned desktop client logins are evicted before handling any request
+                context.client_mfa_server.remove_expired_sessions().await;
                 let payload = match received.payload {
                     // rpc CodeMfaSetupStart return (CodeMfaSetupStartResponse)
                     Some(core_request::Payload::CodeMfaSetupStart(request)) => {
@@ -192,11 +213,25 @@ async fn handle_proxy_message_loop(
                     }
                     // rpc StartEnrollment (EnrollmentStartRequest) returns (EnrollmentStartResponse)
                     Some(core_request::Payload::EnrollmentStart(request)) => {
-                        match context
-                            .enrollment_server
-                            .start_enrollment(request, received.device_info)
-                            .await
+                        let result = match check_rate_limit(
+                            &pool,
+                            &context.rate_limiter,
+                            &context.bidi_event_tx,
+                            RateLimitedEndpoint::Enrollment,
+                            &received.device_info,
+                            ProxySession::Token(&request.token),
+                        )
+                        .await
                         {
+                            Ok(()) => {
+                                context
+                                    .enrollment_server
+                                    .start_enrollment(request, received.device_info)
+                                    .await
+                            }
+                            Err(err) => Err(err),
+                        };
+                        match result {
                             Ok(response_payload) => {
                                 Some(core_response::Payload::EnrollmentStart(response_payload))
                             }
@@ -254,11 +289,25 @@ async fn handle_proxy_message_loop(
                     }
                     // rpc RequestPasswordReset (PasswordResetInitializeRequest) returns (google.protobuf.Empty)
                     Some(core_request::Payload::PasswordResetInit(request)) => {
-                        match context
-                            .password_reset_server
-                            .request_password_reset(request, received.device_info)
-                            .await
+                        let result = match check_rate_limit(
+                            &pool,
+                            &context.rate_limiter,
+                            &context.bidi_event_tx,
+                            RateLimitedEndpoint::PasswordReset,
+                            &received.device_info,
+                            ProxySession::Email(&request.email),
+                        )
+                        .await
                         {
+                            Ok(()) => {
+                                context
+                                    .password_reset_server
+                                    .request_password_reset(request, received.device_info)
+                                    .await
+                            }
+                            Err(err) => Err(err),
+                        };
+                        match result {
                             Ok(()) => Some(core_response::Payload::Empty(())),
                             Err(err) => {
                                 error!("password reset init error {err}");
@@ -268,11 +317,25 @@ async fn handle_proxy_message_loop(
                     }
                     // rpc StartPasswordReset (PasswordResetStartRequest) returns (PasswordResetStartResponse)
                     Some(core_request::Payload::PasswordResetStart(request)) => {
-                        match context
-                            .password_reset_server
-                            .start_password_reset(request, received.device_info)
-                            .await
+                        let result = match check_rate_limit(
+                            &pool,
+                            &context.rate_limiter,
+                            &context.bidi_event_tx,
+                            RateLimitedEndpoint::PasswordReset,
+                            &received.device_info,
+                            ProxySession::Token(&request.token),
+                        )
+                        .await
                         {
+                            Ok(()) => {
+                                context
+                                    .password_reset_server
+                                    .start_password_reset(request, received.device_info)
+                                    .await
+                            }
+                            Err(err) => Err(err),
+                        };
+                        match result {
                             Ok(response_payload) => {
                                 Some(core_response::Payload::PasswordResetStart(response_payload))
                             }
@@ -300,7 +363,7 @@ async fn handle_proxy_message_loop(
                     Some(core_request::Payload::ClientMfaStart(request)) => {
                         match context
                             .client_mfa_server
-                            .start_client_mfa_login(request)
+                            .start_client_mfa_login(request, received.device_info)
                             .await
                         {
                             Ok(response_payload) => {
@@ -351,6 +414,49 @@ async fn handle_proxy_message_loop(
                             }
                         }
                     }
+                    // rpc ClientHeartbeat (ClientHeartbeatRequest) returns (google.protobuf.Empty)
+                    Some(core_request::Payload::ClientHeartbeat(request)) => {
+                        let result = verify_heartbeat_token(&pool, &request.token, &request.pubkey)
+                            .await
+                            .and_then(|()| {
+                                context
+                                    .client_state
+                                    .lock()
+                                    .map_err(|_| Status::internal("client state lock poisoned"))
+                            })
+                            .and_then(|mut client_map| {
+                                client_map
+                                    .record_heartbeat(
+                                        request.location_id,
+                                        &request.pubkey,
+                                        request.connected,
+                                    )
+                                    .map_err(Status::from)
+                            });
+                        match result {
+                            Ok(()) => Some(core_response::Payload::Empty(())),
+                            Err(err) => {
+                                debug!("Client heartbeat error {err}");
+                                Some(core_response::Payload::CoreError(err.into()))
+                            }
+                        }
+                    }
+                    // rpc RotateDeviceKey (RotateDeviceKeyRequest) returns (DeviceConfigResponse)
+                    Some(core_request::Payload::RotateDeviceKey(request)) => {
+                        match context
+                            .polling_server
+                            .rotate_key(request, &context.wireguard_tx)
+                            .await
+                        {
+                            Ok(response_payload) => {
+                                Some(core_response::Payload::DeviceConfig(response_payload))
+                            }
+                            Err(err) => {
+                                error!("Device key rotation error {err}");
+                                Some(core_response::Payload::CoreError(err.into()))
+                            }
+                        }
+                    }
                     // rpc LocationInfo (LocationInfoRequest) returns (LocationInfoResponse)
                     Some(core_request::Payload::InstanceInfo(request)) => {
                         match context
@@ -449,15 +555,52 @@ async fn handle_proxy_message_loop(
                         match Url::parse(&request.callback_url) {
                             Ok(callback_url) => {
                                 let code = AuthorizationCode::new(request.code);
-                                match user_from_claims(
+                                let result = user_from_claims(
                                     &pool,
                                     Nonce::new(request.nonce),
                                     code,
                                     callback_url,
+                                    &context.wireguard_tx,
                                 )
-                                .await
-                                {
-                                    Ok(mut user) => {
+                                .await;
+                                let (ip, user_agent) = parse_client_ip_agent(&received.device_info)
+                                    .unwrap_or_else(|_| {
+                                        (IpAddr::V4(Ipv4Addr::UNSPECIFIED), String::new())
+                                    });
+                                let event = match &result {
+                                    Ok((user, Some(provisioning))) => {
+                                        Some(user_provisioned_bidi_event(
+                                            user,
+                                            provisioning.clone(),
+                                            ip,
+                                            user_agent,
+                                        ))
+                                    }
+                                    Err(WebError::AccountCreationRefused(refused)) => {
+                                        Some(account_creation_refused_bidi_event(
+                                            refused, ip, user_agent,
+                                        ))
+                                    }
+                                    _ => None,
+                                };
+                                if let Some(event) = event {
+                                    if let Err(err) = context.bidi_event_tx.send(event) {
+                                        error!("Failed to record provisioning decision: {err}");
+                                    }
+                                }
+                                match result {
+                                    Ok((user, _)) if !user.is_active => {
+                                        info!(
+                                            "User {} logged in through OpenID provider via proxy, \
+                                            but their account is awaiting approval",
+                                            user.username
+                                        );
+                                        Some(core_response::Payload::CoreError(CoreError {
+                                            status_code: Code::PermissionDenied as i32,
+                                            message: "account awaiting approval".into(),
+                                        }))
+                                    }
+                                    Ok((mut user, _)) => {
                                         user.clear_unused_enrollment_tokens(&pool).await?;
                                         if let Err(err) = sync_user_groups_if_configured(
                                             &user,
@@ -554,6 +697,7 @@ pub async fn run_grpc_bidi_stream(
     wireguard_tx: Sender<GatewayEvent>,
     mail_tx: UnboundedSender<Mail>,
     bidi_event_tx: UnboundedSender<BidiStreamEvent>,
+    client_state: Arc<Mutex<ClientMap>>,
     incompatible_components: Arc<RwLock<IncompatibleComponents>>,
 ) -> Result<(), anyhow::Error> {
     let config = server_config();
@@ -567,9 +711,17 @@ pub async fn run_grpc_bidi_stream(
     );
     let mut password_reset_server =
         PasswordResetServer::new(pool.clone(), mail_tx.clone(), bidi_event_tx.clone());
-    let mut client_mfa_server =
-        ClientMfaServer::new(pool.clone(), mail_tx, wireguard_tx.clone(), bidi_event_tx);
+    let mut client_mfa_server = ClientMfaServer::new(
+        pool.clone(),
+        mail_tx,
+        wireguard_tx.clone(),
+        bidi_event_tx.clone(),
+        Arc::new(build_webauthn()),
+    );
     let mut polling_server = PollingServer::new(pool.clone());
+    // requests coming through the proxy are limited separately from the web API
+    let rate_limiter = Arc::default();
+    spawn(run_rate_limiter_sweep(Arc::downgrade(&rate_limiter)));
 
     let endpoint = Endpoint::from_shared(config.proxy_url.as_deref().unwrap())?;
     let endpoint = endpoint
@@ -648,6 +800,9 @@ pub async fn run_grpc_bidi_stream(
             password_reset_server: &mut password_reset_server,
             client_mfa_server: &mut client_mfa_server,
             polling_server: &mut polling_server,
+            rate_limiter: Arc::clone(&rate_limiter),
+            bidi_event_tx: bidi_event_tx.clone(),
+            client_state: Arc::clone(&client_state),
             endpoint_uri: endpoint.uri(),
         })
         .await?;
@@ -662,12 +817,15 @@ pub async fn run_grpc_server(
     gateway_state: Arc<Mutex<GatewayMap>>,
     client_state: Arc<Mutex<ClientMap>>,
     wireguard_tx: Sender<GatewayEvent>,
+    gateway_events_tx: Sender<GatewayEvent>,
     mail_tx: UnboundedSender<Mail>,
     grpc_cert: Option<String>,
     grpc_key: Option<String>,
     failed_logins: Arc<Mutex<FailedLoginMap>>,
     grpc_event_tx: UnboundedSender<GrpcEvent>,
+    webhook_tx: UnboundedSender<AppEvent>,
     incompatible_components: Arc<RwLock<IncompatibleComponents>>,
+    shutdown: impl Future<Output = ()> + Send,
 ) -> Result<(), anyhow::Error> {
     // Build gRPC services
     let server = if let (Some(cert), Some(key)) = (grpc_cert, grpc_key) {
@@ -684,9 +842,11 @@ pub async fn run_grpc_server(
         gateway_state,
         client_state,
         wireguard_tx,
+        gateway_events_tx,
         mail_tx,
         failed_logins,
         grpc_event_tx,
+        webhook_tx,
         incompatible_components,
     )
     .await?;
@@ -699,8 +859,8 @@ pub async fn run_grpc_server(
         server_config().grpc_port,
     );
     debug!("Starting gRPC services");
-    router.serve(addr).await?;
-    info!("gRPC server started on {addr}");
+    router.serve_with_shutdown(addr, shutdown).await?;
+    info!("gRPC server on {addr} stopped");
     Ok(())
 }
 
@@ -711,9 +871,11 @@ pub async fn build_grpc_service_router(
     gateway_state: Arc<Mutex<GatewayMap>>,
     client_state: Arc<Mutex<ClientMap>>,
     wireguard_tx: Sender<GatewayEvent>,
+    gateway_events_tx: Sender<GatewayEvent>,
     mail_tx: UnboundedSender<Mail>,
     failed_logins: Arc<Mutex<FailedLoginMap>>,
     grpc_event_tx: UnboundedSender<GrpcEvent>,
+    webhook_tx: UnboundedSender<AppEvent>,
     incompatible_components: Arc<RwLock<IncompatibleComponents>>,
 ) -> Result<Router, anyhow::Error> {
     let auth_service = AuthServiceServer::new(AuthServer::new(pool.clone(), failed_logins));
@@ -723,16 +885,47 @@ pub async fn build_grpc_service_router(
         JwtInterceptor::new(ClaimsType::YubiBridge),
     );
 
+    // report readiness of all services to load balancers
     let (health_reporter, health_service) = tonic_health::server::health_reporter();
     health_reporter
         .set_serving::<AuthServiceServer<AuthServer>>()
         .await;
+    health_reporter
+        .set_serving::<GatewayServiceServer<GatewayServer>>()
+        .await;
+    health_reporter
+        .set_serving::<WorkerServiceServer<WorkerServer>>()
+        .await;
+
+    // allow tools like grpcurl to discover the APIs
+    let reflection_service = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build_v1()?;
 
     let router = server
         .http2_keepalive_interval(Some(TEN_SECS))
         .tcp_keepalive(Some(TEN_SECS))
-        .add_service(health_service)
-        .add_service(auth_service);
+        .trace_fn(|request| {
+            let span = info_span!(
+                "grpc_request",
+                path = %request.uri().path(),
+                request_id = field::Empty,
+            );
+            // callers may pass ID of the HTTP request which triggered this call
+            if let Some(request_id) = request
+                .headers()
+                .get(&REQUEST_ID_HEADER_NAME)
+                .and_then(|value| value.to_str().ok())
+            {
+                span.record("request_id", request_id);
+            }
+            set_remote_parent(&span, request.headers());
+            span
+        })
+        .add_service(GrpcMetricsLayer.layer(health_service))
+        .add_service(GrpcMetricsLayer.layer(reflection_service))
+        .add_service(GrpcMetricsLayer.layer(auth_service));
 
     let router = {
         use crate::version::GatewayVersionInterceptor;
@@ -742,13 +935,16 @@ pub async fn build_grpc_service_router(
             gateway_state,
             client_state,
             wireguard_tx,
+            gateway_events_tx,
             mail_tx,
             grpc_event_tx,
+            webhook_tx,
         ));
 
         let own_version = Version::parse(VERSION)?;
         router.add_service(
             ServiceBuilder::new()
+                .layer(GrpcMetricsLayer)
                 .layer(tonic::service::InterceptorLayer::new(JwtInterceptor::new(
                     ClaimsType::Gateway,
                 )))
@@ -760,7 +956,7 @@ pub async fn build_grpc_service_router(
         )
     };
 
-    let router = router.add_service(worker_service);
+    let router = router.add_service(GrpcMetricsLayer.layer(worker_service));
 
     Ok(router)
 }
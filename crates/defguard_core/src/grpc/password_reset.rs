@@ -1,3 +1,6 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use defguard_common::db::Id;
 use defguard_mail::Mail;
 use defguard_proto::proxy::{
     DeviceInfo, PasswordResetInitializeRequest, PasswordResetRequest, PasswordResetStartRequest,
@@ -14,11 +17,10 @@ use crate::{
     },
     enterprise::ldap::utils::ldap_change_password,
     events::{BidiRequestContext, BidiStreamEvent, BidiStreamEventType, PasswordResetEvent},
-    grpc::utils::parse_client_ip_agent,
-    handlers::{
-        mail::{send_password_reset_email, send_password_reset_success_email},
-        user::check_password_strength,
+    grpc::utils::{
+        check_captcha, check_new_password, parse_client_ip_agent, self_service_network_allowed,
     },
+    handlers::mail::{send_password_reset_email, send_password_reset_success_email},
     headers::get_device_info,
     server_config,
 };
@@ -90,6 +92,19 @@ impl PasswordResetServer {
         self.bidi_event_tx.send(event)
     }
 
+    /// Records a password reset attempt rejected by the network policy.
+    fn emit_blocked_event(&self, user: User<Id>, info: &Option<DeviceInfo>) -> Result<(), Status> {
+        // client address may be missing in blocked requests
+        let (ip, user_agent) = parse_client_ip_agent(info)
+            .unwrap_or_else(|_| (IpAddr::V4(Ipv4Addr::UNSPECIFIED), String::new()));
+        let context = BidiRequestContext::new(user.id, user.username, ip, user_agent);
+        self.emit_event(context, PasswordResetEvent::PasswordResetBlocked)
+            .map_err(|err| {
+                error!("Failed to send event. Reason: {err}",);
+                Status::internal("unexpected error")
+            })
+    }
+
     #[instrument(skip_all)]
     pub async fn request_password_reset(
         &self,
@@ -98,6 +113,8 @@ impl PasswordResetServer {
     ) -> Result<(), Status> {
         let config = server_config();
         debug!("Starting password reset request");
+        // verify before looking up the user, so bots can't trigger emails
+        check_captcha(&req_device_info, request.captcha_token.as_deref()).await?;
 
         let ip_address;
         let device_info;
@@ -111,6 +128,9 @@ impl PasswordResetServer {
         }
 
         let email = request.email;
+        // Check the policy regardless of the user lookup result, so the response doesn't reveal
+        // whether the user exists.
+        let network_allowed = self_service_network_allowed(&self.pool, &req_device_info).await?;
 
         let user = User::find_by_email(&self.pool, email.to_string().as_str())
             .await
@@ -119,6 +139,18 @@ impl PasswordResetServer {
                 Status::internal("unexpected error")
             })?;
 
+        if !network_allowed {
+            warn!(
+                "Blocked password reset request for {email} from a network outside of the allowed ones"
+            );
+            if let Some(user) = user {
+                self.emit_blocked_event(user, &req_device_info)?;
+            }
+            return Err(Status::permission_denied(
+                "password reset is only allowed from the corporate network or VPN",
+            ));
+        }
+
         let Some(user) = user else {
             // Do not return information whether user exists
             debug!("Password reset skipped for non-existing user {email}");
@@ -209,6 +241,17 @@ impl PasswordResetServer {
             ));
         }
 
+        if !self_service_network_allowed(&self.pool, &info).await? {
+            warn!(
+                "Blocked password reset start for user {} from a network outside of the allowed ones",
+                user.username
+            );
+            self.emit_blocked_event(user, &info)?;
+            return Err(Status::permission_denied(
+                "password reset is only allowed from the corporate network or VPN",
+            ));
+        }
+
         let mut transaction = self.pool.begin().await.map_err(|_| {
             error!("Failed to begin transaction");
             Status::internal("unexpected error")
@@ -266,11 +309,6 @@ impl PasswordResetServer {
             device_info = String::new();
         }
 
-        if let Err(err) = check_password_strength(&request.password) {
-            error!("Password not strong enough: {err}");
-            return Err(Status::invalid_argument("password not strong enough"));
-        }
-
         let mut user = enrollment.fetch_user(&self.pool).await?;
 
         if !user.is_active {
@@ -281,6 +319,8 @@ impl PasswordResetServer {
             return Err(Status::permission_denied("user disabled"));
         }
 
+        check_new_password(&self.pool, &request.password, &user).await?;
+
         let mut transaction = self.pool.begin().await.map_err(|_| {
             error!("Failed to begin transaction");
             Status::internal("unexpected error")
@@ -292,6 +332,15 @@ impl PasswordResetServer {
             error!("Failed to update user {}: {err}", user.username);
             Status::internal("unexpected error")
         })?;
+        user.remember_password(&mut transaction)
+            .await
+            .map_err(|err| {
+                error!(
+                    "Failed to store password history of user {}: {err}",
+                    user.username
+                );
+                Status::internal("unexpected error")
+            })?;
 
         transaction.commit().await.map_err(|_| {
             error!("Failed to commit transaction");
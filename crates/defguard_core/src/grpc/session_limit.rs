@@ -0,0 +1,122 @@
+//! Enforcement of group limits of devices connected to VPN at the same time.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use chrono::Utc;
+use defguard_common::db::Id;
+use sqlx::{Error as SqlxError, PgPool};
+use tokio::sync::broadcast::Sender;
+
+use super::gateway::send_wireguard_event;
+use crate::db::{
+    Device, GatewayEvent, WireguardNetwork,
+    models::{
+        device::{DeviceInfo, DeviceNetworkInfo, WireguardNetworkDevice},
+        group::session_limit,
+        vpn_session::VpnSession,
+    },
+};
+
+/// VPN session disconnected because its owner exceeded the session limit.
+pub(crate) struct TerminatedSession {
+    pub location: WireguardNetwork<Id>,
+    pub device: Device<Id>,
+    pub ip: IpAddr,
+    pub limit: i32,
+}
+
+/// Disconnect the oldest VPN sessions of the user exceeding the session limit of their groups.
+///
+/// The session of `device_id` in `location_id`, which has just started or is about to start, is
+/// counted in and never disconnected.
+///
+/// Only sessions in MFA-protected locations can be disconnected: the device loses its
+/// authorization and has to complete MFA again to reconnect. Peers in other locations are always
+/// configured on gateways, so their sessions are left alone.
+pub(crate) async fn enforce_session_limit(
+    pool: &PgPool,
+    wireguard_tx: &Sender<GatewayEvent>,
+    user_id: Id,
+    location_id: Id,
+    device_id: Id,
+) -> Result<Vec<TerminatedSession>, SqlxError> {
+    let Some(limit) = session_limit(pool, user_id).await? else {
+        return Ok(Vec::new());
+    };
+    let sessions: Vec<_> = VpnSession::find_ongoing_by_user(pool, user_id)
+        .await?
+        .into_iter()
+        .filter(|session| session.location_id != location_id || session.device_id != device_id)
+        .collect();
+    let mut excess = (sessions.len() + 1).saturating_sub(usize::try_from(limit).unwrap_or(0));
+    let mut terminated = Vec::new();
+    for session in sessions {
+        if excess == 0 {
+            break;
+        }
+        let Some(location) = WireguardNetwork::find_by_id(pool, session.location_id).await? else {
+            continue;
+        };
+        if !location.mfa_enabled() {
+            continue;
+        }
+        let Some(device) = Device::find_by_id(pool, session.device_id).await? else {
+            continue;
+        };
+
+        let mut transaction = pool.begin().await?;
+        let Some(mut network_device) =
+            WireguardNetworkDevice::find(&mut *transaction, device.id, location.id).await?
+        else {
+            continue;
+        };
+        network_device.is_authorized = false;
+        network_device.preshared_key = None;
+        network_device.update(&mut *transaction).await?;
+        VpnSession::finish(
+            &mut *transaction,
+            device.id,
+            location.id,
+            Utc::now().naive_utc(),
+        )
+        .await?;
+        transaction.commit().await?;
+
+        debug!("Sending `peer_delete` message to gateway");
+        send_wireguard_event(
+            GatewayEvent::DeviceDeleted(DeviceInfo {
+                device: device.clone(),
+                network_info: vec![DeviceNetworkInfo {
+                    network_id: location.id,
+                    device_wireguard_ips: network_device.wireguard_ips,
+                    preshared_key: network_device.preshared_key,
+                    is_authorized: network_device.is_authorized,
+                }],
+            }),
+            wireguard_tx,
+        );
+        warn!(
+            "User {user_id} exceeded the limit of {limit} VPN sessions, disconnected device \
+            {device} from location {location}"
+        );
+        let ip = session
+            .endpoint
+            .parse::<SocketAddr>()
+            .map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |addr| addr.ip());
+        terminated.push(TerminatedSession {
+            location,
+            device,
+            ip,
+            limit,
+        });
+        excess -= 1;
+    }
+    if excess > 0 {
+        warn!(
+            "User {user_id} exceeded the limit of {limit} VPN sessions, but {excess} of them \
+            can't be disconnected since their locations don't use MFA"
+        );
+    }
+
+    Ok(terminated)
+}
@@ -1,6 +1,11 @@
-use std::{net::IpAddr, str::FromStr};
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    str::FromStr,
+    sync::Mutex,
+};
 
 use defguard_common::{
+    config::server_config,
     csv::AsCsv,
     db::{Id, models::Settings},
 };
@@ -8,15 +13,23 @@ use defguard_proto::proxy::{
     DeviceConfig as ProtoDeviceConfig, DeviceConfigResponse, DeviceInfo,
     LocationMfaMode as ProtoLocationMfaMode,
 };
-use sqlx::PgPool;
+use sqlx::{Error as SqlxError, PgPool};
+use tokio::sync::mpsc::UnboundedSender;
 use tonic::Status;
 
 use super::InstanceInfo;
 use crate::{
+    auth::{
+        password_policy::check_password_policy,
+        rate_limit::{RateLimitKey, RateLimitPolicy, RateLimitedEndpoint, RateLimiter},
+    },
+    captcha::{CaptchaError, verify_captcha},
     db::{
         Device, User,
         models::{
             device::{DeviceType, WireguardNetworkDevice},
+            device_profile::{DeviceProfile, apply_device_profile},
+            enrollment::Token,
             polling_token::PollingToken,
             wireguard::{
                 LocationMfaMode, ServiceLocationMode, WireguardNetwork, get_allowed_ips_for_device,
@@ -26,9 +39,29 @@ use crate::{
     enterprise::db::models::{
         enterprise_settings::EnterpriseSettings, openid_provider::OpenIdProvider,
     },
+    error::WebError,
+    events::{BidiRequestContext, BidiStreamEvent, BidiStreamEventType},
     grpc::client_version::ClientFeature,
 };
 
+/// Preshared key of a location peer, if the location rotates preshared keys periodically.
+/// Peers of MFA-protected locations receive their preshared key after each MFA login instead.
+async fn rotated_preshared_key(
+    pool: &PgPool,
+    location: &WireguardNetwork<Id>,
+    wireguard_network_device: &WireguardNetworkDevice,
+) -> Result<Option<String>, Status> {
+    if location.mfa_enabled() {
+        return Ok(None);
+    }
+    let interval = location.psk_rotation_interval(pool).await.map_err(|err| {
+        error!("Failed to fetch preshared key rotation interval of location {location}: {err}");
+        Status::internal(format!("unexpected error: {err}"))
+    })?;
+
+    Ok(interval.and(wireguard_network_device.preshared_key.clone()))
+}
+
 // Create a new token for configuration polling.
 pub(crate) async fn new_polling_token(
     pool: &PgPool,
@@ -71,6 +104,38 @@ pub(crate) async fn new_polling_token(
     Ok(new_token.token)
 }
 
+/// Verifies that a desktop client heartbeat comes from the device it reports the state of,
+/// using the polling token issued to the device.
+pub(crate) async fn verify_heartbeat_token(
+    pool: &PgPool,
+    token: &str,
+    pubkey: &str,
+) -> Result<(), Status> {
+    let Some(token) = PollingToken::find(pool, token).await.map_err(|err| {
+        error!("Failed to retrieve polling token: {err}");
+        Status::internal("failed to retrieve token")
+    })?
+    else {
+        warn!("Received client heartbeat for device {pubkey} with an invalid token");
+        return Err(Status::permission_denied("invalid token"));
+    };
+    let device = Device::find_by_id(pool, token.device_id)
+        .await
+        .map_err(|err| {
+            error!("Failed to retrieve device id {}: {err}", token.device_id);
+            Status::internal("failed to retrieve device")
+        })?;
+    if device.is_none_or(|device| device.wireguard_pubkey != pubkey) {
+        warn!(
+            "Received client heartbeat for device {pubkey} with a token of another device (ID {})",
+            token.device_id
+        );
+        return Err(Status::permission_denied("invalid token"));
+    }
+
+    Ok(())
+}
+
 pub(crate) async fn build_device_config_response(
     pool: &PgPool,
     device: Device<Id>,
@@ -105,6 +170,12 @@ pub(crate) async fn build_device_config_response(
             error!("User not found: {}", device.user_id);
             Status::internal("unexpected error")
         })?;
+    let profile = DeviceProfile::find_for_device(pool, device.id)
+        .await
+        .map_err(|err| {
+            error!("Failed to fetch profile of device {}: {err}", device.id);
+            Status::internal(format!("unexpected error: {err}"))
+        })?;
     if device.device_type == DeviceType::Network {
         let wireguard_network_device = WireguardNetworkDevice::find_first(pool, device.id)
             .await
@@ -125,7 +196,25 @@ pub(crate) async fn build_device_config_response(
                         device.name
                     );
                     Status::internal(format!("unexpected error: {err}"))
+                })?
+                .apply_device_settings(pool, &device)
+                .await
+                .map_err(|err| {
+                    error!(
+                        "Failed to fetch settings of network device {} in its location: {err}",
+                        device.name
+                    );
+                    Status::internal(format!("unexpected error: {err}"))
                 })?;
+            let Some(location) = apply_device_profile(profile.as_ref(), location) else {
+                warn!(
+                    "Profile of network device {} doesn't allow its location",
+                    device.name
+                );
+                return Err(Status::permission_denied(
+                    "device profile doesn't allow this location",
+                ));
+            };
 
             if location.service_location_mode != ServiceLocationMode::Disabled {
                 error!(
@@ -140,6 +229,8 @@ pub(crate) async fn build_device_config_response(
             // DEPRECATED(1.5): superseeded by location_mfa_mode
             let mfa_enabled = location.location_mfa_mode == LocationMfaMode::Internal;
             let allowed_ips = get_allowed_ips_for_device(&enterprise_settings, &location).as_csv();
+            let preshared_key =
+                rotated_preshared_key(pool, &location, &wireguard_network_device).await?;
             let config =
                 ProtoDeviceConfig {
                     config: Device::create_config(
@@ -163,6 +254,7 @@ pub(crate) async fn build_device_config_response(
                         )
                         .into(),
                     ),
+                    preshared_key,
                     service_location_mode:
                         Some(
                             <ServiceLocationMode as Into<
@@ -175,6 +267,24 @@ pub(crate) async fn build_device_config_response(
         }
     } else {
         for location in locations {
+            let location_id = location.id;
+            let location = location
+                .apply_device_settings(pool, &device)
+                .await
+                .map_err(|err| {
+                    error!(
+                        "Failed to fetch settings of device {} in location {location_id}: {err}",
+                        device.name
+                    );
+                    Status::internal(format!("unexpected error: {err}"))
+                })?;
+            let Some(location) = apply_device_profile(profile.as_ref(), location) else {
+                debug!(
+                    "Profile of device {} doesn't allow location {location_id}, skipping its configuration",
+                    device.name
+                );
+                continue;
+            };
             let wireguard_network_device = WireguardNetworkDevice::find(
                 pool,
                 device.id,
@@ -208,6 +318,8 @@ pub(crate) async fn build_device_config_response(
             let mfa_enabled = location.location_mfa_mode == LocationMfaMode::Internal;
             let allowed_ips = get_allowed_ips_for_device(&enterprise_settings, &location).as_csv();
             if let Some(wireguard_network_device) = wireguard_network_device {
+                let preshared_key =
+                    rotated_preshared_key(pool, &location, &wireguard_network_device).await?;
                 let config = ProtoDeviceConfig {
                     config: Device::create_config(
                         &location,
@@ -230,6 +342,7 @@ pub(crate) async fn build_device_config_response(
                         )
                         .into(),
                     ),
+                    preshared_key,
                     service_location_mode:
                         Some(
                             <ServiceLocationMode as Into<
@@ -281,3 +394,192 @@ pub(crate) fn parse_client_ip_agent(info: &Option<DeviceInfo>) -> Result<(IpAddr
 
     Ok((ip, escaped_agent))
 }
+
+/// What proxy requests are attributed to, besides the client address.
+pub(crate) enum ProxySession<'a> {
+    /// Enrollment or password reset token.
+    Token(&'a str),
+    /// E-mail address of the user requesting password reset.
+    Email(&'a str),
+}
+
+impl ProxySession<'_> {
+    fn key(&self) -> RateLimitKey {
+        match self {
+            Self::Token(token) => RateLimitKey::Session((*token).to_string()),
+            Self::Email(email) => RateLimitKey::Username(email.to_lowercase()),
+        }
+    }
+
+    async fn find_user(&self, pool: &PgPool) -> Result<Option<User<Id>>, SqlxError> {
+        match self {
+            Self::Token(token) => match Token::find_by_id(pool, token).await {
+                Ok(token) => User::find_by_id(pool, token.user_id).await,
+                Err(_) => Ok(None),
+            },
+            Self::Email(email) => User::find_by_email(pool, email).await,
+        }
+    }
+}
+
+/// Rejects proxy requests exceeding the rate limit of the endpoint. Repeated violations
+/// of clients attributed to a user are recorded in the activity log.
+pub(crate) async fn check_rate_limit(
+    pool: &PgPool,
+    limiter: &Mutex<RateLimiter>,
+    event_tx: &UnboundedSender<BidiStreamEvent>,
+    endpoint: RateLimitedEndpoint,
+    info: &Option<DeviceInfo>,
+    session: ProxySession<'_>,
+) -> Result<(), Status> {
+    let policy = RateLimitPolicy::from(server_config());
+    let ip = info
+        .as_ref()
+        .and_then(|info| IpAddr::from_str(&info.ip_address).ok())
+        .map(|ip| ip.to_canonical());
+    let mut keys = vec![session.key()];
+    keys.extend(ip.map(RateLimitKey::Ip));
+
+    let result = limiter
+        .lock()
+        .expect("Failed to get a lock on rate limiter.")
+        .check(&policy, endpoint, &keys);
+    let Err(limited) = result else {
+        return Ok(());
+    };
+    if limited.rejected_requests == policy.violation_threshold {
+        let ip_address = ip.map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+        warn!(
+            "Client {ip_address} exceeded the {endpoint} rate limit, {} consecutive requests \
+            rejected",
+            limited.rejected_requests
+        );
+        // activity log events are attributed to users, violations of unknown clients are only
+        // logged
+        match session.find_user(pool).await {
+            Ok(Some(user)) => {
+                let user_agent = info
+                    .as_ref()
+                    .and_then(|info| info.user_agent.clone())
+                    .unwrap_or_default();
+                let context = BidiRequestContext::new(
+                    user.id,
+                    user.username,
+                    ip.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+                    user_agent,
+                );
+                let event = BidiStreamEvent {
+                    context,
+                    event: BidiStreamEventType::RateLimitExceeded {
+                        endpoint: endpoint.to_string(),
+                        ip_address,
+                        rejected_requests: limited.rejected_requests,
+                    },
+                };
+                if let Err(err) = event_tx.send(event) {
+                    error!("Failed to record {endpoint} rate limit violation: {err}");
+                }
+            }
+            Ok(None) => (),
+            Err(err) => {
+                error!("Failed to find the user exceeding the {endpoint} rate limit: {err}");
+            }
+        }
+    }
+    Err(Status::resource_exhausted(format!(
+        "too many requests, retry after {} seconds",
+        limited.retry_after_secs()
+    )))
+}
+
+/// Verifies the CAPTCHA solved by the client on the proxy page, if CAPTCHA is enabled in settings.
+pub(crate) async fn check_captcha(
+    info: &Option<DeviceInfo>,
+    token: Option<&str>,
+) -> Result<(), Status> {
+    let ip = info
+        .as_ref()
+        .and_then(|info| IpAddr::from_str(&info.ip_address).ok());
+    match verify_captcha(&Settings::get_current_settings(), token, ip).await {
+        Ok(()) => Ok(()),
+        Err(err @ (CaptchaError::Missing | CaptchaError::Rejected(_))) => {
+            warn!("Rejecting request from {ip:?}: {err}");
+            Err(Status::permission_denied("CAPTCHA verification failed"))
+        }
+        Err(err) => {
+            error!("Failed to verify CAPTCHA: {err}");
+            Err(Status::internal("unexpected error"))
+        }
+    }
+}
+
+/// Checks if enrollment or password reset may be started from the client address
+/// according to [`EnterpriseSettings`] network policy.
+/// Requests with unknown client address are rejected if the policy is in effect.
+pub(crate) async fn self_service_network_allowed(
+    pool: &PgPool,
+    info: &Option<DeviceInfo>,
+) -> Result<bool, Status> {
+    let enterprise_settings = EnterpriseSettings::get(pool).await.map_err(|err| {
+        error!("Failed to get enterprise settings: {err}");
+        Status::internal("unexpected error")
+    })?;
+    if !enterprise_settings.self_service_restricted() {
+        return Ok(true);
+    }
+
+    let Some(ip) = info
+        .as_ref()
+        .and_then(|info| IpAddr::from_str(&info.ip_address).ok())
+    else {
+        warn!("Client address missing in request while self-service network policy is active");
+        return Ok(false);
+    };
+
+    let location_addresses = if enterprise_settings.self_service_allow_vpn {
+        WireguardNetwork::all(pool)
+            .await
+            .map_err(|err| {
+                error!("Failed to fetch all locations: {err}");
+                Status::internal("unexpected error")
+            })?
+            .into_iter()
+            .flat_map(|location| location.address)
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(enterprise_settings.self_service_allows(ip, &location_addresses))
+}
+
+/// Checks a password set during enrollment or password reset against the password policy.
+/// Policy details and violated requirements are returned as JSON in the status message,
+/// so the enrollment frontend can guide the user.
+pub(crate) async fn check_new_password(
+    pool: &PgPool,
+    password: &str,
+    user: &User<Id>,
+) -> Result<(), Status> {
+    match check_password_policy(pool, password, Some(user)).await {
+        Ok(()) => Ok(()),
+        Err(WebError::PasswordPolicy(err)) => {
+            debug!(
+                "Password of user {} not strong enough: {err}",
+                user.username
+            );
+            Err(Status::invalid_argument(
+                serde_json::json!({
+                    "msg": "password not strong enough",
+                    "policy": err.policy,
+                    "violations": err.violations,
+                })
+                .to_string(),
+            ))
+        }
+        Err(err) => {
+            error!("Failed to check password of user {}: {err}", user.username);
+            Err(Status::internal("unexpected error"))
+        }
+    }
+}
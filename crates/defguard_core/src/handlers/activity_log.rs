@@ -1,17 +1,60 @@
-use std::fmt::{self, Display, Formatter};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
 
-use axum::extract::State;
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
 use axum_extra::extract::Query;
-use chrono::{DateTime, NaiveDateTime, Utc};
-use defguard_common::db::Id;
+use base64::prelude::{BASE64_URL_SAFE_NO_PAD, Engine};
+use bytes::Bytes;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, SecondsFormat, TimeDelta, Utc};
+use defguard_common::{db::Id, timezone::localize};
+use futures_util::TryStreamExt;
 use ipnetwork::IpNetwork;
+use serde_json::json;
 use sqlx::{FromRow, Postgres, QueryBuilder, Type};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use utoipa::ToSchema;
 
 use super::{
-    DEFAULT_API_PAGE_SIZE,
-    pagination::{PaginatedApiResponse, PaginatedApiResult, PaginationMeta, PaginationParams},
+    ApiResponse, ApiResult, DEFAULT_API_PAGE_SIZE,
+    pagination::{
+        PaginatedApiResponse, PaginatedApiResult, PaginationParams, get_pagination_metadata,
+    },
+};
+use crate::{
+    appstate::AppState,
+    auth::{AdminRole, SessionInfo},
+    db::models::activity_log::ActivityLogModule,
+    error::WebError,
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
+    server_config,
 };
-use crate::{appstate::AppState, auth::SessionInfo, db::models::activity_log::ActivityLogModule};
+
+// Namespace reserved for events generated by Defguard itself
+const RESERVED_NAMESPACE: &str = "defguard";
+const MAX_CUSTOM_EVENT_NAME_LENGTH: usize = 64;
+const MAX_CUSTOM_EVENT_DESCRIPTION_LENGTH: usize = 1024;
+const MAX_CUSTOM_EVENT_DATA_SIZE: usize = 8 * 1024;
+// Rate limit time window in seconds
+const CUSTOM_EVENT_WINDOW: i64 = 60;
+const ACTIVITY_LOG_SELECT: &str = "SELECT id, timestamp, user_id, username, location, ip, event, module, device, description, request_id, \
+    impersonator_id, impersonator, \
+    EXISTS (SELECT 1 FROM \"user\" u WHERE u.id = activity_log_event.user_id AND u.service_account) service_account \
+    FROM activity_log_event WHERE 1=1 ";
+// Exported rows are sent in chunks of roughly this size
+const EXPORT_CHUNK_SIZE: usize = 64 * 1024;
+// Number of chunks buffered before the database cursor waits for the client
+const EXPORT_CHANNEL_SIZE: usize = 4;
+const CSV_HEADER: &str =
+    "id,timestamp,user_id,username,location,ip,event,module,device,description\r\n";
 
 #[derive(Debug, Deserialize, Default)]
 pub struct FilterParams {
@@ -25,6 +68,11 @@ pub struct FilterParams {
     pub event: Vec<String>,
     #[serde(default = "default_module")]
     pub module: Vec<ActivityLogModule>,
+    // IP addresses or networks, events with IP contained in any of them are matched
+    #[serde(default)]
+    pub ip: Vec<IpNetwork>,
+    // correlates events with logs of a single HTTP request
+    pub request_id: Option<String>,
     pub search: Option<String>,
 }
 
@@ -97,11 +145,61 @@ impl Display for SortOrder {
     }
 }
 
+/// Query params for keyset pagination
+#[derive(Debug, Deserialize, Default)]
+pub struct CursorParams {
+    pub cursor: Option<String>,
+}
+
+/// Position in the activity log used for keyset pagination.
+///
+/// Events are uniquely ordered by `(timestamp, id)`, so the cursor holds both values of the last
+/// returned event. It's exposed to clients as an opaque base64 string.
+#[derive(Debug, PartialEq)]
+struct EventCursor {
+    timestamp: NaiveDateTime,
+    id: Id,
+}
+
+impl EventCursor {
+    fn encode(&self) -> String {
+        BASE64_URL_SAFE_NO_PAD.encode(format!(
+            "{}:{}",
+            self.timestamp.and_utc().timestamp_micros(),
+            self.id
+        ))
+    }
+
+    fn decode(cursor: &str) -> Result<Self, WebError> {
+        let invalid = || WebError::BadRequest("Invalid pagination cursor".into());
+        let decoded = BASE64_URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(invalid)?;
+        let (timestamp, id) = decoded.split_once(':').ok_or_else(invalid)?;
+        let timestamp = timestamp
+            .parse()
+            .ok()
+            .and_then(DateTime::from_timestamp_micros)
+            .ok_or_else(invalid)?;
+        let id = id.parse().map_err(|_| invalid())?;
+
+        Ok(Self {
+            timestamp: timestamp.naive_utc(),
+            id,
+        })
+    }
+}
+
 /// Activity log event with additional info as returned by the API
 #[derive(Serialize, FromRow)]
 pub struct ApiActivityLogEvent {
     pub id: Id,
     pub timestamp: NaiveDateTime,
+    // `timestamp` in timezone of the requesting user
+    #[sqlx(skip)]
+    pub timestamp_local: Option<DateTime<FixedOffset>>,
     pub user_id: Id,
     pub username: String,
     pub location: Option<String>,
@@ -110,19 +208,31 @@ pub struct ApiActivityLogEvent {
     pub module: ActivityLogModule,
     pub device: String,
     pub description: Option<String>,
+    // `X-Request-Id` of the API request which triggered the event
+    pub request_id: Option<String>,
+    // admin who triggered the event while impersonating the user
+    pub impersonator_id: Option<Id>,
+    pub impersonator: Option<String>,
+    // event was triggered by a service account
+    pub service_account: bool,
 }
 
 // TODO: add utoipa API schema
 /// Filtered list of activity log events
 ///
 /// Retrieves a paginated list of activity log events filtered by following query parameters:
-/// TODO: add explanations
-/// - from
-/// - until
-/// - module
-/// - event_type
-/// - username
-/// - search
+/// - `from`, `until`: time range of events
+/// - `module`: Defguard, client, VPN, enrollment or custom module
+/// - `event`: event type
+/// - `username`
+/// - `location`
+/// - `ip`: IP addresses or networks in CIDR notation
+/// - `request_id`: identifier of the API request which triggered the event
+/// - `search`: term matched against most of the event fields
+///
+/// Results can be paginated either by `page` number or, when sorted by timestamp, with `cursor`
+/// returned as `next_cursor` in previous response. Cursor pagination remains stable when new
+/// events are added and doesn't slow down on deep pages, so it's preferred for scripts.
 ///
 /// # Returns
 /// Returns a paginated list of `ApiActivityLogEvent` objects or `WebError` if error occurs.
@@ -130,53 +240,97 @@ pub async fn get_activity_log_events(
     session_info: SessionInfo,
     State(appstate): State<AppState>,
     pagination: Query<PaginationParams>,
+    cursor: Query<CursorParams>,
     filters: Query<FilterParams>,
     sorting: Query<SortParams>,
 ) -> PaginatedApiResult<ApiActivityLogEvent> {
-    debug!("Fetching activity log with filters {filters:?} and pagination {pagination:?}");
+    debug!(
+        "Fetching activity log with filters {filters:?}, pagination {pagination:?} and cursor {cursor:?}"
+    );
+    let cursor = cursor
+        .cursor
+        .as_deref()
+        .map(EventCursor::decode)
+        .transpose()?;
+    let sorted_by_timestamp = matches!(sorting.sort_by, SortKey::Timestamp);
+    if cursor.is_some() && !sorted_by_timestamp {
+        return Err(WebError::BadRequest(
+            "Cursor pagination requires sorting by timestamp".into(),
+        ));
+    }
+
     // start with base SELECT query
     // dummy WHERE filter is use to enable composable filtering
-    let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
-        "SELECT id, timestamp, user_id, username, location, ip, event, module, device, description FROM activity_log_event WHERE 1=1 ",
-    );
+    let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(ACTIVITY_LOG_SELECT);
+
+    // timestamps are additionally returned in the user's timezone
+    let timezone = session_info.user.effective_timezone(&appstate.pool).await?;
 
     // filter events for non-admin users to show only their own events
-    if !session_info.is_admin {
-        query_builder
-            .push(" AND username = ")
-            .push_bind(session_info.user.username)
-            .push(" ");
-    }
+    apply_user_scope(&mut query_builder, &session_info);
 
     // add optional filters
     apply_filters(&mut query_builder, &filters);
 
+    // skip events up to the cursor
+    if let Some(cursor) = &cursor {
+        let operator = match sorting.sort_order {
+            SortOrder::Asc => ">",
+            SortOrder::Desc => "<",
+        };
+        query_builder
+            .push(format!(" AND (timestamp, id) {operator} ("))
+            .push_bind(cursor.timestamp)
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(") ");
+    }
+
     // apply ordering
     apply_sorting(&mut query_builder, &sorting);
 
     // add limit and offset to fetch a specific page
     let limit = DEFAULT_API_PAGE_SIZE;
     query_builder.push(" LIMIT ").push_bind(i64::from(limit));
-    let offset = (pagination.page - 1) * DEFAULT_API_PAGE_SIZE;
-    query_builder.push(" OFFSET ").push_bind(i64::from(offset));
+    if cursor.is_none() {
+        let offset = (pagination.page - 1) * DEFAULT_API_PAGE_SIZE;
+        query_builder.push(" OFFSET ").push_bind(i64::from(offset));
+    }
 
     // fetch filtered events
-    let events = query_builder
+    let mut events = query_builder
         .build_query_as::<ApiActivityLogEvent>()
-        .fetch_all(&appstate.pool)
+        .fetch_all(appstate.read_pool())
         .await?;
+    for event in &mut events {
+        event.timestamp_local = Some(localize(event.timestamp, timezone));
+    }
+
+    // cursor is returned only if there may be more events to fetch
+    let next_cursor = if sorted_by_timestamp && events.len() == limit as usize {
+        events.last().map(|event| {
+            EventCursor {
+                timestamp: event.timestamp,
+                id: event.id,
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
 
     // execute count query
     // fetch total number of filtered events
     let mut count_query_builder: QueryBuilder<Postgres> =
         QueryBuilder::new("SELECT COUNT(*) FROM activity_log_event WHERE 1=1 ");
+    apply_user_scope(&mut count_query_builder, &session_info);
     apply_filters(&mut count_query_builder, &filters);
     let total_items: i64 = count_query_builder
         .build_query_scalar()
-        .fetch_one(&appstate.pool)
+        .fetch_one(appstate.read_pool())
         .await?;
 
-    let pagination = get_pagination_metadata(pagination.page, total_items as u32);
+    let pagination = get_pagination_metadata(pagination.page, total_items as u32, next_cursor);
 
     Ok(PaginatedApiResponse {
         data: events,
@@ -184,6 +338,182 @@ pub async fn get_activity_log_events(
     })
 }
 
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Jsonl,
+}
+
+impl ExportFormat {
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Jsonl => "application/x-ndjson",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+        }
+    }
+
+    /// Appends a single event in this format to the output buffer.
+    fn write_event(self, buffer: &mut String, event: &ApiActivityLogEvent) {
+        match self {
+            Self::Csv => {
+                let module = serde_json::to_value(&event.module)
+                    .ok()
+                    .and_then(|value| value.as_str().map(ToString::to_string))
+                    .unwrap_or_default();
+                let fields = [
+                    event.id.to_string(),
+                    event
+                        .timestamp
+                        .and_utc()
+                        .to_rfc3339_opts(SecondsFormat::Micros, true),
+                    event.user_id.to_string(),
+                    event.username.clone(),
+                    event.location.clone().unwrap_or_default(),
+                    event.ip.ip().to_string(),
+                    event.event.clone(),
+                    module,
+                    event.device.clone(),
+                    event.description.clone().unwrap_or_default(),
+                ];
+                let line = fields
+                    .iter()
+                    .map(|field| escape_csv_field(field))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                buffer.push_str(&line);
+                buffer.push_str("\r\n");
+            }
+            Self::Jsonl => match serde_json::to_string(event) {
+                Ok(line) => {
+                    buffer.push_str(&line);
+                    buffer.push('\n');
+                }
+                Err(err) => error!("Failed to serialize activity log event {}: {err}", event.id),
+            },
+        }
+    }
+}
+
+/// Quotes CSV field if needed and neutralizes values which spreadsheets would evaluate as
+/// formulas.
+fn escape_csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{value}")
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Query params for activity log export
+#[derive(Debug, Deserialize)]
+pub struct ExportParams {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Export filtered activity log events
+///
+/// Streams all activity log events matching the same filters as `get_activity_log_events`
+/// as CSV or JSON Lines. Rows are read from a database cursor and sent in chunks, so the full
+/// result set is never loaded into memory.
+///
+/// # Returns
+/// Returns a chunked response with exported events or `WebError` if error occurs.
+pub async fn export_activity_log_events(
+    session_info: SessionInfo,
+    State(appstate): State<AppState>,
+    export: Query<ExportParams>,
+    filters: Query<FilterParams>,
+    sorting: Query<SortParams>,
+) -> Result<Response, WebError> {
+    let format = export.format;
+    info!(
+        "User {} is exporting activity log as {format:?} with filters {filters:?}",
+        session_info.user.username
+    );
+    let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(ACTIVITY_LOG_SELECT);
+    apply_user_scope(&mut query_builder, &session_info);
+    apply_filters(&mut query_builder, &filters);
+    apply_sorting(&mut query_builder, &sorting);
+
+    let (tx, rx) = mpsc::channel::<Result<Bytes, std::io::Error>>(EXPORT_CHANNEL_SIZE);
+    let pool = appstate.read_pool().clone();
+    tokio::spawn(async move {
+        let mut rows = query_builder
+            .build_query_as::<ApiActivityLogEvent>()
+            .fetch(&pool);
+        let mut buffer = String::new();
+        if matches!(format, ExportFormat::Csv) {
+            buffer.push_str(CSV_HEADER);
+        }
+        loop {
+            match rows.try_next().await {
+                Ok(Some(event)) => {
+                    format.write_event(&mut buffer, &event);
+                    if buffer.len() < EXPORT_CHUNK_SIZE {
+                        continue;
+                    }
+                }
+                Ok(None) => {
+                    if !buffer.is_empty() {
+                        let _ = tx.send(Ok(Bytes::from(buffer))).await;
+                    }
+                    break;
+                }
+                Err(err) => {
+                    error!("Failed to fetch activity log events for export: {err}");
+                    // aborts the response so the client doesn't get a truncated file silently
+                    let _ = tx.send(Err(std::io::Error::other(err))).await;
+                    break;
+                }
+            }
+            let chunk = Bytes::from(std::mem::take(&mut buffer));
+            if tx.send(Ok(chunk)).await.is_err() {
+                debug!("Activity log export cancelled by the client");
+                break;
+            }
+        }
+    });
+
+    let filename = format!(
+        "attachment; filename=\"activity_log_{}.{}\"",
+        Utc::now().format("%Y%m%d%H%M%S"),
+        format.extension()
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, filename),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response())
+}
+
+/// Limits events visible to non-admin users to their own events
+fn apply_user_scope(query_builder: &mut QueryBuilder<Postgres>, session_info: &SessionInfo) {
+    if !session_info.is_admin {
+        query_builder
+            .push(" AND username = ")
+            .push_bind(session_info.user.username.clone())
+            .push(" ");
+    }
+}
+
 /// Adds optional filtering statements to SQL query based on request query params
 fn apply_filters(query_builder: &mut QueryBuilder<Postgres>, filters: &FilterParams) {
     debug!("Applying query filters: {filters:?}");
@@ -232,6 +562,21 @@ fn apply_filters(query_builder: &mut QueryBuilder<Postgres>, filters: &FilterPar
             .push(") ");
     }
 
+    // IP filter
+    if !filters.ip.is_empty() {
+        query_builder
+            .push(" AND ip <<= ANY(")
+            .push_bind(filters.ip.clone())
+            .push(") ");
+    }
+
+    // request ID filter
+    if let Some(request_id) = &filters.request_id {
+        query_builder
+            .push(" AND request_id = ")
+            .push_bind(request_id.clone());
+    }
+
     // search by provided term
     // following columns are supported:
     // - username
@@ -256,23 +601,209 @@ fn apply_sorting(query_builder: &mut QueryBuilder<Postgres>, sorting: &SortParam
         .push(" ORDER BY ")
         .push(sorting.sort_by.to_string())
         .push(" ")
+        .push(sorting.sort_order.to_string())
+        // event ID makes the order deterministic for events with equal sort key
+        .push(", id ")
         .push(sorting.sort_order.to_string());
 }
 
-/// Prepares pagination metadata that's part of the response
-fn get_pagination_metadata(current_page: u32, total_items: u32) -> PaginationMeta {
-    let total_pages = (total_items).div_ceil(DEFAULT_API_PAGE_SIZE);
-    let next_page = if current_page < total_pages {
-        Some(current_page + 1)
-    } else {
-        None
-    };
+/// Tracks how many custom events each user submitted in the current time window.
+#[derive(Default)]
+pub(crate) struct CustomEventRateLimiter(HashMap<Id, (DateTime<Utc>, u32)>);
+
+impl CustomEventRateLimiter {
+    /// Registers a new event for given user.
+    /// Returns `false` if the user already reached `limit` events in the current window.
+    fn try_acquire(&mut self, user_id: Id, limit: u32) -> bool {
+        self.try_acquire_at(user_id, limit, Utc::now())
+    }
+
+    fn try_acquire_at(&mut self, user_id: Id, limit: u32, now: DateTime<Utc>) -> bool {
+        let window = TimeDelta::seconds(CUSTOM_EVENT_WINDOW);
+        // forget users whose window has passed, so that the map doesn't grow indefinitely
+        self.0
+            .retain(|_, (window_start, _)| now.signed_duration_since(*window_start) <= window);
+        let (_, count) = self.0.entry(user_id).or_insert((now, 0));
+        if *count >= limit {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
+
+/// Custom activity log event submitted by an external integration
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CustomActivityLogEvent {
+    pub namespace: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub data: Option<serde_json::Value>,
+}
+
+/// Checks if namespace or event name contains only lowercase letters, digits, `_` and `-`.
+fn is_valid_custom_event_identifier(value: &str) -> bool {
+    (1..=MAX_CUSTOM_EVENT_NAME_LENGTH).contains(&value.len())
+        && value
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && value
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+impl CustomActivityLogEvent {
+    fn validate(&self) -> Result<(), WebError> {
+        if !is_valid_custom_event_identifier(&self.namespace) {
+            return Err(WebError::BadRequest(format!(
+                "Invalid custom event namespace: {}",
+                self.namespace
+            )));
+        }
+        if self.namespace == RESERVED_NAMESPACE {
+            return Err(WebError::BadRequest(format!(
+                "Namespace {RESERVED_NAMESPACE} is reserved"
+            )));
+        }
+        if !is_valid_custom_event_identifier(&self.name) {
+            return Err(WebError::BadRequest(format!(
+                "Invalid custom event name: {}",
+                self.name
+            )));
+        }
+        if self
+            .description
+            .as_ref()
+            .is_some_and(|description| description.len() > MAX_CUSTOM_EVENT_DESCRIPTION_LENGTH)
+        {
+            return Err(WebError::BadRequest(
+                "Custom event description is too long".into(),
+            ));
+        }
+        if let Some(data) = &self.data {
+            if !data.is_object() {
+                return Err(WebError::BadRequest(
+                    "Custom event data must be a JSON object".into(),
+                ));
+            }
+            if data.to_string().len() > MAX_CUSTOM_EVENT_DATA_SIZE {
+                return Err(WebError::BadRequest(
+                    "Custom event data is too large".into(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Add custom activity log event
+///
+/// Allows trusted integrations (e.g. badge readers, ticketing systems) to add their own events
+/// to the activity log. Events are namespaced and stored in the `custom` module.
+/// Number of events a single user can submit is rate limited.
+///
+/// # Returns
+/// Returns empty response with status 201 or `WebError` if error occurs.
+#[utoipa::path(
+    post,
+    path = "/api/v1/activity_log/custom",
+    request_body = CustomActivityLogEvent,
+    responses(
+        (status = 201, description = "Custom event added to the activity log.", body = ApiResponse, example = json!({})),
+        (status = 400, description = "Invalid namespace, name, description or data.", body = ApiResponse, example = json!({"msg": "Namespace defguard is reserved"})),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden - Admin role required"),
+        (status = 429, description = "Custom event rate limit exceeded."),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub async fn create_custom_event(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Json(data): Json<CustomActivityLogEvent>,
+) -> ApiResult {
+    debug!(
+        "User {} is adding custom activity log event {}.{}",
+        session.user.username, data.namespace, data.name
+    );
+    data.validate()?;
+
+    let limit = server_config().custom_event_rate_limit;
+    if !appstate
+        .custom_event_limits
+        .lock()
+        .expect("Failed to lock custom event rate limiter")
+        .try_acquire(session.user.id, limit)
+    {
+        warn!(
+            "User {} exceeded custom activity log event rate limit",
+            session.user.username
+        );
+        return Err(WebError::Http(StatusCode::TOO_MANY_REQUESTS));
+    }
+
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::CustomEvent {
+            namespace: data.namespace,
+            name: data.name,
+            description: data.description,
+            data: data.data,
+        }),
+    })?;
+
+    Ok(ApiResponse::new(json!({}), StatusCode::CREATED))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_escape_csv_field() {
+        assert_eq!(escape_csv_field("admin"), "admin");
+        assert_eq!(escape_csv_field("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv_field("line\nbreak"), "\"line\nbreak\"");
+        assert_eq!(escape_csv_field("=SUM(A1)"), "'=SUM(A1)");
+        assert_eq!(escape_csv_field(""), "");
+    }
+
+    #[test]
+    fn test_event_cursor() {
+        let cursor = EventCursor {
+            timestamp: DateTime::from_timestamp_micros(1_700_000_000_123_456)
+                .unwrap()
+                .naive_utc(),
+            id: 42,
+        };
+        assert_eq!(EventCursor::decode(&cursor.encode()).unwrap(), cursor);
+
+        assert!(EventCursor::decode("not a cursor").is_err());
+        assert!(EventCursor::decode(&BASE64_URL_SAFE_NO_PAD.encode("123")).is_err());
+        assert!(EventCursor::decode(&BASE64_URL_SAFE_NO_PAD.encode("abc:1")).is_err());
+    }
 
-    PaginationMeta {
-        current_page,
-        page_size: DEFAULT_API_PAGE_SIZE,
-        total_items,
-        total_pages,
-        next_page,
+    #[test]
+    fn test_custom_event_rate_limiter() {
+        let mut limiter = CustomEventRateLimiter::default();
+        let start = Utc::now();
+        assert!(limiter.try_acquire_at(1, 2, start));
+        assert!(limiter.try_acquire_at(1, 2, start));
+        assert!(!limiter.try_acquire_at(1, 2, start));
+        assert!(limiter.try_acquire_at(2, 2, start));
+
+        // a new window starts and stale entries are evicted
+        let later = start + TimeDelta::seconds(CUSTOM_EVENT_WINDOW + 1);
+        assert!(limiter.try_acquire_at(1, 2, later));
+        assert_eq!(limiter.0.len(), 1);
     }
 }
@@ -43,6 +43,7 @@ pub struct AppInfo {
     version: String,
     network_present: bool,
     smtp_enabled: bool,
+    sms_enabled: bool,
     license_info: LicenseInfo,
     ldap_info: LdapInfo,
     external_openid_enabled: bool,
@@ -67,6 +68,7 @@ pub(crate) async fn get_app_info(
     let res = AppInfo {
         network_present: !networks.is_empty(),
         smtp_enabled: settings.smtp_configured(),
+        sms_enabled: settings.sms_configured(),
         version: VERSION.into(),
         license_info: LicenseInfo {
             enterprise,
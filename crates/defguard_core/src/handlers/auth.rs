@@ -1,10 +1,11 @@
 use std::net::IpAddr;
 
 use axum::{
+    Extension,
     extract::{Json, Path, State},
     http::StatusCode,
 };
-use axum_client_ip::InsecureClientIp;
+use axum_client_ip::SecureClientIp;
 use axum_extra::{
     TypedHeader,
     extract::{
@@ -19,7 +20,7 @@ use defguard_common::db::{
 };
 use defguard_mail::Mail;
 use serde_json::json;
-use sqlx::{PgPool, types::Uuid};
+use sqlx::{PgConnection, PgPool, types::Uuid};
 use time::Duration;
 use tokio::sync::mpsc::UnboundedSender;
 use uaparser::Parser;
@@ -28,29 +29,60 @@ use webauthn_rs_proto::options::CollectedClientData;
 
 use super::{
     ApiResponse, ApiResult, Auth, AuthCode, AuthResponse, AuthTotp, RecoveryCode, RecoveryCodes,
-    SESSION_COOKIE_NAME, WebAuthnRegistration,
+    RecoveryCodesInfo, SESSION_COOKIE_NAME, SmsMfaSetup, WebAuthnRegistration,
 };
 use crate::{
     appstate::AppState,
     auth::{
         SessionInfo,
-        failed_login::{check_failed_logins, log_failed_login_attempt},
+        break_glass::{check_break_glass_login, record_failed_break_glass_login},
+        client_ip::ClientIp,
+        email_mfa::check_email_mfa_resend,
+        failed_login::{FailedLoginError, check_failed_logins, log_failed_login_attempt},
+        login_lockout::{
+            check_login_allowed, check_user_lockout, record_failed_login, reset_failed_logins,
+        },
+    },
+    db::{
+        MFAInfo, Session, SessionState, User, UserInfo, WebAuthn,
+        models::{impersonation::Impersonation, sms_mfa::SmsMfa},
+    },
+    enterprise::{
+        handlers::saml::complete_saml_login,
+        ldap::{error::LdapError, utils::login_through_ldap},
+        provisioning::{
+            check_pending_approval, emit_account_creation_refused, emit_user_provisioned,
+        },
     },
-    db::{MFAInfo, Session, SessionState, User, UserInfo, WebAuthn},
-    enterprise::ldap::utils::login_through_ldap,
     error::WebError,
     events::{ApiEvent, ApiEventType, ApiRequestContext},
     handlers::{
         SIGN_IN_COOKIE_NAME,
+        impersonation::{IMPERSONATION_COOKIE_NAME, end_impersonation},
         mail::{
             send_email_mfa_activation_email, send_email_mfa_code_email, send_mfa_configured_email,
         },
         user_for_admin_or_self,
     },
     headers::{USER_AGENT_PARSER, check_new_device_login, get_user_agent_device},
-    server_config,
+    is_valid_phone_number, server_config,
+    sms::send_sms,
 };
 
+/// Marks the session as MFA verified and finishes the login: resets failed login counters and
+/// applies group membership of a pending SAML login.
+async fn complete_mfa_login(
+    appstate: &AppState,
+    session: &mut Session,
+    ip: IpAddr,
+) -> Result<(), WebError> {
+    session
+        .set_state(&appstate.pool, SessionState::MultiFactorVerified)
+        .await?;
+    reset_failed_logins(appstate, session.user_id, ip).await?;
+    complete_saml_login(appstate, &session.id).await
+}
+
 /// Common functionality for `authenticate()` and `auth_callback()`.
 /// Returns either `AuthResponse` or `MFAInfo`.
 pub(crate) async fn create_session(
@@ -135,44 +167,67 @@ pub(crate) async fn authenticate(
     cookies: CookieJar,
     mut private_cookies: PrivateCookieJar,
     user_agent: TypedHeader<UserAgent>,
-    InsecureClientIp(insecure_ip): InsecureClientIp,
+    client_ip: ClientIp,
     State(appstate): State<AppState>,
     Json(data): Json<Auth>,
 ) -> Result<(CookieJar, PrivateCookieJar, ApiResponse), WebError> {
+    let ip = client_ip.ip;
     let username_or_email = data.username;
     debug!("Authenticating user {username_or_email}");
 
     // check if user can proceed with login
-    check_failed_logins(&appstate.failed_logins, &username_or_email)?;
+    check_login_allowed(&appstate, &username_or_email, client_ip).await?;
 
     let settings = Settings::get_current_settings();
 
     // Attempt to find a user: first by username, and then by email.
     let mut conn = appstate.pool.acquire().await?;
-    let mut user = if let Some(user) =
+    let mut locked_out = false;
+    let mut user = if let Some(mut user) =
         User::find_by_username_or_email(&mut conn, &username_or_email).await?
     {
+        // failed attempts to use the break-glass account are limited per client and in total
+        let break_glass = user.is_break_glass(&mut *conn).await?;
+        if break_glass {
+            check_break_glass_login(client_ip.limited())?;
+        }
+        locked_out = check_user_lockout(&appstate, &user).await?;
         // user was found, attempt to authenticate by password first
         match user.verify_password(&data.password) {
-            Ok(()) => user,
+            Ok(()) => {
+                upgrade_password_hash(&mut conn, &mut user, &data.password).await;
+                user
+            }
             Err(err) => {
                 // password authentication failed, try authenticating with LDAP if configured
                 if settings.ldap_enabled {
                     match login_through_ldap(&appstate.pool, &username_or_email, &data.password)
                         .await
                     {
-                        Ok(user) => user,
+                        Ok((mut user, provisioning)) => {
+                            upgrade_password_hash(&mut conn, &mut user, &data.password).await;
+                            if let Some(provisioning) = provisioning {
+                                emit_user_provisioned(
+                                    &appstate,
+                                    &user,
+                                    provisioning,
+                                    ip,
+                                    user_agent.as_str(),
+                                )?;
+                                check_pending_approval(&user)?;
+                            }
+                            user
+                        }
                         Err(ldap_err) => {
                             warn!(
                                 "Failed to authenticate user {username_or_email} internally and through LDAP. Internal error: {err}, LDAP error: {ldap_err}"
                             );
 
-                            log_failed_login_attempt(&appstate.failed_logins, &user.username);
                             appstate.emit_event(ApiEvent {
                             context: ApiRequestContext::new(
                                 user.id,
-                                user.username,
-                                insecure_ip,
+                                user.username.clone(),
+                                ip,
                                 user_agent.to_string(),
                             ),
                             event: Box::new(ApiEventType::UserLoginFailed {
@@ -181,17 +236,27 @@ pub(crate) async fn authenticate(
                                 ),
                             }),
                         })?;
+                            if break_glass {
+                                record_failed_break_glass_login(client_ip.limited());
+                            }
+                            record_failed_login(
+                                &appstate,
+                                &user.username,
+                                Some(&user),
+                                client_ip,
+                                user_agent.as_str(),
+                            )
+                            .await?;
                             return Err(WebError::Authentication);
                         }
                     }
                 } else {
                     warn!("Failed to authenticate user {username_or_email}: {err}");
-                    log_failed_login_attempt(&appstate.failed_logins, &user.username);
                     appstate.emit_event(ApiEvent {
                         context: ApiRequestContext::new(
                             user.id,
-                            user.username,
-                            insecure_ip,
+                            user.username.clone(),
+                            ip,
                             user_agent.to_string(),
                         ),
                         event: Box::new(ApiEventType::UserLoginFailed {
@@ -200,6 +265,17 @@ pub(crate) async fn authenticate(
                             ),
                         }),
                     })?;
+                    if break_glass {
+                        record_failed_break_glass_login(client_ip.limited());
+                    }
+                    record_failed_login(
+                        &appstate,
+                        &user.username,
+                        Some(&user),
+                        client_ip,
+                        user_agent.as_str(),
+                    )
+                    .await?;
                     return Err(WebError::Authentication);
                 }
             }
@@ -208,10 +284,27 @@ pub(crate) async fn authenticate(
         // try to create user from LDAP
         debug!("User not found in DB, authenticating user {username_or_email} with LDAP");
         match login_through_ldap(&appstate.pool, &username_or_email, &data.password).await {
-            Ok(user) => user,
+            Ok((mut user, provisioning)) => {
+                upgrade_password_hash(&mut conn, &mut user, &data.password).await;
+                if let Some(provisioning) = provisioning {
+                    emit_user_provisioned(&appstate, &user, provisioning, ip, user_agent.as_str())?;
+                    check_pending_approval(&user)?;
+                }
+                user
+            }
             Err(err) => {
                 info!("Failed to authenticate user {username_or_email} with LDAP: {err}");
-                log_failed_login_attempt(&appstate.failed_logins, &username_or_email);
+                if let LdapError::AccountCreationRefused(refused) = &err {
+                    emit_account_creation_refused(&appstate, refused, ip, user_agent.as_str())?;
+                }
+                record_failed_login(
+                    &appstate,
+                    &username_or_email,
+                    None,
+                    client_ip,
+                    user_agent.as_str(),
+                )
+                .await?;
                 return Err(WebError::Authentication);
             }
         }
@@ -223,10 +316,42 @@ pub(crate) async fn authenticate(
         return Err(WebError::Authentication);
     }
 
+    // service accounts can't log into the web UI
+    if user.is_service_account(&appstate.pool).await? {
+        info!("Failed to authenticate user {username_or_email}: user is a service account");
+        return Err(WebError::Authentication);
+    }
+
+    if user.is_break_glass(&appstate.pool).await? {
+        warn!(
+            "Break-glass admin account {} is used to log in",
+            user.username
+        );
+        appstate.emit_event(ApiEvent {
+            context: ApiRequestContext::new(
+                user.id,
+                user.username.clone(),
+                ip,
+                user_agent.to_string(),
+            ),
+            event: Box::new(ApiEventType::BreakGlassLogin),
+        })?;
+    }
+
+    // locked out users can only log in with MFA, which they have to complete for the lockout
+    // to be lifted
+    if locked_out {
+        user.verify_mfa_state(&appstate.pool).await?;
+        if !user.mfa_enabled {
+            info!("Preventing locked out user {username_or_email} without MFA from logging in");
+            return Err(FailedLoginError.into());
+        }
+    }
+
     let (session, user_info, mfa_info) = create_session(
         &appstate.pool,
         &appstate.mail_tx,
-        insecure_ip,
+        ip,
         user_agent.as_str(),
         &mut user,
     )
@@ -259,6 +384,7 @@ pub(crate) async fn authenticate(
     }
 
     if let Some(user_info) = user_info {
+        reset_failed_logins(&appstate, user.id, ip).await?;
         let url = if let Some(openid_cookie) = private_cookies.get(SIGN_IN_COOKIE_NAME) {
             debug!("Found OpenID session cookie, returning the redirect URL stored in it.");
             let url = openid_cookie.value().to_string();
@@ -273,7 +399,7 @@ pub(crate) async fn authenticate(
             context: ApiRequestContext::new(
                 user_info.id,
                 user_info.username.clone(),
-                insecure_ip,
+                ip,
                 user_agent.to_string(),
             ),
             event: Box::new(ApiEventType::UserLogin),
@@ -296,15 +422,25 @@ pub(crate) async fn authenticate(
 }
 
 /// Logout - forget the session cookie.
+///
+/// Logging out while impersonating a user stops the impersonation and keeps the admin logged in.
 pub async fn logout(
     cookies: CookieJar,
+    private_cookies: PrivateCookieJar,
     session: Session,
+    impersonation: Option<Extension<Impersonation>>,
     user_agent: TypedHeader<UserAgent>,
-    InsecureClientIp(insecure_ip): InsecureClientIp,
+    SecureClientIp(ip): SecureClientIp,
     State(appstate): State<AppState>,
-) -> Result<(CookieJar, ApiResponse), WebError> {
-    // remove auth cookie
+) -> Result<(CookieJar, PrivateCookieJar, ApiResponse), WebError> {
+    if let Some(Extension(impersonation)) = impersonation {
+        end_impersonation(&appstate, impersonation, ip, &user_agent.to_string(), false).await?;
+        let private_cookies = private_cookies.remove(Cookie::from(IMPERSONATION_COOKIE_NAME));
+        return Ok((cookies, private_cookies, ApiResponse::default()));
+    }
+    // remove auth cookie, impersonation started from this session ends along with it
     let cookies = cookies.remove(Cookie::from(SESSION_COOKIE_NAME));
+    let private_cookies = private_cookies.remove(Cookie::from(IMPERSONATION_COOKIE_NAME));
     let user = User::find_by_id(&appstate.pool, session.user_id)
         .await?
         .ok_or_else(|| WebError::BadRequest(format!("User {} does not exist", session.user_id)))?;
@@ -315,16 +451,11 @@ pub async fn logout(
         // User may not be fully authenticated so we can't use
         // context extractor in this handler since it requires
         // the `SessionInfo` object.
-        context: ApiRequestContext::new(
-            user.id,
-            user.username,
-            insecure_ip,
-            user_agent.to_string(),
-        ),
+        context: ApiRequestContext::new(user.id, user.username, ip, user_agent.to_string()),
         event: Box::new(ApiEventType::UserLogout),
     })?;
 
-    Ok((cookies, ApiResponse::default()))
+    Ok((cookies, private_cookies, ApiResponse::default()))
 }
 
 /// Enable MFA
@@ -519,7 +650,7 @@ pub async fn webauthn_end(
     private_cookies: PrivateCookieJar,
     mut session: Session,
     user_agent: TypedHeader<UserAgent>,
-    InsecureClientIp(insecure_ip): InsecureClientIp,
+    SecureClientIp(ip): SecureClientIp,
     State(appstate): State<AppState>,
     Json(pubkey): Json<PublicKeyCredential>,
 ) -> Result<(PrivateCookieJar, ApiResponse), WebError> {
@@ -540,9 +671,7 @@ pub async fn webauthn_end(
                     }
                 }
 
-                session
-                    .set_state(&appstate.pool, SessionState::MultiFactorVerified)
-                    .await?;
+                complete_mfa_login(&appstate, &mut session, ip).await?;
 
                 return if let Some(user) = User::find_by_id(&appstate.pool, session.user_id).await?
                 {
@@ -554,7 +683,7 @@ pub async fn webauthn_end(
                         context: ApiRequestContext::new(
                             user.id,
                             user.username,
-                            insecure_ip,
+                            ip,
                             user_agent.to_string(),
                         ),
                         event: Box::new(ApiEventType::UserMfaLogin {
@@ -602,7 +731,7 @@ pub async fn webauthn_end(
                         context: ApiRequestContext::new(
                             user.id,
                             user.username,
-                            insecure_ip,
+                            ip,
                             user_agent.to_string(),
                         ),
                         event: Box::new(ApiEventType::UserMfaLoginFailed {
@@ -624,8 +753,13 @@ pub async fn totp_secret(session: SessionInfo, State(appstate): State<AppState>)
 
     let secret = user.new_totp_secret(&appstate.pool).await?;
     info!("Generated new TOTP secret for user {}", user.username);
+    let settings = Settings::get_current_settings();
     Ok(ApiResponse {
-        json: json!(AuthTotp::new(secret)),
+        json: json!(AuthTotp::new(
+            secret,
+            settings.totp_algorithm,
+            settings.totp_digits
+        )),
         status: StatusCode::OK,
     })
 }
@@ -690,7 +824,7 @@ pub async fn totp_code(
     private_cookies: PrivateCookieJar,
     mut session: Session,
     user_agent: TypedHeader<UserAgent>,
-    InsecureClientIp(insecure_ip): InsecureClientIp,
+    SecureClientIp(ip): SecureClientIp,
     State(appstate): State<AppState>,
     Json(data): Json<AuthCode>,
 ) -> Result<(PrivateCookieJar, ApiResponse), WebError> {
@@ -701,21 +835,14 @@ pub async fn totp_code(
 
         debug!("Verifying TOTP for user {}", username);
         if user.totp_enabled && user.verify_totp_code(&data.code) {
-            session
-                .set_state(&appstate.pool, SessionState::MultiFactorVerified)
-                .await?;
+            complete_mfa_login(&appstate, &mut session, ip).await?;
             let user_info = UserInfo::from_user(&appstate.pool, &user).await?;
             info!("Verified TOTP for user {username}");
             appstate.emit_event(ApiEvent {
                 // User may not be fully authenticated so we can't use
                 // context extractor in this handler since it requires
                 // the `SessionInfo` object.
-                context: ApiRequestContext::new(
-                    user.id,
-                    user.username,
-                    insecure_ip,
-                    user_agent.to_string(),
-                ),
+                context: ApiRequestContext::new(user.id, user.username, ip, user_agent.to_string()),
                 event: Box::new(ApiEventType::UserMfaLogin {
                     mfa_method: MFAMethod::OneTimePassword,
                 }),
@@ -759,12 +886,7 @@ pub async fn totp_code(
                 // User may not be fully authenticated so we can't use
                 // context extractor in this handler since it requires
                 // the `SessionInfo` object.
-                context: ApiRequestContext::new(
-                    user.id,
-                    user.username,
-                    insecure_ip,
-                    user_agent.to_string(),
-                ),
+                context: ApiRequestContext::new(user.id, user.username, ip, user_agent.to_string()),
                 event: Box::new(ApiEventType::UserMfaLoginFailed {
                     mfa_method: MFAMethod::OneTimePassword,
                     message,
@@ -861,7 +983,13 @@ pub async fn request_email_mfa_code(
     if let Some(user) = User::find_by_id(&appstate.pool, session.user_id).await? {
         debug!("Sending email MFA code for user {}", user.username);
         if user.email_mfa_enabled {
-            send_email_mfa_code_email(&user, &appstate.mail_tx, Some(&session.into()))?;
+            send_email_mfa_code_email(
+                &appstate.pool,
+                &user,
+                &appstate.mail_tx,
+                Some(&session.into()),
+            )
+            .await?;
             info!("Sent email MFA code for user {}", user.username);
             Ok(ApiResponse::default())
         } else {
@@ -872,12 +1000,38 @@ pub async fn request_email_mfa_code(
     }
 }
 
+/// Send a new email code to user, invalidating previously sent codes
+pub async fn resend_email_mfa_code(
+    session: Session,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    let Some(mut user) = User::find_by_id(&appstate.pool, session.user_id).await? else {
+        return Err(WebError::ObjectNotFound("Invalid user".into()));
+    };
+    if !user.email_mfa_enabled {
+        return Err(WebError::Authorization("Email MFA not enabled".into()));
+    }
+    check_email_mfa_resend(user.id)?;
+    debug!("Resending email MFA code for user {}", user.username);
+    // rotate the secret, so codes sent before are no longer valid
+    user.new_email_secret(&appstate.pool).await?;
+    send_email_mfa_code_email(
+        &appstate.pool,
+        &user,
+        &appstate.mail_tx,
+        Some(&session.into()),
+    )
+    .await?;
+    info!("Resent email MFA code for user {}", user.username);
+    Ok(ApiResponse::default())
+}
+
 /// Validate email MFA code
 pub async fn email_mfa_code(
     private_cookies: PrivateCookieJar,
     mut session: Session,
     user_agent: TypedHeader<UserAgent>,
-    InsecureClientIp(insecure_ip): InsecureClientIp,
+    SecureClientIp(ip): SecureClientIp,
     State(appstate): State<AppState>,
     Json(data): Json<AuthCode>,
 ) -> Result<(PrivateCookieJar, ApiResponse), WebError> {
@@ -889,21 +1043,14 @@ pub async fn email_mfa_code(
 
         debug!("Verifying email MFA code for user {}", username);
         if user.email_mfa_enabled && user.verify_email_mfa_code(&data.code) {
-            session
-                .set_state(&appstate.pool, SessionState::MultiFactorVerified)
-                .await?;
+            complete_mfa_login(&appstate, &mut session, ip).await?;
             let user_info = UserInfo::from_user(&appstate.pool, &user).await?;
             info!("Verified email MFA code for user {username}");
             appstate.emit_event(ApiEvent {
                 // User may not be fully authenticated so we can't use
                 // context extractor in this handler since it requires
                 // the `SessionInfo` object.
-                context: ApiRequestContext::new(
-                    user.id,
-                    user.username,
-                    insecure_ip,
-                    user_agent.to_string(),
-                ),
+                context: ApiRequestContext::new(user.id, user.username, ip, user_agent.to_string()),
                 event: Box::new(ApiEventType::UserMfaLogin {
                     mfa_method: MFAMethod::Email,
                 }),
@@ -947,12 +1094,7 @@ pub async fn email_mfa_code(
                 // User may not be fully authenticated so we can't use
                 // context extractor in this handler since it requires
                 // the `SessionInfo` object.
-                context: ApiRequestContext::new(
-                    user.id,
-                    user.username,
-                    insecure_ip,
-                    user_agent.to_string(),
-                ),
+                context: ApiRequestContext::new(user.id, user.username, ip, user_agent.to_string()),
                 event: Box::new(ApiEventType::UserMfaLoginFailed {
                     mfa_method: MFAMethod::Email,
                     message,
@@ -965,12 +1107,264 @@ pub async fn email_mfa_code(
     }
 }
 
+/// Replaces a legacy password hash once the password has been verified, either by Defguard or by
+/// LDAP. Failures are only logged, since they don't affect the login itself.
+async fn upgrade_password_hash(conn: &mut PgConnection, user: &mut User<Id>, password: &str) {
+    if let Err(err) = user.upgrade_password_hash(&mut *conn, password).await {
+        error!(
+            "Failed to upgrade password hash for user {}: {err}",
+            user.username
+        );
+    }
+}
+
+/// Send a text message with the current SMS MFA code.
+async fn send_sms_mfa_code(sms_mfa: &SmsMfa) -> Result<(), WebError> {
+    let code = sms_mfa
+        .generate_code()
+        .ok_or_else(|| WebError::SmsMfa("SystemTime before UNIX epoch".into()))?;
+    let instance_name = Settings::get_current_settings().instance_name;
+    send_sms(
+        &sms_mfa.phone,
+        &format!("Your {instance_name} verification code is {code}"),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Initialize SMS MFA setup by sending a verification code to the given phone number
+pub async fn sms_mfa_init(
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    Json(data): Json<SmsMfaSetup>,
+) -> ApiResult {
+    // check if SMS gateway is configured
+    let settings = Settings::get_current_settings();
+    if !settings.sms_configured() {
+        error!("Unable to start SMS MFA configuration. SMS gateway is not configured.");
+        return Err(WebError::SmsMfa("SMS gateway not configured".into()));
+    }
+    if !is_valid_phone_number(&data.phone) {
+        return Err(WebError::BadRequest("Invalid phone number".into()));
+    }
+
+    let user = session.user;
+    if SmsMfa::find_by_user(&appstate.pool, user.id)
+        .await?
+        .is_some_and(|sms_mfa| sms_mfa.enabled)
+    {
+        return Err(WebError::BadRequest(
+            "SMS MFA is already enabled, disable it to change the phone number".into(),
+        ));
+    }
+    debug!("Starting SMS MFA setup for user {}", user.username);
+    let sms_mfa = SmsMfa::init(&appstate.pool, user.id, data.phone).await?;
+    send_sms_mfa_code(&sms_mfa).await?;
+    info!("Sent SMS MFA verification code for user {}", user.username);
+
+    Ok(ApiResponse::default())
+}
+
+/// Enable SMS MFA once the phone number has been verified with a code
+pub async fn sms_mfa_enable(
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Json(data): Json<AuthCode>,
+) -> ApiResult {
+    let mut user = session.user;
+    debug!("Enabling SMS MFA for user {}", user.username);
+    let Some(mut sms_mfa) = SmsMfa::find_by_user(&appstate.pool, user.id).await? else {
+        return Err(WebError::BadRequest("SMS MFA setup not started".into()));
+    };
+    if !sms_mfa.verify_code(&data.code) {
+        return Err(WebError::ObjectNotFound("Invalid SMS code".into()));
+    }
+    let recovery_codes = RecoveryCodes::new(user.get_recovery_codes(&appstate.pool).await?);
+    sms_mfa.enable(&appstate.pool).await?;
+    if user.mfa_method == MFAMethod::None {
+        send_mfa_configured_email(
+            Some(&session.session.into()),
+            &user,
+            &MFAMethod::Sms,
+            &appstate.mail_tx,
+        )?;
+        user.set_mfa_method(&appstate.pool, MFAMethod::Sms).await?;
+    }
+
+    info!("Enabled SMS MFA for user {}", user.username);
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::MfaSmsEnabled),
+    })?;
+    Ok(ApiResponse {
+        json: json!(recovery_codes),
+        status: StatusCode::OK,
+    })
+}
+
+/// Disable SMS MFA
+pub async fn sms_mfa_disable(
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    let mut user = session.user;
+    debug!("Disabling SMS MFA for user {}", user.username);
+    SmsMfa::delete_for_user(&appstate.pool, user.id).await?;
+    user.verify_mfa_state(&appstate.pool).await?;
+    info!("Disabled SMS MFA for user {}", user.username);
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::MfaSmsDisabled),
+    })?;
+    Ok(ApiResponse::default())
+}
+
+/// Send SMS code to user
+pub async fn request_sms_mfa_code(session: Session, State(appstate): State<AppState>) -> ApiResult {
+    let Some(user) = User::find_by_id(&appstate.pool, session.user_id).await? else {
+        return Err(WebError::ObjectNotFound("Invalid user".into()));
+    };
+    // don't let blocked users trigger text messages
+    check_failed_logins(&appstate.failed_logins, &user.username)?;
+    match SmsMfa::find_by_user(&appstate.pool, user.id).await? {
+        Some(sms_mfa) if sms_mfa.enabled => {
+            debug!("Sending SMS MFA code for user {}", user.username);
+            send_sms_mfa_code(&sms_mfa).await?;
+            info!("Sent SMS MFA code for user {}", user.username);
+            Ok(ApiResponse::default())
+        }
+        _ => Err(WebError::Authorization("SMS MFA not enabled".into())),
+    }
+}
+
+/// Validate SMS MFA code
+pub async fn sms_mfa_code(
+    private_cookies: PrivateCookieJar,
+    mut session: Session,
+    user_agent: TypedHeader<UserAgent>,
+    SecureClientIp(ip): SecureClientIp,
+    State(appstate): State<AppState>,
+    Json(data): Json<AuthCode>,
+) -> Result<(PrivateCookieJar, ApiResponse), WebError> {
+    let Some(user) = User::find_by_id(&appstate.pool, session.user_id).await? else {
+        return Err(WebError::ObjectNotFound("Invalid user".into()));
+    };
+    let username = user.username.clone();
+
+    // check if user can proceed with login
+    check_failed_logins(&appstate.failed_logins, &username)?;
+
+    debug!("Verifying SMS MFA code for user {username}");
+    let sms_mfa = SmsMfa::find_by_user(&appstate.pool, user.id)
+        .await?
+        .filter(|sms_mfa| sms_mfa.enabled);
+    // User may not be fully authenticated so we can't use context extractor in this handler
+    // since it requires the `SessionInfo` object.
+    let context =
+        ApiRequestContext::new(user.id, user.username.clone(), ip, user_agent.to_string());
+    if !sms_mfa
+        .as_ref()
+        .is_some_and(|sms_mfa| sms_mfa.verify_code(&data.code))
+    {
+        let message = if sms_mfa.is_some() {
+            "SMS code verification failed".to_string()
+        } else {
+            format!("SMS code authentication is disabled for {username}")
+        };
+        log_failed_login_attempt(&appstate.failed_logins, &username);
+        appstate.emit_event(ApiEvent {
+            context,
+            event: Box::new(ApiEventType::UserMfaLoginFailed {
+                mfa_method: MFAMethod::Sms,
+                message,
+            }),
+        })?;
+        return Err(WebError::Authorization("Invalid SMS MFA code".into()));
+    }
+
+    complete_mfa_login(&appstate, &mut session, ip).await?;
+    let user_info = UserInfo::from_user(&appstate.pool, &user).await?;
+    info!("Verified SMS MFA code for user {username}");
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::UserMfaLogin {
+            mfa_method: MFAMethod::Sms,
+        }),
+    })?;
+    let (private_cookies, url) =
+        if let Some(openid_cookie) = private_cookies.get(SIGN_IN_COOKIE_NAME) {
+            debug!("Found OpenID session cookie.");
+            let redirect_url = openid_cookie.value().to_string();
+            (private_cookies.remove(openid_cookie), Some(redirect_url))
+        } else {
+            (private_cookies, None)
+        };
+    Ok((
+        private_cookies,
+        ApiResponse {
+            json: json!(AuthResponse {
+                user: user_info,
+                url,
+            }),
+            status: StatusCode::OK,
+        },
+    ))
+}
+
+/// Replace own recovery codes with a new set, invalidating the old ones.
+pub async fn regenerate_recovery_codes(
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    let mut user = session.user;
+    debug!("Regenerating recovery codes for user {}", user.username);
+    if !user.mfa_enabled {
+        return Err(WebError::BadRequest(
+            "Recovery codes are only available with MFA enabled".into(),
+        ));
+    }
+    let codes = user.regenerate_recovery_codes(&appstate.pool).await?;
+    let count = codes.len();
+    info!(
+        "Regenerated {count} recovery codes for user {}",
+        user.username
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::RecoveryCodesRegenerated { count }),
+    })?;
+    Ok(ApiResponse {
+        json: json!(RecoveryCodes::new(Some(codes))),
+        status: StatusCode::OK,
+    })
+}
+
+/// Number of recovery codes left and times at which codes were used.
+pub async fn recovery_codes_info(
+    session_info: SessionInfo,
+    State(appstate): State<AppState>,
+    Path(username): Path<String>,
+) -> ApiResult {
+    let user = user_for_admin_or_self(&appstate.pool, &session_info, &username).await?;
+    let used_at = user.recovery_code_usage(&appstate.pool).await?;
+    Ok(ApiResponse {
+        json: json!(RecoveryCodesInfo {
+            remaining: user.recovery_codes.len(),
+            used_at,
+        }),
+        status: StatusCode::OK,
+    })
+}
+
 /// Authenticate with a recovery code.
 pub async fn recovery_code(
     private_cookies: PrivateCookieJar,
     mut session: Session,
     user_agent: TypedHeader<UserAgent>,
-    InsecureClientIp(insecure_ip): InsecureClientIp,
+    SecureClientIp(ip): SecureClientIp,
     State(appstate): State<AppState>,
     Json(recovery_code): Json<RecoveryCode>,
 ) -> Result<(PrivateCookieJar, ApiResponse), WebError> {
@@ -981,22 +1375,18 @@ pub async fn recovery_code(
             .verify_recovery_code(&appstate.pool, &recovery_code.code)
             .await?
         {
-            session
-                .set_state(&appstate.pool, SessionState::MultiFactorVerified)
-                .await?;
+            complete_mfa_login(&appstate, &mut session, ip).await?;
             let user_info = UserInfo::from_user(&appstate.pool, &user).await?;
-            info!("Authenticated user {username} with recovery code");
+            let remaining = user.recovery_codes.len();
+            info!(
+                "Authenticated user {username} with recovery code, {remaining} recovery codes left"
+            );
             appstate.emit_event(ApiEvent {
                 // User may not be fully authenticated so we can't use
                 // context extractor in this handler since it requires
                 // the `SessionInfo` object.
-                context: ApiRequestContext::new(
-                    user.id,
-                    user.username,
-                    insecure_ip,
-                    user_agent.to_string(),
-                ),
-                event: Box::new(ApiEventType::RecoveryCodeUsed),
+                context: ApiRequestContext::new(user.id, user.username, ip, user_agent.to_string()),
+                event: Box::new(ApiEventType::RecoveryCodeUsed { remaining }),
             })?;
             if let Some(openid_cookie) = private_cookies.get(SIGN_IN_COOKIE_NAME) {
                 debug!("Found OpenID session cookie.");
@@ -0,0 +1,212 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::NaiveDateTime;
+use defguard_common::db::Id;
+use serde_json::json;
+use sqlx::query_as;
+use utoipa::ToSchema;
+
+use super::{ApiResponse, ApiResult};
+use crate::{
+    appstate::AppState,
+    auth::{AdminRole, SessionInfo},
+    db::{
+        Device, GatewayEvent, User, WireguardNetwork,
+        models::{
+            client_login_session::ClientLoginSession,
+            device::{DeviceInfo, DeviceNetworkInfo, WireguardNetworkDevice},
+        },
+    },
+    error::WebError,
+};
+
+/// Desktop client MFA login which has been started, but not finished yet.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PendingClientMfaSession {
+    pub location_id: Id,
+    pub location_name: String,
+    pub device_id: Id,
+    pub device_name: String,
+    pub user_id: Id,
+    pub username: String,
+    pub method: String,
+    pub failed_attempts: u32,
+    pub expires_at: NaiveDateTime,
+}
+
+/// Device authorized to connect to an MFA-protected location.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuthorizedClientMfaSession {
+    pub location_id: Id,
+    pub location_name: String,
+    pub device_id: Id,
+    pub device_name: String,
+    pub user_id: Id,
+    pub username: String,
+    pub authorized_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ClientMfaSessions {
+    pub pending: Vec<PendingClientMfaSession>,
+    pub authorized: Vec<AuthorizedClientMfaSession>,
+}
+
+/// List desktop client MFA sessions
+///
+/// Lists MFA logins in progress and devices currently authorized to connect to MFA-protected
+/// locations.
+///
+/// # Returns
+/// - `ClientMfaSessions` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    get,
+    path = "/api/v1/client_mfa/sessions",
+    responses(
+        (status = 200, description = "Desktop client MFA sessions", body = ClientMfaSessions),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden - Admin role required"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub async fn list_client_mfa_sessions(
+    _admin_role: AdminRole,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    debug!("Listing desktop client MFA sessions");
+    // expired sessions are only evicted when the proxy sends a message, they're skipped here
+    let sessions = ClientLoginSession::all_active(&appstate.pool).await?;
+    let mut pending = Vec::with_capacity(sessions.len());
+    for session in sessions {
+        let location = WireguardNetwork::find_by_id(&appstate.pool, session.location_id).await?;
+        let device = Device::find_by_id(&appstate.pool, session.device_id).await?;
+        let user = User::find_by_id(&appstate.pool, session.user_id).await?;
+        let (Some(location), Some(device), Some(user)) = (location, device, user) else {
+            continue;
+        };
+        pending.push(PendingClientMfaSession {
+            location_id: location.id,
+            location_name: location.name,
+            device_id: device.id,
+            device_name: device.name,
+            user_id: user.id,
+            username: user.username,
+            method: session.method.as_str_name().into(),
+            failed_attempts: session.failed_attempts,
+            expires_at: session.expires_at,
+        });
+    }
+
+    let authorized = query_as!(
+        AuthorizedClientMfaSession,
+        "SELECT n.id location_id, n.name location_name, d.id device_id, d.name device_name, \
+        u.id user_id, u.username, wnd.authorized_at \
+        FROM wireguard_network_device wnd \
+        JOIN device d ON d.id = wnd.device_id \
+        JOIN \"user\" u ON u.id = d.user_id \
+        JOIN wireguard_network n ON n.id = wnd.wireguard_network_id \
+        WHERE wnd.is_authorized AND n.location_mfa_mode <> 'disabled' \
+        ORDER BY n.id, d.id"
+    )
+    .fetch_all(&appstate.pool)
+    .await?;
+
+    Ok(ApiResponse {
+        json: json!(ClientMfaSessions {
+            pending,
+            authorized
+        }),
+        status: StatusCode::OK,
+    })
+}
+
+/// Revoke desktop client MFA session
+///
+/// Aborts MFA login in progress for the device and removes its authorization to connect
+/// to the location. The device has to complete MFA again to reconnect.
+///
+/// # Returns
+/// - empty JSON
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    delete,
+    path = "/api/v1/client_mfa/sessions/{location_id}/{device_id}",
+    params(
+        ("location_id" = Id, Path, description = "WireGuard location ID"),
+        ("device_id" = Id, Path, description = "Device ID")
+    ),
+    responses(
+        (status = 200, description = "Desktop client MFA session revoked"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden - Admin role required"),
+        (status = 404, description = "Not found - no active session for the device and location"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub async fn revoke_client_mfa_session(
+    _admin_role: AdminRole,
+    session: SessionInfo,
+    Path((location_id, device_id)): Path<(Id, Id)>,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    let location = WireguardNetwork::find_by_id(&appstate.pool, location_id)
+        .await?
+        .ok_or_else(|| WebError::ObjectNotFound(format!("Location {location_id} not found")))?;
+    let device = Device::find_by_id(&appstate.pool, device_id)
+        .await?
+        .ok_or_else(|| WebError::ObjectNotFound(format!("Device {device_id} not found")))?;
+    debug!(
+        "User {} revoking desktop client MFA session of device {device} in location {location}",
+        session.user.username
+    );
+
+    let login_aborted =
+        ClientLoginSession::delete_for_device(&appstate.pool, location.id, device.id).await?;
+
+    let authorization_revoked =
+        match WireguardNetworkDevice::find(&appstate.pool, device.id, location.id).await? {
+            Some(mut network_device) if network_device.is_authorized => {
+                network_device.is_authorized = false;
+                network_device.preshared_key = None;
+                network_device.update(&appstate.pool).await?;
+
+                debug!("Sending `peer_delete` message to gateway");
+                appstate.send_wireguard_event(GatewayEvent::DeviceDeleted(DeviceInfo {
+                    device: device.clone(),
+                    network_info: vec![DeviceNetworkInfo {
+                        network_id: location.id,
+                        device_wireguard_ips: network_device.wireguard_ips,
+                        preshared_key: network_device.preshared_key,
+                        is_authorized: network_device.is_authorized,
+                    }],
+                }));
+                true
+            }
+            _ => false,
+        };
+
+    if !login_aborted && !authorization_revoked {
+        return Err(WebError::ObjectNotFound(format!(
+            "No active desktop client MFA session of device {device} in location {location}"
+        )));
+    }
+    info!(
+        "User {} revoked desktop client MFA session of device {device} in location {location}",
+        session.user.username
+    );
+
+    Ok(ApiResponse::default())
+}
@@ -0,0 +1,237 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+};
+use defguard_common::db::Id;
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::{ApiResponse, ApiResult, mail::send_device_approval_email};
+use crate::{
+    appstate::AppState,
+    auth::{AdminRole, SessionInfo},
+    db::{
+        Device, GatewayEvent, User, WireguardNetwork,
+        models::{
+            device::{DeviceInfo, DeviceNetworkInfo, WireguardNetworkDevice},
+            device_approval::{DeviceApproval, DeviceApprovalInfo, DeviceApprovalStatus},
+        },
+    },
+    error::WebError,
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
+};
+
+/// List pending device approvals
+///
+/// Devices added to locations which require device approval wait here until an admin decides
+/// on them.
+///
+/// # Returns
+/// - list of `DeviceApprovalInfo` objects
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    get,
+    path = "/api/v1/device-approval",
+    responses(
+        (status = 200, description = "List of pending device approvals.", body = [DeviceApprovalInfo]),
+        (status = 401, description = "Unauthorized to list device approvals.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to list device approvals.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 500, description = "Cannot list device approvals.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn list_device_approvals(
+    _role: AdminRole,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    let approvals = DeviceApprovalInfo::all_pending(&appstate.pool).await?;
+    Ok(ApiResponse {
+        json: json!(approvals),
+        status: StatusCode::OK,
+    })
+}
+
+/// Fetches a pending device approval along with its device, owner and location.
+async fn pending_device_approval(
+    pool: &PgPool,
+    id: Id,
+) -> Result<
+    (
+        DeviceApproval<Id>,
+        Device<Id>,
+        User<Id>,
+        WireguardNetwork<Id>,
+    ),
+    WebError,
+> {
+    let not_found = || WebError::ObjectNotFound(format!("Device approval {id} not found"));
+    let approval = DeviceApproval::find_by_id(pool, id)
+        .await?
+        .ok_or_else(not_found)?;
+    if approval.status != DeviceApprovalStatus::Pending {
+        return Err(WebError::BadRequest(format!(
+            "Device approval {id} has already been decided on"
+        )));
+    }
+    let device = Device::find_by_id(pool, approval.device_id)
+        .await?
+        .ok_or_else(not_found)?;
+    let owner = User::find_by_id(pool, device.user_id)
+        .await?
+        .ok_or_else(not_found)?;
+    let location = WireguardNetwork::find_by_id(pool, approval.location_id)
+        .await?
+        .ok_or_else(not_found)?;
+    Ok((approval, device, owner, location))
+}
+
+/// Approve device
+///
+/// Push the device peer to gateways of the location and notify the owner.
+///
+/// # Returns
+/// - `WebError` if error occurs
+#[utoipa::path(
+    post,
+    path = "/api/v1/device-approval/{id}/approve",
+    params(
+        ("id" = Id, description = "Device approval ID")
+    ),
+    responses(
+        (status = 200, description = "Successfully approved device."),
+        (status = 400, description = "Device has already been decided on.", body = ApiResponse, example = json!({"msg": "Device approval <id> has already been decided on"})),
+        (status = 401, description = "Unauthorized to approve device.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to approve devices.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Device approval not found.", body = ApiResponse, example = json!({"msg": "Device approval <id> not found"})),
+        (status = 500, description = "Cannot approve device.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn approve_device(
+    _role: AdminRole,
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    context: ApiRequestContext,
+    Path(id): Path<Id>,
+) -> ApiResult {
+    let (mut approval, device, owner, location) =
+        pending_device_approval(&appstate.pool, id).await?;
+    debug!(
+        "User {} approving device {} of user {} in location {location}",
+        session.user.username, device.name, owner.username
+    );
+
+    let mut transaction = appstate.pool.begin().await?;
+    approval.decide(true, session.user.id);
+    approval.save(&mut *transaction).await?;
+    let mut events = Vec::new();
+    if let Some(network_device) =
+        WireguardNetworkDevice::find(&mut *transaction, device.id, location.id).await?
+    {
+        if let Some(firewall_config) = location.try_get_firewall_config(&mut transaction).await? {
+            events.push(GatewayEvent::FirewallConfigChanged(
+                location.id,
+                firewall_config,
+            ));
+        }
+        events.push(GatewayEvent::DeviceCreated(DeviceInfo {
+            device: device.clone(),
+            network_info: vec![DeviceNetworkInfo {
+                network_id: location.id,
+                device_wireguard_ips: network_device.wireguard_ips,
+                preshared_key: network_device.preshared_key,
+                is_authorized: network_device.is_authorized,
+            }],
+        }));
+    }
+    transaction.commit().await?;
+    appstate.send_multiple_wireguard_events(events);
+
+    send_device_approval_email(
+        &owner,
+        &device.name,
+        &location.name,
+        true,
+        &appstate.mail_tx,
+    )?;
+    info!(
+        "User {} approved device {} of user {} in location {location}",
+        session.user.username, device.name, owner.username
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::DeviceApproved {
+            owner,
+            device,
+            location,
+        }),
+    })?;
+    Ok(ApiResponse::default())
+}
+
+/// Reject device
+///
+/// The device can't connect to the location until approval is disabled for the location.
+/// The owner is notified about the decision.
+///
+/// # Returns
+/// - `WebError` if error occurs
+#[utoipa::path(
+    post,
+    path = "/api/v1/device-approval/{id}/reject",
+    params(
+        ("id" = Id, description = "Device approval ID")
+    ),
+    responses(
+        (status = 200, description = "Successfully rejected device."),
+        (status = 400, description = "Device has already been decided on.", body = ApiResponse, example = json!({"msg": "Device approval <id> has already been decided on"})),
+        (status = 401, description = "Unauthorized to reject device.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to reject devices.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Device approval not found.", body = ApiResponse, example = json!({"msg": "Device approval <id> not found"})),
+        (status = 500, description = "Cannot reject device.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn reject_device(
+    _role: AdminRole,
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    context: ApiRequestContext,
+    Path(id): Path<Id>,
+) -> ApiResult {
+    let (mut approval, device, owner, location) =
+        pending_device_approval(&appstate.pool, id).await?;
+    approval.decide(false, session.user.id);
+    approval.save(&appstate.pool).await?;
+
+    send_device_approval_email(
+        &owner,
+        &device.name,
+        &location.name,
+        false,
+        &appstate.mail_tx,
+    )?;
+    info!(
+        "User {} rejected device {} of user {} in location {location}",
+        session.user.username, device.name, owner.username
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::DeviceRejected {
+            owner,
+            device,
+            location,
+        }),
+    })?;
+    Ok(ApiResponse::default())
+}
@@ -0,0 +1,474 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use defguard_common::db::Id;
+use serde_json::json;
+use sqlx::PgConnection;
+use utoipa::ToSchema;
+
+use super::{ApiResponse, ApiResult};
+use crate::{
+    appstate::AppState,
+    auth::{AdminRole, SessionInfo},
+    db::{
+        Device, GatewayEvent, WireguardNetwork,
+        models::device_profile::{DeviceProfile, assign_device_profile},
+    },
+    error::WebError,
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
+};
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DeviceProfileData {
+    pub name: String,
+    pub description: Option<String>,
+    /// Overrides location DNS.
+    pub dns: Option<String>,
+    /// Overrides location keepalive interval.
+    pub keepalive_interval: Option<i32>,
+    /// IDs of locations which devices with this profile may use. Empty means all locations.
+    #[serde(default)]
+    pub allowed_locations: Vec<Id>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DeviceProfileAssignment {
+    /// Profile to assign, `null` removes current profile.
+    pub profile_id: Option<Id>,
+}
+
+/// Profile with IDs of devices it's assigned to.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeviceProfileInfo {
+    #[serde(flatten)]
+    pub profile: DeviceProfile<Id>,
+    pub devices: Vec<Id>,
+}
+
+async fn validate_profile_data(
+    conn: &mut PgConnection,
+    data: &DeviceProfileData,
+    id: Option<Id>,
+) -> Result<(), WebError> {
+    if data.name.trim().is_empty() {
+        return Err(WebError::BadRequest(
+            "Device profile name can't be empty".into(),
+        ));
+    }
+    if let Some(existing) = DeviceProfile::find_by_name(&mut *conn, &data.name).await? {
+        if Some(existing.id) != id {
+            return Err(WebError::ObjectAlreadyExists(format!(
+                "Device profile {} already exists",
+                data.name
+            )));
+        }
+    }
+    if data
+        .keepalive_interval
+        .is_some_and(|keepalive_interval| keepalive_interval < 0)
+    {
+        return Err(WebError::BadRequest(
+            "Keepalive interval can't be negative".into(),
+        ));
+    }
+    for location_id in &data.allowed_locations {
+        if WireguardNetwork::find_by_id(&mut *conn, *location_id)
+            .await?
+            .is_none()
+        {
+            return Err(WebError::BadRequest(format!(
+                "Location {location_id} not found"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Prepares current peers of all locations for gateways, so they reflect profile changes.
+/// Events should be sent once the transaction is committed.
+async fn location_peer_updates(conn: &mut PgConnection) -> Result<Vec<GatewayEvent>, WebError> {
+    let mut events = Vec::new();
+    for location in WireguardNetwork::all(&mut *conn).await? {
+        let peers = location.get_peers(&mut *conn).await?;
+        let maybe_firewall_config = location.try_get_firewall_config(&mut *conn).await?;
+        events.push(GatewayEvent::NetworkModified(
+            location.id,
+            location,
+            peers,
+            maybe_firewall_config,
+        ));
+    }
+
+    Ok(events)
+}
+
+fn send_peer_updates(appstate: &AppState, events: Vec<GatewayEvent>) {
+    for event in events {
+        appstate.send_wireguard_event(event);
+    }
+}
+
+/// List device profiles
+///
+/// # Returns
+/// - list of `DeviceProfileInfo` objects
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    get,
+    path = "/api/v1/device_profile",
+    responses(
+        (status = 200, description = "List of device profiles.", body = [DeviceProfileInfo]),
+        (status = 401, description = "Unauthorized to list device profiles.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to list device profiles.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 500, description = "Cannot list device profiles.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn list_device_profiles(
+    _role: AdminRole,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    let mut profiles = Vec::new();
+    for profile in DeviceProfile::all(&appstate.pool).await? {
+        let devices = profile.device_ids(&appstate.pool).await?;
+        profiles.push(DeviceProfileInfo { profile, devices });
+    }
+
+    Ok(ApiResponse {
+        json: json!(profiles),
+        status: StatusCode::OK,
+    })
+}
+
+/// Get device profile
+///
+/// # Returns
+/// - `DeviceProfileInfo` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    get,
+    path = "/api/v1/device_profile/{id}",
+    params(
+        ("id" = Id, description = "Device profile ID")
+    ),
+    responses(
+        (status = 200, description = "Device profile.", body = DeviceProfileInfo),
+        (status = 401, description = "Unauthorized to get device profile.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to get device profile.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Device profile not found.", body = ApiResponse, example = json!({"msg": "Device profile <id> not found"})),
+        (status = 500, description = "Cannot get device profile.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn get_device_profile(
+    _role: AdminRole,
+    State(appstate): State<AppState>,
+    Path(id): Path<Id>,
+) -> ApiResult {
+    let Some(profile) = DeviceProfile::find_by_id(&appstate.pool, id).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "Device profile {id} not found"
+        )));
+    };
+    let devices = profile.device_ids(&appstate.pool).await?;
+
+    Ok(ApiResponse {
+        json: json!(DeviceProfileInfo { profile, devices }),
+        status: StatusCode::OK,
+    })
+}
+
+/// Create device profile
+///
+/// # Returns
+/// - created `DeviceProfile` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    post,
+    path = "/api/v1/device_profile",
+    request_body = DeviceProfileData,
+    responses(
+        (status = 201, description = "Device profile created.", body = DeviceProfile),
+        (status = 400, description = "Invalid device profile.", body = ApiResponse, example = json!({"msg": "Location 5 not found"})),
+        (status = 401, description = "Unauthorized to create device profile.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to create device profile.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 500, description = "Cannot create device profile.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn create_device_profile(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Json(data): Json<DeviceProfileData>,
+) -> ApiResult {
+    debug!(
+        "User {} creating device profile {}",
+        session.user.username, data.name
+    );
+    let mut transaction = appstate.pool.begin().await?;
+    validate_profile_data(&mut transaction, &data, None).await?;
+    let profile = DeviceProfile::new(
+        data.name,
+        data.description,
+        data.dns,
+        data.keepalive_interval,
+        data.allowed_locations,
+    )
+    .save(&mut transaction)
+    .await?;
+    transaction.commit().await?;
+    info!(
+        "User {} created device profile {}",
+        session.user.username, profile.name
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::DeviceProfileAdded {
+            profile: profile.clone(),
+        }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!(profile),
+        status: StatusCode::CREATED,
+    })
+}
+
+/// Modify device profile
+///
+/// Changes are applied to configuration of all devices with this profile.
+///
+/// # Returns
+/// - updated `DeviceProfile` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    put,
+    path = "/api/v1/device_profile/{id}",
+    params(
+        ("id" = Id, description = "Device profile ID")
+    ),
+    request_body = DeviceProfileData,
+    responses(
+        (status = 200, description = "Device profile modified.", body = DeviceProfile),
+        (status = 400, description = "Invalid device profile.", body = ApiResponse, example = json!({"msg": "Location 5 not found"})),
+        (status = 401, description = "Unauthorized to modify device profile.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to modify device profile.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Device profile not found.", body = ApiResponse, example = json!({"msg": "Device profile <id> not found"})),
+        (status = 500, description = "Cannot modify device profile.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn modify_device_profile(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(id): Path<Id>,
+    Json(data): Json<DeviceProfileData>,
+) -> ApiResult {
+    debug!(
+        "User {} modifying device profile {id}",
+        session.user.username
+    );
+    let mut transaction = appstate.pool.begin().await?;
+    let Some(mut profile) = DeviceProfile::find_by_id(&mut *transaction, id).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "Device profile {id} not found"
+        )));
+    };
+    validate_profile_data(&mut transaction, &data, Some(id)).await?;
+    let before = profile.clone();
+    profile.name = data.name;
+    profile.description = data.description;
+    profile.dns = data.dns;
+    profile.keepalive_interval = data.keepalive_interval;
+    profile.set_allowed_locations(data.allowed_locations);
+    profile.save(&mut transaction).await?;
+    let peer_updates = if profile.device_ids(&mut *transaction).await?.is_empty() {
+        Vec::new()
+    } else {
+        location_peer_updates(&mut transaction).await?
+    };
+    transaction.commit().await?;
+    send_peer_updates(&appstate, peer_updates);
+    info!(
+        "User {} modified device profile {}",
+        session.user.username, profile.name
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::DeviceProfileModified {
+            before,
+            after: profile.clone(),
+        }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!(profile),
+        status: StatusCode::OK,
+    })
+}
+
+/// Delete device profile
+///
+/// Devices with this profile fall back to location settings.
+///
+/// # Returns
+/// - empty JSON
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    delete,
+    path = "/api/v1/device_profile/{id}",
+    params(
+        ("id" = Id, description = "Device profile ID")
+    ),
+    responses(
+        (status = 200, description = "Device profile deleted.", body = ApiResponse, example = json!({})),
+        (status = 401, description = "Unauthorized to delete device profile.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to delete device profile.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Device profile not found.", body = ApiResponse, example = json!({"msg": "Device profile <id> not found"})),
+        (status = 500, description = "Cannot delete device profile.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn delete_device_profile(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(id): Path<Id>,
+) -> ApiResult {
+    debug!(
+        "User {} deleting device profile {id}",
+        session.user.username
+    );
+    let mut transaction = appstate.pool.begin().await?;
+    let Some(profile) = DeviceProfile::find_by_id(&mut *transaction, id).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "Device profile {id} not found"
+        )));
+    };
+    let had_devices = !profile.device_ids(&mut *transaction).await?.is_empty();
+    profile.clone().delete(&mut *transaction).await?;
+    let peer_updates = if had_devices {
+        location_peer_updates(&mut transaction).await?
+    } else {
+        Vec::new()
+    };
+    transaction.commit().await?;
+    send_peer_updates(&appstate, peer_updates);
+    info!(
+        "User {} deleted device profile {}",
+        session.user.username, profile.name
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::DeviceProfileRemoved { profile }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!({}),
+        status: StatusCode::OK,
+    })
+}
+
+/// Assign device profile
+///
+/// Assign a profile to a device, replacing the current one. Set `profile_id` to `null` to remove
+/// the profile, so that the device uses location settings.
+///
+/// # Returns
+/// - `DeviceProfileAssignment` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    put,
+    path = "/api/v1/device/{device_id}/profile",
+    params(
+        ("device_id" = Id, description = "Device ID")
+    ),
+    request_body = DeviceProfileAssignment,
+    responses(
+        (status = 200, description = "Device profile assigned.", body = DeviceProfileAssignment, example = json!({"profile_id": 1})),
+        (status = 401, description = "Unauthorized to assign device profile.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to assign device profile.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Device or device profile not found.", body = ApiResponse, example = json!({"msg": "Device profile <id> not found"})),
+        (status = 500, description = "Cannot assign device profile.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn set_device_profile(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(device_id): Path<Id>,
+    Json(data): Json<DeviceProfileAssignment>,
+) -> ApiResult {
+    debug!(
+        "User {} assigning profile {:?} to device {device_id}",
+        session.user.username, data.profile_id
+    );
+    let mut transaction = appstate.pool.begin().await?;
+    let Some(device) = Device::find_by_id(&mut *transaction, device_id).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "Device {device_id} not found"
+        )));
+    };
+    let profile = match data.profile_id {
+        Some(profile_id) => {
+            let Some(profile) = DeviceProfile::find_by_id(&mut *transaction, profile_id).await?
+            else {
+                return Err(WebError::ObjectNotFound(format!(
+                    "Device profile {profile_id} not found"
+                )));
+            };
+            Some(profile)
+        }
+        None => None,
+    };
+    assign_device_profile(&mut *transaction, device.id, data.profile_id).await?;
+    let peer_updates = location_peer_updates(&mut transaction).await?;
+    transaction.commit().await?;
+    send_peer_updates(&appstate, peer_updates);
+    info!(
+        "User {} assigned profile {:?} to device {device}",
+        session.user.username, data.profile_id
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::DeviceProfileAssigned { device, profile }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!(data),
+        status: StatusCode::OK,
+    })
+}
@@ -0,0 +1,100 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::NaiveDateTime;
+use defguard_common::db::Id;
+use serde_json::json;
+
+use super::{ApiResponse, ApiResult};
+use crate::{
+    appstate::AppState,
+    auth::{AdminRole, SessionInfo},
+    db::models::enrollment::{ENROLLMENT_TOKEN_TYPE, Token},
+    error::WebError,
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
+};
+
+/// Enrollment token which can still be used, as returned by the API.
+#[derive(Debug, Serialize)]
+pub struct EnrollmentTokenInfo {
+    pub id: String,
+    pub username: String,
+    /// Username of the admin who created the token.
+    pub admin: Option<String>,
+    pub email: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    /// Start of the last enrollment session.
+    pub used_at: Option<NaiveDateTime>,
+    pub max_uses: i32,
+    pub use_count: i32,
+    /// Names of groups the user is added to when enrollment is completed.
+    pub groups: Vec<String>,
+    /// Locations the user gets access to when enrollment is completed.
+    pub locations: Vec<Id>,
+}
+
+/// List enrollment tokens which can still be used to start an enrollment, newest first.
+pub(crate) async fn list_enrollment_tokens(
+    _role: AdminRole,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    debug!("Listing enrollment tokens");
+    let tokens = Token::fetch_active_enrollment_tokens(&appstate.pool).await?;
+    let mut infos = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        let user = token.fetch_user(&appstate.pool).await?;
+        let admin = token.fetch_admin(&appstate.pool).await?;
+        let groups = token.fetch_groups(&appstate.pool).await?;
+        let locations = token.fetch_location_ids(&appstate.pool).await?;
+        infos.push(EnrollmentTokenInfo {
+            id: token.id,
+            username: user.username,
+            admin: admin.map(|admin| admin.username),
+            email: token.email,
+            created_at: token.created_at,
+            expires_at: token.expires_at,
+            used_at: token.used_at,
+            max_uses: token.max_uses,
+            use_count: token.use_count,
+            groups: groups.into_iter().map(|group| group.name).collect(),
+            locations,
+        });
+    }
+    debug!("Listed {} enrollment tokens", infos.len());
+
+    Ok(ApiResponse {
+        json: json!(infos),
+        status: StatusCode::OK,
+    })
+}
+
+/// Revoke an enrollment token, so it can't be used to start or continue an enrollment.
+pub(crate) async fn revoke_enrollment_token(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(token_id): Path<String>,
+) -> ApiResult {
+    debug!("Revoking enrollment token");
+    let token = Token::find_by_id(&appstate.pool, &token_id).await?;
+    if token.token_type.as_deref() != Some(ENROLLMENT_TOKEN_TYPE) {
+        return Err(WebError::ObjectNotFound(
+            "enrollment token not found".into(),
+        ));
+    }
+    let user = token.fetch_user(&appstate.pool).await?;
+    token.delete(&appstate.pool).await?;
+    info!(
+        "User {} revoked enrollment token of user {}",
+        session.user.username, user.username
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::EnrollmentTokenRevoked { user }),
+    })?;
+
+    Ok(ApiResponse::default())
+}
@@ -1,19 +1,32 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use axum::{
-    extract::{Json, Path, State},
+    extract::{Json, Path, Query, State},
     http::StatusCode,
+    response::{IntoResponse, Response},
 };
-use defguard_common::db::Id;
+use chrono::{NaiveDateTime, Utc};
+use defguard_common::db::{Id, models::Settings};
 use serde_json::json;
-use sqlx::query_as;
+use sqlx::{Error as SqlxError, PgPool, Postgres, QueryBuilder, query, query_as};
 use utoipa::ToSchema;
 
-use super::{ApiResponse, ApiResult, EditGroupInfo, GroupInfo, Username};
+use super::{
+    ApiResponse, ApiResult, EditGroupInfo, GroupInfo, activity_log::SortOrder,
+    mail::send_group_join_request_email,
+};
 use crate::{
     appstate::AppState,
-    auth::{AdminRole, SessionInfo},
-    db::{Group, User, WireguardNetwork, models::group::Permission},
+    auth::{AdminRole, GroupOwner, SessionInfo},
+    db::{
+        Group, User, WireguardNetwork,
+        models::{
+            group::{GroupQuota, GroupQuotaUsage, GroupSyncAuthority, Permission},
+            group_join_request::{
+                GroupJoinRequest, GroupJoinRequestInfo, GroupJoinRequestStatus, SelfServiceGroup,
+            },
+        },
+    },
     enterprise::ldap::utils::{
         ldap_add_user_to_groups, ldap_add_users_to_groups, ldap_delete_group, ldap_modify_group,
         ldap_remove_user_from_groups, ldap_remove_users_from_groups, ldap_update_user_state,
@@ -36,6 +49,168 @@ impl Groups {
     }
 }
 
+#[derive(Deserialize, Debug, Clone, Serialize, ToSchema)]
+pub(crate) struct GroupSyncAuthorityRequest {
+    sync_authority: Option<GroupSyncAuthority>,
+}
+
+/// Rejects local changes to groups whose membership is managed by LDAP.
+pub(crate) async fn check_not_ldap_managed(
+    pool: &PgPool,
+    group: &Group<Id>,
+) -> Result<(), WebError> {
+    let settings = Settings::get_current_settings();
+    if !settings.ldap_enabled || !settings.ldap_sync_enabled {
+        return Ok(());
+    }
+    if group.sync_authority(pool).await? == Some(GroupSyncAuthority::Ldap) {
+        warn!(
+            "Rejecting local change of group {}, it is managed by LDAP",
+            group.name
+        );
+        return Err(WebError::BadRequest(format!(
+            "Group {} is managed by LDAP",
+            group.name
+        )));
+    }
+    Ok(())
+}
+
+/// Desired state of all groups. Groups which aren't listed are removed.
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct GroupsReconciliationRequest {
+    groups: Vec<EditGroupInfo>,
+}
+
+/// Changes applied to a single group by reconciliation.
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct GroupChanges {
+    name: String,
+    is_admin: bool,
+    /// Usernames of added members.
+    added: Vec<String>,
+    /// Usernames of removed members.
+    removed: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub(crate) struct GroupsReconciliation {
+    created: Vec<GroupChanges>,
+    modified: Vec<GroupChanges>,
+    removed: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct CreateGroupQuery {
+    /// Add members to an existing group instead of failing.
+    #[serde(default)]
+    if_not_exists: bool,
+}
+
+/// Result of creating a group which already existed.
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct MergedGroupMembers {
+    name: String,
+    /// Users who have been added to the group.
+    added: Vec<String>,
+}
+
+/// Upper limit of `per_page` accepted when listing groups info.
+const MAX_GROUP_INFO_PAGE_SIZE: u32 = 500;
+static TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum GroupInfoSortKey {
+    #[default]
+    Name,
+    Members,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct GroupInfoQuery {
+    #[serde(default)]
+    search: Option<String>,
+    #[serde(default)]
+    sort_by: GroupInfoSortKey,
+    #[serde(default)]
+    sort_order: Option<SortOrder>,
+    #[serde(default = "default_page")]
+    page: u32,
+    #[serde(default)]
+    per_page: Option<u32>,
+}
+
+fn default_page() -> u32 {
+    1
+}
+
+impl GroupInfoQuery {
+    /// Narrows down groups to those matching the search term by name or member username.
+    fn apply_search(&self, query_builder: &mut QueryBuilder<Postgres>) {
+        if let Some(search) = self.search.as_deref().filter(|search| !search.is_empty()) {
+            let pattern = format!("%{search}%");
+            query_builder
+                .push(" AND (g.name ILIKE ")
+                .push_bind(pattern.clone())
+                .push(
+                    " OR EXISTS (SELECT 1 FROM group_user gu JOIN \"user\" u ON u.id = gu.user_id \
+                    WHERE gu.group_id = g.id AND u.username ILIKE ",
+                )
+                .push_bind(pattern)
+                .push("))");
+        }
+    }
+}
+
+/// Resolves usernames to user IDs, failing on the first unknown user.
+async fn find_user_ids(pool: &PgPool, usernames: &[String]) -> Result<Vec<Id>, WebError> {
+    let mut ids = Vec::with_capacity(usernames.len());
+    for username in usernames {
+        let Some(user) = User::find_by_username(pool, username).await? else {
+            let msg = format!("User {username} not found");
+            error!(msg);
+            return Err(WebError::ObjectNotFound(msg));
+        };
+        ids.push(user.id);
+    }
+    Ok(ids)
+}
+
+/// User to add to a group. Members with `expires_at` set are removed from the group
+/// automatically once it passes.
+#[derive(Deserialize, Debug, Clone, Serialize, ToSchema)]
+pub(crate) struct GroupMemberRequest {
+    username: String,
+    #[serde(default)]
+    expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize, ToSchema)]
+pub(crate) struct GroupParentRequest {
+    parent: Option<String>,
+}
+
+/// Self-service settings of a group. Owners decide on join requests along with admins.
+#[derive(Deserialize, Debug, Clone, Serialize, ToSchema)]
+pub(crate) struct GroupSelfServiceRequest {
+    self_service: bool,
+    #[serde(default)]
+    owners: Vec<String>,
+}
+
+/// Users allowed to manage membership of a group without being admins.
+#[derive(Deserialize, Debug, Clone, Serialize, ToSchema)]
+pub(crate) struct GroupOwnersRequest {
+    owners: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, ToSchema)]
+pub(crate) struct GroupJoinRequestData {
+    #[serde(default)]
+    comment: Option<String>,
+}
+
 #[derive(Deserialize, Debug, Clone, ToSchema)]
 pub(crate) struct BulkAssignToGroupsRequest {
     // groups by name
@@ -67,6 +242,7 @@ pub(crate) struct BulkAssignToGroupsRequest {
 )]
 pub(crate) async fn bulk_assign_to_groups(
     _role: AdminRole,
+    session: SessionInfo,
     State(appstate): State<AppState>,
     context: ApiRequestContext,
     Json(data): Json<BulkAssignToGroupsRequest>,
@@ -86,7 +262,7 @@ pub(crate) async fn bulk_assign_to_groups(
 
     let groups = query_as!(
         Group,
-        "SELECT * FROM \"group\" WHERE name = ANY($1)",
+        "SELECT id, name, is_admin FROM \"group\" WHERE name = ANY($1)",
         &data.groups
     )
     .fetch_all(&appstate.pool)
@@ -103,6 +279,17 @@ pub(crate) async fn bulk_assign_to_groups(
             "Request contained groups that doesn't exists in db.".into(),
         ));
     }
+    for group in &groups {
+        check_not_ldap_managed(&appstate.pool, group).await?;
+        session
+            .check_token_target_group(&appstate.pool, group)
+            .await?;
+    }
+    for user in &users {
+        session
+            .check_token_target_user(&appstate.pool, user)
+            .await?;
+    }
 
     let mut ldap_user_groups: HashMap<&User<Id>, HashSet<&str>> = HashMap::new();
     let mut transaction = appstate.pool.begin().await?;
@@ -114,6 +301,7 @@ pub(crate) async fn bulk_assign_to_groups(
                 .or_default()
                 .insert(&group.name);
         }
+        group.check_quota(&mut *transaction).await?;
     }
 
     WireguardNetwork::sync_all_networks(&mut transaction, &appstate.wireguard_tx).await?;
@@ -145,6 +333,15 @@ pub(crate) async fn bulk_assign_to_groups(
 ///
 /// For each group, the endpoint retrieves a `GroupInfo` object containing: group name, a list of members usernames and a list of vpn_location.
 ///
+/// Results can be narrowed down with following query parameters:
+/// - `search`: term matched against group names and usernames of members
+/// - `sort_by`: `name` (default) or `members` (number of members)
+/// - `sort_order`: `asc` (default) or `desc`
+/// - `page`, `per_page`: page of results to return, all matching groups are returned
+///   if `per_page` isn't set
+///
+/// Total number of matching groups is returned in the `X-Total-Count` header.
+///
 /// **There is another endpoint "/api/v1/group" that retrieves only name of each groups if you don't want all information.**
 ///
 /// # Returns
@@ -154,14 +351,24 @@ pub(crate) async fn bulk_assign_to_groups(
 #[utoipa::path(
     get,
     path = "/api/v1/group-info",
+    params(
+        ("search" = Option<String>, Query, description = "Term matched against group names and member usernames"),
+        ("sort_by" = Option<String>, Query, description = "Sort by `name` or `members`"),
+        ("sort_order" = Option<String>, Query, description = "Sort order, `asc` or `desc`"),
+        ("page" = Option<u32>, Query, description = "Page number, starting from 1"),
+        ("per_page" = Option<u32>, Query, description = "Number of groups per page")
+    ),
     responses(
-        (status = 200, description = "Successfully listed groups info.", body = [GroupInfo], example = json!([
+        (status = 200, description = "Successfully listed groups info.", body = [GroupInfo], headers(
+            ("X-Total-Count" = u32, description = "Total number of matching groups")
+        ), example = json!([
             {
                 "name": "name",
                 "members": ["user"],
                 "vpn_locations": ["location"]
             }
         ])),
+        (status = 400, description = "Invalid pagination parameters.", body = ApiResponse, example = json!({"msg": "Page numbers start from 1"})),
         (status = 401, description = "Unauthorized to list groups info.", body = ApiResponse, example = json!({"msg": "Session is required"})),
         (status = 401, description = "Unauthorized to assign users to groups.", body = ApiResponse, example = json!({"msg": "Session is required"})),
         (status = 403, description = "You don't have permission to list groups info.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
@@ -175,27 +382,58 @@ pub(crate) async fn bulk_assign_to_groups(
 pub(crate) async fn list_groups_info(
     _role: AdminRole,
     State(appstate): State<AppState>,
-) -> ApiResult {
-    debug!("Listing groups info");
-    let q_result = query_as!(
-        GroupInfo,
+    Query(params): Query<GroupInfoQuery>,
+) -> Result<Response, WebError> {
+    debug!("Listing groups info with {params:?}");
+    if params.page == 0 {
+        return Err(WebError::BadRequest("Page numbers start from 1".into()));
+    }
+    if params.per_page == Some(0) {
+        return Err(WebError::BadRequest("Page size must be positive".into()));
+    }
+
+    // member and location lists are only aggregated for groups on the requested page
+    let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
         "SELECT g.id, g.name, \
-        COALESCE(ARRAY_AGG(DISTINCT u.username) FILTER (WHERE u.username IS NOT NULL), '{}') \"members!\", \
-        COALESCE(ARRAY_AGG(DISTINCT wn.name) FILTER (WHERE wn.name IS NOT NULL), '{}') \"vpn_locations!\", \
-        is_admin \
-        FROM \"group\" g \
-        LEFT JOIN \"group_user\" gu ON gu.group_id = g.id \
-        LEFT JOIN \"user\" u ON u.id = gu.user_id \
-        LEFT JOIN \"wireguard_network_allowed_group\" wnag ON wnag.group_id = g.id \
-        LEFT JOIN \"wireguard_network\" wn ON wn.id = wnag.network_id \
-        GROUP BY g.name, g.id"
-    )
-    .fetch_all(&appstate.pool)
-    .await?;
-    Ok(ApiResponse {
-        json: json!(q_result),
-        status: StatusCode::OK,
-    })
+        ARRAY(SELECT u.username FROM group_user gu JOIN \"user\" u ON u.id = gu.user_id \
+            WHERE gu.group_id = g.id ORDER BY u.username) members, \
+        ARRAY(SELECT wn.name FROM wireguard_network_allowed_group wnag \
+            JOIN wireguard_network wn ON wn.id = wnag.network_id \
+            WHERE wnag.group_id = g.id ORDER BY wn.name) vpn_locations, \
+        g.is_admin, p.name parent \
+        FROM \"group\" g LEFT JOIN \"group\" p ON p.id = g.parent_id WHERE 1=1",
+    );
+    params.apply_search(&mut query_builder);
+    let order = params.sort_order.as_ref().unwrap_or(&SortOrder::Asc);
+    match params.sort_by {
+        GroupInfoSortKey::Name => query_builder.push(format!(" ORDER BY g.name {order}")),
+        GroupInfoSortKey::Members => query_builder.push(format!(
+            " ORDER BY (SELECT COUNT(*) FROM group_user gu WHERE gu.group_id = g.id) {order}, \
+            g.name"
+        )),
+    };
+    if let Some(per_page) = params.per_page {
+        let per_page = per_page.min(MAX_GROUP_INFO_PAGE_SIZE);
+        query_builder
+            .push(" LIMIT ")
+            .push_bind(i64::from(per_page))
+            .push(" OFFSET ")
+            .push_bind(i64::from(params.page - 1) * i64::from(per_page));
+    }
+    let groups = query_builder
+        .build_query_as::<GroupInfo>()
+        .fetch_all(appstate.read_pool())
+        .await?;
+
+    let mut count_query_builder: QueryBuilder<Postgres> =
+        QueryBuilder::new("SELECT COUNT(*) FROM \"group\" g WHERE 1=1");
+    params.apply_search(&mut count_query_builder);
+    let total: i64 = count_query_builder
+        .build_query_scalar()
+        .fetch_one(appstate.read_pool())
+        .await?;
+
+    Ok(([(TOTAL_COUNT_HEADER, total.to_string())], Json(groups)).into_response())
 }
 
 /// Retrieve all groups.
@@ -255,7 +493,8 @@ pub(crate) async fn list_groups(
                 "name": "name",
                 "members": ["user"],
                 "vpn_locations": ["location"],
-                "is_admin": false
+                "is_admin": false,
+                "parent": null
             }
         )),
         (status = 401, description = "Unauthorized to retrieve a group.", body = ApiResponse, example = json!({"msg": "Session is required"})),
@@ -280,6 +519,10 @@ pub(crate) async fn get_group(
         let is_admin = group
             .has_permission(&appstate.pool, Permission::IsAdmin)
             .await?;
+        let parent = group
+            .parent(&appstate.pool)
+            .await?
+            .map(|parent| parent.name);
         info!("Retrieved group {name}");
         Ok(ApiResponse {
             json: json!(GroupInfo::new(
@@ -287,7 +530,8 @@ pub(crate) async fn get_group(
                 name,
                 members,
                 vpn_locations,
-                is_admin
+                is_admin,
+                parent
             )),
             status: StatusCode::OK,
         })
@@ -304,24 +548,41 @@ pub(crate) async fn get_group(
 ///
 /// You can also choose whether group should grant admin privileges by changing `is_admin` parameter.
 ///
+/// Creating a group which already exists fails, unless `if_not_exists` query parameter is set.
+/// Then given members are added to the existing group instead, other members and `is_admin`
+/// remain unchanged.
+///
 /// # Returns
-/// - `EditGroupInfo` object
+/// - `EditGroupInfo` object if the group has been created
+///
+/// - `MergedGroupMembers` object if members have been added to an existing group
 ///
 /// - `WebError` if error occurs
 #[utoipa::path(
     post,
     path = "/api/v1/group",
+    params(
+        ("if_not_exists" = Option<bool>, Query, description = "Add members to the group if it already exists")
+    ),
     request_body = EditGroupInfo,
     responses(
+        (status = 200, description = "Group already existed, added missing members.", body = MergedGroupMembers, example = json!(
+            {
+                "name": "name",
+                "added": ["user"]
+            }
+        )),
         (status = 201, description = "Successfully created a group and added users.", body = EditGroupInfo, example = json!(
             {
                 "name": "name",
                 "members": ["user"]
             }
         )),
+        (status = 400, description = "Existing group is managed by LDAP.", body = ApiResponse, example = json!({"msg": "Group <group_name> is managed by LDAP"})),
         (status = 401, description = "Unauthorized to retrieve a group.", body = ApiResponse, example = json!({"msg": "Session is required"})),
         (status = 403, description = "You don't have permission to list groups info.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
         (status = 404, description = "Cannot create group: user don't exist.", body = ApiResponse, example = json!({"msg": "Failed to find user <username>"})),
+        (status = 409, description = "Group already exists.", body = ApiResponse, example = json!({"msg": "Group <group_name> already exists"})),
         (status = 500, description = "Cannot retrieve a group.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
     ),
     security(
@@ -331,17 +592,40 @@ pub(crate) async fn get_group(
 )]
 pub(crate) async fn create_group(
     _role: AdminRole,
+    session: SessionInfo,
     State(appstate): State<AppState>,
     context: ApiRequestContext,
+    Query(params): Query<CreateGroupQuery>,
     Json(group_info): Json<EditGroupInfo>,
 ) -> ApiResult {
     debug!("Creating group {}", group_info.name);
+    if group_info.is_admin && session.is_scoped_token() {
+        return Err(WebError::Forbidden(
+            "API token is not allowed to modify admin groups".into(),
+        ));
+    }
+
+    if let Some(group) = Group::find_by_name(&appstate.pool, &group_info.name).await? {
+        if params.if_not_exists {
+            session
+                .check_token_target_group(&appstate.pool, &group)
+                .await?;
+            return merge_group_members(&appstate, context, group, &group_info.members).await;
+        }
+        return Err(group_exists(&group_info.name));
+    }
 
     let mut ldap_user_groups: HashMap<&User<Id>, HashSet<&str>> = HashMap::new();
     let mut transaction = appstate.pool.begin().await?;
 
-    // FIXME: conflicts must not return internal server error (500).
-    let group = Group::new(&group_info.name).save(&appstate.pool).await?;
+    // the group may have been created concurrently
+    let group = match Group::new(&group_info.name).save(&mut *transaction).await {
+        Ok(group) => group,
+        Err(SqlxError::Database(err)) if err.is_unique_violation() => {
+            return Err(group_exists(&group_info.name));
+        }
+        Err(err) => return Err(err.into()),
+    };
     group
         .set_permission(&mut *transaction, Permission::IsAdmin, group_info.is_admin)
         .await?;
@@ -349,6 +633,9 @@ pub(crate) async fn create_group(
     let mut members = Vec::new();
     for member_username in &group_info.members {
         if let Some(user) = User::find_by_username(&mut *transaction, member_username).await? {
+            session
+                .check_token_target_user(&mut *transaction, &user)
+                .await?;
             members.push(user);
         } else {
             let msg = format!("Failed to find user {member_username}");
@@ -391,6 +678,84 @@ pub(crate) async fn create_group(
     })
 }
 
+fn group_exists(name: &str) -> WebError {
+    let msg = format!("Group {name} already exists");
+    warn!(msg);
+    WebError::ObjectAlreadyExists(msg)
+}
+
+/// Adds users who aren't members of the group yet, leaving other members in place.
+async fn merge_group_members(
+    appstate: &AppState,
+    context: ApiRequestContext,
+    group: Group<Id>,
+    usernames: &[String],
+) -> ApiResult {
+    debug!("Group {} already exists, merging members", group.name);
+    check_not_ldap_managed(&appstate.pool, &group).await?;
+    let mut current_members: HashSet<String> = group
+        .member_usernames(&appstate.pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    let mut added = Vec::new();
+    let mut transaction = appstate.pool.begin().await?;
+    for username in usernames {
+        if current_members.contains(username) {
+            continue;
+        }
+        let Some(user) = User::find_by_username(&mut *transaction, username).await? else {
+            let msg = format!("Failed to find user {username}");
+            error!(msg);
+            return Err(WebError::ObjectNotFound(msg));
+        };
+        user.add_to_group(&mut *transaction, &group).await?;
+        current_members.insert(user.username.clone());
+        added.push(user);
+    }
+    if !added.is_empty() {
+        group.check_quota(&mut *transaction).await?;
+        WireguardNetwork::sync_all_networks(&mut transaction, &appstate.wireguard_tx).await?;
+    }
+    transaction.commit().await?;
+
+    let added_usernames: Vec<_> = added.iter().map(|user| user.username.clone()).collect();
+    if !added.is_empty() {
+        let ldap_user_groups = added
+            .iter()
+            .map(|user| (user, hashset![group.name.as_str()]))
+            .collect();
+        ldap_add_users_to_groups(ldap_user_groups, &appstate.pool).await;
+        Box::pin(ldap_update_users_state(
+            added.iter_mut().collect(),
+            &appstate.pool,
+        ))
+        .await;
+
+        info!(
+            "Added users {added_usernames:?} to existing group {}",
+            group.name
+        );
+        appstate.emit_event(ApiEvent {
+            context,
+            event: Box::new(ApiEventType::GroupMembersModified {
+                group: group.clone(),
+                added,
+                removed: Vec::new(),
+            }),
+        })?;
+    }
+
+    Ok(ApiResponse {
+        json: json!(MergedGroupMembers {
+            name: group.name,
+            added: added_usernames,
+        }),
+        status: StatusCode::OK,
+    })
+}
+
 /// Modify group
 ///
 /// Rename group and change members basing on `EditGroupInfo` object.
@@ -407,6 +772,7 @@ pub(crate) async fn create_group(
     request_body = EditGroupInfo,
     responses(
         (status = 201, description = "Successfully updated group."),
+        (status = 400, description = "Group is managed by LDAP.", body = ApiResponse, example = json!({"msg": "Group <group_name> is managed by LDAP"})),
         (status = 401, description = "Unauthorized to update user group.", body = ApiResponse, example = json!({"msg": "Session is required"})),
         (status = 403, description = "You don't have permission to update user group.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
         (status = 404, description = "Cannot update group: user or group don't exist.", body = ApiResponse, example = json!({"msg": "Group <group_name> not found"})),
@@ -419,17 +785,24 @@ pub(crate) async fn create_group(
 )]
 pub(crate) async fn modify_group(
     _role: AdminRole,
+    session: SessionInfo,
     State(appstate): State<AppState>,
     context: ApiRequestContext,
     Path(name): Path<String>,
     Json(group_info): Json<EditGroupInfo>,
 ) -> ApiResult {
     debug!("Modifying group {}", group_info.name);
+    if group_info.is_admin && session.is_scoped_token() {
+        return Err(WebError::Forbidden(
+            "API token is not allowed to modify admin groups".into(),
+        ));
+    }
     let Some(mut group) = Group::find_by_name(&appstate.pool, &name).await? else {
         let msg = format!("Group {name} not found");
         error!(msg);
         return Err(WebError::ObjectNotFound(msg));
     };
+    check_not_ldap_managed(&appstate.pool, &group).await?;
     // store group before modifications
     let before = group.clone();
 
@@ -484,6 +857,11 @@ pub(crate) async fn modify_group(
             members.push(user);
         }
     }
+    for user in members.iter().chain(&current_members) {
+        session
+            .check_token_target_user(&mut *transaction, user)
+            .await?;
+    }
 
     for user in &members {
         user.add_to_group(&mut *transaction, &group).await?;
@@ -492,6 +870,9 @@ pub(crate) async fn modify_group(
             .or_default()
             .insert(group.name.as_str());
     }
+    if !members.is_empty() {
+        group.check_quota(&mut *transaction).await?;
+    }
 
     // Remove outstanding members.
     for user in &current_members {
@@ -584,6 +965,7 @@ pub(crate) async fn delete_group(
 ) -> ApiResult {
     debug!("User {} deletes group {name}", &session.user.username);
     if let Some(group) = Group::find_by_name(&appstate.pool, &name).await? {
+        check_not_ldap_managed(&appstate.pool, &group).await?;
         // Prevent removing the last admin group
         if group.is_admin {
             let admin_group_count = Group::find_by_permission(&appstate.pool, Permission::IsAdmin)
@@ -620,6 +1002,8 @@ pub(crate) async fn delete_group(
 /// Add a group member
 ///
 /// Find a group with `name` and add `username` as a member.
+/// Membership can be limited in time with `expires_at`, which also updates the expiry
+/// of existing members. Allowed for admins and owners of the group.
 ///
 /// # Returns
 /// - `WebError` if error occurs
@@ -629,9 +1013,10 @@ pub(crate) async fn delete_group(
     params(
         ("name" = String, description = "Group name")
     ),
-    request_body = Username,
+    request_body = GroupMemberRequest,
     responses(
         (status = 200, description = "Successfully add a new member to group."),
+        (status = 400, description = "Group is managed by LDAP or expiry time is in the past.", body = ApiResponse, example = json!({"msg": "Group <group_name> is managed by LDAP"})),
         (status = 401, description = "Unauthorized to add a new group member.", body = ApiResponse, example = json!({"msg": "Session is required"})),
         (status = 403, description = "You don't have permission to add a new group member.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
         (status = 404, description = "Cannot add a new group member: user or group don't exist.", body = ApiResponse, example = json!({"msg": "Failed to find group <group_name>"})),
@@ -643,16 +1028,36 @@ pub(crate) async fn delete_group(
     )
 )]
 pub(crate) async fn add_group_member(
-    _role: AdminRole,
+    _role: GroupOwner,
+    session: SessionInfo,
     State(appstate): State<AppState>,
     context: ApiRequestContext,
     Path(name): Path<String>,
-    Json(data): Json<Username>,
+    Json(data): Json<GroupMemberRequest>,
 ) -> ApiResult {
+    if data
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now().naive_utc())
+    {
+        return Err(WebError::BadRequest(
+            "Group membership expiry time must be in the future".into(),
+        ));
+    }
     if let Some(group) = Group::find_by_name(&appstate.pool, &name).await? {
+        check_not_ldap_managed(&appstate.pool, &group).await?;
         if let Some(mut user) = User::find_by_username(&appstate.pool, &data.username).await? {
-            debug!("Adding user: {} to group: {}", user.username, group.name);
-            user.add_to_group(&appstate.pool, &group).await?;
+            session
+                .check_token_target_user(&appstate.pool, &user)
+                .await?;
+            debug!(
+                "Adding user: {} to group: {} until {:?}",
+                user.username, group.name, data.expires_at
+            );
+            let mut transaction = appstate.pool.begin().await?;
+            user.add_to_group_until(&mut *transaction, &group, data.expires_at)
+                .await?;
+            group.check_quota(&mut *transaction).await?;
+            transaction.commit().await?;
             ldap_add_user_to_groups(&user, hashset![group.name.as_str()], &appstate.pool).await;
             ldap_update_user_state(&mut user, &appstate.pool).await;
             let mut conn = appstate.pool.acquire().await?;
@@ -680,6 +1085,7 @@ pub(crate) async fn add_group_member(
 /// Remove `username` from group with `name`.
 ///
 /// Find a group with `name` and remove `username` as a member.
+/// Allowed for admins and owners of the group.
 ///
 /// # Returns
 /// - `WebError` if error occurs
@@ -692,6 +1098,7 @@ pub(crate) async fn add_group_member(
     ),
     responses(
         (status = 200, description = "Successfully remove a member from group.", body = ApiResponse, example = json!({})),
+        (status = 400, description = "Group is managed by LDAP.", body = ApiResponse, example = json!({"msg": "Group <group_name> is managed by LDAP"})),
         (status = 401, description = "Unauthorized to remove a group member.", body = ApiResponse, example = json!({"msg": "Session is required"})),
         (status = 403, description = "You don't have permission to remove a group member.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
         (status = 404, description = "Cannot remove a  group member: user or group don't exist.", body = ApiResponse, example = json!({"msg": "Failed to find group <group_name>"})),
@@ -703,12 +1110,13 @@ pub(crate) async fn add_group_member(
     )
 )]
 pub(crate) async fn remove_group_member(
-    _role: AdminRole,
+    _role: GroupOwner,
     State(appstate): State<AppState>,
     context: ApiRequestContext,
     Path((name, username)): Path<(String, String)>,
 ) -> ApiResult {
     if let Some(group) = Group::find_by_name(&appstate.pool, &name).await? {
+        check_not_ldap_managed(&appstate.pool, &group).await?;
         if let Some(user) = User::find_by_username(&appstate.pool, &username).await? {
             debug!(
                 "Removing user: {} from group: {}",
@@ -739,3 +1147,941 @@ pub(crate) async fn remove_group_member(
         Err(WebError::ObjectNotFound(format!("Group {name} not found",)))
     }
 }
+
+/// Retrieve quota utilization of all groups
+///
+/// For each group, returns its user and device limits along with the current number of members
+/// and the total number of their devices.
+///
+/// # Returns
+/// - list of `GroupQuotaUsage` objects
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    get,
+    path = "/api/v1/group-quota",
+    responses(
+        (status = 200, description = "Successfully listed group quotas.", body = [GroupQuotaUsage], example = json!([
+            {
+                "name": "contractors",
+                "max_users": 10,
+                "users": 4,
+                "max_devices": 20,
+                "devices": 7
+            }
+        ])),
+        (status = 401, description = "Unauthorized to list group quotas.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to list group quotas.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 500, description = "Cannot list group quotas.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn list_group_quotas(
+    _role: AdminRole,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    debug!("Listing group quotas");
+    let quotas = GroupQuotaUsage::all(&appstate.pool).await?;
+    Ok(ApiResponse {
+        json: json!(quotas),
+        status: StatusCode::OK,
+    })
+}
+
+/// Set group quota
+///
+/// Limit the number of group members, the total number of their devices and the number of
+/// devices each member can have connected to VPN at the same time.
+/// Limits set to `null` are disabled. Lowering a limit below current utilization doesn't remove
+/// any members or devices, but blocks adding new ones.
+///
+/// When a member connects more devices than `max_sessions` allows, their oldest sessions in
+/// MFA-protected locations are disconnected. Users in multiple groups get the lowest limit.
+///
+/// # Returns
+/// - `GroupQuota` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    put,
+    path = "/api/v1/group-quota/{name}",
+    params(
+        ("name" = String, description = "Group name")
+    ),
+    request_body = GroupQuota,
+    responses(
+        (status = 200, description = "Successfully set group quota.", body = GroupQuota, example = json!({"max_users": 10, "max_devices": null, "max_sessions": 2})),
+        (status = 400, description = "Invalid quota.", body = ApiResponse, example = json!({"msg": "Quota limits can't be negative"})),
+        (status = 401, description = "Unauthorized to set group quota.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to set group quota.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Group not found.", body = ApiResponse, example = json!({"msg": "Group <name> not found"})),
+        (status = 500, description = "Cannot set group quota.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn set_group_quota(
+    _role: AdminRole,
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    context: ApiRequestContext,
+    Path(name): Path<String>,
+    Json(quota): Json<GroupQuota>,
+) -> ApiResult {
+    debug!(
+        "User {} setting quota for group {name}",
+        session.user.username
+    );
+    let Some(group) = Group::find_by_name(&appstate.pool, &name).await? else {
+        let msg = format!("Group {name} not found");
+        error!(msg);
+        return Err(WebError::ObjectNotFound(msg));
+    };
+    if quota.max_users.is_some_and(|limit| limit < 0)
+        || quota.max_devices.is_some_and(|limit| limit < 0)
+    {
+        return Err(WebError::BadRequest(
+            "Quota limits can't be negative".into(),
+        ));
+    }
+    if quota.max_sessions.is_some_and(|limit| limit < 1) {
+        return Err(WebError::BadRequest(
+            "Session limit must be at least 1".into(),
+        ));
+    }
+    group.set_quota(&appstate.pool, &quota).await?;
+    info!(
+        "User {} set quota for group {name}: {quota:?}",
+        session.user.username
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::GroupQuotaModified {
+            group,
+            quota: quota.clone(),
+        }),
+    })?;
+    Ok(ApiResponse {
+        json: json!(quota),
+        status: StatusCode::OK,
+    })
+}
+
+/// Set group sync authority
+///
+/// Decide how membership differences between Defguard and LDAP are resolved for this group:
+/// `local` keeps Defguard membership, `ldap` keeps LDAP membership and rejects local membership
+/// changes, `merge` adds missing members on both sides. Set to `null` to follow the global
+/// LDAP authority.
+///
+/// # Returns
+/// - `GroupSyncAuthorityRequest` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    put,
+    path = "/api/v1/group/{name}/sync_authority",
+    params(
+        ("name" = String, description = "Group name")
+    ),
+    request_body = GroupSyncAuthorityRequest,
+    responses(
+        (status = 200, description = "Successfully set group sync authority.", body = GroupSyncAuthorityRequest, example = json!({"sync_authority": "merge"})),
+        (status = 401, description = "Unauthorized to set group sync authority.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to set group sync authority.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Group not found.", body = ApiResponse, example = json!({"msg": "Group <name> not found"})),
+        (status = 500, description = "Cannot set group sync authority.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn set_group_sync_authority(
+    _role: AdminRole,
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    context: ApiRequestContext,
+    Path(name): Path<String>,
+    Json(data): Json<GroupSyncAuthorityRequest>,
+) -> ApiResult {
+    debug!(
+        "User {} setting sync authority for group {name}",
+        session.user.username
+    );
+    let Some(group) = Group::find_by_name(&appstate.pool, &name).await? else {
+        let msg = format!("Group {name} not found");
+        error!(msg);
+        return Err(WebError::ObjectNotFound(msg));
+    };
+    group
+        .set_sync_authority(&appstate.pool, data.sync_authority)
+        .await?;
+    info!(
+        "User {} set sync authority for group {name}: {:?}",
+        session.user.username, data.sync_authority
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::GroupSyncAuthorityModified {
+            group,
+            sync_authority: data.sync_authority,
+        }),
+    })?;
+    Ok(ApiResponse {
+        json: json!(data),
+        status: StatusCode::OK,
+    })
+}
+
+/// Set group parent
+///
+/// Nest group under a parent group. Members of the group inherit VPN location access and admin
+/// permission from the parent group and all of its ancestors. Set to `null` to make it
+/// a top-level group.
+///
+/// # Returns
+/// - `GroupParentRequest` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    put,
+    path = "/api/v1/group/{name}/parent",
+    params(
+        ("name" = String, description = "Group name")
+    ),
+    request_body = GroupParentRequest,
+    responses(
+        (status = 200, description = "Successfully set group parent.", body = GroupParentRequest, example = json!({"parent": "engineering"})),
+        (status = 400, description = "Group can't be nested under itself or its descendant.", body = ApiResponse, example = json!({"msg": "Group <name> can't be nested under <parent>"})),
+        (status = 401, description = "Unauthorized to set group parent.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to set group parent.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Group not found.", body = ApiResponse, example = json!({"msg": "Group <name> not found"})),
+        (status = 500, description = "Cannot set group parent.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn set_group_parent(
+    _role: AdminRole,
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    context: ApiRequestContext,
+    Path(name): Path<String>,
+    Json(data): Json<GroupParentRequest>,
+) -> ApiResult {
+    debug!(
+        "User {} setting parent of group {name}",
+        session.user.username
+    );
+    let Some(group) = Group::find_by_name(&appstate.pool, &name).await? else {
+        let msg = format!("Group {name} not found");
+        error!(msg);
+        return Err(WebError::ObjectNotFound(msg));
+    };
+    let parent = match &data.parent {
+        Some(parent_name) => {
+            let Some(parent) = Group::find_by_name(&appstate.pool, parent_name).await? else {
+                let msg = format!("Group {parent_name} not found");
+                error!(msg);
+                return Err(WebError::ObjectNotFound(msg));
+            };
+            // prevent cycles in group hierarchy
+            if parent.id == group.id
+                || group
+                    .descendant_ids(&appstate.pool)
+                    .await?
+                    .contains(&parent.id)
+            {
+                let msg = format!("Group {name} can't be nested under {parent_name}");
+                error!(msg);
+                return Err(WebError::BadRequest(msg));
+            }
+            Some(parent)
+        }
+        None => None,
+    };
+    // moving a group changes inherited memberships of its old and new ancestors
+    check_not_ldap_managed(&appstate.pool, &group).await?;
+    if let Some(old_parent) = group.parent(&appstate.pool).await? {
+        check_not_ldap_managed(&appstate.pool, &old_parent).await?;
+    }
+    if let Some(parent) = &parent {
+        check_not_ldap_managed(&appstate.pool, parent).await?;
+    }
+
+    let mut transaction = appstate.pool.begin().await?;
+    group
+        .set_parent(&mut *transaction, parent.as_ref().map(|parent| parent.id))
+        .await?;
+    // inherited location access may have changed
+    WireguardNetwork::sync_all_networks(&mut transaction, &appstate.wireguard_tx).await?;
+    transaction.commit().await?;
+
+    info!(
+        "User {} set parent of group {name}: {:?}",
+        session.user.username, data.parent
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::GroupParentModified { group, parent }),
+    })?;
+    Ok(ApiResponse {
+        json: json!(data),
+        status: StatusCode::OK,
+    })
+}
+
+/// Set group owners
+///
+/// Owners can add and remove members of the group without being admins.
+/// Groups granting admin permissions can still be managed by admins only.
+///
+/// # Returns
+/// - `GroupOwnersRequest` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    put,
+    path = "/api/v1/group/{name}/owners",
+    params(
+        ("name" = String, description = "Group name")
+    ),
+    request_body = GroupOwnersRequest,
+    responses(
+        (status = 200, description = "Successfully set group owners.", body = GroupOwnersRequest, example = json!({"owners": ["hpotter"]})),
+        (status = 401, description = "Unauthorized to set group owners.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to set group owners.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Group or owner not found.", body = ApiResponse, example = json!({"msg": "Group <name> not found"})),
+        (status = 500, description = "Cannot set group owners.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn set_group_owners(
+    _role: AdminRole,
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    Path(name): Path<String>,
+    Json(data): Json<GroupOwnersRequest>,
+) -> ApiResult {
+    debug!(
+        "User {} setting owners of group {name}",
+        session.user.username
+    );
+    let Some(group) = Group::find_by_name(&appstate.pool, &name).await? else {
+        let msg = format!("Group {name} not found");
+        error!(msg);
+        return Err(WebError::ObjectNotFound(msg));
+    };
+    let owner_ids = find_user_ids(&appstate.pool, &data.owners).await?;
+    let mut transaction = appstate.pool.begin().await?;
+    group.set_owners(&mut transaction, &owner_ids).await?;
+    transaction.commit().await?;
+
+    info!(
+        "User {} set owners of group {name}: {:?}",
+        session.user.username, data.owners
+    );
+    Ok(ApiResponse {
+        json: json!(data),
+        status: StatusCode::OK,
+    })
+}
+
+/// Set group self-service settings
+///
+/// Allow users to ask to join the group and choose its owners, who are notified about
+/// join requests and can decide on them.
+///
+/// # Returns
+/// - `GroupSelfServiceRequest` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    put,
+    path = "/api/v1/group/{name}/self_service",
+    params(
+        ("name" = String, description = "Group name")
+    ),
+    request_body = GroupSelfServiceRequest,
+    responses(
+        (status = 200, description = "Successfully set group self-service settings.", body = GroupSelfServiceRequest, example = json!({"self_service": true, "owners": ["hpotter"]})),
+        (status = 401, description = "Unauthorized to set group self-service settings.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to set group self-service settings.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Group or owner not found.", body = ApiResponse, example = json!({"msg": "Group <name> not found"})),
+        (status = 500, description = "Cannot set group self-service settings.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn set_group_self_service(
+    _role: AdminRole,
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    Path(name): Path<String>,
+    Json(data): Json<GroupSelfServiceRequest>,
+) -> ApiResult {
+    debug!(
+        "User {} setting self-service settings of group {name}",
+        session.user.username
+    );
+    let Some(group) = Group::find_by_name(&appstate.pool, &name).await? else {
+        let msg = format!("Group {name} not found");
+        error!(msg);
+        return Err(WebError::ObjectNotFound(msg));
+    };
+    let owner_ids = find_user_ids(&appstate.pool, &data.owners).await?;
+
+    let mut transaction = appstate.pool.begin().await?;
+    group
+        .set_self_service(&mut *transaction, data.self_service)
+        .await?;
+    group.set_owners(&mut transaction, &owner_ids).await?;
+    transaction.commit().await?;
+
+    info!(
+        "User {} set self-service settings of group {name}: {data:?}",
+        session.user.username
+    );
+    Ok(ApiResponse {
+        json: json!(data),
+        status: StatusCode::OK,
+    })
+}
+
+/// List self-service groups
+///
+/// List groups which the current user isn't a member of, but can ask to join.
+///
+/// # Returns
+/// - list of `SelfServiceGroup` objects
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    get,
+    path = "/api/v1/group-self-service",
+    responses(
+        (status = 200, description = "List of self-service groups.", body = [SelfServiceGroup], example = json!([{"name": "quidditch", "pending": false}])),
+        (status = 401, description = "Unauthorized to list self-service groups.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 500, description = "Cannot list self-service groups.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn list_self_service_groups(
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    let groups = SelfServiceGroup::all_for_user(&appstate.pool, session.user.id).await?;
+    Ok(ApiResponse {
+        json: json!(groups),
+        status: StatusCode::OK,
+    })
+}
+
+/// Request group membership
+///
+/// Ask to join a self-service group. Group owners, or admins if the group has no owners,
+/// are notified by email and decide on the request.
+///
+/// # Returns
+/// - `WebError` if error occurs
+#[utoipa::path(
+    post,
+    path = "/api/v1/group/{name}/join_request",
+    params(
+        ("name" = String, description = "Group name")
+    ),
+    request_body = GroupJoinRequestData,
+    responses(
+        (status = 201, description = "Successfully requested group membership."),
+        (status = 400, description = "Group doesn't accept join requests, user is already a member or waits for a decision.", body = ApiResponse, example = json!({"msg": "Group <name> doesn't accept join requests"})),
+        (status = 401, description = "Unauthorized to request group membership.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 404, description = "Group not found.", body = ApiResponse, example = json!({"msg": "Group <name> not found"})),
+        (status = 500, description = "Cannot request group membership.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn request_group_membership(
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    Path(name): Path<String>,
+    Json(data): Json<GroupJoinRequestData>,
+) -> ApiResult {
+    let user = session.user;
+    debug!("User {} asking to join group {name}", user.username);
+    let Some(group) = Group::find_by_name(&appstate.pool, &name).await? else {
+        let msg = format!("Group {name} not found");
+        error!(msg);
+        return Err(WebError::ObjectNotFound(msg));
+    };
+    if !group.self_service(&appstate.pool).await? {
+        let msg = format!("Group {name} doesn't accept join requests");
+        warn!(msg);
+        return Err(WebError::BadRequest(msg));
+    }
+    check_not_ldap_managed(&appstate.pool, &group).await?;
+    if user
+        .member_of(&appstate.pool)
+        .await?
+        .iter()
+        .any(|member_of| member_of.id == group.id)
+    {
+        let msg = format!("User {} is already a member of group {name}", user.username);
+        warn!(msg);
+        return Err(WebError::BadRequest(msg));
+    }
+    if GroupJoinRequest::find_pending(&appstate.pool, group.id, user.id)
+        .await?
+        .is_some()
+    {
+        let msg = format!(
+            "User {} has already asked to join group {name}",
+            user.username
+        );
+        warn!(msg);
+        return Err(WebError::BadRequest(msg));
+    }
+
+    let comment = data.comment.filter(|comment| !comment.trim().is_empty());
+    GroupJoinRequest::new(group.id, user.id, comment.clone())
+        .save(&appstate.pool)
+        .await?;
+    send_group_join_request_email(
+        &appstate.pool,
+        &appstate.mail_tx,
+        &group,
+        &user,
+        comment.as_deref(),
+    )
+    .await?;
+
+    info!("User {} asked to join group {name}", user.username);
+    Ok(ApiResponse {
+        json: json!({}),
+        status: StatusCode::CREATED,
+    })
+}
+
+/// List pending group join requests
+///
+/// Admins see all pending requests, other users only requests to join groups they own.
+///
+/// # Returns
+/// - list of `GroupJoinRequestInfo` objects
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    get,
+    path = "/api/v1/group-join-request",
+    responses(
+        (status = 200, description = "List of pending group join requests.", body = [GroupJoinRequestInfo]),
+        (status = 401, description = "Unauthorized to list group join requests.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 500, description = "Cannot list group join requests.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn list_group_join_requests(
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    let owner_id = (!session.is_admin).then_some(session.user.id);
+    let requests = GroupJoinRequestInfo::all_pending(&appstate.pool, owner_id).await?;
+    Ok(ApiResponse {
+        json: json!(requests),
+        status: StatusCode::OK,
+    })
+}
+
+/// Fetches a pending join request along with its group and user, provided that the current
+/// user can decide on it.
+async fn pending_join_request(
+    pool: &PgPool,
+    session: &SessionInfo,
+    id: Id,
+) -> Result<(GroupJoinRequest<Id>, Group<Id>, User<Id>), WebError> {
+    let not_found = || WebError::ObjectNotFound(format!("Group join request {id} not found"));
+    let request = GroupJoinRequest::find_by_id(pool, id)
+        .await?
+        .ok_or_else(not_found)?;
+    let group = Group::find_by_id(pool, request.group_id)
+        .await?
+        .ok_or_else(not_found)?;
+    if !session.is_admin && !group.is_owner(pool, session.user.id).await? {
+        warn!(
+            "User {} tried to decide on request to join group {} they don't own",
+            session.user.username, group.name
+        );
+        return Err(WebError::Forbidden(format!(
+            "Only admins and owners of group {} can decide on join requests",
+            group.name
+        )));
+    }
+    if request.status != GroupJoinRequestStatus::Pending {
+        return Err(WebError::BadRequest(format!(
+            "Group join request {id} has already been decided on"
+        )));
+    }
+    let user = User::find_by_id(pool, request.user_id)
+        .await?
+        .ok_or_else(not_found)?;
+    Ok((request, group, user))
+}
+
+/// Approve group join request
+///
+/// Add the requesting user to the group. Allowed for admins and owners of the group.
+///
+/// # Returns
+/// - `WebError` if error occurs
+#[utoipa::path(
+    post,
+    path = "/api/v1/group-join-request/{id}/approve",
+    params(
+        ("id" = Id, description = "Group join request ID")
+    ),
+    responses(
+        (status = 200, description = "Successfully approved group join request."),
+        (status = 400, description = "Request has already been decided on or group is managed by LDAP.", body = ApiResponse, example = json!({"msg": "Group join request <id> has already been decided on"})),
+        (status = 401, description = "Unauthorized to approve group join request.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't own the group.", body = ApiResponse, example = json!({"msg": "Only admins and owners of group <name> can decide on join requests"})),
+        (status = 404, description = "Group join request not found.", body = ApiResponse, example = json!({"msg": "Group join request <id> not found"})),
+        (status = 500, description = "Cannot approve group join request.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn approve_group_join_request(
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    context: ApiRequestContext,
+    Path(id): Path<Id>,
+) -> ApiResult {
+    let (mut request, group, mut user) = pending_join_request(&appstate.pool, &session, id).await?;
+    check_not_ldap_managed(&appstate.pool, &group).await?;
+    debug!(
+        "User {} approving request of user {} to join group {}",
+        session.user.username, user.username, group.name
+    );
+
+    let mut transaction = appstate.pool.begin().await?;
+    request.decide(true, session.user.id);
+    request.save(&mut *transaction).await?;
+    user.add_to_group_until(&mut *transaction, &group, None)
+        .await?;
+    group.check_quota(&mut *transaction).await?;
+    transaction.commit().await?;
+    ldap_add_user_to_groups(&user, hashset![group.name.as_str()], &appstate.pool).await;
+    ldap_update_user_state(&mut user, &appstate.pool).await;
+    let mut conn = appstate.pool.acquire().await?;
+    WireguardNetwork::sync_all_networks(&mut conn, &appstate.wireguard_tx).await?;
+
+    info!(
+        "User {} approved request of user {} to join group {}",
+        session.user.username, user.username, group.name
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::GroupJoinRequestApproved { group, user }),
+    })?;
+    Ok(ApiResponse::default())
+}
+
+/// Reject group join request
+///
+/// Allowed for admins and owners of the group.
+///
+/// # Returns
+/// - `WebError` if error occurs
+#[utoipa::path(
+    post,
+    path = "/api/v1/group-join-request/{id}/reject",
+    params(
+        ("id" = Id, description = "Group join request ID")
+    ),
+    responses(
+        (status = 200, description = "Successfully rejected group join request."),
+        (status = 400, description = "Request has already been decided on.", body = ApiResponse, example = json!({"msg": "Group join request <id> has already been decided on"})),
+        (status = 401, description = "Unauthorized to reject group join request.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't own the group.", body = ApiResponse, example = json!({"msg": "Only admins and owners of group <name> can decide on join requests"})),
+        (status = 404, description = "Group join request not found.", body = ApiResponse, example = json!({"msg": "Group join request <id> not found"})),
+        (status = 500, description = "Cannot reject group join request.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn reject_group_join_request(
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    context: ApiRequestContext,
+    Path(id): Path<Id>,
+) -> ApiResult {
+    let (mut request, group, user) = pending_join_request(&appstate.pool, &session, id).await?;
+    request.decide(false, session.user.id);
+    request.save(&appstate.pool).await?;
+
+    info!(
+        "User {} rejected request of user {} to join group {}",
+        session.user.username, user.username, group.name
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::GroupJoinRequestRejected { group, user }),
+    })?;
+    Ok(ApiResponse::default())
+}
+
+/// Reconcile groups
+///
+/// Bring all groups to the state described by `GroupsReconciliationRequest`: missing groups are
+/// created, members and admin permissions of existing groups are updated and groups which
+/// aren't listed are removed. All changes are applied in a single transaction.
+///
+/// # Returns
+/// - `GroupsReconciliation` object describing applied changes
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    put,
+    path = "/api/v1/groups",
+    request_body = GroupsReconciliationRequest,
+    responses(
+        (status = 200, description = "Successfully reconciled groups.", body = GroupsReconciliation, example = json!({
+            "created": [{"name": "staff", "is_admin": false, "added": ["hpotter"], "removed": []}],
+            "modified": [],
+            "removed": ["crew"]
+        })),
+        (status = 400, description = "Invalid desired state or an affected group is managed by LDAP.", body = ApiResponse, example = json!({"msg": "At least one group must grant admin permissions"})),
+        (status = 401, description = "Unauthorized to reconcile groups.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to reconcile groups.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "One of the members doesn't exist.", body = ApiResponse, example = json!({"msg": "Failed to find user <username>"})),
+        (status = 500, description = "Cannot reconcile groups.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn reconcile_groups(
+    _role: AdminRole,
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    context: ApiRequestContext,
+    Json(data): Json<GroupsReconciliationRequest>,
+) -> ApiResult {
+    debug!(
+        "User {} reconciling {} groups",
+        session.user.username,
+        data.groups.len()
+    );
+    let mut names = HashSet::new();
+    for group in &data.groups {
+        if !names.insert(group.name.as_str()) {
+            return Err(WebError::BadRequest(format!(
+                "Group {} is listed more than once",
+                group.name
+            )));
+        }
+    }
+    if !data.groups.iter().any(|group| group.is_admin) {
+        return Err(WebError::BadRequest(
+            "At least one group must grant admin permissions".into(),
+        ));
+    }
+
+    let mut transaction = appstate.pool.begin().await?;
+    // changes are computed from the current state, which mustn't change in the meantime
+    query!("LOCK TABLE \"group\" IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut *transaction)
+        .await?;
+
+    let mut users: HashMap<&str, User<Id>> = HashMap::new();
+    for username in data.groups.iter().flat_map(|group| &group.members) {
+        if users.contains_key(username.as_str()) {
+            continue;
+        }
+        let Some(user) = User::find_by_username(&mut *transaction, username).await? else {
+            let msg = format!("Failed to find user {username}");
+            error!(msg);
+            return Err(WebError::ObjectNotFound(msg));
+        };
+        users.insert(username.as_str(), user);
+    }
+    let mut current_groups: HashMap<String, Group<Id>> = Group::all(&mut *transaction)
+        .await?
+        .into_iter()
+        .map(|group| (group.name.clone(), group))
+        .collect();
+
+    let mut result = GroupsReconciliation::default();
+    let mut events = Vec::new();
+    // (user, group name) pairs for LDAP synchronization
+    let mut added_memberships = Vec::new();
+    let mut removed_memberships = Vec::new();
+    for desired in &data.groups {
+        let desired_members: BTreeSet<&str> = desired.members.iter().map(String::as_str).collect();
+        let Some(group) = current_groups.remove(&desired.name) else {
+            let group = Group::new(&desired.name).save(&mut *transaction).await?;
+            group
+                .set_permission(&mut *transaction, Permission::IsAdmin, desired.is_admin)
+                .await?;
+            for username in &desired_members {
+                let user = &users[username];
+                user.add_to_group(&mut *transaction, &group).await?;
+                added_memberships.push((user.clone(), group.name.clone()));
+            }
+            if !desired_members.is_empty() {
+                group.check_quota(&mut *transaction).await?;
+            }
+            result.created.push(GroupChanges {
+                name: group.name.clone(),
+                is_admin: desired.is_admin,
+                added: desired_members.iter().map(ToString::to_string).collect(),
+                removed: Vec::new(),
+            });
+            events.push(ApiEventType::GroupAdded { group });
+            continue;
+        };
+
+        let current_members = group.members(&mut *transaction).await?;
+        let added: Vec<User<Id>> = desired_members
+            .iter()
+            .filter(|username| {
+                !current_members
+                    .iter()
+                    .any(|member| member.username == **username)
+            })
+            .map(|username| users[username].clone())
+            .collect();
+        let removed: Vec<User<Id>> = current_members
+            .into_iter()
+            .filter(|member| !desired_members.contains(member.username.as_str()))
+            .collect();
+        let admin_changed = group.is_admin != desired.is_admin;
+        if added.is_empty() && removed.is_empty() && !admin_changed {
+            continue;
+        }
+        check_not_ldap_managed(&appstate.pool, &group).await?;
+
+        if admin_changed {
+            group
+                .set_permission(&mut *transaction, Permission::IsAdmin, desired.is_admin)
+                .await?;
+            let mut after = group.clone();
+            after.is_admin = desired.is_admin;
+            events.push(ApiEventType::GroupModified {
+                before: group.clone(),
+                after,
+            });
+        }
+        for user in &added {
+            user.add_to_group(&mut *transaction, &group).await?;
+            added_memberships.push((user.clone(), group.name.clone()));
+        }
+        for user in &removed {
+            user.remove_from_group(&mut *transaction, &group).await?;
+            removed_memberships.push((user.clone(), group.name.clone()));
+        }
+        if !added.is_empty() {
+            group.check_quota(&mut *transaction).await?;
+        }
+        result.modified.push(GroupChanges {
+            name: group.name.clone(),
+            is_admin: desired.is_admin,
+            added: added.iter().map(|user| user.username.clone()).collect(),
+            removed: removed.iter().map(|user| user.username.clone()).collect(),
+        });
+        if !(added.is_empty() && removed.is_empty()) {
+            events.push(ApiEventType::GroupMembersModified {
+                group,
+                added,
+                removed,
+            });
+        }
+    }
+
+    // groups which weren't listed
+    let mut obsolete_groups: Vec<_> = current_groups.into_values().collect();
+    obsolete_groups.sort_by(|a, b| a.name.cmp(&b.name));
+    for group in obsolete_groups {
+        check_not_ldap_managed(&appstate.pool, &group).await?;
+        group.clone().delete(&mut *transaction).await?;
+        result.removed.push(group.name.clone());
+        events.push(ApiEventType::GroupRemoved { group });
+    }
+
+    WireguardNetwork::sync_all_networks(&mut transaction, &appstate.wireguard_tx).await?;
+    transaction.commit().await?;
+
+    let mut add_to_ldap_groups: HashMap<&User<Id>, HashSet<&str>> = HashMap::new();
+    for (user, group) in &added_memberships {
+        add_to_ldap_groups
+            .entry(user)
+            .or_default()
+            .insert(group.as_str());
+    }
+    let mut remove_from_ldap_groups: HashMap<&User<Id>, HashSet<&str>> = HashMap::new();
+    for (user, group) in &removed_memberships {
+        remove_from_ldap_groups
+            .entry(user)
+            .or_default()
+            .insert(group.as_str());
+    }
+    ldap_add_users_to_groups(add_to_ldap_groups, &appstate.pool).await;
+    ldap_remove_users_from_groups(remove_from_ldap_groups, &appstate.pool).await;
+    for name in &result.removed {
+        ldap_delete_group(name, &appstate.pool).await;
+    }
+    let mut affected_users: HashMap<Id, User<Id>> = added_memberships
+        .into_iter()
+        .chain(removed_memberships)
+        .map(|(user, _)| (user.id, user))
+        .collect();
+    Box::pin(ldap_update_users_state(
+        affected_users.values_mut().collect(),
+        &appstate.pool,
+    ))
+    .await;
+
+    info!(
+        "User {} reconciled groups: {} created, {} modified, {} removed",
+        session.user.username,
+        result.created.len(),
+        result.modified.len(),
+        result.removed.len()
+    );
+    for event in events {
+        appstate.emit_event(ApiEvent {
+            context: context.clone(),
+            event: Box::new(event),
+        })?;
+    }
+    Ok(ApiResponse {
+        json: json!(result),
+        status: StatusCode::OK,
+    })
+}
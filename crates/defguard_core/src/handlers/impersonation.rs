@@ -0,0 +1,238 @@
+use std::net::IpAddr;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use axum_client_ip::SecureClientIp;
+use axum_extra::{
+    TypedHeader,
+    extract::{
+        PrivateCookieJar,
+        cookie::{Cookie, CookieJar, SameSite},
+    },
+    headers::UserAgent,
+};
+use chrono::TimeDelta;
+use serde_json::json;
+use time::Duration;
+
+use super::{ApiResponse, SESSION_COOKIE_NAME};
+use crate::{
+    appstate::AppState,
+    auth::{AdminRole, SessionInfo},
+    db::{
+        Session, SessionState, User,
+        models::impersonation::{Impersonation, ImpersonationInfo, ImpersonationMode},
+    },
+    error::WebError,
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
+    server_config,
+};
+
+pub(crate) static IMPERSONATION_COOKIE_NAME: &str = "defguard_impersonation";
+// Impersonation time limits in minutes
+const IMPERSONATION_DEFAULT_DURATION: u32 = 15;
+const IMPERSONATION_MAX_DURATION: u32 = 60;
+
+#[derive(Deserialize, Serialize)]
+pub struct StartImpersonation {
+    pub mode: ImpersonationMode,
+    /// Defaults to 15 minutes, at most 60 minutes.
+    pub duration_minutes: Option<u32>,
+}
+
+/// Start impersonating a user.
+///
+/// The admin's requests use the user's session until the impersonation is stopped or expires,
+/// as long as the admin session it was started from is still valid.
+pub(crate) async fn start_impersonation(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    private_cookies: PrivateCookieJar,
+    State(appstate): State<AppState>,
+    Path(username): Path<String>,
+    Json(data): Json<StartImpersonation>,
+) -> Result<(PrivateCookieJar, ApiResponse), WebError> {
+    debug!(
+        "Admin {} starting {} impersonation of user {username}",
+        session.user.username, data.mode
+    );
+    // impersonation is tied to the admin session cookie
+    if session.session.state == SessionState::ApiTokenVerified {
+        return Err(WebError::BadRequest(
+            "Impersonation can't be started with an API token".into(),
+        ));
+    }
+    if session.impersonation.is_some() {
+        return Err(WebError::BadRequest(
+            "Stop the current impersonation before starting another one".into(),
+        ));
+    }
+    let duration = data
+        .duration_minutes
+        .unwrap_or(IMPERSONATION_DEFAULT_DURATION);
+    if duration == 0 || duration > IMPERSONATION_MAX_DURATION {
+        return Err(WebError::BadRequest(format!(
+            "Impersonation duration must be between 1 and {IMPERSONATION_MAX_DURATION} minutes"
+        )));
+    }
+    let Some(user) = User::find_by_username(&appstate.pool, &username).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "User {username} not found"
+        )));
+    };
+    if user.id == session.user.id {
+        return Err(WebError::BadRequest(
+            "You can't impersonate yourself".into(),
+        ));
+    }
+    if user.is_admin(&appstate.pool).await? {
+        return Err(WebError::Forbidden("Admins can't be impersonated".into()));
+    }
+    if user.is_service_account(&appstate.pool).await? {
+        return Err(WebError::BadRequest(
+            "Service accounts can't be impersonated".into(),
+        ));
+    }
+    if !user.is_active {
+        return Err(WebError::BadRequest(format!("User {username} is disabled")));
+    }
+
+    let mut user_session = Session::new(
+        user.id,
+        SessionState::MultiFactorVerified,
+        context.ip.to_string(),
+        Some(context.device.clone()),
+    );
+    let impersonation = Impersonation::new(
+        user_session.id.clone(),
+        session.user.id,
+        session.session.id.clone(),
+        user.id,
+        data.mode,
+        TimeDelta::minutes(duration.into()),
+    );
+    user_session.expires = impersonation.expires;
+    user_session.save(&appstate.pool).await?;
+    impersonation.save(&appstate.pool).await?;
+
+    let config = server_config();
+    let cookie_domain = config
+        .cookie_domain
+        .as_ref()
+        .expect("Cookie domain not found");
+    let cookie = Cookie::build((IMPERSONATION_COOKIE_NAME, user_session.id))
+        .domain(cookie_domain)
+        .path("/")
+        .http_only(true)
+        .secure(!config.cookie_insecure)
+        .same_site(SameSite::Lax)
+        .max_age(Duration::minutes(duration.into()));
+    let private_cookies = private_cookies.add(cookie);
+    info!(
+        "Admin {} started {} impersonation of user {username} until {}",
+        session.user.username, data.mode, impersonation.expires
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::ImpersonationStarted {
+            admin: session.user.clone(),
+            user,
+            mode: data.mode,
+            expires: impersonation.expires,
+        }),
+    })?;
+
+    Ok((
+        private_cookies,
+        ApiResponse {
+            json: json!(ImpersonationInfo {
+                admin: session.user.username,
+                mode: impersonation.mode,
+                expires: impersonation.expires,
+            }),
+            status: StatusCode::CREATED,
+        },
+    ))
+}
+
+/// Stop impersonating a user and go back to the admin session.
+///
+/// Doesn't use the session extractor, so that read-only and expired impersonations can be stopped.
+pub(crate) async fn stop_impersonation(
+    cookies: CookieJar,
+    private_cookies: PrivateCookieJar,
+    user_agent: TypedHeader<UserAgent>,
+    SecureClientIp(ip): SecureClientIp,
+    State(appstate): State<AppState>,
+) -> Result<(PrivateCookieJar, ApiResponse), WebError> {
+    let Some(cookie) = private_cookies.get(IMPERSONATION_COOKIE_NAME) else {
+        return Err(WebError::BadRequest("No user is being impersonated".into()));
+    };
+    let impersonation = Impersonation::find_by_session_id(&appstate.pool, cookie.value()).await?;
+    // only the admin session the impersonation was started from can stop it,
+    // the cookie is removed in any case
+    if let Some(impersonation) = impersonation.filter(|impersonation| {
+        cookies.get(SESSION_COOKIE_NAME).map(Cookie::value)
+            == Some(impersonation.admin_session_id.as_str())
+    }) {
+        let expired = impersonation.expired();
+        end_impersonation(
+            &appstate,
+            impersonation,
+            ip,
+            &user_agent.to_string(),
+            expired,
+        )
+        .await?;
+    }
+
+    Ok((
+        private_cookies.remove(Cookie::from(IMPERSONATION_COOKIE_NAME)),
+        ApiResponse::default(),
+    ))
+}
+
+/// Removes the impersonated user session and records the end of impersonation in the activity
+/// log, on behalf of the admin.
+pub(crate) async fn end_impersonation(
+    appstate: &AppState,
+    impersonation: Impersonation,
+    ip: IpAddr,
+    user_agent: &str,
+    expired: bool,
+) -> Result<(), WebError> {
+    if let Some(session) =
+        Session::find_impersonated(&appstate.pool, &impersonation.session_id).await?
+    {
+        session.delete(&appstate.pool).await?;
+    }
+    let admin = User::find_by_id(&appstate.pool, impersonation.admin_id).await?;
+    let user = User::find_by_id(&appstate.pool, impersonation.user_id).await?;
+    let (Some(admin), Some(user)) = (admin, user) else {
+        // impersonations are removed together with the users
+        return Ok(());
+    };
+    if expired {
+        info!("Impersonation of user {user} by admin {admin} expired");
+    } else {
+        info!("Admin {admin} stopped impersonating user {user}");
+    }
+    appstate.emit_event(ApiEvent {
+        context: ApiRequestContext::new(
+            admin.id,
+            admin.username.clone(),
+            ip,
+            user_agent.to_string(),
+        ),
+        event: Box::new(ApiEventType::ImpersonationEnded {
+            admin,
+            user,
+            mode: impersonation.mode,
+            expired,
+        }),
+    })
+}
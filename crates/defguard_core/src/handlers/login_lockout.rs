@@ -0,0 +1,132 @@
+use std::net::IpAddr;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::NaiveDateTime;
+use defguard_common::db::Id;
+use serde_json::json;
+
+use super::{ApiResponse, ApiResult};
+use crate::{
+    appstate::AppState,
+    auth::{AdminRole, SessionInfo, login_lockout},
+    db::{User, models::login_lockout::LoginLockout},
+    error::WebError,
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
+};
+
+/// User locked out after consecutive failed logins.
+#[derive(Debug, Serialize)]
+pub struct LockedUser {
+    pub user_id: Id,
+    pub username: String,
+    pub failed_attempts: u32,
+    pub locked_until: NaiveDateTime,
+}
+
+/// Client address locked out after consecutive failed logins.
+#[derive(Debug, Serialize)]
+pub struct LockedIp {
+    pub ip_address: IpAddr,
+    pub failed_attempts: u32,
+    pub locked_until: NaiveDateTime,
+}
+
+/// List users and client addresses currently locked out of the web UI.
+pub(crate) async fn list_login_lockouts(
+    _role: AdminRole,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    debug!("Listing login lockouts");
+    let mut users = Vec::new();
+    let mut ips = Vec::new();
+    // ordered by the end of the lockout
+    for lockout in LoginLockout::all_active(&appstate.pool).await? {
+        let (Some(locked_until), Ok(failed_attempts)) =
+            (lockout.locked_until, u32::try_from(lockout.failed_attempts))
+        else {
+            continue;
+        };
+        if let Some(user_id) = lockout.user_id {
+            // skip users removed in the meantime
+            if let Some(user) = User::find_by_id(&appstate.pool, user_id).await? {
+                users.push(LockedUser {
+                    user_id: user.id,
+                    username: user.username,
+                    failed_attempts,
+                    locked_until,
+                });
+            }
+        } else if let Some(ip_address) = lockout.ip_address.and_then(|ip| ip.parse::<IpAddr>().ok())
+        {
+            ips.push(LockedIp {
+                ip_address,
+                failed_attempts,
+                locked_until,
+            });
+        }
+    }
+
+    Ok(ApiResponse {
+        json: json!({ "users": users, "ips": ips }),
+        status: StatusCode::OK,
+    })
+}
+
+/// Lift the lockout of the user before it expires.
+pub(crate) async fn unlock_user(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(username): Path<String>,
+) -> ApiResult {
+    debug!("Unlocking user {username}");
+    let Some(user) = User::find_by_username(&appstate.pool, &username).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "user {username} not found"
+        )));
+    };
+    if !login_lockout::unlock_user(&appstate, user.id).await? {
+        return Err(WebError::ObjectNotFound(format!(
+            "user {username} is not locked out"
+        )));
+    }
+    info!("User {} unlocked user {username}", session.user.username);
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::UserUnlocked { user }),
+    })?;
+
+    Ok(ApiResponse::default())
+}
+
+/// Lift the lockout of the client address before it expires.
+pub(crate) async fn unlock_ip(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(ip): Path<String>,
+) -> ApiResult {
+    debug!("Unlocking address {ip}");
+    let Ok(ip) = ip.parse::<IpAddr>() else {
+        return Err(WebError::BadRequest(format!("invalid IP address {ip}")));
+    };
+    if !login_lockout::unlock_ip(&appstate, ip).await? {
+        return Err(WebError::ObjectNotFound(format!(
+            "address {ip} is not locked out"
+        )));
+    }
+    info!("User {} unlocked address {ip}", session.user.username);
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::LoginIpUnlocked {
+            ip_address: ip.to_canonical().to_string(),
+        }),
+    })?;
+
+    Ok(ApiResponse::default())
+}
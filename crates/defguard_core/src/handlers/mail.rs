@@ -1,11 +1,14 @@
 use std::fmt::Display;
 
 use axum::{
-    extract::{Json, State},
+    extract::{Json, Path, Query, State},
     http::StatusCode,
 };
 use chrono::{NaiveDateTime, Utc};
-use defguard_common::db::{Id, models::MFAMethod};
+use defguard_common::db::{
+    Id,
+    models::{MFAMethod, MailDelivery, MailDeliveryStatus, MailTemplateKind, MailTemplateOverride},
+};
 use defguard_mail::{
     Attachment, Mail,
     templates::{self, SessionContext, TemplateError, TemplateLocation, support_data_mail},
@@ -13,6 +16,7 @@ use defguard_mail::{
 use lettre::message::header::ContentType;
 use reqwest::Url;
 use serde_json::json;
+use sqlx::PgExecutor;
 use tokio::{
     fs::read_to_string,
     sync::mpsc::{UnboundedSender, unbounded_channel},
@@ -22,9 +26,16 @@ use super::{ApiResponse, ApiResult};
 use crate::{
     PgPool,
     appstate::AppState,
-    auth::{AdminRole, SessionInfo},
-    db::{User, models::enrollment::TokenError},
+    auth::{AdminRole, SessionInfo, email_mfa::register_email_mfa_code_sent},
+    db::{
+        Group, User,
+        models::{
+            enrollment::TokenError,
+            notification_digest::{DigestEntry, NotificationSettings},
+        },
+    },
     error::WebError,
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
     server_config,
     support::dump_config,
 };
@@ -38,12 +49,21 @@ static NEW_DEVICE_LOGIN_EMAIL_SUBJECT: &str = "Defguard: new device logged in to
 
 static EMAIL_MFA_ACTIVATION_EMAIL_SUBJECT: &str = "Your Multi-Factor Authentication Activation";
 static EMAIL_MFA_CODE_EMAIL_SUBJECT: &str = "Your Multi-Factor Authentication Code for Login";
+static CLIENT_MFA_LOCKED_OUT_EMAIL_SUBJECT: &str = "Defguard: VPN client MFA login blocked";
+static DEVICE_EXPIRED_EMAIL_SUBJECT: &str = "Defguard: device access expired";
+static DEVICE_APPROVED_EMAIL_SUBJECT: &str = "Defguard: device approved";
+static DEVICE_REJECTED_EMAIL_SUBJECT: &str = "Defguard: device rejected";
 
 static GATEWAY_DISCONNECTED: &str = "Defguard: Gateway disconnected";
 static GATEWAY_RECONNECTED: &str = "Defguard: Gateway reconnected";
 
 pub static EMAIL_PASSWORD_RESET_START_SUBJECT: &str = "Defguard: Password reset";
 pub static EMAIL_PASSWORD_RESET_SUCCESS_SUBJECT: &str = "Defguard: Password reset success";
+static EMAIL_VERIFICATION_SUBJECT: &str = "Defguard: Verify your new email address";
+static GROUP_JOIN_REQUEST_SUBJECT: &str = "Defguard: request to join a group";
+
+// maximum number of delivery records returned at once
+const MAIL_DELIVERY_LIST_LIMIT: i64 = 500;
 
 #[derive(Clone, Deserialize)]
 pub struct TestMail {
@@ -77,6 +97,7 @@ pub async fn test_mail(
         to: data.to.clone(),
         subject: TEST_MAIL_SUBJECT.to_string(),
         content: templates::test_mail(Some(&session.session.into()))?,
+        plain_content: None,
         attachments: Vec::new(),
         result_tx: Some(tx),
     };
@@ -146,6 +167,7 @@ pub async fn send_support_data(
         to: SUPPORT_EMAIL_ADDRESS.to_string(),
         subject: SUPPORT_EMAIL_SUBJECT.to_string(),
         content: support_data_mail()?,
+        plain_content: None,
         attachments: vec![config, logs],
         result_tx: Some(tx),
     };
@@ -173,6 +195,179 @@ pub async fn send_support_data(
     }
 }
 
+#[derive(Deserialize)]
+pub struct MailTemplateData {
+    pub subject: Option<String>,
+    pub body: Option<String>,
+}
+
+#[derive(Serialize)]
+struct MailTemplateInfo {
+    kind: MailTemplateKind,
+    variables: &'static [&'static str],
+    subject: Option<String>,
+    body: Option<String>,
+}
+
+/// Lists built-in templates which can be customized, along with current overrides.
+pub async fn list_mail_templates(_admin: AdminRole, State(appstate): State<AppState>) -> ApiResult {
+    debug!("Listing mail template overrides");
+    let overrides = MailTemplateOverride::all(&appstate.pool).await?;
+    let templates: Vec<MailTemplateInfo> = MailTemplateKind::ALL
+        .into_iter()
+        .map(|kind| {
+            let custom = overrides.iter().find(|custom| custom.kind == kind);
+            MailTemplateInfo {
+                kind,
+                variables: kind.variables(),
+                subject: custom.and_then(|custom| custom.subject.clone()),
+                body: custom.and_then(|custom| custom.body.clone()),
+            }
+        })
+        .collect();
+
+    Ok(ApiResponse {
+        json: json!(templates),
+        status: StatusCode::OK,
+    })
+}
+
+pub async fn set_mail_template(
+    _admin: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(kind): Path<MailTemplateKind>,
+    Json(data): Json<MailTemplateData>,
+) -> ApiResult {
+    debug!(
+        "User {} setting {kind} mail template override",
+        session.user.username
+    );
+    for template in [&data.subject, &data.body].into_iter().flatten() {
+        if let Err(err) = templates::validate_custom_template(template) {
+            return Err(WebError::BadRequest(format!(
+                "Invalid {kind} mail template: {err}"
+            )));
+        }
+    }
+
+    match MailTemplateOverride::find_by_kind(&appstate.pool, kind).await? {
+        Some(mut custom) => {
+            custom.subject = data.subject;
+            custom.body = data.body;
+            custom.save(&appstate.pool).await?;
+        }
+        None => {
+            MailTemplateOverride::new(kind, data.subject, data.body)
+                .save(&appstate.pool)
+                .await?;
+        }
+    }
+
+    info!(
+        "User {} set {kind} mail template override",
+        session.user.username
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::MailTemplateModified { kind }),
+    })?;
+
+    Ok(ApiResponse::default())
+}
+
+/// Removes mail template override, so that the built-in template is used again.
+pub async fn reset_mail_template(
+    _admin: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(kind): Path<MailTemplateKind>,
+) -> ApiResult {
+    debug!(
+        "User {} resetting {kind} mail template",
+        session.user.username
+    );
+    let Some(custom) = MailTemplateOverride::find_by_kind(&appstate.pool, kind).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "{kind} mail template is not customized"
+        )));
+    };
+    custom.delete(&appstate.pool).await?;
+
+    info!("User {} reset {kind} mail template", session.user.username);
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::MailTemplateReset { kind }),
+    })?;
+
+    Ok(ApiResponse::default())
+}
+
+#[derive(Deserialize)]
+pub struct MailDeliveryQuery {
+    pub status: Option<MailDeliveryStatus>,
+}
+
+/// Lists most recent outgoing emails, optionally filtered by delivery status.
+pub async fn list_mail_deliveries(
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+    Query(query): Query<MailDeliveryQuery>,
+) -> ApiResult {
+    debug!("Listing mail deliveries");
+    let deliveries =
+        MailDelivery::list(&appstate.pool, query.status, MAIL_DELIVERY_LIST_LIMIT).await?;
+
+    Ok(ApiResponse {
+        json: json!(deliveries),
+        status: StatusCode::OK,
+    })
+}
+
+/// Queues a failed email for delivery again. The new attempt gets its own delivery record.
+pub async fn resend_mail(
+    _admin: AdminRole,
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    Path(id): Path<Id>,
+) -> ApiResult {
+    debug!("User {} resending mail {id}", session.user.username);
+    let Some(mut delivery) = MailDelivery::find_by_id(&appstate.pool, id).await? else {
+        return Err(WebError::ObjectNotFound(format!("Mail {id} not found")));
+    };
+    if !delivery.can_resend() {
+        return Err(WebError::BadRequest(format!(
+            "Mail {id} can't be resent, only failed mails without attachments can"
+        )));
+    }
+
+    let mail = Mail {
+        to: delivery.recipient.clone(),
+        subject: delivery.subject.clone(),
+        content: delivery.content.clone().unwrap_or_default(),
+        plain_content: delivery.plain_content.clone(),
+        attachments: Vec::new(),
+        result_tx: None,
+    };
+    appstate.mail_tx.send(mail).map_err(|err| {
+        error!("Failed to queue mail {id} for resending: {err}");
+        WebError::Serialization(format!("Failed to queue mail: {err}"))
+    })?;
+    delivery.resent_at = Some(Utc::now().naive_utc());
+    delivery.save(&appstate.pool).await?;
+    info!(
+        "User {} resent mail {id} to {}",
+        session.user.username, delivery.recipient
+    );
+
+    Ok(ApiResponse {
+        json: json!(delivery),
+        status: StatusCode::OK,
+    })
+}
+
 pub fn send_new_device_added_email(
     device_name: &str,
     public_key: &str,
@@ -194,6 +389,7 @@ pub fn send_new_device_added_email(
             ip_address,
             device_info,
         )?,
+        plain_content: None,
         attachments: Vec::new(),
         result_tx: None,
     };
@@ -231,6 +427,7 @@ pub async fn send_gateway_disconnected_email(
                 gateway_adress,
                 &network_name,
             )?,
+            plain_content: None,
             attachments: Vec::new(),
             result_tx: None,
         };
@@ -269,6 +466,7 @@ pub async fn send_gateway_reconnected_email(
                 gateway_adress,
                 &network_name,
             )?,
+            plain_content: None,
             attachments: Vec::new(),
             result_tx: None,
         };
@@ -288,18 +486,133 @@ pub async fn send_gateway_reconnected_email(
     Ok(())
 }
 
+/// Notifies owners of the group, or all admins if it has none, about a new join request.
+pub(crate) async fn send_group_join_request_email(
+    pool: &PgPool,
+    mail_tx: &UnboundedSender<Mail>,
+    group: &Group<Id>,
+    user: &User<Id>,
+    comment: Option<&str>,
+) -> Result<(), WebError> {
+    let mut recipients = group.owners(pool).await?;
+    if recipients.is_empty() {
+        recipients = User::find_admins(pool).await?;
+    }
+    debug!(
+        "Sending request of user {} to join group {} to {} recipients",
+        user.username,
+        group.name,
+        recipients.len()
+    );
+    let content = templates::group_join_request_mail(
+        &user.clone().into(),
+        &user.username,
+        &group.name,
+        comment,
+    )?;
+    for recipient in recipients {
+        let mail = Mail {
+            to: recipient.email,
+            subject: GROUP_JOIN_REQUEST_SUBJECT.to_string(),
+            content: content.clone(),
+            plain_content: None,
+            attachments: Vec::new(),
+            result_tx: None,
+        };
+        let to = mail.to.clone();
+
+        match mail_tx.send(mail) {
+            Ok(()) => {
+                info!("Sent group join request notification to {to}");
+            }
+            Err(err) => {
+                error!("Sending group join request notification to {to} failed with error:\n{err}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fetches override of given built-in template configured by admins.
+/// Lookup errors are only logged, so that the built-in template is used instead.
+pub(crate) async fn mail_template_override<'e, E>(
+    executor: E,
+    kind: MailTemplateKind,
+) -> Option<MailTemplateOverride<Id>>
+where
+    E: PgExecutor<'e>,
+{
+    MailTemplateOverride::find_by_kind(executor, kind)
+        .await
+        .inspect_err(|err| error!("Failed to fetch {kind} mail template override: {err}"))
+        .ok()
+        .flatten()
+}
+
+/// Holds back a non-critical notification for the daily digest if the user opted for it.
+/// Returns `false` if the notification should be sent right away.
+async fn hold_for_digest(pool: &PgPool, user: &User<Id>, entry: DigestEntry) -> bool {
+    match NotificationSettings::find_by_user(pool, user.id).await {
+        Ok(settings) if settings.email_digest => match entry.save(pool).await {
+            Ok(_) => {
+                debug!(
+                    "Notification for user {} held back for digest",
+                    user.username
+                );
+                true
+            }
+            Err(err) => {
+                error!(
+                    "Failed to queue notification for user {} digest, sending it right away: {err}",
+                    user.username
+                );
+                false
+            }
+        },
+        Ok(_) => false,
+        Err(err) => {
+            error!(
+                "Failed to fetch notification settings of user {}: {err}",
+                user.username
+            );
+            false
+        }
+    }
+}
+
 pub async fn send_new_device_login_email(
-    user_email: &str,
+    pool: &PgPool,
+    user: &User<Id>,
     mail_tx: &UnboundedSender<Mail>,
     session: &SessionContext,
     created: NaiveDateTime,
 ) -> Result<(), TemplateError> {
+    let user_email = &user.email;
     debug!("User {user_email} new device login mail to {SUPPORT_EMAIL_ADDRESS}");
 
+    let custom = mail_template_override(pool, MailTemplateKind::NewDeviceLogin).await;
+    let rendered = templates::new_device_login_mail(
+        session,
+        created,
+        NEW_DEVICE_LOGIN_EMAIL_SUBJECT,
+        custom.as_ref(),
+    )?;
+    let device = session.device_info.as_deref().unwrap_or("Unknown device");
+    let entry = DigestEntry::new(
+        user.id,
+        rendered.subject.clone(),
+        format!("{device} from IP address {}", session.ip_address),
+        created,
+    );
+    if hold_for_digest(pool, user, entry).await {
+        return Ok(());
+    }
+
     let mail = Mail {
         to: user_email.to_string(),
-        subject: NEW_DEVICE_LOGIN_EMAIL_SUBJECT.to_string(),
-        content: templates::new_device_login_mail(session, created)?,
+        subject: rendered.subject,
+        content: rendered.content,
+        plain_content: None,
         attachments: Vec::new(),
         result_tx: None,
     };
@@ -332,6 +645,7 @@ pub async fn send_new_device_ocid_login_email(
         to: user_email.to_string(),
         subject,
         content: templates::new_device_ocid_login_mail(session, &oauth2client_name)?,
+        plain_content: None,
         attachments: Vec::new(),
         result_tx: None,
     };
@@ -364,6 +678,7 @@ pub fn send_mfa_configured_email(
         to: user.email.clone(),
         subject,
         content: templates::mfa_configured_mail(session, mfa_method)?,
+        plain_content: None,
         attachments: Vec::new(),
         result_tx: None,
     };
@@ -399,6 +714,7 @@ pub fn send_email_mfa_activation_email(
         to: user.email.clone(),
         subject: EMAIL_MFA_ACTIVATION_EMAIL_SUBJECT.into(),
         content: templates::email_mfa_activation_mail(&user.clone().into(), &code, session)?,
+        plain_content: None,
         attachments: Vec::new(),
         result_tx: None,
     };
@@ -417,7 +733,8 @@ pub fn send_email_mfa_activation_email(
     }
 }
 
-pub fn send_email_mfa_code_email(
+pub async fn send_email_mfa_code_email(
+    pool: &PgPool,
     user: &User<Id>,
     mail_tx: &UnboundedSender<Mail>,
     session: Option<&SessionContext>,
@@ -430,10 +747,19 @@ pub fn send_email_mfa_code_email(
         TemplateError::MfaError
     })?;
 
+    let custom = mail_template_override(pool, MailTemplateKind::EmailMfaCode).await;
+    let rendered = templates::email_mfa_code_mail(
+        &user.clone().into(),
+        &code,
+        session,
+        EMAIL_MFA_CODE_EMAIL_SUBJECT,
+        custom.as_ref(),
+    )?;
     let mail = Mail {
         to: user.email.clone(),
-        subject: EMAIL_MFA_CODE_EMAIL_SUBJECT.into(),
-        content: templates::email_mfa_code_mail(&user.clone().into(), &code, session)?,
+        subject: rendered.subject,
+        content: rendered.content,
+        plain_content: None,
         attachments: Vec::new(),
         result_tx: None,
     };
@@ -442,6 +768,7 @@ pub fn send_email_mfa_code_email(
 
     match mail_tx.send(mail) {
         Ok(()) => {
+            register_email_mfa_code_sent(user.id);
             info!("Email MFA code mail sent to {to}");
             Ok(())
         }
@@ -452,6 +779,114 @@ pub fn send_email_mfa_code_email(
     }
 }
 
+pub fn send_client_mfa_locked_out_email(
+    user: &User<Id>,
+    device_name: &str,
+    location_name: &str,
+    locked_until: NaiveDateTime,
+    mail_tx: &UnboundedSender<Mail>,
+    session: Option<&SessionContext>,
+) -> Result<(), TemplateError> {
+    debug!("Sending client MFA lockout mail to {}", user.email);
+
+    let mail = Mail {
+        to: user.email.clone(),
+        subject: CLIENT_MFA_LOCKED_OUT_EMAIL_SUBJECT.into(),
+        content: templates::client_mfa_locked_out_mail(
+            session,
+            device_name,
+            location_name,
+            locked_until,
+        )?,
+        plain_content: None,
+        attachments: Vec::new(),
+        result_tx: None,
+    };
+
+    let to = mail.to.clone();
+
+    match mail_tx.send(mail) {
+        Ok(()) => {
+            info!("Client MFA lockout mail sent to {to}");
+            Ok(())
+        }
+        Err(err) => {
+            error!("Failed to send client MFA lockout mail to {to} with error:\n{err}");
+            Ok(())
+        }
+    }
+}
+
+pub fn send_device_expired_email(
+    user: &User<Id>,
+    device_name: &str,
+    expired_at: NaiveDateTime,
+    mail_tx: &UnboundedSender<Mail>,
+) -> Result<(), TemplateError> {
+    debug!("Sending device expiry mail to {}", user.email);
+
+    let mail = Mail {
+        to: user.email.clone(),
+        subject: DEVICE_EXPIRED_EMAIL_SUBJECT.into(),
+        content: templates::device_expired_mail(device_name, expired_at)?,
+        plain_content: None,
+        attachments: Vec::new(),
+        result_tx: None,
+    };
+
+    let to = mail.to.clone();
+
+    match mail_tx.send(mail) {
+        Ok(()) => {
+            info!("Device expiry mail sent to {to}");
+            Ok(())
+        }
+        Err(err) => {
+            error!("Failed to send device expiry mail to {to} with error:\n{err}");
+            Ok(())
+        }
+    }
+}
+
+/// Notifies the owner about an admin decision on a device added to a location
+/// which requires device approval.
+pub fn send_device_approval_email(
+    user: &User<Id>,
+    device_name: &str,
+    location_name: &str,
+    approved: bool,
+    mail_tx: &UnboundedSender<Mail>,
+) -> Result<(), TemplateError> {
+    debug!("Sending device approval mail to {}", user.email);
+
+    let subject = if approved {
+        DEVICE_APPROVED_EMAIL_SUBJECT
+    } else {
+        DEVICE_REJECTED_EMAIL_SUBJECT
+    };
+    let mail = Mail {
+        to: user.email.clone(),
+        subject: subject.into(),
+        content: templates::device_approval_mail(device_name, location_name, approved)?,
+        plain_content: None,
+        attachments: Vec::new(),
+        result_tx: None,
+    };
+
+    let to = mail.to.clone();
+
+    match mail_tx.send(mail) {
+        Ok(()) => {
+            info!("Device approval mail sent to {to}");
+            Ok(())
+        }
+        Err(err) => {
+            error!("Failed to send device approval mail to {to} with error:\n{err}");
+            Ok(())
+        }
+    }
+}
+
 pub fn send_password_reset_email(
     user: &User<Id>,
     mail_tx: &UnboundedSender<Mail>,
@@ -466,6 +901,7 @@ pub fn send_password_reset_email(
         to: user.email.clone(),
         subject: EMAIL_PASSWORD_RESET_START_SUBJECT.into(),
         content: templates::email_password_reset_mail(service_url, token, ip_address, device_info)?,
+        plain_content: None,
         attachments: Vec::new(),
         result_tx: None,
     };
@@ -496,6 +932,7 @@ pub fn send_password_reset_success_email(
         to: user.email.clone(),
         subject: EMAIL_PASSWORD_RESET_SUCCESS_SUBJECT.into(),
         content: templates::email_password_reset_success_mail(ip_address, device_info)?,
+        plain_content: None,
         attachments: Vec::new(),
         result_tx: None,
     };
@@ -512,3 +949,34 @@ pub fn send_password_reset_success_email(
     }
     Ok(())
 }
+
+/// Sends verification link to a new email address requested by the user.
+pub fn send_email_verification_email(
+    email: &str,
+    mail_tx: &UnboundedSender<Mail>,
+    token: &str,
+    ip_address: Option<&str>,
+    device_info: Option<&str>,
+) -> Result<(), TokenError> {
+    debug!("Sending email verification email to {email}");
+
+    let mail = Mail {
+        to: email.to_string(),
+        subject: EMAIL_VERIFICATION_SUBJECT.into(),
+        content: templates::email_verification_mail(email, token, ip_address, device_info)?,
+        plain_content: None,
+        attachments: Vec::new(),
+        result_tx: None,
+    };
+
+    match mail_tx.send(mail) {
+        Ok(()) => {
+            info!("Email verification email sent to {email}");
+            Ok(())
+        }
+        Err(err) => {
+            error!("Failed to send email verification email to {email} with error:\n{err}");
+            Err(TokenError::NotificationError(err.to_string()))
+        }
+    }
+}
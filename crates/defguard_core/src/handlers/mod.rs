@@ -4,19 +4,23 @@ use axum::{
     http::{StatusCode, request::Parts},
     response::{IntoResponse, Response},
 };
-use axum_client_ip::InsecureClientIp;
+use axum_client_ip::SecureClientIp;
 use axum_extra::{TypedHeader, headers::UserAgent};
-use defguard_common::db::{Id, NoId};
+use chrono::NaiveDateTime;
+use defguard_common::db::{Id, NoId, models::settings::TotpAlgorithm};
 use serde_json::{Value, json};
-use sqlx::PgPool;
+use sqlx::{FromRow, PgPool};
 use utoipa::ToSchema;
 use webauthn_rs::prelude::RegisterPublicKeyCredential;
 
 use crate::{
     appstate::AppState,
     auth::SessionInfo,
-    db::{Device, User, UserInfo, WebHook},
-    enterprise::{db::models::acl::AclError, license::LicenseError},
+    db::{Device, User, UserInfo, WebHook, models::impersonation::Impersonation},
+    enterprise::{
+        activity_log_stream::error::ActivityLogStreamError, db::models::acl::AclError,
+        license::LicenseError,
+    },
     error::WebError,
     events::ApiRequestContext,
 };
@@ -24,8 +28,14 @@ use crate::{
 pub(crate) mod activity_log;
 pub(crate) mod app_info;
 pub(crate) mod auth;
+pub(crate) mod client_mfa;
+pub(crate) mod device_approval;
+pub(crate) mod device_profile;
+pub(crate) mod enrollment_tokens;
 pub(crate) mod forward_auth;
 pub(crate) mod group;
+pub(crate) mod impersonation;
+pub(crate) mod login_lockout;
 pub(crate) mod mail;
 pub mod network_devices;
 pub(crate) mod openid_clients;
@@ -35,7 +45,10 @@ pub(crate) mod settings;
 pub(crate) mod ssh_authorized_keys;
 pub(crate) mod support;
 pub(crate) mod updates;
+pub(crate) mod upload;
 pub(crate) mod user;
+pub(crate) mod vpn_sessions;
+pub(crate) mod web_sessions;
 pub(crate) mod webhooks;
 pub mod wireguard;
 pub mod worker;
@@ -76,6 +89,9 @@ impl From<WebError> for ApiResponse {
                 ApiResponse::new(json!({ "msg": msg }), StatusCode::UNAUTHORIZED)
             }
             WebError::Authentication => ApiResponse::new(json!({}), StatusCode::UNAUTHORIZED),
+            WebError::AccountCreationRefused(err) => {
+                ApiResponse::new(json!({ "msg": err.to_string() }), StatusCode::UNAUTHORIZED)
+            }
             WebError::Forbidden(msg) => {
                 error!(msg);
                 ApiResponse::new(json!({ "msg": msg }), StatusCode::FORBIDDEN)
@@ -88,16 +104,33 @@ impl From<WebError> for ApiResponse {
             | WebError::ModelError(_)
             | WebError::ServerConfigMissing
             | WebError::EmailMfa(_)
+            | WebError::SmsMfa(_)
             | WebError::ClientIpError
             | WebError::FirewallError(_)
-            | WebError::ApiEventChannelError(_)
-            | WebError::ActivityLogStreamError(_) => {
+            | WebError::ApiEventChannelError(_) => {
                 error!("{web_error}");
                 ApiResponse::new(
                     json!({"msg": "Internal server error"}),
                     StatusCode::INTERNAL_SERVER_ERROR,
                 )
             }
+            WebError::ActivityLogStreamError(err) => match err {
+                ActivityLogStreamError::ConfigDeserializeError(..)
+                | ActivityLogStreamError::InvalidTlsConfig(_)
+                | ActivityLogStreamError::InvalidSyslogConfig(_)
+                | ActivityLogStreamError::InvalidKafkaConfig(_) => {
+                    warn!("{err}");
+                    ApiResponse::new(json!({ "msg": err.to_string() }), StatusCode::BAD_REQUEST)
+                }
+                ActivityLogStreamError::SqlxError(_)
+                | ActivityLogStreamError::HeaderValueParsing() => {
+                    error!("{err}");
+                    ApiResponse::new(
+                        json!({"msg": "Internal server error"}),
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                    )
+                }
+            },
             WebError::AclError(err) => match err {
                 AclError::ParseIntError(_)
                 | AclError::IpNetworkError(_)
@@ -165,6 +198,17 @@ impl From<WebError> for ApiResponse {
                 error!(msg);
                 ApiResponse::new(json!({ "msg": msg }), StatusCode::BAD_REQUEST)
             }
+            WebError::PasswordPolicy(err) => {
+                debug!("{err}");
+                ApiResponse::new(
+                    json!({
+                        "msg": err.to_string(),
+                        "policy": err.policy,
+                        "violations": err.violations,
+                    }),
+                    StatusCode::BAD_REQUEST,
+                )
+            }
             WebError::TemplateError(err) => {
                 error!("Template error: {err}");
                 ApiResponse::new(
@@ -240,17 +284,28 @@ impl Auth {
 #[derive(Deserialize, Serialize)]
 pub struct AuthTotp {
     pub secret: String,
+    // parameters authenticator apps need to generate matching codes
+    pub algorithm: TotpAlgorithm,
+    pub digits: i32,
 }
 
 impl AuthTotp {
     #[must_use]
-    pub fn new<S: Into<String>>(secret: S) -> Self {
+    pub fn new<S: Into<String>>(secret: S, algorithm: TotpAlgorithm, digits: i32) -> Self {
         Self {
             secret: secret.into(),
+            algorithm,
+            digits,
         }
     }
 }
 
+/// Phone number to verify for SMS MFA.
+#[derive(Deserialize, Serialize)]
+pub struct SmsMfaSetup {
+    pub phone: String,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct AuthCode {
     code: String,
@@ -263,13 +318,15 @@ impl AuthCode {
     }
 }
 
-#[derive(Deserialize, Serialize, ToSchema)]
+#[derive(Deserialize, FromRow, Serialize, ToSchema)]
 pub struct GroupInfo {
     pub id: Id,
     pub name: String,
     pub members: Vec<String>,
     pub vpn_locations: Vec<String>,
     pub is_admin: bool,
+    /// Name of the parent group, which this group inherits location access and permissions from.
+    pub parent: Option<String>,
 }
 
 impl GroupInfo {
@@ -280,6 +337,7 @@ impl GroupInfo {
         members: Vec<String>,
         vpn_locations: Vec<String>,
         is_admin: bool,
+        parent: Option<String>,
     ) -> Self {
         Self {
             id,
@@ -287,6 +345,7 @@ impl GroupInfo {
             members,
             vpn_locations,
             is_admin,
+            parent,
         }
     }
 }
@@ -323,6 +382,9 @@ pub struct AddUserData {
     pub email: String,
     pub phone: Option<String>,
     pub password: Option<String>,
+    /// Create a service account which can only authenticate with API tokens.
+    #[serde(default)]
+    pub service_account: bool,
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -331,6 +393,14 @@ pub struct StartEnrollmentRequest {
     pub send_enrollment_notification: bool,
     pub email: Option<String>,
     pub token_expiration_time: Option<String>,
+    /// Number of enrollment sessions which can be started with the token, 1 by default.
+    pub max_uses: Option<i32>,
+    /// Names of groups the user is added to when enrollment is completed.
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Identifiers of locations the user gets access to when enrollment is completed.
+    #[serde(default)]
+    pub locations: Vec<Id>,
 }
 
 #[derive(Deserialize, Serialize, ToSchema)]
@@ -339,6 +409,11 @@ pub struct PasswordChangeSelf {
     pub new_password: String,
 }
 
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct EmailVerification {
+    pub token: String,
+}
+
 #[derive(Deserialize, Serialize, ToSchema)]
 pub struct PasswordChange {
     pub new_password: String,
@@ -367,6 +442,13 @@ impl RecoveryCodes {
     }
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct RecoveryCodesInfo {
+    pub remaining: usize,
+    // most recent first
+    pub used_at: Vec<NaiveDateTime>,
+}
+
 #[derive(Deserialize)]
 pub struct WebHookData {
     pub url: String,
@@ -377,6 +459,17 @@ pub struct WebHookData {
     pub on_user_deleted: bool,
     pub on_user_modified: bool,
     pub on_hwkey_provision: bool,
+    #[serde(default)]
+    pub on_gateway_disconnected: bool,
+    #[serde(default)]
+    pub on_client_connected: bool,
+    #[serde(default)]
+    pub on_client_disconnected: bool,
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// Locations to receive client connection events from; all locations if empty.
+    #[serde(default)]
+    pub locations: Vec<Id>,
 }
 
 impl From<WebHookData> for WebHook {
@@ -391,6 +484,10 @@ impl From<WebHookData> for WebHook {
             on_user_deleted: data.on_user_deleted,
             on_user_modified: data.on_user_modified,
             on_hwkey_provision: data.on_hwkey_provision,
+            on_gateway_disconnected: data.on_gateway_disconnected,
+            on_client_connected: data.on_client_connected,
+            on_client_disconnected: data.on_client_disconnected,
+            secret: data.secret,
         }
     }
 }
@@ -404,13 +501,13 @@ pub struct AuthResponse {
 }
 
 /// Try to fetch [`User`] if the username is of the currently logged in user, or
-/// the logged in user is an admin.
+/// the session can manage other users.
 pub async fn user_for_admin_or_self(
     pool: &PgPool,
     session: &SessionInfo,
     username: &str,
 ) -> Result<User<Id>, WebError> {
-    if session.user.username == username || session.is_admin {
+    if session.user.username == username || session.can_manage_others() {
         debug!(
             "The user meets one or both of these conditions: \
             1) the user from the current session has admin privileges, \
@@ -434,13 +531,13 @@ pub async fn user_for_admin_or_self(
 }
 
 /// Try to fetch [`Device'] if the device.id is of the currently logged in user, or
-/// the logged in user is an admin.
+/// the session can manage other users.
 pub async fn device_for_admin_or_self<'e, E: sqlx::PgExecutor<'e>>(
     executor: E,
     session: &SessionInfo,
     id: Id,
 ) -> Result<Device<Id>, WebError> {
-    let fetch = if session.is_admin {
+    let fetch = if session.can_manage_others() {
         Device::find_by_id(executor, id).await
     } else {
         Device::find_by_id_and_username(executor, id, &session.user.username).await
@@ -465,7 +562,7 @@ where
         let TypedHeader(user_agent) = TypedHeader::<UserAgent>::from_request_parts(parts, state)
             .await
             .map_err(|_| WebError::BadRequest("Missing UserAgent header".to_string()))?;
-        let InsecureClientIp(insecure_ip) = InsecureClientIp::from_request_parts(parts, state)
+        let SecureClientIp(ip) = SecureClientIp::from_request_parts(parts, state)
             .await
             .map_err(|_| WebError::BadRequest("Missing client IP".to_string()))?;
         let session = if let Some(cached) = parts.extensions.get::<SessionInfo>() {
@@ -476,11 +573,20 @@ where
 
         // Store session info into request extensions so future extractors can use it
         parts.extensions.insert(session.clone());
-        Ok(ApiRequestContext::new(
+        let mut context = ApiRequestContext::new(
             session.user.id,
             session.user.username,
-            insecure_ip,
+            ip,
             user_agent.to_string(),
-        ))
+        );
+        // keep both identities in the activity log while an admin is impersonating the user
+        if let (Some(impersonation), Some(info)) = (
+            parts.extensions.get::<Impersonation>(),
+            session.impersonation,
+        ) {
+            context.impersonator_id = Some(impersonation.admin_id);
+            context.impersonator = Some(info.admin);
+        }
+        Ok(context)
     }
 }
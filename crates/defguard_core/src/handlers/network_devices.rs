@@ -1,10 +1,11 @@
 use std::{
+    collections::HashMap,
     net::{AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr},
     str::FromStr,
 };
 
 use axum::{
-    extract::{Json, Path, State},
+    extract::{Json, Multipart, Path, State},
     http::StatusCode,
 };
 use chrono::NaiveDateTime;
@@ -12,7 +13,7 @@ use defguard_common::{csv::AsCsv, db::Id};
 use defguard_mail::templates::TemplateLocation;
 use ipnetwork::IpNetwork;
 use serde_json::json;
-use sqlx::PgConnection;
+use sqlx::{Error as SqlxError, PgConnection};
 
 use super::{ApiResponse, ApiResult, WebError};
 use crate::{
@@ -21,13 +22,17 @@ use crate::{
     db::{
         Device, GatewayEvent, User, WireguardNetwork,
         models::{
-            device::{DeviceConfig, DeviceInfo, DeviceType, WireguardNetworkDevice},
+            device::{DeviceConfig, DeviceError, DeviceInfo, DeviceType, WireguardNetworkDevice},
+            device_profile::{DeviceProfile, apply_device_profile},
             wireguard::NetworkAddressError,
         },
     },
     enterprise::{db::models::enterprise_settings::EnterpriseSettings, limits::update_counts},
     events::{ApiEvent, ApiEventType, ApiRequestContext},
-    handlers::mail::send_new_device_added_email,
+    handlers::{
+        mail::send_new_device_added_email,
+        upload::{ImportRowError, UploadLimit, import_errors_response},
+    },
     server_config,
 };
 
@@ -45,6 +50,7 @@ struct NetworkDeviceInfo {
     description: Option<String>,
     added_by: String,
     added_date: NaiveDateTime,
+    expires_at: Option<NaiveDateTime>,
     location: NetworkDeviceLocation,
     wireguard_pubkey: String,
     configured: bool,
@@ -72,6 +78,7 @@ impl NetworkDeviceInfo {
                     device.name, network.name
                 )))?;
         let added_by = device.get_owner(&mut *transaction).await?;
+        let expires_at = device.expires_at(&mut *transaction).await?;
         let split_ips: Vec<SplitIp> = wireguard_device
             .wireguard_ips
             .iter()
@@ -95,6 +102,7 @@ impl NetworkDeviceInfo {
             description: device.description,
             added_by: added_by.username,
             added_date: device.created,
+            expires_at,
             wireguard_pubkey: device.wireguard_pubkey,
             location: NetworkDeviceLocation {
                 id: wireguard_device.wireguard_network_id,
@@ -126,7 +134,13 @@ pub async fn download_network_device_config(
         .ok_or(WebError::ObjectNotFound(format!(
             "No location found for network device: {}({})",
             device.name, device.id
-        )))?;
+        )))?
+        .apply_device_settings(&appstate.pool, &device)
+        .await?;
+    let profile = DeviceProfile::find_for_device(&appstate.pool, device_id).await?;
+    let Some(location) = apply_device_profile(profile.as_ref(), location.clone()) else {
+        return Err(DeviceError::LocationNotAllowed(location.name).into());
+    };
     let network_device = WireguardNetworkDevice::find(&appstate.pool, device_id, location.id)
         .await?
         .ok_or(WebError::ObjectNotFound(format!(
@@ -318,23 +332,12 @@ pub(crate) async fn check_ip_availability(
     })
 }
 
-pub(crate) async fn find_available_ips(
-    _admin_role: AdminRole,
-    Path(network_id): Path<i64>,
-    State(appstate): State<AppState>,
-) -> ApiResult {
-    let network = WireguardNetwork::find_by_id(&appstate.pool, network_id)
-        .await?
-        .ok_or_else(|| {
-            error!(
-                "Failed to find available IP for network with ID {}",
-                network_id
-            );
-            WebError::BadRequest("Failed to find available IP, network not found".to_string())
-        })?;
-
-    let mut transaction = appstate.pool.begin().await?;
-    let mut split_ips = Vec::new();
+/// Finds the first unassigned address in each of the location's network addresses.
+async fn first_available_ips(
+    conn: &mut PgConnection,
+    network: &WireguardNetwork<Id>,
+) -> Result<Vec<(IpAddr, IpNetwork)>, SqlxError> {
+    let mut ips = Vec::new();
     for network_address in &network.address {
         let net_ip = network_address.ip();
         let net_network = network_address.network();
@@ -345,16 +348,41 @@ pub(crate) async fn find_available_ips(
             }
 
             // Break the loop if IP is unassigned and return network device
-            if Device::find_by_ip(&mut *transaction, ip, network.id)
+            if Device::find_by_ip(&mut *conn, ip, network.id)
                 .await?
                 .is_none()
             {
-                split_ips.push(split_ip(&ip, network_address));
+                ips.push((ip, *network_address));
                 break;
             }
         }
     }
 
+    Ok(ips)
+}
+
+pub(crate) async fn find_available_ips(
+    _admin_role: AdminRole,
+    Path(network_id): Path<i64>,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    let network = WireguardNetwork::find_by_id(&appstate.pool, network_id)
+        .await?
+        .ok_or_else(|| {
+            error!(
+                "Failed to find available IP for network with ID {}",
+                network_id
+            );
+            WebError::BadRequest("Failed to find available IP, network not found".to_string())
+        })?;
+
+    let mut transaction = appstate.pool.begin().await?;
+    let split_ips: Vec<SplitIp> = first_available_ips(&mut transaction, &network)
+        .await?
+        .iter()
+        .map(|(ip, network_address)| split_ip(ip, network_address))
+        .collect();
+
     transaction.commit().await?;
     if split_ips.len() == network.address.len() {
         debug!(
@@ -778,6 +806,321 @@ pub async fn modify_network_device(
     })
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ImportNetworkDevice {
+    pub name: String,
+    pub wireguard_pubkey: String,
+    /// Addresses are assigned automatically if not provided.
+    #[serde(default)]
+    pub assigned_ips: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Username of the user the device is added by, defaults to the importing admin.
+    #[serde(default)]
+    pub owner: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportNetworkDevices {
+    pub location_id: Id,
+    pub devices: Vec<ImportNetworkDevice>,
+}
+
+struct ValidatedNetworkDevice {
+    row: usize,
+    data: ImportNetworkDevice,
+    owner: User<Id>,
+    ips: Vec<IpAddr>,
+}
+
+/// Reads devices from CSV with a header row. `name` and `wireguard_pubkey` columns are required,
+/// `assigned_ips` (comma-separated), `description` and `owner` are optional.
+fn parse_network_devices_csv(
+    records: Vec<Vec<String>>,
+) -> Result<Vec<ImportNetworkDevice>, WebError> {
+    let mut records = records.into_iter();
+    let header: Vec<String> = records
+        .next()
+        .ok_or_else(|| WebError::BadRequest("CSV file is empty".into()))?
+        .iter()
+        .map(|column| column.trim().to_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|column| column == name);
+    let (Some(name_column), Some(pubkey_column)) = (column("name"), column("wireguard_pubkey"))
+    else {
+        return Err(WebError::BadRequest(
+            "CSV header must contain name and wireguard_pubkey columns".into(),
+        ));
+    };
+    let ips_column = column("assigned_ips");
+    let description_column = column("description");
+    let owner_column = column("owner");
+
+    Ok(records
+        .map(|record| {
+            let value = |column: Option<usize>| {
+                column
+                    .and_then(|column| record.get(column))
+                    .map(|value| value.trim())
+                    .filter(|value| !value.is_empty())
+                    .map(ToString::to_string)
+            };
+            ImportNetworkDevice {
+                name: value(Some(name_column)).unwrap_or_default(),
+                wireguard_pubkey: value(Some(pubkey_column)).unwrap_or_default(),
+                assigned_ips: value(ips_column)
+                    .map(|ips| ips.split(',').map(|ip| ip.trim().to_string()).collect())
+                    .unwrap_or_default(),
+                description: value(description_column),
+                owner: value(owner_column),
+            }
+        })
+        .collect())
+}
+
+pub(crate) async fn import_network_devices(
+    _admin_role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Json(data): Json<ImportNetworkDevices>,
+) -> ApiResult {
+    import_network_devices_data(&appstate, session, context, data).await
+}
+
+pub(crate) async fn import_network_devices_upload(
+    _admin_role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    mut multipart: Multipart,
+) -> ApiResult {
+    debug!("Reading uploaded network devices file");
+    let mut limit = UploadLimit::new(server_config().http_import_body_limit);
+    let mut location_id = None;
+    let mut devices = None;
+    while let Some(field) = multipart.next_field().await? {
+        let field_name = field.name().map(ToString::to_string);
+        match field_name.as_deref() {
+            Some("location_id") => {
+                let value = limit.read_text(field).await?;
+                location_id =
+                    Some(value.trim().parse().map_err(|_| {
+                        WebError::BadRequest(format!("Invalid location ID: {value}"))
+                    })?);
+            }
+            Some("file") => {
+                devices = Some(parse_network_devices_csv(limit.read_csv(field).await?)?);
+            }
+            _ => debug!("Ignoring unexpected field {field_name:?} in network devices upload"),
+        }
+    }
+
+    let missing = |field: &str| WebError::BadRequest(format!("Missing field: {field}"));
+    let data = ImportNetworkDevices {
+        location_id: location_id.ok_or_else(|| missing("location_id"))?,
+        devices: devices.ok_or_else(|| missing("file"))?,
+    };
+
+    import_network_devices_data(&appstate, session, context, data).await
+}
+
+/// Validates all devices first and creates them in a single transaction, so either all devices
+/// are imported or none. Validation errors of all rows are returned at once.
+async fn import_network_devices_data(
+    appstate: &AppState,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    data: ImportNetworkDevices,
+) -> ApiResult {
+    debug!(
+        "User {} importing {} network devices to location with ID {}",
+        session.user.username,
+        data.devices.len(),
+        data.location_id
+    );
+    if data.devices.is_empty() {
+        return Err(WebError::BadRequest("No devices to import".into()));
+    }
+    let location = WireguardNetwork::find_by_id(&appstate.pool, data.location_id)
+        .await?
+        .ok_or_else(|| {
+            WebError::BadRequest(format!(
+                "Failed to import network devices, location with ID {} not found",
+                data.location_id
+            ))
+        })?;
+    let enterprise_settings = EnterpriseSettings::get(&appstate.pool).await?;
+
+    let mut transaction = appstate.pool.begin().await?;
+    let mut errors = Vec::new();
+    let mut validated = Vec::new();
+    let mut pubkeys: HashMap<String, usize> = HashMap::new();
+    let mut requested_ips: HashMap<IpAddr, usize> = HashMap::new();
+    let mut owners: HashMap<String, User<Id>> = HashMap::new();
+    for (index, device) in data.devices.into_iter().enumerate() {
+        let row = index + 1;
+        let mut fail = |message: String| errors.push(ImportRowError { row, message });
+
+        if device.name.trim().is_empty() {
+            fail("Device name is required".into());
+            continue;
+        }
+        if let Err(err) = Device::validate_pubkey(&device.wireguard_pubkey) {
+            fail(err);
+            continue;
+        }
+        if let Some(other_row) = pubkeys.insert(device.wireguard_pubkey.clone(), row) {
+            fail(format!("Public key is also used in row {other_row}"));
+            continue;
+        }
+        if Device::find_by_pubkey(&mut *transaction, &device.wireguard_pubkey)
+            .await?
+            .is_some()
+        {
+            fail("Device with identical public key already exists".into());
+            continue;
+        }
+
+        let ips = match device
+            .assigned_ips
+            .iter()
+            .map(|ip| IpAddr::from_str(ip.trim()))
+            .collect::<Result<Vec<IpAddr>, AddrParseError>>()
+        {
+            Ok(ips) => ips,
+            Err(err) => {
+                fail(format!("Invalid IP address: {err}"));
+                continue;
+            }
+        };
+        if let Some((ip, other_row)) = ips
+            .iter()
+            .find_map(|ip| requested_ips.get(ip).map(|other_row| (ip, other_row)))
+        {
+            fail(format!(
+                "IP address {ip} is also assigned in row {other_row}"
+            ));
+            continue;
+        }
+        if let Err(err) = location.can_assign_ips(&mut transaction, &ips, None).await {
+            fail(err.to_string());
+            continue;
+        }
+        requested_ips.extend(ips.iter().map(|ip| (*ip, row)));
+
+        let owner = match &device.owner {
+            None => session.user.clone(),
+            Some(username) => {
+                let user = match owners.get(username) {
+                    Some(user) => Some(user.clone()),
+                    None => User::find_by_username(&mut *transaction, username).await?,
+                };
+                let Some(user) = user else {
+                    fail(format!("User {username} not found"));
+                    continue;
+                };
+                owners.insert(username.clone(), user.clone());
+                user
+            }
+        };
+
+        validated.push(ValidatedNetworkDevice {
+            row,
+            data: device,
+            owner,
+            ips,
+        });
+    }
+    if !errors.is_empty() {
+        warn!(
+            "Failed to import network devices to location {location}, {} rows are invalid",
+            errors.len()
+        );
+        return Ok(import_errors_response(errors));
+    }
+
+    // Create devices with static addresses first, so that automatic assignment doesn't take them.
+    validated.sort_by_key(|device| device.ips.is_empty());
+    let mut created = Vec::with_capacity(validated.len());
+    for ValidatedNetworkDevice {
+        row,
+        data,
+        owner,
+        mut ips,
+    } in validated
+    {
+        if ips.is_empty() {
+            ips = first_available_ips(&mut transaction, &location)
+                .await?
+                .into_iter()
+                .map(|(ip, _)| ip)
+                .collect();
+            if ips.len() != location.address.len() {
+                return Ok(import_errors_response(vec![ImportRowError {
+                    row,
+                    message: format!("No free IP address left in location {location}"),
+                }]));
+            }
+        }
+        let device = Device::new(
+            data.name,
+            data.wireguard_pubkey,
+            owner.id,
+            DeviceType::Network,
+            data.description,
+            true,
+        )
+        .save(&mut *transaction)
+        .await?;
+        let (network_info, _config) = device
+            .add_to_network(&mut transaction, &location, &ips, &enterprise_settings)
+            .await?;
+        created.push((row, device, network_info));
+    }
+    created.sort_by_key(|(row, _, _)| *row);
+
+    update_counts(&mut *transaction).await?;
+    let firewall_config = location.try_get_firewall_config(&mut transaction).await?;
+    let mut devices = Vec::with_capacity(created.len());
+    for (_, device, _) in &created {
+        devices.push(NetworkDeviceInfo::from_device(device.clone(), &mut transaction).await?);
+    }
+
+    transaction.commit().await?;
+
+    info!(
+        "User {} imported {} network devices to location {location}",
+        session.user.username,
+        created.len()
+    );
+    for (_, device, network_info) in created {
+        appstate.send_wireguard_event(GatewayEvent::DeviceCreated(DeviceInfo {
+            device: device.clone(),
+            network_info: vec![network_info],
+        }));
+        appstate.emit_event(ApiEvent {
+            context: context.clone(),
+            event: Box::new(ApiEventType::NetworkDeviceAdded {
+                device,
+                location: location.clone(),
+            }),
+        })?;
+    }
+    // send firewall update event if ACLs & enterprise features are enabled
+    if let Some(firewall_config) = firewall_config {
+        appstate.send_wireguard_event(GatewayEvent::FirewallConfigChanged(
+            location.id,
+            firewall_config,
+        ));
+    }
+
+    Ok(ApiResponse {
+        json: json!({ "devices": devices }),
+        status: StatusCode::CREATED,
+    })
+}
+
 #[derive(Debug, Serialize)]
 struct SplitIp {
     network_part: String,
@@ -864,6 +1207,7 @@ fn split_ip(ip: &IpAddr, network: &IpNetwork) -> SplitIp {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::handlers::upload::parse_csv;
 
     #[test]
     fn test_ip_splitter() {
@@ -903,4 +1247,29 @@ mod test {
         assert_eq!(net.modifiable_part, "aaaa");
         assert_eq!(net.network_prefix, "125");
     }
+
+    #[test]
+    fn test_parse_network_devices_csv() {
+        let devices = parse_network_devices_csv(
+            parse_csv(
+                "Name,WireGuard_Pubkey,assigned_ips,owner\n\
+                sensor,key1,\"10.1.1.2, 10.1.1.3\",hpotter\n\
+                printer,key2,,\n",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].name, "sensor");
+        assert_eq!(devices[0].wireguard_pubkey, "key1");
+        assert_eq!(devices[0].assigned_ips, ["10.1.1.2", "10.1.1.3"]);
+        assert_eq!(devices[0].owner.as_deref(), Some("hpotter"));
+        assert!(devices[1].assigned_ips.is_empty());
+        assert_eq!(devices[1].description, None);
+        assert_eq!(devices[1].owner, None);
+
+        let records = parse_csv("name,description\nsensor,test\n").unwrap();
+        assert!(parse_network_devices_csv(records).is_err());
+        assert!(parse_network_devices_csv(Vec::new()).is_err());
+    }
 }
@@ -14,8 +14,11 @@ use axum::{
 };
 use axum_extra::extract::cookie::{Cookie, CookieJar, PrivateCookieJar, SameSite};
 use base64::{Engine, prelude::BASE64_STANDARD};
-use chrono::Utc;
-use defguard_common::db::{Id, NoId, models::AuthCode};
+use chrono::{TimeDelta, Utc};
+use defguard_common::{
+    db::{Id, NoId, models::AuthCode},
+    random::gen_alphanumeric,
+};
 use openidconnect::{
     AccessToken, AdditionalClaims, Audience, AuthUrl, AuthorizationCode,
     EmptyAdditionalProviderMetadata, EmptyExtraTokenFields, EndUserEmail, EndUserFamilyName,
@@ -36,7 +39,7 @@ use serde::{
     ser::{Serialize, Serializer},
 };
 use serde_json::json;
-use sqlx::PgPool;
+use sqlx::{Error as SqlxError, PgPool};
 use time::Duration;
 
 use super::{ApiResponse, ApiResult, SESSION_COOKIE_NAME};
@@ -47,11 +50,22 @@ use crate::{
         OAuth2AuthorizedApp, OAuth2Token, Session, SessionState, User,
         models::oauth2client::OAuth2Client,
     },
+    enterprise::{
+        db::models::{
+            api_tokens::ApiTokenScope,
+            client_credentials::{ClientCredentials, ClientCredentialsToken},
+        },
+        is_business_license_active,
+    },
     error::WebError,
     handlers::{SIGN_IN_COOKIE_NAME, mail::send_new_device_ocid_login_email},
     server_config,
 };
 
+const CLIENT_CREDENTIALS_TOKEN_LENGTH: usize = 32;
+/// Lifetime of access tokens issued with the `client_credentials` grant.
+const CLIENT_CREDENTIALS_TOKEN_LIFETIME: std::time::Duration = std::time::Duration::from_secs(3600);
+
 /// https://openid.net/specs/openid-connect-core-1_0.html#StandardClaims
 impl From<&UserClaims> for StandardClaims<CoreGenderClaim> {
     fn from(user_claims: &UserClaims) -> StandardClaims<CoreGenderClaim> {
@@ -662,7 +676,8 @@ pub struct TokenRequest {
     redirect_uri: Option<String>,
     // grant_type == "refresh_token"
     refresh_token: Option<String>,
-    // scope: Option<String>,
+    // grant_type == "client_credentials"
+    scope: Option<String>,
     // Authorization
     client_id: Option<String>,
     client_secret: Option<String>,
@@ -791,17 +806,94 @@ impl TokenRequest {
         token_response
     }
 
-    async fn oauth2client(&self, pool: &PgPool) -> Option<OAuth2Client<Id>> {
-        if let (Some(client_id), Some(client_secret)) =
-            (self.client_id.as_ref(), self.client_secret.as_ref())
-        {
-            OAuth2Client::find_by_auth(pool, client_id, client_secret)
-                .await
-                .unwrap_or_default()
-            // .map_err(|_| CoreErrorResponseType::InvalidClient)
-        } else {
-            None
+    /// Issue an access token to a client acting on behalf of its service account.
+    /// https://www.rfc-editor.org/rfc/rfc6749#section-4.4
+    async fn client_credentials_flow(
+        &self,
+        pool: &PgPool,
+        client: &OAuth2Client<Id>,
+    ) -> Result<
+        Result<StandardTokenResponse<EmptyExtraTokenFields, CoreTokenType>, CoreErrorResponseType>,
+        SqlxError,
+    > {
+        // assume self.grant_type == "client_credentials"
+        let Some(credentials) = ClientCredentials::find_by_client(pool, client.id).await? else {
+            error!(
+                "OAuth client `{}` is not allowed to use client_credentials grant",
+                client.name
+            );
+            return Ok(Err(CoreErrorResponseType::UnauthorizedClient));
+        };
+
+        // all configured scopes are granted unless the client asks for fewer
+        let scopes = match &self.scope {
+            Some(scope) => {
+                let mut scopes = Vec::new();
+                for name in scope.split_whitespace() {
+                    match name.parse::<ApiTokenScope>() {
+                        Ok(scope) if credentials.scopes.contains(&scope) => scopes.push(scope),
+                        _ => {
+                            error!(
+                                "OAuth client `{}` requested invalid scope {name}",
+                                client.name
+                            );
+                            return Ok(Err(CoreErrorResponseType::InvalidScope));
+                        }
+                    }
+                }
+                scopes
+            }
+            None => credentials.scopes.clone(),
+        };
+        if scopes.is_empty() {
+            error!("No scopes requested by OAuth client `{}`", client.name);
+            return Ok(Err(CoreErrorResponseType::InvalidScope));
         }
+
+        ClientCredentialsToken::delete_expired(pool).await?;
+        let token_string = format!("dgc-{}", gen_alphanumeric(CLIENT_CREDENTIALS_TOKEN_LENGTH));
+        ClientCredentialsToken::new(
+            &credentials,
+            &token_string,
+            scopes.clone(),
+            TimeDelta::seconds(CLIENT_CREDENTIALS_TOKEN_LIFETIME.as_secs() as i64),
+        )
+        .save(pool)
+        .await?;
+
+        let mut token_response = StandardTokenResponse::new(
+            AccessToken::new(token_string),
+            CoreTokenType::Bearer,
+            EmptyExtraTokenFields {},
+        );
+        token_response.set_expires_in(Some(&CLIENT_CREDENTIALS_TOKEN_LIFETIME));
+        token_response.set_scopes(Some(
+            scopes
+                .iter()
+                .map(|scope| Scope::new(scope.to_string()))
+                .collect(),
+        ));
+        Ok(Ok(token_response))
+    }
+
+    async fn oauth2client(&self, pool: &PgPool) -> Option<OAuth2Client<Id>> {
+        find_client_by_auth(pool, self.client_id.as_ref(), self.client_secret.as_ref()).await
+    }
+}
+
+/// Authenticate client with credentials passed in request body.
+async fn find_client_by_auth(
+    pool: &PgPool,
+    client_id: Option<&String>,
+    client_secret: Option<&String>,
+) -> Option<OAuth2Client<Id>> {
+    if let (Some(client_id), Some(client_secret)) = (client_id, client_secret) {
+        OAuth2Client::find_by_auth(pool, client_id, client_secret)
+            .await
+            .unwrap_or_default()
+        // .map_err(|_| CoreErrorResponseType::InvalidClient)
+    } else {
+        None
     }
 }
 
@@ -975,6 +1067,41 @@ pub async fn token(
                 }
             }
         }
+        "client_credentials" if is_business_license_active() => {
+            debug!("Starting client_credentials flow");
+            let Some(client) = oauth2client.or(form.oauth2client(&appstate.pool).await) else {
+                error!("OAuth client authentication failed for client_credentials grant");
+                let response = StandardErrorResponse::<CoreErrorResponseType>::new(
+                    CoreErrorResponseType::InvalidClient,
+                    None,
+                    None,
+                );
+                return Ok(ApiResponse {
+                    json: json!(response),
+                    status: StatusCode::UNAUTHORIZED,
+                });
+            };
+            match form
+                .client_credentials_flow(&appstate.pool, &client)
+                .await?
+            {
+                Ok(response) => {
+                    info!("Issued new access token for client {}", client.name);
+                    return Ok(ApiResponse {
+                        json: json!(response),
+                        status: StatusCode::OK,
+                    });
+                }
+                Err(err) => {
+                    let response =
+                        StandardErrorResponse::<CoreErrorResponseType>::new(err, None, None);
+                    return Ok(ApiResponse {
+                        json: json!(response),
+                        status: StatusCode::BAD_REQUEST,
+                    });
+                }
+            }
+        }
         _ => (), // TODO: Err(CoreErrorResponseType::UnsupportedGrantType),
     }
     let err = CoreErrorResponseType::UnsupportedGrantType;
@@ -1033,6 +1160,155 @@ pub async fn userinfo(State(appstate): State<AppState>, headers: HeaderMap) -> A
     })
 }
 
+/// Request to introspect or revoke a token.
+/// https://www.rfc-editor.org/rfc/rfc7662#section-2.1
+/// https://www.rfc-editor.org/rfc/rfc7009#section-2.1
+#[derive(Deserialize)]
+pub struct TokenManagementRequest {
+    token: String,
+    // Authorization
+    client_id: Option<String>,
+    client_secret: Option<String>,
+}
+
+impl TokenManagementRequest {
+    async fn oauth2client(&self, pool: &PgPool) -> Option<OAuth2Client<Id>> {
+        find_client_by_auth(pool, self.client_id.as_ref(), self.client_secret.as_ref()).await
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc7662#section-2.2
+#[derive(Default, Serialize)]
+pub struct TokenIntrospection {
+    active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iat: Option<i64>,
+}
+
+/// Token Introspection Endpoint
+/// https://www.rfc-editor.org/rfc/rfc7662
+pub async fn introspect(
+    State(appstate): State<AppState>,
+    oauth2client: Option<OAuth2Client<Id>>,
+    Form(form): Form<TokenManagementRequest>,
+) -> ApiResult {
+    let Some(client) = oauth2client.or(form.oauth2client(&appstate.pool).await) else {
+        return Err(WebError::Authorization("Invalid client credentials".into()));
+    };
+    debug!("OAuth client {} introspecting token", client.name);
+
+    let mut introspection = TokenIntrospection::default();
+    if let Some(token) = ClientCredentialsToken::find_active(&appstate.pool, &form.token).await? {
+        let owner = OAuth2Client::find_by_id(&appstate.pool, token.oauth2client_id).await?;
+        let user = User::find_by_id(&appstate.pool, token.user_id).await?;
+        let scopes: Vec<String> = token.scopes.iter().map(ToString::to_string).collect();
+        introspection = TokenIntrospection {
+            active: true,
+            scope: Some(scopes.join(" ")),
+            client_id: owner.map(|owner| owner.client_id),
+            username: user.map(|user| user.username),
+            token_type: Some("Bearer".into()),
+            exp: Some(token.expires_at.and_utc().timestamp()),
+            iat: Some(token.created_at.and_utc().timestamp()),
+        };
+    } else if let Some(token) = OAuth2Token::find_access_token(&appstate.pool, &form.token).await? {
+        if let Some(owner) = OAuth2Client::find_by_token(&appstate.pool, &token).await? {
+            if owner.enabled {
+                let authorized_app =
+                    OAuth2AuthorizedApp::find_by_id(&appstate.pool, token.oauth2authorizedapp_id)
+                        .await?;
+                let user = match authorized_app {
+                    Some(app) => User::find_by_id(&appstate.pool, app.user_id).await?,
+                    None => None,
+                };
+                introspection = TokenIntrospection {
+                    active: true,
+                    scope: Some(token.scope),
+                    client_id: Some(owner.client_id),
+                    username: user.map(|user| user.username),
+                    token_type: Some("Bearer".into()),
+                    exp: Some(token.expires_in),
+                    iat: None,
+                };
+            }
+        }
+    }
+
+    Ok(ApiResponse {
+        json: json!(introspection),
+        status: StatusCode::OK,
+    })
+}
+
+/// Token Revocation Endpoint
+/// https://www.rfc-editor.org/rfc/rfc7009
+pub async fn revoke(
+    State(appstate): State<AppState>,
+    oauth2client: Option<OAuth2Client<Id>>,
+    Form(form): Form<TokenManagementRequest>,
+) -> ApiResult {
+    let Some(client) = oauth2client.or(form.oauth2client(&appstate.pool).await) else {
+        return Err(WebError::Authorization("Invalid client credentials".into()));
+    };
+
+    let unauthorized = || {
+        let response = StandardErrorResponse::<CoreErrorResponseType>::new(
+            CoreErrorResponseType::UnauthorizedClient,
+            None,
+            None,
+        );
+        ApiResponse {
+            json: json!(response),
+            status: StatusCode::BAD_REQUEST,
+        }
+    };
+
+    if let Some(token) = ClientCredentialsToken::find_active(&appstate.pool, &form.token).await? {
+        if token.oauth2client_id != client.id {
+            error!(
+                "OAuth client {} attempted to revoke token issued to another client",
+                client.name
+            );
+            return Ok(unauthorized());
+        }
+        token.delete(&appstate.pool).await?;
+        info!("Revoked access token of OAuth client {}", client.name);
+    } else {
+        let token = match OAuth2Token::find_access_token(&appstate.pool, &form.token).await? {
+            Some(token) => Some(token),
+            None => OAuth2Token::find_refresh_token(&appstate.pool, &form.token).await?,
+        };
+        if let Some(token) = token {
+            let owner = OAuth2Client::find_by_token(&appstate.pool, &token).await?;
+            if owner.is_none_or(|owner| owner.id != client.id) {
+                error!(
+                    "OAuth client {} attempted to revoke token issued to another client",
+                    client.name
+                );
+                return Ok(unauthorized());
+            }
+            token.delete(&appstate.pool).await?;
+            info!("Revoked token of OAuth client {}", client.name);
+        }
+    }
+
+    // invalid tokens aren't reported to the client, see RFC 7009 section 2.2
+    Ok(ApiResponse {
+        json: json!({}),
+        status: StatusCode::OK,
+    })
+}
+
 // Must be served under /.well-known/openid-configuration
 pub async fn openid_configuration() -> ApiResult {
     let config = server_config();
@@ -1074,6 +1350,7 @@ pub async fn openid_configuration() -> ApiResult {
     .set_grant_types_supported(Some(vec![
         CoreGrantType::AuthorizationCode,
         CoreGrantType::RefreshToken,
+        CoreGrantType::ClientCredentials,
     ]))
     .set_userinfo_endpoint(Some(UserInfoUrl::from_url(
         config.url.join("api/v1/oauth/userinfo").unwrap(),
@@ -5,6 +5,7 @@ use axum::{
 use reqwest::StatusCode;
 use serde::Serialize;
 
+use super::DEFAULT_API_PAGE_SIZE;
 use crate::error::WebError;
 
 /// Query params for paginated endpoints
@@ -26,6 +27,32 @@ pub struct PaginationMeta {
     pub total_items: u32,
     pub total_pages: u32,
     pub next_page: Option<u32>,
+    // opaque cursor pointing after the last returned item, for keyset pagination
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Build pagination metadata for given page of `total_items` results.
+pub(crate) fn get_pagination_metadata(
+    current_page: u32,
+    total_items: u32,
+    next_cursor: Option<String>,
+) -> PaginationMeta {
+    let total_pages = (total_items).div_ceil(DEFAULT_API_PAGE_SIZE);
+    let next_page = if current_page < total_pages {
+        Some(current_page + 1)
+    } else {
+        None
+    };
+
+    PaginationMeta {
+        current_page,
+        page_size: DEFAULT_API_PAGE_SIZE,
+        total_items,
+        total_pages,
+        next_page,
+        next_cursor,
+    }
 }
 
 pub type PaginatedApiResult<T> = Result<PaginatedApiResponse<T>, WebError>;
@@ -1,10 +1,13 @@
 use axum::{
-    extract::{Json, Path, State},
+    extract::{Json, Path, Query, State},
     http::StatusCode,
 };
-use defguard_common::db::models::{
-    Settings, SettingsEssentials,
-    settings::{LdapSyncStatus, SettingsPatch, update_current_settings},
+use defguard_common::db::{
+    Id,
+    models::{
+        Settings, SettingsEssentials,
+        settings::{LdapSyncStatus, SettingsPatch, update_current_settings},
+    },
 };
 use serde_json::json;
 use struct_patch::Patch;
@@ -13,7 +16,12 @@ use super::{ApiResponse, ApiResult};
 use crate::{
     AppState,
     auth::{AdminRole, SessionInfo},
-    enterprise::{ldap::LDAPConnection, license::update_cached_license},
+    db::{User, WireguardNetwork, models::gateway_alert::GatewayAlertRule},
+    enterprise::{
+        db::models::{ldap_operation::LdapOperation, ldap_sync_conflict::LdapSyncConflict},
+        ldap::{LDAPConnection, sync::is_ldap_desynced},
+        license::update_cached_license,
+    },
     error::WebError,
     events::{ApiEvent, ApiEventType, ApiRequestContext},
 };
@@ -171,18 +179,146 @@ pub async fn patch_settings(
     Ok(ApiResponse::default())
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PasswordHashReport {
+    pub total: i64,
+    // hashes which will be upgraded on next successful login
+    pub legacy: i64,
+}
+
+/// Reports how many stored password hashes use a legacy KDF or outdated parameters.
+pub async fn password_hash_report(
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    debug!("Counting legacy password hashes");
+    let (total, legacy) = User::count_legacy_password_hashes(&appstate.pool).await?;
+    info!("{legacy} out of {total} password hashes use legacy parameters");
+
+    Ok(ApiResponse {
+        json: json!(PasswordHashReport { total, legacy }),
+        status: StatusCode::OK,
+    })
+}
+
+/// Lists group membership conflicts resolved during the last LDAP sync.
+pub async fn list_ldap_sync_conflicts(
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    debug!("Listing LDAP sync conflicts");
+    let conflicts = LdapSyncConflict::all(&appstate.pool).await?;
+    Ok(ApiResponse {
+        json: json!(conflicts),
+        status: StatusCode::OK,
+    })
+}
+
+/// Lists LDAP operations awaiting a retry, including the ones which failed permanently.
+pub async fn list_ldap_operations(
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    debug!("Listing queued LDAP operations");
+    let mut operations = LdapOperation::all(&appstate.pool).await?;
+    operations.sort_by_key(|operation| operation.id);
+    Ok(ApiResponse {
+        json: json!(operations),
+        status: StatusCode::OK,
+    })
+}
+
+/// Schedules a queued LDAP operation to be retried right away, also if it failed permanently.
+pub async fn retry_ldap_operation(
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+    Path(id): Path<Id>,
+) -> ApiResult {
+    debug!("Scheduling retry of queued LDAP operation {id}");
+    let Some(mut operation) = LdapOperation::find_by_id(&appstate.pool, id).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "LDAP operation {id} not found"
+        )));
+    };
+    operation.reset();
+    operation.save(&appstate.pool).await?;
+    info!("Scheduled retry of queued LDAP operation {id}");
+
+    Ok(ApiResponse {
+        json: json!(operation),
+        status: StatusCode::OK,
+    })
+}
+
+/// Removes an operation from the queue without applying it.
+pub async fn delete_ldap_operation(
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+    Path(id): Path<Id>,
+) -> ApiResult {
+    debug!("Deleting queued LDAP operation {id}");
+    let Some(operation) = LdapOperation::find_by_id(&appstate.pool, id).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "LDAP operation {id} not found"
+        )));
+    };
+    operation.delete(&appstate.pool).await?;
+    info!("Deleted queued LDAP operation {id}");
+
+    Ok(ApiResponse {
+        json: json!({}),
+        status: StatusCode::OK,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LdapSyncPreviewParams {
+    // defaults to the mode the next scheduled sync would run in
+    pub full: Option<bool>,
+}
+
+/// Computes changes the LDAP sync would make, without applying any of them.
+pub async fn preview_ldap_sync(
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+    Query(params): Query<LdapSyncPreviewParams>,
+) -> ApiResult {
+    let full = params.full.unwrap_or_else(is_ldap_desynced);
+    debug!("Previewing LDAP sync, full: {full}");
+    let mut ldap_connection = LDAPConnection::create().await?;
+    let preview = ldap_connection.sync_preview(&appstate.pool, full).await?;
+    info!(
+        "Previewed LDAP sync: {} conflicting group memberships found",
+        preview.conflicts.len()
+    );
+
+    Ok(ApiResponse {
+        json: json!(preview),
+        status: StatusCode::OK,
+    })
+}
+
+/// Tests LDAP connection and reports which of the configured user attributes resolved.
 pub async fn test_ldap_settings(_admin: AdminRole) -> ApiResult {
     debug!("Testing LDAP connection");
-    match LDAPConnection::create().await {
-        Ok(_) => {
-            debug!("LDAP connected successfully");
-            Ok(ApiResponse {
+    let mut connection = match LDAPConnection::create().await {
+        Ok(connection) => connection,
+        Err(err) => {
+            debug!("LDAP connection rejected: {err}");
+            return Ok(ApiResponse {
                 json: json!({}),
-                status: StatusCode::OK,
-            })
+                status: StatusCode::BAD_REQUEST,
+            });
         }
+    };
+    debug!("LDAP connected successfully");
+    match connection.resolve_user_attributes().await {
+        Ok(resolution) => Ok(ApiResponse {
+            json: json!(resolution),
+            status: StatusCode::OK,
+        }),
         Err(err) => {
-            debug!("LDAP connection rejected: {err}");
+            debug!("Failed to resolve LDAP user attributes: {err}");
             Ok(ApiResponse {
                 json: json!({}),
                 status: StatusCode::BAD_REQUEST,
@@ -190,3 +326,91 @@ pub async fn test_ldap_settings(_admin: AdminRole) -> ApiResult {
         }
     }
 }
+
+/// Per-location gateway disconnect alert as sent by the API.
+#[derive(Debug, Deserialize)]
+pub struct GatewayAlertRuleData {
+    pub threshold_minutes: i32,
+    pub notify_webhooks: bool,
+    pub notify_admins: bool,
+}
+
+/// Lists gateway disconnect alert rules of all locations.
+pub async fn list_gateway_alert_rules(
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    debug!("Listing gateway alert rules");
+    let rules = GatewayAlertRule::all(&appstate.pool).await?;
+    Ok(ApiResponse {
+        json: json!(rules),
+        status: StatusCode::OK,
+    })
+}
+
+/// Creates or replaces gateway disconnect alert rule for a location.
+pub async fn set_gateway_alert_rule(
+    _admin: AdminRole,
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    Path(location_id): Path<Id>,
+    Json(data): Json<GatewayAlertRuleData>,
+) -> ApiResult {
+    debug!(
+        "User {} setting gateway alert rule for location {location_id}",
+        session.user.username
+    );
+    let Some(location) = WireguardNetwork::find_by_id(&appstate.pool, location_id).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "Location {location_id} not found"
+        )));
+    };
+    if data.threshold_minutes < 1 {
+        return Err(WebError::BadRequest(
+            "Alert threshold must be at least one minute".into(),
+        ));
+    }
+    let rule = GatewayAlertRule {
+        location_id,
+        threshold_minutes: data.threshold_minutes,
+        notify_webhooks: data.notify_webhooks,
+        notify_admins: data.notify_admins,
+    };
+    rule.save(&appstate.pool).await?;
+    info!(
+        "User {} set gateway alert rule for location {} to {} minutes",
+        session.user.username, location.name, rule.threshold_minutes
+    );
+
+    Ok(ApiResponse {
+        json: json!(rule),
+        status: StatusCode::OK,
+    })
+}
+
+/// Removes gateway disconnect alert rule of a location.
+pub async fn delete_gateway_alert_rule(
+    _admin: AdminRole,
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    Path(location_id): Path<Id>,
+) -> ApiResult {
+    if GatewayAlertRule::find_by_location(&appstate.pool, location_id)
+        .await?
+        .is_none()
+    {
+        return Err(WebError::ObjectNotFound(format!(
+            "Gateway alert rule for location {location_id} not found"
+        )));
+    }
+    GatewayAlertRule::delete_for_location(&appstate.pool, location_id).await?;
+    info!(
+        "User {} removed gateway alert rule for location {location_id}",
+        session.user.username
+    );
+
+    Ok(ApiResponse {
+        json: json!({}),
+        status: StatusCode::OK,
+    })
+}
@@ -1,10 +1,28 @@
-use axum::{extract::State, http::StatusCode};
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Extension,
+    extract::State,
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+use defguard_common::{
+    db::Id,
+    diagnostics::{
+        EVENT_LOGGER_QUEUE, GATEWAY_EVENT_QUEUES, MAIL_QUEUE, QueueSnapshot, labels,
+        render_labeled_metrics,
+    },
+};
+use serde_json::json;
 
 use super::{ApiResponse, ApiResult};
 use crate::{
     AppState,
     auth::{AdminRole, SessionInfo},
     error::WebError,
+    grpc::gateway::map::GatewayMap,
+    health::{HealthStatus, health_report},
+    metrics::render,
     server_config,
     support::dump_config,
 };
@@ -43,3 +61,118 @@ pub async fn logs(_admin: AdminRole, session: SessionInfo) -> Result<String, Web
         Ok("Log file not configured".to_string())
     }
 }
+
+/// Current saturation of internal queues. Gateway event streams are aggregated, see
+/// [`gateway_queue_snapshots`] for each gateway separately.
+pub(crate) fn queue_snapshots(appstate: &AppState) -> [(&'static str, QueueSnapshot); 3] {
+    let gateways = GATEWAY_EVENT_QUEUES.snapshots();
+    let mut gateway_events = QueueSnapshot::aggregate(gateways.iter().map(|(_, queue)| queue));
+    // Broadcast channel knows how many events the slowest gateway still has to receive.
+    gateway_events.depth = appstate.wireguard_tx.len() as u64;
+    [
+        ("event_logger", EVENT_LOGGER_QUEUE.snapshot()),
+        ("mail", MAIL_QUEUE.snapshot()),
+        ("gateway_events", gateway_events),
+    ]
+}
+
+/// Saturation of event streams of every gateway, keyed by location ID and hostname.
+pub(crate) fn gateway_queue_snapshots() -> Vec<((Id, String), QueueSnapshot)> {
+    GATEWAY_EVENT_QUEUES.snapshots()
+}
+
+/// Renders queue snapshots in Prometheus text format, gateway event streams are additionally
+/// labeled with location ID and gateway hostname.
+pub(crate) fn render_queue_metrics(appstate: &AppState) -> String {
+    let snapshots = queue_snapshots(appstate);
+    let gateways = gateway_queue_snapshots();
+    let queues = snapshots
+        .iter()
+        .map(|(name, snapshot)| (labels(&[("queue", name)]), snapshot))
+        .chain(gateways.iter().map(|((location_id, hostname), snapshot)| {
+            (
+                labels(&[
+                    ("queue", "gateway_events"),
+                    ("location_id", &location_id.to_string()),
+                    ("gateway", hostname),
+                ]),
+                snapshot,
+            )
+        }))
+        .collect::<Vec<_>>();
+    render_labeled_metrics(&queues)
+}
+
+pub async fn diagnostics(_admin: AdminRole, State(appstate): State<AppState>) -> ApiResult {
+    debug!("Retrieving internal queue diagnostics");
+    let [(_, event_logger), (_, mail), (_, gateway_events)] = queue_snapshots(&appstate);
+    let gateways = gateway_queue_snapshots()
+        .into_iter()
+        .map(|((location_id, hostname), queue)| {
+            json!({
+                "location_id": location_id,
+                "hostname": hostname,
+                "queue": queue,
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(ApiResponse {
+        json: json!({
+            "event_logger": event_logger,
+            "mail": mail,
+            "gateway_events": gateway_events,
+            "gateways": gateways,
+        }),
+        status: StatusCode::OK,
+    })
+}
+
+/// Internal queue diagnostics in Prometheus text format.
+pub async fn metrics(_admin: AdminRole, State(appstate): State<AppState>) -> String {
+    debug!("Rendering internal queue metrics");
+    render_queue_metrics(&appstate)
+}
+
+/// Deployment metrics in Prometheus text format
+///
+/// Reports connected peers per location, gateway connection state, internal queue saturation,
+/// LDAP sync status, failed MFA attempts and HTTP/gRPC request latencies. Meant to be scraped
+/// with an admin API token.
+pub async fn prometheus_metrics(
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+    Extension(gateway_state): Extension<Arc<Mutex<GatewayMap>>>,
+) -> Result<impl IntoResponse, WebError> {
+    debug!("Rendering Prometheus metrics");
+    let metrics = render(&appstate, &gateway_state).await?;
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        metrics,
+    ))
+}
+
+/// Detailed health report of core components
+///
+/// Reports status and last error of the database, mail handler, LDAP, license, gateway
+/// connections and background workers. Responds with 503 Service Unavailable if any component
+/// is down, so it can be polled by load balancers and monitoring systems.
+pub async fn detailed_health(
+    _admin: AdminRole,
+    State(appstate): State<AppState>,
+    Extension(gateway_state): Extension<Arc<Mutex<GatewayMap>>>,
+) -> ApiResult {
+    debug!("Checking health of core components");
+    let report = health_report(&appstate, &gateway_state).await;
+    let status = if report.status == HealthStatus::Down {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    Ok(ApiResponse {
+        json: json!(report),
+        status,
+    })
+}
@@ -0,0 +1,205 @@
+use std::{mem, str};
+
+use axum::{body::Bytes, extract::multipart::Field, http::StatusCode};
+use serde_json::json;
+
+use super::ApiResponse;
+use crate::error::WebError;
+
+/// Validation error of a single imported row. Rows are numbered from 1, for CSV files
+/// the header is not counted.
+#[derive(Debug, Serialize)]
+pub(crate) struct ImportRowError {
+    pub row: usize,
+    pub message: String,
+}
+
+pub(crate) fn import_errors_response(errors: Vec<ImportRowError>) -> ApiResponse {
+    ApiResponse {
+        json: json!({ "errors": errors }),
+        status: StatusCode::UNPROCESSABLE_ENTITY,
+    }
+}
+
+/// Limits the total size of all fields read from a single multipart request.
+/// Fields are read chunk by chunk and the request is rejected as soon as the limit is exceeded,
+/// so oversized uploads are never buffered in full.
+pub(crate) struct UploadLimit {
+    remaining: usize,
+}
+
+impl UploadLimit {
+    #[must_use]
+    pub(crate) fn new(limit: usize) -> Self {
+        Self { remaining: limit }
+    }
+
+    async fn next_chunk(&mut self, field: &mut Field<'_>) -> Result<Option<Bytes>, WebError> {
+        let Some(chunk) = field.chunk().await? else {
+            return Ok(None);
+        };
+        if chunk.len() > self.remaining {
+            warn!(
+                "Upload exceeds the limit while reading field {}",
+                field.name().unwrap_or_default()
+            );
+            return Err(WebError::Http(StatusCode::PAYLOAD_TOO_LARGE));
+        }
+        self.remaining -= chunk.len();
+
+        Ok(Some(chunk))
+    }
+
+    /// Reads a single multipart field as UTF-8 text.
+    pub(crate) async fn read_text(&mut self, mut field: Field<'_>) -> Result<String, WebError> {
+        let mut buffer = Vec::new();
+        while let Some(chunk) = self.next_chunk(&mut field).await? {
+            buffer.extend_from_slice(&chunk);
+        }
+        String::from_utf8(buffer).map_err(|_| {
+            WebError::BadRequest(format!(
+                "Field {} is not valid UTF-8 text",
+                field.name().unwrap_or_default()
+            ))
+        })
+    }
+
+    /// Parses a multipart field as CSV while it's being received, without keeping the raw file.
+    pub(crate) async fn read_csv(
+        &mut self,
+        mut field: Field<'_>,
+    ) -> Result<Vec<Vec<String>>, WebError> {
+        let mut reader = CsvReader::default();
+        // a multi-byte character can be split between chunks
+        let mut pending = Vec::new();
+        while let Some(chunk) = self.next_chunk(&mut field).await? {
+            pending.extend_from_slice(&chunk);
+            let valid = match str::from_utf8(&pending) {
+                Ok(text) => text.len(),
+                Err(err) if err.error_len().is_none() => err.valid_up_to(),
+                Err(_) => return Err(WebError::BadRequest("CSV file is not valid UTF-8".into())),
+            };
+            let text = str::from_utf8(&pending[..valid]).expect("validated above");
+            reader.feed(text);
+            pending.drain(..valid);
+        }
+        if !pending.is_empty() {
+            return Err(WebError::BadRequest("CSV file is not valid UTF-8".into()));
+        }
+
+        reader
+            .finish()
+            .map_err(|err| WebError::BadRequest(format!("Invalid CSV file: {err}")))
+    }
+}
+
+/// Incremental CSV parser. Supports quoted fields containing commas, escaped quotes
+/// and line breaks. Blank lines are skipped.
+#[derive(Default)]
+struct CsvReader {
+    records: Vec<Vec<String>>,
+    record: Vec<String>,
+    field: String,
+    quoted: bool,
+    // last character was a quote inside a quoted field, which either escapes a quote
+    // or ends the field
+    quote_pending: bool,
+    carriage_return: bool,
+}
+
+impl CsvReader {
+    fn feed(&mut self, text: &str) {
+        for c in text.chars() {
+            if self.quote_pending {
+                self.quote_pending = false;
+                if c == '"' {
+                    self.field.push('"');
+                    continue;
+                }
+                self.quoted = false;
+            }
+            if self.carriage_return {
+                self.carriage_return = false;
+                if c != '\n' {
+                    self.field.push('\r');
+                }
+            }
+            if self.quoted {
+                match c {
+                    '"' => self.quote_pending = true,
+                    _ => self.field.push(c),
+                }
+            } else {
+                match c {
+                    '"' if self.field.is_empty() => self.quoted = true,
+                    ',' => self.record.push(mem::take(&mut self.field)),
+                    '\r' => self.carriage_return = true,
+                    '\n' => {
+                        self.record.push(mem::take(&mut self.field));
+                        self.records.push(mem::take(&mut self.record));
+                    }
+                    _ => self.field.push(c),
+                }
+            }
+        }
+    }
+
+    fn finish(mut self) -> Result<Vec<Vec<String>>, String> {
+        if self.quoted && !self.quote_pending {
+            return Err("unterminated quoted field".into());
+        }
+        if self.carriage_return {
+            self.field.push('\r');
+        }
+        if !self.field.is_empty() || !self.record.is_empty() {
+            self.record.push(self.field);
+            self.records.push(self.record);
+        }
+        self.records.retain(|record| {
+            record.len() > 1 || record.iter().any(|field| !field.trim().is_empty())
+        });
+
+        Ok(self.records)
+    }
+}
+
+/// Parses CSV text into records. See [`CsvReader`].
+pub(crate) fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut reader = CsvReader::default();
+    reader.feed(text);
+    reader.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let records = parse_csv(
+            "name,description\r\n\"camera, lobby\",\"the \"\"main\"\" one\"\r\n\r\nprinter,\n",
+        )
+        .unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["name", "description"],
+                vec!["camera, lobby", "the \"main\" one"],
+                vec!["printer", ""],
+            ]
+        );
+        assert!(parse_csv("name\n\"unterminated\n").is_err());
+    }
+
+    #[test]
+    fn test_csv_reader_chunks() {
+        let text = "name,description\r\n\"camera, lobby\",\"the \"\"main\"\" one\"\r\nprinter,\n";
+        // records don't depend on where the text is split between chunks
+        for split in 1..text.len() {
+            let mut reader = CsvReader::default();
+            reader.feed(&text[..split]);
+            reader.feed(&text[split..]);
+            assert_eq!(reader.finish().unwrap(), parse_csv(text).unwrap());
+        }
+    }
+}
@@ -1,26 +1,39 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use axum::{
-    extract::{Json, Path, State},
+    extract::{Json, Multipart, Path, State},
     http::StatusCode,
 };
+use chrono::Utc;
+use chrono_tz::Tz;
+use defguard_common::db::Id;
 use defguard_mail::{Mail, templates};
 use humantime::parse_duration;
 use serde_json::json;
+use sqlx::PgPool;
 
 use super::{
-    AddUserData, ApiResponse, ApiResult, PasswordChange, PasswordChangeSelf,
-    StartEnrollmentRequest, Username, mail::EMAIL_PASSWORD_RESET_START_SUBJECT,
+    AddUserData, ApiResponse, ApiResult, EmailVerification, PasswordChange, PasswordChangeSelf,
+    StartEnrollmentRequest, Username,
+    group::check_not_ldap_managed,
+    mail::{EMAIL_PASSWORD_RESET_START_SUBJECT, send_email_verification_email},
+    upload::{ImportRowError, UploadLimit, import_errors_response},
     user_for_admin_or_self,
 };
 use crate::{
     appstate::AppState,
-    auth::{AdminRole, SessionInfo},
+    auth::{AdminRole, SessionInfo, password_policy::check_password_policy},
     db::{
-        AppEvent, OAuth2AuthorizedApp, User, UserDetails, UserInfo, WebAuthn,
+        AppEvent, Group, OAuth2AuthorizedApp, User, UserDetails, UserInfo, WebAuthn,
+        WireguardNetwork,
         models::{
             GroupDiff,
-            enrollment::{PASSWORD_RESET_TOKEN_TYPE, Token},
+            enrollment::{
+                EMAIL_VERIFICATION_TOKEN_TYPE, EnrollmentTokenOptions, PASSWORD_RESET_TOKEN_TYPE,
+                Token, TokenError,
+            },
+            impersonation::ImpersonationInfo,
+            notification_digest::NotificationSettings,
         },
     },
     enterprise::{
@@ -82,31 +95,6 @@ pub fn check_username(username: &str) -> Result<(), WebError> {
     Ok(())
 }
 
-pub(crate) fn check_password_strength(password: &str) -> Result<(), WebError> {
-    if !(8..=128).contains(&password.len()) {
-        return Err(WebError::Serialization("Incorrect password length".into()));
-    }
-    if !password.chars().any(|c| c.is_ascii_punctuation()) {
-        return Err(WebError::Serialization(
-            "No special characters in password".into(),
-        ));
-    }
-    if !password.chars().any(|c| c.is_ascii_digit()) {
-        return Err(WebError::Serialization("No numbers in password".into()));
-    }
-    if !password.chars().any(|c| c.is_ascii_lowercase()) {
-        return Err(WebError::Serialization(
-            "No lowercase characters in password".into(),
-        ));
-    }
-    if !password.chars().any(|c| c.is_ascii_uppercase()) {
-        return Err(WebError::Serialization(
-            "No uppercase characters in password".into(),
-        ));
-    }
-    Ok(())
-}
-
 /// List of all users
 ///
 /// Retrieves list of users.
@@ -310,22 +298,25 @@ pub async fn add_user(
         }
     }
 
+    // service accounts never log in interactively, so they have no password
+    if user_data.service_account && user_data.password.is_some() {
+        debug!("Password provided for new service account {username}");
+        return Ok(ApiResponse {
+            json: json!({}),
+            status: StatusCode::BAD_REQUEST,
+        });
+    }
+
     let password = match &user_data.password {
         Some(password) => {
-            // check password strength
-            if let Err(err) = check_password_strength(password) {
-                debug!("Password not strong enough: {err}");
-                return Ok(ApiResponse {
-                    json: json!({}),
-                    status: StatusCode::BAD_REQUEST,
-                });
-            }
+            check_password_policy(&appstate.pool, password, None).await?;
             Some(password.as_str())
         }
         None => None,
     };
 
     // create new user
+    let mut transaction = appstate.pool.begin().await?;
     let mut user = User::new(
         user_data.username,
         password,
@@ -334,8 +325,13 @@ pub async fn add_user(
         user_data.email,
         user_data.phone,
     )
-    .save(&appstate.pool)
+    .save(&mut *transaction)
     .await?;
+    user.remember_password(&mut transaction).await?;
+    transaction.commit().await?;
+    if user_data.service_account {
+        user.set_service_account(&appstate.pool, true).await?;
+    }
     update_counts(&appstate.pool).await?;
 
     if let Some(password) = user_data.password {
@@ -358,6 +354,366 @@ pub async fn add_user(
     })
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ImportUser {
+    pub username: String,
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    /// Names of existing groups the user is added to.
+    #[serde(default)]
+    pub groups: Vec<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportUsers {
+    pub users: Vec<ImportUser>,
+    /// Start enrollment of imported users and send them the enrollment email.
+    #[serde(default)]
+    pub send_enrollment_notification: bool,
+}
+
+/// Reads users from CSV with a header row. `username`, `email`, `first_name` and `last_name`
+/// columns are required, `groups` (comma-separated) and `phone` are optional.
+fn parse_users_csv(records: Vec<Vec<String>>) -> Result<Vec<ImportUser>, WebError> {
+    let mut records = records.into_iter();
+    let header: Vec<String> = records
+        .next()
+        .ok_or_else(|| WebError::BadRequest("CSV file is empty".into()))?
+        .iter()
+        .map(|column| column.trim().to_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|column| column == name);
+    let (
+        Some(username_column),
+        Some(email_column),
+        Some(first_name_column),
+        Some(last_name_column),
+    ) = (
+        column("username"),
+        column("email"),
+        column("first_name"),
+        column("last_name"),
+    )
+    else {
+        return Err(WebError::BadRequest(
+            "CSV header must contain username, email, first_name and last_name columns".into(),
+        ));
+    };
+    let groups_column = column("groups");
+    let phone_column = column("phone");
+
+    Ok(records
+        .map(|record| {
+            let value = |column: Option<usize>| {
+                column
+                    .and_then(|column| record.get(column))
+                    .map(|value| value.trim())
+                    .filter(|value| !value.is_empty())
+                    .map(ToString::to_string)
+            };
+            ImportUser {
+                username: value(Some(username_column)).unwrap_or_default(),
+                email: value(Some(email_column)).unwrap_or_default(),
+                first_name: value(Some(first_name_column)).unwrap_or_default(),
+                last_name: value(Some(last_name_column)).unwrap_or_default(),
+                groups: value(groups_column)
+                    .map(|groups| {
+                        groups
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|group| !group.is_empty())
+                            .map(ToString::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                phone: value(phone_column),
+            }
+        })
+        .collect())
+}
+
+fn is_valid_email(email: &str) -> bool {
+    email.split_once('@').is_some_and(|(local, domain)| {
+        !local.is_empty()
+            && !domain.is_empty()
+            && !domain.contains('@')
+            && !email.contains(char::is_whitespace)
+    })
+}
+
+pub(crate) async fn import_users(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Json(data): Json<ImportUsers>,
+) -> ApiResult {
+    import_users_data(&appstate, session, context, data).await
+}
+
+pub(crate) async fn import_users_upload(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    mut multipart: Multipart,
+) -> ApiResult {
+    debug!("Reading uploaded users file");
+    let mut limit = UploadLimit::new(server_config().http_import_body_limit);
+    let mut users = None;
+    let mut send_enrollment_notification = false;
+    while let Some(field) = multipart.next_field().await? {
+        let field_name = field.name().map(ToString::to_string);
+        match field_name.as_deref() {
+            Some("send_enrollment_notification") => {
+                let value = limit.read_text(field).await?;
+                send_enrollment_notification = value.trim().parse().map_err(|_| {
+                    WebError::BadRequest(format!(
+                        "Invalid send_enrollment_notification value: {value}"
+                    ))
+                })?;
+            }
+            Some("file") => {
+                users = Some(parse_users_csv(limit.read_csv(field).await?)?);
+            }
+            _ => debug!("Ignoring unexpected field {field_name:?} in users upload"),
+        }
+    }
+
+    let data = ImportUsers {
+        users: users.ok_or_else(|| WebError::BadRequest("Missing field: file".into()))?,
+        send_enrollment_notification,
+    };
+
+    import_users_data(&appstate, session, context, data).await
+}
+
+/// Validates all users first and creates them in a single transaction, so either all users
+/// are imported or none. Validation errors of all rows are returned at once.
+async fn import_users_data(
+    appstate: &AppState,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    data: ImportUsers,
+) -> ApiResult {
+    debug!(
+        "User {} importing {} users",
+        session.user.username,
+        data.users.len()
+    );
+    if data.users.is_empty() {
+        return Err(WebError::BadRequest("No users to import".into()));
+    }
+
+    let mut errors = Vec::new();
+    let mut validated = Vec::new();
+    let mut usernames: HashMap<String, usize> = HashMap::new();
+    let mut emails: HashMap<String, usize> = HashMap::new();
+    // `None` marks groups which don't exist or can't be modified locally
+    let mut groups: HashMap<String, Option<Group<Id>>> = HashMap::new();
+    for (index, user) in data.users.into_iter().enumerate() {
+        let row = index + 1;
+        let mut fail = |message: String| errors.push(ImportRowError { row, message });
+
+        if let Err(err) = check_username(&user.username) {
+            fail(match err {
+                WebError::Serialization(message) => message,
+                err => err.to_string(),
+            });
+            continue;
+        }
+        if user.first_name.trim().is_empty() || user.last_name.trim().is_empty() {
+            fail("First and last name are required".into());
+            continue;
+        }
+        if !is_valid_email(&user.email) {
+            fail(format!("Invalid email address: {}", user.email));
+            continue;
+        }
+        if let Some(phone) = &user.phone {
+            if !is_valid_phone_number(phone) {
+                fail(format!("Invalid phone number: {phone}"));
+                continue;
+            }
+        }
+        if let Some(other_row) = usernames.insert(user.username.clone(), row) {
+            fail(format!("Username is also used in row {other_row}"));
+            continue;
+        }
+        if let Some(other_row) = emails.insert(user.email.to_lowercase(), row) {
+            fail(format!("Email is also used in row {other_row}"));
+            continue;
+        }
+        if User::find_by_username(&appstate.pool, &user.username)
+            .await?
+            .is_some()
+        {
+            fail(format!("User {} already exists", user.username));
+            continue;
+        }
+        if User::find_by_email(&appstate.pool, &user.email)
+            .await?
+            .is_some()
+        {
+            fail(format!("User with email {} already exists", user.email));
+            continue;
+        }
+
+        let mut user_groups = Vec::with_capacity(user.groups.len());
+        let mut invalid_group = None;
+        for name in &user.groups {
+            if !groups.contains_key(name) {
+                let mut group = Group::find_by_name(&appstate.pool, name).await?;
+                if let Some(found) = &group {
+                    if check_not_ldap_managed(&appstate.pool, found).await.is_err() {
+                        group = None;
+                    }
+                }
+                groups.insert(name.clone(), group);
+            }
+            match &groups[name] {
+                Some(group) => user_groups.push(group.clone()),
+                None => {
+                    invalid_group = Some(name);
+                    break;
+                }
+            }
+        }
+        if let Some(name) = invalid_group {
+            fail(format!("Group {name} doesn't exist or is managed by LDAP"));
+            continue;
+        }
+
+        validated.push((user, user_groups));
+    }
+    if !errors.is_empty() {
+        warn!("Failed to import users, {} rows are invalid", errors.len());
+        return Ok(import_errors_response(errors));
+    }
+
+    let config = server_config();
+    let mut transaction = appstate.pool.begin().await?;
+    let mut created = Vec::with_capacity(validated.len());
+    for (data, user_groups) in validated {
+        let user = User::new(
+            data.username,
+            None,
+            data.last_name,
+            data.first_name,
+            data.email,
+            data.phone,
+        )
+        .save(&mut *transaction)
+        .await?;
+        for group in &user_groups {
+            user.add_to_group(&mut *transaction, group).await?;
+        }
+        created.push((user, user_groups));
+    }
+    for group in groups.values().flatten() {
+        group.check_quota(&mut *transaction).await?;
+    }
+    update_counts(&mut *transaction).await?;
+    WireguardNetwork::sync_all_networks(&mut transaction, &appstate.wireguard_tx).await?;
+
+    let mut enrolled = HashSet::new();
+    if data.send_enrollment_notification {
+        for (user, _) in &mut created {
+            let email = user.email.clone();
+            user.start_enrollment(
+                &mut transaction,
+                &session.user,
+                Some(email),
+                config.enrollment_token_timeout.as_secs(),
+                &EnrollmentTokenOptions::default(),
+                config.enrollment_url.clone(),
+                true,
+                appstate.mail_tx.clone(),
+            )
+            .await?;
+            enrolled.insert(user.id);
+        }
+    }
+
+    transaction.commit().await?;
+
+    info!(
+        "User {} imported {} users",
+        session.user.username,
+        created.len()
+    );
+    let mut users = Vec::with_capacity(created.len());
+    for (user, user_groups) in created {
+        let user_info = UserInfo::from_user(&appstate.pool, &user).await?;
+        appstate.trigger_action(AppEvent::UserCreated(user_info.clone()));
+        users.push(user_info);
+        appstate.emit_event(ApiEvent {
+            context: context.clone(),
+            event: Box::new(ApiEventType::UserAdded { user: user.clone() }),
+        })?;
+        for group in user_groups {
+            appstate.emit_event(ApiEvent {
+                context: context.clone(),
+                event: Box::new(ApiEventType::GroupMemberAdded {
+                    group,
+                    user: user.clone(),
+                }),
+            })?;
+        }
+        if enrolled.contains(&user.id) {
+            appstate.emit_event(ApiEvent {
+                context: context.clone(),
+                event: Box::new(ApiEventType::EnrollmentTokenAdded { user }),
+            })?;
+        }
+    }
+
+    Ok(ApiResponse {
+        json: json!({ "users": users }),
+        status: StatusCode::CREATED,
+    })
+}
+
+/// Validate enrollment token customization and find groups assigned to the token.
+async fn enrollment_token_options(
+    pool: &PgPool,
+    data: &StartEnrollmentRequest,
+) -> Result<EnrollmentTokenOptions, WebError> {
+    let max_uses = data.max_uses.unwrap_or(1);
+    if max_uses < 1 {
+        return Err(WebError::BadRequest(
+            "Maximum number of token uses must be positive".into(),
+        ));
+    }
+    let mut group_ids = Vec::with_capacity(data.groups.len());
+    for name in &data.groups {
+        let Some(group) = Group::find_by_name(pool, name).await? else {
+            return Err(WebError::ObjectNotFound(format!("group {name} not found")));
+        };
+        check_not_ldap_managed(pool, &group).await?;
+        group_ids.push(group.id);
+    }
+    for location_id in &data.locations {
+        if WireguardNetwork::find_by_id(pool, *location_id)
+            .await?
+            .is_none()
+        {
+            return Err(WebError::ObjectNotFound(format!(
+                "location {location_id} not found"
+            )));
+        }
+    }
+
+    Ok(EnrollmentTokenOptions {
+        max_uses,
+        group_ids,
+        location_ids: data.locations.clone(),
+    })
+}
+
 /// Trigger enrollment process manually
 ///
 /// Allows admin to start new enrollment for user that is provided as a parameter in endpoint.
@@ -370,6 +726,9 @@ pub async fn add_user(
 ///
 /// Optionally this endpoint can send an email notification to the user about the enrollment.
 ///
+/// The token can be customized with its expiration time, the number of enrollment sessions which can be started with it,
+/// and groups and locations assigned to the user when enrollment is completed.
+///
 /// # Returns
 /// - JSON with `enrollment_token` and `enrollment_url`
 ///
@@ -383,7 +742,7 @@ pub async fn add_user(
         (status = 400, description = "Bad request, invalid enrollment request.", body = ApiResponse, example = json!({"msg": "Email notification is enabled, but email was not provided"})),
         (status = 401, description = "Unauthorized to start enrollment.", body = ApiResponse, example = json!({"msg": "Session is required"})),
         (status = 403, description = "You don't have permission to start enrollment.", body = ApiResponse, example = json!({"msg": "access denied"})),
-        (status = 404, description = "Provided user does not exist.", body = ApiResponse, example = json!({"msg": "user <username> not found"})),
+        (status = 404, description = "Provided user, group or location does not exist.", body = ApiResponse, example = json!({"msg": "user <username> not found"})),
         (status = 500, description = "Unable to start enrollment.", body = ApiResponse, example = json!({"msg": "unexpected error"}))
     ),
     security(
@@ -425,6 +784,13 @@ pub async fn start_enrollment(
             "user {username} not found"
         )));
     };
+    if user.is_service_account(&appstate.pool).await? {
+        error!("User {username} is a service account, enrollment aborted");
+        return Err(WebError::BadRequest(
+            "Service accounts can't be enrolled".into(),
+        ));
+    }
+    let options = enrollment_token_options(&appstate.pool, &data).await?;
 
     debug!("Create a new database transaction to save a new enrollment token into the database.");
     let mut transaction = appstate.pool.begin().await?;
@@ -447,6 +813,7 @@ pub async fn start_enrollment(
             &session.user,
             data.email,
             token_expiration_time_seconds,
+            &options,
             config.enrollment_url.clone(),
             data.send_enrollment_notification,
             appstate.mail_tx.clone(),
@@ -697,9 +1064,25 @@ pub async fn modify_user(
         }
     }
 
+    // check timezone, empty name resets it to the deployment timezone
+    if let Some(ref timezone) = user_info.timezone {
+        if !timezone.is_empty() && timezone.parse::<Tz>().is_err() {
+            debug!("Invalid timezone for user {username}: {timezone}");
+            return Ok(ApiResponse {
+                json: json!({"msg": format!("Unknown timezone {timezone}")}),
+                status: StatusCode::BAD_REQUEST,
+            });
+        }
+    }
+
     let status_changing = user_info.is_active != user.is_active;
 
     let mut transaction = appstate.pool.begin().await?;
+    // timezone is left unchanged if not sent
+    if let Some(ref timezone) = user_info.timezone {
+        let timezone = (!timezone.is_empty()).then_some(timezone.as_str());
+        user.set_timezone(&mut *transaction, timezone).await?;
+    }
     let ldap_sync_allowed = user.ldap_sync_allowed(&mut *transaction).await?;
 
     // remove authorized apps if needed
@@ -719,7 +1102,7 @@ pub async fn modify_user(
             .await?;
     }
     let mut group_diff = GroupDiff::default();
-    if session.is_admin {
+    if session.can_manage_others() {
         // prevent admin from disabling himself
         if session.user.username == username && !user_info.is_active {
             debug!("Admin {username} attempted to disable himself");
@@ -729,10 +1112,43 @@ pub async fn modify_user(
             });
         }
 
+        // schedule deactivation, e.g. at the end of employment
+        if user_info.deactivate_at != user.deactivate_at(&mut *transaction).await? {
+            if let Some(deactivate_at) = user_info.deactivate_at {
+                if session.user.username == username || deactivate_at <= Utc::now().naive_utc() {
+                    debug!("Invalid deactivation time {deactivate_at} for user {username}");
+                    return Ok(ApiResponse {
+                        json: json!({}),
+                        status: StatusCode::BAD_REQUEST,
+                    });
+                }
+            }
+            user.set_deactivate_at(&mut *transaction, user_info.deactivate_at)
+                .await?;
+        }
+
         // update VPN gateway config if user status or groups have changed
         group_diff = user_info
             .handle_user_groups(&mut transaction, &mut user)
             .await?;
+        // memberships of LDAP-managed groups can only be changed in LDAP
+        for group_name in &group_diff.removed {
+            if let Some(group) = Group::find_by_name(&mut *transaction, group_name).await? {
+                check_not_ldap_managed(&appstate.pool, &group).await?;
+                session
+                    .check_token_target_group(&mut *transaction, &group)
+                    .await?;
+            }
+        }
+        for group_name in &group_diff.added {
+            if let Some(group) = Group::find_by_name(&mut *transaction, group_name).await? {
+                check_not_ldap_managed(&appstate.pool, &group).await?;
+                session
+                    .check_token_target_group(&mut *transaction, &group)
+                    .await?;
+                group.check_quota(&mut *transaction).await?;
+            }
+        }
         if group_diff.changed()
             || user_info
                 .handle_status_change(&mut transaction, &mut user)
@@ -756,6 +1172,41 @@ pub async fn modify_user(
 
         user_info.into_user_all_fields(&mut user)?;
     } else {
+        // Users changing their own email have to confirm the new address first.
+        // The current address stays in use (e.g. for email MFA) until then.
+        if user_info.email != user.email {
+            if User::find_by_email(&mut *transaction, &user_info.email)
+                .await?
+                .is_some_and(|other| other.id != user.id)
+            {
+                debug!("User with email {} already exists", user_info.email);
+                return Ok(ApiResponse {
+                    json: json!({}),
+                    status: StatusCode::BAD_REQUEST,
+                });
+            }
+
+            Token::delete_unused_user_email_verification_tokens(&mut transaction, user.id).await?;
+            let token = Token::new(
+                user.id,
+                None,
+                Some(user_info.email.clone()),
+                server_config().email_verification_token_timeout.as_secs(),
+                Some(EMAIL_VERIFICATION_TOKEN_TYPE.to_string()),
+            );
+            token.save(&mut *transaction).await?;
+            send_email_verification_email(
+                &user_info.email,
+                &appstate.mail_tx,
+                &token.id,
+                Some(&context.ip.to_string()),
+                Some(&context.device),
+            )?;
+            info!(
+                "User {username} requested email change, verification link sent to {}",
+                user_info.email
+            );
+        }
         user_info.into_user_safe_fields(&mut user)?;
     }
     user.save(&mut *transaction).await?;
@@ -904,6 +1355,99 @@ pub async fn delete_user(
     }
 }
 
+/// Verify new email address
+///
+/// Confirms email address change requested by the currently logged in user using the token
+/// from the verification email. Until this is done the previous address stays active.
+///
+/// # Returns
+/// - `WebError` if error occurs
+#[utoipa::path(
+    post,
+    path = "/api/v1/user/verify_email",
+    request_body = EmailVerification,
+    responses(
+        (status = 200, description = "Email address has been changed.", body = ApiResponse, example = json!({})),
+        (status = 400, description = "Bad request, invalid token or email address already in use.", body = ApiResponse, example = json!({"msg": "Invalid email verification token"})),
+        (status = 401, description = "Unauthorized to verify email, or token expired.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 404, description = "Token not found.", body = ApiResponse, example = json!({"msg": "Enrollment token not found"})),
+        (status = 500, description = "Unable to change email address.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub async fn verify_email(
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Json(data): Json<EmailVerification>,
+) -> ApiResult {
+    debug!(
+        "User {} is verifying new email address",
+        session.user.username
+    );
+    let mut token = Token::find_by_id(&appstate.pool, &data.token).await?;
+    if token.token_type.as_deref() != Some(EMAIL_VERIFICATION_TOKEN_TYPE)
+        || token.user_id != session.user.id
+    {
+        debug!(
+            "User {} used invalid email verification token",
+            session.user.username
+        );
+        return Err(WebError::BadRequest(
+            "Invalid email verification token".into(),
+        ));
+    }
+    if token.is_used() {
+        return Err(TokenError::TokenUsed.into());
+    }
+    if token.is_expired() {
+        return Err(TokenError::TokenExpired.into());
+    }
+    let Some(email) = token.email.clone() else {
+        return Err(WebError::BadRequest(
+            "Invalid email verification token".into(),
+        ));
+    };
+
+    let mut transaction = appstate.pool.begin().await?;
+    if User::find_by_email(&mut *transaction, &email)
+        .await?
+        .is_some_and(|other| other.id != session.user.id)
+    {
+        debug!("User with email {email} already exists");
+        return Err(WebError::BadRequest("Email address already in use".into()));
+    }
+
+    let mut user = session.user;
+    let before = user.clone();
+    let username = user.username.clone();
+    let ldap_sync_allowed = user.ldap_sync_allowed(&mut *transaction).await?;
+    user.email = email;
+    user.save(&mut *transaction).await?;
+    token.mark_used(&mut *transaction).await?;
+    transaction.commit().await?;
+
+    if ldap_sync_allowed {
+        ldap_handle_user_modify(&username, &mut user, &appstate.pool).await;
+    }
+
+    let user_info = UserInfo::from_user(&appstate.pool, &user).await?;
+    appstate.trigger_action(AppEvent::UserModified(user_info));
+    info!("User {username} verified new email address {}", user.email);
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::UserModified {
+            before,
+            after: user,
+        }),
+    })?;
+
+    Ok(ApiResponse::default())
+}
+
 /// Change your own password
 ///
 /// Changes your own password basing on `PasswordChangeSelf` object.
@@ -942,16 +1486,13 @@ pub async fn change_self_password(
         });
     }
 
-    if let Err(err) = check_password_strength(&data.new_password) {
-        debug!("User {} password change failed: {err}", user.username);
-        return Ok(ApiResponse {
-            json: json!({}),
-            status: StatusCode::BAD_REQUEST,
-        });
-    }
+    check_password_policy(&appstate.pool, &data.new_password, Some(&user)).await?;
 
     user.set_password(&data.new_password);
-    user.save(&appstate.pool).await?;
+    let mut transaction = appstate.pool.begin().await?;
+    user.save(&mut *transaction).await?;
+    user.remember_password(&mut transaction).await?;
+    transaction.commit().await?;
 
     ldap_change_password(&mut user, &data.new_password, &appstate.pool).await;
 
@@ -1018,13 +1559,6 @@ pub async fn change_password(
         });
     }
 
-    if let Err(err) = check_password_strength(&data.new_password) {
-        debug!("Password for user {username} not strong enough: {err}");
-        return Ok(ApiResponse {
-            json: json!({}),
-            status: StatusCode::BAD_REQUEST,
-        });
-    }
     if let Err(err) = check_username(&username) {
         debug!("Invalid username ({username}): {err}");
         return Ok(ApiResponse {
@@ -1036,8 +1570,12 @@ pub async fn change_password(
     let user = User::find_by_username(&appstate.pool, &username).await?;
 
     if let Some(mut user) = user {
+        check_password_policy(&appstate.pool, &data.new_password, Some(&user)).await?;
         user.set_password(&data.new_password);
-        user.save(&appstate.pool).await?;
+        let mut transaction = appstate.pool.begin().await?;
+        user.save(&mut *transaction).await?;
+        user.remember_password(&mut transaction).await?;
+        transaction.commit().await?;
         ldap_change_password(&mut user, &data.new_password, &appstate.pool).await;
         info!(
             "Admin {} changed password for user {username}",
@@ -1130,6 +1668,7 @@ pub async fn reset_password(
                 None,
                 None,
             )?,
+            plain_content: None,
             attachments: Vec::new(),
             result_tx: None,
         };
@@ -1236,12 +1775,21 @@ pub async fn delete_security_key(
     }
 }
 
+/// Current session user data, with details of impersonation if an admin is impersonating the user.
+#[derive(Serialize)]
+struct MeInfo {
+    #[serde(flatten)]
+    user_info: UserInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    impersonation: Option<ImpersonationInfo>,
+}
+
 /// Returns your data
 ///
 /// Endpoint returns the data associated with the current session user
 ///
 /// # Returns
-/// - `UserInfo` object
+/// - `UserInfo` object, along with `impersonation` details while an admin is impersonating the user
 ///
 /// - `WebError` if error occurs
 #[utoipa::path(
@@ -1281,7 +1829,10 @@ pub async fn delete_security_key(
 pub async fn me(session: SessionInfo, State(appstate): State<AppState>) -> ApiResult {
     let user_info = UserInfo::from_user(&appstate.pool, &session.user).await?;
     Ok(ApiResponse {
-        json: json!(user_info),
+        json: json!(MeInfo {
+            user_info,
+            impersonation: session.impersonation,
+        }),
         status: StatusCode::OK,
     })
 }
@@ -1351,6 +1902,90 @@ pub async fn delete_authorized_app(
     }
 }
 
+/// Get notification settings
+///
+/// Returns email notification preferences of a user.
+///
+/// # Returns
+/// - `NotificationSettings` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    get,
+    path = "/api/v1/user/{username}/notifications",
+    params(
+        ("username" = String, description = "Name of a user"),
+    ),
+    responses(
+        (status = 200, description = "Notification settings of the user.", body = NotificationSettings, example = json!({"email_digest": false})),
+        (status = 401, description = "Unauthorized to return notification settings.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to return notification settings.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 500, description = "Unable to return notification settings.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub async fn get_notification_settings(
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    Path(username): Path<String>,
+) -> ApiResult {
+    let user = user_for_admin_or_self(&appstate.pool, &session, &username).await?;
+    let settings = NotificationSettings::find_by_user(&appstate.pool, user.id).await?;
+
+    Ok(ApiResponse {
+        json: json!(settings),
+        status: StatusCode::OK,
+    })
+}
+
+/// Change notification settings
+///
+/// Non-critical notifications, like new device login notices, can be collected into a daily
+/// digest instead of being sent right away.
+///
+/// # Returns
+/// - `WebError` if error occurs
+#[utoipa::path(
+    put,
+    path = "/api/v1/user/{username}/notifications",
+    params(
+        ("username" = String, description = "Name of a user"),
+    ),
+    request_body = NotificationSettings,
+    responses(
+        (status = 200, description = "Notification settings changed."),
+        (status = 401, description = "Unauthorized to change notification settings.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to change notification settings.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 500, description = "Unable to change notification settings.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub async fn set_notification_settings(
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    Path(username): Path<String>,
+    Json(data): Json<NotificationSettings>,
+) -> ApiResult {
+    debug!(
+        "User {} changing notification settings of user {username}",
+        session.user.username
+    );
+    let user = user_for_admin_or_self(&appstate.pool, &session, &username).await?;
+    data.save_for_user(&appstate.pool, user.id).await?;
+    info!(
+        "User {} changed notification settings of user {username}, email digest: {}",
+        session.user.username, data.email_digest
+    );
+
+    Ok(ApiResponse::default())
+}
+
 #[cfg(test)]
 mod test {
     use claims::{assert_err, assert_ok};
@@ -0,0 +1,77 @@
+use axum::extract::{Path, Query, State};
+use defguard_common::db::Id;
+
+use super::{
+    DEFAULT_API_PAGE_SIZE, device_for_admin_or_self,
+    pagination::{
+        PaginatedApiResponse, PaginatedApiResult, PaginationParams, get_pagination_metadata,
+    },
+    user_for_admin_or_self,
+};
+use crate::{appstate::AppState, auth::SessionInfo, db::models::vpn_session::VpnSessionInfo};
+
+// limit and offset of the requested page
+fn page_bounds(page: u32) -> (i64, i64) {
+    let limit = i64::from(DEFAULT_API_PAGE_SIZE);
+    (limit, i64::from(page.max(1) - 1) * limit)
+}
+
+/// List VPN sessions of the user's devices, most recent first.
+///
+/// Admins can list sessions of any user, other users only their own.
+///
+/// # Returns
+/// Returns a paginated list of `VpnSessionInfo` objects or `WebError` if error occurs.
+pub(crate) async fn list_user_vpn_sessions(
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    Path(username): Path<String>,
+    pagination: Query<PaginationParams>,
+) -> PaginatedApiResult<VpnSessionInfo> {
+    debug!(
+        "Listing VPN sessions of user {username}, page {}",
+        pagination.page
+    );
+    let user = user_for_admin_or_self(&appstate.pool, &session, &username).await?;
+    let (limit, offset) = page_bounds(pagination.page);
+    let data = VpnSessionInfo::find_by_user(&appstate.pool, user.id, limit, offset).await?;
+    let total_items = VpnSessionInfo::count_by_user(&appstate.pool, user.id).await?;
+    debug!("Listed {} VPN sessions of user {username}", data.len());
+
+    Ok(PaginatedApiResponse {
+        data,
+        pagination: get_pagination_metadata(pagination.page, total_items as u32, None),
+    })
+}
+
+/// List VPN sessions of the device, most recent first.
+///
+/// Admins can list sessions of any device, other users only of their own devices.
+///
+/// # Returns
+/// Returns a paginated list of `VpnSessionInfo` objects or `WebError` if error occurs.
+pub(crate) async fn list_device_vpn_sessions(
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    Path(device_id): Path<Id>,
+    pagination: Query<PaginationParams>,
+) -> PaginatedApiResult<VpnSessionInfo> {
+    debug!(
+        "Listing VPN sessions of device {device_id}, page {}",
+        pagination.page
+    );
+    let device = device_for_admin_or_self(&appstate.pool, &session, device_id).await?;
+    let (limit, offset) = page_bounds(pagination.page);
+    let data = VpnSessionInfo::find_by_device(&appstate.pool, device.id, limit, offset).await?;
+    let total_items = VpnSessionInfo::count_by_device(&appstate.pool, device.id).await?;
+    debug!(
+        "Listed {} VPN sessions of device {}",
+        data.len(),
+        device.name
+    );
+
+    Ok(PaginatedApiResponse {
+        data,
+        pagination: get_pagination_metadata(pagination.page, total_items as u32, None),
+    })
+}
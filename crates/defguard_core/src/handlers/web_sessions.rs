@@ -0,0 +1,133 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::NaiveDateTime;
+use serde_json::json;
+
+use super::{ApiResponse, ApiResult, user_for_admin_or_self};
+use crate::{
+    appstate::AppState,
+    auth::SessionInfo,
+    db::Session,
+    error::WebError,
+    events::{ApiEvent, ApiEventType, ApiRequestContext},
+};
+
+/// Web UI session, as returned by the API.
+#[derive(Debug, Serialize)]
+pub struct WebSessionInfo {
+    /// Session identifier, derived from the session cookie but not revealing it.
+    pub id: String,
+    pub created: NaiveDateTime,
+    pub last_seen: NaiveDateTime,
+    pub expires: NaiveDateTime,
+    pub ip_address: String,
+    pub device_info: Option<String>,
+    /// Whether this is the session used to make the request.
+    pub current: bool,
+}
+
+impl WebSessionInfo {
+    fn new(session: Session, current_session: &Session) -> Self {
+        Self {
+            id: session.public_id(),
+            current: session.id == current_session.id,
+            created: session.created,
+            last_seen: session.last_seen,
+            expires: session.expires,
+            ip_address: session.ip_address,
+            device_info: session.device_info,
+        }
+    }
+}
+
+/// List active web UI sessions of the user, most recently used first.
+///
+/// Admins can list sessions of any user, other users only their own.
+pub(crate) async fn list_web_sessions(
+    session: SessionInfo,
+    State(appstate): State<AppState>,
+    Path(username): Path<String>,
+) -> ApiResult {
+    debug!("Listing web sessions of user {username}");
+    let user = user_for_admin_or_self(&appstate.pool, &session, &username).await?;
+    let sessions: Vec<_> = Session::find_active_by_user(&appstate.pool, user.id)
+        .await?
+        .into_iter()
+        .map(|user_session| WebSessionInfo::new(user_session, &session.session))
+        .collect();
+    debug!("Listed {} web sessions of user {username}", sessions.len());
+
+    Ok(ApiResponse {
+        json: json!(sessions),
+        status: StatusCode::OK,
+    })
+}
+
+/// Revoke a single web UI session of the user, logging it out.
+///
+/// Admins can revoke sessions of any user, other users only their own.
+pub(crate) async fn revoke_web_session(
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path((username, session_id)): Path<(String, String)>,
+) -> ApiResult {
+    debug!("Revoking web session {session_id} of user {username}");
+    let user = user_for_admin_or_self(&appstate.pool, &session, &username).await?;
+    let Some(user_session) = Session::find_active_by_user(&appstate.pool, user.id)
+        .await?
+        .into_iter()
+        .find(|user_session| user_session.public_id() == session_id)
+    else {
+        return Err(WebError::ObjectNotFound(format!(
+            "session {session_id} not found"
+        )));
+    };
+    let ip_address = user_session.ip_address.clone();
+    let device_info = user_session.device_info.clone();
+    user_session.delete(&appstate.pool).await?;
+    info!(
+        "User {} revoked web session of user {username} started from {ip_address}",
+        session.user.username
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::UserSessionRevoked {
+            user,
+            ip_address,
+            device_info,
+        }),
+    })?;
+
+    Ok(ApiResponse::default())
+}
+
+/// Revoke all web UI sessions of the user, except for the session used to make the request.
+///
+/// Admins can revoke sessions of any user, other users only their own.
+pub(crate) async fn revoke_all_web_sessions(
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(username): Path<String>,
+) -> ApiResult {
+    debug!("Revoking all web sessions of user {username}");
+    let user = user_for_admin_or_self(&appstate.pool, &session, &username).await?;
+    let count =
+        Session::delete_all_for_user_except(&appstate.pool, user.id, &session.session.id).await?;
+    info!(
+        "User {} revoked {count} web sessions of user {username}",
+        session.user.username
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::UserSessionsRevoked { user, count }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!({ "count": count }),
+        status: StatusCode::OK,
+    })
+}
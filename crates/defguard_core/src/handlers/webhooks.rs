@@ -2,16 +2,34 @@ use axum::{
     extract::{Json, Path, State},
     http::StatusCode,
 };
+use defguard_common::db::Id;
 use serde_json::json;
+use sqlx::PgPool;
 
 use super::{ApiResponse, ApiResult, WebHookData};
 use crate::{
     appstate::AppState,
     auth::{AdminRole, SessionInfo},
     db::WebHook,
+    error::WebError,
     events::{ApiEvent, ApiEventType, ApiRequestContext},
 };
 
+/// Webhook along with locations it receives client connection events from.
+#[derive(Deserialize, Serialize)]
+pub struct WebHookInfo {
+    #[serde(flatten)]
+    pub webhook: WebHook<Id>,
+    pub locations: Vec<Id>,
+}
+
+impl WebHookInfo {
+    async fn from_webhook(pool: &PgPool, webhook: WebHook<Id>) -> Result<Self, WebError> {
+        let locations = webhook.locations(pool).await?;
+        Ok(Self { webhook, locations })
+    }
+}
+
 pub async fn add_webhook(
     _admin: AdminRole,
     session: SessionInfo,
@@ -20,10 +38,14 @@ pub async fn add_webhook(
     Json(webhookdata): Json<WebHookData>,
 ) -> ApiResult {
     let url = webhookdata.url.clone();
+    let locations = webhookdata.locations.clone();
     debug!("User {} adding webhook {url}", session.user.username);
     let webhook: WebHook = webhookdata.into();
-    let status = match webhook.save(&appstate.pool).await {
+    let mut transaction = appstate.pool.begin().await?;
+    let status = match webhook.save(&mut *transaction).await {
         Ok(webhook) => {
+            webhook.set_locations(&mut transaction, &locations).await?;
+            transaction.commit().await?;
             info!("User {} added webhook {url}", session.user.username);
             appstate.emit_event(ApiEvent {
                 context,
@@ -42,7 +64,10 @@ pub async fn add_webhook(
 
 // TODO: paginate
 pub async fn list_webhooks(_admin: AdminRole, State(appstate): State<AppState>) -> ApiResult {
-    let webhooks = WebHook::all(&appstate.pool).await?;
+    let mut webhooks = Vec::new();
+    for webhook in WebHook::all(&appstate.pool).await? {
+        webhooks.push(WebHookInfo::from_webhook(&appstate.pool, webhook).await?);
+    }
 
     Ok(ApiResponse {
         json: json!(webhooks),
@@ -57,7 +82,7 @@ pub async fn get_webhook(
 ) -> ApiResult {
     match WebHook::find_by_id(&appstate.pool, id).await? {
         Some(webhook) => Ok(ApiResponse {
-            json: json!(webhook),
+            json: json!(WebHookInfo::from_webhook(&appstate.pool, webhook).await?),
             status: StatusCode::OK,
         }),
         None => Ok(ApiResponse {
@@ -88,7 +113,16 @@ pub async fn change_webhook(
             webhook.on_user_deleted = data.on_user_deleted;
             webhook.on_user_modified = data.on_user_modified;
             webhook.on_hwkey_provision = data.on_hwkey_provision;
-            webhook.save(&appstate.pool).await?;
+            webhook.on_gateway_disconnected = data.on_gateway_disconnected;
+            webhook.on_client_connected = data.on_client_connected;
+            webhook.on_client_disconnected = data.on_client_disconnected;
+            webhook.secret = data.secret;
+            let mut transaction = appstate.pool.begin().await?;
+            webhook.save(&mut *transaction).await?;
+            webhook
+                .set_locations(&mut transaction, &data.locations)
+                .await?;
+            transaction.commit().await?;
             info!("User {} updated webhook {id}", session.user.username);
             appstate.emit_event(ApiEvent {
                 context,
@@ -1,5 +1,6 @@
 use std::{
     collections::HashSet,
+    iter::zip,
     net::IpAddr,
     str::FromStr,
     sync::{Arc, Mutex},
@@ -7,7 +8,7 @@ use std::{
 
 use axum::{
     Extension,
-    extract::{Json, Path, Query, State},
+    extract::{Json, Multipart, Path, Query, State},
     http::StatusCode,
 };
 use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
@@ -24,17 +25,26 @@ use crate::{
     appstate::AppState,
     auth::{AdminRole, SessionInfo},
     db::{
-        AddDevice, Device, GatewayEvent, WireguardNetwork,
+        AddDevice, Device, GatewayEvent, User, WireguardNetwork,
         models::{
+            client_login_session::ClientLoginSession,
             device::{
-                DeviceConfig, DeviceInfo, DeviceNetworkInfo, DeviceType, ModifyDevice,
+                DeviceConfig, DeviceError, DeviceInfo, DeviceNetworkInfo, DeviceType, ModifyDevice,
                 WireguardNetworkDevice,
             },
+            device_approval::DeviceApproval,
+            device_key_history::DeviceKeyHistory,
+            device_profile::{DeviceProfile, apply_device_profile},
+            gateway_token::GatewayToken,
+            group::{NetworkAccessWindow, NetworkGroupPool, check_device_quota},
+            trusted_device::TrustedDevice,
             wireguard::{
-                DateTimeAggregation, LocationMfaMode, MappedDevice, ServiceLocationMode,
-                WireguardDeviceStatsRow, WireguardNetworkInfo, WireguardNetworkStats,
-                WireguardUserStatsRow, networks_stats,
+                DateTimeAggregation, IpAssignmentStrategy, LocationMaintenance, LocationMfaMethod,
+                LocationMfaMode, MappedDevice, ServiceLocationMode, WireguardDeviceStatsRow,
+                WireguardNetworkInfo, WireguardNetworkStats, WireguardUserStatsRow, networks_stats,
+                translate_ip,
             },
+            wireguard_peer_stats::{StatsResolution, WireguardPeerStats},
         },
     },
     enterprise::{
@@ -44,8 +54,8 @@ use crate::{
         limits::update_counts,
     },
     events::{ApiEvent, ApiEventType, ApiRequestContext},
-    grpc::gateway::map::GatewayMap,
-    handlers::mail::send_new_device_added_email,
+    grpc::gateway::{map::GatewayMap, state::GatewayState},
+    handlers::{mail::send_new_device_added_email, upload::UploadLimit},
     server_config,
     wg_config::{ImportedDevice, parse_wireguard_config},
 };
@@ -79,7 +89,7 @@ pub struct WireguardNetworkData {
     pub endpoint: String,
     pub port: i32,
     pub allowed_ips: Option<String>,
-    pub dns: Option<String>,
+    pub dns: Option<String>, // comma-separated list of DNS servers
     pub allowed_groups: Vec<String>,
     pub keepalive_interval: i32,
     pub peer_disconnect_threshold: i32,
@@ -87,6 +97,67 @@ pub struct WireguardNetworkData {
     pub acl_default_allow: bool,
     pub location_mfa_mode: LocationMfaMode,
     pub service_location_mode: ServiceLocationMode,
+    // Keep current strategy on modification if not provided.
+    #[serde(default)]
+    pub ip_assignment_strategy: Option<IpAssignmentStrategy>,
+    // Keep current group pools on modification if not provided.
+    #[serde(default)]
+    pub group_pools: Option<Vec<NetworkGroupPool>>,
+    // Keep current access windows on modification if not provided.
+    #[serde(default)]
+    pub access_windows: Option<Vec<NetworkAccessWindow>>,
+    // Keep current MFA methods on modification if not provided.
+    #[serde(default)]
+    pub allowed_mfa_methods: Option<Vec<LocationMfaMethod>>,
+    // Keep current device trust policy on modification if not provided.
+    #[serde(default)]
+    pub mfa_device_trust_enabled: Option<bool>,
+    // Keep current preshared key rotation interval (in hours) on modification if not provided,
+    // 0 disables the rotation.
+    #[serde(default)]
+    pub psk_rotation_interval: Option<i32>,
+    // Keep current DNS search domains on modification if not provided.
+    #[serde(default)]
+    pub dns_search_domains: Option<Vec<String>>,
+    // Keep current device approval requirement on modification if not provided.
+    #[serde(default)]
+    pub device_approval_required: Option<bool>,
+}
+
+// Checks whether the value is a valid DNS domain name.
+fn is_valid_domain(domain: &str) -> bool {
+    let domain = domain.strip_suffix('.').unwrap_or(domain);
+    !domain.is_empty()
+        && domain.len() <= 253
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+// Checks a comma-separated list of DNS servers. Domains are accepted as well, since WireGuard
+// configuration treats them as search domains.
+fn validate_dns_servers(dns: &str) -> Result<(), WebError> {
+    for server in dns.split(',').map(str::trim) {
+        if server.parse::<IpAddr>().is_err() && !is_valid_domain(server) {
+            return Err(WebError::BadRequest(format!("Invalid DNS server {server}")));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_dns_search_domains(domains: &[String]) -> Result<(), WebError> {
+    if let Some(domain) = domains.iter().find(|domain| !is_valid_domain(domain)) {
+        return Err(WebError::BadRequest(format!(
+            "Invalid DNS search domain {domain}"
+        )));
+    }
+
+    Ok(())
 }
 
 impl WireguardNetworkData {
@@ -119,6 +190,118 @@ impl WireguardNetworkData {
         Ok(subnets)
     }
 
+    /// Ensure group pools refer to allowed groups and fit within network addresses.
+    pub(crate) fn validate_group_pools(&self, addresses: &[IpNetwork]) -> Result<(), WebError> {
+        for pool in self.group_pools.iter().flatten() {
+            if !self.allowed_groups.contains(&pool.group) {
+                return Err(WebError::BadRequest(format!(
+                    "Group {} is not allowed in the network",
+                    pool.group
+                )));
+            }
+            for subnet in &pool.address_pool {
+                if !addresses.iter().any(|address| {
+                    address.contains(subnet.network()) && address.prefix() <= subnet.prefix()
+                }) {
+                    return Err(WebError::BadRequest(format!(
+                        "Address pool {subnet} of group {} is outside of network addresses",
+                        pool.group
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ensure access windows refer to allowed groups of an MFA-protected network. Windows are
+    /// enforced when authorizing devices, so they have no effect without MFA.
+    pub(crate) fn validate_access_windows(&self) -> Result<(), WebError> {
+        let windows = self.access_windows.as_deref().unwrap_or_default();
+        if !windows.is_empty() && self.location_mfa_mode == LocationMfaMode::Disabled {
+            return Err(WebError::BadRequest(
+                "Access windows require location MFA to be enabled".into(),
+            ));
+        }
+        for window in windows {
+            if !self.allowed_groups.contains(&window.group) {
+                return Err(WebError::BadRequest(format!(
+                    "Group {} is not allowed in the network",
+                    window.group
+                )));
+            }
+            if window.weekdays.is_empty()
+                || window
+                    .weekdays
+                    .iter()
+                    .any(|weekday| !(1..=7).contains(weekday))
+            {
+                return Err(WebError::BadRequest(format!(
+                    "Access window of group {} has invalid weekdays",
+                    window.group
+                )));
+            }
+            if window.start_time == window.end_time {
+                return Err(WebError::BadRequest(format!(
+                    "Access window of group {} is empty",
+                    window.group
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ensure MFA methods are only restricted for locations using internal MFA.
+    pub(crate) fn validate_allowed_mfa_methods(&self) -> Result<(), WebError> {
+        let methods = self.allowed_mfa_methods.as_deref().unwrap_or_default();
+        if !methods.is_empty() && self.location_mfa_mode != LocationMfaMode::Internal {
+            return Err(WebError::BadRequest(
+                "Allowed MFA methods require internal location MFA".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Ensure DNS servers and search domains of the network and its group pools are valid.
+    /// Empty DNS is allowed and disables DNS in generated configuration.
+    pub(crate) fn validate_dns(&self) -> Result<(), WebError> {
+        let servers = self
+            .group_pools
+            .iter()
+            .flatten()
+            .filter_map(|pool| pool.dns.as_deref())
+            .chain(self.dns.as_deref());
+        for dns in servers.filter(|dns| !dns.trim().is_empty()) {
+            validate_dns_servers(dns)?;
+        }
+        validate_dns_search_domains(self.dns_search_domains.as_deref().unwrap_or_default())?;
+        for pool in self.group_pools.iter().flatten() {
+            validate_dns_search_domains(&pool.dns_search_domains)?;
+        }
+
+        Ok(())
+    }
+
+    /// Ensure preshared keys are only rotated in locations without MFA, where MFA logins
+    /// already generate a new key for each session.
+    pub(crate) fn validate_psk_rotation_interval(&self) -> Result<(), WebError> {
+        match self.psk_rotation_interval {
+            Some(interval) if interval < 0 => Err(WebError::BadRequest(
+                "Preshared key rotation interval can't be negative".into(),
+            )),
+            Some(interval)
+                if interval > 0 && self.location_mfa_mode != LocationMfaMode::Disabled =>
+            {
+                Err(WebError::BadRequest(
+                    "Preshared key rotation requires location MFA to be disabled".into(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub(crate) async fn validate_location_mfa_mode<'e, E: sqlx::PgExecutor<'e>>(
         &self,
         executor: E,
@@ -207,9 +390,14 @@ pub(crate) async fn create_network(
     );
 
     data.validate_location_mfa_mode(&appstate.pool).await?;
+    data.validate_group_pools(&parse_address_list(&data.address))?;
+    data.validate_access_windows()?;
+    data.validate_allowed_mfa_methods()?;
+    data.validate_psk_rotation_interval()?;
+    data.validate_dns()?;
 
     let allowed_ips = data.parse_allowed_ips();
-    let network = WireguardNetwork::new(
+    let mut network = WireguardNetwork::new(
         data.name,
         parse_address_list(&data.address),
         data.port,
@@ -223,12 +411,48 @@ pub(crate) async fn create_network(
         data.location_mfa_mode,
         data.service_location_mode,
     );
+    network.ip_assignment_strategy = data.ip_assignment_strategy.unwrap_or_default();
 
     let mut transaction = appstate.pool.begin().await?;
     let network = network.save(&mut *transaction).await?;
     network
         .set_allowed_groups(&mut transaction, data.allowed_groups)
         .await?;
+    if let Some(group_pools) = data.group_pools {
+        network
+            .set_group_pools(&mut transaction, &group_pools)
+            .await?;
+    }
+    if let Some(access_windows) = data.access_windows {
+        network
+            .set_access_windows(&mut transaction, &access_windows)
+            .await?;
+    }
+    if let Some(allowed_mfa_methods) = data.allowed_mfa_methods {
+        network
+            .set_allowed_mfa_methods(&mut *transaction, &allowed_mfa_methods)
+            .await?;
+    }
+    if let Some(enabled) = data.mfa_device_trust_enabled {
+        network
+            .set_mfa_device_trust_enabled(&mut *transaction, enabled)
+            .await?;
+    }
+    if let Some(required) = data.device_approval_required {
+        network
+            .set_device_approval_required(&mut *transaction, required)
+            .await?;
+    }
+    if let Some(interval) = data.psk_rotation_interval.filter(|interval| *interval > 0) {
+        network
+            .set_psk_rotation_interval(&mut *transaction, Some(interval))
+            .await?;
+    }
+    if let Some(domains) = &data.dns_search_domains {
+        network
+            .set_dns_search_domains(&mut *transaction, domains)
+            .await?;
+    }
 
     // generate IP addresses for existing devices
     network.add_all_allowed_devices(&mut transaction).await?;
@@ -257,6 +481,198 @@ pub(crate) async fn create_network(
     })
 }
 
+#[derive(Deserialize, ToSchema)]
+pub struct CloneNetworkData {
+    pub name: String,
+    /// Comma-separated list of addresses, matching subnets of the cloned location by position
+    /// and IP version.
+    pub address: String,
+    // Use endpoint of the cloned location if not provided.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    // Use port of the cloned location if not provided.
+    #[serde(default)]
+    pub port: Option<i32>,
+    /// Keep device addresses at the same offsets in the new subnets, instead of assigning them
+    /// from scratch.
+    #[serde(default)]
+    pub copy_device_assignments: bool,
+}
+
+// Move address pools of groups to the same offsets in the subnets of the cloned location.
+fn translate_group_pools(
+    pools: Vec<NetworkGroupPool>,
+    from: &[IpNetwork],
+    to: &[IpNetwork],
+) -> Result<Vec<NetworkGroupPool>, WebError> {
+    pools
+        .into_iter()
+        .map(|mut pool| {
+            let mut address_pool = Vec::with_capacity(pool.address_pool.len());
+            for range in &pool.address_pool {
+                let translated = translate_ip(range.network(), from, to)
+                    .and_then(|ip| IpNetwork::new(ip, range.prefix()).ok())
+                    .filter(|translated| {
+                        to.iter().any(|address| {
+                            address.contains(translated.network())
+                                && address.contains(translated.broadcast())
+                        })
+                    })
+                    .ok_or_else(|| {
+                        WebError::BadRequest(format!(
+                            "Address pool {range} of group {} doesn't fit in {}",
+                            pool.group,
+                            to.as_csv()
+                        ))
+                    })?;
+                address_pool.push(translated);
+            }
+            pool.address_pool = address_pool;
+            Ok(pool)
+        })
+        .collect()
+}
+
+/// Clone network
+///
+/// Create a new network with settings of network `network_id`: allowed groups with their
+/// overrides, MFA requirements, access windows, DNS, keepalive, port and endpoint, under a new
+/// name and addresses. Firewall rules, posture policies and network devices are not copied.
+///
+/// # Returns
+/// - `WireguardNetwork` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    post,
+    path = "/api/v1/network/{network_id}/clone",
+    params(
+        ("network_id" = Id, description = "ID of network to clone")
+    ),
+    request_body = CloneNetworkData,
+    responses(
+        (status = 201, description = "Successfully cloned network.", body = WireguardNetwork),
+        (status = 400, description = "Addresses don't match the cloned network.", body = ApiResponse, example = json!({"msg": "Addresses have to match subnets of location <name> (10.1.1.1/24)"})),
+        (status = 401, description = "Unauthorized to clone network.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to clone a network.", body = ApiResponse, example = json!({"msg": "access denied"})),
+        (status = 404, description = "Network not found", body = ApiResponse, example = json!({"msg": "network not found"})),
+        (status = 500, description = "Unable to clone network.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn clone_network(
+    _role: AdminRole,
+    Path(network_id): Path<Id>,
+    State(appstate): State<AppState>,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    Json(data): Json<CloneNetworkData>,
+) -> ApiResult {
+    debug!(
+        "User {} cloning WireGuard network {network_id} as {}",
+        session.user.username, data.name
+    );
+    let source = find_network(network_id, &appstate.pool).await?;
+    let address = parse_address_list(&data.address);
+    let matching = address.len() == source.address.len()
+        && zip(&address, &source.address).all(|(new, old)| new.is_ipv4() == old.is_ipv4());
+    if !matching {
+        return Err(WebError::BadRequest(format!(
+            "Addresses have to match subnets of location {source} ({})",
+            source.address.as_csv()
+        )));
+    }
+    let group_pools = translate_group_pools(
+        source.fetch_group_pools(&appstate.pool).await?,
+        &source.address,
+        &address,
+    )?;
+    let allowed_groups = source.fetch_allowed_groups(&appstate.pool).await?;
+    let access_windows = source.fetch_access_windows(&appstate.pool).await?;
+    let allowed_mfa_methods = source.allowed_mfa_methods(&appstate.pool).await?;
+    let mfa_device_trust_enabled = source.mfa_device_trust_enabled(&appstate.pool).await?;
+    let psk_rotation_interval = source.psk_rotation_interval(&appstate.pool).await?;
+    let dns_search_domains = source.dns_search_domains(&appstate.pool).await?;
+    let device_approval_required = source.device_approval_required(&appstate.pool).await?;
+
+    let mut network = WireguardNetwork::new(
+        data.name,
+        address,
+        data.port.unwrap_or(source.port),
+        data.endpoint.unwrap_or_else(|| source.endpoint.clone()),
+        source.dns.clone(),
+        source.allowed_ips.clone(),
+        source.keepalive_interval,
+        source.peer_disconnect_threshold,
+        source.acl_enabled,
+        source.acl_default_allow,
+        source.location_mfa_mode.clone(),
+        source.service_location_mode.clone(),
+    );
+    network.ip_assignment_strategy = source.ip_assignment_strategy;
+
+    let mut transaction = appstate.pool.begin().await?;
+    let network = network.save(&mut *transaction).await?;
+    network
+        .set_allowed_groups(&mut transaction, allowed_groups)
+        .await?;
+    network
+        .set_group_pools(&mut transaction, &group_pools)
+        .await?;
+    network
+        .set_access_windows(&mut transaction, &access_windows)
+        .await?;
+    network
+        .set_allowed_mfa_methods(&mut *transaction, &allowed_mfa_methods)
+        .await?;
+    network
+        .set_mfa_device_trust_enabled(&mut *transaction, mfa_device_trust_enabled)
+        .await?;
+    network
+        .set_psk_rotation_interval(&mut *transaction, psk_rotation_interval)
+        .await?;
+    network
+        .set_dns_search_domains(&mut *transaction, &dns_search_domains)
+        .await?;
+    network
+        .set_device_approval_required(&mut *transaction, device_approval_required)
+        .await?;
+
+    // generate IP addresses for existing devices
+    if data.copy_device_assignments {
+        network
+            .add_all_allowed_devices_from(&mut transaction, &source)
+            .await?;
+    } else {
+        network.add_all_allowed_devices(&mut transaction).await?;
+    }
+
+    appstate.send_wireguard_event(GatewayEvent::NetworkCreated(network.id, network.clone()));
+
+    transaction.commit().await?;
+
+    info!(
+        "User {} cloned WireGuard network {source} as {network}",
+        session.user.username
+    );
+
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::VpnLocationAdded {
+            location: network.clone(),
+        }),
+    })?;
+    update_counts(&appstate.pool).await?;
+
+    Ok(ApiResponse {
+        json: json!(network),
+        status: StatusCode::CREATED,
+    })
+}
+
 async fn find_network(id: Id, pool: &PgPool) -> Result<WireguardNetwork<Id>, WebError> {
     WireguardNetwork::find_by_id(pool, id)
         .await?
@@ -305,6 +721,11 @@ pub(crate) async fn modify_network(
     // store network before mods
     let before = network.clone();
     network.address = data.parse_addresses()?;
+    data.validate_group_pools(&network.address)?;
+    data.validate_access_windows()?;
+    data.validate_allowed_mfa_methods()?;
+    data.validate_psk_rotation_interval()?;
+    data.validate_dns()?;
 
     network.allowed_ips = data.parse_allowed_ips();
     network.name = data.name;
@@ -330,11 +751,70 @@ pub(crate) async fn modify_network(
         }
     };
     network.location_mfa_mode = data.location_mfa_mode;
+    if let Some(ip_assignment_strategy) = data.ip_assignment_strategy {
+        network.ip_assignment_strategy = ip_assignment_strategy;
+    }
 
     network.save(&mut *transaction).await?;
     network
         .set_allowed_groups(&mut transaction, data.allowed_groups)
         .await?;
+    if let Some(group_pools) = data.group_pools {
+        network
+            .set_group_pools(&mut transaction, &group_pools)
+            .await?;
+    }
+    if let Some(access_windows) = data.access_windows {
+        network
+            .set_access_windows(&mut transaction, &access_windows)
+            .await?;
+    }
+    if let Some(allowed_mfa_methods) = data.allowed_mfa_methods {
+        network
+            .set_allowed_mfa_methods(&mut *transaction, &allowed_mfa_methods)
+            .await?;
+    }
+    if let Some(enabled) = data.mfa_device_trust_enabled {
+        network
+            .set_mfa_device_trust_enabled(&mut *transaction, enabled)
+            .await?;
+        if !enabled {
+            TrustedDevice::delete_for_location(&mut *transaction, network.id).await?;
+        }
+    }
+    if let Some(interval) = data.psk_rotation_interval {
+        if interval > 0 {
+            network
+                .set_psk_rotation_interval(&mut *transaction, Some(interval))
+                .await?;
+        } else if network
+            .psk_rotation_interval(&mut *transaction)
+            .await?
+            .is_some()
+        {
+            network
+                .set_psk_rotation_interval(&mut *transaction, None)
+                .await?;
+            // peers of locations without MFA stop using preshared keys
+            if !network.mfa_enabled() {
+                network.clear_preshared_keys(&mut *transaction).await?;
+            }
+        }
+    }
+    if let Some(domains) = &data.dns_search_domains {
+        network
+            .set_dns_search_domains(&mut *transaction, domains)
+            .await?;
+    }
+    if let Some(required) = data.device_approval_required {
+        network
+            .set_device_approval_required(&mut *transaction, required)
+            .await?;
+        // devices waiting for a decision, or rejected, can connect again
+        if !required {
+            DeviceApproval::delete_for_location(&mut *transaction, network.id).await?;
+        }
+    }
     let _events = network.sync_allowed_devices(&mut transaction, None).await?;
 
     let peers = network.get_peers(&mut *transaction).await?;
@@ -457,6 +937,14 @@ pub(crate) async fn list_networks(
     for network in networks {
         let network_id = network.id;
         let allowed_groups = network.fetch_allowed_groups(&appstate.pool).await?;
+        let group_pools = network.fetch_group_pools(&appstate.pool).await?;
+        let access_windows = network.fetch_access_windows(&appstate.pool).await?;
+        let allowed_mfa_methods = network.allowed_mfa_methods(&appstate.pool).await?;
+        let mfa_device_trust_enabled = network.mfa_device_trust_enabled(&appstate.pool).await?;
+        let psk_rotation_interval = network.psk_rotation_interval(&appstate.pool).await?;
+        let dns_search_domains = network.dns_search_domains(&appstate.pool).await?;
+        let maintenance = network.maintenance(&appstate.pool).await?;
+        let device_approval_required = network.device_approval_required(&appstate.pool).await?;
         {
             let gateway_state = gateway_state
                 .lock()
@@ -466,6 +954,14 @@ pub(crate) async fn list_networks(
                 connected: gateway_state.connected(network_id),
                 gateways: gateway_state.get_network_gateway_status(network_id),
                 allowed_groups,
+                group_pools,
+                access_windows,
+                allowed_mfa_methods,
+                mfa_device_trust_enabled,
+                psk_rotation_interval,
+                dns_search_domains,
+                maintenance,
+                device_approval_required,
             });
         }
     }
@@ -511,6 +1007,14 @@ pub(crate) async fn network_details(
     let response = match network {
         Some(network) => {
             let allowed_groups = network.fetch_allowed_groups(&appstate.pool).await?;
+            let group_pools = network.fetch_group_pools(&appstate.pool).await?;
+            let access_windows = network.fetch_access_windows(&appstate.pool).await?;
+            let allowed_mfa_methods = network.allowed_mfa_methods(&appstate.pool).await?;
+            let mfa_device_trust_enabled = network.mfa_device_trust_enabled(&appstate.pool).await?;
+            let psk_rotation_interval = network.psk_rotation_interval(&appstate.pool).await?;
+            let dns_search_domains = network.dns_search_domains(&appstate.pool).await?;
+            let maintenance = network.maintenance(&appstate.pool).await?;
+            let device_approval_required = network.device_approval_required(&appstate.pool).await?;
             let gateway_state = gateway_state
                 .lock()
                 .expect("Failed to acquire gateway state lock");
@@ -519,6 +1023,14 @@ pub(crate) async fn network_details(
                 connected: gateway_state.connected(network_id),
                 gateways: gateway_state.get_network_gateway_status(network_id),
                 allowed_groups,
+                group_pools,
+                access_windows,
+                allowed_mfa_methods,
+                mfa_device_trust_enabled,
+                psk_rotation_interval,
+                dns_search_domains,
+                maintenance,
+                device_approval_required,
             };
             ApiResponse {
                 json: json!(network_info),
@@ -535,6 +1047,117 @@ pub(crate) async fn network_details(
     Ok(response)
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetLocationMaintenance {
+    pub enabled: bool,
+    /// Message shown to users, e.g. expected end of the maintenance.
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Disconnect devices logged in with MFA when enabling the maintenance.
+    #[serde(default)]
+    pub disconnect_peers: bool,
+}
+
+/// Set location maintenance
+///
+/// Location maintenance allows draining a location, e.g. before gateway upgrades. While it's
+/// enabled, new desktop client MFA logins to the location are rejected and the maintenance message
+/// is returned along with location details. Devices logged in with MFA can be disconnected when
+/// enabling the maintenance. Peers of locations without MFA stay configured on gateways.
+///
+/// # Returns
+/// - `LocationMaintenance` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    put,
+    path = "/api/v1/network/{network_id}/maintenance",
+    params(
+        ("network_id" = Id, description = "Location ID")
+    ),
+    request_body = SetLocationMaintenance,
+    responses(
+        (status = 200, description = "Location maintenance set.", body = LocationMaintenance, example = json!({"enabled": true, "message": "Gateway upgrade until 18:00"})),
+        (status = 401, description = "Unauthorized to set location maintenance.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to set location maintenance.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Location not found.", body = ApiResponse, example = json!({"msg": "Network <id> not found"})),
+        (status = 500, description = "Cannot set location maintenance.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn set_location_maintenance(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(network_id): Path<Id>,
+    Json(data): Json<SetLocationMaintenance>,
+) -> ApiResult {
+    debug!(
+        "User {} setting maintenance of location {network_id} to {data:?}",
+        session.user.username
+    );
+    let location = find_network(network_id, &appstate.pool).await?;
+    let previous = location.maintenance(&appstate.pool).await?;
+    let maintenance = LocationMaintenance {
+        enabled: data.enabled,
+        message: data
+            .message
+            .map(|message| message.trim().to_string())
+            .filter(|message| !message.is_empty()),
+    };
+
+    let mut transaction = appstate.pool.begin().await?;
+    location
+        .set_maintenance(&mut *transaction, &maintenance)
+        .await?;
+    let revoked = if maintenance.enabled && data.disconnect_peers && location.mfa_enabled() {
+        ClientLoginSession::delete_for_location(&mut *transaction, location.id).await?;
+        location.revoke_mfa_authorizations(&mut transaction).await?
+    } else {
+        Vec::new()
+    };
+    transaction.commit().await?;
+
+    let disconnected = revoked.len();
+    for device_info in revoked {
+        appstate.send_wireguard_event(GatewayEvent::DeviceDeleted(device_info));
+    }
+    let event = if maintenance.enabled {
+        info!(
+            "User {} enabled maintenance of location {location}, disconnected {disconnected} devices",
+            session.user.username
+        );
+        Some(ApiEventType::VpnLocationMaintenanceEnabled {
+            location,
+            message: maintenance.message.clone(),
+            disconnected,
+        })
+    } else if previous.enabled {
+        info!(
+            "User {} disabled maintenance of location {location}",
+            session.user.username
+        );
+        Some(ApiEventType::VpnLocationMaintenanceDisabled { location })
+    } else {
+        None
+    };
+    if let Some(event) = event {
+        appstate.emit_event(ApiEvent {
+            context,
+            event: Box::new(event),
+        })?;
+    }
+
+    Ok(ApiResponse {
+        json: json!(maintenance),
+        status: StatusCode::OK,
+    })
+}
+
 /// Returns state of gateways in a given network
 ///
 /// # Returns
@@ -556,6 +1179,50 @@ pub(crate) async fn gateway_status(
     })
 }
 
+#[derive(Serialize)]
+pub struct LocationConnectivity {
+    pub location_id: Id,
+    pub connected: bool,
+    pub last_handshake: Option<NaiveDateTime>,
+    pub gateways: Vec<GatewayState>,
+}
+
+/// Returns connectivity of gateways in a given network
+///
+/// Includes state, version, last peer handshake and recent connection changes of every gateway.
+/// History is kept in memory, so it only covers the time since core startup.
+pub(crate) async fn location_connectivity(
+    Path(network_id): Path<i64>,
+    _role: AdminRole,
+    State(appstate): State<AppState>,
+    Extension(gateway_state): Extension<Arc<Mutex<GatewayMap>>>,
+) -> ApiResult {
+    debug!("Displaying gateway connectivity for network {network_id}");
+    find_network(network_id, &appstate.pool).await?;
+    let connectivity = {
+        let gateway_state = gateway_state
+            .lock()
+            .expect("Failed to acquire gateway state lock");
+        let mut gateways = gateway_state.get_network_gateway_status(network_id);
+        gateways.sort_by(|a, b| a.hostname.cmp(&b.hostname));
+        LocationConnectivity {
+            location_id: network_id,
+            connected: gateway_state.connected(network_id),
+            last_handshake: gateways
+                .iter()
+                .filter_map(|gateway| gateway.last_handshake)
+                .max(),
+            gateways,
+        }
+    };
+    debug!("Displayed gateway connectivity for network {network_id}");
+
+    Ok(ApiResponse {
+        json: json!(connectivity),
+        status: StatusCode::OK,
+    })
+}
+
 /// Returns state of gateways for all networks
 ///
 /// Returns current state of gateways as `HashMap<i64, Vec<GatewayState>>` where key is an id of `WireguardNetwork`
@@ -604,14 +1271,61 @@ pub(crate) async fn import_network(
     context: ApiRequestContext,
     Json(data): Json<ImportNetworkData>,
 ) -> ApiResult {
-    debug!("Importing network from config file");
-    let (mut network, imported_devices) =
-        parse_wireguard_config(&data.config).map_err(|error| {
-            error!("{error}");
-            WebError::Http(StatusCode::UNPROCESSABLE_ENTITY)
-        })?;
-    network.name = data.name;
-    network.endpoint = data.endpoint;
+    import_network_data(&appstate, context, data).await
+}
+
+/// Import network from an uploaded WireGuard config file.
+///
+/// Expects `multipart/form-data` with `name` and `endpoint` fields, optional repeated
+/// `allowed_groups` fields and a `config` file. Fields are read in chunks and the upload is
+/// rejected once all fields together exceed the configured import body limit.
+pub(crate) async fn import_network_upload(
+    _role: AdminRole,
+    State(appstate): State<AppState>,
+    context: ApiRequestContext,
+    mut multipart: Multipart,
+) -> ApiResult {
+    debug!("Reading uploaded network config file");
+    let mut limit = UploadLimit::new(server_config().http_import_body_limit);
+    let mut name = None;
+    let mut endpoint = None;
+    let mut config = None;
+    let mut allowed_groups = Vec::new();
+    while let Some(field) = multipart.next_field().await? {
+        let field_name = field.name().map(ToString::to_string);
+        match field_name.as_deref() {
+            Some("name") => name = Some(limit.read_text(field).await?),
+            Some("endpoint") => endpoint = Some(limit.read_text(field).await?),
+            Some("config") => config = Some(limit.read_text(field).await?),
+            Some("allowed_groups") => allowed_groups.push(limit.read_text(field).await?),
+            _ => debug!("Ignoring unexpected field {field_name:?} in network import upload"),
+        }
+    }
+
+    let missing = |field: &str| WebError::BadRequest(format!("Missing field: {field}"));
+    let data = ImportNetworkData {
+        name: name.ok_or_else(|| missing("name"))?,
+        endpoint: endpoint.ok_or_else(|| missing("endpoint"))?,
+        config: config.ok_or_else(|| missing("config"))?,
+        allowed_groups,
+    };
+
+    import_network_data(&appstate, context, data).await
+}
+
+async fn import_network_data(
+    appstate: &AppState,
+    context: ApiRequestContext,
+    data: ImportNetworkData,
+) -> ApiResult {
+    debug!("Importing network from config file");
+    let (mut network, imported_devices) =
+        parse_wireguard_config(&data.config).map_err(|error| {
+            error!("{error}");
+            WebError::Http(StatusCode::UNPROCESSABLE_ENTITY)
+        })?;
+    network.name = data.name;
+    network.endpoint = data.endpoint;
 
     let mut transaction = appstate.pool.begin().await?;
     let network = network.save(&mut *transaction).await?;
@@ -716,6 +1430,71 @@ pub struct AddDeviceResult {
     pub device: Device<Id>,
 }
 
+/// Saves a new device for the given user, assigns it IPs in all locations
+/// and notifies gateways about the new peer.
+async fn save_user_device(
+    appstate: &AppState,
+    user: &User<Id>,
+    name: String,
+    wireguard_pubkey: String,
+) -> Result<(Device<Id>, Vec<DeviceConfig>), WebError> {
+    let mut transaction = appstate.pool.begin().await?;
+    check_device_quota(&mut *transaction, user.id).await?;
+    let device = Device::new(
+        name,
+        wireguard_pubkey,
+        user.id,
+        DeviceType::User,
+        None,
+        true,
+    )
+    .save(&mut *transaction)
+    .await?;
+
+    let (network_info, configs) = device.add_to_all_networks(&mut transaction).await?;
+
+    // prepare a list of gateway events to be sent
+    let mut events = Vec::new();
+
+    // get all locations affected by device being added
+    let mut affected_location_ids = HashSet::new();
+    for network_info_item in network_info.clone() {
+        affected_location_ids.insert(network_info_item.network_id);
+    }
+
+    // send firewall config updates to affected locations
+    // if they have ACL enabled & enterprise features are active
+    for location_id in affected_location_ids {
+        if let Some(location) = WireguardNetwork::find_by_id(&mut *transaction, location_id).await?
+        {
+            if let Some(firewall_config) =
+                location.try_get_firewall_config(&mut transaction).await?
+            {
+                debug!(
+                    "Sending firewall config update for location {location} affected by adding new user {} devices",
+                    user.username
+                );
+                events.push(GatewayEvent::FirewallConfigChanged(
+                    location_id,
+                    firewall_config,
+                ));
+            }
+        }
+    }
+
+    // add peer on relevant gateways
+    events.push(GatewayEvent::DeviceCreated(DeviceInfo {
+        device: device.clone(),
+        network_info,
+    }));
+
+    appstate.send_multiple_wireguard_events(events);
+
+    transaction.commit().await?;
+
+    Ok((device, configs))
+}
+
 /// Add device
 ///
 /// Add a new device for a user by sending `AddDevice` object.
@@ -794,7 +1573,7 @@ pub(crate) async fn add_device(
     let user = user_for_admin_or_self(&appstate.pool, &session, &username).await?;
 
     let settings = EnterpriseSettings::get(&appstate.pool).await?;
-    if settings.only_client_activation && !session.is_admin {
+    if settings.only_client_activation && !session.can_manage_others() {
         warn!(
             "User {} tried to add a device, but manual device management is disaled",
             session.user.username
@@ -805,7 +1584,7 @@ pub(crate) async fn add_device(
     }
 
     // Let admins manage devices for disabled users
-    if !user.is_active && !session.is_admin {
+    if !user.is_active && !session.can_manage_others() {
         warn!(
             "User {} tried to add a device for a disabled user {username}",
             session.user.username
@@ -836,59 +1615,14 @@ pub(crate) async fn add_device(
         )));
     }
 
-    // save the device
-    let mut transaction = appstate.pool.begin().await?;
-    let device = Device::new(
+    let (device, configs) = save_user_device(
+        &appstate,
+        &user,
         add_device.name,
         add_device.wireguard_pubkey,
-        user.id,
-        DeviceType::User,
-        None,
-        true,
     )
-    .save(&mut *transaction)
     .await?;
 
-    let (network_info, configs) = device.add_to_all_networks(&mut transaction).await?;
-
-    // prepare a list of gateway events to be sent
-    let mut events = Vec::new();
-
-    // get all locations affected by device being added
-    let mut affected_location_ids = HashSet::new();
-    for network_info_item in network_info.clone() {
-        affected_location_ids.insert(network_info_item.network_id);
-    }
-
-    // send firewall config updates to affected locations
-    // if they have ACL enabled & enterprise features are active
-    for location_id in affected_location_ids {
-        if let Some(location) = WireguardNetwork::find_by_id(&mut *transaction, location_id).await?
-        {
-            if let Some(firewall_config) =
-                location.try_get_firewall_config(&mut transaction).await?
-            {
-                debug!(
-                    "Sending firewall config update for location {location} affected by adding new user {username} devices"
-                );
-                events.push(GatewayEvent::FirewallConfigChanged(
-                    location_id,
-                    firewall_config,
-                ));
-            }
-        }
-    }
-
-    // add peer on relevant gateways
-    events.push(GatewayEvent::DeviceCreated(DeviceInfo {
-        device: device.clone(),
-        network_info: network_info.clone(),
-    }));
-
-    appstate.send_multiple_wireguard_events(events);
-
-    transaction.commit().await?;
-
     let template_locations: Vec<TemplateLocation> = configs
         .iter()
         .map(|c| TemplateLocation {
@@ -898,7 +1632,7 @@ pub(crate) async fn add_device(
         .collect();
 
     // hide session info if triggered by admin for other user
-    let (session_ip, session_device_info) = if session.is_admin && session.user != user {
+    let (session_ip, session_device_info) = if session.can_manage_others() && session.user != user {
         (None, None)
     } else {
         (
@@ -942,6 +1676,160 @@ pub(crate) async fn add_device(
     })
 }
 
+#[derive(Deserialize, ToSchema)]
+pub struct ProvisionDevice {
+    pub name: String,
+    // if not provided, a keypair is generated server-side
+    pub wireguard_pubkey: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct ProvisionDeviceResult {
+    pub configs: Vec<DeviceConfig>,
+    pub device: Device<Id>,
+    // only present if the keypair was generated server-side; it is not stored anywhere
+    pub private_key: Option<String>,
+}
+
+/// Provision device for a user
+///
+/// Lets an admin fully set up a device on behalf of a user, without the user going through
+/// the enrollment flow, e.g. for kiosk machines or users without access to email.
+///
+/// If `wireguard_pubkey` is not provided, a keypair is generated server-side and the returned
+/// configs are ready to use. The private key is returned only once and is not stored.
+///
+/// # Returns
+/// - `ProvisionDeviceResult` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    post,
+    path = "/api/v1/device/user/{username}/provision",
+    params(
+        ("username" = String, description = "Username of the device owner.")
+    ),
+    request_body = ProvisionDevice,
+    responses(
+        (status = 201, description = "Successfully provisioned a new device for a user.", body = ProvisionDeviceResult),
+        (status = 400, description = "Bad request, no networks found or device with provided pubkey already exists.", body = ApiResponse, example = json!({})),
+        (status = 401, description = "Unauthorized to provision a device.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to provision a device.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "User not found.", body = ApiResponse, example = json!({"msg": "user not found"})),
+        (status = 500, description = "Cannot provision a device.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn provision_device(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(username): Path<String>,
+    Json(provision_device): Json<ProvisionDevice>,
+) -> ApiResult {
+    let device_name = provision_device.name.clone();
+    debug!(
+        "Admin {} provisioning device {device_name} for user {username}",
+        session.user.username,
+    );
+
+    let Some(user) = User::find_by_username(&appstate.pool, &username).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "user {username} not found"
+        )));
+    };
+    if !user.is_active {
+        return Err(WebError::BadRequest(format!(
+            "Failed to provision device {device_name}, user {username} is disabled"
+        )));
+    }
+
+    let networks = WireguardNetwork::all(&appstate.pool).await?;
+    if networks.is_empty() {
+        error!("Failed to provision device {device_name}, no networks found");
+        return Err(WebError::BadRequest("No networks found".into()));
+    }
+
+    let (wireguard_pubkey, private_key) = match provision_device.wireguard_pubkey {
+        Some(pubkey) => {
+            Device::validate_pubkey(&pubkey).map_err(WebError::PubkeyValidation)?;
+            (pubkey, None)
+        }
+        None => {
+            debug!("Generating keypair for device {device_name} of user {username}");
+            let key = WireguardNetwork::genkey();
+            (key.public, Some(key.private))
+        }
+    };
+
+    // Make sure there is no device with the same pubkey, such state may lead to unexpected issues
+    if Device::find_by_pubkey(&appstate.pool, &wireguard_pubkey)
+        .await?
+        .is_some()
+    {
+        return Err(WebError::PubkeyExists(format!(
+            "Failed to provision device {device_name}, identical pubkey ({wireguard_pubkey}) already exists"
+        )));
+    }
+
+    let (device, configs) =
+        save_user_device(&appstate, &user, provision_device.name, wireguard_pubkey).await?;
+
+    let template_locations: Vec<TemplateLocation> = configs
+        .iter()
+        .map(|c| TemplateLocation {
+            name: c.network_name.clone(),
+            assigned_ips: c.address.as_csv(),
+        })
+        .collect();
+    send_new_device_added_email(
+        &device.name,
+        &device.wireguard_pubkey,
+        &template_locations,
+        &user.email,
+        &appstate.mail_tx,
+        None,
+        None,
+    )?;
+
+    // fill in generated private key so configs can be handed over to the device as-is
+    let configs = match &private_key {
+        Some(private_key) => configs
+            .into_iter()
+            .map(|config| config.with_private_key(private_key))
+            .collect(),
+        None => configs,
+    };
+
+    info!(
+        "Admin {} provisioned device {device_name} for user {username}",
+        session.user.username
+    );
+
+    update_counts(&appstate.pool).await?;
+
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::UserDeviceAdded {
+            device: device.clone(),
+            owner: user,
+        }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!(ProvisionDeviceResult {
+            configs,
+            device,
+            private_key,
+        }),
+        status: StatusCode::CREATED,
+    })
+}
+
 /// Modify device
 ///
 /// Update a device for a user by sending `ModifyDevice` object.
@@ -992,7 +1880,7 @@ pub(crate) async fn modify_device(
     debug!("User {} updating device {device_id}", session.user.username);
 
     let settings = EnterpriseSettings::get(&appstate.pool).await?;
-    if settings.only_client_activation && !session.is_admin {
+    if settings.only_client_activation && !session.can_manage_others() {
         warn!(
             "User {} tried to add a device, but manual device management is disaled",
             session.user.username
@@ -1120,45 +2008,261 @@ pub(crate) async fn get_device(
     })
 }
 
-/// Delete device
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DeviceExpiry {
+    /// Time after which the device is removed from locations, `null` removes the expiry.
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// Set device expiry
 ///
-/// Delete user device and trigger new update in gateway server.
+/// Set the time after which the device (user or network device) is removed from gateways and
+/// marked as not configured, e.g. for temporary access. The owner is notified by email.
 ///
 /// # Returns
-/// - empty JSON
+/// - `DeviceExpiry` object
 ///
 /// - `WebError` if error occurs
 #[utoipa::path(
-    delete,
-    path = "/api/v1/device/{device_id}",
+    put,
+    path = "/api/v1/device/{device_id}/expiry",
     params(
-        ("device_id" = i64, description = "ID of device to update details.")
+        ("device_id" = Id, description = "Device ID")
     ),
+    request_body = DeviceExpiry,
     responses(
-        (status = 200, description = "Successfully deleted device."),
-        (status = 401, description = "Unauthorized to update a device.", body = ApiResponse, example = json!({"msg": "Session is required"})),
-        (status = 404, description = "Device not found.", body = ApiResponse, example = json!({"msg": "device id <id> not found"})),
-        (status = 500, description = "Cannot update a device.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+        (status = 200, description = "Device expiry set.", body = DeviceExpiry, example = json!({"expires_at": "2026-07-10T10:25:43"})),
+        (status = 400, description = "Expiry time is in the past.", body = ApiResponse, example = json!({"msg": "device expiry time must be in the future"})),
+        (status = 401, description = "Unauthorized to set device expiry.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to set device expiry.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Device not found.", body = ApiResponse, example = json!({"msg": "Device <id> not found"})),
+        (status = 500, description = "Cannot set device expiry.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
     ),
     security(
         ("cookie" = []),
         ("api_token" = [])
     )
 )]
-pub(crate) async fn delete_device(
-    _can_manage_devices: CanManageDevices,
+pub(crate) async fn set_device_expiry(
+    _role: AdminRole,
     session: SessionInfo,
-    context: ApiRequestContext,
-    Path(device_id): Path<i64>,
     State(appstate): State<AppState>,
+    Path(device_id): Path<Id>,
+    Json(data): Json<DeviceExpiry>,
 ) -> ApiResult {
-    // bind username to a variable for easier reference
-    let username = &session.user.username;
+    debug!(
+        "User {} setting expiry of device {device_id} to {:?}",
+        session.user.username, data.expires_at
+    );
+    let Some(device) = Device::find_by_id(&appstate.pool, device_id).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "Device {device_id} not found"
+        )));
+    };
+    if let Some(expires_at) = data.expires_at {
+        if expires_at <= Utc::now().naive_utc() {
+            debug!("Invalid expiry time {expires_at} for device {device}");
+            return Err(WebError::BadRequest(
+                "device expiry time must be in the future".into(),
+            ));
+        }
+    }
+    device
+        .set_expires_at(&appstate.pool, data.expires_at)
+        .await?;
+    info!(
+        "User {} set expiry of device {device} to {:?}",
+        session.user.username, data.expires_at
+    );
 
-    debug!("User {username} deleting device {device_id}");
-    let mut transaction = appstate.pool.begin().await?;
+    Ok(ApiResponse {
+        json: json!(data),
+        status: StatusCode::OK,
+    })
+}
 
-    let device = device_for_admin_or_self(&mut *transaction, &session, device_id).await?;
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RotateDeviceKey {
+    /// New public key generated by the device.
+    pub wireguard_pubkey: String,
+}
+
+/// Rotate device key
+///
+/// Replace the WireGuard public key of a device, keeping its addresses, settings and statistics.
+/// Gateways replace the peer and the previous key is kept in the device key history. The device
+/// has to complete MFA again in MFA-protected locations.
+///
+/// # Returns
+/// - `Device` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    post,
+    path = "/api/v1/device/{device_id}/key-rotation",
+    params(
+        ("device_id" = Id, description = "Device ID")
+    ),
+    request_body = RotateDeviceKey,
+    responses(
+        (status = 200, description = "Device key rotated.", body = Device),
+        (status = 400, description = "Invalid public key or device not configured.", body = ApiResponse, example = json!({"msg": "<pubkey> is not a valid pubkey"})),
+        (status = 401, description = "Unauthorized to rotate device key.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 404, description = "Device not found.", body = ApiResponse, example = json!({"msg": "device id <id> not found"})),
+        (status = 500, description = "Cannot rotate device key.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn rotate_device_key(
+    _can_manage_devices: CanManageDevices,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    Path(device_id): Path<Id>,
+    State(appstate): State<AppState>,
+    Json(data): Json<RotateDeviceKey>,
+) -> ApiResult {
+    debug!(
+        "User {} rotating key of device {device_id}",
+        session.user.username
+    );
+
+    let settings = EnterpriseSettings::get(&appstate.pool).await?;
+    if settings.only_client_activation && !session.can_manage_others() {
+        warn!(
+            "User {} tried to rotate device key, but manual device management is disabled",
+            session.user.username
+        );
+        return Err(WebError::Forbidden(
+            "Manual device management is disabled".into(),
+        ));
+    }
+
+    let mut device = device_for_admin_or_self(&appstate.pool, &session, device_id).await?;
+    if !device.configured {
+        return Err(WebError::BadRequest(format!(
+            "Device {device_id} is not configured yet"
+        )));
+    }
+    Device::validate_pubkey(&data.wireguard_pubkey).map_err(WebError::PubkeyValidation)?;
+    if Device::find_by_pubkey(&appstate.pool, &data.wireguard_pubkey)
+        .await?
+        .is_some()
+    {
+        return Err(WebError::PubkeyExists(format!(
+            "Failed to rotate key of device {device_id}, identical pubkey ({}) already exists",
+            data.wireguard_pubkey
+        )));
+    }
+    for network in WireguardNetwork::all(&appstate.pool).await? {
+        if network.pubkey == data.wireguard_pubkey {
+            return Err(WebError::PubkeyValidation(
+                "device's pubkey must be different from server's pubkey".into(),
+            ));
+        }
+    }
+
+    let before = device.clone();
+    let mut transaction = appstate.pool.begin().await?;
+    let (previous, current) = device
+        .rotate_pubkey(&mut transaction, data.wireguard_pubkey)
+        .await?;
+    let location = match device.device_type {
+        DeviceType::Network => device
+            .find_network_device_networks(&mut *transaction)
+            .await?
+            .pop(),
+        DeviceType::User => None,
+    };
+    transaction.commit().await?;
+
+    appstate.send_wireguard_event(GatewayEvent::DeviceDeleted(previous));
+    appstate.send_wireguard_event(GatewayEvent::DeviceCreated(current));
+    info!(
+        "User {} rotated key of device {device}",
+        session.user.username
+    );
+
+    let event = match location {
+        Some(location) => ApiEventType::NetworkDeviceModified {
+            before,
+            after: device.clone(),
+            location,
+        },
+        None => ApiEventType::UserDeviceModified {
+            owner: device.get_owner(&appstate.pool).await?,
+            before,
+            after: device.clone(),
+        },
+    };
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(event),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!(device),
+        status: StatusCode::OK,
+    })
+}
+
+/// List previous keys of a device, most recently replaced first.
+pub(crate) async fn list_device_key_history(
+    session: SessionInfo,
+    Path(device_id): Path<Id>,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    debug!("Listing key history of device {device_id}");
+    let device = device_for_admin_or_self(&appstate.pool, &session, device_id).await?;
+    let history = DeviceKeyHistory::all_for_device(&appstate.pool, device.id).await?;
+
+    Ok(ApiResponse {
+        json: json!(history),
+        status: StatusCode::OK,
+    })
+}
+
+/// Delete device
+///
+/// Delete user device and trigger new update in gateway server.
+///
+/// # Returns
+/// - empty JSON
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    delete,
+    path = "/api/v1/device/{device_id}",
+    params(
+        ("device_id" = i64, description = "ID of device to update details.")
+    ),
+    responses(
+        (status = 200, description = "Successfully deleted device."),
+        (status = 401, description = "Unauthorized to update a device.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 404, description = "Device not found.", body = ApiResponse, example = json!({"msg": "device id <id> not found"})),
+        (status = 500, description = "Cannot update a device.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn delete_device(
+    _can_manage_devices: CanManageDevices,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    Path(device_id): Path<i64>,
+    State(appstate): State<AppState>,
+) -> ApiResult {
+    // bind username to a variable for easier reference
+    let username = &session.user.username;
+
+    debug!("User {username} deleting device {device_id}");
+    let mut transaction = appstate.pool.begin().await?;
+
+    let device = device_for_admin_or_self(&mut *transaction, &session, device_id).await?;
 
     let mut events = Vec::new();
 
@@ -1314,7 +2418,7 @@ pub(crate) async fn list_user_devices(
     Path(username): Path<String>,
 ) -> ApiResult {
     // only allow for admin or user themselves
-    if !session.is_admin && session.user.username != username {
+    if !session.can_manage_others() && session.user.username != username {
         warn!(
             "User {} tried to list devices for user {username}, but is not an admin",
             session.user.username
@@ -1339,7 +2443,7 @@ pub(crate) async fn download_config(
     debug!("Creating config for device {device_id} in network {network_id}");
 
     let enterprise_settings = EnterpriseSettings::get(&appstate.pool).await?;
-    if enterprise_settings.only_client_activation && !session.is_admin {
+    if enterprise_settings.only_client_activation && !session.can_manage_others() {
         warn!(
             "User {} tried to download device config, but manual device management is disabled",
             session.user.username
@@ -1349,8 +2453,15 @@ pub(crate) async fn download_config(
         ));
     }
 
-    let network = find_network(network_id, &appstate.pool).await?;
     let device = device_for_admin_or_self(&appstate.pool, &session, device_id).await?;
+    let network = find_network(network_id, &appstate.pool)
+        .await?
+        .apply_device_settings(&appstate.pool, &device)
+        .await?;
+    let profile = DeviceProfile::find_for_device(&appstate.pool, device_id).await?;
+    let Some(network) = apply_device_profile(profile.as_ref(), network.clone()) else {
+        return Err(DeviceError::LocationNotAllowed(network.name).into());
+    };
     let wireguard_network_device =
         WireguardNetworkDevice::find(&appstate.pool, device_id, network_id).await?;
     if let Some(wireguard_network_device) = wireguard_network_device {
@@ -1372,6 +2483,273 @@ pub(crate) async fn download_config(
     }
 }
 
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DeviceIpReservation {
+    /// One address in each subnet of the location.
+    #[schema(value_type = Vec<String>)]
+    pub ips: Vec<IpAddr>,
+    /// Whether the addresses were set by an admin and are kept when user groups or address
+    /// pools change.
+    #[serde(default)]
+    pub reserved: bool,
+}
+
+/// Find the device and its addresses in the location.
+async fn find_device_in_location(
+    pool: &PgPool,
+    network_id: Id,
+    device_id: Id,
+) -> Result<(WireguardNetwork<Id>, Device<Id>, WireguardNetworkDevice), WebError> {
+    let location = find_network(network_id, pool).await?;
+    let Some(device) = Device::find_by_id(pool, device_id).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "Device {device_id} not found"
+        )));
+    };
+    let Some(network_device) = WireguardNetworkDevice::find(pool, device_id, network_id).await?
+    else {
+        return Err(WebError::ObjectNotFound(format!(
+            "Device {device_id} not found in location {location}"
+        )));
+    };
+
+    Ok((location, device, network_device))
+}
+
+/// Get device addresses in location
+///
+/// # Returns
+/// - `DeviceIpReservation` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    get,
+    path = "/api/v1/network/{network_id}/device/{device_id}/ip",
+    params(
+        ("network_id" = Id, description = "Location ID"),
+        ("device_id" = Id, description = "Device ID")
+    ),
+    responses(
+        (status = 200, description = "Device addresses.", body = DeviceIpReservation, example = json!({"ips": ["10.0.0.10"], "reserved": true})),
+        (status = 401, description = "Unauthorized to get device addresses.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to get device addresses.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Device not found in location.", body = ApiResponse, example = json!({"msg": "Device <id> not found in location <name>"})),
+        (status = 500, description = "Cannot get device addresses.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn get_device_ips(
+    _role: AdminRole,
+    State(appstate): State<AppState>,
+    Path((network_id, device_id)): Path<(Id, Id)>,
+) -> ApiResult {
+    debug!("Getting addresses of device {device_id} in location {network_id}");
+    let (_, _, network_device) =
+        find_device_in_location(&appstate.pool, network_id, device_id).await?;
+    let reserved = network_device.ip_reserved(&appstate.pool).await?;
+
+    Ok(ApiResponse {
+        json: json!(DeviceIpReservation {
+            ips: network_device.wireguard_ips,
+            reserved,
+        }),
+        status: StatusCode::OK,
+    })
+}
+
+/// Reserve device addresses in location
+///
+/// Set the addresses of a device (user or network device) in the location. Reserved addresses
+/// are not reassigned when user groups or address pools change, e.g. for network devices
+/// referenced in firewall rules. They have to be free, within the location subnets and the address
+/// pool of the device owner.
+///
+/// # Returns
+/// - `DeviceIpReservation` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    put,
+    path = "/api/v1/network/{network_id}/device/{device_id}/ip",
+    params(
+        ("network_id" = Id, description = "Location ID"),
+        ("device_id" = Id, description = "Device ID")
+    ),
+    request_body = DeviceIpReservation,
+    responses(
+        (status = 200, description = "Device addresses reserved.", body = DeviceIpReservation, example = json!({"ips": ["10.0.0.10"], "reserved": true})),
+        (status = 400, description = "Addresses can't be assigned to the device.", body = ApiResponse, example = json!({"msg": "IP address 10.0.0.10 is already assigned in network <name>"})),
+        (status = 401, description = "Unauthorized to reserve device addresses.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to reserve device addresses.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Device not found in location.", body = ApiResponse, example = json!({"msg": "Device <id> not found in location <name>"})),
+        (status = 500, description = "Cannot reserve device addresses.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn reserve_device_ips(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path((network_id, device_id)): Path<(Id, Id)>,
+    Json(data): Json<DeviceIpReservation>,
+) -> ApiResult {
+    debug!(
+        "User {} reserving addresses {} for device {device_id} in location {network_id}",
+        session.user.username,
+        data.ips.as_csv()
+    );
+    let (location, device, mut network_device) =
+        find_device_in_location(&appstate.pool, network_id, device_id).await?;
+
+    let one_per_subnet = location
+        .address
+        .iter()
+        .all(|address| data.ips.iter().filter(|ip| address.contains(**ip)).count() == 1);
+    if data.ips.len() != location.address.len() || !one_per_subnet {
+        return Err(WebError::BadRequest(format!(
+            "Exactly one address in each subnet of location {location} ({}) is required",
+            location.address.as_csv()
+        )));
+    }
+
+    let mut transaction = appstate.pool.begin().await?;
+    location
+        .can_assign_ips(&mut transaction, &data.ips, Some(device.id))
+        .await?;
+    let scope = location
+        .device_address_scope(&mut *transaction, device.user_id)
+        .await?;
+    if !scope.allows_all(&location.address, &data.ips) {
+        return Err(WebError::BadRequest(format!(
+            "Addresses {} are outside of the address pool of device {device}",
+            data.ips.as_csv()
+        )));
+    }
+
+    let before = network_device.wireguard_ips.clone();
+    network_device.wireguard_ips.clone_from(&data.ips);
+    network_device.update(&mut *transaction).await?;
+    network_device
+        .set_ip_reserved(&mut *transaction, true)
+        .await?;
+    let device_info = DeviceInfo::from_device(&mut *transaction, device.clone()).await?;
+    let firewall_config = if location.acl_enabled {
+        location.try_get_firewall_config(&mut transaction).await?
+    } else {
+        None
+    };
+    transaction.commit().await?;
+
+    appstate.send_wireguard_event(GatewayEvent::DeviceModified(device_info));
+    if let Some(firewall_config) = firewall_config {
+        appstate.send_wireguard_event(GatewayEvent::FirewallConfigChanged(
+            location.id,
+            firewall_config,
+        ));
+    }
+    info!(
+        "User {} reserved addresses {} for device {device} in location {location}",
+        session.user.username,
+        data.ips.as_csv()
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::DeviceAddressReserved {
+            device,
+            location,
+            before,
+            after: data.ips.clone(),
+        }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!(DeviceIpReservation {
+            ips: data.ips,
+            reserved: true,
+        }),
+        status: StatusCode::OK,
+    })
+}
+
+/// Release device address reservation in location
+///
+/// The device keeps its current addresses, but they may be reassigned automatically when user
+/// groups or address pools change.
+///
+/// # Returns
+/// - `DeviceIpReservation` object
+///
+/// - `WebError` if error occurs
+#[utoipa::path(
+    delete,
+    path = "/api/v1/network/{network_id}/device/{device_id}/ip",
+    params(
+        ("network_id" = Id, description = "Location ID"),
+        ("device_id" = Id, description = "Device ID")
+    ),
+    responses(
+        (status = 200, description = "Device address reservation released.", body = DeviceIpReservation, example = json!({"ips": ["10.0.0.10"], "reserved": false})),
+        (status = 401, description = "Unauthorized to release device addresses.", body = ApiResponse, example = json!({"msg": "Session is required"})),
+        (status = 403, description = "You don't have permission to release device addresses.", body = ApiResponse, example = json!({"msg": "requires privileged access"})),
+        (status = 404, description = "Device addresses not reserved in location.", body = ApiResponse, example = json!({"msg": "Addresses of device <name> are not reserved in location <name>"})),
+        (status = 500, description = "Cannot release device addresses.", body = ApiResponse, example = json!({"msg": "Internal server error"}))
+    ),
+    security(
+        ("cookie" = []),
+        ("api_token" = [])
+    )
+)]
+pub(crate) async fn release_device_ips(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path((network_id, device_id)): Path<(Id, Id)>,
+) -> ApiResult {
+    debug!(
+        "User {} releasing addresses of device {device_id} in location {network_id}",
+        session.user.username
+    );
+    let (location, device, network_device) =
+        find_device_in_location(&appstate.pool, network_id, device_id).await?;
+    if !network_device.ip_reserved(&appstate.pool).await? {
+        return Err(WebError::ObjectNotFound(format!(
+            "Addresses of device {device} are not reserved in location {location}"
+        )));
+    }
+    network_device
+        .set_ip_reserved(&appstate.pool, false)
+        .await?;
+    info!(
+        "User {} released addresses {} of device {device} in location {location}",
+        session.user.username,
+        network_device.wireguard_ips.as_csv()
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::DeviceAddressReleased {
+            device,
+            location,
+            addresses: network_device.wireguard_ips.clone(),
+        }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!(DeviceIpReservation {
+            ips: network_device.wireguard_ips,
+            reserved: false,
+        }),
+        status: StatusCode::OK,
+    })
+}
+
 pub(crate) async fn create_network_token(
     _role: AdminRole,
     State(appstate): State<AppState>,
@@ -1393,6 +2771,112 @@ pub(crate) async fn create_network_token(
     })
 }
 
+#[derive(Deserialize)]
+pub struct GatewayTokenData {
+    pub name: String,
+}
+
+/// List gateway tokens issued for the location.
+pub(crate) async fn list_gateway_tokens(
+    _role: AdminRole,
+    State(appstate): State<AppState>,
+    Path(network_id): Path<Id>,
+) -> ApiResult {
+    debug!("Listing gateway tokens of network {network_id}");
+    let network = find_network(network_id, &appstate.pool).await?;
+    let tokens = GatewayToken::all_for_location(&appstate.pool, network.id).await?;
+
+    Ok(ApiResponse {
+        json: json!(tokens),
+        status: StatusCode::OK,
+    })
+}
+
+/// Issue a new gateway token for the location. Multiple tokens can be issued, so that redundant
+/// gateways can be run and tokens rotated without downtime. The token is returned only once.
+pub(crate) async fn create_gateway_token(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path(network_id): Path<Id>,
+    Json(data): Json<GatewayTokenData>,
+) -> ApiResult {
+    debug!(
+        "Issuing gateway token {} for network {network_id}",
+        data.name
+    );
+    let network = find_network(network_id, &appstate.pool).await?;
+    let name = data.name.trim();
+    if name.is_empty() {
+        return Err(WebError::BadRequest("Token name is required".into()));
+    }
+    let token = GatewayToken::new(network.id, name.into())
+        .save(&appstate.pool)
+        .await?;
+    let jwt = token.to_jwt().map_err(|err| {
+        error!("Failed to create token for gateway {}: {err}", network.name);
+        WebError::Authorization(format!(
+            "Failed to create token for gateway {}",
+            network.name
+        ))
+    })?;
+    info!(
+        "User {} issued gateway token {token} for network {network}",
+        session.user.username
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::GatewayTokenAdded {
+            location: network,
+            token: token.clone(),
+        }),
+    })?;
+
+    Ok(ApiResponse {
+        json: json!({
+            "id": token.id,
+            "name": token.name,
+            "token": jwt,
+            "grpc_url": server_config().grpc_url.to_string(),
+        }),
+        status: StatusCode::CREATED,
+    })
+}
+
+/// Revoke gateway token. Gateways using it are disconnected and can't connect again.
+pub(crate) async fn revoke_gateway_token(
+    _role: AdminRole,
+    session: SessionInfo,
+    context: ApiRequestContext,
+    State(appstate): State<AppState>,
+    Path((network_id, token_id)): Path<(Id, Id)>,
+) -> ApiResult {
+    debug!("Revoking gateway token {token_id} of network {network_id}");
+    let network = find_network(network_id, &appstate.pool).await?;
+    let Some(token) = GatewayToken::find_for_location(&appstate.pool, network.id, token_id).await?
+    else {
+        return Err(WebError::ObjectNotFound(format!(
+            "Gateway token {token_id} not found"
+        )));
+    };
+    token.clone().delete(&appstate.pool).await?;
+    appstate.send_wireguard_event(GatewayEvent::GatewayTokenRevoked(network.id, token.id));
+    info!(
+        "User {} revoked gateway token {token} of network {network}",
+        session.user.username
+    );
+    appstate.emit_event(ApiEvent {
+        context,
+        event: Box::new(ApiEventType::GatewayTokenRevoked {
+            location: network,
+            token,
+        }),
+    })?;
+
+    Ok(ApiResponse::default())
+}
+
 /// Returns appropriate aggregation level depending on the `from` date param
 /// If `from` is >= than 6 hours ago, returns `Hour` aggregation
 /// Otherwise returns `Minute` aggregation
@@ -1446,10 +2930,15 @@ pub(crate) async fn devices_stats(
     let from = query_from.parse_timestamp()?.naive_utc();
     let aggregation = get_aggregation(from)?;
     let user_devices_stats = network
-        .user_stats(&appstate.pool, &from, &aggregation)
+        .user_stats(appstate.read_pool(), &from, &aggregation)
         .await?;
     let network_devices_stats = network
-        .distinct_device_stats(&appstate.pool, &from, &aggregation, DeviceType::Network)
+        .distinct_device_stats(
+            appstate.read_pool(),
+            &from,
+            &aggregation,
+            DeviceType::Network,
+        )
         .await?;
     let response = DevicesStatsResponse {
         user_devices: user_devices_stats,
@@ -1483,7 +2972,7 @@ pub(crate) async fn network_stats(
     let from = query_from.parse_timestamp()?.naive_utc();
     let aggregation: DateTimeAggregation = get_aggregation(from)?;
     let stats: WireguardNetworkStats = network
-        .network_stats(&appstate.pool, &from, &aggregation)
+        .network_stats(appstate.read_pool(), &from, &aggregation)
         .await?;
     debug!("Displayed WireGuard network stats for network {network_id}");
 
@@ -1493,6 +2982,79 @@ pub(crate) async fn network_stats(
     })
 }
 
+#[derive(Deserialize)]
+pub struct AggregatedStatsQuery {
+    #[serde(default)]
+    resolution: StatsResolution,
+    from: Option<String>,
+    to: Option<String>,
+    device_id: Option<Id>,
+}
+
+impl AggregatedStatsQuery {
+    /// Parses requested time range. By default returns last 24 hours for hourly resolution
+    /// and last 30 days for daily resolution.
+    fn parse_range(&self) -> Result<(NaiveDateTime, NaiveDateTime), StatusCode> {
+        let parse = |timestamp: &str| {
+            DateTime::<Utc>::from_str(timestamp)
+                .map(|timestamp| timestamp.naive_utc())
+                .map_err(|_| StatusCode::BAD_REQUEST)
+        };
+        let to = match &self.to {
+            Some(to) => parse(to)?,
+            None => Utc::now().naive_utc(),
+        };
+        let from = match &self.from {
+            Some(from) => parse(from)?,
+            None => match self.resolution {
+                StatsResolution::Hour => to - TimeDelta::days(1),
+                StatsResolution::Day => to - TimeDelta::days(30),
+            },
+        };
+        if from > to {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Ok((from, to))
+    }
+}
+
+/// Returns rolled up transfer statistics for requested network
+///
+/// Raw stats are rolled up into hourly and daily totals in the background, so this works for
+/// long time ranges, but doesn't include the current hour.
+///
+/// # Returns
+/// Returns a list of `AggregatedPeerStats` for requested network, device and time period
+pub(crate) async fn network_aggregated_stats(
+    _role: AdminRole,
+    State(appstate): State<AppState>,
+    Path(network_id): Path<i64>,
+    Query(query): Query<AggregatedStatsQuery>,
+) -> ApiResult {
+    debug!("Displaying aggregated WireGuard stats for network {network_id}");
+    let Some(network) = WireguardNetwork::find_by_id(&appstate.pool, network_id).await? else {
+        return Err(WebError::ObjectNotFound(format!(
+            "Requested network ({network_id}) not found"
+        )));
+    };
+    let (from, to) = query.parse_range()?;
+    let stats = WireguardPeerStats::aggregated_series(
+        appstate.read_pool(),
+        network.id,
+        query.device_id,
+        query.resolution,
+        from,
+        to,
+    )
+    .await?;
+    debug!("Displayed aggregated WireGuard stats for network {network_id}");
+
+    Ok(ApiResponse {
+        json: json!(stats),
+        status: StatusCode::OK,
+    })
+}
+
 /// Returns statistics for all networks
 ///
 /// # Returns
@@ -1505,7 +3067,7 @@ pub(crate) async fn networks_overview_stats(
     debug!("Preparing networks overview stats");
     let from = query_from.parse_timestamp()?.naive_utc();
     let aggregation = get_aggregation(from)?;
-    let all_networks_stats = networks_stats(&appstate.pool, &from, &aggregation).await?;
+    let all_networks_stats = networks_stats(appstate.read_pool(), &from, &aggregation).await?;
     debug!("Finished processing networks overview stats");
     Ok(ApiResponse {
         json: json!(all_networks_stats),
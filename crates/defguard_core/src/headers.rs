@@ -1,6 +1,11 @@
 use std::{borrow::Borrow, sync::LazyLock};
 
-use axum::http::{HeaderName, HeaderValue};
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
 use defguard_common::db::{Id, models::DeviceLoginEvent};
 use defguard_mail::{
     Mail,
@@ -9,6 +14,7 @@ use defguard_mail::{
 use sqlx::PgPool;
 use tokio::sync::mpsc::UnboundedSender;
 use uaparser::{Client, Parser, UserAgentParser};
+use uuid::Uuid;
 
 use crate::{db::User, handlers::mail::send_new_device_login_email};
 
@@ -17,6 +23,50 @@ pub(crate) const CONTENT_SECURITY_POLICY_HEADER_NAME: HeaderName =
 pub(crate) const CONTENT_SECURITY_POLICY_HEADER_VALUE: HeaderValue =
     HeaderValue::from_static("frame-ancestors 'none';");
 
+pub(crate) const REQUEST_ID_HEADER_NAME: HeaderName = HeaderName::from_static("x-request-id");
+// Longer identifiers supplied by clients are replaced with a generated one.
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    /// Identifier of the HTTP request currently being handled.
+    static REQUEST_ID: String;
+}
+
+/// Returns identifier of the HTTP request handled by the current task, if any.
+#[must_use]
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Accepts request identifier sent in the `X-Request-Id` header (e.g. by a reverse proxy) or
+/// assigns a new one, and returns it in the response.
+///
+/// The identifier is available to the rest of the request handling through the request header
+/// and `current_request_id()`, so it can be attached to tracing spans and activity log events.
+pub(crate) async fn propagate_request_id(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER_NAME)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| {
+            !value.is_empty()
+                && value.len() <= MAX_REQUEST_ID_LENGTH
+                && value.bytes().all(|byte| byte.is_ascii_graphic())
+        })
+        .map_or_else(|| Uuid::new_v4().to_string(), ToString::to_string);
+    let header_value =
+        HeaderValue::from_str(&request_id).expect("request ID is a valid header value");
+    request
+        .headers_mut()
+        .insert(REQUEST_ID_HEADER_NAME, header_value.clone());
+
+    let mut response = REQUEST_ID.scope(request_id, next.run(request)).await;
+    response
+        .headers_mut()
+        .insert(REQUEST_ID_HEADER_NAME, header_value);
+    response
+}
+
 pub(crate) static USER_AGENT_PARSER: LazyLock<UserAgentParser> = LazyLock::new(|| {
     let regexes = include_bytes!("../user_agent_header_regexes.yaml");
     UserAgentParser::from_bytes(regexes).expect("Parser creation failed")
@@ -105,7 +155,8 @@ pub(crate) async fn check_new_device_login(
         .await
     {
         send_new_device_login_email(
-            &user.email,
+            pool,
+            user,
             mail_tx,
             session,
             created_device_login_event.created,
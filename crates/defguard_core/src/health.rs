@@ -0,0 +1,307 @@
+//! Detailed health report of core components, meant to be polled by monitoring systems.
+//!
+//! Errors and heartbeats of background components are recorded in
+//! [`defguard_common::diagnostics`] where they happen, the rest is checked on every request.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use defguard_common::{
+    db::models::Settings,
+    diagnostics::{
+        ComponentSnapshot, LDAP_SYNC, MAIL_HANDLER, MAIL_QUEUE, QueueSnapshot, UTILITY_THREAD,
+    },
+};
+use serde::Serialize;
+use serde_json::{Value, json};
+use sqlx::Connection;
+
+use crate::{
+    appstate::AppState,
+    db::replica::ReadReplica,
+    enterprise::{
+        ldap::LDAPConnection,
+        license::{LicenseTier, get_cached_license, validate_license},
+        limits::get_counts,
+    },
+    grpc::gateway::map::GatewayMap,
+    handlers::support::{gateway_queue_snapshots, queue_snapshots},
+};
+
+// Errors older than this don't affect the component status.
+const RECENT_ERROR: Duration = Duration::from_secs(15 * 60);
+// Queue with waiting messages which weren't processed for this long is considered stalled.
+const STALLED_QUEUE: Duration = Duration::from_secs(60);
+// Utility thread wakes up every few seconds, so a missing heartbeat means it's stuck or dead.
+const MISSED_HEARTBEAT: Duration = Duration::from_secs(60);
+const LDAP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Component status, ordered from best to worst.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// Component is not configured.
+    Disabled,
+    Ok,
+    /// Component works, but needs attention.
+    Degraded,
+    Down,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ComponentHealth {
+    pub status: HealthStatus,
+    pub last_error: Option<String>,
+    /// Unix timestamp of the last error.
+    pub last_error_at: Option<u64>,
+    pub details: Value,
+}
+
+impl ComponentHealth {
+    fn new(status: HealthStatus, details: Value) -> Self {
+        Self {
+            status,
+            last_error: None,
+            last_error_at: None,
+            details,
+        }
+    }
+
+    fn with_error<E: ToString>(mut self, error: E) -> Self {
+        self.last_error = Some(error.to_string());
+        self.last_error_at = Some(unix_now());
+        self
+    }
+
+    /// Takes the last error recorded by a background component, degrading the status if the error
+    /// is recent.
+    fn with_recorded_error(mut self, snapshot: ComponentSnapshot) -> Self {
+        if self.last_error.is_none() {
+            if let (Some(error), Some(at)) = (snapshot.last_error, snapshot.last_error_at) {
+                if is_recent(at, RECENT_ERROR) {
+                    self.status = self.status.max(HealthStatus::Degraded);
+                }
+                self.last_error = Some(error);
+                self.last_error_at = Some(at);
+            }
+        }
+        self
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    /// Worst status of all components.
+    pub status: HealthStatus,
+    pub components: BTreeMap<&'static str, ComponentHealth>,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+fn is_recent(timestamp: u64, period: Duration) -> bool {
+    unix_now().saturating_sub(timestamp) < period.as_secs()
+}
+
+fn is_stalled(queue: &QueueSnapshot) -> bool {
+    queue.depth > 0
+        && queue
+            .last_processed
+            .is_none_or(|last_processed| !is_recent(last_processed, STALLED_QUEUE))
+}
+
+async fn database_health(appstate: &AppState) -> ComponentHealth {
+    let pool = &appstate.pool;
+    let started = Instant::now();
+    let result = match pool.acquire().await {
+        Ok(mut conn) => conn.ping().await,
+        Err(err) => Err(err),
+    };
+    let replica_available = appstate.replica.as_ref().map(ReadReplica::is_available);
+    let details = json!({
+        "latency_ms": started.elapsed().as_secs_f64() * 1000.0,
+        "pool_size": pool.size(),
+        "pool_idle": pool.num_idle(),
+        "pool_max": pool.options().get_max_connections(),
+        "replica_available": replica_available,
+    });
+    match result {
+        // reads fall back to the primary, but it has to handle the extra load
+        Ok(()) if replica_available == Some(false) => {
+            ComponentHealth::new(HealthStatus::Degraded, details)
+                .with_error("database replica is unreachable")
+        }
+        Ok(()) => ComponentHealth::new(HealthStatus::Ok, details),
+        Err(err) => ComponentHealth::new(HealthStatus::Down, details).with_error(err),
+    }
+}
+
+fn mail_health() -> ComponentHealth {
+    let queue = MAIL_QUEUE.snapshot();
+    let status = if is_stalled(&queue) {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Ok
+    };
+    ComponentHealth::new(status, json!({ "queue": queue }))
+        .with_recorded_error(MAIL_HANDLER.snapshot())
+}
+
+async fn ldap_health() -> ComponentHealth {
+    let settings = Settings::get_current_settings();
+    if !settings.ldap_enabled {
+        return ComponentHealth::new(HealthStatus::Disabled, json!({}));
+    }
+    let details = json!({
+        "sync_enabled": settings.ldap_sync_enabled,
+        "in_sync": !settings.ldap_sync_status.is_out_of_sync(),
+    });
+    let health = match tokio::time::timeout(LDAP_TIMEOUT, LDAPConnection::create()).await {
+        Ok(Ok(_)) => ComponentHealth::new(HealthStatus::Ok, details),
+        Ok(Err(err)) => ComponentHealth::new(HealthStatus::Degraded, details).with_error(err),
+        Err(_) => ComponentHealth::new(HealthStatus::Degraded, details)
+            .with_error("LDAP connection timed out"),
+    };
+    health.with_recorded_error(LDAP_SYNC.snapshot())
+}
+
+fn license_health() -> ComponentHealth {
+    let counts = get_counts();
+    let license = get_cached_license();
+    let details = json!({
+        "present": license.is_some(),
+        "tier": license.as_ref().map(|license| license.tier.to_string()),
+        "valid_until": license.as_ref().and_then(|license| license.valid_until),
+        "paid_license_required": counts.needs_paid_license(),
+    });
+    let health = match validate_license(license.as_ref(), &counts, LicenseTier::Business) {
+        Err(err) if counts.needs_paid_license() => {
+            ComponentHealth::new(HealthStatus::Degraded, details).with_error(err)
+        }
+        _ => ComponentHealth::new(HealthStatus::Ok, details),
+    };
+    match license {
+        Some(license) if license.is_expired() || license.requires_renewal() => ComponentHealth {
+            status: HealthStatus::Degraded,
+            ..health
+        },
+        _ => health,
+    }
+}
+
+fn gateway_health(gateway_state: &Arc<Mutex<GatewayMap>>) -> ComponentHealth {
+    let locations = gateway_state
+        .lock()
+        .expect("Failed to acquire gateway state lock")
+        .as_flattened();
+    let gateways = locations.values().flatten();
+    let total = gateways.clone().count();
+    let connected = gateways.filter(|gateway| gateway.connected).count();
+    // locations which have seen a gateway, but none of them is connected
+    let mut unavailable_locations = locations
+        .values()
+        .filter(|gateways| !gateways.iter().any(|gateway| gateway.connected))
+        .filter_map(|gateways| gateways.first().map(|gateway| gateway.network_name.clone()))
+        .collect::<Vec<_>>();
+    unavailable_locations.sort();
+    let status = if unavailable_locations.is_empty() {
+        HealthStatus::Ok
+    } else {
+        HealthStatus::Degraded
+    };
+    ComponentHealth::new(
+        status,
+        json!({
+            "total": total,
+            "connected": connected,
+            "unavailable_locations": unavailable_locations,
+        }),
+    )
+}
+
+fn worker_health(appstate: &AppState) -> ComponentHealth {
+    let utility_thread = UTILITY_THREAD.snapshot();
+    let mut status = match utility_thread.last_heartbeat {
+        Some(heartbeat) if is_recent(heartbeat, MISSED_HEARTBEAT) => HealthStatus::Ok,
+        _ => HealthStatus::Down,
+    };
+    let mut queues = BTreeMap::new();
+    for (name, queue) in queue_snapshots(appstate) {
+        if name == "mail" {
+            continue;
+        }
+        if is_stalled(&queue) {
+            status = status.max(HealthStatus::Degraded);
+        }
+        queues.insert(name, queue);
+    }
+    // a single stuck gateway doesn't show up in aggregated statistics
+    if gateway_queue_snapshots()
+        .iter()
+        .any(|(_, queue)| is_stalled(queue))
+    {
+        status = status.max(HealthStatus::Degraded);
+    }
+    ComponentHealth::new(
+        status,
+        json!({
+            "utility_thread_heartbeat": utility_thread.last_heartbeat,
+            "queues": queues,
+        }),
+    )
+}
+
+/// Checks all components. Slow checks, like connecting to LDAP, are bounded by a timeout.
+pub(crate) async fn health_report(
+    appstate: &AppState,
+    gateway_state: &Arc<Mutex<GatewayMap>>,
+) -> HealthReport {
+    let (database, ldap) = tokio::join!(database_health(appstate), ldap_health());
+    let components = BTreeMap::from([
+        ("database", database),
+        ("mail", mail_health()),
+        ("ldap", ldap),
+        ("license", license_health()),
+        ("gateways", gateway_health(gateway_state)),
+        ("workers", worker_health(appstate)),
+    ]);
+    let status = components
+        .values()
+        .map(|component| component.status)
+        .max()
+        .unwrap_or(HealthStatus::Ok);
+
+    HealthReport { status, components }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stalled_queue() {
+        let mut queue = MAIL_QUEUE.snapshot();
+        queue.depth = 0;
+        queue.last_processed = None;
+        assert!(!is_stalled(&queue));
+        queue.depth = 3;
+        assert!(is_stalled(&queue));
+        queue.last_processed = Some(unix_now());
+        assert!(!is_stalled(&queue));
+        queue.last_processed = Some(unix_now() - 120);
+        assert!(is_stalled(&queue));
+    }
+
+    #[test]
+    fn test_health_status_order() {
+        assert!(HealthStatus::Disabled < HealthStatus::Ok);
+        assert!(HealthStatus::Ok < HealthStatus::Degraded);
+        assert!(HealthStatus::Degraded < HealthStatus::Down);
+    }
+}
@@ -9,10 +9,13 @@ use std::{
 use anyhow::anyhow;
 use axum::{
     Extension, Json, Router,
+    extract::DefaultBodyLimit,
     http::{Request, StatusCode},
+    middleware,
     routing::{delete, get, post, put},
     serve,
 };
+use axum_client_ip::{SecureClientIp, SecureClientIpSource};
 use db::models::{device::DeviceType, wireguard::LocationMfaMode};
 use defguard_common::{
     VERSION,
@@ -36,11 +39,20 @@ use enterprise::{
         },
         api_tokens::{add_api_token, delete_api_token, fetch_api_tokens, rename_api_token},
         check_enterprise_info,
+        client_credentials::{
+            delete_client_credentials, get_client_credentials, set_client_credentials,
+        },
         enterprise_settings::{get_enterprise_settings, patch_enterprise_settings},
         openid_login::{auth_callback, get_auth_info},
         openid_providers::{
             add_openid_provider, delete_openid_provider, get_current_openid_provider,
-            test_dirsync_connection,
+            preview_group_claim_mapping, test_dirsync_connection,
+        },
+        posture_policy::{delete_posture_policy, get_posture_policy, set_posture_policy},
+        provisioning::{list_provisioning_policies, modify_provisioning_policy},
+        saml::{
+            add_saml_provider, delete_saml_provider, get_saml_auth_info, get_saml_link_info,
+            get_saml_metadata, list_saml_providers, modify_saml_provider, saml_acs, saml_callback,
         },
     },
     snat::handlers::{
@@ -49,12 +61,13 @@ use enterprise::{
 };
 use events::ApiEvent;
 use handlers::{
-    activity_log::get_activity_log_events,
+    activity_log::{create_custom_event, export_activity_log_events, get_activity_log_events},
     auth::disable_user_mfa,
     group::{bulk_assign_to_groups, list_groups_info},
     network_devices::{
         add_network_device, check_ip_availability, download_network_device_config,
-        find_available_ips, get_network_device, list_network_devices, modify_network_device,
+        find_available_ips, get_network_device, import_network_devices,
+        import_network_devices_upload, list_network_devices, modify_network_device,
         start_network_device_setup, start_network_device_setup_for_device,
     },
     ssh_authorized_keys::{
@@ -81,7 +94,7 @@ use tower_http::{
     set_header::SetResponseHeaderLayer,
     trace::{DefaultOnResponse, TraceLayer},
 };
-use tracing::Level;
+use tracing::{Level, field};
 use utoipa::{
     Modify, OpenApi,
     openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
@@ -90,83 +103,122 @@ use utoipa_swagger_ui::SwaggerUi;
 
 use self::{
     appstate::AppState,
-    auth::failed_login::FailedLoginMap,
+    auth::{
+        failed_login::FailedLoginMap,
+        rate_limit::{RateLimitedEndpoint, rate_limit},
+    },
     db::{
         AppEvent, Device, GatewayEvent, User, WireguardNetwork,
         models::{
             oauth2client::OAuth2Client,
             wireguard::{DEFAULT_DISCONNECT_THRESHOLD, DEFAULT_KEEPALIVE_INTERVAL},
         },
+        replica::ReadReplica,
     },
     grpc::{WorkerState, gateway::map::GatewayMap},
     handlers::{
         app_info::get_app_info,
         auth::{
             authenticate, email_mfa_code, email_mfa_disable, email_mfa_enable, email_mfa_init,
-            logout, mfa_disable, mfa_enable, recovery_code, request_email_mfa_code, totp_code,
-            totp_disable, totp_enable, totp_secret, webauthn_end, webauthn_finish, webauthn_init,
-            webauthn_start,
+            logout, mfa_disable, mfa_enable, recovery_code, recovery_codes_info,
+            regenerate_recovery_codes, request_email_mfa_code, request_sms_mfa_code,
+            resend_email_mfa_code, sms_mfa_code, sms_mfa_disable, sms_mfa_enable, sms_mfa_init,
+            totp_code, totp_disable, totp_enable, totp_secret, webauthn_end, webauthn_finish,
+            webauthn_init, webauthn_start,
+        },
+        client_mfa::{list_client_mfa_sessions, revoke_client_mfa_session},
+        device_approval::{approve_device, list_device_approvals, reject_device},
+        device_profile::{
+            create_device_profile, delete_device_profile, get_device_profile, list_device_profiles,
+            modify_device_profile, set_device_profile,
         },
+        enrollment_tokens::{list_enrollment_tokens, revoke_enrollment_token},
         forward_auth::forward_auth,
         group::{
-            add_group_member, create_group, delete_group, get_group, list_groups, modify_group,
-            remove_group_member,
+            add_group_member, approve_group_join_request, create_group, delete_group, get_group,
+            list_group_join_requests, list_group_quotas, list_groups, list_self_service_groups,
+            modify_group, reconcile_groups, reject_group_join_request, remove_group_member,
+            request_group_membership, set_group_owners, set_group_parent, set_group_quota,
+            set_group_self_service, set_group_sync_authority,
+        },
+        impersonation::{start_impersonation, stop_impersonation},
+        login_lockout::{list_login_lockouts, unlock_ip, unlock_user},
+        mail::{
+            list_mail_deliveries, list_mail_templates, resend_mail, reset_mail_template,
+            send_support_data, set_mail_template, test_mail,
         },
-        mail::{send_support_data, test_mail},
         openid_clients::{
             add_openid_client, change_openid_client, change_openid_client_state,
             delete_openid_client, get_openid_client, list_openid_clients,
         },
         openid_flow::{
-            authorization, discovery_keys, openid_configuration, secure_authorization, token,
-            userinfo,
+            authorization, discovery_keys, introspect, openid_configuration, revoke,
+            secure_authorization, token, userinfo,
         },
         settings::{
-            get_settings, get_settings_essentials, patch_settings, set_default_branding,
-            test_ldap_settings, update_settings,
+            delete_gateway_alert_rule, delete_ldap_operation, get_settings,
+            get_settings_essentials, list_gateway_alert_rules, list_ldap_operations,
+            list_ldap_sync_conflicts, password_hash_report, patch_settings, preview_ldap_sync,
+            retry_ldap_operation, set_default_branding, set_gateway_alert_rule, test_ldap_settings,
+            update_settings,
         },
         ssh_authorized_keys::get_authorized_keys,
-        support::{configuration, logs},
+        support::{configuration, detailed_health, diagnostics, logs, metrics, prometheus_metrics},
         updates::outdated_components,
         user::{
             add_user, change_password, change_self_password, delete_authorized_app,
-            delete_security_key, delete_user, get_user, list_users, me, modify_user,
-            reset_password, start_enrollment, start_remote_desktop_configuration,
-            username_available,
+            delete_security_key, delete_user, get_notification_settings, get_user, import_users,
+            import_users_upload, list_users, me, modify_user, reset_password,
+            set_notification_settings, start_enrollment, start_remote_desktop_configuration,
+            username_available, verify_email,
         },
+        vpn_sessions::{list_device_vpn_sessions, list_user_vpn_sessions},
+        web_sessions::{list_web_sessions, revoke_all_web_sessions, revoke_web_session},
         webhooks::{
             add_webhook, change_enabled, change_webhook, delete_webhook, get_webhook, list_webhooks,
         },
         wireguard::{
-            add_device, add_user_devices, create_network, create_network_token, delete_device,
-            delete_network, devices_stats, download_config, gateway_status, get_device,
-            import_network, list_devices, list_networks, list_user_devices, modify_device,
-            modify_network, network_details, network_stats, remove_gateway,
+            add_device, add_user_devices, clone_network, create_gateway_token, create_network,
+            create_network_token, delete_device, delete_network, devices_stats, download_config,
+            gateway_status, get_device, get_device_ips, import_network, import_network_upload,
+            list_device_key_history, list_devices, list_gateway_tokens, list_networks,
+            list_user_devices, location_connectivity, modify_device, modify_network,
+            network_aggregated_stats, network_details, network_stats, provision_device,
+            release_device_ips, remove_gateway, reserve_device_ips, revoke_gateway_token,
+            rotate_device_key, set_device_expiry, set_location_maintenance,
         },
         worker::{create_job, create_worker_token, job_status, list_workers, remove_worker},
     },
 };
 use crate::{
     db::models::wireguard::ServiceLocationMode, grpc::gateway::gen_config,
-    version::IncompatibleComponents,
+    metrics::track_http_request, telemetry::set_remote_parent, version::IncompatibleComponents,
 };
 
 pub mod appstate;
 pub mod auth;
+pub mod captcha;
 pub mod db;
 pub mod enterprise;
 mod error;
 pub mod events;
+pub mod gateway_alerts;
 pub mod grpc;
 pub mod handlers;
 pub mod headers;
+pub mod health;
+pub mod metrics;
+pub mod notification_digest;
+pub mod sms;
 pub mod support;
+pub mod telemetry;
 pub mod updates;
 pub mod utility_thread;
 pub mod version;
 pub mod wg_config;
 pub mod wireguard_peer_disconnect;
 pub mod wireguard_stats_purge;
+pub mod wireguard_stats_rollup;
 
 #[macro_use]
 extern crate tracing;
@@ -185,14 +237,32 @@ pub(crate) const KEY_LENGTH: usize = 32;
 mod openapi {
     use db::{
         AddDevice, UserDetails, UserInfo,
-        models::device::{ModifyDevice, UserDevice},
+        models::{
+            device::{ModifyDevice, UserDevice},
+            device_approval::DeviceApprovalInfo,
+            device_profile::DeviceProfile,
+            group::{GroupQuota, GroupQuotaUsage, GroupSyncAuthority},
+            group_join_request::{GroupJoinRequestInfo, SelfServiceGroup},
+            notification_digest::NotificationSettings,
+        },
     };
     use handlers::{
-        ApiResponse, EditGroupInfo, GroupInfo, PasswordChange, PasswordChangeSelf,
-        SESSION_COOKIE_NAME, StartEnrollmentRequest, Username,
-        group::{self, BulkAssignToGroupsRequest, Groups},
+        ApiResponse, EditGroupInfo, EmailVerification, GroupInfo, PasswordChange,
+        PasswordChangeSelf, SESSION_COOKIE_NAME, StartEnrollmentRequest, Username,
+        activity_log::{self, CustomActivityLogEvent},
+        client_mfa, device_approval,
+        device_profile::{self, DeviceProfileAssignment, DeviceProfileData, DeviceProfileInfo},
+        group::{
+            self, BulkAssignToGroupsRequest, GroupChanges, GroupJoinRequestData,
+            GroupMemberRequest, GroupOwnersRequest, GroupParentRequest, GroupSelfServiceRequest,
+            GroupSyncAuthorityRequest, Groups, GroupsReconciliation, GroupsReconciliationRequest,
+            MergedGroupMembers,
+        },
         user, wireguard as device, wireguard as network,
-        wireguard::AddDeviceResult,
+        wireguard::{
+            AddDeviceResult, DeviceExpiry, DeviceIpReservation, ProvisionDevice,
+            ProvisionDeviceResult, RotateDeviceKey,
+        },
     };
     use utoipa::{
         OpenApi,
@@ -200,7 +270,10 @@ mod openapi {
     };
 
     use super::*;
-    use crate::{enterprise::snat::handlers as snat, error::WebError};
+    use crate::{
+        enterprise::{handlers::posture_policy, snat::handlers as snat},
+        error::WebError,
+    };
 
     #[derive(OpenApi)]
     #[openapi(
@@ -216,13 +289,17 @@ mod openapi {
             user::modify_user,
             user::delete_user,
             user::change_self_password,
+            user::verify_email,
             user::change_password,
             user::reset_password,
             user::delete_security_key,
             user::me,
             user::delete_authorized_app,
+            user::get_notification_settings,
+            user::set_notification_settings,
             // /group
             group::bulk_assign_to_groups,
+            group::reconcile_groups,
             group::list_groups_info,
             group::list_groups,
             group::get_group,
@@ -231,28 +308,67 @@ mod openapi {
             group::delete_group,
             group::add_group_member,
             group::remove_group_member,
+            group::list_group_quotas,
+            group::set_group_quota,
+            group::set_group_sync_authority,
+            group::set_group_parent,
+            group::set_group_owners,
+            group::set_group_self_service,
+            group::list_self_service_groups,
+            group::request_group_membership,
+            group::list_group_join_requests,
+            group::approve_group_join_request,
+            group::reject_group_join_request,
             // /device
             device::add_device,
             device::modify_device,
             device::get_device,
+            device::set_device_expiry,
+            device::rotate_device_key,
             device::delete_device,
             device::list_devices,
             device::list_user_devices,
+            device::provision_device,
+            // /device-approval
+            device_approval::list_device_approvals,
+            device_approval::approve_device,
+            device_approval::reject_device,
+            // /device_profile
+            device_profile::list_device_profiles,
+            device_profile::get_device_profile,
+            device_profile::create_device_profile,
+            device_profile::modify_device_profile,
+            device_profile::delete_device_profile,
+            device_profile::set_device_profile,
             // /network
             network::create_network,
             network::modify_network,
+            network::clone_network,
+            network::set_location_maintenance,
             network::delete_network,
             network::list_networks,
             network::network_details,
+            network::get_device_ips,
+            network::reserve_device_ips,
+            network::release_device_ips,
             // /network/{location_id}/snat
 			snat::list_snat_bindings,
 			snat::create_snat_binding,
 			snat::modify_snat_binding,
 			snat::delete_snat_binding,
+            // /network/{location_id}/posture_policy
+            posture_policy::get_posture_policy,
+            posture_policy::set_posture_policy,
+            posture_policy::delete_posture_policy,
+            // /client_mfa/sessions
+            client_mfa::list_client_mfa_sessions,
+            client_mfa::revoke_client_mfa_session,
+            // /activity_log
+            activity_log::create_custom_event,
         ),
         components(
             schemas(
-                ApiResponse, UserInfo, UserDetails, UserDevice, Groups, Username, StartEnrollmentRequest, PasswordChangeSelf, PasswordChange, AddDevice, AddDeviceResult, Device, ModifyDevice, BulkAssignToGroupsRequest, GroupInfo, EditGroupInfo, WebError
+                ApiResponse, UserInfo, UserDetails, UserDevice, NotificationSettings, Groups, Username, StartEnrollmentRequest, PasswordChangeSelf, PasswordChange, EmailVerification, AddDevice, AddDeviceResult, ProvisionDevice, ProvisionDeviceResult, DeviceExpiry, DeviceIpReservation, RotateDeviceKey, Device, ModifyDevice, BulkAssignToGroupsRequest, GroupInfo, EditGroupInfo, GroupQuota, GroupQuotaUsage, GroupSyncAuthority, GroupSyncAuthorityRequest, GroupMemberRequest, GroupParentRequest, GroupOwnersRequest, GroupSelfServiceRequest, GroupJoinRequestData, MergedGroupMembers, GroupsReconciliationRequest, GroupsReconciliation, GroupChanges, GroupJoinRequestInfo, SelfServiceGroup, DeviceApprovalInfo, DeviceProfile, DeviceProfileData, DeviceProfileInfo, DeviceProfileAssignment, CustomActivityLogEvent, WebError
             ),
         ),
         tags(
@@ -282,6 +398,7 @@ Available actions:
 Available actions:
 - list all devices or user devices
 - CRUD mechanism for handling devices.
+- manage device profiles with configuration overrides shared by groups of devices
             "),
             (name = "network", description = "
 ### Endpoints that allow to control your networks.
@@ -344,11 +461,35 @@ pub fn build_webapp(
     worker_state: Arc<Mutex<WorkerState>>,
     gateway_state: Arc<Mutex<GatewayMap>>,
     pool: PgPool,
+    replica: Option<ReadReplica>,
     failed_logins: Arc<Mutex<FailedLoginMap>>,
     event_tx: UnboundedSender<ApiEvent>,
     version: Version,
     incompatible_components: Arc<RwLock<IncompatibleComponents>>,
 ) -> Router {
+    let config = server_config();
+    // validated when parsing the configuration
+    let client_ip_source: SecureClientIpSource = config
+        .client_ip_source
+        .parse()
+        .expect("Invalid client IP source");
+    // Import endpoints accept larger bodies than the rest of the API.
+    let import_body_limit = config.http_import_body_limit;
+    let appstate = AppState::new(
+        pool,
+        replica,
+        webhook_tx,
+        webhook_rx,
+        wireguard_tx,
+        mail_tx,
+        failed_logins,
+        event_tx,
+        incompatible_components,
+    );
+    let limit = |endpoint: RateLimitedEndpoint| {
+        middleware::from_fn_with_state((appstate.clone(), endpoint), rate_limit)
+    };
+
     let webapp: Router<AppState> = Router::new()
         .route("/", get(index))
         .route("/{*path}", get(index))
@@ -361,21 +502,31 @@ pub fn build_webapp(
         "/api/v1",
         Router::new()
             .route("/health", get(health_check))
+            .route("/health/detailed", get(detailed_health))
             .route("/info", get(get_app_info))
             .route("/ssh_authorized_keys", get(get_authorized_keys))
             .route("/api-docs", get(openapi))
             .route("/updates", get(check_new_version))
             // /auth
-            .route("/auth", post(authenticate))
+            .route(
+                "/auth",
+                post(authenticate).layer(limit(RateLimitedEndpoint::Login)),
+            )
             .route("/auth/logout", post(logout))
             .route("/auth/mfa", put(mfa_enable).delete(mfa_disable))
             .route("/auth/webauthn/init", post(webauthn_init))
             .route("/auth/webauthn/finish", post(webauthn_finish))
             .route("/auth/webauthn/start", post(webauthn_start))
-            .route("/auth/webauthn", post(webauthn_end))
+            .route(
+                "/auth/webauthn",
+                post(webauthn_end).layer(limit(RateLimitedEndpoint::Mfa)),
+            )
             .route("/auth/totp/init", post(totp_secret))
             .route("/auth/totp", post(totp_enable).delete(totp_disable))
-            .route("/auth/totp/verify", post(totp_code))
+            .route(
+                "/auth/totp/verify",
+                post(totp_code).layer(limit(RateLimitedEndpoint::Mfa)),
+            )
             .route("/auth/email/init", post(email_mfa_init))
             .route(
                 "/auth/email",
@@ -383,22 +534,56 @@ pub fn build_webapp(
                     .post(email_mfa_enable)
                     .delete(email_mfa_disable),
             )
-            .route("/auth/email/verify", post(email_mfa_code))
-            .route("/auth/recovery", post(recovery_code))
+            .route("/auth/email/resend", post(resend_email_mfa_code))
+            .route(
+                "/auth/email/verify",
+                post(email_mfa_code).layer(limit(RateLimitedEndpoint::Mfa)),
+            )
+            .route("/auth/sms/init", post(sms_mfa_init))
+            .route(
+                "/auth/sms",
+                get(request_sms_mfa_code)
+                    .post(sms_mfa_enable)
+                    .delete(sms_mfa_disable),
+            )
+            .route(
+                "/auth/sms/verify",
+                post(sms_mfa_code).layer(limit(RateLimitedEndpoint::Mfa)),
+            )
+            .route(
+                "/auth/recovery",
+                post(recovery_code).layer(limit(RateLimitedEndpoint::Mfa)),
+            )
+            .route("/auth/recovery/regenerate", post(regenerate_recovery_codes))
             // /user
             .route("/user", get(list_users).post(add_user))
             .route("/user/{username}", get(get_user))
-            .route("/user/{username}/start_enrollment", post(start_enrollment))
+            .route(
+                "/user/{username}/start_enrollment",
+                post(start_enrollment).layer(limit(RateLimitedEndpoint::Enrollment)),
+            )
             .route(
                 "/user/{username}/start_desktop",
                 post(start_remote_desktop_configuration),
             )
             .route("/user/available", post(username_available))
+            .route(
+                "/user/import",
+                post(import_users).layer(DefaultBodyLimit::max(import_body_limit)),
+            )
+            .route(
+                "/user/import/upload",
+                post(import_users_upload).layer(DefaultBodyLimit::max(import_body_limit)),
+            )
             .route("/user/{username}", put(modify_user).delete(delete_user))
             // FIXME: username `change_password` is invalid
             .route("/user/change_password", put(change_self_password))
+            .route("/user/verify_email", post(verify_email))
             .route("/user/{username}/password", put(change_password))
-            .route("/user/{username}/reset_password", post(reset_password))
+            .route(
+                "/user/{username}/reset_password",
+                post(reset_password).layer(limit(RateLimitedEndpoint::PasswordReset)),
+            )
             // auth keys
             .route(
                 "/user/{username}/auth_key",
@@ -441,6 +626,32 @@ pub fn build_webapp(
                 delete(delete_authorized_app),
             )
             .route("/user/{username}/mfa", delete(disable_user_mfa))
+            .route("/user/{username}/recovery_codes", get(recovery_codes_info))
+            .route(
+                "/user/{username}/notifications",
+                get(get_notification_settings).put(set_notification_settings),
+            )
+            .route("/user/{username}/vpn-sessions", get(list_user_vpn_sessions))
+            .route(
+                "/user/{username}/session",
+                get(list_web_sessions).delete(revoke_all_web_sessions),
+            )
+            .route(
+                "/user/{username}/session/{session_id}",
+                delete(revoke_web_session),
+            )
+            .route("/user/{username}/lockout", delete(unlock_user))
+            .route("/user/{username}/impersonate", post(start_impersonation))
+            .route("/impersonation", delete(stop_impersonation))
+            // login lockouts
+            .route("/lockout", get(list_login_lockouts))
+            .route("/lockout/ip/{ip}", delete(unlock_ip))
+            // enrollment tokens
+            .route("/enrollment_token", get(list_enrollment_tokens))
+            .route(
+                "/enrollment_token/{token_id}",
+                delete(revoke_enrollment_token),
+            )
             // forward_auth
             .route("/forward_auth", get(forward_auth))
             // group
@@ -453,17 +664,51 @@ pub fn build_webapp(
                     .post(add_group_member),
             )
             .route("/group/{name}/user/{username}", delete(remove_group_member))
+            .route(
+                "/group/{name}/sync_authority",
+                put(set_group_sync_authority),
+            )
+            .route("/group/{name}/parent", put(set_group_parent))
+            .route("/group/{name}/owners", put(set_group_owners))
+            .route("/group/{name}/self_service", put(set_group_self_service))
+            .route("/group/{name}/join_request", post(request_group_membership))
             .route("/group-info", get(list_groups_info))
+            .route("/groups", put(reconcile_groups))
             .route("/groups-assign", post(bulk_assign_to_groups))
+            .route("/group-quota", get(list_group_quotas))
+            .route("/group-quota/{name}", put(set_group_quota))
+            .route("/group-self-service", get(list_self_service_groups))
+            .route("/group-join-request", get(list_group_join_requests))
+            .route(
+                "/group-join-request/{id}/approve",
+                post(approve_group_join_request),
+            )
+            .route(
+                "/group-join-request/{id}/reject",
+                post(reject_group_join_request),
+            )
             // mail
             .route("/mail/test", post(test_mail))
             .route("/mail/support", post(send_support_data))
+            .route("/mail/templates", get(list_mail_templates))
+            .route(
+                "/mail/templates/{kind}",
+                put(set_mail_template).delete(reset_mail_template),
+            )
+            .route("/mail/delivery", get(list_mail_deliveries))
+            .route("/mail/delivery/{id}/resend", post(resend_mail))
             // settings
             .route(
                 "/settings",
                 get(get_settings).put(update_settings).patch(patch_settings),
             )
             .route("/settings/{id}", put(set_default_branding))
+            .route("/settings/password_hashes", get(password_hash_report))
+            .route("/settings/gateway_alerts", get(list_gateway_alert_rules))
+            .route(
+                "/settings/gateway_alerts/{location_id}",
+                put(set_gateway_alert_rule).delete(delete_gateway_alert_rule),
+            )
             // settings for frontend
             .route("/settings_essentials", get(get_settings_essentials))
             // enterprise settings
@@ -474,6 +719,8 @@ pub fn build_webapp(
             // support
             .route("/support/configuration", get(configuration))
             .route("/support/logs", get(logs))
+            .route("/diagnostics", get(diagnostics))
+            .route("/diagnostics/metrics", get(metrics))
             // webhooks
             .route("/webhook", post(add_webhook).get(list_webhooks))
             .route(
@@ -485,8 +732,15 @@ pub fn build_webapp(
             )
             // ldap
             .route("/ldap/test", get(test_ldap_settings))
+            .route("/ldap/conflicts", get(list_ldap_sync_conflicts))
+            .route("/ldap/sync/preview", get(preview_ldap_sync))
+            .route("/ldap/operations", get(list_ldap_operations))
+            .route("/ldap/operations/{id}", delete(delete_ldap_operation))
+            .route("/ldap/operations/{id}/retry", post(retry_ldap_operation))
             // activity log
-            .route("/activity_log", get(get_activity_log_events)),
+            .route("/activity_log", get(get_activity_log_events))
+            .route("/activity_log/export", get(export_activity_log_events))
+            .route("/activity_log/custom", post(create_custom_event)),
     );
 
     // Enterprise features
@@ -498,15 +752,39 @@ pub fn build_webapp(
                 get(get_current_openid_provider).post(add_openid_provider),
             )
             .route("/provider/{name}", delete(delete_openid_provider))
+            .route("/group_mapping/preview", post(preview_group_claim_mapping))
             .route("/callback", post(auth_callback))
             .route("/auth_info", get(get_auth_info)),
     );
 
+    let webapp = webapp.nest(
+        "/api/v1/saml",
+        Router::new()
+            .route(
+                "/provider",
+                get(list_saml_providers).post(add_saml_provider),
+            )
+            .route(
+                "/provider/{name}",
+                put(modify_saml_provider).delete(delete_saml_provider),
+            )
+            .route("/callback", post(saml_callback))
+            .route("/{name}/metadata", get(get_saml_metadata))
+            .route("/{name}/auth_info", get(get_saml_auth_info))
+            .route("/{name}/link", get(get_saml_link_info))
+            .route("/{name}/acs", post(saml_acs)),
+    );
+
     let webapp = webapp.nest(
         "/api/v1",
         Router::new()
             .route("/enterprise_info", get(check_enterprise_info))
-            .route("/test_directory_sync", get(test_dirsync_connection)),
+            .route("/test_directory_sync", get(test_dirsync_connection))
+            .route("/provisioning_policy", get(list_provisioning_policies))
+            .route(
+                "/provisioning_policy/{source}",
+                put(modify_provisioning_policy),
+            ),
     );
 
     // activity log stream
@@ -536,8 +814,16 @@ pub fn build_webapp(
                         .post(change_openid_client_state)
                         .delete(delete_openid_client),
                 )
+                .route(
+                    "/{client_id}/client_credentials",
+                    get(get_client_credentials)
+                        .put(set_client_credentials)
+                        .delete(delete_client_credentials),
+                )
                 .route("/authorize", get(authorization).post(secure_authorization))
                 .route("/token", post(token))
+                .route("/introspect", post(introspect))
+                .route("/revoke", post(revoke))
                 .route("/userinfo", get(userinfo)),
         )
         .route(
@@ -575,13 +861,46 @@ pub fn build_webapp(
                 "/device/{device_id}",
                 put(modify_device).get(get_device).delete(delete_device),
             )
+            .route("/device/{device_id}/profile", put(set_device_profile))
+            .route("/device/{device_id}/expiry", put(set_device_expiry))
+            .route("/device/{device_id}/key-rotation", post(rotate_device_key))
+            .route(
+                "/device/{device_id}/key-history",
+                get(list_device_key_history),
+            )
+            .route(
+                "/device/{device_id}/vpn-sessions",
+                get(list_device_vpn_sessions),
+            )
             .route("/device", get(list_devices))
+            .route("/device-approval", get(list_device_approvals))
+            .route("/device-approval/{id}/approve", post(approve_device))
+            .route("/device-approval/{id}/reject", post(reject_device))
+            .route(
+                "/device_profile",
+                post(create_device_profile).get(list_device_profiles),
+            )
+            .route(
+                "/device_profile/{id}",
+                get(get_device_profile)
+                    .put(modify_device_profile)
+                    .delete(delete_device_profile),
+            )
             .route("/device/user/{username}", get(list_user_devices))
+            .route("/device/user/{username}/provision", post(provision_device))
             // Network devices, as opposed to user devices
             .route(
                 "/device/network",
                 post(add_network_device).get(list_network_devices),
             )
+            .route(
+                "/device/network/import",
+                post(import_network_devices).layer(DefaultBodyLimit::max(import_body_limit)),
+            )
+            .route(
+                "/device/network/import/upload",
+                post(import_network_devices_upload).layer(DefaultBodyLimit::max(import_body_limit)),
+            )
             .route(
                 "/device/network/ip/{network_id}",
                 get(find_available_ips).post(check_ip_availability),
@@ -605,7 +924,14 @@ pub fn build_webapp(
                 post(start_network_device_setup_for_device),
             )
             .route("/network", post(create_network).get(list_networks))
-            .route("/network/import", post(import_network))
+            .route(
+                "/network/import",
+                post(import_network).layer(DefaultBodyLimit::max(import_body_limit)),
+            )
+            .route(
+                "/network/import/upload",
+                post(import_network_upload).layer(DefaultBodyLimit::max(import_body_limit)),
+            )
             .route("/network/stats", get(networks_overview_stats))
             .route("/network/gateways", get(all_gateways_status))
             .route(
@@ -614,7 +940,16 @@ pub fn build_webapp(
                     .delete(delete_network)
                     .get(network_details),
             )
+            .route("/network/{network_id}/clone", post(clone_network))
+            .route(
+                "/network/{network_id}/maintenance",
+                put(set_location_maintenance),
+            )
             .route("/network/{network_id}/gateways", get(gateway_status))
+            .route(
+                "/network/{network_id}/connectivity",
+                get(location_connectivity),
+            )
             .route(
                 "/network/{network_id}/gateways/{gateway_id}",
                 delete(remove_gateway),
@@ -624,9 +959,27 @@ pub fn build_webapp(
                 "/network/{network_id}/device/{device_id}/config",
                 get(download_config),
             )
+            .route(
+                "/network/{network_id}/device/{device_id}/ip",
+                get(get_device_ips)
+                    .put(reserve_device_ips)
+                    .delete(release_device_ips),
+            )
             .route("/network/{network_id}/token", get(create_network_token))
+            .route(
+                "/network/{network_id}/gateway_token",
+                get(list_gateway_tokens).post(create_gateway_token),
+            )
+            .route(
+                "/network/{network_id}/gateway_token/{token_id}",
+                delete(revoke_gateway_token),
+            )
             .route("/network/{network_id}/stats/users", get(devices_stats))
             .route("/network/{network_id}/stats", get(network_stats))
+            .route(
+                "/network/{network_id}/stats/aggregated",
+                get(network_aggregated_stats),
+            )
             .route(
                 "/network/{location_id}/snat",
                 get(list_snat_bindings).post(create_snat_binding),
@@ -635,7 +988,14 @@ pub fn build_webapp(
                 "/network/{location_id}/snat/{user_id}",
                 put(modify_snat_binding).delete(delete_snat_binding),
             )
+            .route(
+                "/network/{location_id}/posture_policy",
+                get(get_posture_policy)
+                    .put(set_posture_policy)
+                    .delete(delete_posture_policy),
+            )
             .route("/outdated", get(outdated_components))
+            .route("/metrics", get(prometheus_metrics))
             .layer(Extension(gateway_state)),
     );
 
@@ -649,38 +1009,66 @@ pub fn build_webapp(
             .layer(Extension(worker_state)),
     );
 
-    let webapp = webapp.layer(DefguardVersionLayer::new(version)).layer(
-        SetResponseHeaderLayer::if_not_present(
+    let webapp = webapp.nest(
+        "/api/v1/client_mfa",
+        Router::new()
+            .route("/sessions", get(list_client_mfa_sessions))
+            .route(
+                "/sessions/{location_id}/{device_id}",
+                delete(revoke_client_mfa_session),
+            ),
+    );
+
+    let webapp = webapp
+        .layer(middleware::from_fn(track_http_request))
+        .layer(DefaultBodyLimit::max(config.http_body_limit))
+        .layer(DefguardVersionLayer::new(version))
+        .layer(SetResponseHeaderLayer::if_not_present(
             headers::CONTENT_SECURITY_POLICY_HEADER_NAME,
             headers::CONTENT_SECURITY_POLICY_HEADER_VALUE,
-        ),
-    );
+        ));
 
     let swagger =
         SwaggerUi::new("/api-docs").url("/api-docs/openapi.json", openapi::ApiDoc::openapi());
+    let span_ip_source = client_ip_source.clone();
 
     webapp
-        .with_state(AppState::new(
-            pool,
-            webhook_tx,
-            webhook_rx,
-            wireguard_tx,
-            mail_tx,
-            failed_logins,
-            event_tx,
-            incompatible_components,
-        ))
+        .with_state(appstate)
         .layer(
             TraceLayer::new_for_http()
-                .make_span_with(|request: &Request<_>| {
-                    info_span!(
+                .make_span_with(move |request: &Request<_>| {
+                    // `user` is recorded once the request has been authenticated
+                    let span = info_span!(
                         "http_request",
                         method = ?request.method(),
                         path = ?request.uri(),
-                    )
+                        request_id = field::Empty,
+                        ip = field::Empty,
+                        user = field::Empty,
+                    );
+                    if let Ok(SecureClientIp(ip)) = SecureClientIp::from(
+                        &span_ip_source,
+                        request.headers(),
+                        request.extensions(),
+                    ) {
+                        span.record("ip", field::display(ip));
+                    }
+                    if let Some(request_id) = request
+                        .headers()
+                        .get(&headers::REQUEST_ID_HEADER_NAME)
+                        .and_then(|value| value.to_str().ok())
+                    {
+                        span.record("request_id", request_id);
+                    }
+                    set_remote_parent(&span, request.headers());
+                    span
                 })
                 .on_response(DefaultOnResponse::new().level(Level::INFO)),
         )
+        // runs before the trace layer, so the span includes the request ID
+        .layer(middleware::from_fn(headers::propagate_request_id))
+        // used by `SecureClientIp` extractors
+        .layer(client_ip_source.into_extension())
         .merge(swagger)
 }
 
@@ -694,9 +1082,11 @@ pub async fn run_web_server(
     wireguard_tx: Sender<GatewayEvent>,
     mail_tx: UnboundedSender<Mail>,
     pool: PgPool,
+    replica: Option<ReadReplica>,
     failed_logins: Arc<Mutex<FailedLoginMap>>,
     event_tx: UnboundedSender<ApiEvent>,
     incompatible_components: Arc<RwLock<IncompatibleComponents>>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
 ) -> Result<(), anyhow::Error> {
     let webapp = build_webapp(
         webhook_tx,
@@ -706,6 +1096,7 @@ pub async fn run_web_server(
         worker_state,
         gateway_state,
         pool,
+        replica,
         failed_logins,
         event_tx,
         Version::parse(VERSION)?,
@@ -724,8 +1115,11 @@ pub async fn run_web_server(
         listener,
         webapp.into_make_service_with_connect_info::<SocketAddr>(),
     )
+    .with_graceful_shutdown(shutdown)
     .await
-    .map_err(|err| anyhow!("Web server can't be started {err}"))
+    .map_err(|err| anyhow!("Web server can't be started {err}"))?;
+    info!("Web server stopped");
+    Ok(())
 }
 
 /// Automates test objects creation to easily setup development environment.
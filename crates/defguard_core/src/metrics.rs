@@ -0,0 +1,230 @@
+//! Prometheus metrics describing the state of the deployment.
+//!
+//! Queue saturation, request latencies and MFA failures are recorded in
+//! [`defguard_common::diagnostics`] where they happen, the rest is collected on every scrape.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http,
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use defguard_common::{
+    db::{Id, models::Settings},
+    diagnostics::{GRPC_REQUESTS, HTTP_REQUESTS, MFA_FAILURES, labels, write_metric},
+};
+use sqlx::{query, query_as};
+use tonic::server::NamedService;
+use tower::{Layer, Service};
+
+use crate::{
+    appstate::AppState, db::models::wireguard::WIREGUARD_MAX_HANDSHAKE,
+    grpc::gateway::map::GatewayMap, handlers::support::render_queue_metrics,
+};
+
+/// Records processing time of HTTP requests handled by the web server.
+pub(crate) async fn track_http_request(request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    // Label by route instead of the actual path to keep the number of series bounded.
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "unmatched".to_string(), |path| path.as_str().to_string());
+    let started = Instant::now();
+    let response = next.run(request).await;
+    HTTP_REQUESTS.observe(
+        &[("method", method.as_str()), ("route", &route)],
+        started.elapsed(),
+    );
+    response
+}
+
+/// Tower layer recording processing time of gRPC requests.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GrpcMetricsLayer;
+
+impl<S> Layer<S> for GrpcMetricsLayer {
+    type Service = GrpcMetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcMetricsService { inner }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GrpcMetricsService<S> {
+    inner: S,
+}
+
+impl<S: NamedService> NamedService for GrpcMetricsService<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S, B> Service<http::Request<B>> for GrpcMetricsService<S>
+where
+    S: Service<http::Request<B>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        // gRPC path is `/<package>.<service>/<method>`
+        let path = request.uri().path().to_string();
+        let started = Instant::now();
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await;
+            // For streaming methods this is the time until the stream has been established.
+            GRPC_REQUESTS.observe(&[("method", &path)], started.elapsed());
+            response
+        })
+    }
+}
+
+struct LocationPeers {
+    id: Id,
+    name: String,
+    peers: i64,
+}
+
+/// Renders all metrics in Prometheus text exposition format.
+pub(crate) async fn render(
+    appstate: &AppState,
+    gateway_state: &Arc<Mutex<GatewayMap>>,
+) -> Result<String, sqlx::Error> {
+    let mut metrics = render_queue_metrics(appstate);
+
+    let oldest_handshake = (Utc::now() - WIREGUARD_MAX_HANDSHAKE).naive_utc();
+    let locations = query_as!(
+        LocationPeers,
+        "SELECT n.id, n.name, COUNT(DISTINCT s.device_id) \"peers!\" \
+        FROM wireguard_network n \
+        LEFT JOIN wireguard_peer_stats s ON s.network = n.id AND s.latest_handshake >= $1 \
+        GROUP BY n.id, n.name ORDER BY n.id",
+        oldest_handshake
+    )
+    .fetch_all(&appstate.pool)
+    .await?;
+    write_metric(
+        &mut metrics,
+        "defguard_location_connected_peers",
+        "gauge",
+        "Number of peers with a recent handshake in the location.",
+        locations.iter().map(|location| {
+            (
+                labels(&[
+                    ("location_id", &location.id.to_string()),
+                    ("location", &location.name),
+                ]),
+                location.peers as f64,
+            )
+        }),
+    );
+
+    let gateways = gateway_state
+        .lock()
+        .expect("Failed to acquire gateway state lock")
+        .as_flattened();
+    let mut gateways = gateways.into_values().flatten().collect::<Vec<_>>();
+    gateways.sort_by(|a, b| (a.network_id, &a.hostname).cmp(&(b.network_id, &b.hostname)));
+    write_metric(
+        &mut metrics,
+        "defguard_gateway_connected",
+        "gauge",
+        "Whether the gateway is connected to core (1) or not (0).",
+        gateways.iter().map(|gateway| {
+            (
+                labels(&[
+                    ("location_id", &gateway.network_id.to_string()),
+                    ("location", &gateway.network_name),
+                    ("hostname", &gateway.hostname),
+                ]),
+                f64::from(u8::from(gateway.connected)),
+            )
+        }),
+    );
+
+    let settings = Settings::get_current_settings();
+    let ldap_operations = query!(
+        "SELECT COUNT(*) FILTER (WHERE NOT failed) \"pending!\", \
+        COUNT(*) FILTER (WHERE failed) \"failed!\" FROM ldap_operation"
+    )
+    .fetch_one(&appstate.pool)
+    .await?;
+    write_metric(
+        &mut metrics,
+        "defguard_ldap_enabled",
+        "gauge",
+        "Whether LDAP integration is enabled.",
+        [(String::new(), f64::from(u8::from(settings.ldap_enabled)))],
+    );
+    write_metric(
+        &mut metrics,
+        "defguard_ldap_sync_enabled",
+        "gauge",
+        "Whether LDAP synchronization is enabled.",
+        [(
+            String::new(),
+            f64::from(u8::from(settings.ldap_sync_enabled)),
+        )],
+    );
+    write_metric(
+        &mut metrics,
+        "defguard_ldap_in_sync",
+        "gauge",
+        "Whether users and groups are in sync with LDAP.",
+        [(
+            String::new(),
+            f64::from(u8::from(!settings.ldap_sync_status.is_out_of_sync())),
+        )],
+    );
+    write_metric(
+        &mut metrics,
+        "defguard_ldap_queued_operations",
+        "gauge",
+        "Number of LDAP modifications waiting in the retry queue.",
+        [
+            (
+                labels(&[("state", "pending")]),
+                ldap_operations.pending as f64,
+            ),
+            (
+                labels(&[("state", "failed")]),
+                ldap_operations.failed as f64,
+            ),
+        ],
+    );
+
+    MFA_FAILURES.render(
+        &mut metrics,
+        "defguard_mfa_failures_total",
+        "Number of failed MFA attempts.",
+    );
+    HTTP_REQUESTS.render(
+        &mut metrics,
+        "defguard_http_request_duration_seconds",
+        "HTTP request processing time in seconds.",
+    );
+    GRPC_REQUESTS.render(
+        &mut metrics,
+        "defguard_grpc_request_duration_seconds",
+        "gRPC request processing time in seconds.",
+    );
+
+    Ok(metrics)
+}
@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use defguard_common::db::Id;
+use defguard_mail::{
+    Mail,
+    templates::{DigestNotification, TemplateError, notification_digest_mail},
+};
+use sqlx::PgPool;
+use thiserror::Error;
+use tokio::{sync::mpsc::UnboundedSender, time::sleep};
+
+use crate::db::{User, models::notification_digest::DigestEntry};
+
+// How long to sleep between loop iterations
+const DIGEST_LOOP_SLEEP: Duration = Duration::from_secs(600); // 10 minutes
+// Digest is sent once the oldest notification included in it is that old
+const DIGEST_PERIOD: TimeDelta = TimeDelta::days(1);
+static NOTIFICATION_DIGEST_SUBJECT: &str = "Defguard: daily notification digest";
+
+#[derive(Debug, Error)]
+enum DigestError {
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+    #[error(transparent)]
+    Template(#[from] TemplateError),
+    #[error("Mail channel closed")]
+    MailChannel,
+}
+
+/// Sends all notifications held back for the user in a single email.
+async fn send_digest(
+    pool: &PgPool,
+    mail_tx: &UnboundedSender<Mail>,
+    user_id: Id,
+) -> Result<(), DigestError> {
+    let entries = DigestEntry::all_for_user(pool, user_id).await?;
+    let ids: Vec<Id> = entries.iter().map(|entry| entry.id).collect();
+    // the user may have been removed in the meantime, entries are removed along with it
+    let Some(user) = User::find_by_id(pool, user_id).await? else {
+        return Ok(());
+    };
+
+    let notifications: Vec<_> = entries
+        .into_iter()
+        .map(|entry| DigestNotification {
+            title: entry.title,
+            summary: entry.summary,
+            created: entry.created_at,
+        })
+        .collect();
+    let mail = Mail {
+        to: user.email.clone(),
+        subject: NOTIFICATION_DIGEST_SUBJECT.to_string(),
+        content: notification_digest_mail(&notifications)?,
+        plain_content: None,
+        attachments: Vec::new(),
+        result_tx: None,
+    };
+    mail_tx.send(mail).map_err(|_| DigestError::MailChannel)?;
+    DigestEntry::delete_many(pool, &ids).await?;
+    info!(
+        "Sent digest of {} notifications to user {}",
+        ids.len(),
+        user.username
+    );
+
+    Ok(())
+}
+
+/// Periodically sends daily digests of notifications held back for users who opted for them.
+#[instrument(skip_all)]
+pub async fn run_periodic_notification_digest(
+    pool: PgPool,
+    mail_tx: UnboundedSender<Mail>,
+) -> Result<(), sqlx::Error> {
+    info!("Starting periodic notification digest");
+
+    loop {
+        debug!("Sending notification digests");
+        let created_before = Utc::now().naive_utc() - DIGEST_PERIOD;
+        match DigestEntry::users_due(&pool, created_before).await {
+            Ok(user_ids) => {
+                for user_id in user_ids {
+                    if let Err(err) = send_digest(&pool, &mail_tx, user_id).await {
+                        error!("Failed to send notification digest to user {user_id}: {err}");
+                    }
+                }
+            }
+            Err(err) => error!("Failed to fetch pending notification digests: {err}"),
+        }
+
+        // wait till next iteration
+        debug!("Sleeping until next iteration");
+        sleep(DIGEST_LOOP_SLEEP).await;
+    }
+}
@@ -0,0 +1,84 @@
+//! Sending text messages through the SMS gateway configured in settings.
+
+use defguard_common::db::models::Settings;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SmsError {
+    #[error("SMS gateway is not configured")]
+    NotConfigured,
+    #[error("SMS gateway request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("SMS gateway rejected the message with status {0}")]
+    Rejected(reqwest::StatusCode),
+}
+
+/// Common interface of SMS gateways.
+///
+/// To support a new kind of gateway, implement this trait for it and construct it in
+/// [`send_sms`] based on the settings.
+#[trait_variant::make(Send)]
+pub trait SmsSender {
+    /// Send a text message with `body` to the `to` phone number.
+    async fn send_sms(&self, to: &str, body: &str) -> Result<(), SmsError>;
+}
+
+/// Gateway with a Twilio-style HTTP API: a form-encoded POST request with `To`, `From` and
+/// `Body` fields, optionally authenticated with HTTP basic auth.
+pub struct HttpSmsSender {
+    url: String,
+    user: Option<String>,
+    password: Option<String>,
+    sender: String,
+}
+
+impl HttpSmsSender {
+    pub fn from_settings(settings: &Settings) -> Result<Self, SmsError> {
+        if !settings.sms_configured() {
+            return Err(SmsError::NotConfigured);
+        }
+        let (Some(url), Some(sender)) = (&settings.sms_gateway_url, &settings.sms_sender) else {
+            return Err(SmsError::NotConfigured);
+        };
+        Ok(Self {
+            url: url.clone(),
+            user: settings
+                .sms_gateway_user
+                .clone()
+                .filter(|user| !user.is_empty()),
+            password: settings
+                .sms_gateway_password
+                .as_ref()
+                .map(|password| password.expose_secret().to_string()),
+            sender: sender.clone(),
+        })
+    }
+}
+
+impl SmsSender for HttpSmsSender {
+    async fn send_sms(&self, to: &str, body: &str) -> Result<(), SmsError> {
+        let mut request = reqwest::Client::new().post(&self.url).form(&[
+            ("To", to),
+            ("From", &self.sender),
+            ("Body", body),
+        ]);
+        if let Some(user) = &self.user {
+            request = request.basic_auth(user, self.password.as_ref());
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(SmsError::Rejected(response.status()));
+        }
+        Ok(())
+    }
+}
+
+/// Send a text message through the SMS gateway configured in settings.
+pub async fn send_sms(to: &str, body: &str) -> Result<(), SmsError> {
+    let settings = Settings::get_current_settings();
+    let sender = HttpSmsSender::from_settings(&settings)?;
+    debug!("Sending SMS to {to}");
+    sender.send_sms(to, body).await?;
+    info!("Sent SMS to {to}");
+    Ok(())
+}
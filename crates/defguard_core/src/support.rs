@@ -27,6 +27,7 @@ pub async fn dump_config(db: &PgPool) -> Value {
     let settings = match Settings::get(db).await {
         Ok(Some(mut settings)) => {
             settings.smtp_password = None;
+            settings.sms_gateway_password = None;
             json!(settings)
         }
         Ok(None) => json!({"error": "Settings not found"}),
@@ -0,0 +1,67 @@
+//! Export of tracing spans to OpenTelemetry backends such as Jaeger or Tempo.
+//!
+//! Export is enabled by setting `DEFGUARD_OTLP_ENDPOINT`. Incoming HTTP and gRPC requests
+//! carrying W3C trace context headers continue the caller's trace.
+
+use axum::http::{HeaderMap, HeaderName};
+use defguard_common::config::DefGuardConfig;
+use opentelemetry::{global, propagation::Extractor, trace::TracerProvider as _};
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{Resource, propagation::TraceContextPropagator, trace::SdkTracerProvider};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{Layer, Registry};
+
+pub type TelemetryLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Reads trace context from request headers.
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+/// Builds a tracing layer exporting spans over OTLP/gRPC if an endpoint is configured.
+///
+/// The returned provider should be shut down before exiting to flush pending spans.
+pub fn otlp_layer(
+    config: &DefGuardConfig,
+) -> Result<Option<(TelemetryLayer, SdkTracerProvider)>, ExporterBuildError> {
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(None);
+    };
+    let exporter = SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint.as_str())
+        .build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.otlp_service_name.clone())
+                .build(),
+        )
+        .build();
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    global::set_tracer_provider(provider.clone());
+
+    let layer = tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer("defguard"))
+        .boxed();
+
+    Ok(Some((layer, provider)))
+}
+
+/// Makes `span` a child of the remote span described by trace context headers, if present.
+/// Does nothing unless span export is enabled.
+pub(crate) fn set_remote_parent(span: &Span, headers: &HeaderMap) {
+    let context =
+        global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    let _ = span.set_parent(context);
+}
@@ -1,22 +1,51 @@
-use std::{collections::HashSet, time::Duration};
+use std::{
+    collections::HashSet,
+    net::{IpAddr, Ipv4Addr},
+    time::Duration,
+};
 
-use defguard_common::db::Id;
+use chrono::{TimeDelta, Utc};
+use defguard_common::{
+    db::{Id, models::Settings},
+    diagnostics::{LDAP_SYNC, UTILITY_THREAD},
+};
+use defguard_mail::Mail;
 use sqlx::{PgPool, query_as};
 use tokio::{
-    sync::broadcast::Sender,
+    sync::{broadcast::Sender, mpsc::UnboundedSender},
     time::{Instant, sleep},
 };
 use tracing::Instrument;
 
 use crate::{
-    db::{GatewayEvent, WireguardNetwork, models::wireguard::ServiceLocationMode},
+    db::{
+        Device, GatewayEvent, Group, User, WireguardNetwork,
+        models::{
+            activity_log::ActivityLogEvent,
+            device::{DeviceInfo, DeviceNetworkInfo, WireguardNetworkDevice},
+            group::{NetworkAccessWindow, remove_expired_memberships},
+            wireguard::ServiceLocationMode,
+        },
+    },
     enterprise::{
-        db::models::acl::{AclRule, RuleState},
+        db::models::{
+            acl::{AclRule, RuleState},
+            api_tokens::ApiToken,
+        },
         directory_sync::{do_directory_sync, get_directory_sync_interval},
         is_business_license_active,
-        ldap::{do_ldap_sync, sync::get_ldap_sync_interval},
+        ldap::{
+            do_ldap_sync,
+            queue::do_ldap_operation_queue,
+            sync::get_ldap_sync_interval,
+            utils::{ldap_remove_user_from_groups, ldap_update_user_state},
+        },
         limits::do_count_update,
     },
+    events::{InternalEvent, InternalEventContext},
+    grpc::gateway::send_wireguard_event,
+    handlers::mail::send_device_expired_email,
+    hashset,
     updates::do_new_version_check,
 };
 
@@ -26,11 +55,20 @@ const COUNT_UPDATE_INTERVAL: u64 = 60 * 60;
 const UPDATES_CHECK_INTERVAL: u64 = 60 * 60 * 6;
 const EXPIRED_ACL_RULES_CHECK_INTERVAL: u64 = 60 * 5;
 const ENTERPRISE_STATUS_CHECK_INTERVAL: u64 = 60 * 5;
+const EXPIRED_GROUP_MEMBERSHIPS_CHECK_INTERVAL: u64 = 60;
+const ACTIVITY_LOG_RETENTION_CHECK_INTERVAL: u64 = 60 * 60;
+const LDAP_OPERATION_QUEUE_INTERVAL: u64 = 30;
+const ACCESS_WINDOWS_CHECK_INTERVAL: u64 = 60;
+const SCHEDULED_DEACTIVATIONS_CHECK_INTERVAL: u64 = 60;
+const EXPIRED_DEVICES_CHECK_INTERVAL: u64 = 60;
+const PSK_ROTATION_CHECK_INTERVAL: u64 = 60 * 5;
 
 #[instrument(skip_all)]
 pub async fn run_utility_thread(
     pool: &PgPool,
     wireguard_tx: Sender<GatewayEvent>,
+    mail_tx: UnboundedSender<Mail>,
+    internal_event_tx: UnboundedSender<InternalEvent>,
 ) -> Result<(), anyhow::Error> {
     let mut last_count_update = Instant::now();
     let mut last_directory_sync = Instant::now();
@@ -38,6 +76,13 @@ pub async fn run_utility_thread(
     let mut last_ldap_sync = Instant::now();
     let mut last_expired_acl_rules_check = Instant::now();
     let mut last_enterprise_status_check = Instant::now();
+    let mut last_expired_group_memberships_check = Instant::now();
+    let mut last_activity_log_retention_check = Instant::now();
+    let mut last_ldap_operation_queue_check = Instant::now();
+    let mut last_access_windows_check = Instant::now();
+    let mut last_scheduled_deactivations_check = Instant::now();
+    let mut last_expired_devices_check = Instant::now();
+    let mut last_psk_rotation_check = Instant::now();
 
     // helper variable which stores previous enterprise features status
     let mut enterprise_enabled = is_business_license_active();
@@ -71,11 +116,22 @@ pub async fn run_utility_thread(
     };
 
     let ldap_sync_task = || async {
-        if let Err(e) = do_ldap_sync(pool)
+        if let Err(e) = do_ldap_sync(pool, &internal_event_tx)
             .instrument(info_span!("ldap_sync_task"))
             .await
         {
             error!("There was an error while performing LDAP sync job: {e}");
+            LDAP_SYNC.record_error(e);
+        }
+    };
+
+    let ldap_operation_queue_task = || async {
+        if let Err(err) = do_ldap_operation_queue(pool)
+            .instrument(info_span!("ldap_operation_queue_task"))
+            .await
+        {
+            error!("Failed to retry queued LDAP operations: {err}");
+            LDAP_SYNC.record_error(err);
         }
     };
 
@@ -88,13 +144,74 @@ pub async fn run_utility_thread(
         }
     };
 
+    let expired_group_memberships_task = || async {
+        if let Err(err) = expired_group_memberships_check(pool, &wireguard_tx, &internal_event_tx)
+            .instrument(info_span!("expired_group_memberships_task"))
+            .await
+        {
+            error!("Failed to remove expired group memberships: {err}");
+        }
+    };
+
+    let activity_log_retention_task = || async {
+        if let Err(err) = activity_log_retention_check(pool, &internal_event_tx)
+            .instrument(info_span!("activity_log_retention_task"))
+            .await
+        {
+            error!("Failed to purge activity log events: {err}");
+        }
+    };
+
+    let access_windows_task = || async {
+        if let Err(err) = access_windows_check(pool, &wireguard_tx, &internal_event_tx)
+            .instrument(info_span!("access_windows_task"))
+            .await
+        {
+            error!("Failed to check location access windows: {err}");
+        }
+    };
+
+    let scheduled_deactivations_task = || async {
+        if let Err(err) = scheduled_deactivations_check(pool, &wireguard_tx, &internal_event_tx)
+            .instrument(info_span!("scheduled_deactivations_task"))
+            .await
+        {
+            error!("Failed to disable users scheduled for deactivation: {err}");
+        }
+    };
+
+    let expired_devices_task = || async {
+        if let Err(err) = expired_devices_check(pool, &wireguard_tx, &mail_tx, &internal_event_tx)
+            .instrument(info_span!("expired_devices_task"))
+            .await
+        {
+            error!("Failed to remove expired devices: {err}");
+        }
+    };
+
+    let psk_rotation_task = || async {
+        if let Err(err) = psk_rotation_check(pool, &wireguard_tx)
+            .instrument(info_span!("psk_rotation_task"))
+            .await
+        {
+            error!("Failed to rotate location preshared keys: {err}");
+        }
+    };
+
     directory_sync_task().await;
     count_update_task().await;
     updates_check_task().await;
     ldap_sync_task().await;
     expired_acl_rules_task().await;
+    expired_group_memberships_task().await;
+    activity_log_retention_task().await;
+    access_windows_task().await;
+    scheduled_deactivations_task().await;
+    expired_devices_task().await;
+    psk_rotation_task().await;
 
     loop {
+        UTILITY_THREAD.heartbeat();
         sleep(Duration::from_secs(UTILITY_THREAD_MAIN_SLEEP_TIME)).await;
 
         // Count update job for updating device/user/network counts
@@ -121,12 +238,60 @@ pub async fn run_utility_thread(
             last_ldap_sync = Instant::now();
         }
 
+        // Retry LDAP operations which couldn't be applied right away
+        if last_ldap_operation_queue_check.elapsed().as_secs() >= LDAP_OPERATION_QUEUE_INTERVAL {
+            ldap_operation_queue_task().await;
+            last_ldap_operation_queue_check = Instant::now();
+        }
+
         // Mark expired ACL rules
         if last_expired_acl_rules_check.elapsed().as_secs() >= EXPIRED_ACL_RULES_CHECK_INTERVAL {
             expired_acl_rules_task().await;
             last_expired_acl_rules_check = Instant::now();
         }
 
+        // Remove expired group memberships
+        if last_expired_group_memberships_check.elapsed().as_secs()
+            >= EXPIRED_GROUP_MEMBERSHIPS_CHECK_INTERVAL
+        {
+            expired_group_memberships_task().await;
+            last_expired_group_memberships_check = Instant::now();
+        }
+
+        // Purge activity log events older than the configured retention period
+        if last_activity_log_retention_check.elapsed().as_secs()
+            >= ACTIVITY_LOG_RETENTION_CHECK_INTERVAL
+        {
+            activity_log_retention_task().await;
+            last_activity_log_retention_check = Instant::now();
+        }
+
+        // Revoke authorization of devices outside of location access windows
+        if last_access_windows_check.elapsed().as_secs() >= ACCESS_WINDOWS_CHECK_INTERVAL {
+            access_windows_task().await;
+            last_access_windows_check = Instant::now();
+        }
+
+        // Disable users whose scheduled deactivation time has passed
+        if last_scheduled_deactivations_check.elapsed().as_secs()
+            >= SCHEDULED_DEACTIVATIONS_CHECK_INTERVAL
+        {
+            scheduled_deactivations_task().await;
+            last_scheduled_deactivations_check = Instant::now();
+        }
+
+        // Remove devices whose expiry time has passed from locations
+        if last_expired_devices_check.elapsed().as_secs() >= EXPIRED_DEVICES_CHECK_INTERVAL {
+            expired_devices_task().await;
+            last_expired_devices_check = Instant::now();
+        }
+
+        // Regenerate preshared keys of locations with periodic rotation
+        if last_psk_rotation_check.elapsed().as_secs() >= PSK_ROTATION_CHECK_INTERVAL {
+            psk_rotation_task().await;
+            last_psk_rotation_check = Instant::now();
+        }
+
         // Check if enterprise features got enabled or disabled
         if last_enterprise_status_check.elapsed().as_secs() >= ENTERPRISE_STATUS_CHECK_INTERVAL {
             let new_enterprise_enabled = is_business_license_active();
@@ -183,17 +348,20 @@ async fn enterprise_status_check(
                 // Handle service location update or just update the firewall
                 if location.service_location_mode != ServiceLocationMode::Disabled {
                     let new_peers = location.get_peers(&mut *transaction).await?;
-                    wireguard_tx.send(GatewayEvent::NetworkModified(
-                        location.id,
-                        location,
-                        new_peers,
-                        Some(firewall_config),
-                    ))?;
+                    send_wireguard_event(
+                        GatewayEvent::NetworkModified(
+                            location.id,
+                            location,
+                            new_peers,
+                            Some(firewall_config),
+                        ),
+                        &wireguard_tx,
+                    );
                 } else {
-                    wireguard_tx.send(GatewayEvent::FirewallConfigChanged(
-                        location.id,
-                        firewall_config,
-                    ))?;
+                    send_wireguard_event(
+                        GatewayEvent::FirewallConfigChanged(location.id, firewall_config),
+                        &wireguard_tx,
+                    );
                 }
             }
             transaction.commit().await?;
@@ -206,16 +374,22 @@ async fn enterprise_status_check(
                         "Disabling gateway firewall configuration and service location client connections \
                         for location {location:?}"
                     );
-                    wireguard_tx.send(GatewayEvent::NetworkModified(
-                        location.id,
-                        location,
-                        // Send empty peer list, we are disabling the service location
-                        Vec::new(),
-                        None,
-                    ))?;
+                    send_wireguard_event(
+                        GatewayEvent::NetworkModified(
+                            location.id,
+                            location,
+                            // Send empty peer list, we are disabling the service location
+                            Vec::new(),
+                            None,
+                        ),
+                        &wireguard_tx,
+                    );
                 } else {
                     debug!("Disabling gateway firewall configuration for location {location:?}");
-                    wireguard_tx.send(GatewayEvent::FirewallDisabled(location.id))?;
+                    send_wireguard_event(
+                        GatewayEvent::FirewallDisabled(location.id),
+                        &wireguard_tx,
+                    );
                 }
             }
         }
@@ -269,10 +443,10 @@ async fn expired_acl_rules_check(
         match location.try_get_firewall_config(&mut conn).await? {
             Some(firewall_config) => {
                 debug!("Sending firewall update event for location {location}");
-                wireguard_tx.send(GatewayEvent::FirewallConfigChanged(
-                    location.id,
-                    firewall_config,
-                ))?;
+                send_wireguard_event(
+                    GatewayEvent::FirewallConfigChanged(location.id, firewall_config),
+                    &wireguard_tx,
+                );
             }
             None => {
                 debug!(
@@ -285,3 +459,289 @@ async fn expired_acl_rules_check(
 
     Ok(())
 }
+
+/// Remove expired group memberships and update locations the users lost access to.
+async fn expired_group_memberships_check(
+    pool: &PgPool,
+    wireguard_tx: &Sender<GatewayEvent>,
+    internal_event_tx: &UnboundedSender<InternalEvent>,
+) -> Result<(), anyhow::Error> {
+    let expired = remove_expired_memberships(pool).await?;
+    if expired.is_empty() {
+        return Ok(());
+    }
+    info!("Removed {} expired group memberships", expired.len());
+
+    for membership in expired {
+        let (Some(group), Some(user)) = (
+            Group::find_by_id(pool, membership.group_id).await?,
+            User::find_by_id(pool, membership.user_id).await?,
+        ) else {
+            continue;
+        };
+        debug!(
+            "Membership of user {} in group {} expired at {}",
+            user.username, group.name, membership.expires_at
+        );
+        ldap_remove_user_from_groups(&user, hashset![group.name.as_str()], pool).await;
+        internal_event_tx.send(InternalEvent::GroupMembershipExpired {
+            timestamp: membership.expires_at,
+            group,
+            user,
+        })?;
+    }
+
+    let mut conn = pool.acquire().await?;
+    WireguardNetwork::sync_all_networks(&mut conn, wireguard_tx).await?;
+
+    Ok(())
+}
+
+/// Disable users whose scheduled deactivation time has passed, log out their sessions and
+/// remove their devices from gateways.
+async fn scheduled_deactivations_check(
+    pool: &PgPool,
+    wireguard_tx: &Sender<GatewayEvent>,
+    internal_event_tx: &UnboundedSender<InternalEvent>,
+) -> Result<(), anyhow::Error> {
+    let due = User::find_due_for_deactivation(pool, Utc::now().naive_utc()).await?;
+    if due.is_empty() {
+        return Ok(());
+    }
+
+    let mut transaction = pool.begin().await?;
+    let mut disabled = Vec::with_capacity(due.len());
+    for user_id in due {
+        let Some(mut user) = User::find_by_id(&mut *transaction, user_id).await? else {
+            continue;
+        };
+        let Some(deactivate_at) = user.deactivate_at(&mut *transaction).await? else {
+            continue;
+        };
+        debug!(
+            "User {} was scheduled for deactivation at {deactivate_at}",
+            user.username
+        );
+        user.is_active = false;
+        user.save(&mut *transaction).await?;
+        user.set_deactivate_at(&mut *transaction, None).await?;
+        user.logout_all_sessions(&mut *transaction).await?;
+        // API tokens are removed, same as when disabling the user manually
+        for token in ApiToken::find_by_user_id(&mut *transaction, user.id).await? {
+            token.delete(&mut *transaction).await?;
+        }
+        disabled.push((deactivate_at, user));
+    }
+    WireguardNetwork::sync_all_networks(&mut transaction, wireguard_tx).await?;
+    transaction.commit().await?;
+    info!(
+        "Disabled {} users at their scheduled deactivation time",
+        disabled.len()
+    );
+
+    for (timestamp, mut user) in disabled {
+        Box::pin(ldap_update_user_state(&mut user, pool)).await;
+        internal_event_tx.send(InternalEvent::UserDisabled { timestamp, user })?;
+    }
+
+    Ok(())
+}
+
+/// Remove devices whose expiry time has passed from gateways, mark them as not configured and
+/// notify their owners.
+async fn expired_devices_check(
+    pool: &PgPool,
+    wireguard_tx: &Sender<GatewayEvent>,
+    mail_tx: &UnboundedSender<Mail>,
+    internal_event_tx: &UnboundedSender<InternalEvent>,
+) -> Result<(), anyhow::Error> {
+    let expired = Device::find_expired(pool, Utc::now().naive_utc()).await?;
+    if expired.is_empty() {
+        return Ok(());
+    }
+
+    let mut transaction = pool.begin().await?;
+    let mut removed = Vec::with_capacity(expired.len());
+    for device_id in expired {
+        let Some(mut device) = Device::find_by_id(&mut *transaction, device_id).await? else {
+            continue;
+        };
+        let Some(expires_at) = device.expires_at(&mut *transaction).await? else {
+            continue;
+        };
+        debug!("Device {device} expired at {expires_at}");
+        let device_info = DeviceInfo::from_device(&mut *transaction, device.clone()).await?;
+        device.configured = false;
+        device.save(&mut *transaction).await?;
+        device.set_expires_at(&mut *transaction, None).await?;
+        let owner = device.get_owner(&mut *transaction).await?;
+        removed.push((expires_at, owner, device_info));
+    }
+    transaction.commit().await?;
+    info!("Removed {} devices at their expiry time", removed.len());
+
+    let mut affected_locations = HashSet::new();
+    for (timestamp, owner, device_info) in removed {
+        let device = device_info.device.clone();
+        affected_locations.extend(device_info.network_info.iter().map(|info| info.network_id));
+        debug!("Sending `peer_delete` message to gateway for expired device {device}");
+        send_wireguard_event(GatewayEvent::DeviceDeleted(device_info), wireguard_tx);
+        if let Err(err) = send_device_expired_email(&owner, &device.name, timestamp, mail_tx) {
+            error!(
+                "Failed to render device expiry mail for {}: {err}",
+                owner.email
+            );
+        }
+        internal_event_tx.send(InternalEvent::DeviceExpired {
+            timestamp,
+            owner,
+            device,
+        })?;
+    }
+
+    // expired devices are no longer included in firewall rules
+    let mut conn = pool.acquire().await?;
+    for location_id in affected_locations {
+        let Some(location) = WireguardNetwork::find_by_id(&mut *conn, location_id).await? else {
+            continue;
+        };
+        if let Some(firewall_config) = location.try_get_firewall_config(&mut conn).await? {
+            debug!("Sending firewall update event for location {location}");
+            send_wireguard_event(
+                GatewayEvent::FirewallConfigChanged(location.id, firewall_config),
+                wireguard_tx,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Regenerate preshared keys of peers in locations whose rotation interval has passed and update
+/// gateway peers. Desktop clients receive new keys with their next configuration update.
+async fn psk_rotation_check(
+    pool: &PgPool,
+    wireguard_tx: &Sender<GatewayEvent>,
+) -> Result<(), anyhow::Error> {
+    let due = WireguardNetwork::find_due_for_psk_rotation(pool, Utc::now().naive_utc()).await?;
+    for location_id in due {
+        let mut transaction = pool.begin().await?;
+        let Some(location) = WireguardNetwork::find_by_id(&mut *transaction, location_id).await?
+        else {
+            continue;
+        };
+        let devices = location.rotate_preshared_keys(&mut transaction).await?;
+        transaction.commit().await?;
+        info!(
+            "Rotated preshared keys of {} peers in location {location}",
+            devices.len()
+        );
+
+        debug!("Sending `peer_update` messages to gateways of location {location}");
+        for device_info in devices {
+            send_wireguard_event(GatewayEvent::DeviceModified(device_info), wireguard_tx);
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove or archive activity log events older than the configured retention period.
+async fn activity_log_retention_check(
+    pool: &PgPool,
+    internal_event_tx: &UnboundedSender<InternalEvent>,
+) -> Result<(), anyhow::Error> {
+    let settings = Settings::get_current_settings();
+    let Some(retention_days) = settings.activity_log_retention_days else {
+        debug!("Activity log retention is not configured, skipping purge");
+        return Ok(());
+    };
+    let archived = settings.activity_log_retention_archive;
+    let timestamp = Utc::now().naive_utc();
+    let threshold = timestamp - TimeDelta::days(retention_days.into());
+    debug!("Purging activity log events older than {threshold}, archive: {archived}");
+
+    let removed = ActivityLogEvent::purge_older_than(pool, threshold, archived).await?;
+    if removed == 0 {
+        return Ok(());
+    }
+    info!("Purged {removed} activity log events older than {threshold}");
+    internal_event_tx.send(InternalEvent::ActivityLogPurged {
+        timestamp,
+        removed,
+        archived,
+        threshold,
+    })?;
+
+    Ok(())
+}
+
+/// Revoke authorization of devices in MFA-protected locations whose owners are outside of access
+/// windows of their allowed groups. Such devices have to complete MFA again, which is rejected
+/// until a window opens.
+async fn access_windows_check(
+    pool: &PgPool,
+    wireguard_tx: &Sender<GatewayEvent>,
+    internal_event_tx: &UnboundedSender<InternalEvent>,
+) -> Result<(), anyhow::Error> {
+    let mut conn = pool.acquire().await?;
+    for location in WireguardNetwork::all(&mut *conn).await? {
+        if !location.mfa_enabled() || location.fetch_access_windows(&mut *conn).await?.is_empty() {
+            continue;
+        }
+        debug!("Checking access windows of authorized devices in location {location}");
+        for mut network_device in
+            WireguardNetworkDevice::all_for_network(&mut *conn, location.id).await?
+        {
+            if !network_device.is_authorized {
+                continue;
+            }
+            let Some(device) = Device::find_by_id(&mut *conn, network_device.device_id).await?
+            else {
+                continue;
+            };
+            let user = device.get_owner(&mut *conn).await?;
+            let now = NetworkAccessWindow::local_now(user.effective_timezone(&mut *conn).await?);
+            if location
+                .is_within_access_window(&mut conn, &user, now)
+                .await?
+            {
+                continue;
+            }
+
+            info!(
+                "Revoking authorization of device {device} in location {location}, user {} is \
+                outside of access windows at {now}",
+                user.username
+            );
+            network_device.is_authorized = false;
+            network_device.preshared_key = None;
+            network_device.update(&mut *conn).await?;
+
+            debug!("Sending `peer_delete` message to gateway");
+            send_wireguard_event(
+                GatewayEvent::DeviceDeleted(DeviceInfo {
+                    device: device.clone(),
+                    network_info: vec![DeviceNetworkInfo {
+                        network_id: location.id,
+                        device_wireguard_ips: network_device.wireguard_ips,
+                        preshared_key: network_device.preshared_key,
+                        is_authorized: network_device.is_authorized,
+                    }],
+                }),
+                wireguard_tx,
+            );
+            internal_event_tx.send(InternalEvent::DesktopClientMfaDisconnected {
+                context: InternalEventContext::new(
+                    user.id,
+                    user.username,
+                    IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                    device,
+                ),
+                location: location.clone(),
+            })?;
+        }
+    }
+
+    Ok(())
+}
@@ -16,7 +16,7 @@ use sqlx::{Error as SqlxError, PgPool, query_as};
 use thiserror::Error;
 use tokio::{
     sync::{
-        broadcast::{self, Sender},
+        broadcast::Sender,
         mpsc::{self, UnboundedSender},
     },
     time::sleep,
@@ -27,10 +27,13 @@ use crate::{
         Device, GatewayEvent, WireguardNetwork,
         models::{
             device::{DeviceInfo, DeviceNetworkInfo, DeviceType, WireguardNetworkDevice},
-            wireguard::{LocationMfaMode, ServiceLocationMode, WireguardNetworkError},
+            wireguard::{
+                IpAssignmentStrategy, LocationMfaMode, ServiceLocationMode, WireguardNetworkError,
+            },
         },
     },
     events::{InternalEvent, InternalEventContext},
+    grpc::gateway::send_wireguard_event,
 };
 
 // How long to sleep between loop iterations
@@ -44,8 +47,6 @@ pub enum PeerDisconnectError {
     ModelError(#[from] ModelError),
     #[error(transparent)]
     WireguardError(#[from] WireguardNetworkError),
-    #[error("Failed to send gateway event: {0}")]
-    GatewayEventError(#[from] broadcast::error::SendError<GatewayEvent>),
     #[error("Failed to send internal event: {0}")]
     InternalEventError(#[from] mpsc::error::SendError<InternalEvent>),
 }
@@ -98,7 +99,8 @@ pub async fn run_periodic_peer_disconnect(
                 id, name, address, port, pubkey, prvkey, endpoint, dns, allowed_ips, \
                 connected_at, keepalive_interval, peer_disconnect_threshold, \
                 acl_enabled, acl_default_allow, location_mfa_mode \"location_mfa_mode: LocationMfaMode\", \
-                service_location_mode \"service_location_mode: ServiceLocationMode\" \
+                service_location_mode \"service_location_mode: ServiceLocationMode\", \
+                ip_assignment_strategy \"ip_assignment_strategy: IpAssignmentStrategy\" \
             FROM wireguard_network WHERE location_mfa_mode != 'disabled'::location_mfa_mode",
         )
         .fetch_all(&pool)
@@ -162,10 +164,7 @@ pub async fn run_periodic_peer_disconnect(
                         }],
                     };
                     let event = GatewayEvent::DeviceDeleted(device_info);
-                    wireguard_tx.send(event).map_err(|err| {
-                        error!("Error sending WireGuard event: {err}");
-                        PeerDisconnectError::GatewayEventError(err)
-                    })?;
+                    send_wireguard_event(event, &wireguard_tx);
                     let user = device.get_owner(&mut *transaction).await?;
                     let ip = endpoint
                         .as_ref()
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::time::sleep;
+
+use crate::db::models::wireguard_peer_stats::WireguardPeerStats;
+
+// How long to sleep between loop iterations
+const ROLLUP_LOOP_SLEEP: Duration = Duration::from_secs(600); // 10 minutes
+
+/// Periodically aggregates raw peer stats into hourly and daily rollup tables.
+#[instrument(skip_all)]
+pub async fn run_periodic_stats_rollup(pool: PgPool) -> Result<(), sqlx::Error> {
+    info!("Starting periodic stats rollup");
+
+    loop {
+        debug!("Rolling up peer stats");
+        if let Err(err) = WireguardPeerStats::rollup_stats(&pool).await {
+            error!("Error while rolling up stats: {err}");
+        }
+
+        // wait till next iteration
+        debug!("Sleeping until next iteration");
+        sleep(ROLLUP_LOOP_SLEEP).await;
+    }
+}
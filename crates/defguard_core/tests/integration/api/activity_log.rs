@@ -0,0 +1,304 @@
+use chrono::{DateTime, TimeDelta};
+use defguard_common::db::NoId;
+use defguard_core::{
+    db::{
+        User,
+        models::activity_log::{ActivityLogEvent, ActivityLogModule, EventType},
+    },
+    events::ApiEventType,
+};
+use reqwest::{StatusCode, header::HeaderName};
+use serde_json::{Value, json};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+use super::common::{make_test_client, setup_pool};
+
+#[sqlx::test]
+async fn test_custom_activity_log_events(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_test_client(pool).await;
+
+    // only admins can add custom events
+    client.login_user("hpotter", "pass123").await;
+    let event = json!({
+        "namespace": "badge_reader",
+        "name": "door_opened",
+        "description": "Main entrance opened",
+        "data": {"door": "main", "badge": 1234},
+    });
+    let response = client
+        .post("/api/v1/activity_log/custom")
+        .json(&event)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    client.drain_all_events();
+    client.login_user("admin", "pass123").await;
+    let response = client
+        .post("/api/v1/activity_log/custom")
+        .json(&event)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // reserved namespace
+    let response = client
+        .post("/api/v1/activity_log/custom")
+        .json(&json!({"namespace": "defguard", "name": "user_login"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // invalid event name
+    let response = client
+        .post("/api/v1/activity_log/custom")
+        .json(&json!({"namespace": "ticketing", "name": "Ticket Created"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // data has to be an object
+    let response = client
+        .post("/api/v1/activity_log/custom")
+        .json(&json!({"namespace": "ticketing", "name": "ticket_created", "data": [1, 2]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    client.verify_api_events(&[ApiEventType::CustomEvent {
+        namespace: "badge_reader".into(),
+        name: "door_opened".into(),
+        description: Some("Main entrance opened".into()),
+        data: Some(json!({"door": "main", "badge": 1234})),
+    }]);
+
+    // rate limit
+    for _ in 1..60 {
+        let response = client
+            .post("/api/v1/activity_log/custom")
+            .json(&json!({"namespace": "ticketing", "name": "ticket_created"}))
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    let response = client
+        .post("/api/v1/activity_log/custom")
+        .json(&json!({"namespace": "ticketing", "name": "ticket_created"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[sqlx::test]
+async fn test_activity_log_query(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    // add 60 events for admin and 5 for a regular user
+    let start = DateTime::from_timestamp(1_700_000_000, 0)
+        .unwrap()
+        .naive_utc();
+    for (index, username) in (0..65).map(|i| (i, if i < 60 { "admin" } else { "hpotter" })) {
+        let user = User::find_by_username(&pool, username)
+            .await
+            .unwrap()
+            .unwrap();
+        ActivityLogEvent {
+            id: NoId,
+            // pairs of events share a timestamp to check ordering stability
+            timestamp: start + TimeDelta::seconds(index / 2),
+            user_id: user.id,
+            username: user.username,
+            location: None,
+            ip: format!("10.0.{}.{}", index % 2, index).parse().unwrap(),
+            event: EventType::UserLogin,
+            module: ActivityLogModule::Defguard,
+            device: "Firefox".into(),
+            description: None,
+            metadata: None,
+            request_id: None,
+            impersonator_id: None,
+            impersonator: None,
+        }
+        .save(&pool)
+        .await
+        .unwrap();
+    }
+
+    let (mut client, _) = make_test_client(pool).await;
+    client.login_user("admin", "pass123").await;
+
+    // filter by IP network
+    let response = client
+        .get("/api/v1/activity_log?ip=10.0.1.0/24&username=admin")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = response.json().await;
+    assert_eq!(body["pagination"]["total_items"], 30);
+    assert!(body["pagination"]["next_cursor"].is_null());
+
+    // walk through all events with cursor pagination
+    let mut ids = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let url = match &cursor {
+            Some(cursor) => format!("/api/v1/activity_log?sort_order=asc&cursor={cursor}"),
+            None => "/api/v1/activity_log?sort_order=asc".to_string(),
+        };
+        let response = client.get(url).send().await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = response.json().await;
+        ids.extend(
+            body["data"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|event| event["id"].as_i64().unwrap()),
+        );
+        match body["pagination"]["next_cursor"].as_str() {
+            Some(next_cursor) => cursor = Some(next_cursor.to_string()),
+            None => break,
+        }
+    }
+    assert_eq!(ids.len(), 65);
+    let mut sorted_ids = ids.clone();
+    sorted_ids.sort_unstable();
+    assert_eq!(ids, sorted_ids);
+
+    // cursor requires sorting by timestamp
+    let response = client
+        .get(format!(
+            "/api/v1/activity_log?sort_by=username&cursor={}",
+            cursor.unwrap()
+        ))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // malformed cursor
+    let response = client
+        .get("/api/v1/activity_log?cursor=invalid")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // regular users see only their own events
+    client.login_user("hpotter", "pass123").await;
+    let response = client.get("/api/v1/activity_log").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = response.json().await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 5);
+    assert_eq!(body["pagination"]["total_items"], 5);
+}
+
+#[sqlx::test]
+async fn test_activity_log_export(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let user = User::find_by_username(&pool, "admin")
+        .await
+        .unwrap()
+        .unwrap();
+    let start = DateTime::from_timestamp(1_700_000_000, 0)
+        .unwrap()
+        .naive_utc();
+    for (index, description) in ["Logged in", "Said \"hi\", twice", "=cmd"]
+        .iter()
+        .enumerate()
+    {
+        ActivityLogEvent {
+            id: NoId,
+            timestamp: start + TimeDelta::seconds(index as i64),
+            user_id: user.id,
+            username: user.username.clone(),
+            location: None,
+            ip: "10.0.0.1".parse().unwrap(),
+            event: EventType::UserLogin,
+            module: ActivityLogModule::Defguard,
+            device: "Firefox".into(),
+            description: Some((*description).to_string()),
+            metadata: None,
+            request_id: None,
+            impersonator_id: None,
+            impersonator: None,
+        }
+        .save(&pool)
+        .await
+        .unwrap();
+    }
+
+    let (mut client, _) = make_test_client(pool).await;
+    client.login_user("admin", "pass123").await;
+
+    let response = client
+        .get("/api/v1/activity_log/export?format=csv&sort_order=asc")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/csv; charset=utf-8"
+    );
+    let body = response.text().await;
+    let lines: Vec<&str> = body.split("\r\n").collect();
+    assert_eq!(
+        lines[0],
+        "id,timestamp,user_id,username,location,ip,event,module,device,description"
+    );
+    assert_eq!(lines.len(), 5);
+    assert!(lines[1].ends_with(",admin,,10.0.0.1,user_login,defguard,Firefox,Logged in"));
+    assert!(lines[2].ends_with(",\"Said \"\"hi\"\", twice\""));
+    assert!(lines[3].ends_with(",'=cmd"));
+    assert_eq!(lines[4], "");
+
+    // same filters as in the query API
+    let response = client
+        .get("/api/v1/activity_log/export?format=jsonl&search=twice")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.text().await;
+    let events: Vec<Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["description"], "Said \"hi\", twice");
+}
+
+#[sqlx::test]
+async fn test_request_id_propagation(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_test_client(pool).await;
+    client.login_user("admin", "pass123").await;
+    let request_id_header = HeaderName::from_static("x-request-id");
+
+    // request ID is generated if not provided
+    let response = client.get("/api/v1/me").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let generated = response.headers()[&request_id_header].to_str().unwrap();
+    assert!(uuid::Uuid::parse_str(generated).is_ok());
+
+    // request ID provided by a reverse proxy is returned and attached to events
+    let response = client
+        .post("/api/v1/activity_log/custom")
+        .header(request_id_header.clone(), "proxy-1234")
+        .json(&json!({"namespace": "ticketing", "name": "ticket_created"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.headers()[&request_id_header], "proxy-1234");
+    let event = client.next_api_event().unwrap();
+    assert_eq!(event.context.request_id.as_deref(), Some("proxy-1234"));
+
+    // invalid request ID is replaced
+    let response = client
+        .get("/api/v1/me")
+        .header(request_id_header.clone(), "two words")
+        .send()
+        .await;
+    assert_ne!(response.headers()[&request_id_header], "two words");
+}
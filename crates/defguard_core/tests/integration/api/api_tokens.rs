@@ -2,17 +2,17 @@ use chrono::Utc;
 use defguard_core::{
     db::{Group, UserInfo, models::group::Permission},
     enterprise::{
-        db::models::api_tokens::{ApiToken, ApiTokenInfo},
+        db::models::api_tokens::{ApiToken, ApiTokenInfo, ApiTokenScope},
         handlers::api_tokens::{AddApiTokenData, RenameRequest},
     },
-    handlers::Auth,
+    handlers::{Auth, EditGroupInfo, GroupInfo},
 };
 use reqwest::{StatusCode, header::HeaderName};
 use serde::Deserialize;
-use serde_json::json;
+use serde_json::{Value, json};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 
-use super::common::{make_client, make_test_client, setup_pool};
+use super::common::{make_client, make_network, make_test_client, setup_pool};
 use crate::api::common::fetch_user_details;
 
 #[sqlx::test]
@@ -37,6 +37,9 @@ async fn test_normal_user_cannot_access_token_endpoints(
         .post("/api/v1/user/hpotter/api_token")
         .json(&AddApiTokenData {
             name: "dummy token".into(),
+            location_id: None,
+            group_id: None,
+            scopes: Vec::new(),
         })
         .send()
         .await;
@@ -71,6 +74,9 @@ async fn test_normal_user_cannot_use_token_auth(_: PgPoolOptions, options: PgCon
         Utc::now().naive_utc(),
         "dummy token".into(),
         token_string,
+        None,
+        None,
+        Vec::new(),
     );
     token.save(&state.pool).await.unwrap();
 
@@ -107,6 +113,9 @@ async fn test_admin_user_can_manage_api_tokens(_: PgPoolOptions, options: PgConn
         .post("/api/v1/user/admin/api_token")
         .json(&AddApiTokenData {
             name: "dummy token 1".into(),
+            location_id: None,
+            group_id: None,
+            scopes: Vec::new(),
         })
         .send()
         .await;
@@ -121,6 +130,9 @@ async fn test_admin_user_can_manage_api_tokens(_: PgPoolOptions, options: PgConn
         .post("/api/v1/user/admin/api_token")
         .json(&AddApiTokenData {
             name: "dummy token 2".into(),
+            location_id: None,
+            group_id: None,
+            scopes: Vec::new(),
         })
         .send()
         .await;
@@ -135,6 +147,9 @@ async fn test_admin_user_can_manage_api_tokens(_: PgPoolOptions, options: PgConn
         .post("/api/v1/user/admin/api_token")
         .json(&AddApiTokenData {
             name: "dummy token 3".into(),
+            location_id: None,
+            group_id: None,
+            scopes: Vec::new(),
         })
         .send()
         .await;
@@ -151,6 +166,9 @@ async fn test_admin_user_can_manage_api_tokens(_: PgPoolOptions, options: PgConn
         .post("/api/v1/user/hpotter/api_token")
         .json(&AddApiTokenData {
             name: "nope".into(),
+            location_id: None,
+            group_id: None,
+            scopes: Vec::new(),
         })
         .send()
         .await;
@@ -216,6 +234,9 @@ async fn test_admin_user_can_use_api_tokens_to_authenticate(
         .post("/api/v1/user/admin/api_token")
         .json(&AddApiTokenData {
             name: "dummy token 1".into(),
+            location_id: None,
+            group_id: None,
+            scopes: Vec::new(),
         })
         .send()
         .await;
@@ -317,6 +338,9 @@ async fn dg25_3_test_token_invalidation(_: PgPoolOptions, options: PgConnectOpti
         .post("/api/v1/user/hpotter/api_token")
         .json(&AddApiTokenData {
             name: "dummy token 1".into(),
+            location_id: None,
+            group_id: None,
+            scopes: Vec::new(),
         })
         .send()
         .await;
@@ -375,3 +399,403 @@ async fn dg25_3_test_token_invalidation(_: PgPoolOptions, options: PgConnectOpti
         .await;
     assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
+
+#[sqlx::test]
+async fn test_location_scoped_api_token(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let client = make_client(pool).await;
+
+    // log in as admin user
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // create two locations
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let location: Value = response.json().await;
+    let location_id = location["id"].as_i64().unwrap();
+    let mut other_location = make_network();
+    other_location["name"] = json!("other network");
+    let response = client
+        .post("/api/v1/network")
+        .json(&other_location)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let other_location: Value = response.json().await;
+    let other_location_id = other_location["id"].as_i64().unwrap();
+
+    // cannot scope a token to a non-existent location
+    let response = client
+        .post("/api/v1/user/admin/api_token")
+        .json(&AddApiTokenData {
+            name: "scoped token".into(),
+            location_id: Some(1234),
+            group_id: None,
+            scopes: Vec::new(),
+        })
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // create location-scoped token
+    let response = client
+        .post("/api/v1/user/admin/api_token")
+        .json(&AddApiTokenData {
+            name: "scoped token".into(),
+            location_id: Some(location_id),
+            group_id: None,
+            scopes: Vec::new(),
+        })
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let token = response
+        .into_inner()
+        .json::<NewTokenResponse>()
+        .await
+        .unwrap()
+        .token;
+
+    let response = client.get("/api/v1/user/admin/api_token").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let tokens: Vec<ApiTokenInfo> = response.json().await;
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].location_id, Some(location_id));
+
+    // log out
+    let response = client.post("/api/v1/auth/logout").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let auth_header = format!("Bearer {token}");
+
+    // read-only location endpoints are available
+    for path in ["", "/stats", "/stats/users", "/gateways"] {
+        let response = client
+            .get(format!("/api/v1/network/{location_id}{path}"))
+            .header(HeaderName::from_static("authorization"), &auth_header)
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::OK, "path: {path}");
+    }
+
+    // other locations are not available
+    let response = client
+        .get(format!("/api/v1/network/{other_location_id}/stats"))
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // other routes are not available
+    let response = client
+        .get("/api/v1/me")
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .get(format!("/api/v1/network/{location_id}/token"))
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // modifications are not allowed
+    let response = client
+        .delete(format!("/api/v1/network/{location_id}"))
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test]
+async fn test_group_scoped_api_token(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let client = make_client(pool).await;
+
+    // log in as admin user
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // create automation group with a normal user
+    let response = client
+        .post("/api/v1/group")
+        .json(&EditGroupInfo::new(
+            "automation",
+            vec!["hpotter".into()],
+            false,
+        ))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = client.get("/api/v1/group/automation").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let group: GroupInfo = response.json().await;
+
+    // normal users can't get unrestricted tokens
+    let response = client
+        .post("/api/v1/user/hpotter/api_token")
+        .json(&AddApiTokenData {
+            name: "automation token".into(),
+            location_id: None,
+            group_id: None,
+            scopes: vec![ApiTokenScope::UserManagement],
+        })
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // token can't be bound to a group the owner doesn't belong to
+    let response = client
+        .post("/api/v1/user/admin/api_token")
+        .json(&AddApiTokenData {
+            name: "automation token".into(),
+            location_id: None,
+            group_id: Some(group.id),
+            scopes: vec![ApiTokenScope::UserManagement],
+        })
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = client
+        .post("/api/v1/user/hpotter/api_token")
+        .json(&AddApiTokenData {
+            name: "automation token".into(),
+            location_id: None,
+            group_id: Some(group.id),
+            scopes: vec![ApiTokenScope::UserManagement],
+        })
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let token = response
+        .into_inner()
+        .json::<NewTokenResponse>()
+        .await
+        .unwrap()
+        .token;
+
+    let response = client.get("/api/v1/user/hpotter/api_token").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let tokens: Vec<ApiTokenInfo> = response.json().await;
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].group_id, Some(group.id));
+    assert_eq!(tokens[0].scopes, vec![ApiTokenScope::UserManagement]);
+    let mut user_details = fetch_user_details(&client, "hpotter").await;
+
+    // log out
+    let response = client.post("/api/v1/auth/logout").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let auth_header = format!("Bearer {token}");
+
+    // user management is available
+    let response = client
+        .get("/api/v1/user")
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .get("/api/v1/user/admin")
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // API token management and other resources are not available
+    let response = client
+        .get("/api/v1/user/hpotter/api_token")
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .get("/api/v1/network")
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .get("/api/v1/settings")
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // admins and admin groups can't be modified
+    let response = client
+        .delete("/api/v1/user/admin")
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .post("/api/v1/group/admin")
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .json(&json!({"username": "hpotter"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .post("/api/v1/group/automation")
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .json(&json!({"username": "admin"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .post("/api/v1/group")
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .json(&EditGroupInfo::new(
+            "superusers",
+            vec!["hpotter".into()],
+            true,
+        ))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    user_details.user.groups.push("admin".into());
+    let response = client
+        .put("/api/v1/user/hpotter")
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .json(&user_details.user)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // removing the owner from the group invalidates the token
+    let response = client
+        .delete("/api/v1/group/automation/user/hpotter")
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .get("/api/v1/user")
+        .header(HeaderName::from_static("authorization"), &auth_header)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn test_service_account(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let client = make_client(pool).await;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // service accounts can't have a password
+    let mut service_account = json!({
+        "username": "ci-bot",
+        "first_name": "CI",
+        "last_name": "Bot",
+        "email": "ci-bot@example.com",
+        "phone": null,
+        "password": "Alohomora!12",
+        "service_account": true,
+    });
+    let response = client
+        .post("/api/v1/user")
+        .json(&service_account)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    service_account["password"] = Value::Null;
+    let response = client
+        .post("/api/v1/user")
+        .json(&service_account)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let user_info: UserInfo = response.json().await;
+    assert!(user_info.service_account);
+
+    // flagged in user listing
+    let response = client.get("/api/v1/user").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let users: Vec<UserInfo> = response.json().await;
+    for user in users {
+        assert_eq!(user.service_account, user.username == "ci-bot");
+    }
+
+    // service accounts can't be enrolled
+    let response = client
+        .post("/api/v1/user/ci-bot/start_enrollment")
+        .json(&json!({}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // multiple tokens can be issued
+    let response = client
+        .post("/api/v1/group/admin")
+        .json(&json!({"username": "ci-bot"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut tokens = Vec::new();
+    for name in ["deploy", "backup"] {
+        let response = client
+            .post("/api/v1/user/ci-bot/api_token")
+            .json(&AddApiTokenData {
+                name: name.into(),
+                location_id: None,
+                group_id: None,
+                scopes: Vec::new(),
+            })
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        tokens.push(
+            response
+                .into_inner()
+                .json::<NewTokenResponse>()
+                .await
+                .unwrap()
+                .token,
+        );
+    }
+    let response = client.get("/api/v1/user/ci-bot/api_token").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let token_info: Vec<ApiTokenInfo> = response.json().await;
+    assert_eq!(token_info.len(), 2);
+
+    let response = client.post("/api/v1/auth/logout").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // tokens authenticate without MFA
+    for token in &tokens {
+        let response = client
+            .get("/api/v1/me")
+            .header(
+                HeaderName::from_static("authorization"),
+                &format!("Bearer {token}"),
+            )
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let me: UserInfo = response.json().await;
+        assert_eq!(me.username, "ci-bot");
+    }
+
+    // but web UI login is rejected
+    let auth = Auth::new("ci-bot", "");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -1,15 +1,19 @@
 use std::time::SystemTime;
 
-use chrono::DateTime;
+use chrono::{DateTime, Utc};
 use claims::{assert_err, assert_ok};
-use defguard_common::db::models::{MFAMethod, Settings, settings::update_current_settings};
+use defguard_common::{
+    config::DefGuardConfig,
+    db::models::{MFAMethod, Settings, settings::update_current_settings},
+};
 use defguard_core::{
-    auth::{TOTP_CODE_DIGITS, TOTP_CODE_VALIDITY_PERIOD},
+    auth::{TOTP_CODE_DIGITS, TOTP_CODE_VALIDITY_PERIOD, break_glass::init_break_glass_admin},
     db::{MFAInfo, User, UserDetails},
     events::ApiEventType,
-    handlers::{Auth, AuthCode, AuthResponse, AuthTotp},
+    handlers::{Auth, AuthCode, AuthResponse, AuthTotp, RecoveryCodesInfo},
 };
 use reqwest::{StatusCode, header::USER_AGENT};
+use secrecy::SecretString;
 use serde::Deserialize;
 use serde_json::json;
 use sqlx::{
@@ -514,6 +518,11 @@ async fn test_email_mfa(_: PgPoolOptions, options: PgConnectOptions) {
     );
     let code = extract_email_code(&mail.content);
 
+    // resending right after the code was sent is throttled
+    let response = client.post("/api/v1/auth/email/resend").send().await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_err!(mail_rx.try_recv());
+
     // login
     let response = client.post("/api/v1/auth").json(&auth).send().await;
     assert_eq!(response.status(), StatusCode::CREATED);
@@ -1059,3 +1068,164 @@ async fn test_all_session_logout(_: PgPoolOptions, options: PgConnectOptions) {
     let auth_cookie = response.cookies().find(|c| c.name() == SESSION_COOKIE_NAME);
     assert!(auth_cookie.is_none());
 }
+
+#[sqlx::test]
+async fn test_break_glass_admin(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, pool) = make_client_with_db(pool).await;
+    let mut config = DefGuardConfig::new_test_config();
+
+    // existing regular users can't be turned into break-glass accounts
+    config.break_glass_password = Some(SecretString::from("Break-glass-pass1"));
+    config.break_glass_username = "hpotter".into();
+    assert_err!(init_break_glass_admin(&pool, &config).await);
+
+    config.break_glass_username = "breakglass".into();
+    init_break_glass_admin(&pool, &config).await.unwrap();
+
+    client.login_user("breakglass", "Break-glass-pass1").await;
+    let event = client.next_api_event().unwrap();
+    assert_eq!(event.context.username, "breakglass");
+    assert!(matches!(*event.event, ApiEventType::BreakGlassLogin));
+    let event = client.next_api_event().unwrap();
+    assert!(matches!(*event.event, ApiEventType::UserLogin));
+
+    let user_info = fetch_user_details(&client, "breakglass").await.user;
+    assert!(user_info.is_admin);
+    assert!(user_info.is_active);
+    assert!(user_info.deactivate_at.unwrap() > Utc::now().naive_utc());
+
+    // account is scheduled for deactivation right away once the password is removed
+    config.break_glass_password = None;
+    init_break_glass_admin(&pool, &config).await.unwrap();
+    let user_info = fetch_user_details(&client, "breakglass").await.user;
+    assert!(user_info.deactivate_at.unwrap() <= Utc::now().naive_utc());
+}
+
+#[sqlx::test]
+async fn test_recovery_codes_regeneration(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_test_client(pool).await;
+
+    // login
+    let auth = Auth::new("hpotter", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // recovery codes are only available with MFA enabled
+    let response = client.post("/api/v1/auth/recovery/regenerate").send().await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // code usage of other users is only visible to admins
+    let response = client.get("/api/v1/user/admin/recovery_codes").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // enable TOTP
+    let response = client.post("/api/v1/auth/totp/init").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let auth_totp: AuthTotp = response.json().await;
+    let response = client
+        .post("/api/v1/auth/totp")
+        .json(&totp_code(&auth_totp))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let old_codes = response.json::<RecoveryCodes>().await.codes.unwrap();
+
+    let response = client
+        .get("/api/v1/user/hpotter/recovery_codes")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let info: RecoveryCodesInfo = response.json().await;
+    assert_eq!(info.remaining, 8);
+    assert!(info.used_at.is_empty());
+
+    // regenerate
+    client.drain_all_events();
+    let response = client.post("/api/v1/auth/recovery/regenerate").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let new_codes = response.json::<RecoveryCodes>().await.codes.unwrap();
+    assert_eq!(new_codes.len(), 8);
+    assert!(new_codes.iter().all(|code| !old_codes.contains(code)));
+    let event = client.next_api_event().unwrap();
+    assert!(matches!(
+        *event.event,
+        ApiEventType::RecoveryCodesRegenerated { count: 8 }
+    ));
+
+    // old codes no longer work
+    let response = client.post("/api/v1/auth/logout").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = client
+        .post("/api/v1/auth/recovery")
+        .json(&json!({ "code": old_codes[0] }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    client.drain_all_events();
+    let response = client
+        .post("/api/v1/auth/recovery")
+        .json(&json!({ "code": new_codes[0] }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let event = client.next_api_event().unwrap();
+    assert!(matches!(
+        *event.event,
+        ApiEventType::RecoveryCodeUsed { remaining: 7 }
+    ));
+
+    // admin sees code usage
+    client.login_user("admin", "pass123").await;
+    let response = client
+        .get("/api/v1/user/hpotter/recovery_codes")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let info: RecoveryCodesInfo = response.json().await;
+    assert_eq!(info.remaining, 7);
+    assert_eq!(info.used_at.len(), 1);
+}
+
+#[sqlx::test]
+async fn test_sms_mfa(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, _) = make_test_client(pool).await;
+
+    // try to initialize SMS MFA setup before logging in
+    let response = client
+        .post("/api/v1/auth/sms/init")
+        .json(&json!({ "phone": "+48123456789" }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // login
+    let auth = Auth::new("hpotter", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // try to initialize SMS MFA setup without SMS gateway configured
+    let response = client
+        .post("/api/v1/auth/sms/init")
+        .json(&json!({ "phone": "+48123456789" }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    // codes can't be requested without SMS MFA enabled
+    let response = client.get("/api/v1/auth/sms").send().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // SMS MFA is still disabled
+    let user_info = fetch_user_details(&client, "hpotter").await.user;
+    assert!(!user_info.sms_mfa_enabled);
+    assert!(!user_info.mfa_enabled);
+}
@@ -0,0 +1,259 @@
+use chrono::TimeDelta;
+use defguard_core::{
+    db::{
+        GatewayEvent,
+        models::{client_login_session::ClientLoginSession, device::WireguardNetworkDevice},
+    },
+    events::ApiEventType,
+};
+use defguard_proto::proxy::MfaMethod;
+use matches::assert_matches;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    query,
+};
+
+use super::common::{authenticate_admin, make_network, make_test_client, setup_pool};
+
+#[sqlx::test]
+async fn test_revoke_client_mfa_session(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, client_state) = make_test_client(pool).await;
+    let mut wg_rx = client_state.wireguard_rx;
+    let pool = client_state.pool;
+
+    // admin login
+    authenticate_admin(&mut client).await;
+
+    // create MFA location
+    let mut network = make_network();
+    network["location_mfa_mode"] = json!("internal");
+    let response = client.post("/api/v1/network").json(&network).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkCreated(..));
+
+    // create device
+    let response = client
+        .post("/api/v1/device/admin")
+        .json(&json!({
+            "name": "laptop",
+            "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::DeviceCreated(..));
+
+    // nothing to revoke yet
+    let response = client
+        .delete("/api/v1/client_mfa/sessions/1/1")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // device completes MFA
+    query(
+        "UPDATE wireguard_network_device SET is_authorized = true, authorized_at = NOW(), \
+        preshared_key = 'psk' WHERE device_id = 1 AND wireguard_network_id = 1",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let response = client.get("/api/v1/client_mfa/sessions").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let sessions: Value = response.json().await;
+    assert_eq!(sessions["pending"], json!([]));
+    assert_eq!(sessions["authorized"].as_array().unwrap().len(), 1);
+    assert_eq!(sessions["authorized"][0]["device_name"], "laptop");
+    assert_eq!(sessions["authorized"][0]["username"], "admin");
+
+    // device starts another login; expired logins aren't listed
+    let pubkey = "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=";
+    ClientLoginSession::new(
+        pubkey.into(),
+        MfaMethod::Totp,
+        1,
+        1,
+        1,
+        TimeDelta::minutes(-1),
+    )
+    .save(&pool)
+    .await
+    .unwrap();
+    let response = client.get("/api/v1/client_mfa/sessions").send().await;
+    let sessions: Value = response.json().await;
+    assert_eq!(sessions["pending"], json!([]));
+    ClientLoginSession::new(
+        pubkey.into(),
+        MfaMethod::Totp,
+        1,
+        1,
+        1,
+        TimeDelta::minutes(5),
+    )
+    .save(&pool)
+    .await
+    .unwrap();
+    let response = client.get("/api/v1/client_mfa/sessions").send().await;
+    let sessions: Value = response.json().await;
+    assert_eq!(sessions["pending"].as_array().unwrap().len(), 1);
+    assert_eq!(sessions["pending"][0]["device_name"], "laptop");
+    assert_eq!(sessions["pending"][0]["method"], "TOTP");
+
+    // revoke session
+    let response = client
+        .delete("/api/v1/client_mfa/sessions/1/1")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    match wg_rx.try_recv().unwrap() {
+        GatewayEvent::DeviceDeleted(info) => {
+            assert_eq!(info.device.id, 1);
+            assert_eq!(info.network_info[0].network_id, 1);
+        }
+        event => panic!("Unexpected event: {event:?}"),
+    }
+    let network_device = WireguardNetworkDevice::find_by_device(&pool, 1)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!network_device[0].is_authorized);
+    assert!(network_device[0].preshared_key.is_none());
+    assert!(
+        ClientLoginSession::find(&pool, pubkey)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let response = client.get("/api/v1/client_mfa/sessions").send().await;
+    let sessions: Value = response.json().await;
+    assert_eq!(sessions["pending"], json!([]));
+    assert_eq!(sessions["authorized"], json!([]));
+
+    // unknown device
+    let response = client
+        .delete("/api/v1/client_mfa/sessions/1/2")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn test_location_maintenance(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, client_state) = make_test_client(pool).await;
+    let mut wg_rx = client_state.wireguard_rx;
+    let pool = client_state.pool;
+
+    authenticate_admin(&mut client).await;
+
+    let mut network = make_network();
+    network["location_mfa_mode"] = json!("internal");
+    let response = client.post("/api/v1/network").json(&network).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkCreated(..));
+    let response = client
+        .post("/api/v1/device/admin")
+        .json(&json!({
+            "name": "laptop",
+            "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::DeviceCreated(..));
+    query(
+        "UPDATE wireguard_network_device SET is_authorized = true, authorized_at = NOW(), \
+        preshared_key = 'psk' WHERE device_id = 1 AND wireguard_network_id = 1",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    while client.next_api_event().is_some() {}
+
+    let response = client.get("/api/v1/network/1").send().await;
+    let location: Value = response.json().await;
+    assert_eq!(
+        location["maintenance"],
+        json!({"enabled": false, "message": null})
+    );
+
+    // disabling maintenance which isn't enabled doesn't emit an event
+    let response = client
+        .put("/api/v1/network/1/maintenance")
+        .json(&json!({"enabled": false}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(client.next_api_event().is_none());
+
+    // enable maintenance and disconnect devices logged in with MFA
+    let response = client
+        .put("/api/v1/network/1/maintenance")
+        .json(&json!({
+            "enabled": true,
+            "message": " Gateway upgrade until 18:00 ",
+            "disconnect_peers": true,
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let maintenance: Value = response.json().await;
+    assert_eq!(
+        maintenance,
+        json!({"enabled": true, "message": "Gateway upgrade until 18:00"})
+    );
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::DeviceDeleted(..));
+    let network_device = WireguardNetworkDevice::find_by_device(&pool, 1)
+        .await
+        .unwrap()
+        .unwrap()
+        .remove(0);
+    assert!(!network_device.is_authorized);
+    assert!(network_device.preshared_key.is_none());
+    let event = client.next_api_event().unwrap();
+    assert_matches!(
+        *event.event,
+        ApiEventType::VpnLocationMaintenanceEnabled {
+            disconnected: 1,
+            ..
+        }
+    );
+
+    let response = client.get("/api/v1/network/1").send().await;
+    let location: Value = response.json().await;
+    assert_eq!(location["maintenance"], maintenance);
+    let response = client.get("/api/v1/network").send().await;
+    let locations: Value = response.json().await;
+    assert_eq!(locations[0]["maintenance"], maintenance);
+
+    let response = client
+        .put("/api/v1/network/1/maintenance")
+        .json(&json!({"enabled": false}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let event = client.next_api_event().unwrap();
+    assert_matches!(
+        *event.event,
+        ApiEventType::VpnLocationMaintenanceDisabled { .. }
+    );
+    let response = client.get("/api/v1/network/1").send().await;
+    let location: Value = response.json().await;
+    assert_eq!(location["maintenance"]["enabled"], json!(false));
+
+    // only admins can set maintenance
+    client.login_user("hpotter", "pass123").await;
+    let response = client
+        .put("/api/v1/network/1/maintenance")
+        .json(&json!({"enabled": true}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
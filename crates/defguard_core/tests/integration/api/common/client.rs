@@ -67,9 +67,12 @@ impl TestClient {
     }
 
     pub fn set_cookie(&mut self, cookie: &Cookie) {
+        self.set_cookie_value(cookie.name(), cookie.value());
+    }
+
+    pub fn set_cookie_value(&mut self, name: &str, value: &str) {
         let url = Url::parse(&self.base_url()).unwrap();
-        self.jar
-            .add_cookie_str(&format!("{}={}", cookie.name(), cookie.value()), &url);
+        self.jar.add_cookie_str(&format!("{name}={value}"), &url);
     }
 
     // Helper to perform API login
@@ -223,6 +226,11 @@ impl TestClient {
         all_events
     }
 
+    /// Receive next message from API event queue, if any
+    pub fn next_api_event(&mut self) -> Option<ApiEvent> {
+        self.api_event_rx.try_recv().ok()
+    }
+
     /// Assert there are no events queued
     pub fn assert_event_queue_is_empty(&mut self) {
         match self.api_event_rx.try_recv() {
@@ -276,10 +284,10 @@ impl RequestBuilder {
         self
     }
 
-    // pub fn multipart(mut self, form: reqwest::multipart::Form) -> Self {
-    //     self.builder = self.builder.multipart(form);
-    //     self
-    // }
+    pub fn multipart(mut self, form: reqwest::multipart::Form) -> Self {
+        self.builder = self.builder.multipart(form);
+        self
+    }
 }
 
 /// A wrapper around [`reqwest::Response`] that provides common methods with internal `unwrap()`s.
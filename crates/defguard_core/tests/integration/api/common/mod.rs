@@ -131,6 +131,7 @@ pub(crate) async fn make_base_client(
         worker_state,
         gateway_state,
         pool,
+        None,
         failed_logins,
         api_event_tx,
         Version::parse(VERSION).unwrap(),
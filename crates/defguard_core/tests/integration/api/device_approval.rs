@@ -0,0 +1,108 @@
+use defguard_core::{
+    db::{GatewayEvent, WireguardNetwork},
+    events::ApiEventType,
+};
+use matches::assert_matches;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+use super::common::{authenticate_admin, make_network, make_test_client, setup_pool};
+
+#[sqlx::test]
+async fn test_device_approval(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, client_state) = make_test_client(pool).await;
+    let mut wg_rx = client_state.wireguard_rx;
+    let mut mail_rx = client_state.mail_rx;
+    let pool = client_state.pool;
+    authenticate_admin(&mut client).await;
+
+    // create location which requires device approval
+    let mut network = make_network();
+    network["device_approval_required"] = json!(true);
+    let response = client.post("/api/v1/network").json(&network).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkCreated(..));
+    let response = client.get("/api/v1/network/1").send().await;
+    let location: Value = response.json().await;
+    assert_eq!(location["device_approval_required"], json!(true));
+    let location = WireguardNetwork::find_by_id(&pool, 1)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // new devices aren't pushed to gateways
+    for (name, pubkey) in [
+        ("laptop", "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU="),
+        ("phone", "hNuapt7lOxF93KUqZGUY00oKJxH8LYwwsUVB1uUa0y4="),
+    ] {
+        let response = client
+            .post("/api/v1/device/admin")
+            .json(&json!({"name": name, "wireguard_pubkey": pubkey}))
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let event = wg_rx.try_recv().unwrap();
+        assert_matches!(event, GatewayEvent::DeviceCreated(info) if info.network_info.is_empty());
+    }
+    assert!(location.get_peers(&pool).await.unwrap().is_empty());
+    while mail_rx.try_recv().is_ok() {}
+    client.drain_all_events();
+
+    let response = client.get("/api/v1/device-approval").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let approvals: Value = response.json().await;
+    assert_eq!(approvals.as_array().unwrap().len(), 2);
+    assert_eq!(approvals[0]["device_name"], "laptop");
+    assert_eq!(approvals[0]["username"], "admin");
+    assert_eq!(approvals[0]["location_name"], "network");
+
+    // approve the first device
+    let response = client
+        .post("/api/v1/device-approval/1/approve")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let event = wg_rx.try_recv().unwrap();
+    assert_matches!(event, GatewayEvent::DeviceCreated(info) if info.network_info.len() == 1);
+    let peers = location.get_peers(&pool).await.unwrap();
+    assert_eq!(peers.len(), 1);
+    assert_eq!(
+        peers[0].pubkey,
+        "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU="
+    );
+    let mail = mail_rx.try_recv().unwrap();
+    assert_eq!(mail.subject, "Defguard: device approved");
+    assert_matches!(
+        *client.next_api_event().unwrap().event,
+        ApiEventType::DeviceApproved { .. }
+    );
+    let response = client.post("/api/v1/device-approval/1/reject").send().await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // reject the second one
+    let response = client.post("/api/v1/device-approval/2/reject").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(wg_rx.try_recv().is_err());
+    assert_eq!(location.get_peers(&pool).await.unwrap().len(), 1);
+    let mail = mail_rx.try_recv().unwrap();
+    assert_eq!(mail.subject, "Defguard: device rejected");
+    assert_matches!(
+        *client.next_api_event().unwrap().event,
+        ApiEventType::DeviceRejected { .. }
+    );
+    let response = client.get("/api/v1/device-approval").send().await;
+    let approvals: Value = response.json().await;
+    assert_eq!(approvals, json!([]));
+
+    // rejected devices can connect once the location stops requiring approval
+    network["device_approval_required"] = json!(false);
+    let response = client.put("/api/v1/network/1").json(&network).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_matches!(
+        wg_rx.try_recv().unwrap(),
+        GatewayEvent::NetworkModified(_, _, peers, _) if peers.len() == 2
+    );
+}
@@ -0,0 +1,174 @@
+use defguard_core::{db::GatewayEvent, events::ApiEventType, handlers::Auth};
+use matches::assert_matches;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+use super::common::{make_network, make_test_client, setup_pool};
+
+#[sqlx::test]
+async fn test_device_profile(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, client_state) = make_test_client(pool).await;
+    let mut wg_rx = client_state.wireguard_rx;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // create two locations
+    for _ in 0..2 {
+        let response = client
+            .post("/api/v1/network")
+            .json(&make_network())
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkCreated(..));
+    }
+
+    // create device
+    let response = client
+        .post("/api/v1/device/admin")
+        .json(&json!({
+            "name": "server",
+            "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::DeviceCreated(..));
+    client.drain_all_events();
+
+    // profile referencing non-existing location is rejected
+    let response = client
+        .post("/api/v1/device_profile")
+        .json(&json!({
+            "name": "servers",
+            "allowed_locations": [3],
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // create profile
+    let response = client
+        .post("/api/v1/device_profile")
+        .json(&json!({
+            "name": "servers",
+            "description": "Servers in the datacenter",
+            "dns": "10.0.0.53",
+            "keepalive_interval": 5,
+            "allowed_locations": [1],
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let profile: Value = response.json().await;
+    let profile_id = profile["id"].as_i64().unwrap();
+    assert_eq!(profile["restrict_locations"], true);
+    assert_matches!(
+        *client.next_api_event().unwrap().event,
+        ApiEventType::DeviceProfileAdded { ref profile } if profile.name == "servers"
+    );
+
+    // profile names are unique
+    let response = client
+        .post("/api/v1/device_profile")
+        .json(&json!({"name": "servers"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    // assign profile to device
+    let response = client
+        .put("/api/v1/device/1/profile")
+        .json(&json!({"profile_id": profile_id}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_matches!(
+        *client.next_api_event().unwrap().event,
+        ApiEventType::DeviceProfileAssigned {
+            ref device,
+            profile: Some(ref profile),
+        } if device.id == 1 && profile.id == profile_id
+    );
+    // gateways of all locations get updated peers
+    for location_id in 1..=2 {
+        match wg_rx.try_recv().unwrap() {
+            GatewayEvent::NetworkModified(id, _, peers, _) => {
+                assert_eq!(id, location_id);
+                if id == 1 {
+                    assert_eq!(peers.len(), 1);
+                    assert_eq!(peers[0].keepalive_interval, Some(5));
+                } else {
+                    assert!(peers.is_empty());
+                }
+            }
+            event => panic!("Unexpected event: {event:?}"),
+        }
+    }
+
+    let response = client.get("/api/v1/device_profile").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let profiles: Value = response.json().await;
+    assert_eq!(profiles[0]["name"], "servers");
+    assert_eq!(profiles[0]["devices"], json!([1]));
+
+    // config uses profile overrides
+    let response = client.get("/api/v1/network/1/device/1/config").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let config = response.text().await;
+    assert!(config.contains("DNS = 10.0.0.53\n"));
+    assert!(config.ends_with("PersistentKeepalive = 5"));
+
+    // location not allowed by profile
+    let response = client.get("/api/v1/network/2/device/1/config").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // remove profile assignment
+    let response = client
+        .put("/api/v1/device/1/profile")
+        .json(&json!({"profile_id": null}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/network/2/device/1/config").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let config = response.text().await;
+    assert!(config.contains("DNS = 1.1.1.1\n"));
+    assert_matches!(
+        *client.next_api_event().unwrap().event,
+        ApiEventType::DeviceProfileAssigned { profile: None, .. }
+    );
+
+    // removing the only allowed location doesn't allow all locations
+    let response = client.delete("/api/v1/network/1").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .get(format!("/api/v1/device_profile/{profile_id}"))
+        .send()
+        .await;
+    let profile: Value = response.json().await;
+    assert_eq!(profile["allowed_locations"], json!([]));
+    assert_eq!(profile["restrict_locations"], true);
+    client.drain_all_events();
+
+    // delete profile
+    let response = client
+        .delete(format!("/api/v1/device_profile/{profile_id}"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_matches!(
+        *client.next_api_event().unwrap().event,
+        ApiEventType::DeviceProfileRemoved { .. }
+    );
+    let response = client
+        .get(format!("/api/v1/device_profile/{profile_id}"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
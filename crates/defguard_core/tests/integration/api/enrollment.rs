@@ -1,6 +1,6 @@
 use chrono::Duration;
 use defguard_core::{
-    db::{User, models::enrollment::Token},
+    db::{Group, User, models::enrollment::Token},
     handlers::{AddUserData, Auth},
 };
 use reqwest::StatusCode;
@@ -28,6 +28,7 @@ async fn test_initialize_enrollment(_: PgPoolOptions, options: PgConnectOptions)
         email: "a.dumbledore@hogwart.edu.uk".into(),
         phone: Some("1234".into()),
         password: Some("Password1234543$!".into()),
+        service_account: false,
     };
     let response = client.post("/api/v1/user").json(&new_user).send().await;
     assert_eq!(response.status(), StatusCode::CREATED);
@@ -56,6 +57,7 @@ async fn test_initialize_enrollment(_: PgPoolOptions, options: PgConnectOptions)
         email: "a.dumbledore2@hogwart.edu.uk".into(),
         phone: Some("1234".into()),
         password: None,
+        service_account: false,
     };
     let response = client.post("/api/v1/user").json(&new_user).send().await;
     assert_eq!(response.status(), StatusCode::CREATED);
@@ -99,6 +101,7 @@ async fn test_enroll_disabled_user(_: PgPoolOptions, options: PgConnectOptions)
         email: "a.dumbledore@hogwart.edu.uk".into(),
         phone: Some("1234".into()),
         password: None,
+        service_account: false,
     };
     let response = client.post("/api/v1/user").json(&new_user).send().await;
     assert_eq!(response.status(), StatusCode::CREATED);
@@ -143,6 +146,7 @@ async fn test_enrollment_pending_unset_for_regular_user(
         email: "a.dumbledore@hogwart.edu.uk".into(),
         phone: Some("1234".into()),
         password: Some("Password1234543$!".into()),
+        service_account: false,
     };
     let response = client.post("/api/v1/user").json(&new_user).send().await;
     assert_eq!(response.status(), StatusCode::CREATED);
@@ -177,6 +181,7 @@ async fn test_request_enrollment(_: PgPoolOptions, options: PgConnectOptions) {
         email: "a.dumbledore@hogwart.edu.uk".into(),
         phone: Some("1234".into()),
         password: None,
+        service_account: false,
     };
     let response = client.post("/api/v1/user").json(&new_user).send().await;
     assert_eq!(response.status(), StatusCode::CREATED);
@@ -237,6 +242,7 @@ async fn test_enrollment_token_expiration_time(_: PgPoolOptions, options: PgConn
         email: "a.dumbledore@hogwart.edu.uk".into(),
         phone: Some("1234".into()),
         password: None,
+        service_account: false,
     };
     let response = client.post("/api/v1/user").json(&new_user).send().await;
     assert_eq!(response.status(), StatusCode::CREATED);
@@ -328,6 +334,7 @@ async fn test_enrollment_pending_unset_for_desktop_client(
         email: "a.dumbledore@hogwart.edu.uk".into(),
         phone: Some("1234".into()),
         password: Some("Password1234543$!".into()),
+        service_account: false,
     };
     let response = client.post("/api/v1/user").json(&new_user).send().await;
     assert_eq!(response.status(), StatusCode::CREATED);
@@ -367,3 +374,93 @@ async fn test_enrollment_pending_unset_for_desktop_client(
     assert!(!user.enrollment_pending);
     assert!(user.is_enrolled());
 }
+
+#[sqlx::test]
+async fn test_enrollment_token_options(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, pool) = make_client_with_db(pool).await;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // create user without password
+    let new_user = AddUserData {
+        username: "adumbledore".into(),
+        last_name: "Dumbledore".into(),
+        first_name: "Albus".into(),
+        email: "a.dumbledore@hogwart.edu.uk".into(),
+        phone: Some("1234".into()),
+        password: None,
+        service_account: false,
+    };
+    let response = client.post("/api/v1/user").json(&new_user).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    Group::new("staff").save(&pool).await.unwrap();
+
+    // invalid options
+    let response = client
+        .post("/api/v1/user/adumbledore/start_enrollment")
+        .json(&json!({"max_uses": 0}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = client
+        .post("/api/v1/user/adumbledore/start_enrollment")
+        .json(&json!({"groups": ["teachers"]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = client
+        .post("/api/v1/user/adumbledore/start_enrollment")
+        .json(&json!({"locations": [1000]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let tokens = Token::fetch_all(&pool).await.unwrap();
+    assert!(tokens.is_empty());
+
+    // create customized token
+    let response = client
+        .post("/api/v1/user/adumbledore/start_enrollment")
+        .json(&json!({"max_uses": 3, "groups": ["staff"]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let tokens = Token::fetch_all(&pool).await.unwrap();
+    assert_eq!(tokens.len(), 1);
+    let token = tokens.first().unwrap();
+    assert_eq!(token.max_uses, 3);
+    assert_eq!(token.use_count, 0);
+    let groups = token.fetch_groups(&pool).await.unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].name, "staff");
+
+    // list tokens
+    let response = client.get("/api/v1/enrollment_token").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let listed: serde_json::Value = response.json().await;
+    let listed = listed.as_array().unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0]["id"], token.id);
+    assert_eq!(listed[0]["username"], "adumbledore");
+    assert_eq!(listed[0]["admin"], "admin");
+    assert_eq!(listed[0]["max_uses"], 3);
+    assert_eq!(listed[0]["groups"], json!(["staff"]));
+
+    // revoke token
+    let response = client
+        .delete(format!("/api/v1/enrollment_token/{}", token.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .delete(format!("/api/v1/enrollment_token/{}", token.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = client.get("/api/v1/enrollment_token").send().await;
+    let listed: serde_json::Value = response.json().await;
+    assert!(listed.as_array().unwrap().is_empty());
+}
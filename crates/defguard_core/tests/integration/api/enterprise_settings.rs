@@ -38,6 +38,8 @@ async fn test_only_enterprise_can_modify_enterpise_settings(
         admin_device_management: false,
         client_traffic_policy: ClientTrafficPolicy::None,
         only_client_activation: false,
+        self_service_allowed_networks: Vec::new(),
+        self_service_allow_vpn: false,
     };
 
     let response = client
@@ -86,6 +88,8 @@ async fn test_admin_devices_management_is_enforced(_: PgPoolOptions, options: Pg
         admin_device_management: true,
         client_traffic_policy: ClientTrafficPolicy::None,
         only_client_activation: false,
+        self_service_allowed_networks: Vec::new(),
+        self_service_allow_vpn: false,
     };
     let response = client
         .patch("/api/v1/settings_enterprise")
@@ -182,6 +186,8 @@ async fn test_regular_user_device_management(_: PgPoolOptions, options: PgConnec
         admin_device_management: false,
         client_traffic_policy: ClientTrafficPolicy::None,
         only_client_activation: false,
+        self_service_allowed_networks: Vec::new(),
+        self_service_allow_vpn: false,
     };
     let response = client
         .patch("/api/v1/settings_enterprise")
@@ -270,6 +276,8 @@ async fn dg25_12_test_enforce_client_activation_only(_: PgPoolOptions, options:
         admin_device_management: false,
         client_traffic_policy: ClientTrafficPolicy::None,
         only_client_activation: true,
+        self_service_allowed_networks: Vec::new(),
+        self_service_allow_vpn: false,
     };
     let response = client
         .patch("/api/v1/settings_enterprise")
@@ -351,6 +359,8 @@ async fn dg25_13_test_disable_device_config(_: PgPoolOptions, options: PgConnect
         admin_device_management: false,
         client_traffic_policy: ClientTrafficPolicy::None,
         only_client_activation: true,
+        self_service_allowed_networks: Vec::new(),
+        self_service_allow_vpn: false,
     };
     let response = client
         .patch("/api/v1/settings_enterprise")
@@ -407,6 +417,8 @@ async fn test_override_allowed_ips(_: PgPoolOptions, options: PgConnectOptions)
         admin_device_management: false,
         client_traffic_policy: ClientTrafficPolicy::ForceAllTraffic,
         only_client_activation: false,
+        self_service_allowed_networks: Vec::new(),
+        self_service_allow_vpn: false,
     };
     let response = client
         .patch("/api/v1/settings_enterprise")
@@ -441,3 +453,46 @@ async fn test_override_allowed_ips(_: PgPoolOptions, options: PgConnectOptions)
         )
     }
 }
+
+#[sqlx::test]
+async fn test_self_service_network_policy(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    // admin login
+    let (client, _) = make_test_client(pool).await;
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    exceed_enterprise_limits(&client).await;
+
+    // no restrictions by default
+    let response = client.get("/api/v1/settings_enterprise").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let settings: EnterpriseSettings = response.json().await;
+    assert!(settings.self_service_allowed_networks.is_empty());
+    assert!(!settings.self_service_allow_vpn);
+
+    // restrict enrollment and password reset
+    let response = client
+        .patch("/api/v1/settings_enterprise")
+        .json(&json!({
+            "self_service_allowed_networks": ["192.168.0.0/16", "fd00::/64"],
+            "self_service_allow_vpn": true,
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get("/api/v1/settings_enterprise").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let settings: EnterpriseSettings = response.json().await;
+    assert_eq!(
+        settings.self_service_allowed_networks,
+        vec![
+            "192.168.0.0/16".parse::<IpNetwork>().unwrap(),
+            "fd00::/64".parse::<IpNetwork>().unwrap(),
+        ]
+    );
+    assert!(settings.self_service_allow_vpn);
+}
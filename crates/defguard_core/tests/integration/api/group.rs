@@ -1,9 +1,15 @@
-use defguard_core::handlers::{Auth, EditGroupInfo, GroupInfo};
+use chrono::{TimeDelta, Utc};
+use defguard_common::db::models::{Settings, settings::update_current_settings};
+use defguard_core::{
+    db::models::group::{GroupQuotaUsage, GroupSyncAuthority},
+    events::ApiEventType,
+    handlers::{Auth, EditGroupInfo, GroupInfo},
+};
 use reqwest::StatusCode;
-use serde_json::json;
+use serde_json::{Value, json};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 
-use super::common::{make_test_client, setup_pool};
+use super::common::{fetch_user_details, make_network, make_test_client, setup_pool};
 
 #[sqlx::test]
 async fn test_create_group(_: PgPoolOptions, options: PgConnectOptions) {
@@ -23,7 +29,7 @@ async fn test_create_group(_: PgPoolOptions, options: PgConnectOptions) {
 
     // Try to create the same group again.
     let response = client.post("/api/v1/group").json(&data).send().await;
-    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(response.status(), StatusCode::CONFLICT);
 
     // Delete the group.
     let response = client.delete("/api/v1/group/hogwards").send().await;
@@ -235,3 +241,857 @@ async fn test_group_endpoints_access(_: PgPoolOptions, options: PgConnectOptions
     let response = client.put("/api/v1/group/admin").json(&data).send().await;
     assert_eq!(response.status(), StatusCode::FORBIDDEN);
 }
+
+#[sqlx::test]
+async fn test_group_quota(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, _) = make_test_client(pool).await;
+
+    // Authorize as an administrator.
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let data = EditGroupInfo::new("contractors", Vec::new(), false);
+    let response = client.post("/api/v1/group").json(&data).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // Invalid quotas.
+    let response = client
+        .put("/api/v1/group-quota/contractors")
+        .json(&json!({"max_users": -1, "max_devices": null}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = client
+        .put("/api/v1/group-quota/contractors")
+        .json(&json!({"max_users": null, "max_devices": null, "max_sessions": 0}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = client
+        .put("/api/v1/group-quota/nonexistent")
+        .json(&json!({"max_users": 1, "max_devices": 1}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client
+        .put("/api/v1/group-quota/contractors")
+        .json(&json!({"max_users": 1, "max_devices": 1, "max_sessions": 2}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Only one member is allowed.
+    let response = client
+        .post("/api/v1/group/contractors")
+        .json(&json!({"username": "hpotter"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .post("/api/v1/group/contractors")
+        .json(&json!({"username": "admin"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let data = EditGroupInfo::new("contractors", vec!["hpotter".into(), "admin".into()], false);
+    let response = client
+        .put("/api/v1/group/contractors")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Only one device is allowed for all members.
+    let response = client
+        .post("/api/v1/device/hpotter")
+        .json(&json!({
+            "name": "laptop",
+            "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = client
+        .post("/api/v1/device/hpotter")
+        .json(&json!({
+            "name": "phone",
+            "wireguard_pubkey": "hNuapt7lOxF93KUqZGUY00oKJxH8LYwwsUVB1uUa0y4=",
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Quota utilization report.
+    let response = client.get("/api/v1/group-quota").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let quotas: Vec<GroupQuotaUsage> = response.json().await;
+    let quota = quotas.iter().find(|q| q.name == "contractors").unwrap();
+    assert_eq!(quota.max_users, Some(1));
+    assert_eq!(quota.users, 1);
+    assert_eq!(quota.max_devices, Some(1));
+    assert_eq!(quota.devices, 1);
+    assert_eq!(quota.max_sessions, Some(2));
+    let admin_quota = quotas.iter().find(|q| q.name == "admin").unwrap();
+    assert_eq!(admin_quota.max_users, None);
+}
+
+#[sqlx::test]
+async fn test_group_sync_authority(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, _) = make_test_client(pool.clone()).await;
+
+    // Authorize as an administrator.
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let data = EditGroupInfo::new("staff", Vec::new(), false);
+    let response = client.post("/api/v1/group").json(&data).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = client
+        .put("/api/v1/group/nonexistent/sync_authority")
+        .json(&json!({"sync_authority": "ldap"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = client
+        .put("/api/v1/group/staff/sync_authority")
+        .json(&json!({"sync_authority": "ldap"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Membership of LDAP-managed groups can't be changed locally while LDAP sync is enabled.
+    let mut settings = Settings::get_current_settings();
+    settings.ldap_enabled = true;
+    settings.ldap_sync_enabled = true;
+    update_current_settings(&pool, settings).await.unwrap();
+    let response = client
+        .post("/api/v1/group/staff")
+        .json(&json!({"username": "hpotter"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = client.delete("/api/v1/group/staff").send().await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    // neither through user details
+    let mut user_details = fetch_user_details(&client, "hpotter").await;
+    user_details.user.groups.push("staff".into());
+    let response = client
+        .put("/api/v1/user/hpotter")
+        .json(&user_details.user)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(
+        !fetch_user_details(&client, "hpotter")
+            .await
+            .user
+            .groups
+            .contains(&"staff".to_string())
+    );
+    // nor by moving it in group hierarchy
+    let response = client
+        .put("/api/v1/group/staff/parent")
+        .json(&json!({"parent": "admin"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Local changes are allowed again once LDAP sync is disabled.
+    let mut settings = Settings::get_current_settings();
+    settings.ldap_enabled = false;
+    settings.ldap_sync_enabled = false;
+    update_current_settings(&pool, settings).await.unwrap();
+    let response = client
+        .post("/api/v1/group/staff")
+        .json(&json!({"username": "hpotter"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .put("/api/v1/group/staff/sync_authority")
+        .json(&json!({"sync_authority": null}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // No sync has run, so there are no conflicts to report.
+    let response = client.get("/api/v1/ldap/conflicts").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let conflicts: serde_json::Value = response.json().await;
+    assert_eq!(conflicts, json!([]));
+}
+
+#[sqlx::test]
+async fn test_group_parent(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, _) = make_test_client(pool).await;
+
+    // Authorize as an administrator.
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let data = EditGroupInfo::new("hogwards", vec!["hpotter".into()], false);
+    let response = client.post("/api/v1/group").json(&data).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = client
+        .put("/api/v1/group/hogwards/parent")
+        .json(&json!({"parent": "nonexistent"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = client
+        .put("/api/v1/group/hogwards/parent")
+        .json(&json!({"parent": "hogwards"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Nest group under admin group.
+    let response = client
+        .put("/api/v1/group/hogwards/parent")
+        .json(&json!({"parent": "admin"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/group/hogwards").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let group_info: GroupInfo = response.json().await;
+    assert_eq!(group_info.parent, Some("admin".into()));
+    assert!(!group_info.is_admin);
+
+    // Cycles are rejected.
+    let response = client
+        .put("/api/v1/group/admin/parent")
+        .json(&json!({"parent": "hogwards"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Members of nested group inherit admin permission.
+    client.post("/api/v1/auth/logout").send().await;
+    let auth = Auth::new("hpotter", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/group").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Make it a top-level group again.
+    let response = client
+        .put("/api/v1/group/hogwards/parent")
+        .json(&json!({"parent": null}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/group").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test]
+async fn test_group_member_expiry(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, _) = make_test_client(pool).await;
+
+    // Authorize as an administrator.
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let data = EditGroupInfo::new("contractors", Vec::new(), false);
+    let response = client.post("/api/v1/group").json(&data).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // Expiry must be in the future.
+    let expires_at = Utc::now().naive_utc() - TimeDelta::hours(1);
+    let response = client
+        .post("/api/v1/group/contractors")
+        .json(&json!({"username": "hpotter", "expires_at": expires_at}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let expires_at = Utc::now().naive_utc() + TimeDelta::days(7);
+    let response = client
+        .post("/api/v1/group/contractors")
+        .json(&json!({"username": "hpotter", "expires_at": expires_at}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get("/api/v1/group/contractors").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let group: GroupInfo = response.json().await;
+    assert_eq!(group.members, vec!["hpotter".to_string()]);
+}
+
+#[sqlx::test]
+async fn test_group_settings_events(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_test_client(pool).await;
+    client.login_user("admin", "pass123").await;
+
+    for name in ["staff", "engineering"] {
+        let data = EditGroupInfo::new(name, Vec::new(), false);
+        let response = client.post("/api/v1/group").json(&data).send().await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    client.drain_all_events();
+
+    let response = client
+        .put("/api/v1/group-quota/staff")
+        .json(&json!({"max_users": 5, "max_devices": null}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .put("/api/v1/group/staff/sync_authority")
+        .json(&json!({"sync_authority": "merge"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .put("/api/v1/group/staff/parent")
+        .json(&json!({"parent": "engineering"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let events = client.drain_all_events();
+    assert_eq!(events.len(), 3);
+    assert!(matches!(
+        &events[0].0,
+        ApiEventType::GroupQuotaModified { group, quota }
+            if group.name == "staff" && quota.max_users == Some(5) && quota.max_devices.is_none()
+    ));
+    assert!(matches!(
+        &events[1].0,
+        ApiEventType::GroupSyncAuthorityModified {
+            group,
+            sync_authority: Some(GroupSyncAuthority::Merge),
+        } if group.name == "staff"
+    ));
+    assert!(matches!(
+        &events[2].0,
+        ApiEventType::GroupParentModified { group, parent: Some(parent) }
+            if group.name == "staff" && parent.name == "engineering"
+    ));
+}
+
+#[sqlx::test]
+async fn test_group_events(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_test_client(pool).await;
+    client.login_user("admin", "pass123").await;
+
+    let data = EditGroupInfo::new("staff", Vec::new(), false);
+    let response = client.post("/api/v1/group").json(&data).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = client
+        .post("/api/v1/group/staff")
+        .json(&json!({"username": "hpotter"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .delete("/api/v1/group/staff/user/hpotter")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let data = EditGroupInfo::new("crew", vec!["hpotter".into()], false);
+    let response = client.put("/api/v1/group/staff").json(&data).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .post("/api/v1/groups-assign")
+        .json(&json!({"groups": ["crew"], "users": [1]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.delete("/api/v1/group/crew").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let events = client.drain_all_events();
+    assert_eq!(events.len(), 7);
+    // all events are attributed to the admin performing the changes
+    assert!(
+        events
+            .iter()
+            .all(|(_, user_id, username)| *user_id == 1 && username == "admin")
+    );
+    assert!(matches!(
+        &events[0].0,
+        ApiEventType::GroupAdded { group } if group.name == "staff"
+    ));
+    assert!(matches!(
+        &events[1].0,
+        ApiEventType::GroupMemberAdded { group, user }
+            if group.name == "staff" && user.username == "hpotter"
+    ));
+    assert!(matches!(
+        &events[2].0,
+        ApiEventType::GroupMemberRemoved { group, user }
+            if group.name == "staff" && user.username == "hpotter"
+    ));
+    assert!(matches!(
+        &events[3].0,
+        ApiEventType::GroupMembersModified { group, added, removed }
+            if group.name == "crew" && added.len() == 1 && removed.is_empty()
+    ));
+    assert!(matches!(
+        &events[4].0,
+        ApiEventType::GroupModified { before, after }
+            if before.name == "staff" && after.name == "crew"
+    ));
+    assert!(matches!(
+        &events[5].0,
+        ApiEventType::GroupsBulkAssigned { users, groups }
+            if users.len() == 1 && groups.len() == 1 && groups[0].name == "crew"
+    ));
+    assert!(matches!(
+        &events[6].0,
+        ApiEventType::GroupRemoved { group } if group.name == "crew"
+    ));
+}
+
+#[sqlx::test]
+async fn test_group_join_request(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, state) = make_test_client(pool).await;
+    let mut mail_rx = state.mail_rx;
+    client.login_user("admin", "pass123").await;
+
+    for name in ["quidditch", "staff"] {
+        let data = EditGroupInfo::new(name, Vec::new(), false);
+        let response = client.post("/api/v1/group").json(&data).send().await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    let response = client
+        .put("/api/v1/group/quidditch/self_service")
+        .json(&json!({"self_service": true, "owners": ["admin"]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .put("/api/v1/group/quidditch/self_service")
+        .json(&json!({"self_service": true, "owners": ["nobody"]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    client.drain_all_events();
+
+    // ask to join as a regular user
+    client.login_user("hpotter", "pass123").await;
+    let response = client.get("/api/v1/group-self-service").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let groups: Value = response.json().await;
+    assert_eq!(groups, json!([{"name": "quidditch", "pending": false}]));
+
+    let response = client
+        .post("/api/v1/group/staff/join_request")
+        .json(&json!({}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = client
+        .post("/api/v1/group/quidditch/join_request")
+        .json(&json!({"comment": "I can play seeker"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = client
+        .post("/api/v1/group/quidditch/join_request")
+        .json(&json!({}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // group owner is notified
+    let mail = std::iter::from_fn(|| mail_rx.try_recv().ok())
+        .find(|mail| mail.subject == "Defguard: request to join a group")
+        .unwrap();
+    assert_eq!(mail.to, "admin@defguard");
+    assert!(mail.content.contains("I can play seeker"));
+
+    let response = client.get("/api/v1/group-self-service").send().await;
+    let groups: Value = response.json().await;
+    assert_eq!(groups, json!([{"name": "quidditch", "pending": true}]));
+
+    // only admins and group owners can decide on requests
+    let response = client.get("/api/v1/group-join-request").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let requests: Value = response.json().await;
+    assert_eq!(requests, json!([]));
+
+    client.login_user("admin", "pass123").await;
+    let response = client.get("/api/v1/group-join-request").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let requests: Value = response.json().await;
+    let requests = requests.as_array().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["group"], "quidditch");
+    assert_eq!(requests[0]["username"], "hpotter");
+    let id = requests[0]["id"].as_i64().unwrap();
+
+    client.login_user("hpotter", "pass123").await;
+    let response = client
+        .post(format!("/api/v1/group-join-request/{id}/approve"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    client.login_user("admin", "pass123").await;
+    let response = client
+        .post(format!("/api/v1/group-join-request/{id}/approve"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .post(format!("/api/v1/group-join-request/{id}/reject"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = client.get("/api/v1/group/quidditch").send().await;
+    let group: GroupInfo = response.json().await;
+    assert_eq!(group.members, vec!["hpotter"]);
+
+    let events = client.drain_all_events();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(
+        &events[0].0,
+        ApiEventType::GroupJoinRequestApproved { group, user }
+            if group.name == "quidditch" && user.username == "hpotter"
+    ));
+}
+
+#[sqlx::test]
+async fn test_group_owner(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_test_client(pool).await;
+    client.login_user("admin", "pass123").await;
+
+    for (name, is_admin) in [
+        ("staff", false),
+        ("crew", false),
+        ("board", true),
+        ("council", false),
+    ] {
+        let data = EditGroupInfo::new(name, Vec::new(), is_admin);
+        let response = client.post("/api/v1/group").json(&data).send().await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    // council members inherit admin permissions from board
+    let response = client
+        .put("/api/v1/group/council/parent")
+        .json(&json!({"parent": "board"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    for name in ["staff", "council"] {
+        let response = client
+            .put(format!("/api/v1/group/{name}/owners"))
+            .json(&json!({"owners": ["hpotter"]}))
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = client
+        .put("/api/v1/group/staff/owners")
+        .json(&json!({"owners": ["nobody"]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    client.drain_all_events();
+
+    // owner can manage members of their own group
+    client.login_user("hpotter", "pass123").await;
+    let response = client
+        .post("/api/v1/group/staff")
+        .json(&json!({"username": "admin"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.delete("/api/v1/group/staff/user/admin").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // but not of other groups, nor groups granting admin permissions
+    for name in ["crew", "council", "nonexistent"] {
+        let response = client
+            .post(format!("/api/v1/group/{name}"))
+            .json(&json!({"username": "hpotter"}))
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+    let response = client.delete("/api/v1/group/crew/user/admin").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .put("/api/v1/group/staff/owners")
+        .json(&json!({"owners": []}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client.get("/api/v1/group/staff").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let events = client.drain_all_events();
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().all(|(_, _, username)| username == "hpotter"));
+    assert!(matches!(
+        &events[0].0,
+        ApiEventType::GroupMemberAdded { group, user }
+            if group.name == "staff" && user.username == "admin"
+    ));
+}
+
+#[sqlx::test]
+async fn test_groups_info_pagination(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_test_client(pool).await;
+    client.login_user("admin", "pass123").await;
+
+    for (name, members) in [
+        ("team-alpha", vec!["hpotter".into(), "admin".into()]),
+        ("team-beta", Vec::new()),
+        ("team-gamma", vec!["hpotter".into()]),
+    ] {
+        let data = EditGroupInfo::new(name, members, false);
+        let response = client.post("/api/v1/group").json(&data).send().await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    // all groups are returned without pagination parameters
+    let response = client.get("/api/v1/group-info").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let total: usize = response.headers()["x-total-count"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let groups: Vec<GroupInfo> = response.json().await;
+    assert_eq!(groups.len(), total);
+
+    let response = client
+        .get("/api/v1/group-info?search=team-&per_page=2&page=2")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-total-count"], "3");
+    let groups: Vec<GroupInfo> = response.json().await;
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].name, "team-gamma");
+    assert_eq!(groups[0].members, vec!["hpotter"]);
+
+    // search matches usernames of members
+    let response = client
+        .get("/api/v1/group-info?search=POTTER&sort_by=members&sort_order=desc")
+        .send()
+        .await;
+    assert_eq!(response.headers()["x-total-count"], "2");
+    let groups: Vec<GroupInfo> = response.json().await;
+    let names: Vec<_> = groups.iter().map(|group| group.name.as_str()).collect();
+    assert_eq!(names, ["team-alpha", "team-gamma"]);
+    assert_eq!(groups[0].members, vec!["admin", "hpotter"]);
+
+    let response = client.get("/api/v1/group-info?page=0").send().await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test]
+async fn test_create_group_if_not_exists(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_test_client(pool).await;
+    client.login_user("admin", "pass123").await;
+
+    let data = EditGroupInfo::new("hogwards", vec!["hpotter".into()], false);
+    let response = client
+        .post("/api/v1/group?if_not_exists=true")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // existing members are kept, missing ones added
+    let data = EditGroupInfo::new("hogwards", vec!["admin".into(), "hpotter".into()], true);
+    let response = client
+        .post("/api/v1/group?if_not_exists=true")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let merged: Value = response.json().await;
+    assert_eq!(merged, json!({"name": "hogwards", "added": ["admin"]}));
+
+    // nothing to add
+    let response = client
+        .post("/api/v1/group?if_not_exists=true")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let merged: Value = response.json().await;
+    assert_eq!(merged, json!({"name": "hogwards", "added": []}));
+
+    let data = EditGroupInfo::new("hogwards", vec!["nobody".into()], false);
+    let response = client
+        .post("/api/v1/group?if_not_exists=true")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client.get("/api/v1/group/hogwards").send().await;
+    let mut group: GroupInfo = response.json().await;
+    group.members.sort();
+    assert_eq!(group.members, vec!["admin", "hpotter"]);
+    // admin flag of an existing group isn't changed
+    assert!(!group.is_admin);
+
+    let events = client.drain_all_events();
+    assert_eq!(events.len(), 2);
+    assert!(matches!(
+        &events[0].0,
+        ApiEventType::GroupAdded { group } if group.name == "hogwards"
+    ));
+    assert!(matches!(
+        &events[1].0,
+        ApiEventType::GroupMembersModified { group, added, removed }
+            if group.name == "hogwards" && added.len() == 1 && removed.is_empty()
+    ));
+}
+
+#[sqlx::test]
+async fn test_reconcile_groups(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_test_client(pool).await;
+    client.login_user("admin", "pass123").await;
+
+    let data = EditGroupInfo::new("crew", vec!["hpotter".into()], false);
+    let response = client.post("/api/v1/group").json(&data).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    client.drain_all_events();
+
+    // invalid desired states are rejected without changes
+    let response = client
+        .put("/api/v1/groups")
+        .json(&json!({"groups": [{"name": "staff", "members": [], "is_admin": false}]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = client
+        .put("/api/v1/groups")
+        .json(&json!({"groups": [
+            {"name": "admin", "members": ["admin"], "is_admin": true},
+            {"name": "admin", "members": [], "is_admin": true},
+        ]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = client
+        .put("/api/v1/groups")
+        .json(&json!({"groups": [
+            {"name": "admin", "members": ["admin"], "is_admin": true},
+            {"name": "staff", "members": ["nobody"], "is_admin": false},
+        ]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(client.drain_all_events().is_empty());
+
+    let response = client
+        .put("/api/v1/groups")
+        .json(&json!({"groups": [
+            {"name": "admin", "members": ["admin", "hpotter"], "is_admin": true},
+            {"name": "staff", "members": ["hpotter"], "is_admin": false},
+        ]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let changes: Value = response.json().await;
+    assert_eq!(
+        changes,
+        json!({
+            "created": [{"name": "staff", "is_admin": false, "added": ["hpotter"], "removed": []}],
+            "modified": [{"name": "admin", "is_admin": true, "added": ["hpotter"], "removed": []}],
+            "removed": ["crew"],
+        })
+    );
+    let events = client.drain_all_events();
+    assert_eq!(events.len(), 3);
+    assert!(matches!(
+        &events[0].0,
+        ApiEventType::GroupMembersModified { group, added, removed }
+            if group.name == "admin" && added.len() == 1 && removed.is_empty()
+    ));
+    assert!(matches!(
+        &events[1].0,
+        ApiEventType::GroupAdded { group } if group.name == "staff"
+    ));
+    assert!(matches!(
+        &events[2].0,
+        ApiEventType::GroupRemoved { group } if group.name == "crew"
+    ));
+
+    // applying the same state again is a no-op
+    let response = client
+        .put("/api/v1/groups")
+        .json(&json!({"groups": [
+            {"name": "admin", "members": ["admin", "hpotter"], "is_admin": true},
+            {"name": "staff", "members": ["hpotter"], "is_admin": false},
+        ]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let changes: Value = response.json().await;
+    assert_eq!(
+        changes,
+        json!({"created": [], "modified": [], "removed": []})
+    );
+
+    let response = client
+        .put("/api/v1/groups")
+        .json(&json!({"groups": [
+            {"name": "admin", "members": ["admin"], "is_admin": true},
+            {"name": "staff", "members": [], "is_admin": true},
+        ]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let changes: Value = response.json().await;
+    assert_eq!(
+        changes["modified"],
+        json!([
+            {"name": "admin", "is_admin": true, "added": [], "removed": ["hpotter"]},
+            {"name": "staff", "is_admin": true, "added": [], "removed": ["hpotter"]},
+        ])
+    );
+    let response = client.get("/api/v1/group/staff").send().await;
+    let group: GroupInfo = response.json().await;
+    assert!(group.is_admin);
+    assert!(group.members.is_empty());
+}
@@ -0,0 +1,196 @@
+use defguard_core::{
+    db::models::impersonation::ImpersonationMode, events::ApiEventType, handlers::Auth,
+};
+use matches::assert_matches;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    query_scalar,
+};
+
+use super::common::{authenticate_admin, make_test_client, setup_pool};
+
+static SESSION_COOKIE_NAME: &str = "defguard_session";
+static IMPERSONATION_COOKIE_NAME: &str = "defguard_impersonation";
+
+#[sqlx::test]
+async fn test_impersonation(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let (mut client, _) = make_test_client(pool).await;
+    authenticate_admin(&mut client).await;
+    client.drain_all_events();
+
+    // admins can't impersonate themselves, impersonation has a time limit
+    let response = client
+        .post("/api/v1/user/admin/impersonate")
+        .json(&json!({"mode": "read_only"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = client
+        .post("/api/v1/user/hpotter/impersonate")
+        .json(&json!({"mode": "full", "duration_minutes": 61}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = client.delete("/api/v1/impersonation").send().await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // read-only impersonation
+    let response = client
+        .post("/api/v1/user/hpotter/impersonate")
+        .json(&json!({"mode": "read_only"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let info: Value = response.json().await;
+    assert_eq!(info["admin"], "admin");
+    assert_eq!(info["mode"], "read_only");
+    let event = client.next_api_event().unwrap();
+    assert_eq!(event.context.username, "admin");
+    assert_eq!(event.context.impersonator, None);
+    assert_matches!(
+        *event.event,
+        ApiEventType::ImpersonationStarted {
+            ref admin,
+            ref user,
+            mode: ImpersonationMode::ReadOnly,
+            ..
+        } if admin.username == "admin" && user.username == "hpotter"
+    );
+
+    let response = client.get("/api/v1/me").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let me: Value = response.json().await;
+    assert_eq!(me["username"], "hpotter");
+    assert_eq!(me["impersonation"]["admin"], "admin");
+    assert_eq!(me["impersonation"]["mode"], "read_only");
+    // the impersonated user isn't an admin
+    let response = client.get("/api/v1/user").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client.delete("/api/v1/user/hpotter/session").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    // impersonated users can't start another impersonation
+    let response = client
+        .post("/api/v1/user/admin/impersonate")
+        .json(&json!({"mode": "read_only"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = client.delete("/api/v1/impersonation").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let event = client.next_api_event().unwrap();
+    assert_eq!(event.context.username, "admin");
+    assert_matches!(
+        *event.event,
+        ApiEventType::ImpersonationEnded {
+            ref user,
+            mode: ImpersonationMode::ReadOnly,
+            expired: false,
+            ..
+        } if user.username == "hpotter"
+    );
+    let response = client.get("/api/v1/me").send().await;
+    let me: Value = response.json().await;
+    assert_eq!(me["username"], "admin");
+    assert!(me.get("impersonation").is_none());
+
+    // full impersonation, actions are attributed to both identities
+    let response = client
+        .post("/api/v1/user/hpotter/impersonate")
+        .json(&json!({"mode": "full", "duration_minutes": 5}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    client.drain_all_events();
+    // the impersonated session isn't one of the user's sessions
+    let response = client.get("/api/v1/user/hpotter/session").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let sessions: Vec<Value> = response.json().await;
+    assert!(sessions.is_empty());
+    let response = client.delete("/api/v1/user/hpotter/session").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let event = client.next_api_event().unwrap();
+    assert_eq!(event.context.username, "hpotter");
+    assert_eq!(event.context.impersonator.as_deref(), Some("admin"));
+    assert!(event.context.impersonator_id.is_some());
+    assert_matches!(
+        *event.event,
+        ApiEventType::UserSessionsRevoked { count: 0, .. }
+    );
+
+    // logging out stops the impersonation, the admin stays logged in
+    let response = client.post("/api/v1/auth/logout").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_matches!(
+        *client.next_api_event().unwrap().event,
+        ApiEventType::ImpersonationEnded {
+            mode: ImpersonationMode::Full,
+            expired: false,
+            ..
+        }
+    );
+    let response = client.get("/api/v1/me").send().await;
+    let me: Value = response.json().await;
+    assert_eq!(me["username"], "admin");
+}
+
+#[sqlx::test]
+async fn test_impersonated_session_cookie(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let (mut client, client_state) = make_test_client(pool).await;
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let admin_cookie = response
+        .cookies()
+        .find(|c| c.name() == SESSION_COOKIE_NAME)
+        .unwrap();
+
+    let response = client
+        .post("/api/v1/user/hpotter/impersonate")
+        .json(&json!({"mode": "read_only"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let impersonation_cookie = response
+        .cookies()
+        .find(|c| c.name() == IMPERSONATION_COOKIE_NAME)
+        .unwrap();
+    let session_id: String = query_scalar("SELECT session_id FROM impersonation")
+        .fetch_one(&client_state.pool)
+        .await
+        .unwrap();
+    // the cookie doesn't reveal the impersonated session
+    assert_ne!(impersonation_cookie.value(), session_id);
+
+    // the impersonated session can't be used as a regular one, e.g. to get around read-only mode
+    client.set_cookie_value(SESSION_COOKIE_NAME, &session_id);
+    let response = client.get("/api/v1/me").send().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // logging out the admin ends the impersonated session as well
+    client.set_cookie(&admin_cookie);
+    client.set_cookie_value(IMPERSONATION_COOKIE_NAME, "invalid");
+    let response = client.get("/api/v1/me").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let me: Value = response.json().await;
+    assert_eq!(me["username"], "admin");
+    let response = client.post("/api/v1/auth/logout").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let exists: bool = query_scalar("SELECT EXISTS (SELECT 1 FROM session WHERE id = $1)")
+        .bind(&session_id)
+        .fetch_one(&client_state.pool)
+        .await
+        .unwrap();
+    assert!(!exists);
+    client.set_cookie(&admin_cookie);
+    client.set_cookie(&impersonation_cookie);
+    let response = client.get("/api/v1/me").send().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    client.set_cookie_value(SESSION_COOKIE_NAME, &session_id);
+    let response = client.get("/api/v1/me").send().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,184 @@
+use defguard_common::db::models::{Settings, settings::update_current_settings};
+use defguard_core::{events::ApiEventType, handlers::Auth};
+use reqwest::StatusCode;
+use serde_json::Value;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+use super::common::{X_FORWARDED_FOR, make_client_with_db, setup_pool};
+
+#[sqlx::test]
+async fn test_login_lockout(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, pool) = make_client_with_db(pool).await;
+
+    let mut settings = Settings::get_current_settings();
+    settings.login_lockout_threshold = 3;
+    settings.login_lockout_duration = 10;
+    update_current_settings(&pool, settings).await.unwrap();
+
+    let wrong_auth = Auth::new("hpotter", "wrong");
+    for _ in 0..3 {
+        let response = client.post("/api/v1/auth").json(&wrong_auth).send().await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let events: Vec<_> = std::iter::from_fn(|| client.next_api_event())
+        .map(|event| *event.event)
+        .collect();
+    assert_eq!(events.len(), 4);
+    assert!(matches!(
+        events[3],
+        ApiEventType::UserLockedOut {
+            failed_attempts: 3,
+            ..
+        }
+    ));
+
+    // the correct password is rejected as well
+    let auth = Auth::new("hpotter", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // other users aren't affected
+    client.login_user("admin", "pass123").await;
+    let response = client.get("/api/v1/lockout").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let lockouts: Value = response.json().await;
+    assert_eq!(lockouts["users"].as_array().unwrap().len(), 1);
+    assert_eq!(lockouts["users"][0]["username"], "hpotter");
+    assert_eq!(lockouts["users"][0]["failed_attempts"], 3);
+    assert!(lockouts["ips"].as_array().unwrap().is_empty());
+
+    // admin lifts the lockout
+    let response = client.delete("/api/v1/user/hpotter/lockout").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let event = client.next_api_event().unwrap();
+    assert!(matches!(
+        *event.event,
+        ApiEventType::UserUnlocked { ref user } if user.username == "hpotter"
+    ));
+    let response = client.delete("/api/v1/user/hpotter/lockout").send().await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    client.login_user("hpotter", "pass123").await;
+    let response = client.get("/api/v1/lockout").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test]
+async fn test_login_ip_lockout(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, pool) = make_client_with_db(pool).await;
+
+    let mut settings = Settings::get_current_settings();
+    settings.login_lockout_ip_threshold = 2;
+    update_current_settings(&pool, settings).await.unwrap();
+
+    client.login_user("admin", "pass123").await;
+
+    // failed logins to unknown accounts count as well
+    for username in ["unknown", "hpotter"] {
+        let response = client
+            .post("/api/v1/auth")
+            .json(&Auth::new(username, "wrong"))
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let events: Vec<_> = std::iter::from_fn(|| client.next_api_event())
+        .map(|event| *event.event)
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert!(matches!(
+        events[1],
+        ApiEventType::LoginIpLockedOut {
+            ref ip_address,
+            failed_attempts: 2,
+            ..
+        } if ip_address == "127.0.0.1"
+    ));
+
+    // all users are locked out from the address
+    let response = client
+        .post("/api/v1/auth")
+        .json(&Auth::new("admin", "pass123"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // admin lifts the lockout using the existing session
+    let response = client.get("/api/v1/lockout").send().await;
+    let lockouts: Value = response.json().await;
+    assert_eq!(lockouts["ips"][0]["ip_address"], "127.0.0.1");
+    let response = client.delete("/api/v1/lockout/ip/127.0.0.1").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let event = client.next_api_event().unwrap();
+    assert_eq!(
+        *event.event,
+        ApiEventType::LoginIpUnlocked {
+            ip_address: "127.0.0.1".into()
+        }
+    );
+    let response = client.delete("/api/v1/lockout/ip/127.0.0.1").send().await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    client.login_user("hpotter", "pass123").await;
+}
+
+#[sqlx::test]
+async fn test_login_ip_lockout_behind_proxy(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, pool) = make_client_with_db(pool).await;
+
+    let mut settings = Settings::get_current_settings();
+    settings.login_lockout_ip_threshold = 2;
+    update_current_settings(&pool, settings).await.unwrap();
+
+    // requests forwarded by a proxy on a loopback address share its address, which isn't locked out
+    for username in ["unknown", "hpotter", "admin"] {
+        let response = client
+            .post("/api/v1/auth")
+            .header(X_FORWARDED_FOR, "203.0.113.10")
+            .json(&Auth::new(username, "wrong"))
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let events: Vec<_> = std::iter::from_fn(|| client.next_api_event())
+        .map(|event| *event.event)
+        .collect();
+    assert!(
+        !events
+            .iter()
+            .any(|event| matches!(event, ApiEventType::LoginIpLockedOut { .. }))
+    );
+
+    client.login_user("hpotter", "pass123").await;
+}
+
+#[sqlx::test]
+async fn test_login_lockout_mfa_fallback(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, pool) = make_client_with_db(pool).await;
+
+    let mut settings = Settings::get_current_settings();
+    settings.login_lockout_threshold = 2;
+    settings.login_lockout_mfa_fallback = true;
+    update_current_settings(&pool, settings).await.unwrap();
+
+    let wrong_auth = Auth::new("hpotter", "wrong");
+    for _ in 0..2 {
+        let response = client.post("/api/v1/auth").json(&wrong_auth).send().await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    // the fallback doesn't apply to users without MFA
+    let auth = Auth::new("hpotter", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let response = client.get("/api/v1/me").send().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,81 @@
+use defguard_core::handlers::Auth;
+use reqwest::StatusCode;
+use serde_json::Value;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+use super::common::{make_network, make_test_client, setup_pool};
+
+#[sqlx::test]
+async fn test_prometheus_metrics(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let (client, _) = make_test_client(pool).await;
+
+    // admin role is required
+    let response = client.get("/api/v1/metrics").send().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let auth = Auth::new("hpotter", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/metrics").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = client.get("/api/v1/metrics").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let metrics = response.text().await;
+    for line in [
+        "# TYPE defguard_location_connected_peers gauge\n",
+        "defguard_location_connected_peers{location_id=\"1\",location=\"network\"} 0\n",
+        "# TYPE defguard_gateway_connected gauge\n",
+        "defguard_queue_depth{queue=\"mail\"} ",
+        "defguard_ldap_enabled 0\n",
+        "defguard_ldap_queued_operations{state=\"pending\"} 0\n",
+        "# TYPE defguard_mfa_failures_total counter\n",
+        "# TYPE defguard_http_request_duration_seconds histogram\n",
+        "defguard_http_request_duration_seconds_count{method=\"POST\",route=\"/api/v1/network\"} ",
+        "# TYPE defguard_grpc_request_duration_seconds histogram\n",
+    ] {
+        assert!(metrics.contains(line), "missing {line}");
+    }
+}
+
+#[sqlx::test]
+async fn test_detailed_health(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let (client, _) = make_test_client(pool).await;
+
+    // admin role is required
+    let response = client.get("/api/v1/health/detailed").send().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let auth = Auth::new("hpotter", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/health/detailed").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // utility thread isn't running in tests, so background workers are reported down
+    let response = client.get("/api/v1/health/detailed").send().await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let report: Value = response.json().await;
+    assert_eq!(report["status"], "down");
+    assert_eq!(report["components"]["workers"]["status"], "down");
+    assert_eq!(report["components"]["database"]["status"], "ok");
+    assert_eq!(report["components"]["ldap"]["status"], "disabled");
+    assert_eq!(report["components"]["license"]["status"], "ok");
+    assert_eq!(report["components"]["gateways"]["details"]["total"], 0);
+}
@@ -1,17 +1,30 @@
 mod acl;
+mod activity_log;
 mod api_tokens;
 mod auth;
+mod client_mfa;
 mod common;
+mod device_approval;
+mod device_profile;
 mod enrollment;
 mod enterprise_settings;
 mod forward_auth;
 mod group;
+mod impersonation;
+mod login_lockout;
+mod metrics;
 mod oauth;
 mod openid;
 mod openid_login;
+mod posture_policy;
+mod provisioning;
+mod rate_limit;
+mod saml;
 mod settings;
 mod snat;
 mod user;
+mod vpn_sessions;
+mod web_sessions;
 mod webhook;
 mod wireguard;
 mod wireguard_network_allowed_groups;
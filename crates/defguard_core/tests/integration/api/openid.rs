@@ -8,6 +8,8 @@ use defguard_core::{
         User,
         models::{NewOpenIDClient, oauth2client::OAuth2Client},
     },
+    enterprise::db::models::api_tokens::ApiTokenScope,
+    events::ApiEventType,
     handlers::Auth,
 };
 use openidconnect::{
@@ -25,6 +27,7 @@ use reqwest::{
 };
 use rsa::RsaPrivateKey;
 use serde::Deserialize;
+use serde_json::{Value, json};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 
 use super::{
@@ -1600,3 +1603,196 @@ async fn test_openid_flow_new_login_mail(_: PgPoolOptions, options: PgConnectOpt
     // No new mail recevied
     assert_err!(mail_rx.try_recv());
 }
+
+#[sqlx::test]
+async fn test_openid_client_credentials(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let (mut client, _) = make_test_client(pool).await;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .post("/api/v1/user")
+        .json(&json!({
+            "username": "ci-bot",
+            "first_name": "CI",
+            "last_name": "Bot",
+            "email": "ci-bot@example.com",
+            "service_account": true,
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let openid_client = NewOpenIDClient {
+        name: "Deployment".into(),
+        redirect_uri: vec![TEST_SERVER_URL.into()],
+        scope: vec!["openid".into()],
+        enabled: true,
+    };
+    let response = client
+        .post("/api/v1/oauth")
+        .json(&openid_client)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let openid_client: OAuth2Client<Id> = response.json().await;
+    let credentials = format!(
+        "client_id={}&client_secret={}",
+        openid_client.client_id, openid_client.client_secret
+    );
+    let token_request = |body: String| {
+        client
+            .post("/api/v1/oauth/token")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(body)
+    };
+
+    // grant has to be enabled first
+    let response = token_request(format!("grant_type=client_credentials&{credentials}"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // only service accounts can be used
+    let config_url = format!(
+        "/api/v1/oauth/{}/client_credentials",
+        openid_client.client_id
+    );
+    let response = client
+        .put(&config_url)
+        .json(&json!({"service_account": "hpotter", "scopes": ["read_only"]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = client
+        .put(&config_url)
+        .json(&json!({"service_account": "ci-bot", "scopes": ["read_only", "user_management"]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get(&config_url).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let config: Value = response.json().await;
+    assert_eq!(config["service_account"], "ci-bot");
+
+    client.post("/api/v1/auth/logout").send().await;
+
+    // wrong client secret
+    let response = token_request(format!(
+        "grant_type=client_credentials&client_id={}&client_secret=wrong",
+        openid_client.client_id
+    ))
+    .send()
+    .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // scope which hasn't been granted to the client
+    let response = token_request(format!(
+        "grant_type=client_credentials&scope=network_management&{credentials}"
+    ))
+    .send()
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = token_request(format!(
+        "grant_type=client_credentials&scope=read_only&{credentials}"
+    ))
+    .send()
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let token_response: Value = response.json().await;
+    assert_eq!(token_response["token_type"], "bearer");
+    assert_eq!(token_response["expires_in"], 3600);
+    assert_eq!(token_response["scope"], "read_only");
+    assert!(token_response.get("refresh_token").is_none());
+    let bearer = format!(
+        "Bearer {}",
+        token_response["access_token"].as_str().unwrap()
+    );
+
+    // token grants access limited to its scopes
+    let response = client
+        .get("/api/v1/user")
+        .header(AUTHORIZATION, &bearer)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .post("/api/v1/group")
+        .header(AUTHORIZATION, &bearer)
+        .json(&json!({"name": "bots", "members": []}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .get("/api/v1/settings")
+        .header(AUTHORIZATION, &bearer)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // introspection requires client authentication
+    let token = token_response["access_token"].as_str().unwrap();
+    let introspect = |body: String| {
+        client
+            .post("/api/v1/oauth/introspect")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(body)
+    };
+    let response = introspect(format!("token={token}")).send().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = introspect(format!("token={token}&{credentials}"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let introspection: Value = response.json().await;
+    assert_eq!(introspection["active"], true);
+    assert_eq!(introspection["username"], "ci-bot");
+    assert_eq!(introspection["client_id"], openid_client.client_id.as_str());
+    assert_eq!(introspection["scope"], "read_only");
+
+    // revoked token stops working
+    let response = client
+        .post("/api/v1/oauth/revoke")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(format!("token={token}&{credentials}"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = introspect(format!("token={token}&{credentials}"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let introspection: Value = response.json().await;
+    assert_eq!(introspection, json!({"active": false}));
+    let response = client
+        .get("/api/v1/user")
+        .header(AUTHORIZATION, &bearer)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // disable the grant, both changes are recorded
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.delete(&config_url).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get(&config_url).send().await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let events = client.drain_all_events();
+    assert!(events.iter().any(|(event, _, username)| username == "admin"
+        && matches!(
+            event,
+            ApiEventType::ClientCredentialsSet { app, service_account, scopes }
+                if app.client_id == openid_client.client_id
+                    && service_account.username == "ci-bot"
+                    && scopes == &[ApiTokenScope::ReadOnly, ApiTokenScope::UserManagement]
+        )));
+    assert!(events.iter().any(|(event, _, _)| matches!(
+        event,
+        ApiEventType::ClientCredentialsRemoved { app } if app.client_id == openid_client.client_id
+    )));
+}
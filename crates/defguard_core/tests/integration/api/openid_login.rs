@@ -3,14 +3,17 @@ use defguard_common::db::{Id, models::settings::OpenidUsernameHandling};
 use defguard_core::{
     db::models::{NewOpenIDClient, oauth2client::OAuth2Client},
     enterprise::{
-        db::models::openid_provider::{DirectorySyncTarget, DirectorySyncUserBehavior},
+        db::models::openid_provider::{
+            DirectorySyncTarget, DirectorySyncUserBehavior, GroupClaimMapping,
+        },
         handlers::openid_providers::AddProviderData,
         license::{License, LicenseTier, set_cached_license},
     },
-    handlers::Auth,
+    handlers::{Auth, EditGroupInfo},
 };
 use reqwest::{StatusCode, Url};
 use serde::Deserialize;
+use serde_json::{Value, json};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 
 use super::common::{exceed_enterprise_limits, make_client, setup_pool};
@@ -54,6 +57,7 @@ async fn test_openid_providers(_: PgPoolOptions, options: PgConnectOptions) {
         username_handling: OpenidUsernameHandling::PruneEmailDomain,
         jumpcloud_api_key: None,
         prefetch_users: false,
+        group_claim_mapping: GroupClaimMapping::default(),
     };
 
     let response = client
@@ -100,6 +104,90 @@ async fn test_openid_providers(_: PgPoolOptions, options: PgConnectOptions) {
     assert_eq!(response.status(), StatusCode::FORBIDDEN);
 }
 
+#[sqlx::test]
+async fn test_openid_group_claim_mapping(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let client = make_client(pool).await;
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    exceed_enterprise_limits(&client).await;
+
+    for (name, members) in [("vpn", Vec::new()), ("sales", vec!["admin".to_string()])] {
+        let data = EditGroupInfo::new(name, members, false);
+        let response = client.post("/api/v1/group").json(&data).send().await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    let preview = json!({"username": "admin", "claims": {"groups": ["vpn", "ops"]}});
+    let response = client
+        .post("/api/v1/openid/group_mapping/preview")
+        .json(&preview)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let provider_data = AddProviderData {
+        name: "Entra".to_string(),
+        base_url: "https://login.microsoftonline.com/tenant/v2.0".to_string(),
+        client_id: "client_id".to_string(),
+        client_secret: "client_secret".to_string(),
+        display_name: None,
+        admin_email: None,
+        google_service_account_email: None,
+        google_service_account_key: None,
+        directory_sync_enabled: false,
+        directory_sync_interval: 100,
+        directory_sync_user_behavior: DirectorySyncUserBehavior::Keep.to_string(),
+        directory_sync_admin_behavior: DirectorySyncUserBehavior::Keep.to_string(),
+        directory_sync_target: DirectorySyncTarget::All.to_string(),
+        create_account: false,
+        okta_dirsync_client_id: None,
+        okta_private_jwk: None,
+        directory_sync_group_match: None,
+        username_handling: OpenidUsernameHandling::PruneEmailDomain,
+        jumpcloud_api_key: None,
+        prefetch_users: false,
+        group_claim_mapping: GroupClaimMapping {
+            claim: Some(" groups ".to_string()),
+            create_groups: false,
+            dry_run: true,
+        },
+    };
+    let response = client
+        .post("/api/v1/openid/provider")
+        .json(&provider_data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = client.get("/api/v1/openid/provider").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let provider: Value = response.json().await;
+    assert_eq!(
+        provider["group_claim_mapping"],
+        json!({"claim": "groups", "create_groups": false, "dry_run": true})
+    );
+
+    // admin group is never left because of the claim
+    let response = client
+        .post("/api/v1/openid/group_mapping/preview")
+        .json(&preview)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let changes: Value = response.json().await;
+    assert_eq!(
+        changes,
+        json!({"created": [], "joined": ["vpn"], "left": ["sales"], "skipped": ["ops"]})
+    );
+
+    // nothing is changed by the preview
+    let response = client.get("/api/v1/group/vpn").send().await;
+    let group: Value = response.json().await;
+    assert_eq!(group["members"], json!([]));
+}
+
 // FIXME: tihs test sometimes fails because of test_openid_providers.
 // The license state is possibly preserved between those two. This requires further research.
 #[sqlx::test]
@@ -156,6 +244,7 @@ async fn test_openid_login(_: PgPoolOptions, options: PgConnectOptions) {
         username_handling: OpenidUsernameHandling::PruneEmailDomain,
         jumpcloud_api_key: None,
         prefetch_users: false,
+        group_claim_mapping: GroupClaimMapping::default(),
     };
     let response = client
         .post("/api/v1/openid/provider")
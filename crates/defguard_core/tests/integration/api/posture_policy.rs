@@ -0,0 +1,89 @@
+use defguard_common::db::Id;
+use defguard_core::enterprise::{
+    db::models::posture_policy::LocationPosturePolicy, handlers::posture_policy::PosturePolicyData,
+};
+use reqwest::StatusCode;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+use super::common::{authenticate_admin, make_network, make_test_client, setup_pool};
+
+#[sqlx::test]
+async fn test_posture_policy_crud(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_test_client(pool).await;
+
+    // admin login
+    authenticate_admin(&mut client).await;
+
+    // create location
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // no policy by default
+    let response = client.get("/api/v1/network/1/posture_policy").send().await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // invalid versions are rejected
+    let mut data = PosturePolicyData {
+        require_disk_encryption: true,
+        min_os_version: Some("latest".into()),
+        min_client_version: None,
+    };
+    let response = client
+        .put("/api/v1/network/1/posture_policy")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // set policy
+    data.min_os_version = Some("14.2".into());
+    let response = client
+        .put("/api/v1/network/1/posture_policy")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let policy: LocationPosturePolicy<Id> = response.json().await;
+    assert_eq!(policy.location_id, 1);
+    assert!(policy.require_disk_encryption);
+    assert_eq!(policy.min_os_version.as_deref(), Some("14.2"));
+
+    // modify policy
+    data.require_disk_encryption = false;
+    data.min_client_version = Some("1.6.0".into());
+    let response = client
+        .put("/api/v1/network/1/posture_policy")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/network/1/posture_policy").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let modified: LocationPosturePolicy<Id> = response.json().await;
+    assert_eq!(modified.id, policy.id);
+    assert!(!modified.require_disk_encryption);
+    assert_eq!(modified.min_client_version.as_deref(), Some("1.6.0"));
+
+    // remove policy
+    let response = client
+        .delete("/api/v1/network/1/posture_policy")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/network/1/posture_policy").send().await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // location must exist
+    let response = client
+        .put("/api/v1/network/2/posture_policy")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,173 @@
+use defguard_common::db::models::settings::OpenidUsernameHandling;
+use defguard_core::{
+    enterprise::{
+        db::models::{
+            openid_provider::{DirectorySyncTarget, DirectorySyncUserBehavior, GroupClaimMapping},
+            provisioning_policy::{ProvisioningPolicy, ProvisioningSource},
+        },
+        handlers::{openid_providers::AddProviderData, provisioning::ProvisioningPolicyData},
+    },
+    events::ApiEventType,
+    handlers::EditGroupInfo,
+};
+use matches::assert_matches;
+use reqwest::StatusCode;
+use serde_json::Value;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+use super::common::{authenticate_admin, exceed_enterprise_limits, make_test_client, setup_pool};
+
+#[sqlx::test]
+async fn test_provisioning_policies(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let (mut client, _) = make_test_client(pool).await;
+    authenticate_admin(&mut client).await;
+    exceed_enterprise_limits(&client).await;
+
+    // defaults keep the behaviour from before policies were configurable
+    let response = client.get("/api/v1/provisioning_policy").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let policies: Vec<ProvisioningPolicy> = response.json().await;
+    assert_eq!(
+        policies,
+        [
+            ProvisioningPolicy::new(ProvisioningSource::OpenId),
+            ProvisioningPolicy::new(ProvisioningSource::Ldap),
+            ProvisioningPolicy::new(ProvisioningSource::Saml),
+        ]
+    );
+
+    let response = client
+        .post("/api/v1/group")
+        .json(&EditGroupInfo::new("contractors", Vec::new(), false))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    client.drain_all_events();
+
+    // invalid policies
+    let mut data = ProvisioningPolicyData {
+        create_account: false,
+        default_groups: vec!["contractors".into(), "missing".into()],
+        start_disabled: true,
+        username_template: Some("ext-{given_initial}{family_name}".into()),
+    };
+    let response = client
+        .put("/api/v1/provisioning_policy/openid")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    // LDAP accounts always keep their LDAP username
+    data.default_groups = vec!["contractors".into()];
+    let response = client
+        .put("/api/v1/provisioning_policy/ldap")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    data.username_template = Some("{nickname}".into());
+    let response = client
+        .put("/api/v1/provisioning_policy/openid")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // group names are trimmed and deduplicated
+    data.default_groups = vec!["contractors".into(), " contractors ".into()];
+    data.username_template = Some("ext-{given_initial}{family_name}".into());
+    let response = client
+        .put("/api/v1/provisioning_policy/openid")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let policy: ProvisioningPolicy = response.json().await;
+    assert_eq!(policy.default_groups, ["contractors"]);
+    assert!(policy.start_disabled);
+    assert_matches!(
+        *client.next_api_event().unwrap().event,
+        ApiEventType::ProvisioningPolicyModified { ref before, ref after }
+            if before.create_account && !after.create_account
+    );
+
+    // the OpenID provider form controls account creation of the policy
+    let provider_data = AddProviderData {
+        name: "Entra".to_string(),
+        base_url: "https://login.microsoftonline.com/tenant/v2.0".to_string(),
+        client_id: "client_id".to_string(),
+        client_secret: "client_secret".to_string(),
+        display_name: None,
+        admin_email: None,
+        google_service_account_email: None,
+        google_service_account_key: None,
+        directory_sync_enabled: false,
+        directory_sync_interval: 100,
+        directory_sync_user_behavior: DirectorySyncUserBehavior::Keep.to_string(),
+        directory_sync_admin_behavior: DirectorySyncUserBehavior::Keep.to_string(),
+        directory_sync_target: DirectorySyncTarget::All.to_string(),
+        create_account: true,
+        okta_dirsync_client_id: None,
+        okta_private_jwk: None,
+        directory_sync_group_match: None,
+        username_handling: OpenidUsernameHandling::PruneEmailDomain,
+        jumpcloud_api_key: None,
+        prefetch_users: false,
+        group_claim_mapping: GroupClaimMapping::default(),
+    };
+    let response = client
+        .post("/api/v1/openid/provider")
+        .json(&provider_data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = client.get("/api/v1/provisioning_policy").send().await;
+    let policies: Vec<ProvisioningPolicy> = response.json().await;
+    assert!(policies[0].create_account);
+    assert_eq!(policies[0].default_groups, ["contractors"]);
+    let response = client
+        .put("/api/v1/provisioning_policy/openid")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/openid/provider").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let provider: Value = response.json().await;
+    assert_eq!(provider["settings"]["create_account"], false);
+    client.drain_all_events();
+
+    data.username_template = Some(" ".into());
+    let response = client
+        .put("/api/v1/provisioning_policy/ldap")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get("/api/v1/provisioning_policy").send().await;
+    let policies: Vec<ProvisioningPolicy> = response.json().await;
+    assert_eq!(
+        policies[0].username_template.as_deref(),
+        Some("ext-{given_initial}{family_name}")
+    );
+    assert_eq!(policies[1].source, ProvisioningSource::Ldap);
+    assert_eq!(policies[1].username_template, None);
+    assert!(!policies[1].create_account);
+
+    let response = client
+        .put("/api/v1/provisioning_policy/saml")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let policy: ProvisioningPolicy = response.json().await;
+    assert_eq!(policy.source, ProvisioningSource::Saml);
+    let response = client
+        .put("/api/v1/provisioning_policy/kerberos")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
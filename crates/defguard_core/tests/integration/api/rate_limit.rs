@@ -0,0 +1,43 @@
+use defguard_core::handlers::Auth;
+use reqwest::{StatusCode, header::RETRY_AFTER};
+use serde_json::json;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+use super::common::{make_client, setup_pool};
+
+#[sqlx::test]
+async fn test_login_rate_limit(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let client = make_client(pool).await;
+
+    // default burst of 10 requests, failed login counters are per user, so they don't interfere
+    for i in 0..10 {
+        let response = client
+            .post("/api/v1/auth")
+            .json(&Auth::new(format!("unknown{i}"), "wrong".into()))
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let response = client
+        .post("/api/v1/auth")
+        .json(&Auth::new("hpotter", "pass123"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = response.headers()[RETRY_AFTER]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0);
+
+    // other endpoints are limited separately
+    let response = client
+        .post("/api/v1/auth/recovery")
+        .json(&json!({ "code": "invalid" }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
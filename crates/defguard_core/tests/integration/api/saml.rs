@@ -0,0 +1,140 @@
+use defguard_core::{enterprise::handlers::saml::SamlProviderData, events::ApiEventType};
+use matches::assert_matches;
+use reqwest::{StatusCode, header::CONTENT_TYPE};
+use serde_json::Value;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+use super::common::{authenticate_admin, exceed_enterprise_limits, make_test_client, setup_pool};
+
+const IDP_METADATA: &str = r#"<?xml version="1.0"?>
+<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" entityID="https://idp.example.com/metadata">
+  <md:IDPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+    <md:SingleSignOnService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect" Location="https://idp.example.com/sso"/>
+  </md:IDPSSODescriptor>
+</md:EntityDescriptor>"#;
+
+fn provider_data(name: &str) -> SamlProviderData {
+    SamlProviderData {
+        name: name.into(),
+        display_name: Some("Entra ID".into()),
+        enabled: true,
+        idp_metadata: IDP_METADATA.into(),
+        email_attribute: "email".into(),
+        username_attribute: None,
+        first_name_attribute: "givenName".into(),
+        last_name_attribute: "surname".into(),
+        phone_attribute: Some(" ".into()),
+        groups_attribute: Some("groups".into()),
+        create_groups: false,
+    }
+}
+
+#[sqlx::test]
+async fn test_saml_providers(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let (mut client, _) = make_test_client(pool).await;
+    authenticate_admin(&mut client).await;
+    exceed_enterprise_limits(&client).await;
+    client.drain_all_events();
+
+    // invalid configuration
+    let mut data = provider_data("entra id");
+    let response = client
+        .post("/api/v1/saml/provider")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    data.name = "entra".into();
+    data.idp_metadata = "<xml>".into();
+    let response = client
+        .post("/api/v1/saml/provider")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let data = provider_data("entra");
+    let response = client
+        .post("/api/v1/saml/provider")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let provider: Value = response.json().await;
+    assert_eq!(provider["name"], "entra");
+    assert_eq!(provider["phone_attribute"], Value::Null);
+    assert_matches!(
+        *client.next_api_event().unwrap().event,
+        ApiEventType::SamlProviderModified { .. }
+    );
+    let response = client
+        .post("/api/v1/saml/provider")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = client.get("/api/v1/saml/provider").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let providers: Value = response.json().await;
+    assert_eq!(providers.as_array().unwrap().len(), 1);
+
+    // service provider metadata
+    let response = client.get("/api/v1/saml/entra/metadata").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[CONTENT_TYPE],
+        "application/samlmetadata+xml"
+    );
+    let metadata = response.text().await;
+    assert!(metadata.contains("/api/v1/saml/entra/metadata"));
+    assert!(metadata.contains("/api/v1/saml/entra/acs"));
+
+    // authentication request redirects to the identity provider
+    let response = client.get("/api/v1/saml/entra/auth_info").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let auth_info: Value = response.json().await;
+    assert!(
+        auth_info["url"]
+            .as_str()
+            .unwrap()
+            .starts_with("https://idp.example.com/sso?SAMLRequest=")
+    );
+    assert_eq!(auth_info["button_display_name"], "Entra ID");
+    // logged in users can link their account with the provider
+    let response = client.get("/api/v1/saml/entra/link").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // unsigned garbage is rejected
+    let response = client
+        .post("/api/v1/saml/entra/acs")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body("SAMLResponse=PHNhbWxwOlJlc3BvbnNlLz4%3D")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = client.post("/api/v1/saml/callback").send().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // disabled providers can't be used to log in
+    let mut data = provider_data("entra");
+    data.enabled = false;
+    let response = client
+        .put("/api/v1/saml/provider/entra")
+        .json(&data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/saml/entra/auth_info").send().await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client.delete("/api/v1/saml/provider/entra").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    client.drain_all_events();
+    let response = client.get("/api/v1/saml/provider").send().await;
+    let providers: Value = response.json().await;
+    assert!(providers.as_array().unwrap().is_empty());
+    let response = client.delete("/api/v1/saml/provider/entra").send().await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
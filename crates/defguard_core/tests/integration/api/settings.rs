@@ -1,9 +1,17 @@
-use defguard_common::db::models::{Settings, settings::SettingsPatch};
-use defguard_core::handlers::Auth;
+use defguard_common::db::{
+    Id,
+    models::{MailDelivery, Settings, settings::SettingsPatch},
+};
+use defguard_core::{
+    db::{WireguardNetwork, models::gateway_alert::GatewayAlertRule},
+    enterprise::db::models::ldap_operation::{LDAP_OPERATION_MAX_ATTEMPTS, LdapOperation},
+    handlers::Auth,
+};
 use reqwest::StatusCode;
+use serde_json::{Value, json};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 
-use super::common::{make_test_client, setup_pool};
+use super::common::{authenticate_admin, make_network, make_test_client, setup_pool};
 
 #[sqlx::test]
 async fn test_settings(_: PgPoolOptions, options: PgConnectOptions) {
@@ -44,3 +52,402 @@ async fn test_settings(_: PgPoolOptions, options: PgConnectOptions) {
     let new_settings: Settings = response.json().await;
     assert!(new_settings.wireguard_enabled);
 }
+
+#[sqlx::test]
+async fn test_password_hash_upgrade(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, _client_state) = make_test_client(pool).await;
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // all hashes use current parameters
+    let response = client.get("/api/v1/settings/password_hashes").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let report: Value = response.json().await;
+    let total = report["total"].as_u64().unwrap();
+    assert!(total > 1);
+    assert_eq!(report["legacy"], 0);
+
+    // invalid parameters are rejected
+    let response = client
+        .patch("/api/v1/settings")
+        .json(&json!({"password_hash_parallelism": 0}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // change parameters, all existing hashes become legacy
+    let response = client
+        .patch("/api/v1/settings")
+        .json(&json!({"password_hash_time_cost": 3}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/settings/password_hashes").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let report: Value = response.json().await;
+    assert_eq!(report["total"], total);
+    assert_eq!(report["legacy"], total);
+
+    // hash is upgraded on next successful login
+    let response = client.post("/api/v1/auth/logout").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/settings/password_hashes").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let report: Value = response.json().await;
+    assert_eq!(report["legacy"], total - 1);
+
+    // upgraded hash still works
+    let response = client.post("/api/v1/auth/logout").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[sqlx::test]
+async fn test_activity_log_retention_settings(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, _client_state) = make_test_client(pool).await;
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // events are kept forever by default
+    let response = client.get("/api/v1/settings").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let settings: Settings = response.json().await;
+    assert_eq!(settings.activity_log_retention_days, None);
+    assert!(!settings.activity_log_retention_archive);
+
+    // retention period must be positive
+    let response = client
+        .patch("/api/v1/settings")
+        .json(&json!({"activity_log_retention_days": 0}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = client
+        .patch("/api/v1/settings")
+        .json(&json!({
+            "activity_log_retention_days": 90,
+            "activity_log_retention_archive": true
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/settings").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let settings: Settings = response.json().await;
+    assert_eq!(settings.activity_log_retention_days, Some(90));
+    assert!(settings.activity_log_retention_archive);
+}
+
+#[sqlx::test]
+async fn test_ldap_operation_queue(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let mut operation = LdapOperation::delete_group("staff".into());
+    for _ in 0..LDAP_OPERATION_MAX_ATTEMPTS {
+        operation.register_failure("connection refused".into());
+    }
+    let operation = operation.save(&pool).await.unwrap();
+
+    let (client, _client_state) = make_test_client(pool).await;
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // permanently failed operations are listed
+    let response = client.get("/api/v1/ldap/operations").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let operations: Value = response.json().await;
+    assert_eq!(operations[0]["id"], json!(operation.id));
+    assert_eq!(operations[0]["kind"], json!("delete_group"));
+    assert_eq!(operations[0]["failed"], json!(true));
+    assert_eq!(operations[0]["last_error"], json!("connection refused"));
+
+    // retrying makes the operation pending again
+    let response = client
+        .post(format!("/api/v1/ldap/operations/{}/retry", operation.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let retried: Value = response.json().await;
+    assert_eq!(retried["failed"], json!(false));
+    assert_eq!(retried["attempts"], json!(0));
+
+    let response = client
+        .delete(format!("/api/v1/ldap/operations/{}", operation.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/ldap/operations").send().await;
+    let operations: Value = response.json().await;
+    assert_eq!(operations, json!([]));
+
+    let response = client
+        .post(format!("/api/v1/ldap/operations/{}/retry", operation.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn test_mail_template_override(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, state) = make_test_client(pool).await;
+    let mut mail_rx = state.mail_rx;
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // all templates use built-in versions by default
+    let response = client.get("/api/v1/mail/templates").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let templates: Vec<Value> = response.json().await;
+    assert_eq!(templates.len(), 3);
+    assert!(
+        templates
+            .iter()
+            .all(|template| template["subject"].is_null())
+    );
+
+    // invalid template is rejected
+    let response = client
+        .put("/api/v1/mail/templates/new_device_login")
+        .json(&json!({"subject": "{% if %}", "body": null}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = client
+        .put("/api/v1/mail/templates/new_device_login")
+        .json(&json!({
+            "subject": "New login from {{ ip_address }}",
+            "body": "Someone logged in from {{ ip_address }}"
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/mail/templates").send().await;
+    let templates: Vec<Value> = response.json().await;
+    let template = templates
+        .iter()
+        .find(|template| template["kind"] == "new_device_login")
+        .unwrap();
+    assert_eq!(template["subject"], "New login from {{ ip_address }}");
+
+    // only customized templates can be reset
+    let response = client
+        .delete("/api/v1/mail/templates/email_mfa_code")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // new device login mail uses the override
+    while mail_rx.try_recv().is_ok() {}
+    let auth = Auth::new("hpotter", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let mail = mail_rx.try_recv().unwrap();
+    assert_eq!(mail.subject, "New login from 127.0.0.1");
+    assert_eq!(mail.content, "Someone logged in from 127.0.0.1");
+
+    // restore built-in template
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .delete("/api/v1/mail/templates/new_device_login")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/mail/templates").send().await;
+    let templates: Vec<Value> = response.json().await;
+    assert!(
+        templates
+            .iter()
+            .all(|template| template["subject"].is_null())
+    );
+}
+
+#[sqlx::test]
+async fn test_mail_delivery_resend(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, state) = make_test_client(pool).await;
+    let mut mail_rx = state.mail_rx;
+
+    let mut failed = MailDelivery::new(
+        "hpotter@hogwart.edu.uk".into(),
+        "Enrollment".into(),
+        "<p>Welcome</p>".into(),
+        None,
+        false,
+    );
+    failed.register_failure("550 Mailbox unavailable".into(), true);
+    let failed = failed.save(&state.pool).await.unwrap();
+    let mut sent = MailDelivery::new(
+        "hpotter@hogwart.edu.uk".into(),
+        "Password reset".into(),
+        "<p>Reset</p>".into(),
+        None,
+        false,
+    );
+    sent.register_sent("250 OK".into());
+    let sent = sent.save(&state.pool).await.unwrap();
+
+    // only admins can see deliveries
+    let auth = Auth::new("hpotter", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/mail/delivery").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/mail/delivery").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let deliveries: Vec<Value> = response.json().await;
+    assert_eq!(deliveries.len(), 2);
+
+    let response = client
+        .get("/api/v1/mail/delivery?status=failed")
+        .send()
+        .await;
+    let deliveries: Vec<Value> = response.json().await;
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0]["id"], json!(failed.id));
+    assert_eq!(deliveries[0]["smtp_response"], "550 Mailbox unavailable");
+    // message content isn't exposed
+    assert!(deliveries[0].get("content").is_none());
+
+    // sent messages can't be resent
+    let response = client
+        .post(format!("/api/v1/mail/delivery/{}/resend", sent.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    while mail_rx.try_recv().is_ok() {}
+    let response = client
+        .post(format!("/api/v1/mail/delivery/{}/resend", failed.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let resent: Value = response.json().await;
+    assert!(!resent["resent_at"].is_null());
+    let mail = mail_rx.try_recv().unwrap();
+    assert_eq!(mail.to, "hpotter@hogwart.edu.uk");
+    assert_eq!(mail.subject, "Enrollment");
+    assert_eq!(mail.content, "<p>Welcome</p>");
+
+    let response = client
+        .post("/api/v1/mail/delivery/1000/resend")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn test_gateway_alert_rules(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _client_state) = make_test_client(pool).await;
+    authenticate_admin(&mut client).await;
+
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let network: WireguardNetwork<Id> = response.json().await;
+
+    // no rules by default
+    let response = client.get("/api/v1/settings/gateway_alerts").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let rules: Vec<GatewayAlertRule> = response.json().await;
+    assert!(rules.is_empty());
+
+    // threshold has to be positive
+    let rule = json!({
+        "threshold_minutes": 0,
+        "notify_webhooks": true,
+        "notify_admins": false,
+    });
+    let response = client
+        .put(format!("/api/v1/settings/gateway_alerts/{}", network.id))
+        .json(&rule)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // unknown location
+    let rule = json!({
+        "threshold_minutes": 15,
+        "notify_webhooks": true,
+        "notify_admins": false,
+    });
+    let response = client
+        .put("/api/v1/settings/gateway_alerts/999")
+        .json(&rule)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // create and replace a rule
+    let response = client
+        .put(format!("/api/v1/settings/gateway_alerts/{}", network.id))
+        .json(&rule)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let rule = json!({
+        "threshold_minutes": 30,
+        "notify_webhooks": false,
+        "notify_admins": true,
+    });
+    let response = client
+        .put(format!("/api/v1/settings/gateway_alerts/{}", network.id))
+        .json(&rule)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get("/api/v1/settings/gateway_alerts").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let rules: Vec<GatewayAlertRule> = response.json().await;
+    assert_eq!(
+        rules,
+        vec![GatewayAlertRule {
+            location_id: network.id,
+            threshold_minutes: 30,
+            notify_webhooks: false,
+            notify_admins: true,
+        }]
+    );
+
+    // remove the rule
+    let response = client
+        .delete(format!("/api/v1/settings/gateway_alerts/{}", network.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .delete(format!("/api/v1/settings/gateway_alerts/{}", network.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = client.get("/api/v1/settings/gateway_alerts").send().await;
+    let rules: Vec<GatewayAlertRule> = response.json().await;
+    assert!(rules.is_empty());
+}
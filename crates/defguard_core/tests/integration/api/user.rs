@@ -1,13 +1,25 @@
-use defguard_common::db::Id;
+use chrono::{SubsecRound, TimeDelta, Utc};
+use defguard_common::db::{
+    Id,
+    models::{Settings, settings::update_current_settings},
+};
 use defguard_core::{
     db::{
         AddDevice, UserInfo,
-        models::{NewOpenIDClient, oauth2client::OAuth2Client},
+        models::{NewOpenIDClient, notification_digest::DigestEntry, oauth2client::OAuth2Client},
     },
     events::ApiEventType,
-    handlers::{AddUserData, Auth, PasswordChange, PasswordChangeSelf, Username},
+    handlers::{
+        AddUserData, Auth, EditGroupInfo, EmailVerification, PasswordChange, PasswordChangeSelf,
+        Username,
+    },
 };
-use reqwest::{StatusCode, header::USER_AGENT};
+use reqwest::{
+    StatusCode,
+    header::USER_AGENT,
+    multipart::{Form, Part},
+};
+use serde_json::{Value, json};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 use tokio_stream::{self as stream, StreamExt};
 
@@ -306,6 +318,7 @@ async fn test_crud_user(_: PgPoolOptions, options: PgConnectOptions) {
         email: "a.dumbledore@hogwart.edu.uk".into(),
         phone: Some("1234".into()),
         password: Some("Password1234543$!".into()),
+        service_account: false,
     };
     let response = client.post("/api/v1/user").json(&new_user).send().await;
     assert_eq!(response.status(), StatusCode::CREATED);
@@ -364,6 +377,7 @@ async fn test_check_username(_: PgPoolOptions, options: PgConnectOptions) {
             email: format!("a.dumbledore{i}@hogwart.edu.uk"),
             phone: Some("1234".into()),
             password: Some("Alohomora!12".into()),
+            service_account: false,
         };
         let response = client.post("/api/v1/user").json(&new_user).send().await;
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
@@ -378,6 +392,7 @@ async fn test_check_username(_: PgPoolOptions, options: PgConnectOptions) {
             email: format!("a.dumbledore{i}@hogwart.edu.uk"),
             phone: Some("1234".into()),
             password: Some("Alohomora!12".into()),
+            service_account: false,
         };
         let response = client.post("/api/v1/user").json(&new_user).send().await;
         assert_eq!(response.status(), StatusCode::CREATED);
@@ -413,6 +428,7 @@ async fn test_check_password_strength(_: PgPoolOptions, options: PgConnectOption
             last_name: "testpassln".into(),
             email: format!("testpass{index}@test.test"),
             password: Some(password.to_owned().into()),
+            service_account: false,
             phone: None,
         };
         let response = client
@@ -429,6 +445,7 @@ async fn test_check_password_strength(_: PgPoolOptions, options: PgConnectOption
         email: "strongpass@test.test".into(),
         phone: None,
         password: Some(strong_password.into()),
+        service_account: false,
     };
     let response = client
         .post("/api/v1/user")
@@ -442,6 +459,91 @@ async fn test_check_password_strength(_: PgPoolOptions, options: PgConnectOption
     client.verify_api_events(&[ApiEventType::UserAdded { user: test_user }]);
 }
 
+#[sqlx::test]
+async fn test_password_policy(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, pool) = make_client_with_db(pool).await;
+
+    let mut settings = Settings::get_current_settings();
+    settings.password_min_length = 12;
+    settings.password_banned = vec!["Gryffindor123!".into()];
+    settings.password_history_size = 2;
+    update_current_settings(&pool, settings).await.unwrap();
+
+    client.login_user("hpotter", "pass123").await;
+
+    // policy details are returned with violated requirements
+    let response = client
+        .put("/api/v1/user/change_password")
+        .json(&PasswordChangeSelf {
+            old_password: "pass123".into(),
+            new_password: "Short123!".into(),
+        })
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response: Value = response.json().await;
+    assert_eq!(response["violations"], json!(["too_short"]));
+    assert_eq!(response["policy"]["min_length"], 12);
+    assert_eq!(response["policy"]["history_size"], 2);
+
+    let response = client
+        .put("/api/v1/user/change_password")
+        .json(&PasswordChangeSelf {
+            old_password: "pass123".into(),
+            new_password: "gryffindor123!".into(),
+        })
+        .send()
+        .await;
+    let response: Value = response.json().await;
+    assert_eq!(response["violations"], json!(["no_uppercase", "banned"]));
+
+    // recent passwords can't be reused
+    let passwords = ["pass123", "FirstPassword123!", "SecondPassword123!"];
+    for window in passwords.windows(2) {
+        let response = client
+            .put("/api/v1/user/change_password")
+            .json(&PasswordChangeSelf {
+                old_password: window[0].into(),
+                new_password: window[1].into(),
+            })
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = client
+        .put("/api/v1/user/change_password")
+        .json(&PasswordChangeSelf {
+            old_password: "SecondPassword123!".into(),
+            new_password: "FirstPassword123!".into(),
+        })
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response: Value = response.json().await;
+    assert_eq!(response["violations"], json!(["reused"]));
+
+    // admins are subject to the same policy
+    client.login_user("admin", "pass123").await;
+    let response = client
+        .put("/api/v1/user/hpotter/password")
+        .json(&PasswordChange {
+            new_password: "SecondPassword123!".into(),
+        })
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = client
+        .put("/api/v1/user/hpotter/password")
+        .json(&PasswordChange {
+            new_password: "ThirdPassword123!".into(),
+        })
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
 #[sqlx::test]
 async fn test_user_unregister_authorized_app(_: PgPoolOptions, options: PgConnectOptions) {
     let pool = setup_pool(options).await;
@@ -712,6 +814,7 @@ async fn test_disable(_: PgPoolOptions, options: PgConnectOptions) {
         email: "a.dumbledore@hogwart.edu.uk".into(),
         phone: Some("1234".into()),
         password: Some("Password1234543$!".into()),
+        service_account: false,
     };
     let response = client.post("/api/v1/user").json(&new_user).send().await;
     assert_eq!(response.status(), StatusCode::CREATED);
@@ -765,6 +868,7 @@ async fn test_unique_email(_: PgPoolOptions, options: PgConnectOptions) {
         email: "a.dumbledore@hogwart.edu.uk".into(),
         phone: Some("1234".into()),
         password: Some("Password1234543$!".into()),
+        service_account: false,
     };
     let response = client.post("/api/v1/user").json(&new_user).send().await;
     assert_eq!(response.status(), StatusCode::CREATED);
@@ -777,6 +881,7 @@ async fn test_unique_email(_: PgPoolOptions, options: PgConnectOptions) {
         email: "a.dumbledore@hogwart.edu.uk".into(),
         phone: Some("1234".into()),
         password: Some("Password1234543$!".into()),
+        service_account: false,
     };
     let response = client.post("/api/v1/user").json(&new_user).send().await;
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
@@ -785,3 +890,322 @@ async fn test_unique_email(_: PgPoolOptions, options: PgConnectOptions) {
 
     client.verify_api_events(&[ApiEventType::UserAdded { user: test_user }]);
 }
+
+#[sqlx::test]
+async fn test_email_change_verification(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, client_state) = make_test_client(pool).await;
+    let mut mail_rx = client_state.mail_rx;
+
+    client.login_user("hpotter", "pass123").await;
+
+    // request email change
+    let mut user_details = fetch_user_details(&client, "hpotter").await;
+    let old_email = user_details.user.email.clone();
+    user_details.user.email = "harry@hogwart.edu.uk".into();
+    let response = client
+        .put("/api/v1/user/hpotter")
+        .json(&user_details.user)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // current address stays active until the new one is confirmed
+    let user_details = fetch_user_details(&client, "hpotter").await;
+    assert_eq!(user_details.user.email, old_email);
+
+    let mail = mail_rx.try_recv().unwrap();
+    assert_eq!(mail.to, "harry@hogwart.edu.uk");
+    let token: String = mail
+        .content
+        .split("token=")
+        .nth(1)
+        .unwrap()
+        .chars()
+        .take_while(char::is_ascii_alphanumeric)
+        .collect();
+
+    // invalid token
+    let response = client
+        .post("/api/v1/user/verify_email")
+        .json(&EmailVerification {
+            token: "invalid".into(),
+        })
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // verify new address
+    let response = client
+        .post("/api/v1/user/verify_email")
+        .json(&EmailVerification {
+            token: token.clone(),
+        })
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user_details = fetch_user_details(&client, "hpotter").await;
+    assert_eq!(user_details.user.email, "harry@hogwart.edu.uk");
+
+    // token can't be reused
+    let response = client
+        .post("/api/v1/user/verify_email")
+        .json(&EmailVerification {
+            token: token.clone(),
+        })
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn test_user_timezone(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_client_with_db(pool).await;
+    client.login_user("hpotter", "pass123").await;
+
+    // deployment timezone is used by default
+    let response = client.get("/api/v1/me").send().await;
+    let mut user_info: UserInfo = response.json().await;
+    assert_eq!(user_info.timezone, None);
+
+    user_info.timezone = Some("Hogwarts/Great_Hall".into());
+    let response = client
+        .put("/api/v1/user/hpotter")
+        .json(&user_info)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: Value = response.json().await;
+    assert_eq!(body["msg"], "Unknown timezone Hogwarts/Great_Hall");
+
+    user_info.timezone = Some("Europe/London".into());
+    let response = client
+        .put("/api/v1/user/hpotter")
+        .json(&user_info)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/me").send().await;
+    let mut user_info: UserInfo = response.json().await;
+    assert_eq!(user_info.timezone.as_deref(), Some("Europe/London"));
+
+    // timezone is kept if not sent
+    user_info.timezone = None;
+    let response = client
+        .put("/api/v1/user/hpotter")
+        .json(&user_info)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/me").send().await;
+    let mut user_info: UserInfo = response.json().await;
+    assert_eq!(user_info.timezone.as_deref(), Some("Europe/London"));
+
+    // empty name goes back to the deployment timezone
+    user_info.timezone = Some(String::new());
+    let response = client
+        .put("/api/v1/user/hpotter")
+        .json(&user_info)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/me").send().await;
+    let user_info: UserInfo = response.json().await;
+    assert_eq!(user_info.timezone, None);
+}
+
+#[sqlx::test]
+async fn test_user_deactivate_at(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_client_with_db(pool).await;
+    client.login_user("admin", "pass123").await;
+
+    let mut user_info = fetch_user_details(&client, "hpotter").await.user;
+    assert_eq!(user_info.deactivate_at, None);
+
+    // deactivation can't be scheduled in the past
+    user_info.deactivate_at = Some(Utc::now().naive_utc() - TimeDelta::hours(1));
+    let response = client
+        .put("/api/v1/user/hpotter")
+        .json(&user_info)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let deactivate_at = (Utc::now() + TimeDelta::days(14))
+        .naive_utc()
+        .trunc_subsecs(0);
+    user_info.deactivate_at = Some(deactivate_at);
+    let response = client
+        .put("/api/v1/user/hpotter")
+        .json(&user_info)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user_info = fetch_user_details(&client, "hpotter").await.user;
+    assert_eq!(user_info.deactivate_at, Some(deactivate_at));
+    assert!(user_info.is_active);
+
+    // admins can't schedule their own deactivation
+    let mut admin_info = fetch_user_details(&client, "admin").await.user;
+    admin_info.deactivate_at = Some(deactivate_at);
+    let response = client
+        .put("/api/v1/user/admin")
+        .json(&admin_info)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // users can't change their own deactivation time
+    client.login_user("hpotter", "pass123").await;
+    let mut user_info = fetch_user_details(&client, "hpotter").await.user;
+    user_info.deactivate_at = None;
+    let response = client
+        .put("/api/v1/user/hpotter")
+        .json(&user_info)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user_info = fetch_user_details(&client, "hpotter").await.user;
+    assert_eq!(user_info.deactivate_at, Some(deactivate_at));
+}
+
+#[sqlx::test]
+async fn test_notification_digest(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, state) = make_test_client(pool).await;
+    let mut mail_rx = state.mail_rx;
+    let user_agent_header = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1";
+
+    let auth = Auth::new("hpotter", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .get("/api/v1/user/hpotter/notifications")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let settings: Value = response.json().await;
+    assert_eq!(settings["email_digest"], false);
+
+    // users can only change their own settings
+    let response = client
+        .put("/api/v1/user/admin/notifications")
+        .json(&json!({"email_digest": true}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .put("/api/v1/user/hpotter/notifications")
+        .json(&json!({"email_digest": true}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // new device login notice is held back for the digest
+    while mail_rx.try_recv().is_ok() {}
+    let response = client
+        .post("/api/v1/auth")
+        .header(USER_AGENT, user_agent_header)
+        .json(&auth)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(mail_rx.try_recv().is_err());
+    let user = get_db_user(&state.pool, "hpotter").await;
+    let entries = DigestEntry::all_for_user(&state.pool, user.id)
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(
+        entries[0].title,
+        "Defguard: new device logged in to your account"
+    );
+    assert!(entries[0].summary.contains("127.0.0.1"));
+}
+
+#[sqlx::test]
+async fn test_import_users(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_test_client(pool).await;
+    client.login_user("admin", "pass123").await;
+    let group = EditGroupInfo::new("students", Vec::new(), false);
+    let response = client.post("/api/v1/group").json(&group).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    client.drain_all_events();
+
+    // invalid rows are reported and nothing is imported
+    let data = json!({"users": [
+        {"username": "hgranger", "email": "h.granger@hogwart.edu.uk", "first_name": "Hermione", "last_name": "Granger"},
+        {"username": "hpotter", "email": "harry@hogwart.edu.uk", "first_name": "Harry", "last_name": "Potter"},
+        {"username": "rweasley", "email": "h.granger@hogwart.edu.uk", "first_name": "Ron", "last_name": "Weasley"},
+        {"username": "nlongbottom", "email": "neville", "first_name": "Neville", "last_name": "Longbottom"},
+        {"username": "lluna", "email": "l.lovegood@hogwart.edu.uk", "first_name": "Luna", "last_name": "Lovegood", "groups": ["ravenclaw"]},
+    ]});
+    let response = client.post("/api/v1/user/import").json(&data).send().await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let errors: Value = response.json().await;
+    let rows: Vec<u64> = errors["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["row"].as_u64().unwrap())
+        .collect();
+    assert_eq!(rows, [2, 3, 4, 5]);
+    let response = client.get("/api/v1/user/hgranger").send().await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    client.assert_event_queue_is_empty();
+
+    // import from CSV file
+    let csv = "username,email,first_name,last_name,groups,phone\n\
+        hgranger,h.granger@hogwart.edu.uk,Hermione,Granger,\"students, admin\",\n\
+        rweasley,r.weasley@hogwart.edu.uk,Ron,Weasley,,\n";
+    let form = Form::new().part(
+        "file",
+        Part::text(csv)
+            .file_name("users.csv")
+            .mime_str("text/csv")
+            .unwrap(),
+    );
+    let response = client
+        .post("/api/v1/user/import/upload")
+        .multipart(form)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let imported: Value = response.json().await;
+    assert_eq!(imported["users"][0]["username"], "hgranger");
+    let mut groups: Vec<&str> = imported["users"][0]["groups"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|group| group.as_str().unwrap())
+        .collect();
+    groups.sort_unstable();
+    assert_eq!(groups, ["admin", "students"]);
+    assert_eq!(imported["users"][1]["username"], "rweasley");
+    assert_eq!(imported["users"][1]["enrolled"], false);
+
+    let events = client.drain_all_events();
+    assert_eq!(events.len(), 4);
+    assert!(matches!(events[0].0, ApiEventType::UserAdded { .. }));
+    assert!(matches!(events[1].0, ApiEventType::GroupMemberAdded { .. }));
+    assert!(matches!(events[2].0, ApiEventType::GroupMemberAdded { .. }));
+    assert!(matches!(events[3].0, ApiEventType::UserAdded { .. }));
+
+    // already existing users are rejected
+    let response = client
+        .post("/api/v1/user/import")
+        .json(&json!({"users": [
+            {"username": "rweasley", "email": "ron@hogwart.edu.uk", "first_name": "Ron", "last_name": "Weasley"},
+        ]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -0,0 +1,138 @@
+use chrono::{Duration, SubsecRound, Utc};
+use defguard_common::db::{Id, NoId};
+use defguard_core::{
+    db::models::{
+        device::Device, vpn_session::VpnSession, wireguard_peer_stats::WireguardPeerStats,
+    },
+    handlers::Auth,
+};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+use super::common::{make_network, make_test_client, setup_pool};
+
+#[sqlx::test]
+async fn test_vpn_sessions(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, client_state) = make_test_client(pool).await;
+    let pool = client_state.pool;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let device = json!({
+        "name": "laptop",
+        "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
+    });
+    let response = client
+        .post("/api/v1/device/admin")
+        .json(&device)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = client.get("/api/v1/device/1").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let device: Device<Id> = response.json().await;
+
+    // no sessions yet
+    let response = client.get("/api/v1/user/admin/vpn-sessions").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let sessions: Value = response.json().await;
+    assert_eq!(sessions["data"], json!([]));
+    assert_eq!(sessions["pagination"]["total_items"], 0);
+
+    // session lasting an hour, with stats collected while connected
+    let connected_at = (Utc::now() - Duration::hours(2))
+        .naive_utc()
+        .trunc_subsecs(6);
+    let disconnected_at = connected_at + Duration::hours(1);
+    for (minutes, upload, download) in [(0, 100, 200), (30, 600, 1200), (60, 1100, 2200)] {
+        WireguardPeerStats {
+            id: NoId,
+            device_id: device.id,
+            collected_at: connected_at + Duration::minutes(minutes),
+            network: 1,
+            endpoint: Some("11.22.33.44:5555".into()),
+            upload,
+            download,
+            latest_handshake: connected_at + Duration::minutes(minutes),
+            allowed_ips: Some("10.1.1.2".into()),
+        }
+        .save(&pool)
+        .await
+        .unwrap();
+    }
+    VpnSession::start(
+        &pool,
+        device.user_id,
+        device.id,
+        1,
+        "11.22.33.44:5555",
+        connected_at,
+    )
+    .await
+    .unwrap();
+    VpnSession::finish(&pool, device.id, 1, disconnected_at)
+        .await
+        .unwrap();
+
+    // ongoing session
+    VpnSession::start(
+        &pool,
+        device.user_id,
+        device.id,
+        1,
+        "11.22.33.44:6666",
+        disconnected_at + Duration::minutes(30),
+    )
+    .await
+    .unwrap();
+
+    // most recent session comes first
+    let response = client.get("/api/v1/user/admin/vpn-sessions").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let sessions: Value = response.json().await;
+    assert_eq!(sessions["pagination"]["total_items"], 2);
+    assert_eq!(sessions["pagination"]["next_page"], Value::Null);
+    let data = sessions["data"].as_array().unwrap();
+    assert_eq!(data[0]["endpoint"], "11.22.33.44:6666");
+    assert_eq!(data[0]["disconnected_at"], Value::Null);
+    assert_eq!(data[0]["duration_secs"], Value::Null);
+    assert_eq!(data[1]["device_name"], "laptop");
+    assert_eq!(data[1]["location_name"], "network");
+    assert_eq!(data[1]["duration_secs"], 3600);
+    assert_eq!(data[1]["upload"], 1000);
+    assert_eq!(data[1]["download"], 2000);
+
+    let response = client
+        .get(format!("/api/v1/device/{}/vpn-sessions?page=2", device.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let sessions: Value = response.json().await;
+    assert_eq!(sessions["pagination"]["current_page"], 2);
+    assert_eq!(sessions["pagination"]["total_items"], 2);
+    assert_eq!(sessions["data"], json!([]));
+
+    // other users can't see the sessions
+    let auth = Auth::new("hpotter", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/user/admin/vpn-sessions").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .get(format!("/api/v1/device/{}/vpn-sessions", device.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = client.get("/api/v1/user/hpotter/vpn-sessions").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
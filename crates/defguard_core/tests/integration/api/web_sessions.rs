@@ -0,0 +1,80 @@
+use defguard_core::events::ApiEventType;
+use reqwest::StatusCode;
+use serde_json::Value;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+use super::common::{make_client_with_db, setup_pool};
+
+#[sqlx::test]
+async fn test_web_sessions(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_client_with_db(pool).await;
+
+    // the first session is no longer used by the client after logging in again
+    client.login_user("hpotter", "pass123").await;
+    client.login_user("hpotter", "pass123").await;
+
+    let response = client.get("/api/v1/user/hpotter/session").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let sessions: Vec<Value> = response.json().await;
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0]["current"], true);
+    assert_eq!(sessions[1]["current"], false);
+    assert_eq!(sessions[1]["ip_address"], "127.0.0.1");
+    let old_session_id = sessions[1]["id"].as_str().unwrap().to_string();
+
+    let response = client
+        .delete(format!("/api/v1/user/hpotter/session/{old_session_id}"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let event = client.next_api_event().unwrap();
+    assert!(matches!(
+        *event.event,
+        ApiEventType::UserSessionRevoked { ref user, .. } if user.username == "hpotter"
+    ));
+    let response = client
+        .delete(format!("/api/v1/user/hpotter/session/{old_session_id}"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client.get("/api/v1/user/hpotter/session").send().await;
+    let sessions: Vec<Value> = response.json().await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0]["current"], true);
+
+    // users can't manage sessions of other users
+    let response = client.get("/api/v1/user/admin/session").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client.delete("/api/v1/user/admin/session").send().await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // admin revokes all sessions of the user
+    client.login_user("admin", "pass123").await;
+    let response = client.get("/api/v1/user/hpotter/session").send().await;
+    let sessions: Vec<Value> = response.json().await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0]["current"], false);
+    let response = client.delete("/api/v1/user/hpotter/session").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response: Value = response.json().await;
+    assert_eq!(response["count"], 1);
+    let event = client.next_api_event().unwrap();
+    assert!(matches!(
+        *event.event,
+        ApiEventType::UserSessionsRevoked { ref user, count: 1 } if user.username == "hpotter"
+    ));
+    let response = client.get("/api/v1/user/hpotter/session").send().await;
+    let sessions: Vec<Value> = response.json().await;
+    assert!(sessions.is_empty());
+
+    // revoking all own sessions keeps the current one
+    let response = client.delete("/api/v1/user/admin/session").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response: Value = response.json().await;
+    assert_eq!(response["count"], 0);
+    let response = client.get("/api/v1/me").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -1,9 +1,14 @@
+use chrono::Utc;
 use defguard_common::db::{Id, NoId};
-use defguard_core::{db::WebHook, handlers::Auth};
+use defguard_core::{
+    db::{AppEvent, ClientConnectionData, WebHook},
+    handlers::Auth,
+};
 use reqwest::StatusCode;
+use serde_json::{Value, json};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 
-use super::common::{make_client, setup_pool};
+use super::common::{make_client, make_client_with_db, make_network, setup_pool};
 
 #[sqlx::test]
 async fn test_webhooks(_: PgPoolOptions, options: PgConnectOptions) {
@@ -25,6 +30,10 @@ async fn test_webhooks(_: PgPoolOptions, options: PgConnectOptions) {
         on_user_deleted: false,
         on_user_modified: true,
         on_hwkey_provision: false,
+        on_gateway_disconnected: true,
+        on_client_connected: false,
+        on_client_disconnected: false,
+        secret: None,
     };
 
     let response = client.post("/api/v1/webhook").json(&webhook).send().await;
@@ -65,3 +74,122 @@ async fn test_webhooks(_: PgPoolOptions, options: PgConnectOptions) {
     let webhooks: Vec<WebHook<Id>> = response.json().await;
     assert!(webhooks.is_empty());
 }
+
+fn client_connected(location_id: Id) -> AppEvent {
+    AppEvent::ClientConnected(ClientConnectionData {
+        location_id,
+        location_name: "network".into(),
+        user_id: 1,
+        username: "admin".into(),
+        device_id: 1,
+        device_name: "device".into(),
+        public_ip: "1.2.3.4".parse().unwrap(),
+        timestamp: Utc::now().naive_utc(),
+    })
+}
+
+#[sqlx::test]
+async fn test_webhook_client_events(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, pool) = make_client_with_db(pool).await;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // create two locations
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let location: Value = response.json().await;
+    let location_id = location["id"].as_i64().unwrap();
+    let mut other_location = make_network();
+    other_location["name"] = json!("other network");
+    let response = client
+        .post("/api/v1/network")
+        .json(&other_location)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let other_location: Value = response.json().await;
+    let other_location_id = other_location["id"].as_i64().unwrap();
+
+    // subscribe a webhook to connections in the first location
+    let webhook = json!({
+        "url": "http://localhost:3000/presence",
+        "description": "Presence",
+        "token": "1234567890",
+        "enabled": true,
+        "on_user_created": false,
+        "on_user_deleted": false,
+        "on_user_modified": false,
+        "on_hwkey_provision": false,
+        "on_client_connected": true,
+        "secret": "secret",
+        "locations": [location_id],
+    });
+    let response = client.post("/api/v1/webhook").json(&webhook).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = client.get("/api/v1/webhook").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let webhooks: Vec<Value> = response.json().await;
+    assert_eq!(webhooks.len(), 1);
+    assert_eq!(webhooks[0]["locations"], json!([location_id]));
+    let webhook_id = webhooks[0]["id"].as_i64().unwrap();
+
+    // only events from subscribed locations trigger the webhook
+    let triggered = WebHook::all_enabled(&pool, &client_connected(location_id))
+        .await
+        .unwrap();
+    assert_eq!(triggered.len(), 1);
+    let triggered = WebHook::all_enabled(&pool, &client_connected(other_location_id))
+        .await
+        .unwrap();
+    assert!(triggered.is_empty());
+
+    // payloads are signed with the webhook secret
+    let webhook = WebHook::find_by_id(&pool, webhook_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        webhook.signature(br#"{"test":true}"#).unwrap(),
+        "sha256=3d286aded37566af78d3a9e37d83623c3abd9e7af955e386232717707db10ba8"
+    );
+
+    // webhook without locations receives events from all locations
+    let response = client
+        .put(format!("/api/v1/webhook/{webhook_id}"))
+        .json(&json!({
+            "url": "http://localhost:3000/presence",
+            "description": "Presence",
+            "token": "1234567890",
+            "enabled": true,
+            "on_user_created": false,
+            "on_user_deleted": false,
+            "on_user_modified": false,
+            "on_hwkey_provision": false,
+            "on_client_connected": true,
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .get(format!("/api/v1/webhook/{webhook_id}"))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let fetched: Value = response.json().await;
+    assert_eq!(fetched["locations"], json!([]));
+    assert!(fetched["secret"].is_null());
+    let triggered = WebHook::all_enabled(&pool, &client_connected(other_location_id))
+        .await
+        .unwrap();
+    assert_eq!(triggered.len(), 1);
+    assert!(triggered[0].signature(b"{}").is_none());
+}
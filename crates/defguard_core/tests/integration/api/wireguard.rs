@@ -1,22 +1,31 @@
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
-use defguard_common::db::{Id, models::settings::OpenidUsernameHandling};
+use chrono::{TimeDelta, Timelike, Utc};
+use defguard_common::{
+    csv::AsCsv,
+    db::{Id, models::settings::OpenidUsernameHandling},
+};
 use defguard_core::{
     db::{
-        Device, GatewayEvent, WireguardNetwork,
+        Device, GatewayEvent, UserDetails, WireguardNetwork,
         models::{
             device::WireguardNetworkDevice,
+            group::NetworkGroupPool,
+            trusted_device::{MAX_DEVICE_TRUST_HOURS, TrustedDevice},
             wireguard::{
-                DEFAULT_DISCONNECT_THRESHOLD, DEFAULT_KEEPALIVE_INTERVAL, LocationMfaMode,
-                ServiceLocationMode,
+                DEFAULT_DISCONNECT_THRESHOLD, DEFAULT_KEEPALIVE_INTERVAL, IpAssignmentStrategy,
+                LocationMfaMethod, LocationMfaMode, ServiceLocationMode,
             },
         },
     },
     enterprise::{
-        db::models::openid_provider::{DirectorySyncTarget, DirectorySyncUserBehavior},
+        db::models::openid_provider::{
+            DirectorySyncTarget, DirectorySyncUserBehavior, GroupClaimMapping,
+        },
         handlers::openid_providers::AddProviderData,
         license::{get_cached_license, set_cached_license},
     },
+    events::ApiEventType,
     handlers::{Auth, GroupInfo, wireguard::WireguardNetworkData},
 };
 use ipnetwork::IpNetwork;
@@ -73,6 +82,14 @@ async fn test_network(_: PgPoolOptions, options: PgConnectOptions) {
         acl_default_allow: false,
         location_mfa_mode: LocationMfaMode::Disabled,
         service_location_mode: ServiceLocationMode::Disabled,
+        ip_assignment_strategy: None,
+        group_pools: None,
+        access_windows: None,
+        allowed_mfa_methods: None,
+        mfa_device_trust_enabled: None,
+        psk_rotation_interval: None,
+        dns_search_domains: None,
+        device_approval_required: None,
     };
     let response = client
         .put(format!("/api/v1/network/{}", network.id))
@@ -153,6 +170,14 @@ async fn test_location_mfa_mode_validation_create(_: PgPoolOptions, options: PgC
         acl_default_allow: false,
         location_mfa_mode: LocationMfaMode::External,
         service_location_mode: ServiceLocationMode::Disabled,
+        ip_assignment_strategy: None,
+        group_pools: None,
+        access_windows: None,
+        allowed_mfa_methods: None,
+        mfa_device_trust_enabled: None,
+        psk_rotation_interval: None,
+        dns_search_domains: None,
+        device_approval_required: None,
     };
 
     // create network
@@ -194,6 +219,7 @@ async fn test_location_mfa_mode_validation_create(_: PgPoolOptions, options: PgC
         username_handling: OpenidUsernameHandling::PruneEmailDomain,
         jumpcloud_api_key: None,
         prefetch_users: false,
+        group_claim_mapping: GroupClaimMapping::default(),
     };
 
     let response = client
@@ -234,6 +260,14 @@ async fn test_location_mfa_mode_validation_modify(_: PgPoolOptions, options: PgC
         acl_default_allow: false,
         location_mfa_mode: LocationMfaMode::Disabled,
         service_location_mode: ServiceLocationMode::Disabled,
+        ip_assignment_strategy: None,
+        group_pools: None,
+        access_windows: None,
+        allowed_mfa_methods: None,
+        mfa_device_trust_enabled: None,
+        psk_rotation_interval: None,
+        dns_search_domains: None,
+        device_approval_required: None,
     };
 
     // create network
@@ -290,6 +324,7 @@ async fn test_location_mfa_mode_validation_modify(_: PgPoolOptions, options: PgC
         username_handling: OpenidUsernameHandling::PruneEmailDomain,
         jumpcloud_api_key: None,
         prefetch_users: false,
+        group_claim_mapping: GroupClaimMapping::default(),
     };
 
     let response = client
@@ -310,191 +345,614 @@ async fn test_location_mfa_mode_validation_modify(_: PgPoolOptions, options: PgC
 }
 
 #[sqlx::test]
-async fn test_device(_: PgPoolOptions, options: PgConnectOptions) {
+async fn test_location_allowed_mfa_methods(_: PgPoolOptions, options: PgConnectOptions) {
     let pool = setup_pool(options).await;
 
-    let (client, client_state) = make_test_client(pool).await;
+    let (mut client, _client_state) = make_test_client(pool).await;
+    authenticate_admin(&mut client).await;
 
-    let mut wg_rx = client_state.wireguard_rx;
+    let mut location_data = WireguardNetworkData {
+        name: "test_location".into(),
+        address: "10.1.1.0/24".into(),
+        endpoint: "10.1.1.1".parse().unwrap(),
+        port: 55555,
+        allowed_ips: Some("10.1.1.0/24".into()),
+        dns: None,
+        allowed_groups: vec!["admin".into()],
+        keepalive_interval: DEFAULT_KEEPALIVE_INTERVAL,
+        peer_disconnect_threshold: DEFAULT_DISCONNECT_THRESHOLD,
+        acl_enabled: false,
+        acl_default_allow: false,
+        location_mfa_mode: LocationMfaMode::Disabled,
+        service_location_mode: ServiceLocationMode::Disabled,
+        ip_assignment_strategy: None,
+        group_pools: None,
+        access_windows: None,
+        allowed_mfa_methods: Some(vec![LocationMfaMethod::Totp]),
+        mfa_device_trust_enabled: None,
+        psk_rotation_interval: None,
+        dns_search_domains: None,
+        device_approval_required: None,
+    };
 
-    let auth = Auth::new("admin", "pass123");
-    let response = &client.post("/api/v1/auth").json(&auth).send().await;
-    assert_eq!(response.status(), StatusCode::OK);
+    // methods can't be restricted without internal MFA
+    let response = client
+        .post("/api/v1/network")
+        .json(&location_data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
-    // create network
+    location_data.location_mfa_mode = LocationMfaMode::Internal;
     let response = client
         .post("/api/v1/network")
-        .json(&make_network())
+        .json(&location_data)
         .send()
         .await;
     assert_eq!(response.status(), StatusCode::CREATED);
-    let event = wg_rx.try_recv().unwrap();
-    assert_matches!(event, GatewayEvent::NetworkCreated(..));
 
-    // network details
     let response = client.get("/api/v1/network/1").send().await;
     assert_eq!(response.status(), StatusCode::OK);
-    let network_from_details: WireguardNetwork<Id> = response.json().await;
+    let network: serde_json::Value = response.json().await;
+    assert_eq!(network["allowed_mfa_methods"], json!(["totp"]));
 
-    // create device
-    let device = json!({
-        "name": "device",
-        "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
-    });
+    // methods are kept if not provided
+    location_data.allowed_mfa_methods = None;
     let response = client
-        .post("/api/v1/device/admin")
-        .json(&device)
+        .put("/api/v1/network/1")
+        .json(&location_data)
         .send()
         .await;
-    assert_eq!(response.status(), StatusCode::CREATED);
-    let event = wg_rx.try_recv().unwrap();
-    assert_matches!(event, GatewayEvent::DeviceCreated(..));
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/network/1").send().await;
+    let network: serde_json::Value = response.json().await;
+    assert_eq!(network["allowed_mfa_methods"], json!(["totp"]));
 
-    // an IP was assigned for new device
-    let network_devices = WireguardNetworkDevice::find_by_device(&client_state.pool, 1)
-        .await
-        .unwrap()
-        .unwrap();
-    assert_eq!(
-        network_devices[0].wireguard_network_id,
-        network_from_details.id
-    );
+    // empty list allows all methods
+    location_data.allowed_mfa_methods = Some(Vec::new());
+    let response = client
+        .put("/api/v1/network/1")
+        .json(&location_data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/network/1").send().await;
+    let network: serde_json::Value = response.json().await;
+    assert_eq!(network["allowed_mfa_methods"], json!([]));
+}
 
-    // add another network
+#[sqlx::test]
+async fn test_location_mfa_device_trust(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, client_state) = make_test_client(pool).await;
+    authenticate_admin(&mut client).await;
+
+    let mut location_data = WireguardNetworkData {
+        name: "test_location".into(),
+        address: "10.1.1.0/24".into(),
+        endpoint: "10.1.1.1".parse().unwrap(),
+        port: 55555,
+        allowed_ips: Some("10.1.1.0/24".into()),
+        dns: None,
+        allowed_groups: vec!["admin".into()],
+        keepalive_interval: DEFAULT_KEEPALIVE_INTERVAL,
+        peer_disconnect_threshold: DEFAULT_DISCONNECT_THRESHOLD,
+        acl_enabled: false,
+        acl_default_allow: false,
+        location_mfa_mode: LocationMfaMode::Internal,
+        service_location_mode: ServiceLocationMode::Disabled,
+        ip_assignment_strategy: None,
+        group_pools: None,
+        access_windows: None,
+        allowed_mfa_methods: None,
+        mfa_device_trust_enabled: Some(true),
+        psk_rotation_interval: None,
+        dns_search_domains: None,
+        device_approval_required: None,
+    };
     let response = client
         .post("/api/v1/network")
-        .json(&make_network())
+        .json(&location_data)
         .send()
         .await;
     assert_eq!(response.status(), StatusCode::CREATED);
-    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkCreated(..));
 
-    // an IP was assigned for an existing device
-    let network_devices = WireguardNetworkDevice::find_by_device(&client_state.pool, 1)
+    let response = client.get("/api/v1/network/1").send().await;
+    let network: serde_json::Value = response.json().await;
+    assert_eq!(network["mfa_device_trust_enabled"], json!(true));
+
+    // trust period is capped
+    let trusted_device =
+        TrustedDevice::new(1, "pubkey".into(), "secret", MAX_DEVICE_TRUST_HOURS + 100);
+    assert!(
+        trusted_device.trusted_until
+            <= Utc::now().naive_utc() + TimeDelta::hours(MAX_DEVICE_TRUST_HOURS.into())
+    );
+    trusted_device.save(&client_state.pool).await.unwrap();
+    assert!(
+        TrustedDevice::is_trusted(&client_state.pool, 1, "pubkey", "secret")
+            .await
+            .unwrap()
+    );
+    assert!(
+        !TrustedDevice::is_trusted(&client_state.pool, 1, "other", "secret")
+            .await
+            .unwrap()
+    );
+    // knowing the public key isn't enough
+    assert!(
+        !TrustedDevice::is_trusted(&client_state.pool, 1, "pubkey", "guess")
+            .await
+            .unwrap()
+    );
+
+    // trust is revoked once the device loses access to the location
+    let response = client
+        .post("/api/v1/group/admin")
+        .json(&json!({"username": "hpotter"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let pubkey = "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=";
+    let response = client
+        .post("/api/v1/device/hpotter")
+        .json(&json!({"name": "device", "wireguard_pubkey": pubkey}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    TrustedDevice::new(1, pubkey.into(), "secret", 24)
+        .save(&client_state.pool)
         .await
-        .unwrap()
         .unwrap();
-    assert_eq!(network_devices.len(), 2);
-
-    // list devices
-    let response = client.get("/api/v1/device").json(&device).send().await;
+    let response = client
+        .delete("/api/v1/group/admin/user/hpotter")
+        .send()
+        .await;
     assert_eq!(response.status(), StatusCode::OK);
-    let devices: Vec<Device<Id>> = response.json().await;
-    assert_eq!(devices.len(), 1);
-    let device = devices[0].clone();
-    assert_eq!(device.name, "device");
-    assert_eq!(
-        device.wireguard_pubkey,
-        "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU="
+    assert!(
+        !TrustedDevice::is_trusted(&client_state.pool, 1, pubkey, "secret")
+            .await
+            .unwrap()
     );
 
-    // list user devices
+    // disabling the policy removes trust records
+    location_data.mfa_device_trust_enabled = Some(false);
     let response = client
-        .get("/api/v1/device/user/admin")
-        .json(&device)
+        .put("/api/v1/network/1")
+        .json(&location_data)
         .send()
         .await;
     assert_eq!(response.status(), StatusCode::OK);
-    let user_devices: Vec<Device<Id>> = response.json().await;
-    assert_eq!(user_devices.len(), 1);
-    assert_eq!(devices.len(), 1);
-    assert_eq!(device.id, user_devices[0].id);
+    let response = client.get("/api/v1/network/1").send().await;
+    let network: serde_json::Value = response.json().await;
+    assert_eq!(network["mfa_device_trust_enabled"], json!(false));
+    assert!(
+        !TrustedDevice::is_trusted(&client_state.pool, 1, "pubkey", "secret")
+            .await
+            .unwrap()
+    );
+}
 
-    // modify device
-    let modified_name = "modified-device";
-    let modified_key = "sIhx53MsX+iLk83sssybHrD7M+5m+CmpLzWL/zo8C38=";
-    let mut modified_device = device.clone();
-    modified_device.name = modified_name.into();
-    modified_device.wireguard_pubkey = modified_key.into();
+#[sqlx::test]
+async fn test_location_psk_rotation(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _) = make_test_client(pool).await;
+    authenticate_admin(&mut client).await;
+
+    let mut location_data = WireguardNetworkData {
+        name: "test_location".into(),
+        address: "10.1.1.0/24".into(),
+        endpoint: "10.1.1.1".parse().unwrap(),
+        port: 55555,
+        allowed_ips: Some("10.1.1.0/24".into()),
+        dns: None,
+        allowed_groups: vec!["admin".into()],
+        keepalive_interval: DEFAULT_KEEPALIVE_INTERVAL,
+        peer_disconnect_threshold: DEFAULT_DISCONNECT_THRESHOLD,
+        acl_enabled: false,
+        acl_default_allow: false,
+        location_mfa_mode: LocationMfaMode::Internal,
+        service_location_mode: ServiceLocationMode::Disabled,
+        ip_assignment_strategy: None,
+        group_pools: None,
+        access_windows: None,
+        allowed_mfa_methods: None,
+        mfa_device_trust_enabled: None,
+        psk_rotation_interval: Some(24),
+        dns_search_domains: None,
+        device_approval_required: None,
+    };
+
+    // MFA locations already use per-session preshared keys
     let response = client
-        .put(format!("/api/v1/device/{}", device.id))
-        .json(&modified_device)
+        .post("/api/v1/network")
+        .json(&location_data)
         .send()
         .await;
-    assert_eq!(response.status(), StatusCode::OK);
-    let event = wg_rx.try_recv().unwrap();
-    assert_matches!(event, GatewayEvent::DeviceModified(..));
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
-    // device details
+    location_data.location_mfa_mode = LocationMfaMode::Disabled;
+    location_data.psk_rotation_interval = Some(-1);
     let response = client
-        .get(format!("/api/v1/device/{}", device.id))
+        .post("/api/v1/network")
+        .json(&location_data)
         .send()
         .await;
-    assert_eq!(response.status(), StatusCode::OK);
-    let device_from_details: Device<Id> = response.json().await;
-    assert_eq!(device_from_details.name, modified_name);
-    assert_eq!(device_from_details.wireguard_pubkey, modified_key);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
-    // device config
+    location_data.psk_rotation_interval = Some(24);
     let response = client
-        .get(format!("/api/v1/network/1/device/{}/config", device.id))
+        .post("/api/v1/network")
+        .json(&location_data)
         .send()
         .await;
-    assert_eq!(response.status(), StatusCode::OK);
-    let config = response.text().await;
-    assert_eq!(
-        config,
-        format!(
-            "[Interface]\n\
-            PrivateKey = YOUR_PRIVATE_KEY\n\
-            Address = 10.1.1.2\n\
-            DNS = 1.1.1.1\n\
-            \n\
-            [Peer]\n\
-            PublicKey = {}\n\
-            AllowedIPs = 10.1.1.0/24\n\
-            Endpoint = 192.168.4.14:55555\n\
-            PersistentKeepalive = 25",
-            network_from_details.pubkey
-        )
-    );
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = client.get("/api/v1/network/1").send().await;
+    let network: serde_json::Value = response.json().await;
+    assert_eq!(network["psk_rotation_interval"], json!(24));
 
+    // interval is kept if not provided
+    location_data.psk_rotation_interval = None;
     let response = client
-        .delete(format!("/api/v1/network/{}", network_from_details.id))
+        .put("/api/v1/network/1")
+        .json(&location_data)
         .send()
         .await;
     assert_eq!(response.status(), StatusCode::OK);
-    let event = wg_rx.try_recv().unwrap();
-    assert_matches!(event, GatewayEvent::NetworkDeleted(..));
+    let response = client.get("/api/v1/network/1").send().await;
+    let network: serde_json::Value = response.json().await;
+    assert_eq!(network["psk_rotation_interval"], json!(24));
 
-    // delete device
+    // zero disables rotation
+    location_data.psk_rotation_interval = Some(0);
     let response = client
-        .delete(format!("/api/v1/device/{}", device.id))
+        .put("/api/v1/network/1")
+        .json(&location_data)
         .send()
         .await;
     assert_eq!(response.status(), StatusCode::OK);
-    let event = wg_rx.try_recv().unwrap();
-    assert_matches!(event, GatewayEvent::DeviceDeleted(..));
-
-    let response = client.get("/api/v1/device").json(&device).send().await;
-    assert_eq!(response.status(), StatusCode::OK);
-    let devices: Vec<Device<Id>> = response.json().await;
-    assert!(devices.is_empty());
+    let response = client.get("/api/v1/network/1").send().await;
+    let network: serde_json::Value = response.json().await;
+    assert_eq!(network["psk_rotation_interval"], json!(null));
 }
 
 #[sqlx::test]
-async fn test_network_address_reassignment(_: PgPoolOptions, options: PgConnectOptions) {
+async fn test_location_dns(_: PgPoolOptions, options: PgConnectOptions) {
     let pool = setup_pool(options).await;
 
-    let (client, client_state) = make_test_client(pool).await;
+    let (mut client, _) = make_test_client(pool).await;
+    authenticate_admin(&mut client).await;
 
-    let auth = Auth::new("admin", "pass123");
-    let response = &client.post("/api/v1/auth").json(&auth).send().await;
-    assert_eq!(response.status(), StatusCode::OK);
+    let mut location_data = WireguardNetworkData {
+        name: "test_location".into(),
+        address: "10.1.1.0/24".into(),
+        endpoint: "10.1.1.1".parse().unwrap(),
+        port: 55555,
+        allowed_ips: Some("10.1.1.0/24".into()),
+        dns: Some("10.1.1.53, 10.1.1.54".into()),
+        allowed_groups: vec!["admin".into()],
+        keepalive_interval: DEFAULT_KEEPALIVE_INTERVAL,
+        peer_disconnect_threshold: DEFAULT_DISCONNECT_THRESHOLD,
+        acl_enabled: false,
+        acl_default_allow: false,
+        location_mfa_mode: LocationMfaMode::Disabled,
+        service_location_mode: ServiceLocationMode::Disabled,
+        ip_assignment_strategy: None,
+        group_pools: None,
+        access_windows: None,
+        allowed_mfa_methods: None,
+        mfa_device_trust_enabled: None,
+        psk_rotation_interval: None,
+        dns_search_domains: Some(vec!["-invalid.example.com".into()]),
+        device_approval_required: None,
+    };
+    let response = client
+        .post("/api/v1/network")
+        .json(&location_data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
-    // create network
-    let network = json!({
-        "name": "network",
-        "address": "10.1.1.1/24",
-        "port": 55555,
-        "endpoint": "192.168.4.14",
-        "allowed_ips": "10.1.1.0/24",
-        "dns": "1.1.1.1",
-        "allowed_groups": [],
-        "keepalive_interval": 25,
-        "peer_disconnect_threshold": 300,
-        "acl_enabled": false,
-        "acl_default_allow": false,
+    location_data.dns_search_domains = Some(vec!["corp.example.com".into()]);
+    let response = client
+        .post("/api/v1/network")
+        .json(&location_data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = client.get("/api/v1/network/1").send().await;
+    let network: serde_json::Value = response.json().await;
+    assert_eq!(network["dns_search_domains"], json!(["corp.example.com"]));
+
+    let response = client
+        .post("/api/v1/device/admin")
+        .json(&json!({
+            "name": "device",
+            "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // search domains follow DNS servers
+    let response = client.get("/api/v1/network/1/device/1/config").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let config = response.text().await;
+    assert!(config.contains("DNS = 10.1.1.53, 10.1.1.54, corp.example.com\n"));
+
+    // group DNS servers replace location servers
+    location_data.dns_search_domains = None;
+    location_data.group_pools = Some(vec![NetworkGroupPool {
+        group: "admin".into(),
+        address_pool: Vec::new(),
+        allowed_ips: Vec::new(),
+        dns: Some("10.1.1.99".into()),
+        dns_search_domains: Vec::new(),
+        firewall_tag: None,
+        priority: 0,
+    }]);
+    let response = client
+        .put("/api/v1/network/1")
+        .json(&location_data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/network/1/device/1/config").send().await;
+    let config = response.text().await;
+    assert!(config.contains("DNS = 10.1.1.99, corp.example.com\n"));
+}
+
+#[sqlx::test]
+async fn test_dual_stack_location(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, client_state) = make_test_client(pool).await;
+    let mut wg_rx = client_state.wireguard_rx;
+    authenticate_admin(&mut client).await;
+
+    let network = json!({
+        "name": "network",
+        "address": "10.1.1.1/24,fd00::1/64",
+        "port": 55555,
+        "endpoint": "192.168.4.14",
+        "allowed_ips": "10.1.1.0/24,fd00::/64",
+        "dns": "1.1.1.1",
+        "allowed_groups": [],
+        "keepalive_interval": 25,
+        "peer_disconnect_threshold": 300,
+        "acl_enabled": false,
+        "acl_default_allow": false,
+        "location_mfa_mode": "disabled",
+        "service_location_mode": "disabled"
+    });
+    let response = client.post("/api/v1/network").json(&network).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkCreated(..));
+
+    // device gets an address from each network
+    let response = client
+        .post("/api/v1/device/admin")
+        .json(&json!({
+            "name": "device",
+            "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let expected_ips = vec![
+        IpAddr::V4(Ipv4Addr::new(10, 1, 1, 2)),
+        IpAddr::V6(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 2)),
+    ];
+    match wg_rx.try_recv().unwrap() {
+        GatewayEvent::DeviceCreated(info) => {
+            assert_eq!(info.network_info[0].device_wireguard_ips, expected_ips);
+        }
+        event => panic!("Unexpected event: {event:?}"),
+    }
+
+    let response = client.get("/api/v1/network/1/device/1/config").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let config = response.text().await;
+    assert!(config.contains(&format!("Address = {}\n", expected_ips.as_csv())));
+    assert!(config.contains("AllowedIPs = 10.1.1.0/24,fd00::/64\n"));
+
+    // gateways get both addresses as peer allowed IPs
+    let location = WireguardNetwork::find_by_id(&client_state.pool, 1)
+        .await
+        .unwrap()
+        .unwrap();
+    let peers = location.get_peers(&client_state.pool).await.unwrap();
+    assert_eq!(peers[0].allowed_ips, ["10.1.1.2", "fd00::2"]);
+}
+
+#[sqlx::test]
+async fn test_device(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, client_state) = make_test_client(pool).await;
+
+    let mut wg_rx = client_state.wireguard_rx;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = &client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // create network
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let event = wg_rx.try_recv().unwrap();
+    assert_matches!(event, GatewayEvent::NetworkCreated(..));
+
+    // network details
+    let response = client.get("/api/v1/network/1").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let network_from_details: WireguardNetwork<Id> = response.json().await;
+
+    // create device
+    let device = json!({
+        "name": "device",
+        "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
+    });
+    let response = client
+        .post("/api/v1/device/admin")
+        .json(&device)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let event = wg_rx.try_recv().unwrap();
+    assert_matches!(event, GatewayEvent::DeviceCreated(..));
+
+    // an IP was assigned for new device
+    let network_devices = WireguardNetworkDevice::find_by_device(&client_state.pool, 1)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        network_devices[0].wireguard_network_id,
+        network_from_details.id
+    );
+
+    // add another network
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkCreated(..));
+
+    // an IP was assigned for an existing device
+    let network_devices = WireguardNetworkDevice::find_by_device(&client_state.pool, 1)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(network_devices.len(), 2);
+
+    // list devices
+    let response = client.get("/api/v1/device").json(&device).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let devices: Vec<Device<Id>> = response.json().await;
+    assert_eq!(devices.len(), 1);
+    let device = devices[0].clone();
+    assert_eq!(device.name, "device");
+    assert_eq!(
+        device.wireguard_pubkey,
+        "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU="
+    );
+
+    // list user devices
+    let response = client
+        .get("/api/v1/device/user/admin")
+        .json(&device)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user_devices: Vec<Device<Id>> = response.json().await;
+    assert_eq!(user_devices.len(), 1);
+    assert_eq!(devices.len(), 1);
+    assert_eq!(device.id, user_devices[0].id);
+
+    // modify device
+    let modified_name = "modified-device";
+    let modified_key = "sIhx53MsX+iLk83sssybHrD7M+5m+CmpLzWL/zo8C38=";
+    let mut modified_device = device.clone();
+    modified_device.name = modified_name.into();
+    modified_device.wireguard_pubkey = modified_key.into();
+    let response = client
+        .put(format!("/api/v1/device/{}", device.id))
+        .json(&modified_device)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let event = wg_rx.try_recv().unwrap();
+    assert_matches!(event, GatewayEvent::DeviceModified(..));
+
+    // device details
+    let response = client
+        .get(format!("/api/v1/device/{}", device.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let device_from_details: Device<Id> = response.json().await;
+    assert_eq!(device_from_details.name, modified_name);
+    assert_eq!(device_from_details.wireguard_pubkey, modified_key);
+
+    // device config
+    let response = client
+        .get(format!("/api/v1/network/1/device/{}/config", device.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let config = response.text().await;
+    assert_eq!(
+        config,
+        format!(
+            "[Interface]\n\
+            PrivateKey = YOUR_PRIVATE_KEY\n\
+            Address = 10.1.1.2\n\
+            DNS = 1.1.1.1\n\
+            \n\
+            [Peer]\n\
+            PublicKey = {}\n\
+            AllowedIPs = 10.1.1.0/24\n\
+            Endpoint = 192.168.4.14:55555\n\
+            PersistentKeepalive = 25",
+            network_from_details.pubkey
+        )
+    );
+
+    let response = client
+        .delete(format!("/api/v1/network/{}", network_from_details.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let event = wg_rx.try_recv().unwrap();
+    assert_matches!(event, GatewayEvent::NetworkDeleted(..));
+
+    // delete device
+    let response = client
+        .delete(format!("/api/v1/device/{}", device.id))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let event = wg_rx.try_recv().unwrap();
+    assert_matches!(event, GatewayEvent::DeviceDeleted(..));
+
+    let response = client.get("/api/v1/device").json(&device).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let devices: Vec<Device<Id>> = response.json().await;
+    assert!(devices.is_empty());
+}
+
+#[sqlx::test]
+async fn test_network_address_reassignment(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, client_state) = make_test_client(pool).await;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = &client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // create network
+    let network = json!({
+        "name": "network",
+        "address": "10.1.1.1/24",
+        "port": 55555,
+        "endpoint": "192.168.4.14",
+        "allowed_ips": "10.1.1.0/24",
+        "dns": "1.1.1.1",
+        "allowed_groups": [],
+        "keepalive_interval": 25,
+        "peer_disconnect_threshold": 300,
+        "acl_enabled": false,
+        "acl_default_allow": false,
         "location_mfa_mode": "disabled",
         "service_location_mode": "disabled"
     });
@@ -979,3 +1437,682 @@ async fn test_network_size_validation(_: PgPoolOptions, options: PgConnectOption
         .await;
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 }
+
+#[sqlx::test]
+async fn test_device_provisioning(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, client_state) = make_test_client(pool).await;
+
+    let mut wg_rx = client_state.wireguard_rx;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = &client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // create network
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkCreated(..));
+
+    // provision device with server-generated keys
+    let response = client
+        .post("/api/v1/device/user/hpotter/provision")
+        .json(&json!({"name": "kiosk"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::DeviceCreated(..));
+    let result: serde_json::Value = response.json().await;
+    let private_key = result["private_key"].as_str().unwrap();
+    assert_eq!(result["device"]["user_id"], client_state.test_user.id);
+    assert_eq!(result["device"]["name"], "kiosk");
+    let config = result["configs"][0]["config"].as_str().unwrap();
+    assert!(config.contains(&format!("PrivateKey = {private_key}")));
+    assert!(!config.contains("YOUR_PRIVATE_KEY"));
+
+    // provision device with pubkey provided by admin
+    let response = client
+        .post("/api/v1/device/user/hpotter/provision")
+        .json(&json!({
+            "name": "laptop",
+            "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::DeviceCreated(..));
+    let result: serde_json::Value = response.json().await;
+    assert!(result["private_key"].is_null());
+    let config = result["configs"][0]["config"].as_str().unwrap();
+    assert!(config.contains("YOUR_PRIVATE_KEY"));
+
+    // duplicate pubkey is rejected
+    let response = client
+        .post("/api/v1/device/user/hpotter/provision")
+        .json(&json!({
+            "name": "laptop-2",
+            "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // unknown user
+    let response = client
+        .post("/api/v1/device/user/nobody/provision")
+        .json(&json!({"name": "kiosk"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client.get("/api/v1/device/user/hpotter").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let devices: Vec<Device<Id>> = response.json().await;
+    assert_eq!(devices.len(), 2);
+
+    // only admins can provision devices
+    client.drain_all_events();
+    client.login_user("hpotter", "pass123").await;
+    let response = client
+        .post("/api/v1/device/user/hpotter/provision")
+        .json(&json!({"name": "own"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // disabled users can't get new devices
+    authenticate_admin(&mut client).await;
+    let mut user = client_state.test_user.clone();
+    user.is_active = false;
+    user.save(&client_state.pool).await.unwrap();
+    let response = client
+        .post("/api/v1/device/user/hpotter/provision")
+        .json(&json!({"name": "kiosk-2"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test]
+async fn test_ip_assignment_strategy(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, client_state) = make_test_client(pool).await;
+
+    let mut wg_rx = client_state.wireguard_rx;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = &client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // create network with addresses derived from device public keys
+    let mut network = make_network();
+    network["ip_assignment_strategy"] = json!("pubkey_hash");
+    let response = client.post("/api/v1/network").json(&network).send().await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkCreated(..));
+
+    let response = client.get("/api/v1/network/1").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let network: WireguardNetwork<Id> = response.json().await;
+    assert_eq!(
+        network.ip_assignment_strategy,
+        IpAssignmentStrategy::PubkeyHash
+    );
+
+    // addresses don't depend on the order in which devices are created
+    for (pubkey, expected_ip) in [
+        ("hNuapt7lOxF93KUqZGUY00oKJxH8LYwwsUVB1uUa0y4=", "10.1.1.37"),
+        ("LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=", "10.1.1.210"),
+    ] {
+        let response = client
+            .post("/api/v1/device/admin")
+            .json(&json!({"name": expected_ip, "wireguard_pubkey": pubkey}))
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::DeviceCreated(..));
+        let result: serde_json::Value = response.json().await;
+        assert_eq!(result["configs"][0]["address"], json!([expected_ip]));
+    }
+
+    // strategy is kept if not provided
+    let response = client
+        .put("/api/v1/network/1")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkModified(..));
+    let response = client.get("/api/v1/network/1").send().await;
+    let network: WireguardNetwork<Id> = response.json().await;
+    assert_eq!(
+        network.ip_assignment_strategy,
+        IpAssignmentStrategy::PubkeyHash
+    );
+
+    // switch to sequential assignment
+    let mut network = make_network();
+    network["ip_assignment_strategy"] = json!("sequential");
+    let response = client.put("/api/v1/network/1").json(&network).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkModified(..));
+
+    let response = client
+        .post("/api/v1/device/admin")
+        .json(&json!({
+            "name": "sequential",
+            "wireguard_pubkey": "mgVXE8WcfStoD8mRatHcX5aaQ0DlcpjvPXibHEOjL58=",
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let result: serde_json::Value = response.json().await;
+    assert_eq!(result["configs"][0]["address"], json!(["10.1.1.2"]));
+}
+
+#[sqlx::test]
+async fn test_location_connectivity(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, _client_state) = make_test_client(pool).await;
+    authenticate_admin(&mut client).await;
+
+    let response = client.get("/api/v1/network/1/connectivity").send().await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // no gateway has connected yet
+    let response = client.get("/api/v1/network/1/connectivity").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let connectivity: serde_json::Value = response.json().await;
+    assert_eq!(
+        connectivity,
+        json!({
+            "location_id": 1,
+            "connected": false,
+            "last_handshake": null,
+            "gateways": [],
+        })
+    );
+}
+
+#[sqlx::test]
+async fn test_device_expiry(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, _) = make_test_client(pool).await;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = &client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // create network
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // create device for hpotter
+    let device = json!({
+        "name": "auditor-laptop",
+        "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
+    });
+    let response = client
+        .post("/api/v1/device/hpotter")
+        .json(&device)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // expiry time must be in the future
+    let expires_at = Utc::now().naive_utc() - TimeDelta::hours(1);
+    let response = client
+        .put("/api/v1/device/1/expiry")
+        .json(&json!({ "expires_at": expires_at }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = client
+        .put("/api/v1/device/2/expiry")
+        .json(&json!({ "expires_at": null }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let expires_at = (Utc::now() + TimeDelta::days(7))
+        .naive_utc()
+        .with_nanosecond(0);
+    let response = client
+        .put("/api/v1/device/1/expiry")
+        .json(&json!({ "expires_at": expires_at }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/user/hpotter").send().await;
+    let user_details: UserDetails = response.json().await;
+    assert_eq!(user_details.devices[0].expires_at, expires_at);
+
+    // device owners can't change the expiry
+    let auth = Auth::new("hpotter", "pass123");
+    let response = &client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .put("/api/v1/device/1/expiry")
+        .json(&json!({ "expires_at": null }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // admin removes the expiry
+    let auth = Auth::new("admin", "pass123");
+    let response = &client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .put("/api/v1/device/1/expiry")
+        .json(&json!({ "expires_at": null }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/api/v1/user/hpotter").send().await;
+    let user_details: UserDetails = response.json().await;
+    assert_eq!(user_details.devices[0].expires_at, None);
+}
+
+#[sqlx::test]
+async fn test_device_key_rotation(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, client_state) = make_test_client(pool).await;
+    let mut wg_rx = client_state.wireguard_rx;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = &client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // create network
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkCreated(..));
+
+    // create devices
+    let old_pubkey = "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=";
+    let other_pubkey = "TJgN9JzUF5zdZAPYD96G/Wys2M3TvaT5TIrErUl20nI=";
+    let new_pubkey = "sIhx53MsX+iLk83sssybHrD7M+5m+CmpLzWL/zo8C38=";
+    for (name, pubkey) in [("laptop", old_pubkey), ("phone", other_pubkey)] {
+        let response = client
+            .post("/api/v1/device/hpotter")
+            .json(&json!({"name": name, "wireguard_pubkey": pubkey}))
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::DeviceCreated(..));
+    }
+    let ips_before = WireguardNetworkDevice::find_by_device(&client_state.pool, 1)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // invalid or already used keys are rejected
+    for pubkey in ["invalid", other_pubkey] {
+        let response = client
+            .post("/api/v1/device/1/key-rotation")
+            .json(&json!({ "wireguard_pubkey": pubkey }))
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    let response = client
+        .post("/api/v1/device/1/key-rotation")
+        .json(&json!({ "wireguard_pubkey": new_pubkey }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let device: Device<Id> = response.json().await;
+    assert_eq!(device.id, 1);
+    assert_eq!(device.wireguard_pubkey, new_pubkey);
+
+    // gateways replace the peer
+    assert_matches!(
+        wg_rx.try_recv().unwrap(),
+        GatewayEvent::DeviceDeleted(info) if info.device.wireguard_pubkey == old_pubkey
+    );
+    assert_matches!(
+        wg_rx.try_recv().unwrap(),
+        GatewayEvent::DeviceCreated(info) if info.device.wireguard_pubkey == new_pubkey
+    );
+
+    // addresses are kept
+    let ips_after = WireguardNetworkDevice::find_by_device(&client_state.pool, 1)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(ips_before[0].wireguard_ips, ips_after[0].wireguard_ips);
+
+    // previous key is kept in the history
+    let response = client.get("/api/v1/device/1/key-history").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let history: Vec<serde_json::Value> = response.json().await;
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0]["wireguard_pubkey"], old_pubkey);
+
+    // owners can rotate keys of their devices
+    let auth = Auth::new("hpotter", "pass123");
+    let response = &client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .post("/api/v1/device/1/key-rotation")
+        .json(&json!({ "wireguard_pubkey": old_pubkey }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[sqlx::test]
+async fn test_device_ip_reservation(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, client_state) = make_test_client(pool).await;
+    let mut wg_rx = client_state.wireguard_rx;
+    authenticate_admin(&mut client).await;
+
+    let mut location_data = WireguardNetworkData {
+        name: "test_location".into(),
+        address: "10.1.1.1/24".into(),
+        endpoint: "10.1.1.1".parse().unwrap(),
+        port: 55555,
+        allowed_ips: Some("10.1.1.0/24".into()),
+        dns: None,
+        allowed_groups: vec!["admin".into()],
+        keepalive_interval: DEFAULT_KEEPALIVE_INTERVAL,
+        peer_disconnect_threshold: DEFAULT_DISCONNECT_THRESHOLD,
+        acl_enabled: false,
+        acl_default_allow: false,
+        location_mfa_mode: LocationMfaMode::Disabled,
+        service_location_mode: ServiceLocationMode::Disabled,
+        ip_assignment_strategy: None,
+        group_pools: None,
+        access_windows: None,
+        allowed_mfa_methods: None,
+        mfa_device_trust_enabled: None,
+        psk_rotation_interval: None,
+        dns_search_domains: None,
+        device_approval_required: None,
+    };
+    let response = client
+        .post("/api/v1/network")
+        .json(&location_data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkCreated(..));
+
+    for (name, pubkey) in [
+        ("printer", "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU="),
+        ("laptop", "TJgN9JzUF5zdZAPYD96G/Wys2M3TvaT5TIrErUl20nI="),
+    ] {
+        let response = client
+            .post("/api/v1/device/admin")
+            .json(&json!({"name": name, "wireguard_pubkey": pubkey}))
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::DeviceCreated(..));
+    }
+
+    // addresses have to be free and within the location subnets, one in each of them
+    for ips in [
+        json!([]),
+        json!(["10.1.1.1"]),
+        json!(["10.1.1.3"]),
+        json!(["10.1.1.255"]),
+        json!(["10.2.1.5"]),
+        json!(["10.1.1.50", "10.1.1.51"]),
+    ] {
+        let response = client
+            .put("/api/v1/network/1/device/1/ip")
+            .json(&json!({ "ips": ips }))
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    let response = client
+        .put("/api/v1/network/1/device/3/ip")
+        .json(&json!({"ips": ["10.1.1.50"]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    client.drain_all_events();
+    let reserved_ip = IpAddr::V4(Ipv4Addr::new(10, 1, 1, 50));
+    let response = client
+        .put("/api/v1/network/1/device/1/ip")
+        .json(&json!({"ips": ["10.1.1.50"]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    match wg_rx.try_recv().unwrap() {
+        GatewayEvent::DeviceModified(info) => {
+            assert_eq!(info.network_info[0].device_wireguard_ips, [reserved_ip]);
+        }
+        event => panic!("Unexpected event: {event:?}"),
+    }
+    let event = client.next_api_event().unwrap();
+    assert_matches!(
+        *event.event,
+        ApiEventType::DeviceAddressReserved { ref before, ref after, .. }
+            if before == &[IpAddr::V4(Ipv4Addr::new(10, 1, 1, 2))] && after == &[reserved_ip]
+    );
+    let response = client.get("/api/v1/network/1/device/1/ip").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let reservation: serde_json::Value = response.json().await;
+    assert_eq!(reservation, json!({"ips": ["10.1.1.50"], "reserved": true}));
+
+    // reserved addresses are kept when address pools change
+    location_data.group_pools = Some(vec![NetworkGroupPool {
+        group: "admin".into(),
+        address_pool: vec!["10.1.1.128/25".parse().unwrap()],
+        allowed_ips: Vec::new(),
+        dns: None,
+        dns_search_domains: Vec::new(),
+        firewall_tag: None,
+        priority: 0,
+    }]);
+    let response = client
+        .put("/api/v1/network/1")
+        .json(&location_data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let printer = WireguardNetworkDevice::find_by_device(&client_state.pool, 1)
+        .await
+        .unwrap()
+        .unwrap()
+        .remove(0);
+    assert_eq!(printer.wireguard_ips, [reserved_ip]);
+    let laptop = WireguardNetworkDevice::find_by_device(&client_state.pool, 2)
+        .await
+        .unwrap()
+        .unwrap()
+        .remove(0);
+    let pool: IpNetwork = "10.1.1.128/25".parse().unwrap();
+    assert!(pool.contains(laptop.wireguard_ips[0]));
+
+    // new reservations have to be within the address pool of the device owner
+    let response = client
+        .put("/api/v1/network/1/device/2/ip")
+        .json(&json!({"ips": ["10.1.1.60"]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // released addresses are reassigned automatically
+    let response = client.delete("/api/v1/network/1/device/1/ip").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let event = client.next_api_event().unwrap();
+    assert_matches!(
+        *event.event,
+        ApiEventType::DeviceAddressReleased { ref addresses, .. } if addresses == &[reserved_ip]
+    );
+    let response = client.delete("/api/v1/network/1/device/1/ip").send().await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = client
+        .put("/api/v1/network/1")
+        .json(&location_data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let printer = WireguardNetworkDevice::find_by_device(&client_state.pool, 1)
+        .await
+        .unwrap()
+        .unwrap()
+        .remove(0);
+    assert!(pool.contains(printer.wireguard_ips[0]));
+}
+
+#[sqlx::test]
+async fn test_location_clone(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (mut client, client_state) = make_test_client(pool).await;
+    let mut wg_rx = client_state.wireguard_rx;
+    authenticate_admin(&mut client).await;
+
+    let location_data = WireguardNetworkData {
+        name: "warsaw".into(),
+        address: "10.1.1.1/24".into(),
+        endpoint: "10.1.1.1".parse().unwrap(),
+        port: 55555,
+        allowed_ips: Some("192.168.1.0/24".into()),
+        dns: Some("10.1.1.53".into()),
+        allowed_groups: vec!["admin".into()],
+        keepalive_interval: 30,
+        peer_disconnect_threshold: DEFAULT_DISCONNECT_THRESHOLD,
+        acl_enabled: false,
+        acl_default_allow: false,
+        location_mfa_mode: LocationMfaMode::Disabled,
+        service_location_mode: ServiceLocationMode::Disabled,
+        ip_assignment_strategy: None,
+        group_pools: Some(vec![NetworkGroupPool {
+            group: "admin".into(),
+            address_pool: vec!["10.1.1.64/26".parse().unwrap()],
+            allowed_ips: Vec::new(),
+            dns: None,
+            dns_search_domains: Vec::new(),
+            firewall_tag: None,
+            priority: 0,
+        }]),
+        access_windows: None,
+        allowed_mfa_methods: None,
+        mfa_device_trust_enabled: None,
+        psk_rotation_interval: Some(24),
+        dns_search_domains: Some(vec!["corp.example.com".into()]),
+        device_approval_required: None,
+    };
+    let response = client
+        .post("/api/v1/network")
+        .json(&location_data)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkCreated(..));
+    let response = client
+        .post("/api/v1/device/admin")
+        .json(&json!({
+            "name": "device",
+            "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = client
+        .put("/api/v1/network/1/device/1/ip")
+        .json(&json!({"ips": ["10.1.1.100"]}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .post("/api/v1/network/5/clone")
+        .json(&json!({"name": "berlin", "address": "10.2.1.1/24"}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    // addresses have to match subnets of the cloned location and fit its address pools
+    for address in ["10.2.1.1/24,fd00::1/64", "fd00::1/64", "10.2.1.1/27"] {
+        let response = client
+            .post("/api/v1/network/1/clone")
+            .json(&json!({"name": "berlin", "address": address}))
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    let response = client
+        .post("/api/v1/network/1/clone")
+        .json(&json!({
+            "name": "berlin",
+            "address": "10.2.1.1/24",
+            "endpoint": "10.2.1.1",
+            "copy_device_assignments": true,
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let clone: serde_json::Value = response.json().await;
+    assert_eq!(clone["id"], 2);
+    assert_ne!(clone["pubkey"], json!(null));
+    let response = client.get("/api/v1/network/1").send().await;
+    let source: serde_json::Value = response.json().await;
+    assert_ne!(clone["pubkey"], source["pubkey"]);
+
+    let response = client.get("/api/v1/network/2").send().await;
+    let clone: serde_json::Value = response.json().await;
+    assert_eq!(clone["name"], "berlin");
+    assert_eq!(clone["address"], json!(["10.2.1.1/24"]));
+    assert_eq!(clone["endpoint"], "10.2.1.1");
+    for setting in [
+        "port",
+        "dns",
+        "allowed_ips",
+        "keepalive_interval",
+        "allowed_groups",
+        "location_mfa_mode",
+        "psk_rotation_interval",
+        "dns_search_domains",
+        "device_approval_required",
+    ] {
+        assert_eq!(clone[setting], source[setting], "{setting} not copied");
+    }
+    assert_eq!(
+        clone["group_pools"][0]["address_pool"],
+        json!(["10.2.1.64/26"])
+    );
+
+    // device keeps its address offset and reservation
+    let response = client.get("/api/v1/network/2/device/1/ip").send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let reservation: serde_json::Value = response.json().await;
+    assert_eq!(
+        reservation,
+        json!({"ips": ["10.2.1.100"], "reserved": true})
+    );
+}
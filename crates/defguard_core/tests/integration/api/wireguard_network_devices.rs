@@ -7,7 +7,10 @@ use defguard_core::{
 };
 use ipnetwork::IpNetwork;
 use matches::assert_matches;
-use reqwest::StatusCode;
+use reqwest::{
+    StatusCode,
+    multipart::{Form, Part},
+};
 use serde::Deserialize;
 use serde_json::{Value, json};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
@@ -387,3 +390,104 @@ async fn test_device_ip_validation(_: PgPoolOptions, options: PgConnectOptions)
         ]
     )
 }
+
+#[sqlx::test]
+async fn test_import_network_devices(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, client_state) = make_test_client(pool).await;
+    let mut wg_rx = client_state.wireguard_rx;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = &client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::NetworkCreated(..));
+
+    // invalid rows are reported and nothing is created
+    let response = client
+        .post("/api/v1/device/network/import")
+        .json(&json!({
+            "location_id": 1,
+            "devices": [
+                {"name": "sensor1", "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU="},
+                {"name": "sensor2", "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU="},
+                {"name": "sensor3", "wireguard_pubkey": "invalid"},
+                {"name": "sensor4", "wireguard_pubkey": "2LYRr2HgSSpGCdXKDDAlcFe0Uuc6RR8TFgSquNc9VAE=", "assigned_ips": ["10.2.1.5"]},
+                {"name": "sensor5", "wireguard_pubkey": "sIhx53MsX+iLk83sssybHrD7M+5m+CmpLzWL/zo8C38=", "owner": "nobody"},
+            ],
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let errors: Value = response.json().await;
+    let rows: Vec<u64> = errors["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["row"].as_u64().unwrap())
+        .collect();
+    assert_eq!(rows, [2, 3, 4, 5]);
+    assert!(
+        Device::all(&client_state.pool)
+            .await
+            .unwrap()
+            .iter()
+            .all(|device| !device.name.starts_with("sensor"))
+    );
+    assert!(wg_rx.try_recv().is_err());
+
+    // import devices with static and automatically assigned addresses
+    let response = client
+        .post("/api/v1/device/network/import")
+        .json(&json!({
+            "location_id": 1,
+            "devices": [
+                {"name": "sensor1", "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU="},
+                {"name": "sensor2", "wireguard_pubkey": "2LYRr2HgSSpGCdXKDDAlcFe0Uuc6RR8TFgSquNc9VAE=", "assigned_ips": ["10.1.1.2"], "owner": "hpotter"},
+            ],
+        }))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let imported: Value = response.json().await;
+    let devices = imported["devices"].as_array().unwrap();
+    assert_eq!(devices[0]["name"], "sensor1");
+    assert_ne!(devices[0]["assigned_ips"], json!(["10.1.1.2"]));
+    assert_eq!(devices[1]["assigned_ips"], json!(["10.1.1.2"]));
+    assert_eq!(devices[1]["added_by"], "hpotter");
+    for _ in 0..2 {
+        assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::DeviceCreated(..));
+    }
+
+    // import from CSV file
+    let csv = "name,wireguard_pubkey,assigned_ips,description\n\
+        camera,sIhx53MsX+iLk83sssybHrD7M+5m+CmpLzWL/zo8C38=,10.1.1.20,\"Lobby, main entrance\"\n";
+    let form = Form::new().text("location_id", "1").part(
+        "file",
+        Part::text(csv)
+            .file_name("devices.csv")
+            .mime_str("text/csv")
+            .unwrap(),
+    );
+    let response = client
+        .post("/api/v1/device/network/import/upload")
+        .multipart(form)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let imported: Value = response.json().await;
+    assert_eq!(imported["devices"][0]["name"], "camera");
+    assert_eq!(imported["devices"][0]["assigned_ips"], json!(["10.1.1.20"]));
+    assert_eq!(
+        imported["devices"][0]["description"],
+        "Lobby, main entrance"
+    );
+    assert_matches!(wg_rx.try_recv().unwrap(), GatewayEvent::DeviceCreated(..));
+}
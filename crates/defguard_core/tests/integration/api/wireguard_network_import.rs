@@ -14,7 +14,10 @@ use defguard_core::{
     handlers::{Auth, wireguard::ImportedNetworkData},
 };
 use matches::assert_matches;
-use reqwest::StatusCode;
+use reqwest::{
+    StatusCode,
+    multipart::{Form, Part},
+};
 use serde_json::json;
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 use tokio::sync::broadcast::error::TryRecvError;
@@ -410,3 +413,100 @@ async fn test_config_import_nonadmin(_: PgPoolOptions, options: PgConnectOptions
         .await;
     assert_eq!(response.status(), StatusCode::FORBIDDEN);
 }
+
+#[sqlx::test]
+async fn test_config_import_upload(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let wg_config = "
+        [Interface]
+        PrivateKey = GAA2X3DW0WakGVx+DsGjhDpTgg50s1MlmrLf24Psrlg=
+        Address = 10.0.0.1/24
+        ListenPort = 55055
+        DNS = 10.0.0.2
+
+        [Peer]
+        PublicKey = 2LYRr2HgSSpGCdXKDDAlcFe0Uuc6RR8TFgSquNc9VAE=
+        AllowedIPs = 10.0.0.10/24
+        PersistentKeepalive = 300
+    ";
+    let (client, _) = make_test_client(pool).await;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = &client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // missing config file
+    let form = Form::new()
+        .text("name", "network")
+        .text("endpoint", "192.168.1.1");
+    let response = client
+        .post("/api/v1/network/import/upload")
+        .multipart(form)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // import network from uploaded file
+    let form = Form::new()
+        .text("name", "network")
+        .text("endpoint", "192.168.1.1")
+        .part(
+            "config",
+            Part::text(wg_config)
+                .file_name("wg0.conf")
+                .mime_str("text/plain")
+                .unwrap(),
+        );
+    let response = client
+        .post("/api/v1/network/import/upload")
+        .multipart(form)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response: ImportedNetworkData = response.json().await;
+    assert_eq!(response.network.name, "network");
+    assert_eq!(response.network.endpoint, "192.168.1.1");
+    assert_eq!(response.devices.len(), 1);
+}
+
+#[sqlx::test]
+async fn test_body_size_limits(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let (client, client_state) = make_test_client(pool).await;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = &client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // body above the regular API limit is rejected
+    let oversized = "a".repeat(client_state.config.http_body_limit + 1);
+    let response = client
+        .post("/api/v1/group")
+        .json(&json!({"name": oversized, "members": []}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // import endpoints accept bodies above the regular API limit
+    let response = client
+        .post("/api/v1/network/import")
+        .json(&json!({"name": "network", "endpoint": "192.168.1.1", "config": oversized, "allowed_groups": []}))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // the import limit applies to all uploaded fields together
+    let half = "a".repeat(client_state.config.http_import_body_limit / 2 + 1);
+    let form = Form::new()
+        .text("name", "network")
+        .text("endpoint", "192.168.1.1")
+        .text("allowed_groups", half.clone())
+        .text("allowed_groups", half);
+    let response = client
+        .post("/api/v1/network/import/upload")
+        .multipart(form)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
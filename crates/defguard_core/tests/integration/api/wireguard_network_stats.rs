@@ -1,4 +1,4 @@
-use chrono::{Datelike, Duration, NaiveDate, SubsecRound, Timelike, Utc};
+use chrono::{Datelike, Duration, DurationRound, NaiveDate, SubsecRound, Timelike, Utc};
 use defguard_common::db::{Id, NoId};
 use defguard_core::{
     db::models::{
@@ -7,7 +7,7 @@ use defguard_core::{
             WireguardDeviceStatsRow, WireguardDeviceTransferRow, WireguardNetworkStats,
             WireguardUserStatsRow,
         },
-        wireguard_peer_stats::WireguardPeerStats,
+        wireguard_peer_stats::{AggregatedPeerStats, WireguardPeerStats},
     },
     handlers::Auth,
 };
@@ -288,3 +288,126 @@ async fn test_stats(_: PgPoolOptions, options: PgConnectOptions) {
             .sum::<i64>()
     );
 }
+
+#[sqlx::test]
+async fn test_aggregated_stats(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+
+    let (client, client_state) = make_test_client(pool).await;
+    let pool = client_state.pool;
+
+    let auth = Auth::new("admin", "pass123");
+    let response = &client.post("/api/v1/auth").json(&auth).send().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .post("/api/v1/network")
+        .json(&make_network())
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let device = json!({
+        "name": "device-1",
+        "wireguard_pubkey": "LQKsT6/3HWKuJmMulH63R8iK+5sI8FyYEL6WDIi6lQU=",
+    });
+    let response = client
+        .post("/api/v1/device/admin")
+        .json(&device)
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // nothing to roll up yet
+    assert_eq!(WireguardPeerStats::rollup_stats(&pool).await.unwrap(), None);
+
+    // insert 3 full hours of samples
+    let current_hour = Utc::now()
+        .duration_trunc(Duration::hours(1))
+        .unwrap()
+        .naive_utc();
+    let first_hour = current_hour - Duration::hours(3);
+    for i in 0..180 {
+        WireguardPeerStats {
+            id: NoId,
+            device_id: 1,
+            collected_at: first_hour + Duration::minutes(i),
+            network: 1,
+            endpoint: Some("11.22.33.44".into()),
+            upload: i * 10,
+            download: i * 20,
+            latest_handshake: first_hour + Duration::minutes(i),
+            allowed_ips: Some("10.1.1.2".into()),
+        }
+        .save(&pool)
+        .await
+        .unwrap();
+    }
+
+    assert_eq!(
+        WireguardPeerStats::rollup_stats(&pool).await.unwrap(),
+        Some(current_hour)
+    );
+    // rolling up again doesn't change anything
+    assert_eq!(
+        WireguardPeerStats::rollup_stats(&pool).await.unwrap(),
+        Some(current_hour)
+    );
+
+    let response = client
+        .get(format!(
+            "/api/v1/network/1/stats/aggregated?resolution=hour&from={}",
+            (first_hour - Duration::hours(1)).format(DATE_FORMAT),
+        ))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let stats: Vec<AggregatedPeerStats> = response.json().await;
+    assert_eq!(
+        stats
+            .iter()
+            .map(|stats| (stats.period_start, stats.upload, stats.download))
+            .collect::<Vec<_>>(),
+        [
+            (first_hour, 590, 1180),
+            (first_hour + Duration::hours(1), 600, 1200),
+            (first_hour + Duration::hours(2), 600, 1200),
+        ]
+    );
+    assert_eq!(
+        stats[2].latest_handshake,
+        Some(first_hour + Duration::minutes(179))
+    );
+
+    // daily totals, samples may span two days
+    let response = client
+        .get(format!(
+            "/api/v1/network/1/stats/aggregated?resolution=day&device_id=1&from={}",
+            (first_hour - Duration::days(1)).format(DATE_FORMAT),
+        ))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let stats: Vec<AggregatedPeerStats> = response.json().await;
+    assert!(!stats.is_empty());
+    assert_eq!(stats.iter().map(|stats| stats.upload).sum::<i64>(), 1790);
+    assert_eq!(stats.iter().map(|stats| stats.download).sum::<i64>(), 3580);
+
+    // other devices have no stats
+    let response = client
+        .get("/api/v1/network/1/stats/aggregated?device_id=2")
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let stats: Vec<AggregatedPeerStats> = response.json().await;
+    assert!(stats.is_empty());
+
+    // invalid range
+    let response = client
+        .get(format!(
+            "/api/v1/network/1/stats/aggregated?from={}&to={}",
+            current_hour.format(DATE_FORMAT),
+            first_hour.format(DATE_FORMAT),
+        ))
+        .send()
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -33,6 +33,7 @@ pub mod mock_gateway;
 pub struct TestGrpcServer {
     grpc_server_task_handle: JoinHandle<()>,
     pub grpc_event_rx: UnboundedReceiver<GrpcEvent>,
+    pub app_event_rx: UnboundedReceiver<AppEvent>,
     wireguard_tx: Sender<GatewayEvent>,
     gateway_state: Arc<Mutex<GatewayMap>>,
     client_state: Arc<Mutex<ClientMap>>,
@@ -45,6 +46,7 @@ impl TestGrpcServer {
         server_stream: DuplexStream,
         grpc_router: Router,
         grpc_event_rx: UnboundedReceiver<GrpcEvent>,
+        app_event_rx: UnboundedReceiver<AppEvent>,
         wireguard_tx: Sender<GatewayEvent>,
         gateway_state: Arc<Mutex<GatewayMap>>,
         client_state: Arc<Mutex<ClientMap>>,
@@ -62,6 +64,7 @@ impl TestGrpcServer {
         Self {
             grpc_server_task_handle,
             grpc_event_rx,
+            app_event_rx,
             wireguard_tx,
             gateway_state,
             client_state,
@@ -124,7 +127,7 @@ pub(crate) async fn make_grpc_test_server(pool: &PgPool) -> TestGrpcServer {
 
     // setup helper structs
     let (grpc_event_tx, grpc_event_rx) = unbounded_channel::<GrpcEvent>();
-    let (app_event_tx, _app_event_rx) = unbounded_channel::<AppEvent>();
+    let (app_event_tx, app_event_rx) = unbounded_channel::<AppEvent>();
     let worker_state = Arc::new(Mutex::new(WorkerState::new(app_event_tx.clone())));
     let (wg_tx, _wg_rx) = broadcast::channel::<GatewayEvent>(16);
     let (mail_tx, _mail_rx) = unbounded_channel::<Mail>();
@@ -160,9 +163,11 @@ pub(crate) async fn make_grpc_test_server(pool: &PgPool) -> TestGrpcServer {
         gateway_state.clone(),
         client_state.clone(),
         wg_tx.clone(),
+        wg_tx.clone(),
         mail_tx,
         failed_logins,
         grpc_event_tx,
+        app_event_tx,
         Default::default(),
     )
     .await
@@ -172,6 +177,7 @@ pub(crate) async fn make_grpc_test_server(pool: &PgPool) -> TestGrpcServer {
         server_stream,
         grpc_router,
         grpc_event_rx,
+        app_event_rx,
         wg_tx,
         gateway_state,
         client_state,
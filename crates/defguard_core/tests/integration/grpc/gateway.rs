@@ -8,16 +8,17 @@ use claims::{assert_err_eq, assert_matches};
 use defguard_common::db::{Id, NoId, setup_pool};
 use defguard_core::{
     db::{
-        Device, User, WireguardNetwork,
+        AppEvent, Device, GatewayEvent, Group, User, WireguardNetwork,
         models::{
             device::DeviceType,
+            gateway_token::GatewayToken,
             wireguard::{LocationMfaMode, ServiceLocationMode},
             wireguard_peer_stats::WireguardPeerStats,
         },
     },
     enterprise::{license::set_cached_license, limits::update_counts},
     events::GrpcEvent,
-    grpc::MIN_GATEWAY_VERSION,
+    grpc::{MIN_GATEWAY_VERSION, gateway::state::GatewayConnectionEventKind},
 };
 use defguard_proto::{
     enterprise::firewall::FirewallPolicy,
@@ -27,6 +28,7 @@ use semver::Version;
 use sqlx::{
     PgPool,
     postgres::{PgConnectOptions, PgPoolOptions},
+    query, query_scalar,
 };
 use tokio::{sync::mpsc::error::TryRecvError, time::sleep};
 use tonic::Code;
@@ -129,6 +131,88 @@ async fn test_gateway_authorization(_: PgPoolOptions, options: PgConnectOptions)
     assert!(response.is_ok());
 }
 
+#[sqlx::test]
+async fn test_gateway_tokens(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let (test_server, _gateway, test_location, _test_user) = setup_test_server(pool.clone()).await;
+
+    // redundant gateways use separate tokens
+    let primary_token = GatewayToken::new(test_location.id, "primary".into())
+        .save(&pool)
+        .await
+        .unwrap();
+    let backup_token = GatewayToken::new(test_location.id, "backup".into())
+        .save(&pool)
+        .await
+        .unwrap();
+    let mut primary_gateway = MockGateway::new(
+        test_server.client_channel.clone(),
+        MIN_GATEWAY_VERSION,
+        Some(primary_token.to_jwt().unwrap()),
+        Some("primary-gateway".into()),
+    )
+    .await;
+    let mut backup_gateway = MockGateway::new(
+        test_server.client_channel.clone(),
+        MIN_GATEWAY_VERSION,
+        Some(backup_token.to_jwt().unwrap()),
+        Some("backup-gateway".into()),
+    )
+    .await;
+    for gateway in [&mut primary_gateway, &mut backup_gateway] {
+        assert!(gateway.get_gateway_config().await.is_ok());
+        gateway.connect_to_updates_stream().await;
+    }
+    {
+        let gateway_map = test_server.get_gateway_map();
+        let location_gateways = gateway_map.get_network_gateway_status(test_location.id);
+        let primary_state = location_gateways
+            .iter()
+            .find(|state| state.hostname == "primary-gateway")
+            .unwrap();
+        assert!(primary_state.connected);
+        assert_eq!(primary_state.token_id, Some(primary_token.id));
+        assert_eq!(primary_state.token_name.as_deref(), Some("primary"));
+    }
+    let token = GatewayToken::find_for_location(&pool, test_location.id, primary_token.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(token.last_hostname.as_deref(), Some("primary-gateway"));
+    assert!(token.last_connected_at.is_some());
+
+    // revoking a token disconnects only the gateway using it
+    primary_token.clone().delete(&pool).await.unwrap();
+    test_server.send_wireguard_event(GatewayEvent::GatewayTokenRevoked(
+        test_location.id,
+        primary_token.id,
+    ));
+    sleep(Duration::from_millis(100)).await;
+    {
+        let gateway_map = test_server.get_gateway_map();
+        let location_gateways = gateway_map.get_network_gateway_status(test_location.id);
+        for state in location_gateways {
+            assert_eq!(state.connected, state.hostname == "backup-gateway");
+        }
+    }
+    let status = primary_gateway.get_gateway_config().await.unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+    assert!(backup_gateway.get_gateway_config().await.is_ok());
+
+    // tokens of other locations are rejected
+    let mut other_token = backup_token.clone();
+    other_token.location_id = test_location.id + 1;
+    let mut other_gateway = MockGateway::new(
+        test_server.client_channel.clone(),
+        MIN_GATEWAY_VERSION,
+        Some(other_token.to_jwt().unwrap()),
+        Some("other-gateway".into()),
+    )
+    .await;
+    let status = other_gateway.get_gateway_config().await.unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+}
+
 #[sqlx::test]
 async fn test_gateway_hostname_is_required(_: PgPoolOptions, options: PgConnectOptions) {
     let pool = setup_pool(options).await;
@@ -188,6 +272,7 @@ async fn test_gateway_status(_: PgPoolOptions, options: PgConnectOptions) {
         assert!(gateway_state.connected_at.is_none());
         assert!(gateway_state.disconnected_at.is_none());
         assert_eq!(gateway_state.hostname, gateway.hostname());
+        assert!(gateway_state.history.is_empty());
     }
 
     // gateway connects to updates stream
@@ -202,6 +287,11 @@ async fn test_gateway_status(_: PgPoolOptions, options: PgConnectOptions) {
         assert!(gateway_state.connected_at.is_some());
         assert!(gateway_state.disconnected_at.is_none());
         assert_eq!(gateway_state.hostname, gateway.hostname());
+        assert_eq!(gateway_state.history.len(), 1);
+        assert_eq!(
+            gateway_state.history[0].kind,
+            GatewayConnectionEventKind::Connected
+        );
     }
 
     // gateway disconnect from updates stream
@@ -219,6 +309,11 @@ async fn test_gateway_status(_: PgPoolOptions, options: PgConnectOptions) {
         assert!(gateway_state.connected_at.is_some());
         assert!(gateway_state.disconnected_at.is_some());
         assert_eq!(gateway_state.hostname, gateway.hostname());
+        assert_eq!(gateway_state.history.len(), 2);
+        assert_eq!(
+            gateway_state.history[1].kind,
+            GatewayConnectionEventKind::Disconnected
+        );
     }
 }
 
@@ -297,6 +392,113 @@ async fn test_vpn_client_connected(_: PgPoolOptions, options: PgConnectOptions)
             device
         } if ((location.id == test_location.id) & (device.id == test_device.id))
     );
+
+    // webhooks are triggered as well
+    let app_event = test_server
+        .app_event_rx
+        .try_recv()
+        .expect("failed to receive webhook event");
+    assert_matches!(
+        app_event,
+        AppEvent::ClientConnected(data)
+            if ((data.location_id == test_location.id) & (data.device_id == test_device.id))
+    );
+}
+
+#[sqlx::test]
+async fn test_vpn_client_session_limit(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let (mut test_server, mut gateway, mut test_location, test_user) =
+        setup_test_server(pool.clone()).await;
+    test_location.location_mfa_mode = LocationMfaMode::Internal;
+    test_location.save(&pool).await.unwrap();
+
+    // members of the group can have one device connected at a time
+    let group = Group::new("limited").save(&pool).await.unwrap();
+    test_user.add_to_group(&pool, &group).await.unwrap();
+    query("UPDATE \"group\" SET max_sessions = 1 WHERE name = 'limited'")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // add two user devices authorized in the location
+    let pubkeys = [
+        "wYOt6ImBaQ3BEMQ3Xf5P5fTnbqwOvjcqYkkSBt+1xOg=",
+        "hNuapt7lOxF93KUqZGUY00oKJxH8LYwwsUVB1uUa0y4=",
+    ];
+    let mut devices = Vec::new();
+    for (i, pubkey) in pubkeys.iter().enumerate() {
+        let device = Device::new(
+            format!("device {i}"),
+            (*pubkey).into(),
+            test_user.id,
+            DeviceType::User,
+            None,
+            true,
+        )
+        .save(&pool)
+        .await
+        .unwrap();
+        query(
+            "INSERT INTO wireguard_network_device \
+            (wireguard_network_id, device_id, wireguard_ips, is_authorized, authorized_at) \
+            VALUES ($1, $2, ARRAY[$3::inet], true, NOW())",
+        )
+        .bind(test_location.id)
+        .bind(device.id)
+        .bind(format!("10.0.0.{}", i + 2))
+        .execute(&pool)
+        .await
+        .unwrap();
+        devices.push(device);
+    }
+
+    // connect both devices
+    let stats_tx = gateway.setup_stats_update_stream().await;
+    for (i, pubkey) in pubkeys.iter().enumerate() {
+        stats_tx
+            .send(StatsUpdate {
+                id: i as u64 + 1,
+                payload: Some(Payload::PeerStats(PeerStats {
+                    public_key: (*pubkey).into(),
+                    endpoint: format!("1.2.3.4:{}", 1000 + i),
+                    latest_handshake: Utc::now().timestamp() as u64,
+                    ..Default::default()
+                })),
+            })
+            .expect("failed to send stats update");
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    for device in &devices {
+        let grpc_event = test_server
+            .grpc_event_rx
+            .try_recv()
+            .expect("failed to receive gRPC event");
+        assert_matches!(
+            grpc_event,
+            GrpcEvent::ClientConnected { device: connected, .. } if connected.id == device.id
+        );
+    }
+
+    // the older session has been disconnected
+    let grpc_event = test_server
+        .grpc_event_rx
+        .try_recv()
+        .expect("failed to receive gRPC event");
+    assert_matches!(
+        grpc_event,
+        GrpcEvent::ClientSessionLimitExceeded { device, limit: 1, .. } if device.id == devices[0].id
+    );
+    let authorized: Vec<bool> = query_scalar(
+        "SELECT is_authorized FROM wireguard_network_device \
+        WHERE wireguard_network_id = $1 ORDER BY device_id",
+    )
+    .bind(test_location.id)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(authorized, [false, true]);
 }
 
 #[sqlx::test]
@@ -382,6 +584,122 @@ async fn test_vpn_client_disconnected(_: PgPoolOptions, options: PgConnectOption
     );
 }
 
+#[sqlx::test]
+async fn test_vpn_client_heartbeat(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let (mut test_server, mut gateway, test_location, test_user) =
+        setup_test_server(pool.clone()).await;
+
+    // add user device
+    let device_pubkey = "wYOt6ImBaQ3BEMQ3Xf5P5fTnbqwOvjcqYkkSBt+1xOg=";
+    let test_device = Device::new(
+        "test device".into(),
+        device_pubkey.into(),
+        test_user.id,
+        DeviceType::User,
+        None,
+        true,
+    )
+    .save(&pool)
+    .await
+    .unwrap();
+
+    // insert device into client map with an old handshake and a fresh heartbeat
+    {
+        let mut client_map = test_server.get_client_map();
+        let now = Utc::now().naive_utc();
+        let stats = WireguardPeerStats {
+            id: NoId,
+            device_id: test_device.id,
+            collected_at: now,
+            network: test_location.id,
+            endpoint: None,
+            upload: 0,
+            download: 0,
+            latest_handshake: now.checked_sub_days(Days::new(1)).unwrap(),
+            allowed_ips: None,
+        };
+        client_map
+            .connect_vpn_client(
+                test_location.id,
+                &gateway.hostname(),
+                device_pubkey,
+                &test_device,
+                &test_user,
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080),
+                &stats,
+            )
+            .expect("failed to insert connected client");
+        client_map
+            .record_heartbeat(test_location.id, device_pubkey, true)
+            .expect("failed to record heartbeat");
+    }
+
+    // heartbeats are only accepted from connected clients
+    assert!(
+        test_server
+            .get_client_map()
+            .record_heartbeat(test_location.id, "unknown pubkey", true)
+            .is_err()
+    );
+
+    // connect stats stream
+    let stats_tx = gateway.setup_stats_update_stream().await;
+    let stats_update = StatsUpdate {
+        id: 1,
+        payload: Some(Payload::PeerStats(PeerStats {
+            public_key: device_pubkey.into(),
+            endpoint: "1.2.3.4:1234".into(),
+            latest_handshake: 0,
+            ..Default::default()
+        })),
+    };
+
+    // idle client which is still sending heartbeats is not disconnected
+    stats_tx
+        .send(stats_update.clone())
+        .expect("failed to send stats update");
+    sleep(Duration::from_millis(100)).await;
+    assert_err_eq!(test_server.grpc_event_rx.try_recv(), TryRecvError::Empty);
+
+    // client reports it has disconnected
+    let heartbeat_at = {
+        let mut client_map = test_server.get_client_map();
+        client_map
+            .record_heartbeat(test_location.id, device_pubkey, false)
+            .expect("failed to record heartbeat");
+        client_map
+            .get_vpn_client(test_location.id, device_pubkey)
+            .unwrap()
+            .heartbeat
+            .unwrap()
+            .received_at
+    };
+
+    // session ends at the time of the latest heartbeat
+    stats_tx
+        .send(StatsUpdate {
+            id: 2,
+            ..stats_update
+        })
+        .expect("failed to send stats update");
+    sleep(Duration::from_millis(100)).await;
+    let grpc_event = test_server
+        .grpc_event_rx
+        .try_recv()
+        .expect("failed to receive gRPC event");
+    assert_matches!(
+        grpc_event,
+        GrpcEvent::ClientDisconnected {
+            context,
+            location,
+            device
+        } if location.id == test_location.id
+            && device.id == test_device.id
+            && context.timestamp == heartbeat_at
+    );
+}
+
 #[sqlx::test]
 async fn test_gateway_update_routing(_: PgPoolOptions, options: PgConnectOptions) {
     let pool = setup_pool(options).await;
@@ -672,3 +990,33 @@ async fn test_device_pubkey_change(_: PgPoolOptions, options: PgConnectOptions)
         } if ((location.id == test_location.id) & (device.id == test_device.id))
     );
 }
+
+#[sqlx::test]
+async fn test_gateway_update_stream_lagging(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let (test_server, mut gateway, test_location, _test_user) = setup_test_server(pool).await;
+
+    let _config = gateway.get_gateway_config().await;
+    gateway.connect_to_updates_stream().await;
+
+    // send more events than the broadcast channel can hold while the gateway isn't reading
+    for _ in 0..40 {
+        test_server.send_wireguard_event(GatewayEvent::FirewallDisabled(test_location.id));
+    }
+
+    // the stream isn't closed, the gateway gets full network configuration instead
+    let mut full_update = None;
+    while let Some(update) = gateway.receive_next_update().await {
+        if let Some(update::Update::Network(config)) = update.update {
+            full_update = Some((update.update_type, config));
+        }
+    }
+    let (update_type, config) = full_update.expect("full network configuration not received");
+    assert_eq!(update_type, 1);
+    assert_eq!(config.name, test_location.name);
+    assert_eq!(config.port, test_location.port as u32);
+
+    // the stream keeps working after resynchronization
+    test_server.send_wireguard_event(GatewayEvent::FirewallDisabled(test_location.id));
+    assert!(gateway.receive_next_update().await.is_some());
+}
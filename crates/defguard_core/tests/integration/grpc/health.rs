@@ -0,0 +1,38 @@
+use defguard_common::db::setup_pool;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use tonic_health::pb::{
+    HealthCheckRequest, health_check_response::ServingStatus, health_client::HealthClient,
+};
+
+use crate::grpc::common::make_grpc_test_server;
+
+#[sqlx::test]
+async fn test_health_check(_: PgPoolOptions, options: PgConnectOptions) {
+    let pool = setup_pool(options).await;
+    let test_server = make_grpc_test_server(&pool).await;
+    let mut client = HealthClient::new(test_server.client_channel.clone());
+
+    for service in [
+        "auth.AuthService",
+        "gateway.GatewayService",
+        "worker.WorkerService",
+    ] {
+        let response = client
+            .check(HealthCheckRequest {
+                service: service.into(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.status(), ServingStatus::Serving);
+    }
+
+    // unknown services are reported as not found
+    let status = client
+        .check(HealthCheckRequest {
+            service: "unknown.Service".into(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+}
@@ -8,7 +8,9 @@
 //! and returns an optional description string. Some events may not require additional
 //! description beyond their event type name, in which case `None` is returned.
 
-use crate::message::{DefguardEvent, EnrollmentEvent, VpnEvent};
+use defguard_common::csv::AsCsv;
+
+use crate::message::{CustomEvent, DefguardEvent, EnrollmentEvent, LdapEvent, VpnEvent};
 
 #[must_use]
 pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
@@ -17,6 +19,40 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
         DefguardEvent::UserLoginFailed { message } => {
             Some(format!("User login failed with: {message}"))
         }
+        DefguardEvent::BreakGlassLogin => {
+            Some("Logged in using the break-glass admin account".to_string())
+        }
+        DefguardEvent::AdminAccessDenied {
+            method,
+            path,
+            reason,
+        } => Some(format!("Denied access to {method} {path}: {reason}")),
+        DefguardEvent::UserLockedOut {
+            failed_attempts,
+            locked_until,
+        } => Some(format!(
+            "User locked out until {locked_until} after {failed_attempts} failed logins"
+        )),
+        DefguardEvent::LoginIpLockedOut {
+            ip_address,
+            failed_attempts,
+            locked_until,
+        } => Some(format!(
+            "Address {ip_address} locked out until {locked_until} after {failed_attempts} failed \
+            logins"
+        )),
+        DefguardEvent::UserUnlocked { user } => Some(format!("Unlocked user {user}")),
+        DefguardEvent::LoginIpUnlocked { ip_address } => {
+            Some(format!("Unlocked address {ip_address}"))
+        }
+        DefguardEvent::RateLimitExceeded {
+            endpoint,
+            ip_address,
+            rejected_requests,
+        } => Some(format!(
+            "Rate limit of {endpoint} requests exceeded from {ip_address}, \
+            {rejected_requests} requests rejected"
+        )),
         DefguardEvent::UserMfaLogin { mfa_method } => {
             Some(format!("User logged in using {mfa_method}"))
         }
@@ -27,7 +63,12 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
             "User login using {mfa_method} failed with: {message}"
         )),
         DefguardEvent::UserLogout => None,
-        DefguardEvent::RecoveryCodeUsed => None,
+        DefguardEvent::RecoveryCodeUsed { remaining } => Some(format!(
+            "Logged in using a recovery code, {remaining} recovery codes left"
+        )),
+        DefguardEvent::RecoveryCodesRegenerated { count } => {
+            Some(format!("Generated {count} new recovery codes"))
+        }
         DefguardEvent::PasswordChanged => None,
         DefguardEvent::MfaDisabled => Some("Disabled own MFA".to_string()),
         DefguardEvent::UserMfaDisabled { user } => Some(format!("Disabled MFA for user {user}")),
@@ -35,12 +76,47 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
         DefguardEvent::MfaTotpDisabled => Some("User disabled TOTP for MFA".to_string()),
         DefguardEvent::MfaEmailEnabled => Some("User configured email for MFA".to_string()),
         DefguardEvent::MfaEmailDisabled => Some("User disabled email for MFA".to_string()),
+        DefguardEvent::MfaSmsEnabled => Some("User configured SMS for MFA".to_string()),
+        DefguardEvent::MfaSmsDisabled => Some("User disabled SMS for MFA".to_string()),
         DefguardEvent::PasswordChangedByAdmin { user } => {
             Some(format!("Password for user {user} was changed by an admin"))
         }
         DefguardEvent::PasswordReset { user } => {
             Some(format!("Password for user {user} was reset"))
         }
+        DefguardEvent::UserSessionRevoked {
+            user,
+            ip_address,
+            device_info,
+        } => Some(format!(
+            "Revoked session of user {user} started from {ip_address} on {}",
+            device_info.as_deref().unwrap_or("unknown device")
+        )),
+        DefguardEvent::UserSessionsRevoked { user, count } => {
+            Some(format!("Revoked {count} sessions of user {user}"))
+        }
+        DefguardEvent::ImpersonationStarted {
+            admin,
+            user,
+            mode,
+            expires,
+        } => Some(format!(
+            "Admin {admin} started impersonating user {user} ({mode}) until {expires}"
+        )),
+        DefguardEvent::ImpersonationEnded {
+            admin,
+            user,
+            mode: _,
+            expired,
+        } => {
+            if *expired {
+                Some(format!(
+                    "Impersonation of user {user} by admin {admin} expired"
+                ))
+            } else {
+                Some(format!("Admin {admin} stopped impersonating user {user}"))
+            }
+        }
         DefguardEvent::MfaSecurityKeyAdded { key } => {
             Some(format!("Added MFA security key {}", key.name))
         }
@@ -60,6 +136,9 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
             ))
         }
         DefguardEvent::UserRemoved { user } => Some(format!("Removed user {user}")),
+        DefguardEvent::UserDisabled { user } => Some(format!(
+            "Disabled user {user} at the scheduled deactivation time"
+        )),
         DefguardEvent::UserModified { before, after } => {
             let mut description = format!("Modified user {after}");
 
@@ -81,6 +160,26 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
         } => Some(format!(
             "User groups modified! User:{user} Before: {before:?} After {after:?}"
         )),
+        DefguardEvent::UserProvisioned {
+            user,
+            source,
+            groups,
+            pending_approval,
+        } => {
+            let mut description =
+                format!("Created account of user {user} logging in through {source}");
+            if !groups.is_empty() {
+                description = format!("{description}, joined groups {}", groups.join(", "));
+            }
+            if *pending_approval {
+                description = format!("{description}, awaiting approval");
+            }
+            Some(description)
+        }
+        DefguardEvent::AccountCreationRefused { source, identity } => Some(format!(
+            "Refused login of {identity} through {source}, the automatic account creation is \
+            disabled"
+        )),
         DefguardEvent::UserDeviceAdded { owner, device } => {
             Some(format!("Added device {device} for user {owner}"))
         }
@@ -92,6 +191,20 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
             before: _,
             after,
         } => Some(format!("Modified device {after} owned by user {owner}")),
+        DefguardEvent::DeviceApproved {
+            owner,
+            device,
+            location,
+        } => Some(format!(
+            "Approved device {device} owned by user {owner} in location {location}"
+        )),
+        DefguardEvent::DeviceRejected {
+            owner,
+            device,
+            location,
+        } => Some(format!(
+            "Rejected device {device} owned by user {owner} in location {location}"
+        )),
         DefguardEvent::NetworkDeviceAdded { device, location } => Some(format!(
             "Added network device {device} to location {location}"
         )),
@@ -105,6 +218,39 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
         } => Some(format!(
             "Modified network device {after} in location {location}"
         )),
+        DefguardEvent::DeviceAddressReserved {
+            device,
+            location,
+            before: _,
+            after,
+        } => Some(format!(
+            "Reserved addresses {} for device {device} in location {location}",
+            after.as_csv()
+        )),
+        DefguardEvent::DeviceAddressReleased {
+            device,
+            location,
+            addresses,
+        } => Some(format!(
+            "Released reserved addresses {} of device {device} in location {location}",
+            addresses.as_csv()
+        )),
+        DefguardEvent::DeviceProfileAdded { profile } => {
+            Some(format!("Added device profile {}", profile.name))
+        }
+        DefguardEvent::DeviceProfileModified { before: _, after } => {
+            Some(format!("Modified device profile {}", after.name))
+        }
+        DefguardEvent::DeviceProfileRemoved { profile } => {
+            Some(format!("Removed device profile {}", profile.name))
+        }
+        DefguardEvent::DeviceProfileAssigned { device, profile } => match profile {
+            Some(profile) => Some(format!(
+                "Assigned device profile {} to device {device}",
+                profile.name
+            )),
+            None => Some(format!("Removed device profile from device {device}")),
+        },
         DefguardEvent::ActivityLogStreamCreated { stream } => Some(format!(
             "Created {} activity log stream {}",
             stream.stream_type, stream.name
@@ -117,6 +263,14 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
             "Removed {} activity log stream {}",
             stream.stream_type, stream.name
         )),
+        DefguardEvent::ActivityLogPurged {
+            removed,
+            archived,
+            threshold,
+        } => Some(format!(
+            "{} {removed} activity log events older than {threshold}",
+            if *archived { "Archived" } else { "Removed" }
+        )),
         DefguardEvent::VpnLocationAdded { location } => {
             Some(format!("Added VPN location {location}"))
         }
@@ -126,8 +280,47 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
         DefguardEvent::VpnLocationModified { before: _, after } => {
             Some(format!("VPN location {after} was modified"))
         }
+        DefguardEvent::VpnLocationMaintenanceEnabled {
+            location,
+            message,
+            disconnected,
+        } => {
+            let mut description = format!("Enabled maintenance of VPN location {location}");
+            if let Some(message) = message {
+                description.push_str(&format!(": {message}"));
+            }
+            if *disconnected > 0 {
+                description.push_str(&format!(", disconnected {disconnected} devices"));
+            }
+            Some(description)
+        }
+        DefguardEvent::VpnLocationMaintenanceDisabled { location } => {
+            Some(format!("Disabled maintenance of VPN location {location}"))
+        }
+        DefguardEvent::GatewayTokenAdded { location, token } => Some(format!(
+            "Issued gateway token {token} for VPN location {location}"
+        )),
+        DefguardEvent::GatewayTokenRevoked { location, token } => Some(format!(
+            "Revoked gateway token {token} of VPN location {location}"
+        )),
         DefguardEvent::ApiTokenAdded { owner, token } => {
-            Some(format!("Added API token {} for user {owner}", token.name))
+            let mut description = format!("Added API token {} for user {owner}", token.name);
+            if let Some(location_id) = token.location_id {
+                description.push_str(&format!(" scoped to location {location_id}"));
+            }
+            if let Some(group_id) = token.group_id {
+                description.push_str(&format!(" bound to group {group_id}"));
+            }
+            if !token.scopes.is_empty() {
+                let scopes = token
+                    .scopes
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                description.push_str(&format!(" with scopes: {scopes}"));
+            }
+            Some(description)
         }
         DefguardEvent::ApiTokenRemoved { owner, token } => Some(format!(
             "Removed API token {} owned by user {owner}",
@@ -154,12 +347,33 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
             let state = if *enabled { "Enabled" } else { "Disabled" };
             Some(format!("{} OpenID application {}", state, app.name))
         }
+        DefguardEvent::ClientCredentialsSet {
+            app,
+            service_account,
+            scopes: _,
+        } => Some(format!(
+            "Enabled client credentials grant for OpenID application {} acting as {}",
+            app.name, service_account.username
+        )),
+        DefguardEvent::ClientCredentialsRemoved { app } => Some(format!(
+            "Disabled client credentials grant for OpenID application {}",
+            app.name
+        )),
         DefguardEvent::OpenIdProviderModified { provider } => {
             Some(format!("Modified OpenID provider {}", provider.name))
         }
         DefguardEvent::OpenIdProviderRemoved { provider } => {
             Some(format!("Removed OpenID provider {}", provider.name))
         }
+        DefguardEvent::SamlProviderModified { provider } => {
+            Some(format!("Modified SAML provider {}", provider.name))
+        }
+        DefguardEvent::SamlProviderRemoved { provider } => {
+            Some(format!("Removed SAML provider {}", provider.name))
+        }
+        DefguardEvent::ProvisioningPolicyModified { before: _, after } => {
+            Some(format!("Modified {} provisioning policy", after.source))
+        }
         DefguardEvent::SettingsUpdated {
             before: _,
             after: _,
@@ -171,6 +385,12 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
         DefguardEvent::SettingsDefaultBrandingRestored => {
             Some("Restored default branding settings".to_string())
         }
+        DefguardEvent::MailTemplateModified { kind } => {
+            Some(format!("Customized {kind} email template"))
+        }
+        DefguardEvent::MailTemplateReset { kind } => {
+            Some(format!("Restored built-in {kind} email template"))
+        }
         DefguardEvent::GroupsBulkAssigned { users, groups } => Some(format!(
             "Assigned {} users to {} groups",
             users.len(),
@@ -187,6 +407,10 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
         DefguardEvent::GroupMemberRemoved { group, user } => {
             Some(format!("Removed user {user} from group {}", group.name))
         }
+        DefguardEvent::GroupMembershipExpired { group, user } => Some(format!(
+            "Membership of user {user} in group {} expired",
+            group.name
+        )),
         DefguardEvent::GroupMembersModified {
             group,
             added,
@@ -205,6 +429,39 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
                 .join(", "),
             group.name
         )),
+        DefguardEvent::GroupQuotaModified { group, quota } => Some(format!(
+            "Set quota for group {}: {} users, {} devices, {} sessions per member",
+            group.name,
+            format_limit(quota.max_users),
+            format_limit(quota.max_devices),
+            format_limit(quota.max_sessions)
+        )),
+        DefguardEvent::GroupSyncAuthorityModified {
+            group,
+            sync_authority,
+        } => Some(match sync_authority {
+            Some(authority) => format!(
+                "Set sync authority of group {} to {}",
+                group.name,
+                format!("{authority:?}").to_lowercase()
+            ),
+            None => format!(
+                "Group {} now follows the global LDAP sync authority",
+                group.name
+            ),
+        }),
+        DefguardEvent::GroupParentModified { group, parent } => Some(match parent {
+            Some(parent) => format!("Nested group {} under group {}", group.name, parent.name),
+            None => format!("Made group {} a top-level group", group.name),
+        }),
+        DefguardEvent::GroupJoinRequestApproved { group, user } => Some(format!(
+            "Approved request of user {user} to join group {}",
+            group.name
+        )),
+        DefguardEvent::GroupJoinRequestRejected { group, user } => Some(format!(
+            "Rejected request of user {user} to join group {}",
+            group.name
+        )),
         DefguardEvent::WebHookAdded { webhook } => {
             Some(format!("Added webhook with URL {}", webhook.url))
         }
@@ -260,6 +517,11 @@ pub fn get_defguard_event_description(event: &DefguardEvent) -> Option<String> {
     }
 }
 
+/// Formats an optional quota limit, `None` meaning no limit.
+fn format_limit(limit: Option<i32>) -> String {
+    limit.map_or_else(|| "unlimited".to_string(), |limit| limit.to_string())
+}
+
 #[must_use]
 pub fn get_vpn_event_description(event: &VpnEvent) -> Option<String> {
     match event {
@@ -281,12 +543,54 @@ pub fn get_vpn_event_description(event: &VpnEvent) -> Option<String> {
         } => Some(format!(
             "Device {device} failed to connect to MFA location {location} using {method} with: {message}"
         )),
+        VpnEvent::MfaLockedOut {
+            location,
+            device,
+            method,
+            attempts,
+            locked_until,
+        } => Some(match locked_until {
+            Some(locked_until) => format!(
+                "Device {device} was locked out of MFA location {location} after {attempts} failed {method} attempts, user blocked until {locked_until}"
+            ),
+            None => format!(
+                "Device {device} was locked out of MFA location {location} after {attempts} failed {method} attempts"
+            ),
+        }),
+        VpnEvent::MfaTimeout {
+            location,
+            device,
+            method,
+        } => Some(format!(
+            "Device {device} didn't finish MFA login to location {location} using {method} in time"
+        )),
+        VpnEvent::MfaPostureFailed {
+            location,
+            device,
+            method,
+            violations,
+        } => Some(format!(
+            "Device {device} was denied access to MFA location {location} using {method} because it doesn't meet posture requirements: {}",
+            violations
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
         VpnEvent::ConnectedToLocation { location, device } => {
             Some(format!("Device {device} connected to location {location}"))
         }
         VpnEvent::DisconnectedFromLocation { location, device } => Some(format!(
             "Device {device} disconnected from location {location}"
         )),
+        VpnEvent::SessionLimitExceeded {
+            location,
+            device,
+            limit,
+        } => Some(format!(
+            "Device {device} was disconnected from location {location} because the limit of \
+            {limit} concurrent VPN sessions was exceeded"
+        )),
     }
 }
 
@@ -300,11 +604,50 @@ pub fn get_enrollment_event_description(event: &EnrollmentEvent) -> Option<Strin
         EnrollmentEvent::EnrollmentCompleted => {
             Some("User completed enrollment process".to_string())
         }
+        EnrollmentEvent::EnrollmentBlocked => {
+            Some("Enrollment blocked: request from outside of the allowed networks".to_string())
+        }
         EnrollmentEvent::PasswordResetRequested => None,
         EnrollmentEvent::PasswordResetStarted => None,
         EnrollmentEvent::PasswordResetCompleted => None,
+        EnrollmentEvent::PasswordResetBlocked => {
+            Some("Password reset blocked: request from outside of the allowed networks".to_string())
+        }
         EnrollmentEvent::TokenAdded { user } => {
             Some(format!("Added enrollment token for user {user}"))
         }
+        EnrollmentEvent::TokenRevoked { user } => {
+            Some(format!("Revoked enrollment token of user {user}"))
+        }
+    }
+}
+
+#[must_use]
+pub fn get_ldap_event_description(event: &LdapEvent) -> Option<String> {
+    match event {
+        LdapEvent::UserAdded { user } => Some(format!("Added user {user} from LDAP")),
+        LdapEvent::UserRemoved { user } => Some(format!("Removed user {user} missing from LDAP")),
+        LdapEvent::UserModified { after, .. } => {
+            Some(format!("Updated user {after} with attributes from LDAP"))
+        }
+        LdapEvent::GroupMemberAdded { group, user } => Some(format!(
+            "Added user {user} to group {} following LDAP membership",
+            group.name
+        )),
+        LdapEvent::GroupMemberRemoved { group, user } => Some(format!(
+            "Removed user {user} from group {} following LDAP membership",
+            group.name
+        )),
+    }
+}
+
+#[must_use]
+pub fn get_custom_event_description(event: &CustomEvent) -> Option<String> {
+    match &event.description {
+        Some(description) => Some(description.clone()),
+        None => Some(format!(
+            "Custom event {}.{} reported",
+            event.namespace, event.name
+        )),
     }
 }
@@ -1,30 +1,45 @@
+use std::time::Instant;
+
 use bytes::Bytes;
-use defguard_common::db::NoId;
+use defguard_common::{db::NoId, diagnostics::EVENT_LOGGER_QUEUE};
 use defguard_core::db::models::activity_log::{
     ActivityLogEvent, ActivityLogModule, EventType,
     metadata::{
-        ActivityLogStreamMetadata, ActivityLogStreamModifiedMetadata, ApiTokenMetadata,
+        AccountCreationRefusedMetadata, ActivityLogPurgedMetadata, ActivityLogStreamMetadata,
+        ActivityLogStreamModifiedMetadata, AdminAccessDeniedMetadata, ApiTokenMetadata,
         ApiTokenRenamedMetadata, AuthenticationKeyMetadata, AuthenticationKeyRenamedMetadata,
-        ClientConfigurationTokenMetadata, DeviceMetadata, DeviceModifiedMetadata,
-        EnrollmentDeviceAddedMetadata, EnrollmentTokenMetadata, GroupAssignedMetadata,
+        ClientConfigurationTokenMetadata, ClientCredentialsMetadata, CustomEventMetadata,
+        DeviceAddressReleasedMetadata, DeviceAddressReservedMetadata, DeviceApprovalMetadata,
+        DeviceMetadata, DeviceModifiedMetadata, DeviceProfileAssignedMetadata,
+        DeviceProfileMetadata, DeviceProfileModifiedMetadata, EnrollmentDeviceAddedMetadata,
+        EnrollmentTokenMetadata, GatewayTokenMetadata, GroupAssignedMetadata,
         GroupMembersModifiedMetadata, GroupMetadata, GroupModifiedMetadata,
-        GroupsBulkAssignedMetadata, LoginFailedMetadata, MfaLoginFailedMetadata, MfaLoginMetadata,
+        GroupParentModifiedMetadata, GroupQuotaModifiedMetadata,
+        GroupSyncAuthorityModifiedMetadata, GroupsBulkAssignedMetadata, ImpersonationEndedMetadata,
+        ImpersonationStartedMetadata, LoginFailedMetadata, LoginIpLockedOutMetadata,
+        LoginIpUnlockedMetadata, MailTemplateMetadata, MfaLoginFailedMetadata, MfaLoginMetadata,
         MfaSecurityKeyMetadata, NetworkDeviceMetadata, NetworkDeviceModifiedMetadata,
         OpenIdAppMetadata, OpenIdAppModifiedMetadata, OpenIdAppStateChangedMetadata,
         OpenIdProviderMetadata, PasswordChangedByAdminMetadata, PasswordResetMetadata,
-        SettingsUpdateMetadata, UserGroupsModifiedMetadata, UserMetadata, UserMfaDisabledMetadata,
-        UserModifiedMetadata, UserSnatBindingMetadata, UserSnatBindingModifiedMetadata,
-        VpnClientMetadata, VpnClientMfaFailedMetadata, VpnClientMfaMetadata, VpnLocationMetadata,
-        VpnLocationModifiedMetadata, WebHookMetadata, WebHookModifiedMetadata,
-        WebHookStateChangedMetadata,
+        ProvisioningPolicyModifiedMetadata, RateLimitExceededMetadata, RecoveryCodeUsedMetadata,
+        RecoveryCodesRegeneratedMetadata, SamlProviderMetadata, SettingsUpdateMetadata,
+        UserGroupsModifiedMetadata, UserLockedOutMetadata, UserMetadata, UserMfaDisabledMetadata,
+        UserModifiedMetadata, UserProvisionedMetadata, UserSessionRevokedMetadata,
+        UserSessionsRevokedMetadata, UserSnatBindingMetadata, UserSnatBindingModifiedMetadata,
+        VpnClientMetadata, VpnClientMfaFailedMetadata, VpnClientMfaLockedOutMetadata,
+        VpnClientMfaMetadata, VpnClientMfaPostureFailedMetadata, VpnClientSessionLimitMetadata,
+        VpnLocationMaintenanceMetadata, VpnLocationMetadata, VpnLocationModifiedMetadata,
+        WebHookMetadata, WebHookModifiedMetadata, WebHookStateChangedMetadata,
     },
 };
 use description::{
-    get_defguard_event_description, get_enrollment_event_description, get_vpn_event_description,
+    get_custom_event_description, get_defguard_event_description, get_enrollment_event_description,
+    get_ldap_event_description, get_vpn_event_description,
 };
 use error::EventLoggerError;
 use message::{
-    DefguardEvent, EnrollmentEvent, EventContext, EventLoggerMessage, LoggerEvent, VpnEvent,
+    CustomEvent, DefguardEvent, EnrollmentEvent, EventContext, EventLoggerMessage, LdapEvent,
+    LoggerEvent, VpnEvent,
 };
 use sqlx::PgPool;
 use tokio::sync::{broadcast::Sender, mpsc::UnboundedReceiver};
@@ -62,8 +77,12 @@ pub async fn run_event_logger(
         }
 
         debug!("Processing batch of {message_count} activity log events");
+        EVENT_LOGGER_QUEUE.record_depth(event_logger_rx.len());
 
-        if let Err(e) = process_batch(&pool, message_buffer, &activity_log_messages_tx).await {
+        let started = Instant::now();
+        let result = process_batch(&pool, message_buffer, &activity_log_messages_tx).await;
+        EVENT_LOGGER_QUEUE.record_processed(message_count, started.elapsed());
+        if let Err(e) = result {
             error!("Failed to process activity log event batch, batch will be discarded: {e}");
             continue;
         }
@@ -88,6 +107,9 @@ async fn process_batch(
             timestamp,
             ip,
             device,
+            request_id,
+            impersonator_id,
+            impersonator,
         } = message.context;
 
         // Convert each message to a related activity log event
@@ -103,6 +125,65 @@ async fn process_batch(
                             EventType::UserLoginFailed,
                             serde_json::to_value(LoginFailedMetadata { message }).ok(),
                         ),
+                        DefguardEvent::BreakGlassLogin => (EventType::BreakGlassLogin, None),
+                        DefguardEvent::AdminAccessDenied {
+                            method,
+                            path,
+                            reason,
+                        } => (
+                            EventType::AdminAccessDenied,
+                            serde_json::to_value(AdminAccessDeniedMetadata {
+                                method,
+                                path,
+                                reason,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::UserLockedOut {
+                            failed_attempts,
+                            locked_until,
+                        } => (
+                            EventType::UserLockedOut,
+                            serde_json::to_value(UserLockedOutMetadata {
+                                failed_attempts,
+                                locked_until,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::LoginIpLockedOut {
+                            ip_address,
+                            failed_attempts,
+                            locked_until,
+                        } => (
+                            EventType::LoginIpLockedOut,
+                            serde_json::to_value(LoginIpLockedOutMetadata {
+                                ip_address,
+                                failed_attempts,
+                                locked_until,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::UserUnlocked { user } => (
+                            EventType::UserUnlocked,
+                            serde_json::to_value(UserMetadata { user: user.into() }).ok(),
+                        ),
+                        DefguardEvent::LoginIpUnlocked { ip_address } => (
+                            EventType::LoginIpUnlocked,
+                            serde_json::to_value(LoginIpUnlockedMetadata { ip_address }).ok(),
+                        ),
+                        DefguardEvent::RateLimitExceeded {
+                            endpoint,
+                            ip_address,
+                            rejected_requests,
+                        } => (
+                            EventType::RateLimitExceeded,
+                            serde_json::to_value(RateLimitExceededMetadata {
+                                endpoint,
+                                ip_address,
+                                rejected_requests,
+                            })
+                            .ok(),
+                        ),
                         DefguardEvent::UserMfaLogin { mfa_method } => (
                             EventType::UserMfaLogin,
                             serde_json::to_value(MfaLoginMetadata { mfa_method }).ok(),
@@ -161,7 +242,37 @@ async fn process_batch(
                             })
                             .ok(),
                         ),
-                        DefguardEvent::RecoveryCodeUsed => (EventType::RecoveryCodeUsed, None),
+                        DefguardEvent::UserProvisioned {
+                            user,
+                            source,
+                            groups,
+                            pending_approval,
+                        } => (
+                            EventType::UserProvisioned,
+                            serde_json::to_value(UserProvisionedMetadata {
+                                user: user.into(),
+                                source,
+                                groups,
+                                pending_approval,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::AccountCreationRefused { source, identity } => (
+                            EventType::AccountCreationRefused,
+                            serde_json::to_value(AccountCreationRefusedMetadata {
+                                source,
+                                identity,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::RecoveryCodeUsed { remaining } => (
+                            EventType::RecoveryCodeUsed,
+                            serde_json::to_value(RecoveryCodeUsedMetadata { remaining }).ok(),
+                        ),
+                        DefguardEvent::RecoveryCodesRegenerated { count } => (
+                            EventType::RecoveryCodesRegenerated,
+                            serde_json::to_value(RecoveryCodesRegeneratedMetadata { count }).ok(),
+                        ),
                         DefguardEvent::PasswordChanged => (EventType::PasswordChanged, None),
                         DefguardEvent::PasswordChangedByAdmin { user } => (
                             EventType::PasswordChangedByAdmin,
@@ -180,6 +291,8 @@ async fn process_batch(
                         DefguardEvent::MfaTotpDisabled => (EventType::MfaTotpDisabled, None),
                         DefguardEvent::MfaEmailEnabled => (EventType::MfaEmailEnabled, None),
                         DefguardEvent::MfaEmailDisabled => (EventType::MfaEmailDisabled, None),
+                        DefguardEvent::MfaSmsEnabled => (EventType::MfaSmsEnabled, None),
+                        DefguardEvent::MfaSmsDisabled => (EventType::MfaSmsDisabled, None),
                         DefguardEvent::MfaSecurityKeyAdded { key } => (
                             EventType::MfaSecurityKeyAdded,
                             serde_json::to_value(MfaSecurityKeyMetadata { key: key.into() }).ok(),
@@ -250,6 +363,10 @@ async fn process_batch(
                             EventType::UserRemoved,
                             serde_json::to_value(UserMetadata { user: user.into() }).ok(),
                         ),
+                        DefguardEvent::UserDisabled { user } => (
+                            EventType::UserDisabled,
+                            serde_json::to_value(UserMetadata { user: user.into() }).ok(),
+                        ),
                         DefguardEvent::UserModified { before, after } => (
                             EventType::UserModified,
                             serde_json::to_value(UserModifiedMetadata {
@@ -258,6 +375,32 @@ async fn process_batch(
                             })
                             .ok(),
                         ),
+                        DefguardEvent::DeviceApproved {
+                            owner,
+                            device,
+                            location,
+                        } => (
+                            EventType::DeviceApproved,
+                            serde_json::to_value(DeviceApprovalMetadata {
+                                owner: owner.into(),
+                                device,
+                                location,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::DeviceRejected {
+                            owner,
+                            device,
+                            location,
+                        } => (
+                            EventType::DeviceRejected,
+                            serde_json::to_value(DeviceApprovalMetadata {
+                                owner: owner.into(),
+                                device,
+                                location,
+                            })
+                            .ok(),
+                        ),
                         DefguardEvent::NetworkDeviceAdded { device, location } => (
                             EventType::NetworkDeviceAdded,
                             serde_json::to_value(NetworkDeviceMetadata { device, location }).ok(),
@@ -279,6 +422,52 @@ async fn process_batch(
                             })
                             .ok(),
                         ),
+                        DefguardEvent::DeviceAddressReserved {
+                            device,
+                            location,
+                            before,
+                            after,
+                        } => (
+                            EventType::DeviceAddressReserved,
+                            serde_json::to_value(DeviceAddressReservedMetadata {
+                                device,
+                                location,
+                                before,
+                                after,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::DeviceAddressReleased {
+                            device,
+                            location,
+                            addresses,
+                        } => (
+                            EventType::DeviceAddressReleased,
+                            serde_json::to_value(DeviceAddressReleasedMetadata {
+                                device,
+                                location,
+                                addresses,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::DeviceProfileAdded { profile } => (
+                            EventType::DeviceProfileAdded,
+                            serde_json::to_value(DeviceProfileMetadata { profile }).ok(),
+                        ),
+                        DefguardEvent::DeviceProfileModified { before, after } => (
+                            EventType::DeviceProfileModified,
+                            serde_json::to_value(DeviceProfileModifiedMetadata { before, after })
+                                .ok(),
+                        ),
+                        DefguardEvent::DeviceProfileRemoved { profile } => (
+                            EventType::DeviceProfileRemoved,
+                            serde_json::to_value(DeviceProfileMetadata { profile }).ok(),
+                        ),
+                        DefguardEvent::DeviceProfileAssigned { device, profile } => (
+                            EventType::DeviceProfileAssigned,
+                            serde_json::to_value(DeviceProfileAssignedMetadata { device, profile })
+                                .ok(),
+                        ),
                         DefguardEvent::VpnLocationAdded { location } => (
                             EventType::VpnLocationAdded,
                             serde_json::to_value(VpnLocationMetadata { location }).ok(),
@@ -292,6 +481,31 @@ async fn process_batch(
                             serde_json::to_value(VpnLocationModifiedMetadata { before, after })
                                 .ok(),
                         ),
+                        DefguardEvent::VpnLocationMaintenanceEnabled {
+                            location,
+                            message,
+                            disconnected,
+                        } => (
+                            EventType::VpnLocationMaintenanceEnabled,
+                            serde_json::to_value(VpnLocationMaintenanceMetadata {
+                                location,
+                                message,
+                                disconnected,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::VpnLocationMaintenanceDisabled { location } => (
+                            EventType::VpnLocationMaintenanceDisabled,
+                            serde_json::to_value(VpnLocationMetadata { location }).ok(),
+                        ),
+                        DefguardEvent::GatewayTokenAdded { location, token } => (
+                            EventType::GatewayTokenAdded,
+                            serde_json::to_value(GatewayTokenMetadata { location, token }).ok(),
+                        ),
+                        DefguardEvent::GatewayTokenRevoked { location, token } => (
+                            EventType::GatewayTokenRevoked,
+                            serde_json::to_value(GatewayTokenMetadata { location, token }).ok(),
+                        ),
                         DefguardEvent::OpenIdAppAdded { app } => (
                             EventType::OpenIdAppAdded,
                             serde_json::to_value(OpenIdAppMetadata { app: app.into() }).ok(),
@@ -316,6 +530,23 @@ async fn process_batch(
                             })
                             .ok(),
                         ),
+                        DefguardEvent::ClientCredentialsSet {
+                            app,
+                            service_account,
+                            scopes,
+                        } => (
+                            EventType::ClientCredentialsSet,
+                            serde_json::to_value(ClientCredentialsMetadata {
+                                app: app.into(),
+                                service_account: service_account.into(),
+                                scopes,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::ClientCredentialsRemoved { app } => (
+                            EventType::ClientCredentialsRemoved,
+                            serde_json::to_value(OpenIdAppMetadata { app: app.into() }).ok(),
+                        ),
                         DefguardEvent::OpenIdProviderModified { provider } => (
                             EventType::OpenIdProviderModified,
                             serde_json::to_value(OpenIdProviderMetadata {
@@ -330,6 +561,22 @@ async fn process_batch(
                             })
                             .ok(),
                         ),
+                        DefguardEvent::SamlProviderModified { provider } => (
+                            EventType::SamlProviderModified,
+                            serde_json::to_value(SamlProviderMetadata::from(provider)).ok(),
+                        ),
+                        DefguardEvent::SamlProviderRemoved { provider } => (
+                            EventType::SamlProviderRemoved,
+                            serde_json::to_value(SamlProviderMetadata::from(provider)).ok(),
+                        ),
+                        DefguardEvent::ProvisioningPolicyModified { before, after } => (
+                            EventType::ProvisioningPolicyModified,
+                            serde_json::to_value(ProvisioningPolicyModifiedMetadata {
+                                before,
+                                after,
+                            })
+                            .ok(),
+                        ),
                         DefguardEvent::SettingsUpdatedPartial { before, after } => (
                             EventType::SettingsUpdatedPartial,
                             serde_json::to_value(SettingsUpdateMetadata {
@@ -349,6 +596,14 @@ async fn process_batch(
                         DefguardEvent::SettingsDefaultBrandingRestored => {
                             (EventType::SettingsDefaultBrandingRestored, None)
                         }
+                        DefguardEvent::MailTemplateModified { kind } => (
+                            EventType::MailTemplateModified,
+                            serde_json::to_value(MailTemplateMetadata { kind }).ok(),
+                        ),
+                        DefguardEvent::MailTemplateReset { kind } => (
+                            EventType::MailTemplateReset,
+                            serde_json::to_value(MailTemplateMetadata { kind }).ok(),
+                        ),
                         DefguardEvent::ActivityLogStreamCreated { stream } => (
                             EventType::ActivityLogStreamCreated,
                             serde_json::to_value(ActivityLogStreamMetadata {
@@ -363,6 +618,19 @@ async fn process_batch(
                             })
                             .ok(),
                         ),
+                        DefguardEvent::ActivityLogPurged {
+                            removed,
+                            archived,
+                            threshold,
+                        } => (
+                            EventType::ActivityLogPurged,
+                            serde_json::to_value(ActivityLogPurgedMetadata {
+                                removed,
+                                archived,
+                                threshold,
+                            })
+                            .ok(),
+                        ),
                         DefguardEvent::ActivityLogStreamModified { before, after } => (
                             EventType::ActivityLogStreamModified,
                             serde_json::to_value(ActivityLogStreamModifiedMetadata {
@@ -407,6 +675,14 @@ async fn process_batch(
                             })
                             .ok(),
                         ),
+                        DefguardEvent::GroupMembershipExpired { group, user } => (
+                            EventType::GroupMembershipExpired,
+                            serde_json::to_value(GroupAssignedMetadata {
+                                group,
+                                user: user.into(),
+                            })
+                            .ok(),
+                        ),
                         DefguardEvent::GroupMembersModified {
                             group,
                             added,
@@ -420,6 +696,42 @@ async fn process_batch(
                             })
                             .ok(),
                         ),
+                        DefguardEvent::GroupQuotaModified { group, quota } => (
+                            EventType::GroupQuotaModified,
+                            serde_json::to_value(GroupQuotaModifiedMetadata { group, quota }).ok(),
+                        ),
+                        DefguardEvent::GroupSyncAuthorityModified {
+                            group,
+                            sync_authority,
+                        } => (
+                            EventType::GroupSyncAuthorityModified,
+                            serde_json::to_value(GroupSyncAuthorityModifiedMetadata {
+                                group,
+                                sync_authority,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::GroupParentModified { group, parent } => (
+                            EventType::GroupParentModified,
+                            serde_json::to_value(GroupParentModifiedMetadata { group, parent })
+                                .ok(),
+                        ),
+                        DefguardEvent::GroupJoinRequestApproved { group, user } => (
+                            EventType::GroupJoinRequestApproved,
+                            serde_json::to_value(GroupAssignedMetadata {
+                                group,
+                                user: user.into(),
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::GroupJoinRequestRejected { group, user } => (
+                            EventType::GroupJoinRequestRejected,
+                            serde_json::to_value(GroupAssignedMetadata {
+                                group,
+                                user: user.into(),
+                            })
+                            .ok(),
+                        ),
                         DefguardEvent::WebHookAdded { webhook } => (
                             EventType::WebHookAdded,
                             serde_json::to_value(WebHookMetadata { webhook }).ok(),
@@ -441,6 +753,57 @@ async fn process_batch(
                             EventType::PasswordReset,
                             serde_json::to_value(PasswordResetMetadata { user: user.into() }).ok(),
                         ),
+                        DefguardEvent::UserSessionRevoked {
+                            user,
+                            ip_address,
+                            device_info,
+                        } => (
+                            EventType::UserSessionRevoked,
+                            serde_json::to_value(UserSessionRevokedMetadata {
+                                user: user.into(),
+                                ip_address,
+                                device_info,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::UserSessionsRevoked { user, count } => (
+                            EventType::UserSessionsRevoked,
+                            serde_json::to_value(UserSessionsRevokedMetadata {
+                                user: user.into(),
+                                count,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::ImpersonationStarted {
+                            admin,
+                            user,
+                            mode,
+                            expires,
+                        } => (
+                            EventType::ImpersonationStarted,
+                            serde_json::to_value(ImpersonationStartedMetadata {
+                                admin: admin.into(),
+                                user: user.into(),
+                                mode,
+                                expires,
+                            })
+                            .ok(),
+                        ),
+                        DefguardEvent::ImpersonationEnded {
+                            admin,
+                            user,
+                            mode,
+                            expired,
+                        } => (
+                            EventType::ImpersonationEnded,
+                            serde_json::to_value(ImpersonationEndedMetadata {
+                                admin: admin.into(),
+                                user: user.into(),
+                                mode,
+                                expired,
+                            })
+                            .ok(),
+                        ),
                         DefguardEvent::ClientConfigurationTokenAdded { user } => (
                             EventType::ClientConfigurationTokenAdded,
                             serde_json::to_value(ClientConfigurationTokenMetadata {
@@ -492,12 +855,49 @@ async fn process_batch(
                             message,
                         } => (
                             EventType::VpnClientMfaFailed,
-                            serde_json::to_value(VpnClientMfaFailedMetadata {
+                            serde_json::to_value(VpnClientMfaFailedMetadata::new(
+                                location, device, method, message,
+                            ))
+                            .ok(),
+                        ),
+                        VpnEvent::MfaLockedOut {
+                            location,
+                            device,
+                            method,
+                            attempts,
+                            locked_until,
+                        } => (
+                            EventType::VpnClientMfaLockedOut,
+                            serde_json::to_value(VpnClientMfaLockedOutMetadata::new(
                                 location,
                                 device,
                                 method,
-                                message,
-                            })
+                                attempts,
+                                locked_until,
+                            ))
+                            .ok(),
+                        ),
+                        VpnEvent::MfaTimeout {
+                            location,
+                            device,
+                            method,
+                        } => (
+                            EventType::VpnClientMfaTimeout,
+                            serde_json::to_value(VpnClientMfaMetadata::new(
+                                location, device, method,
+                            ))
+                            .ok(),
+                        ),
+                        VpnEvent::MfaPostureFailed {
+                            location,
+                            device,
+                            method,
+                            violations,
+                        } => (
+                            EventType::VpnClientMfaPostureFailed,
+                            serde_json::to_value(VpnClientMfaPostureFailedMetadata::new(
+                                location, device, method, violations,
+                            ))
                             .ok(),
                         ),
                         VpnEvent::ConnectedToMfaLocation {
@@ -506,24 +906,33 @@ async fn process_batch(
                             method,
                         } => (
                             EventType::VpnClientConnectedMfa,
-                            serde_json::to_value(VpnClientMfaMetadata {
-                                location,
-                                device,
-                                method,
-                            })
+                            serde_json::to_value(VpnClientMfaMetadata::new(
+                                location, device, method,
+                            ))
                             .ok(),
                         ),
                         VpnEvent::DisconnectedFromMfaLocation { location, device } => (
                             EventType::VpnClientDisconnectedMfa,
-                            serde_json::to_value(VpnClientMetadata { location, device }).ok(),
+                            serde_json::to_value(VpnClientMetadata::new(location, device)).ok(),
                         ),
                         VpnEvent::ConnectedToLocation { location, device } => (
                             EventType::VpnClientConnected,
-                            serde_json::to_value(VpnClientMetadata { location, device }).ok(),
+                            serde_json::to_value(VpnClientMetadata::new(location, device)).ok(),
                         ),
                         VpnEvent::DisconnectedFromLocation { location, device } => (
                             EventType::VpnClientDisconnected,
-                            serde_json::to_value(VpnClientMetadata { location, device }).ok(),
+                            serde_json::to_value(VpnClientMetadata::new(location, device)).ok(),
+                        ),
+                        VpnEvent::SessionLimitExceeded {
+                            location,
+                            device,
+                            limit,
+                        } => (
+                            EventType::VpnClientSessionLimitExceeded,
+                            serde_json::to_value(VpnClientSessionLimitMetadata::new(
+                                location, device, limit,
+                            ))
+                            .ok(),
                         ),
                     };
                     (module, event_type, description, metadata)
@@ -541,6 +950,7 @@ async fn process_batch(
                             EventType::EnrollmentDeviceAdded,
                             serde_json::to_value(EnrollmentDeviceAddedMetadata { device }).ok(),
                         ),
+                        EnrollmentEvent::EnrollmentBlocked => (EventType::EnrollmentBlocked, None),
                         EnrollmentEvent::PasswordResetRequested => {
                             (EventType::PasswordResetRequested, None)
                         }
@@ -550,14 +960,80 @@ async fn process_batch(
                         EnrollmentEvent::PasswordResetCompleted => {
                             (EventType::PasswordResetCompleted, None)
                         }
+                        EnrollmentEvent::PasswordResetBlocked => {
+                            (EventType::PasswordResetBlocked, None)
+                        }
                         EnrollmentEvent::TokenAdded { user } => (
                             EventType::EnrollmentTokenAdded,
                             serde_json::to_value(EnrollmentTokenMetadata { user: user.into() })
                                 .ok(),
                         ),
+                        EnrollmentEvent::TokenRevoked { user } => (
+                            EventType::EnrollmentTokenRevoked,
+                            serde_json::to_value(EnrollmentTokenMetadata { user: user.into() })
+                                .ok(),
+                        ),
                     };
                     (module, event_type, description, metadata)
                 }
+                LoggerEvent::Ldap(event) => {
+                    let module = ActivityLogModule::Ldap;
+                    let description = get_ldap_event_description(&event);
+
+                    let (event_type, metadata) = match *event {
+                        LdapEvent::UserAdded { user } => (
+                            EventType::UserAdded,
+                            serde_json::to_value(UserMetadata { user: user.into() }).ok(),
+                        ),
+                        LdapEvent::UserRemoved { user } => (
+                            EventType::UserRemoved,
+                            serde_json::to_value(UserMetadata { user: user.into() }).ok(),
+                        ),
+                        LdapEvent::UserModified { before, after } => (
+                            EventType::UserModified,
+                            serde_json::to_value(UserModifiedMetadata {
+                                before: before.into(),
+                                after: after.into(),
+                            })
+                            .ok(),
+                        ),
+                        LdapEvent::GroupMemberAdded { group, user } => (
+                            EventType::GroupMemberAdded,
+                            serde_json::to_value(GroupAssignedMetadata {
+                                group,
+                                user: user.into(),
+                            })
+                            .ok(),
+                        ),
+                        LdapEvent::GroupMemberRemoved { group, user } => (
+                            EventType::GroupMemberRemoved,
+                            serde_json::to_value(GroupAssignedMetadata {
+                                group,
+                                user: user.into(),
+                            })
+                            .ok(),
+                        ),
+                    };
+                    (module, event_type, description, metadata)
+                }
+                LoggerEvent::Custom(event) => {
+                    let module = ActivityLogModule::Custom;
+                    let description = get_custom_event_description(&event);
+
+                    let CustomEvent {
+                        namespace,
+                        name,
+                        data,
+                        ..
+                    } = *event;
+                    let metadata = serde_json::to_value(CustomEventMetadata {
+                        namespace,
+                        name,
+                        data,
+                    })
+                    .ok();
+                    (module, EventType::CustomEvent, description, metadata)
+                }
             };
 
             ActivityLogEvent {
@@ -572,6 +1048,9 @@ async fn process_batch(
                 device,
                 description,
                 metadata,
+                request_id,
+                impersonator_id,
+                impersonator,
             }
         };
 
@@ -1,18 +1,29 @@
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr};
 
 use chrono::NaiveDateTime;
 use defguard_common::db::{
     Id,
-    models::{AuthenticationKey, MFAMethod, Settings},
+    models::{AuthenticationKey, MFAMethod, MailTemplateKind, Settings},
 };
 use defguard_core::{
     db::{
         Device, Group, User, WebAuthn, WebHook, WireguardNetwork,
-        models::oauth2client::OAuth2Client,
+        models::{
+            device_profile::DeviceProfile,
+            gateway_token::GatewayToken,
+            group::{GroupQuota, GroupSyncAuthority},
+            impersonation::ImpersonationMode,
+            oauth2client::OAuth2Client,
+        },
     },
     enterprise::db::models::{
-        activity_log_stream::ActivityLogStream, api_tokens::ApiToken,
-        openid_provider::OpenIdProvider, snat::UserSnatBinding,
+        activity_log_stream::ActivityLogStream,
+        api_tokens::{ApiToken, ApiTokenScope},
+        openid_provider::OpenIdProvider,
+        posture_policy::PostureViolation,
+        provisioning_policy::{ProvisioningPolicy, ProvisioningSource},
+        saml_provider::SamlProvider,
+        snat::UserSnatBinding,
     },
     events::{
         ApiRequestContext, BidiRequestContext, ClientMFAMethod, GrpcRequestContext,
@@ -20,6 +31,9 @@ use defguard_core::{
     },
 };
 
+/// Username recorded for events which are not attributed to any user.
+pub const SYSTEM_USERNAME: &str = "system";
+
 /// Messages that can be sent to the event logger
 pub struct EventLoggerMessage {
     pub context: EventContext,
@@ -38,6 +52,8 @@ pub enum LoggerEvent {
     Defguard(Box<DefguardEvent>),
     Vpn(Box<VpnEvent>),
     Enrollment(Box<EnrollmentEvent>),
+    Ldap(Box<LdapEvent>),
+    Custom(Box<CustomEvent>),
 }
 
 /// Shared context that's included in all activity log events
@@ -48,6 +64,9 @@ pub struct EventContext {
     pub location: Option<String>,
     pub ip: IpAddr,
     pub device: String,
+    pub request_id: Option<String>,
+    pub impersonator_id: Option<Id>,
+    pub impersonator: Option<String>,
 }
 
 impl EventContext {
@@ -65,6 +84,9 @@ impl EventContext {
             location,
             ip: val.ip,
             device: val.device,
+            request_id: val.request_id,
+            impersonator_id: val.impersonator_id,
+            impersonator: val.impersonator,
         }
     }
 
@@ -82,6 +104,9 @@ impl EventContext {
             location,
             ip: val.ip,
             device: val.device_name,
+            request_id: None,
+            impersonator_id: None,
+            impersonator: None,
         }
     }
 
@@ -99,6 +124,43 @@ impl EventContext {
             location,
             ip: val.ip,
             device: format!("{} (ID {})", val.device.name, val.device.id),
+            request_id: None,
+            impersonator_id: None,
+            impersonator: None,
+        }
+    }
+}
+
+impl EventContext {
+    /// Context for events triggered by background tasks, attributed to the affected user.
+    #[must_use]
+    pub fn from_background_task(timestamp: NaiveDateTime, user: &User<Id>) -> Self {
+        Self {
+            timestamp,
+            user_id: user.id,
+            username: user.username.clone(),
+            location: None,
+            ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            device: String::new(),
+            request_id: None,
+            impersonator_id: None,
+            impersonator: None,
+        }
+    }
+
+    /// Context for events triggered by background tasks which don't concern any specific user.
+    #[must_use]
+    pub fn from_system_task(timestamp: NaiveDateTime) -> Self {
+        Self {
+            timestamp,
+            user_id: 0,
+            username: SYSTEM_USERNAME.to_string(),
+            location: None,
+            ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            device: String::new(),
+            request_id: None,
+            impersonator_id: None,
+            impersonator: None,
         }
     }
 }
@@ -112,6 +174,9 @@ impl From<GrpcRequestContext> for EventContext {
             location: Some(val.location.name),
             ip: val.ip,
             device: format!("{} (ID {})", val.device_name, val.device_id),
+            request_id: None,
+            impersonator_id: None,
+            impersonator: None,
         }
     }
 }
@@ -123,6 +188,32 @@ pub enum DefguardEvent {
         message: String,
     },
     UserLogout,
+    BreakGlassLogin,
+    AdminAccessDenied {
+        method: String,
+        path: String,
+        reason: String,
+    },
+    UserLockedOut {
+        failed_attempts: u32,
+        locked_until: NaiveDateTime,
+    },
+    LoginIpLockedOut {
+        ip_address: String,
+        failed_attempts: u32,
+        locked_until: NaiveDateTime,
+    },
+    UserUnlocked {
+        user: User<Id>,
+    },
+    LoginIpUnlocked {
+        ip_address: String,
+    },
+    RateLimitExceeded {
+        endpoint: String,
+        ip_address: String,
+        rejected_requests: u32,
+    },
     UserMfaLogin {
         mfa_method: MFAMethod,
     },
@@ -130,7 +221,12 @@ pub enum DefguardEvent {
         mfa_method: MFAMethod,
         message: String,
     },
-    RecoveryCodeUsed,
+    RecoveryCodeUsed {
+        remaining: usize,
+    },
+    RecoveryCodesRegenerated {
+        count: usize,
+    },
     PasswordChangedByAdmin {
         user: User<Id>,
     },
@@ -138,6 +234,27 @@ pub enum DefguardEvent {
     PasswordReset {
         user: User<Id>,
     },
+    UserSessionRevoked {
+        user: User<Id>,
+        ip_address: String,
+        device_info: Option<String>,
+    },
+    UserSessionsRevoked {
+        user: User<Id>,
+        count: u64,
+    },
+    ImpersonationStarted {
+        admin: User<Id>,
+        user: User<Id>,
+        mode: ImpersonationMode,
+        expires: NaiveDateTime,
+    },
+    ImpersonationEnded {
+        admin: User<Id>,
+        user: User<Id>,
+        mode: ImpersonationMode,
+        expired: bool,
+    },
     MfaDisabled,
     UserMfaDisabled {
         user: User<Id>,
@@ -146,6 +263,8 @@ pub enum DefguardEvent {
     MfaTotpEnabled,
     MfaEmailDisabled,
     MfaEmailEnabled,
+    MfaSmsDisabled,
+    MfaSmsEnabled,
     MfaSecurityKeyAdded {
         key: WebAuthn<Id>,
     },
@@ -162,11 +281,24 @@ pub enum DefguardEvent {
         before: User<Id>,
         after: User<Id>,
     },
+    UserDisabled {
+        user: User<Id>,
+    },
     UserGroupsModified {
         user: User<Id>,
         before: Vec<String>,
         after: Vec<String>,
     },
+    UserProvisioned {
+        user: User<Id>,
+        source: ProvisioningSource,
+        groups: Vec<String>,
+        pending_approval: bool,
+    },
+    AccountCreationRefused {
+        source: ProvisioningSource,
+        identity: String,
+    },
     UserDeviceAdded {
         owner: User<Id>,
         device: Device<Id>,
@@ -180,6 +312,16 @@ pub enum DefguardEvent {
         before: Device<Id>,
         after: Device<Id>,
     },
+    DeviceApproved {
+        owner: User<Id>,
+        device: Device<Id>,
+        location: WireguardNetwork<Id>,
+    },
+    DeviceRejected {
+        owner: User<Id>,
+        device: Device<Id>,
+        location: WireguardNetwork<Id>,
+    },
     NetworkDeviceAdded {
         device: Device<Id>,
         location: WireguardNetwork<Id>,
@@ -193,6 +335,31 @@ pub enum DefguardEvent {
         after: Device<Id>,
         location: WireguardNetwork<Id>,
     },
+    DeviceAddressReserved {
+        device: Device<Id>,
+        location: WireguardNetwork<Id>,
+        before: Vec<IpAddr>,
+        after: Vec<IpAddr>,
+    },
+    DeviceAddressReleased {
+        device: Device<Id>,
+        location: WireguardNetwork<Id>,
+        addresses: Vec<IpAddr>,
+    },
+    DeviceProfileAdded {
+        profile: DeviceProfile<Id>,
+    },
+    DeviceProfileModified {
+        before: DeviceProfile<Id>,
+        after: DeviceProfile<Id>,
+    },
+    DeviceProfileRemoved {
+        profile: DeviceProfile<Id>,
+    },
+    DeviceProfileAssigned {
+        device: Device<Id>,
+        profile: Option<DeviceProfile<Id>>,
+    },
     ActivityLogStreamCreated {
         stream: ActivityLogStream<Id>,
     },
@@ -203,6 +370,11 @@ pub enum DefguardEvent {
     ActivityLogStreamRemoved {
         stream: ActivityLogStream<Id>,
     },
+    ActivityLogPurged {
+        removed: u64,
+        archived: bool,
+        threshold: NaiveDateTime,
+    },
     VpnLocationAdded {
         location: WireguardNetwork<Id>,
     },
@@ -213,6 +385,22 @@ pub enum DefguardEvent {
         before: WireguardNetwork<Id>,
         after: WireguardNetwork<Id>,
     },
+    VpnLocationMaintenanceEnabled {
+        location: WireguardNetwork<Id>,
+        message: Option<String>,
+        disconnected: usize,
+    },
+    VpnLocationMaintenanceDisabled {
+        location: WireguardNetwork<Id>,
+    },
+    GatewayTokenAdded {
+        location: WireguardNetwork<Id>,
+        token: GatewayToken<Id>,
+    },
+    GatewayTokenRevoked {
+        location: WireguardNetwork<Id>,
+        token: GatewayToken<Id>,
+    },
     ApiTokenAdded {
         owner: User<Id>,
         token: ApiToken<Id>,
@@ -241,12 +429,30 @@ pub enum DefguardEvent {
         app: OAuth2Client<Id>,
         enabled: bool,
     },
+    ClientCredentialsSet {
+        app: OAuth2Client<Id>,
+        service_account: User<Id>,
+        scopes: Vec<ApiTokenScope>,
+    },
+    ClientCredentialsRemoved {
+        app: OAuth2Client<Id>,
+    },
     OpenIdProviderModified {
         provider: OpenIdProvider<Id>,
     },
     OpenIdProviderRemoved {
         provider: OpenIdProvider<Id>,
     },
+    SamlProviderModified {
+        provider: SamlProvider<Id>,
+    },
+    SamlProviderRemoved {
+        provider: SamlProvider<Id>,
+    },
+    ProvisioningPolicyModified {
+        before: ProvisioningPolicy,
+        after: ProvisioningPolicy,
+    },
     SettingsUpdated {
         before: Settings,
         after: Settings,
@@ -256,6 +462,12 @@ pub enum DefguardEvent {
         after: Settings,
     },
     SettingsDefaultBrandingRestored,
+    MailTemplateModified {
+        kind: MailTemplateKind,
+    },
+    MailTemplateReset {
+        kind: MailTemplateKind,
+    },
     GroupsBulkAssigned {
         users: Vec<User<Id>>,
         groups: Vec<Group<Id>>,
@@ -278,11 +490,35 @@ pub enum DefguardEvent {
         group: Group<Id>,
         user: User<Id>,
     },
+    GroupMembershipExpired {
+        group: Group<Id>,
+        user: User<Id>,
+    },
     GroupMembersModified {
         group: Group<Id>,
         added: Vec<User<Id>>,
         removed: Vec<User<Id>>,
     },
+    GroupQuotaModified {
+        group: Group<Id>,
+        quota: GroupQuota,
+    },
+    GroupSyncAuthorityModified {
+        group: Group<Id>,
+        sync_authority: Option<GroupSyncAuthority>,
+    },
+    GroupParentModified {
+        group: Group<Id>,
+        parent: Option<Group<Id>>,
+    },
+    GroupJoinRequestApproved {
+        group: Group<Id>,
+        user: User<Id>,
+    },
+    GroupJoinRequestRejected {
+        group: Group<Id>,
+        user: User<Id>,
+    },
     WebHookAdded {
         webhook: WebHook<Id>,
     },
@@ -349,6 +585,24 @@ pub enum VpnEvent {
         method: ClientMFAMethod,
         message: String,
     },
+    MfaLockedOut {
+        location: WireguardNetwork<Id>,
+        device: Device<Id>,
+        method: ClientMFAMethod,
+        attempts: u32,
+        locked_until: Option<NaiveDateTime>,
+    },
+    MfaTimeout {
+        location: WireguardNetwork<Id>,
+        device: Device<Id>,
+        method: ClientMFAMethod,
+    },
+    MfaPostureFailed {
+        location: WireguardNetwork<Id>,
+        device: Device<Id>,
+        method: ClientMFAMethod,
+        violations: Vec<PostureViolation>,
+    },
     ConnectedToLocation {
         location: WireguardNetwork<Id>,
         device: Device<Id>,
@@ -357,6 +611,11 @@ pub enum VpnEvent {
         location: WireguardNetwork<Id>,
         device: Device<Id>,
     },
+    SessionLimitExceeded {
+        location: WireguardNetwork<Id>,
+        device: Device<Id>,
+        limit: i32,
+    },
 }
 
 /// Represents activity log events related to user enrollment process
@@ -364,8 +623,28 @@ pub enum EnrollmentEvent {
     EnrollmentStarted,
     EnrollmentDeviceAdded { device: Device<Id> },
     EnrollmentCompleted,
+    EnrollmentBlocked,
     PasswordResetRequested,
     PasswordResetStarted,
     PasswordResetCompleted,
+    PasswordResetBlocked,
     TokenAdded { user: User<Id> },
+    TokenRevoked { user: User<Id> },
+}
+
+/// Represents activity log events related to changes made by LDAP synchronization
+pub enum LdapEvent {
+    UserAdded { user: User<Id> },
+    UserRemoved { user: User<Id> },
+    UserModified { before: User<Id>, after: User<Id> },
+    GroupMemberAdded { group: Group<Id>, user: User<Id> },
+    GroupMemberRemoved { group: Group<Id>, user: User<Id> },
+}
+
+/// Represents custom activity log events submitted by external integrations
+pub struct CustomEvent {
+    pub namespace: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub data: Option<serde_json::Value>,
 }
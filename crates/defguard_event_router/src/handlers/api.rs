@@ -1,5 +1,7 @@
 use defguard_core::events::{ApiEvent, ApiEventType};
-use defguard_event_logger::message::{DefguardEvent, EnrollmentEvent, EventContext, LoggerEvent};
+use defguard_event_logger::message::{
+    CustomEvent, DefguardEvent, EnrollmentEvent, EventContext, LoggerEvent,
+};
 use tracing::debug;
 
 use crate::{EventRouter, error::EventRouterError};
@@ -16,6 +18,64 @@ impl EventRouter {
                 LoggerEvent::Defguard(Box::new(DefguardEvent::UserLoginFailed { message })),
                 None,
             ),
+            ApiEventType::BreakGlassLogin => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::BreakGlassLogin)),
+                None,
+            ),
+            ApiEventType::AdminAccessDenied {
+                method,
+                path,
+                reason,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::AdminAccessDenied {
+                    method,
+                    path,
+                    reason,
+                })),
+                None,
+            ),
+            ApiEventType::UserLockedOut {
+                failed_attempts,
+                locked_until,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::UserLockedOut {
+                    failed_attempts,
+                    locked_until,
+                })),
+                None,
+            ),
+            ApiEventType::LoginIpLockedOut {
+                ip_address,
+                failed_attempts,
+                locked_until,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::LoginIpLockedOut {
+                    ip_address,
+                    failed_attempts,
+                    locked_until,
+                })),
+                None,
+            ),
+            ApiEventType::UserUnlocked { user } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::UserUnlocked { user })),
+                None,
+            ),
+            ApiEventType::LoginIpUnlocked { ip_address } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::LoginIpUnlocked { ip_address })),
+                None,
+            ),
+            ApiEventType::RateLimitExceeded {
+                endpoint,
+                ip_address,
+                rejected_requests,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::RateLimitExceeded {
+                    endpoint,
+                    ip_address,
+                    rejected_requests,
+                })),
+                None,
+            ),
             ApiEventType::UserMfaLogin { mfa_method } => (
                 LoggerEvent::Defguard(Box::new(DefguardEvent::UserMfaLogin { mfa_method })),
                 None,
@@ -30,8 +90,12 @@ impl EventRouter {
                 })),
                 None,
             ),
-            ApiEventType::RecoveryCodeUsed => (
-                LoggerEvent::Defguard(Box::new(DefguardEvent::RecoveryCodeUsed)),
+            ApiEventType::RecoveryCodeUsed { remaining } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::RecoveryCodeUsed { remaining })),
+                None,
+            ),
+            ApiEventType::RecoveryCodesRegenerated { count } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::RecoveryCodesRegenerated { count })),
                 None,
             ),
             ApiEventType::UserLogout => (
@@ -62,6 +126,27 @@ impl EventRouter {
                 })),
                 None,
             ),
+            ApiEventType::UserProvisioned {
+                user,
+                source,
+                groups,
+                pending_approval,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::UserProvisioned {
+                    user,
+                    source,
+                    groups,
+                    pending_approval,
+                })),
+                None,
+            ),
+            ApiEventType::AccountCreationRefused { source, identity } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::AccountCreationRefused {
+                    source,
+                    identity,
+                })),
+                None,
+            ),
             ApiEventType::MfaDisabled => (
                 LoggerEvent::Defguard(Box::new(DefguardEvent::MfaDisabled)),
                 None,
@@ -86,6 +171,14 @@ impl EventRouter {
                 LoggerEvent::Defguard(Box::new(DefguardEvent::MfaEmailEnabled)),
                 None,
             ),
+            ApiEventType::MfaSmsDisabled => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::MfaSmsDisabled)),
+                None,
+            ),
+            ApiEventType::MfaSmsEnabled => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::MfaSmsEnabled)),
+                None,
+            ),
             ApiEventType::MfaSecurityKeyAdded { key } => (
                 LoggerEvent::Defguard(Box::new(DefguardEvent::MfaSecurityKeyAdded { key })),
                 None,
@@ -114,6 +207,30 @@ impl EventRouter {
                 })),
                 None,
             ),
+            ApiEventType::DeviceApproved {
+                owner,
+                device,
+                location,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::DeviceApproved {
+                    owner,
+                    device,
+                    location: location.clone(),
+                })),
+                Some(location),
+            ),
+            ApiEventType::DeviceRejected {
+                owner,
+                device,
+                location,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::DeviceRejected {
+                    owner,
+                    device,
+                    location: location.clone(),
+                })),
+                Some(location),
+            ),
             ApiEventType::NetworkDeviceAdded { device, location } => (
                 LoggerEvent::Defguard(Box::new(DefguardEvent::NetworkDeviceAdded {
                     device,
@@ -133,6 +250,54 @@ impl EventRouter {
                 })),
                 Some(location),
             ),
+            ApiEventType::DeviceAddressReserved {
+                device,
+                location,
+                before,
+                after,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::DeviceAddressReserved {
+                    device,
+                    location: location.clone(),
+                    before,
+                    after,
+                })),
+                Some(location),
+            ),
+            ApiEventType::DeviceAddressReleased {
+                device,
+                location,
+                addresses,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::DeviceAddressReleased {
+                    device,
+                    location: location.clone(),
+                    addresses,
+                })),
+                Some(location),
+            ),
+            ApiEventType::DeviceProfileAdded { profile } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::DeviceProfileAdded { profile })),
+                None,
+            ),
+            ApiEventType::DeviceProfileModified { before, after } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::DeviceProfileModified {
+                    before,
+                    after,
+                })),
+                None,
+            ),
+            ApiEventType::DeviceProfileRemoved { profile } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::DeviceProfileRemoved { profile })),
+                None,
+            ),
+            ApiEventType::DeviceProfileAssigned { device, profile } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::DeviceProfileAssigned {
+                    device,
+                    profile,
+                })),
+                None,
+            ),
             ApiEventType::NetworkDeviceRemoved { device, location } => (
                 LoggerEvent::Defguard(Box::new(DefguardEvent::NetworkDeviceRemoved {
                     device,
@@ -190,6 +355,38 @@ impl EventRouter {
                 })),
                 Some(after),
             ),
+            ApiEventType::VpnLocationMaintenanceEnabled {
+                location,
+                message,
+                disconnected,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::VpnLocationMaintenanceEnabled {
+                    location: location.clone(),
+                    message,
+                    disconnected,
+                })),
+                Some(location),
+            ),
+            ApiEventType::VpnLocationMaintenanceDisabled { location } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::VpnLocationMaintenanceDisabled {
+                    location: location.clone(),
+                })),
+                Some(location),
+            ),
+            ApiEventType::GatewayTokenAdded { location, token } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::GatewayTokenAdded {
+                    location: location.clone(),
+                    token,
+                })),
+                Some(location),
+            ),
+            ApiEventType::GatewayTokenRevoked { location, token } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::GatewayTokenRevoked {
+                    location: location.clone(),
+                    token,
+                })),
+                Some(location),
+            ),
             ApiEventType::ApiTokenAdded { owner, token } => (
                 LoggerEvent::Defguard(Box::new(DefguardEvent::ApiTokenAdded { owner, token })),
                 None,
@@ -231,6 +428,22 @@ impl EventRouter {
                 })),
                 None,
             ),
+            ApiEventType::ClientCredentialsSet {
+                app,
+                service_account,
+                scopes,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::ClientCredentialsSet {
+                    app,
+                    service_account,
+                    scopes,
+                })),
+                None,
+            ),
+            ApiEventType::ClientCredentialsRemoved { app } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::ClientCredentialsRemoved { app })),
+                None,
+            ),
             ApiEventType::OpenIdProviderRemoved { provider } => (
                 LoggerEvent::Defguard(Box::new(DefguardEvent::OpenIdProviderRemoved { provider })),
                 None,
@@ -239,6 +452,21 @@ impl EventRouter {
                 LoggerEvent::Defguard(Box::new(DefguardEvent::OpenIdProviderModified { provider })),
                 None,
             ),
+            ApiEventType::SamlProviderModified { provider } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::SamlProviderModified { provider })),
+                None,
+            ),
+            ApiEventType::SamlProviderRemoved { provider } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::SamlProviderRemoved { provider })),
+                None,
+            ),
+            ApiEventType::ProvisioningPolicyModified { before, after } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::ProvisioningPolicyModified {
+                    before,
+                    after,
+                })),
+                None,
+            ),
             ApiEventType::SettingsUpdated { before, after } => (
                 LoggerEvent::Defguard(Box::new(DefguardEvent::SettingsUpdated { before, after })),
                 None,
@@ -254,6 +482,14 @@ impl EventRouter {
                 LoggerEvent::Defguard(Box::new(DefguardEvent::SettingsDefaultBrandingRestored)),
                 None,
             ),
+            ApiEventType::MailTemplateModified { kind } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::MailTemplateModified { kind })),
+                None,
+            ),
+            ApiEventType::MailTemplateReset { kind } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::MailTemplateReset { kind })),
+                None,
+            ),
             ApiEventType::GroupsBulkAssigned { users, groups } => (
                 LoggerEvent::Defguard(Box::new(DefguardEvent::GroupsBulkAssigned {
                     users,
@@ -293,6 +529,41 @@ impl EventRouter {
                 })),
                 None,
             ),
+            ApiEventType::GroupQuotaModified { group, quota } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::GroupQuotaModified { group, quota })),
+                None,
+            ),
+            ApiEventType::GroupSyncAuthorityModified {
+                group,
+                sync_authority,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::GroupSyncAuthorityModified {
+                    group,
+                    sync_authority,
+                })),
+                None,
+            ),
+            ApiEventType::GroupParentModified { group, parent } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::GroupParentModified {
+                    group,
+                    parent,
+                })),
+                None,
+            ),
+            ApiEventType::GroupJoinRequestApproved { group, user } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::GroupJoinRequestApproved {
+                    group,
+                    user,
+                })),
+                None,
+            ),
+            ApiEventType::GroupJoinRequestRejected { group, user } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::GroupJoinRequestRejected {
+                    group,
+                    user,
+                })),
+                None,
+            ),
             ApiEventType::WebHookAdded { webhook } => (
                 LoggerEvent::Defguard(Box::new(DefguardEvent::WebHookAdded { webhook })),
                 None,
@@ -336,6 +607,10 @@ impl EventRouter {
                 LoggerEvent::Enrollment(Box::new(EnrollmentEvent::TokenAdded { user })),
                 None,
             ),
+            ApiEventType::EnrollmentTokenRevoked { user } => (
+                LoggerEvent::Enrollment(Box::new(EnrollmentEvent::TokenRevoked { user })),
+                None,
+            ),
             ApiEventType::PasswordChanged => (
                 LoggerEvent::Defguard(Box::new(DefguardEvent::PasswordChanged)),
                 None,
@@ -348,6 +623,50 @@ impl EventRouter {
                 LoggerEvent::Defguard(Box::new(DefguardEvent::PasswordReset { user })),
                 None,
             ),
+            ApiEventType::UserSessionRevoked {
+                user,
+                ip_address,
+                device_info,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::UserSessionRevoked {
+                    user,
+                    ip_address,
+                    device_info,
+                })),
+                None,
+            ),
+            ApiEventType::UserSessionsRevoked { user, count } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::UserSessionsRevoked { user, count })),
+                None,
+            ),
+            ApiEventType::ImpersonationStarted {
+                admin,
+                user,
+                mode,
+                expires,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::ImpersonationStarted {
+                    admin,
+                    user,
+                    mode,
+                    expires,
+                })),
+                None,
+            ),
+            ApiEventType::ImpersonationEnded {
+                admin,
+                user,
+                mode,
+                expired,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::ImpersonationEnded {
+                    admin,
+                    user,
+                    mode,
+                    expired,
+                })),
+                None,
+            ),
             ApiEventType::ClientConfigurationTokenAdded { user } => (
                 LoggerEvent::Defguard(Box::new(DefguardEvent::ClientConfigurationTokenAdded {
                     user,
@@ -389,6 +708,20 @@ impl EventRouter {
                 })),
                 Some(location),
             ),
+            ApiEventType::CustomEvent {
+                namespace,
+                name,
+                description,
+                data,
+            } => (
+                LoggerEvent::Custom(Box::new(CustomEvent {
+                    namespace,
+                    name,
+                    description,
+                    data,
+                })),
+                None,
+            ),
         };
         self.log_event(
             EventContext::from_api_context(event.context, location),
@@ -1,7 +1,9 @@
 use defguard_core::events::{
     self, BidiStreamEvent, BidiStreamEventType, DesktopClientMfaEvent, PasswordResetEvent,
 };
-use defguard_event_logger::message::{EnrollmentEvent, EventContext, LoggerEvent, VpnEvent};
+use defguard_event_logger::message::{
+    DefguardEvent, EnrollmentEvent, EventContext, LoggerEvent, VpnEvent,
+};
 use tracing::debug;
 
 use crate::{EventRouter, error::EventRouterError};
@@ -29,6 +31,11 @@ impl EventRouter {
                     })),
                     None,
                 ),
+
+                events::EnrollmentEvent::EnrollmentBlocked => (
+                    LoggerEvent::Enrollment(Box::new(EnrollmentEvent::EnrollmentBlocked)),
+                    None,
+                ),
             },
             BidiStreamEventType::PasswordReset(event) => match *event {
                 PasswordResetEvent::PasswordResetRequested => (
@@ -43,6 +50,10 @@ impl EventRouter {
                     LoggerEvent::Enrollment(Box::new(EnrollmentEvent::PasswordResetCompleted)),
                     None,
                 ),
+                PasswordResetEvent::PasswordResetBlocked => (
+                    LoggerEvent::Enrollment(Box::new(EnrollmentEvent::PasswordResetBlocked)),
+                    None,
+                ),
             },
             BidiStreamEventType::DesktopClientMfa(event) => match *event {
                 DesktopClientMfaEvent::Connected {
@@ -71,7 +82,94 @@ impl EventRouter {
                     })),
                     Some(location),
                 ),
+                DesktopClientMfaEvent::LockedOut {
+                    location,
+                    device,
+                    method,
+                    attempts,
+                    locked_until,
+                } => (
+                    LoggerEvent::Vpn(Box::new(VpnEvent::MfaLockedOut {
+                        location: location.clone(),
+                        device,
+                        method,
+                        attempts,
+                        locked_until,
+                    })),
+                    Some(location),
+                ),
+                DesktopClientMfaEvent::Timeout {
+                    location,
+                    device,
+                    method,
+                } => (
+                    LoggerEvent::Vpn(Box::new(VpnEvent::MfaTimeout {
+                        location: location.clone(),
+                        device,
+                        method,
+                    })),
+                    Some(location),
+                ),
+                DesktopClientMfaEvent::PostureFailed {
+                    location,
+                    device,
+                    method,
+                    violations,
+                } => (
+                    LoggerEvent::Vpn(Box::new(VpnEvent::MfaPostureFailed {
+                        location: location.clone(),
+                        device,
+                        method,
+                        violations,
+                    })),
+                    Some(location),
+                ),
+                DesktopClientMfaEvent::SessionLimitExceeded {
+                    location,
+                    device,
+                    limit,
+                } => (
+                    LoggerEvent::Vpn(Box::new(VpnEvent::SessionLimitExceeded {
+                        location: location.clone(),
+                        device,
+                        limit,
+                    })),
+                    Some(location),
+                ),
             },
+            BidiStreamEventType::RateLimitExceeded {
+                endpoint,
+                ip_address,
+                rejected_requests,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::RateLimitExceeded {
+                    endpoint,
+                    ip_address,
+                    rejected_requests,
+                })),
+                None,
+            ),
+            BidiStreamEventType::UserProvisioned {
+                user,
+                source,
+                groups,
+                pending_approval,
+            } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::UserProvisioned {
+                    user,
+                    source,
+                    groups,
+                    pending_approval,
+                })),
+                None,
+            ),
+            BidiStreamEventType::AccountCreationRefused { source, identity } => (
+                LoggerEvent::Defguard(Box::new(DefguardEvent::AccountCreationRefused {
+                    source,
+                    identity,
+                })),
+                None,
+            ),
         };
 
         self.log_event(
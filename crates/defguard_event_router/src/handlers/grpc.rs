@@ -34,6 +34,21 @@ impl EventRouter {
                     })),
                 )?;
             }
+            GrpcEvent::ClientSessionLimitExceeded {
+                context,
+                location,
+                device,
+                limit,
+            } => {
+                self.log_event(
+                    context.into(),
+                    LoggerEvent::Vpn(Box::new(VpnEvent::SessionLimitExceeded {
+                        location,
+                        device,
+                        limit,
+                    })),
+                )?;
+            }
         }
 
         Ok(())
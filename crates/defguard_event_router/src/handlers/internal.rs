@@ -1,5 +1,7 @@
-use defguard_core::events::InternalEvent;
-use defguard_event_logger::message::{EventContext, LoggerEvent, VpnEvent};
+use defguard_core::events::{InternalEvent, LdapSyncChange};
+use defguard_event_logger::message::{
+    DefguardEvent, EventContext, LdapEvent, LoggerEvent, VpnEvent,
+};
 use tracing::debug;
 
 use crate::{EventRouter, error::EventRouterError};
@@ -22,6 +24,66 @@ impl EventRouter {
                     })),
                 )
             }
+            InternalEvent::GroupMembershipExpired {
+                timestamp,
+                group,
+                user,
+            } => self.log_event(
+                EventContext::from_background_task(timestamp, &user),
+                LoggerEvent::Defguard(Box::new(DefguardEvent::GroupMembershipExpired {
+                    group,
+                    user,
+                })),
+            ),
+            InternalEvent::UserDisabled { timestamp, user } => self.log_event(
+                EventContext::from_background_task(timestamp, &user),
+                LoggerEvent::Defguard(Box::new(DefguardEvent::UserDisabled { user })),
+            ),
+            InternalEvent::DeviceExpired {
+                timestamp,
+                owner,
+                device,
+            } => self.log_event(
+                EventContext::from_background_task(timestamp, &owner),
+                LoggerEvent::Defguard(Box::new(DefguardEvent::UserDeviceRemoved { owner, device })),
+            ),
+            InternalEvent::ActivityLogPurged {
+                timestamp,
+                removed,
+                archived,
+                threshold,
+            } => self.log_event(
+                EventContext::from_system_task(timestamp),
+                LoggerEvent::Defguard(Box::new(DefguardEvent::ActivityLogPurged {
+                    removed,
+                    archived,
+                    threshold,
+                })),
+            ),
+            InternalEvent::LdapSync { timestamp, change } => {
+                // attribute the change to the affected user, like other background changes
+                let (user, event) = match change {
+                    LdapSyncChange::UserAdded { user } => {
+                        (user.clone(), LdapEvent::UserAdded { user })
+                    }
+                    LdapSyncChange::UserRemoved { user } => {
+                        (user.clone(), LdapEvent::UserRemoved { user })
+                    }
+                    LdapSyncChange::UserModified { before, after } => {
+                        (after.clone(), LdapEvent::UserModified { before, after })
+                    }
+                    LdapSyncChange::GroupMemberAdded { group, user } => {
+                        (user.clone(), LdapEvent::GroupMemberAdded { group, user })
+                    }
+                    LdapSyncChange::GroupMemberRemoved { group, user } => {
+                        (user.clone(), LdapEvent::GroupMemberRemoved { group, user })
+                    }
+                };
+                self.log_event(
+                    EventContext::from_background_task(timestamp, &user),
+                    LoggerEvent::Ldap(Box::new(event)),
+                )
+            }
         }
     }
 }
@@ -17,7 +17,7 @@
 //!    - Mail events go to the mail service
 //!    - etc.
 
-use std::sync::Arc;
+use std::{pin::pin, sync::Arc};
 
 use defguard_core::{
     db::GatewayEvent,
@@ -60,6 +60,14 @@ impl RouterReceiverSet {
             internal,
         }
     }
+
+    /// Rejects new events, while keeping already queued ones available.
+    fn close(&mut self) {
+        self.api.close();
+        self.grpc.close();
+        self.bidi.close();
+        self.internal.close();
+    }
 }
 
 #[allow(dead_code)]
@@ -106,11 +114,16 @@ impl EventRouter {
         }
     }
 
-    /// Runs the event processing loop
-    async fn run(&mut self) -> Result<(), EventRouterError> {
+    /// Runs the event processing loop until `shutdown` completes
+    async fn run(&mut self, shutdown: impl Future<Output = ()>) -> Result<(), EventRouterError> {
+        let mut shutdown = pin!(shutdown);
         loop {
             // Receive an event from  one of the component event channels
             let event = tokio::select! {
+              () = &mut shutdown => {
+                    info!("Stopping event router, routing queued events");
+                    return self.drain();
+              },
               event = self.receivers.api.recv() => if let Some(api_event) = event { Event::Api(api_event) } else {
                     error!("API event channel closed");
                     return Err(EventRouterError::ApiEventChannelClosed);
@@ -140,18 +153,39 @@ impl EventRouter {
             }
         }
     }
+
+    /// Routes events which have been queued before shutdown.
+    fn drain(&mut self) -> Result<(), EventRouterError> {
+        self.receivers.close();
+        while let Ok(api_event) = self.receivers.api.try_recv() {
+            self.handle_api_event(api_event)?;
+        }
+        while let Ok(grpc_event) = self.receivers.grpc.try_recv() {
+            self.handle_grpc_event(grpc_event)?;
+        }
+        while let Ok(bidi_event) = self.receivers.bidi.try_recv() {
+            self.handle_bidi_event(bidi_event)?;
+        }
+        while let Ok(internal_event) = self.receivers.internal.try_recv() {
+            self.handle_internal_event(internal_event)?;
+        }
+
+        Ok(())
+    }
 }
 
 /// Run the event router service
 ///
-/// This function runs in an infinite loop, receiving messages from the event_rx channel
-/// and routing them to the appropriate service channels.
+/// This function runs in a loop, receiving messages from the event_rx channel
+/// and routing them to the appropriate service channels. Once `shutdown` completes,
+/// events which are already queued are routed and the function returns.
 pub async fn run_event_router(
     receivers: RouterReceiverSet,
     event_logger_tx: UnboundedSender<EventLoggerMessage>,
     wireguard_tx: Sender<GatewayEvent>,
     mail_tx: UnboundedSender<Mail>,
     activity_log_stream_reload_notify: Arc<Notify>,
+    shutdown: impl Future<Output = ()>,
 ) -> Result<(), EventRouterError> {
     info!("Starting main event router service");
 
@@ -163,5 +197,5 @@ pub async fn run_event_router(
         activity_log_stream_reload_notify,
     );
 
-    event_router.run().await
+    event_router.run(shutdown).await
 }
@@ -1,19 +1,36 @@
-use std::time::Duration;
+use std::{collections::VecDeque, future::pending, pin::pin, time::Duration};
 
-use defguard_common::db::models::{Settings, settings::SmtpEncryption};
+use defguard_common::{
+    db::{
+        Id,
+        models::{MailDelivery, Settings, settings::SmtpEncryption},
+    },
+    diagnostics::{MAIL_HANDLER, MAIL_QUEUE},
+};
 use lettre::{
     Address, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
     address::AddressError,
     message::{Mailbox, MultiPart, SinglePart, header::ContentType},
     transport::smtp::{authentication::Credentials, response::Response},
 };
+use plaintext::html_to_text;
+use sqlx::PgPool;
 use thiserror::Error;
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    time::{Instant, sleep_until},
+};
 use tracing::{debug, error, info, instrument, warn};
 
+mod plaintext;
 pub mod templates;
 
 const SMTP_TIMEOUT_SECONDS: u64 = 15;
+// messages waiting for redelivery; failures beyond that go straight to the dead-letter log
+const MAX_RETRY_QUEUE_SIZE: usize = 100;
+const MAX_DELIVERY_ATTEMPTS: u32 = 6;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(10);
+const DEAD_LETTER_TARGET: &str = "defguard_mail::dead_letter";
 
 #[derive(Debug, Error)]
 pub enum MailError {
@@ -40,6 +57,7 @@ pub enum MailError {
 }
 
 /// Subset of Settings object representing SMTP configuration
+#[derive(Clone, PartialEq)]
 struct SmtpSettings {
     pub server: String,
     pub port: u16,
@@ -80,6 +98,8 @@ pub struct Mail {
     pub to: String,
     pub subject: String,
     pub content: String,
+    /// Plaintext alternative of HTML `content`; generated from it if not provided.
+    pub plain_content: Option<String>,
     pub attachments: Vec<Attachment>,
     pub result_tx: Option<UnboundedSender<Result<Response, MailError>>>,
 }
@@ -105,12 +125,14 @@ impl Mail {
             .from(Self::mailbox(from)?)
             .to(Self::mailbox(&self.to)?)
             .subject(self.subject.clone());
+        let plain_content = self
+            .plain_content
+            .unwrap_or_else(|| html_to_text(&self.content));
+        let body = MultiPart::alternative_plain_html(plain_content, self.content);
         match self.attachments {
-            attachments if attachments.is_empty() => Ok(builder
-                .header(ContentType::TEXT_HTML)
-                .body(self.content.clone())?),
+            attachments if attachments.is_empty() => Ok(builder.multipart(body)?),
             attachments => {
-                let mut multipart = MultiPart::mixed().singlepart(SinglePart::html(self.content));
+                let mut multipart = MultiPart::mixed().multipart(body);
                 for attachment in attachments {
                     multipart = multipart.singlepart(attachment.into());
                 }
@@ -130,13 +152,46 @@ impl Mail {
     }
 }
 
+/// Message which couldn't be delivered because of a transient SMTP failure.
+struct PendingMail {
+    to: String,
+    subject: String,
+    message: Message,
+    attempts: u32,
+    retry_at: Instant,
+    delivery: Option<MailDelivery<Id>>,
+}
+
+/// Status line of SMTP server response stored in the delivery record.
+fn describe_response(response: &Response) -> String {
+    format!(
+        "{} {}",
+        response.code(),
+        response.message().collect::<Vec<_>>().join(" ")
+    )
+}
+
+/// Delay before the next delivery attempt, doubled after every failed one.
+fn retry_delay(attempts: u32) -> Duration {
+    RETRY_BASE_DELAY * 2_u32.pow(attempts.saturating_sub(1))
+}
+
 struct MailHandler {
     rx: UnboundedReceiver<Mail>,
+    pool: PgPool,
+    // reused between messages, rebuilt only when SMTP settings change
+    transport: Option<(SmtpSettings, AsyncSmtpTransport<Tokio1Executor>)>,
+    retry_queue: VecDeque<PendingMail>,
 }
 
 impl MailHandler {
-    pub fn new(rx: UnboundedReceiver<Mail>) -> Self {
-        Self { rx }
+    pub fn new(rx: UnboundedReceiver<Mail>, pool: PgPool) -> Self {
+        Self {
+            rx,
+            pool,
+            transport: None,
+            retry_queue: VecDeque::new(),
+        }
     }
 
     pub fn send_result(
@@ -153,60 +208,287 @@ impl MailHandler {
     }
 
     /// Listens on rx channel for messages and sends them via SMTP.
-    pub async fn run(mut self) {
-        while let Some(mail) = self.rx.recv().await {
-            let (to, subject) = (mail.to.clone(), mail.subject.clone());
-            debug!("Sending mail to: {to}, subject: {subject}");
-
-            // fetch SMTP settings
-            let settings = Settings::get_current_settings();
-            let settings = match SmtpSettings::from_settings(settings) {
-                Ok(settings) => settings,
-                Err(MailError::SmtpNotConfigured) => {
-                    warn!("SMTP not configured, email sending skipped");
-                    continue;
+    ///
+    /// Messages which failed with a transient error are retried in between. Once `shutdown`
+    /// completes, new messages are rejected and the handler stops after sending queued ones.
+    pub async fn run(mut self, shutdown: impl Future<Output = ()>) {
+        let mut shutdown = pin!(shutdown);
+        let mut closing = false;
+        loop {
+            let retry_at = self.retry_queue.iter().map(|mail| mail.retry_at).min();
+            tokio::select! {
+                mail = self.rx.recv() => {
+                    let Some(mail) = mail else {
+                        break;
+                    };
+                    MAIL_QUEUE.record_depth(self.rx.len());
+                    let started = Instant::now();
+                    self.send(mail).await;
+                    MAIL_QUEUE.record_processed(1, started.elapsed());
                 }
-                Err(err) => {
-                    error!("Error retrieving SMTP settings: {err}");
-                    continue;
+                () = Self::wait_until(retry_at) => self.retry_pending().await,
+                () = &mut shutdown, if !closing => {
+                    info!("Stopping mail handler, {} queued emails left to send", self.rx.len());
+                    closing = true;
+                    self.rx.close();
                 }
-            };
+            }
+        }
 
-            // Construct lettre Message
-            let result_tx = mail.result_tx.clone();
-            let message: Message = match mail.into_message(&settings.sender) {
-                Ok(message) => message,
-                Err(err) => {
-                    error!("Failed to build message to: {to}, subject: {subject}, error: {err}");
-                    continue;
+        let pending = self.retry_queue.drain(..).collect::<Vec<_>>();
+        for mail in pending {
+            self.abandon(mail, "mail handler stopped").await;
+        }
+    }
+
+    async fn wait_until(deadline: Option<Instant>) {
+        match deadline {
+            Some(deadline) => sleep_until(deadline).await,
+            None => pending().await,
+        }
+    }
+
+    /// Returns sender address and SMTP transport for current settings.
+    /// The transport is only rebuilt if settings have changed since the last message.
+    fn transport(&mut self) -> Result<(String, AsyncSmtpTransport<Tokio1Executor>), MailError> {
+        let settings = SmtpSettings::from_settings(Settings::get_current_settings())?;
+        if let Some((current, transport)) = &self.transport {
+            if *current == settings {
+                return Ok((settings.sender, transport.clone()));
+            }
+        }
+
+        debug!(
+            "Building SMTP transport for {}:{}",
+            settings.server, settings.port
+        );
+        let transport = Self::mailer(settings.clone())?;
+        let sender = settings.sender.clone();
+        self.transport = Some((settings, transport.clone()));
+        Ok((sender, transport))
+    }
+
+    /// Stores record of a new outgoing message. Failing to store it doesn't prevent sending.
+    async fn record_delivery(&self, mail: &Mail) -> Option<MailDelivery<Id>> {
+        let delivery = MailDelivery::new(
+            mail.to.clone(),
+            mail.subject.clone(),
+            mail.content.clone(),
+            mail.plain_content.clone(),
+            !mail.attachments.is_empty(),
+        );
+        match delivery.save(&self.pool).await {
+            Ok(delivery) => Some(delivery),
+            Err(err) => {
+                error!(
+                    "Failed to record delivery of mail to: {}, subject: {}, error: {err}",
+                    mail.to, mail.subject
+                );
+                None
+            }
+        }
+    }
+
+    /// Applies `update` to the delivery record and stores it.
+    async fn update_delivery(
+        &self,
+        delivery: &mut Option<MailDelivery<Id>>,
+        update: impl FnOnce(&mut MailDelivery<Id>),
+    ) {
+        if let Some(delivery) = delivery {
+            update(delivery);
+            if let Err(err) = delivery.save(&self.pool).await {
+                error!(
+                    "Failed to update status of mail delivery {}: {err}",
+                    delivery.id
+                );
+            }
+        }
+    }
+
+    /// Sends a single message via SMTP.
+    #[instrument(name = "smtp_send", skip_all, fields(subject = %mail.subject))]
+    async fn send(&mut self, mail: Mail) {
+        let (to, subject) = (mail.to.clone(), mail.subject.clone());
+        debug!("Sending mail to: {to}, subject: {subject}");
+
+        let mut delivery = self.record_delivery(&mail).await;
+        let result_tx = mail.result_tx.clone();
+        let (sender, transport) = match self.transport() {
+            Ok(transport) => transport,
+            Err(MailError::SmtpNotConfigured) => {
+                warn!("SMTP not configured, email sending skipped");
+                let reason = MailError::SmtpNotConfigured.to_string();
+                self.update_delivery(&mut delivery, |delivery| {
+                    delivery.register_failure(reason, true);
+                })
+                .await;
+                Self::send_result(result_tx, Err(MailError::SmtpNotConfigured));
+                return;
+            }
+            Err(err) => {
+                error!("Error building mailer: {err}");
+                MAIL_HANDLER.record_error(&err);
+                self.update_delivery(&mut delivery, |delivery| {
+                    delivery.register_failure(err.to_string(), true);
+                })
+                .await;
+                Self::send_result(result_tx, Err(err));
+                return;
+            }
+        };
+
+        // Construct lettre Message
+        let message: Message = match mail.into_message(&sender) {
+            Ok(message) => message,
+            Err(err) => {
+                error!("Failed to build message to: {to}, subject: {subject}, error: {err}");
+                self.update_delivery(&mut delivery, |delivery| {
+                    delivery.register_failure(err.to_string(), true);
+                })
+                .await;
+                return;
+            }
+        };
+        match transport.send(message.clone()).await {
+            Ok(response) => {
+                self.update_delivery(&mut delivery, |delivery| {
+                    delivery.register_sent(describe_response(&response));
+                })
+                .await;
+                Self::send_result(result_tx, Ok(response.clone()));
+                info!(
+                    "Mail sent successfully to: {to}, subject: {subject}, response: {response:?}"
+                );
+            }
+            Err(err) => {
+                error!("Mail sending failed to: {to}, subject: {subject}, error: {err}");
+                MAIL_HANDLER.record_error(&err);
+                // callers waiting for the result get the error right away instead of a retry
+                let retry = result_tx.is_none() && !err.is_permanent();
+                self.update_delivery(&mut delivery, |delivery| {
+                    delivery.register_failure(err.to_string(), !retry);
+                })
+                .await;
+                if retry {
+                    self.schedule_retry(PendingMail {
+                        to,
+                        subject,
+                        message,
+                        attempts: 1,
+                        retry_at: Instant::now(),
+                        delivery,
+                    })
+                    .await;
+                }
+                Self::send_result(result_tx, Err(MailError::SmtpError(err)));
+            }
+        }
+    }
+
+    /// Queues failed message for redelivery with exponential backoff.
+    async fn schedule_retry(&mut self, mut mail: PendingMail) {
+        if mail.attempts >= MAX_DELIVERY_ATTEMPTS {
+            self.abandon(mail, "delivery attempts exhausted").await;
+            return;
+        }
+        if self.retry_queue.len() >= MAX_RETRY_QUEUE_SIZE {
+            self.abandon(mail, "retry queue is full").await;
+            return;
+        }
+
+        let delay = retry_delay(mail.attempts);
+        debug!(
+            "Retrying mail to: {}, subject: {} in {}s",
+            mail.to,
+            mail.subject,
+            delay.as_secs()
+        );
+        mail.retry_at = Instant::now() + delay;
+        self.retry_queue.push_back(mail);
+    }
+
+    /// Attempts redelivery of queued messages whose backoff has elapsed.
+    #[instrument(name = "smtp_retry", skip_all)]
+    async fn retry_pending(&mut self) {
+        let now = Instant::now();
+        let (due, waiting): (Vec<_>, VecDeque<_>) = self
+            .retry_queue
+            .drain(..)
+            .partition(|mail| mail.retry_at <= now);
+        self.retry_queue = waiting;
+
+        let transport = match self.transport() {
+            Ok((_, transport)) => transport,
+            Err(err) => {
+                warn!("Failed to prepare SMTP transport for mail redelivery: {err}");
+                for mut mail in due {
+                    mail.attempts += 1;
+                    self.schedule_retry(mail).await;
+                }
+                return;
+            }
+        };
+
+        for mut mail in due {
+            match transport.send(mail.message.clone()).await {
+                Ok(response) => {
+                    self.update_delivery(&mut mail.delivery, |delivery| {
+                        delivery.register_sent(describe_response(&response));
+                    })
+                    .await;
+                    info!(
+                        "Mail sent successfully to: {}, subject: {} after {} attempts, \
+                        response: {response:?}",
+                        mail.to,
+                        mail.subject,
+                        mail.attempts + 1
+                    );
                 }
-            };
-            // Build mailer and send the message
-            match Self::mailer(settings) {
-                Ok(mailer) => match mailer.send(message).await {
-                    Ok(response) => {
-                        Self::send_result(result_tx, Ok(response.clone()));
-                        info!(
-                            "Mail sent successfully to: {to}, subject: {subject}, response: {response:?}"
-                        );
-                    }
-                    Err(err) => {
-                        error!("Mail sending failed to: {to}, subject: {subject}, error: {err}");
-                        Self::send_result(result_tx, Err(MailError::SmtpError(err)));
-                    }
-                },
-                Err(MailError::SmtpNotConfigured) => {
-                    warn!("SMTP not configured, onboarding email sending skipped");
-                    Self::send_result(result_tx, Err(MailError::SmtpNotConfigured));
+                Err(err) if err.is_permanent() => {
+                    MAIL_HANDLER.record_error(&err);
+                    self.update_delivery(&mut mail.delivery, |delivery| {
+                        delivery.register_failure(err.to_string(), true);
+                    })
+                    .await;
+                    Self::dead_letter(&mail, &err.to_string());
                 }
                 Err(err) => {
-                    error!("Error building mailer: {err}");
-                    Self::send_result(result_tx, Err(err));
+                    warn!(
+                        "Mail redelivery failed to: {}, subject: {}, error: {err}",
+                        mail.to, mail.subject
+                    );
+                    MAIL_HANDLER.record_error(&err);
+                    self.update_delivery(&mut mail.delivery, |delivery| {
+                        delivery.register_failure(err.to_string(), false);
+                    })
+                    .await;
+                    mail.attempts += 1;
+                    self.schedule_retry(mail).await;
                 }
             }
         }
     }
 
+    /// Gives up on delivering the message.
+    async fn abandon(&self, mut mail: PendingMail, reason: &str) {
+        self.update_delivery(&mut mail.delivery, MailDelivery::give_up)
+            .await;
+        Self::dead_letter(&mail, reason);
+    }
+
+    /// Records message which won't be delivered.
+    fn dead_letter(mail: &PendingMail, reason: &str) {
+        error!(
+            target: DEAD_LETTER_TARGET,
+            "Giving up on mail to: {}, subject: {} after {} delivery attempts: {reason}",
+            mail.to,
+            mail.subject,
+            mail.attempts
+        );
+        MAIL_QUEUE.record_dropped(1);
+    }
+
     /// Builds mailer object with specified configuration
     fn mailer(settings: SmtpSettings) -> Result<AsyncSmtpTransport<Tokio1Executor>, MailError> {
         let builder = match settings.encryption {
@@ -235,9 +517,26 @@ impl MailHandler {
     }
 }
 
-/// Builds MailHandler and runs it.
+/// Builds MailHandler and runs it until `shutdown` completes and queued messages are sent.
 #[instrument(skip_all)]
-pub async fn run_mail_handler(rx: UnboundedReceiver<Mail>) {
+pub async fn run_mail_handler(
+    rx: UnboundedReceiver<Mail>,
+    pool: PgPool,
+    shutdown: impl Future<Output = ()>,
+) {
     info!("Starting mail sending service");
-    MailHandler::new(rx).run().await;
+    MailHandler::new(rx, pool).run(shutdown).await;
+    info!("Mail sending service stopped");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), RETRY_BASE_DELAY);
+        assert_eq!(retry_delay(2), RETRY_BASE_DELAY * 2);
+        assert_eq!(retry_delay(5), RETRY_BASE_DELAY * 16);
+    }
 }
@@ -0,0 +1,207 @@
+//! Plaintext rendering of HTML mail bodies, used as the `text/plain` alternative part.
+
+// elements whose content is never rendered
+const SKIPPED_ELEMENTS: [&str; 4] = ["head", "style", "script", "title"];
+// elements which start a new line of text
+const BLOCK_ELEMENTS: [&str; 14] = [
+    "br", "p", "div", "tr", "table", "li", "ul", "ol", "h1", "h2", "h3", "h4", "h5", "h6",
+];
+
+/// Converts HTML document into readable plaintext.
+///
+/// Markup, comments and styles are dropped, block elements are separated by blank lines and
+/// link targets are appended after the link text.
+#[must_use]
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut link_href: Option<String> = None;
+    let mut link_text_start = 0;
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        push_text(&mut text, &rest[..start]);
+        rest = &rest[start..];
+
+        // comments, including conditional ones used for Outlook
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+
+        let Some(end) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        if !closing && SKIPPED_ELEMENTS.contains(&name.as_str()) {
+            let closing_tag = format!("</{name}");
+            rest = find_ignore_case(rest, &closing_tag)
+                .and_then(|position| {
+                    rest[position..]
+                        .find('>')
+                        .map(|end| &rest[position + end + 1..])
+                })
+                .unwrap_or("");
+            continue;
+        }
+
+        match (name.as_str(), closing) {
+            ("a", false) => {
+                link_href = attribute(tag, "href");
+                link_text_start = text.len();
+            }
+            ("a", true) => {
+                if let Some(href) = link_href.take() {
+                    if !href.is_empty() && !text[link_text_start..].contains(&href) {
+                        text.push_str(" (");
+                        text.push_str(&href);
+                        text.push(')');
+                    }
+                }
+            }
+            (name, _) if BLOCK_ELEMENTS.contains(&name) => text.push('\n'),
+            ("td" | "th", true) => text.push(' '),
+            _ => {}
+        }
+    }
+    push_text(&mut text, rest);
+
+    normalize_lines(&text)
+}
+
+/// Appends decoded text content, collapsing whitespace into single spaces.
+fn push_text(text: &mut String, content: &str) {
+    let decoded = decode_entities(content);
+    for (index, word) in decoded.split_whitespace().enumerate() {
+        if (index > 0 || decoded.starts_with(char::is_whitespace)) && !text.ends_with([' ', '\n']) {
+            text.push(' ');
+        }
+        text.push_str(word);
+    }
+    if decoded.ends_with(char::is_whitespace) && !text.ends_with([' ', '\n']) {
+        text.push(' ');
+    }
+}
+
+/// Trims lines and leaves at most a single blank line between paragraphs.
+fn normalize_lines(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+
+    lines.join("\n")
+}
+
+fn decode_entities(content: &str) -> String {
+    let mut decoded = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end]).map(|value| (value, end)));
+        match entity {
+            Some((value, end)) => {
+                decoded.push(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = entity.strip_prefix('#')?;
+            let code = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+/// Extracts value of a quoted attribute from tag content.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let position = find_ignore_case(tag, &format!("{name}="))?;
+    let value = &tag[position + name.len() + 1..];
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &value[1..];
+    let end = value.find(quote)?;
+
+    Some(decode_entities(&value[..end]))
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .to_ascii_lowercase()
+        .find(&needle.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_html_to_text() {
+        let html = r#"<!doctype html>
+            <html>
+            <head><title>Title</title><style>p { margin: 0; }</style></head>
+            <body>
+              <!--[if mso]><table><tr><td>outlook</td></tr></table><![endif]-->
+              <p>Hello   <b>John</b>,</p>
+              <p>Your code is:&nbsp;123&amp;456</p>
+              <div>Line<br/>break</div>
+              <a href="https://example.com/reset?a=1&amp;b=2">Reset password</a>
+              <a href="https://example.com">https://example.com</a>
+            </body>
+            </html>"#;
+
+        assert_eq!(
+            html_to_text(html),
+            "Hello John,\n\nYour code is: 123&456\n\nLine\nbreak\n\
+            Reset password (https://example.com/reset?a=1&b=2) https://example.com"
+        );
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(
+            decode_entities("&lt;b&gt; &#39;x&#x27; &unknown; & done"),
+            "<b> 'x' &unknown; & done"
+        );
+    }
+}
@@ -1,13 +1,22 @@
 use std::collections::HashMap;
 
 use chrono::{Datelike, NaiveDateTime, Utc};
-use defguard_common::{VERSION, config::server_config, db::models::user::MFAMethod};
+use chrono_tz::Tz;
+use defguard_common::{
+    VERSION,
+    config::server_config,
+    db::{
+        Id,
+        models::{MailTemplateOverride, Settings, settings::get_settings, user::MFAMethod},
+    },
+    timezone::localize,
+};
 use reqwest::Url;
 use serde::Serialize;
 use serde_json::Value;
-use tera::{Context, Function, Tera};
+use tera::{Context, Function, Tera, escape_html};
 use thiserror::Error;
-use tracing::debug;
+use tracing::{debug, error};
 
 static MAIL_BASE: &str = include_str!("../templates/base.tera");
 static MAIL_MACROS: &str = include_str!("../templates/macros.tera");
@@ -33,7 +42,14 @@ static MAIL_PASSWORD_RESET_START: &str =
     include_str!("../templates/mail_password_reset_start.tera");
 static MAIL_PASSWORD_RESET_SUCCESS: &str =
     include_str!("../templates/mail_password_reset_success.tera");
-static MAIL_DATETIME_FORMAT: &str = "%A, %B %d, %Y at %r";
+static MAIL_EMAIL_VERIFICATION: &str = include_str!("../templates/mail_email_verification.tera");
+static MAIL_CLIENT_MFA_LOCKED_OUT: &str =
+    include_str!("../templates/mail_client_mfa_locked_out.tera");
+static MAIL_DEVICE_EXPIRED: &str = include_str!("../templates/mail_device_expired.tera");
+static MAIL_DEVICE_APPROVAL: &str = include_str!("../templates/mail_device_approval.tera");
+static MAIL_NOTIFICATION_DIGEST: &str = include_str!("../templates/mail_notification_digest.tera");
+static MAIL_GROUP_JOIN_REQUEST: &str = include_str!("../templates/mail_group_join_request.tera");
+static MAIL_DATETIME_FORMAT: &str = "%A, %B %d, %Y at %r (UTC%:z)";
 
 #[derive(Error, Debug)]
 pub enum TemplateError {
@@ -72,6 +88,67 @@ pub struct UserContext {
     pub first_name: String,
 }
 
+/// Subject and content of an email rendered from a template which admins can override.
+pub struct RenderedMail {
+    pub subject: String,
+    pub content: String,
+}
+
+/// Checks that admin-provided template can be parsed.
+pub fn validate_custom_template(template: &str) -> Result<(), TemplateError> {
+    let (mut tera, _) = get_base_tera(None, None, None, None)?;
+    tera.add_raw_template("custom", template)?;
+
+    Ok(())
+}
+
+/// Renders admin-provided template, logging errors so that the built-in one can be used instead.
+fn render_custom(tera: &mut Tera, context: &Context, name: &str, template: &str) -> Option<String> {
+    tera.add_raw_template(name, template)
+        .and_then(|()| tera.render(name, context))
+        .inspect_err(|err| {
+            error!("Failed to render custom mail template {name}, using the built-in one: {err}");
+        })
+        .ok()
+}
+
+/// Renders subject and content using overrides configured by admins, if there are any.
+fn render_with_override(
+    mut tera: Tera,
+    context: &Context,
+    name: &str,
+    template: &str,
+    subject: &str,
+    custom: Option<&MailTemplateOverride<Id>>,
+) -> Result<RenderedMail, TemplateError> {
+    let subject = custom
+        .and_then(|custom| custom.subject.as_deref())
+        .and_then(|custom| render_custom(&mut tera, context, &format!("{name}_subject"), custom))
+        .unwrap_or_else(|| subject.to_string());
+    let content = match custom
+        .and_then(|custom| custom.body.as_deref())
+        .and_then(|custom| render_custom(&mut tera, context, &format!("{name}_custom"), custom))
+    {
+        Some(content) => content,
+        None => {
+            tera.add_raw_template(name, template)?;
+            tera.render(name, context)?
+        }
+    };
+
+    Ok(RenderedMail { subject, content })
+}
+
+/// Formats a naive UTC timestamp in the deployment timezone.
+fn format_mail_datetime(timestamp: NaiveDateTime) -> String {
+    let timezone = get_settings()
+        .as_ref()
+        .map_or(Tz::UTC, Settings::deployment_timezone);
+    localize(timestamp, timezone)
+        .format(MAIL_DATETIME_FORMAT)
+        .to_string()
+}
+
 fn get_base_tera(
     external_context: Option<Context>,
     session: Option<&SessionContext>,
@@ -87,7 +164,7 @@ fn get_base_tera(
     let now = Utc::now();
     let current_year = format!("{:04}", now.year());
     context.insert("current_year", &current_year);
-    context.insert("date_now", &now.format(MAIL_DATETIME_FORMAT).to_string());
+    context.insert("date_now", &format_mail_datetime(now.naive_utc()));
 
     if let Some(current_session) = session {
         let device_info = &current_session.device_info;
@@ -118,7 +195,9 @@ pub fn enrollment_start_mail(
     context: Context,
     mut enrollment_service_url: Url,
     enrollment_token: &str,
-) -> Result<String, TemplateError> {
+    subject: &str,
+    custom: Option<&MailTemplateOverride<Id>>,
+) -> Result<RenderedMail, TemplateError> {
     debug!("Render an enrollment start mail template for the user.");
     let (mut tera, mut context) = get_base_tera(Some(context), None, None, None)?;
 
@@ -134,9 +213,14 @@ pub fn enrollment_start_mail(
 
     context.insert("link_url", &enrollment_service_url.to_string());
 
-    tera.add_raw_template("mail_enrollment_start", MAIL_ENROLLMENT_START)?;
-
-    Ok(tera.render("mail_enrollment_start", &context)?)
+    render_with_override(
+        tera,
+        &context,
+        "mail_enrollment_start",
+        MAIL_ENROLLMENT_START,
+        subject,
+        custom,
+    )
 }
 // mail with link to enrollment service
 pub fn desktop_start_mail(
@@ -243,16 +327,21 @@ pub fn mfa_configured_mail(
 pub fn new_device_login_mail(
     session: &SessionContext,
     created: NaiveDateTime,
-) -> Result<String, TemplateError> {
+    subject: &str,
+    custom: Option<&MailTemplateOverride<Id>>,
+) -> Result<RenderedMail, TemplateError> {
     let (mut tera, mut context) = get_base_tera(None, Some(session), None, None)?;
     tera.add_raw_template("mail_base", MAIL_BASE)?;
-    context.insert(
-        "date_now",
-        &created.format(MAIL_DATETIME_FORMAT).to_string(),
-    );
-
-    tera.add_raw_template("mail_new_device_login", MAIL_NEW_DEVICE_LOGIN)?;
-    Ok(tera.render("mail_new_device_login", &context)?)
+    context.insert("date_now", &format_mail_datetime(created));
+
+    render_with_override(
+        tera,
+        &context,
+        "mail_new_device_login",
+        MAIL_NEW_DEVICE_LOGIN,
+        subject,
+        custom,
+    )
 }
 
 pub fn new_device_ocid_login_mail(
@@ -271,6 +360,56 @@ pub fn new_device_ocid_login_mail(
     Ok(tera.render("mail_new_device_oicd_login", &context)?)
 }
 
+/// Notification included in a digest email.
+pub struct DigestNotification {
+    pub title: String,
+    pub summary: String,
+    pub created: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+struct DigestNotificationContext {
+    title: String,
+    summary: String,
+    created: String,
+}
+
+pub fn notification_digest_mail(
+    notifications: &[DigestNotification],
+) -> Result<String, TemplateError> {
+    let (mut tera, mut context) = get_base_tera(None, None, None, None)?;
+    // rendered as HTML, while summaries may contain e.g. user agent strings
+    let notifications: Vec<_> = notifications
+        .iter()
+        .map(|notification| DigestNotificationContext {
+            title: escape_html(&notification.title),
+            summary: escape_html(&notification.summary),
+            created: format_mail_datetime(notification.created),
+        })
+        .collect();
+    context.insert("notifications", &notifications);
+    tera.add_raw_template("mail_notification_digest", MAIL_NOTIFICATION_DIGEST)?;
+    Ok(tera.render("mail_notification_digest", &context)?)
+}
+
+/// Notifies group owner about a user asking to join the group.
+pub fn group_join_request_mail(
+    user: &UserContext,
+    username: &str,
+    group_name: &str,
+    comment: Option<&str>,
+) -> Result<String, TemplateError> {
+    let (mut tera, mut context) = get_base_tera(None, None, None, None)?;
+    // rendered as HTML, while all of these are provided by the user
+    context.insert("first_name", &escape_html(&user.first_name));
+    context.insert("last_name", &escape_html(&user.last_name));
+    context.insert("username", &escape_html(username));
+    context.insert("group_name", &escape_html(group_name));
+    context.insert("comment", &comment.map(escape_html));
+    tera.add_raw_template("mail_group_join_request", MAIL_GROUP_JOIN_REQUEST)?;
+    Ok(tera.render("mail_group_join_request", &context)?)
+}
+
 pub fn gateway_disconnected_mail(
     gateway_name: &str,
     gateway_ip: &str,
@@ -317,16 +456,24 @@ pub fn email_mfa_code_mail(
     user: &UserContext,
     code: &str,
     session: Option<&SessionContext>,
-) -> Result<String, TemplateError> {
+    subject: &str,
+    custom: Option<&MailTemplateOverride<Id>>,
+) -> Result<RenderedMail, TemplateError> {
     let (mut tera, mut context) = get_base_tera(None, session, None, None)?;
     let timeout = server_config().mfa_code_timeout;
     // zero-pad code to make sure it's always 6 digits long
     context.insert("code", &format!("{code:0>6}"));
     context.insert("timeout", &timeout.to_string());
     context.insert("name", &user.first_name);
-    tera.add_raw_template("mail_email_mfa_code", MAIL_EMAIL_MFA_CODE)?;
 
-    Ok(tera.render("mail_email_mfa_code", &context)?)
+    render_with_override(
+        tera,
+        &context,
+        "mail_email_mfa_code",
+        MAIL_EMAIL_MFA_CODE,
+        subject,
+        custom,
+    )
 }
 
 pub fn email_password_reset_mail(
@@ -364,10 +511,77 @@ pub fn email_password_reset_success_mail(
     Ok(tera.render("mail_passowrd_reset_success", &context)?)
 }
 
+pub fn email_verification_mail(
+    email: &str,
+    verification_token: &str,
+    ip_address: Option<&str>,
+    device_info: Option<&str>,
+) -> Result<String, TemplateError> {
+    let (mut tera, mut context) = get_base_tera(None, None, ip_address, device_info)?;
+
+    let mut link_url = server_config().url.clone();
+    link_url.set_path("/verify-email");
+    link_url
+        .query_pairs_mut()
+        .append_pair("token", verification_token);
+
+    context.insert("defguard_url", &server_config().url);
+    context.insert("email", email);
+    context.insert("link_url", &link_url.to_string());
+
+    tera.add_raw_template("mail_email_verification", MAIL_EMAIL_VERIFICATION)?;
+
+    Ok(tera.render("mail_email_verification", &context)?)
+}
+
+pub fn client_mfa_locked_out_mail(
+    session: Option<&SessionContext>,
+    device_name: &str,
+    location_name: &str,
+    locked_until: NaiveDateTime,
+) -> Result<String, TemplateError> {
+    let (mut tera, mut context) = get_base_tera(None, session, None, None)?;
+    context.insert("device_name", device_name);
+    context.insert("location_name", location_name);
+    context.insert("locked_until", &format_mail_datetime(locked_until));
+    tera.add_raw_template("mail_client_mfa_locked_out", MAIL_CLIENT_MFA_LOCKED_OUT)?;
+
+    Ok(tera.render("mail_client_mfa_locked_out", &context)?)
+}
+
+pub fn device_expired_mail(
+    device_name: &str,
+    expired_at: NaiveDateTime,
+) -> Result<String, TemplateError> {
+    let (mut tera, mut context) = get_base_tera(None, None, None, None)?;
+    context.insert("device_name", device_name);
+    context.insert("expired_at", &format_mail_datetime(expired_at));
+    tera.add_raw_template("mail_device_expired", MAIL_DEVICE_EXPIRED)?;
+
+    Ok(tera.render("mail_device_expired", &context)?)
+}
+
+pub fn device_approval_mail(
+    device_name: &str,
+    location_name: &str,
+    approved: bool,
+) -> Result<String, TemplateError> {
+    let (mut tera, mut context) = get_base_tera(None, None, None, None)?;
+    context.insert("device_name", device_name);
+    context.insert("location_name", location_name);
+    context.insert("approved", &approved);
+    tera.add_raw_template("mail_device_approval", MAIL_DEVICE_APPROVAL)?;
+
+    Ok(tera.render("mail_device_approval", &context)?)
+}
+
 #[cfg(test)]
 mod test {
     use claims::assert_ok;
-    use defguard_common::config::{DefGuardConfig, SERVER_CONFIG};
+    use defguard_common::{
+        config::{DefGuardConfig, SERVER_CONFIG},
+        db::models::MailTemplateKind,
+    };
 
     use super::*;
 
@@ -413,10 +627,40 @@ mod test {
         assert_ok!(enrollment_start_mail(
             Context::new(),
             Url::parse("http://localhost:8080").unwrap(),
-            "test_token"
+            "test_token",
+            "Subject",
+            None
         ));
     }
 
+    #[test]
+    fn test_mail_template_override() {
+        let _ = SERVER_CONFIG.set(DefGuardConfig::new_test_config());
+        let user = UserContext {
+            last_name: "test_last".into(),
+            first_name: "test_first".into(),
+        };
+        let mut custom = MailTemplateOverride {
+            id: 1,
+            kind: MailTemplateKind::EmailMfaCode,
+            subject: Some("Code {{ code }}".into()),
+            body: Some("Hi {{ name }}, your code is {{ code }}".into()),
+        };
+        let mail = email_mfa_code_mail(&user, "123", None, "Default", Some(&custom)).unwrap();
+        assert_eq!(mail.subject, "Code 000123");
+        assert_eq!(mail.content, "Hi test_first, your code is 000123");
+
+        // broken templates fall back to built-in ones
+        custom.subject = Some("{{ missing }}".into());
+        custom.body = Some("{% if %}".into());
+        let mail = email_mfa_code_mail(&user, "123", None, "Default", Some(&custom)).unwrap();
+        assert_eq!(mail.subject, "Default");
+        assert!(mail.content.contains("000123"));
+
+        assert!(validate_custom_template("{% extends \"base.tera\" %}").is_ok());
+        assert!(validate_custom_template("{% if %}").is_err());
+    }
+
     #[test]
     fn test_enrollment_welcome_mail() {
         assert_ok!(enrollment_welcome_mail(
@@ -454,6 +698,31 @@ mod test {
             None,
         ));
     }
+    #[test]
+    fn test_notification_digest_mail() {
+        let notifications = [DigestNotification {
+            title: "New device login".into(),
+            summary: "<script> from 10.0.0.1".into(),
+            created: NaiveDateTime::default(),
+        }];
+        let content = notification_digest_mail(&notifications).unwrap();
+        assert!(content.contains("New device login"));
+        assert!(content.contains("&lt;script&gt; from 10.0.0.1"));
+    }
+
+    #[test]
+    fn test_group_join_request_mail() {
+        let user = UserContext {
+            last_name: "Potter".into(),
+            first_name: "Harry".into(),
+        };
+        let content =
+            group_join_request_mail(&user, "hpotter", "quidditch", Some("<b>Seeker</b>")).unwrap();
+        assert!(content.contains("Harry Potter (hpotter) asked to join group quidditch"));
+        assert!(content.contains("&lt;b&gt;Seeker&lt;&#x2F;b&gt;"));
+        assert_ok!(group_join_request_mail(&user, "hpotter", "quidditch", None));
+    }
+
     #[test]
     fn test_gateway_disconnected() {
         assert_ok!(gateway_disconnected_mail(
@@ -478,6 +747,38 @@ mod test {
         ));
     }
 
+    #[test]
+    fn test_client_mfa_locked_out_mail() {
+        assert_ok!(client_mfa_locked_out_mail(
+            None,
+            "Laptop",
+            "Location1",
+            Utc::now().naive_utc()
+        ));
+    }
+
+    #[test]
+    fn test_device_expired_mail() {
+        assert_ok!(device_expired_mail("Laptop", Utc::now().naive_utc()));
+    }
+
+    #[test]
+    fn test_device_approval_mail() {
+        assert_ok!(device_approval_mail("Laptop", "Office", true));
+        assert_ok!(device_approval_mail("Laptop", "Office", false));
+    }
+
+    #[test]
+    fn test_email_verification_mail() {
+        let _ = SERVER_CONFIG.set(DefGuardConfig::new_test_config());
+        assert_ok!(email_verification_mail(
+            "new@example.com",
+            "test_token",
+            Some("1.1.1.1"),
+            None
+        ));
+    }
+
     #[test]
     fn dg25_8_server_side_template_injection() {
         let mut tera = safe_tera();
@@ -1,5 +1,10 @@
+use std::{env, path::PathBuf};
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = PathBuf::from(env::var("OUT_DIR")?);
     tonic_prost_build::configure()
+        // Used by the gRPC reflection service.
+        .file_descriptor_set_path(out_dir.join("defguard_descriptor.bin"))
         // These types contain sensitive data.
         .skip_debug([
             "ActivateUserRequest",
@@ -18,6 +18,9 @@ pub mod enterprise {
     }
 }
 
+/// Encoded descriptors of all services and messages, used by the gRPC reflection service.
+pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("defguard_descriptor");
+
 use proxy::{CoreError, MfaMethod};
 use serde::Serialize;
 use tonic::Status;
@@ -34,6 +37,7 @@ impl fmt::Display for MfaMethod {
                 Self::Oidc => "OIDC",
                 Self::Biometric => "Biometric",
                 Self::MobileApprove => "MobileApprove",
+                Self::Webauthn => "WebAuthn",
             }
         )
     }
@@ -52,6 +56,7 @@ impl Serialize for MfaMethod {
             Self::MobileApprove => {
                 serializer.serialize_unit_variant("MfaMethod", 4, "MobileApprove")
             }
+            Self::Webauthn => serializer.serialize_unit_variant("MfaMethod", 5, "Webauthn"),
         }
     }
 }
@@ -88,6 +88,9 @@ pub enum DefguardVersionError {
 
     #[error("Invalid DefguardComponent: {0}")]
     InvalidDefguardComponent(String),
+
+    #[error("Invalid log format: {0}")]
+    InvalidLogFormat(String),
 }
 
 /// Represents the different types of Defguard components that can communicate via gRPC.
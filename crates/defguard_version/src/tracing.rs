@@ -75,22 +75,31 @@
 //! 2. **`VersionSuffixFormat`** - Custom formatter that adds version suffixes to log messages
 //! 3. **`VersionFilteredFields`** - Field formatter that excludes version fields from normal output
 //! 4. **Utility functions** - Extract and format version information from span hierarchy
+//!
+//! # JSON Output
+//!
+//! With [`LogFormat::Json`] every log line is a JSON object, so logs can be ingested by log
+//! aggregators without parsing. `timestamp`, `level`, `module`, `message`, `request_id`, `user`
+//! and `ip` are always present (`null` if unknown), followed by fields of the event and of
+//! enclosing spans. Version information is placed in `version`, `component` and
+//! `component_version` fields instead of a suffix.
 
 use std::{fmt, str::FromStr};
 
 use semver::Version;
 use serde::Serialize;
-use tracing::{Level, Subscriber};
+use serde_json::{Map, Value};
+use tracing::{Level, Subscriber, field::Field};
 use tracing_subscriber::{
     EnvFilter, Layer,
     field::RecordFields,
     fmt::{
         FmtContext, FormatEvent, FormatFields,
         format::{Format, Full, Writer},
-        time::SystemTime,
+        time::{FormatTime, SystemTime},
     },
     layer::{Context, SubscriberExt},
-    registry::LookupSpan,
+    registry::{LookupSpan, Registry},
     util::SubscriberInitExt,
 };
 
@@ -404,6 +413,184 @@ impl tracing::field::Visit for FieldFilterVisitor<'_> {
     }
 }
 
+/// Output format of log lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines with version suffix.
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = DefguardVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(Self::Err::InvalidLogFormat(s.to_string())),
+        }
+    }
+}
+
+/// Fields present in every JSON log line, `null` if not known in the current context.
+const JSON_CONTEXT_FIELDS: [&str; 3] = ["request_id", "user", "ip"];
+
+/// Span fields carrying remote component version, output separately.
+const VERSION_FIELDS: [&str; 3] = ["component", "version", "info"];
+
+/// Span or event fields collected as JSON values.
+#[derive(Clone, Debug, Default)]
+pub struct JsonFields(pub Map<String, Value>);
+
+impl tracing::field::Visit for JsonFields {
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
+
+/// A layer that captures span fields for [`JsonFormat`], including the ones recorded after
+/// the span has been created, e.g. the user once the request has been authenticated.
+pub struct JsonFieldLayer;
+
+impl<S> Layer<S> for JsonFieldLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(
+        &self,
+        attrs: &tracing::span::Attributes<'_>,
+        id: &tracing::span::Id,
+        ctx: Context<'_, S>,
+    ) {
+        if let Some(span) = ctx.span(id) {
+            let mut fields = JsonFields::default();
+            attrs.record(&mut fields);
+            span.extensions_mut().insert(fields);
+        }
+    }
+
+    fn on_record(
+        &self,
+        id: &tracing::span::Id,
+        values: &tracing::span::Record<'_>,
+        ctx: Context<'_, S>,
+    ) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(fields) = extensions.get_mut::<JsonFields>() {
+            values.record(fields);
+        }
+    }
+}
+
+/// Tracing formatter writing each event as a single line JSON object.
+pub struct JsonFormat {
+    pub component_info: ComponentInfo,
+}
+
+impl JsonFormat {
+    #[must_use]
+    pub fn new(own_version: crate::Version) -> Self {
+        Self {
+            component_info: ComponentInfo::new(own_version),
+        }
+    }
+}
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &tracing::Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut timestamp = String::new();
+        SystemTime.format_time(&mut Writer::new(&mut timestamp))?;
+
+        let mut line = Map::new();
+        line.insert("timestamp".into(), timestamp.into());
+        line.insert("level".into(), metadata.level().as_str().into());
+        line.insert(
+            "module".into(),
+            metadata.module_path().unwrap_or(metadata.target()).into(),
+        );
+        for field in JSON_CONTEXT_FIELDS {
+            line.insert(field.into(), Value::Null);
+        }
+
+        // fields of inner spans take precedence over the outer ones
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                line.insert("span".into(), span.name().into());
+                if let Some(fields) = span.extensions().get::<JsonFields>() {
+                    for (name, value) in &fields.0 {
+                        if !VERSION_FIELDS.contains(&name.as_str()) {
+                            line.insert(name.clone(), value.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+        line.extend(fields.0);
+
+        let extracted = extract_version_info_from_context(ctx);
+        line.insert(
+            "version".into(),
+            self.component_info.version.to_string().into(),
+        );
+        if let (Some(component), Some(version)) = (&extracted.component, &extracted.version) {
+            line.insert("component".into(), component.to_string().into());
+            line.insert("component_version".into(), version.clone().into());
+        }
+        if *metadata.level() == Level::ERROR {
+            line.insert(
+                "system".into(),
+                self.component_info.system.to_string().into(),
+            );
+            if let Some(info) = extracted.info {
+                line.insert("component_system".into(), info.into());
+            }
+        }
+
+        let json = serde_json::to_string(&line).map_err(|_| fmt::Error)?;
+        writeln!(writer, "{json}")
+    }
+}
+
 /// Initializes tracing with custom formatter that conditionally displays version information.
 ///
 /// The formatter will:
@@ -424,22 +611,115 @@ impl tracing::field::Visit for FieldFilterVisitor<'_> {
 /// defguard_version::tracing::init(defguard_version::Version::new(1, 5, 0), "info");
 /// ```
 pub fn init(own_version: crate::Version, log_level: &str) -> Result<(), DefguardVersionError> {
+    init_with_layer(own_version, log_level, LogFormat::Text, None)
+}
+
+/// Same as [`init`], writing logs in given `format` and additionally installing `layer`,
+/// e.g. to export spans to a tracing backend.
+/// The layer receives spans and events accepted by the log level filter.
+pub fn init_with_layer(
+    own_version: crate::Version,
+    log_level: &str,
+    format: LogFormat,
+    layer: Option<Box<dyn Layer<Registry> + Send + Sync>>,
+) -> Result<(), DefguardVersionError> {
+    let (text_layer, json_layer) = match format {
+        LogFormat::Text => (
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(true)
+                    .event_format(VersionSuffixFormat::new(
+                        own_version,
+                        Format::default().with_ansi(true),
+                    ))
+                    .fmt_fields(VersionFilteredFields),
+            ),
+            None,
+        ),
+        LogFormat::Json => (
+            None,
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .event_format(JsonFormat::new(own_version)),
+            ),
+        ),
+    };
+    let json_field_layer = json_layer.as_ref().map(|_| JsonFieldLayer);
+
     tracing_subscriber::registry()
+        .with(layer)
         .with(
             EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| format!("{log_level},h2=info").into()),
         )
         .with(VersionFieldLayer)
-        .with(
-            tracing_subscriber::fmt::layer()
-                .with_ansi(true)
-                .event_format(VersionSuffixFormat::new(
-                    own_version,
-                    Format::default().with_ansi(true),
-                ))
-                .fmt_fields(VersionFilteredFields),
-        )
+        .with(json_field_layer)
+        .with(text_layer)
+        .with(json_layer)
         .init();
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::{field, info, info_span, subscriber::with_default};
+    use tracing_subscriber::fmt::MakeWriter;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_json_format() {
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::registry().with(JsonFieldLayer).with(
+            tracing_subscriber::fmt::layer()
+                .event_format(JsonFormat::new(Version::new(1, 6, 0)))
+                .with_writer(buffer.clone()),
+        );
+        with_default(subscriber, || {
+            let span = info_span!("http_request", request_id = "abc", user = field::Empty);
+            let _guard = span.enter();
+            span.record("user", "admin");
+            info!(count = 3, "Listing users");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["module"], "defguard_version::tracing::test");
+        assert_eq!(line["message"], "Listing users");
+        assert_eq!(line["span"], "http_request");
+        assert_eq!(line["request_id"], "abc");
+        assert_eq!(line["user"], "admin");
+        assert_eq!(line["ip"], Value::Null);
+        assert_eq!(line["count"], 3);
+        assert_eq!(line["version"], "1.6.0");
+    }
+}
@@ -28,7 +28,7 @@
       };
       # define shared build inputs
       nativeBuildInputs = with pkgs; [rustToolchain pkg-config];
-      buildInputs = with pkgs; [openssl protobuf curl nodejs_24 pnpm];
+      buildInputs = with pkgs; [openssl protobuf curl nodejs_24 pnpm libxml2 xmlsec];
     in {
       devShells.default = pkgs.mkShell {
         inherit nativeBuildInputs buildInputs;
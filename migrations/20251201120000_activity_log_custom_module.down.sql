@@ -0,0 +1,11 @@
+DELETE FROM activity_log_event WHERE module = 'custom';
+ALTER TYPE activity_log_module RENAME TO activity_log_module_old;
+CREATE TYPE activity_log_module AS ENUM (
+    'defguard',
+    'client',
+    'vpn',
+    'enrollment'
+);
+ALTER TABLE activity_log_event
+    ALTER COLUMN module TYPE activity_log_module USING module::text::activity_log_module;
+DROP TYPE activity_log_module_old;
@@ -0,0 +1,2 @@
+ALTER TABLE wireguard_network DROP COLUMN "ip_assignment_strategy";
+DROP TYPE ip_assignment_strategy;
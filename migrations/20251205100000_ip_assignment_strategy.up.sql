@@ -0,0 +1,7 @@
+CREATE TYPE ip_assignment_strategy AS ENUM (
+    'sequential',
+    'random',
+    'pubkey_hash'
+);
+
+ALTER TABLE wireguard_network ADD COLUMN "ip_assignment_strategy" ip_assignment_strategy NOT NULL DEFAULT 'sequential';
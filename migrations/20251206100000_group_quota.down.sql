@@ -0,0 +1,2 @@
+ALTER TABLE "group" DROP COLUMN max_devices;
+ALTER TABLE "group" DROP COLUMN max_users;
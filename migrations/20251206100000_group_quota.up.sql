@@ -0,0 +1,2 @@
+ALTER TABLE "group" ADD COLUMN max_users integer NULL;
+ALTER TABLE "group" ADD COLUMN max_devices integer NULL;
@@ -0,0 +1 @@
+ALTER TABLE api_token ADD COLUMN location_id bigint NULL REFERENCES wireguard_network(id) ON DELETE CASCADE;
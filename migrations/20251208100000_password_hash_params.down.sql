@@ -0,0 +1,3 @@
+ALTER TABLE settings DROP COLUMN password_hash_parallelism;
+ALTER TABLE settings DROP COLUMN password_hash_time_cost;
+ALTER TABLE settings DROP COLUMN password_hash_memory_cost;
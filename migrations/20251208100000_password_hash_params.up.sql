@@ -0,0 +1,4 @@
+-- defaults match the argon2id parameters used so far
+ALTER TABLE settings ADD COLUMN password_hash_memory_cost integer NOT NULL DEFAULT 19456;
+ALTER TABLE settings ADD COLUMN password_hash_time_cost integer NOT NULL DEFAULT 2;
+ALTER TABLE settings ADD COLUMN password_hash_parallelism integer NOT NULL DEFAULT 1;
@@ -0,0 +1,3 @@
+DROP TABLE ldap_sync_conflict;
+ALTER TABLE "group" DROP COLUMN sync_authority;
+DROP TYPE group_sync_authority;
@@ -0,0 +1,16 @@
+CREATE TYPE group_sync_authority AS ENUM (
+    'local',
+    'ldap',
+    'merge'
+);
+
+ALTER TABLE "group" ADD COLUMN sync_authority group_sync_authority NULL;
+
+CREATE TABLE ldap_sync_conflict (
+    id bigserial PRIMARY KEY,
+    group_name text NOT NULL,
+    defguard_only text[] NOT NULL,
+    ldap_only text[] NOT NULL,
+    resolution group_sync_authority NOT NULL,
+    detected_at timestamp without time zone NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE "user" DROP COLUMN timezone;
+ALTER TABLE settings DROP COLUMN timezone;
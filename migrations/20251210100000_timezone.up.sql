@@ -0,0 +1,2 @@
+ALTER TABLE settings ADD COLUMN timezone text NOT NULL DEFAULT 'UTC';
+ALTER TABLE "user" ADD COLUMN timezone text NULL;
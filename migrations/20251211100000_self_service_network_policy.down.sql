@@ -0,0 +1,2 @@
+ALTER TABLE enterprisesettings DROP COLUMN self_service_allow_vpn;
+ALTER TABLE enterprisesettings DROP COLUMN self_service_allowed_networks;
@@ -0,0 +1,3 @@
+-- restricts where enrollment and password reset may be started from
+ALTER TABLE enterprisesettings ADD COLUMN self_service_allowed_networks inet[] NOT NULL DEFAULT '{}';
+ALTER TABLE enterprisesettings ADD COLUMN self_service_allow_vpn boolean NOT NULL DEFAULT false;
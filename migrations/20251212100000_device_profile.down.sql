@@ -0,0 +1,2 @@
+DROP TABLE device_profile_device;
+DROP TABLE device_profile;
@@ -0,0 +1,14 @@
+CREATE TABLE device_profile (
+    id bigserial PRIMARY KEY,
+    name text NOT NULL UNIQUE,
+    description text NULL,
+    dns text NULL,
+    keepalive_interval integer NULL,
+    -- empty means all locations are allowed
+    allowed_locations bigint[] NOT NULL DEFAULT '{}'
+);
+
+CREATE TABLE device_profile_device (
+    device_id bigint PRIMARY KEY REFERENCES device(id) ON DELETE CASCADE,
+    profile_id bigint NOT NULL REFERENCES device_profile(id) ON DELETE CASCADE
+);
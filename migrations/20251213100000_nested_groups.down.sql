@@ -0,0 +1,2 @@
+DROP VIEW group_user_effective;
+ALTER TABLE "group" DROP COLUMN parent_id;
@@ -0,0 +1,12 @@
+ALTER TABLE "group" ADD COLUMN parent_id bigint NULL REFERENCES "group"(id) ON DELETE SET NULL;
+
+-- Direct group memberships extended with memberships inherited from ancestor groups.
+CREATE VIEW group_user_effective AS
+WITH RECURSIVE membership(user_id, group_id) AS (
+    SELECT user_id, group_id FROM group_user
+    UNION
+    SELECT m.user_id, g.parent_id FROM membership m
+    JOIN "group" g ON g.id = m.group_id
+    WHERE g.parent_id IS NOT NULL
+)
+SELECT user_id, group_id FROM membership;
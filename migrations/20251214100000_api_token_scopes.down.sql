@@ -0,0 +1,3 @@
+ALTER TABLE api_token DROP COLUMN scopes;
+ALTER TABLE api_token DROP COLUMN group_id;
+DROP TYPE api_token_scope;
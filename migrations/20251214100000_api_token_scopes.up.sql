@@ -0,0 +1,8 @@
+CREATE TYPE api_token_scope AS ENUM (
+    'read_only',
+    'user_management',
+    'network_management'
+);
+
+ALTER TABLE api_token ADD COLUMN group_id bigint NULL REFERENCES "group"(id) ON DELETE CASCADE;
+ALTER TABLE api_token ADD COLUMN scopes api_token_scope[] NOT NULL DEFAULT '{}';
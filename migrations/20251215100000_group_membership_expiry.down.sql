@@ -0,0 +1 @@
+ALTER TABLE group_user DROP COLUMN expires_at;
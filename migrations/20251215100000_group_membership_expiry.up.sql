@@ -0,0 +1 @@
+ALTER TABLE group_user ADD COLUMN expires_at timestamp without time zone NULL;
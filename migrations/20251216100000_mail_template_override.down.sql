@@ -0,0 +1,2 @@
+DROP TABLE mail_template_override;
+DROP TYPE mail_template_kind;
@@ -0,0 +1,12 @@
+CREATE TYPE mail_template_kind AS ENUM (
+    'enrollment_start',
+    'new_device_login',
+    'email_mfa_code'
+);
+
+CREATE TABLE mail_template_override (
+    id bigserial PRIMARY KEY,
+    kind mail_template_kind NOT NULL UNIQUE,
+    subject text NULL,
+    body text NULL
+);
@@ -0,0 +1,3 @@
+DROP TABLE activity_log_event_archive;
+ALTER TABLE settings DROP COLUMN activity_log_retention_archive;
+ALTER TABLE settings DROP COLUMN activity_log_retention_days;
@@ -0,0 +1,5 @@
+ALTER TABLE settings ADD COLUMN activity_log_retention_days integer NULL;
+ALTER TABLE settings ADD COLUMN activity_log_retention_archive boolean NOT NULL DEFAULT false;
+CREATE TABLE activity_log_event_archive (LIKE activity_log_event);
+ALTER TABLE activity_log_event_archive ADD PRIMARY KEY (id);
+CREATE INDEX activity_log_event_archive_timestamp_idx ON activity_log_event_archive(timestamp);
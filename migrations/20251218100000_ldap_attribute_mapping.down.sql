@@ -0,0 +1,4 @@
+ALTER TABLE settings DROP COLUMN ldap_email_attr;
+ALTER TABLE settings DROP COLUMN ldap_first_name_attr;
+ALTER TABLE settings DROP COLUMN ldap_last_name_attr;
+ALTER TABLE settings DROP COLUMN ldap_phone_attr;
@@ -0,0 +1,4 @@
+ALTER TABLE settings ADD COLUMN ldap_email_attr text DEFAULT 'mail';
+ALTER TABLE settings ADD COLUMN ldap_first_name_attr text DEFAULT 'givenName';
+ALTER TABLE settings ADD COLUMN ldap_last_name_attr text DEFAULT 'sn';
+ALTER TABLE settings ADD COLUMN ldap_phone_attr text DEFAULT 'mobile';
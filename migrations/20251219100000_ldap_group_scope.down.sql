@@ -0,0 +1,2 @@
+ALTER TABLE settings DROP COLUMN ldap_group_filter;
+ALTER TABLE settings DROP COLUMN ldap_group_exclude;
@@ -0,0 +1,2 @@
+ALTER TABLE settings ADD COLUMN ldap_group_filter TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE settings ADD COLUMN ldap_group_exclude TEXT[] NOT NULL DEFAULT '{}';
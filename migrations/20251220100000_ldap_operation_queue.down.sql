@@ -0,0 +1,2 @@
+DROP TABLE ldap_operation;
+DROP TYPE ldap_operation_kind;
@@ -0,0 +1,20 @@
+CREATE TYPE ldap_operation_kind AS ENUM (
+    'add_user_to_groups',
+    'remove_user_from_groups',
+    'update_user_state',
+    'modify_group',
+    'delete_group'
+);
+
+CREATE TABLE ldap_operation (
+    id bigserial PRIMARY KEY,
+    kind ldap_operation_kind NOT NULL,
+    user_id bigint NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    group_id bigint NULL REFERENCES "group"(id) ON DELETE CASCADE,
+    group_names text[] NOT NULL DEFAULT '{}',
+    attempts integer NOT NULL DEFAULT 0,
+    last_error text NULL,
+    next_attempt_at timestamp without time zone NOT NULL,
+    failed boolean NOT NULL DEFAULT false,
+    created_at timestamp without time zone NOT NULL
+);
@@ -0,0 +1,4 @@
+ALTER TABLE wireguard_network_allowed_group
+    DROP COLUMN address_pool,
+    DROP COLUMN firewall_tag,
+    DROP COLUMN priority;
@@ -0,0 +1,4 @@
+ALTER TABLE wireguard_network_allowed_group
+    ADD COLUMN address_pool inet[] NOT NULL DEFAULT '{}',
+    ADD COLUMN firewall_tag text NULL,
+    ADD COLUMN priority integer NOT NULL DEFAULT 0;
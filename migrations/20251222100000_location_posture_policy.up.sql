@@ -0,0 +1,8 @@
+CREATE TABLE location_posture_policy (
+    id bigserial PRIMARY KEY,
+    location_id bigint NOT NULL REFERENCES wireguard_network(id) ON DELETE CASCADE,
+    require_disk_encryption boolean NOT NULL DEFAULT false,
+    min_os_version text NULL,
+    min_client_version text NULL,
+    CONSTRAINT location_posture_policy_location UNIQUE (location_id)
+);
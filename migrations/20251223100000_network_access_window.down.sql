@@ -0,0 +1 @@
+DROP TABLE wireguard_network_access_window;
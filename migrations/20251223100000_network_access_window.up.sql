@@ -0,0 +1,11 @@
+CREATE TABLE wireguard_network_access_window (
+    id bigserial PRIMARY KEY,
+    network_id bigint NOT NULL,
+    group_id bigint NOT NULL,
+    -- ISO weekday numbers, 1 is Monday
+    weekdays integer[] NOT NULL,
+    start_time time NOT NULL,
+    end_time time NOT NULL,
+    FOREIGN KEY (network_id, group_id)
+        REFERENCES wireguard_network_allowed_group (network_id, group_id) ON DELETE CASCADE
+);
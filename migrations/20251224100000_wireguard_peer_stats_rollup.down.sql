@@ -0,0 +1,3 @@
+DROP TABLE wireguard_stats_rollup;
+DROP TABLE wireguard_peer_stats_daily;
+DROP TABLE wireguard_peer_stats_hourly;
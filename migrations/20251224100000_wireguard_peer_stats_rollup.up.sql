@@ -0,0 +1,28 @@
+CREATE TABLE wireguard_peer_stats_hourly (
+    device_id bigint NOT NULL REFERENCES "device"(id) ON DELETE CASCADE,
+    network bigint NOT NULL REFERENCES wireguard_network(id) ON DELETE CASCADE,
+    period_start timestamp without time zone NOT NULL,
+    -- bytes transferred during the period
+    upload bigint NOT NULL,
+    download bigint NOT NULL,
+    latest_handshake timestamp without time zone NOT NULL,
+    PRIMARY KEY (device_id, network, period_start)
+);
+
+CREATE TABLE wireguard_peer_stats_daily (
+    device_id bigint NOT NULL REFERENCES "device"(id) ON DELETE CASCADE,
+    network bigint NOT NULL REFERENCES wireguard_network(id) ON DELETE CASCADE,
+    period_start timestamp without time zone NOT NULL,
+    upload bigint NOT NULL,
+    download bigint NOT NULL,
+    latest_handshake timestamp without time zone NOT NULL,
+    PRIMARY KEY (device_id, network, period_start)
+);
+
+CREATE TABLE wireguard_stats_rollup (
+    id bigserial PRIMARY KEY,
+    started_at timestamp without time zone NOT NULL,
+    finished_at timestamp without time zone NOT NULL,
+    -- raw stats collected before this time are included in rollup tables
+    rolled_up_until timestamp without time zone NOT NULL
+);
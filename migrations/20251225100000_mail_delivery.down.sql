@@ -0,0 +1,2 @@
+DROP TABLE mail_delivery;
+DROP TYPE mail_delivery_status;
@@ -0,0 +1,23 @@
+CREATE TYPE mail_delivery_status AS ENUM (
+    'queued',
+    'sent',
+    'failed'
+);
+
+CREATE TABLE mail_delivery (
+    id bigserial PRIMARY KEY,
+    recipient text NOT NULL,
+    subject text NOT NULL,
+    content text NULL,
+    plain_content text NULL,
+    has_attachments boolean NOT NULL DEFAULT false,
+    status mail_delivery_status NOT NULL DEFAULT 'queued',
+    attempts integer NOT NULL DEFAULT 0,
+    smtp_response text NULL,
+    created_at timestamp without time zone NOT NULL,
+    sent_at timestamp without time zone NULL,
+    failed_at timestamp without time zone NULL,
+    resent_at timestamp without time zone NULL
+);
+
+CREATE INDEX mail_delivery_status_idx ON mail_delivery(status);
@@ -0,0 +1,2 @@
+DROP TABLE notification_digest_entry;
+DROP TABLE user_notification_settings;
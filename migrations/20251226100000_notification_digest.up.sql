@@ -0,0 +1,14 @@
+CREATE TABLE user_notification_settings (
+    user_id bigint PRIMARY KEY REFERENCES "user"(id) ON DELETE CASCADE,
+    email_digest boolean NOT NULL DEFAULT false
+);
+
+CREATE TABLE notification_digest_entry (
+    id bigserial PRIMARY KEY,
+    user_id bigint NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    title text NOT NULL,
+    summary text NOT NULL,
+    created_at timestamp without time zone NOT NULL
+);
+
+CREATE INDEX notification_digest_entry_user_id_idx ON notification_digest_entry(user_id);
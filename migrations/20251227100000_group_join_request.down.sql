@@ -0,0 +1,4 @@
+DROP TABLE group_join_request;
+DROP TYPE group_join_request_status;
+DROP TABLE group_owner;
+ALTER TABLE "group" DROP COLUMN self_service;
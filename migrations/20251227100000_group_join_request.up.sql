@@ -0,0 +1,24 @@
+ALTER TABLE "group" ADD COLUMN self_service boolean NOT NULL DEFAULT false;
+
+CREATE TABLE group_owner (
+    group_id bigint NOT NULL REFERENCES "group"(id) ON DELETE CASCADE,
+    user_id bigint NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    PRIMARY KEY (group_id, user_id)
+);
+
+CREATE TYPE group_join_request_status AS ENUM ('pending', 'approved', 'rejected');
+
+CREATE TABLE group_join_request (
+    id bigserial PRIMARY KEY,
+    group_id bigint NOT NULL REFERENCES "group"(id) ON DELETE CASCADE,
+    user_id bigint NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    status group_join_request_status NOT NULL DEFAULT 'pending',
+    comment text NULL,
+    created_at timestamp without time zone NOT NULL,
+    decided_at timestamp without time zone NULL,
+    decided_by bigint NULL REFERENCES "user"(id) ON DELETE SET NULL
+);
+
+-- only one request can be waiting for a decision at a time
+CREATE UNIQUE INDEX group_join_request_pending_idx ON group_join_request(group_id, user_id)
+    WHERE status = 'pending';
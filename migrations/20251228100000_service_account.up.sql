@@ -0,0 +1 @@
+ALTER TABLE "user" ADD COLUMN service_account boolean NOT NULL DEFAULT false;
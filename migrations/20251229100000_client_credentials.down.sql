@@ -0,0 +1,2 @@
+DROP TABLE oauth2client_token;
+DROP TABLE oauth2client_credentials;
@@ -0,0 +1,15 @@
+CREATE TABLE oauth2client_credentials (
+    oauth2client_id bigint PRIMARY KEY REFERENCES oauth2client(id) ON DELETE CASCADE,
+    user_id bigint NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    scopes api_token_scope[] NOT NULL
+);
+
+CREATE TABLE oauth2client_token (
+    id bigserial PRIMARY KEY,
+    oauth2client_id bigint NOT NULL REFERENCES oauth2client(id) ON DELETE CASCADE,
+    user_id bigint NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    token_hash text NOT NULL UNIQUE,
+    scopes api_token_scope[] NOT NULL,
+    created_at timestamp without time zone NOT NULL,
+    expires_at timestamp without time zone NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE wireguard_network DROP COLUMN allowed_mfa_methods;
+DROP TYPE location_mfa_method;
@@ -0,0 +1,10 @@
+CREATE TYPE location_mfa_method AS ENUM (
+    'totp',
+    'email',
+    'webauthn',
+    'biometric',
+    'mobile_approve'
+);
+
+-- empty list allows all methods
+ALTER TABLE wireguard_network ADD COLUMN allowed_mfa_methods location_mfa_method[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,2 @@
+DROP TABLE client_mfa_trusted_device;
+ALTER TABLE wireguard_network DROP COLUMN mfa_device_trust_enabled;
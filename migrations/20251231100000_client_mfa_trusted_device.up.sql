@@ -0,0 +1,9 @@
+ALTER TABLE wireguard_network ADD COLUMN mfa_device_trust_enabled boolean NOT NULL DEFAULT false;
+
+CREATE TABLE client_mfa_trusted_device (
+    id bigserial PRIMARY KEY,
+    location_id bigint NOT NULL REFERENCES wireguard_network(id) ON DELETE CASCADE,
+    device_pubkey text NOT NULL,
+    trusted_until timestamp without time zone NOT NULL,
+    CONSTRAINT client_mfa_trusted_device_location_pubkey UNIQUE (location_id, device_pubkey)
+);
@@ -0,0 +1,2 @@
+DROP TABLE gateway_alert_rule;
+ALTER TABLE webhook DROP COLUMN on_gateway_disconnected;
@@ -0,0 +1,8 @@
+ALTER TABLE webhook ADD COLUMN on_gateway_disconnected boolean NOT NULL DEFAULT false;
+
+CREATE TABLE gateway_alert_rule (
+    location_id bigint PRIMARY KEY REFERENCES wireguard_network(id) ON DELETE CASCADE,
+    threshold_minutes integer NOT NULL,
+    notify_webhooks boolean NOT NULL DEFAULT true,
+    notify_admins boolean NOT NULL DEFAULT false
+);
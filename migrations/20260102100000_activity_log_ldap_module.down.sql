@@ -0,0 +1,15 @@
+DELETE FROM activity_log_event WHERE module = 'ldap';
+DELETE FROM activity_log_event_archive WHERE module = 'ldap';
+ALTER TYPE activity_log_module RENAME TO activity_log_module_old;
+CREATE TYPE activity_log_module AS ENUM (
+    'defguard',
+    'client',
+    'vpn',
+    'enrollment',
+    'custom'
+);
+ALTER TABLE activity_log_event
+    ALTER COLUMN module TYPE activity_log_module USING module::text::activity_log_module;
+ALTER TABLE activity_log_event_archive
+    ALTER COLUMN module TYPE activity_log_module USING module::text::activity_log_module;
+DROP TYPE activity_log_module_old;
@@ -0,0 +1 @@
+ALTER TYPE activity_log_module ADD VALUE 'ldap';
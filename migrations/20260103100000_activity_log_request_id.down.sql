@@ -0,0 +1,3 @@
+DROP INDEX activity_log_event_request_id_idx;
+ALTER TABLE activity_log_event_archive DROP COLUMN request_id;
+ALTER TABLE activity_log_event DROP COLUMN request_id;
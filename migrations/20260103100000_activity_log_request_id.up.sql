@@ -0,0 +1,3 @@
+ALTER TABLE activity_log_event ADD COLUMN request_id TEXT;
+ALTER TABLE activity_log_event_archive ADD COLUMN request_id TEXT;
+CREATE INDEX activity_log_event_request_id_idx ON activity_log_event(request_id);
@@ -0,0 +1 @@
+ALTER TABLE "user" ADD COLUMN deactivate_at TIMESTAMP WITHOUT TIME ZONE NULL;
@@ -0,0 +1 @@
+ALTER TABLE "user" ADD COLUMN break_glass BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,4 @@
+ALTER TABLE settings DROP COLUMN totp_skew;
+ALTER TABLE settings DROP COLUMN totp_digits;
+ALTER TABLE settings DROP COLUMN totp_algorithm;
+DROP TYPE totp_algorithm;
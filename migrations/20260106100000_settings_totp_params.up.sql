@@ -0,0 +1,4 @@
+CREATE TYPE totp_algorithm AS ENUM ('sha1', 'sha256');
+ALTER TABLE settings ADD COLUMN totp_algorithm totp_algorithm NOT NULL DEFAULT 'sha1';
+ALTER TABLE settings ADD COLUMN totp_digits integer NOT NULL DEFAULT 6;
+ALTER TABLE settings ADD COLUMN totp_skew integer NOT NULL DEFAULT 0;
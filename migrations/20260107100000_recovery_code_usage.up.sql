@@ -0,0 +1,6 @@
+CREATE TABLE recovery_code_usage (
+    id bigserial PRIMARY KEY,
+    user_id bigint NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    used_at timestamp without time zone NOT NULL
+);
+CREATE INDEX recovery_code_usage_user_id_idx ON recovery_code_usage(user_id);
@@ -0,0 +1,20 @@
+ALTER TABLE settings DROP COLUMN sms_sender;
+ALTER TABLE settings DROP COLUMN sms_gateway_password;
+ALTER TABLE settings DROP COLUMN sms_gateway_user;
+ALTER TABLE settings DROP COLUMN sms_gateway_url;
+
+DROP TABLE user_sms_mfa;
+
+UPDATE "user" SET mfa_method = 'none' WHERE mfa_method = 'sms';
+ALTER TYPE mfa_method RENAME TO mfa_method_old;
+CREATE TYPE mfa_method AS ENUM (
+    'none',
+    'one_time_password',
+    'webauthn',
+    'email'
+);
+ALTER TABLE "user"
+    ALTER COLUMN mfa_method DROP DEFAULT,
+    ALTER COLUMN mfa_method TYPE mfa_method USING mfa_method::text::mfa_method,
+    ALTER COLUMN mfa_method SET DEFAULT 'none'::mfa_method;
+DROP TYPE mfa_method_old;
@@ -0,0 +1,16 @@
+-- add new variant to methods enum
+ALTER TYPE mfa_method ADD VALUE 'sms';
+
+-- phone number verified for SMS MFA, kept apart from the profile phone number
+CREATE TABLE user_sms_mfa (
+    user_id bigint PRIMARY KEY REFERENCES "user"(id) ON DELETE CASCADE,
+    phone text NOT NULL,
+    secret bytea NOT NULL,
+    enabled boolean NOT NULL DEFAULT false
+);
+
+-- Twilio-style HTTP SMS gateway
+ALTER TABLE settings ADD COLUMN sms_gateway_url text NULL;
+ALTER TABLE settings ADD COLUMN sms_gateway_user text NULL;
+ALTER TABLE settings ADD COLUMN sms_gateway_password text NULL;
+ALTER TABLE settings ADD COLUMN sms_sender text NULL;
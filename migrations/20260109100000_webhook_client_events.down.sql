@@ -0,0 +1,5 @@
+DROP TABLE webhook_location;
+ALTER TABLE webhook
+    DROP COLUMN on_client_connected,
+    DROP COLUMN on_client_disconnected,
+    DROP COLUMN secret;
@@ -0,0 +1,10 @@
+ALTER TABLE webhook
+    ADD COLUMN on_client_connected boolean NOT NULL DEFAULT false,
+    ADD COLUMN on_client_disconnected boolean NOT NULL DEFAULT false,
+    ADD COLUMN secret text NULL;
+
+CREATE TABLE webhook_location (
+    webhook_id bigint NOT NULL REFERENCES webhook(id) ON DELETE CASCADE,
+    location_id bigint NOT NULL REFERENCES wireguard_network(id) ON DELETE CASCADE,
+    PRIMARY KEY (webhook_id, location_id)
+);
@@ -0,0 +1,14 @@
+CREATE TABLE vpn_session (
+    id bigserial PRIMARY KEY,
+    user_id bigint NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    device_id bigint NOT NULL REFERENCES device(id) ON DELETE CASCADE,
+    location_id bigint NOT NULL REFERENCES wireguard_network(id) ON DELETE CASCADE,
+    endpoint text NOT NULL,
+    connected_at timestamp without time zone NOT NULL,
+    disconnected_at timestamp without time zone NULL,
+    upload bigint NOT NULL DEFAULT 0,
+    download bigint NOT NULL DEFAULT 0
+);
+
+CREATE INDEX vpn_session_user_id_connected_at_idx ON vpn_session(user_id, connected_at);
+CREATE INDEX vpn_session_device_id_connected_at_idx ON vpn_session(device_id, connected_at);
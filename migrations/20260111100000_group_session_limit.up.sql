@@ -0,0 +1 @@
+ALTER TABLE "group" ADD COLUMN max_sessions integer NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE "session" ADD COLUMN last_seen timestamp without time zone NULL;
+UPDATE "session" SET last_seen = created;
+ALTER TABLE "session" ALTER COLUMN last_seen SET NOT NULL;
@@ -0,0 +1,11 @@
+DROP TABLE password_history;
+
+ALTER TABLE settings
+    DROP COLUMN password_min_length,
+    DROP COLUMN password_require_uppercase,
+    DROP COLUMN password_require_lowercase,
+    DROP COLUMN password_require_digit,
+    DROP COLUMN password_require_special,
+    DROP COLUMN password_min_score,
+    DROP COLUMN password_banned,
+    DROP COLUMN password_history_size;
@@ -0,0 +1,19 @@
+-- password policy, defaults match requirements enforced so far
+ALTER TABLE settings
+    ADD COLUMN password_min_length integer NOT NULL DEFAULT 8,
+    ADD COLUMN password_require_uppercase boolean NOT NULL DEFAULT true,
+    ADD COLUMN password_require_lowercase boolean NOT NULL DEFAULT true,
+    ADD COLUMN password_require_digit boolean NOT NULL DEFAULT true,
+    ADD COLUMN password_require_special boolean NOT NULL DEFAULT true,
+    ADD COLUMN password_min_score integer NOT NULL DEFAULT 0,
+    ADD COLUMN password_banned text[] NOT NULL DEFAULT array[]::text[],
+    ADD COLUMN password_history_size integer NOT NULL DEFAULT 0;
+
+-- hashes of passwords set by users, used to prevent their reuse
+CREATE TABLE password_history (
+    id bigserial PRIMARY KEY,
+    user_id bigint NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    password_hash text NOT NULL,
+    created_at timestamp without time zone NOT NULL DEFAULT now()
+);
+CREATE INDEX password_history_user_id_idx ON password_history (user_id);
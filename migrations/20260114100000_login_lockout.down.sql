@@ -0,0 +1,5 @@
+ALTER TABLE settings
+    DROP COLUMN login_lockout_threshold,
+    DROP COLUMN login_lockout_ip_threshold,
+    DROP COLUMN login_lockout_duration,
+    DROP COLUMN login_lockout_mfa_fallback;
@@ -0,0 +1,6 @@
+-- lockout after consecutive failed web UI logins, disabled by default
+ALTER TABLE settings
+    ADD COLUMN login_lockout_threshold integer NOT NULL DEFAULT 0,
+    ADD COLUMN login_lockout_ip_threshold integer NOT NULL DEFAULT 0,
+    ADD COLUMN login_lockout_duration integer NOT NULL DEFAULT 15,
+    ADD COLUMN login_lockout_mfa_fallback boolean NOT NULL DEFAULT false;
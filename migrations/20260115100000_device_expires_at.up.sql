@@ -0,0 +1 @@
+ALTER TABLE device ADD COLUMN expires_at TIMESTAMP WITHOUT TIME ZONE NULL;
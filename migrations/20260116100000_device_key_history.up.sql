@@ -0,0 +1,8 @@
+-- public keys replaced during key rotation
+CREATE TABLE device_key_history (
+    id bigserial PRIMARY KEY,
+    device_id bigint NOT NULL REFERENCES device(id) ON DELETE CASCADE,
+    wireguard_pubkey text NOT NULL,
+    rotated_at timestamp without time zone NOT NULL
+);
+CREATE INDEX device_key_history_device_id ON device_key_history(device_id);
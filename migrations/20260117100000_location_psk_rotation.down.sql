@@ -0,0 +1,3 @@
+ALTER TABLE wireguard_network
+    DROP COLUMN psk_rotation_interval,
+    DROP COLUMN psk_rotated_at;
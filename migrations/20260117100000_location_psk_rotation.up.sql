@@ -0,0 +1,4 @@
+-- interval in hours after which preshared keys of location peers are regenerated
+ALTER TABLE wireguard_network
+    ADD COLUMN psk_rotation_interval integer NULL,
+    ADD COLUMN psk_rotated_at timestamp without time zone NULL;
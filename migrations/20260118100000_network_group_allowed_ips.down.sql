@@ -0,0 +1 @@
+ALTER TABLE wireguard_network_allowed_group DROP COLUMN allowed_ips;
@@ -0,0 +1 @@
+ALTER TABLE wireguard_network_allowed_group ADD COLUMN allowed_ips inet[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,4 @@
+ALTER TABLE wireguard_network_allowed_group
+    DROP COLUMN dns,
+    DROP COLUMN dns_search_domains;
+ALTER TABLE wireguard_network DROP COLUMN dns_search_domains;
@@ -0,0 +1,5 @@
+ALTER TABLE wireguard_network ADD COLUMN dns_search_domains text[] NOT NULL DEFAULT '{}';
+-- DNS overrides of allowed groups
+ALTER TABLE wireguard_network_allowed_group
+    ADD COLUMN dns text NULL,
+    ADD COLUMN dns_search_domains text[] NOT NULL DEFAULT '{}';
@@ -0,0 +1 @@
+ALTER TABLE wireguard_network_device DROP COLUMN ip_reserved;
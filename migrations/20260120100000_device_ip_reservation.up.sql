@@ -0,0 +1,2 @@
+-- addresses set explicitly by an admin, kept when user groups or address pools change
+ALTER TABLE wireguard_network_device ADD COLUMN ip_reserved boolean NOT NULL DEFAULT false;
@@ -0,0 +1,3 @@
+ALTER TABLE wireguard_network
+    DROP COLUMN maintenance_enabled,
+    DROP COLUMN maintenance_message;
@@ -0,0 +1,4 @@
+-- locations drained before gateway upgrades, new desktop client MFA logins are rejected
+ALTER TABLE wireguard_network
+    ADD COLUMN maintenance_enabled boolean NOT NULL DEFAULT false,
+    ADD COLUMN maintenance_message text NULL;
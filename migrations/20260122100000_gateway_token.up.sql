@@ -0,0 +1,10 @@
+-- gateway auth tokens, allowing multiple gateways per location and revoking single tokens
+CREATE TABLE gateway_token (
+    id bigserial PRIMARY KEY,
+    location_id bigint NOT NULL REFERENCES wireguard_network(id) ON DELETE CASCADE,
+    name text NOT NULL,
+    created_at timestamp without time zone NOT NULL,
+    last_connected_at timestamp without time zone NULL,
+    last_hostname text NULL
+);
+CREATE INDEX gateway_token_location_id_idx ON gateway_token (location_id);
@@ -0,0 +1,2 @@
+DROP TABLE client_mfa_user_attempts;
+DROP TABLE client_login_session;
@@ -0,0 +1,22 @@
+-- desktop client MFA state, shared by all core instances
+CREATE TABLE client_login_session (
+    pubkey text PRIMARY KEY,
+    method integer NOT NULL,
+    location_id bigint NOT NULL REFERENCES wireguard_network(id) ON DELETE CASCADE,
+    device_id bigint NOT NULL REFERENCES device(id) ON DELETE CASCADE,
+    user_id bigint NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    openid_auth_completed boolean NOT NULL DEFAULT false,
+    biometric_auth_pub_key text NULL,
+    biometric_challenge text NULL,
+    passkey_authentication bytea NULL,
+    failed_attempts integer NOT NULL DEFAULT 0,
+    expires_at timestamp without time zone NOT NULL
+);
+CREATE INDEX client_login_session_expires_at_idx ON client_login_session (expires_at);
+
+CREATE TABLE client_mfa_user_attempts (
+    user_id bigint PRIMARY KEY REFERENCES "user"(id) ON DELETE CASCADE,
+    failed_attempts integer NOT NULL,
+    window_start timestamp without time zone NOT NULL,
+    locked_until timestamp without time zone NULL
+);
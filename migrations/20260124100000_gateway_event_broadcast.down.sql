@@ -0,0 +1,2 @@
+DROP TABLE gateway_event_broadcast;
+DROP FUNCTION notify_gateway_event;
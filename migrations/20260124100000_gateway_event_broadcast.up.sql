@@ -0,0 +1,19 @@
+-- gateway events shared between core instances, kept only until all instances had a chance to
+-- fetch them
+CREATE TABLE gateway_event_broadcast (
+    id bigserial PRIMARY KEY,
+    origin uuid NOT NULL,
+    payload bytea NOT NULL,
+    created_at timestamp without time zone NOT NULL DEFAULT (NOW() AT TIME ZONE 'UTC')
+);
+CREATE INDEX gateway_event_broadcast_created_at_idx ON gateway_event_broadcast (created_at);
+
+CREATE FUNCTION notify_gateway_event() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('gateway_event', NEW.id::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER gateway_event_broadcast_notify AFTER INSERT ON gateway_event_broadcast
+    FOR EACH ROW EXECUTE FUNCTION notify_gateway_event();
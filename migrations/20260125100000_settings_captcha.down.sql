@@ -0,0 +1,5 @@
+ALTER TABLE settings
+    DROP COLUMN captcha_provider,
+    DROP COLUMN captcha_site_key,
+    DROP COLUMN captcha_secret_key;
+DROP TYPE captcha_provider;
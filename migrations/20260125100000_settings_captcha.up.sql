@@ -0,0 +1,6 @@
+-- CAPTCHA on public enrollment and password reset pages, disabled by default
+CREATE TYPE captcha_provider AS ENUM ('none', 'hcaptcha', 'turnstile');
+ALTER TABLE settings
+    ADD COLUMN captcha_provider captcha_provider NOT NULL DEFAULT 'none',
+    ADD COLUMN captcha_site_key text NULL,
+    ADD COLUMN captcha_secret_key text NULL;
@@ -0,0 +1,4 @@
+DROP TABLE token_location;
+DROP TABLE token_group;
+ALTER TABLE token DROP COLUMN use_count;
+ALTER TABLE token DROP COLUMN max_uses;
@@ -0,0 +1,16 @@
+-- enrollment sessions which can be started with a token
+ALTER TABLE token ADD COLUMN max_uses integer NOT NULL DEFAULT 1;
+ALTER TABLE token ADD COLUMN use_count integer NOT NULL DEFAULT 0;
+UPDATE token SET use_count = 1 WHERE used_at IS NOT NULL;
+
+-- groups and locations assigned to the user when enrollment is completed
+CREATE TABLE token_group (
+    token_id text NOT NULL REFERENCES token(id) ON DELETE CASCADE,
+    group_id bigint NOT NULL REFERENCES "group"(id) ON DELETE CASCADE,
+    PRIMARY KEY (token_id, group_id)
+);
+CREATE TABLE token_location (
+    token_id text NOT NULL REFERENCES token(id) ON DELETE CASCADE,
+    location_id bigint NOT NULL REFERENCES wireguard_network(id) ON DELETE CASCADE,
+    PRIMARY KEY (token_id, location_id)
+);
@@ -0,0 +1,3 @@
+DROP TABLE device_approval;
+DROP TYPE device_approval_status;
+ALTER TABLE wireguard_network DROP COLUMN device_approval_required;
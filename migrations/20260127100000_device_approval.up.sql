@@ -0,0 +1,15 @@
+ALTER TABLE wireguard_network ADD COLUMN device_approval_required boolean NOT NULL DEFAULT false;
+
+CREATE TYPE device_approval_status AS ENUM ('pending', 'approved', 'rejected');
+-- admin decisions about devices added to locations which require approval
+CREATE TABLE device_approval (
+    id bigserial PRIMARY KEY,
+    device_id bigint NOT NULL REFERENCES device(id) ON DELETE CASCADE,
+    location_id bigint NOT NULL REFERENCES wireguard_network(id) ON DELETE CASCADE,
+    status device_approval_status NOT NULL DEFAULT 'pending',
+    requested_at timestamp without time zone NOT NULL,
+    decided_by bigint NULL REFERENCES "user"(id) ON DELETE SET NULL,
+    decided_at timestamp without time zone NULL,
+    UNIQUE (device_id, location_id)
+);
+CREATE INDEX device_approval_status_idx ON device_approval (status);
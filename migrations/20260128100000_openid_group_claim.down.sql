@@ -0,0 +1,3 @@
+ALTER TABLE openidprovider DROP COLUMN group_claim_dry_run;
+ALTER TABLE openidprovider DROP COLUMN group_claim_create_groups;
+ALTER TABLE openidprovider DROP COLUMN group_claim;
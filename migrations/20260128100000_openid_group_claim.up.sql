@@ -0,0 +1,4 @@
+-- ID token claim mapped onto Defguard groups on every login through the provider
+ALTER TABLE openidprovider ADD COLUMN group_claim text NULL;
+ALTER TABLE openidprovider ADD COLUMN group_claim_create_groups boolean NOT NULL DEFAULT false;
+ALTER TABLE openidprovider ADD COLUMN group_claim_dry_run boolean NOT NULL DEFAULT false;
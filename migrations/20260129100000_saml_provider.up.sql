@@ -0,0 +1,18 @@
+-- SAML 2.0 identity providers, Defguard acts as the service provider
+CREATE TABLE saml_provider (
+    id bigserial PRIMARY KEY,
+    name text NOT NULL UNIQUE,
+    display_name text NULL,
+    enabled boolean NOT NULL DEFAULT true,
+    -- identity provider metadata XML with SSO endpoints and signing certificates
+    idp_metadata text NOT NULL,
+    create_account boolean NOT NULL DEFAULT false,
+    -- assertion attributes mapped onto user fields, matched by name or friendly name
+    email_attribute text NOT NULL DEFAULT 'email',
+    username_attribute text NULL,
+    first_name_attribute text NOT NULL DEFAULT 'firstName',
+    last_name_attribute text NOT NULL DEFAULT 'lastName',
+    phone_attribute text NULL,
+    groups_attribute text NULL,
+    create_groups boolean NOT NULL DEFAULT false
+);
@@ -0,0 +1,2 @@
+DROP TABLE provisioning_policy;
+DROP TYPE provisioning_source;
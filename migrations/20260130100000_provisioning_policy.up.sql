@@ -0,0 +1,15 @@
+-- just-in-time provisioning of accounts for users logging in through external sources
+CREATE TYPE provisioning_source AS ENUM ('openid', 'ldap');
+CREATE TABLE provisioning_policy (
+    source provisioning_source PRIMARY KEY,
+    create_account boolean NOT NULL DEFAULT true,
+    -- names of groups new accounts join
+    default_groups text[] NOT NULL DEFAULT '{}',
+    -- new accounts are disabled until an admin enables them
+    start_disabled boolean NOT NULL DEFAULT false,
+    username_template text NULL
+);
+INSERT INTO provisioning_policy (source, create_account)
+    SELECT 'openid', openid_create_account FROM settings WHERE id = 1;
+INSERT INTO provisioning_policy (source) VALUES ('openid') ON CONFLICT DO NOTHING;
+INSERT INTO provisioning_policy (source) VALUES ('ldap');
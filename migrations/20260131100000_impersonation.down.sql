@@ -0,0 +1,2 @@
+DROP TABLE impersonation;
+DROP TYPE impersonation_mode;
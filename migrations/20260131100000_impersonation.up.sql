@@ -0,0 +1,12 @@
+CREATE TYPE impersonation_mode AS ENUM ('read_only', 'full');
+
+-- sessions of users assumed by admins; removed together with either of the sessions
+CREATE TABLE impersonation (
+    session_id text PRIMARY KEY REFERENCES session(id) ON DELETE CASCADE,
+    admin_id bigint NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    admin_session_id text NOT NULL REFERENCES session(id) ON DELETE CASCADE,
+    user_id bigint NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    mode impersonation_mode NOT NULL,
+    created timestamp without time zone NOT NULL,
+    expires timestamp without time zone NOT NULL
+);
@@ -0,0 +1,6 @@
+ALTER TABLE device_profile ADD COLUMN allowed_locations bigint[] NOT NULL DEFAULT '{}';
+UPDATE device_profile p SET allowed_locations = ARRAY(
+    SELECT location_id FROM device_profile_location WHERE profile_id = p.id ORDER BY location_id
+);
+ALTER TABLE device_profile DROP COLUMN restrict_locations;
+DROP TABLE device_profile_location;
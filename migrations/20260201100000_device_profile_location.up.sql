@@ -0,0 +1,14 @@
+-- allowed locations reference locations, so that removed locations aren't left behind
+CREATE TABLE device_profile_location (
+    profile_id bigint NOT NULL REFERENCES device_profile(id) ON DELETE CASCADE,
+    location_id bigint NOT NULL REFERENCES wireguard_network(id) ON DELETE CASCADE,
+    PRIMARY KEY (profile_id, location_id)
+);
+INSERT INTO device_profile_location (profile_id, location_id)
+    SELECT p.id, n.id FROM device_profile p
+    JOIN wireguard_network n ON n.id = ANY(p.allowed_locations);
+
+-- profile stays restricted when all of its allowed locations are removed
+ALTER TABLE device_profile ADD COLUMN restrict_locations boolean NOT NULL DEFAULT false;
+UPDATE device_profile SET restrict_locations = cardinality(allowed_locations) > 0;
+ALTER TABLE device_profile DROP COLUMN allowed_locations;
@@ -0,0 +1,10 @@
+-- consecutive failed web UI logins, shared by all Defguard instances
+CREATE TABLE login_lockout (
+    id bigserial PRIMARY KEY,
+    user_id bigint UNIQUE REFERENCES "user"(id) ON DELETE CASCADE,
+    ip_address text UNIQUE,
+    failed_attempts integer NOT NULL,
+    last_attempt timestamp without time zone NOT NULL,
+    locked_until timestamp without time zone,
+    CHECK ((user_id IS NULL) <> (ip_address IS NULL))
+);
@@ -0,0 +1,9 @@
+DROP TABLE saml_login;
+DROP TABLE saml_user;
+
+DELETE FROM provisioning_policy WHERE source = 'saml';
+ALTER TYPE provisioning_source RENAME TO provisioning_source_old;
+CREATE TYPE provisioning_source AS ENUM ('openid', 'ldap');
+ALTER TABLE provisioning_policy
+    ALTER COLUMN source TYPE provisioning_source USING source::text::provisioning_source;
+DROP TYPE provisioning_source_old;
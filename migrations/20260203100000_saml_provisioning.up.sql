@@ -0,0 +1,23 @@
+-- accounts of users logging in through SAML are provisioned like OpenID and LDAP ones
+ALTER TYPE provisioning_source ADD VALUE 'saml';
+
+-- users are linked to SAML identities by subject name ID, not by email address
+CREATE TABLE saml_user (
+    provider_id bigint NOT NULL REFERENCES saml_provider(id) ON DELETE CASCADE,
+    name_id text NOT NULL,
+    user_id bigint NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    PRIMARY KEY (provider_id, name_id)
+);
+
+-- users authenticated at the assertion consumer service, whose login hasn't been completed yet
+CREATE TABLE saml_login (
+    -- stored in the login cookie
+    id text PRIMARY KEY,
+    provider_id bigint NOT NULL REFERENCES saml_provider(id) ON DELETE CASCADE,
+    user_id bigint NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    -- NULL if group mapping is disabled
+    groups text[] NULL,
+    created timestamp without time zone NOT NULL,
+    -- session which has to complete MFA before groups are synced
+    session_id text NULL REFERENCES session(id) ON DELETE CASCADE
+);
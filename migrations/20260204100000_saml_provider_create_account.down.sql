@@ -0,0 +1,3 @@
+ALTER TABLE saml_provider ADD COLUMN create_account boolean NOT NULL DEFAULT false;
+UPDATE saml_provider SET create_account = policy.create_account
+    FROM provisioning_policy policy WHERE policy.source = 'saml';
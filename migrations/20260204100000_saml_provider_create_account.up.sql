@@ -0,0 +1,4 @@
+-- account creation is controlled by the SAML provisioning policy
+INSERT INTO provisioning_policy (source, create_account)
+    SELECT 'saml', COALESCE(bool_or(create_account), false) FROM saml_provider;
+ALTER TABLE saml_provider DROP COLUMN create_account;
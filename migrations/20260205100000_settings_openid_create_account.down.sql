@@ -0,0 +1,3 @@
+ALTER TABLE settings ADD COLUMN openid_create_account boolean NOT NULL DEFAULT true;
+UPDATE settings SET openid_create_account = policy.create_account
+    FROM provisioning_policy policy WHERE policy.source = 'openid';
@@ -0,0 +1,2 @@
+-- account creation is controlled by the OpenID provisioning policy
+ALTER TABLE settings DROP COLUMN openid_create_account;
@@ -0,0 +1,4 @@
+ALTER TABLE activity_log_event_archive DROP COLUMN impersonator;
+ALTER TABLE activity_log_event_archive DROP COLUMN impersonator_id;
+ALTER TABLE activity_log_event DROP COLUMN impersonator;
+ALTER TABLE activity_log_event DROP COLUMN impersonator_id;
@@ -0,0 +1,5 @@
+-- admin who triggered the event while impersonating the user
+ALTER TABLE activity_log_event ADD COLUMN impersonator_id bigint NULL;
+ALTER TABLE activity_log_event ADD COLUMN impersonator text NULL;
+ALTER TABLE activity_log_event_archive ADD COLUMN impersonator_id bigint NULL;
+ALTER TABLE activity_log_event_archive ADD COLUMN impersonator text NULL;
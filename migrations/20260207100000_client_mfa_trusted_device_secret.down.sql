@@ -0,0 +1 @@
+ALTER TABLE client_mfa_trusted_device DROP COLUMN secret_hash;
@@ -0,0 +1,3 @@
+-- trust is bound to a secret handed to the client, records issued without it can't be used
+DELETE FROM client_mfa_trusted_device;
+ALTER TABLE client_mfa_trusted_device ADD COLUMN secret_hash text NOT NULL;
@@ -0,0 +1,2 @@
+DROP TRIGGER session_delete_impersonated ON session;
+DROP FUNCTION delete_impersonated_sessions;
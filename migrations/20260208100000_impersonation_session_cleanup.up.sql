@@ -0,0 +1,12 @@
+-- impersonated user sessions end together with the admin session they were started from,
+-- e.g. when the admin logs out or the session expires
+CREATE FUNCTION delete_impersonated_sessions() RETURNS trigger AS $$
+BEGIN
+    DELETE FROM session WHERE id IN
+        (SELECT session_id FROM impersonation WHERE admin_session_id = OLD.id);
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER session_delete_impersonated BEFORE DELETE ON session
+    FOR EACH ROW EXECUTE FUNCTION delete_impersonated_sessions();
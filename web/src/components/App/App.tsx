@@ -20,6 +20,7 @@ import { SupportPage } from '../../pages/support/SupportPage';
 import { UserProfile } from '../../pages/users/UserProfile/UserProfile';
 import { UsersPage } from '../../pages/users/UsersPage';
 import { UsersSharedModals } from '../../pages/users/UsersSharedModals';
+import { VerifyEmailPage } from '../../pages/verifyEmail/VerifyEmailPage';
 import { WebhooksListPage } from '../../pages/webhooks/WebhooksListPage';
 import { WizardPage } from '../../pages/wizard/WizardPage';
 import { PageContainer } from '../../shared/components/Layout/PageContainer/PageContainer';
@@ -183,6 +184,7 @@ const App = () => {
                 </ProtectedRoute>
               }
             />
+            <Route path="verify-email" element={<VerifyEmailPage />} />
             <Route
               path="consent/*"
               element={
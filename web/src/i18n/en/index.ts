@@ -421,6 +421,31 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
         },
       },
     },
+    registerSmsMFA: {
+      title: 'SMS MFA Setup',
+      infoMessage: 'Enter the phone number you want to receive codes on. A verification code will be sent to it.',
+      messages: {
+        success: 'SMS MFA Enabled',
+        resend: 'Verification code resent',
+      },
+      form: {
+        fields: {
+          phone: {
+            label: 'Phone number',
+            error: 'Phone number is invalid',
+          },
+          code: {
+            label: 'SMS code',
+            error: 'Code is invalid',
+          },
+        },
+        controls: {
+          send: 'Send code',
+          submit: 'Verify code',
+          resend: 'Resend SMS',
+        },
+      },
+    },
     editDevice: {
       title: 'Edit device',
       messages: {
@@ -618,6 +643,23 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
           hwkeyProvision: {
             label: 'User Yubikey provision',
           },
+          gatewayDisconnected: {
+            label: 'Gateway disconnected',
+          },
+          clientConnected: {
+            label: 'VPN client connected',
+          },
+          clientDisconnected: {
+            label: 'VPN client disconnected',
+          },
+          secret: {
+            label: 'Signing secret',
+            placeholder: 'Optional key used to sign payloads with HMAC-SHA256',
+          },
+          locations: {
+            label: 'Locations',
+            placeholder: 'All locations',
+          },
         },
       },
     },
@@ -824,6 +866,7 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
           mfaDisabled: 'MFA disabled.',
           OTPDisabled: 'One time password disabled.',
           EmailMFADisabled: 'Email MFA disabled.',
+          SmsMFADisabled: 'SMS MFA disabled.',
           changeMFAMethod: 'MFA method changed',
         },
         securityKey: {
@@ -836,6 +879,7 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
         labels: {
           totp: 'Time based one time passwords',
           email: 'Email',
+          sms: 'SMS',
           webauth: 'Security keys',
         },
         editMode: {
@@ -1020,6 +1064,7 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
         resetPassword: 'Reset password',
         disableMfa: 'Disable MFA',
       },
+      serviceAccount: 'Service account',
     },
   },
   navigation: {
@@ -1212,6 +1257,14 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
           create: 'Add Logstash destination',
           modify: 'Edit Logstash destination',
         },
+        syslog: {
+          create: 'Add Syslog destination',
+          modify: 'Edit Syslog destination',
+        },
+        kafka: {
+          create: 'Add Kafka destination',
+          modify: 'Edit Kafka destination',
+        },
         shared: {
           formLabels: {
             name: 'Name',
@@ -1219,6 +1272,18 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
             username: 'Username',
             password: 'Password',
             cert: 'Certificate',
+            host: 'Host',
+            port: 'Port',
+            protocol: 'Protocol',
+            facility: 'Facility',
+            severity: 'Default severity',
+            hostname: 'Hostname',
+            brokers: 'Brokers (comma separated)',
+            topic: 'Topic',
+            format: 'Payload format',
+            tls: 'Use TLS',
+            saslMechanism: 'SASL mechanism',
+            saslNone: 'None',
           },
         },
       },
@@ -1275,6 +1340,12 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
           ldap_uses_ad: 'LDAP server is Active Directory',
           ldap_user_rdn_attr: 'User RDN Attribute',
           ldap_sync_groups: 'Limit synchronization to these groups',
+          ldap_group_filter: 'Synchronized group name patterns',
+          ldap_group_exclude: 'Excluded group name patterns',
+          ldap_email_attr: 'Email Attribute',
+          ldap_first_name_attr: 'First Name Attribute',
+          ldap_last_name_attr: 'Last Name Attribute',
+          ldap_phone_attr: 'Phone Attribute',
         },
         helpers: {
           ldap_user_obj_class:
@@ -1291,6 +1362,10 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
             'The object class that represents a group in LDAP. This is used to determine if an LDAP object is a group.',
           ldap_user_rdn_attr:
             "If your user's RDN attribute is different than your username attribute, please provide it here, otherwise leave it empty to use the username attribute as the user's RDN.",
+          ldap_group_filter:
+            'Comma-separated group name patterns. Only matching LDAP groups are synchronized with Defguard; leave empty to synchronize all groups. Use * and ? as wildcards or wrap a pattern in slashes (/^vpn-.*$/) to use a regular expression.',
+          ldap_group_exclude:
+            'Comma-separated group name patterns. Matching LDAP groups are never synchronized with Defguard, even if they match the patterns above.',
         },
         headings: {
           user_settings: 'User settings',
@@ -1305,6 +1380,8 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
         messages: {
           success: 'LDAP connected successfully',
           error: 'LDAP connection rejected',
+          unresolvedAttributes:
+            'LDAP connected, but these attributes were not found on a sample user: {attributes}',
         },
       },
     },
@@ -2039,7 +2116,7 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
     form: {
       helpers: {
         address:
-          'Based on this address VPN network address will be defined, eg. 10.10.10.1/24 (and VPN network will be: 10.10.10.0/24). You can optionally specify multiple addresses separated by a comma. The first address is the primary address, and this one will be used for IP address assignment for devices. The other IP addresses are auxiliary and are not managed by Defguard.',
+          'Based on this address VPN network address will be defined, eg. 10.10.10.1/24 (and VPN network will be: 10.10.10.0/24). You can optionally specify multiple addresses separated by a comma, e.g. an IPv4 and an IPv6 address for a dual-stack network. Devices get an address from each of the networks.',
         endpoint:
           'Public IP address or domain name to which the remote peers/users will connect to. This address will be used in the configuration for the clients, but Defguard Gateways do not bind to this address.',
         gateway: 'Gateway public address, used by VPN users to connect',
@@ -2186,6 +2263,7 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
         useWebauthn: 'Use security key instead',
         useRecoveryCode: 'Use recovery code instead',
         useEmail: 'Use E-mail instead',
+        useSms: 'Use SMS instead',
       },
       email: {
         header: 'Use code we sent to your e-mail to proceed.',
@@ -2197,6 +2275,20 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
             resendCode: 'Resend Code',
           },
         },
+        messages: {
+          resendTooSoon: 'Please wait a moment before requesting another code.',
+        },
+      },
+      sms: {
+        header: 'Use code we sent to your phone to proceed.',
+        form: {
+          labels: {
+            code: 'Code',
+          },
+          controls: {
+            resendCode: 'Resend Code',
+          },
+        },
       },
       totp: {
         header: 'Use code from your authentication app and click button to proceed.',
@@ -2307,6 +2399,13 @@ Licensing information: [https://docs.defguard.net/enterprise/license](https://do
     title: 'You have been logged in',
     subtitle: 'You will be redirected in a moment...',
   },
+  verifyEmailPage: {
+    messages: {
+      success: 'Email address has been changed',
+      error: 'Email address could not be verified, the link may have expired',
+      missingToken: 'Email verification link is invalid',
+    },
+  },
   enrollmentPage: {
     title: 'Enrollment',
     controls: {
@@ -2712,6 +2811,8 @@ This alias is currently in use by the following rule(s) and cannot be deleted. T
         data: 'No activities present',
         search: 'No activities found',
       },
+      serviceAccount: 'service account',
+      impersonatedBy: 'impersonated by {admin}',
     },
   },
   enums: {
@@ -2721,11 +2822,22 @@ This alias is currently in use by the following rule(s) and cannot be deleted. T
       user_mfa_login: 'User MFA login',
       user_mfa_login_failed: 'User MFA login failed',
       recovery_code_used: 'Recovery code used',
+      recovery_codes_regenerated: 'Recovery codes regenerated',
       user_logout: 'User logout',
+      break_glass_login: 'Break-glass login',
+      admin_access_denied: 'Admin access denied',
+      user_locked_out: 'User locked out',
+      login_ip_locked_out: 'Login address locked out',
+      user_unlocked: 'User unlocked',
+      login_ip_unlocked: 'Login address unlocked',
+      rate_limit_exceeded: 'Rate limit exceeded',
       user_added: 'User added',
       user_removed: 'User removed',
       user_modified: 'User modified',
+      user_disabled: 'User disabled',
       user_groups_modified: 'User groups modified',
+      user_provisioned: 'User provisioned',
+      account_creation_refused: 'Account creation refused',
       mfa_enabled: 'MFA enabled',
       mfa_disabled: 'MFA disabled',
       user_mfa_disabled: 'User MFA disabled',
@@ -2733,32 +2845,54 @@ This alias is currently in use by the following rule(s) and cannot be deleted. T
       mfa_totp_disabled: 'MFA TOTP disabled',
       mfa_email_enabled: 'MFA email enabled',
       mfa_email_disabled: 'MFA email disabled',
+      mfa_sms_enabled: 'MFA SMS enabled',
+      mfa_sms_disabled: 'MFA SMS disabled',
       mfa_security_key_added: 'MFA security key added',
       mfa_security_key_removed: 'MFA security key removed',
       device_added: 'Device added',
       device_removed: 'Device removed',
       device_modified: 'Device modified',
+      device_approved: 'Device approved',
+      device_rejected: 'Device rejected',
       network_device_added: 'Network device added',
       network_device_removed: 'Network device removed',
       network_device_modified: 'Network device modified',
+      device_address_reserved: 'Device address reserved',
+      device_address_released: 'Device address released',
+      device_profile_added: 'Device profile added',
+      device_profile_modified: 'Device profile modified',
+      device_profile_removed: 'Device profile removed',
+      device_profile_assigned: 'Device profile assigned',
       activity_log_stream_created: 'Activity log stream created',
       activity_log_stream_modified: 'Activity log stream modified',
       activity_log_stream_removed: 'Activity log stream removed',
+      activity_log_purged: 'Activity log purged',
       vpn_client_connected: 'VPN client connected',
       vpn_client_disconnected: 'VPN client disconnected',
       vpn_client_connected_mfa: 'VPN client connected to MFA location',
       vpn_client_disconnected_mfa: 'VPN client disconnected from MFA location',
       vpn_client_mfa_failed: 'VPN client failed MFA authentication',
+      vpn_client_mfa_locked_out: 'VPN client locked out of MFA authentication',
+      vpn_client_mfa_posture_failed: 'VPN client denied due to device posture',
+      vpn_client_mfa_timeout: 'VPN client MFA authentication timed out',
+      vpn_client_session_limit_exceeded: 'VPN session limit exceeded',
       enrollment_token_added: 'Enrollment token added',
+      enrollment_token_revoked: 'Enrollment token revoked',
       enrollment_started: 'Enrollment started',
       enrollment_device_added: 'Device added',
       enrollment_completed: 'Enrollment completed',
+      enrollment_blocked: 'Enrollment blocked',
       password_reset_requested: 'Password reset requested',
       password_reset_started: 'Password reset started',
       password_reset_completed: 'Password reset completed',
+      password_reset_blocked: 'Password reset blocked',
       vpn_location_added: 'VPN location added',
       vpn_location_removed: 'VPN location removed',
       vpn_location_modified: 'VPN location modified',
+      vpn_location_maintenance_enabled: 'VPN location maintenance enabled',
+      vpn_location_maintenance_disabled: 'VPN location maintenance disabled',
+      gateway_token_added: 'Gateway token issued',
+      gateway_token_revoked: 'Gateway token revoked',
       api_token_added: 'API token added',
       api_token_removed: 'API token removed',
       api_token_renamed: 'API token renamed',
@@ -2766,18 +2900,31 @@ This alias is currently in use by the following rule(s) and cannot be deleted. T
       open_id_app_removed: 'OpenID app removed',
       open_id_app_modified: 'OpenID app modified',
       open_id_app_state_changed: 'OpenID app state changed',
+      client_credentials_set: 'Client credentials grant enabled',
+      client_credentials_removed: 'Client credentials grant disabled',
       open_id_provider_removed: 'OpenID provider removed',
       open_id_provider_modified: 'OpenID provider modified',
+      saml_provider_modified: 'SAML provider modified',
+      saml_provider_removed: 'SAML provider removed',
+      provisioning_policy_modified: 'Provisioning policy modified',
       settings_updated: 'Settings updated',
       settings_updated_partial: 'Settings partially updated',
       settings_default_branding_restored: 'Default branding restored',
+      mail_template_modified: 'Email template customized',
+      mail_template_reset: 'Email template reset',
       groups_bulk_assigned: 'Groups bulk assigned',
       group_added: 'Group added',
       group_modified: 'Group modified',
       group_removed: 'Group removed',
       group_member_added: 'Group member added',
       group_member_removed: 'Group member removed',
+      group_membership_expired: 'Group membership expired',
       group_members_modified: 'Group members modified',
+      group_quota_modified: 'Group quota modified',
+      group_sync_authority_modified: 'Group sync authority modified',
+      group_parent_modified: 'Group parent modified',
+      group_join_request_approved: 'Group join request approved',
+      group_join_request_rejected: 'Group join request rejected',
       web_hook_added: 'Webhook added',
       web_hook_modified: 'Webhook modified',
       web_hook_removed: 'Webhook removed',
@@ -2788,16 +2935,23 @@ This alias is currently in use by the following rule(s) and cannot be deleted. T
       password_changed: 'Password changed',
       password_changed_by_admin: 'Password changed by admin',
       password_reset: 'Password reset',
+      user_session_revoked: 'User session revoked',
+      user_sessions_revoked: 'User sessions revoked',
+      impersonation_started: 'Impersonation started',
+      impersonation_ended: 'Impersonation ended',
       client_configuration_token_added: 'Client configuration token added',
       user_snat_binding_added: 'User SNAT binding added',
       user_snat_binding_modified: 'User SNAT binding modified',
       user_snat_binding_removed: 'User SNAT binding removed',
+      custom_event: 'Custom event',
     },
     activityLogModule: {
       defguard: 'Defguard',
       client: 'Client',
       enrollment: 'Enrollment',
       vpn: 'VPN',
+      ldap: 'LDAP',
+      custom: 'Custom',
     },
   },
 };
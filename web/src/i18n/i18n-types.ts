@@ -1010,6 +1010,64 @@ type RootTranslation = {
 				}
 			}
 		}
+		registerSmsMFA: {
+			/**
+			 * S​M​S​ ​M​F​A​ ​S​e​t​u​p
+			 */
+			title: string
+			/**
+			 * E​n​t​e​r​ ​t​h​e​ ​p​h​o​n​e​ ​n​u​m​b​e​r​ ​y​o​u​ ​w​a​n​t​ ​t​o​ ​r​e​c​e​i​v​e​ ​c​o​d​e​s​ ​o​n​.​ ​A​ ​v​e​r​i​f​i​c​a​t​i​o​n​ ​c​o​d​e​ ​w​i​l​l​ ​b​e​ ​s​e​n​t​ ​t​o​ ​i​t​.
+			 */
+			infoMessage: string
+			messages: {
+				/**
+				 * S​M​S​ ​M​F​A​ ​E​n​a​b​l​e​d
+				 */
+				success: string
+				/**
+				 * V​e​r​i​f​i​c​a​t​i​o​n​ ​c​o​d​e​ ​r​e​s​e​n​t
+				 */
+				resend: string
+			}
+			form: {
+				fields: {
+					phone: {
+						/**
+						 * P​h​o​n​e​ ​n​u​m​b​e​r
+						 */
+						label: string
+						/**
+						 * P​h​o​n​e​ ​n​u​m​b​e​r​ ​i​s​ ​i​n​v​a​l​i​d
+						 */
+						error: string
+					}
+					code: {
+						/**
+						 * S​M​S​ ​c​o​d​e
+						 */
+						label: string
+						/**
+						 * C​o​d​e​ ​i​s​ ​i​n​v​a​l​i​d
+						 */
+						error: string
+					}
+				}
+				controls: {
+					/**
+					 * S​e​n​d​ ​c​o​d​e
+					 */
+					send: string
+					/**
+					 * V​e​r​i​f​y​ ​c​o​d​e
+					 */
+					submit: string
+					/**
+					 * R​e​s​e​n​d​ ​S​M​S
+					 */
+					resend: string
+				}
+			}
+		}
 		editDevice: {
 			/**
 			 * E​d​i​t​ ​d​e​v​i​c​e
@@ -1452,6 +1510,44 @@ type RootTranslation = {
 						 */
 						label: string
 					}
+					gatewayDisconnected: {
+						/**
+						 * G​a​t​e​w​a​y​ ​d​i​s​c​o​n​n​e​c​t​e​d
+						 */
+						label: string
+					}
+					clientConnected: {
+						/**
+						 * V​P​N​ ​c​l​i​e​n​t​ ​c​o​n​n​e​c​t​e​d
+						 */
+						label: string
+					}
+					clientDisconnected: {
+						/**
+						 * V​P​N​ ​c​l​i​e​n​t​ ​d​i​s​c​o​n​n​e​c​t​e​d
+						 */
+						label: string
+					}
+					secret: {
+						/**
+						 * S​i​g​n​i​n​g​ ​s​e​c​r​e​t
+						 */
+						label: string
+						/**
+						 * O​p​t​i​o​n​a​l​ ​k​e​y​ ​u​s​e​d​ ​t​o​ ​s​i​g​n​ ​p​a​y​l​o​a​d​s​ ​w​i​t​h​ ​H​M​A​C​-​S​H​A​2​5​6
+						 */
+						placeholder: string
+					}
+					locations: {
+						/**
+						 * L​o​c​a​t​i​o​n​s
+						 */
+						label: string
+						/**
+						 * A​l​l​ ​l​o​c​a​t​i​o​n​s
+						 */
+						placeholder: string
+					}
 				}
 			}
 		}
@@ -1895,6 +1991,10 @@ type RootTranslation = {
 					 * E​m​a​i​l​ ​M​F​A​ ​d​i​s​a​b​l​e​d​.
 					 */
 					EmailMFADisabled: string
+					/**
+					 * S​M​S​ ​M​F​A​ ​d​i​s​a​b​l​e​d​.
+					 */
+					SmsMFADisabled: string
 					/**
 					 * M​F​A​ ​m​e​t​h​o​d​ ​c​h​a​n​g​e​d
 					 */
@@ -1931,6 +2031,10 @@ type RootTranslation = {
 					 * E​m​a​i​l
 					 */
 					email: string
+					/**
+					 * S​M​S
+					 */
+					sms: string
 					/**
 					 * S​e​c​u​r​i​t​y​ ​k​e​y​s
 					 */
@@ -2414,6 +2518,10 @@ type RootTranslation = {
 				 */
 				disableMfa: string
 			}
+			/**
+			 * S​e​r​v​i​c​e​ ​a​c​c​o​u​n​t
+			 */
+			serviceAccount: string
 		}
 	}
 	navigation: {
@@ -3160,6 +3268,30 @@ type RootTranslation = {
 					 * L​i​m​i​t​ ​s​y​n​c​h​r​o​n​i​z​a​t​i​o​n​ ​t​o​ ​t​h​e​s​e​ ​g​r​o​u​p​s
 					 */
 					ldap_sync_groups: string
+					/**
+					 * S​y​n​c​h​r​o​n​i​z​e​d​ ​g​r​o​u​p​ ​n​a​m​e​ ​p​a​t​t​e​r​n​s
+					 */
+					ldap_group_filter: string
+					/**
+					 * E​x​c​l​u​d​e​d​ ​g​r​o​u​p​ ​n​a​m​e​ ​p​a​t​t​e​r​n​s
+					 */
+					ldap_group_exclude: string
+					/**
+					 * E​m​a​i​l​ ​A​t​t​r​i​b​u​t​e
+					 */
+					ldap_email_attr: string
+					/**
+					 * F​i​r​s​t​ ​N​a​m​e​ ​A​t​t​r​i​b​u​t​e
+					 */
+					ldap_first_name_attr: string
+					/**
+					 * L​a​s​t​ ​N​a​m​e​ ​A​t​t​r​i​b​u​t​e
+					 */
+					ldap_last_name_attr: string
+					/**
+					 * P​h​o​n​e​ ​A​t​t​r​i​b​u​t​e
+					 */
+					ldap_phone_attr: string
 				}
 				helpers: {
 					/**
@@ -3190,6 +3322,14 @@ type RootTranslation = {
 					 * I​f​ ​y​o​u​r​ ​u​s​e​r​'​s​ ​R​D​N​ ​a​t​t​r​i​b​u​t​e​ ​i​s​ ​d​i​f​f​e​r​e​n​t​ ​t​h​a​n​ ​y​o​u​r​ ​u​s​e​r​n​a​m​e​ ​a​t​t​r​i​b​u​t​e​,​ ​p​l​e​a​s​e​ ​p​r​o​v​i​d​e​ ​i​t​ ​h​e​r​e​,​ ​o​t​h​e​r​w​i​s​e​ ​l​e​a​v​e​ ​i​t​ ​e​m​p​t​y​ ​t​o​ ​u​s​e​ ​t​h​e​ ​u​s​e​r​n​a​m​e​ ​a​t​t​r​i​b​u​t​e​ ​a​s​ ​t​h​e​ ​u​s​e​r​'​s​ ​R​D​N​.
 					 */
 					ldap_user_rdn_attr: string
+					/**
+					 * C​o​m​m​a​-​s​e​p​a​r​a​t​e​d​ ​g​r​o​u​p​ ​n​a​m​e​ ​p​a​t​t​e​r​n​s​.​ ​O​n​l​y​ ​m​a​t​c​h​i​n​g​ ​L​D​A​P​ ​g​r​o​u​p​s​ ​a​r​e​ ​s​y​n​c​h​r​o​n​i​z​e​d​ ​w​i​t​h​ ​D​e​f​g​u​a​r​d​;​ ​l​e​a​v​e​ ​e​m​p​t​y​ ​t​o​ ​s​y​n​c​h​r​o​n​i​z​e​ ​a​l​l​ ​g​r​o​u​p​s​.​ ​U​s​e​ ​*​ ​a​n​d​ ​?​ ​a​s​ ​w​i​l​d​c​a​r​d​s​ ​o​r​ ​w​r​a​p​ ​a​ ​p​a​t​t​e​r​n​ ​i​n​ ​s​l​a​s​h​e​s​ ​(​/​^​v​p​n​-​.​*​$​/​)​ ​t​o​ ​u​s​e​ ​a​ ​r​e​g​u​l​a​r​ ​e​x​p​r​e​s​s​i​o​n​.
+					 */
+					ldap_group_filter: string
+					/**
+					 * C​o​m​m​a​-​s​e​p​a​r​a​t​e​d​ ​g​r​o​u​p​ ​n​a​m​e​ ​p​a​t​t​e​r​n​s​.​ ​M​a​t​c​h​i​n​g​ ​L​D​A​P​ ​g​r​o​u​p​s​ ​a​r​e​ ​n​e​v​e​r​ ​s​y​n​c​h​r​o​n​i​z​e​d​ ​w​i​t​h​ ​D​e​f​g​u​a​r​d​,​ ​e​v​e​n​ ​i​f​ ​t​h​e​y​ ​m​a​t​c​h​ ​t​h​e​ ​p​a​t​t​e​r​n​s​ ​a​b​o​v​e​.
+					 */
+					ldap_group_exclude: string
 				}
 				headings: {
 					/**
@@ -3228,6 +3368,11 @@ type RootTranslation = {
 					 * L​D​A​P​ ​c​o​n​n​e​c​t​i​o​n​ ​r​e​j​e​c​t​e​d
 					 */
 					error: string
+					/**
+					 * L​D​A​P​ ​c​o​n​n​e​c​t​e​d​,​ ​b​u​t​ ​t​h​e​s​e​ ​a​t​t​r​i​b​u​t​e​s​ ​w​e​r​e​ ​n​o​t​ ​f​o​u​n​d​ ​o​n​ ​a​ ​s​a​m​p​l​e​ ​u​s​e​r​:​ ​{​a​t​t​r​i​b​u​t​e​s​}
+					 * @param {string} attributes
+					 */
+					unresolvedAttributes: RequiredParams<'attributes'>
 				}
 			}
 		}
@@ -5223,6 +5368,10 @@ type RootTranslation = {
 				 * U​s​e​ ​E​-​m​a​i​l​ ​i​n​s​t​e​a​d
 				 */
 				useEmail: string
+				/**
+				 * U​s​e​ ​S​M​S​ ​i​n​s​t​e​a​d
+				 */
+				useSms: string
 			}
 			email: {
 				/**
@@ -5243,6 +5392,32 @@ type RootTranslation = {
 						resendCode: string
 					}
 				}
+				messages: {
+					/**
+					 * P​l​e​a​s​e​ ​w​a​i​t​ ​a​ ​m​o​m​e​n​t​ ​b​e​f​o​r​e​ ​r​e​q​u​e​s​t​i​n​g​ ​a​n​o​t​h​e​r​ ​c​o​d​e​.
+					 */
+					resendTooSoon: string
+				}
+			}
+			sms: {
+				/**
+				 * U​s​e​ ​c​o​d​e​ ​w​e​ ​s​e​n​t​ ​t​o​ ​y​o​u​r​ ​p​h​o​n​e​ ​t​o​ ​p​r​o​c​e​e​d​.
+				 */
+				header: string
+				form: {
+					labels: {
+						/**
+						 * C​o​d​e
+						 */
+						code: string
+					}
+					controls: {
+						/**
+						 * R​e​s​e​n​d​ ​C​o​d​e
+						 */
+						resendCode: string
+					}
+				}
 			}
 			totp: {
 				/**
@@ -5486,6 +5661,22 @@ type RootTranslation = {
 		 */
 		subtitle: string
 	}
+	verifyEmailPage: {
+		messages: {
+			/**
+			 * E​m​a​i​l​ ​a​d​d​r​e​s​s​ ​h​a​s​ ​b​e​e​n​ ​c​h​a​n​g​e​d
+			 */
+			success: string
+			/**
+			 * E​m​a​i​l​ ​a​d​d​r​e​s​s​ ​c​o​u​l​d​ ​n​o​t​ ​b​e​ ​v​e​r​i​f​i​e​d​,​ ​t​h​e​ ​l​i​n​k​ ​m​a​y​ ​h​a​v​e​ ​e​x​p​i​r​e​d
+			 */
+			error: string
+			/**
+			 * E​m​a​i​l​ ​v​e​r​i​f​i​c​a​t​i​o​n​ ​l​i​n​k​ ​i​s​ ​i​n​v​a​l​i​d
+			 */
+			missingToken: string
+		}
+	}
 	enrollmentPage: {
 		/**
 		 * E​n​r​o​l​l​m​e​n​t
@@ -6491,6 +6682,15 @@ type RootTranslation = {
 				 */
 				search: string
 			}
+			/**
+			 * s​e​r​v​i​c​e​ ​a​c​c​o​u​n​t
+			 */
+			serviceAccount: string
+			/**
+			 * i​m​p​e​r​s​o​n​a​t​e​d​ ​b​y​ ​{​a​d​m​i​n​}
+			 * @param {string} admin
+			 */
+			impersonatedBy: RequiredParams<'admin'>
 		}
 	}
 	enums: {
@@ -6515,10 +6715,42 @@ type RootTranslation = {
 			 * R​e​c​o​v​e​r​y​ ​c​o​d​e​ ​u​s​e​d
 			 */
 			recovery_code_used: string
+			/**
+			 * R​e​c​o​v​e​r​y​ ​c​o​d​e​s​ ​r​e​g​e​n​e​r​a​t​e​d
+			 */
+			recovery_codes_regenerated: string
 			/**
 			 * U​s​e​r​ ​l​o​g​o​u​t
 			 */
 			user_logout: string
+			/**
+			 * B​r​e​a​k​-​g​l​a​s​s​ ​l​o​g​i​n
+			 */
+			break_glass_login: string
+			/**
+			 * A​d​m​i​n​ ​a​c​c​e​s​s​ ​d​e​n​i​e​d
+			 */
+			admin_access_denied: string
+			/**
+			 * U​s​e​r​ ​l​o​c​k​e​d​ ​o​u​t
+			 */
+			user_locked_out: string
+			/**
+			 * L​o​g​i​n​ ​a​d​d​r​e​s​s​ ​l​o​c​k​e​d​ ​o​u​t
+			 */
+			login_ip_locked_out: string
+			/**
+			 * U​s​e​r​ ​u​n​l​o​c​k​e​d
+			 */
+			user_unlocked: string
+			/**
+			 * L​o​g​i​n​ ​a​d​d​r​e​s​s​ ​u​n​l​o​c​k​e​d
+			 */
+			login_ip_unlocked: string
+			/**
+			 * R​a​t​e​ ​l​i​m​i​t​ ​e​x​c​e​e​d​e​d
+			 */
+			rate_limit_exceeded: string
 			/**
 			 * U​s​e​r​ ​a​d​d​e​d
 			 */
@@ -6531,10 +6763,22 @@ type RootTranslation = {
 			 * U​s​e​r​ ​m​o​d​i​f​i​e​d
 			 */
 			user_modified: string
+			/**
+			 * U​s​e​r​ ​d​i​s​a​b​l​e​d
+			 */
+			user_disabled: string
 			/**
 			 * U​s​e​r​ ​g​r​o​u​p​s​ ​m​o​d​i​f​i​e​d
 			 */
 			user_groups_modified: string
+			/**
+			 * U​s​e​r​ ​p​r​o​v​i​s​i​o​n​e​d
+			 */
+			user_provisioned: string
+			/**
+			 * A​c​c​o​u​n​t​ ​c​r​e​a​t​i​o​n​ ​r​e​f​u​s​e​d
+			 */
+			account_creation_refused: string
 			/**
 			 * M​F​A​ ​e​n​a​b​l​e​d
 			 */
@@ -6563,6 +6807,14 @@ type RootTranslation = {
 			 * M​F​A​ ​e​m​a​i​l​ ​d​i​s​a​b​l​e​d
 			 */
 			mfa_email_disabled: string
+			/**
+			 * M​F​A​ ​S​M​S​ ​e​n​a​b​l​e​d
+			 */
+			mfa_sms_enabled: string
+			/**
+			 * M​F​A​ ​S​M​S​ ​d​i​s​a​b​l​e​d
+			 */
+			mfa_sms_disabled: string
 			/**
 			 * M​F​A​ ​s​e​c​u​r​i​t​y​ ​k​e​y​ ​a​d​d​e​d
 			 */
@@ -6583,6 +6835,14 @@ type RootTranslation = {
 			 * D​e​v​i​c​e​ ​m​o​d​i​f​i​e​d
 			 */
 			device_modified: string
+			/**
+			 * D​e​v​i​c​e​ ​a​p​p​r​o​v​e​d
+			 */
+			device_approved: string
+			/**
+			 * D​e​v​i​c​e​ ​r​e​j​e​c​t​e​d
+			 */
+			device_rejected: string
 			/**
 			 * N​e​t​w​o​r​k​ ​d​e​v​i​c​e​ ​a​d​d​e​d
 			 */
@@ -6595,6 +6855,30 @@ type RootTranslation = {
 			 * N​e​t​w​o​r​k​ ​d​e​v​i​c​e​ ​m​o​d​i​f​i​e​d
 			 */
 			network_device_modified: string
+			/**
+			 * D​e​v​i​c​e​ ​a​d​d​r​e​s​s​ ​r​e​s​e​r​v​e​d
+			 */
+			device_address_reserved: string
+			/**
+			 * D​e​v​i​c​e​ ​a​d​d​r​e​s​s​ ​r​e​l​e​a​s​e​d
+			 */
+			device_address_released: string
+			/**
+			 * D​e​v​i​c​e​ ​p​r​o​f​i​l​e​ ​a​d​d​e​d
+			 */
+			device_profile_added: string
+			/**
+			 * D​e​v​i​c​e​ ​p​r​o​f​i​l​e​ ​m​o​d​i​f​i​e​d
+			 */
+			device_profile_modified: string
+			/**
+			 * D​e​v​i​c​e​ ​p​r​o​f​i​l​e​ ​r​e​m​o​v​e​d
+			 */
+			device_profile_removed: string
+			/**
+			 * D​e​v​i​c​e​ ​p​r​o​f​i​l​e​ ​a​s​s​i​g​n​e​d
+			 */
+			device_profile_assigned: string
 			/**
 			 * A​c​t​i​v​i​t​y​ ​l​o​g​ ​s​t​r​e​a​m​ ​c​r​e​a​t​e​d
 			 */
@@ -6607,6 +6891,10 @@ type RootTranslation = {
 			 * A​c​t​i​v​i​t​y​ ​l​o​g​ ​s​t​r​e​a​m​ ​r​e​m​o​v​e​d
 			 */
 			activity_log_stream_removed: string
+			/**
+			 * A​c​t​i​v​i​t​y​ ​l​o​g​ ​p​u​r​g​e​d
+			 */
+			activity_log_purged: string
 			/**
 			 * V​P​N​ ​c​l​i​e​n​t​ ​c​o​n​n​e​c​t​e​d
 			 */
@@ -6627,10 +6915,30 @@ type RootTranslation = {
 			 * V​P​N​ ​c​l​i​e​n​t​ ​f​a​i​l​e​d​ ​M​F​A​ ​a​u​t​h​e​n​t​i​c​a​t​i​o​n
 			 */
 			vpn_client_mfa_failed: string
+			/**
+			 * V​P​N​ ​c​l​i​e​n​t​ ​l​o​c​k​e​d​ ​o​u​t​ ​o​f​ ​M​F​A​ ​a​u​t​h​e​n​t​i​c​a​t​i​o​n
+			 */
+			vpn_client_mfa_locked_out: string
+			/**
+			 * V​P​N​ ​c​l​i​e​n​t​ ​d​e​n​i​e​d​ ​d​u​e​ ​t​o​ ​d​e​v​i​c​e​ ​p​o​s​t​u​r​e
+			 */
+			vpn_client_mfa_posture_failed: string
+			/**
+			 * V​P​N​ ​c​l​i​e​n​t​ ​M​F​A​ ​a​u​t​h​e​n​t​i​c​a​t​i​o​n​ ​t​i​m​e​d​ ​o​u​t
+			 */
+			vpn_client_mfa_timeout: string
+			/**
+			 * V​P​N​ ​s​e​s​s​i​o​n​ ​l​i​m​i​t​ ​e​x​c​e​e​d​e​d
+			 */
+			vpn_client_session_limit_exceeded: string
 			/**
 			 * E​n​r​o​l​l​m​e​n​t​ ​t​o​k​e​n​ ​a​d​d​e​d
 			 */
 			enrollment_token_added: string
+			/**
+			 * E​n​r​o​l​l​m​e​n​t​ ​t​o​k​e​n​ ​r​e​v​o​k​e​d
+			 */
+			enrollment_token_revoked: string
 			/**
 			 * E​n​r​o​l​l​m​e​n​t​ ​s​t​a​r​t​e​d
 			 */
@@ -6643,6 +6951,10 @@ type RootTranslation = {
 			 * E​n​r​o​l​l​m​e​n​t​ ​c​o​m​p​l​e​t​e​d
 			 */
 			enrollment_completed: string
+			/**
+			 * E​n​r​o​l​l​m​e​n​t​ ​b​l​o​c​k​e​d
+			 */
+			enrollment_blocked: string
 			/**
 			 * P​a​s​s​w​o​r​d​ ​r​e​s​e​t​ ​r​e​q​u​e​s​t​e​d
 			 */
@@ -6655,6 +6967,10 @@ type RootTranslation = {
 			 * P​a​s​s​w​o​r​d​ ​r​e​s​e​t​ ​c​o​m​p​l​e​t​e​d
 			 */
 			password_reset_completed: string
+			/**
+			 * P​a​s​s​w​o​r​d​ ​r​e​s​e​t​ ​b​l​o​c​k​e​d
+			 */
+			password_reset_blocked: string
 			/**
 			 * V​P​N​ ​l​o​c​a​t​i​o​n​ ​a​d​d​e​d
 			 */
@@ -6667,6 +6983,22 @@ type RootTranslation = {
 			 * V​P​N​ ​l​o​c​a​t​i​o​n​ ​m​o​d​i​f​i​e​d
 			 */
 			vpn_location_modified: string
+			/**
+			 * V​P​N​ ​l​o​c​a​t​i​o​n​ ​m​a​i​n​t​e​n​a​n​c​e​ ​e​n​a​b​l​e​d
+			 */
+			vpn_location_maintenance_enabled: string
+			/**
+			 * V​P​N​ ​l​o​c​a​t​i​o​n​ ​m​a​i​n​t​e​n​a​n​c​e​ ​d​i​s​a​b​l​e​d
+			 */
+			vpn_location_maintenance_disabled: string
+			/**
+			 * G​a​t​e​w​a​y​ ​t​o​k​e​n​ ​i​s​s​u​e​d
+			 */
+			gateway_token_added: string
+			/**
+			 * G​a​t​e​w​a​y​ ​t​o​k​e​n​ ​r​e​v​o​k​e​d
+			 */
+			gateway_token_revoked: string
 			/**
 			 * A​P​I​ ​t​o​k​e​n​ ​a​d​d​e​d
 			 */
@@ -6695,6 +7027,14 @@ type RootTranslation = {
 			 * O​p​e​n​I​D​ ​a​p​p​ ​s​t​a​t​e​ ​c​h​a​n​g​e​d
 			 */
 			open_id_app_state_changed: string
+			/**
+			 * C​l​i​e​n​t​ ​c​r​e​d​e​n​t​i​a​l​s​ ​g​r​a​n​t​ ​e​n​a​b​l​e​d
+			 */
+			client_credentials_set: string
+			/**
+			 * C​l​i​e​n​t​ ​c​r​e​d​e​n​t​i​a​l​s​ ​g​r​a​n​t​ ​d​i​s​a​b​l​e​d
+			 */
+			client_credentials_removed: string
 			/**
 			 * O​p​e​n​I​D​ ​p​r​o​v​i​d​e​r​ ​r​e​m​o​v​e​d
 			 */
@@ -6703,6 +7043,18 @@ type RootTranslation = {
 			 * O​p​e​n​I​D​ ​p​r​o​v​i​d​e​r​ ​m​o​d​i​f​i​e​d
 			 */
 			open_id_provider_modified: string
+			/**
+			 * S​A​M​L​ ​p​r​o​v​i​d​e​r​ ​m​o​d​i​f​i​e​d
+			 */
+			saml_provider_modified: string
+			/**
+			 * S​A​M​L​ ​p​r​o​v​i​d​e​r​ ​r​e​m​o​v​e​d
+			 */
+			saml_provider_removed: string
+			/**
+			 * P​r​o​v​i​s​i​o​n​i​n​g​ ​p​o​l​i​c​y​ ​m​o​d​i​f​i​e​d
+			 */
+			provisioning_policy_modified: string
 			/**
 			 * S​e​t​t​i​n​g​s​ ​u​p​d​a​t​e​d
 			 */
@@ -6715,6 +7067,14 @@ type RootTranslation = {
 			 * D​e​f​a​u​l​t​ ​b​r​a​n​d​i​n​g​ ​r​e​s​t​o​r​e​d
 			 */
 			settings_default_branding_restored: string
+			/**
+			 * E​m​a​i​l​ ​t​e​m​p​l​a​t​e​ ​c​u​s​t​o​m​i​z​e​d
+			 */
+			mail_template_modified: string
+			/**
+			 * E​m​a​i​l​ ​t​e​m​p​l​a​t​e​ ​r​e​s​e​t
+			 */
+			mail_template_reset: string
 			/**
 			 * G​r​o​u​p​s​ ​b​u​l​k​ ​a​s​s​i​g​n​e​d
 			 */
@@ -6739,10 +7099,34 @@ type RootTranslation = {
 			 * G​r​o​u​p​ ​m​e​m​b​e​r​ ​r​e​m​o​v​e​d
 			 */
 			group_member_removed: string
+			/**
+			 * G​r​o​u​p​ ​m​e​m​b​e​r​s​h​i​p​ ​e​x​p​i​r​e​d
+			 */
+			group_membership_expired: string
 			/**
 			 * G​r​o​u​p​ ​m​e​m​b​e​r​s​ ​m​o​d​i​f​i​e​d
 			 */
 			group_members_modified: string
+			/**
+			 * G​r​o​u​p​ ​q​u​o​t​a​ ​m​o​d​i​f​i​e​d
+			 */
+			group_quota_modified: string
+			/**
+			 * G​r​o​u​p​ ​s​y​n​c​ ​a​u​t​h​o​r​i​t​y​ ​m​o​d​i​f​i​e​d
+			 */
+			group_sync_authority_modified: string
+			/**
+			 * G​r​o​u​p​ ​p​a​r​e​n​t​ ​m​o​d​i​f​i​e​d
+			 */
+			group_parent_modified: string
+			/**
+			 * G​r​o​u​p​ ​j​o​i​n​ ​r​e​q​u​e​s​t​ ​a​p​p​r​o​v​e​d
+			 */
+			group_join_request_approved: string
+			/**
+			 * G​r​o​u​p​ ​j​o​i​n​ ​r​e​q​u​e​s​t​ ​r​e​j​e​c​t​e​d
+			 */
+			group_join_request_rejected: string
 			/**
 			 * W​e​b​h​o​o​k​ ​a​d​d​e​d
 			 */
@@ -6783,6 +7167,22 @@ type RootTranslation = {
 			 * P​a​s​s​w​o​r​d​ ​r​e​s​e​t
 			 */
 			password_reset: string
+			/**
+			 * U​s​e​r​ ​s​e​s​s​i​o​n​ ​r​e​v​o​k​e​d
+			 */
+			user_session_revoked: string
+			/**
+			 * U​s​e​r​ ​s​e​s​s​i​o​n​s​ ​r​e​v​o​k​e​d
+			 */
+			user_sessions_revoked: string
+			/**
+			 * I​m​p​e​r​s​o​n​a​t​i​o​n​ ​s​t​a​r​t​e​d
+			 */
+			impersonation_started: string
+			/**
+			 * I​m​p​e​r​s​o​n​a​t​i​o​n​ ​e​n​d​e​d
+			 */
+			impersonation_ended: string
 			/**
 			 * C​l​i​e​n​t​ ​c​o​n​f​i​g​u​r​a​t​i​o​n​ ​t​o​k​e​n​ ​a​d​d​e​d
 			 */
@@ -6799,6 +7199,10 @@ type RootTranslation = {
 			 * U​s​e​r​ ​S​N​A​T​ ​b​i​n​d​i​n​g​ ​r​e​m​o​v​e​d
 			 */
 			user_snat_binding_removed: string
+			/**
+			 * C​u​s​t​o​m​ ​e​v​e​n​t
+			 */
+			custom_event: string
 		}
 		activityLogModule: {
 			/**
@@ -6817,6 +7221,14 @@ type RootTranslation = {
 			 * V​P​N
 			 */
 			vpn: string
+			/**
+			 * L​D​A​P
+			 */
+			ldap: string
+			/**
+			 * C​u​s​t​o​m
+			 */
+			custom: string
 		}
 	}
 }
@@ -7808,14 +8220,72 @@ export type TranslationFunctions = {
 				}
 			}
 		}
-		editDevice: {
+		registerSmsMFA: {
 			/**
-			 * Edit device
+			 * SMS MFA Setup
 			 */
 			title: () => LocalizedString
+			/**
+			 * Enter the phone number you want to receive codes on. A verification code will be sent to it.
+			 */
+			infoMessage: () => LocalizedString
 			messages: {
 				/**
-				 * Device has been updated.
+				 * SMS MFA Enabled
+				 */
+				success: () => LocalizedString
+				/**
+				 * Verification code resent
+				 */
+				resend: () => LocalizedString
+			}
+			form: {
+				fields: {
+					phone: {
+						/**
+						 * Phone number
+						 */
+						label: () => LocalizedString
+						/**
+						 * Phone number is invalid
+						 */
+						error: () => LocalizedString
+					}
+					code: {
+						/**
+						 * SMS code
+						 */
+						label: () => LocalizedString
+						/**
+						 * Code is invalid
+						 */
+						error: () => LocalizedString
+					}
+				}
+				controls: {
+					/**
+					 * Send code
+					 */
+					send: () => LocalizedString
+					/**
+					 * Verify code
+					 */
+					submit: () => LocalizedString
+					/**
+					 * Resend SMS
+					 */
+					resend: () => LocalizedString
+				}
+			}
+		}
+		editDevice: {
+			/**
+			 * Edit device
+			 */
+			title: () => LocalizedString
+			messages: {
+				/**
+				 * Device has been updated.
 				 */
 				success: () => LocalizedString
 			}
@@ -8241,6 +8711,44 @@ export type TranslationFunctions = {
 						 */
 						label: () => LocalizedString
 					}
+					gatewayDisconnected: {
+						/**
+						 * Gateway disconnected
+						 */
+						label: () => LocalizedString
+					}
+					clientConnected: {
+						/**
+						 * VPN client connected
+						 */
+						label: () => LocalizedString
+					}
+					clientDisconnected: {
+						/**
+						 * VPN client disconnected
+						 */
+						label: () => LocalizedString
+					}
+					secret: {
+						/**
+						 * Signing secret
+						 */
+						label: () => LocalizedString
+						/**
+						 * Optional key used to sign payloads with HMAC-SHA256
+						 */
+						placeholder: () => LocalizedString
+					}
+					locations: {
+						/**
+						 * Locations
+						 */
+						label: () => LocalizedString
+						/**
+						 * All locations
+						 */
+						placeholder: () => LocalizedString
+					}
 				}
 			}
 		}
@@ -8680,6 +9188,10 @@ export type TranslationFunctions = {
 					 * Email MFA disabled.
 					 */
 					EmailMFADisabled: () => LocalizedString
+					/**
+					 * SMS MFA disabled.
+					 */
+					SmsMFADisabled: () => LocalizedString
 					/**
 					 * MFA method changed
 					 */
@@ -8716,6 +9228,10 @@ export type TranslationFunctions = {
 					 * Email
 					 */
 					email: () => LocalizedString
+					/**
+					 * SMS
+					 */
+					sms: () => LocalizedString
 					/**
 					 * Security keys
 					 */
@@ -9196,6 +9712,10 @@ export type TranslationFunctions = {
 				 */
 				disableMfa: () => LocalizedString
 			}
+			/**
+			 * Service account
+			 */
+			serviceAccount: () => LocalizedString
 		}
 	}
 	navigation: {
@@ -9753,6 +10273,26 @@ export type TranslationFunctions = {
 					 */
 					modify: () => LocalizedString
 				}
+				syslog: {
+					/**
+					 * Add Syslog destination
+					 */
+					create: () => LocalizedString
+					/**
+					 * Edit Syslog destination
+					 */
+					modify: () => LocalizedString
+				}
+				kafka: {
+					/**
+					 * Add Kafka destination
+					 */
+					create: () => LocalizedString
+					/**
+					 * Edit Kafka destination
+					 */
+					modify: () => LocalizedString
+				}
 				shared: {
 					formLabels: {
 						/**
@@ -9775,6 +10315,54 @@ export type TranslationFunctions = {
 						 * Certificate
 						 */
 						cert: () => LocalizedString
+						/**
+						 * Host
+						 */
+						host: () => LocalizedString
+						/**
+						 * Port
+						 */
+						port: () => LocalizedString
+						/**
+						 * Protocol
+						 */
+						protocol: () => LocalizedString
+						/**
+						 * Facility
+						 */
+						facility: () => LocalizedString
+						/**
+						 * Default severity
+						 */
+						severity: () => LocalizedString
+						/**
+						 * Hostname
+						 */
+						hostname: () => LocalizedString
+						/**
+						 * Brokers (comma separated)
+						 */
+						brokers: () => LocalizedString
+						/**
+						 * Topic
+						 */
+						topic: () => LocalizedString
+						/**
+						 * Payload format
+						 */
+						format: () => LocalizedString
+						/**
+						 * Use TLS
+						 */
+						tls: () => LocalizedString
+						/**
+						 * SASL mechanism
+						 */
+						saslMechanism: () => LocalizedString
+						/**
+						 * None
+						 */
+						saslNone: () => LocalizedString
 					}
 				}
 			}
@@ -9931,6 +10519,30 @@ export type TranslationFunctions = {
 					 * Limit synchronization to these groups
 					 */
 					ldap_sync_groups: () => LocalizedString
+					/**
+					 * Synchronized group name patterns
+					 */
+					ldap_group_filter: () => LocalizedString
+					/**
+					 * Excluded group name patterns
+					 */
+					ldap_group_exclude: () => LocalizedString
+					/**
+					 * Email Attribute
+					 */
+					ldap_email_attr: () => LocalizedString
+					/**
+					 * First Name Attribute
+					 */
+					ldap_first_name_attr: () => LocalizedString
+					/**
+					 * Last Name Attribute
+					 */
+					ldap_last_name_attr: () => LocalizedString
+					/**
+					 * Phone Attribute
+					 */
+					ldap_phone_attr: () => LocalizedString
 				}
 				helpers: {
 					/**
@@ -9961,6 +10573,14 @@ export type TranslationFunctions = {
 					 * If your user's RDN attribute is different than your username attribute, please provide it here, otherwise leave it empty to use the username attribute as the user's RDN.
 					 */
 					ldap_user_rdn_attr: () => LocalizedString
+					/**
+					 * Comma-separated group name patterns. Only matching LDAP groups are synchronized with Defguard; leave empty to synchronize all groups. Use * and ? as wildcards or wrap a pattern in slashes (/^vpn-.*$/) to use a regular expression.
+					 */
+					ldap_group_filter: () => LocalizedString
+					/**
+					 * Comma-separated group name patterns. Matching LDAP groups are never synchronized with Defguard, even if they match the patterns above.
+					 */
+					ldap_group_exclude: () => LocalizedString
 				}
 				headings: {
 					/**
@@ -9999,6 +10619,10 @@ export type TranslationFunctions = {
 					 * LDAP connection rejected
 					 */
 					error: () => LocalizedString
+					/**
+					 * LDAP connected, but these attributes were not found on a sample user: {attributes}
+					 */
+					unresolvedAttributes: (arg: { attributes: string }) => LocalizedString
 				}
 			}
 		}
@@ -11644,7 +12268,7 @@ export type TranslationFunctions = {
 		form: {
 			helpers: {
 				/**
-				 * Based on this address VPN network address will be defined, eg. 10.10.10.1/24 (and VPN network will be: 10.10.10.0/24). You can optionally specify multiple addresses separated by a comma. The first address is the primary address, and this one will be used for IP address assignment for devices. The other IP addresses are auxiliary and are not managed by Defguard.
+				 * Based on this address VPN network address will be defined, eg. 10.10.10.1/24 (and VPN network will be: 10.10.10.0/24). You can optionally specify multiple addresses separated by a comma, e.g. an IPv4 and an IPv6 address for a dual-stack network. Devices get an address from each of the networks.
 				 */
 				address: () => LocalizedString
 				/**
@@ -11974,6 +12598,10 @@ export type TranslationFunctions = {
 				 * Use E-mail instead
 				 */
 				useEmail: () => LocalizedString
+				/**
+				 * Use SMS instead
+				 */
+				useSms: () => LocalizedString
 			}
 			email: {
 				/**
@@ -11994,6 +12622,32 @@ export type TranslationFunctions = {
 						resendCode: () => LocalizedString
 					}
 				}
+				messages: {
+					/**
+					 * Please wait a moment before requesting another code.
+					 */
+					resendTooSoon: () => LocalizedString
+				}
+			}
+			sms: {
+				/**
+				 * Use code we sent to your phone to proceed.
+				 */
+				header: () => LocalizedString
+				form: {
+					labels: {
+						/**
+						 * Code
+						 */
+						code: () => LocalizedString
+					}
+					controls: {
+						/**
+						 * Resend Code
+						 */
+						resendCode: () => LocalizedString
+					}
+				}
 			}
 			totp: {
 				/**
@@ -12237,6 +12891,22 @@ export type TranslationFunctions = {
 		 */
 		subtitle: () => LocalizedString
 	}
+	verifyEmailPage: {
+		messages: {
+			/**
+			 * Email address has been changed
+			 */
+			success: () => LocalizedString
+			/**
+			 * Email address could not be verified, the link may have expired
+			 */
+			error: () => LocalizedString
+			/**
+			 * Email verification link is invalid
+			 */
+			missingToken: () => LocalizedString
+		}
+	}
 	enrollmentPage: {
 		/**
 		 * Enrollment
@@ -13234,6 +13904,14 @@ export type TranslationFunctions = {
 				 */
 				search: () => LocalizedString
 			}
+			/**
+			 * service account
+			 */
+			serviceAccount: () => LocalizedString
+			/**
+			 * impersonated by {admin}
+			 */
+			impersonatedBy: (arg: { admin: string }) => LocalizedString
 		}
 	}
 	enums: {
@@ -13258,10 +13936,42 @@ export type TranslationFunctions = {
 			 * Recovery code used
 			 */
 			recovery_code_used: () => LocalizedString
+			/**
+			 * Recovery codes regenerated
+			 */
+			recovery_codes_regenerated: () => LocalizedString
 			/**
 			 * User logout
 			 */
 			user_logout: () => LocalizedString
+			/**
+			 * Break-glass login
+			 */
+			break_glass_login: () => LocalizedString
+			/**
+			 * Admin access denied
+			 */
+			admin_access_denied: () => LocalizedString
+			/**
+			 * User locked out
+			 */
+			user_locked_out: () => LocalizedString
+			/**
+			 * Login address locked out
+			 */
+			login_ip_locked_out: () => LocalizedString
+			/**
+			 * User unlocked
+			 */
+			user_unlocked: () => LocalizedString
+			/**
+			 * Login address unlocked
+			 */
+			login_ip_unlocked: () => LocalizedString
+			/**
+			 * Rate limit exceeded
+			 */
+			rate_limit_exceeded: () => LocalizedString
 			/**
 			 * User added
 			 */
@@ -13274,10 +13984,22 @@ export type TranslationFunctions = {
 			 * User modified
 			 */
 			user_modified: () => LocalizedString
+			/**
+			 * User disabled
+			 */
+			user_disabled: () => LocalizedString
 			/**
 			 * User groups modified
 			 */
 			user_groups_modified: () => LocalizedString
+			/**
+			 * User provisioned
+			 */
+			user_provisioned: () => LocalizedString
+			/**
+			 * Account creation refused
+			 */
+			account_creation_refused: () => LocalizedString
 			/**
 			 * MFA enabled
 			 */
@@ -13306,6 +14028,14 @@ export type TranslationFunctions = {
 			 * MFA email disabled
 			 */
 			mfa_email_disabled: () => LocalizedString
+			/**
+			 * MFA SMS enabled
+			 */
+			mfa_sms_enabled: () => LocalizedString
+			/**
+			 * MFA SMS disabled
+			 */
+			mfa_sms_disabled: () => LocalizedString
 			/**
 			 * MFA security key added
 			 */
@@ -13326,6 +14056,14 @@ export type TranslationFunctions = {
 			 * Device modified
 			 */
 			device_modified: () => LocalizedString
+			/**
+			 * Device approved
+			 */
+			device_approved: () => LocalizedString
+			/**
+			 * Device rejected
+			 */
+			device_rejected: () => LocalizedString
 			/**
 			 * Network device added
 			 */
@@ -13338,6 +14076,30 @@ export type TranslationFunctions = {
 			 * Network device modified
 			 */
 			network_device_modified: () => LocalizedString
+			/**
+			 * Device address reserved
+			 */
+			device_address_reserved: () => LocalizedString
+			/**
+			 * Device address released
+			 */
+			device_address_released: () => LocalizedString
+			/**
+			 * Device profile added
+			 */
+			device_profile_added: () => LocalizedString
+			/**
+			 * Device profile modified
+			 */
+			device_profile_modified: () => LocalizedString
+			/**
+			 * Device profile removed
+			 */
+			device_profile_removed: () => LocalizedString
+			/**
+			 * Device profile assigned
+			 */
+			device_profile_assigned: () => LocalizedString
 			/**
 			 * Activity log stream created
 			 */
@@ -13350,6 +14112,10 @@ export type TranslationFunctions = {
 			 * Activity log stream removed
 			 */
 			activity_log_stream_removed: () => LocalizedString
+			/**
+			 * Activity log purged
+			 */
+			activity_log_purged: () => LocalizedString
 			/**
 			 * VPN client connected
 			 */
@@ -13370,10 +14136,30 @@ export type TranslationFunctions = {
 			 * VPN client failed MFA authentication
 			 */
 			vpn_client_mfa_failed: () => LocalizedString
+			/**
+			 * VPN client locked out of MFA authentication
+			 */
+			vpn_client_mfa_locked_out: () => LocalizedString
+			/**
+			 * VPN client denied due to device posture
+			 */
+			vpn_client_mfa_posture_failed: () => LocalizedString
+			/**
+			 * VPN client MFA authentication timed out
+			 */
+			vpn_client_mfa_timeout: () => LocalizedString
+			/**
+			 * VPN session limit exceeded
+			 */
+			vpn_client_session_limit_exceeded: () => LocalizedString
 			/**
 			 * Enrollment token added
 			 */
 			enrollment_token_added: () => LocalizedString
+			/**
+			 * Enrollment token revoked
+			 */
+			enrollment_token_revoked: () => LocalizedString
 			/**
 			 * Enrollment started
 			 */
@@ -13386,6 +14172,10 @@ export type TranslationFunctions = {
 			 * Enrollment completed
 			 */
 			enrollment_completed: () => LocalizedString
+			/**
+			 * Enrollment blocked
+			 */
+			enrollment_blocked: () => LocalizedString
 			/**
 			 * Password reset requested
 			 */
@@ -13398,6 +14188,10 @@ export type TranslationFunctions = {
 			 * Password reset completed
 			 */
 			password_reset_completed: () => LocalizedString
+			/**
+			 * Password reset blocked
+			 */
+			password_reset_blocked: () => LocalizedString
 			/**
 			 * VPN location added
 			 */
@@ -13410,6 +14204,22 @@ export type TranslationFunctions = {
 			 * VPN location modified
 			 */
 			vpn_location_modified: () => LocalizedString
+			/**
+			 * VPN location maintenance enabled
+			 */
+			vpn_location_maintenance_enabled: () => LocalizedString
+			/**
+			 * VPN location maintenance disabled
+			 */
+			vpn_location_maintenance_disabled: () => LocalizedString
+			/**
+			 * Gateway token issued
+			 */
+			gateway_token_added: () => LocalizedString
+			/**
+			 * Gateway token revoked
+			 */
+			gateway_token_revoked: () => LocalizedString
 			/**
 			 * API token added
 			 */
@@ -13438,6 +14248,14 @@ export type TranslationFunctions = {
 			 * OpenID app state changed
 			 */
 			open_id_app_state_changed: () => LocalizedString
+			/**
+			 * Client credentials grant enabled
+			 */
+			client_credentials_set: () => LocalizedString
+			/**
+			 * Client credentials grant disabled
+			 */
+			client_credentials_removed: () => LocalizedString
 			/**
 			 * OpenID provider removed
 			 */
@@ -13446,6 +14264,18 @@ export type TranslationFunctions = {
 			 * OpenID provider modified
 			 */
 			open_id_provider_modified: () => LocalizedString
+			/**
+			 * SAML provider modified
+			 */
+			saml_provider_modified: () => LocalizedString
+			/**
+			 * SAML provider removed
+			 */
+			saml_provider_removed: () => LocalizedString
+			/**
+			 * Provisioning policy modified
+			 */
+			provisioning_policy_modified: () => LocalizedString
 			/**
 			 * Settings updated
 			 */
@@ -13458,6 +14288,14 @@ export type TranslationFunctions = {
 			 * Default branding restored
 			 */
 			settings_default_branding_restored: () => LocalizedString
+			/**
+			 * Email template customized
+			 */
+			mail_template_modified: () => LocalizedString
+			/**
+			 * Email template reset
+			 */
+			mail_template_reset: () => LocalizedString
 			/**
 			 * Groups bulk assigned
 			 */
@@ -13482,10 +14320,34 @@ export type TranslationFunctions = {
 			 * Group member removed
 			 */
 			group_member_removed: () => LocalizedString
+			/**
+			 * Group membership expired
+			 */
+			group_membership_expired: () => LocalizedString
 			/**
 			 * Group members modified
 			 */
 			group_members_modified: () => LocalizedString
+			/**
+			 * Group quota modified
+			 */
+			group_quota_modified: () => LocalizedString
+			/**
+			 * Group sync authority modified
+			 */
+			group_sync_authority_modified: () => LocalizedString
+			/**
+			 * Group parent modified
+			 */
+			group_parent_modified: () => LocalizedString
+			/**
+			 * Group join request approved
+			 */
+			group_join_request_approved: () => LocalizedString
+			/**
+			 * Group join request rejected
+			 */
+			group_join_request_rejected: () => LocalizedString
 			/**
 			 * Webhook added
 			 */
@@ -13526,6 +14388,22 @@ export type TranslationFunctions = {
 			 * Password reset
 			 */
 			password_reset: () => LocalizedString
+			/**
+			 * User session revoked
+			 */
+			user_session_revoked: () => LocalizedString
+			/**
+			 * User sessions revoked
+			 */
+			user_sessions_revoked: () => LocalizedString
+			/**
+			 * Impersonation started
+			 */
+			impersonation_started: () => LocalizedString
+			/**
+			 * Impersonation ended
+			 */
+			impersonation_ended: () => LocalizedString
 			/**
 			 * Client configuration token added
 			 */
@@ -13542,6 +14420,10 @@ export type TranslationFunctions = {
 			 * User SNAT binding removed
 			 */
 			user_snat_binding_removed: () => LocalizedString
+			/**
+			 * Custom event
+			 */
+			custom_event: () => LocalizedString
 		}
 		activityLogModule: {
 			/**
@@ -13560,6 +14442,14 @@ export type TranslationFunctions = {
 			 * VPN
 			 */
 			vpn: () => LocalizedString
+			/**
+			 * LDAP
+			 */
+			ldap: () => LocalizedString
+			/**
+			 * Custom
+			 */
+			custom: () => LocalizedString
 		}
 	}
 }
@@ -138,7 +138,17 @@ export const ActivityList = ({
                   />
                 </div>
                 <div className="cell user">
-                  <ListCellText text={activity.username} />
+                  <ListCellText
+                    text={
+                      activity.service_account
+                        ? `${activity.username} (${localLL.serviceAccount()})`
+                        : activity.impersonator
+                          ? `${activity.username} (${localLL.impersonatedBy({
+                              admin: activity.impersonator,
+                            })})`
+                          : activity.username
+                    }
+                  />
                 </div>
                 <div className="cell ip">
                   <ListCellText text={activity.ip} />
@@ -1,10 +1,18 @@
-export type ActivityLogModule = 'defguard' | 'client' | 'vpn' | 'enrollment';
+export type ActivityLogModule =
+  | 'defguard'
+  | 'client'
+  | 'vpn'
+  | 'enrollment'
+  | 'ldap'
+  | 'custom';
 
 export const activityLogModuleValues: ActivityLogModule[] = [
   'defguard',
   'client',
   'enrollment',
   'vpn',
+  'ldap',
+  'custom',
 ];
 
 export type ActivityLogEventType =
@@ -13,43 +21,76 @@ export type ActivityLogEventType =
   | 'user_mfa_login'
   | 'user_mfa_login_failed'
   | 'recovery_code_used'
+  | 'recovery_codes_regenerated'
   | 'user_logout'
+  | 'break_glass_login'
+  | 'admin_access_denied'
+  | 'user_locked_out'
+  | 'login_ip_locked_out'
+  | 'user_unlocked'
+  | 'login_ip_unlocked'
+  | 'rate_limit_exceeded'
   | 'user_added'
   | 'user_modified'
+  | 'user_disabled'
   | 'user_removed'
   | 'user_groups_modified'
+  | 'user_provisioned'
+  | 'account_creation_refused'
   | 'mfa_disabled'
   | 'user_mfa_disabled'
   | 'mfa_totp_enabled'
   | 'mfa_totp_disabled'
   | 'mfa_email_enabled'
   | 'mfa_email_disabled'
+  | 'mfa_sms_enabled'
+  | 'mfa_sms_disabled'
   | 'mfa_security_key_added'
   | 'mfa_security_key_removed'
   | 'device_added'
   | 'device_modified'
+  | 'device_approved'
+  | 'device_rejected'
   | 'device_removed'
   | 'network_device_added'
   | 'network_device_modified'
+  | 'device_address_reserved'
+  | 'device_address_released'
+  | 'device_profile_added'
+  | 'device_profile_modified'
+  | 'device_profile_removed'
+  | 'device_profile_assigned'
   | 'network_device_removed'
   | 'activity_log_stream_created'
   | 'activity_log_stream_modified'
   | 'activity_log_stream_removed'
+  | 'activity_log_purged'
   | 'vpn_client_connected'
   | 'vpn_client_disconnected'
   | 'vpn_client_connected_mfa'
   | 'vpn_client_disconnected_mfa'
   | 'vpn_client_mfa_failed'
+  | 'vpn_client_mfa_locked_out'
+  | 'vpn_client_mfa_posture_failed'
+  | 'vpn_client_mfa_timeout'
+  | 'vpn_client_session_limit_exceeded'
   | 'enrollment_token_added'
+  | 'enrollment_token_revoked'
   | 'enrollment_started'
   | 'enrollment_device_added'
   | 'enrollment_completed'
+  | 'enrollment_blocked'
   | 'password_reset_requested'
   | 'password_reset_started'
   | 'password_reset_completed'
+  | 'password_reset_blocked'
   | 'vpn_location_added'
   | 'vpn_location_removed'
   | 'vpn_location_modified'
+  | 'vpn_location_maintenance_enabled'
+  | 'vpn_location_maintenance_disabled'
+  | 'gateway_token_added'
+  | 'gateway_token_revoked'
   | 'api_token_added'
   | 'api_token_removed'
   | 'api_token_renamed'
@@ -57,18 +98,31 @@ export type ActivityLogEventType =
   | 'open_id_app_removed'
   | 'open_id_app_modified'
   | 'open_id_app_state_changed'
+  | 'client_credentials_set'
+  | 'client_credentials_removed'
   | 'open_id_provider_removed'
   | 'open_id_provider_modified'
+  | 'saml_provider_modified'
+  | 'saml_provider_removed'
+  | 'provisioning_policy_modified'
   | 'settings_updated'
   | 'settings_updated_partial'
   | 'settings_default_branding_restored'
+  | 'mail_template_modified'
+  | 'mail_template_reset'
   | 'groups_bulk_assigned'
   | 'group_added'
   | 'group_modified'
   | 'group_removed'
   | 'group_member_added'
   | 'group_member_removed'
+  | 'group_membership_expired'
   | 'group_members_modified'
+  | 'group_quota_modified'
+  | 'group_sync_authority_modified'
+  | 'group_parent_modified'
+  | 'group_join_request_approved'
+  | 'group_join_request_rejected'
   | 'web_hook_added'
   | 'web_hook_modified'
   | 'web_hook_removed'
@@ -79,10 +133,15 @@ export type ActivityLogEventType =
   | 'password_changed'
   | 'password_changed_by_admin'
   | 'password_reset'
+  | 'user_session_revoked'
+  | 'user_sessions_revoked'
+  | 'impersonation_started'
+  | 'impersonation_ended'
   | 'client_configuration_token_added'
   | 'user_snat_binding_added'
   | 'user_snat_binding_modified'
-  | 'user_snat_binding_removed';
+  | 'user_snat_binding_removed'
+  | 'custom_event';
 
 export const activityLogEventTypeValues: ActivityLogEventType[] = [
   'user_login',
@@ -90,10 +149,21 @@ export const activityLogEventTypeValues: ActivityLogEventType[] = [
   'user_mfa_login',
   'user_mfa_login_failed',
   'user_groups_modified',
+  'user_provisioned',
+  'account_creation_refused',
   'recovery_code_used',
+  'recovery_codes_regenerated',
   'user_logout',
+  'break_glass_login',
+  'admin_access_denied',
+  'user_locked_out',
+  'login_ip_locked_out',
+  'user_unlocked',
+  'login_ip_unlocked',
+  'rate_limit_exceeded',
   'user_added',
   'user_modified',
+  'user_disabled',
   'user_removed',
   'mfa_disabled',
   'user_mfa_disabled',
@@ -101,32 +171,54 @@ export const activityLogEventTypeValues: ActivityLogEventType[] = [
   'mfa_totp_disabled',
   'mfa_email_enabled',
   'mfa_email_disabled',
+  'mfa_sms_enabled',
+  'mfa_sms_disabled',
   'mfa_security_key_added',
   'mfa_security_key_removed',
   'device_added',
   'device_modified',
+  'device_approved',
+  'device_rejected',
   'device_removed',
   'network_device_added',
   'network_device_modified',
+  'device_address_reserved',
+  'device_address_released',
+  'device_profile_added',
+  'device_profile_modified',
+  'device_profile_removed',
+  'device_profile_assigned',
   'network_device_removed',
   'activity_log_stream_created',
   'activity_log_stream_modified',
   'activity_log_stream_removed',
+  'activity_log_purged',
   'vpn_client_connected',
   'vpn_client_disconnected',
   'vpn_client_connected_mfa',
   'vpn_client_disconnected_mfa',
   'vpn_client_mfa_failed',
+  'vpn_client_mfa_locked_out',
+  'vpn_client_mfa_posture_failed',
+  'vpn_client_mfa_timeout',
+  'vpn_client_session_limit_exceeded',
   'enrollment_token_added',
+  'enrollment_token_revoked',
   'enrollment_started',
   'enrollment_device_added',
   'enrollment_completed',
+  'enrollment_blocked',
   'password_reset_requested',
   'password_reset_started',
   'password_reset_completed',
+  'password_reset_blocked',
   'vpn_location_added',
   'vpn_location_removed',
   'vpn_location_modified',
+  'vpn_location_maintenance_enabled',
+  'vpn_location_maintenance_disabled',
+  'gateway_token_added',
+  'gateway_token_revoked',
   'api_token_added',
   'api_token_removed',
   'api_token_renamed',
@@ -134,18 +226,31 @@ export const activityLogEventTypeValues: ActivityLogEventType[] = [
   'open_id_app_removed',
   'open_id_app_modified',
   'open_id_app_state_changed',
+  'client_credentials_set',
+  'client_credentials_removed',
   'open_id_provider_removed',
   'open_id_provider_modified',
+  'saml_provider_modified',
+  'saml_provider_removed',
+  'provisioning_policy_modified',
   'settings_updated',
   'settings_updated_partial',
   'settings_default_branding_restored',
+  'mail_template_modified',
+  'mail_template_reset',
   'groups_bulk_assigned',
   'group_added',
   'group_modified',
   'group_removed',
   'group_member_added',
   'group_member_removed',
+  'group_membership_expired',
   'group_members_modified',
+  'group_quota_modified',
+  'group_sync_authority_modified',
+  'group_parent_modified',
+  'group_join_request_approved',
+  'group_join_request_rejected',
   'web_hook_added',
   'web_hook_modified',
   'web_hook_removed',
@@ -156,8 +261,13 @@ export const activityLogEventTypeValues: ActivityLogEventType[] = [
   'password_changed',
   'password_changed_by_admin',
   'password_reset',
+  'user_session_revoked',
+  'user_sessions_revoked',
+  'impersonation_started',
+  'impersonation_ended',
   'client_configuration_token_added',
   'user_snat_binding_added',
   'user_snat_binding_modified',
   'user_snat_binding_removed',
+  'custom_event',
 ];
@@ -45,8 +45,8 @@ export const AuthPage = () => {
 
   const setAuthStore = useAuthStore((state) => state.setState);
 
-  const [openIdParams, user] = useAuthStore(
-    (state) => [state.openIdParams, state.user],
+  const [openIdParams, emailVerificationToken, user] = useAuthStore(
+    (state) => [state.openIdParams, state.emailVerificationToken, state.user],
     shallow,
   );
 
@@ -113,6 +113,9 @@ export const AuthPage = () => {
           case UserMFAMethod.EMAIL:
             mfaUrl = '/auth/mfa/email';
             break;
+          case UserMFAMethod.SMS:
+            mfaUrl = '/auth/mfa/sms';
+            break;
           default:
             toaster.error(LL.messages.error());
             console.error('API did not return any MFA method in MFA flow.');
@@ -123,6 +126,15 @@ export const AuthPage = () => {
       }
 
       // authorization finished
+      if (user && emailVerificationToken) {
+        setAuthStore({ user });
+        resetMFAStore();
+        navigate(`/verify-email?token=${encodeURIComponent(emailVerificationToken)}`, {
+          replace: true,
+        });
+        return;
+      }
+
       if (user) {
         let navigateURL = '/me';
         if (user.is_admin) {
@@ -150,7 +162,7 @@ export const AuthPage = () => {
     });
     return () => sub?.unsubscribe();
     // eslint-disable-next-line react-hooks/exhaustive-deps
-  }, [loginSubject, openIdParams, redirectUrl]);
+  }, [loginSubject, openIdParams, emailVerificationToken, redirectUrl]);
 
   if (showRedirect) return <RedirectPage />;
 
@@ -1,5 +1,6 @@
 import { zodResolver } from '@hookform/resolvers/zod';
-import { useMutation, useQuery, useQueryClient } from '@tanstack/react-query';
+import { useMutation, useQuery } from '@tanstack/react-query';
+import type { AxiosError } from 'axios';
 import { useEffect, useMemo, useState } from 'react';
 import { type SubmitHandler, useForm } from 'react-hook-form';
 import { useNavigate } from 'react-router';
@@ -14,6 +15,7 @@ import {
 } from '../../../../shared/defguard-ui/components/Layout/Button/types';
 import { useAuthStore } from '../../../../shared/hooks/store/useAuthStore';
 import useApi from '../../../../shared/hooks/useApi';
+import { useToaster } from '../../../../shared/hooks/useToaster';
 import { patternNumbersOnly } from '../../../../shared/patterns';
 import { trimObjectStrings } from '../../../../shared/utils/trimObjectStrings';
 import { useMFAStore } from '../../shared/hooks/useMFAStore';
@@ -31,7 +33,7 @@ const defaultValues: FormFields = {
 export const MFAEmail = () => {
   const { LL } = useI18nContext();
   const localLL = LL.loginPage.mfa.email;
-  const queryClient = useQueryClient();
+  const toaster = useToaster();
   const [resendEnabled, setResendEnabled] = useState<boolean>(true);
   const navigate = useNavigate();
   const loginSubject = useAuthStore((state) => state.loginSubject);
@@ -40,7 +42,7 @@ export const MFAEmail = () => {
   const {
     auth: {
       mfa: {
-        email: { verify, sendCode },
+        email: { verify, sendCode, resendCode },
       },
     },
   } = useApi();
@@ -90,6 +92,18 @@ export const MFAEmail = () => {
     },
   });
 
+  const { mutate: resendMutate, isPending: resendLoading } = useMutation({
+    mutationFn: resendCode,
+    onError: (e: AxiosError) => {
+      if (e.response?.status === 429) {
+        toaster.error(localLL.messages.resendTooSoon());
+      } else {
+        toaster.error(LL.messages.error());
+      }
+      console.error(e);
+    },
+  });
+
   const handleValidSubmit: SubmitHandler<FormFields> = (data) => {
     const trimmed = trimObjectStrings(data);
     verifyMutate({
@@ -124,16 +138,14 @@ export const MFAEmail = () => {
           size={ButtonSize.LARGE}
           styleVariant={ButtonStyleVariant.LINK}
           text={localLL.form.controls.resendCode()}
-          loading={codeLoading}
+          loading={codeLoading || resendLoading}
           disabled={verifyLoading || !resendEnabled}
           onClick={() => {
-            void queryClient.invalidateQueries({
-              queryKey: [queryKey],
-            });
+            resendMutate();
             setResendEnabled(false);
             setTimeout(() => {
               setResendEnabled(true);
-            }, 10000);
+            }, 30000);
           }}
         />
       </form>
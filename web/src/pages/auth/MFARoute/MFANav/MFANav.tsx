@@ -18,12 +18,18 @@ export const MFANav = () => {
   const totpRoute = useMatch('/auth/mfa/totp');
   const webAuthNRoute = useMatch('/auth/mfa/webauthn');
   const emailRoute = useMatch('/auth/mfa/email');
+  const smsRoute = useMatch('/auth/mfa/sms');
   const recoveryRoute = useMatch('/auth/mfa/recovery');
 
   const navigate = useNavigate();
 
-  const [emailAvailable, totpAvailable, webauthnAvailable] = useMFAStore(
-    (state) => [state.email_available, state.totp_available, state.webauthn_available],
+  const [emailAvailable, smsAvailable, totpAvailable, webauthnAvailable] = useMFAStore(
+    (state) => [
+      state.email_available,
+      state.sms_available,
+      state.totp_available,
+      state.webauthn_available,
+    ],
     shallow,
   );
 
@@ -32,6 +38,9 @@ export const MFANav = () => {
     if (emailAvailable && !emailRoute) {
       res.push(UserMFAMethod.EMAIL);
     }
+    if (smsAvailable && !smsRoute) {
+      res.push(UserMFAMethod.SMS);
+    }
     if (totpAvailable && !totpRoute) {
       res.push(UserMFAMethod.ONE_TIME_PASSWORD);
     }
@@ -42,8 +51,10 @@ export const MFANav = () => {
   }, [
     totpRoute,
     emailRoute,
+    smsRoute,
     totpAvailable,
     emailAvailable,
+    smsAvailable,
     webauthnAvailable,
     webAuthNRoute,
   ]);
@@ -57,6 +68,13 @@ export const MFANav = () => {
         link: '/auth/mfa/email',
         type: UserMFAMethod.EMAIL,
       },
+      {
+        key: 3,
+        // biome-ignore lint/correctness/useHookAtTopLevel: below
+        text: localLL.controls.useSms(),
+        link: '/auth/mfa/sms',
+        type: UserMFAMethod.SMS,
+      },
       {
         key: 1,
         // biome-ignore lint/correctness/useHookAtTopLevel: below
@@ -22,8 +22,13 @@ import { useMFAStore } from '../../shared/hooks/useMFAStore';
 
 export const MFARecovery = () => {
   const navigate = useNavigate();
-  const [totpAvailable, webauthnAvailable, emailAvailable] = useMFAStore(
-    (state) => [state.totp_available, state.webauthn_available, state.email_available],
+  const [totpAvailable, webauthnAvailable, emailAvailable, smsAvailable] = useMFAStore(
+    (state) => [
+      state.totp_available,
+      state.webauthn_available,
+      state.email_available,
+      state.sms_available,
+    ],
     shallow,
   );
   const loginSubject = useAuthStore((state) => state.loginSubject);
@@ -71,7 +76,7 @@ export const MFARecovery = () => {
 
   // biome-ignore lint/correctness/useExhaustiveDependencies: migration, checkMeLater
   useEffect(() => {
-    if (!totpAvailable && !webauthnAvailable && !emailAvailable) {
+    if (!totpAvailable && !webauthnAvailable && !emailAvailable && !smsAvailable) {
       navigate('../');
     }
     // eslint-disable-next-line react-hooks/exhaustive-deps
@@ -9,6 +9,7 @@ import { useMFAStore } from '../shared/hooks/useMFAStore';
 import { MFAEmail } from './MFAEmail/MFAEmail';
 import { MFANav } from './MFANav/MFANav';
 import { MFARecovery } from './MFARecovery/MFARecovery';
+import { MFASms } from './MFASms/MFASms';
 import { MFATOTPAuth } from './MFATOTPAuth/MFATOTPAuth';
 import { MFAWebAuthN } from './MFAWebAuthN/MFAWebAuthN';
 
@@ -22,6 +23,7 @@ export const MFARoute = () => {
         <Route path="totp" element={<MFATOTPAuth />} />
         <Route path="webauthn" element={<MFAWebAuthN />} />
         <Route path="email" element={<MFAEmail />} />
+        <Route path="sms" element={<MFASms />} />
         <Route path="recovery" element={<MFARecovery />} />
         <Route path="/*" element={<RedirectToDefaultMFA />} />
       </Routes>
@@ -45,6 +47,9 @@ const RedirectToDefaultMFA = () => {
       case UserMFAMethod.EMAIL:
         navigate('/auth/mfa/email', { replace: true });
         break;
+      case UserMFAMethod.SMS:
+        navigate('/auth/mfa/sms', { replace: true });
+        break;
       default:
         navigate('/auth/login', { replace: true });
         break;
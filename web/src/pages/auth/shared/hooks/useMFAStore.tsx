@@ -13,6 +13,7 @@ const defaultState: MFALoginResponse = {
   totp_available: false,
   webauthn_available: false,
   email_available: false,
+  sms_available: false,
 };
 
 export const useMFAStore = createWithEqualityFn<
@@ -28,7 +29,7 @@ export const useMFAStore = createWithEqualityFn<
     {
       name: 'mfa-storage',
       storage: createJSONStorage(() => sessionStorage),
-      version: 2,
+      version: 3,
     },
   ),
   Object.is,
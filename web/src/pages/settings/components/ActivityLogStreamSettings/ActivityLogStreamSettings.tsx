@@ -25,11 +25,21 @@ import { isPresent } from '../../../../shared/defguard-ui/utils/isPresent';
 import useApi from '../../../../shared/hooks/useApi';
 import { useToaster } from '../../../../shared/hooks/useToaster';
 import queryClient from '../../../../shared/query-client';
-import type { ActivityLogStream } from '../../../../shared/types';
+import type {
+  ActivityLogStream,
+  ActivityLogStreamKafka,
+  ActivityLogStreamLogstashHttp,
+  ActivityLogStreamSyslog,
+  ActivityLogStreamVectorHttp,
+} from '../../../../shared/types';
 import { CreateActivityLogStreamModal } from './modals/CreateActivityLogStreamModal/CreateActivityLogStreamModal';
 import { useCreateActivityLogStreamModalStore } from './modals/CreateActivityLogStreamModal/store';
+import { KafkaStreamCEModal } from './modals/KafkaStreamCEModal/KafkaStreamCEModal';
+import { useKafkaStreamCEModal } from './modals/KafkaStreamCEModal/store';
 import { LogStashHttpStreamCEModal } from './modals/LogStashHttpStreamCEModal/LogStashHttpStreamCEModal';
 import { useLogstashHttpStreamCEModalStore } from './modals/LogStashHttpStreamCEModal/store';
+import { useSyslogStreamCEModal } from './modals/SyslogStreamCEModal/store';
+import { SyslogStreamCEModal } from './modals/SyslogStreamCEModal/SyslogStreamCEModal';
 import { useVectorHttpStreamCEModal } from './modals/VectorHttpStreamCEModal/store';
 import { VectorHttpStreamCEModal } from './modals/VectorHttpStreamCEModal/VectorHttpStreamCEModal';
 import {
@@ -52,6 +62,8 @@ export const ActivityLogStreamSettings = () => {
       <CreateActivityLogStreamModal />
       <VectorHttpStreamCEModal />
       <LogStashHttpStreamCEModal />
+      <SyslogStreamCEModal />
+      <KafkaStreamCEModal />
     </>
   );
 };
@@ -181,6 +193,8 @@ type EditProps = {
 
 const EditListItem = ({ stream }: EditProps) => {
   const openVectorHttpStreamModal = useVectorHttpStreamCEModal((s) => s.open, shallow);
+  const openSyslogStreamModal = useSyslogStreamCEModal((s) => s.open, shallow);
+  const openKafkaStreamModal = useKafkaStreamCEModal((s) => s.open, shallow);
   const openLogstashHttpStreamModal = useLogstashHttpStreamCEModalStore(
     (s) => s.open,
     shallow,
@@ -214,10 +228,28 @@ const EditListItem = ({ stream }: EditProps) => {
   const handleEdit = () => {
     switch (stream.stream_type) {
       case 'logstash_http':
-        openLogstashHttpStreamModal(stream);
+        openLogstashHttpStreamModal({
+          ...stream,
+          config: stream.config as ActivityLogStreamLogstashHttp,
+        });
         break;
       case 'vector_http':
-        openVectorHttpStreamModal(stream);
+        openVectorHttpStreamModal({
+          ...stream,
+          config: stream.config as ActivityLogStreamVectorHttp,
+        });
+        break;
+      case 'syslog':
+        openSyslogStreamModal({
+          ...stream,
+          config: stream.config as ActivityLogStreamSyslog,
+        });
+        break;
+      case 'kafka':
+        openKafkaStreamModal({
+          ...stream,
+          config: stream.config as ActivityLogStreamKafka,
+        });
         break;
       default:
         toast.error('Unimplemented');
@@ -11,7 +11,9 @@ import { ModalWithTitle } from '../../../../../../shared/defguard-ui/components/
 import { RadioButton } from '../../../../../../shared/defguard-ui/components/Layout/RadioButton/Radiobutton';
 import type { ActivityLogStreamType } from '../../../../../../shared/types';
 import { activityLogStreamTypeToLabel } from '../../utils/activityLogStreamToLabel';
+import { useKafkaStreamCEModal } from '../KafkaStreamCEModal/store';
 import { useLogstashHttpStreamCEModalStore } from '../LogStashHttpStreamCEModal/store';
+import { useSyslogStreamCEModal } from '../SyslogStreamCEModal/store';
 import { useVectorHttpStreamCEModal } from '../VectorHttpStreamCEModal/store';
 import { useCreateActivityLogStreamModalStore } from './store';
 
@@ -41,7 +43,12 @@ export const CreateActivityLogStreamModal = () => {
   );
 };
 
-const availableTypes: ActivityLogStreamType[] = ['vector_http', 'logstash_http'];
+const availableTypes: ActivityLogStreamType[] = [
+  'vector_http',
+  'logstash_http',
+  'syslog',
+  'kafka',
+];
 
 const ModalContent = () => {
   const { LL } = useI18nContext();
@@ -49,6 +56,8 @@ const ModalContent = () => {
   const closeModal = useCreateActivityLogStreamModalStore((s) => s.close, shallow);
   const openCreateLogstash = useLogstashHttpStreamCEModalStore((s) => s.open, shallow);
   const openCreateVector = useVectorHttpStreamCEModal((s) => s.open, shallow);
+  const openCreateSyslog = useSyslogStreamCEModal((s) => s.open, shallow);
+  const openCreateKafka = useKafkaStreamCEModal((s) => s.open, shallow);
 
   const [currentStreamType, setStreamType] =
     useState<ActivityLogStreamType>('vector_http');
@@ -92,6 +101,12 @@ const ModalContent = () => {
               case 'logstash_http':
                 openCreateLogstash();
                 break;
+              case 'syslog':
+                openCreateSyslog();
+                break;
+              case 'kafka':
+                openCreateKafka();
+                break;
             }
             closeModal();
           }}
@@ -0,0 +1,319 @@
+import { zodResolver } from '@hookform/resolvers/zod';
+import { useMutation } from '@tanstack/react-query';
+import type { AxiosError } from 'axios';
+import { useCallback, useMemo } from 'react';
+import { type SubmitHandler, useForm } from 'react-hook-form';
+import { z } from 'zod';
+import { shallow } from 'zustand/shallow';
+
+import { useI18nContext } from '../../../../../../i18n/i18n-react';
+import { FormCheckBox } from '../../../../../../shared/defguard-ui/components/Form/FormCheckBox/FormCheckBox';
+import { FormInput } from '../../../../../../shared/defguard-ui/components/Form/FormInput/FormInput';
+import { FormSelect } from '../../../../../../shared/defguard-ui/components/Form/FormSelect/FormSelect';
+import { Button } from '../../../../../../shared/defguard-ui/components/Layout/Button/Button';
+import { ButtonStyleVariant } from '../../../../../../shared/defguard-ui/components/Layout/Button/types';
+import { ModalWithTitle } from '../../../../../../shared/defguard-ui/components/Layout/modals/ModalWithTitle/ModalWithTitle';
+import {
+  type SelectOption,
+  SelectSizeVariant,
+} from '../../../../../../shared/defguard-ui/components/Layout/Select/types';
+import { isPresent } from '../../../../../../shared/defguard-ui/utils/isPresent';
+import useApi from '../../../../../../shared/hooks/useApi';
+import { useToaster } from '../../../../../../shared/hooks/useToaster';
+import queryClient from '../../../../../../shared/query-client';
+import type {
+  ActivityLogStreamKafka,
+  KafkaPayloadFormat,
+  KafkaSaslMechanism,
+} from '../../../../../../shared/types';
+import { activityLogStreamTypeToLabel } from '../../utils/activityLogStreamToLabel';
+import { useKafkaStreamCEModal } from './store';
+
+const formatOptions: SelectOption<KafkaPayloadFormat>[] = [
+  {
+    key: 'json',
+    value: 'json',
+    label: 'JSON',
+  },
+  {
+    key: 'avro',
+    value: 'avro',
+    label: 'Avro',
+  },
+];
+
+const saslMechanisms: KafkaSaslMechanism[] = ['plain', 'scram-sha-256', 'scram-sha-512'];
+
+export const KafkaStreamCEModal = () => {
+  const { LL } = useI18nContext();
+  const localLL = LL.settingsPage.activityLogStreamSettings.modals.kafka;
+  const isOpen = useKafkaStreamCEModal((s) => s.visible);
+  const [close, reset] = useKafkaStreamCEModal((s) => [s.close, s.reset], shallow);
+  const isEdit = useKafkaStreamCEModal((s) => isPresent(s.initStreamData));
+
+  const title = isEdit ? localLL.modify() : localLL.create();
+
+  return (
+    <ModalWithTitle
+      title={title}
+      isOpen={isOpen}
+      onClose={() => {
+        close();
+      }}
+      afterClose={() => {
+        reset();
+      }}
+    >
+      <ModalContent />
+    </ModalWithTitle>
+  );
+};
+
+const ModalContent = () => {
+  const closeModal = useKafkaStreamCEModal((s) => s.close, shallow);
+  const [isEdit, initialData] = useKafkaStreamCEModal((s) => [s.edit, s.initStreamData]);
+
+  const { LL } = useI18nContext();
+  const localLL = LL.settingsPage.activityLogStreamSettings;
+  const formLabels = LL.settingsPage.activityLogStreamSettings.modals.shared.formLabels;
+  const toaster = useToaster();
+
+  const {
+    activityLogStream: { createActivityLogStream, modifyActivityLogStream },
+  } = useApi();
+
+  const saslOptions = useMemo(
+    (): SelectOption<string>[] => [
+      {
+        key: 'none',
+        value: '',
+        label: formLabels.saslNone(),
+      },
+      ...saslMechanisms.map((mechanism) => ({
+        key: mechanism,
+        value: mechanism,
+        label: mechanism.toUpperCase(),
+      })),
+    ],
+    [formLabels],
+  );
+
+  const schema = useMemo(
+    () =>
+      z
+        .object({
+          name: z.string().trim().min(1, LL.form.error.required()),
+          brokers: z.string().trim().min(1, LL.form.error.required()),
+          topic: z.string().trim().min(1, LL.form.error.required()),
+          format: z.enum(['json', 'avro']),
+          sasl_mechanism: z.string(),
+          username: z.string().trim(),
+          password: z.string().trim(),
+          tls: z.boolean(),
+          cert: z.string().trim(),
+        })
+        .superRefine((values, ctx) => {
+          if (values.sasl_mechanism.length && !values.username.length) {
+            ctx.addIssue({
+              code: 'custom',
+              path: ['username'],
+              message: LL.form.error.required(),
+            });
+          }
+        }),
+    [LL.form.error],
+  );
+
+  type FormFields = z.infer<typeof schema>;
+
+  const defaultValues = useMemo((): FormFields => {
+    if (isEdit && isPresent(initialData)) {
+      return {
+        name: initialData.name ?? '',
+        brokers: initialData.config.brokers.join(', '),
+        topic: initialData.config.topic,
+        format: initialData.config.format ?? 'json',
+        sasl_mechanism: initialData.config.sasl?.mechanism ?? '',
+        username: initialData.config.sasl?.username ?? '',
+        password: initialData.config.sasl?.password ?? '',
+        tls: initialData.config.tls ?? false,
+        cert: initialData.config.cert ?? '',
+      };
+    }
+    return {
+      name: '',
+      brokers: '',
+      topic: '',
+      format: 'json',
+      sasl_mechanism: '',
+      username: '',
+      password: '',
+      tls: false,
+      cert: '',
+    };
+  }, [initialData, isEdit]);
+
+  const { handleSubmit, control, resetField, watch } = useForm({
+    defaultValues,
+    resolver: zodResolver(schema),
+    mode: 'all',
+  });
+
+  const [saslMechanism, tls] = watch(['sasl_mechanism', 'tls']);
+
+  const handleSuccess = useCallback(() => {
+    closeModal();
+    void queryClient.invalidateQueries({
+      queryKey: ['activity_stream'],
+    });
+  }, [closeModal]);
+
+  const handleError = useCallback(
+    (e: AxiosError) => {
+      toaster.error(LL.messages.error());
+      console.error(e);
+    },
+    [LL.messages, toaster],
+  );
+
+  const { mutateAsync: modifyMutation } = useMutation({
+    mutationFn: modifyActivityLogStream,
+    onError: handleError,
+    onSuccess: () => {
+      toaster.success(
+        localLL.messages.destinationCrud.modify({
+          destination: activityLogStreamTypeToLabel('kafka'),
+        }),
+      );
+      handleSuccess();
+    },
+  });
+
+  const { mutateAsync: createMutation } = useMutation({
+    mutationFn: createActivityLogStream,
+    onError: handleError,
+    onSuccess: () => {
+      toaster.success(
+        localLL.messages.destinationCrud.create({
+          destination: activityLogStreamTypeToLabel('kafka'),
+        }),
+      );
+      handleSuccess();
+    },
+  });
+
+  const handleValidSubmit: SubmitHandler<FormFields> = async (values) => {
+    // prepare output
+    const config: ActivityLogStreamKafka = {
+      brokers: values.brokers
+        .split(',')
+        .map((broker) => broker.trim())
+        .filter((broker) => broker.length > 0),
+      topic: values.topic,
+      format: values.format,
+      tls: values.tls,
+    };
+    if (values.sasl_mechanism.length) {
+      config.sasl = {
+        mechanism: values.sasl_mechanism as KafkaSaslMechanism,
+        username: values.username,
+        password: values.password,
+      };
+    }
+    if (values.tls && values.cert.length) {
+      config.cert = values.cert;
+    }
+
+    if (isPresent(initialData)) {
+      await modifyMutation({
+        id: initialData.id,
+        name: values.name,
+        stream_type: 'kafka',
+        stream_config: config,
+      });
+    } else {
+      await createMutation({
+        name: values.name,
+        stream_config: config,
+        stream_type: 'kafka',
+      });
+    }
+  };
+
+  return (
+    <form onSubmit={handleSubmit(handleValidSubmit)}>
+      <FormInput
+        label={formLabels.name()}
+        controller={{ control, name: 'name' }}
+        required
+      />
+      <FormInput
+        label={formLabels.brokers()}
+        controller={{ control, name: 'brokers' }}
+        required
+      />
+      <FormInput
+        label={formLabels.topic()}
+        controller={{ control, name: 'topic' }}
+        required
+      />
+      <FormSelect
+        controller={{ control, name: 'format' }}
+        sizeVariant={SelectSizeVariant.STANDARD}
+        options={formatOptions}
+        label={formLabels.format()}
+      />
+      <FormSelect
+        controller={{ control, name: 'sasl_mechanism' }}
+        sizeVariant={SelectSizeVariant.STANDARD}
+        options={saslOptions}
+        label={formLabels.saslMechanism()}
+      />
+      {saslMechanism.length > 0 && (
+        <>
+          <FormInput
+            controller={{ control, name: 'username' }}
+            label={formLabels.username()}
+            required
+          />
+          <FormInput
+            controller={{ control, name: 'password' }}
+            type="password"
+            label={formLabels.password()}
+          />
+        </>
+      )}
+      <FormCheckBox
+        controller={{ control, name: 'tls' }}
+        label={formLabels.tls()}
+        labelPlacement="right"
+      />
+      {tls && (
+        <FormInput
+          label={formLabels.cert()}
+          controller={{ control, name: 'cert' }}
+          disposable
+          disposeHandler={() => {
+            resetField('cert', { defaultValue: '' });
+          }}
+        />
+      )}
+
+      <div className="controls">
+        <Button
+          text={LL.common.controls.cancel()}
+          className="cancel"
+          onClick={() => {
+            closeModal();
+          }}
+        />
+        <Button
+          styleVariant={ButtonStyleVariant.PRIMARY}
+          text={LL.common.controls.submit()}
+          className="submit"
+          type="submit"
+        />
+      </div>
+    </form>
+  );
+};
@@ -0,0 +1,320 @@
+import { zodResolver } from '@hookform/resolvers/zod';
+import { useMutation } from '@tanstack/react-query';
+import type { AxiosError } from 'axios';
+import { useCallback, useMemo } from 'react';
+import { type SubmitHandler, useForm } from 'react-hook-form';
+import { z } from 'zod';
+import { shallow } from 'zustand/shallow';
+
+import { useI18nContext } from '../../../../../../i18n/i18n-react';
+import { FormInput } from '../../../../../../shared/defguard-ui/components/Form/FormInput/FormInput';
+import { FormSelect } from '../../../../../../shared/defguard-ui/components/Form/FormSelect/FormSelect';
+import { Button } from '../../../../../../shared/defguard-ui/components/Layout/Button/Button';
+import { ButtonStyleVariant } from '../../../../../../shared/defguard-ui/components/Layout/Button/types';
+import { ModalWithTitle } from '../../../../../../shared/defguard-ui/components/Layout/modals/ModalWithTitle/ModalWithTitle';
+import {
+  type SelectOption,
+  SelectSizeVariant,
+} from '../../../../../../shared/defguard-ui/components/Layout/Select/types';
+import { isPresent } from '../../../../../../shared/defguard-ui/utils/isPresent';
+import useApi from '../../../../../../shared/hooks/useApi';
+import { useToaster } from '../../../../../../shared/hooks/useToaster';
+import queryClient from '../../../../../../shared/query-client';
+import type {
+  SyslogFacility,
+  SyslogProtocol,
+  SyslogSeverity,
+} from '../../../../../../shared/types';
+import { removeEmptyStrings } from '../../../../../../shared/utils/removeEmptyStrings';
+import { trimObjectStrings } from '../../../../../../shared/utils/trimObjectStrings';
+import { activityLogStreamTypeToLabel } from '../../utils/activityLogStreamToLabel';
+import { useSyslogStreamCEModal } from './store';
+
+const protocols: SyslogProtocol[] = ['udp', 'tcp', 'tls'];
+
+const facilities: SyslogFacility[] = [
+  'kern',
+  'user',
+  'mail',
+  'daemon',
+  'auth',
+  'syslog',
+  'lpr',
+  'news',
+  'uucp',
+  'cron',
+  'authpriv',
+  'ftp',
+  'ntp',
+  'audit',
+  'alert',
+  'clock',
+  'local0',
+  'local1',
+  'local2',
+  'local3',
+  'local4',
+  'local5',
+  'local6',
+  'local7',
+];
+
+const severities: SyslogSeverity[] = [
+  'emergency',
+  'alert',
+  'critical',
+  'error',
+  'warning',
+  'notice',
+  'informational',
+  'debug',
+];
+
+const toOptions = <T extends string>(values: T[]): SelectOption<T>[] =>
+  values.map((value) => ({
+    key: value,
+    value,
+    label: value,
+  }));
+
+const protocolOptions = toOptions(protocols);
+const facilityOptions = toOptions(facilities);
+const severityOptions = toOptions(severities);
+
+export const SyslogStreamCEModal = () => {
+  const { LL } = useI18nContext();
+  const localLL = LL.settingsPage.activityLogStreamSettings.modals.syslog;
+  const isOpen = useSyslogStreamCEModal((s) => s.visible);
+  const [close, reset] = useSyslogStreamCEModal((s) => [s.close, s.reset], shallow);
+  const isEdit = useSyslogStreamCEModal((s) => isPresent(s.initStreamData));
+
+  const title = isEdit ? localLL.modify() : localLL.create();
+
+  return (
+    <ModalWithTitle
+      title={title}
+      isOpen={isOpen}
+      onClose={() => {
+        close();
+      }}
+      afterClose={() => {
+        reset();
+      }}
+    >
+      <ModalContent />
+    </ModalWithTitle>
+  );
+};
+
+const ModalContent = () => {
+  const closeModal = useSyslogStreamCEModal((s) => s.close, shallow);
+  const [isEdit, initialData] = useSyslogStreamCEModal((s) => [s.edit, s.initStreamData]);
+
+  const { LL } = useI18nContext();
+  const localLL = LL.settingsPage.activityLogStreamSettings;
+  const formLabels = LL.settingsPage.activityLogStreamSettings.modals.shared.formLabels;
+  const toaster = useToaster();
+
+  const {
+    activityLogStream: { createActivityLogStream, modifyActivityLogStream },
+  } = useApi();
+
+  const schema = useMemo(
+    () =>
+      z.object({
+        name: z.string().trim().min(1, LL.form.error.required()),
+        host: z.string().trim().min(1, LL.form.error.required()),
+        port: z
+          .number({
+            invalid_type_error: LL.form.error.validPort(),
+          })
+          .int(LL.form.error.validPort())
+          .min(1, LL.form.error.validPort())
+          .max(65535, LL.form.error.portMax()),
+        protocol: z.enum(['udp', 'tcp', 'tls']),
+        facility: z.string().min(1, LL.form.error.required()),
+        severity: z.string().min(1, LL.form.error.required()),
+        hostname: z.string().trim(),
+        cert: z.string().trim(),
+      }),
+    [LL.form.error],
+  );
+
+  type FormFields = z.infer<typeof schema>;
+
+  const defaultValues = useMemo((): FormFields => {
+    if (isEdit && isPresent(initialData)) {
+      return {
+        name: initialData.name ?? '',
+        host: initialData.config.host,
+        port: initialData.config.port,
+        protocol: initialData.config.protocol ?? 'udp',
+        facility: initialData.config.facility ?? 'local0',
+        severity: initialData.config.severity ?? 'informational',
+        hostname: initialData.config.hostname ?? '',
+        cert: initialData.config.cert ?? '',
+      };
+    }
+    return {
+      name: '',
+      host: '',
+      port: 514,
+      protocol: 'udp',
+      facility: 'local0',
+      severity: 'informational',
+      hostname: '',
+      cert: '',
+    };
+  }, [initialData, isEdit]);
+
+  const { handleSubmit, control, resetField, watch } = useForm({
+    defaultValues,
+    resolver: zodResolver(schema),
+    mode: 'all',
+  });
+
+  const protocol = watch('protocol');
+
+  const handleSuccess = useCallback(() => {
+    closeModal();
+    void queryClient.invalidateQueries({
+      queryKey: ['activity_stream'],
+    });
+  }, [closeModal]);
+
+  const handleError = useCallback(
+    (e: AxiosError) => {
+      toaster.error(LL.messages.error());
+      console.error(e);
+    },
+    [LL.messages, toaster],
+  );
+
+  const { mutateAsync: modifyMutation } = useMutation({
+    mutationFn: modifyActivityLogStream,
+    onError: handleError,
+    onSuccess: () => {
+      toaster.success(
+        localLL.messages.destinationCrud.modify({
+          destination: activityLogStreamTypeToLabel('syslog'),
+        }),
+      );
+      handleSuccess();
+    },
+  });
+
+  const { mutateAsync: createMutation } = useMutation({
+    mutationFn: createActivityLogStream,
+    onError: handleError,
+    onSuccess: () => {
+      toaster.success(
+        localLL.messages.destinationCrud.create({
+          destination: activityLogStreamTypeToLabel('syslog'),
+        }),
+      );
+      handleSuccess();
+    },
+  });
+
+  const handleValidSubmit: SubmitHandler<FormFields> = async (values) => {
+    // prepare output
+    const { name, facility, severity, ...rest } = removeEmptyStrings(
+      trimObjectStrings(values),
+    );
+    const config = {
+      ...rest,
+      facility: facility as SyslogFacility,
+      severity: severity as SyslogSeverity,
+      // per event type severity mapping can only be managed through the API, keep it intact
+      severity_mapping: initialData?.config.severity_mapping,
+    };
+
+    if (isPresent(initialData)) {
+      await modifyMutation({
+        id: initialData.id,
+        name,
+        stream_type: 'syslog',
+        stream_config: config,
+      });
+    } else {
+      await createMutation({
+        name,
+        stream_config: config,
+        stream_type: 'syslog',
+      });
+    }
+  };
+
+  return (
+    <form onSubmit={handleSubmit(handleValidSubmit)}>
+      <FormInput
+        label={formLabels.name()}
+        controller={{ control, name: 'name' }}
+        required
+      />
+      <FormInput
+        label={formLabels.host()}
+        controller={{ control, name: 'host' }}
+        required
+      />
+      <FormInput
+        label={formLabels.port()}
+        controller={{ control, name: 'port' }}
+        type="number"
+        required
+      />
+      <FormSelect
+        controller={{ control, name: 'protocol' }}
+        sizeVariant={SelectSizeVariant.STANDARD}
+        options={protocolOptions}
+        label={formLabels.protocol()}
+      />
+      <FormSelect
+        controller={{ control, name: 'facility' }}
+        sizeVariant={SelectSizeVariant.STANDARD}
+        options={facilityOptions}
+        label={formLabels.facility()}
+      />
+      <FormSelect
+        controller={{ control, name: 'severity' }}
+        sizeVariant={SelectSizeVariant.STANDARD}
+        options={severityOptions}
+        label={formLabels.severity()}
+      />
+      <FormInput
+        label={formLabels.hostname()}
+        controller={{ control, name: 'hostname' }}
+        disposable
+        disposeHandler={() => {
+          resetField('hostname', { defaultValue: '' });
+        }}
+      />
+      {protocol === 'tls' && (
+        <FormInput
+          label={formLabels.cert()}
+          controller={{ control, name: 'cert' }}
+          disposable
+          disposeHandler={() => {
+            resetField('cert', { defaultValue: '' });
+          }}
+        />
+      )}
+
+      <div className="controls">
+        <Button
+          text={LL.common.controls.cancel()}
+          className="cancel"
+          onClick={() => {
+            closeModal();
+          }}
+        />
+        <Button
+          styleVariant={ButtonStyleVariant.PRIMARY}
+          text={LL.common.controls.submit()}
+          className="submit"
+          type="submit"
+        />
+      </div>
+    </form>
+  );
+};
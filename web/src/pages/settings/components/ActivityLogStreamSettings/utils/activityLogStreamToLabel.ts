@@ -12,6 +12,10 @@ export const activityLogStreamTypeToLabel = (value: ActivityLogStreamType): stri
       return 'Vector';
     case 'logstash_http':
       return 'Logstash';
+    case 'syslog':
+      return 'Syslog';
+    case 'kafka':
+      return 'Kafka';
     default:
       return 'Unknown';
   }
@@ -20,8 +20,17 @@ export const LdapConnectionTest = () => {
 
   const { isPending: isLoading, mutate } = useMutation({
     mutationFn: testLdapSettings,
-    onSuccess: () => {
-      toaster.success(localLL.messages.success());
+    onSuccess: (resolution) => {
+      const unresolved = Object.values(resolution.attributes)
+        .filter((attribute) => !attribute.resolved)
+        .map((attribute) => attribute.name);
+      if (unresolved.length) {
+        toaster.warning(
+          localLL.messages.unresolvedAttributes({ attributes: unresolved.join(', ') }),
+        );
+      } else {
+        toaster.success(localLL.messages.success());
+      }
     },
     onError: () => {
       toaster.error(localLL.messages.error());
@@ -95,6 +95,12 @@ export const LdapSettingsForm = () => {
         ldap_uses_ad: z.boolean(),
         ldap_user_rdn_attr: z.string().trim().optional(),
         ldap_sync_groups: z.string().trim(),
+        ldap_group_filter: z.string().trim(),
+        ldap_group_exclude: z.string().trim(),
+        ldap_email_attr: z.string().trim().min(1, LL.form.error.required()),
+        ldap_first_name_attr: z.string().trim().min(1, LL.form.error.required()),
+        ldap_last_name_attr: z.string().trim().min(1, LL.form.error.required()),
+        ldap_phone_attr: z.string().trim().min(1, LL.form.error.required()),
       }),
     [LL.form.error],
   );
@@ -125,6 +131,12 @@ export const LdapSettingsForm = () => {
       ldap_uses_ad: settings?.ldap_uses_ad ?? false,
       ldap_user_rdn_attr: settings?.ldap_user_rdn_attr ?? '',
       ldap_sync_groups: settings?.ldap_sync_groups.join(', ') ?? '',
+      ldap_group_filter: settings?.ldap_group_filter.join(', ') ?? '',
+      ldap_group_exclude: settings?.ldap_group_exclude.join(', ') ?? '',
+      ldap_email_attr: settings?.ldap_email_attr ?? 'mail',
+      ldap_first_name_attr: settings?.ldap_first_name_attr ?? 'givenName',
+      ldap_last_name_attr: settings?.ldap_last_name_attr ?? 'sn',
+      ldap_phone_attr: settings?.ldap_phone_attr ?? 'mobile',
     }),
     [settings],
   );
@@ -152,6 +164,12 @@ export const LdapSettingsForm = () => {
       ldap_uses_ad: false,
       ldap_user_rdn_attr: '',
       ldap_sync_groups: '',
+      ldap_group_filter: '',
+      ldap_group_exclude: '',
+      ldap_email_attr: 'mail',
+      ldap_first_name_attr: 'givenName',
+      ldap_last_name_attr: 'sn',
+      ldap_phone_attr: 'mobile',
     }),
     [],
   );
@@ -173,6 +191,14 @@ export const LdapSettingsForm = () => {
         .split(',')
         .map((group) => group.trim())
         .filter((group) => group.length > 0),
+      ldap_group_filter: data.ldap_group_filter
+        .split(',')
+        .map((pattern) => pattern.trim())
+        .filter((pattern) => pattern.length > 0),
+      ldap_group_exclude: data.ldap_group_exclude
+        .split(',')
+        .map((pattern) => pattern.trim())
+        .filter((pattern) => pattern.length > 0),
     };
     mutate(formattedData);
   };
@@ -182,6 +208,8 @@ export const LdapSettingsForm = () => {
       ...emptyValues,
       ldap_user_auxiliary_obj_classes: [],
       ldap_sync_groups: [],
+      ldap_group_filter: [],
+      ldap_group_exclude: [],
     });
     reset(emptyValues);
   }, [mutate, emptyValues, reset]);
@@ -288,6 +316,26 @@ export const LdapSettingsForm = () => {
               disabled={!enterpriseEnabled}
               labelExtras={<Helper>{localLL.form.helpers.ldap_user_rdn_attr()}</Helper>}
             />
+            <FormInput
+              controller={{ control, name: 'ldap_email_attr' }}
+              label={localLL.form.labels.ldap_email_attr()}
+              disabled={!enterpriseEnabled}
+            />
+            <FormInput
+              controller={{ control, name: 'ldap_first_name_attr' }}
+              label={localLL.form.labels.ldap_first_name_attr()}
+              disabled={!enterpriseEnabled}
+            />
+            <FormInput
+              controller={{ control, name: 'ldap_last_name_attr' }}
+              label={localLL.form.labels.ldap_last_name_attr()}
+              disabled={!enterpriseEnabled}
+            />
+            <FormInput
+              controller={{ control, name: 'ldap_phone_attr' }}
+              label={localLL.form.labels.ldap_phone_attr()}
+              disabled={!enterpriseEnabled}
+            />
             <FormInput
               controller={{ control, name: 'ldap_user_search_base' }}
               label={localLL.form.labels.ldap_user_search_base()}
@@ -341,6 +389,18 @@ export const LdapSettingsForm = () => {
               label={localLL.form.labels.ldap_group_search_base()}
               disabled={!enterpriseEnabled}
             />
+            <FormInput
+              controller={{ control, name: 'ldap_group_filter' }}
+              label={localLL.form.labels.ldap_group_filter()}
+              disabled={!enterpriseEnabled}
+              labelExtras={<Helper>{localLL.form.helpers.ldap_group_filter()}</Helper>}
+            />
+            <FormInput
+              controller={{ control, name: 'ldap_group_exclude' }}
+              label={localLL.form.labels.ldap_group_exclude()}
+              disabled={!enterpriseEnabled}
+              labelExtras={<Helper>{localLL.form.helpers.ldap_group_exclude()}</Helper>}
+            />
           </div>
           <div>
             <div className="helper-row subsection-header">
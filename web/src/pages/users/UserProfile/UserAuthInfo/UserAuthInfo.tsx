@@ -12,6 +12,7 @@ import { ChangeSelfPasswordModal } from './modals/ChangeSelfPasswordModal/Change
 import { ManageWebAuthNKeysModal } from './modals/ManageWebAuthNModal/ManageWebAuthNModal';
 import { RecoveryCodesModal } from './modals/RecoveryCodesModal/RecoveryCodesModal';
 import { RegisterEmailMFAModal } from './modals/RegisterEmailMFAModal/RegisterEmailMFAModal';
+import { RegisterSmsMFAModal } from './modals/RegisterSmsMFAModal/RegisterSmsMFAModal';
 import { RegisterTOTPModal } from './modals/RegisterTOTPModal/RegisterTOTPModal';
 import { UserAuthInfoMFA } from './UserAuthInfoMFA';
 import { UserAuthInfoPassword } from './UserAuthInfoPassword';
@@ -57,6 +58,7 @@ export const UserAuthInfo = () => {
       <RecoveryCodesModal />
       <ChangeSelfPasswordModal />
       <RegisterEmailMFAModal />
+      <RegisterSmsMFAModal />
     </section>
   );
 };
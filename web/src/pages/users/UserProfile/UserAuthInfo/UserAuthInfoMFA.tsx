@@ -19,6 +19,7 @@ import { MutationKeys } from '../../../../shared/mutations';
 import { QueryKeys } from '../../../../shared/queries';
 import { UserMFAMethod } from '../../../../shared/types';
 import { useEmailMFAModal } from './modals/RegisterEmailMFAModal/hooks/useEmailMFAModal.tsx';
+import { useSmsMFAModal } from './modals/RegisterSmsMFAModal/hooks/useSmsMFAModal.tsx';
 
 export const UserAuthInfoMFA = () => {
   const { LL, locale } = useI18nContext();
@@ -30,6 +31,8 @@ export const UserAuthInfoMFA = () => {
   const setModalsState = useModalStore((store) => store.setState);
   const smtpEnabled = useAppStore((state) => state.appInfo?.smtp_enabled);
   const openEmailMFAModal = useEmailMFAModal((state) => state.open);
+  const smsEnabled = useAppStore((state) => state.appInfo?.sms_enabled);
+  const openSmsMFAModal = useSmsMFAModal((state) => state.open);
   const queryClient = useQueryClient();
 
   const refreshUserQueries = () => {
@@ -44,6 +47,7 @@ export const UserAuthInfoMFA = () => {
       mfa: {
         totp: { disable: disableTOTP },
         email: { disable: disableEmailMFA },
+        sms: { disable: disableSmsMFA },
       },
     },
   } = useApi();
@@ -94,6 +98,19 @@ export const UserAuthInfoMFA = () => {
     },
   });
 
+  const { mutate: disableSmsMFAMutation } = useMutation({
+    mutationKey: [MutationKeys.DISABLE_SMS_MFA],
+    mutationFn: disableSmsMFA,
+    onSuccess: () => {
+      refreshUserQueries();
+      toaster.success(LL.userPage.userAuthInfo.mfa.messages.SmsMFADisabled());
+    },
+    onError: (err) => {
+      toaster.error(LL.messages.error());
+      console.error(err);
+    },
+  });
+
   const { mutate: editUserMutation } = useMutation({
     mutationKey: [MutationKeys.EDIT_USER],
     mutationFn: editUser,
@@ -147,6 +164,20 @@ export const UserAuthInfoMFA = () => {
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [userProfile, locale]);
 
+  // biome-ignore lint/correctness/useExhaustiveDependencies: migration, checkMeLater
+  const getSmsMFAInfoText = useMemo(() => {
+    if (userProfile?.user.sms_mfa_enabled) {
+      const res: string[] = [LL.userPage.userAuthInfo.mfa.enabled()];
+      if (userProfile?.user.mfa_method === UserMFAMethod.SMS) {
+        const defaultStr = `(${LL.userPage.userAuthInfo.mfa.default()})`;
+        res.push(defaultStr);
+      }
+      return res.join(' ');
+    }
+    return LL.userPage.userAuthInfo.mfa.disabled();
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [userProfile, locale]);
+
   // biome-ignore lint/correctness/useExhaustiveDependencies: migration, checkMeLater
   const getWebAuthNInfoText = useMemo(() => {
     if (userProfile) {
@@ -259,6 +290,38 @@ export const UserAuthInfoMFA = () => {
               </div>
             </RowBox>
           )}
+          {smsEnabled && (
+            <RowBox>
+              <p>{LL.userPage.userAuthInfo.mfa.labels.sms()}</p>
+              <div className="right">
+                <span>{getSmsMFAInfoText}</span>
+                <EditButton data-testid="edit-sms-mfa">
+                  {userProfile?.user.sms_mfa_enabled && (
+                    <EditButtonOption
+                      onClick={() => disableSmsMFAMutation()}
+                      text={LL.userPage.userAuthInfo.mfa.editMode.disable()}
+                      styleVariant={EditButtonOptionStyleVariant.WARNING}
+                    />
+                  )}
+                  {!userProfile?.user.sms_mfa_enabled && (
+                    <EditButtonOption
+                      data-testid="enable-sms-mfa-option"
+                      text={LL.userPage.userAuthInfo.mfa.editMode.enable()}
+                      onClick={() => openSmsMFAModal()}
+                    />
+                  )}
+                  <EditButtonOption
+                    disabled={
+                      !userProfile?.user.sms_mfa_enabled ||
+                      userProfile?.user.mfa_method === UserMFAMethod.SMS
+                    }
+                    text={LL.userPage.userAuthInfo.mfa.editMode.makeDefault()}
+                    onClick={() => changeDefaultMFAMethod(UserMFAMethod.SMS)}
+                  />
+                </EditButton>
+              </div>
+            </RowBox>
+          )}
           <RowBox>
             <p>{LL.userPage.userAuthInfo.mfa.labels.webauth()}</p>
             <div className="right">
@@ -296,6 +359,12 @@ export const UserAuthInfoMFA = () => {
               <p className="info">{getEmailMFAInfoText}</p>
             </div>
           )}
+          {smsEnabled && (
+            <div className="row">
+              <p>{LL.userPage.userAuthInfo.mfa.labels.sms()}</p>
+              <p className="info">{getSmsMFAInfoText}</p>
+            </div>
+          )}
           <div className="row">
             <p>{LL.userPage.userAuthInfo.mfa.labels.webauth()}</p>
             <p className="info">{getWebAuthNInfoText}</p>
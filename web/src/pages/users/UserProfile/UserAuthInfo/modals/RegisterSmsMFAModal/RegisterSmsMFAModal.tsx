@@ -0,0 +1,30 @@
+import './style.scss';
+
+import { shallow } from 'zustand/shallow';
+
+import { useI18nContext } from '../../../../../../i18n/i18n-react';
+import { MessageBox } from '../../../../../../shared/defguard-ui/components/Layout/MessageBox/MessageBox';
+import { MessageBoxType } from '../../../../../../shared/defguard-ui/components/Layout/MessageBox/types';
+import { ModalWithTitle } from '../../../../../../shared/defguard-ui/components/Layout/modals/ModalWithTitle/ModalWithTitle';
+import { RegisterMFASmsForm } from './components/RegisterMFASmsForm/RegisterMFASmsForm';
+import { useSmsMFAModal } from './hooks/useSmsMFAModal';
+
+export const RegisterSmsMFAModal = () => {
+  const { LL } = useI18nContext();
+  const localLL = LL.modals.registerSmsMFA;
+  const visible = useSmsMFAModal((state) => state.visible);
+  const [close, reset] = useSmsMFAModal((state) => [state.close, state.reset], shallow);
+
+  return (
+    <ModalWithTitle
+      title={localLL.title()}
+      isOpen={visible}
+      onClose={close}
+      afterClose={reset}
+      id="register-mfa-sms-modal"
+    >
+      <MessageBox type={MessageBoxType.INFO} message={localLL.infoMessage()} />
+      <RegisterMFASmsForm />
+    </ModalWithTitle>
+  );
+};
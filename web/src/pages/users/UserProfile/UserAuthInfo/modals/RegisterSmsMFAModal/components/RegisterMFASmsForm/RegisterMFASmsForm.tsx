@@ -0,0 +1,187 @@
+import './style.scss';
+
+import { zodResolver } from '@hookform/resolvers/zod';
+import { useMutation, useQueryClient } from '@tanstack/react-query';
+import { useMemo, useState } from 'react';
+import { type SubmitHandler, useForm } from 'react-hook-form';
+import { z } from 'zod';
+
+import { useI18nContext } from '../../../../../../../../i18n/i18n-react';
+import { FormInput } from '../../../../../../../../shared/defguard-ui/components/Form/FormInput/FormInput';
+import { Button } from '../../../../../../../../shared/defguard-ui/components/Layout/Button/Button';
+import {
+  ButtonSize,
+  ButtonStyleVariant,
+} from '../../../../../../../../shared/defguard-ui/components/Layout/Button/types';
+import { useModalStore } from '../../../../../../../../shared/hooks/store/useModalStore';
+import { useUserProfileStore } from '../../../../../../../../shared/hooks/store/useUserProfileStore';
+import useApi from '../../../../../../../../shared/hooks/useApi';
+import { useToaster } from '../../../../../../../../shared/hooks/useToaster';
+import {
+  patternNumbersOnly,
+  patternValidPhoneNumber,
+} from '../../../../../../../../shared/patterns';
+import { QueryKeys } from '../../../../../../../../shared/queries';
+import { trimObjectStrings } from '../../../../../../../../shared/utils/trimObjectStrings';
+import { useSmsMFAModal } from '../../hooks/useSmsMFAModal';
+
+type FormFields = {
+  phone: string;
+  code: string;
+};
+
+export const RegisterMFASmsForm = () => {
+  const { LL } = useI18nContext();
+  const [codeSent, setCodeSent] = useState(false);
+  const [disableResend, setDisableResend] = useState(false);
+  const setModalsState = useModalStore((state) => state.setState);
+  const closeModal = useSmsMFAModal((state) => state.close);
+  const userProfile = useUserProfileStore((state) => state.userProfile);
+
+  const {
+    auth: {
+      mfa: {
+        sms: {
+          register: { start, finish },
+        },
+      },
+    },
+  } = useApi();
+
+  const toaster = useToaster();
+  const queryClient = useQueryClient();
+
+  const localLL = LL.modals.registerSmsMFA;
+
+  const schema = useMemo(
+    () =>
+      z.object({
+        phone: z
+          .string()
+          .trim()
+          .min(1, LL.form.error.required())
+          .regex(patternValidPhoneNumber, localLL.form.fields.phone.error()),
+        code: codeSent
+          ? z
+              .string()
+              .trim()
+              .regex(patternNumbersOnly, LL.form.error.invalid())
+              .min(6, LL.form.error.minimumLength())
+              .max(6, LL.form.error.maximumLength())
+          : z.string(),
+      }),
+    [LL.form.error, localLL.form.fields.phone, codeSent],
+  );
+
+  const { handleSubmit, control, setError, getValues } = useForm<FormFields>({
+    defaultValues: {
+      phone: userProfile?.user.phone ?? '',
+      code: '',
+    },
+    resolver: zodResolver(schema),
+  });
+
+  const { mutateAsync: mutateStart, isPending: startLoading } = useMutation({
+    mutationFn: start,
+    onError: () => {
+      toaster.error(LL.messages.error());
+    },
+  });
+
+  const { mutate: mutateFinish, isPending: finishLoading } = useMutation({
+    mutationFn: finish,
+    onSuccess: (res) => {
+      toaster.success(localLL.messages.success());
+      void queryClient.invalidateQueries({
+        queryKey: [QueryKeys.FETCH_USER_PROFILE],
+      });
+      if (res?.codes) {
+        setModalsState({
+          recoveryCodesModal: { visible: true, codes: res.codes },
+        });
+      }
+      closeModal();
+    },
+    onError: () => {
+      setError(
+        'code',
+        {
+          type: 'validate',
+          message: localLL.form.fields.code.error(),
+        },
+        {
+          shouldFocus: true,
+        },
+      );
+    },
+  });
+
+  const handleValidSubmit: SubmitHandler<FormFields> = (data) => {
+    data = trimObjectStrings(data);
+    if (codeSent) {
+      mutateFinish({
+        code: String(data.code),
+      });
+    } else {
+      void mutateStart({ phone: data.phone }).then(() => {
+        setCodeSent(true);
+      });
+    }
+  };
+
+  return (
+    <form id="register-mfa-sms-form" onSubmit={handleSubmit(handleValidSubmit)}>
+      <FormInput
+        type="tel"
+        controller={{ control, name: 'phone' }}
+        label={localLL.form.fields.phone.label()}
+        disabled={codeSent}
+      />
+      {codeSent && (
+        <>
+          <FormInput
+            type="text"
+            inputMode="numeric"
+            controller={{ control, name: 'code' }}
+            label={localLL.form.fields.code.label()}
+          />
+          <div className="form-extras">
+            <Button
+              className="resend"
+              size={ButtonSize.LARGE}
+              styleVariant={ButtonStyleVariant.LINK}
+              loading={startLoading}
+              disabled={disableResend}
+              text={localLL.form.controls.resend()}
+              onClick={() => {
+                void mutateStart({ phone: getValues('phone').trim() }).then(() => {
+                  toaster.success(localLL.messages.resend());
+                });
+                setDisableResend(true);
+                setTimeout(() => {
+                  setDisableResend(false);
+                }, 30000);
+              }}
+            />
+          </div>
+        </>
+      )}
+      <div className="controls">
+        <Button
+          className="cancel"
+          size={ButtonSize.LARGE}
+          text={LL.common.controls.cancel()}
+          onClick={() => closeModal()}
+        />
+        <Button
+          type="submit"
+          size={ButtonSize.LARGE}
+          styleVariant={ButtonStyleVariant.PRIMARY}
+          className="submit"
+          text={codeSent ? localLL.form.controls.submit() : localLL.form.controls.send()}
+          loading={finishLoading || startLoading}
+        />
+      </div>
+    </form>
+  );
+};
@@ -90,7 +90,10 @@ const TOTPRegisterQRCode = () => {
   }, [totpInitError]);
 
   const qrData = useMemo(
-    () => (data ? `otpauth://totp/Defguard?secret=${data.secret}` : undefined),
+    () =>
+      data
+        ? `otpauth://totp/Defguard?secret=${data.secret}&algorithm=${data.algorithm.toUpperCase()}&digits=${data.digits}`
+        : undefined,
     [data],
   );
 
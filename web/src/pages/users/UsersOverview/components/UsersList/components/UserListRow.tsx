@@ -3,6 +3,7 @@ import { useCallback } from 'react';
 import { useNavigate } from 'react-router';
 
 import { CheckBox } from '../../../../../../shared/defguard-ui/components/Layout/Checkbox/CheckBox';
+import { useI18nContext } from '../../../../../../i18n/i18n-react';
 import { UserInitials } from '../../../../../../shared/defguard-ui/components/Layout/UserInitials/UserInitials';
 import { useAuthStore } from '../../../../../../shared/hooks/store/useAuthStore';
 import { useUserProfileStore } from '../../../../../../shared/hooks/store/useUserProfileStore';
@@ -17,6 +18,7 @@ type Props = {
 };
 
 export const UserListRow = ({ user, onSelect, selected = false }: Props) => {
+  const { LL } = useI18nContext();
   const navigate = useNavigate();
   const currentUser = useAuthStore((state) => state.user);
   const resetUserProfile = useUserProfileStore((s) => s.reset);
@@ -51,6 +53,11 @@ export const UserListRow = ({ user, onSelect, selected = false }: Props) => {
       </div>
       <div className="username-cell">
         <span>{user.username}</span>
+        {user.service_account && (
+          <span className="service-account">
+            {LL.usersOverview.list.serviceAccount()}
+          </span>
+        )}
       </div>
       <div className="user-phone-cell">
         <span>{user.phone}</span>
@@ -0,0 +1,63 @@
+import { useMutation, useQueryClient } from '@tanstack/react-query';
+import { useEffect, useRef } from 'react';
+import { useNavigate, useSearchParams } from 'react-router-dom';
+
+import { useI18nContext } from '../../i18n/i18n-react';
+import { useAuthStore } from '../../shared/hooks/store/useAuthStore';
+import useApi from '../../shared/hooks/useApi';
+import { useToaster } from '../../shared/hooks/useToaster';
+import { QueryKeys } from '../../shared/queries';
+import { LoaderPage } from '../loader/LoaderPage';
+
+// opened from the link in email verification message
+export const VerifyEmailPage = () => {
+  const { LL } = useI18nContext();
+  const navigate = useNavigate();
+  const toaster = useToaster();
+  const queryClient = useQueryClient();
+  const [params] = useSearchParams();
+  const user = useAuthStore((state) => state.user);
+  const setAuthStore = useAuthStore((state) => state.setState);
+  const {
+    user: { verifyEmail },
+  } = useApi();
+  const started = useRef(false);
+
+  const { mutate } = useMutation({
+    mutationFn: verifyEmail,
+    onSuccess: () => {
+      void queryClient.invalidateQueries({
+        queryKey: [QueryKeys.FETCH_ME],
+      });
+      toaster.success(LL.verifyEmailPage.messages.success());
+      navigate('/me', { replace: true });
+    },
+    onError: (e) => {
+      toaster.error(LL.verifyEmailPage.messages.error());
+      console.error(e);
+      navigate('/me', { replace: true });
+    },
+  });
+
+  // biome-ignore lint/correctness/useExhaustiveDependencies: verify only once
+  useEffect(() => {
+    if (started.current) return;
+    started.current = true;
+    const token = params.get('token');
+    if (!token) {
+      toaster.error(LL.verifyEmailPage.messages.missingToken());
+      navigate('/', { replace: true });
+      return;
+    }
+    // verification requires a session, continue after logging in
+    if (!user) {
+      setAuthStore({ emailVerificationToken: token });
+      navigate('/auth/login', { replace: true });
+      return;
+    }
+    setAuthStore({ emailVerificationToken: undefined });
+    mutate({ token });
+  }, []);
+
+  return <LoaderPage />;
+};
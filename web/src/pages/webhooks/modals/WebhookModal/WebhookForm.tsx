@@ -1,5 +1,5 @@
 import { zodResolver } from '@hookform/resolvers/zod';
-import { useMutation, useQueryClient } from '@tanstack/react-query';
+import { useMutation, useQuery, useQueryClient } from '@tanstack/react-query';
 import { isUndefined } from 'lodash-es';
 import { useMemo } from 'react';
 import { type SubmitHandler, useForm } from 'react-hook-form';
@@ -8,11 +8,13 @@ import { z } from 'zod';
 import { useI18nContext } from '../../../../i18n/i18n-react';
 import { FormCheckBox } from '../../../../shared/defguard-ui/components/Form/FormCheckBox/FormCheckBox';
 import { FormInput } from '../../../../shared/defguard-ui/components/Form/FormInput/FormInput';
+import { FormSelect } from '../../../../shared/defguard-ui/components/Form/FormSelect/FormSelect';
 import { Button } from '../../../../shared/defguard-ui/components/Layout/Button/Button';
 import {
   ButtonSize,
   ButtonStyleVariant,
 } from '../../../../shared/defguard-ui/components/Layout/Button/types';
+import type { SelectOption } from '../../../../shared/defguard-ui/components/Layout/Select/types';
 import { useModalStore } from '../../../../shared/hooks/store/useModalStore';
 import useApi from '../../../../shared/hooks/useApi';
 import { useToaster } from '../../../../shared/hooks/useToaster';
@@ -24,6 +26,7 @@ export const WebhookForm = () => {
   const toaster = useToaster();
   const {
     webhook: { addWebhook, editWebhook },
+    network: { getNetworks },
   } = useApi();
   const modalState = useModalStore((state) => state.webhookModal);
   const setModalState = useModalStore((state) => state.setWebhookModal);
@@ -31,6 +34,22 @@ export const WebhookForm = () => {
 
   const queryClient = useQueryClient();
 
+  const { data: networks, isLoading: networksLoading } = useQuery({
+    queryKey: [QueryKeys.FETCH_NETWORKS],
+    queryFn: getNetworks,
+    refetchOnWindowFocus: false,
+  });
+
+  const locationOptions = useMemo(
+    (): SelectOption<number>[] =>
+      networks?.map((network) => ({
+        key: network.id,
+        value: network.id,
+        label: network.name,
+      })) ?? [],
+    [networks],
+  );
+
   const zodSchema = useMemo(
     () =>
       z
@@ -51,11 +70,19 @@ export const WebhookForm = () => {
           on_user_deleted: z.boolean(),
           on_user_modified: z.boolean(),
           on_hwkey_provision: z.boolean(),
+          on_gateway_disconnected: z.boolean(),
+          on_client_connected: z.boolean(),
+          on_client_disconnected: z.boolean(),
+          secret: z.string().max(250, LL.form.error.maximumLength()),
+          locations: z.array(z.number()),
         })
         .superRefine((val, ctx) => {
           if (val.enabled) {
             if (
               !val.on_hwkey_provision &&
+              !val.on_gateway_disconnected &&
+              !val.on_client_connected &&
+              !val.on_client_disconnected &&
               !val.on_user_created &&
               !val.on_user_deleted &&
               !val.on_user_modified
@@ -74,7 +101,10 @@ export const WebhookForm = () => {
 
   const defaultFormState = useMemo((): FormFields => {
     if (!isUndefined(modalState.webhook)) {
-      return modalState.webhook;
+      return {
+        ...modalState.webhook,
+        secret: modalState.webhook.secret ?? '',
+      };
     }
     const defaultValues: FormFields = {
       url: '',
@@ -82,6 +112,11 @@ export const WebhookForm = () => {
       token: '',
       enabled: true,
       on_hwkey_provision: false,
+      on_gateway_disconnected: false,
+      on_client_connected: false,
+      on_client_disconnected: false,
+      secret: '',
+      locations: [],
       on_user_created: false,
       on_user_deleted: false,
       on_user_modified: false,
@@ -135,7 +170,8 @@ export const WebhookForm = () => {
     },
   });
 
-  const onValidSubmit: SubmitHandler<FormFields> = (values) => {
+  const onValidSubmit: SubmitHandler<FormFields> = (formValues) => {
+    const values = { ...formValues, secret: formValues.secret.length ? formValues.secret : null };
     if (editMode) {
       if (modalState.webhook) {
         editWebhookMutation({ ...modalState.webhook, ...values });
@@ -171,6 +207,11 @@ export const WebhookForm = () => {
         placeholder={LL.modals.webhookModal.form.fields.token.placeholder()}
         required
       />
+      <FormInput
+        label={LL.modals.webhookModal.form.fields.secret.label()}
+        controller={{ control, name: 'secret' }}
+        placeholder={LL.modals.webhookModal.form.fields.secret.placeholder()}
+      />
       <h3>{LL.modals.webhookModal.form.triggers()}</h3>
       <div className="events">
         <FormCheckBox
@@ -193,7 +234,33 @@ export const WebhookForm = () => {
           label={LL.modals.webhookModal.form.fields.hwkeyProvision.label()}
           labelPlacement="right"
         />
+        <FormCheckBox
+          controller={{ control, name: 'on_gateway_disconnected' }}
+          label={LL.modals.webhookModal.form.fields.gatewayDisconnected.label()}
+          labelPlacement="right"
+        />
+        <FormCheckBox
+          controller={{ control, name: 'on_client_connected' }}
+          label={LL.modals.webhookModal.form.fields.clientConnected.label()}
+          labelPlacement="right"
+        />
+        <FormCheckBox
+          controller={{ control, name: 'on_client_disconnected' }}
+          label={LL.modals.webhookModal.form.fields.clientDisconnected.label()}
+          labelPlacement="right"
+        />
       </div>
+      <FormSelect
+        controller={{ control, name: 'locations' }}
+        label={LL.modals.webhookModal.form.fields.locations.label()}
+        placeholder={LL.modals.webhookModal.form.fields.locations.placeholder()}
+        options={locationOptions}
+        loading={networksLoading}
+        renderSelected={(id) => ({
+          key: id,
+          displayValue: networks?.find((network) => network.id === id)?.name ?? String(id),
+        })}
+      />
       <div className="controls">
         <Button
           styleVariant={ButtonStyleVariant.STANDARD}
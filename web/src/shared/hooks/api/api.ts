@@ -10,11 +10,15 @@ import type {
   ChangeOpenidClientStateRequest,
   ChangePasswordRequest,
   changeWebhookStateRequest,
+  CreateGatewayTokenResponse,
   Device,
   EditOpenidClientRequest,
   EmptyApiResponse,
+  GatewayToken,
   GetNetworkStatsRequest,
   GroupsResponse,
+  ImportUsersResponse,
+  LocationMaintenance,
   LoginData,
   LoginResponse,
   MFALoginResponse,
@@ -49,6 +53,9 @@ export const buildApi = (client: Axios): Api => {
   const addUser = (data: AddUserRequest) =>
     client.post<User>(`/user`, data).then(unpackRequest);
 
+  const importUsers: Api['user']['importUsers'] = (data) =>
+    client.post<ImportUsersResponse>(`/user/import`, data).then(unpackRequest);
+
   const getMe = () => client.get<User>(`/me`).then(unpackRequest);
 
   const getUser: Api['user']['getUser'] = (username) =>
@@ -99,6 +106,17 @@ export const buildApi = (client: Axios): Api => {
   const addNetwork: Api['network']['addNetwork'] = (network) =>
     client.post(`/network`, network).then(unpackRequest);
 
+  const cloneNetwork: Api['network']['cloneNetwork'] = ({ id, ...data }) =>
+    client.post<Network>(`/network/${id}/clone`, data).then(unpackRequest);
+
+  const setNetworkMaintenance: Api['network']['setNetworkMaintenance'] = ({
+    id,
+    ...data
+  }) =>
+    client
+      .put<LocationMaintenance>(`/network/${id}/maintenance`, data)
+      .then(unpackRequest);
+
   const importNetwork: Api['network']['importNetwork'] = (network) =>
     client.post(`/network/import`, network).then(unpackRequest);
 
@@ -143,6 +161,9 @@ export const buildApi = (client: Axios): Api => {
   const resetPassword = ({ username }: ResetPasswordRequest) =>
     client.post<EmptyApiResponse>(`/user/${username}/reset_password`);
 
+  const verifyEmail: Api['user']['verifyEmail'] = (data) =>
+    client.post<EmptyApiResponse>('/user/verify_email', data);
+
   const startEnrollment = ({ username, ...rest }: StartEnrollmentRequest) =>
     client
       .post<StartEnrollmentResponse>(`/user/${username}/start_enrollment`, rest)
@@ -233,6 +254,24 @@ export const buildApi = (client: Axios): Api => {
   const getNetworkToken: Api['network']['getNetworkToken'] = (networkId) =>
     client.get<NetworkToken>(`/network/${networkId}/token`).then(unpackRequest);
 
+  const getGatewayTokens: Api['network']['getGatewayTokens'] = (networkId) =>
+    client
+      .get<GatewayToken[]>(`/network/${networkId}/gateway_token`)
+      .then(unpackRequest);
+
+  const createGatewayToken: Api['network']['createGatewayToken'] = ({
+    networkId,
+    ...data
+  }) =>
+    client
+      .post<CreateGatewayTokenResponse>(`/network/${networkId}/gateway_token`, data)
+      .then(unpackRequest);
+
+  const revokeGatewayToken: Api['network']['revokeGatewayToken'] = ({
+    networkId,
+    tokenId,
+  }) => client.delete<EmptyApiResponse>(`/network/${networkId}/gateway_token/${tokenId}`);
+
   const getNetworkStats: Api['network']['getNetworkStats'] = (data) => {
     const fromParam = getNetworkStatsFilterValue(data.from ?? 1);
     return client
@@ -281,9 +320,26 @@ export const buildApi = (client: Axios): Api => {
   const mfaEmailMFASendCode: Api['auth']['mfa']['email']['sendCode'] = () =>
     client.get('/auth/email').then(unpackRequest);
 
+  const mfaEmailMFAResendCode: Api['auth']['mfa']['email']['resendCode'] = () =>
+    client.post('/auth/email/resend').then(unpackRequest);
+
   const mfaEmailMFAVerify: Api['auth']['mfa']['email']['verify'] = (data) =>
     client.post('/auth/email/verify', data).then(unpackRequest);
 
+  const mfaSmsMFAInit: Api['auth']['mfa']['sms']['register']['start'] = (data) =>
+    client.post('/auth/sms/init', data).then(unpackRequest);
+
+  const mfaSmsMFAEnable: Api['auth']['mfa']['sms']['register']['finish'] = (data) =>
+    client.post('/auth/sms', data).then(unpackRequest);
+
+  const mfaSmsMFADisable = () => client.delete('/auth/sms').then(unpackRequest);
+
+  const mfaSmsMFASendCode: Api['auth']['mfa']['sms']['sendCode'] = () =>
+    client.get('/auth/sms').then(unpackRequest);
+
+  const mfaSmsMFAVerify: Api['auth']['mfa']['sms']['verify'] = (data) =>
+    client.post('/auth/sms/verify', data).then(unpackRequest);
+
   const mfaWebauthnDeleteKey: Api['auth']['mfa']['webauthn']['deleteKey'] = ({
     keyId,
     username,
@@ -301,6 +357,9 @@ export const buildApi = (client: Axios): Api => {
   const recovery: Api['auth']['mfa']['recovery'] = (data) =>
     client.post('/auth/recovery', data).then(unpackRequest);
 
+  const regenerateRecoveryCodes: Api['auth']['mfa']['regenerateRecoveryCodes'] = () =>
+    client.post('/auth/recovery/regenerate').then(unpackRequest);
+
   const getAppInfo: Api['getAppInfo'] = () => client.get('/info').then(unpackRequest);
 
   const setDefaultBranding: Api['settings']['setDefaultBranding'] = (id: string) =>
@@ -315,6 +374,9 @@ export const buildApi = (client: Axios): Api => {
   const getGatewaysStatus: Api['network']['getGatewaysStatus'] = (networkId) =>
     client.get(`/network/${networkId}/gateways`).then(unpackRequest);
 
+  const getLocationConnectivity: Api['network']['getLocationConnectivity'] = (networkId) =>
+    client.get(`/network/${networkId}/connectivity`).then(unpackRequest);
+
   const deleteGateway: Api['network']['deleteGateway'] = (data) =>
     client.delete(`/network/${data.networkId}/gateways/${data.gatewayId}`);
 
@@ -375,9 +437,23 @@ export const buildApi = (client: Axios): Api => {
   const disableUserMfa: Api['user']['disableUserMfa'] = (username) =>
     client.delete(`/user/${username}/mfa`).then(unpackRequest);
 
+  const getRecoveryCodesInfo: Api['user']['getRecoveryCodesInfo'] = (username) =>
+    client.get(`/user/${username}/recovery_codes`).then(unpackRequest);
+
   const patchSettings: Api['settings']['patchSettings'] = (data) =>
     client.patch('/settings', data).then(unpackRequest);
 
+  const getGatewayAlertRules: Api['settings']['getGatewayAlertRules'] = () =>
+    client.get('/settings/gateway_alerts').then(unpackRequest);
+
+  const setGatewayAlertRule: Api['settings']['setGatewayAlertRule'] = ({
+    location_id,
+    ...data
+  }) => client.put(`/settings/gateway_alerts/${location_id}`, data).then(unpackRequest);
+
+  const deleteGatewayAlertRule: Api['settings']['deleteGatewayAlertRule'] = (locationId) =>
+    client.delete(`/settings/gateway_alerts/${locationId}`).then(unpackRequest);
+
   const getEssentialSettings: Api['settings']['getEssentialSettings'] = () =>
     client.get('/settings_essentials').then(unpackRequest);
 
@@ -604,6 +680,7 @@ export const buildApi = (client: Axios): Api => {
     user: {
       getMe,
       addUser,
+      importUsers,
       getUser,
       getUsers,
       editUser,
@@ -611,6 +688,7 @@ export const buildApi = (client: Axios): Api => {
       usernameAvailable,
       changePassword,
       resetPassword,
+      verifyEmail,
       addToGroup,
       removeFromGroup,
       startEnrollment,
@@ -626,6 +704,7 @@ export const buildApi = (client: Axios): Api => {
       deleteApiToken,
       renameApiToken,
       disableUserMfa,
+      getRecoveryCodesInfo,
     },
     device: {
       addDevice: addDevice,
@@ -645,10 +724,16 @@ export const buildApi = (client: Axios): Api => {
       getNetwork: fetchNetwork,
       getNetworks: fetchNetworks,
       editNetwork: modifyNetwork,
+      cloneNetwork,
+      setNetworkMaintenance,
       deleteNetwork,
       getNetworkToken,
+      getGatewayTokens,
+      createGatewayToken,
+      revokeGatewayToken,
       getNetworkStats,
       getGatewaysStatus,
+      getLocationConnectivity,
       deleteGateway,
       getOverviewStats: getOverviewStats,
     },
@@ -663,6 +748,7 @@ export const buildApi = (client: Axios): Api => {
         disable: mfaDisable,
         enable: mfaEnable,
         recovery: recovery,
+        regenerateRecoveryCodes,
         webauthn: {
           register: {
             start: mfaWebauthnRegisterStart,
@@ -685,8 +771,18 @@ export const buildApi = (client: Axios): Api => {
           },
           disable: mfaEmailMFADisable,
           sendCode: mfaEmailMFASendCode,
+          resendCode: mfaEmailMFAResendCode,
           verify: mfaEmailMFAVerify,
         },
+        sms: {
+          register: {
+            start: mfaSmsMFAInit,
+            finish: mfaSmsMFAEnable,
+          },
+          disable: mfaSmsMFADisable,
+          sendCode: mfaSmsMFASendCode,
+          verify: mfaSmsMFAVerify,
+        },
       },
     },
     provisioning: {
@@ -728,6 +824,9 @@ export const buildApi = (client: Axios): Api => {
       deleteOpenIdProvider,
       editOpenIdProvider,
       testDirsync,
+      getGatewayAlertRules,
+      setGatewayAlertRule,
+      deleteGatewayAlertRule,
     },
     support: {
       downloadSupportData,
@@ -10,12 +10,14 @@ export const useAuthStore = createWithEqualityFn<AuthStore>()(
     (set, get) => ({
       user: undefined,
       openIdParams: undefined,
+      emailVerificationToken: undefined,
       loginSubject: new Subject<LoginSubjectData>(),
       setState: (newState) => set({ ...get(), ...newState }),
       resetState: () =>
         set({
           user: undefined,
           openIdParams: undefined,
+          emailVerificationToken: undefined,
         }),
     }),
     {
@@ -31,6 +33,8 @@ export interface AuthStore {
   user?: User;
   // If this is set, redirect user to allow page and nowhere else
   openIdParams?: URLSearchParams;
+  // Email verification link opened before logging in, verified once the user logs in
+  emailVerificationToken?: string;
   setState: (newState: Partial<AuthStore>) => void;
   resetState: () => void;
 }
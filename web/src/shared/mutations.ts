@@ -32,6 +32,7 @@ export const MutationKeys = {
   ENABLE_EMAIL_MFA_INIT: 'ENABLE_EMAIL_MFA_INIT',
   ENABLE_EMAIL_MFA_FINISH: 'ENABLE_EMAIL_MFA_FINISH',
   DISABLE_EMAIL_MFA: 'DISABLE_EMAIL_MFA',
+  DISABLE_SMS_MFA: 'DISABLE_SMS_MFA',
   VERIFY_EMAIL_MFA: 'VERIFY_EMAIL_MFA',
   WEBAUTHN_MFA_START: 'WEBAUTHN_MFA_START',
   WEBAUTHN_MFA_FINISH: 'WEBAUTHN_MFA_FINISH',
@@ -44,6 +44,7 @@ export enum UserMFAMethod {
   NONE = 'None',
   ONE_TIME_PASSWORD = 'OneTimePassword',
   EMAIL = 'Email',
+  SMS = 'Sms',
   WEB_AUTH_N = 'Webauthn',
 }
 
@@ -61,6 +62,7 @@ export type User = {
   mfa_enabled: boolean;
   totp_enabled: boolean;
   email_mfa_enabled: boolean;
+  sms_mfa_enabled: boolean;
   email: string;
   phone?: string;
   groups: string[];
@@ -69,8 +71,31 @@ export type User = {
   enrolled: boolean;
   is_admin: boolean;
   ldap_pass_requires_change: boolean;
+  timezone?: string;
+  // authenticates only with API tokens
+  service_account: boolean;
+  // Naive UTC datetime, account is disabled automatically at this moment
+  deactivate_at?: string;
+  // only returned by /me while an admin is impersonating the user
+  impersonation?: ImpersonationInfo;
 };
 
+export type ImpersonationMode = 'read_only' | 'full';
+
+export interface ImpersonationInfo {
+  // username of the impersonating admin
+  admin: string;
+  mode: ImpersonationMode;
+  // Naive UTC datetime
+  expires: string;
+}
+
+export interface StartImpersonationRequest {
+  mode: ImpersonationMode;
+  // defaults to 15, at most 60
+  duration_minutes?: number;
+}
+
 export type UserProfile = {
   user: User;
   devices: Device[];
@@ -105,6 +130,8 @@ export interface Device {
   name: string;
   wireguard_pubkey: string;
   created: string;
+  // device is removed from locations after this time
+  expires_at?: string;
   networks: DeviceNetworkInfo[];
 }
 
@@ -118,6 +145,25 @@ export type DeviceNetworkInfo = {
   last_connected_ip?: string;
 };
 
+export type RotateDeviceKeyRequest = {
+  wireguard_pubkey: string;
+};
+
+export type DeviceKeyHistory = {
+  device_id: number;
+  wireguard_pubkey: string;
+  rotated_at: string;
+};
+
+export type DeviceExpiry = {
+  expires_at?: string;
+};
+
+export type DeviceIpReservation = {
+  ips: string[];
+  reserved: boolean;
+};
+
 export interface AddDeviceRequest {
   username: string;
   name: string;
@@ -131,6 +177,33 @@ export type GatewayStatus = {
   name?: string;
   hostname: string;
   uid: string;
+  // token used by the gateway, empty for tokens which can't be revoked individually
+  token_id?: number;
+  token_name?: string;
+  connected_at?: string;
+  disconnected_at?: string;
+  last_handshake?: string;
+  version?: string;
+  history?: GatewayConnectionEvent[];
+};
+
+export type GatewayConnectionEvent = {
+  kind: 'connected' | 'disconnected';
+  timestamp: string;
+};
+
+export type LocationConnectivity = {
+  location_id: number;
+  connected: boolean;
+  last_handshake?: string;
+  gateways: GatewayStatus[];
+};
+
+export type GatewayAlertRule = {
+  location_id: number;
+  threshold_minutes: number;
+  notify_webhooks: boolean;
+  notify_admins: boolean;
 };
 
 export enum LocationMfaMode {
@@ -139,12 +212,51 @@ export enum LocationMfaMode {
   EXTERNAL = 'external',
 }
 
+export enum LocationMfaMethod {
+  TOTP = 'totp',
+  EMAIL = 'email',
+  WEBAUTHN = 'webauthn',
+  BIOMETRIC = 'biometric',
+  MOBILE_APPROVE = 'mobile_approve',
+}
+
 export enum ServiceLocationMode {
   DISABLED = 'disabled',
   PRELOGON = 'prelogon',
   ALWAYSON = 'alwayson',
 }
 
+export enum IpAssignmentStrategy {
+  SEQUENTIAL = 'sequential',
+  RANDOM = 'random',
+  PUBKEY_HASH = 'pubkey_hash',
+}
+
+export interface NetworkGroupPool {
+  group: string;
+  address_pool: string[];
+  // routes replacing network allowed IPs for group members
+  allowed_ips?: string[];
+  // DNS overrides for group members
+  dns?: string;
+  dns_search_domains?: string[];
+  firewall_tag?: string;
+  priority: number;
+}
+
+export interface NetworkAccessWindow {
+  group: string;
+  // ISO weekday numbers, 1 is Monday
+  weekdays: number[];
+  start_time: string;
+  end_time: string;
+}
+
+export interface LocationMaintenance {
+  enabled: boolean;
+  message?: string;
+}
+
 export interface Network {
   id: number;
   name: string;
@@ -163,6 +275,29 @@ export interface Network {
   acl_default_allow: boolean;
   location_mfa_mode: LocationMfaMode;
   service_location_mode: ServiceLocationMode;
+  ip_assignment_strategy?: IpAssignmentStrategy;
+  group_pools?: NetworkGroupPool[];
+  access_windows?: NetworkAccessWindow[];
+  // empty list allows all methods
+  allowed_mfa_methods?: LocationMfaMethod[];
+  mfa_device_trust_enabled?: boolean;
+  // preshared key rotation interval in hours, 0 disables rotation
+  psk_rotation_interval?: number;
+  dns_search_domains?: string[];
+  maintenance?: LocationMaintenance;
+  // devices added to the location wait for admin approval
+  device_approval_required?: boolean;
+}
+
+export interface DeviceApprovalInfo {
+  id: number;
+  device_id: number;
+  device_name: string;
+  wireguard_pubkey: string;
+  username: string;
+  location_id: number;
+  location_name: string;
+  requested_at: string;
 }
 
 export type ModifyNetworkRequest = {
@@ -175,6 +310,24 @@ export type ModifyNetworkRequest = {
   };
 };
 
+export type CloneNetworkRequest = {
+  id: number;
+  name: string;
+  // one address for each subnet of the cloned location
+  address: string;
+  endpoint?: string;
+  port?: number;
+  copy_device_assignments?: boolean;
+};
+
+export type SetLocationMaintenanceRequest = {
+  id: number;
+  enabled: boolean;
+  message?: string;
+  // disconnect devices logged in with MFA
+  disconnect_peers?: boolean;
+};
+
 export interface ImportNetworkRequest {
   name: string;
   endpoint: string;
@@ -192,6 +345,30 @@ export interface NetworkToken {
   grpc_url: string;
 }
 
+export interface GatewayToken {
+  id: number;
+  location_id: number;
+  name: string;
+  created_at: string;
+  last_connected_at?: string;
+  last_hostname?: string;
+}
+
+export interface CreateGatewayTokenRequest {
+  networkId: number;
+  name: string;
+}
+
+export interface CreateGatewayTokenResponse extends NetworkToken {
+  id: number;
+  name: string;
+}
+
+export interface RevokeGatewayTokenRequest {
+  networkId: number;
+  tokenId: number;
+}
+
 export interface LoginData {
   username: string;
   password: string;
@@ -276,18 +453,64 @@ export interface AddUserRequest {
   last_name: string;
   first_name: string;
   phone?: string;
+  service_account?: boolean;
+}
+
+export interface ImportUser {
+  username: string;
+  email: string;
+  first_name: string;
+  last_name: string;
+  // names of existing groups
+  groups?: string[];
+  phone?: string;
+}
+
+export interface ImportUsersRequest {
+  users: ImportUser[];
+  send_enrollment_notification?: boolean;
+}
+
+export interface ImportUsersResponse {
+  users: User[];
+}
+
+// returned with 422 status, rows are numbered from 1
+export interface ImportRowError {
+  row: number;
+  message: string;
 }
 
 export interface StartEnrollmentRequest {
   username: string;
   send_enrollment_notification: boolean;
   email?: string;
+  token_expiration_time?: string;
+  // enrollment sessions which can be started with the token, 1 by default
+  max_uses?: number;
+  // group names and location ids assigned to the user when enrollment is completed
+  groups?: string[];
+  locations?: number[];
 }
 
 export interface StartEnrollmentResponse {
   enrollment_url: string;
   enrollment_token: string;
 }
+
+export interface EnrollmentTokenInfo {
+  id: string;
+  username: string;
+  admin?: string;
+  email?: string;
+  created_at: string;
+  expires_at: string;
+  used_at?: string;
+  max_uses: number;
+  use_count: number;
+  groups: string[];
+  locations: number[];
+}
 export interface GroupsResponse {
   groups: string[];
 }
@@ -295,6 +518,7 @@ export interface GroupsResponse {
 export interface UserGroupRequest {
   group: string;
   username: string;
+  expires_at?: string;
 }
 
 export interface ChangeUserPasswordRequest {
@@ -317,6 +541,7 @@ export interface MFALoginResponse {
   totp_available: boolean;
   webauthn_available: boolean;
   email_available: boolean;
+  sms_available: boolean;
 }
 
 export interface LoginResponse {
@@ -343,6 +568,12 @@ export interface RecoveryLoginRequest {
   code: string;
 }
 
+export interface RecoveryCodesInfo {
+  remaining: number;
+  // most recent first
+  used_at: string[];
+}
+
 export type MFARecoveryCodesResponse = Promise<undefined | RecoveryCodes>;
 
 export interface VersionResponse {
@@ -379,6 +610,7 @@ export interface AppInfo {
   version: string;
   network_present: boolean;
   smtp_enabled: boolean;
+  sms_enabled: boolean;
   license_info: LicenseInfo;
   ldap_info: LdapInfo;
   external_openid_enabled: boolean;
@@ -447,8 +679,13 @@ export type RenameApiTokenRequest = {
   name: string;
 } & ApiTokenRequestBase;
 
+export type ApiTokenScope = 'read_only' | 'user_management' | 'network_management';
+
 export type AddApiTokenRequest = {
   name: string;
+  location_id?: number;
+  group_id?: number;
+  scopes?: ApiTokenScope[];
 } & ApiTokenRequestBase;
 
 export type AddApiTokenResponse = {
@@ -482,6 +719,9 @@ export type ApiToken = {
   id: number;
   name: string;
   created_at: string;
+  location_id?: number;
+  group_id?: number;
+  scopes: ApiTokenScope[];
 };
 
 export type EnterpriseInfoResponse = {
@@ -534,6 +774,8 @@ export type AclRuleInfo = {
 export type ActivityLogEvent = {
   id: number;
   timestamp: string;
+  // ISO 8601 with offset, in the user's timezone
+  timestamp_local?: string;
   user_id: number;
   username: string;
   location?: string;
@@ -542,6 +784,12 @@ export type ActivityLogEvent = {
   module: ActivityLogModule;
   device: string;
   description?: string;
+  // `X-Request-Id` of the API request which triggered the event
+  request_id?: string;
+  // admin who triggered the event while impersonating the user
+  impersonator_id?: number;
+  impersonator?: string;
+  service_account: boolean;
 };
 
 export type PaginationParams = {
@@ -554,6 +802,8 @@ export type PaginationMeta = {
   total_items: number;
   total_pagers: number;
   next_page?: number;
+  // opaque cursor of the next page, present only for endpoints supporting keyset pagination
+  next_cursor?: string;
 };
 
 export type PaginatedResponse<T> = {
@@ -572,6 +822,9 @@ export type ActivityLogFilters = {
   location?: string[];
   event?: ActivityLogEventType[];
   module?: ActivityLogModule[];
+  // IP addresses or networks in CIDR notation
+  ip?: string[];
+  request_id?: string;
   search?: string;
 };
 
@@ -593,9 +846,12 @@ export type RequestSortParams<T> = {
 
 export type ActivityLogRequestParams = ActivityLogFilters &
   RequestSortParams<ActivityLogSortKey> &
-  PaginationParams;
+  PaginationParams & {
+    // cursor returned in `next_cursor`, takes precedence over `page`
+    cursor?: string;
+  };
 
-export type ActivityLogStreamType = 'vector_http' | 'logstash_http';
+export type ActivityLogStreamType = 'vector_http' | 'logstash_http' | 'syslog' | 'kafka';
 
 export type ActivityLogStream = {
   id: number;
@@ -618,6 +874,73 @@ export type ActivityLogStreamLogstashHttp = {
   cert?: string;
 };
 
+export type SyslogProtocol = 'udp' | 'tcp' | 'tls';
+
+export type SyslogFacility =
+  | 'kern'
+  | 'user'
+  | 'mail'
+  | 'daemon'
+  | 'auth'
+  | 'syslog'
+  | 'lpr'
+  | 'news'
+  | 'uucp'
+  | 'cron'
+  | 'authpriv'
+  | 'ftp'
+  | 'ntp'
+  | 'audit'
+  | 'alert'
+  | 'clock'
+  | 'local0'
+  | 'local1'
+  | 'local2'
+  | 'local3'
+  | 'local4'
+  | 'local5'
+  | 'local6'
+  | 'local7';
+
+export type SyslogSeverity =
+  | 'emergency'
+  | 'alert'
+  | 'critical'
+  | 'error'
+  | 'warning'
+  | 'notice'
+  | 'informational'
+  | 'debug';
+
+export type ActivityLogStreamSyslog = {
+  host: string;
+  port: number;
+  protocol?: SyslogProtocol;
+  facility?: SyslogFacility;
+  severity?: SyslogSeverity;
+  // severity overrides keyed by activity log event type
+  severity_mapping?: Partial<Record<ActivityLogEventType, SyslogSeverity>>;
+  hostname?: string;
+  cert?: string;
+};
+
+export type KafkaPayloadFormat = 'json' | 'avro';
+
+export type KafkaSaslMechanism = 'plain' | 'scram-sha-256' | 'scram-sha-512';
+
+export type ActivityLogStreamKafka = {
+  brokers: string[];
+  topic: string;
+  format?: KafkaPayloadFormat;
+  sasl?: {
+    mechanism: KafkaSaslMechanism;
+    username: string;
+    password: string;
+  };
+  tls?: boolean;
+  cert?: string;
+};
+
 export type ActivityLogStreamModifyRequest = {
   id: number;
   name: string;
@@ -627,7 +950,9 @@ export type ActivityLogStreamModifyRequest = {
 
 export type ActivityLogStreamConfig =
   | ActivityLogStreamVectorHttp
-  | ActivityLogStreamLogstashHttp;
+  | ActivityLogStreamLogstashHttp
+  | ActivityLogStreamSyslog
+  | ActivityLogStreamKafka;
 
 export type ActivityLogStreamCreateRequest = Omit<ActivityLogStreamModifyRequest, 'id'>;
 
@@ -684,6 +1009,7 @@ export type Api = {
   user: {
     getMe: () => Promise<User>;
     addUser: (data: AddUserRequest) => Promise<User>;
+    importUsers: (data: ImportUsersRequest) => Promise<ImportUsersResponse>;
     startEnrollment: (data: StartEnrollmentRequest) => Promise<StartEnrollmentResponse>;
     getUser: (username: string) => Promise<UserProfile>;
     getUsers: () => Promise<User[]>;
@@ -692,6 +1018,7 @@ export type Api = {
     usernameAvailable: (username: string) => EmptyApiResponse;
     changePassword: (data: ChangePasswordRequest) => EmptyApiResponse;
     resetPassword: (data: ResetPasswordRequest) => EmptyApiResponse;
+    verifyEmail: (data: { token: string }) => EmptyApiResponse;
     addToGroup: (data: UserGroupRequest) => EmptyApiResponse;
     removeFromGroup: (data: UserGroupRequest) => EmptyApiResponse;
     startDesktopActivation: (
@@ -722,6 +1049,7 @@ export type Api = {
       name: string;
     }) => EmptyApiResponse;
     disableUserMfa: (username: string) => EmptyApiResponse;
+    getRecoveryCodesInfo: (username: string) => Promise<RecoveryCodesInfo>;
   };
   standaloneDevice: {
     createManualDevice: (
@@ -759,11 +1087,21 @@ export type Api = {
     getNetwork: (networkId: number) => Promise<Network>;
     getNetworks: () => Promise<Network[]>;
     editNetwork: (network: ModifyNetworkRequest) => Promise<Network>;
+    cloneNetwork: (data: CloneNetworkRequest) => Promise<Network>;
+    setNetworkMaintenance: (
+      data: SetLocationMaintenanceRequest,
+    ) => Promise<LocationMaintenance>;
     deleteNetwork: (networkId: number) => EmptyApiResponse;
     getOverviewStats: (data: GetNetworkStatsRequest) => Promise<OverviewStatsResponse>;
     getNetworkToken: (networkId: Network['id']) => Promise<NetworkToken>;
+    getGatewayTokens: (networkId: Network['id']) => Promise<GatewayToken[]>;
+    createGatewayToken: (
+      data: CreateGatewayTokenRequest,
+    ) => Promise<CreateGatewayTokenResponse>;
+    revokeGatewayToken: (data: RevokeGatewayTokenRequest) => EmptyApiResponse;
     getNetworkStats: (data: GetNetworkStatsRequest) => Promise<WireguardNetworkStats>;
     getGatewaysStatus: (networkId: number) => Promise<GatewayStatus[]>;
+    getLocationConnectivity: (networkId: number) => Promise<LocationConnectivity>;
     deleteGateway: (data: DeleteGatewayRequest) => Promise<void>;
     getAllNetworksStats: (data: { from?: number }) => Promise<WireguardNetworkStats>;
     getAllGatewaysStatus: () => Promise<AllGateWaysResponse>;
@@ -779,6 +1117,7 @@ export type Api = {
       disable: () => EmptyApiResponse;
       enable: () => EmptyApiResponse;
       recovery: (data: RecoveryLoginRequest) => Promise<MFAFinishResponse>;
+      regenerateRecoveryCodes: () => Promise<RecoveryCodes>;
       email: {
         register: {
           start: () => EmptyApiResponse;
@@ -786,6 +1125,16 @@ export type Api = {
         };
         disable: () => EmptyApiResponse;
         sendCode: () => EmptyApiResponse;
+        resendCode: () => EmptyApiResponse;
+        verify: (data: AuthCodeRequest) => Promise<MFAFinishResponse>;
+      };
+      sms: {
+        register: {
+          start: (data: { phone: string }) => EmptyApiResponse;
+          finish: (data: AuthCodeRequest) => MFARecoveryCodesResponse;
+        };
+        disable: () => EmptyApiResponse;
+        sendCode: () => EmptyApiResponse;
         verify: (data: AuthCodeRequest) => Promise<MFAFinishResponse>;
       };
       webauthn: {
@@ -800,7 +1149,7 @@ export type Api = {
         deleteKey: (data: DeleteWebAuthNKeyRequest) => EmptyApiResponse;
       };
       totp: {
-        init: () => Promise<{ secret: string }>;
+        init: () => Promise<{ secret: string; algorithm: TotpAlgorithm; digits: number }>;
         enable: (data: TOTPRequest) => MFARecoveryCodesResponse;
         disable: () => EmptyApiResponse;
         verify: (data: TOTPRequest) => Promise<MFAFinishResponse>;
@@ -840,12 +1189,15 @@ export type Api = {
     getEssentialSettings: () => Promise<SettingsEssentials>;
     getEnterpriseSettings: () => Promise<SettingsEnterprise>;
     patchEnterpriseSettings: (data: Partial<SettingsEnterprise>) => EmptyApiResponse;
-    testLdapSettings: () => Promise<EmptyApiResponse>;
+    testLdapSettings: () => Promise<LdapAttributeResolution>;
     fetchOpenIdProviders: () => Promise<OpenIdInfo>;
     addOpenIdProvider: (data: OpenIdProvider) => Promise<EmptyApiResponse>;
     deleteOpenIdProvider: (name: string) => Promise<EmptyApiResponse>;
     editOpenIdProvider: (data: OpenIdProvider) => Promise<EmptyApiResponse>;
     testDirsync: () => Promise<DirsyncTestResponse>;
+    getGatewayAlertRules: () => Promise<GatewayAlertRule[]>;
+    setGatewayAlertRule: (data: GatewayAlertRule) => Promise<GatewayAlertRule>;
+    deleteGatewayAlertRule: (locationId: number) => EmptyApiResponse;
   };
   support: {
     downloadSupportData: () => Promise<unknown>;
@@ -1043,12 +1395,19 @@ export interface UseOpenIDStore {
  */
 export type Settings = SettingsModules &
   SettingsSMTP &
+  SettingsSMS &
   SettingsEnrollment &
   SettingsBranding &
   SettingsLDAP &
-  SettingsOpenID &
   SettingsLicense &
-  SettingsGatewayNotifications;
+  SettingsGatewayNotifications &
+  SettingsPasswordHashing &
+  SettingsTimezone &
+  SettingsActivityLogRetention &
+  SettingsTotp &
+  SettingsPasswordPolicy &
+  SettingsLoginLockout &
+  SettingsCaptcha;
 
 // essentials for core frontend, includes only those that are required for frontend operations
 export type SettingsEssentials = SettingsModules & SettingsBranding;
@@ -1070,6 +1429,14 @@ export type SettingsSMTP = {
   smtp_sender?: string;
 };
 
+// Twilio-style HTTP SMS gateway used for SMS MFA
+export type SettingsSMS = {
+  sms_gateway_url?: string;
+  sms_gateway_user?: string;
+  sms_gateway_password?: string;
+  sms_sender?: string;
+};
+
 export type SettingsModules = {
   openid_enabled: boolean;
   wireguard_enabled: boolean;
@@ -1105,16 +1472,131 @@ export type SettingsLDAP = {
   ldap_uses_ad: boolean;
   ldap_user_rdn_attr?: string;
   ldap_sync_groups: string[];
+  ldap_group_filter: string[];
+  ldap_group_exclude: string[];
+  ldap_email_attr: string;
+  ldap_first_name_attr: string;
+  ldap_last_name_attr: string;
+  ldap_phone_attr: string;
 };
 
-export type SettingsOpenID = {
-  openid_create_account: boolean;
+export type LdapResolvedAttribute = {
+  name: string;
+  resolved: boolean;
+};
+
+export type LdapAttributeResolution = {
+  sample_dn?: string;
+  attributes: Record<string, LdapResolvedAttribute>;
 };
 
 export type SettingsLicense = {
   license: string;
 };
 
+export type SettingsPasswordHashing = {
+  password_hash_memory_cost: number;
+  password_hash_time_cost: number;
+  password_hash_parallelism: number;
+};
+
+export type SettingsTimezone = {
+  // IANA timezone name, e.g. Europe/Warsaw
+  timezone: string;
+};
+
+export type TotpAlgorithm = 'Sha1' | 'Sha256';
+
+export type SettingsTotp = {
+  // changing the algorithm or digits requires users to enroll TOTP again
+  totp_algorithm: TotpAlgorithm;
+  totp_digits: number;
+  // number of time steps before and after the current one accepted to tolerate clock drift
+  totp_skew: number;
+};
+
+export type SettingsPasswordPolicy = {
+  password_min_length: number;
+  password_require_uppercase: boolean;
+  password_require_lowercase: boolean;
+  password_require_digit: boolean;
+  password_require_special: boolean;
+  // minimal zxcvbn strength score (0-4), 0 disables the check
+  password_min_score: number;
+  // passwords which can't be used, case-insensitive
+  password_banned: string[];
+  // number of most recent passwords which can't be reused, 0 disables the check
+  password_history_size: number;
+};
+
+export type SettingsLoginLockout = {
+  // consecutive failed logins of a user before the account is locked, 0 disables the lockout
+  login_lockout_threshold: number;
+  // consecutive failed logins from an IP address before it is locked, 0 disables the lockout
+  login_lockout_ip_threshold: number;
+  // lockout duration in minutes
+  login_lockout_duration: number;
+  // let locked users with MFA enabled log in, as they still have to complete MFA
+  login_lockout_mfa_fallback: boolean;
+};
+
+export type CaptchaProvider = 'none' | 'hcaptcha' | 'turnstile';
+
+export type SettingsCaptcha = {
+  // CAPTCHA on enrollment start and password reset request pages served by the proxy
+  captcha_provider: CaptchaProvider;
+  captcha_site_key?: string;
+  captcha_secret_key?: string;
+};
+
+export type LoginLockouts = {
+  users: {
+    user_id: number;
+    username: string;
+    failed_attempts: number;
+    locked_until: string;
+  }[];
+  ips: {
+    ip_address: string;
+    failed_attempts: number;
+    locked_until: string;
+  }[];
+};
+
+export type PasswordPolicyViolation =
+  | 'too_short'
+  | 'too_long'
+  | 'no_uppercase'
+  | 'no_lowercase'
+  | 'no_digit'
+  | 'no_special'
+  | 'too_weak'
+  | 'banned'
+  | 'reused';
+
+// returned with 400 status when a new password doesn't meet the password policy
+export type PasswordPolicyErrorResponse = {
+  msg: string;
+  policy: {
+    min_length: number;
+    max_length: number;
+    require_uppercase: boolean;
+    require_lowercase: boolean;
+    require_digit: boolean;
+    require_special: boolean;
+    min_score: number;
+    history_size: number;
+  };
+  violations: PasswordPolicyViolation[];
+};
+
+export type SettingsActivityLogRetention = {
+  // events older than this number of days are removed, null keeps them forever
+  activity_log_retention_days: number | null;
+  // move removed events to the archive table instead of deleting them
+  activity_log_retention_archive: boolean;
+};
+
 export type SettingsGatewayNotifications = {
   gateway_disconnect_notifications_enabled: boolean;
   gateway_disconnect_notifications_inactivity_threshold: number;
@@ -1131,6 +1613,8 @@ export type SettingsEnterprise = {
   admin_device_management: boolean;
   client_traffic_policy: ClientTrafficPolicy;
   only_client_activation: boolean;
+  self_service_allowed_networks: string[];
+  self_service_allow_vpn: boolean;
 };
 
 export type EnterpriseLicenseInfo = {
@@ -1161,6 +1645,11 @@ export interface Webhook {
   on_user_deleted: boolean;
   on_user_modified: boolean;
   on_hwkey_provision: boolean;
+  on_gateway_disconnected: boolean;
+  on_client_connected: boolean;
+  on_client_disconnected: boolean;
+  secret?: string | null;
+  locations: number[];
 }
 
 export interface OpenidClient {
@@ -1178,6 +1667,26 @@ export interface OpenIdInfo {
     create_account: boolean;
   };
   provider?: OpenIdProvider;
+  group_claim_mapping?: OpenIdGroupClaimMapping;
+}
+
+export interface OpenIdGroupClaimMapping {
+  // dot separated path of the ID token claim, e.g. `groups` or `realm_access.roles`
+  claim?: string;
+  create_groups: boolean;
+  dry_run: boolean;
+}
+
+export interface OpenIdGroupClaimPreviewRequest {
+  username: string;
+  claims: Record<string, unknown>;
+}
+
+export interface OpenIdGroupClaimChanges {
+  created: string[];
+  joined: string[];
+  left: string[];
+  skipped: string[];
 }
 
 export interface OpenIdProvider {
@@ -1198,6 +1707,48 @@ export interface OpenIdProvider {
   okta_private_jwk?: string;
   okta_dirsync_client_id?: string;
   directory_sync_group_match?: string;
+  group_claim_mapping?: OpenIdGroupClaimMapping;
+}
+
+export interface SamlProviderData {
+  name: string;
+  display_name?: string;
+  enabled: boolean;
+  // identity provider metadata XML
+  idp_metadata: string;
+  // names (or friendly names) of assertion attributes mapped onto user fields
+  email_attribute: string;
+  username_attribute?: string;
+  first_name_attribute: string;
+  last_name_attribute: string;
+  phone_attribute?: string;
+  groups_attribute?: string;
+  create_groups: boolean;
+}
+
+export interface SamlProvider extends SamlProviderData {
+  id: number;
+}
+
+export interface SamlAuthInfo {
+  url: string;
+  button_display_name?: string;
+}
+
+export type ProvisioningSource = 'openid' | 'ldap' | 'saml';
+
+export interface ProvisioningPolicyData {
+  create_account: boolean;
+  // names of groups new accounts join
+  default_groups: string[];
+  // new accounts are disabled until an admin enables them
+  start_disabled: boolean;
+  // e.g. `{given_initial}{family_name}`, not supported for LDAP
+  username_template?: string;
+}
+
+export interface ProvisioningPolicy extends ProvisioningPolicyData {
+  source: ProvisioningSource;
 }
 
 export enum OpenIdSyncBehavior {
@@ -1345,6 +1896,7 @@ export type GroupInfo = {
   members: string[];
   vpn_locations: string[];
   is_admin: boolean;
+  parent?: string;
 };
 
 export type DirsyncTestResponse = {
@@ -1388,6 +1940,7 @@ export type StandaloneDevice = {
   description?: string;
   added_by: string;
   added_date: string;
+  expires_at?: string;
   configured: boolean;
   // when configured is false this will be empty
   wireguard_pubkey?: string;